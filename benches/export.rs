@@ -0,0 +1,41 @@
+//! PDF rendering throughput on generated seed data: `cargo bench --bench export`
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use herding_cats_rust::database::fixtures::FixtureSpec;
+use herding_cats_rust::database::{DatabaseConfig, EnhancedDatabaseService};
+use herding_cats_rust::profiling::{ProfileOptions, Workload};
+use herding_cats_rust::publishing::pdf;
+use tokio::sync::RwLock;
+
+fn export(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let options = ProfileOptions {
+        spec: FixtureSpec::parse("projects=1,documents=100,words=500-2500,codex=0").unwrap(),
+        queries: 0,
+        ..Default::default()
+    };
+    let workload = runtime.block_on(async {
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("bench.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        Workload::generate(&Arc::new(RwLock::new(db)), &options)
+            .await
+            .unwrap()
+    });
+    let manuscript = workload.manuscript;
+    let pages = pdf::page_count(&manuscript.render_pdf());
+
+    let mut group = c.benchmark_group("export");
+    group.sample_size(10);
+    // Reported as pages per second
+    group.throughput(Throughput::Elements(pages as u64));
+    group.bench_function("pdf", |b| b.iter(|| manuscript.render_pdf()));
+    group.finish();
+}
+
+criterion_group!(benches, export);
+criterion_main!(benches);
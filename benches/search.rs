@@ -0,0 +1,68 @@
+//! Search latency on generated seed data: `cargo bench --bench search`
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use herding_cats_rust::database::fixtures::FixtureSpec;
+use herding_cats_rust::database::{
+    DatabaseConfig, EnhancedDatabaseService, VectorEmbeddingService,
+};
+use herding_cats_rust::profiling::{self, ProfileOptions, Workload};
+use tokio::runtime::Runtime;
+use tokio::sync::RwLock;
+
+fn search(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let options = ProfileOptions {
+        spec: FixtureSpec::parse("projects=2,documents=100,words=500-2500,codex=0,embeddings=true")
+            .unwrap(),
+        ..Default::default()
+    };
+    let (db, workload) = runtime.block_on(async {
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("bench.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let workload = Workload::generate(&db, &options).await.unwrap();
+        (db, workload)
+    });
+    let search = profiling::uncached_search(&db);
+    runtime.block_on(search.initialize()).unwrap();
+    let embeddings = VectorEmbeddingService::new(Arc::clone(&db));
+
+    let mut group = c.benchmark_group("search");
+    let mut samples = workload.samples.iter().cycle();
+    group.bench_function("full_text", |b| {
+        b.to_async(&runtime).iter(|| {
+            let sample = samples.next().unwrap();
+            let search = &search;
+            async move {
+                search
+                    .search_documents_advanced(&sample.terms, Some(sample.full_text_options()))
+                    .await
+                    .unwrap()
+            }
+        })
+    });
+    group.bench_function("semantic", |b| {
+        b.to_async(&runtime).iter(|| {
+            let sample = samples.next().unwrap();
+            let embeddings = &embeddings;
+            async move {
+                embeddings
+                    .find_similar_documents(
+                        &sample.paragraph,
+                        Some(profiling::semantic_options(options.recall_k)),
+                    )
+                    .await
+                    .unwrap()
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, search);
+criterion_main!(benches);
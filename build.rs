@@ -1,3 +1,55 @@
+//! Build script: embeds the built frontend (`frontend/dist`) into release
+//! builds so the app can serve it without reading the working directory.
+//! Debug builds load the frontend from the Vite dev server instead and get
+//! an empty table. Set `HC_EMBED_FRONTEND=1` to embed in debug builds too.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
 fn main() {
-    // No build steps required for now
+    println!("cargo:rerun-if-changed=frontend/dist");
+    println!("cargo:rerun-if-env-changed=HC_EMBED_FRONTEND");
+
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let dist = manifest_dir.join("frontend").join("dist");
+    let embed = env::var("PROFILE").as_deref() == Ok("release")
+        || env::var("HC_EMBED_FRONTEND").is_ok_and(|v| v == "1");
+
+    let mut files = Vec::new();
+    if embed && dist.is_dir() {
+        collect_files(&dist, &dist, &mut files);
+    }
+    files.sort();
+
+    let mut generated = String::from("pub static FRONTEND_ASSETS: &[(&str, &[u8])] = &[\n");
+    for (relative, absolute) in &files {
+        generated.push_str(&format!(
+            "    ({:?}, include_bytes!({:?})),\n",
+            relative, absolute
+        ));
+    }
+    generated.push_str("];\n");
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("frontend_assets.rs"), generated).unwrap();
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<(String, String)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(root, &path, files);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let relative = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((relative, path.to_string_lossy().into_owned()));
+        }
+    }
 }
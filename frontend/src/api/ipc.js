@@ -1,5 +1,8 @@
 const pendingRequests = new Map();
 
+// IPC API version this frontend speaks; see IPC_API_VERSION in ipc_bridge.rs
+export const API_VERSION = 2;
+
 // Direct callback for Rust
 window.__IPC_RECEIVE__ = (data) => {
     if (data && data.id && pendingRequests.has(data.id)) {
@@ -7,7 +10,10 @@ window.__IPC_RECEIVE__ = (data) => {
         pendingRequests.delete(data.id);
 
         if (data.type === 'error') {
-            reject(new Error(data.payload ? data.payload.message : 'Unknown error'));
+            const error = new Error(data.payload ? data.payload.message : 'Unknown error');
+            error.code = data.payload ? data.payload.code : undefined;
+            error.correlationId = data.correlation_id;
+            reject(error);
         } else if (data.type === 'db_result') {
             resolve(data.payload); // Return the payload directly
         } else if (data.type === 'ai_response') {
@@ -18,9 +24,29 @@ window.__IPC_RECEIVE__ = (data) => {
     } else if (data && data.type === 'open_document') {
         // Handle unsolicited open_document event from backend
         window.dispatchEvent(new CustomEvent('open-document', { detail: data.payload.id }));
+    } else if (data && data.type === 'open_project') {
+        // Project picked from the jump list or dock menu
+        window.dispatchEvent(new CustomEvent('open-project', { detail: data.payload.id }));
+    } else if (data && data.type === 'show_log') {
+        // "Show log" clicked on a desktop notification
+        window.dispatchEvent(new CustomEvent('show-log'));
+    } else if (data && data.type === 'export_job') {
+        // Export job progress, completion (with output_path) or failure
+        window.dispatchEvent(new CustomEvent('export-job', { detail: data.payload }));
     }
 };
 
+// Subscribe to pushed export job updates; returns an unsubscribe function
+export function onExportJob(callback, jobId) {
+    const listener = (event) => {
+        if (!jobId || event.detail.job_id === jobId) {
+            callback(event.detail);
+        }
+    };
+    window.addEventListener('export-job', listener);
+    return () => window.removeEventListener('export-job', listener);
+}
+
 function generateId() {
     return Math.random().toString(36).substring(2, 15) + Math.random().toString(36).substring(2, 15);
 }
@@ -71,6 +97,11 @@ export const db = {
 
 export const ai = {
     request: (prompt, context = null) => sendRequest('ai_request', { prompt, context }),
+    // Answer with [n] markers; sources carry links back to the documents
+    askProject: (projectId, question, { maxSources = 5 } = {}) =>
+        sendRequest('ask_project', {
+            request: { project_id: projectId, question, max_sources: maxSources },
+        }),
 };
 
 export const app = {
@@ -85,3 +116,311 @@ export const app = {
 };
 
 export const log = (message) => sendRequest('log', { message });
+
+// Chatty editor updates; the backend coalesces these per document
+export const editor = {
+    cursorPosition: (documentId, offset) => sendRequest('cursor_position', { document_id: documentId, offset }),
+    autosave: (documentId, content) => sendRequest('autosave_ping', { document_id: documentId, content }),
+};
+
+// Recently opened projects/documents, mirrored to the jump list and dock menu
+export const recent = {
+    record: (kind, id, title) => sendRequest('recent_record', { kind, id, title }),
+    list: () => sendRequest('recent_list'),
+};
+
+export const projectFiles = {
+    // Resolves with the written path (".hcats" is appended if missing)
+    create: (projectId, path) => sendRequest('project_file_create', { project_id: projectId, path }),
+};
+
+// Local crash reports; nothing is sent unless the user submits a report
+export const crashReports = {
+    pending: () => sendRequest('crash_reports'),
+    submit: (id) => sendRequest('crash_report_submit', { id }),
+    dismiss: (id) => sendRequest('crash_report_dismiss', { id }),
+    metrics: () => sendRequest('crash_metrics'),
+};
+
+// Where data is kept; mode is "installed" or "portable"
+export const storage = {
+    info: () => sendRequest('storage_info'),
+    // Preview, then copy the library to a new data directory; takes effect after a restart
+    planMove: (path) => sendRequest('data_dir_plan', { path }),
+    moveTo: (path) => sendRequest('data_dir_migrate', { path }),
+};
+
+// Files linked to a document or codex entry; ownerKind is "document" or "codex_entry".
+// exportInclusion is "exclude", "list" or "embed".
+export const attachments = {
+    add: (projectId, ownerKind, ownerId, path) =>
+        sendRequest('attachment_add', { project_id: projectId, owner_kind: ownerKind, owner_id: ownerId, path }),
+    list: (ownerKind, ownerId) => sendRequest('attachment_list', { owner_kind: ownerKind, owner_id: ownerId }),
+    update: (id, { description = null, exportInclusion = 'exclude' } = {}) =>
+        sendRequest('attachment_update', { id, description, export_inclusion: exportInclusion }),
+    remove: (id) => sendRequest('attachment_remove', { id }),
+};
+
+// Cached, downscaled copy of an image asset; size is "small", "medium" or "large"
+export const thumbnailUrl = (assetHash, size = 'medium') => `app://localhost/thumbnails/${size}/${assetHash}`;
+
+// Dominant colours of a cover image with derived heading/accent colours, and
+// cover rendering. trimSize is "ebook", "print5x8", "print5_5x8_5" or "print6x9";
+// the rendered cover is stored as an asset and optionally written to path.
+export const covers = {
+    palette: (assetHash, maxColors = null) =>
+        sendRequest('cover_palette', { asset_hash: assetHash, max_colors: maxColors }),
+    render: (design, trimSize = 'ebook', path = null) =>
+        sendRequest('cover_render', { design, trim_size: trimSize, path }),
+};
+
+export const devices = {
+    list: () => sendRequest('device_list'),
+    smtpSettings: () => sendRequest('device_smtp_get'),
+    saveSmtpSettings: (settings) => sendRequest('device_smtp_save', { settings }),
+    // target: { kind: 'email', address } or { kind: 'folder', path }
+    send: (projectId, target) =>
+        sendRequest('device_send', { project_id: projectId, target }),
+    status: (jobId) => sendRequest('device_send_status', { job_id: jobId }),
+    jobs: () => sendRequest('device_send_jobs'),
+};
+
+export const generators = {
+    // Shared tables, plus the project's own when projectId is given
+    tables: (projectId = null) =>
+        sendRequest('generator_tables', { project_id: projectId }),
+    save: (table) => sendRequest('generator_save', { table }),
+    remove: (tableId) => sendRequest('generator_delete', { table_id: tableId }),
+    roll: (table, { projectId = null, variables = {}, count = 1 } = {}) =>
+        sendRequest('generator_roll', { project_id: projectId, table, variables, count }),
+};
+
+export const stats = {
+    startSession: (projectId) => sendRequest('stats_session_start', { project_id: projectId }),
+    endSession: (sessionId) => sendRequest('stats_session_end', { session_id: sessionId }),
+    // dataset: 'sessions' | 'word_counts' | 'chapters' | 'ai_usage'; format: 'csv' | 'json'.
+    // Without a path the content is returned instead of written to disk.
+    export: (dataset, { projectId = null, format = 'csv', from = null, to = null, path = null } = {}) =>
+        sendRequest('stats_export', { project_id: projectId, dataset, format, from, to, path }),
+};
+
+export const codexGraph = {
+    // query: { project_id, entry_types, start, max_hops, via, appears_in, limit }
+    query: (query) => sendRequest('codex_graph_query', { query }),
+    // e.g. 'find characters within 2 hops of "Mara" via sibling, rival in "Act 2"'
+    queryText: (projectId, query) =>
+        sendRequest('codex_graph_query_text', { project_id: projectId, query }),
+    backlinks: (projectId, entryId) =>
+        sendRequest('codex_backlinks', { project_id: projectId, entry_id: entryId }),
+};
+
+export const codexAutofill = {
+    // Proposed CharacterData/PlaceData changes with the sentences behind them
+    propose: (projectId, entryId, documentIds = []) =>
+        sendRequest('codex_autofill_propose', {
+            request: { project_id: projectId, entry_id: entryId, document_ids: documentIds },
+        }),
+    // Leave acceptedFields empty to write every proposed change
+    apply: (proposal, acceptedFields = []) =>
+        sendRequest('codex_autofill_apply', { apply: { proposal, accepted_fields: acceptedFields } }),
+};
+
+export const storyBible = {
+    // format: 'Pdf' | 'Epub'; entryTypes empty for the whole codex
+    export: (projectId, format, path, { entryTypes = [], includeImages = true, includeRelationshipMatrix = true } = {}) =>
+        sendRequest('story_bible_export', {
+            request: {
+                project_id: projectId,
+                format,
+                entry_types: entryTypes,
+                include_images: includeImages,
+                include_relationship_matrix: includeRelationshipMatrix,
+            },
+            path,
+        }),
+};
+
+export const lint = {
+    // Built-in genre packs: romance, fantasy, thriller
+    packs: () => sendRequest('lint_packs'),
+    settings: (projectId) => sendRequest('lint_settings_get', { project_id: projectId }),
+    // severity_overrides: { head_hopping: 'off', 'thriller.chapter_length': 'info' }
+    saveSettings: (settings) => sendRequest('lint_settings_save', { settings }),
+    run: (projectId) => sendRequest('lint_project', { project_id: projectId }),
+};
+
+export const narrativeVoice = {
+    check: (projectId) => sendRequest('narrative_voice_check', { project_id: projectId }),
+    // voice: { pov: 'Mara', person: 'first' | 'second' | 'third', tense: 'past' | 'present' }
+    setSceneVoice: (documentId, voice) =>
+        sendRequest('scene_voice_set', { document_id: documentId, voice }),
+};
+
+export const chronology = {
+    // Scenes are dated with metadata { time: { start_time, calendar_id }, flashback }
+    check: (projectId) => sendRequest('chronology_check', { project_id: projectId }),
+    readingOrder: (projectId) =>
+        sendRequest('chronology_reading_order', { project_id: projectId }),
+    // format: 'Pdf' | 'Epub'
+    export: (projectId, format, path) =>
+        sendRequest('chronology_export', { project_id: projectId, format, path }),
+};
+
+export const submissions = {
+    markets: () => sendRequest('submission_markets'),
+    // kind: 'agent' | 'publisher' | 'magazine' | 'contest' | 'other'
+    saveMarket: (market) => sendRequest('submission_market_save', { market }),
+    deleteMarket: (marketId) => sendRequest('submission_market_delete', { market_id: marketId }),
+    marketStats: () => sendRequest('submission_market_stats'),
+    save: (submission) => sendRequest('submission_save', { submission }),
+    list: (projectId) => sendRequest('submission_list', { project_id: projectId }),
+    // status: 'more_requested' | 'accepted' | 'rejected' | 'withdrawn' | 'no_response'
+    respond: (submissionId, status, response = null) =>
+        sendRequest('submission_respond', { response: { submission_id: submissionId, status, response } }),
+    // Open submissions for a project, or across all projects when projectId is null
+    report: (projectId = null) => sendRequest('submission_report', { project_id: projectId }),
+};
+
+export const deadlines = {
+    list: (projectId) => sendRequest('deadline_list', { project_id: projectId }),
+    // kind: 'contest' | 'submission' | 'publisher' | 'personal' | 'other'
+    save: (deadline) => sendRequest('deadline_save', { deadline }),
+    remove: (deadlineId) => sendRequest('deadline_delete', { deadline_id: deadlineId }),
+    // Time left, words to go and the daily pace needed for open deadlines
+    countdowns: (projectId) => sendRequest('deadline_countdowns', { project_id: projectId }),
+};
+
+export const serial = {
+    plan: (projectId) => sendRequest('serial_plan', { project_id: projectId }),
+    // status: 'planned' | 'staged' | 'published' | 'skipped'
+    save: (release) => sendRequest('serial_release_save', { release }),
+    remove: (releaseId) => sendRequest('serial_release_delete', { release_id: releaseId }),
+    // Writes the chapter page to the staging folder now instead of a day ahead
+    stage: (releaseId) => sendRequest('serial_release_stage', { release_id: releaseId }),
+    markPublished: (releaseId, publishedUrl = null) =>
+        sendRequest('serial_release_published', { release_id: releaseId, published_url: publishedUrl }),
+};
+
+export const certification = {
+    // Signs and stores the project's current per-document and total word counts
+    certify: (projectId) => sendRequest('word_count_certify', { project_id: projectId }),
+    list: (projectId) => sendRequest('word_count_certificates', { project_id: projectId }),
+    // Checks the signature and whether the certified text has changed since
+    verify: (certificateId) => sendRequest('word_count_certificate_verify', { certificate_id: certificateId }),
+    exportPdf: (certificateId, path) =>
+        sendRequest('word_count_certificate_export', { certificate_id: certificateId, path }),
+};
+
+export const challenges = {
+    list: (projectId) => sendRequest('challenge_list', { project_id: projectId }),
+    // start_date / end_date: 'YYYY-MM-DD', end inclusive
+    save: (challenge) => sendRequest('challenge_save', { challenge }),
+    remove: (challengeId) => sendRequest('challenge_delete', { challenge_id: challengeId }),
+    // Progress from writing sessions with pace, projection and words needed today
+    dashboard: (challengeId) => sendRequest('challenge_dashboard', { challenge_id: challengeId }),
+};
+
+export const focus = {
+    // Opt-in; events stay in the local database
+    status: () => sendRequest('focus_status'),
+    enable: (enabled) => sendRequest('focus_enable', { enabled }),
+    // Per-day focus stretches, distractions and switches, oldest first
+    summaries: (days = 7) => sendRequest('focus_summaries', { days }),
+    clear: () => sendRequest('focus_clear'),
+};
+
+export const workspaces = {
+    list: (projectId) => sendRequest('workspace_list', { project_id: projectId }),
+    // open_documents in tab order; names are unique per project
+    save: (workspace) => sendRequest('workspace_save', { workspace }),
+    remove: (workspaceId) => sendRequest('workspace_delete', { workspace_id: workspaceId }),
+    // Marks it current; resolves without documents deleted since it was saved
+    switchTo: (workspaceId) => sendRequest('workspace_switch', { workspace_id: workspaceId }),
+    active: (projectId) => sendRequest('workspace_active', { project_id: projectId }),
+    // kind: 'document' | 'codex' | 'research' | 'url'
+    pin: (workspaceId, kind, target, label) =>
+        sendRequest('workspace_pin', { workspace_id: workspaceId, pin: { kind, target, label } }),
+    unpin: (workspaceId, kind, target) =>
+        sendRequest('workspace_unpin', { workspace_id: workspaceId, kind, target }),
+};
+
+export const history = {
+    // { undo, redo, history }; undo/redo carry the menu label or null
+    state: (projectId) => sendRequest('undo_state', { project_id: projectId }),
+    // Resolve with the operation applied; rejects when rows it touched
+    // have been edited since
+    undo: (projectId) => sendRequest('undo', { project_id: projectId }),
+    redo: (projectId) => sendRequest('redo', { project_id: projectId }),
+    moveDocuments: (projectId, documentIds) =>
+        sendRequest('binder_move', { project_id: projectId, document_ids: documentIds }),
+    deleteCodexEntry: (entryId) => sendRequest('codex_entry_delete', { entry_id: entryId }),
+    tagDocuments: (projectId, documentIds, tags, { remove = false } = {}) =>
+        sendRequest('documents_tag', { project_id: projectId, document_ids: documentIds, tags, remove }),
+};
+
+export const activity = {
+    // kinds: 'document_edited' | 'codex_changed' | 'structure' | 'export' | 'backup' | 'ai'
+    feed: (projectId, { kinds = [], subjectId = null, since = null, limit = null } = {}) =>
+        sendRequest('activity_feed', {
+            project_id: projectId,
+            filter: { kinds, subject_id: subjectId, since, limit },
+        }),
+    summary: (projectId, since = null) =>
+        sendRequest('activity_summary', { project_id: projectId, since }),
+    // "What changed since I last opened this"; call once when a project is
+    // opened, as it marks the project seen
+    openProject: (projectId) => sendRequest('activity_open_project', { project_id: projectId }),
+};
+
+export const anonymizer = {
+    // Copies the project into a new one with every word and name replaced
+    // by a made-up word of the same length; resolves with the copy's id
+    anonymize: (projectId, { name = null, includeCodex = true } = {}) =>
+        sendRequest('project_anonymize', {
+            request: { project_id: projectId, name, include_codex: includeCodex },
+        }),
+};
+
+export const embeddings = {
+    models: () => sendRequest('embedding_models'),
+    registerModel: (name, dimensions, provider = '') =>
+        sendRequest('embedding_model_register', { name, dimensions, provider }),
+    // Re-embeds the corpus in the background; the current default model
+    // keeps serving searches until the migration completes
+    migrate: (model, { pruneOld = false } = {}) =>
+        sendRequest('embedding_migration_start', { model, prune_old: pruneOld }),
+    migrationStatus: (migrationId = null) =>
+        sendRequest('embedding_migration_status', { migration_id: migrationId }),
+    cancelMigration: (migrationId) =>
+        sendRequest('embedding_migration_cancel', { migration_id: migrationId }),
+    search: (query, { model = null, limit = 10, documentId = null } = {}) =>
+        sendRequest('embedding_search', { query, model, limit, document_id: documentId }),
+    // Keyword and semantic matches in one list; fusion is
+    // { method: 'rrf', k } or { method: 'weighted_sum', keyword_weight, semantic_weight }
+    hybridSearch: (query, { projectId = null, limit = 20, fusion = { method: 'rrf' }, model = null } = {}) =>
+        sendRequest('hybrid_search', {
+            request: { query, project_id: projectId, limit, fusion, model },
+        }),
+    // Pairs of near-identical paragraphs, with document positions and scores
+    duplicates: (projectId, { threshold = null, minWords = null, model = null, limit = null } = {}) =>
+        sendRequest('find_duplicate_paragraphs', {
+            project_id: projectId, threshold, min_words: minWords, model, limit,
+        }),
+    // Notes for the sidebar; cheap to call on every pause in typing
+    related: (projectId, text, { limit = 8, includeOtherProjects = false, kinds = [] } = {}) =>
+        sendRequest('related_notes', {
+            request: {
+                project_id: projectId, text, limit,
+                include_other_projects: includeOtherProjects, kinds,
+            },
+        }),
+};
+
+export const printing = {
+    listPrinters: () => sendRequest('list_printers'),
+    printDocument: (documentId, { printer = null, copies = 1 } = {}) =>
+        sendRequest('print_document', { document_id: documentId, printer, copies }),
+};
+
+// Declare our API version; resolves with the backend's commands and capabilities
+export const handshake = () => sendRequest('handshake', { api_version: API_VERSION, client: 'herding-cats-frontend' });
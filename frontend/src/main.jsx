@@ -4,13 +4,17 @@ import { HashRouter } from 'react-router-dom';
 import App from './App';
 import './styles/main.css';
 
-import { log } from './api/ipc';
+import { handshake, log } from './api/ipc';
 
 console.log('Main JSX executing');
 if (window.ipc) {
   window.ipc.postMessage(JSON.stringify({ type: 'log', payload: { message: 'Main JSX executing' }, id: 'debug-main' }));
 }
 
+handshake()
+  .then((info) => log(`IPC API v${info.api_version} (backend ${info.backend_version})`))
+  .catch((err) => console.error('IPC handshake failed', err));
+
 ReactDOM.createRoot(document.getElementById('root')).render(
   <React.StrictMode>
     <HashRouter>
@@ -0,0 +1,12 @@
+//! Feeds arbitrary frames to the IPC parser: `cargo +nightly fuzz run ipc_parse`
+
+#![no_main]
+
+use herding_cats_rust::ipc_bridge::parse_request;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = parse_request(text);
+    }
+});
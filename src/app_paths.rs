@@ -0,0 +1,222 @@
+//! App Paths
+//!
+//! Single place that decides where the app keeps its files. Subsystems ask
+//! `AppPaths::global()` instead of building paths from the working or
+//! per-user directories themselves.
+//!
+//! An installed app keeps user data (database, backups, settings) in the
+//! working directory and machine-local state (crash reports, the instance
+//! lock) in the per-user local data directory. A `portable` marker file next
+//! to the executable switches to portable mode, where everything lives in a
+//! `data` directory beside the executable so the app can run from a USB
+//! stick without touching the host.
+//!
+//! Installed apps can move their data directory (see `data_migration`); the
+//! chosen location is recorded in the local state directory, which itself
+//! never moves.
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Marker file next to the executable that enables portable mode
+pub const PORTABLE_MARKER: &str = "portable";
+/// Data directory next to the executable in portable mode
+const PORTABLE_DATA_DIR: &str = "data";
+const APP_DIR: &str = "herding-cats";
+/// Pointer to a user-chosen data directory, kept in the local state directory
+const DATA_LOCATION_FILE: &str = "data-location.json";
+
+static GLOBAL_PATHS: OnceCell<AppPaths> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageMode {
+    Installed,
+    Portable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppPaths {
+    pub mode: StorageMode,
+    /// Database, backups and settings
+    pub data_dir: PathBuf,
+    /// Machine-local state: crash reports, instance lock, registration markers
+    pub local_dir: PathBuf,
+    /// Regenerable files such as thumbnails
+    pub cache_dir: PathBuf,
+}
+
+impl AppPaths {
+    /// Resolve paths for the running executable
+    pub fn detect() -> Self {
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf));
+        let working_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let mut paths = Self::resolve(exe_dir.as_deref(), &working_dir);
+        if let Some(data_dir) = paths.data_dir_override() {
+            paths.data_dir = data_dir;
+        }
+        paths
+    }
+
+    /// Portable when `exe_dir` holds the marker, installed otherwise
+    pub fn resolve(exe_dir: Option<&Path>, working_dir: &Path) -> Self {
+        if let Some(exe_dir) = exe_dir.filter(|dir| dir.join(PORTABLE_MARKER).is_file()) {
+            let data_dir = exe_dir.join(PORTABLE_DATA_DIR);
+            return Self {
+                mode: StorageMode::Portable,
+                local_dir: data_dir.join("local"),
+                cache_dir: data_dir.join("cache"),
+                data_dir,
+            };
+        }
+        Self {
+            mode: StorageMode::Installed,
+            data_dir: working_dir.to_path_buf(),
+            local_dir: dirs::data_local_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join(APP_DIR),
+            cache_dir: dirs::cache_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join(APP_DIR),
+        }
+    }
+
+    /// Paths used by this process, detected on first use
+    pub fn global() -> &'static AppPaths {
+        GLOBAL_PATHS.get_or_init(Self::detect)
+    }
+
+    /// Use `paths` for this process. Has no effect once paths were resolved.
+    pub fn install_global(paths: AppPaths) -> bool {
+        GLOBAL_PATHS.set(paths).is_ok()
+    }
+
+    pub fn is_portable(&self) -> bool {
+        self.mode == StorageMode::Portable
+    }
+
+    /// Data directory chosen by the user, if one was recorded. Portable
+    /// installs always use the directory next to the executable.
+    pub fn data_dir_override(&self) -> Option<PathBuf> {
+        if self.is_portable() {
+            return None;
+        }
+        let content = std::fs::read_to_string(self.local_dir.join(DATA_LOCATION_FILE)).ok()?;
+        let location: DataLocation = serde_json::from_str(&content).ok()?;
+        Some(location.data_dir)
+    }
+
+    /// Record `data_dir` as the data directory for future starts, or go
+    /// back to the default with `None`
+    pub fn set_data_dir_override(&self, data_dir: Option<&Path>) -> std::io::Result<()> {
+        let pointer = self.local_dir.join(DATA_LOCATION_FILE);
+        match data_dir {
+            Some(data_dir) => {
+                std::fs::create_dir_all(&self.local_dir)?;
+                let location = DataLocation {
+                    data_dir: data_dir.to_path_buf(),
+                };
+                std::fs::write(pointer, serde_json::to_string_pretty(&location)?)
+            }
+            None if pointer.exists() => std::fs::remove_file(pointer),
+            None => Ok(()),
+        }
+    }
+
+    /// Create the data, local and cache directories
+    pub fn ensure_dirs(&self) -> std::io::Result<()> {
+        for dir in [&self.data_dir, &self.local_dir, &self.cache_dir] {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Main library database; backups go in `backups` beside it
+    pub fn database_path(&self) -> PathBuf {
+        self.data_dir.join("herding_cats.db")
+    }
+
+    pub fn settings_path(&self) -> PathBuf {
+        self.data_dir.join("settings.json")
+    }
+
+    pub fn theme_settings_path(&self) -> PathBuf {
+        self.data_dir.join("theme_settings.json")
+    }
+
+    pub fn recent_items_path(&self) -> PathBuf {
+        self.data_dir.join("recent_items.json")
+    }
+
+    /// How often each palette and menu command was run
+    pub fn command_usage_path(&self) -> PathBuf {
+        self.data_dir.join("command_usage.json")
+    }
+
+    /// Serial chapters staged for upload, one folder per project
+    pub fn serial_staging_dir(&self) -> PathBuf {
+        self.data_dir.join("serial")
+    }
+
+    /// Working directories for exports and backups, cleared at startup
+    pub fn scratch_dir(&self) -> PathBuf {
+        self.cache_dir.join("scratch")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DataLocation {
+    data_dir: PathBuf,
+}
+
+/// Turn portable mode on or off for the executable in `exe_dir`. Takes
+/// effect on the next start.
+pub fn set_portable(exe_dir: &Path, portable: bool) -> std::io::Result<()> {
+    let marker = exe_dir.join(PORTABLE_MARKER);
+    if portable {
+        std::fs::write(
+            marker,
+            "Herding Cats keeps all of its data in the data folder next to this file.\n",
+        )
+    } else if marker.exists() {
+        std::fs::remove_file(marker)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marker_switches_to_portable_paths() {
+        let exe_dir = tempfile::tempdir().unwrap();
+        let working_dir = Path::new("/home/me/novels");
+
+        let installed = AppPaths::resolve(Some(exe_dir.path()), working_dir);
+        assert_eq!(installed.mode, StorageMode::Installed);
+        assert_eq!(
+            installed.database_path(),
+            working_dir.join("herding_cats.db")
+        );
+
+        set_portable(exe_dir.path(), true).unwrap();
+        let portable = AppPaths::resolve(Some(exe_dir.path()), working_dir);
+        assert!(portable.is_portable());
+        for path in [
+            portable.database_path(),
+            portable.settings_path(),
+            portable.local_dir.clone(),
+            portable.cache_dir.clone(),
+        ] {
+            assert!(path.starts_with(exe_dir.path()));
+        }
+
+        set_portable(exe_dir.path(), false).unwrap();
+        assert!(!AppPaths::resolve(Some(exe_dir.path()), working_dir).is_portable());
+    }
+}
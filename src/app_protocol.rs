@@ -0,0 +1,396 @@
+//! app:// Protocol
+//!
+//! Request handling for the webview's custom protocol: the bundled frontend
+//! plus backend-generated media (fonts, audio previews, PDFs for preview)
+//! registered in a `MediaRegistry` and exposed as `app://localhost/media/<id>`.
+//! Single byte-range requests are honoured for both, and media files are
+//! read chunk by chunk from disk rather than loaded whole, so the webview
+//! can seek through large files. Thumbnails of image assets are served
+//! from `app://localhost/thumbnails/<size>/<hash>`.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use crate::frontend_assets::{AssetResponse, FrontendAssets};
+use crate::thumbnails::{ThumbnailError, ThumbnailSize, Thumbnailer, THUMBNAIL_PREFIX};
+
+/// URL prefix for registered media
+pub const MEDIA_PREFIX: &str = "/media/";
+
+/// Largest body returned for one request; open-ended ranges are cut to this
+/// and the webview asks for the rest
+pub const MAX_CHUNK_BYTES: u64 = 4 * 1024 * 1024;
+
+/// The parts of an incoming request the handler looks at
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolRequest<'a> {
+    pub path: &'a str,
+    pub range: Option<&'a str>,
+    pub if_none_match: Option<&'a str>,
+}
+
+/// An inclusive byte range within a resource of known length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// The `Range` header lies outside the resource (answered with 416)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("requested range not satisfiable")]
+pub struct RangeNotSatisfiable;
+
+/// Parse a `Range` header against a resource of `total` bytes.
+/// `Ok(None)` means the header should be ignored (absent, not bytes, or
+/// multiple ranges); `Err` means the range cannot be satisfied.
+pub fn parse_range(
+    header: Option<&str>,
+    total: u64,
+) -> Result<Option<ByteRange>, RangeNotSatisfiable> {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let (start, end) = spec.split_once('-').ok_or(RangeNotSatisfiable)?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        // Suffix range: the last N bytes
+        let suffix: u64 = end.parse().map_err(|_| RangeNotSatisfiable)?;
+        if suffix == 0 || total == 0 {
+            return Err(RangeNotSatisfiable);
+        }
+        ByteRange {
+            start: total.saturating_sub(suffix),
+            end: total - 1,
+        }
+    } else {
+        let start: u64 = start.parse().map_err(|_| RangeNotSatisfiable)?;
+        let end = match end {
+            "" => total.saturating_sub(1),
+            end => end
+                .parse::<u64>()
+                .map_err(|_| RangeNotSatisfiable)?
+                .min(total.saturating_sub(1)),
+        };
+        if start >= total || end < start {
+            return Err(RangeNotSatisfiable);
+        }
+        ByteRange { start, end }
+    };
+    Ok(Some(range))
+}
+
+/// A file the backend has made available to the webview
+#[derive(Debug, Clone)]
+pub struct MediaEntry {
+    pub path: PathBuf,
+    pub mime_type: String,
+}
+
+/// Files exposed under `app://localhost/media/<id>`. Only registered files
+/// can be reached; ids are random so URLs cannot be guessed.
+#[derive(Debug, Default)]
+pub struct MediaRegistry {
+    entries: RwLock<HashMap<String, MediaEntry>>,
+}
+
+impl MediaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expose a file and return the URL the frontend should load
+    pub fn register(&self, path: &Path, mime_type: &str) -> String {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        if let Ok(mut entries) = self.entries.write() {
+            entries.insert(
+                id.clone(),
+                MediaEntry {
+                    path: path.to_path_buf(),
+                    mime_type: mime_type.to_string(),
+                },
+            );
+        }
+        format!("app://localhost{}{}", MEDIA_PREFIX, id)
+    }
+
+    pub fn unregister(&self, url_or_id: &str) {
+        let id = url_or_id.rsplit('/').next().unwrap_or(url_or_id);
+        if let Ok(mut entries) = self.entries.write() {
+            entries.remove(id);
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<MediaEntry> {
+        self.entries.read().ok()?.get(id).cloned()
+    }
+}
+
+/// Handler behind the `app://` scheme
+#[derive(Debug, Default)]
+pub struct AppProtocol {
+    assets: FrontendAssets,
+    media: Arc<MediaRegistry>,
+    thumbnails: Option<Arc<Thumbnailer>>,
+}
+
+impl AppProtocol {
+    pub fn new(assets: FrontendAssets, media: Arc<MediaRegistry>) -> Self {
+        Self {
+            assets,
+            media,
+            thumbnails: None,
+        }
+    }
+
+    /// Serve asset thumbnails under `/thumbnails/`
+    pub fn with_thumbnails(mut self, thumbnails: Arc<Thumbnailer>) -> Self {
+        self.thumbnails = Some(thumbnails);
+        self
+    }
+
+    pub fn media(&self) -> Arc<MediaRegistry> {
+        self.media.clone()
+    }
+
+    pub fn handle(&self, request: &ProtocolRequest) -> AssetResponse {
+        if let Some(rest) = request.path.strip_prefix(THUMBNAIL_PREFIX) {
+            return self.serve_thumbnail(rest, request.if_none_match);
+        }
+        match request.path.strip_prefix(MEDIA_PREFIX) {
+            Some(id) => self.serve_media(id, request.range),
+            None => {
+                let response = self.assets.respond(request.path, request.if_none_match);
+                apply_range(response, request.range)
+            }
+        }
+    }
+
+    fn serve_media(&self, id: &str, range: Option<&str>) -> AssetResponse {
+        let Some(entry) = self.media.get(id) else {
+            return status_only(404);
+        };
+        match read_file_range(&entry.path, range) {
+            Ok((status, content_range, body)) => {
+                let mut headers = vec![
+                    ("Content-Type", entry.mime_type.clone()),
+                    ("Accept-Ranges", "bytes".to_string()),
+                    ("Cache-Control", "no-store".to_string()),
+                ];
+                if let Some(content_range) = content_range {
+                    headers.push(("Content-Range", content_range));
+                }
+                AssetResponse {
+                    status,
+                    headers,
+                    body: Cow::Owned(body),
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to serve media {}: {}", entry.path.display(), e);
+                status_only(404)
+            }
+        }
+    }
+
+    /// `rest` is `<size>/<hash>`. Thumbnails never change for a hash, so
+    /// they may be cached indefinitely and the hash doubles as the ETag.
+    fn serve_thumbnail(&self, rest: &str, if_none_match: Option<&str>) -> AssetResponse {
+        let Some(thumbnails) = &self.thumbnails else {
+            return status_only(404);
+        };
+        let Some((size, hash)) = rest
+            .split_once('/')
+            .and_then(|(size, hash)| Some((ThumbnailSize::parse(size)?, hash)))
+        else {
+            return status_only(404);
+        };
+        let etag = format!("\"{}-{}\"", hash, size.as_str());
+        let cache_headers = vec![
+            ("ETag", etag.clone()),
+            ("Cache-Control", "max-age=31536000, immutable".to_string()),
+        ];
+        if if_none_match == Some(etag.as_str()) {
+            return AssetResponse {
+                status: 304,
+                headers: cache_headers,
+                body: Cow::Borrowed(&[]),
+            };
+        }
+
+        match thumbnails
+            .get(hash, size)
+            .and_then(|thumbnail| Ok((thumbnail.mime_type, std::fs::read(thumbnail.path)?)))
+        {
+            Ok((mime_type, body)) => {
+                let mut headers = cache_headers;
+                headers.push(("Content-Type", mime_type.to_string()));
+                AssetResponse {
+                    status: 200,
+                    headers,
+                    body: Cow::Owned(body),
+                }
+            }
+            Err(ThumbnailError::NotFound(_)) => status_only(404),
+            Err(e) => {
+                log::warn!("Failed to generate thumbnail for {}: {}", hash, e);
+                status_only(415)
+            }
+        }
+    }
+}
+
+/// Read the requested part of a file: (status, Content-Range, body)
+fn read_file_range(
+    path: &Path,
+    range: Option<&str>,
+) -> std::io::Result<(u16, Option<String>, Vec<u8>)> {
+    let mut file = File::open(path)?;
+    let total = file.metadata()?.len();
+
+    let range = match parse_range(range, total) {
+        Ok(range) => range,
+        Err(RangeNotSatisfiable) => {
+            return Ok((416, Some(format!("bytes */{}", total)), Vec::new()))
+        }
+    };
+    let range = match range {
+        Some(range) => range,
+        None if total <= MAX_CHUNK_BYTES => {
+            let mut body = Vec::with_capacity(total as usize);
+            file.read_to_end(&mut body)?;
+            return Ok((200, None, body));
+        }
+        // Too large to hand over in one piece: answer with the first chunk
+        // as a partial response and let the webview request the rest
+        None => ByteRange {
+            start: 0,
+            end: total - 1,
+        },
+    };
+
+    let end = range.end.min(range.start + MAX_CHUNK_BYTES - 1);
+    let mut body = vec![0; (end - range.start + 1) as usize];
+    file.seek(SeekFrom::Start(range.start))?;
+    file.read_exact(&mut body)?;
+    Ok((
+        206,
+        Some(format!("bytes {}-{}/{}", range.start, end, total)),
+        body,
+    ))
+}
+
+/// Narrow a full in-memory response to the requested range
+fn apply_range(mut response: AssetResponse, range: Option<&str>) -> AssetResponse {
+    if response.status != 200 {
+        return response;
+    }
+    response
+        .headers
+        .push(("Accept-Ranges", "bytes".to_string()));
+    let total = response.body.len() as u64;
+
+    match parse_range(range, total) {
+        Ok(None) => response,
+        Ok(Some(range)) => {
+            let (start, end) = (range.start as usize, range.end as usize + 1);
+            response.body = match response.body {
+                Cow::Borrowed(bytes) => Cow::Borrowed(&bytes[start..end]),
+                Cow::Owned(bytes) => Cow::Owned(bytes[start..end].to_vec()),
+            };
+            response.status = 206;
+            response.headers.push((
+                "Content-Range",
+                format!("bytes {}-{}/{}", range.start, range.end, total),
+            ));
+            response
+        }
+        Err(RangeNotSatisfiable) => AssetResponse {
+            status: 416,
+            headers: vec![("Content-Range", format!("bytes */{}", total))],
+            body: Cow::Borrowed(&[]),
+        },
+    }
+}
+
+fn status_only(status: u16) -> AssetResponse {
+    AssetResponse {
+        status,
+        headers: Vec::new(),
+        body: Cow::Borrowed(&[]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_parse_range_forms() {
+        let range = |start, end| Ok(Some(ByteRange { start, end }));
+        assert_eq!(parse_range(Some("bytes=0-99"), 1000), range(0, 99));
+        assert_eq!(parse_range(Some("bytes=900-"), 1000), range(900, 999));
+        assert_eq!(parse_range(Some("bytes=-100"), 1000), range(900, 999));
+        assert_eq!(parse_range(Some("bytes=990-2000"), 1000), range(990, 999));
+        assert_eq!(
+            parse_range(Some("bytes=1000-"), 1000),
+            Err(RangeNotSatisfiable)
+        );
+        assert_eq!(parse_range(Some("bytes=0-1,5-9"), 1000), Ok(None));
+        assert_eq!(parse_range(None, 1000), Ok(None));
+    }
+
+    #[test]
+    fn test_media_is_served_in_ranges() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"0123456789").unwrap();
+        let protocol = AppProtocol::default();
+        let url = protocol.media().register(file.path(), "audio/mpeg");
+        let path = url.trim_start_matches("app://localhost");
+
+        let partial = protocol.handle(&ProtocolRequest {
+            path,
+            range: Some("bytes=2-5"),
+            ..Default::default()
+        });
+        assert_eq!(partial.status, 206);
+        assert_eq!(partial.body.as_ref(), b"2345");
+        assert!(partial
+            .headers
+            .contains(&("Content-Range", "bytes 2-5/10".to_string())));
+
+        let full = protocol.handle(&ProtocolRequest {
+            path,
+            ..Default::default()
+        });
+        assert_eq!(full.status, 200);
+        assert_eq!(full.body.len(), 10);
+
+        let unsatisfiable = protocol.handle(&ProtocolRequest {
+            path,
+            range: Some("bytes=50-"),
+            ..Default::default()
+        });
+        assert_eq!(unsatisfiable.status, 416);
+
+        protocol.media().unregister(&url);
+        assert_eq!(
+            protocol
+                .handle(&ProtocolRequest {
+                    path,
+                    ..Default::default()
+                })
+                .status,
+            404
+        );
+    }
+}
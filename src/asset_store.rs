@@ -0,0 +1,163 @@
+//! Asset Store
+//!
+//! Content-addressed storage for binary files the library references
+//! (attachments, images). Each blob lives at `assets/<aa>/<sha256>` in the
+//! data directory, so identical files are stored once and a blob never
+//! changes after it is written. Database rows refer to blobs by hash; blobs
+//! no row refers to are removed by `retain`.
+
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::app_paths::AppPaths;
+
+/// A blob written to the store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredAsset {
+    /// Lowercase hex SHA-256 of the content
+    pub hash: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct AssetStore {
+    root: PathBuf,
+}
+
+impl AssetStore {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// `assets` in the data directory
+    pub fn open_default() -> Self {
+        Self::new(&AppPaths::global().data_dir.join("assets"))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn put_bytes(&self, bytes: &[u8]) -> std::io::Result<StoredAsset> {
+        self.put_reader(bytes)
+    }
+
+    pub fn put_file(&self, path: &Path) -> std::io::Result<StoredAsset> {
+        self.put_reader(std::fs::File::open(path)?)
+    }
+
+    /// Stream `reader` into a temporary file while hashing it, then move it
+    /// into place under its hash
+    pub fn put_reader(&self, mut reader: impl Read) -> std::io::Result<StoredAsset> {
+        std::fs::create_dir_all(&self.root)?;
+        let mut temp = tempfile::NamedTempFile::new_in(&self.root)?;
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            temp.write_all(&buffer[..read])?;
+            size += read as u64;
+        }
+
+        let hash = format!("{:x}", hasher.finalize());
+        let target = self.blob_path(&hash);
+        if !target.exists() {
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            temp.persist(&target).map_err(|e| e.error)?;
+        }
+        Ok(StoredAsset { hash, size })
+    }
+
+    /// Path of a stored blob, `None` for malformed hashes or missing blobs
+    pub fn path(&self, hash: &str) -> Option<PathBuf> {
+        if !is_hash(hash) {
+            return None;
+        }
+        Some(self.blob_path(hash)).filter(|path| path.is_file())
+    }
+
+    pub fn read(&self, hash: &str) -> std::io::Result<Vec<u8>> {
+        let path = self.path(hash).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, format!("asset {}", hash))
+        })?;
+        std::fs::read(path)
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.path(hash).is_some()
+    }
+
+    /// Hashes of every stored blob
+    pub fn hashes(&self) -> std::io::Result<Vec<String>> {
+        let mut hashes = Vec::new();
+        let Ok(shards) = std::fs::read_dir(&self.root) else {
+            return Ok(hashes);
+        };
+        for shard in shards.flatten().filter(|entry| entry.path().is_dir()) {
+            for blob in std::fs::read_dir(shard.path())?.flatten() {
+                let name = blob.file_name().to_string_lossy().to_string();
+                if is_hash(&name) {
+                    hashes.push(name);
+                }
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// Delete every blob whose hash `keep` rejects. Returns how many were removed.
+    pub fn retain(&self, keep: impl Fn(&str) -> bool) -> std::io::Result<usize> {
+        let mut removed = 0;
+        for hash in self.hashes()? {
+            if !keep(&hash) {
+                std::fs::remove_file(self.blob_path(&hash))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.root.join(&hash[..2]).join(hash)
+    }
+}
+
+fn is_hash(value: &str) -> bool {
+    value.len() == 64
+        && value
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_content_is_stored_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AssetStore::new(dir.path());
+
+        let first = store.put_bytes(b"portrait").unwrap();
+        let second = store.put_bytes(b"portrait").unwrap();
+        let other = store.put_bytes(b"map").unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.size, 8);
+        assert_eq!(store.read(&first.hash).unwrap(), b"portrait");
+        assert_eq!(store.hashes().unwrap().len(), 2);
+
+        assert!(store.path("../../etc/passwd").is_none());
+        assert_eq!(store.retain(|hash| hash == other.hash).unwrap(), 1);
+        assert!(!store.contains(&first.hash));
+        assert!(store.contains(&other.hash));
+    }
+}
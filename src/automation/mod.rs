@@ -1,13 +1,19 @@
+use crate::database::backup_service::{BackupType, RetentionPolicy};
+use crate::database::models::export_record::ExportHook;
+use crate::database::BackupService;
 use crate::error::{AppError, WritingToolError};
-use chrono::{DateTime, Utc};
+use crate::generators::{self, GeneratorTable};
+use crate::notifications::{DesktopNotification, NotificationAction, Notifier};
+use chrono::{DateTime, Datelike, Local, NaiveTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 /// Scripting and Automation Framework
 /// Provides comprehensive workflow automation, macro system, and custom script execution capabilities
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
 use uuid::Uuid;
 
 /// Script definition and metadata
@@ -204,6 +210,68 @@ pub struct AutomationWorkflow {
     pub tags: Vec<String>,
 }
 
+impl AutomationWorkflow {
+    /// Workflow that runs `backup` on `schedule`
+    pub fn scheduled_backup(name: &str, schedule: WorkflowSchedule, backup: BackupAction) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: format!("{:?} backup", backup.backup_type),
+            version: "1.0".to_string(),
+            author: "system".to_string(),
+            created_at: now,
+            updated_at: now,
+            enabled: true,
+            triggers: vec![WorkflowTrigger::Schedule { schedule }],
+            actions: vec![WorkflowAction {
+                id: Uuid::new_v4(),
+                action_type: ActionType::Backup(backup),
+                name: "Back up database".to_string(),
+                parameters: HashMap::new(),
+                condition: None,
+                on_error: ErrorAction::Stop,
+                timeout: None,
+            }],
+            conditions: vec![],
+            error_handling: ErrorHandling {
+                on_error: ErrorAction::Stop,
+                retry_count: 0,
+                retry_delay: Duration::from_secs(0),
+                continue_on_error: false,
+                log_errors: true,
+                notify_on_error: true,
+            },
+            schedule: None,
+            tags: vec!["backup".to_string()],
+        }
+    }
+
+    /// Schedules from the workflow's triggers and its own schedule
+    pub fn schedules(&self) -> impl Iterator<Item = &WorkflowSchedule> {
+        self.triggers
+            .iter()
+            .filter_map(|trigger| match trigger {
+                WorkflowTrigger::Schedule { schedule } => Some(schedule),
+                _ => None,
+            })
+            .chain(self.schedule.as_ref())
+    }
+
+    /// Earliest run of any of the workflow's schedules after `after`
+    pub fn next_run_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedules()
+            .filter_map(|schedule| schedule.next_after(after))
+            .min()
+    }
+
+    fn runs_backup(&self) -> bool {
+        self.actions
+            .iter()
+            .any(|action| matches!(action.action_type, ActionType::Backup(_)))
+    }
+}
+
 /// Workflow trigger types
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum WorkflowTrigger {
@@ -233,8 +301,14 @@ pub enum EventType {
     DocumentModified,
     DocumentDeleted,
     DocumentRenamed,
+    /// A document went to another project
+    DocumentMoved,
+    /// A document was copied into a project
+    DocumentCopied,
     ProjectOpened,
     ProjectClosed,
+    /// A project's daily or weekly word goal was reached
+    WritingGoalMet,
     Custom(String),
 }
 
@@ -282,6 +356,17 @@ pub struct WorkflowAction {
     pub timeout: Option<Duration>,
 }
 
+/// Backup taken by a workflow, followed by pruning
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BackupAction {
+    pub backup_type: BackupType,
+    pub project_id: Option<String>,
+    pub description: Option<String>,
+    /// Old automatic backups are pruned to this policy after each run
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+}
+
 /// Action types
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ActionType {
@@ -311,6 +396,9 @@ pub enum ActionType {
         title: String,
         message: String,
         level: NotificationLevel,
+        /// Buttons shown on the notification, e.g. "Open file"
+        #[serde(default)]
+        actions: Vec<NotificationAction>,
     },
     OpenDocument {
         path: PathBuf,
@@ -322,6 +410,18 @@ pub enum ActionType {
         name: String,
         template: Option<String>,
     },
+    /// Roll a random generator table; the results are the action's output,
+    /// one per line
+    RollGenerator {
+        table: String,
+        #[serde(default)]
+        variables: BTreeMap<String, String>,
+        /// Rolls once when 0
+        #[serde(default)]
+        count: usize,
+    },
+    /// Back up the database with the engine's backup service
+    Backup(BackupAction),
     Custom {
         type_name: String,
         implementation: String,
@@ -390,6 +490,105 @@ pub struct WorkflowSchedule {
     pub end_date: Option<DateTime<Utc>>,
 }
 
+impl WorkflowSchedule {
+    /// Every day at `time` ("HH:MM" local time)
+    pub fn daily(time: &str) -> Self {
+        Self {
+            schedule_type: ScheduleType::Daily,
+            interval: None,
+            time: Some(time.to_string()),
+            days: vec![],
+            timezone: "local".to_string(),
+            start_date: None,
+            end_date: None,
+        }
+    }
+
+    /// Every week on `day` (0 is Sunday) at `time` ("HH:MM" local time)
+    pub fn weekly(day: u8, time: &str) -> Self {
+        Self {
+            schedule_type: ScheduleType::Weekly,
+            days: vec![day],
+            ..Self::daily(time)
+        }
+    }
+
+    /// First run after `after`, or `None` once the schedule has ended.
+    /// Times of day are UTC when `timezone` is "UTC" and local time
+    /// otherwise. Daily schedules with `days` run only on those days;
+    /// weekly schedules without any run on Sundays; monthly schedules run
+    /// on the day of the month of `start_date`, or the 1st. Cron schedules
+    /// aren't supported and never run.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let next = match self.schedule_type {
+            ScheduleType::Interval => {
+                let interval = chrono::Duration::from_std(self.interval?).ok()?;
+                if interval <= chrono::Duration::zero() {
+                    return None;
+                }
+                match self.start_date {
+                    Some(start) if start > after => start,
+                    _ => after + interval,
+                }
+            }
+            ScheduleType::Daily | ScheduleType::Weekly | ScheduleType::Monthly => {
+                self.next_calendar_run(after)?
+            }
+            ScheduleType::Cron => return None,
+        };
+
+        match self.end_date {
+            Some(end) if next > end => None,
+            _ => Some(next),
+        }
+    }
+
+    fn next_calendar_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let utc = self.timezone.eq_ignore_ascii_case("UTC");
+        let time = match &self.time {
+            Some(time) => NaiveTime::parse_from_str(time, "%H:%M").ok()?,
+            None => NaiveTime::MIN,
+        };
+        let from = self.start_date.map_or(after, |start| start.max(after));
+        let first_day = if utc {
+            from.date_naive()
+        } else {
+            from.with_timezone(&Local).date_naive()
+        };
+        let month_day = self.start_date.map_or(1, |start| start.day());
+
+        // A year and a bit covers every weekly and monthly pattern
+        for date in first_day.iter_days().take(400) {
+            let weekday = date.weekday().num_days_from_sunday() as u8;
+            let due = match self.schedule_type {
+                ScheduleType::Daily => self.days.is_empty() || self.days.contains(&weekday),
+                ScheduleType::Weekly if self.days.is_empty() => weekday == 0,
+                ScheduleType::Weekly => self.days.contains(&weekday),
+                ScheduleType::Monthly => date.day() == month_day,
+                _ => false,
+            };
+            if !due {
+                continue;
+            }
+
+            let at = date.and_time(time);
+            // Local times skipped by a DST change don't run that day
+            let at = if utc {
+                Some(Utc.from_utc_datetime(&at))
+            } else {
+                Local
+                    .from_local_datetime(&at)
+                    .earliest()
+                    .map(|at| at.with_timezone(&Utc))
+            };
+            if let Some(at) = at.filter(|at| *at > after && *at >= from) {
+                return Some(at);
+            }
+        }
+        None
+    }
+}
+
 /// Schedule types
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ScheduleType {
@@ -539,6 +738,42 @@ pub struct ExecutionResult {
     pub logs: Vec<LogEntry>,
 }
 
+/// The finished export an export hook runs for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportHookInput {
+    pub job_id: String,
+    pub document_id: Option<String>,
+    pub export_type: String,
+    pub output_path: PathBuf,
+}
+
+impl ExportHookInput {
+    /// Script parameters and workflow placeholders, such as `{output_path}`
+    pub fn parameters(&self) -> HashMap<String, serde_json::Value> {
+        let mut parameters = HashMap::from([
+            ("job_id".to_string(), self.job_id.clone().into()),
+            ("export_type".to_string(), self.export_type.clone().into()),
+            (
+                "output_path".to_string(),
+                self.output_path.to_string_lossy().into_owned().into(),
+            ),
+        ]);
+        if let Some(document_id) = &self.document_id {
+            parameters.insert("document_id".to_string(), document_id.clone().into());
+        }
+        parameters
+    }
+}
+
+/// How one export hook went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportHookOutcome {
+    pub hook: ExportHook,
+    pub success: bool,
+    pub output: String,
+    pub error_message: Option<String>,
+}
+
 /// Log entry for script execution
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LogEntry {
@@ -569,6 +804,8 @@ pub struct ScriptEngine {
     event_system: Arc<RwLock<EventSystem>>,
     scheduler: Arc<RwLock<WorkflowScheduler>>,
     sandbox: Arc<RwLock<ScriptSandbox>>,
+    generator_tables: Arc<RwLock<Vec<GeneratorTable>>>,
+    backup_service: Arc<RwLock<Option<Arc<RwLock<BackupService>>>>>,
 }
 
 /// Runtime context for script execution
@@ -587,6 +824,13 @@ pub struct EventSystem {
     pub event_queue: Arc<Mutex<VecDeque<SystemEvent>>>,
 }
 
+impl EventSystem {
+    /// Queue an event for workflows to pick up
+    pub async fn push(&self, event: SystemEvent) {
+        self.event_queue.lock().await.push_back(event);
+    }
+}
+
 /// System event definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemEvent {
@@ -615,6 +859,7 @@ pub struct ScheduledWorkflow {
     pub next_execution: DateTime<Utc>,
     pub interval: Option<Duration>,
     pub trigger_type: ScheduleType,
+    pub last_execution: Option<DateTime<Utc>>,
 }
 
 /// Running workflow information
@@ -815,25 +1060,43 @@ impl ScriptEngine {
                     sandbox_by_default: true,
                 },
             })),
+            generator_tables: Arc::new(RwLock::new(Vec::new())),
+            backup_service: Arc::new(RwLock::new(None)),
         }
     }
 
-    /// Create a new script
-    pub fn create_script(&self, script: Script) -> Result<Uuid, crate::error::AppError> {
-        let script_id = script.id;
+    /// Set the tables `RollGenerator` actions roll from
+    pub async fn set_generator_tables(&self, tables: Vec<GeneratorTable>) {
+        *self.generator_tables.write().await = tables;
+    }
 
-        {
-            let mut scripts = self.scripts.write().unwrap();
-            scripts.insert(script_id, script);
-        }
+    /// Set the service `Backup` actions back up with; it is told when the
+    /// next scheduled backup is due
+    pub async fn set_backup_service(&self, service: Arc<RwLock<BackupService>>) {
+        *self.backup_service.write().await = Some(service);
+        self.sync_backup_schedule().await;
+    }
 
+    /// Create a new script
+    pub async fn create_script(&self, script: Script) -> Result<Uuid, crate::error::AppError> {
+        let script_id = script.id;
+        self.scripts.write().await.insert(script_id, script);
         Ok(script_id)
     }
 
     /// Get script by ID
-    pub fn get_script(&self, script_id: Uuid) -> Option<Script> {
-        let scripts = self.scripts.read().unwrap();
-        scripts.get(&script_id).cloned()
+    pub async fn get_script(&self, script_id: Uuid) -> Option<Script> {
+        self.scripts.read().await.get(&script_id).cloned()
+    }
+
+    /// All scripts, in no particular order
+    pub async fn scripts(&self) -> Vec<Script> {
+        self.scripts.read().await.values().cloned().collect()
+    }
+
+    /// All workflows, in no particular order
+    pub async fn workflows(&self) -> Vec<AutomationWorkflow> {
+        self.workflows.read().await.values().cloned().collect()
     }
 
     /// Execute a script
@@ -842,59 +1105,60 @@ impl ScriptEngine {
         script_id: Uuid,
         parameters: HashMap<String, serde_json::Value>,
     ) -> Result<ExecutionResult, crate::error::AppError> {
-        let script = self
-            .get_script(script_id)
-            .ok_or(crate::error::AppError::ToolNotFound {
-                tool: format!("script_{}", script_id),
-            })?;
+        let script =
+            self.get_script(script_id)
+                .await
+                .ok_or(crate::error::AppError::ToolNotFound {
+                    tool: format!("script_{}", script_id),
+                })?;
 
         let execution_id = Uuid::new_v4();
         let start_time = Instant::now();
 
         // Update runtime context
         {
-            let mut context = self.runtime_context.write().unwrap();
+            let mut context = self.runtime_context.write().await;
             context.current_execution = Some(execution_id);
             context.execution_count += 1;
             context.active_variables.extend(parameters.clone());
         }
 
         // Check permissions and sandbox if needed
-        if script.permissions.sandboxed {
+        let result = if script.permissions.sandboxed {
             self.execute_in_sandbox(&script, parameters).await
         } else {
             self.execute_directly(&script, parameters).await
-        }
-        .inspect(|result| {
-            let execution_time = start_time.elapsed();
+        }?;
+        let execution_time = start_time.elapsed();
 
-            // Update script metadata
-            {
-                let mut scripts = self.scripts.write().unwrap();
-                if let Some(script_mut) = scripts.get_mut(&script_id) {
-                    script_mut.metadata.execution_count += 1;
-                    script_mut.metadata.total_execution_time += execution_time;
-                    script_mut.metadata.last_executed = Some(Utc::now());
-
-                    if script_mut.metadata.execution_count > 0 {
-                        script_mut.metadata.average_execution_time =
-                            script_mut.metadata.total_execution_time
-                                / script_mut.metadata.execution_count as u32;
-                    }
+        // Update script metadata
+        {
+            let mut scripts = self.scripts.write().await;
+            if let Some(script_mut) = scripts.get_mut(&script_id) {
+                script_mut.metadata.execution_count += 1;
+                script_mut.metadata.total_execution_time += execution_time;
+                script_mut.metadata.last_executed = Some(Utc::now());
+
+                if script_mut.metadata.execution_count > 0 {
+                    script_mut.metadata.average_execution_time =
+                        script_mut.metadata.total_execution_time
+                            / script_mut.metadata.execution_count as u32;
                 }
             }
+        }
 
-            // Store execution history
-            {
-                let mut history = self.execution_history.write().unwrap();
-                history.push_back(result.clone());
+        // Store execution history
+        {
+            let mut history = self.execution_history.write().await;
+            history.push_back(result.clone());
 
-                // Keep only last 1000 executions
-                if history.len() > 1000 {
-                    history.pop_front();
-                }
+            // Keep only last 1000 executions
+            if history.len() > 1000 {
+                history.pop_front();
             }
-        })
+        }
+
+        Ok(result)
     }
 
     /// Execute script in sandbox
@@ -908,7 +1172,7 @@ impl ScriptEngine {
 
         // Create isolated environment
         {
-            let mut sandbox = self.sandbox.write().unwrap();
+            let mut sandbox = self.sandbox.write().await;
             sandbox.isolated_environments.insert(
                 execution_id,
                 IsolatedEnvironment {
@@ -1124,64 +1388,213 @@ impl ScriptEngine {
     }
 
     /// Create automation workflow
-    pub fn create_workflow(&self, workflow: AutomationWorkflow) -> Result<Uuid, AppError> {
+    pub async fn create_workflow(&self, workflow: AutomationWorkflow) -> Result<Uuid, AppError> {
         let workflow_id = workflow.id;
-
-        {
-            let mut workflows = self.workflows.write().unwrap();
-            workflows.insert(workflow_id, workflow);
-        }
+        self.workflows.write().await.insert(workflow_id, workflow);
 
         // Register workflow triggers
-        self.register_workflow_triggers(workflow_id)?;
+        self.register_workflow_triggers(workflow_id).await?;
 
         Ok(workflow_id)
     }
 
     /// Register workflow triggers
-    fn register_workflow_triggers(&self, workflow_id: Uuid) -> Result<(), AppError> {
-        let workflows = self.workflows.read().unwrap();
-        let workflow = workflows.get(&workflow_id).ok_or(AppError::ToolNotFound {
-            tool: format!("workflow_{}", workflow_id),
-        })?;
+    async fn register_workflow_triggers(&self, workflow_id: Uuid) -> Result<(), AppError> {
+        let workflow = self
+            .workflows
+            .read()
+            .await
+            .get(&workflow_id)
+            .cloned()
+            .ok_or(AppError::ToolNotFound {
+                tool: format!("workflow_{}", workflow_id),
+            })?;
 
         for trigger in &workflow.triggers {
-            match trigger {
-                WorkflowTrigger::Event { event_type, .. } => {
-                    let _event_system = self.event_system.write().unwrap();
-                    // Event handlers functionality removed - EventSystem only has event_queue
-                    // This would need to be reimplemented if event handling is required
-                    log::info!(
-                        "Registering workflow {} for event type {:?}",
+            if let WorkflowTrigger::Event { event_type, .. } = trigger {
+                let _event_system = self.event_system.write().await;
+                // Event handlers functionality removed - EventSystem only has event_queue
+                // This would need to be reimplemented if event handling is required
+                log::info!(
+                    "Registering workflow {} for event type {:?}",
+                    workflow_id,
+                    event_type
+                );
+            }
+        }
+
+        // Register with scheduler; a workflow replaced without schedules
+        // stops running
+        let now = Utc::now();
+        let first = workflow
+            .schedules()
+            .filter_map(|schedule| Some((schedule.next_after(now)?, schedule)))
+            .min_by_key(|(next_execution, _)| *next_execution);
+        {
+            let mut scheduler = self.scheduler.write().await;
+            match first {
+                Some((next_execution, schedule)) => {
+                    scheduler.scheduled_workflows.insert(
                         workflow_id,
-                        event_type
+                        ScheduledWorkflow {
+                            workflow_id,
+                            next_execution,
+                            interval: schedule.interval,
+                            trigger_type: schedule.schedule_type.clone(),
+                            last_execution: None,
+                        },
                     );
                 }
-                WorkflowTrigger::Schedule { .. } => {
-                    // Register with scheduler
-                    let _scheduler = self.scheduler.write().unwrap();
-                    // Add to scheduled workflows
+                None => {
+                    scheduler.scheduled_workflows.remove(&workflow_id);
                 }
-                _ => {}
             }
         }
+        self.sync_backup_schedule().await;
 
         Ok(())
     }
 
+    /// When a scheduled workflow next runs
+    pub async fn next_scheduled_run(&self, workflow_id: Uuid) -> Option<DateTime<Utc>> {
+        self.scheduler
+            .read()
+            .await
+            .scheduled_workflows
+            .get(&workflow_id)
+            .map(|scheduled| scheduled.next_execution)
+    }
+
+    /// Run the enabled workflows whose next run is at or before `now`,
+    /// and schedule their following runs. Runs missed while the app was
+    /// closed are not made up.
+    pub async fn run_due_workflows(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Vec<(Uuid, Result<ExecutionResult, WritingToolError>)> {
+        let due: Vec<Uuid> = self
+            .scheduler
+            .read()
+            .await
+            .scheduled_workflows
+            .values()
+            .filter(|scheduled| scheduled.next_execution <= now)
+            .map(|scheduled| scheduled.workflow_id)
+            .collect();
+
+        let mut results = Vec::new();
+        for workflow_id in due {
+            let workflow = self.workflows.read().await.get(&workflow_id).cloned();
+
+            // Move on before running, so a slow run isn't started twice
+            {
+                let next = workflow.as_ref().and_then(|w| w.next_run_after(now));
+                let mut scheduler = self.scheduler.write().await;
+                match next {
+                    Some(next_execution) => {
+                        if let Some(scheduled) = scheduler.scheduled_workflows.get_mut(&workflow_id)
+                        {
+                            scheduled.next_execution = next_execution;
+                            scheduled.last_execution = Some(now);
+                        }
+                    }
+                    None => {
+                        scheduler.scheduled_workflows.remove(&workflow_id);
+                    }
+                }
+            }
+
+            let Some(workflow) = workflow.filter(|w| w.enabled) else {
+                continue;
+            };
+            let result = self.execute_workflow(workflow_id).await;
+            if workflow.runs_backup() {
+                if let Some(service) = self.backup_service.read().await.clone() {
+                    service.read().await.record_scheduled_run().await;
+                }
+            }
+            results.push((workflow_id, result));
+        }
+
+        self.sync_backup_schedule().await;
+        results
+    }
+
+    /// Run due workflows every `tick` until the task is aborted
+    pub fn spawn_scheduler(self: Arc<Self>, tick: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(tick);
+            loop {
+                ticks.tick().await;
+                for (workflow_id, result) in self.run_due_workflows(Utc::now()).await {
+                    match result {
+                        Ok(result) if result.success => {}
+                        Ok(result) => log::warn!(
+                            "Scheduled workflow {} failed: {}",
+                            workflow_id,
+                            result.error_message.unwrap_or(result.output)
+                        ),
+                        Err(e) => log::warn!("Scheduled workflow {} failed: {}", workflow_id, e),
+                    }
+                }
+            }
+        })
+    }
+
+    /// Tell the backup service when the next scheduled backup is due
+    async fn sync_backup_schedule(&self) {
+        let Some(service) = self.backup_service.read().await.clone() else {
+            return;
+        };
+        let next = {
+            let workflows = self.workflows.read().await;
+            let scheduler = self.scheduler.read().await;
+            scheduler
+                .scheduled_workflows
+                .values()
+                .filter(|scheduled| {
+                    workflows
+                        .get(&scheduled.workflow_id)
+                        .is_some_and(|w| w.enabled && w.runs_backup())
+                })
+                .map(|scheduled| scheduled.next_execution)
+                .min()
+        };
+        service
+            .read()
+            .await
+            .set_next_scheduled_run(next.map(|at| at.timestamp().max(0) as u64))
+            .await;
+    }
+
     /// Execute workflow
     pub async fn execute_workflow(
         &self,
         workflow_id: Uuid,
     ) -> Result<ExecutionResult, WritingToolError> {
-        let workflows = self.workflows.read().unwrap();
-        let workflow = workflows
+        self.execute_workflow_with(workflow_id, HashMap::new())
+            .await
+    }
+
+    /// Execute a workflow with `context` values, which are passed to its
+    /// scripts and fill `{name}` placeholders in command arguments and
+    /// file paths
+    pub async fn execute_workflow_with(
+        &self,
+        workflow_id: Uuid,
+        context: HashMap<String, serde_json::Value>,
+    ) -> Result<ExecutionResult, WritingToolError> {
+        // Run from a copy so actions never wait on the workflow table
+        let workflow = self
+            .workflows
+            .read()
+            .await
             .get(&workflow_id)
+            .cloned()
             .ok_or(WritingToolError::WorkflowNotFound(workflow_id))?;
 
         let start_time = Instant::now();
         let mut logs = Vec::new();
-        let context = HashMap::new();
 
         // Execute actions in sequence
         for (index, action) in workflow.actions.iter().enumerate() {
@@ -1303,8 +1716,8 @@ impl ScriptEngine {
                 ref command,
                 ref arguments,
             } => {
-                let mut cmd = Command::new(command);
-                cmd.args(arguments)
+                let mut cmd = Command::new(fill_placeholders(command, context));
+                cmd.args(arguments.iter().map(|arg| fill_placeholders(arg, context)))
                     .stdout(Stdio::piped())
                     .stderr(Stdio::piped());
 
@@ -1350,28 +1763,84 @@ impl ScriptEngine {
                     logs: vec![],
                 })
             }
+            ActionType::CopyFile { ref from, ref to } => {
+                let from = PathBuf::from(fill_placeholders(&from.to_string_lossy(), context));
+                let mut to = PathBuf::from(fill_placeholders(&to.to_string_lossy(), context));
+                // Into a folder, keeping the file's name
+                if to.is_dir() {
+                    if let Some(name) = from.file_name() {
+                        to.push(name);
+                    }
+                }
+                if let Some(parent) = to.parent() {
+                    std::fs::create_dir_all(parent)
+                        .map_err(|e| WritingToolError::FileSystemError(e.to_string()))?;
+                }
+                std::fs::copy(&from, &to)
+                    .map_err(|e| WritingToolError::FileSystemError(e.to_string()))?;
+
+                Ok(ExecutionResult {
+                    success: true,
+                    output: format!("Copied {} to {}", from.display(), to.display()),
+                    error_message: None,
+                    execution_time: Duration::from_millis(0),
+                    return_code: Some(0),
+                    stdout_file: None,
+                    stderr_file: None,
+                    logs: vec![],
+                })
+            }
             ActionType::SendNotification {
                 ref title,
                 ref message,
                 ref level,
+                ref actions,
             } => {
-                // Send system notification
-                println!(
-                    "[{}] {}: {}",
-                    match level {
-                        NotificationLevel::Info => "INFO",
-                        NotificationLevel::Warning => "WARNING",
-                        NotificationLevel::Error => "ERROR",
-                        NotificationLevel::Success => "SUCCESS",
-                    },
-                    title,
-                    message
-                );
+                let notification = DesktopNotification {
+                    title: title.clone(),
+                    body: message.clone(),
+                    level: level.clone(),
+                    actions: actions.clone(),
+                };
+                let sent = Notifier::global().notify(&notification);
 
                 Ok(ExecutionResult {
-                    success: true,
+                    success: sent.is_ok(),
                     output: format!("Notification sent: {}", title),
-                    error_message: None,
+                    error_message: sent.err().map(|e| e.to_string()),
+                    execution_time: Duration::from_millis(0),
+                    return_code: Some(0),
+                    stdout_file: None,
+                    stderr_file: None,
+                    logs: vec![],
+                })
+            }
+            ActionType::RollGenerator {
+                ref table,
+                ref variables,
+                count,
+            } => {
+                // Context values that are plain strings are available as
+                // variables too, with the action's own taking precedence
+                let mut values: HashMap<String, String> = context
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                    .collect();
+                values.extend(variables.clone());
+
+                let tables = self.generator_tables.read().await;
+                let rolled = generators::roll_many(
+                    tables.iter(),
+                    table,
+                    &values,
+                    *count,
+                    &mut rand::thread_rng(),
+                );
+
+                Ok(ExecutionResult {
+                    success: rolled.is_ok(),
+                    output: rolled.as_ref().map(|r| r.join("\n")).unwrap_or_default(),
+                    error_message: rolled.err().map(|e| e.to_string()),
                     execution_time: Duration::from_millis(0),
                     return_code: Some(0),
                     stdout_file: None,
@@ -1379,6 +1848,7 @@ impl ScriptEngine {
                     logs: vec![],
                 })
             }
+            ActionType::Backup(backup) => self.run_backup(backup).await,
             _ => {
                 // Handle other action types
                 Ok(ExecutionResult {
@@ -1395,6 +1865,97 @@ impl ScriptEngine {
         }
     }
 
+    /// Run an export template's hooks, in order, for a finished export.
+    /// Each runs whether or not the ones before it succeeded.
+    pub async fn run_export_hooks(
+        &self,
+        hooks: &[ExportHook],
+        export: &ExportHookInput,
+    ) -> Vec<ExportHookOutcome> {
+        let parameters = export.parameters();
+        let mut outcomes = Vec::with_capacity(hooks.len());
+        for hook in hooks {
+            let result = match *hook {
+                ExportHook::Script(script_id) => self
+                    .execute_script(script_id, parameters.clone())
+                    .await
+                    .map_err(|e| e.to_string()),
+                ExportHook::Workflow(workflow_id) => self
+                    .execute_workflow_with(workflow_id, parameters.clone())
+                    .await
+                    .map_err(|e| e.to_string()),
+            };
+            outcomes.push(match result {
+                Ok(result) => ExportHookOutcome {
+                    hook: *hook,
+                    success: result.success,
+                    output: result.output,
+                    error_message: result.error_message,
+                },
+                Err(e) => ExportHookOutcome {
+                    hook: *hook,
+                    success: false,
+                    output: String::new(),
+                    error_message: Some(e),
+                },
+            });
+        }
+        outcomes
+    }
+
+    /// Take the backup and prune old ones
+    async fn run_backup(&self, backup: &BackupAction) -> Result<ExecutionResult, WritingToolError> {
+        let start_time = Instant::now();
+        let service = self.backup_service.read().await.clone();
+        let outcome = match service {
+            Some(service) => {
+                let service = service.read().await;
+                match service
+                    .create_backup(
+                        backup.backup_type.clone(),
+                        backup.project_id.as_deref(),
+                        backup.description.as_deref(),
+                    )
+                    .await
+                {
+                    Ok(backup_id) => service
+                        .prune_backups(&backup.retention)
+                        .await
+                        .map(|pruned| (backup_id, pruned))
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            None => Err("No backup service is configured".to_string()),
+        };
+
+        Ok(match outcome {
+            Ok((backup_id, pruned)) => ExecutionResult {
+                success: true,
+                output: format!(
+                    "Created backup {}, pruned {} old backups",
+                    backup_id, pruned
+                ),
+                error_message: None,
+                execution_time: start_time.elapsed(),
+                return_code: Some(0),
+                stdout_file: None,
+                stderr_file: None,
+                logs: vec![],
+            },
+            Err(error) => ExecutionResult {
+                success: false,
+                output: String::new(),
+                error_message: Some(error),
+                execution_time: start_time.elapsed(),
+                return_code: Some(-1),
+                stdout_file: None,
+                stderr_file: None,
+                logs: vec![],
+            },
+        })
+    }
+
     /// Evaluate condition expression
     fn evaluate_condition(
         &self,
@@ -1407,22 +1968,21 @@ impl ScriptEngine {
     }
 
     /// Create macro
-    pub fn create_macro(&self, macro_def: Macro) -> Result<Uuid, WritingToolError> {
+    pub async fn create_macro(&self, macro_def: Macro) -> Result<Uuid, WritingToolError> {
         let macro_id = macro_def.id;
-
-        {
-            let mut macros = self.macros.write().unwrap();
-            macros.insert(macro_id, macro_def);
-        }
-
+        self.macros.write().await.insert(macro_id, macro_def);
         Ok(macro_id)
     }
 
     /// Execute macro
     pub async fn execute_macro(&self, macro_id: Uuid) -> Result<ExecutionResult, WritingToolError> {
-        let macros = self.macros.read().unwrap();
-        let macro_def = macros
+        // Run from a copy: script actions update the engine's other tables
+        let macro_def = self
+            .macros
+            .read()
+            .await
             .get(&macro_id)
+            .cloned()
             .ok_or(WritingToolError::MacroNotFound(macro_id))?;
 
         let start_time = Instant::now();
@@ -1474,18 +2034,21 @@ impl ScriptEngine {
     }
 
     /// Get execution history
-    pub fn get_execution_history(&self, limit: usize) -> Vec<ExecutionResult> {
-        let history = self.execution_history.read().unwrap();
+    pub async fn get_execution_history(&self, limit: usize) -> Vec<ExecutionResult> {
+        let history = self.execution_history.read().await;
         history.iter().rev().take(limit).cloned().collect()
     }
 
+    /// The event queue, for services that report events of their own
+    pub async fn event_system(&self) -> EventSystem {
+        self.event_system.read().await.clone()
+    }
+
     /// Trigger system event
     pub async fn trigger_event(&self, event: SystemEvent) -> Result<(), WritingToolError> {
-        let event_system = self.event_system.clone();
-        let system = event_system.write().unwrap();
-
         // Add to event queue
-        system.event_queue.lock().unwrap().push_back(event.clone());
+        let event_queue = self.event_system.read().await.event_queue.clone();
+        event_queue.lock().await.push_back(event.clone());
 
         // Event handlers functionality removed - EventSystem only has event_queue
         // Event processing would need to be implemented separately
@@ -1656,3 +2219,161 @@ impl Default for Script {
     }
 }
 
+/// Replace `{name}` in `text` with the value of `name` in `context`
+fn fill_placeholders(text: &str, context: &HashMap<String, serde_json::Value>) -> String {
+    context
+        .iter()
+        .fold(text.to_string(), |text, (name, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            text.replace(&format!("{{{}}}", name), &value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::backup_service::SecureBackupConfig;
+    use crate::database::{DatabaseConfig, EnhancedDatabaseService};
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_schedule_next_run() {
+        // 2024-03-06 is a Wednesday
+        let after = utc("2024-03-06T12:00:00Z");
+        let mut daily = WorkflowSchedule::daily("02:30");
+        daily.timezone = "UTC".to_string();
+        assert_eq!(daily.next_after(after), Some(utc("2024-03-07T02:30:00Z")));
+
+        let weekly = WorkflowSchedule {
+            timezone: "UTC".to_string(),
+            ..WorkflowSchedule::weekly(1, "02:30")
+        };
+        assert_eq!(weekly.next_after(after), Some(utc("2024-03-11T02:30:00Z")));
+
+        let ended = WorkflowSchedule {
+            end_date: Some(utc("2024-03-10T00:00:00Z")),
+            ..weekly.clone()
+        };
+        assert_eq!(ended.next_after(after), None);
+
+        let cron = WorkflowSchedule {
+            schedule_type: ScheduleType::Cron,
+            ..daily
+        };
+        assert_eq!(cron.next_after(after), None);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_backup_runs_when_due() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = EnhancedDatabaseService::new(&db_path, DatabaseConfig::default())
+            .await
+            .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let backups =
+            BackupService::new(db, &db_path).with_config(SecureBackupConfig { encrypt: false });
+        backups.initialize().await.unwrap();
+        let backups = Arc::new(RwLock::new(backups));
+
+        let engine = ScriptEngine::new();
+        engine.set_backup_service(backups.clone()).await;
+        let workflow = AutomationWorkflow::scheduled_backup(
+            "Nightly backup",
+            WorkflowSchedule::daily("03:00"),
+            BackupAction {
+                backup_type: BackupType::Automatic,
+                project_id: None,
+                description: None,
+                retention: RetentionPolicy {
+                    automatic_chains: 1,
+                    ..RetentionPolicy::default()
+                },
+            },
+        );
+        let workflow_id = engine.create_workflow(workflow).await.unwrap();
+        let next = engine.next_scheduled_run(workflow_id).await.unwrap();
+
+        let stats = backups
+            .read()
+            .await
+            .get_backup_statistics(None)
+            .await
+            .unwrap();
+        assert_eq!(stats.next_scheduled_run, Some(next.timestamp() as u64));
+        assert_eq!(stats.last_scheduled_run, None);
+
+        assert!(engine
+            .run_due_workflows(next - chrono::Duration::seconds(1))
+            .await
+            .is_empty());
+        let results = engine.run_due_workflows(next).await;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.as_ref().unwrap().success);
+        assert!(engine.run_due_workflows(next).await.is_empty());
+
+        // A day later the second backup replaces the first
+        let following = engine.next_scheduled_run(workflow_id).await.unwrap();
+        assert_eq!(engine.run_due_workflows(following).await.len(), 1);
+
+        let stats = backups
+            .read()
+            .await
+            .get_backup_statistics(None)
+            .await
+            .unwrap();
+        assert_eq!(stats.total_backups, 1);
+        assert!(stats.last_scheduled_run.is_some());
+        assert!(stats.next_scheduled_run.unwrap() > next.timestamp() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_export_hooks_get_output_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_path = dir.path().join("novel.epub");
+        std::fs::write(&output_path, b"epub").unwrap();
+        let dropbox = dir.path().join("Dropbox");
+        std::fs::create_dir(&dropbox).unwrap();
+
+        let engine = ScriptEngine::new();
+        let mut workflow = AutomationWorkflow::scheduled_backup(
+            "Copy to Dropbox",
+            WorkflowSchedule::daily("03:00"),
+            BackupAction {
+                backup_type: BackupType::Manual,
+                project_id: None,
+                description: None,
+                retention: RetentionPolicy::default(),
+            },
+        );
+        workflow.triggers.clear();
+        workflow.actions[0].action_type = ActionType::CopyFile {
+            from: PathBuf::from("{output_path}"),
+            to: dropbox.clone(),
+        };
+        let workflow_id = engine.create_workflow(workflow).await.unwrap();
+
+        // A missing script fails on its own; the workflow still runs
+        let hooks = [
+            ExportHook::Script(Uuid::new_v4()),
+            ExportHook::Workflow(workflow_id),
+        ];
+        let export = ExportHookInput {
+            job_id: "job-1".to_string(),
+            document_id: None,
+            export_type: "epub".to_string(),
+            output_path,
+        };
+        let outcomes = engine.run_export_hooks(&hooks, &export).await;
+        assert_eq!(outcomes.len(), 2);
+        assert!(!outcomes[0].success);
+        assert!(outcomes[1].success, "{:?}", outcomes[1]);
+        assert_eq!(std::fs::read(dropbox.join("novel.epub")).unwrap(), b"epub");
+    }
+}
@@ -0,0 +1,700 @@
+//! Command Registry
+//!
+//! The commands behind the command palette and the native context menus,
+//! each with the context it needs before it can run. Built-in commands
+//! point at IPC handlers and are only listed while the backend serves
+//! them; enabled automation workflows and scripts are added alongside.
+//! How often each command was run is kept in `command_usage.json` and
+//! ranks the palette.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use crate::app_paths::AppPaths;
+use crate::automation::{AutomationWorkflow, Script, ScriptEngine};
+use crate::ipc_bridge::{supported_commands, IPC_API_VERSION};
+
+/// What running a command does
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "target", rename_all = "snake_case")]
+pub enum CommandSource {
+    /// Send this IPC message
+    Ipc(String),
+    /// Run this automation workflow
+    Workflow(Uuid),
+    /// Run this user script
+    Script(Uuid),
+}
+
+/// Something that must hold for a command to be enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextRequirement {
+    /// A project is open
+    Project,
+    /// A document is open
+    Document,
+    /// Text is selected in the editor
+    Selection,
+    /// The app isn't in offline mode
+    Online,
+}
+
+/// Context menus a command appears in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MenuLocation {
+    Editor,
+    Binder,
+    Codex,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandEntry {
+    /// `ipc.<name>`, `workflow.<id>` or `script.<id>`
+    pub id: String,
+    pub title: String,
+    pub category: String,
+    pub source: CommandSource,
+    pub requires: Vec<ContextRequirement>,
+    pub menus: Vec<MenuLocation>,
+}
+
+impl CommandEntry {
+    pub fn is_enabled(&self, context: &CommandContext) -> bool {
+        self.requires.iter().all(|requirement| match requirement {
+            ContextRequirement::Project => context.project_id.is_some(),
+            ContextRequirement::Document => context.document_id.is_some(),
+            ContextRequirement::Selection => context.has_selection,
+            ContextRequirement::Online => !context.offline,
+        })
+    }
+}
+
+/// Where the user is when they open the palette or a menu
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandContext {
+    pub project_id: Option<String>,
+    pub document_id: Option<String>,
+    #[serde(default)]
+    pub has_selection: bool,
+    #[serde(default)]
+    pub offline: bool,
+}
+
+/// A command as listed in the palette or a menu
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandItem {
+    #[serde(flatten)]
+    pub command: CommandEntry,
+    pub enabled: bool,
+    /// Times the command was run
+    pub uses: u64,
+}
+
+/// Built-in commands: (IPC command, title, category, requires, menus)
+type BuiltIn = (
+    &'static str,
+    &'static str,
+    &'static str,
+    &'static [ContextRequirement],
+    &'static [MenuLocation],
+);
+
+const BUILT_IN_COMMANDS: &[BuiltIn] = {
+    use ContextRequirement::*;
+    use MenuLocation::*;
+    &[
+        (
+            "print_document",
+            "Print Document",
+            "File",
+            &[Document],
+            &[Editor, Binder],
+        ),
+        (
+            "project_file_create",
+            "Save Project File",
+            "File",
+            &[Project],
+            &[],
+        ),
+        (
+            "secure_copy",
+            "Secure Copy",
+            "Edit",
+            &[Selection],
+            &[Editor],
+        ),
+        (
+            "ai_request",
+            "Ask AI About Selection",
+            "AI",
+            &[Selection, Online],
+            &[Editor],
+        ),
+        (
+            "ask_project",
+            "Ask About This Project",
+            "AI",
+            &[Project, Online],
+            &[],
+        ),
+        ("ai_log_list", "Show AI Log", "AI", &[], &[]),
+        (
+            "document_versions",
+            "Show Version History",
+            "Document",
+            &[Document],
+            &[Editor, Binder],
+        ),
+        (
+            "document_share",
+            "Share Read-Only Copy",
+            "Document",
+            &[Document],
+            &[Editor, Binder],
+        ),
+        (
+            "document_trash",
+            "Move to Trash",
+            "Document",
+            &[Document],
+            &[Editor, Binder],
+        ),
+        ("trash_list", "Show Trash", "File", &[], &[]),
+        (
+            "markdown_sync_run",
+            "Sync Markdown Folder",
+            "File",
+            &[Project],
+            &[],
+        ),
+        (
+            "git_history_log",
+            "Show Project History",
+            "File",
+            &[Project],
+            &[],
+        ),
+        (
+            "git_history_commit",
+            "Save Milestone to History",
+            "File",
+            &[Project],
+            &[],
+        ),
+        (
+            "obsidian_import",
+            "Import Obsidian Vault",
+            "File",
+            &[Project],
+            &[],
+        ),
+        ("notion_import", "Import Notion Export", "File", &[], &[]),
+        ("journal_today", "Open Today's Journal", "File", &[], &[]),
+        (
+            "document_templates",
+            "New Document from Template",
+            "File",
+            &[Project],
+            &[Binder],
+        ),
+        (
+            "related_notes",
+            "Show Related Notes",
+            "Document",
+            &[Document],
+            &[Editor],
+        ),
+        (
+            "binder_move",
+            "Move in Binder",
+            "Binder",
+            &[Document],
+            &[Binder],
+        ),
+        (
+            "documents_tag",
+            "Tag Documents",
+            "Binder",
+            &[Project],
+            &[Binder],
+        ),
+        (
+            "codex_autofill_propose",
+            "Suggest Codex Entries",
+            "Codex",
+            &[Document, Online],
+            &[Editor],
+        ),
+        (
+            "codex_backlinks",
+            "Show Backlinks",
+            "Codex",
+            &[Project],
+            &[Codex],
+        ),
+        (
+            "codex_entry_delete",
+            "Delete Codex Entry",
+            "Codex",
+            &[Project],
+            &[Codex],
+        ),
+        (
+            "story_bible_export",
+            "Export Story Bible",
+            "Codex",
+            &[Project],
+            &[],
+        ),
+        (
+            "codex_export",
+            "Export Codex",
+            "Codex",
+            &[Project],
+            &[Codex],
+        ),
+        (
+            "codex_import",
+            "Import Codex Entries",
+            "Codex",
+            &[Project],
+            &[Codex],
+        ),
+        (
+            "timeline_get",
+            "Show Timeline",
+            "Codex",
+            &[Project],
+            &[Codex],
+        ),
+        ("lint_project", "Check Style", "Analysis", &[Project], &[]),
+        (
+            "style_sheet_check",
+            "Check Style Sheet",
+            "Analysis",
+            &[Project],
+            &[],
+        ),
+        (
+            "content_scan",
+            "Scan for Content Warnings",
+            "Analysis",
+            &[Project],
+            &[],
+        ),
+        (
+            "word_usage_analyze",
+            "Analyze Word Usage",
+            "Analysis",
+            &[Project],
+            &[],
+        ),
+        (
+            "narrative_voice_check",
+            "Check Narrative Voice",
+            "Analysis",
+            &[Document],
+            &[Editor],
+        ),
+        (
+            "readability_analyze",
+            "Analyze Readability",
+            "Analysis",
+            &[Document],
+            &[Editor],
+        ),
+        (
+            "chronology_check",
+            "Check Chronology",
+            "Analysis",
+            &[Project],
+            &[],
+        ),
+        (
+            "find_duplicate_paragraphs",
+            "Find Duplicate Paragraphs",
+            "Analysis",
+            &[Project],
+            &[],
+        ),
+        (
+            "stats_session_start",
+            "Start Writing Session",
+            "Statistics",
+            &[Project],
+            &[],
+        ),
+        (
+            "stats_session_end",
+            "End Writing Session",
+            "Statistics",
+            &[Project],
+            &[],
+        ),
+        (
+            "stats_export",
+            "Export Writing Statistics",
+            "Statistics",
+            &[Project],
+            &[],
+        ),
+        (
+            "writing_stats_dashboard",
+            "Show Writing Dashboard",
+            "Statistics",
+            &[Project],
+            &[],
+        ),
+        (
+            "word_count_certify",
+            "Certify Word Count",
+            "Statistics",
+            &[Project],
+            &[],
+        ),
+        ("activity_feed", "Show Activity", "Project", &[Project], &[]),
+        (
+            "project_anonymize",
+            "Anonymize Project",
+            "Project",
+            &[Project],
+            &[],
+        ),
+        ("backup_list", "Show Backups", "Backups", &[], &[]),
+        (
+            "backup_verify_all",
+            "Verify All Backups",
+            "Backups",
+            &[],
+            &[],
+        ),
+        ("focus_enable", "Enter Focus Mode", "View", &[], &[Editor]),
+        (
+            "network_set_offline",
+            "Toggle Offline Mode",
+            "Network",
+            &[],
+            &[],
+        ),
+        ("crash_reports", "Show Crash Reports", "Help", &[], &[]),
+    ]
+};
+
+/// Palette and context menu commands, with usage counts
+#[derive(Debug)]
+pub struct CommandRegistry {
+    path: PathBuf,
+    commands: Mutex<Vec<CommandEntry>>,
+    usage: Mutex<HashMap<String, u64>>,
+}
+
+impl CommandRegistry {
+    /// Registry of the built-in commands, with the usage counts stored at
+    /// `path`; a missing or unreadable file starts the counts at zero
+    pub fn load(path: &Path) -> Self {
+        let usage = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            path: path.to_path_buf(),
+            commands: Mutex::new(built_in_commands(IPC_API_VERSION)),
+            usage: Mutex::new(usage),
+        }
+    }
+
+    /// `command_usage.json` in the data directory
+    pub fn default_path() -> PathBuf {
+        AppPaths::global().command_usage_path()
+    }
+
+    /// Add a command, replacing any with the same id
+    pub fn register(&self, command: CommandEntry) {
+        if let Ok(mut commands) = self.commands.lock() {
+            match commands.iter_mut().find(|c| c.id == command.id) {
+                Some(existing) => *existing = command,
+                None => commands.push(command),
+            }
+        }
+    }
+
+    pub fn unregister(&self, id: &str) {
+        if let Ok(mut commands) = self.commands.lock() {
+            commands.retain(|c| c.id != id);
+        }
+    }
+
+    /// List a workflow in the palette while it's enabled
+    pub fn register_workflow(&self, workflow: &AutomationWorkflow) {
+        let id = format!("workflow.{}", workflow.id);
+        if !workflow.enabled {
+            return self.unregister(&id);
+        }
+        self.register(CommandEntry {
+            id,
+            title: workflow.name.clone(),
+            category: "Workflows".to_string(),
+            source: CommandSource::Workflow(workflow.id),
+            requires: vec![],
+            menus: vec![],
+        });
+    }
+
+    /// List a script in the palette while it's enabled; scripts allowed
+    /// off-machine network access need the app online
+    pub fn register_script(&self, script: &Script) {
+        let id = format!("script.{}", script.id);
+        if !script.is_enabled {
+            return self.unregister(&id);
+        }
+        let network = &script.permissions.network_access;
+        let online = (network.http_allowed || network.https_allowed || network.ftp_allowed)
+            && !network.local_only;
+        self.register(CommandEntry {
+            id,
+            title: script.name.clone(),
+            category: "Scripts".to_string(),
+            source: CommandSource::Script(script.id),
+            requires: if online {
+                vec![ContextRequirement::Online]
+            } else {
+                vec![]
+            },
+            menus: vec![],
+        });
+    }
+
+    /// Replace the listed workflows and scripts with those `engine` has now
+    pub async fn sync_automation(&self, engine: &ScriptEngine) {
+        let (workflows, scripts) = (engine.workflows().await, engine.scripts().await);
+        if let Ok(mut commands) = self.commands.lock() {
+            commands.retain(|c| matches!(c.source, CommandSource::Ipc(_)));
+        }
+        for workflow in &workflows {
+            self.register_workflow(workflow);
+        }
+        for script in &scripts {
+            self.register_script(script);
+        }
+    }
+
+    pub fn commands(&self) -> Vec<CommandEntry> {
+        self.commands
+            .lock()
+            .map(|commands| commands.clone())
+            .unwrap_or_default()
+    }
+
+    /// Every command, enabled ones first, then most used
+    pub fn palette(&self, context: &CommandContext) -> Vec<CommandItem> {
+        let mut items = self.items(context);
+        items.sort_by(|a, b| {
+            b.enabled
+                .cmp(&a.enabled)
+                .then(b.uses.cmp(&a.uses))
+                .then_with(|| a.command.title.cmp(&b.command.title))
+        });
+        items
+    }
+
+    /// Commands whose title or category matches `query`: enabled ones
+    /// first, then closer matches, then most used
+    pub fn search(&self, query: &str, context: &CommandContext, limit: usize) -> Vec<CommandItem> {
+        let query = query.trim().to_lowercase();
+        let mut matches: Vec<(u8, CommandItem)> = self
+            .items(context)
+            .into_iter()
+            .filter_map(|item| Some((match_rank(&query, &item.command)?, item)))
+            .collect();
+        matches.sort_by(|(rank_a, a), (rank_b, b)| {
+            b.enabled
+                .cmp(&a.enabled)
+                .then(rank_a.cmp(rank_b))
+                .then(b.uses.cmp(&a.uses))
+                .then_with(|| a.command.title.cmp(&b.command.title))
+        });
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, item)| item)
+            .collect()
+    }
+
+    /// Commands in the context menu at `location`, grouped by category in
+    /// the order they were registered; disabled ones are kept so the menu
+    /// can grey them out
+    pub fn menu(&self, location: MenuLocation, context: &CommandContext) -> Vec<CommandItem> {
+        let mut items: Vec<CommandItem> = self
+            .items(context)
+            .into_iter()
+            .filter(|item| item.command.menus.contains(&location))
+            .collect();
+        let mut categories: Vec<String> = Vec::new();
+        for item in &items {
+            if !categories.contains(&item.command.category) {
+                categories.push(item.command.category.clone());
+            }
+        }
+        items.sort_by_key(|item| categories.iter().position(|c| *c == item.command.category));
+        items
+    }
+
+    /// Count a run of command `id`
+    pub fn record_use(&self, id: &str) -> Result<(), String> {
+        if !self.commands().iter().any(|c| c.id == id) {
+            return Err(format!("Unknown command: {}", id));
+        }
+        let snapshot = {
+            let mut usage = self
+                .usage
+                .lock()
+                .map_err(|_| "Command usage lock poisoned".to_string())?;
+            *usage.entry(id.to_string()).or_default() += 1;
+            usage.clone()
+        };
+
+        let json = serde_json::to_string_pretty(&snapshot)
+            .map_err(|e| format!("Failed to serialize command usage: {}", e))?;
+        std::fs::write(&self.path, json)
+            .map_err(|e| format!("Failed to write command usage file: {}", e))
+    }
+
+    fn items(&self, context: &CommandContext) -> Vec<CommandItem> {
+        let usage = self
+            .usage
+            .lock()
+            .map(|usage| usage.clone())
+            .unwrap_or_default();
+        self.commands()
+            .into_iter()
+            .map(|command| CommandItem {
+                enabled: command.is_enabled(context),
+                uses: usage.get(&command.id).copied().unwrap_or(0),
+                command,
+            })
+            .collect()
+    }
+}
+
+/// Built-in commands whose IPC handler a frontend speaking `version` can call
+fn built_in_commands(version: u32) -> Vec<CommandEntry> {
+    let served: Vec<String> = supported_commands(version)
+        .into_iter()
+        .map(|spec| spec.name)
+        .collect();
+    BUILT_IN_COMMANDS
+        .iter()
+        .filter(|(name, ..)| served.iter().any(|s| s == name))
+        .map(|(name, title, category, requires, menus)| CommandEntry {
+            id: format!("ipc.{}", name),
+            title: title.to_string(),
+            category: category.to_string(),
+            source: CommandSource::Ipc(name.to_string()),
+            requires: requires.to_vec(),
+            menus: menus.to_vec(),
+        })
+        .collect()
+}
+
+/// How well `query` (lowercase) matches a command, lower being better:
+/// title prefix, then a word in the title, then anywhere in the title or
+/// category, then the query's letters in order in the title
+fn match_rank(query: &str, command: &CommandEntry) -> Option<u8> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let title = command.title.to_lowercase();
+    if title.starts_with(query) {
+        Some(0)
+    } else if title.split_whitespace().any(|word| word.starts_with(query)) {
+        Some(1)
+    } else if title.contains(query) || command.category.to_lowercase().contains(query) {
+        Some(2)
+    } else {
+        let mut letters = title.chars();
+        query
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .all(|c| letters.any(|t| t == c))
+            .then_some(3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automation::{BackupAction, WorkflowSchedule};
+    use crate::database::backup_service::{BackupType, RetentionPolicy};
+
+    #[test]
+    fn test_built_ins_are_served_ipc_commands() {
+        let served: Vec<String> = supported_commands(IPC_API_VERSION)
+            .into_iter()
+            .map(|spec| spec.name)
+            .collect();
+        for (name, ..) in BUILT_IN_COMMANDS {
+            assert!(served.iter().any(|s| s == name), "{} isn't served", name);
+        }
+        assert_eq!(
+            built_in_commands(IPC_API_VERSION).len(),
+            BUILT_IN_COMMANDS.len()
+        );
+        // Commands added after v1 don't show up for a v1 frontend
+        assert!(built_in_commands(1).len() < BUILT_IN_COMMANDS.len());
+    }
+
+    #[tokio::test]
+    async fn test_palette_ranks_by_match_and_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("command_usage.json");
+        let registry = CommandRegistry::load(&path);
+
+        let engine = ScriptEngine::new();
+        let workflow = AutomationWorkflow::scheduled_backup(
+            "Nightly Backup",
+            WorkflowSchedule::daily("03:00"),
+            BackupAction {
+                backup_type: BackupType::Automatic,
+                project_id: None,
+                description: None,
+                retention: RetentionPolicy::default(),
+            },
+        );
+        let workflow_id = engine.create_workflow(workflow).await.unwrap();
+        registry.sync_automation(&engine).await;
+        let id = format!("workflow.{}", workflow_id);
+        assert!(registry.commands().iter().any(|c| c.id == id));
+
+        let context = CommandContext {
+            project_id: Some("p1".to_string()),
+            ..CommandContext::default()
+        };
+        let results = registry.search("backup", &context, 10);
+        assert_eq!(results[0].command.title, "Nightly Backup");
+        registry.record_use("ipc.backup_verify_all").unwrap();
+        let results = registry.search("backup", &context, 10);
+        assert_eq!(results[0].command.id, "ipc.backup_verify_all");
+        assert!(registry.record_use("ipc.nope").is_err());
+
+        // Counts survive a restart
+        let registry = CommandRegistry::load(&path);
+        assert_eq!(registry.palette(&context)[0].uses, 1);
+
+        // Menus keep commands that need a document, greyed out
+        let menu = registry.menu(MenuLocation::Editor, &context);
+        let print = menu
+            .iter()
+            .find(|item| item.command.id == "ipc.print_document")
+            .unwrap();
+        assert!(!print.enabled);
+        assert!(menu
+            .iter()
+            .all(|item| item.command.menus.contains(&MenuLocation::Editor)));
+    }
+}
@@ -1,10 +1,19 @@
 //! Compliance Module
-//! 
+//!
 //! Handles regulatory compliance, data protection, and audit logging.
 
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::publishing::{PdfBuilder, PdfColor, PdfFont, PdfTextStyle};
+
+/// Most recent audit entries listed individually in a compliance report
+const REPORT_AUDIT_ENTRY_LIMIT: usize = 200;
+
 /// Compliance audit entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditEntry {
@@ -17,8 +26,18 @@ pub struct AuditEntry {
     pub user_agent: Option<String>,
 }
 
+/// Sensitivity classification for documents and copied data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub enum DataClassification {
+    Public,
+    #[default]
+    Internal,
+    Confidential,
+    Restricted,
+}
+
 /// Data protection compliance
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataProtectionCompliance {
     pub gdpr_compliant: bool,
     pub ccpa_compliant: bool,
@@ -111,6 +130,22 @@ impl ComplianceService {
 
         self.audit_log.retain(|entry| entry.timestamp > cutoff);
     }
+
+    /// Build an audit report from a compliance check and the current audit log
+    pub fn generate_report(&self, check: &ComplianceCheck) -> ComplianceReport {
+        ComplianceReport {
+            id: uuid::Uuid::new_v4().to_string(),
+            generated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            passed: check.passed,
+            violations: check.issues.clone(),
+            recommendations: check.recommendations.clone(),
+            data_protection: self.data_protection.clone(),
+            audit_entries: self.audit_log.clone(),
+        }
+    }
 }
 
 /// Compliance validation result
@@ -141,6 +176,208 @@ impl ComplianceCheck {
     }
 }
 
+/// Snapshot of compliance state for auditors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceReport {
+    pub id: String,
+    pub generated_at: u64,
+    pub passed: bool,
+    pub violations: Vec<String>,
+    pub recommendations: Vec<String>,
+    pub data_protection: DataProtectionCompliance,
+    pub audit_entries: Vec<AuditEntry>,
+}
+
+/// A rendered report with its detached HMAC-SHA256 signature
+#[derive(Debug, Clone)]
+pub struct SignedComplianceReport {
+    pub report: ComplianceReport,
+    pub pdf: Vec<u8>,
+    /// Hex-encoded HMAC-SHA256 of `pdf`
+    pub signature: String,
+}
+
+impl ComplianceReport {
+    /// Render the report as a PDF
+    pub fn render_pdf(&self) -> Vec<u8> {
+        let generated = format_timestamp(self.generated_at);
+        let mut pdf = PdfBuilder::new()
+            .title("Compliance Report")
+            .author("Herding Cats")
+            .footer(format!(
+                "Compliance report {} - generated {}",
+                self.id, generated
+            ));
+
+        let label = PdfTextStyle {
+            font: PdfFont::Bold,
+            ..PdfTextStyle::default()
+        };
+        let (status, color) = if self.passed {
+            ("PASSED", PdfColor::GREEN)
+        } else {
+            ("FAILED", PdfColor::RED)
+        };
+
+        pdf.heading(1, "Compliance Report")
+            .paragraph(&format!("Report ID: {}", self.id))
+            .paragraph(&format!("Generated: {}", generated))
+            .styled_paragraph(
+                &format!("Overall status: {}", status),
+                PdfTextStyle {
+                    font: PdfFont::Bold,
+                    size: 13.0,
+                    color,
+                    ..PdfTextStyle::default()
+                },
+            );
+
+        pdf.heading(2, "Data Protection")
+            .paragraph(&format!(
+                "GDPR: {}",
+                yes_no(self.data_protection.gdpr_compliant)
+            ))
+            .paragraph(&format!(
+                "CCPA: {}",
+                yes_no(self.data_protection.ccpa_compliant)
+            ))
+            .paragraph(&format!(
+                "HIPAA: {}",
+                yes_no(self.data_protection.hipaa_compliant)
+            ))
+            .paragraph(&format!(
+                "Audit retention: {} days",
+                self.data_protection.data_retention_days
+            ));
+
+        pdf.heading(2, "Violations");
+        if self.violations.is_empty() {
+            pdf.paragraph("No violations found.");
+        }
+        for (i, violation) in self.violations.iter().enumerate() {
+            pdf.styled_paragraph(
+                &format!("{}. {}", i + 1, violation),
+                PdfTextStyle {
+                    color: PdfColor::RED,
+                    ..PdfTextStyle::default()
+                },
+            );
+        }
+
+        pdf.heading(2, "Remediation");
+        if self.recommendations.is_empty() {
+            pdf.paragraph("No remediation required.");
+        }
+        for (i, recommendation) in self.recommendations.iter().enumerate() {
+            pdf.paragraph(&format!("{}. {}", i + 1, recommendation));
+        }
+
+        pdf.heading(2, "Audit Log Summary");
+        let mut by_action: BTreeMap<&str, usize> = BTreeMap::new();
+        for entry in &self.audit_entries {
+            *by_action.entry(entry.action.as_str()).or_default() += 1;
+        }
+        pdf.paragraph(&format!("{} entries recorded.", self.audit_entries.len()));
+        for (action, count) in by_action {
+            pdf.styled_paragraph(&format!("{}: {}", action, count), label);
+        }
+
+        if !self.audit_entries.is_empty() {
+            pdf.heading(2, "Recent Audit Entries");
+            let mono = PdfTextStyle {
+                font: PdfFont::Mono,
+                size: 8.0,
+                ..PdfTextStyle::default()
+            };
+            let skip = self
+                .audit_entries
+                .len()
+                .saturating_sub(REPORT_AUDIT_ENTRY_LIMIT);
+            for entry in self.audit_entries.iter().skip(skip) {
+                pdf.styled_paragraph(
+                    &format!(
+                        "{}  {}  {}  {}",
+                        format_timestamp(entry.timestamp),
+                        entry.action,
+                        entry.resource,
+                        entry.result
+                    ),
+                    mono,
+                );
+            }
+        }
+
+        pdf.build()
+    }
+
+    /// Render and sign the report with the given key
+    pub fn sign(self, key: &[u8]) -> SignedComplianceReport {
+        let pdf = self.render_pdf();
+        let signature = sign_report_bytes(&pdf, key);
+        SignedComplianceReport {
+            report: self,
+            pdf,
+            signature,
+        }
+    }
+}
+
+impl SignedComplianceReport {
+    /// Write `<id>.pdf` and its detached `<id>.pdf.sig` into `dir`
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<(PathBuf, PathBuf)> {
+        std::fs::create_dir_all(dir)?;
+        let pdf_path = dir.join(format!("compliance-report-{}.pdf", self.report.id));
+        let sig_path = pdf_path.with_extension("pdf.sig");
+        std::fs::write(&pdf_path, &self.pdf)?;
+        std::fs::write(&sig_path, format!("hmac-sha256:{}\n", self.signature))?;
+        Ok((pdf_path, sig_path))
+    }
+}
+
+/// Hex-encoded HMAC-SHA256 of report bytes
+pub fn sign_report_bytes(bytes: &[u8], key: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(bytes);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Check a report against its signature (constant time)
+pub fn verify_report_signature(bytes: &[u8], signature: &str, key: &[u8]) -> bool {
+    let signature = signature.trim().trim_start_matches("hmac-sha256:");
+    let Some(expected) = (0..signature.len())
+        .step_by(2)
+        .map(|i| {
+            signature
+                .get(i..i + 2)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+        })
+        .collect::<Option<Vec<u8>>>()
+    else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(bytes);
+    mac.verify_slice(&expected).is_ok()
+}
+
+fn format_timestamp(secs: u64) -> String {
+    chrono::DateTime::from_timestamp(secs as i64, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| secs.to_string())
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "Yes"
+    } else {
+        "No"
+    }
+}
+
 /// Validate compliance requirements
 pub fn validate_compliance() -> ComplianceCheck {
     let mut issues = Vec::new();
@@ -180,17 +417,48 @@ mod tests {
     fn test_audit_logging() {
         let mut service = ComplianceService::new();
         service.log_audit("read", "document", "success");
-        
+
         assert_eq!(service.audit_log.len(), 1);
         assert_eq!(service.audit_log[0].action, "read");
         assert_eq!(service.audit_log[0].resource, "document");
         assert_eq!(service.audit_log[0].result, "success");
     }
 
+    #[test]
+    fn test_signed_report_verifies() {
+        let mut service = ComplianceService::new();
+        service.log_audit("credential_add", "credential:openai", "success");
+        let check = ComplianceCheck::failed(
+            vec!["Encryption key not configured".to_string()],
+            vec!["Set ENCRYPTION_KEY environment variable".to_string()],
+        );
+
+        let signed = service.generate_report(&check).sign(b"report-key");
+        assert!(signed.pdf.starts_with(b"%PDF-"));
+        assert!(verify_report_signature(
+            &signed.pdf,
+            &signed.signature,
+            b"report-key"
+        ));
+        assert!(!verify_report_signature(
+            &signed.pdf,
+            &signed.signature,
+            b"other-key"
+        ));
+
+        let mut tampered = signed.pdf.clone();
+        tampered.push(b' ');
+        assert!(!verify_report_signature(
+            &tampered,
+            &signed.signature,
+            b"report-key"
+        ));
+    }
+
     #[test]
     fn test_compliance_validation() {
         let check = validate_compliance();
         // Should pass basic validation in test environment
         assert!(check.passed || !check.issues.is_empty());
     }
-}
\ No newline at end of file
+}
@@ -11,12 +11,34 @@ pub fn generate_encryption_key() -> String {
     security_service.secure_random_string(32)
 }
 
+/// Hosts outbound requests may reach by default: AI providers, sync
+/// providers, web search and the font CDNs. `*.` entries match subdomains.
+pub const DEFAULT_NETWORK_ALLOW_LIST: &[&str] = &[
+    "api.openai.com",
+    "api.anthropic.com",
+    "api.dropboxapi.com",
+    "content.dropboxapi.com",
+    "www.googleapis.com",
+    "api.search.brave.com",
+    "serpapi.com",
+    "github.com",
+    "*.githubusercontent.com",
+    "fonts.googleapis.com",
+    "fonts.gstatic.com",
+    // Only contacted when the user submits a crash report
+    "crash.herdingcats.dev",
+];
+
 /// Security configuration structure
 #[derive(Debug, Clone)]
 pub struct SecurityConfig {
     pub encryption_key: String,
     pub api_key: Option<String>,
     pub enable_encryption: bool,
+    /// Hosts the `NetworkClient` may contact
+    pub network_allow_list: Vec<String>,
+    /// Start with all outbound traffic blocked
+    pub offline_mode: bool,
 }
 
 impl Default for SecurityConfig {
@@ -25,6 +47,11 @@ impl Default for SecurityConfig {
             encryption_key: generate_encryption_key(),
             api_key: None,
             enable_encryption: true,
+            network_allow_list: DEFAULT_NETWORK_ALLOW_LIST
+                .iter()
+                .map(|host| host.to_string())
+                .collect(),
+            offline_mode: false,
         }
     }
 }
@@ -46,6 +73,18 @@ impl SecurityConfig {
         self.enable_encryption = enabled;
         self
     }
+
+    /// Allow outbound requests to an additional host
+    pub fn with_allowed_host(mut self, host: &str) -> Self {
+        self.network_allow_list.push(host.to_lowercase());
+        self
+    }
+
+    /// Start in offline mode
+    pub fn with_offline_mode(mut self, offline: bool) -> Self {
+        self.offline_mode = offline;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,40 @@
+//! Request Correlation
+//!
+//! Every IPC request gets a correlation ID that lives in a tokio task-local
+//! for the duration of its handling. Services read it with `current()` and
+//! attach it to their tracing spans and log lines, so one user action can
+//! be followed from the IPC bridge through services down to the database.
+
+use std::future::Future;
+
+tokio::task_local! {
+    static CORRELATION_ID: String;
+}
+
+/// Generate a new correlation ID
+pub fn new_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Correlation ID of the request being handled on this task, if any
+pub fn current() -> Option<String> {
+    CORRELATION_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Run `future` with `id` as the current correlation ID
+pub async fn scope<F: Future>(id: String, future: F) -> F::Output {
+    CORRELATION_ID.scope(id, future).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scope_sets_and_clears_current_id() {
+        assert_eq!(current(), None);
+        let seen = scope("abc".to_string(), async { current() }).await;
+        assert_eq!(seen.as_deref(), Some("abc"));
+        assert_eq!(current(), None);
+    }
+}
@@ -0,0 +1,425 @@
+//! Crash Reporting
+//!
+//! Panics are written to a local crash report: the panic message and
+//! location, a backtrace, the last lines of the log and a small summary of
+//! app state (open windows, active project and document ids). Reports never
+//! contain document content. They stay on disk until the user has reviewed
+//! them and either submits or dismisses them; nothing is sent without that
+//! opt-in.
+//!
+//! A marker file tracks the running session. Sessions that end in a panic,
+//! or that never reach a clean shutdown (native crashes, being killed), are
+//! counted as crashed in the crash-free session metrics.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use thiserror::Error;
+
+use crate::app_paths::AppPaths;
+use crate::error::{ErrorCode, UserFacingError};
+use crate::security::network::NetworkClient;
+
+/// Endpoint receiving reports the user chose to submit
+pub const CRASH_REPORT_URL: &str = "https://crash.herdingcats.dev/v1/reports";
+/// Log lines kept for the report
+const LOG_TAIL_LINES: usize = 200;
+const SESSION_MARKER: &str = "session.json";
+const METRICS_FILE: &str = "metrics.json";
+
+static LOG_TAIL: Lazy<Mutex<VecDeque<String>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(LOG_TAIL_LINES)));
+static APP_STATE: Lazy<Mutex<BTreeMap<String, String>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+static STARTED_AT: OnceCell<Instant> = OnceCell::new();
+
+#[derive(Debug, Error)]
+pub enum CrashReportError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Crash report not found: {0}")]
+    NotFound(String),
+    #[error("Failed to submit crash report: {0}")]
+    Submission(String),
+}
+
+impl UserFacingError for CrashReportError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            CrashReportError::NotFound(_) => ErrorCode::NotFound,
+            CrashReportError::Io(_) => ErrorCode::Storage,
+            CrashReportError::Submission(_) => ErrorCode::Unavailable,
+            CrashReportError::Serialization(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+/// Initialize `env_logger`, keeping a copy of recent lines for crash reports
+pub fn init_logging() {
+    let inner = env_logger::Builder::from_default_env().build();
+    let max_level = inner.filter();
+    if log::set_boxed_logger(Box::new(TailLogger { inner })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+struct TailLogger {
+    inner: env_logger::Logger,
+}
+
+impl log::Log for TailLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.inner.matches(record) {
+            return;
+        }
+        push_log_line(format!(
+            "{} {} {}: {}",
+            Utc::now().format("%H:%M:%S%.3f"),
+            record.level(),
+            record.target(),
+            record.args()
+        ));
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+fn push_log_line(line: String) {
+    if let Ok(mut tail) = LOG_TAIL.lock() {
+        if tail.len() == LOG_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+}
+
+/// The most recent log lines, oldest first
+pub fn log_tail() -> Vec<String> {
+    LOG_TAIL
+        .lock()
+        .map(|tail| tail.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Set an entry of the app state summary. Values must be ids, counts and
+/// similar metadata, never document text.
+pub fn record_state(key: &str, value: impl ToString) {
+    if let Ok(mut state) = APP_STATE.lock() {
+        state.insert(key.to_string(), value.to_string());
+    }
+}
+
+pub fn clear_state(key: &str) {
+    if let Ok(mut state) = APP_STATE.lock() {
+        state.remove(key);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub thread: Option<String>,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub log_tail: Vec<String>,
+    pub state: BTreeMap<String, String>,
+    pub uptime_secs: u64,
+}
+
+impl CrashReport {
+    pub fn capture(info: &std::panic::PanicHookInfo<'_>) -> Self {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_string());
+        let mut report = Self::new(message);
+        report.location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        report.backtrace = std::backtrace::Backtrace::force_capture().to_string();
+        report
+    }
+
+    /// A report for `message` with the current log tail and state summary
+    pub fn new(message: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            thread: std::thread::current().name().map(str::to_string),
+            message,
+            location: None,
+            backtrace: String::new(),
+            log_tail: log_tail(),
+            state: APP_STATE
+                .lock()
+                .map(|state| state.clone())
+                .unwrap_or_default(),
+            uptime_secs: STARTED_AT.get().map_or(0, |t| t.elapsed().as_secs()),
+        }
+    }
+}
+
+/// Session counts behind the crash-free rate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionMetrics {
+    pub total_sessions: u64,
+    pub crashed_sessions: u64,
+}
+
+impl SessionMetrics {
+    /// Share of finished sessions that did not crash, 1.0 when there are none
+    pub fn crash_free_rate(&self) -> f64 {
+        if self.total_sessions == 0 {
+            return 1.0;
+        }
+        1.0 - self.crashed_sessions as f64 / self.total_sessions as f64
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SessionMarker {
+    started_at: Option<DateTime<Utc>>,
+    crashed: bool,
+}
+
+/// Writes crash reports and keeps session metrics in a local directory
+#[derive(Debug)]
+pub struct CrashReporter {
+    dir: PathBuf,
+}
+
+impl CrashReporter {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// `crashes` in the local state directory
+    pub fn default_dir() -> PathBuf {
+        AppPaths::global().local_dir.join("crashes")
+    }
+
+    /// Start a session and install the panic hook. The previous hook still
+    /// runs afterwards, so panics are printed as before.
+    pub fn install(self: &Arc<Self>) -> Result<(), CrashReportError> {
+        let _ = STARTED_AT.set(Instant::now());
+        self.begin_session()?;
+
+        let reporter = self.clone();
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let report = CrashReport::capture(info);
+            match reporter.write_report(&report) {
+                Ok(path) => eprintln!("Crash report written to {}", path.display()),
+                Err(e) => eprintln!("Failed to write crash report: {}", e),
+            }
+            previous(info);
+        }));
+        Ok(())
+    }
+
+    /// Count a new session. A marker left behind by the previous one means
+    /// it never shut down cleanly.
+    pub fn begin_session(&self) -> Result<(), CrashReportError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let marker_path = self.dir.join(SESSION_MARKER);
+        let mut metrics = self.metrics();
+        if marker_path.exists() {
+            metrics.crashed_sessions += 1;
+            metrics.total_sessions += 1;
+            self.write_metrics(&metrics)?;
+        }
+        let marker = SessionMarker {
+            started_at: Some(Utc::now()),
+            crashed: false,
+        };
+        std::fs::write(marker_path, serde_json::to_string(&marker)?)?;
+        Ok(())
+    }
+
+    /// Record a clean shutdown of the current session
+    pub fn end_session(&self) -> Result<(), CrashReportError> {
+        let marker_path = self.dir.join(SESSION_MARKER);
+        let marker: SessionMarker = match std::fs::read_to_string(&marker_path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+            Err(_) => return Ok(()),
+        };
+        let mut metrics = self.metrics();
+        metrics.total_sessions += 1;
+        if marker.crashed {
+            metrics.crashed_sessions += 1;
+        }
+        self.write_metrics(&metrics)?;
+        std::fs::remove_file(marker_path)?;
+        Ok(())
+    }
+
+    pub fn metrics(&self) -> SessionMetrics {
+        std::fs::read_to_string(self.dir.join(METRICS_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn write_metrics(&self, metrics: &SessionMetrics) -> Result<(), CrashReportError> {
+        std::fs::write(self.dir.join(METRICS_FILE), serde_json::to_string(metrics)?)?;
+        Ok(())
+    }
+
+    /// Save a report for review and mark the session as crashed
+    pub fn write_report(&self, report: &CrashReport) -> Result<PathBuf, CrashReportError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.report_path(&report.id);
+        std::fs::write(&path, serde_json::to_string_pretty(report)?)?;
+
+        let marker_path = self.dir.join(SESSION_MARKER);
+        if marker_path.exists() {
+            let marker = SessionMarker {
+                started_at: None,
+                crashed: true,
+            };
+            std::fs::write(marker_path, serde_json::to_string(&marker)?)?;
+        }
+        Ok(path)
+    }
+
+    /// Reports waiting for the user to submit or dismiss, newest first
+    pub fn pending_reports(&self) -> Vec<CrashReport> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut reports: Vec<CrashReport> = entries
+            .flatten()
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with("crash-") && name.ends_with(".json")
+            })
+            .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+            .filter_map(|content| serde_json::from_str(&content).ok())
+            .collect();
+        reports.sort_by_key(|report| std::cmp::Reverse(report.created_at));
+        reports
+    }
+
+    pub fn get(&self, id: &str) -> Result<CrashReport, CrashReportError> {
+        let content = std::fs::read_to_string(self.report_path(id))
+            .map_err(|_| CrashReportError::NotFound(id.to_string()))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Delete a report without sending it
+    pub fn dismiss(&self, id: &str) -> Result<(), CrashReportError> {
+        std::fs::remove_file(self.report_path(id))
+            .map_err(|_| CrashReportError::NotFound(id.to_string()))
+    }
+
+    /// Send a report the user reviewed, with the current session metrics,
+    /// then delete the local copy
+    pub async fn submit(&self, id: &str) -> Result<(), CrashReportError> {
+        let report = self.get(id)?;
+        let body = serde_json::json!({
+            "report": report,
+            "metrics": self.metrics(),
+        });
+        let response = NetworkClient::global()
+            .post(CRASH_REPORT_URL)
+            .map_err(|e| CrashReportError::Submission(e.to_string()))?
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| CrashReportError::Submission(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(CrashReportError::Submission(format!(
+                "server returned {}",
+                response.status()
+            )));
+        }
+        self.dismiss(id)
+    }
+
+    /// Ids are generated UUIDs; anything else could escape the directory
+    fn report_path(&self, id: &str) -> PathBuf {
+        let id: String = id
+            .chars()
+            .filter(|c| c.is_ascii_hexdigit() || *c == '-')
+            .collect();
+        self.dir.join(format!("crash-{}.json", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_are_kept_until_dismissed() {
+        let dir = tempfile::tempdir().unwrap();
+        let reporter = CrashReporter::new(dir.path());
+        reporter.begin_session().unwrap();
+
+        record_state("active_project", "p-1");
+        push_log_line("INFO herding_cats: opened project".to_string());
+        let report = CrashReport::new("index out of bounds".to_string());
+        reporter.write_report(&report).unwrap();
+
+        let pending = reporter.pending_reports();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].state.get("active_project").unwrap(), "p-1");
+        assert!(pending[0]
+            .log_tail
+            .iter()
+            .any(|line| line.contains("opened project")));
+
+        reporter.dismiss(&report.id).unwrap();
+        assert!(reporter.pending_reports().is_empty());
+        assert!(reporter.dismiss("../metrics").is_err());
+    }
+
+    #[test]
+    fn test_session_metrics_count_unclean_exits() {
+        let dir = tempfile::tempdir().unwrap();
+        let reporter = CrashReporter::new(dir.path());
+
+        // Clean session
+        reporter.begin_session().unwrap();
+        reporter.end_session().unwrap();
+        // Session that panicked but shut down
+        reporter.begin_session().unwrap();
+        reporter
+            .write_report(&CrashReport::new("boom".to_string()))
+            .unwrap();
+        reporter.end_session().unwrap();
+        // Session that never shut down, noticed by the next start
+        reporter.begin_session().unwrap();
+        reporter.begin_session().unwrap();
+
+        let metrics = reporter.metrics();
+        assert_eq!(metrics.total_sessions, 3);
+        assert_eq!(metrics.crashed_sessions, 2);
+        assert!((metrics.crash_free_rate() - 1.0 / 3.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,330 @@
+//! Data Directory Migration
+//!
+//! Moves the library to a user-chosen data directory. The live database is
+//! copied with `VACUUM INTO`, which produces a consistent snapshot while the
+//! app keeps it open; settings, backups and assets are copied and verified
+//! by checksum. The copied database must pass an integrity check, and the
+//! absolute backup paths it stores are rewritten to the new location before
+//! the new directory is recorded for the next start.
+//!
+//! The old directory is left untouched so nothing is lost if the app is
+//! closed mid-way; the user can delete it once the new location works.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{Row, SqlitePool};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::app_paths::AppPaths;
+use crate::error::{ErrorCode, UserFacingError};
+
+/// Database file name inside a data directory
+const DATABASE_FILE: &str = "herding_cats.db";
+/// Top-level entries moved along with the database
+const DATA_ENTRIES: &[&str] = &[
+    "settings.json",
+    "theme_settings.json",
+    "recent_items.json",
+    "backups",
+    "assets",
+];
+
+#[derive(Debug, Error)]
+pub enum DataMigrationError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid target directory: {0}")]
+    InvalidTarget(String),
+    #[error("Copy of {0} does not match the original")]
+    Verification(PathBuf),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+impl UserFacingError for DataMigrationError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            DataMigrationError::InvalidTarget(_) => ErrorCode::InvalidInput,
+            DataMigrationError::Io(_) => ErrorCode::Storage,
+            DataMigrationError::Verification(_) => ErrorCode::Corrupted,
+            DataMigrationError::Database(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+impl From<sqlx::Error> for DataMigrationError {
+    fn from(e: sqlx::Error) -> Self {
+        DataMigrationError::Database(e.to_string())
+    }
+}
+
+/// What a migration will copy, shown to the user before it starts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataMigrationPlan {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// Files besides the database, relative to `from`
+    pub files: Vec<PathBuf>,
+    /// Size of the database and all files
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataMigrationReport {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub files_copied: usize,
+    pub bytes_copied: u64,
+    /// Stored absolute paths rewritten to the new location
+    pub paths_updated: u64,
+}
+
+/// Check `to` and list what would be copied from `from`
+pub fn plan(from: &Path, to: &Path) -> Result<DataMigrationPlan, DataMigrationError> {
+    if AppPaths::global().is_portable() {
+        return Err(DataMigrationError::InvalidTarget(
+            "portable installs keep their data next to the executable".to_string(),
+        ));
+    }
+    if to.is_relative() {
+        return Err(DataMigrationError::InvalidTarget(
+            "the new location must be an absolute path".to_string(),
+        ));
+    }
+    let from = from.canonicalize()?;
+    let resolved_to = resolve_new(to);
+    // Only the entries above are copied, so a subdirectory of the current
+    // location is fine unless it is one of them
+    let inside_copied = DATA_ENTRIES
+        .iter()
+        .any(|entry| resolved_to.starts_with(from.join(entry)));
+    if resolved_to == from || inside_copied {
+        return Err(DataMigrationError::InvalidTarget(
+            "the new location overlaps the current one".to_string(),
+        ));
+    }
+    if resolved_to.join(DATABASE_FILE).exists() {
+        return Err(DataMigrationError::InvalidTarget(format!(
+            "{} already contains a library",
+            to.display()
+        )));
+    }
+
+    let mut files = Vec::new();
+    for entry in DATA_ENTRIES {
+        collect_files(&from, Path::new(entry), &mut files)?;
+    }
+    let mut total_bytes = std::fs::metadata(from.join(DATABASE_FILE))
+        .map(|m| m.len())
+        .unwrap_or(0);
+    for file in &files {
+        total_bytes += std::fs::metadata(from.join(file))?.len();
+    }
+
+    Ok(DataMigrationPlan {
+        from,
+        to: resolved_to,
+        files,
+        total_bytes,
+    })
+}
+
+/// Copy everything in `plan`, verify it and record the new location.
+/// `pool` is the open connection to the current database.
+pub async fn migrate(
+    pool: &SqlitePool,
+    plan: &DataMigrationPlan,
+) -> Result<DataMigrationReport, DataMigrationError> {
+    std::fs::create_dir_all(&plan.to)?;
+
+    let copy_plan = plan.clone();
+    let bytes_copied = tokio::task::spawn_blocking(move || copy_files(&copy_plan))
+        .await
+        .map_err(|e| DataMigrationError::Io(std::io::Error::other(e)))??;
+
+    let new_database = plan.to.join(DATABASE_FILE);
+    sqlx::query("VACUUM INTO ?1")
+        .bind(new_database.to_string_lossy().to_string())
+        .execute(pool)
+        .await?;
+    let database_bytes = std::fs::metadata(&new_database)?.len();
+
+    let paths_updated = finalize_database(&new_database, &plan.from, &plan.to).await?;
+
+    AppPaths::global().set_data_dir_override(Some(&plan.to))?;
+    log::info!(
+        "Moved data from {} to {}",
+        plan.from.display(),
+        plan.to.display()
+    );
+
+    Ok(DataMigrationReport {
+        from: plan.from.clone(),
+        to: plan.to.clone(),
+        files_copied: plan.files.len(),
+        bytes_copied: bytes_copied + database_bytes,
+        paths_updated,
+    })
+}
+
+/// Integrity-check the copied database and point its stored paths at `to`
+async fn finalize_database(
+    database: &Path,
+    from: &Path,
+    to: &Path,
+) -> Result<u64, DataMigrationError> {
+    let pool = SqlitePool::connect_with(SqliteConnectOptions::new().filename(database)).await?;
+    let result = async {
+        let (integrity,): (String,) = sqlx::query_as("PRAGMA integrity_check")
+            .fetch_one(&pool)
+            .await?;
+        if integrity != "ok" {
+            return Err(DataMigrationError::Database(format!(
+                "integrity check of the copied database failed: {}",
+                integrity
+            )));
+        }
+
+        let rows = sqlx::query("SELECT id, file_path FROM backup_metadata")
+            .fetch_all(&pool)
+            .await?;
+        let mut updated = 0;
+        for row in rows {
+            let id: String = row.try_get("id")?;
+            let file_path: String = row.try_get("file_path")?;
+            if let Some(moved) = relocate(Path::new(&file_path), from, to) {
+                sqlx::query("UPDATE backup_metadata SET file_path = ?1 WHERE id = ?2")
+                    .bind(moved.to_string_lossy().to_string())
+                    .bind(&id)
+                    .execute(&pool)
+                    .await?;
+                updated += 1;
+            }
+        }
+        Ok(updated)
+    }
+    .await;
+    pool.close().await;
+    result
+}
+
+/// `path` under `to` instead of `from`, if it was inside `from`
+pub fn relocate(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    path.strip_prefix(from)
+        .ok()
+        .map(|relative| to.join(relative))
+}
+
+fn copy_files(plan: &DataMigrationPlan) -> Result<u64, DataMigrationError> {
+    let mut bytes = 0;
+    for file in &plan.files {
+        let source = plan.from.join(file);
+        let target = plan.to.join(file);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        bytes += std::fs::copy(&source, &target)?;
+        if checksum(&source)? != checksum(&target)? {
+            return Err(DataMigrationError::Verification(file.clone()));
+        }
+    }
+    Ok(bytes)
+}
+
+fn checksum(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    let mut file = std::fs::File::open(path)?;
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+fn collect_files(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let path = root.join(relative);
+    if path.is_file() {
+        files.push(relative.to_path_buf());
+    } else if path.is_dir() {
+        for entry in std::fs::read_dir(&path)? {
+            collect_files(root, &relative.join(entry?.file_name()), files)?;
+        }
+    }
+    Ok(())
+}
+
+/// Canonical form of a directory that may not exist yet
+fn resolve_new(path: &Path) -> PathBuf {
+    path.ancestors()
+        .find_map(|ancestor| {
+            let canonical = ancestor.canonicalize().ok()?;
+            let rest = path.strip_prefix(ancestor).ok()?;
+            Some(canonical.join(rest))
+        })
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_migration_copies_and_relocates_backup_paths() {
+        let root = tempfile::tempdir().unwrap();
+        let from = root.path().join("old");
+        let to = root.path().join("new").join("library");
+        std::fs::create_dir_all(from.join("backups")).unwrap();
+        std::fs::write(from.join("settings.json"), "{}").unwrap();
+        std::fs::write(from.join("backups").join("b1.db"), b"backup").unwrap();
+        std::fs::write(from.join("unrelated.txt"), b"skip").unwrap();
+
+        let options = SqliteConnectOptions::new()
+            .filename(from.join(DATABASE_FILE))
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await.unwrap();
+        sqlx::query("CREATE TABLE backup_metadata (id TEXT PRIMARY KEY, file_path TEXT NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let canonical_from = from.canonicalize().unwrap();
+        sqlx::query("INSERT INTO backup_metadata VALUES ('b1', ?1), ('b2', '/elsewhere/b2.db')")
+            .bind(
+                canonical_from
+                    .join("backups")
+                    .join("b1.db")
+                    .to_string_lossy()
+                    .to_string(),
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let plan = plan(&from, &to).unwrap();
+        assert_eq!(plan.files.len(), 2);
+
+        // Skip recording the location so the test leaves no pointer behind
+        let bytes = copy_files(&plan).unwrap();
+        assert_eq!(bytes, 8);
+        sqlx::query("VACUUM INTO ?1")
+            .bind(plan.to.join(DATABASE_FILE).to_string_lossy().to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        let updated = finalize_database(&plan.to.join(DATABASE_FILE), &plan.from, &plan.to)
+            .await
+            .unwrap();
+        assert_eq!(updated, 1);
+        assert!(plan.to.join("backups").join("b1.db").exists());
+        assert!(!plan.to.join("unrelated.txt").exists());
+
+        // The original directory is now unusable as a target
+        assert!(matches!(
+            super::plan(&to, &from),
+            Err(DataMigrationError::InvalidTarget(_))
+        ));
+        assert!(matches!(
+            super::plan(&from, &from.join("backups").join("nested")),
+            Err(DataMigrationError::InvalidTarget(_))
+        ));
+        assert!(super::plan(&from, &from.join("Library")).is_ok());
+    }
+}
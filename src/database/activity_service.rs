@@ -0,0 +1,437 @@
+//! Activity Service
+//!
+//! Keeps each project's activity feed and answers "what changed since I
+//! last opened this". Other services and the IPC layer write to the feed
+//! through `record_activity` and its helpers on the shared pool, so
+//! recording never needs the service itself; a failure to record is logged
+//! by the caller and never fails the action being recorded.
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::{
+    models::activity::*,
+    parse::{parse_time, parse_uuid},
+    DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+
+type ActivityRow = (
+    String,
+    Option<String>,
+    String,
+    Option<String>,
+    String,
+    i64,
+    String,
+    String,
+);
+
+/// Activity on the same item within this long is folded into one entry
+const ACTIVITY_COALESCE_MINUTES: i64 = 30;
+/// Entries older than this are deleted on startup
+const ACTIVITY_RETENTION_DAYS: i64 = 180;
+/// Entries listed when no limit is given
+const DEFAULT_FEED_LIMIT: usize = 100;
+/// Order kinds appear in summaries
+const SUMMARY_ORDER: [ActivityKind; 6] = [
+    ActivityKind::DocumentEdited,
+    ActivityKind::CodexChanged,
+    ActivityKind::Structure,
+    ActivityKind::Ai,
+    ActivityKind::Export,
+    ActivityKind::Backup,
+];
+
+/// Service for the per-project activity feed
+#[derive(Debug)]
+pub struct ActivityService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl ActivityService {
+    /// Create a new activity service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the activity tables and drop expired entries
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_ACTIVITY_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create activity tables: {}", e))
+            })?;
+        let cutoff = Utc::now() - Duration::days(ACTIVITY_RETENTION_DAYS);
+        sqlx::query("DELETE FROM activity_feed WHERE last_at < ?1")
+            .bind(cutoff.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to prune activity: {}", e)))?;
+        Ok(())
+    }
+
+    /// Add an entry to the feed
+    pub async fn record(&self, entry: &ActivityEntry) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        record_activity(&db.pool, entry).await
+    }
+
+    /// Record a change to a codex entry, in the entry's project
+    pub async fn record_codex_change(
+        &self,
+        entry_id: Uuid,
+        summary: impl Into<String>,
+    ) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        record_codex_change(&db.pool, entry_id, summary).await
+    }
+
+    /// A project's feed, newest first. Activity that covers every project,
+    /// like full backups, is included.
+    pub async fn feed(
+        &self,
+        project_id: Uuid,
+        filter: &ActivityFilter,
+    ) -> DatabaseResult<Vec<ActivityEntry>> {
+        let mut sql = format!(
+            "{} WHERE (project_id = ?1 OR project_id IS NULL)",
+            SELECT_ACTIVITY_SQL
+        );
+        let mut params: Vec<String> = vec![project_id.to_string()];
+        if !filter.kinds.is_empty() {
+            let placeholders: Vec<String> = filter
+                .kinds
+                .iter()
+                .map(|kind| {
+                    params.push(kind.as_str().to_string());
+                    format!("?{}", params.len())
+                })
+                .collect();
+            sql.push_str(&format!(" AND kind IN ({})", placeholders.join(", ")));
+        }
+        if let Some(subject_id) = &filter.subject_id {
+            params.push(subject_id.clone());
+            sql.push_str(&format!(" AND subject_id = ?{}", params.len()));
+        }
+        if let Some(since) = filter.since {
+            params.push(since.to_rfc3339());
+            sql.push_str(&format!(" AND last_at > ?{}", params.len()));
+        }
+        sql.push_str(&format!(
+            " ORDER BY last_at DESC LIMIT {}",
+            filter.limit.unwrap_or(DEFAULT_FEED_LIMIT)
+        ));
+
+        let db = self.db_service.read().await;
+        let mut query = sqlx::query_as::<_, ActivityRow>(&sql);
+        for param in &params {
+            query = query.bind(param);
+        }
+        let rows = query
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load activity: {}", e)))?;
+        rows.into_iter().map(activity_from_row).collect()
+    }
+
+    /// What changed since `since`, or everything recorded when `None`
+    pub async fn summary_since(
+        &self,
+        project_id: Uuid,
+        since: Option<DateTime<Utc>>,
+    ) -> DatabaseResult<ActivitySummary> {
+        let filter = ActivityFilter {
+            since,
+            ..ActivityFilter::default()
+        };
+        let entries = self.feed(project_id, &filter).await?;
+        Ok(summarize(project_id, since, entries))
+    }
+
+    /// What changed since the project was last opened, marking it opened now
+    pub async fn open_project(&self, project_id: Uuid) -> DatabaseResult<ActivitySummary> {
+        let since = self.last_viewed(project_id).await?;
+        let summary = self.summary_since(project_id, since).await?;
+        let db = self.db_service.read().await;
+        sqlx::query(
+            "INSERT INTO activity_views (project_id, last_viewed_at) VALUES (?1, ?2)
+             ON CONFLICT(project_id) DO UPDATE SET last_viewed_at = excluded.last_viewed_at",
+        )
+        .bind(project_id.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to mark project opened: {}", e)))?;
+        Ok(summary)
+    }
+
+    /// When the project was last opened
+    pub async fn last_viewed(&self, project_id: Uuid) -> DatabaseResult<Option<DateTime<Utc>>> {
+        let db = self.db_service.read().await;
+        let viewed: Option<String> =
+            sqlx::query_scalar("SELECT last_viewed_at FROM activity_views WHERE project_id = ?1")
+                .bind(project_id.to_string())
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to load last opened time: {}", e))
+                })?;
+        viewed.as_deref().map(parse_time).transpose()
+    }
+}
+
+/// Add an entry to the feed, folding it into a recent entry for the same
+/// subject when there is one
+pub async fn record_activity(pool: &SqlitePool, entry: &ActivityEntry) -> DatabaseResult<()> {
+    let project_id = entry.project_id.map(|id| id.to_string());
+    if let Some(subject_id) = &entry.subject_id {
+        let window_start = entry.last_at - Duration::minutes(ACTIVITY_COALESCE_MINUTES);
+        let folded = sqlx::query(
+            "UPDATE activity_feed SET count = count + ?1, summary = ?2, last_at = ?3
+             WHERE id = (SELECT id FROM activity_feed
+                         WHERE kind = ?4 AND subject_id = ?5 AND project_id IS ?6 AND last_at >= ?7
+                         ORDER BY last_at DESC LIMIT 1)",
+        )
+        .bind(entry.count as i64)
+        .bind(&entry.summary)
+        .bind(entry.last_at.to_rfc3339())
+        .bind(entry.kind.as_str())
+        .bind(subject_id)
+        .bind(&project_id)
+        .bind(window_start.to_rfc3339())
+        .execute(pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to record activity: {}", e)))?;
+        if folded.rows_affected() > 0 {
+            return Ok(());
+        }
+    }
+
+    sqlx::query(INSERT_ACTIVITY_SQL)
+        .bind(entry.id.to_string())
+        .bind(&project_id)
+        .bind(entry.kind.as_str())
+        .bind(&entry.subject_id)
+        .bind(&entry.summary)
+        .bind(entry.count as i64)
+        .bind(entry.first_at.to_rfc3339())
+        .bind(entry.last_at.to_rfc3339())
+        .execute(pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to record activity: {}", e)))?;
+    Ok(())
+}
+
+/// Record an edit to a document, in the document's project
+pub async fn record_document_edit(pool: &SqlitePool, document_id: &str) -> DatabaseResult<()> {
+    let document: Option<(String, String)> =
+        sqlx::query_as("SELECT project_id, title FROM documents WHERE id = ?1")
+            .bind(document_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load document: {}", e)))?;
+    let Some((project_id, title)) = document else {
+        return Ok(());
+    };
+    let entry = ActivityEntry::new(
+        Some(parse_uuid(&project_id)?),
+        ActivityKind::DocumentEdited,
+        format!("Edited '{}'", title),
+    )
+    .with_subject(document_id);
+    record_activity(pool, &entry).await
+}
+
+/// Record a change to a codex entry, in the entry's project
+pub async fn record_codex_change(
+    pool: &SqlitePool,
+    entry_id: Uuid,
+    summary: impl Into<String>,
+) -> DatabaseResult<()> {
+    let project_id: Option<String> =
+        sqlx::query_scalar("SELECT project_id FROM codex_entries WHERE id = ?1")
+            .bind(entry_id.to_string())
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load codex entry: {}", e)))?;
+    let Some(project_id) = project_id else {
+        return Ok(());
+    };
+    let entry = ActivityEntry::new(
+        Some(parse_uuid(&project_id)?),
+        ActivityKind::CodexChanged,
+        summary,
+    )
+    .with_subject(entry_id.to_string());
+    record_activity(pool, &entry).await
+}
+
+/// Counts per kind and a one-line headline for `entries`
+pub fn summarize(
+    project_id: Uuid,
+    since: Option<DateTime<Utc>>,
+    entries: Vec<ActivityEntry>,
+) -> ActivitySummary {
+    let counts: Vec<ActivityCount> = SUMMARY_ORDER
+        .iter()
+        .filter_map(|kind| {
+            let of_kind: Vec<&ActivityEntry> =
+                entries.iter().filter(|entry| entry.kind == *kind).collect();
+            if of_kind.is_empty() {
+                return None;
+            }
+            // Entries about the same subject count as one item
+            let mut subjects = HashSet::new();
+            let items = of_kind
+                .iter()
+                .filter(|entry| match &entry.subject_id {
+                    Some(subject_id) => subjects.insert(subject_id.as_str()),
+                    None => true,
+                })
+                .count();
+            Some(ActivityCount {
+                kind: *kind,
+                items,
+                occurrences: of_kind.iter().map(|entry| entry.count).sum(),
+            })
+        })
+        .collect();
+
+    let headline = if counts.is_empty() {
+        match since {
+            Some(_) => "Nothing has changed since you were last here".to_string(),
+            None => "No activity recorded yet".to_string(),
+        }
+    } else {
+        counts
+            .iter()
+            .map(|count| {
+                let (singular, plural) = count.kind.noun();
+                let noun = if count.items == 1 { singular } else { plural };
+                format!("{} {}", count.items, noun)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    ActivitySummary {
+        project_id,
+        since,
+        headline,
+        counts,
+        entries,
+    }
+}
+
+fn activity_from_row(row: ActivityRow) -> DatabaseResult<ActivityEntry> {
+    let (id, project_id, kind, subject_id, summary, count, first_at, last_at) = row;
+    Ok(ActivityEntry {
+        id: parse_uuid(&id)?,
+        project_id: project_id.as_deref().map(parse_uuid).transpose()?,
+        kind: ActivityKind::parse(&kind)
+            .ok_or_else(|| DatabaseError::Service(format!("Unknown activity kind: {}", kind)))?,
+        subject_id,
+        summary,
+        count: count.max(0) as u32,
+        first_at: parse_time(&first_at)?,
+        last_at: parse_time(&last_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_feed_folds_repeated_edits_and_summarizes_since_last_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Novel', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let (one, two) = (Uuid::new_v4().to_string(), Uuid::new_v4().to_string());
+        for (id, title) in [(&one, "Arrival"), (&two, "Departure")] {
+            db.create_document(
+                id.clone(),
+                project.to_string(),
+                title.to_string(),
+                "Text.".to_string(),
+            )
+            .await
+            .unwrap();
+        }
+        let pool = db.pool.clone();
+        let service = ActivityService::new(Arc::new(RwLock::new(db)));
+        service.initialize().await.unwrap();
+
+        let first = service.open_project(project).await.unwrap();
+        assert_eq!(first.since, None);
+        assert_eq!(first.headline, "No activity recorded yet");
+
+        for id in [&one, &one, &one, &two] {
+            record_document_edit(&pool, id).await.unwrap();
+        }
+        record_activity(
+            &pool,
+            &ActivityEntry::new(None, ActivityKind::Backup, "Automatic backup"),
+        )
+        .await
+        .unwrap();
+        record_activity(
+            &pool,
+            &ActivityEntry::new(Some(project), ActivityKind::Export, "Exported ePub"),
+        )
+        .await
+        .unwrap();
+
+        let edits = service
+            .feed(
+                project,
+                &ActivityFilter {
+                    kinds: vec![ActivityKind::DocumentEdited],
+                    subject_id: Some(one.clone()),
+                    ..ActivityFilter::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].count, 3);
+        assert_eq!(edits[0].summary, "Edited 'Arrival'");
+
+        let summary = service.open_project(project).await.unwrap();
+        assert!(summary.since.is_some());
+        assert_eq!(summary.entries.len(), 4);
+        assert_eq!(summary.headline, "2 documents edited, 1 export, 1 backup");
+        assert_eq!(
+            summary.counts[0],
+            ActivityCount {
+                kind: ActivityKind::DocumentEdited,
+                items: 2,
+                occurrences: 4,
+            }
+        );
+
+        let again = service.open_project(project).await.unwrap();
+        assert_eq!(
+            again.headline,
+            "Nothing has changed since you were last here"
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! AI Log Service
+//!
+//! Keeps the optional local log of AI prompts and responses. Nothing is
+//! written unless the privacy controls turn the log on and the project
+//! hasn't opted out; secrets are redacted before an entry is stored, and
+//! entries older than the retention period are removed as new ones come
+//! in. The log can be exported as JSON or deleted, whole or per project.
+
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::models::ai_log::{
+    AiInteraction, AI_LOG_PROJECT_SETTING, CREATE_AI_LOG_TABLES_SQL,
+};
+use crate::database::parse::parse_uuid;
+use crate::database::{DatabaseError, DatabaseResult, EnhancedDatabaseService};
+use crate::security::secrets_scanner::{redact, scan_text};
+use crate::settings::PrivacyControls;
+
+type InteractionRow = (
+    String,
+    Option<String>,
+    String,
+    Option<String>,
+    String,
+    String,
+    i64,
+    i64,
+    i64,
+    String,
+);
+
+/// Service for the AI interaction log
+#[derive(Debug)]
+pub struct AiLogService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl AiLogService {
+    /// Create a new AI log service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the log table
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_AI_LOG_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create AI log tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Log an AI request if `controls` and the project allow it, with
+    /// secrets redacted; returns whether it was stored
+    pub async fn record(
+        &self,
+        controls: &PrivacyControls,
+        mut interaction: AiInteraction,
+    ) -> DatabaseResult<bool> {
+        if !controls.log_ai_interactions {
+            return Ok(false);
+        }
+        if let Some(project_id) = interaction.project_id {
+            if !self.project_logging_enabled(project_id).await? {
+                return Ok(false);
+            }
+        }
+
+        for text in [&mut interaction.prompt, &mut interaction.response] {
+            let findings = scan_text(text);
+            if !findings.is_empty() {
+                interaction.redactions += findings.len() as i64;
+                *text = redact(text, &findings);
+            }
+        }
+
+        {
+            let db = self.db_service.read().await;
+            sqlx::query(
+                "INSERT INTO ai_interaction_log
+                 (id, project_id, feature, model, prompt, response, prompt_tokens, response_tokens, redactions, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )
+            .bind(interaction.id.to_string())
+            .bind(interaction.project_id.map(|id| id.to_string()))
+            .bind(&interaction.feature)
+            .bind(&interaction.model)
+            .bind(&interaction.prompt)
+            .bind(&interaction.response)
+            .bind(interaction.prompt_tokens)
+            .bind(interaction.response_tokens)
+            .bind(interaction.redactions)
+            .bind(interaction.created_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to log AI request: {}", e)))?;
+        }
+
+        self.enforce_retention(controls).await?;
+        Ok(true)
+    }
+
+    /// Remove entries older than the retention period; returns how many
+    pub async fn enforce_retention(&self, controls: &PrivacyControls) -> DatabaseResult<u64> {
+        if controls.ai_log_retention_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = Utc::now() - Duration::days(controls.ai_log_retention_days as i64);
+        self.delete_before(cutoff).await
+    }
+
+    async fn delete_before(&self, cutoff: DateTime<Utc>) -> DatabaseResult<u64> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query(
+            "DELETE FROM ai_interaction_log WHERE julianday(created_at) < julianday(?1)",
+        )
+        .bind(cutoff.to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to prune AI log: {}", e)))?;
+        Ok(result.rows_affected())
+    }
+
+    /// Whether requests made in a project are logged; on unless the
+    /// project opted out
+    pub async fn project_logging_enabled(&self, project_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let value: Option<String> = sqlx::query_scalar(
+            "SELECT setting_value FROM project_settings WHERE project_id = ?1 AND setting_key = ?2",
+        )
+        .bind(project_id.to_string())
+        .bind(AI_LOG_PROJECT_SETTING)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to read project setting: {}", e)))?;
+        Ok(value.as_deref() != Some("false"))
+    }
+
+    /// Opt a project out of the log, or back in. Opting out also deletes
+    /// what was already logged for it.
+    pub async fn set_project_logging(&self, project_id: Uuid, enabled: bool) -> DatabaseResult<()> {
+        {
+            let db = self.db_service.read().await;
+            let now = Utc::now().to_rfc3339();
+            sqlx::query(
+                "INSERT OR REPLACE INTO project_settings (id, project_id, setting_key, setting_value, setting_type, created_at, updated_at)
+                 VALUES (COALESCE((SELECT id FROM project_settings WHERE project_id = ?1 AND setting_key = ?2), lower(hex(randomblob(16)))), ?1, ?2, ?3, 'boolean', ?4, ?4)",
+            )
+            .bind(project_id.to_string())
+            .bind(AI_LOG_PROJECT_SETTING)
+            .bind(enabled.to_string())
+            .bind(&now)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to update project setting: {}", e))
+            })?;
+        }
+        if !enabled {
+            self.delete(Some(project_id)).await?;
+        }
+        Ok(())
+    }
+
+    /// Logged requests, of one project or all of them, newest first
+    pub async fn list(
+        &self,
+        project_id: Option<Uuid>,
+        limit: Option<u32>,
+    ) -> DatabaseResult<Vec<AiInteraction>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<InteractionRow> = sqlx::query_as(
+            "SELECT id, project_id, feature, model, prompt, response, prompt_tokens, response_tokens, redactions, created_at
+             FROM ai_interaction_log
+             WHERE (?1 IS NULL OR project_id = ?1)
+             ORDER BY julianday(created_at) DESC
+             LIMIT ?2",
+        )
+        .bind(project_id.map(|id| id.to_string()))
+        .bind(limit.map_or(-1, i64::from))
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load AI log: {}", e)))?;
+        rows.into_iter().map(interaction_from_row).collect()
+    }
+
+    /// The log, of one project or all of it, as a JSON array
+    pub async fn export(&self, project_id: Option<Uuid>) -> DatabaseResult<String> {
+        let entries = self.list(project_id, None).await?;
+        serde_json::to_string_pretty(&entries).map_err(|e| DatabaseError::SerializationError {
+            message: e.to_string(),
+        })
+    }
+
+    /// Delete the log of one project, or all of it; returns how many
+    /// entries went
+    pub async fn delete(&self, project_id: Option<Uuid>) -> DatabaseResult<u64> {
+        let db = self.db_service.read().await;
+        let result =
+            sqlx::query("DELETE FROM ai_interaction_log WHERE (?1 IS NULL OR project_id = ?1)")
+                .bind(project_id.map(|id| id.to_string()))
+                .execute(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to delete AI log: {}", e)))?;
+        Ok(result.rows_affected())
+    }
+}
+
+fn interaction_from_row(row: InteractionRow) -> DatabaseResult<AiInteraction> {
+    let (
+        id,
+        project_id,
+        feature,
+        model,
+        prompt,
+        response,
+        prompt_tokens,
+        response_tokens,
+        redactions,
+        created_at,
+    ) = row;
+    Ok(AiInteraction {
+        id: parse_uuid(&id)?,
+        project_id: project_id.as_deref().map(parse_uuid).transpose()?,
+        feature,
+        model,
+        prompt,
+        response,
+        prompt_tokens,
+        response_tokens,
+        redactions,
+        created_at: DateTime::parse_from_rfc3339(&created_at)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| DatabaseError::Service(format!("Invalid timestamp: {}", e)))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_log_respects_controls_and_redacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let (kept, opted_out) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now().to_rfc3339();
+        for project in [kept, opted_out] {
+            sqlx::query(
+                "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'X', ?2, ?2)",
+            )
+            .bind(project.to_string())
+            .bind(&now)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+        let service = AiLogService::new(Arc::new(RwLock::new(db)));
+        service.initialize().await.unwrap();
+
+        let interaction = |project| {
+            AiInteraction::new(
+                Some(project),
+                "ai_request",
+                "Rewrite this. api_key = sk_live_0123456789abcdef",
+                "Done.",
+            )
+        };
+        let off = PrivacyControls::default();
+        assert!(!service.record(&off, interaction(kept)).await.unwrap());
+
+        let on = PrivacyControls {
+            log_ai_interactions: true,
+            ..PrivacyControls::default()
+        };
+        service.set_project_logging(opted_out, false).await.unwrap();
+        assert!(service.record(&on, interaction(kept)).await.unwrap());
+        assert!(!service.record(&on, interaction(opted_out)).await.unwrap());
+
+        let entries = service.list(None, None).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].redactions, 1);
+        assert!(!entries[0].prompt.contains("sk_live"));
+        assert!(entries[0].prompt.contains("[REDACTED"));
+        assert!(service
+            .export(Some(kept))
+            .await
+            .unwrap()
+            .contains("\"ai_request\""));
+
+        // Entries past the retention period go when the next one comes in
+        let mut old = interaction(kept);
+        old.created_at = Utc::now() - Duration::days(31);
+        assert!(service.record(&on, old).await.unwrap());
+        assert_eq!(service.list(Some(kept), None).await.unwrap().len(), 1);
+
+        assert_eq!(service.delete(Some(kept)).await.unwrap(), 1);
+        assert!(service.list(None, None).await.unwrap().is_empty());
+    }
+}
@@ -2,14 +2,28 @@
 //!
 //! Database service for managing analysis data, providing CRUD operations
 //! and integration with other writing tools through drag-and-drop functionality.
+//! Also runs the genre lint packs a project has enabled, checks each
+//! scene's narrative person and tense against its declared voice, and
+//! scores a document's readability and style, version by version.
 
+use chrono::Utc;
 use sqlx::{self};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+use crate::database::parse::{parse_time, parse_uuid};
 use crate::database::{
+    lint_packs::{builtin_packs, lint_chapters, LintChapter, LintCharacter},
     models::analysis::{AnalysisWithFields, *},
+    models::codex::CharacterData,
+    models::lint_pack::*,
+    models::narrative_voice::{SceneVoice, VoiceReport},
+    models::readability::*,
+    narrative_voice::{check_voice, VoiceDocument},
+    prosemirror,
+    readability::{build_report, count_paragraph, paragraph_hash, paragraphs},
     DatabaseError, DatabaseResult, EnhancedDatabaseService,
 };
 
@@ -41,9 +55,429 @@ impl AnalysisService {
 
     /// Initialize analysis tables and types
     pub async fn initialize(&self) -> DatabaseResult<()> {
+        if let Some(db_service) = &self.db_service {
+            let db = db_service.read().await;
+            sqlx::query(CREATE_LINT_SETTINGS_TABLE_SQL)
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to create lint settings table: {}", e))
+                })?;
+            sqlx::query(CREATE_READABILITY_CACHE_TABLE_SQL)
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!(
+                        "Failed to create readability cache table: {}",
+                        e
+                    ))
+                })?;
+        }
         Ok(())
     }
 
+    /// The genre lint packs that can be enabled
+    pub fn lint_packs(&self) -> Vec<LintPack> {
+        builtin_packs()
+    }
+
+    /// Lint settings for a project; no packs are enabled until saved
+    pub async fn get_lint_settings(&self, project_id: Uuid) -> DatabaseResult<LintSettings> {
+        let db_service = self.db_service.as_ref().ok_or_else(|| {
+            DatabaseError::Connection("Database service not initialized".to_string())
+        })?;
+        let db = db_service.read().await;
+        let row: Option<(String, String, String)> = sqlx::query_as(GET_LINT_SETTINGS_SQL)
+            .bind(project_id.to_string())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get lint settings: {}", e)))?;
+
+        match row {
+            Some((enabled_packs, severity_overrides, updated_at)) => Ok(LintSettings {
+                project_id,
+                enabled_packs: serde_json::from_str(&enabled_packs).map_err(|e| {
+                    DatabaseError::Service(format!("Failed to parse lint packs: {}", e))
+                })?,
+                severity_overrides: serde_json::from_str(&severity_overrides).map_err(|e| {
+                    DatabaseError::Service(format!("Failed to parse severity overrides: {}", e))
+                })?,
+                updated_at: parse_time(&updated_at)?,
+            }),
+            None => Ok(LintSettings::new(project_id)),
+        }
+    }
+
+    /// Save a project's enabled packs and severity overrides
+    pub async fn save_lint_settings(
+        &self,
+        settings: &LintSettings,
+    ) -> DatabaseResult<LintSettings> {
+        let packs = builtin_packs();
+        for pack_id in &settings.enabled_packs {
+            if !packs.iter().any(|pack| &pack.id == pack_id) {
+                return Err(DatabaseError::ValidationError(format!(
+                    "Unknown lint pack '{}'",
+                    pack_id
+                )));
+            }
+        }
+        for key in settings.severity_overrides.keys() {
+            let known = packs.iter().any(|pack| {
+                pack.rules.iter().any(|rule| {
+                    key == rule.rule.id() || *key == format!("{}.{}", pack.id, rule.rule.id())
+                })
+            });
+            if !known {
+                return Err(DatabaseError::ValidationError(format!(
+                    "Unknown lint rule '{}'",
+                    key
+                )));
+            }
+        }
+
+        let mut saved = settings.clone();
+        saved.updated_at = Utc::now();
+        let enabled_packs = serde_json::to_string(&saved.enabled_packs).map_err(|e| {
+            DatabaseError::Service(format!("Failed to serialize lint packs: {}", e))
+        })?;
+        let severity_overrides = serde_json::to_string(&saved.severity_overrides).map_err(|e| {
+            DatabaseError::Service(format!("Failed to serialize severity overrides: {}", e))
+        })?;
+
+        let db_service = self.db_service.as_ref().ok_or_else(|| {
+            DatabaseError::Connection("Database service not initialized".to_string())
+        })?;
+        let db = db_service.read().await;
+        sqlx::query(UPSERT_LINT_SETTINGS_SQL)
+            .bind(saved.project_id.to_string())
+            .bind(enabled_packs)
+            .bind(severity_overrides)
+            .bind(saved.updated_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save lint settings: {}", e)))?;
+        Ok(saved)
+    }
+
+    /// Run the project's enabled lint packs over its chapters in binder order
+    pub async fn lint_project(&self, project_id: Uuid) -> DatabaseResult<LintReport> {
+        let settings = self.get_lint_settings(project_id).await?;
+        let packs = builtin_packs();
+
+        let db_service = self.db_service.as_ref().ok_or_else(|| {
+            DatabaseError::Connection("Database service not initialized".to_string())
+        })?;
+        let db = db_service.read().await;
+        let tables = Self::tables(&db).await?;
+
+        let sql = if tables.iter().any(|t| t == "binder_order") {
+            "SELECT d.id, d.title, d.content, d.document_type FROM documents d LEFT JOIN binder_order b ON b.document_id = d.id
+             WHERE d.project_id = ?1 AND d.is_active = 1
+             ORDER BY b.position IS NULL, b.position, d.created_at, d.title"
+        } else {
+            "SELECT id, title, content, document_type FROM documents WHERE project_id = ?1 AND is_active = 1 ORDER BY created_at, title"
+        };
+        let rows: Vec<(String, String, Option<String>, String)> = sqlx::query_as(sql)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+        let chapters = rows
+            .into_iter()
+            .map(|(id, title, content, document_type)| {
+                Ok(LintChapter {
+                    id: parse_uuid(&id)?,
+                    title,
+                    content: prosemirror::document_text(
+                        &document_type,
+                        content.as_deref().unwrap_or(""),
+                    ),
+                })
+            })
+            .collect::<DatabaseResult<Vec<_>>>()?;
+
+        let characters = Self::characters(&db, project_id, &tables).await?;
+
+        Ok(LintReport {
+            project_id,
+            packs: settings.enabled_packs.clone(),
+            documents_checked: chapters.len(),
+            issues: lint_chapters(&packs, &settings, &chapters, &characters),
+        })
+    }
+
+    /// Detect each scene's narrative person and tense, in binder order, and
+    /// flag where they differ from the declared voice or switch mid-scene
+    pub async fn check_narrative_voice(&self, project_id: Uuid) -> DatabaseResult<VoiceReport> {
+        let db_service = self.db_service.as_ref().ok_or_else(|| {
+            DatabaseError::Connection("Database service not initialized".to_string())
+        })?;
+        let db = db_service.read().await;
+        let tables = Self::tables(&db).await?;
+
+        let sql = if tables.iter().any(|t| t == "binder_order") {
+            "SELECT d.id, d.title, d.content, d.metadata FROM documents d LEFT JOIN binder_order b ON b.document_id = d.id
+             WHERE d.project_id = ?1 AND d.is_active = 1
+             ORDER BY b.position IS NULL, b.position, d.created_at, d.title"
+        } else {
+            "SELECT id, title, content, metadata FROM documents WHERE project_id = ?1 AND is_active = 1 ORDER BY created_at, title"
+        };
+        let rows: Vec<(String, String, Option<String>, Option<String>)> = sqlx::query_as(sql)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+        let documents = rows
+            .into_iter()
+            .map(|(id, title, content, metadata)| {
+                Ok(VoiceDocument {
+                    id: parse_uuid(&id)?,
+                    title,
+                    content: content.unwrap_or_default(),
+                    declared: metadata
+                        .and_then(|m| serde_json::from_str(&m).ok())
+                        .unwrap_or_default(),
+                })
+            })
+            .collect::<DatabaseResult<Vec<_>>>()?;
+        let characters = Self::characters(&db, project_id, &tables).await?;
+
+        let (scenes, issues) = check_voice(&documents, &characters);
+        Ok(VoiceReport {
+            project_id,
+            documents_checked: documents.len(),
+            scenes,
+            issues,
+        })
+    }
+
+    /// Declare a scene's POV character, person and tense in its document
+    /// metadata, keeping the metadata's other keys
+    pub async fn set_scene_voice(
+        &self,
+        document_id: Uuid,
+        voice: &SceneVoice,
+    ) -> DatabaseResult<()> {
+        let db_service = self.db_service.as_ref().ok_or_else(|| {
+            DatabaseError::Connection("Database service not initialized".to_string())
+        })?;
+        let db = db_service.read().await;
+        let metadata: Option<Option<String>> =
+            sqlx::query_scalar("SELECT metadata FROM documents WHERE id = ?1")
+                .bind(document_id.to_string())
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to load document: {}", e)))?;
+        let metadata = metadata.ok_or_else(|| DatabaseError::RecordNotFound {
+            entity: "document".to_string(),
+            id: document_id.to_string(),
+        })?;
+
+        let mut object = match metadata.and_then(|m| serde_json::from_str(&m).ok()) {
+            Some(serde_json::Value::Object(object)) => object,
+            _ => serde_json::Map::new(),
+        };
+        let voice = serde_json::to_value(voice)
+            .map_err(|e| DatabaseError::Service(format!("Failed to serialize voice: {}", e)))?;
+        if let serde_json::Value::Object(voice) = voice {
+            for (key, value) in voice {
+                if value.is_null() {
+                    object.remove(&key);
+                } else {
+                    object.insert(key, value);
+                }
+            }
+        }
+
+        sqlx::query("UPDATE documents SET metadata = ?1, updated_at = ?2 WHERE id = ?3")
+            .bind(serde_json::Value::Object(object).to_string())
+            .bind(Utc::now().to_rfc3339())
+            .bind(document_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save scene voice: {}", e)))?;
+        Ok(())
+    }
+
+    /// Readability scores and style measures of a document at a saved
+    /// version, or as it is now. Counts are cached per version, and a
+    /// version not analysed before only recounts the paragraphs that differ
+    /// from the nearest one that was.
+    pub async fn analyze_readability(
+        &self,
+        document_id: Uuid,
+        version: Option<u32>,
+    ) -> DatabaseResult<ReadabilityReport> {
+        let db_service = self.db_service.as_ref().ok_or_else(|| {
+            DatabaseError::Connection("Database service not initialized".to_string())
+        })?;
+        let db = db_service.read().await;
+        let (text, saved) = match version {
+            // Version ids are kept as written; some aren't hyphenated
+            Some(version) => {
+                let saved: Option<(String, String)> = sqlx::query_as(
+                    "SELECT id, content FROM document_versions
+                     WHERE document_id = ?1 AND version = ?2",
+                )
+                .bind(document_id.to_string())
+                .bind(version as i64)
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to load document version: {}", e))
+                })?;
+                let (id, content) = saved.ok_or_else(|| DatabaseError::RecordNotFound {
+                    entity: "document version".to_string(),
+                    id: format!("{}@{}", document_id, version),
+                })?;
+                (content, Some((id, version)))
+            }
+            None => {
+                let current: Option<(Option<String>, i64)> = sqlx::query_as(
+                    "SELECT content, version FROM documents WHERE id = ?1 AND is_active = 1",
+                )
+                .bind(document_id.to_string())
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to load document: {}", e)))?;
+                let (content, current_version) =
+                    current.ok_or_else(|| DatabaseError::RecordNotFound {
+                        entity: "document".to_string(),
+                        id: document_id.to_string(),
+                    })?;
+                let content = content.unwrap_or_default();
+                // Autosaves change the text without saving a version
+                let latest: Option<(String, i64, String)> = sqlx::query_as(
+                    "SELECT id, version, content FROM document_versions
+                     WHERE document_id = ?1 AND version = ?2",
+                )
+                .bind(document_id.to_string())
+                .bind(current_version)
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to load document version: {}", e))
+                })?;
+                let saved = latest
+                    .filter(|(_, _, saved)| *saved == content)
+                    .map(|(id, version, _)| (id, version as u32));
+                (content, saved)
+            }
+        };
+
+        if let Some((version_id, version)) = &saved {
+            let cached: Option<String> = sqlx::query_scalar(GET_READABILITY_CACHE_SQL)
+                .bind(version_id)
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to load readability cache: {}", e))
+                })?;
+            if let Some(counts) =
+                cached.and_then(|c| serde_json::from_str::<Vec<ParagraphCounts>>(&c).ok())
+            {
+                return Ok(build_report(document_id, Some(*version), &counts, 0));
+            }
+        }
+
+        let nearest_version = match (&saved, version) {
+            (Some((_, version)), _) => *version as i64,
+            (None, Some(version)) => version as i64,
+            (None, None) => i64::from(u32::MAX),
+        };
+        let nearest: Option<String> = sqlx::query_scalar(GET_NEAREST_READABILITY_CACHE_SQL)
+            .bind(document_id.to_string())
+            .bind(nearest_version)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to load readability cache: {}", e))
+            })?;
+        let mut known: HashMap<String, ParagraphCounts> = nearest
+            .and_then(|c| serde_json::from_str::<Vec<ParagraphCounts>>(&c).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|counts| (counts.hash.clone(), counts))
+            .collect();
+        let mut counted = 0;
+        let counts: Vec<ParagraphCounts> = paragraphs(&text)
+            .into_iter()
+            .map(|paragraph| match known.get(&paragraph_hash(paragraph)) {
+                Some(counts) => counts.clone(),
+                None => {
+                    counted += 1;
+                    let counts = count_paragraph(paragraph);
+                    known.insert(counts.hash.clone(), counts.clone());
+                    counts
+                }
+            })
+            .collect();
+
+        if let Some((version_id, version)) = &saved {
+            let json = serde_json::to_string(&counts).map_err(|e| {
+                DatabaseError::Service(format!("Failed to serialize readability counts: {}", e))
+            })?;
+            sqlx::query(UPSERT_READABILITY_CACHE_SQL)
+                .bind(version_id)
+                .bind(document_id.to_string())
+                .bind(*version as i64)
+                .bind(json)
+                .bind(Utc::now().to_rfc3339())
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to cache readability counts: {}", e))
+                })?;
+        }
+        Ok(build_report(
+            document_id,
+            saved.map(|(_, version)| version),
+            &counts,
+            counted,
+        ))
+    }
+
+    /// Which of the optional tables the checks read exist
+    async fn tables(db: &EnhancedDatabaseService) -> DatabaseResult<Vec<String>> {
+        sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name IN ('codex_entries', 'binder_order')",
+        )
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check schema: {}", e)))
+    }
+
+    /// The project's codex characters with all their names
+    async fn characters(
+        db: &EnhancedDatabaseService,
+        project_id: Uuid,
+        tables: &[String],
+    ) -> DatabaseResult<Vec<LintCharacter>> {
+        let mut characters = Vec::new();
+        if tables.iter().any(|t| t == "codex_entries") {
+            let rows: Vec<(String, Option<String>)> = sqlx::query_as(
+                "SELECT title, metadata FROM codex_entries
+                 WHERE project_id = ?1 AND entry_type = 'character_sheet' AND is_active = 1",
+            )
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load characters: {}", e)))?;
+            for (title, metadata) in rows {
+                let mut names = vec![title];
+                let data: Option<CharacterData> =
+                    metadata.and_then(|m| serde_json::from_str(&m).ok());
+                names.extend(data.map(|d| d.names).unwrap_or_default());
+                names.retain(|name| name.trim().chars().count() > 1);
+                if !names.is_empty() {
+                    characters.push(LintCharacter { names });
+                }
+            }
+        }
+        Ok(characters)
+    }
+
     /// Create a new analysis
     pub async fn create_analysis(
         &self,
@@ -410,7 +844,12 @@ impl AnalysisService {
             .await?
         {
             Some(awf) => awf,
-            None => return Err(DatabaseError::NotFound("Analysis not found".to_string())),
+            None => {
+                return Err(DatabaseError::RecordNotFound {
+                    entity: "analysis".to_string(),
+                    id: analysis_id.to_string(),
+                })
+            }
         };
 
         let mut summary = format!(
@@ -452,3 +891,81 @@ impl AnalysisService {
         Ok(summary)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_readability_cached_per_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Novel', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let document = Uuid::new_v4();
+        db.create_document(
+            document.to_string(),
+            project.to_string(),
+            "Storm".to_string(),
+            "The ship was taken by the storm.\nThe crew rowed hard.\nMara watched.".to_string(),
+        )
+        .await
+        .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let service = AnalysisService::with_database_service(db.clone());
+        service.initialize().await.unwrap();
+
+        let first = service.analyze_readability(document, None).await.unwrap();
+        assert_eq!(first.version, Some(1));
+        assert_eq!(first.paragraphs_counted, 3);
+        assert_eq!(first.passive_ratio, 1.0 / 3.0);
+        let again = service.analyze_readability(document, None).await.unwrap();
+        assert_eq!(again.paragraphs_counted, 0);
+        assert_eq!(again.word_count, first.word_count);
+
+        db.read()
+            .await
+            .update_document(
+                document.to_string(),
+                "Storm".to_string(),
+                "The ship was taken by the storm.\nThe crew rowed very hard.\nMara watched."
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+        let second = service.analyze_readability(document, None).await.unwrap();
+        assert_eq!(second.version, Some(2));
+        assert_eq!(second.paragraphs_counted, 1);
+        assert_eq!(second.adverb_density, 100.0 / 14.0);
+
+        db.read()
+            .await
+            .update_document_content(
+                &document.to_string(),
+                "The ship was taken by the storm.\nThe crew rowed very hard.\nMara slept.",
+            )
+            .await
+            .unwrap();
+        let autosaved = service.analyze_readability(document, None).await.unwrap();
+        assert_eq!(autosaved.version, None);
+        assert_eq!(autosaved.paragraphs_counted, 1);
+
+        let old = service
+            .analyze_readability(document, Some(1))
+            .await
+            .unwrap();
+        assert_eq!(old.paragraphs_counted, 0);
+        assert_eq!(old.word_count, first.word_count);
+    }
+}
@@ -0,0 +1,167 @@
+//! Annotation Service
+//!
+//! CRUD for passage-anchored comments on documents.
+
+use chrono::Utc;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{
+    models::annotation::*, DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+
+type AnnotationRow = (
+    String,
+    String,
+    String,
+    i64,
+    i64,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    bool,
+    String,
+    String,
+);
+
+/// Service for managing document annotations
+#[derive(Debug)]
+pub struct AnnotationService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl AnnotationService {
+    /// Create a new annotation service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize annotation tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_ANNOTATION_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create annotation tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Store a new annotation
+    pub async fn create_annotation(&self, annotation: &Annotation) -> DatabaseResult<()> {
+        if annotation.body.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Annotation body cannot be empty".to_string(),
+            ));
+        }
+        if annotation.end < annotation.start {
+            return Err(DatabaseError::ValidationError(
+                "Annotation end must not precede its start".to_string(),
+            ));
+        }
+
+        let db = self.db_service.read().await;
+        sqlx::query(INSERT_ANNOTATION_SQL)
+            .bind(annotation.id.to_string())
+            .bind(annotation.project_id.to_string())
+            .bind(annotation.document_id.to_string())
+            .bind(annotation.start as i64)
+            .bind(annotation.end as i64)
+            .bind(&annotation.quoted_text)
+            .bind(&annotation.body)
+            .bind(&annotation.author_name)
+            .bind(annotation.source.as_str())
+            .bind(annotation.reader_id.map(|id| id.to_string()))
+            .bind(&annotation.passage_anchor)
+            .bind(annotation.resolved)
+            .bind(annotation.created_at.to_rfc3339())
+            .bind(annotation.updated_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to create annotation: {}", e)))?;
+        Ok(())
+    }
+
+    /// Get all annotations on a document, in passage order
+    pub async fn get_annotations_for_document(
+        &self,
+        document_id: Uuid,
+    ) -> DatabaseResult<Vec<Annotation>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<AnnotationRow> = sqlx::query_as(GET_ANNOTATIONS_BY_DOCUMENT_SQL)
+            .bind(document_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get annotations: {}", e)))?;
+
+        rows.into_iter().map(annotation_from_row).collect()
+    }
+
+    /// Mark an annotation resolved or unresolved
+    pub async fn set_resolved(&self, annotation_id: Uuid, resolved: bool) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query(RESOLVE_ANNOTATION_SQL)
+            .bind(annotation_id.to_string())
+            .bind(resolved)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to update annotation: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete an annotation
+    pub async fn delete_annotation(&self, annotation_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query(DELETE_ANNOTATION_SQL)
+            .bind(annotation_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete annotation: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+fn annotation_from_row(row: AnnotationRow) -> DatabaseResult<Annotation> {
+    let (
+        id,
+        project_id,
+        document_id,
+        start,
+        end,
+        quoted_text,
+        body,
+        author_name,
+        source,
+        reader_id,
+        passage_anchor,
+        resolved,
+        created_at,
+        updated_at,
+    ) = row;
+
+    Ok(Annotation {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        document_id: parse_uuid(&document_id)?,
+        start: start.max(0) as usize,
+        end: end.max(0) as usize,
+        quoted_text,
+        body,
+        author_name,
+        source: AnnotationSource::parse(&source).ok_or_else(|| {
+            DatabaseError::Service(format!("Unknown annotation source: {}", source))
+        })?,
+        reader_id: reader_id.as_deref().map(parse_uuid).transpose()?,
+        passage_anchor,
+        resolved,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
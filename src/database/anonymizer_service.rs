@@ -0,0 +1,549 @@
+//! Anonymizer Service
+//!
+//! Copies a project into a new one that can be shared without sharing the
+//! manuscript. Every run of letters becomes a made-up word of the same
+//! length and case and every run of digits other digits, while whitespace,
+//! punctuation, Markdown and HTML tags stay, so word counts, headings,
+//! paragraphs and the binder order survive. Editor documents have only
+//! their text nodes rewritten, so the ProseMirror structure around them is
+//! copied as it was. A word gets the same
+//! replacement everywhere in a copy, which keeps codex names consistent
+//! between entries and the documents that mention them; each copy uses a
+//! fresh salt so replacements can't be matched up across copies. Document
+//! and codex metadata (tags, synopses, character sheets) is not copied.
+
+use chrono::Utc;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::document_structure_service::{order_of, table_exists};
+use crate::database::models::anonymizer::{AnonymizeRequest, AnonymizedProject};
+use crate::database::models::document_structure::INSERT_BINDER_POSITION_SQL;
+use crate::database::prosemirror;
+use crate::database::{DatabaseError, DatabaseResult, EnhancedDatabaseService};
+
+/// id, title, content, document_type, word_count, created_at, updated_at, version
+type DocumentRow = (
+    String,
+    String,
+    Option<String>,
+    String,
+    i64,
+    String,
+    String,
+    i64,
+);
+/// id, entry_type, title, content, status, created_at, updated_at, sort_order
+type CodexRow = (String, String, String, String, String, String, String, i64);
+
+const DEFAULT_NAME: &str = "Sample project";
+const CONSONANTS: &[u8] = b"bcdfghklmnprstvz";
+const VOWELS: &[u8] = b"aeiou";
+/// Attempts at a replacement no other word uses before accepting a clash;
+/// short words run out of distinct replacements quickly
+const MAX_ATTEMPTS: u64 = 16;
+
+/// Service for anonymized sample copies of projects
+#[derive(Debug)]
+pub struct AnonymizerService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl AnonymizerService {
+    /// Create a new anonymizer service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Copy a project with its prose and names replaced
+    pub async fn anonymize(&self, request: &AnonymizeRequest) -> DatabaseResult<AnonymizedProject> {
+        let name = request
+            .name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(DEFAULT_NAME)
+            .to_string();
+        let source = request.project_id.to_string();
+
+        let db = self.db_service.read().await;
+        let mut tx =
+            db.pool.begin().await.map_err(|e| {
+                DatabaseError::Service(format!("Failed to start transaction: {}", e))
+            })?;
+
+        let exists: Option<String> = sqlx::query_scalar("SELECT id FROM projects WHERE id = ?1")
+            .bind(&source)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load project: {}", e)))?;
+        if exists.is_none() {
+            return Err(DatabaseError::RecordNotFound {
+                entity: "project".to_string(),
+                id: request.project_id.to_string(),
+            });
+        }
+
+        let documents: Vec<DocumentRow> = sqlx::query_as(
+            "SELECT id, title, content, document_type, word_count, created_at, updated_at, version
+             FROM documents WHERE project_id = ?1 AND is_active = 1",
+        )
+        .bind(&source)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+
+        let codex: Vec<CodexRow> = if request.include_codex
+            && table_exists(&mut tx, "codex_entries").await?
+        {
+            sqlx::query_as(
+                "SELECT id, entry_type, title, COALESCE(content, ''), status, created_at,
+                            updated_at, sort_order
+                     FROM codex_entries WHERE project_id = ?1 AND is_active = 1",
+            )
+            .bind(&source)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load codex entries: {}", e)))?
+        } else {
+            Vec::new()
+        };
+
+        let binder = if table_exists(&mut tx, "binder_order").await? {
+            order_of(&mut tx, request.project_id).await?
+        } else {
+            Vec::new()
+        };
+
+        let mut anonymizer = Anonymizer::new(rand::random());
+        let names: HashSet<String> = codex
+            .iter()
+            .flat_map(|row| words(&row.2))
+            .map(|word| word.to_lowercase())
+            .collect();
+
+        let project_id = Uuid::new_v4();
+        let now = Utc::now().to_rfc3339();
+        sqlx::query(
+            "INSERT INTO projects (id, name, description, created_at, updated_at, is_archived, is_active, settings)
+             VALUES (?1, ?2, NULL, ?3, ?3, 0, 0, NULL)",
+        )
+        .bind(project_id.to_string())
+        .bind(&name)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to create project: {}", e)))?;
+
+        let mut copied: HashMap<String, Uuid> = HashMap::new();
+        for (id, title, content, document_type, word_count, created_at, updated_at, version) in
+            &documents
+        {
+            let new_id = Uuid::new_v4();
+            let content = content
+                .as_deref()
+                .map(|content| anonymizer.document(document_type, content));
+            sqlx::query(
+                "INSERT INTO documents (id, project_id, title, content, plain_text, document_type,
+                                        word_count, checksum, created_at, updated_at, is_active,
+                                        version, metadata)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 1, ?11, NULL)",
+            )
+            .bind(new_id.to_string())
+            .bind(project_id.to_string())
+            .bind(anonymizer.text(title))
+            .bind(&content)
+            .bind(
+                content
+                    .as_deref()
+                    .and_then(|content| prosemirror::search_text(document_type, content)),
+            )
+            .bind(document_type)
+            .bind(word_count)
+            .bind(EnhancedDatabaseService::calculate_checksum(
+                content.as_deref().unwrap_or(""),
+            ))
+            .bind(created_at)
+            .bind(updated_at)
+            .bind(version)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to copy document: {}", e)))?;
+            copied.insert(id.clone(), new_id);
+        }
+
+        let order = binder.iter().filter_map(|id| copied.get(&id.to_string()));
+        for (position, id) in order.enumerate() {
+            sqlx::query(INSERT_BINDER_POSITION_SQL)
+                .bind(id.to_string())
+                .bind(project_id.to_string())
+                .bind(position as i64)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to copy binder order: {}", e))
+                })?;
+        }
+
+        for (_, entry_type, title, content, status, created_at, updated_at, sort_order) in &codex {
+            sqlx::query(
+                "INSERT INTO codex_entries (id, project_id, entry_type, title, content, status,
+                                            created_at, updated_at, is_active, metadata, sort_order)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1, '', ?9)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(project_id.to_string())
+            .bind(entry_type)
+            .bind(anonymizer.text(title))
+            .bind(anonymizer.text(content))
+            .bind(status)
+            .bind(created_at)
+            .bind(updated_at)
+            .bind(sort_order)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to copy codex entry: {}", e)))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to commit copy: {}", e)))?;
+
+        log::info!(
+            "Created anonymized copy {} of project {}",
+            project_id,
+            request.project_id
+        );
+        Ok(AnonymizedProject {
+            source_project_id: request.project_id,
+            project_id,
+            name,
+            documents: documents.len(),
+            codex_entries: codex.len(),
+            words: anonymizer.words,
+            names: names.len(),
+        })
+    }
+}
+
+/// Replaces words with made-up words of the same shape, consistently
+#[derive(Debug)]
+pub struct Anonymizer {
+    salt: u64,
+    /// Lowercased word to lowercase replacement
+    replacements: HashMap<String, String>,
+    taken: HashSet<String>,
+    /// Words replaced so far
+    pub words: usize,
+}
+
+impl Anonymizer {
+    pub fn new(salt: u64) -> Self {
+        Self {
+            salt,
+            replacements: HashMap::new(),
+            taken: HashSet::new(),
+            words: 0,
+        }
+    }
+
+    /// A stored document with its words replaced: the text nodes of a
+    /// ProseMirror document, or all of any other document's text
+    pub fn document(&mut self, document_type: &str, content: &str) -> String {
+        let Some(mut doc) = prosemirror::parse(document_type, content) else {
+            return self.text(content);
+        };
+        for (_, value) in prosemirror::text_nodes_mut(&mut doc).1 {
+            *value = self.text(value);
+        }
+        doc.to_string()
+    }
+
+    /// `text` with every word and number replaced; everything else,
+    /// including HTML tag and attribute names, is kept
+    pub fn text(&mut self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            if c == '<'
+                && rest[1..].starts_with(|n: char| n.is_ascii_alphabetic() || n == '/' || n == '!')
+            {
+                if let Some(end) = rest.find('>') {
+                    self.tag(&rest[..=end], &mut out);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+            let run = run_length(rest);
+            if run > 0 {
+                out.push_str(&self.word(&rest[..run]));
+                rest = &rest[run..];
+            } else {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        out
+    }
+
+    /// Keep a tag's structure but replace quoted attribute values
+    fn tag(&mut self, tag: &str, out: &mut String) {
+        let mut rest = tag;
+        while let Some(c) = rest.chars().next() {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+            if c == '"' || c == '\'' {
+                if let Some(end) = rest.find(c) {
+                    out.push_str(&self.text(&rest[..end]));
+                    out.push(c);
+                    rest = &rest[end + 1..];
+                }
+            }
+        }
+    }
+
+    /// Replacement for one run of letters or digits, in the same case
+    fn word(&mut self, word: &str) -> String {
+        let digits = word.starts_with(|c: char| c.is_ascii_digit());
+        if !digits {
+            self.words += 1;
+        }
+        let key = word.to_lowercase();
+        let replacement = match self.replacements.get(&key) {
+            Some(replacement) => replacement.clone(),
+            None => {
+                let replacement = self.invent(&key, word.chars().count(), digits);
+                self.taken.insert(replacement.clone());
+                self.replacements.insert(key, replacement.clone());
+                replacement
+            }
+        };
+        word.chars()
+            .zip(replacement.chars())
+            .map(|(original, new)| {
+                if original.is_uppercase() {
+                    new.to_ascii_uppercase()
+                } else {
+                    new
+                }
+            })
+            .collect()
+    }
+
+    fn invent(&self, key: &str, length: usize, digits: bool) -> String {
+        let mut candidate = String::new();
+        for attempt in 0..MAX_ATTEMPTS {
+            let mut hasher = DefaultHasher::new();
+            (self.salt, key, attempt).hash(&mut hasher);
+            let mut state = hasher.finish();
+            let mut consonant = state & 1 == 0;
+            candidate = (0..length)
+                .map(|_| {
+                    let roll = next(&mut state) as usize;
+                    let c = if digits {
+                        (b'0' + (roll % 10) as u8) as char
+                    } else if consonant {
+                        CONSONANTS[roll % CONSONANTS.len()] as char
+                    } else {
+                        VOWELS[roll % VOWELS.len()] as char
+                    };
+                    consonant = !consonant;
+                    c
+                })
+                .collect();
+            if digits || (candidate != key && !self.taken.contains(&candidate)) {
+                break;
+            }
+        }
+        candidate
+    }
+}
+
+/// Length in bytes of the run of letters, or of digits, `text` starts with
+fn run_length(text: &str) -> usize {
+    let Some(first) = text.chars().next() else {
+        return 0;
+    };
+    let same: fn(char) -> bool = if first.is_alphabetic() {
+        char::is_alphabetic
+    } else if first.is_ascii_digit() {
+        |c| c.is_ascii_digit()
+    } else {
+        return 0;
+    };
+    text.char_indices()
+        .find(|(_, c)| !same(*c))
+        .map(|(i, _)| i)
+        .unwrap_or(text.len())
+}
+
+/// The runs of letters in `text`
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+}
+
+/// splitmix64
+fn next(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[test]
+    fn test_replaces_words_keeping_shape_and_consistency() {
+        let mut anonymizer = Anonymizer::new(7);
+        let text = "# Mara's Return\n\nMARA met mara in 1987.\n<p class=\"Mara\">Hi</p>";
+        let out = anonymizer.text(text);
+
+        assert_eq!(out.chars().count(), text.chars().count());
+        assert_eq!(
+            out.split_whitespace().count(),
+            text.split_whitespace().count()
+        );
+        assert!(!out.to_lowercase().contains("mara"));
+        assert!(!out.contains("1987"));
+        assert!(out.starts_with("# "));
+        assert!(out.contains("<p class=\""));
+        assert!(out.ends_with("</p>"));
+
+        let mara = &out[2..6];
+        assert!(mara.starts_with(|c: char| c.is_ascii_uppercase()));
+        assert!(out.contains(&format!("{}'", mara)));
+        assert!(out.contains(&mara.to_uppercase()));
+        assert!(out.contains(&format!(" {} ", mara.to_lowercase())));
+        assert!(out.contains(&format!("class=\"{}\"", mara)));
+        // "Hi" is the only other word with two letters
+        assert_eq!(anonymizer.words, 9);
+    }
+
+    #[test]
+    fn test_editor_documents_keep_their_structure() {
+        let mut anonymizer = Anonymizer::new(7);
+        let doc = serde_json::json!({
+            "type": "doc",
+            "content": [
+                {"type": "heading", "attrs": {"level": 1}, "content": [{"type": "text", "text": "Mara"}]},
+                {"type": "paragraph", "content": [
+                    {"type": "text", "marks": [{"type": "bold"}], "text": "Mara ran."}
+                ]}
+            ]
+        });
+        let out: serde_json::Value =
+            serde_json::from_str(&anonymizer.document("json", &doc.to_string())).unwrap();
+
+        assert_eq!(out["content"][0]["type"], "heading");
+        assert_eq!(out["content"][0]["attrs"]["level"], 1);
+        assert_eq!(out["content"][1]["content"][0]["marks"][0]["type"], "bold");
+        let name = out["content"][0]["content"][0]["text"].as_str().unwrap();
+        assert_ne!(name, "Mara");
+        let text = prosemirror::plain_text(&out);
+        assert!(text.starts_with(&format!("{}\n{} ", name, name)));
+        assert_eq!(anonymizer.words, 3);
+    }
+
+    #[tokio::test]
+    async fn test_copies_project_with_binder_and_codex() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Novel', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "CREATE TABLE codex_entries (id TEXT PRIMARY KEY, project_id TEXT, entry_type TEXT,
+             title TEXT, content TEXT, status TEXT, created_at TEXT, updated_at TEXT,
+             is_active INTEGER, metadata TEXT, sort_order INTEGER)",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO codex_entries VALUES ('c1', ?1, 'character_sheet', 'Mara Voss',
+             'Captain of the Heron.', 'draft', ?2, ?2, 1, '{\"age\": 40}', 0)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let (one, two) = (Uuid::new_v4(), Uuid::new_v4());
+        for (id, title, content) in [
+            (one, "Arrival", "Mara Voss came aboard."),
+            (two, "Departure", "The Heron left without Mara."),
+        ] {
+            db.create_document(
+                id.to_string(),
+                project.to_string(),
+                title.to_string(),
+                content.to_string(),
+            )
+            .await
+            .unwrap();
+        }
+        let db = Arc::new(RwLock::new(db));
+        let structure = crate::database::DocumentStructureService::new(Arc::clone(&db));
+        structure.initialize().await.unwrap();
+        structure
+            .set_binder_order(project, &[two, one])
+            .await
+            .unwrap();
+
+        let service = AnonymizerService::new(Arc::clone(&db));
+        let copy = service
+            .anonymize(&AnonymizeRequest {
+                project_id: project,
+                name: None,
+                include_codex: true,
+            })
+            .await
+            .unwrap();
+        assert_eq!(copy.name, DEFAULT_NAME);
+        assert_eq!((copy.documents, copy.codex_entries, copy.names), (2, 1, 2));
+
+        let order = structure.binder_order(copy.project_id).await.unwrap();
+        assert_eq!(order.len(), 2);
+        let pool = db.read().await.pool.clone();
+        let mut contents = Vec::new();
+        for id in &order {
+            let (content, word_count): (String, i64) =
+                sqlx::query_as("SELECT content, word_count FROM documents WHERE id = ?1")
+                    .bind(id.to_string())
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap();
+            assert_eq!(word_count as usize, content.split_whitespace().count());
+            contents.push(content);
+        }
+        // Binder order kept: "Departure" first
+        assert_eq!(contents[0].len(), "The Heron left without Mara.".len());
+        let (title, metadata): (String, String) =
+            sqlx::query_as("SELECT title, metadata FROM codex_entries WHERE project_id = ?1")
+                .bind(copy.project_id.to_string())
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(metadata, "");
+        assert_ne!(title, "Mara Voss");
+        assert!(contents[1].starts_with(&format!("{} ", title)));
+    }
+}
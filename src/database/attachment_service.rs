@@ -0,0 +1,574 @@
+//! Attachment Service
+//!
+//! Links files to documents and codex entries. File content goes into the
+//! asset store, so attaching the same file twice stores it once; a blob is
+//! removed when the last attachment referring to it is.
+
+use chrono::Utc;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::asset_store::AssetStore;
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{
+    models::attachment::*, DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+use crate::image_metadata;
+use crate::publishing::PublishedAttachment;
+use crate::security::events::{
+    SecurityEvent, SecurityEventKind, SecurityEventLog, SecurityEventSeverity,
+};
+use crate::settings;
+use crate::thumbnails::Thumbnailer;
+
+/// Bytes read from the start of a file to detect its type
+const SNIFF_LEN: usize = 64;
+/// Image types whose metadata can be stripped on import
+const STRIPPABLE_TYPES: &[&str] = &["image/jpeg", "image/png", "image/webp"];
+
+type AttachmentRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    i64,
+    String,
+    Option<String>,
+    String,
+    String,
+);
+
+/// Service for files attached to documents and codex entries
+pub struct AttachmentService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    store: AssetStore,
+    thumbnails: Option<Arc<Thumbnailer>>,
+    /// Set when image metadata is stripped on import; stripping is logged here
+    security_events: Option<Arc<SecurityEventLog>>,
+}
+
+impl std::fmt::Debug for AttachmentService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AttachmentService")
+            .field("store", &self.store)
+            .field("thumbnails", &self.thumbnails)
+            .field("strips_metadata", &self.security_events.is_some())
+            .finish()
+    }
+}
+
+impl AttachmentService {
+    /// Create a new attachment service storing content in `store`
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>, store: AssetStore) -> Self {
+        Self {
+            db_service,
+            store,
+            thumbnails: None,
+            security_events: None,
+        }
+    }
+
+    /// Strip image metadata on import as set in the privacy controls,
+    /// recording each removal in `events`
+    pub fn with_metadata_stripping(mut self, events: Arc<SecurityEventLog>) -> Self {
+        self.security_events = Some(events);
+        self
+    }
+
+    /// Generate thumbnails in the background when images are attached
+    pub fn with_thumbnails(mut self, thumbnails: Arc<Thumbnailer>) -> Self {
+        self.thumbnails = Some(thumbnails);
+        self
+    }
+
+    /// Initialize attachment tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_ATTACHMENT_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create attachment tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Copy the file at `path` into the store and attach it to an owner
+    pub async fn attach_file(
+        &self,
+        project_id: Uuid,
+        owner_kind: AttachmentOwner,
+        owner_id: Uuid,
+        path: &Path,
+    ) -> DatabaseResult<Attachment> {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| {
+                DatabaseError::ValidationError(format!("Not a file: {}", path.display()))
+            })?;
+
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        std::fs::File::open(path)
+            .and_then(|file| file.take(SNIFF_LEN as u64).read_to_end(&mut head))
+            .map_err(|e| DatabaseError::Service(format!("Failed to read attachment: {}", e)))?;
+        let mime_type = detect_mime_type(&file_name, &head);
+        if self.security_events.is_some() && STRIPPABLE_TYPES.contains(&mime_type) {
+            let bytes = tokio::fs::read(path)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to read attachment: {}", e)))?;
+            return self
+                .attach_bytes(project_id, owner_kind, owner_id, &file_name, &bytes)
+                .await;
+        }
+
+        let store = self.store.clone();
+        let source = path.to_path_buf();
+        let stored = tokio::task::spawn_blocking(move || store.put_file(&source))
+            .await
+            .map_err(|e| DatabaseError::Service(e.to_string()))?
+            .map_err(|e| DatabaseError::Service(format!("Failed to store attachment: {}", e)))?;
+
+        self.insert(
+            project_id,
+            owner_kind,
+            owner_id,
+            file_name,
+            mime_type,
+            stored.size,
+            stored.hash,
+        )
+        .await
+    }
+
+    /// Attach in-memory content under `file_name`
+    pub async fn attach_bytes(
+        &self,
+        project_id: Uuid,
+        owner_kind: AttachmentOwner,
+        owner_id: Uuid,
+        file_name: &str,
+        bytes: &[u8],
+    ) -> DatabaseResult<Attachment> {
+        if file_name.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Attachment name cannot be empty".to_string(),
+            ));
+        }
+        let mime_type = detect_mime_type(file_name, &bytes[..bytes.len().min(SNIFF_LEN)]);
+        let bytes = self.strip_image_metadata(file_name, mime_type, bytes);
+        let stored = self
+            .store
+            .put_bytes(&bytes)
+            .map_err(|e| DatabaseError::Service(format!("Failed to store attachment: {}", e)))?;
+
+        self.insert(
+            project_id,
+            owner_kind,
+            owner_id,
+            file_name.to_string(),
+            mime_type,
+            stored.size,
+            stored.hash,
+        )
+        .await
+    }
+
+    /// Remove location and camera details from photos and log what was
+    /// removed. Other files are returned unchanged.
+    fn strip_image_metadata<'a>(
+        &self,
+        file_name: &str,
+        mime_type: &str,
+        bytes: &'a [u8],
+    ) -> Cow<'a, [u8]> {
+        let Some(events) = &self.security_events else {
+            return Cow::Borrowed(bytes);
+        };
+        if !STRIPPABLE_TYPES.contains(&mime_type) {
+            return Cow::Borrowed(bytes);
+        }
+        let controls = settings::load_settings().privacy.unwrap_or_default();
+        let (stripped, report) = image_metadata::strip_metadata(bytes, &controls);
+        if report.is_empty() {
+            return Cow::Borrowed(bytes);
+        }
+
+        let description = format!("Removed {} from {}", report.removed.join(", "), file_name);
+        log::info!("{}", description);
+        events.record(SecurityEvent::new(
+            SecurityEventKind::MetadataStripped,
+            SecurityEventSeverity::Info,
+            "attachment_import",
+            description,
+        ));
+        Cow::Owned(stripped)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn insert(
+        &self,
+        project_id: Uuid,
+        owner_kind: AttachmentOwner,
+        owner_id: Uuid,
+        file_name: String,
+        mime_type: &str,
+        size: u64,
+        asset_hash: String,
+    ) -> DatabaseResult<Attachment> {
+        let attachment = Attachment {
+            id: Uuid::new_v4(),
+            project_id,
+            owner_kind,
+            owner_id,
+            file_name,
+            mime_type: mime_type.to_string(),
+            preview: PreviewKind::from_mime_type(mime_type),
+            size,
+            asset_hash,
+            description: None,
+            export_inclusion: ExportInclusion::default(),
+            created_at: Utc::now(),
+        };
+
+        let db = self.db_service.read().await;
+        sqlx::query(INSERT_ATTACHMENT_SQL)
+            .bind(attachment.id.to_string())
+            .bind(attachment.project_id.to_string())
+            .bind(attachment.owner_kind.as_str())
+            .bind(attachment.owner_id.to_string())
+            .bind(&attachment.file_name)
+            .bind(&attachment.mime_type)
+            .bind(attachment.preview.as_str())
+            .bind(attachment.size as i64)
+            .bind(&attachment.asset_hash)
+            .bind(&attachment.description)
+            .bind(attachment.export_inclusion.as_str())
+            .bind(attachment.created_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to create attachment: {}", e)))?;
+
+        if let Some(thumbnails) = self
+            .thumbnails
+            .clone()
+            .filter(|_| attachment.preview == PreviewKind::Image)
+        {
+            let hash = attachment.asset_hash.clone();
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = thumbnails.generate_all(&hash) {
+                    log::warn!("Failed to generate thumbnails for {}: {}", hash, e);
+                }
+            });
+        }
+        Ok(attachment)
+    }
+
+    /// Attachments of one document or codex entry, oldest first
+    pub async fn list_for_owner(
+        &self,
+        owner_kind: AttachmentOwner,
+        owner_id: Uuid,
+    ) -> DatabaseResult<Vec<Attachment>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<AttachmentRow> = sqlx::query_as(GET_ATTACHMENTS_BY_OWNER_SQL)
+            .bind(owner_kind.as_str())
+            .bind(owner_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get attachments: {}", e)))?;
+
+        rows.into_iter().map(attachment_from_row).collect()
+    }
+
+    /// Get an attachment by id
+    pub async fn get(&self, attachment_id: Uuid) -> DatabaseResult<Option<Attachment>> {
+        let db = self.db_service.read().await;
+        let row: Option<AttachmentRow> = sqlx::query_as(GET_ATTACHMENT_SQL)
+            .bind(attachment_id.to_string())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get attachment: {}", e)))?;
+
+        row.map(attachment_from_row).transpose()
+    }
+
+    /// Change the description and export handling of an attachment
+    pub async fn update(
+        &self,
+        attachment_id: Uuid,
+        description: Option<&str>,
+        export_inclusion: ExportInclusion,
+    ) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query(UPDATE_ATTACHMENT_SQL)
+            .bind(attachment_id.to_string())
+            .bind(description.map(str::trim).filter(|d| !d.is_empty()))
+            .bind(export_inclusion.as_str())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to update attachment: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Content of an attachment
+    pub fn read_content(&self, attachment: &Attachment) -> DatabaseResult<Vec<u8>> {
+        self.store
+            .read(&attachment.asset_hash)
+            .map_err(|e| DatabaseError::Service(format!("Failed to read attachment: {}", e)))
+    }
+
+    /// Delete an attachment, and its content if nothing else refers to it
+    pub async fn delete(&self, attachment_id: Uuid) -> DatabaseResult<bool> {
+        let Some(attachment) = self.get(attachment_id).await? else {
+            return Ok(false);
+        };
+        {
+            let db = self.db_service.read().await;
+            sqlx::query(DELETE_ATTACHMENT_SQL)
+                .bind(attachment_id.to_string())
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to delete attachment: {}", e))
+                })?;
+        }
+        self.collect_garbage(&[attachment.asset_hash]).await?;
+        Ok(true)
+    }
+
+    /// Delete every attachment of an owner that is being deleted
+    pub async fn delete_for_owner(
+        &self,
+        owner_kind: AttachmentOwner,
+        owner_id: Uuid,
+    ) -> DatabaseResult<usize> {
+        let attachments = self.list_for_owner(owner_kind, owner_id).await?;
+        for attachment in &attachments {
+            self.delete(attachment.id).await?;
+        }
+        Ok(attachments.len())
+    }
+
+    /// Remove attachments whose owner was deleted and content no attachment
+    /// refers to. Returns the number of attachments removed.
+    pub async fn cleanup_orphans(&self) -> DatabaseResult<u64> {
+        let removed = {
+            let db = self.db_service.read().await;
+            let mut removed = sqlx::query(DELETE_ORPHANED_DOCUMENT_ATTACHMENTS_SQL)
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to clean up attachments: {}", e))
+                })?
+                .rows_affected();
+
+            // Codex and lexicon tables are created by their own services,
+            // which may not have run against this database
+            for (table, sql) in [
+                ("codex_entries", DELETE_ORPHANED_CODEX_ATTACHMENTS_SQL),
+                ("lexicon_entries", DELETE_ORPHANED_LEXICON_ATTACHMENTS_SQL),
+            ] {
+                let has_table: Option<(String,)> = sqlx::query_as(
+                    "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                )
+                .bind(table)
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(e.to_string()))?;
+                if has_table.is_some() {
+                    removed += sqlx::query(sql)
+                        .execute(&db.pool)
+                        .await
+                        .map_err(|e| {
+                            DatabaseError::Service(format!("Failed to clean up attachments: {}", e))
+                        })?
+                        .rows_affected();
+                }
+            }
+            removed
+        };
+
+        let referenced = self.referenced_hashes().await?;
+        let blobs = self
+            .store
+            .retain(|hash| referenced.contains(hash))
+            .map_err(|e| DatabaseError::Service(format!("Failed to clean up assets: {}", e)))?;
+        if removed > 0 || blobs > 0 {
+            log::info!(
+                "Removed {} orphaned attachments and {} unused assets",
+                removed,
+                blobs
+            );
+        }
+        Ok(removed)
+    }
+
+    /// Attachments of an owner that exports should include, with content
+    /// loaded for those marked to embed
+    pub async fn published_attachments(
+        &self,
+        owner_kind: AttachmentOwner,
+        owner_id: Uuid,
+    ) -> DatabaseResult<Vec<PublishedAttachment>> {
+        let mut published = Vec::new();
+        for attachment in self.list_for_owner(owner_kind, owner_id).await? {
+            let data = match attachment.export_inclusion {
+                ExportInclusion::Exclude => continue,
+                ExportInclusion::List => None,
+                ExportInclusion::Embed => Some(self.read_content(&attachment)?),
+            };
+            published.push(PublishedAttachment {
+                file_name: attachment.file_name,
+                mime_type: attachment.mime_type,
+                description: attachment.description,
+                data,
+            });
+        }
+        Ok(published)
+    }
+
+    async fn referenced_hashes(&self) -> DatabaseResult<HashSet<String>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT DISTINCT asset_hash FROM attachments")
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to list assets: {}", e)))?;
+        Ok(rows.into_iter().map(|(hash,)| hash).collect())
+    }
+
+    /// Delete the blobs in `hashes` that no attachment refers to any more
+    async fn collect_garbage(&self, hashes: &[String]) -> DatabaseResult<()> {
+        let referenced = self.referenced_hashes().await?;
+        for hash in hashes.iter().filter(|hash| !referenced.contains(*hash)) {
+            if let Some(path) = self.store.path(hash) {
+                std::fs::remove_file(path).map_err(|e| {
+                    DatabaseError::Service(format!("Failed to remove asset: {}", e))
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn attachment_from_row(row: AttachmentRow) -> DatabaseResult<Attachment> {
+    let (
+        id,
+        project_id,
+        owner_kind,
+        owner_id,
+        file_name,
+        mime_type,
+        preview,
+        size,
+        asset_hash,
+        description,
+        export_inclusion,
+        created_at,
+    ) = row;
+
+    Ok(Attachment {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        owner_kind: AttachmentOwner::parse(&owner_kind).ok_or_else(|| {
+            DatabaseError::Service(format!("Unknown attachment owner: {}", owner_kind))
+        })?,
+        owner_id: parse_uuid(&owner_id)?,
+        file_name,
+        preview: PreviewKind::parse(&preview)
+            .unwrap_or_else(|| PreviewKind::from_mime_type(&mime_type)),
+        mime_type,
+        size: size.max(0) as u64,
+        asset_hash,
+        description,
+        export_inclusion: ExportInclusion::parse(&export_inclusion).unwrap_or_default(),
+        created_at: parse_time(&created_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_shared_content_survives_until_last_attachment_is_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Novel', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let service = AttachmentService::new(
+            Arc::new(RwLock::new(db)),
+            AssetStore::new(&dir.path().join("assets")),
+        );
+        service.initialize().await.unwrap();
+
+        let chapter = Uuid::new_v4();
+        let entry = Uuid::new_v4();
+        let map = service
+            .attach_bytes(
+                project,
+                AttachmentOwner::Document,
+                chapter,
+                "map.png",
+                b"\x89PNG\r\n\x1a\nmap",
+            )
+            .await
+            .unwrap();
+        let copy = service
+            .attach_bytes(
+                project,
+                AttachmentOwner::CodexEntry,
+                entry,
+                "map.png",
+                b"\x89PNG\r\n\x1a\nmap",
+            )
+            .await
+            .unwrap();
+        assert_eq!(map.preview, PreviewKind::Image);
+        assert_eq!(map.asset_hash, copy.asset_hash);
+
+        service
+            .update(map.id, Some("Harbour district"), ExportInclusion::Embed)
+            .await
+            .unwrap();
+        let published = service
+            .published_attachments(AttachmentOwner::Document, chapter)
+            .await
+            .unwrap();
+        assert_eq!(
+            published[0].description.as_deref(),
+            Some("Harbour district")
+        );
+        assert!(published[0].data.is_some());
+
+        assert_eq!(
+            service
+                .delete_for_owner(AttachmentOwner::Document, chapter)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(service.store.contains(&copy.asset_hash));
+        assert!(service.delete(copy.id).await.unwrap());
+        assert!(!service.store.contains(&copy.asset_hash));
+    }
+}
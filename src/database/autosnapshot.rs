@@ -0,0 +1,184 @@
+//! Automatic document snapshots
+//!
+//! Autosave writes a document's content without making a version, so the
+//! version history only grows on real saves. Snapshots fill the gaps: on
+//! the cadence set in [`DatabaseConfig`](super::DatabaseConfig), autosaved
+//! content is kept as a version described as [`AUTOSNAPSHOT_DESCRIPTION`],
+//! and older snapshots are thinned out to one an hour, then one a day.
+//! Versions saved any other way are never pruned.
+
+use chrono::{DateTime, Duration as ChronoDuration, Timelike, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// `change_description` of versions made by autosnapshots
+pub const AUTOSNAPSHOT_DESCRIPTION: &str = "Automatic snapshot";
+
+/// When autosaved changes become a version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotCadence {
+    /// Never; only saves make versions
+    Disabled,
+    /// On the first autosave at least this long after the last snapshot
+    Interval(Duration),
+    /// On every Nth autosave
+    Changes(u32),
+}
+
+/// How long snapshots are kept, by age
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotRetention {
+    /// Every snapshot younger than this is kept
+    pub keep_all_for: Duration,
+    /// After that, the newest snapshot in each hour is kept until this age
+    pub hourly_for: Duration,
+    /// After that, the newest snapshot in each day is kept until this age;
+    /// older snapshots are removed
+    pub daily_for: Duration,
+}
+
+impl Default for SnapshotRetention {
+    fn default() -> Self {
+        Self {
+            keep_all_for: Duration::from_secs(60 * 60),
+            hourly_for: Duration::from_secs(24 * 60 * 60),
+            daily_for: Duration::from_secs(30 * 24 * 60 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoSnapshotPolicy {
+    pub cadence: SnapshotCadence,
+    pub retention: SnapshotRetention,
+}
+
+impl Default for AutoSnapshotPolicy {
+    fn default() -> Self {
+        Self {
+            cadence: SnapshotCadence::Interval(Duration::from_secs(5 * 60)),
+            retention: SnapshotRetention::default(),
+        }
+    }
+}
+
+/// Autosaves since each document's last snapshot
+#[derive(Debug)]
+struct Pending {
+    changes: u32,
+    since: Instant,
+}
+
+/// Counts autosaves per document; shared by clones of the database service
+/// so every autosave path counts toward the same cadence
+#[derive(Debug, Default)]
+pub(crate) struct AutoSnapshots {
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+impl AutoSnapshots {
+    /// Count an autosave of `document_id`; true when it should be
+    /// snapshotted now
+    pub(crate) fn record_change(&self, document_id: &str, cadence: SnapshotCadence) -> bool {
+        self.record_change_at(document_id, cadence, Instant::now())
+    }
+
+    fn record_change_at(&self, document_id: &str, cadence: SnapshotCadence, now: Instant) -> bool {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = pending.entry(document_id.to_string()).or_insert(Pending {
+            changes: 0,
+            since: now,
+        });
+        entry.changes += 1;
+        let due = match cadence {
+            SnapshotCadence::Disabled => false,
+            SnapshotCadence::Interval(interval) => now.duration_since(entry.since) >= interval,
+            SnapshotCadence::Changes(n) => entry.changes >= n.max(1),
+        };
+        if due {
+            pending.remove(document_id);
+        }
+        due
+    }
+}
+
+/// Versions of the snapshots in `snapshots` (version, created) that
+/// `retention` no longer keeps
+pub fn expired_snapshots(
+    snapshots: &[(u32, DateTime<Utc>)],
+    retention: &SnapshotRetention,
+    now: DateTime<Utc>,
+) -> Vec<u32> {
+    let age = |d: Duration| ChronoDuration::from_std(d).unwrap_or(ChronoDuration::MAX);
+    let keep_all_for = age(retention.keep_all_for);
+    let hourly_for = age(retention.hourly_for);
+    let daily_for = age(retention.daily_for);
+
+    let mut newest_first = snapshots.to_vec();
+    newest_first.sort_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(&a.0)));
+
+    let mut kept_buckets = std::collections::HashSet::new();
+    let mut expired = Vec::new();
+    for (version, created) in newest_first {
+        let age = now - created;
+        let bucket = if age < keep_all_for {
+            continue;
+        } else if age < hourly_for {
+            created.date_naive().and_hms_opt(created.hour(), 0, 0)
+        } else if age < daily_for {
+            created.date_naive().and_hms_opt(0, 0, 0)
+        } else {
+            expired.push(version);
+            continue;
+        };
+        // Hour and day buckets can share a start; tell them apart
+        if !kept_buckets.insert((age < hourly_for, bucket)) {
+            expired.push(version);
+        }
+    }
+    expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cadence() {
+        let snapshots = AutoSnapshots::default();
+        let start = Instant::now();
+        let every_third = SnapshotCadence::Changes(3);
+        assert!(!snapshots.record_change_at("a", every_third, start));
+        assert!(!snapshots.record_change_at("a", every_third, start));
+        assert!(!snapshots.record_change_at("b", every_third, start));
+        assert!(snapshots.record_change_at("a", every_third, start));
+        assert!(!snapshots.record_change_at("a", every_third, start));
+
+        let interval = SnapshotCadence::Interval(Duration::from_secs(300));
+        assert!(!snapshots.record_change_at("c", interval, start));
+        assert!(!snapshots.record_change_at("c", interval, start + Duration::from_secs(299)));
+        assert!(snapshots.record_change_at("c", interval, start + Duration::from_secs(300)));
+        assert!(!snapshots.record_change_at("c", SnapshotCadence::Disabled, start));
+    }
+
+    #[test]
+    fn test_retention_thins_by_age() {
+        let now = "2026-03-31T12:30:00Z".parse::<DateTime<Utc>>().unwrap();
+        let at = |minutes_ago: i64| now - ChronoDuration::minutes(minutes_ago);
+        let snapshots = [
+            (1, at(10)),
+            (2, at(20)),
+            // Two in the 10:00 hour; the newer one stays
+            (3, at(120)),
+            (4, at(140)),
+            // Two on 2026-03-20; the newer one stays
+            (5, at(11 * 24 * 60)),
+            (6, at(11 * 24 * 60 + 60)),
+            (7, at(40 * 24 * 60)),
+        ];
+        let mut expired = expired_snapshots(&snapshots, &SnapshotRetention::default(), now);
+        expired.sort();
+        assert_eq!(expired, [4, 6, 7]);
+    }
+}
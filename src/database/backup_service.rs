@@ -1,5 +1,7 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -7,14 +9,37 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::time::Instant;
 use uuid::Uuid;
 
+use crate::database::activity_service::record_activity;
+use crate::database::enhanced_database_sqlx::DatabaseRow;
+use crate::database::models::activity::{ActivityEntry, ActivityKind};
 use crate::database::{DatabaseError, DatabaseResult, EnhancedDatabaseService};
+use crate::scratch_space;
+use crate::security::confirmation::{ConfirmationGuard, DestructiveOperation};
+use crate::security::secure_storage::SecureStorageService;
+use crate::services::SecurityService;
 
-/// Backup types supported by the system
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Keyring entry holding the backup encryption keys
+const BACKUP_KEYS_ENTRY: &str = "backup_encryption_keys";
+
+/// Backup types supported by the system. Manual, automatic and emergency
+/// backups are full snapshots; incremental and differential backups hold
+/// only the pages that changed since the backup they build on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BackupType {
     Manual,
     Automatic,
     Emergency,
+    /// Changes since the latest backup of any kind
+    Incremental,
+    /// Changes since the latest full backup
+    Differential,
+}
+
+impl BackupType {
+    /// Whether the backup restores on its own
+    pub fn is_full(&self) -> bool {
+        !matches!(self, BackupType::Incremental | BackupType::Differential)
+    }
 }
 
 /// Backup metadata stored in database
@@ -30,8 +55,200 @@ pub struct BackupMetadata {
     pub description: Option<String>,
     pub success: bool,
     pub error_message: Option<String>,
+    /// Backup this one applies on top of; unset for full backups
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// Key the file is encrypted with; unset for plaintext backups. The
+    /// checksum is of the file as stored.
+    #[serde(default)]
+    pub key_id: Option<String>,
+    /// SHA-256 of the database the backup restored to when last verified
+    #[serde(default)]
+    pub verification_hash: Option<String>,
+    /// Whether the last verification restored a sound database; unset
+    /// until the backup is verified
+    #[serde(default)]
+    pub restore_verified: Option<bool>,
+    #[serde(default)]
+    pub verified_at: Option<u64>,
+}
+
+/// Outcome of restoring a backup to a scratch file and checking it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupVerification {
+    pub backup_id: String,
+    /// Every file in the chain matched its checksum and could be read
+    pub archive_ok: bool,
+    /// Problems `PRAGMA integrity_check` found in the restored database
+    pub integrity_errors: Vec<String>,
+    /// SHA-256 of the restored database; unset when it couldn't be rebuilt
+    pub verification_hash: Option<String>,
+    pub verified_at: u64,
+    /// Why the chain couldn't be restored
+    pub error: Option<String>,
+}
+
+impl BackupVerification {
+    pub fn passed(&self) -> bool {
+        self.archive_ok && self.integrity_errors.is_empty()
+    }
 }
 
+/// AES-256 keys backups are encrypted with, by key ID. Keys that were
+/// rotated out stay so older backups can still be restored.
+#[derive(Clone)]
+pub struct BackupKeys {
+    current: String,
+    keys: HashMap<String, [u8; 32]>,
+}
+
+/// `BackupKeys` as kept in the keyring
+#[derive(Serialize, Deserialize)]
+struct StoredBackupKeys {
+    current: String,
+    keys: HashMap<String, String>,
+}
+
+impl std::fmt::Debug for BackupKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackupKeys")
+            .field("current", &self.current)
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl BackupKeys {
+    /// A single new random key
+    pub fn generate() -> Self {
+        let mut keys = Self {
+            current: String::new(),
+            keys: HashMap::new(),
+        };
+        keys.rotate();
+        keys
+    }
+
+    /// The installation's keys from the OS keyring, created on first use.
+    /// A keyring that can't be read or written is `KeyMissing`.
+    pub fn load_or_create(storage: &SecureStorageService) -> DatabaseResult<Self> {
+        let stored = storage
+            .find_api_key(BACKUP_KEYS_ENTRY)
+            .map_err(|e| DatabaseError::KeyMissing(format!("Failed to read backup keys: {}", e)))?;
+        match stored {
+            Some(json) => Self::decode(&json),
+            None => {
+                let keys = Self::generate();
+                keys.save(storage)
+                    .map_err(|e| DatabaseError::KeyMissing(e.to_string()))?;
+                Ok(keys)
+            }
+        }
+    }
+
+    /// Encrypt new backups with a new key
+    pub fn rotate(&mut self) {
+        let mut key = [0u8; 32];
+        rand::Rng::fill(&mut rand::thread_rng(), &mut key);
+        self.current = Uuid::new_v4().to_string();
+        self.keys.insert(self.current.clone(), key);
+    }
+
+    pub fn save(&self, storage: &SecureStorageService) -> DatabaseResult<()> {
+        let stored = StoredBackupKeys {
+            current: self.current.clone(),
+            keys: self
+                .keys
+                .iter()
+                .map(|(id, key)| (id.clone(), STANDARD.encode(key)))
+                .collect(),
+        };
+        let json = serde_json::to_string(&stored)
+            .map_err(|e| DatabaseError::Service(format!("Failed to encode backup keys: {}", e)))?;
+        storage
+            .set_api_key(BACKUP_KEYS_ENTRY, &json)
+            .map_err(|e| DatabaseError::Service(format!("Failed to store backup keys: {}", e)))
+    }
+
+    pub fn current_id(&self) -> &str {
+        &self.current
+    }
+
+    fn decode(json: &str) -> DatabaseResult<Self> {
+        let invalid = || DatabaseError::Service("Stored backup keys are invalid".to_string());
+        let stored: StoredBackupKeys = serde_json::from_str(json).map_err(|_| invalid())?;
+        let mut keys = HashMap::new();
+        for (id, encoded) in stored.keys {
+            let key: [u8; 32] = STANDARD
+                .decode(encoded)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(invalid)?;
+            keys.insert(id, key);
+        }
+        if !keys.contains_key(&stored.current) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            current: stored.current,
+            keys,
+        })
+    }
+}
+
+/// How many backups are kept
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Automatic full backups kept, each with the backups built on it
+    pub automatic_chains: usize,
+    /// Incremental backups on one full backup before the next backup is
+    /// taken in full, which bounds how many files a restore replays
+    pub max_chain_length: usize,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            automatic_chains: 30,
+            max_chain_length: 10,
+        }
+    }
+}
+
+/// Whether new backups are encrypted. Encryption is on unless turned off
+/// here; a missing key stops backups rather than writing them in plaintext.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SecureBackupConfig {
+    pub encrypt: bool,
+}
+
+impl Default for SecureBackupConfig {
+    fn default() -> Self {
+        Self { encrypt: true }
+    }
+}
+
+/// Contents of an incremental or differential backup file
+#[derive(Debug, Serialize, Deserialize)]
+struct PageDelta {
+    page_size: usize,
+    page_count: usize,
+    /// Page index and contents of each page that changed
+    pages: Vec<(usize, Vec<u8>)>,
+    /// Hash of every page of the restored database
+    hashes: Vec<[u8; 32]>,
+}
+
+/// Page hashes of the database a backup restores
+struct PageImage {
+    page_size: usize,
+    hashes: Vec<[u8; 32]>,
+}
+
+const BACKUP_COLUMNS: &str = "id, backup_type, file_path, file_size, checksum, created_at, \
+     project_id, description, success, error_message, parent_id, key_id, \
+     verification_hash, restore_verified, verified_at";
+
 /// Statistics about backups
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackupStatistics {
@@ -40,13 +257,32 @@ pub struct BackupStatistics {
     pub last_backup: Option<u64>,
     pub successful_backups: usize,
     pub failed_backups: usize,
+    /// When a scheduled backup last ran, successfully or not
+    #[serde(default)]
+    pub last_scheduled_run: Option<u64>,
+    /// When the next scheduled backup is due
+    #[serde(default)]
+    pub next_scheduled_run: Option<u64>,
+}
+
+/// Scheduled backup runs, as reported by the automation scheduler
+#[derive(Debug, Clone, Copy, Default)]
+struct BackupSchedule {
+    last_run: Option<u64>,
+    next_run: Option<u64>,
 }
 
 /// Backup service with comprehensive functionality
 #[derive(Debug)]
 pub struct BackupService {
     db_service: Arc<tokio::sync::RwLock<EnhancedDatabaseService>>,
+    database_path: PathBuf,
     backup_directory: PathBuf,
+    confirmation_guard: Option<Arc<ConfirmationGuard>>,
+    retention: RetentionPolicy,
+    config: tokio::sync::RwLock<SecureBackupConfig>,
+    encryption: Option<BackupKeys>,
+    schedule: tokio::sync::RwLock<BackupSchedule>,
 }
 
 impl BackupService {
@@ -62,8 +298,59 @@ impl BackupService {
 
         Self {
             db_service,
+            database_path: database_path.to_path_buf(),
             backup_directory,
+            confirmation_guard: None,
+            retention: RetentionPolicy::default(),
+            config: tokio::sync::RwLock::new(SecureBackupConfig::default()),
+            encryption: None,
+            schedule: tokio::sync::RwLock::new(BackupSchedule::default()),
+        }
+    }
+
+    /// Encrypt new backups with the current key, and decrypt backups made
+    /// with any of the keys
+    pub fn with_encryption(mut self, keys: BackupKeys) -> Self {
+        self.encryption = Some(keys);
+        self
+    }
+
+    /// Encrypt new backups or not according to `config`
+    pub fn with_config(mut self, config: SecureBackupConfig) -> Self {
+        self.config = tokio::sync::RwLock::new(config);
+        self
+    }
+
+    /// Turn encryption of new backups on or off. `confirmation` is the
+    /// token of a `DisableEncryption` grant when a guard is set; turning
+    /// it on needs none.
+    pub async fn set_encryption(
+        &self,
+        encrypt: bool,
+        confirmation: Option<&str>,
+    ) -> DatabaseResult<()> {
+        let mut config = self.config.write().await;
+        if config.encrypt && !encrypt {
+            if let Some(guard) = &self.confirmation_guard {
+                guard
+                    .authorize(DestructiveOperation::DisableEncryption, confirmation)
+                    .map_err(|e| DatabaseError::ValidationError(e.to_string()))?;
+            }
         }
+        config.encrypt = encrypt;
+        Ok(())
+    }
+
+    /// Keep backups according to `policy` instead of the default
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = policy;
+        self
+    }
+
+    /// Require a confirmation grant before restoring over the live database
+    pub fn with_confirmation_guard(mut self, guard: Arc<ConfirmationGuard>) -> Self {
+        self.confirmation_guard = Some(guard);
+        self
     }
 
     /// Initialize backup service - create backup directory and setup
@@ -110,19 +397,64 @@ impl BackupService {
             .await
     }
 
-    /// Create a backup with specified type
-    async fn create_backup(
+    /// Create an incremental backup holding what changed since the latest
+    /// backup. Without a backup to build on, or once the chain is
+    /// `max_chain_length` long, a full automatic backup is taken instead.
+    pub async fn create_incremental_backup(
+        &self,
+        project_id: Option<&str>,
+        description: Option<&str>,
+    ) -> DatabaseResult<String> {
+        self.create_backup(BackupType::Incremental, project_id, description)
+            .await
+    }
+
+    /// Create a differential backup holding what changed since the latest
+    /// full backup, or a full automatic backup when there is none
+    pub async fn create_differential_backup(
+        &self,
+        project_id: Option<&str>,
+        description: Option<&str>,
+    ) -> DatabaseResult<String> {
+        self.create_backup(BackupType::Differential, project_id, description)
+            .await
+    }
+
+    /// Create a backup of the given type
+    pub async fn create_backup(
         &self,
         backup_type: BackupType,
         project_id: Option<&str>,
         description: Option<&str>,
     ) -> DatabaseResult<String> {
         let start_time = Instant::now();
+        let keys = match (self.config.read().await.encrypt, &self.encryption) {
+            (false, _) => None,
+            (true, Some(keys)) => Some(keys),
+            (true, None) => return Err(DatabaseError::KeyMissing(
+                "Backups are encrypted, but the backup key isn't available; no backup was written"
+                    .to_string(),
+            )),
+        };
 
-        // Get database path
-        let db_path = {
-            let db = self.db_service.read().await;
-            db.get_database_path().to_path_buf()
+        let parent = match backup_type {
+            BackupType::Incremental => self.latest_backup(false).await?,
+            BackupType::Differential => self.latest_backup(true).await?,
+            _ => None,
+        };
+        let parent = match parent {
+            Some(parent)
+                if self.backup_chain(&parent.id).await?.len()
+                    <= self.retention.max_chain_length =>
+            {
+                Some(parent)
+            }
+            _ => None,
+        };
+        let backup_type = if parent.is_none() && !backup_type.is_full() {
+            BackupType::Automatic
+        } else {
+            backup_type
         };
 
         // Generate backup filename
@@ -131,12 +463,24 @@ impl BackupService {
             .unwrap_or_default()
             .as_secs();
         let backup_id = Uuid::new_v4();
-        let backup_filename = format!("{}_{}.db", timestamp, backup_id);
+        let extension = if parent.is_some() { "delta" } else { "db" };
+        let extension = match keys {
+            Some(_) => format!("{}.enc", extension),
+            None => extension.to_string(),
+        };
+        let backup_filename = format!("{}_{}.{}", timestamp, backup_id, extension);
         let backup_path = self.backup_directory.join(&backup_filename);
-
-        // Copy database file
-        match fs::copy(&db_path, &backup_path) {
-            Ok(_bytes_copied) => {
+        let written = match (self.preflight(), &parent) {
+            (Err(e), _) => Err(e),
+            (Ok(()), Some(parent)) => self.write_delta(parent, &backup_path).await,
+            (Ok(()), None) => self.snapshot(&backup_path).await,
+        };
+        let written = match written {
+            Ok(()) => Self::encrypt_file(&backup_path, keys).await,
+            Err(e) => Err(e),
+        };
+        match written {
+            Ok(key_id) => {
                 // Calculate checksum
                 let checksum = self.calculate_file_checksum(&backup_path).await?;
 
@@ -158,9 +502,15 @@ impl BackupService {
                     description: description.map(|s| s.to_string()),
                     success: true,
                     error_message: None,
+                    parent_id: parent.map(|p| p.id),
+                    key_id,
+                    verification_hash: None,
+                    restore_verified: None,
+                    verified_at: None,
                 };
 
                 self.store_backup_metadata(&metadata).await?;
+                self.log_activity(&backup_type, project_id).await;
 
                 // Clean up old automatic backups if needed
                 if matches!(backup_type, BackupType::Automatic) {
@@ -187,11 +537,19 @@ impl BackupService {
                     description: description.map(|s| s.to_string()),
                     success: false,
                     error_message: Some(e.to_string()),
+                    parent_id: parent.map(|p| p.id),
+                    key_id: None,
+                    verification_hash: None,
+                    restore_verified: None,
+                    verified_at: None,
                 };
 
                 self.store_backup_metadata(&metadata).await?;
 
                 tracing::error!("Backup failed: {}", e);
+                if matches!(e, DatabaseError::InsufficientSpace(_)) {
+                    return Err(e);
+                }
                 Err(DatabaseError::Service(format!(
                     "Backup creation failed: {}",
                     e
@@ -200,6 +558,175 @@ impl BackupService {
         }
     }
 
+    /// Write a consistent copy of the live database. Unlike copying the
+    /// file, this includes commits still in the write-ahead log.
+    /// Fail before writing anything when the backup disk can't take a
+    /// copy of the database. A delta needs room for a full snapshot next
+    /// to it while it is worked out, so allow for two.
+    fn preflight(&self) -> DatabaseResult<()> {
+        let database_size = fs::metadata(&self.database_path)
+            .map(|m| m.len())
+            .unwrap_or(0);
+        scratch_space::preflight(&self.backup_directory, database_size.saturating_mul(2))?;
+        Ok(())
+    }
+
+    async fn snapshot(&self, path: &Path) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        db.execute("VACUUM INTO ?", &[path.to_string_lossy().into_owned()])
+            .await
+    }
+
+    /// Write the pages of the live database that differ from what
+    /// `parent` restores
+    async fn write_delta(&self, parent: &BackupMetadata, path: &Path) -> DatabaseResult<()> {
+        let base = self.page_image(parent).await?;
+
+        let snapshot_path = path.with_extension("snapshot");
+        self.snapshot(&snapshot_path).await?;
+        let snapshot = tokio::fs::read(&snapshot_path).await;
+        let _ = tokio::fs::remove_file(&snapshot_path).await;
+        let snapshot = snapshot
+            .map_err(|e| DatabaseError::Service(format!("Failed to read snapshot: {}", e)))?;
+
+        let delta = page_delta(&base, &snapshot)?;
+        let encoded = bincode::serialize(&delta)
+            .map_err(|e| DatabaseError::Service(format!("Failed to encode backup: {}", e)))?;
+        tokio::fs::write(path, encoded)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to write backup: {}", e)))
+    }
+
+    /// Encrypt a backup file in place with the current key, if backups
+    /// are encrypted; returns the key's ID
+    async fn encrypt_file(
+        path: &Path,
+        keys: Option<&BackupKeys>,
+    ) -> DatabaseResult<Option<String>> {
+        let Some(keys) = keys else {
+            return Ok(None);
+        };
+        let plaintext = tokio::fs::read(path)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to read backup: {}", e)))?;
+        let encrypted = SecurityService::new()
+            .encrypt_bytes(&plaintext, &keys.keys[&keys.current])
+            .map_err(DatabaseError::Service)?;
+        tokio::fs::write(path, encrypted)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to write backup: {}", e)))?;
+        Ok(Some(keys.current.clone()))
+    }
+
+    /// Contents of a backup file, decrypted. With `verify`, the file must
+    /// match its recorded checksum.
+    async fn read_backup(&self, backup: &BackupMetadata, verify: bool) -> DatabaseResult<Vec<u8>> {
+        if !backup.file_path.exists() {
+            return Err(DatabaseError::Service("Backup file not found".to_string()));
+        }
+        let bytes = tokio::fs::read(&backup.file_path).await.map_err(|e| {
+            DatabaseError::Service(format!("Failed to read backup {}: {}", backup.id, e))
+        })?;
+        if verify && format!("{:x}", Sha256::digest(&bytes)) != backup.checksum {
+            return Err(DatabaseError::Service(format!(
+                "Backup {} is damaged: its checksum doesn't match",
+                backup.id
+            )));
+        }
+
+        let Some(key_id) = &backup.key_id else {
+            return Ok(bytes);
+        };
+        let key = self
+            .encryption
+            .as_ref()
+            .and_then(|keys| keys.keys.get(key_id))
+            .ok_or_else(|| {
+                DatabaseError::KeyMissing(format!(
+                    "Backup {} is encrypted with key {}, which isn't available",
+                    backup.id, key_id
+                ))
+            })?;
+        SecurityService::new()
+            .decrypt_bytes(&bytes, key)
+            .map_err(|e| DatabaseError::Service(format!("Backup {}: {}", backup.id, e)))
+    }
+
+    /// Page hashes of the database `backup` restores
+    async fn page_image(&self, backup: &BackupMetadata) -> DatabaseResult<PageImage> {
+        let bytes = self.read_backup(backup, false).await?;
+        if backup.backup_type.is_full() {
+            let page_size = page_size(&bytes)?;
+            Ok(PageImage {
+                page_size,
+                hashes: page_hashes(&bytes, page_size),
+            })
+        } else {
+            let delta = decode_delta(&bytes)?;
+            Ok(PageImage {
+                page_size: delta.page_size,
+                hashes: delta.hashes,
+            })
+        }
+    }
+
+    /// The backups a restore of `backup_id` replays, full backup first
+    pub async fn backup_chain(&self, backup_id: &str) -> DatabaseResult<Vec<BackupMetadata>> {
+        let mut chain: Vec<BackupMetadata> = Vec::new();
+        let mut next = Some(backup_id.to_string());
+        while let Some(id) = next {
+            if chain.iter().any(|backup| backup.id == id) {
+                return Err(DatabaseError::Service(format!(
+                    "Backup chain of {} loops",
+                    backup_id
+                )));
+            }
+            let backup = self.get_backup(&id).await?.ok_or_else(|| {
+                DatabaseError::Service(format!("Backup {} is missing from the chain", id))
+            })?;
+            next = backup.parent_id.clone();
+            chain.push(backup);
+        }
+        chain.reverse();
+        Ok(chain)
+    }
+
+    /// Metadata of one backup
+    pub async fn get_backup(&self, backup_id: &str) -> DatabaseResult<Option<BackupMetadata>> {
+        let db = self.db_service.read().await;
+        let rows = db
+            .query(
+                &format!(
+                    "SELECT {} FROM backup_metadata WHERE id = ?",
+                    BACKUP_COLUMNS
+                ),
+                &[backup_id.to_string()],
+            )
+            .await?;
+        Ok(rows.rows.first().map(metadata_from_row))
+    }
+
+    /// The newest successful backup, or the newest full one
+    async fn latest_backup(&self, full_only: bool) -> DatabaseResult<Option<BackupMetadata>> {
+        let db = self.db_service.read().await;
+        let filter = if full_only {
+            "AND parent_id IS NULL"
+        } else {
+            ""
+        };
+        let rows = db
+            .query(
+                &format!(
+                    "SELECT {} FROM backup_metadata WHERE success = 1 {}
+                     ORDER BY created_at DESC, rowid DESC LIMIT 1",
+                    BACKUP_COLUMNS, filter
+                ),
+                &[],
+            )
+            .await?;
+        Ok(rows.rows.first().map(metadata_from_row))
+    }
+
     /// List all backups with optional filtering
     pub async fn list_backups(
         &self,
@@ -212,18 +739,20 @@ impl BackupService {
         let project_id_str = project_id.map(|s| s.to_string());
 
         let query = if project_id_str.is_some() {
-            "SELECT id, backup_type, file_path, file_size, checksum, created_at,
-                    project_id, description, success, error_message
-             FROM backup_metadata
-             WHERE project_id = ?
-             ORDER BY created_at DESC
-             LIMIT ?"
+            format!(
+                "SELECT {} FROM backup_metadata
+                 WHERE project_id = ?
+                 ORDER BY created_at DESC, rowid DESC
+                 LIMIT ?",
+                BACKUP_COLUMNS
+            )
         } else {
-            "SELECT id, backup_type, file_path, file_size, checksum, created_at,
-                    project_id, description, success, error_message
-             FROM backup_metadata
-             ORDER BY created_at DESC
-             LIMIT ?"
+            format!(
+                "SELECT {} FROM backup_metadata
+                 ORDER BY created_at DESC, rowid DESC
+                 LIMIT ?",
+                BACKUP_COLUMNS
+            )
         };
 
         let params = if let Some(pid) = &project_id_str {
@@ -232,47 +761,35 @@ impl BackupService {
             vec![limit.to_string()]
         };
 
-        let rows = db.query(query, &params).await?;
-
-        let mut results = Vec::new();
-        for row in rows {
-            let backup_type_str = row.get(1).unwrap_or("\"Manual\"");
-            let backup_type: BackupType =
-                serde_json::from_str(backup_type_str).unwrap_or(BackupType::Manual);
-
-            let metadata = BackupMetadata {
-                id: row.get(0).unwrap_or("").to_string(),
-                backup_type,
-                file_path: PathBuf::from(row.get(2).unwrap_or("")),
-                file_size: row.get(3).and_then(|s| s.parse().ok()).unwrap_or(0),
-                checksum: row.get(4).unwrap_or("").to_string(),
-                created_at: row.get(5).and_then(|s| s.parse().ok()).unwrap_or(0),
-                project_id: row.get(6).map(|s| s.to_string()),
-                description: row.get(7).map(|s| s.to_string()),
-                success: row.get(8).map(|s| s == "true" || s == "1").unwrap_or(false),
-                error_message: row.get(9).map(|s| s.to_string()),
-            };
-            results.push(metadata);
-        }
+        let rows = db.query(&query, &params).await?;
 
-        Ok(results)
+        Ok(rows.rows.iter().map(metadata_from_row).collect())
     }
 
-    /// Delete a specific backup
+    /// Delete a specific backup, along with the backups built on it that
+    /// could no longer be restored
     pub async fn delete_backup(&self, backup_id: &str) -> DatabaseResult<()> {
+        self.delete_chain(backup_id).await.map(|_| ())
+    }
+
+    /// Delete a backup and its dependents; returns how many were deleted
+    async fn delete_chain(&self, backup_id: &str) -> DatabaseResult<usize> {
         let db = self.db_service.read().await;
 
-        // Get backup metadata
         let backup_rows = db
             .query(
-                "SELECT file_path FROM backup_metadata WHERE id = ?",
+                "WITH RECURSIVE chain(id) AS (
+                     SELECT ?
+                     UNION SELECT b.id FROM backup_metadata b JOIN chain c ON b.parent_id = c.id
+                 )
+                 SELECT m.id, m.file_path FROM backup_metadata m JOIN chain c ON m.id = c.id",
                 &[backup_id.to_string()],
             )
             .await?;
 
-        // Delete physical backup file
-        if let Some(row) = backup_rows.rows.first() {
-            if let Some(path_str) = row.get(0) {
+        for row in &backup_rows.rows {
+            // Delete physical backup file
+            if let Some(path_str) = row.get(1) {
                 let path = PathBuf::from(path_str);
                 if path.exists() {
                     fs::remove_file(&path).map_err(|e| {
@@ -280,79 +797,233 @@ impl BackupService {
                     })?;
                 }
             }
-        }
-
-        // Delete metadata from database
-        db.execute(
-            "DELETE FROM backup_metadata WHERE id = ?",
-            &[backup_id.to_string()],
-        )
-        .await?;
-
-        Ok(())
-    }
-
-    /// Restore from a backup
-    pub async fn restore_from_backup(&self, backup_id: &str) -> DatabaseResult<()> {
-        let db = self.db_service.read().await;
 
-        // Get backup metadata
-        let backup_rows = db
-            .query(
-                "SELECT file_path FROM backup_metadata WHERE id = ? AND success = 1",
-                &[backup_id.to_string()],
+            // Delete metadata from database
+            db.execute(
+                "DELETE FROM backup_metadata WHERE id = ?",
+                &[row.get(0).unwrap_or("").to_string()],
             )
             .await?;
+        }
 
-        let backup_path = if let Some(row) = backup_rows.rows.first() {
-            PathBuf::from(row.get(0).unwrap_or(""))
-        } else {
-            return Err(DatabaseError::Service("Backup file not found".to_string()));
-        };
+        Ok(backup_rows.len())
+    }
 
-        if !backup_path.exists() {
-            return Err(DatabaseError::Service("Backup file not found".to_string()));
+    /// Restore from a backup, overwriting the current database. `confirmation`
+    /// is the token of a `RestoreOverExisting` grant when a guard is set.
+    pub async fn restore_from_backup(
+        &self,
+        backup_id: &str,
+        confirmation: Option<&str>,
+    ) -> DatabaseResult<()> {
+        if let Some(guard) = &self.confirmation_guard {
+            guard
+                .authorize(DestructiveOperation::RestoreOverExisting, confirmation)
+                .map_err(|e| DatabaseError::ValidationError(e.to_string()))?;
         }
 
+        let chain = self.backup_chain(backup_id).await?;
+        let database = self.reconstruct(&chain).await?;
+
+        let db = self.db_service.read().await;
+
         // Get current database path
         let current_db_path = db.get_database_path().to_path_buf();
 
-        // Copy backup to current database location
-        fs::copy(&backup_path, &current_db_path)
+        // Fold the write-ahead log in first so it isn't replayed over the
+        // restored file
+        db.execute("PRAGMA wal_checkpoint(TRUNCATE)", &[]).await?;
+
+        // Write the backup to the current database location
+        fs::write(&current_db_path, &database)
             .map_err(|e| DatabaseError::Service(format!("Failed to restore backup: {}", e)))?;
 
         Ok(())
     }
 
-    /// Clean up old backups based on retention policy
-    pub async fn cleanup_old_backups(&self) -> DatabaseResult<()> {
-        let db = self.db_service.read().await;
+    /// Write the database `backup_id` restores to `target`, leaving the
+    /// live database alone
+    pub async fn restore_to(&self, backup_id: &str, target: &Path) -> DatabaseResult<()> {
+        let chain = self.backup_chain(backup_id).await?;
+        let database = self.reconstruct(&chain).await?;
 
-        // Keep only the last 30 automatic backups per project
-        // This is a simplified version - in production you'd want more sophisticated retention
-        db.execute(
-            "DELETE FROM backup_metadata
-             WHERE backup_type = 'Automatic'
-             AND created_at < (
-                 SELECT created_at FROM backup_metadata
-                 WHERE backup_type = 'Automatic'
-                 ORDER BY created_at DESC
-                 LIMIT 1 OFFSET 30
-             )",
-            &[],
+        let partial = target.with_extension("restoring");
+        tokio::fs::write(&partial, &database)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to restore backup: {}", e)))?;
+        tokio::fs::rename(&partial, target)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to restore backup: {}", e)))
+    }
+
+    /// Restore `backup_id` to a scratch file, check it with SQLite's
+    /// integrity check and record the outcome on the backup. A damaged or
+    /// incomplete chain is reported in the result rather than as an error;
+    /// a missing encryption key is an error, since it says nothing about
+    /// the backup itself.
+    pub async fn verify_backup(&self, backup_id: &str) -> DatabaseResult<BackupVerification> {
+        if self.get_backup(backup_id).await?.is_none() {
+            return Err(DatabaseError::RecordNotFound {
+                entity: "backup".to_string(),
+                id: backup_id.to_string(),
+            });
+        }
+        let mut verification = BackupVerification {
+            backup_id: backup_id.to_string(),
+            archive_ok: true,
+            integrity_errors: Vec::new(),
+            verification_hash: None,
+            verified_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            error: None,
+        };
+
+        let database = match self.backup_chain(backup_id).await {
+            Ok(chain) => self.reconstruct(&chain).await,
+            Err(e) => Err(e),
+        };
+        match database {
+            Ok(database) => {
+                verification.verification_hash = Some(format!("{:x}", Sha256::digest(&database)));
+                verification.integrity_errors = integrity_check(&database).await?;
+            }
+            Err(e @ DatabaseError::KeyMissing(_)) => return Err(e),
+            Err(e) => {
+                verification.archive_ok = false;
+                verification.error = Some(e.to_string());
+            }
+        }
+
+        let db = self.db_service.read().await;
+        sqlx::query(
+            "UPDATE backup_metadata
+             SET verification_hash = ?, restore_verified = ?, verified_at = ?
+             WHERE id = ?",
         )
+        .bind(&verification.verification_hash)
+        .bind(verification.passed())
+        .bind(verification.verified_at as i64)
+        .bind(backup_id)
+        .execute(&db.pool)
         .await?;
 
-        tracing::info!("Cleaned up old automatic backups");
+        if !verification.passed() {
+            tracing::warn!("Backup {} failed verification", backup_id);
+        }
+        Ok(verification)
+    }
+
+    /// Verify every completed backup, newest first, up to `limit`
+    pub async fn verify_backups(
+        &self,
+        project_id: Option<&str>,
+        limit: Option<usize>,
+    ) -> DatabaseResult<Vec<BackupVerification>> {
+        let mut verifications = Vec::new();
+        for backup in self.list_backups(project_id, limit).await? {
+            if backup.success {
+                verifications.push(self.verify_backup(&backup.id).await?);
+            }
+        }
+        Ok(verifications)
+    }
+
+    /// Replay a full backup and the deltas built on it, checking each file
+    /// against its recorded checksum
+    async fn reconstruct(&self, chain: &[BackupMetadata]) -> DatabaseResult<Vec<u8>> {
+        let mut database = Vec::new();
+        let mut expected = None;
+        for (index, backup) in chain.iter().enumerate() {
+            if !backup.success {
+                return Err(DatabaseError::Service(format!(
+                    "Backup {} did not complete",
+                    backup.id
+                )));
+            }
+            let bytes = self.read_backup(backup, true).await?;
+
+            if index == 0 {
+                if !backup.backup_type.is_full() {
+                    return Err(DatabaseError::Service(format!(
+                        "Backup chain of {} has no full backup",
+                        backup.id
+                    )));
+                }
+                database = bytes;
+            } else {
+                let delta = decode_delta(&bytes)?;
+                apply_delta(&mut database, &delta);
+                expected = Some((delta.page_size, delta.hashes));
+            }
+        }
+
+        if let Some((page_size, hashes)) = expected {
+            if page_hashes(&database, page_size) != hashes {
+                return Err(DatabaseError::Service(
+                    "Backup chain doesn't reproduce the backed up database".to_string(),
+                ));
+            }
+        }
+        Ok(database)
+    }
+
+    /// Clean up old backups based on retention policy
+    pub async fn cleanup_old_backups(&self) -> DatabaseResult<()> {
+        let removed = self.prune_backups(&self.retention).await?;
+
+        tracing::info!("Cleaned up {} old automatic backups", removed);
 
         Ok(())
     }
 
+    /// Delete automatic backup chains beyond the newest
+    /// `policy.automatic_chains`. Manual and emergency backups stay until
+    /// deleted. Returns how many backups were deleted.
+    pub async fn prune_backups(&self, policy: &RetentionPolicy) -> DatabaseResult<usize> {
+        let expired = {
+            let db = self.db_service.read().await;
+            db.query(
+                "SELECT id FROM backup_metadata
+                 WHERE backup_type = '\"Automatic\"'
+                 ORDER BY created_at DESC, rowid DESC
+                 LIMIT -1 OFFSET ?",
+                &[policy.automatic_chains.to_string()],
+            )
+            .await?
+        };
+
+        let mut removed = 0;
+        for row in &expired.rows {
+            if let Some(id) = row.get(0) {
+                removed += self.delete_chain(id).await?;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Record that a scheduled backup just ran
+    pub async fn record_scheduled_run(&self) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.schedule.write().await.last_run = Some(now);
+    }
+
+    /// Record when the next scheduled backup is due, as a Unix timestamp;
+    /// `None` when no backups are scheduled
+    pub async fn set_next_scheduled_run(&self, next_run: Option<u64>) {
+        self.schedule.write().await.next_run = next_run;
+    }
+
     /// Get backup statistics
     pub async fn get_backup_statistics(
         &self,
         project_id: Option<&str>,
     ) -> DatabaseResult<BackupStatistics> {
+        let schedule = *self.schedule.read().await;
         let db = self.db_service.read().await;
 
         let query = if let Some(_pid) = project_id {
@@ -389,6 +1060,8 @@ impl BackupService {
                 last_backup,
                 successful_backups,
                 failed_backups,
+                last_scheduled_run: schedule.last_run,
+                next_scheduled_run: schedule.next_run,
             })
         } else {
             Ok(BackupStatistics {
@@ -397,6 +1070,8 @@ impl BackupService {
                 last_backup: None,
                 successful_backups: 0,
                 failed_backups: 0,
+                last_scheduled_run: schedule.last_run,
+                next_scheduled_run: schedule.next_run,
             })
         }
     }
@@ -414,12 +1089,65 @@ impl BackupService {
         Ok(format!("{:x}", result))
     }
 
-    /// Initialize backup metadata table (now handled by schema.sql)
+    /// Initialize backup metadata table (created by schema.sql); adds the
+    /// chain, key and verification columns to older databases
     async fn initialize_backup_metadata_table(&self) -> DatabaseResult<()> {
-        // Table creation is now handled by schema.sql initialization
+        let db = self.db_service.read().await;
+        let columns: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info('backup_metadata')")
+                .fetch_all(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to inspect backup metadata: {}", e))
+                })?;
+        for (column, kind) in [
+            ("parent_id", "TEXT"),
+            ("key_id", "TEXT"),
+            ("verification_hash", "TEXT"),
+            ("restore_verified", "BOOLEAN"),
+            ("verified_at", "INTEGER"),
+        ] {
+            if !columns.iter().any(|c| c == column) {
+                sqlx::query(&format!(
+                    "ALTER TABLE backup_metadata ADD COLUMN {} {}",
+                    column, kind
+                ))
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to add backup {}: {}", column, e))
+                })?;
+            }
+        }
+
+        // Rows written before success was stored as a number
+        sqlx::query(
+            "UPDATE backup_metadata SET success = (success = 'true') WHERE typeof(success) = 'text'",
+        )
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Migration(format!("Failed to update backup metadata: {}", e)))?;
         Ok(())
     }
 
+    /// Add a successful backup to the activity feed; a backup of the whole
+    /// database shows in every project's feed
+    async fn log_activity(&self, backup_type: &BackupType, project_id: Option<&str>) {
+        let summary = match backup_type {
+            BackupType::Manual => "Manual backup",
+            BackupType::Automatic => "Automatic backup",
+            BackupType::Emergency => "Emergency backup",
+            BackupType::Incremental => "Incremental backup",
+            BackupType::Differential => "Differential backup",
+        };
+        let project_id = project_id.and_then(|id| Uuid::parse_str(id).ok());
+        let entry = ActivityEntry::new(project_id, ActivityKind::Backup, summary);
+        let db = self.db_service.read().await;
+        if let Err(e) = record_activity(&db.pool, &entry).await {
+            tracing::warn!("Failed to record backup activity: {}", e);
+        }
+    }
+
     /// Store backup metadata in database
     async fn store_backup_metadata(&self, metadata: &BackupMetadata) -> DatabaseResult<()> {
         let db = self.db_service.read().await;
@@ -428,26 +1156,398 @@ impl BackupService {
             DatabaseError::Service(format!("Failed to serialize backup type: {}", e))
         })?;
 
-        db.execute(
+        sqlx::query(
             "INSERT OR REPLACE INTO backup_metadata
              (id, backup_type, file_path, file_size, checksum, created_at,
-              project_id, description, success, error_message)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-            &[
-                metadata.id.clone(),
-                backup_type_str,
-                metadata.file_path.to_str().unwrap_or("").to_string(),
-                metadata.file_size.to_string(),
-                metadata.checksum.clone(),
-                metadata.created_at.to_string(),
-                metadata.project_id.as_deref().unwrap_or("").to_string(),
-                metadata.description.as_deref().unwrap_or("").to_string(),
-                metadata.success.to_string(),
-                metadata.error_message.as_deref().unwrap_or("").to_string(),
-            ],
+              project_id, description, success, error_message, parent_id, key_id,
+              verification_hash, restore_verified, verified_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
-        .await?;
+        .bind(&metadata.id)
+        .bind(backup_type_str)
+        .bind(metadata.file_path.to_str().unwrap_or(""))
+        .bind(metadata.file_size as i64)
+        .bind(&metadata.checksum)
+        .bind(metadata.created_at as i64)
+        .bind(&metadata.project_id)
+        .bind(&metadata.description)
+        .bind(metadata.success)
+        .bind(&metadata.error_message)
+        .bind(&metadata.parent_id)
+        .bind(&metadata.key_id)
+        .bind(&metadata.verification_hash)
+        .bind(metadata.restore_verified)
+        .bind(metadata.verified_at.map(|at| at as i64))
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to store backup metadata: {}", e)))?;
 
         Ok(())
     }
 }
+
+fn metadata_from_row(row: &DatabaseRow) -> BackupMetadata {
+    let backup_type_str = row.get(1).unwrap_or("\"Manual\"");
+    let backup_type: BackupType =
+        serde_json::from_str(backup_type_str).unwrap_or(BackupType::Manual);
+
+    BackupMetadata {
+        id: row.get(0).unwrap_or("").to_string(),
+        backup_type,
+        file_path: PathBuf::from(row.get(2).unwrap_or("")),
+        file_size: row.get(3).and_then(|s| s.parse().ok()).unwrap_or(0),
+        checksum: row.get(4).unwrap_or("").to_string(),
+        created_at: row.get(5).and_then(|s| s.parse().ok()).unwrap_or(0),
+        project_id: optional(row, 6),
+        description: optional(row, 7),
+        success: row.get(8).map(|s| s == "true" || s == "1").unwrap_or(false),
+        error_message: optional(row, 9),
+        parent_id: optional(row, 10),
+        key_id: optional(row, 11),
+        verification_hash: optional(row, 12),
+        restore_verified: optional(row, 13).map(|s| s == "true" || s == "1"),
+        verified_at: row.get(14).and_then(|s| s.parse().ok()),
+    }
+}
+
+/// A nullable text column; NULL reads back as an empty string
+fn optional(row: &DatabaseRow, index: usize) -> Option<String> {
+    row.get(index)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+/// Problems SQLite's integrity check finds in `database`, opened from a
+/// scratch copy; empty when it is sound
+async fn integrity_check(database: &[u8]) -> DatabaseResult<Vec<String>> {
+    use sqlx::Connection;
+
+    let scratch =
+        |e: std::io::Error| DatabaseError::Service(format!("Failed to write scratch copy: {}", e));
+    let dir = tempfile::tempdir().map_err(scratch)?;
+    let path = dir.path().join("verify.db");
+    tokio::fs::write(&path, database).await.map_err(scratch)?;
+
+    let options = sqlx::sqlite::SqliteConnectOptions::new().filename(&path);
+    let mut conn = match sqlx::SqliteConnection::connect_with(&options).await {
+        Ok(conn) => conn,
+        Err(e) => return Ok(vec![e.to_string()]),
+    };
+    let result: Result<Vec<String>, _> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(&mut conn)
+        .await;
+    let _ = conn.close().await;
+    Ok(match result {
+        Ok(rows) if rows == ["ok"] => Vec::new(),
+        Ok(rows) => rows,
+        Err(e) => vec![e.to_string()],
+    })
+}
+
+/// Page size from an SQLite file header
+fn page_size(database: &[u8]) -> DatabaseResult<usize> {
+    if database.len() < 100 || !database.starts_with(b"SQLite format 3\0") {
+        return Err(DatabaseError::Service(
+            "Backup is not an SQLite database".to_string(),
+        ));
+    }
+    Ok(match u16::from_be_bytes([database[16], database[17]]) {
+        1 => 65536,
+        size => size as usize,
+    })
+}
+
+fn page_hashes(database: &[u8], page_size: usize) -> Vec<[u8; 32]> {
+    database
+        .chunks(page_size)
+        .map(|page| Sha256::digest(page).into())
+        .collect()
+}
+
+/// Pages of `snapshot` that differ from `base`
+fn page_delta(base: &PageImage, snapshot: &[u8]) -> DatabaseResult<PageDelta> {
+    let page_size = page_size(snapshot)?;
+    let hashes = page_hashes(snapshot, page_size);
+    let pages = snapshot
+        .chunks(page_size)
+        .zip(&hashes)
+        .enumerate()
+        .filter(|(index, (_, hash))| {
+            base.page_size != page_size || base.hashes.get(*index) != Some(*hash)
+        })
+        .map(|(index, (page, _))| (index, page.to_vec()))
+        .collect();
+    Ok(PageDelta {
+        page_size,
+        page_count: hashes.len(),
+        pages,
+        hashes,
+    })
+}
+
+fn apply_delta(database: &mut Vec<u8>, delta: &PageDelta) {
+    database.resize(delta.page_count * delta.page_size, 0);
+    for (index, page) in &delta.pages {
+        let start = index * delta.page_size;
+        database[start..start + page.len()].copy_from_slice(page);
+    }
+}
+
+fn decode_delta(bytes: &[u8]) -> DatabaseResult<PageDelta> {
+    bincode::deserialize(bytes)
+        .map_err(|e| DatabaseError::Service(format!("Failed to read backup: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    async fn setup(
+        dir: &Path,
+    ) -> (
+        Arc<tokio::sync::RwLock<EnhancedDatabaseService>>,
+        BackupService,
+    ) {
+        let db_path = dir.join("test.db");
+        let db = EnhancedDatabaseService::new(&db_path, DatabaseConfig::default())
+            .await
+            .unwrap();
+        db.create_document(
+            "chapter-1".to_string(),
+            "default-project".to_string(),
+            "Chapter 1".to_string(),
+            "It was a dark and stormy night.".repeat(200),
+        )
+        .await
+        .unwrap();
+        let db = Arc::new(tokio::sync::RwLock::new(db));
+        let service = BackupService::new(db.clone(), &db_path)
+            .with_config(SecureBackupConfig { encrypt: false });
+        service.initialize().await.unwrap();
+        (db, service)
+    }
+
+    async fn restored_content(service: &BackupService, backup_id: &str, target: &Path) -> String {
+        service.restore_to(backup_id, target).await.unwrap();
+        let restored = EnhancedDatabaseService::new(target, DatabaseConfig::default())
+            .await
+            .unwrap();
+        let content = restored
+            .get_document("chapter-1".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        restored.close().await;
+        content
+    }
+
+    #[tokio::test]
+    async fn test_incremental_chain_restores_each_point() {
+        let dir = tempfile::tempdir().unwrap();
+        let (db, service) = setup(dir.path()).await;
+
+        let full = service.create_manual_backup(None, None).await.unwrap();
+        db.read()
+            .await
+            .update_document_content("chapter-1", "Second draft")
+            .await
+            .unwrap();
+        let first = service.create_incremental_backup(None, None).await.unwrap();
+        db.read()
+            .await
+            .update_document_content("chapter-1", "Third draft")
+            .await
+            .unwrap();
+        let second = service.create_incremental_backup(None, None).await.unwrap();
+
+        let chain = service.backup_chain(&second).await.unwrap();
+        let ids: Vec<&str> = chain.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(ids, [full.as_str(), first.as_str(), second.as_str()]);
+        assert_eq!(chain[2].backup_type, BackupType::Incremental);
+        assert!(chain[2].file_size < chain[0].file_size);
+
+        assert!(restored_content(&service, &full, &dir.path().join("a.db"))
+            .await
+            .starts_with("It was a dark"));
+        assert_eq!(
+            restored_content(&service, &first, &dir.path().join("b.db")).await,
+            "Second draft"
+        );
+        assert_eq!(
+            restored_content(&service, &second, &dir.path().join("c.db")).await,
+            "Third draft"
+        );
+
+        // A differential builds on the full backup, not the latest one
+        let differential = service
+            .create_differential_backup(None, None)
+            .await
+            .unwrap();
+        assert_eq!(service.backup_chain(&differential).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_chain_length_and_pruning() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_db, service) = setup(dir.path()).await;
+        let service = service.with_retention(RetentionPolicy {
+            automatic_chains: 1,
+            max_chain_length: 2,
+        });
+
+        // Nothing to build on yet, so the first backup is full
+        let first = service.create_incremental_backup(None, None).await.unwrap();
+        let first_backup = service.get_backup(&first).await.unwrap().unwrap();
+        assert_eq!(first_backup.backup_type, BackupType::Automatic);
+        let delta = service.create_incremental_backup(None, None).await.unwrap();
+        service.create_incremental_backup(None, None).await.unwrap();
+        // The chain is full, so this starts a new one and prunes the old
+        let second = service.create_incremental_backup(None, None).await.unwrap();
+        assert!(service
+            .get_backup(&second)
+            .await
+            .unwrap()
+            .unwrap()
+            .parent_id
+            .is_none());
+
+        assert!(service.get_backup(&first).await.unwrap().is_none());
+        assert!(service.get_backup(&delta).await.unwrap().is_none());
+        assert!(!first_backup.file_path.exists());
+        assert_eq!(service.list_backups(None, None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_backups_need_their_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let (db, service) = setup(dir.path()).await;
+        let keys = BackupKeys::generate();
+        let service = service
+            .with_config(SecureBackupConfig::default())
+            .with_encryption(keys.clone());
+
+        let full = service.create_manual_backup(None, None).await.unwrap();
+        db.read()
+            .await
+            .update_document_content("chapter-1", "Second draft")
+            .await
+            .unwrap();
+        let delta = service.create_incremental_backup(None, None).await.unwrap();
+
+        for id in [&full, &delta] {
+            let backup = service.get_backup(id).await.unwrap().unwrap();
+            assert_eq!(backup.key_id.as_deref(), Some(keys.current_id()));
+            let stored = std::fs::read(&backup.file_path).unwrap();
+            assert!(!stored.starts_with(b"SQLite format 3"));
+            assert!(!stored.windows(12).any(|w| w == b"Second draft"));
+        }
+        assert_eq!(
+            restored_content(&service, &delta, &dir.path().join("a.db")).await,
+            "Second draft"
+        );
+
+        // Without the key, backups are refused rather than written in
+        // plaintext, and restore says which key is missing
+        let db_path = dir.path().join("test.db");
+        let plain = BackupService::new(db.clone(), &db_path);
+        let refused = plain.create_manual_backup(None, None).await.unwrap_err();
+        assert!(matches!(refused, DatabaseError::KeyMissing(_)));
+        let other_key =
+            BackupService::new(db.clone(), &db_path).with_encryption(BackupKeys::generate());
+        for other in [plain, other_key] {
+            let error = other
+                .restore_to(&delta, &dir.path().join("b.db"))
+                .await
+                .unwrap_err();
+            assert!(matches!(error, DatabaseError::KeyMissing(_)));
+            assert!(error.to_string().contains(keys.current_id()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disabling_encryption_needs_confirmation() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_db, service) = setup(dir.path()).await;
+        let guard = Arc::new(ConfirmationGuard::new());
+        guard.set_passphrase(None, "correct horse").unwrap();
+        let service = service
+            .with_config(SecureBackupConfig::default())
+            .with_confirmation_guard(guard.clone());
+
+        assert!(matches!(
+            service.set_encryption(false, None).await,
+            Err(DatabaseError::ValidationError(_))
+        ));
+        assert!(service.config.read().await.encrypt);
+
+        let grant = guard
+            .confirm(
+                DestructiveOperation::DisableEncryption,
+                &crate::security::confirmation::ConfirmationProof::Passphrase(
+                    "correct horse".into(),
+                ),
+            )
+            .unwrap();
+        service
+            .set_encryption(false, Some(&grant.token))
+            .await
+            .unwrap();
+        assert!(!service.config.read().await.encrypt);
+        // Turning it back on needs nothing
+        service.set_encryption(true, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_verify_records_outcome() {
+        let dir = tempfile::tempdir().unwrap();
+        let (db, service) = setup(dir.path()).await;
+
+        let full = service.create_manual_backup(None, None).await.unwrap();
+        db.read()
+            .await
+            .update_document_content("chapter-1", "Second draft")
+            .await
+            .unwrap();
+        let delta = service.create_incremental_backup(None, None).await.unwrap();
+
+        let verifications = service.verify_backups(None, None).await.unwrap();
+        assert_eq!(verifications.len(), 2);
+        assert!(verifications.iter().all(BackupVerification::passed));
+        let recorded = service.get_backup(&delta).await.unwrap().unwrap();
+        assert_eq!(recorded.restore_verified, Some(true));
+        assert_eq!(
+            recorded.verification_hash,
+            verifications[0].verification_hash
+        );
+        assert!(recorded.verified_at.is_some());
+        let restored_path = dir.path().join("a.db");
+        service.restore_to(&full, &restored_path).await.unwrap();
+        let restored = std::fs::read(&restored_path).unwrap();
+
+        // Damaging the full backup breaks every backup built on it
+        let full_backup = service.get_backup(&full).await.unwrap().unwrap();
+        let mut bytes = std::fs::read(&full_backup.file_path).unwrap();
+        bytes[200] ^= 0xff;
+        std::fs::write(&full_backup.file_path, bytes).unwrap();
+        let verification = service.verify_backup(&delta).await.unwrap();
+        assert!(!verification.archive_ok);
+        assert!(verification.error.unwrap().contains("checksum"));
+        let recorded = service.get_backup(&delta).await.unwrap().unwrap();
+        assert_eq!(recorded.restore_verified, Some(false));
+        assert!(recorded.verification_hash.is_none());
+
+        assert!(matches!(
+            service.verify_backup("missing").await,
+            Err(DatabaseError::RecordNotFound { .. })
+        ));
+
+        // A page SQLite can't make sense of fails the integrity check
+        let page_size = page_size(&restored).unwrap();
+        let mut damaged = restored;
+        for byte in &mut damaged[page_size..page_size + 16] {
+            *byte = 0xff;
+        }
+        assert!(!integrity_check(&damaged).await.unwrap().is_empty());
+    }
+}
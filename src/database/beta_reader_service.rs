@@ -0,0 +1,561 @@
+//! Beta Reader Service
+//!
+//! Builds per-reader watermarked packets (PDF or ePub) with labelled
+//! passages, and imports returned reader comments (CSV or Markdown forms)
+//! into the annotation layer anchored to the right passages.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::Entry, HashMap};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{
+    models::annotation::{Annotation, AnnotationSource},
+    models::attachment::AttachmentOwner,
+    models::beta_reader::*,
+    prosemirror, AnnotationService, AttachmentService, DatabaseError, DatabaseResult,
+    EnhancedDatabaseService,
+};
+use crate::publishing::{PublishFormat, PublishedDocument, PublishedSection};
+use crate::security::secrets_scanner::SecretsScanner;
+
+type BetaReaderRow = (String, String, String, Option<String>, String, String);
+
+/// Format of a returned comment form
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommentFormat {
+    /// Header row with `passage` and `comment` columns
+    Csv,
+    /// `## C1.P3` headings followed by the comment text
+    Markdown,
+}
+
+/// Service for beta reader packets and comment import
+#[derive(Debug)]
+pub struct BetaReaderService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    annotation_service: Arc<AnnotationService>,
+    secrets_scanner: Option<Arc<SecretsScanner>>,
+    attachments: Option<Arc<AttachmentService>>,
+}
+
+impl BetaReaderService {
+    /// Create a new beta reader service
+    pub fn new(
+        db_service: Arc<RwLock<EnhancedDatabaseService>>,
+        annotation_service: Arc<AnnotationService>,
+    ) -> Self {
+        Self {
+            db_service,
+            annotation_service,
+            secrets_scanner: None,
+            attachments: None,
+        }
+    }
+
+    /// Screen packet contents for secrets before they are rendered
+    pub fn with_secrets_scanner(mut self, scanner: Arc<SecretsScanner>) -> Self {
+        self.secrets_scanner = Some(scanner);
+        self
+    }
+
+    /// Include chapter attachments marked for export in packets
+    pub fn with_attachments(mut self, attachments: Arc<AttachmentService>) -> Self {
+        self.attachments = Some(attachments);
+        self
+    }
+
+    /// Initialize beta reader tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_BETA_READER_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create beta reader tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Register a beta reader on a project
+    pub async fn add_reader(
+        &self,
+        project_id: Uuid,
+        name: String,
+        email: Option<String>,
+    ) -> DatabaseResult<BetaReader> {
+        if name.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Reader name cannot be empty".to_string(),
+            ));
+        }
+
+        let id = Uuid::new_v4();
+        let reader = BetaReader {
+            id,
+            project_id,
+            name: name.trim().to_string(),
+            email,
+            reader_code: format!("BR-{}", &id.simple().to_string()[..6].to_uppercase()),
+            created_at: Utc::now(),
+        };
+
+        let db = self.db_service.read().await;
+        sqlx::query(INSERT_BETA_READER_SQL)
+            .bind(reader.id.to_string())
+            .bind(reader.project_id.to_string())
+            .bind(&reader.name)
+            .bind(&reader.email)
+            .bind(&reader.reader_code)
+            .bind(reader.created_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to add beta reader: {}", e)))?;
+
+        Ok(reader)
+    }
+
+    /// List beta readers on a project
+    pub async fn list_readers(&self, project_id: Uuid) -> DatabaseResult<Vec<BetaReader>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<BetaReaderRow> = sqlx::query_as(GET_BETA_READERS_SQL)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to list beta readers: {}", e)))?;
+
+        rows.into_iter().map(reader_from_row).collect()
+    }
+
+    /// Look up a reader by the code printed on their packet
+    pub async fn get_reader_by_code(
+        &self,
+        reader_code: &str,
+    ) -> DatabaseResult<Option<BetaReader>> {
+        let db = self.db_service.read().await;
+        let row: Option<BetaReaderRow> = sqlx::query_as(GET_BETA_READER_BY_CODE_SQL)
+            .bind(reader_code.trim().to_uppercase())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get beta reader: {}", e)))?;
+
+        row.map(reader_from_row).transpose()
+    }
+
+    /// Build a watermarked packet of the project for one reader
+    pub async fn build_packet(
+        &self,
+        reader: &BetaReader,
+        project_title: &str,
+        format: PublishFormat,
+    ) -> DatabaseResult<BetaPacket> {
+        let documents: Vec<(String, String, Option<String>, String)> = {
+            let db = self.db_service.read().await;
+            sqlx::query_as(
+                "SELECT id, title, content, document_type FROM documents
+                 WHERE project_id = ?1 AND is_active = 1
+                 ORDER BY created_at ASC",
+            )
+            .bind(reader.project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?
+        };
+
+        let watermark = format!(
+            "Beta copy for {} - {} - not for distribution",
+            reader.name, reader.reader_code
+        );
+        let mut document = PublishedDocument::new(project_title);
+        document.watermark = Some(watermark);
+
+        let mut passages = Vec::new();
+        for (chapter_index, (id, title, content, document_type)) in
+            documents.into_iter().enumerate()
+        {
+            let document_id = parse_uuid(&id)?;
+            let content = content.unwrap_or_default();
+            let (content, spans) = passage_spans(&document_type, &content);
+            let mut section = PublishedSection::new(title, Vec::new());
+
+            for (paragraph_index, (start, end)) in spans.into_iter().enumerate() {
+                let anchor = format!("C{}.P{}", chapter_index + 1, paragraph_index + 1);
+                let text = content[start..end].to_string();
+                section
+                    .paragraphs
+                    .push(text.split_whitespace().collect::<Vec<_>>().join(" "));
+                section.anchors.push(anchor.clone());
+                passages.push(PacketPassage {
+                    anchor,
+                    document_id,
+                    start,
+                    end,
+                    text,
+                });
+            }
+            document.sections.push(section);
+
+            if let Some(attachments) = &self.attachments {
+                document.attachments.extend(
+                    attachments
+                        .published_attachments(AttachmentOwner::Document, document_id)
+                        .await?,
+                );
+            }
+        }
+
+        if let Some(scanner) = &self.secrets_scanner {
+            document
+                .screen_secrets(scanner)
+                .map_err(|e| DatabaseError::Service(e.to_string()))?;
+        }
+
+        let bytes = document
+            .render(format)
+            .map_err(|e| DatabaseError::Service(format!("Failed to render packet: {}", e)))?;
+
+        let packet = BetaPacket {
+            id: Uuid::new_v4(),
+            project_id: reader.project_id,
+            reader_id: reader.id,
+            reader_code: reader.reader_code.clone(),
+            format,
+            created_at: Utc::now(),
+            passages,
+            bytes,
+        };
+
+        let passages_json = serde_json::to_string(&packet.passages)
+            .map_err(|e| DatabaseError::Service(format!("Failed to serialize passages: {}", e)))?;
+        let db = self.db_service.read().await;
+        sqlx::query(INSERT_BETA_PACKET_SQL)
+            .bind(packet.id.to_string())
+            .bind(packet.project_id.to_string())
+            .bind(packet.reader_id.to_string())
+            .bind(format.extension())
+            .bind(passages_json)
+            .bind(packet.created_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to record packet: {}", e)))?;
+
+        Ok(packet)
+    }
+
+    /// Import a reader's returned comments as annotations
+    pub async fn import_comments(
+        &self,
+        reader_code: &str,
+        input: &str,
+        format: CommentFormat,
+    ) -> DatabaseResult<CommentImportResult> {
+        let reader = self.get_reader_by_code(reader_code).await?.ok_or_else(|| {
+            DatabaseError::RecordNotFound {
+                entity: "beta reader".to_string(),
+                id: reader_code.to_string(),
+            }
+        })?;
+
+        let comments = match format {
+            CommentFormat::Csv => parse_csv_comments(input)?,
+            CommentFormat::Markdown => parse_markdown_comments(input),
+        };
+
+        // Later packets win when the same anchor appears in several
+        let mut passages: HashMap<String, PacketPassage> = HashMap::new();
+        {
+            let db = self.db_service.read().await;
+            let rows: Vec<(String,)> = sqlx::query_as(GET_PACKET_PASSAGES_SQL)
+                .bind(reader.id.to_string())
+                .fetch_all(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to load packets: {}", e)))?;
+            for (json,) in rows.into_iter().rev() {
+                let packet_passages: Vec<PacketPassage> = serde_json::from_str(&json)
+                    .map_err(|e| DatabaseError::Service(format!("Invalid passage map: {}", e)))?;
+                for passage in packet_passages {
+                    passages.insert(passage.anchor.to_uppercase(), passage);
+                }
+            }
+        }
+
+        let mut result = CommentImportResult::default();
+        let mut contents: HashMap<Uuid, String> = HashMap::new();
+
+        for comment in comments {
+            let Some(passage) = passages.get(&comment.anchor.to_uppercase()) else {
+                result.unmatched.push(comment);
+                continue;
+            };
+
+            // Passage offsets are into the document's text, not its JSON
+            if let Entry::Vacant(entry) = contents.entry(passage.document_id) {
+                let db = self.db_service.read().await;
+                let row: Option<(Option<String>, String)> = sqlx::query_as(
+                    "SELECT content, document_type FROM documents WHERE id = ?1 AND is_active = 1",
+                )
+                .bind(passage.document_id.to_string())
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to load document: {}", e)))?;
+                let text = row.map_or_else(String::new, |(content, document_type)| {
+                    prosemirror::document_text(&document_type, content.as_deref().unwrap_or(""))
+                });
+                entry.insert(text);
+            }
+            let content = &contents[&passage.document_id];
+
+            // Passages may have moved since the packet was generated
+            let span = match content.find(&passage.text) {
+                Some(start) => (start, start + passage.text.len()),
+                None => {
+                    result.relocated_failed += 1;
+                    let start = passage.start.min(content.len());
+                    if content.is_char_boundary(start) {
+                        (start, start)
+                    } else {
+                        (0, 0)
+                    }
+                }
+            };
+
+            let mut annotation = Annotation::new(
+                reader.project_id,
+                passage.document_id,
+                span,
+                passage.text.clone(),
+                comment.comment.clone(),
+                reader.name.clone(),
+                AnnotationSource::BetaReader,
+            );
+            annotation.reader_id = Some(reader.id);
+            annotation.passage_anchor = Some(passage.anchor.clone());
+            self.annotation_service
+                .create_annotation(&annotation)
+                .await?;
+            result.imported += 1;
+        }
+
+        Ok(result)
+    }
+}
+
+/// Text of a stored document and the byte spans of its paragraphs in that
+/// text: one per block of ProseMirror JSON, blank-line separated otherwise
+fn passage_spans(document_type: &str, content: &str) -> (String, Vec<(usize, usize)>) {
+    let Some(doc) = prosemirror::parse(document_type, content) else {
+        return (content.to_string(), paragraph_spans(content));
+    };
+    let text = prosemirror::plain_text(&doc);
+    let starts = prosemirror::block_starts(&doc);
+    let spans = starts
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(text.len());
+            let block = &text[start..end];
+            let trimmed = block.trim();
+            let start = start + (block.len() - block.trim_start().len());
+            (!trimmed.is_empty()).then_some((start, start + trimmed.len()))
+        })
+        .collect();
+    (text, spans)
+}
+
+/// Byte spans of blank-line separated paragraphs, trimmed of surrounding whitespace
+fn paragraph_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for block in text.split("\n\n") {
+        let leading = block.len() - block.trim_start().len();
+        let trimmed = block.trim();
+        if !trimmed.is_empty() {
+            let start = offset + leading;
+            spans.push((start, start + trimmed.len()));
+        }
+        offset += block.len() + 2;
+    }
+    spans
+}
+
+/// Parse a CSV comment form with `passage` and `comment` columns
+fn parse_csv_comments(input: &str) -> DatabaseResult<Vec<ReaderComment>> {
+    let records = parse_csv(input);
+    let mut rows = records.into_iter();
+    let header = rows
+        .next()
+        .ok_or_else(|| DatabaseError::ValidationError("Comment CSV is empty".to_string()))?;
+
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|h| names.contains(&h.trim().to_lowercase().as_str()))
+    };
+    let anchor_col = column(&["passage", "anchor", "location"]).ok_or_else(|| {
+        DatabaseError::ValidationError("Comment CSV needs a 'passage' column".to_string())
+    })?;
+    let comment_col = column(&["comment", "comments", "note"]).ok_or_else(|| {
+        DatabaseError::ValidationError("Comment CSV needs a 'comment' column".to_string())
+    })?;
+
+    Ok(rows
+        .filter_map(|row| {
+            let anchor = normalize_anchor(row.get(anchor_col)?);
+            let comment = row.get(comment_col)?.trim().to_string();
+            (!anchor.is_empty() && !comment.is_empty()).then_some(ReaderComment { anchor, comment })
+        })
+        .collect())
+}
+
+/// Parse a Markdown comment form: `## C1.P3` headings followed by comment text.
+/// Quoted lines (`> ...`) are ignored so readers can paste the passage.
+fn parse_markdown_comments(input: &str) -> Vec<ReaderComment> {
+    let mut comments = Vec::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+
+    let mut flush = |current: &mut Option<(String, Vec<&str>)>| {
+        if let Some((anchor, lines)) = current.take() {
+            let comment = lines.join("\n").trim().to_string();
+            if !comment.is_empty() {
+                comments.push(ReaderComment { anchor, comment });
+            }
+        }
+    };
+
+    for line in input.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') {
+            flush(&mut current);
+            let anchor = normalize_anchor(trimmed.trim_start_matches('#'));
+            if !anchor.is_empty() {
+                current = Some((anchor, Vec::new()));
+            }
+        } else if trimmed.starts_with('>') {
+            continue;
+        } else if let Some((_, lines)) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+    flush(&mut current);
+
+    comments
+}
+
+fn normalize_anchor(raw: &str) -> String {
+    raw.trim()
+        .trim_matches(|c| c == '[' || c == ']')
+        .trim()
+        .to_uppercase()
+}
+
+/// Minimal RFC 4180 parser: quoted fields, escaped quotes and embedded newlines
+pub(crate) fn parse_csv(input: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|f| !f.is_empty()) {
+                    records.push(std::mem::take(&mut record));
+                } else {
+                    record.clear();
+                }
+            }
+            (c, _) => field.push(c),
+        }
+    }
+    record.push(field);
+    if record.iter().any(|f| !f.is_empty()) {
+        records.push(record);
+    }
+
+    records
+}
+
+fn reader_from_row(row: BetaReaderRow) -> DatabaseResult<BetaReader> {
+    let (id, project_id, name, email, reader_code, created_at) = row;
+    Ok(BetaReader {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        name,
+        email,
+        reader_code,
+        created_at: parse_time(&created_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paragraph_spans_track_offsets() {
+        let text = "First para.\n\n  Second para.\n\n\n\nThird.";
+        let spans = paragraph_spans(text);
+        assert_eq!(spans.len(), 3);
+        assert_eq!(&text[spans[1].0..spans[1].1], "Second para.");
+        assert_eq!(&text[spans[2].0..spans[2].1], "Third.");
+    }
+
+    #[test]
+    fn test_passage_spans_follow_editor_blocks() {
+        let content = serde_json::json!({
+            "type": "doc",
+            "content": [
+                { "type": "heading", "content": [{ "type": "text", "text": "One" }] },
+                { "type": "paragraph" },
+                { "type": "paragraph", "content": [{ "type": "text", "text": "Mara ran home." }] }
+            ]
+        })
+        .to_string();
+        let (text, spans) = passage_spans("json", &content);
+        assert_eq!(spans.len(), 2);
+        assert_eq!(&text[spans[0].0..spans[0].1], "One");
+        assert_eq!(&text[spans[1].0..spans[1].1], "Mara ran home.");
+    }
+
+    #[test]
+    fn test_parse_csv_comments_with_quotes() {
+        let csv = "Passage,Comment\nc1.p2,\"Loved this, \"\"really\"\"\"\n[C2.P1],\"Line one\nline two\"\n";
+        let comments = parse_csv_comments(csv).unwrap();
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].anchor, "C1.P2");
+        assert_eq!(comments[0].comment, "Loved this, \"really\"");
+        assert_eq!(comments[1].comment, "Line one\nline two");
+    }
+
+    #[test]
+    fn test_parse_markdown_comments_skips_quotes() {
+        let md = "# Notes from Sam\n\n## C1.P1\n> The night was dark\nToo cliched?\n\n## C3.P4\n\n## C3.P5\nGreat twist.";
+        let comments = parse_markdown_comments(md);
+        assert_eq!(
+            comments,
+            vec![
+                ReaderComment {
+                    anchor: "C1.P1".to_string(),
+                    comment: "Too cliched?".to_string()
+                },
+                ReaderComment {
+                    anchor: "C3.P5".to_string(),
+                    comment: "Great twist.".to_string()
+                },
+            ]
+        );
+    }
+}
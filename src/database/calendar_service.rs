@@ -0,0 +1,366 @@
+//! Calendar Service
+//!
+//! Stores a project's fictional calendars and lays its dated events out on
+//! one timeline. Each calendar sits on a shared day line through its epoch
+//! offset, so events dated in different calendars still come out in order.
+
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::parse::parse_uuid;
+use crate::database::{
+    models::calendar::*, models::codex::TimeData, DatabaseError, DatabaseResult,
+    EnhancedDatabaseService,
+};
+
+/// Service for calendars and timelines
+#[derive(Debug)]
+pub struct CalendarService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl CalendarService {
+    /// Create a new calendar service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the calendars table
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_CALENDARS_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create calendars table: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Create or update a calendar
+    pub async fn save_calendar(&self, calendar: &CalendarSystem) -> DatabaseResult<()> {
+        calendar.validate()?;
+        let definition = serde_json::to_string(calendar)
+            .map_err(|e| DatabaseError::Service(format!("Failed to serialize calendar: {}", e)))?;
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_CALENDAR_SQL)
+            .bind(calendar.id.to_string())
+            .bind(calendar.project_id.to_string())
+            .bind(&calendar.name)
+            .bind(definition)
+            .bind(calendar.created_at.to_rfc3339())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save calendar: {}", e)))?;
+        Ok(())
+    }
+
+    /// Get a calendar by ID
+    pub async fn get_calendar(&self, calendar_id: Uuid) -> DatabaseResult<Option<CalendarSystem>> {
+        let db = self.db_service.read().await;
+        let definition: Option<String> =
+            sqlx::query_scalar("SELECT definition FROM calendars WHERE id = ?1")
+                .bind(calendar_id.to_string())
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to get calendar: {}", e)))?;
+        definition.as_deref().map(calendar_from_json).transpose()
+    }
+
+    /// Calendars of a project, by name
+    pub async fn list_calendars(&self, project_id: Uuid) -> DatabaseResult<Vec<CalendarSystem>> {
+        let db = self.db_service.read().await;
+        let definitions: Vec<String> = sqlx::query_scalar(
+            "SELECT definition FROM calendars WHERE project_id = ?1 ORDER BY name ASC",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to list calendars: {}", e)))?;
+        definitions.iter().map(|d| calendar_from_json(d)).collect()
+    }
+
+    /// Delete a calendar
+    pub async fn delete_calendar(&self, calendar_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM calendars WHERE id = ?1")
+            .bind(calendar_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete calendar: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Order and check events against the project's calendars
+    pub async fn build_timeline(
+        &self,
+        project_id: Uuid,
+        events: &[TimelineEvent],
+    ) -> DatabaseResult<Timeline> {
+        let calendars = self.list_calendars(project_id).await?;
+        Ok(place_events(&calendars, events))
+    }
+
+    /// Timeline of the project's Time codex entries, whose time data is kept
+    /// in the entry metadata
+    pub async fn project_timeline(&self, project_id: Uuid) -> DatabaseResult<Timeline> {
+        let rows: Vec<(String, String, Option<String>)> = {
+            let db = self.db_service.read().await;
+            let has_codex: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'codex_entries'",
+            )
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to check codex: {}", e)))?;
+            if has_codex == 0 {
+                return Ok(Timeline::default());
+            }
+            sqlx::query_as(
+                "SELECT id, title, metadata FROM codex_entries
+                 WHERE project_id = ?1 AND entry_type = 'time' AND is_active = 1
+                 ORDER BY sort_order, title",
+            )
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load time entries: {}", e)))?
+        };
+
+        let events: Vec<TimelineEvent> = rows
+            .into_iter()
+            .filter_map(|(id, title, metadata)| {
+                let time: TimeData = serde_json::from_str(metadata.as_deref()?).ok()?;
+                Some((id, title, time))
+            })
+            .map(|(id, title, time)| {
+                Ok(TimelineEvent {
+                    id: parse_uuid(&id)?,
+                    title,
+                    time,
+                })
+            })
+            .collect::<DatabaseResult<_>>()?;
+        self.build_timeline(project_id, &events).await
+    }
+}
+
+fn calendar_from_json(definition: &str) -> DatabaseResult<CalendarSystem> {
+    serde_json::from_str(definition)
+        .map_err(|e| DatabaseError::Service(format!("Invalid calendar definition: {}", e)))
+}
+
+/// Place events on the shared day line, earliest first. Events without a
+/// usable start are reported and left off.
+pub fn place_events(calendars: &[CalendarSystem], events: &[TimelineEvent]) -> Timeline {
+    let by_id: HashMap<Uuid, &CalendarSystem> = calendars.iter().map(|c| (c.id, c)).collect();
+    let mut timeline = Timeline::default();
+
+    for event in events {
+        let mut issue = |kind: TimelineIssueKind, message: String| {
+            timeline.issues.push(TimelineIssue {
+                event_id: event.id,
+                kind,
+                message,
+            })
+        };
+        let time = &event.time;
+        if time.start_date.is_none()
+            && time.start_time.is_none()
+            && time.end_date.is_none()
+            && time.end_time.is_none()
+        {
+            continue;
+        }
+
+        let calendar = match time.calendar_id {
+            Some(id) => match by_id.get(&id) {
+                Some(calendar) => *calendar,
+                None => {
+                    issue(
+                        TimelineIssueKind::UnknownCalendar,
+                        format!("\"{}\" uses a calendar that no longer exists", event.title),
+                    );
+                    continue;
+                }
+            },
+            None if calendars.len() == 1 => &calendars[0],
+            None => {
+                issue(
+                    TimelineIssueKind::NoCalendar,
+                    format!("\"{}\" needs a calendar for its dates", event.title),
+                );
+                continue;
+            }
+        };
+
+        let read = |date: Option<CalendarDate>, text: Option<&String>| match (
+            date,
+            text.filter(|t| !t.trim().is_empty()),
+        ) {
+            (Some(date), _) => calendar.shared_day(&date).map(|day| Some((date, day))),
+            (None, Some(text)) => {
+                let date = calendar.parse_date(text)?;
+                Ok(Some((date, calendar.shared_day(&date)?)))
+            }
+            (None, None) => Ok(None),
+        };
+        let start = read(time.start_date, time.start_time.as_ref());
+        let end = read(time.end_date, time.end_time.as_ref());
+        let (start, end) = match (start, end) {
+            (Ok(Some(start)), Ok(end)) => (start, end),
+            (Ok(None), Ok(_)) => {
+                issue(
+                    TimelineIssueKind::NoStart,
+                    format!("\"{}\" has an end but no start", event.title),
+                );
+                continue;
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                issue(
+                    TimelineIssueKind::InvalidDate,
+                    format!("\"{}\": {}", event.title, e),
+                );
+                continue;
+            }
+        };
+        if end.is_some_and(|(_, end_day)| end_day < start.1) {
+            issue(
+                TimelineIssueKind::EndBeforeStart,
+                format!("\"{}\" ends before it starts", event.title),
+            );
+        }
+
+        let label = |date: &CalendarDate| calendar.format_date(date).unwrap_or_default();
+        timeline.entries.push(TimelineEntry {
+            event_id: event.id,
+            title: event.title.clone(),
+            calendar_id: calendar.id,
+            start: start.0,
+            end: end.map(|(date, _)| date),
+            start_day: start.1,
+            end_day: end.map(|(_, day)| day),
+            start_label: label(&start.0),
+            end_label: end.map(|(date, _)| label(&date)),
+        });
+    }
+
+    timeline.entries.sort_by_key(|e| (e.start_day, e.end_day));
+    timeline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    fn time(calendar_id: Option<Uuid>, start: Option<&str>, end: Option<&str>) -> TimeData {
+        TimeData {
+            start_time: start.map(str::to_string),
+            end_time: end.map(str::to_string),
+            duration: None,
+            calendar_system: None,
+            season: None,
+            historical_context: None,
+            era: None,
+            calendar_id,
+            start_date: None,
+            end_date: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timeline_across_calendars() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Saga', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let service = CalendarService::new(Arc::new(RwLock::new(db)));
+        service.initialize().await.unwrap();
+
+        let mut elven = CalendarSystem::new(
+            project,
+            "Elven",
+            vec![
+                CalendarMonth::new("Bloom", 100),
+                CalendarMonth::new("Fall", 100),
+            ],
+        );
+        elven.era_name = "EA".to_string();
+        let mut human =
+            CalendarSystem::new(project, "Human", vec![CalendarMonth::new("Year", 365)]);
+        // Human year 1 began on elven day 1000
+        human.epoch_offset = 1000;
+        service.save_calendar(&elven).await.unwrap();
+        service.save_calendar(&human).await.unwrap();
+        assert_eq!(service.list_calendars(project).await.unwrap().len(), 2);
+        elven.months.clear();
+        assert!(service.save_calendar(&elven).await.is_err());
+
+        let events = vec![
+            TimelineEvent {
+                id: Uuid::new_v4(),
+                title: "Coronation".to_string(),
+                time: time(Some(human.id), Some("1 Year 1"), Some("1-1-10")),
+            },
+            TimelineEvent {
+                id: Uuid::new_v4(),
+                title: "Founding".to_string(),
+                time: time(Some(elven.id), Some("5 Fall 5 EA"), None),
+            },
+            TimelineEvent {
+                id: Uuid::new_v4(),
+                title: "Siege".to_string(),
+                time: time(Some(elven.id), Some("1-2-10"), Some("1-1-1")),
+            },
+            TimelineEvent {
+                id: Uuid::new_v4(),
+                title: "Undated".to_string(),
+                time: time(None, Some("1-1-1"), None),
+            },
+            TimelineEvent {
+                id: Uuid::new_v4(),
+                title: "Typo".to_string(),
+                time: time(Some(elven.id), Some("1-3-1"), None),
+            },
+        ];
+        let timeline = service.build_timeline(project, &events).await.unwrap();
+        let order: Vec<(&str, i64)> = timeline
+            .entries
+            .iter()
+            .map(|e| (e.title.as_str(), e.start_day))
+            .collect();
+        assert_eq!(
+            order,
+            vec![("Siege", 109), ("Founding", 904), ("Coronation", 1000)]
+        );
+        assert_eq!(timeline.entries[1].start_label, "5 Fall 5 EA");
+        assert_eq!(timeline.entries[2].end_day, Some(1009));
+        let issues: Vec<TimelineIssueKind> = timeline.issues.iter().map(|i| i.kind).collect();
+        assert_eq!(
+            issues,
+            vec![
+                TimelineIssueKind::EndBeforeStart,
+                TimelineIssueKind::NoCalendar,
+                TimelineIssueKind::InvalidDate,
+            ]
+        );
+
+        assert!(service.delete_calendar(human.id).await.unwrap());
+        assert!(service.get_calendar(human.id).await.unwrap().is_none());
+    }
+}
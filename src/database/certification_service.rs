@@ -0,0 +1,428 @@
+//! Certification Service
+//!
+//! Issues word count certificates for challenges such as NaNoWriMo and for
+//! contest entries that ask for a verified count. A certificate lists every
+//! active document's count and content hash with the project total and the
+//! time of issue, signed with a key this installation keeps in the OS
+//! keyring. Issued certificates are stored so they can be checked later,
+//! both for tampering and against the project's current text, and can be
+//! exported as a one-page PDF.
+
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::compliance::{sign_report_bytes, verify_report_signature};
+use crate::database::parse::parse_uuid;
+use crate::database::{
+    models::certification::*, DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+use crate::publishing::{PdfBuilder, PdfFont, PdfTextStyle};
+use crate::security::secure_storage::SecureStorageService;
+
+/// Keyring entry holding the hex signing key
+const SIGNING_KEY_ENTRY: &str = "word_count_certification_key";
+
+/// Service for issuing and checking word count certificates
+pub struct CertificationService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    signing_key: Vec<u8>,
+}
+
+impl std::fmt::Debug for CertificationService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CertificationService")
+            .finish_non_exhaustive()
+    }
+}
+
+impl CertificationService {
+    /// Create a new certification service. Until `with_storage` is used
+    /// the signing key lasts only as long as the process.
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self {
+            db_service,
+            signing_key: random_key(),
+        }
+    }
+
+    /// Sign with the installation's key from the OS keyring, creating it on
+    /// first use
+    pub fn with_storage(mut self, storage: Arc<SecureStorageService>) -> Self {
+        let stored = storage
+            .find_api_key(SIGNING_KEY_ENTRY)
+            .ok()
+            .flatten()
+            .and_then(|hex| decode_hex(&hex));
+        match stored {
+            Some(key) => self.signing_key = key,
+            None => {
+                let hex = encode_hex(&self.signing_key);
+                if let Err(e) = storage.set_api_key(SIGNING_KEY_ENTRY, &hex) {
+                    log::warn!("Failed to store the certificate signing key: {}", e);
+                }
+            }
+        }
+        self
+    }
+
+    /// Initialize the certificates table
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_CERTIFICATES_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create certificates table: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Certify the project's current word count and keep the certificate
+    pub async fn certify(&self, project_id: Uuid) -> DatabaseResult<WordCountCertificate> {
+        let project_name = self.project_name(project_id).await?;
+        let documents = self.documents(project_id).await?;
+        if documents.is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "The project has no documents to certify".to_string(),
+            ));
+        }
+        let certificate = issue_certificate(
+            project_id,
+            project_name,
+            &documents,
+            Utc::now(),
+            &self.signing_key,
+        );
+
+        let json = serde_json::to_string(&certificate).map_err(|e| {
+            DatabaseError::Service(format!("Failed to serialize certificate: {}", e))
+        })?;
+        let db = self.db_service.read().await;
+        sqlx::query(INSERT_CERTIFICATE_SQL)
+            .bind(certificate.id.to_string())
+            .bind(project_id.to_string())
+            .bind(certificate.issued_at.to_rfc3339())
+            .bind(certificate.total_words as i64)
+            .bind(json)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save certificate: {}", e)))?;
+        Ok(certificate)
+    }
+
+    /// A project's certificates, newest first
+    pub async fn list_certificates(
+        &self,
+        project_id: Uuid,
+    ) -> DatabaseResult<Vec<WordCountCertificate>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT certificate FROM word_count_certificates WHERE project_id = ?1 ORDER BY issued_at DESC",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to list certificates: {}", e)))?;
+        rows.into_iter()
+            .map(|(json,)| parse_certificate(&json))
+            .collect()
+    }
+
+    /// A stored certificate
+    pub async fn certificate(&self, certificate_id: Uuid) -> DatabaseResult<WordCountCertificate> {
+        let db = self.db_service.read().await;
+        let json: Option<String> =
+            sqlx::query_scalar("SELECT certificate FROM word_count_certificates WHERE id = ?1")
+                .bind(certificate_id.to_string())
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to load certificate: {}", e))
+                })?;
+        let json = json.ok_or_else(|| DatabaseError::RecordNotFound {
+            entity: "certificate".to_string(),
+            id: certificate_id.to_string(),
+        })?;
+        parse_certificate(&json)
+    }
+
+    /// Check a stored certificate's signature and compare it with the
+    /// project's current text
+    pub async fn verify(&self, certificate_id: Uuid) -> DatabaseResult<CertificateVerification> {
+        let certificate = self.certificate(certificate_id).await?;
+        let current = self.documents(certificate.project_id).await?;
+        Ok(verify_certificate(
+            &certificate,
+            &current,
+            &self.signing_key,
+        ))
+    }
+
+    /// Write a stored certificate as a PDF
+    pub async fn export_pdf(
+        &self,
+        certificate_id: Uuid,
+        path: &Path,
+    ) -> DatabaseResult<WordCountCertificate> {
+        let certificate = self.certificate(certificate_id).await?;
+        tokio::fs::write(path, render_certificate_pdf(&certificate))
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to write certificate: {}", e)))?;
+        Ok(certificate)
+    }
+
+    async fn project_name(&self, project_id: Uuid) -> DatabaseResult<String> {
+        let db = self.db_service.read().await;
+        let name: Option<String> = sqlx::query_scalar("SELECT name FROM projects WHERE id = ?1")
+            .bind(project_id.to_string())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load project: {}", e)))?;
+        name.ok_or_else(|| DatabaseError::RecordNotFound {
+            entity: "project".to_string(),
+            id: project_id.to_string(),
+        })
+    }
+
+    /// Active documents in binder order as (id, title, text)
+    async fn documents(&self, project_id: Uuid) -> DatabaseResult<Vec<(Uuid, String, String)>> {
+        let db = self.db_service.read().await;
+        let has_binder: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'binder_order'",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check schema: {}", e)))?;
+        let sql = if has_binder > 0 {
+            "SELECT d.id, d.title, d.content FROM documents d LEFT JOIN binder_order b ON b.document_id = d.id
+             WHERE d.project_id = ?1 AND d.is_active = 1
+             ORDER BY b.position IS NULL, b.position, d.created_at, d.title"
+        } else {
+            "SELECT id, title, content FROM documents WHERE project_id = ?1 AND is_active = 1 ORDER BY created_at, title"
+        };
+        let rows: Vec<(String, String, Option<String>)> = sqlx::query_as(sql)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+
+        rows.into_iter()
+            .map(|(id, title, content)| {
+                let id = parse_uuid(&id)?;
+                Ok((id, title, content.unwrap_or_default()))
+            })
+            .collect()
+    }
+}
+
+/// Count, hash and sign documents given as (id, title, text)
+pub fn issue_certificate(
+    project_id: Uuid,
+    project_name: String,
+    documents: &[(Uuid, String, String)],
+    issued_at: DateTime<Utc>,
+    key: &[u8],
+) -> WordCountCertificate {
+    let documents: Vec<CertifiedDocument> = documents
+        .iter()
+        .map(|(id, title, text)| CertifiedDocument {
+            document_id: *id,
+            title: title.clone(),
+            word_count: text.split_whitespace().count() as u64,
+            content_hash: content_hash(text),
+        })
+        .collect();
+    let mut certificate = WordCountCertificate {
+        id: Uuid::new_v4(),
+        project_id,
+        project_name,
+        issued_at,
+        total_words: documents.iter().map(|d| d.word_count).sum(),
+        content_hash: combined_hash(&documents),
+        documents,
+        signature: String::new(),
+    };
+    certificate.signature = sign_report_bytes(certificate.signed_text().as_bytes(), key);
+    certificate
+}
+
+/// Check a certificate's signature and which of its documents no longer
+/// match `current`, given as (id, title, text)
+pub fn verify_certificate(
+    certificate: &WordCountCertificate,
+    current: &[(Uuid, String, String)],
+    key: &[u8],
+) -> CertificateVerification {
+    let signature_valid = verify_report_signature(
+        certificate.signed_text().as_bytes(),
+        &certificate.signature,
+        key,
+    );
+    let changed_documents: Vec<Uuid> = certificate
+        .documents
+        .iter()
+        .filter(|certified| {
+            !current.iter().any(|(id, _, text)| {
+                *id == certified.document_id && content_hash(text) == certified.content_hash
+            })
+        })
+        .map(|certified| certified.document_id)
+        .collect();
+
+    CertificateVerification {
+        certificate_id: certificate.id,
+        signature_valid,
+        certified_words: certificate.total_words,
+        current_words: current
+            .iter()
+            .map(|(_, _, text)| text.split_whitespace().count() as u64)
+            .sum(),
+        text_unchanged: changed_documents.is_empty(),
+        changed_documents,
+    }
+}
+
+/// A one-page certificate listing the counts, hashes and signature
+pub fn render_certificate_pdf(certificate: &WordCountCertificate) -> Vec<u8> {
+    let issued = certificate
+        .issued_at
+        .format("%Y-%m-%d %H:%M:%S UTC")
+        .to_string();
+    let mut pdf = PdfBuilder::new()
+        .title(format!(
+            "Word Count Certificate - {}",
+            certificate.project_name
+        ))
+        .author("Herding Cats")
+        .footer(format!(
+            "Certificate {} - issued {}",
+            certificate.id, issued
+        ));
+    let bold = PdfTextStyle {
+        font: PdfFont::Bold,
+        size: 14.0,
+        ..PdfTextStyle::default()
+    };
+    let mono = PdfTextStyle {
+        font: PdfFont::Mono,
+        size: 8.0,
+        ..PdfTextStyle::default()
+    };
+
+    pdf.heading(1, "Word Count Certificate")
+        .paragraph(&format!("Project: {}", certificate.project_name))
+        .paragraph(&format!("Issued: {}", issued))
+        .styled_paragraph(&format!("Total: {} words", certificate.total_words), bold);
+
+    pdf.heading(2, "Documents");
+    for document in &certificate.documents {
+        pdf.paragraph(&format!(
+            "{} - {} words",
+            document.title, document.word_count
+        ))
+        .styled_paragraph(&format!("SHA-256 {}", document.content_hash), mono);
+    }
+
+    pdf.heading(2, "Verification")
+        .paragraph("Content hash over all documents:")
+        .styled_paragraph(&certificate.content_hash, mono)
+        .paragraph("Signature (HMAC-SHA256):")
+        .styled_paragraph(&certificate.signature, mono)
+        .paragraph(&format!("Certificate ID: {}", certificate.id));
+    pdf.build()
+}
+
+/// Hex SHA-256 of a document's text
+fn content_hash(text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(text.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Hex SHA-256 over the document hashes in order
+fn combined_hash(documents: &[CertifiedDocument]) -> String {
+    let mut hasher = Sha256::new();
+    for document in documents {
+        hasher.update(document.content_hash.as_bytes());
+        hasher.update([b'\n']);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn parse_certificate(json: &str) -> DatabaseResult<WordCountCertificate> {
+    serde_json::from_str(json)
+        .map_err(|e| DatabaseError::Service(format!("Invalid certificate: {}", e)))
+}
+
+fn random_key() -> Vec<u8> {
+    let mut key = vec![0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    key
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim();
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_certificate_signs_and_detects_changes() {
+        let key = b"certification-key";
+        let project = Uuid::new_v4();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let documents = vec![
+            (
+                first,
+                "Chapter 1".to_string(),
+                "It was a dark and stormy night.".to_string(),
+            ),
+            (
+                second,
+                "Chapter 2".to_string(),
+                "The rain  fell\nin torrents.".to_string(),
+            ),
+        ];
+
+        let certificate =
+            issue_certificate(project, "Storm".to_string(), &documents, Utc::now(), key);
+        assert_eq!(certificate.total_words, 12);
+        assert_eq!(certificate.documents[1].word_count, 5);
+
+        let check = verify_certificate(&certificate, &documents, key);
+        assert!(check.signature_valid && check.text_unchanged);
+        assert!(!verify_certificate(&certificate, &documents, b"other-key").signature_valid);
+
+        let mut inflated = certificate.clone();
+        inflated.total_words = 50_000;
+        assert!(!verify_certificate(&inflated, &documents, key).signature_valid);
+
+        let mut edited = documents.clone();
+        edited[1].2.push_str(" Lightning struck.");
+        let check = verify_certificate(&certificate, &edited, key);
+        assert!(check.signature_valid);
+        assert_eq!(check.changed_documents, vec![second]);
+        assert_eq!(check.current_words, 14);
+
+        assert!(render_certificate_pdf(&certificate).starts_with(b"%PDF"));
+        assert_eq!(decode_hex(&encode_hex(key)), Some(key.to_vec()));
+    }
+}
@@ -0,0 +1,449 @@
+//! Challenge Service
+//!
+//! Runs NaNoWriMo-style writing challenges on top of the writing sessions
+//! the stats service records. The dashboard shows words written against the
+//! even pace, a projection at the current average and how much is still
+//! needed today. Reaching 10, 25, 50, 75 and 100 percent of the target is
+//! celebrated with a notification, once per milestone.
+
+use chrono::{Duration, NaiveDate, Utc};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::automation::NotificationLevel;
+use crate::database::models::stats::WritingSession;
+use crate::database::{
+    models::challenge::*,
+    parse::{parse_time, parse_uuid},
+    DatabaseError, DatabaseResult, EnhancedDatabaseService, StatsService,
+};
+use crate::notifications::{DesktopNotification, Notifier};
+
+type ChallengeRow = (
+    String,
+    String,
+    String,
+    i64,
+    String,
+    String,
+    String,
+    String,
+    String,
+);
+
+/// Service for writing challenges and their dashboards
+#[derive(Debug)]
+pub struct ChallengeService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    stats: Arc<StatsService>,
+}
+
+impl ChallengeService {
+    /// Create a new challenge service reading progress from `stats`
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>, stats: Arc<StatsService>) -> Self {
+        Self { db_service, stats }
+    }
+
+    /// Initialize the challenges table
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_CHALLENGES_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create challenges table: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Save a challenge
+    pub async fn save_challenge(&self, challenge: &Challenge) -> DatabaseResult<Challenge> {
+        if challenge.name.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Challenge name cannot be empty".to_string(),
+            ));
+        }
+        if challenge.target_words == 0 {
+            return Err(DatabaseError::ValidationError(
+                "Challenge target must be at least one word".to_string(),
+            ));
+        }
+        if challenge.end_date < challenge.start_date {
+            return Err(DatabaseError::ValidationError(
+                "Challenge ends before it starts".to_string(),
+            ));
+        }
+        let mut saved = challenge.clone();
+        saved.updated_at = Utc::now();
+        self.write_challenge(&saved).await?;
+        Ok(saved)
+    }
+
+    /// Delete a challenge
+    pub async fn delete_challenge(&self, challenge_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM writing_challenges WHERE id = ?1")
+            .bind(challenge_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete challenge: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A project's challenges, latest first
+    pub async fn list_challenges(&self, project_id: Uuid) -> DatabaseResult<Vec<Challenge>> {
+        self.challenges(Some(project_id)).await
+    }
+
+    /// A challenge's progress, celebrating any milestone newly reached
+    pub async fn dashboard(&self, challenge_id: Uuid) -> DatabaseResult<ChallengeDashboard> {
+        let challenge = self
+            .challenges(None)
+            .await?
+            .into_iter()
+            .find(|c| c.id == challenge_id)
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "challenge".to_string(),
+                id: challenge_id.to_string(),
+            })?;
+        let mut dashboard = self.progress(challenge).await?;
+        dashboard.challenge = self
+            .celebrate(dashboard.challenge.clone(), &dashboard)
+            .await?;
+        Ok(dashboard)
+    }
+
+    /// Celebrate milestones reached in running challenges. Returns how many
+    /// notifications were shown.
+    pub async fn check_milestones(&self) -> DatabaseResult<usize> {
+        let today = Utc::now().date_naive();
+        let mut shown = 0;
+        for challenge in self.challenges(None).await? {
+            if challenge.start_date > today || challenge.end_date + Duration::days(1) < today {
+                continue;
+            }
+            let before = challenge.milestones_reached.len();
+            let dashboard = self.progress(challenge).await?;
+            let challenge = self
+                .celebrate(dashboard.challenge.clone(), &dashboard)
+                .await?;
+            if challenge.milestones_reached.len() > before {
+                shown += 1;
+            }
+        }
+        Ok(shown)
+    }
+
+    /// Check for milestones now and then every `every`
+    pub fn spawn_milestone_checks(
+        self: Arc<Self>,
+        every: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.check_milestones().await {
+                    log::error!("Challenge milestone check failed: {}", e);
+                }
+            }
+        })
+    }
+
+    async fn progress(&self, challenge: Challenge) -> DatabaseResult<ChallengeDashboard> {
+        let from = challenge
+            .start_date
+            .and_hms_opt(0, 0, 0)
+            .map(|t| t.and_utc());
+        let to = (challenge.end_date + Duration::days(1))
+            .and_hms_opt(0, 0, 0)
+            .map(|t| t.and_utc());
+        let sessions = self.stats.sessions(challenge.project_id, from, to).await?;
+        Ok(challenge_dashboard(
+            challenge,
+            &sessions,
+            Utc::now().date_naive(),
+        ))
+    }
+
+    /// Notify about milestones the dashboard shows as newly reached and
+    /// record them on the challenge
+    async fn celebrate(
+        &self,
+        mut challenge: Challenge,
+        dashboard: &ChallengeDashboard,
+    ) -> DatabaseResult<Challenge> {
+        let reached = new_milestones(&challenge, dashboard.words_written);
+        let Some(&highest) = reached.last() else {
+            return Ok(challenge);
+        };
+
+        let message = if highest >= 100 {
+            format!(
+                "You reached {} words and won {}!",
+                challenge.target_words, challenge.name
+            )
+        } else {
+            format!(
+                "{}% of the way through {}: {} of {} words",
+                highest, challenge.name, dashboard.words_written, challenge.target_words
+            )
+        };
+        let notification =
+            DesktopNotification::new("Challenge milestone", message, NotificationLevel::Success);
+        if let Err(e) = Notifier::global().notify(&notification) {
+            log::warn!("Failed to show challenge milestone: {}", e);
+        }
+
+        challenge.milestones_reached.extend(reached);
+        challenge.updated_at = Utc::now();
+        self.write_challenge(&challenge).await?;
+        Ok(challenge)
+    }
+
+    async fn challenges(&self, project_id: Option<Uuid>) -> DatabaseResult<Vec<Challenge>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<ChallengeRow> = match project_id {
+            Some(project_id) => {
+                sqlx::query_as(&format!(
+                    "{} WHERE project_id = ?1 ORDER BY start_date DESC",
+                    SELECT_CHALLENGES_SQL
+                ))
+                .bind(project_id.to_string())
+                .fetch_all(&db.pool)
+                .await
+            }
+            None => {
+                sqlx::query_as(&format!(
+                    "{} ORDER BY start_date DESC",
+                    SELECT_CHALLENGES_SQL
+                ))
+                .fetch_all(&db.pool)
+                .await
+            }
+        }
+        .map_err(|e| DatabaseError::Service(format!("Failed to list challenges: {}", e)))?;
+        rows.into_iter().map(challenge_from_row).collect()
+    }
+
+    async fn write_challenge(&self, challenge: &Challenge) -> DatabaseResult<()> {
+        let milestones = serde_json::to_string(&challenge.milestones_reached).map_err(|e| {
+            DatabaseError::Service(format!("Failed to serialize milestones: {}", e))
+        })?;
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_CHALLENGE_SQL)
+            .bind(challenge.id.to_string())
+            .bind(challenge.project_id.to_string())
+            .bind(&challenge.name)
+            .bind(challenge.target_words as i64)
+            .bind(challenge.start_date.to_string())
+            .bind(challenge.end_date.to_string())
+            .bind(milestones)
+            .bind(challenge.created_at.to_rfc3339())
+            .bind(challenge.updated_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save challenge: {}", e)))?;
+        Ok(())
+    }
+}
+
+/// A challenge's progress from the sessions in it, as of `today`
+pub fn challenge_dashboard(
+    challenge: Challenge,
+    sessions: &[WritingSession],
+    today: NaiveDate,
+) -> ChallengeDashboard {
+    let mut per_day: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    for session in sessions {
+        let date = session.started_at.date_naive();
+        if let (Some(words), true) = (
+            session.net_words(),
+            (challenge.start_date..=challenge.end_date).contains(&date),
+        ) {
+            *per_day.entry(date).or_default() += words;
+        }
+    }
+
+    let days_total = challenge.days();
+    let days_elapsed = ((today - challenge.start_date).num_days() + 1).clamp(0, days_total);
+    let par = |days: i64| (challenge.target_words * days as u64).div_ceil(days_total as u64);
+
+    let mut days = Vec::new();
+    let mut cumulative = 0;
+    for offset in 0..days_elapsed {
+        let date = challenge.start_date + Duration::days(offset);
+        let words = per_day.get(&date).copied().unwrap_or(0);
+        cumulative += words;
+        days.push(ChallengeDay {
+            date,
+            words,
+            cumulative,
+            par: par(offset + 1),
+        });
+    }
+
+    let words_written: i64 = per_day.values().sum();
+    let target = challenge.target_words as i64;
+    let running = (challenge.start_date..=challenge.end_date).contains(&today);
+    let written_today = if running {
+        per_day.get(&today).copied().unwrap_or(0)
+    } else {
+        0
+    };
+    let days_left = if today < challenge.start_date {
+        days_total
+    } else {
+        (challenge.end_date - today).num_days().max(0)
+    };
+    let words_needed_today = if running {
+        let remaining = (target - (words_written - written_today)).max(0) as u64;
+        let needed = remaining.div_ceil(days_left as u64 + 1) as i64;
+        (needed - written_today).max(0) as u64
+    } else {
+        0
+    };
+
+    let daily_average = if days_elapsed > 0 {
+        words_written as f64 / days_elapsed as f64
+    } else {
+        0.0
+    };
+    let projected_finish = if words_written >= target {
+        days.iter()
+            .find(|day| day.cumulative >= target)
+            .map(|day| day.date)
+    } else if daily_average > 0.0 {
+        let days_more = ((target - words_written) as f64 / daily_average).ceil() as i64;
+        Some(today.max(challenge.start_date) + Duration::days(days_more))
+    } else {
+        None
+    };
+
+    let mut longest_streak = 0;
+    let mut streak = 0;
+    for day in &days {
+        streak = if day.words > 0 { streak + 1 } else { 0 };
+        longest_streak = longest_streak.max(streak);
+    }
+    let best_day = days
+        .iter()
+        .filter(|day| day.words > 0)
+        .max_by_key(|day| day.words)
+        .cloned();
+
+    ChallengeDashboard {
+        words_written,
+        percent_complete: words_written.max(0) as f64 / target as f64 * 100.0,
+        days_total,
+        days_elapsed,
+        days_left,
+        daily_average,
+        projected_total: (daily_average * days_total as f64).round().max(0.0) as u64,
+        projected_finish,
+        written_today,
+        words_needed_today,
+        ahead_by: words_written - par(days_elapsed) as i64,
+        longest_streak,
+        best_day,
+        days,
+        challenge,
+    }
+}
+
+/// Milestones reached at `words_written` that haven't been celebrated,
+/// lowest first
+pub fn new_milestones(challenge: &Challenge, words_written: i64) -> Vec<u32> {
+    CHALLENGE_MILESTONES
+        .iter()
+        .copied()
+        .filter(|percent| !challenge.milestones_reached.contains(percent))
+        .filter(|percent| {
+            words_written.max(0) as u64 * 100 >= challenge.target_words * u64::from(*percent)
+        })
+        .collect()
+}
+
+fn parse_day(value: &str) -> DatabaseResult<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| DatabaseError::Service(format!("Invalid challenge date: {}", e)))
+}
+
+fn challenge_from_row(row: ChallengeRow) -> DatabaseResult<Challenge> {
+    let (
+        id,
+        project_id,
+        name,
+        target_words,
+        start_date,
+        end_date,
+        milestones_reached,
+        created_at,
+        updated_at,
+    ) = row;
+    Ok(Challenge {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        name,
+        target_words: u64::try_from(target_words).unwrap_or(0),
+        start_date: parse_day(&start_date)?,
+        end_date: parse_day(&end_date)?,
+        milestones_reached: serde_json::from_str(&milestones_reached).unwrap_or_default(),
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dashboard_pace_and_milestones() {
+        let project = Uuid::new_v4();
+        let day = |d: u32| NaiveDate::from_ymd_opt(2026, 11, d).unwrap();
+        let challenge = Challenge::new(project, "NaNoWriMo", 30_000, day(1), day(30));
+        let session = |d: u32, words: i64| WritingSession {
+            id: Uuid::new_v4(),
+            project_id: project,
+            started_at: day(d).and_hms_opt(20, 0, 0).unwrap().and_utc(),
+            ended_at: Some(day(d).and_hms_opt(21, 0, 0).unwrap().and_utc()),
+            words_start: 0,
+            words_end: Some(words),
+            documents_edited: 1,
+        };
+        let sessions = vec![
+            session(1, 1_500),
+            session(2, 700),
+            session(2, 500),
+            session(4, 1_300),
+            session(5, 500),
+        ];
+
+        let dashboard = challenge_dashboard(challenge.clone(), &sessions, day(5));
+        assert_eq!(dashboard.words_written, 4_500);
+        assert_eq!((dashboard.days_elapsed, dashboard.days_left), (5, 25));
+        assert_eq!(dashboard.daily_average, 900.0);
+        assert_eq!(dashboard.projected_total, 27_000);
+        assert_eq!(
+            dashboard.projected_finish,
+            NaiveDate::from_ymd_opt(2026, 12, 4)
+        );
+        assert_eq!(dashboard.ahead_by, -500);
+        // 26,000 left at the start of the day over 26 days, 500 already in
+        assert_eq!(dashboard.words_needed_today, 500);
+        assert_eq!(dashboard.longest_streak, 2);
+        assert_eq!(dashboard.best_day.map(|d| d.date), Some(day(1)));
+        assert_eq!(dashboard.days[1].cumulative, 2_700);
+
+        assert_eq!(
+            new_milestones(&challenge, dashboard.words_written),
+            vec![10]
+        );
+        let mut celebrated = challenge;
+        celebrated.milestones_reached = vec![10];
+        assert_eq!(new_milestones(&celebrated, 16_000), vec![25, 50]);
+    }
+}
@@ -0,0 +1,345 @@
+//! Chronology Service
+//!
+//! Checks that a project's scenes are told in the order they happen. Scenes
+//! are dated in their document metadata and placed on the project's shared
+//! day line with its calendars, like Time codex entries. A scene that jumps
+//! back before the latest scene told so far is a conflict unless it is
+//! flagged as a flashback. The same dates give a suggested chronological
+//! reading order, which can be exported as a book.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::calendar_service::place_events;
+use crate::database::models::calendar::{CalendarSystem, TimelineEvent, TimelineIssue};
+use crate::database::models::chronology::*;
+use crate::database::parse::parse_uuid;
+use crate::database::{CalendarService, DatabaseError, DatabaseResult, EnhancedDatabaseService};
+use crate::publishing::{PublishFormat, PublishedDocument, PublishedSection};
+
+/// A dated scene's start and end day with the undated scenes that follow it
+type SceneGroup = (Option<(i64, Option<i64>)>, Vec<Uuid>);
+
+/// A document with the time its metadata gives
+#[derive(Debug, Clone)]
+pub struct DatedScene {
+    pub id: Uuid,
+    pub title: String,
+    pub time: SceneTime,
+}
+
+/// Service for scene chronology checks and reading orders
+#[derive(Debug)]
+pub struct ChronologyService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    calendars: Arc<CalendarService>,
+}
+
+impl ChronologyService {
+    /// Create a new chronology service
+    pub fn new(
+        db_service: Arc<RwLock<EnhancedDatabaseService>>,
+        calendars: Arc<CalendarService>,
+    ) -> Self {
+        Self {
+            db_service,
+            calendars,
+        }
+    }
+
+    /// Find scenes told out of chronological order without a flashback flag
+    pub async fn check(&self, project_id: Uuid) -> DatabaseResult<ChronologyReport> {
+        let documents = self.load_documents(project_id).await?;
+        let calendars = self.calendars.list_calendars(project_id).await?;
+        let (scenes, date_issues) = place_scenes(&calendars, &documents);
+        Ok(ChronologyReport {
+            project_id,
+            conflicts: find_conflicts(&scenes),
+            scenes,
+            date_issues,
+        })
+    }
+
+    /// Suggest an order that tells the scenes as they happen
+    pub async fn reading_order(&self, project_id: Uuid) -> DatabaseResult<ReadingOrder> {
+        let documents = self.load_documents(project_id).await?;
+        let calendars = self.calendars.list_calendars(project_id).await?;
+        let (scenes, _) = place_scenes(&calendars, &documents);
+        Ok(reading_order(project_id, &scenes))
+    }
+
+    /// Compile the manuscript in the suggested chronological order
+    pub async fn export_reading_order(
+        &self,
+        project_id: Uuid,
+        format: PublishFormat,
+    ) -> DatabaseResult<ChronologicalExport> {
+        let order = self.reading_order(project_id).await?;
+        if order.document_ids.is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "The project has no documents to export".to_string(),
+            ));
+        }
+
+        let db = self.db_service.read().await;
+        let (name,): (String,) = sqlx::query_as("SELECT name FROM projects WHERE id = ?1")
+            .bind(project_id.to_string())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load project: {}", e)))?
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "project".to_string(),
+                id: project_id.to_string(),
+            })?;
+        let rows: Vec<(String, String, Option<String>)> = sqlx::query_as(
+            "SELECT id, title, content FROM documents WHERE project_id = ?1 AND is_active = 1",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+        let mut contents: HashMap<String, (String, Option<String>)> = rows
+            .into_iter()
+            .map(|(id, title, content)| (id, (title, content)))
+            .collect();
+
+        let title = format!("{} (Chronological Order)", name);
+        let mut book = PublishedDocument::new(title.clone());
+        for id in &order.document_ids {
+            if let Some((title, content)) = contents.remove(&id.to_string()) {
+                book.sections.push(PublishedSection::from_text(
+                    title,
+                    &content.unwrap_or_default(),
+                ));
+            }
+        }
+        let bytes = book.render(format).map_err(|e| {
+            DatabaseError::Service(format!("Failed to render reading order: {}", e))
+        })?;
+
+        Ok(ChronologicalExport {
+            title,
+            format,
+            order,
+            bytes,
+        })
+    }
+
+    /// Active documents in binder order with their scene times
+    async fn load_documents(&self, project_id: Uuid) -> DatabaseResult<Vec<DatedScene>> {
+        let db = self.db_service.read().await;
+        let has_binder: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'binder_order'",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check schema: {}", e)))?;
+        let sql = if has_binder > 0 {
+            "SELECT d.id, d.title, d.metadata FROM documents d LEFT JOIN binder_order b ON b.document_id = d.id
+             WHERE d.project_id = ?1 AND d.is_active = 1
+             ORDER BY b.position IS NULL, b.position, d.created_at, d.title"
+        } else {
+            "SELECT id, title, metadata FROM documents WHERE project_id = ?1 AND is_active = 1 ORDER BY created_at, title"
+        };
+        let rows: Vec<(String, String, Option<String>)> = sqlx::query_as(sql)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+
+        rows.into_iter()
+            .map(|(id, title, metadata)| {
+                Ok(DatedScene {
+                    id: parse_uuid(&id)?,
+                    title,
+                    time: metadata
+                        .and_then(|m| serde_json::from_str(&m).ok())
+                        .unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Place the scenes, given in binder order, on the shared day line
+pub fn place_scenes(
+    calendars: &[CalendarSystem],
+    documents: &[DatedScene],
+) -> (Vec<ChronologyScene>, Vec<TimelineIssue>) {
+    let events: Vec<TimelineEvent> = documents
+        .iter()
+        .filter_map(|document| {
+            Some(TimelineEvent {
+                id: document.id,
+                title: document.title.clone(),
+                time: document.time.time.clone()?,
+            })
+        })
+        .collect();
+    let timeline = place_events(calendars, &events);
+    let placed: HashMap<Uuid, _> = timeline.entries.iter().map(|e| (e.event_id, e)).collect();
+
+    let scenes = documents
+        .iter()
+        .enumerate()
+        .map(|(position, document)| {
+            let entry = placed.get(&document.id);
+            ChronologyScene {
+                document_id: document.id,
+                title: document.title.clone(),
+                position,
+                start_day: entry.map(|e| e.start_day),
+                end_day: entry.and_then(|e| e.end_day),
+                start_label: entry.map(|e| e.start_label.clone()),
+                flashback: document.time.flashback,
+            }
+        })
+        .collect();
+    (scenes, timeline.issues)
+}
+
+/// Dated scenes that start before the latest scene told ahead of them.
+/// Flashbacks are skipped and don't move the story's "now" either.
+pub fn find_conflicts(scenes: &[ChronologyScene]) -> Vec<ChronologyConflict> {
+    let mut conflicts = Vec::new();
+    let mut latest: Option<(&ChronologyScene, i64)> = None;
+    for scene in scenes {
+        let Some(day) = scene.start_day else {
+            continue;
+        };
+        if scene.flashback {
+            continue;
+        }
+        match latest {
+            Some((after, after_day)) if day < after_day => {
+                let label = scene.start_label.clone().unwrap_or_default();
+                let after_label = after.start_label.clone().unwrap_or_default();
+                conflicts.push(ChronologyConflict {
+                    document_id: scene.document_id,
+                    title: scene.title.clone(),
+                    message: format!(
+                        "\"{}\" ({}) comes after \"{}\" ({}) but isn't marked as a flashback",
+                        scene.title, label, after.title, after_label
+                    ),
+                    start_label: label,
+                    after_id: after.document_id,
+                    after_title: after.title.clone(),
+                    after_label,
+                    days_back: after_day - day,
+                });
+            }
+            Some((_, after_day)) if day <= after_day => {}
+            _ => latest = Some((scene, day)),
+        }
+    }
+    conflicts
+}
+
+/// The scenes sorted by start date. Each undated scene moves with the scene
+/// before it; undated scenes at the start stay first.
+pub fn reading_order(project_id: Uuid, scenes: &[ChronologyScene]) -> ReadingOrder {
+    let mut groups: Vec<SceneGroup> = Vec::new();
+    for scene in scenes {
+        match scene.start_day {
+            Some(day) => groups.push((Some((day, scene.end_day)), vec![scene.document_id])),
+            None => match groups.last_mut() {
+                Some((_, ids)) => ids.push(scene.document_id),
+                None => groups.push((None, vec![scene.document_id])),
+            },
+        }
+    }
+    groups.sort_by_key(|(key, _)| *key);
+
+    let document_ids: Vec<Uuid> = groups.into_iter().flat_map(|(_, ids)| ids).collect();
+    let moved = document_ids
+        .iter()
+        .zip(scenes)
+        .filter(|(id, scene)| **id != scene.document_id)
+        .count();
+    ReadingOrder {
+        project_id,
+        document_ids,
+        moved,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::calendar::CalendarMonth;
+    use crate::database::models::codex::TimeData;
+
+    fn scene(title: &str, start: Option<&str>, flashback: bool) -> DatedScene {
+        DatedScene {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            time: SceneTime {
+                time: start.map(|start| TimeData {
+                    start_time: Some(start.to_string()),
+                    end_time: None,
+                    duration: None,
+                    calendar_system: None,
+                    season: None,
+                    historical_context: None,
+                    era: None,
+                    calendar_id: None,
+                    start_date: None,
+                    end_date: None,
+                }),
+                flashback,
+            },
+        }
+    }
+
+    #[test]
+    fn test_conflicts_and_reading_order() {
+        let project = Uuid::new_v4();
+        let calendar =
+            CalendarSystem::new(project, "Reckoning", vec![CalendarMonth::new("Year", 360)]);
+        let documents = vec![
+            scene("Arrival", Some("1-1-10"), false),
+            scene("Interlude", None, false),
+            scene("The Fire", Some("1-1-3"), true),
+            scene("Market Day", Some("1-1-12"), false),
+            scene("Morning After", Some("1-1-11"), false),
+            scene("Bad Date", Some("1-13-1"), false),
+        ];
+        let (scenes, issues) = place_scenes(&[calendar], &documents);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(scenes[5].start_day, None);
+
+        let conflicts = find_conflicts(&scenes);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].title, "Morning After");
+        assert_eq!(conflicts[0].after_title, "Market Day");
+        assert_eq!(conflicts[0].days_back, 1);
+
+        let order = reading_order(project, &scenes);
+        let titles: Vec<&str> = order
+            .document_ids
+            .iter()
+            .map(|id| {
+                documents
+                    .iter()
+                    .find(|d| d.id == *id)
+                    .unwrap()
+                    .title
+                    .as_str()
+            })
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                "The Fire",
+                "Arrival",
+                "Interlude",
+                "Morning After",
+                "Bad Date",
+                "Market Day"
+            ]
+        );
+        assert_eq!(order.moved, 6);
+    }
+}
@@ -0,0 +1,691 @@
+//! Codex Autofill Service
+//!
+//! Scans chapters for sentences naming a character or place and pulls out
+//! facts: how a character looks, who they are related to, what a place's
+//! weather, people and past are like, and where each first appears. The
+//! facts become proposed changes to the entry's `CharacterData` or
+//! `PlaceData`, shown as a diff and written only when accepted.
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::models::codex::{
+    CharacterData, CharacterRelationship, CodexEntryType, PlaceData, RelationshipSentiment,
+};
+use crate::database::models::codex_autofill::{
+    AutofillApply, AutofillProposal, AutofillRequest, Evidence, ExtractedFact, FactKind,
+    FieldChange,
+};
+use crate::database::models::codex_graph::entry_type_from_db;
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::text_match::find_word_matches;
+use crate::database::{prosemirror, DatabaseError, DatabaseResult, EnhancedDatabaseService};
+
+/// Sentences kept per descriptive field
+const SENTENCES_PER_FIELD: usize = 3;
+
+const APPEARANCE_WORDS: &[&str] = &[
+    "eyes", "hair", "tall", "short", "scar", "scarred", "beard", "face", "skin", "freckles",
+    "wore", "wearing", "dressed", "slender", "broad", "limp", "limped", "tattoo", "braid",
+];
+
+/// Relationship words, and the sentiment they usually carry
+const RELATIONSHIP_WORDS: &[(&str, RelationshipSentiment)] = &[
+    ("sister", RelationshipSentiment::Neutral),
+    ("brother", RelationshipSentiment::Neutral),
+    ("mother", RelationshipSentiment::Neutral),
+    ("father", RelationshipSentiment::Neutral),
+    ("daughter", RelationshipSentiment::Neutral),
+    ("son", RelationshipSentiment::Neutral),
+    ("wife", RelationshipSentiment::Neutral),
+    ("husband", RelationshipSentiment::Neutral),
+    ("cousin", RelationshipSentiment::Neutral),
+    ("aunt", RelationshipSentiment::Neutral),
+    ("uncle", RelationshipSentiment::Neutral),
+    ("partner", RelationshipSentiment::Neutral),
+    ("captain", RelationshipSentiment::Neutral),
+    ("apprentice", RelationshipSentiment::Neutral),
+    ("mentor", RelationshipSentiment::Positive),
+    ("friend", RelationshipSentiment::Positive),
+    ("lover", RelationshipSentiment::Positive),
+    ("rival", RelationshipSentiment::Negative),
+    ("enemy", RelationshipSentiment::Negative),
+];
+
+const CLIMATE_WORDS: &[&str] = &[
+    "rain", "snow", "fog", "mist", "wind", "cold", "heat", "frost", "storm", "storms", "humid",
+    "drought",
+];
+const POPULATION_WORDS: &[&str] = &[
+    "people",
+    "villagers",
+    "inhabitants",
+    "residents",
+    "population",
+    "folk",
+    "citizens",
+];
+const CULTURE_WORDS: &[&str] = &[
+    "festival",
+    "custom",
+    "customs",
+    "tradition",
+    "traditions",
+    "temple",
+    "worship",
+    "market",
+];
+const HISTORY_WORDS: &[&str] = &[
+    "founded",
+    "built",
+    "ancient",
+    "centuries",
+    "years ago",
+    "ruins",
+    "once",
+    "war",
+];
+
+/// A codex entry as loaded for autofill
+#[derive(Debug, Clone)]
+struct Entry {
+    id: Uuid,
+    entry_type: CodexEntryType,
+    title: String,
+    metadata: Option<String>,
+    updated_at: DateTime<Utc>,
+}
+
+impl Entry {
+    /// Title and alternate names, as they would be written in the text
+    fn names(&self) -> Vec<String> {
+        let mut names = vec![self.title.trim().to_string()];
+        let others = match self.entry_type {
+            CodexEntryType::CharacterSheet => character_data(self.metadata.as_deref()).names,
+            CodexEntryType::Place => place_data(self.metadata.as_deref()).alternative_names,
+            _ => Vec::new(),
+        };
+        for name in others {
+            let name = name.trim();
+            if !names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                names.push(name.to_string());
+            }
+        }
+        names.retain(|name| name.chars().count() > 1);
+        names
+    }
+}
+
+/// A document as loaded for autofill
+#[derive(Debug, Clone)]
+pub struct AutofillDocument {
+    pub id: Uuid,
+    pub title: String,
+    /// Place in the binder, from 1
+    pub position: usize,
+    /// The document's text, not its stored JSON
+    pub content: String,
+}
+
+/// Service proposing codex updates from the manuscript
+#[derive(Debug)]
+pub struct CodexAutofillService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl CodexAutofillService {
+    /// Create a new codex autofill service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Scan the chosen documents and propose changes to the entry
+    pub async fn propose(&self, request: &AutofillRequest) -> DatabaseResult<AutofillProposal> {
+        let entries = self.entries(request.project_id).await?;
+        let entry = entries
+            .iter()
+            .find(|entry| entry.id == request.entry_id)
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "codex entry".to_string(),
+                id: request.entry_id.to_string(),
+            })?;
+        if !matches!(
+            entry.entry_type,
+            CodexEntryType::CharacterSheet | CodexEntryType::Place
+        ) {
+            return Err(DatabaseError::ValidationError(format!(
+                "Autofill works on characters and places, not {}",
+                entry.entry_type.display_name()
+            )));
+        }
+
+        let mut documents = self.documents(request.project_id).await?;
+        if !request.document_ids.is_empty() {
+            documents.retain(|document| request.document_ids.contains(&document.id));
+        }
+        let facts = extract_facts(entry, &entries, &documents);
+        // Within a selection the earliest mention may not be the first one,
+        // so an existing first appearance is only replaced from a full scan
+        let whole_manuscript = request.document_ids.is_empty();
+        let changes = match entry.entry_type {
+            CodexEntryType::CharacterSheet => character_changes(
+                &character_data(entry.metadata.as_deref()),
+                &facts,
+                whole_manuscript,
+            ),
+            _ => place_changes(
+                &place_data(entry.metadata.as_deref()),
+                &facts,
+                whole_manuscript,
+            ),
+        };
+
+        Ok(AutofillProposal {
+            entry_id: entry.id,
+            entry_type: entry.entry_type,
+            title: entry.title.clone(),
+            entry_updated_at: entry.updated_at,
+            documents_scanned: documents.len(),
+            facts,
+            changes,
+        })
+    }
+
+    /// Write the accepted changes of a proposal, returning the fields written
+    pub async fn apply(&self, accepted: &AutofillApply) -> DatabaseResult<Vec<String>> {
+        let proposal = &accepted.proposal;
+        let db = self.db_service.read().await;
+        let row: Option<(Option<String>, String)> = sqlx::query_as(
+            "SELECT metadata, updated_at FROM codex_entries WHERE id = ?1 AND is_active = 1",
+        )
+        .bind(proposal.entry_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load codex entry: {}", e)))?;
+        let (metadata, updated_at) = row.ok_or_else(|| DatabaseError::RecordNotFound {
+            entity: "codex entry".to_string(),
+            id: proposal.entry_id.to_string(),
+        })?;
+        if parse_time(&updated_at)? != proposal.entry_updated_at {
+            return Err(DatabaseError::ValidationError(
+                "The entry has changed since these changes were proposed; scan again".to_string(),
+            ));
+        }
+
+        let mut data = match proposal.entry_type {
+            CodexEntryType::CharacterSheet => {
+                serde_json::to_value(character_data(metadata.as_deref()))
+            }
+            _ => serde_json::to_value(place_data(metadata.as_deref())),
+        }
+        .map_err(|e| DatabaseError::Service(format!("Failed to read entry data: {}", e)))?;
+        let Value::Object(fields) = &mut data else {
+            return Err(DatabaseError::Service(
+                "Entry data is not an object".to_string(),
+            ));
+        };
+
+        let mut applied = Vec::new();
+        for change in &proposal.changes {
+            if !accepted.accepted_fields.is_empty()
+                && !accepted.accepted_fields.contains(&change.field)
+            {
+                continue;
+            }
+            if !fields.contains_key(&change.field) {
+                return Err(DatabaseError::ValidationError(format!(
+                    "Unknown field: {}",
+                    change.field
+                )));
+            }
+            fields.insert(change.field.clone(), change.proposed.clone());
+            applied.push(change.field.clone());
+        }
+        if applied.is_empty() {
+            return Ok(applied);
+        }
+
+        // Round trip through the typed data so a malformed value is refused
+        let metadata = match proposal.entry_type {
+            CodexEntryType::CharacterSheet => serde_json::from_value::<CharacterData>(data)
+                .and_then(|data| serde_json::to_string(&data)),
+            _ => serde_json::from_value::<PlaceData>(data)
+                .and_then(|data| serde_json::to_string(&data)),
+        }
+        .map_err(|e| DatabaseError::ValidationError(format!("Invalid entry data: {}", e)))?;
+        sqlx::query("UPDATE codex_entries SET metadata = ?1, updated_at = ?2 WHERE id = ?3")
+            .bind(metadata)
+            .bind(Utc::now().to_rfc3339())
+            .bind(proposal.entry_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to update codex entry: {}", e)))?;
+
+        Ok(applied)
+    }
+
+    async fn entries(&self, project_id: Uuid) -> DatabaseResult<Vec<Entry>> {
+        let db = self.db_service.read().await;
+        let has_codex: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'codex_entries'",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check schema: {}", e)))?;
+        if has_codex == 0 {
+            return Ok(Vec::new());
+        }
+
+        let rows: Vec<(String, String, String, Option<String>, String)> = sqlx::query_as(
+            "SELECT id, entry_type, title, metadata, updated_at FROM codex_entries
+             WHERE project_id = ?1 AND is_active = 1",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load codex entries: {}", e)))?;
+        let mut entries = Vec::new();
+        for (id, entry_type, title, metadata, updated_at) in rows {
+            let Some(entry_type) = entry_type_from_db(&entry_type) else {
+                continue;
+            };
+            entries.push(Entry {
+                id: parse_uuid(&id)?,
+                entry_type,
+                title,
+                metadata: metadata.filter(|m| !m.trim().is_empty()),
+                updated_at: parse_time(&updated_at)?,
+            });
+        }
+        Ok(entries)
+    }
+
+    async fn documents(&self, project_id: Uuid) -> DatabaseResult<Vec<AutofillDocument>> {
+        let db = self.db_service.read().await;
+        let has_binder: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'binder_order'",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check schema: {}", e)))?;
+        let sql = if has_binder > 0 {
+            "SELECT d.id, d.title, d.content, d.document_type
+             FROM documents d LEFT JOIN binder_order b ON b.document_id = d.id
+             WHERE d.project_id = ?1 AND d.is_active = 1
+             ORDER BY b.position IS NULL, b.position, d.created_at, d.title"
+        } else {
+            "SELECT d.id, d.title, d.content, d.document_type FROM documents d
+             WHERE d.project_id = ?1 AND d.is_active = 1
+             ORDER BY d.created_at, d.title"
+        };
+        let rows: Vec<(String, String, Option<String>, String)> = sqlx::query_as(sql)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+        rows.into_iter()
+            .enumerate()
+            .map(|(index, (id, title, content, document_type))| {
+                Ok(AutofillDocument {
+                    id: parse_uuid(&id)?,
+                    title,
+                    position: index + 1,
+                    content: prosemirror::document_text(
+                        &document_type,
+                        content.as_deref().unwrap_or(""),
+                    ),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Facts about `entry` in the documents, in manuscript order
+fn extract_facts(
+    entry: &Entry,
+    entries: &[Entry],
+    documents: &[AutofillDocument],
+) -> Vec<ExtractedFact> {
+    let names = entry.names();
+    let others: Vec<(Uuid, Vec<String>)> = entries
+        .iter()
+        .filter(|other| other.id != entry.id && other.entry_type == CodexEntryType::CharacterSheet)
+        .map(|other| (other.id, other.names()))
+        .collect();
+
+    let mut facts = Vec::new();
+    for document in documents {
+        for (start_char, end_char, sentence) in sentences(&document.content) {
+            if !names.iter().any(|name| contains_word(&sentence, name)) {
+                continue;
+            }
+            let first = !facts
+                .iter()
+                .any(|f: &ExtractedFact| f.kind == FactKind::FirstAppearance);
+            let evidence = Evidence {
+                document_id: document.id,
+                title: document.title.clone(),
+                position: document.position,
+                start_char,
+                end_char,
+                sentence: sentence.clone(),
+            };
+            let mut push = |kind: FactKind, value: String, related_entry: Option<Uuid>| {
+                facts.push(ExtractedFact {
+                    kind,
+                    value,
+                    related_entry,
+                    evidence: evidence.clone(),
+                });
+            };
+
+            if first {
+                push(FactKind::FirstAppearance, document.title.clone(), None);
+            }
+            match entry.entry_type {
+                CodexEntryType::CharacterSheet => {
+                    if has_any(&sentence, APPEARANCE_WORDS) {
+                        push(FactKind::Appearance, sentence.clone(), None);
+                    }
+                    for (other, other_names) in &others {
+                        if !other_names
+                            .iter()
+                            .any(|name| contains_word(&sentence, name))
+                        {
+                            continue;
+                        }
+                        if let Some((word, _)) = RELATIONSHIP_WORDS
+                            .iter()
+                            .find(|(word, _)| contains_word(&sentence, word))
+                        {
+                            push(FactKind::Relationship, word.to_string(), Some(*other));
+                        }
+                    }
+                }
+                _ => {
+                    for (kind, words) in [
+                        (FactKind::Climate, CLIMATE_WORDS),
+                        (FactKind::Population, POPULATION_WORDS),
+                        (FactKind::Culture, CULTURE_WORDS),
+                        (FactKind::History, HISTORY_WORDS),
+                    ] {
+                        if has_any(&sentence, words) {
+                            push(kind, sentence.clone(), None);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    facts
+}
+
+fn character_changes(
+    current: &CharacterData,
+    facts: &[ExtractedFact],
+    whole_manuscript: bool,
+) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    first_appearance_change(
+        &mut changes,
+        current.first_appearance.as_deref(),
+        facts,
+        whole_manuscript,
+    );
+    text_change(
+        &mut changes,
+        "physical_description",
+        current.physical_description.as_deref(),
+        facts,
+        FactKind::Appearance,
+    );
+
+    let mut relationships = current.relationships.clone();
+    let mut evidence = Vec::new();
+    for fact in facts.iter().filter(|f| f.kind == FactKind::Relationship) {
+        let Some(other) = fact.related_entry else {
+            continue;
+        };
+        if relationships.iter().any(|r| r.character_id == other) {
+            continue;
+        }
+        let sentiment = RELATIONSHIP_WORDS
+            .iter()
+            .find(|(word, _)| *word == fact.value)
+            .map(|(_, sentiment)| *sentiment)
+            .unwrap_or(RelationshipSentiment::Neutral);
+        relationships.push(CharacterRelationship {
+            character_id: other,
+            relationship_type: fact.value.clone(),
+            description: fact.evidence.sentence.clone(),
+            sentiment,
+        });
+        evidence.push(fact.evidence.clone());
+    }
+    if !evidence.is_empty() {
+        changes.push(FieldChange {
+            field: "relationships".to_string(),
+            current: serde_json::to_value(&current.relationships).unwrap_or(Value::Null),
+            proposed: serde_json::to_value(&relationships).unwrap_or(Value::Null),
+            evidence,
+        });
+    }
+    changes
+}
+
+fn place_changes(
+    current: &PlaceData,
+    facts: &[ExtractedFact],
+    whole_manuscript: bool,
+) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    first_appearance_change(
+        &mut changes,
+        current.first_appearance.as_deref(),
+        facts,
+        whole_manuscript,
+    );
+    for (field, value, kind) in [
+        ("climate", current.climate.as_deref(), FactKind::Climate),
+        (
+            "population",
+            current.population.as_deref(),
+            FactKind::Population,
+        ),
+        ("culture", current.culture.as_deref(), FactKind::Culture),
+        ("history", current.history.as_deref(), FactKind::History),
+    ] {
+        text_change(&mut changes, field, value, facts, kind);
+    }
+    changes
+}
+
+fn first_appearance_change(
+    changes: &mut Vec<FieldChange>,
+    current: Option<&str>,
+    facts: &[ExtractedFact],
+    whole_manuscript: bool,
+) {
+    let Some(fact) = facts.iter().find(|f| f.kind == FactKind::FirstAppearance) else {
+        return;
+    };
+    let replaceable = current.is_none_or(|c| c.trim().is_empty()) || whole_manuscript;
+    if replaceable && current != Some(fact.value.as_str()) {
+        changes.push(FieldChange {
+            field: "first_appearance".to_string(),
+            current: current.map_or(Value::Null, |c| Value::String(c.to_string())),
+            proposed: Value::String(fact.value.clone()),
+            evidence: vec![fact.evidence.clone()],
+        });
+    }
+}
+
+/// Fill an empty text field from the first few sentences of a kind
+fn text_change(
+    changes: &mut Vec<FieldChange>,
+    field: &str,
+    current: Option<&str>,
+    facts: &[ExtractedFact],
+    kind: FactKind,
+) {
+    if current.is_some_and(|c| !c.trim().is_empty()) {
+        return;
+    }
+    let found: Vec<&ExtractedFact> = facts
+        .iter()
+        .filter(|f| f.kind == kind)
+        .take(SENTENCES_PER_FIELD)
+        .collect();
+    if found.is_empty() {
+        return;
+    }
+    let text = found
+        .iter()
+        .map(|f| f.value.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    changes.push(FieldChange {
+        field: field.to_string(),
+        current: current.map_or(Value::Null, |c| Value::String(c.to_string())),
+        proposed: Value::String(text),
+        evidence: found.into_iter().map(|f| f.evidence.clone()).collect(),
+    });
+}
+
+/// Sentences with their character offsets; a line break always ends one
+fn sentences(content: &str) -> Vec<(usize, usize, String)> {
+    let chars: Vec<char> = content.chars().collect();
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut push = |start: usize, end: usize| {
+        let text: String = chars[start..end].iter().collect();
+        let leading = text.chars().take_while(|c| c.is_whitespace()).count();
+        let trimmed = text.trim();
+        if !trimmed.is_empty() {
+            let begin = start + leading;
+            sentences.push((begin, begin + trimmed.chars().count(), trimmed.to_string()));
+        }
+    };
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            push(start, i);
+            start = i + 1;
+        } else if matches!(c, '.' | '!' | '?') {
+            let mut end = i + 1;
+            while end < chars.len()
+                && matches!(chars[end], '"' | '\'' | '\u{201d}' | '\u{2019}' | ')')
+            {
+                end += 1;
+            }
+            if end == chars.len() || chars[end].is_whitespace() {
+                push(start, end);
+                start = end;
+                i = end;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    if start < chars.len() {
+        push(start, chars.len());
+    }
+    sentences
+}
+
+fn contains_word(text: &str, word: &str) -> bool {
+    !find_word_matches(text, word).is_empty()
+}
+
+fn has_any(text: &str, words: &[&str]) -> bool {
+    words.iter().any(|word| contains_word(text, word))
+}
+
+fn character_data(metadata: Option<&str>) -> CharacterData {
+    metadata
+        .and_then(|metadata| serde_json::from_str(metadata).ok())
+        .unwrap_or_default()
+}
+
+fn place_data(metadata: Option<&str>) -> PlaceData {
+    metadata
+        .and_then(|metadata| serde_json::from_str(metadata).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(entry_type: CodexEntryType, title: &str, metadata: Option<String>) -> Entry {
+        Entry {
+            id: Uuid::new_v4(),
+            entry_type,
+            title: title.to_string(),
+            metadata,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_character_facts_become_proposed_changes() {
+        let tobin = entry(CodexEntryType::CharacterSheet, "Tobin", None);
+        let mut data = CharacterData {
+            names: vec!["Mara Voss".to_string()],
+            backstory: Some("Raised at the lighthouse.".to_string()),
+            ..Default::default()
+        };
+        data.first_appearance = Some("Chapter 2".to_string());
+        let mara = entry(
+            CodexEntryType::CharacterSheet,
+            "Mara",
+            Some(serde_json::to_string(&data).unwrap()),
+        );
+        let documents = vec![
+            AutofillDocument {
+                id: Uuid::new_v4(),
+                title: "Chapter 1".to_string(),
+                position: 1,
+                content: "The harbour was quiet. Mara Voss had grey eyes and a scar on her chin.\n\
+                          Tobin, Mara's younger brother, waited by the boats."
+                    .to_string(),
+            },
+            AutofillDocument {
+                id: Uuid::new_v4(),
+                title: "Chapter 2".to_string(),
+                position: 2,
+                content: "\"Go,\" said Mara. The gulls cried.".to_string(),
+            },
+        ];
+        let entries = vec![mara.clone(), tobin.clone()];
+
+        let facts = extract_facts(&mara, &entries, &documents);
+        assert_eq!(facts[0].kind, FactKind::FirstAppearance);
+        assert_eq!(facts[0].value, "Chapter 1");
+        let sentence = &facts[1].evidence;
+        let found: String = documents[0]
+            .content
+            .chars()
+            .skip(sentence.start_char)
+            .take(sentence.end_char - sentence.start_char)
+            .collect();
+        assert_eq!(found, "Mara Voss had grey eyes and a scar on her chin.");
+
+        let changes = character_changes(&data, &facts, true);
+        let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec!["first_appearance", "physical_description", "relationships"]
+        );
+        assert_eq!(changes[2].proposed[0]["relationship_type"], "brother");
+        assert_eq!(changes[2].proposed[0]["character_id"], tobin.id.to_string());
+
+        // A scan of later chapters doesn't move an existing first appearance
+        let changes = character_changes(&data, &facts, false);
+        assert!(changes.iter().all(|c| c.field != "first_appearance"));
+    }
+}
@@ -0,0 +1,545 @@
+//! Codex Graph Service
+//!
+//! Answers traversal queries over a project's codex, such as "characters
+//! within 2 relationship hops of Mara who appear in Act 2". The graph is
+//! built per query from the codex and the documents: relationships on
+//! character sheets and entries naming each other are the edges, and
+//! documents naming an entry are its appearances.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::models::codex::{CharacterData, CodexEntryType};
+use crate::database::models::codex_graph::{
+    entry_type_from_db, Appearance, Backlinks, DocumentScope, EdgeKind, GraphEdge, GraphMatch,
+    GraphQuery, MENTIONS,
+};
+use crate::database::models::codex_relationship::CodexRelationship;
+use crate::database::parse::parse_uuid;
+use crate::database::text_match::find_word_matches;
+use crate::database::{
+    CodexRelationshipService, DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+
+/// A codex entry as loaded for the graph
+#[derive(Debug, Clone)]
+pub struct GraphEntry {
+    pub id: Uuid,
+    pub title: String,
+    pub entry_type: CodexEntryType,
+    pub content: String,
+    /// Character sheet data, for relationships and alternate names
+    pub character: Option<CharacterData>,
+}
+
+/// A document as loaded for the graph
+#[derive(Debug, Clone)]
+pub struct GraphDocument {
+    pub id: Uuid,
+    pub title: String,
+    /// Place in the binder, from 1
+    pub position: usize,
+    pub content: String,
+}
+
+#[derive(Debug, Clone)]
+struct GraphNode {
+    id: Uuid,
+    title: String,
+    entry_type: CodexEntryType,
+    /// Title and alternate names, as they would be written in the text
+    names: Vec<String>,
+}
+
+/// A project's codex as a graph
+#[derive(Debug, Clone, Default)]
+pub struct CodexGraph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+    documents: Vec<GraphDocument>,
+}
+
+impl CodexGraph {
+    /// Link entries through character relationships and mentions of each
+    /// other's names
+    pub fn build(entries: Vec<GraphEntry>, documents: Vec<GraphDocument>) -> Self {
+        let nodes: Vec<GraphNode> = entries
+            .iter()
+            .map(|entry| {
+                let mut names = vec![entry.title.trim().to_string()];
+                if let Some(character) = &entry.character {
+                    for name in &character.names {
+                        let name = name.trim();
+                        if !names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                            names.push(name.to_string());
+                        }
+                    }
+                }
+                names.retain(|name| name.chars().count() > 1);
+                GraphNode {
+                    id: entry.id,
+                    title: entry.title.clone(),
+                    entry_type: entry.entry_type,
+                    names,
+                }
+            })
+            .collect();
+
+        let ids: HashSet<Uuid> = nodes.iter().map(|node| node.id).collect();
+        let mut edges = Vec::new();
+        for entry in &entries {
+            if let Some(character) = &entry.character {
+                for relationship in &character.relationships {
+                    if relationship.character_id != entry.id
+                        && ids.contains(&relationship.character_id)
+                    {
+                        edges.push(GraphEdge {
+                            source: entry.id,
+                            target: relationship.character_id,
+                            relationship: relationship.relationship_type.trim().to_lowercase(),
+                            kind: EdgeKind::Relationship,
+                        });
+                    }
+                }
+            }
+            for node in nodes.iter().filter(|node| node.id != entry.id) {
+                if node
+                    .names
+                    .iter()
+                    .any(|name| !find_word_matches(&entry.content, name).is_empty())
+                {
+                    edges.push(GraphEdge {
+                        source: entry.id,
+                        target: node.id,
+                        relationship: MENTIONS.to_string(),
+                        kind: EdgeKind::Mention,
+                    });
+                }
+            }
+        }
+
+        Self {
+            nodes,
+            edges,
+            documents,
+        }
+    }
+
+    /// Add recorded links between entries in the graph as edges
+    pub fn with_links(mut self, links: &[CodexRelationship]) -> Self {
+        let ids: HashSet<Uuid> = self.nodes.iter().map(|node| node.id).collect();
+        for link in links {
+            if ids.contains(&link.source_id) && ids.contains(&link.target_id) {
+                self.edges.push(GraphEdge {
+                    source: link.source_id,
+                    target: link.target_id,
+                    relationship: link.relationship_type.clone(),
+                    kind: EdgeKind::Link,
+                });
+            }
+        }
+        self
+    }
+
+    /// Relationships and mentions between entries
+    pub fn edges(&self) -> &[GraphEdge] {
+        &self.edges
+    }
+
+    /// Run a query against the graph
+    pub fn run(&self, query: &GraphQuery) -> DatabaseResult<Vec<GraphMatch>> {
+        query.validate()?;
+        let via: HashSet<String> = query.via.iter().map(|v| v.trim().to_lowercase()).collect();
+        let scope: Vec<&GraphDocument> = self
+            .documents
+            .iter()
+            .filter(|document| in_scope(document, query.appears_in.as_ref()))
+            .collect();
+
+        let reached: Vec<(usize, usize, Vec<GraphEdge>)> = match &query.start {
+            Some(start) => {
+                let start = self.resolve(start)?;
+                self.traverse(start, query.max_hops, &via)
+            }
+            None => (0..self.nodes.len()).map(|i| (i, 0, Vec::new())).collect(),
+        };
+
+        let mut matches: Vec<GraphMatch> = reached
+            .into_iter()
+            .filter(|(i, _, _)| {
+                query.entry_types.is_empty()
+                    || query.entry_types.contains(&self.nodes[*i].entry_type)
+            })
+            .filter_map(|(i, hops, path)| {
+                let node = &self.nodes[i];
+                let appearances = appearances(node, &scope);
+                if query.appears_in.is_some() && appearances.is_empty() {
+                    return None;
+                }
+                Some(GraphMatch {
+                    entry_id: node.id,
+                    title: node.title.clone(),
+                    entry_type: node.entry_type,
+                    hops,
+                    path,
+                    appearances,
+                })
+            })
+            .collect();
+        matches.sort_by(|a, b| {
+            a.hops
+                .cmp(&b.hops)
+                .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+        });
+        if let Some(limit) = query.limit {
+            matches.truncate(limit);
+        }
+        Ok(matches)
+    }
+
+    /// Documents naming an entry and the edges pointing at it
+    pub fn backlinks(&self, entry_id: Uuid) -> DatabaseResult<Backlinks> {
+        let node = self
+            .nodes
+            .iter()
+            .find(|node| node.id == entry_id)
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "codex entry".to_string(),
+                id: entry_id.to_string(),
+            })?;
+        let documents: Vec<&GraphDocument> = self.documents.iter().collect();
+        Ok(Backlinks {
+            entry_id,
+            documents: appearances(node, &documents),
+            entries: self
+                .edges
+                .iter()
+                .filter(|edge| edge.target == entry_id)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    /// Index of the node with this ID, title or alternate name
+    fn resolve(&self, start: &str) -> DatabaseResult<usize> {
+        let start = start.trim();
+        let by_id = Uuid::parse_str(start)
+            .ok()
+            .and_then(|id| self.nodes.iter().position(|node| node.id == id));
+        by_id
+            .or_else(|| {
+                self.nodes
+                    .iter()
+                    .position(|node| node.title.trim().eq_ignore_ascii_case(start))
+            })
+            .or_else(|| {
+                self.nodes.iter().position(|node| {
+                    node.names
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(start))
+                })
+            })
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "codex entry".to_string(),
+                id: start.to_string(),
+            })
+    }
+
+    /// Breadth-first walk ignoring edge direction; returns each reached node
+    /// with its distance and the edges walked to reach it
+    fn traverse(
+        &self,
+        start: usize,
+        max_hops: usize,
+        via: &HashSet<String>,
+    ) -> Vec<(usize, usize, Vec<GraphEdge>)> {
+        let index: HashMap<Uuid, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id, i))
+            .collect();
+        let mut adjacent: Vec<Vec<(usize, usize)>> = vec![Vec::new(); self.nodes.len()];
+        for (e, edge) in self.edges.iter().enumerate() {
+            if !via.is_empty() && !via.contains(&edge.relationship) {
+                continue;
+            }
+            let (Some(&source), Some(&target)) = (index.get(&edge.source), index.get(&edge.target))
+            else {
+                continue;
+            };
+            adjacent[source].push((target, e));
+            adjacent[target].push((source, e));
+        }
+
+        let mut parent: HashMap<usize, (usize, usize)> = HashMap::new();
+        let mut depth = HashMap::from([(start, 0)]);
+        let mut queue = VecDeque::from([start]);
+        let mut reached = Vec::new();
+        while let Some(node) = queue.pop_front() {
+            let hops = depth[&node];
+            if node != start {
+                let mut path = Vec::new();
+                let mut at = node;
+                while let Some(&(previous, edge)) = parent.get(&at) {
+                    path.push(self.edges[edge].clone());
+                    at = previous;
+                }
+                path.reverse();
+                reached.push((node, hops, path));
+            }
+            if hops == max_hops {
+                continue;
+            }
+            for &(next, edge) in &adjacent[node] {
+                if let std::collections::hash_map::Entry::Vacant(slot) = depth.entry(next) {
+                    slot.insert(hops + 1);
+                    parent.insert(next, (node, edge));
+                    queue.push_back(next);
+                }
+            }
+        }
+        reached
+    }
+}
+
+fn in_scope(document: &GraphDocument, scope: Option<&DocumentScope>) -> bool {
+    match scope {
+        None => true,
+        Some(DocumentScope::TitleContains { text }) => document
+            .title
+            .to_lowercase()
+            .contains(&text.trim().to_lowercase()),
+        Some(DocumentScope::BinderRange { first, last }) => {
+            (*first..=*last).contains(&document.position)
+        }
+        Some(DocumentScope::Documents { ids }) => ids.contains(&document.id),
+    }
+}
+
+fn appearances(node: &GraphNode, documents: &[&GraphDocument]) -> Vec<Appearance> {
+    documents
+        .iter()
+        .filter_map(|document| {
+            let mentions: usize = node
+                .names
+                .iter()
+                .map(|name| find_word_matches(&document.content, name).len())
+                .sum();
+            (mentions > 0).then(|| Appearance {
+                document_id: document.id,
+                title: document.title.clone(),
+                position: document.position,
+                mentions,
+            })
+        })
+        .collect()
+}
+
+/// Service for codex graph queries
+#[derive(Debug)]
+pub struct CodexGraphService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl CodexGraphService {
+    /// Create a new codex graph service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Run a query
+    pub async fn query(&self, query: &GraphQuery) -> DatabaseResult<Vec<GraphMatch>> {
+        query.validate()?;
+        self.graph(query.project_id).await?.run(query)
+    }
+
+    /// Parse and run a query written in the query language
+    pub async fn query_text(
+        &self,
+        project_id: Uuid,
+        text: &str,
+    ) -> DatabaseResult<Vec<GraphMatch>> {
+        self.query(&GraphQuery::parse(project_id, text)?).await
+    }
+
+    /// Documents and entries that point at an entry
+    pub async fn backlinks(&self, project_id: Uuid, entry_id: Uuid) -> DatabaseResult<Backlinks> {
+        self.graph(project_id).await?.backlinks(entry_id)
+    }
+
+    /// Load the project's codex and documents into a graph
+    pub async fn graph(&self, project_id: Uuid) -> DatabaseResult<CodexGraph> {
+        let db = self.db_service.read().await;
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name IN ('codex_entries', 'binder_order', 'codex_relationships')",
+        )
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check schema: {}", e)))?;
+
+        let mut entries = Vec::new();
+        if tables.iter().any(|t| t == "codex_entries") {
+            let rows: Vec<(String, String, String, String, Option<String>)> = sqlx::query_as(
+                "SELECT id, entry_type, title, content, metadata FROM codex_entries
+                 WHERE project_id = ?1 AND is_active = 1
+                 ORDER BY sort_order, title",
+            )
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load codex entries: {}", e)))?;
+            for (id, entry_type, title, content, metadata) in rows {
+                let Some(entry_type) = entry_type_from_db(&entry_type) else {
+                    continue;
+                };
+                let character = match entry_type {
+                    CodexEntryType::CharacterSheet => metadata
+                        .as_deref()
+                        .and_then(|metadata| serde_json::from_str(metadata).ok()),
+                    _ => None,
+                };
+                entries.push(GraphEntry {
+                    id: parse_uuid(&id)?,
+                    title,
+                    entry_type,
+                    content,
+                    character,
+                });
+            }
+        }
+
+        let sql = if tables.iter().any(|t| t == "binder_order") {
+            "SELECT d.id, d.title, d.content FROM documents d LEFT JOIN binder_order b ON b.document_id = d.id
+             WHERE d.project_id = ?1 AND d.is_active = 1
+             ORDER BY b.position IS NULL, b.position, d.created_at, d.title"
+        } else {
+            "SELECT id, title, content FROM documents WHERE project_id = ?1 AND is_active = 1 ORDER BY created_at, title"
+        };
+        let rows: Vec<(String, String, Option<String>)> = sqlx::query_as(sql)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+        let documents = rows
+            .into_iter()
+            .enumerate()
+            .map(|(index, (id, title, content))| {
+                Ok(GraphDocument {
+                    id: parse_uuid(&id)?,
+                    title,
+                    position: index + 1,
+                    content: content.unwrap_or_default(),
+                })
+            })
+            .collect::<DatabaseResult<Vec<_>>>()?;
+        drop(db);
+
+        let graph = CodexGraph::build(entries, documents);
+        if tables.iter().any(|t| t == "codex_relationships") {
+            let links = CodexRelationshipService::new(self.db_service.clone())
+                .relationships(project_id)
+                .await?;
+            return Ok(graph.with_links(&links));
+        }
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::codex::{CharacterRelationship, RelationshipSentiment};
+
+    fn character(title: &str, names: &[&str], relationships: &[(Uuid, &str)]) -> GraphEntry {
+        GraphEntry {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            entry_type: CodexEntryType::CharacterSheet,
+            content: String::new(),
+            character: Some(CharacterData {
+                names: names.iter().map(|n| n.to_string()).collect(),
+                physical_description: None,
+                personality_traits: Vec::new(),
+                goals: Vec::new(),
+                fears: Vec::new(),
+                backstory: None,
+                arc: None,
+                relationships: relationships
+                    .iter()
+                    .map(|(id, kind)| CharacterRelationship {
+                        character_id: *id,
+                        relationship_type: kind.to_string(),
+                        description: String::new(),
+                        sentiment: RelationshipSentiment::Neutral,
+                    })
+                    .collect(),
+                skills: Vec::new(),
+                inventory: Vec::new(),
+                first_appearance: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_hops_relationship_filter_and_scope() {
+        let mara = character("Mara", &[], &[]);
+        let tobin = character("Tobin", &[], &[(mara.id, "Sibling")]);
+        let wren = character("Wren Ashe", &["the Ashe girl"], &[(tobin.id, "rival")]);
+        let mut lantern = character("The Lantern", &[], &[]);
+        lantern.entry_type = CodexEntryType::Object;
+        lantern.character = None;
+        lantern.content = "Carried by Wren Ashe.".to_string();
+
+        let document = |position: usize, title: &str, content: &str| GraphDocument {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            position,
+            content: content.to_string(),
+        };
+        let act_one = document(1, "Act 1: Ch 1", "Mara and Tobin argue.");
+        let act_two = document(2, "Act 2: Ch 5", "The Ashe girl waits. Tobin leaves.");
+        let graph = CodexGraph::build(
+            vec![mara.clone(), tobin.clone(), wren.clone(), lantern.clone()],
+            vec![act_one, act_two.clone()],
+        );
+
+        let project = Uuid::new_v4();
+        let query = GraphQuery::parse(
+            project,
+            r#"find characters within 2 hops of "mara" via sibling, rival in "Act 2""#,
+        )
+        .unwrap();
+        let matches = graph.run(&query).unwrap();
+        let titles: Vec<&str> = matches.iter().map(|m| m.title.as_str()).collect();
+        assert_eq!(titles, vec!["Tobin", "Wren Ashe"]);
+        assert_eq!(matches[1].hops, 2);
+        assert_eq!(matches[1].path.len(), 2);
+        assert_eq!(matches[1].appearances[0].document_id, act_two.id);
+
+        // Mentions are edges too unless the query names other relationships
+        let query =
+            GraphQuery::parse(project, r#"find objects within 1 hop of "Wren Ashe""#).unwrap();
+        let matches = graph.run(&query).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path[0].kind, EdgeKind::Mention);
+
+        let backlinks = graph.backlinks(wren.id).unwrap();
+        assert_eq!(backlinks.documents.len(), 1);
+        assert_eq!(backlinks.entries.len(), 1);
+        assert!(graph
+            .run(&GraphQuery::parse(project, "find any within 1 hop of Nobody").unwrap())
+            .is_err());
+
+        // Recorded links can be followed by their type
+        let graph =
+            graph.with_links(&[CodexRelationship::new(project, mara.id, lantern.id, "owns")]);
+        let query =
+            GraphQuery::parse(project, r#"find objects within 1 hop of "Mara" via owns"#).unwrap();
+        let matches = graph.run(&query).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path[0].kind, EdgeKind::Link);
+    }
+}
@@ -0,0 +1,383 @@
+//! Codex Relationship Service
+//!
+//! Stores the links authors draw between codex entries and answers the
+//! relationship map's traversal queries: an entry's neighbors, the
+//! shortest path between two entries and everything connected to one.
+//! Links to entries that have been deleted are left out of traversals.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::models::codex_relationship::{
+    CodexRelationship, Direction, MapNode, Neighbor, RelationshipGraph, RelationshipMap,
+    CREATE_CODEX_RELATIONSHIPS_TABLE_SQL, UPSERT_CODEX_RELATIONSHIP_SQL,
+};
+use crate::database::parse::parse_uuid;
+use crate::database::{DatabaseError, DatabaseResult, EnhancedDatabaseService};
+
+type RelationshipRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+);
+
+/// Service for codex relationships
+#[derive(Debug)]
+pub struct CodexRelationshipService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl CodexRelationshipService {
+    /// Create a new codex relationship service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the relationships table
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_CODEX_RELATIONSHIPS_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create codex relationships: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Create or update a relationship. Both entries must be in the
+    /// relationship's project, and two entries can be linked only once
+    /// with each type.
+    pub async fn save(&self, relationship: &CodexRelationship) -> DatabaseResult<()> {
+        let mut relationship = relationship.clone();
+        relationship.relationship_type = relationship.relationship_type.trim().to_lowercase();
+        relationship
+            .validate()
+            .map_err(DatabaseError::ValidationError)?;
+        let metadata = serde_json::to_string(&relationship.metadata)
+            .map_err(|e| DatabaseError::Service(format!("Failed to serialize metadata: {}", e)))?;
+
+        let db = self.db_service.read().await;
+        if has_codex_table(&db).await? {
+            let found: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM codex_entries
+                 WHERE project_id = ?1 AND is_active = 1 AND id IN (?2, ?3)",
+            )
+            .bind(relationship.project_id.to_string())
+            .bind(relationship.source_id.to_string())
+            .bind(relationship.target_id.to_string())
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to check codex entries: {}", e)))?;
+            if found < 2 {
+                return Err(DatabaseError::ValidationError(
+                    "Both entries must be in the project's codex".to_string(),
+                ));
+            }
+        }
+        let duplicate: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM codex_relationships
+             WHERE source_id = ?1 AND target_id = ?2 AND relationship_type = ?3 AND id <> ?4",
+        )
+        .bind(relationship.source_id.to_string())
+        .bind(relationship.target_id.to_string())
+        .bind(&relationship.relationship_type)
+        .bind(relationship.id.to_string())
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check relationships: {}", e)))?;
+        if duplicate > 0 {
+            return Err(DatabaseError::ValidationError(format!(
+                "The entries are already linked as \"{}\"",
+                relationship.relationship_type
+            )));
+        }
+
+        sqlx::query(UPSERT_CODEX_RELATIONSHIP_SQL)
+            .bind(relationship.id.to_string())
+            .bind(relationship.project_id.to_string())
+            .bind(relationship.source_id.to_string())
+            .bind(relationship.target_id.to_string())
+            .bind(&relationship.relationship_type)
+            .bind(metadata)
+            .bind(relationship.created_at.to_rfc3339())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save relationship: {}", e)))?;
+        Ok(())
+    }
+
+    /// Delete a relationship
+    pub async fn delete(&self, relationship_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM codex_relationships WHERE id = ?1")
+            .bind(relationship_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete relationship: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A project's relationships between entries that haven't been deleted
+    pub async fn relationships(&self, project_id: Uuid) -> DatabaseResult<Vec<CodexRelationship>> {
+        let db = self.db_service.read().await;
+        let sql = if has_codex_table(&db).await? {
+            "SELECT id, project_id, source_id, target_id, relationship_type, metadata, created_at, updated_at
+             FROM codex_relationships
+             WHERE project_id = ?1
+             AND source_id IN (SELECT id FROM codex_entries WHERE is_active = 1)
+             AND target_id IN (SELECT id FROM codex_entries WHERE is_active = 1)
+             ORDER BY created_at"
+        } else {
+            "SELECT id, project_id, source_id, target_id, relationship_type, metadata, created_at, updated_at
+             FROM codex_relationships WHERE project_id = ?1 ORDER BY created_at"
+        };
+        let rows: Vec<RelationshipRow> = sqlx::query_as(sql)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load relationships: {}", e)))?;
+        rows.into_iter().map(relationship_from_row).collect()
+    }
+
+    /// Entries one link away from `entry_id`, through links of the given
+    /// types (any type when empty)
+    pub async fn neighbors(
+        &self,
+        project_id: Uuid,
+        entry_id: Uuid,
+        direction: Direction,
+        types: &[String],
+    ) -> DatabaseResult<Vec<Neighbor>> {
+        let types: Vec<String> = types.iter().map(|t| t.trim().to_lowercase()).collect();
+        Ok(self
+            .graph(project_id)
+            .await?
+            .neighbors(entry_id, direction, &types))
+    }
+
+    /// The fewest links leading from one entry to another, in order
+    pub async fn path(
+        &self,
+        project_id: Uuid,
+        from: Uuid,
+        to: Uuid,
+        direction: Direction,
+    ) -> DatabaseResult<Option<Vec<CodexRelationship>>> {
+        Ok(self.graph(project_id).await?.path(from, to, direction))
+    }
+
+    /// Everything linked to an entry, however indirectly, for the map
+    pub async fn component(
+        &self,
+        project_id: Uuid,
+        entry_id: Uuid,
+    ) -> DatabaseResult<RelationshipMap> {
+        let (entries, relationships) = self.graph(project_id).await?.component(entry_id);
+        let nodes = self.nodes(project_id, &entries).await?;
+        Ok(RelationshipMap {
+            nodes,
+            relationships,
+        })
+    }
+
+    /// Every linked entry in a project, for the map
+    pub async fn map(&self, project_id: Uuid) -> DatabaseResult<RelationshipMap> {
+        let relationships = self.relationships(project_id).await?;
+        let mut entries = Vec::new();
+        for link in &relationships {
+            for id in [link.source_id, link.target_id] {
+                if !entries.contains(&id) {
+                    entries.push(id);
+                }
+            }
+        }
+        let nodes = self.nodes(project_id, &entries).await?;
+        Ok(RelationshipMap {
+            nodes,
+            relationships,
+        })
+    }
+
+    async fn graph(&self, project_id: Uuid) -> DatabaseResult<RelationshipGraph> {
+        Ok(RelationshipGraph::new(
+            self.relationships(project_id).await?,
+        ))
+    }
+
+    /// Titles and types of entries, in the order given
+    async fn nodes(&self, project_id: Uuid, entries: &[Uuid]) -> DatabaseResult<Vec<MapNode>> {
+        let db = self.db_service.read().await;
+        let mut known: HashMap<Uuid, (String, String)> = HashMap::new();
+        if has_codex_table(&db).await? {
+            let rows: Vec<(String, String, String)> = sqlx::query_as(
+                "SELECT id, title, entry_type FROM codex_entries WHERE project_id = ?1",
+            )
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load codex entries: {}", e)))?;
+            for (id, title, entry_type) in rows {
+                if let Ok(id) = Uuid::parse_str(&id) {
+                    known.insert(id, (title, entry_type));
+                }
+            }
+        }
+        Ok(entries
+            .iter()
+            .map(|id| {
+                let (title, entry_type) = known.get(id).cloned().unwrap_or_default();
+                MapNode {
+                    entry_id: *id,
+                    title,
+                    entry_type,
+                }
+            })
+            .collect())
+    }
+}
+
+pub(crate) async fn has_codex_table(db: &EnhancedDatabaseService) -> DatabaseResult<bool> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'codex_entries'",
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| DatabaseError::Service(format!("Failed to check codex table: {}", e)))?;
+    Ok(count > 0)
+}
+
+fn relationship_from_row(row: RelationshipRow) -> DatabaseResult<CodexRelationship> {
+    let (id, project_id, source_id, target_id, relationship_type, metadata, created_at, updated_at) =
+        row;
+    let parse_time = |value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| DatabaseError::Service(format!("Invalid timestamp: {}", e)))
+    };
+    Ok(CodexRelationship {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        source_id: parse_uuid(&source_id)?,
+        target_id: parse_uuid(&target_id)?,
+        relationship_type,
+        metadata: serde_json::from_str(&metadata)
+            .map_err(|e| DatabaseError::Service(format!("Invalid relationship metadata: {}", e)))?,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_relationships_skip_deleted_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        sqlx::query(
+            "CREATE TABLE codex_entries (id TEXT PRIMARY KEY, project_id TEXT, entry_type TEXT, title TEXT, is_active INTEGER)",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Ashfall', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let [mara, tobin, harbor] = [(); 3].map(|_| Uuid::new_v4());
+        for (id, entry_type, title) in [
+            (mara, "character_sheet", "Mara"),
+            (tobin, "character_sheet", "Tobin"),
+            (harbor, "place", "Harbor"),
+        ] {
+            sqlx::query("INSERT INTO codex_entries VALUES (?1, ?2, ?3, ?4, 1)")
+                .bind(id.to_string())
+                .bind(project.to_string())
+                .bind(entry_type)
+                .bind(title)
+                .execute(&db.pool)
+                .await
+                .unwrap();
+        }
+        let db = Arc::new(RwLock::new(db));
+        let service = CodexRelationshipService::new(db.clone());
+        service.initialize().await.unwrap();
+
+        let mut siblings = CodexRelationship::new(project, mara, tobin, "sibling of");
+        siblings
+            .metadata
+            .insert("since".to_string(), serde_json::json!("birth"));
+        service.save(&siblings).await.unwrap();
+        service
+            .save(&CodexRelationship::new(
+                project,
+                tobin,
+                harbor,
+                "Located in",
+            ))
+            .await
+            .unwrap();
+        assert!(matches!(
+            service
+                .save(&CodexRelationship::new(project, mara, tobin, "Sibling Of"))
+                .await,
+            Err(DatabaseError::ValidationError(_))
+        ));
+        assert!(matches!(
+            service
+                .save(&CodexRelationship::new(
+                    project,
+                    mara,
+                    Uuid::new_v4(),
+                    "owns"
+                ))
+                .await,
+            Err(DatabaseError::ValidationError(_))
+        ));
+
+        let path = service
+            .path(project, mara, harbor, Direction::Outgoing)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(path.len(), 2);
+        assert_eq!(path[0].metadata["since"], "birth");
+        let map = service.component(project, harbor).await.unwrap();
+        let titles: Vec<_> = map.nodes.iter().map(|n| n.title.as_str()).collect();
+        assert_eq!(titles, ["Harbor", "Tobin", "Mara"]);
+
+        // Deleting Tobin cuts the map in two
+        sqlx::query("UPDATE codex_entries SET is_active = 0 WHERE id = ?1")
+            .bind(tobin.to_string())
+            .execute(&db.read().await.pool)
+            .await
+            .unwrap();
+        assert!(service.map(project).await.unwrap().nodes.is_empty());
+        assert!(service
+            .neighbors(project, mara, Direction::Both, &[])
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(service.delete(siblings.id).await.unwrap());
+    }
+}
@@ -0,0 +1,497 @@
+//! Codex Transfer Service
+//!
+//! Exports a project's codex to JSON or CSV and imports entries from those
+//! files, Scrivener outlines and Plottr projects. An imported entry with
+//! the same type and title as one already in the codex, or earlier in the
+//! same file, is a duplicate and is skipped, merged or kept as the caller
+//! chooses. A dry run reads and matches everything and reports what would
+//! happen without writing; a real import writes in one transaction.
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::beta_reader_service::parse_csv;
+use crate::database::models::codex::{
+    CodexEntry, CodexEntryType, CodexExportResult, CodexImportAction, CodexImportItem,
+    CodexImportResult, CodexStatus, CREATE_CODEX_ENTRIES_TABLE_SQL,
+};
+use crate::database::models::codex_graph::entry_type_from_db;
+use crate::database::models::codex_transfer::{
+    duplicate_key, export_csv, export_json, parse_status, read_json, read_plottr, read_records,
+    CodexFormat, CodexImportOptions, DuplicatePolicy, ParsedImport,
+};
+use crate::database::parse::parse_uuid;
+use crate::database::{DatabaseError, DatabaseResult, EnhancedDatabaseService};
+
+type CodexEntryRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    bool,
+    Option<String>,
+    i64,
+);
+
+/// An entry already in the codex, or planned earlier in the import
+struct Known {
+    id: Uuid,
+    metadata: Option<String>,
+}
+
+/// Service for codex import and export
+#[derive(Debug)]
+pub struct CodexTransferService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl CodexTransferService {
+    /// Create a new codex transfer service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Make sure the codex table exists
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_CODEX_ENTRIES_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create codex table: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// A project's codex in JSON or CSV, with the number of entries
+    pub async fn export(
+        &self,
+        project_id: Uuid,
+        format: CodexFormat,
+    ) -> DatabaseResult<(String, usize)> {
+        if !format.can_export() {
+            return Err(DatabaseError::ValidationError(format!(
+                "Codex entries can't be exported as {}",
+                format.as_str()
+            )));
+        }
+        let entries = self.entries(project_id).await?;
+        let text = match format {
+            CodexFormat::Csv => export_csv(&entries),
+            _ => export_json(&entries)
+                .map_err(|e| DatabaseError::Service(format!("Failed to serialize codex: {}", e)))?,
+        };
+        Ok((text, entries.len()))
+    }
+
+    /// Export a project's codex to a file
+    pub async fn export_to(
+        &self,
+        project_id: Uuid,
+        format: CodexFormat,
+        path: &Path,
+    ) -> DatabaseResult<CodexExportResult> {
+        let started = Instant::now();
+        let (text, exported_count) = self.export(project_id, format).await?;
+        tokio::fs::write(path, text)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to write export: {}", e)))?;
+        Ok(CodexExportResult {
+            exported_count,
+            format: format.as_str().to_string(),
+            file_path: Some(path.display().to_string()),
+            duration_ms: started.elapsed().as_millis(),
+        })
+    }
+
+    /// Import entries from a file
+    pub async fn import_file(
+        &self,
+        project_id: Uuid,
+        path: &Path,
+        options: &CodexImportOptions,
+    ) -> DatabaseResult<CodexImportResult> {
+        let input = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to read import: {}", e)))?;
+        self.import(project_id, &input, options).await
+    }
+
+    /// Import entries into a project's codex. Entries that can't be read
+    /// are reported in the result and the rest are still imported; a file
+    /// that can't be read at all is an error.
+    pub async fn import(
+        &self,
+        project_id: Uuid,
+        input: &str,
+        options: &CodexImportOptions,
+    ) -> DatabaseResult<CodexImportResult> {
+        let started = Instant::now();
+        let input = input.trim_start_matches('\u{feff}');
+        let parsed: ParsedImport = match options.format {
+            CodexFormat::Json => read_json(input, options),
+            CodexFormat::Plottr => read_plottr(input, options),
+            CodexFormat::Csv | CodexFormat::Scrivener => {
+                Ok(read_records(&parse_csv(input), options))
+            }
+        }
+        .map_err(DatabaseError::ValidationError)?;
+
+        let db = self.db_service.read().await;
+        let mut result = import_entries(&db, project_id, parsed, options).await?;
+        result.duration_ms = started.elapsed().as_millis();
+        Ok(result)
+    }
+
+    /// A project's entries that haven't been deleted, by type and order
+    async fn entries(&self, project_id: Uuid) -> DatabaseResult<Vec<CodexEntry>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<CodexEntryRow> = sqlx::query_as(
+            "SELECT id, project_id, entry_type, title, content, status,
+                    created_at, updated_at, is_active, metadata, sort_order
+             FROM codex_entries
+             WHERE project_id = ?1 AND is_active = 1
+             ORDER BY entry_type, sort_order, title",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load codex entries: {}", e)))?;
+        rows.into_iter().map(codex_entry_from_row).collect()
+    }
+}
+
+/// Write entries read from a file into a project's codex, in one
+/// transaction, matching duplicates as the options say
+pub(crate) async fn import_entries(
+    db: &EnhancedDatabaseService,
+    project_id: Uuid,
+    parsed: ParsedImport,
+    options: &CodexImportOptions,
+) -> DatabaseResult<CodexImportResult> {
+    let live: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM projects WHERE id = ?1 AND deleted_at IS NULL")
+            .bind(project_id.to_string())
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load project: {}", e)))?;
+    if live == 0 {
+        return Err(DatabaseError::RecordNotFound {
+            entity: "project".to_string(),
+            id: project_id.to_string(),
+        });
+    }
+
+    let existing: Vec<(String, String, String, Option<String>, i64)> = sqlx::query_as(
+        "SELECT id, entry_type, title, metadata, sort_order FROM codex_entries
+             WHERE project_id = ?1 AND is_active = 1 ORDER BY sort_order",
+    )
+    .bind(project_id.to_string())
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| DatabaseError::Service(format!("Failed to load codex entries: {}", e)))?;
+    let mut known = HashMap::new();
+    let mut next_order: HashMap<CodexEntryType, i32> = HashMap::new();
+    for (id, entry_type, title, metadata, sort_order) in existing {
+        let (Ok(id), Some(entry_type)) = (Uuid::parse_str(&id), entry_type_from_db(&entry_type))
+        else {
+            continue;
+        };
+        let order = next_order.entry(entry_type).or_default();
+        *order = (*order).max(sort_order as i32 + 1);
+        known
+            .entry(duplicate_key(entry_type, &title))
+            .or_insert(Known { id, metadata });
+    }
+
+    let mut result = CodexImportResult {
+        failed_count: parsed.errors.len(),
+        errors: parsed.errors,
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+    let mut tx = db
+        .pool
+        .begin()
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to begin import: {}", e)))?;
+    let now = Utc::now().to_rfc3339();
+
+    for entry in parsed.entries {
+        let key = duplicate_key(entry.entry_type, &entry.title);
+        let duplicate = known.get(&key).map(|k: &Known| k.id);
+        let action = match (duplicate, options.duplicates) {
+            (Some(_), DuplicatePolicy::Skip) => CodexImportAction::Skip,
+            (Some(_), DuplicatePolicy::Update) => CodexImportAction::Update,
+            _ => CodexImportAction::Create,
+        };
+        result.items.push(CodexImportItem {
+            line: entry.line,
+            title: entry.title.clone(),
+            entry_type: entry.entry_type,
+            action,
+            existing_id: duplicate,
+        });
+
+        match action {
+            CodexImportAction::Skip => result.skipped_count += 1,
+            CodexImportAction::Update => {
+                let existing = known.get_mut(&key).expect("duplicate was found");
+                let metadata = merge_metadata(existing.metadata.as_deref(), entry.metadata);
+                if !options.dry_run {
+                    sqlx::query(
+                        "UPDATE codex_entries
+                             SET content = ?1, status = ?2, metadata = ?3,
+                                 sort_order = COALESCE(?4, sort_order), updated_at = ?5
+                             WHERE id = ?6",
+                    )
+                    .bind(&entry.content)
+                    .bind(entry.status.as_str())
+                    .bind(&metadata)
+                    .bind(entry.sort_order)
+                    .bind(&now)
+                    .bind(existing.id.to_string())
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Service(format!("Failed to update codex entry: {}", e))
+                    })?;
+                }
+                existing.metadata = metadata;
+                result.updated_count += 1;
+            }
+            CodexImportAction::Create => {
+                let id = Uuid::new_v4();
+                let metadata = merge_metadata(None, entry.metadata);
+                let order = next_order.entry(entry.entry_type).or_default();
+                let sort_order = entry.sort_order.unwrap_or(*order);
+                *order = (*order).max(sort_order + 1);
+                if !options.dry_run {
+                    sqlx::query(
+                        "INSERT INTO codex_entries (
+                                id, project_id, entry_type, title, content, status,
+                                created_at, updated_at, is_active, metadata, sort_order
+                            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7, 1, ?8, ?9)",
+                    )
+                    .bind(id.to_string())
+                    .bind(project_id.to_string())
+                    .bind(entry.entry_type.as_str())
+                    .bind(&entry.title)
+                    .bind(&entry.content)
+                    .bind(entry.status.as_str())
+                    .bind(&now)
+                    .bind(&metadata)
+                    .bind(sort_order)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Service(format!("Failed to import codex entry: {}", e))
+                    })?;
+                }
+                known.entry(key).or_insert(Known { id, metadata });
+                result.imported_count += 1;
+            }
+        }
+    }
+
+    if options.dry_run {
+        tx.rollback()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to end dry run: {}", e)))?;
+    } else {
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to commit import: {}", e)))?;
+    }
+    Ok(result)
+}
+
+/// Imported metadata laid over an entry's existing metadata, as stored;
+/// None when there's none
+fn merge_metadata(existing: Option<&str>, imported: Map<String, Value>) -> Option<String> {
+    let mut merged = match existing.map(str::trim) {
+        None | Some("") => Map::new(),
+        Some(text) => match serde_json::from_str(text) {
+            Ok(Value::Object(object)) => object,
+            _ => Map::from_iter([("metadata".to_string(), Value::String(text.to_string()))]),
+        },
+    };
+    merged.extend(imported);
+    (!merged.is_empty()).then(|| Value::Object(merged).to_string())
+}
+
+fn codex_entry_from_row(row: CodexEntryRow) -> DatabaseResult<CodexEntry> {
+    let (
+        id,
+        project_id,
+        entry_type,
+        title,
+        content,
+        status,
+        created_at,
+        updated_at,
+        is_active,
+        metadata,
+        sort_order,
+    ) = row;
+    let parse_time = |value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| DatabaseError::Service(format!("Invalid timestamp: {}", e)))
+    };
+    Ok(CodexEntry {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        entry_type: entry_type_from_db(&entry_type)
+            .ok_or_else(|| DatabaseError::Service(format!("Unknown entry type: {}", entry_type)))?,
+        title,
+        content,
+        status: parse_status(&status).unwrap_or(CodexStatus::Draft),
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+        is_active,
+        metadata: metadata.filter(|m| !m.is_empty()),
+        sort_order: sort_order as i32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    async fn project(db: &Arc<RwLock<EnhancedDatabaseService>>, name: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+        )
+        .bind(id.to_string())
+        .bind(name)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.read().await.pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    async fn service() -> (
+        tempfile::TempDir,
+        Arc<RwLock<EnhancedDatabaseService>>,
+        CodexTransferService,
+    ) {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let service = CodexTransferService::new(db.clone());
+        service.initialize().await.unwrap();
+        (dir, db, service)
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_between_projects() {
+        let (_dir, db, service) = service().await;
+        let ashfall = project(&db, "Ashfall").await;
+        let csv = "Name,Category,Description,Status,Eye color\r\n\
+                   Mara,Character,\"Pilot, 34\nFears deep water\",In Review,grey\r\n\
+                   Greyharbor,Location,Port town,,\r\n\
+                   Tobin,Wizard,,,\r\n";
+        let options = CodexImportOptions {
+            format: CodexFormat::Csv,
+            ..Default::default()
+        };
+        let result = service.import(ashfall, csv, &options).await.unwrap();
+        assert_eq!(result.imported_count, 2);
+        assert_eq!(result.failed_count, 1);
+
+        for format in [CodexFormat::Json, CodexFormat::Csv] {
+            let (text, count) = service.export(ashfall, format).await.unwrap();
+            assert_eq!(count, 2);
+            let copy = project(&db, "Copy").await;
+            let options = CodexImportOptions {
+                format,
+                ..Default::default()
+            };
+            let result = service.import(copy, &text, &options).await.unwrap();
+            assert_eq!((result.imported_count, result.failed_count), (2, 0));
+
+            let original = service.entries(ashfall).await.unwrap();
+            let copied = service.entries(copy).await.unwrap();
+            for (a, b) in original.iter().zip(&copied) {
+                assert_eq!(
+                    (a.entry_type, &a.title, &a.content, a.status, a.sort_order),
+                    (b.entry_type, &b.title, &b.content, b.status, b.sort_order)
+                );
+                assert_eq!(a.metadata, b.metadata);
+            }
+            assert_eq!(copied[0].status, CodexStatus::InReview);
+            assert_eq!(
+                copied[0].metadata.as_deref(),
+                Some(r#"{"Eye color":"grey"}"#)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplicates_and_dry_run() {
+        let (_dir, db, service) = service().await;
+        let ashfall = project(&db, "Ashfall").await;
+        let json = r#"[{"type": "character_sheet", "title": "Mara", "content": "Pilot",
+                        "metadata": {"age": 34}}]"#;
+        service
+            .import(ashfall, json, &CodexImportOptions::default())
+            .await
+            .unwrap();
+
+        let again = r#"[{"type": "character_sheet", "title": "  mara ", "content": "Harbor pilot",
+                         "metadata": {"eyes": "grey"}},
+                        {"type": "place", "title": "Greyharbor"},
+                        {"type": "place", "title": "Greyharbor", "content": "Port town"}]"#;
+        let mut options = CodexImportOptions {
+            duplicates: DuplicatePolicy::Update,
+            dry_run: true,
+            ..Default::default()
+        };
+        let planned = service.import(ashfall, again, &options).await.unwrap();
+        let actions: Vec<_> = planned.items.iter().map(|item| item.action).collect();
+        assert_eq!(
+            actions,
+            [
+                CodexImportAction::Update,
+                CodexImportAction::Create,
+                CodexImportAction::Update
+            ]
+        );
+        assert_eq!(service.entries(ashfall).await.unwrap().len(), 1);
+
+        options.dry_run = false;
+        let done = service.import(ashfall, again, &options).await.unwrap();
+        assert_eq!((done.imported_count, done.updated_count), (1, 2));
+        let entries = service.entries(ashfall).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].content, "Harbor pilot");
+        assert_eq!(
+            entries[0].metadata.as_deref(),
+            Some(r#"{"age":34,"eyes":"grey"}"#)
+        );
+        assert_eq!(entries[1].content, "Port town");
+
+        let skipped = service
+            .import(ashfall, again, &CodexImportOptions::default())
+            .await
+            .unwrap();
+        assert_eq!((skipped.imported_count, skipped.skipped_count), (0, 3));
+    }
+}
@@ -0,0 +1,370 @@
+//! Content Scan Service
+//!
+//! Scans project documents against user-editable lexicons and produces
+//! per-chapter reports, plus content-warning front matter for exports.
+
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{
+    models::content_scan::*,
+    prosemirror,
+    text_match::{find_word_matches, line_and_column},
+    DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+
+type LexiconRow = (
+    String,
+    Option<String>,
+    String,
+    String,
+    String,
+    String,
+    bool,
+    String,
+    String,
+);
+
+/// Service for managing content lexicons and scanning documents
+#[derive(Debug)]
+pub struct ContentScanService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl ContentScanService {
+    /// Create a new content scan service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize lexicon tables and seed the shared default lexicons
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        {
+            let db = self.db_service.read().await;
+            sqlx::query(CREATE_CONTENT_LEXICON_TABLES_SQL)
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to create lexicon tables: {}", e))
+                })?;
+        }
+
+        let shared: i64 = {
+            let db = self.db_service.read().await;
+            sqlx::query_scalar("SELECT COUNT(*) FROM content_lexicons WHERE project_id IS NULL")
+                .fetch_one(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to count lexicons: {}", e)))?
+        };
+
+        if shared == 0 {
+            for lexicon in default_lexicons() {
+                self.save_lexicon(&lexicon).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create or update a lexicon
+    pub async fn save_lexicon(&self, lexicon: &ContentLexicon) -> DatabaseResult<()> {
+        if lexicon.name.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Lexicon name cannot be empty".to_string(),
+            ));
+        }
+
+        let terms: Vec<String> = lexicon
+            .terms
+            .iter()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_CONTENT_LEXICON_SQL)
+            .bind(lexicon.id.to_string())
+            .bind(lexicon.project_id.map(|id| id.to_string()))
+            .bind(&lexicon.name)
+            .bind(to_json(&lexicon.category)?)
+            .bind(to_json(&lexicon.severity)?)
+            .bind(to_json(&terms)?)
+            .bind(lexicon.enabled)
+            .bind(lexicon.created_at.to_rfc3339())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save lexicon: {}", e)))?;
+        Ok(())
+    }
+
+    /// Delete a lexicon
+    pub async fn delete_lexicon(&self, lexicon_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query(DELETE_CONTENT_LEXICON_SQL)
+            .bind(lexicon_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete lexicon: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get the lexicons that apply to a project
+    pub async fn get_lexicons(&self, project_id: Uuid) -> DatabaseResult<Vec<ContentLexicon>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<LexiconRow> = sqlx::query_as(GET_LEXICONS_FOR_PROJECT_SQL)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get lexicons: {}", e)))?;
+
+        rows.into_iter().map(lexicon_from_row).collect()
+    }
+
+    /// Scan every active document in a project
+    pub async fn scan_project(&self, project_id: Uuid) -> DatabaseResult<ContentScanReport> {
+        let lexicons = self.get_lexicons(project_id).await?;
+        let db = self.db_service.read().await;
+
+        let documents: Vec<(String, String, Option<String>, String)> = sqlx::query_as(
+            "SELECT id, title, content, document_type FROM documents
+             WHERE project_id = ?1 AND is_active = 1
+             ORDER BY title ASC",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+
+        let mut chapters = Vec::new();
+        let mut totals_by_category: HashMap<ContentCategory, usize> = HashMap::new();
+
+        for (id, title, content, document_type) in documents {
+            let document_id = parse_uuid(&id)?;
+            let text = prosemirror::document_text(&document_type, content.as_deref().unwrap_or(""));
+            let report = scan_chapter(&lexicons, document_id, title, &text);
+            for (category, count) in &report.counts_by_category {
+                *totals_by_category.entry(category.clone()).or_insert(0) += count;
+            }
+            chapters.push(report);
+        }
+
+        Ok(ContentScanReport {
+            project_id,
+            scanned_at: Utc::now(),
+            chapters,
+            totals_by_category,
+        })
+    }
+}
+
+/// Scan a single chapter's text against a set of lexicons
+pub fn scan_chapter(
+    lexicons: &[ContentLexicon],
+    document_id: Uuid,
+    title: String,
+    text: &str,
+) -> ChapterContentReport {
+    let mut matches = Vec::new();
+
+    for lexicon in lexicons.iter().filter(|l| l.enabled) {
+        for term in &lexicon.terms {
+            for (start, end) in find_word_matches(text, term) {
+                matches.push(ContentMatch {
+                    lexicon_id: lexicon.id,
+                    category: lexicon.category.clone(),
+                    severity: lexicon.severity,
+                    term: term.clone(),
+                    start,
+                    end,
+                    line: line_and_column(text, start).0,
+                });
+            }
+        }
+    }
+    matches.sort_by_key(|m| m.start);
+
+    let mut counts_by_category = HashMap::new();
+    for m in &matches {
+        *counts_by_category.entry(m.category.clone()).or_insert(0) += 1;
+    }
+    let highest_severity = matches.iter().map(|m| m.severity).max();
+
+    ChapterContentReport {
+        document_id,
+        title,
+        matches,
+        counts_by_category,
+        highest_severity,
+    }
+}
+
+/// Render a content-warning page as Markdown, or `None` if nothing was flagged
+pub fn render_front_matter_markdown(report: &ContentScanReport) -> Option<String> {
+    let flagged = report.flagged_categories();
+    if flagged.is_empty() {
+        return None;
+    }
+
+    let mut out = String::from("# Content Warnings\n\nThis work contains:\n\n");
+    for (category, severity) in flagged {
+        out.push_str(&format!(
+            "- {} ({})\n",
+            category.display_name(),
+            severity_label(severity)
+        ));
+    }
+    Some(out)
+}
+
+/// Render a content-warning page as an XHTML fragment for ePub/HTML exports
+pub fn render_front_matter_html(report: &ContentScanReport) -> Option<String> {
+    let flagged = report.flagged_categories();
+    if flagged.is_empty() {
+        return None;
+    }
+
+    let mut out = String::from(
+        "<section class=\"content-warnings\" epub:type=\"preface\">\n<h1>Content Warnings</h1>\n<p>This work contains:</p>\n<ul>\n",
+    );
+    for (category, severity) in flagged {
+        out.push_str(&format!(
+            "<li>{} ({})</li>\n",
+            escape_html(category.display_name()),
+            severity_label(severity)
+        ));
+    }
+    out.push_str("</ul>\n</section>\n");
+    Some(out)
+}
+
+fn severity_label(severity: ContentSeverity) -> &'static str {
+    match severity {
+        ContentSeverity::Mild => "mild",
+        ContentSeverity::Moderate => "moderate",
+        ContentSeverity::Strong => "strong",
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Built-in shared lexicons, seeded once and then editable by the user
+fn default_lexicons() -> Vec<ContentLexicon> {
+    let lexicon = |name: &str, category, severity, terms: &[&str]| {
+        ContentLexicon::new(
+            None,
+            name.to_string(),
+            category,
+            severity,
+            terms.iter().map(|t| t.to_string()).collect(),
+        )
+    };
+
+    vec![
+        lexicon(
+            "Violence",
+            ContentCategory::Violence,
+            ContentSeverity::Moderate,
+            &[
+                "murder",
+                "murdered",
+                "stabbed",
+                "strangled",
+                "massacre",
+                "torture",
+            ],
+        ),
+        lexicon(
+            "Mild Profanity",
+            ContentCategory::Profanity,
+            ContentSeverity::Mild,
+            &["damn", "hell", "crap", "bloody"],
+        ),
+        lexicon(
+            "Substance Use",
+            ContentCategory::SubstanceUse,
+            ContentSeverity::Moderate,
+            &["cocaine", "heroin", "overdose", "meth"],
+        ),
+        lexicon(
+            "Self-Harm",
+            ContentCategory::SelfHarm,
+            ContentSeverity::Strong,
+            &["suicide", "self-harm"],
+        ),
+    ]
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> DatabaseResult<String> {
+    serde_json::to_string(value)
+        .map_err(|e| DatabaseError::Service(format!("Failed to serialize lexicon: {}", e)))
+}
+
+fn lexicon_from_row(row: LexiconRow) -> DatabaseResult<ContentLexicon> {
+    let (id, project_id, name, category, severity, terms, enabled, created_at, updated_at) = row;
+    let parse_json = |field: &str, value: &str| {
+        DatabaseError::Service(format!("Failed to parse lexicon {} '{}'", field, value))
+    };
+
+    Ok(ContentLexicon {
+        id: parse_uuid(&id)?,
+        project_id: project_id
+            .map(|p| Uuid::parse_str(&p))
+            .transpose()
+            .map_err(|e| DatabaseError::Service(format!("Invalid UUID: {}", e)))?,
+        name,
+        category: serde_json::from_str(&category).map_err(|_| parse_json("category", &category))?,
+        severity: serde_json::from_str(&severity).map_err(|_| parse_json("severity", &severity))?,
+        terms: serde_json::from_str(&terms).map_err(|_| parse_json("terms", &terms))?,
+        enabled,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_chapter_counts_by_category() {
+        let lexicons = default_lexicons();
+        let report = scan_chapter(
+            &lexicons,
+            Uuid::nil(),
+            "Chapter 1".to_string(),
+            "Damn it, she said.\nThe murder happened at dawn. Hellish weather.",
+        );
+        assert_eq!(report.matches.len(), 2);
+        assert_eq!(report.counts_by_category[&ContentCategory::Profanity], 1);
+        assert_eq!(report.counts_by_category[&ContentCategory::Violence], 1);
+        assert_eq!(report.highest_severity, Some(ContentSeverity::Moderate));
+        assert_eq!(report.matches[1].line, 2);
+    }
+
+    #[test]
+    fn test_front_matter_omitted_when_clean() {
+        let report = ContentScanReport {
+            project_id: Uuid::nil(),
+            scanned_at: Utc::now(),
+            chapters: vec![scan_chapter(
+                &default_lexicons(),
+                Uuid::nil(),
+                "Clean".to_string(),
+                "A quiet walk in the park.",
+            )],
+            totals_by_category: HashMap::new(),
+        };
+        assert!(render_front_matter_markdown(&report).is_none());
+        assert!(render_front_matter_html(&report).is_none());
+    }
+}
@@ -0,0 +1,431 @@
+//! Deadline Service
+//!
+//! Keeps the contest, submission and personal deadlines a project is written
+//! towards and counts them down against the project's word count: time
+//! left, words still to write and the daily pace needed. A background timer
+//! notifies as each deadline comes closer, once per stage, from a quiet
+//! heads-up a month out to an error once it has passed.
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::{
+    models::deadline::*,
+    parse::{parse_time, parse_uuid},
+    DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+use crate::notifications::{DesktopNotification, Notifier};
+
+type DeadlineRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    Option<i64>,
+    Option<String>,
+    String,
+    bool,
+    Option<String>,
+    String,
+    String,
+);
+
+/// Days of word count history the recent pace is taken from
+const RECENT_PACE_DAYS: i64 = 7;
+
+/// Service for deadlines, countdowns and deadline notifications
+#[derive(Debug)]
+pub struct DeadlineService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl DeadlineService {
+    /// Create a new deadline service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the deadlines table
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_DEADLINES_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create deadlines table: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Save a deadline. Moving it further out lets the stages it has left
+    /// behind notify again.
+    pub async fn save_deadline(&self, deadline: &Deadline) -> DatabaseResult<Deadline> {
+        if deadline.title.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Deadline title cannot be empty".to_string(),
+            ));
+        }
+        let mut saved = deadline.clone();
+        saved.updated_at = Utc::now();
+        let stage = DeadlineStage::for_hours_left((saved.due_at - saved.updated_at).num_hours());
+        saved.notified_stage = saved
+            .notified_stage
+            .filter(|notified| Some(*notified) <= stage);
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_DEADLINE_SQL)
+            .bind(saved.id.to_string())
+            .bind(saved.project_id.to_string())
+            .bind(&saved.title)
+            .bind(saved.kind.as_str())
+            .bind(saved.due_at.to_rfc3339())
+            .bind(saved.target_words.map(|w| w as i64))
+            .bind(&saved.url)
+            .bind(&saved.notes)
+            .bind(saved.completed)
+            .bind(saved.notified_stage.map(|s| s.as_str()))
+            .bind(saved.created_at.to_rfc3339())
+            .bind(saved.updated_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save deadline: {}", e)))?;
+        Ok(saved)
+    }
+
+    /// Delete a deadline
+    pub async fn delete_deadline(&self, deadline_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM deadlines WHERE id = ?1")
+            .bind(deadline_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete deadline: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A project's deadlines, soonest first
+    pub async fn list_deadlines(&self, project_id: Uuid) -> DatabaseResult<Vec<Deadline>> {
+        self.deadlines(Some(project_id)).await
+    }
+
+    /// Countdowns for a project's open deadlines, soonest first
+    pub async fn countdowns(&self, project_id: Uuid) -> DatabaseResult<Vec<DeadlineCountdown>> {
+        let deadlines = self.deadlines(Some(project_id)).await?;
+        let current_words = self.current_words(project_id).await?;
+        let recent = self.recent_daily_words(project_id).await?;
+        let now = Utc::now();
+        Ok(deadlines
+            .into_iter()
+            .filter(|d| !d.completed)
+            .map(|deadline| countdown(deadline, current_words, recent, now))
+            .collect())
+    }
+
+    /// Notify about every deadline that has reached a new stage and record
+    /// the stage shown
+    pub async fn send_due_alerts(&self) -> DatabaseResult<usize> {
+        let deadlines = self.deadlines(None).await?;
+        let now = Utc::now();
+        let mut sent = 0;
+        for deadline in deadlines.into_iter().filter(|d| !d.completed) {
+            let current_words = self.current_words(deadline.project_id).await?;
+            let recent = self.recent_daily_words(deadline.project_id).await?;
+            let Some(alert) = due_alert(&countdown(deadline, current_words, recent, now)) else {
+                continue;
+            };
+
+            let notification = DesktopNotification::new(
+                alert.title.clone(),
+                alert.message.clone(),
+                alert.stage.level(),
+            );
+            if let Err(e) = Notifier::global().notify(&notification) {
+                log::warn!("Failed to show deadline notification: {}", e);
+            }
+            let db = self.db_service.read().await;
+            sqlx::query("UPDATE deadlines SET notified_stage = ?2 WHERE id = ?1")
+                .bind(alert.deadline_id.to_string())
+                .bind(alert.stage.as_str())
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to record deadline notification: {}", e))
+                })?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Check for deadline notifications now and then every `every`
+    pub fn spawn_alerts(
+        self: Arc<Self>,
+        every: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.send_due_alerts().await {
+                    log::error!("Deadline notifications failed: {}", e);
+                }
+            }
+        })
+    }
+
+    async fn deadlines(&self, project_id: Option<Uuid>) -> DatabaseResult<Vec<Deadline>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<DeadlineRow> = match project_id {
+            Some(project_id) => {
+                sqlx::query_as(&format!(
+                    "{} WHERE project_id = ?1 ORDER BY due_at ASC",
+                    SELECT_DEADLINES_SQL
+                ))
+                .bind(project_id.to_string())
+                .fetch_all(&db.pool)
+                .await
+            }
+            None => {
+                sqlx::query_as(&format!("{} ORDER BY due_at ASC", SELECT_DEADLINES_SQL))
+                    .fetch_all(&db.pool)
+                    .await
+            }
+        }
+        .map_err(|e| DatabaseError::Service(format!("Failed to list deadlines: {}", e)))?;
+        rows.into_iter().map(deadline_from_row).collect()
+    }
+
+    /// Words in the project's active documents
+    async fn current_words(&self, project_id: Uuid) -> DatabaseResult<u64> {
+        let db = self.db_service.read().await;
+        let words: Option<i64> = sqlx::query_scalar(
+            "SELECT SUM(word_count) FROM documents WHERE project_id = ?1 AND is_active = 1
+               AND json_extract(CASE WHEN json_valid(metadata) THEN metadata END, '$.exclude_from_word_count') IS NOT 1",
+        )
+        .bind(project_id.to_string())
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to count words: {}", e)))?;
+        Ok(words.unwrap_or(0).max(0) as u64)
+    }
+
+    /// Words a day over the last week of word count snapshots, if there are
+    /// at least two days to compare
+    async fn recent_daily_words(&self, project_id: Uuid) -> DatabaseResult<Option<f64>> {
+        let db = self.db_service.read().await;
+        let has_history: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'word_count_history'",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check schema: {}", e)))?;
+        if has_history == 0 {
+            return Ok(None);
+        }
+
+        let since = (Utc::now() - Duration::days(RECENT_PACE_DAYS)).date_naive();
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT day, SUM(word_count) FROM word_count_history
+             WHERE project_id = ?1 AND day >= ?2 GROUP BY day ORDER BY day",
+        )
+        .bind(project_id.to_string())
+        .bind(since.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load word count history: {}", e)))?;
+        let totals: Vec<(NaiveDate, i64)> = rows
+            .into_iter()
+            .filter_map(|(day, words)| Some((day.parse().ok()?, words)))
+            .collect();
+        Ok(daily_words(&totals))
+    }
+}
+
+/// Average words a day between the first and last daily totals
+pub fn daily_words(totals: &[(NaiveDate, i64)]) -> Option<f64> {
+    let (first_day, first) = totals.first()?;
+    let (last_day, last) = totals.last()?;
+    let days = (*last_day - *first_day).num_days();
+    (days > 0).then(|| (last - first).max(0) as f64 / days as f64)
+}
+
+/// Count a deadline down from `now`
+pub fn countdown(
+    deadline: Deadline,
+    current_words: u64,
+    recent_daily_words: Option<f64>,
+    now: DateTime<Utc>,
+) -> DeadlineCountdown {
+    let left = deadline.due_at - now;
+    let hours_left = left.num_hours();
+    let words_remaining = deadline
+        .target_words
+        .map(|target| target.saturating_sub(current_words));
+    // Today counts as a writing day, so a deadline later today needs the
+    // rest written today
+    let writing_days = (left.num_days() + 1).max(1) as u64;
+    let daily_pace = words_remaining
+        .filter(|_| hours_left >= 0)
+        .map(|remaining| remaining.div_ceil(writing_days));
+    let on_track = words_remaining.map(|remaining| {
+        remaining == 0
+            || (hours_left >= 0
+                && recent_daily_words
+                    .is_some_and(|recent| recent * writing_days as f64 >= remaining as f64))
+    });
+
+    DeadlineCountdown {
+        days_left: left.num_days(),
+        hours_left,
+        stage: DeadlineStage::for_hours_left(hours_left),
+        current_words,
+        words_remaining,
+        daily_pace,
+        recent_daily_words,
+        on_track,
+        deadline,
+    }
+}
+
+/// The notification for a deadline that has reached a stage it hasn't
+/// notified yet. Stages skipped over, e.g. while the app was closed, are
+/// not caught up on; only the current one is shown.
+pub fn due_alert(countdown: &DeadlineCountdown) -> Option<DeadlineAlert> {
+    let deadline = &countdown.deadline;
+    let stage = countdown.stage?;
+    if deadline.completed
+        || deadline
+            .notified_stage
+            .is_some_and(|notified| notified >= stage)
+    {
+        return None;
+    }
+
+    let when = match stage {
+        DeadlineStage::Overdue if countdown.days_left == 0 => "was due today".to_string(),
+        DeadlineStage::Overdue => format!("was due {} ago", plural(-countdown.days_left, "day")),
+        DeadlineStage::Day => format!("is due in {}", plural(countdown.hours_left, "hour")),
+        _ => format!("is due in {}", plural(countdown.days_left, "day")),
+    };
+    let mut message = format!("{} {}", deadline.title, when);
+    match (countdown.words_remaining, countdown.daily_pace) {
+        (Some(0), _) => message.push_str("; the word target is reached"),
+        (Some(remaining), Some(pace)) => {
+            message.push_str(&format!("; {} words to go, {} a day", remaining, pace))
+        }
+        (Some(remaining), None) => message.push_str(&format!("; {} words short", remaining)),
+        _ => {}
+    }
+
+    let title = match stage {
+        DeadlineStage::Overdue => "Deadline passed",
+        DeadlineStage::Day | DeadlineStage::Days => "Deadline approaching",
+        _ => "Upcoming deadline",
+    };
+    Some(DeadlineAlert {
+        deadline_id: deadline.id,
+        project_id: deadline.project_id,
+        stage,
+        title: title.to_string(),
+        message,
+    })
+}
+
+fn plural(count: i64, unit: &str) -> String {
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+fn deadline_from_row(row: DeadlineRow) -> DatabaseResult<Deadline> {
+    let (
+        id,
+        project_id,
+        title,
+        kind,
+        due_at,
+        target_words,
+        url,
+        notes,
+        completed,
+        notified_stage,
+        created_at,
+        updated_at,
+    ) = row;
+    Ok(Deadline {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        title,
+        kind: DeadlineKind::parse(&kind),
+        due_at: parse_time(&due_at)?,
+        target_words: target_words.and_then(|w| u64::try_from(w).ok()),
+        url,
+        notes,
+        completed,
+        notified_stage: notified_stage.as_deref().and_then(DeadlineStage::parse),
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automation::NotificationLevel;
+
+    #[test]
+    fn test_countdown_pace_and_escalation() {
+        let now = Utc::now();
+        let mut deadline = Deadline::new(
+            Uuid::new_v4(),
+            "Spring Fiction Contest",
+            DeadlineKind::Contest,
+            now + Duration::days(5) + Duration::hours(1),
+        );
+        deadline.target_words = Some(50_000);
+
+        let day = |d: u32| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+        let recent = daily_words(&[(day(1), 30_000), (day(3), 30_500), (day(5), 32_000)]);
+        assert_eq!(recent, Some(500.0));
+        assert_eq!(daily_words(&[(day(1), 30_000)]), None);
+
+        let status = countdown(deadline.clone(), 40_000, recent, now);
+        assert_eq!(
+            (status.days_left, status.stage),
+            (5, Some(DeadlineStage::Week))
+        );
+        assert_eq!(status.words_remaining, Some(10_000));
+        assert_eq!(status.daily_pace, Some(1_667));
+        assert_eq!(status.on_track, Some(false));
+
+        let alert = due_alert(&status).unwrap();
+        assert_eq!(alert.stage.level(), NotificationLevel::Info);
+        assert_eq!(
+            alert.message,
+            "Spring Fiction Contest is due in 5 days; 10000 words to go, 1667 a day"
+        );
+
+        // Already notified for this stage
+        deadline.notified_stage = Some(DeadlineStage::Week);
+        assert!(due_alert(&countdown(deadline.clone(), 40_000, recent, now)).is_none());
+
+        // Escalates once it's within the last day
+        let status = countdown(deadline.clone(), 40_000, recent, now + Duration::days(5));
+        let alert = due_alert(&status).unwrap();
+        assert_eq!(alert.stage, DeadlineStage::Day);
+        assert_eq!(alert.stage.level(), NotificationLevel::Warning);
+        assert_eq!(status.daily_pace, Some(10_000));
+
+        let status = countdown(deadline, 40_000, recent, now + Duration::days(7));
+        let alert = due_alert(&status).unwrap();
+        assert_eq!(alert.stage.level(), NotificationLevel::Error);
+        assert_eq!(
+            alert.message,
+            "Spring Fiction Contest was due 1 day ago; 10000 words short"
+        );
+    }
+}
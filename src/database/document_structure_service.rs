@@ -0,0 +1,986 @@
+//! Document Structure Service
+//!
+//! Splits a document into scene documents and merges documents into one,
+//! each in a single transaction. Whatever points into the text comes along:
+//! annotations move with the passage they anchor, attachments follow merged
+//! documents and `herdingcats://document/` links to a merged document are
+//! repointed. The binder order is updated to match.
+
+use chrono::Utc;
+use serde_json::Value;
+use sqlx::SqliteConnection;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::parse::parse_uuid;
+use crate::database::{
+    models::document_structure::*, prosemirror, DatabaseError, DatabaseResult,
+    EnhancedDatabaseService,
+};
+use crate::deep_link::DeepLink;
+
+type DocumentRow = (String, String, Option<String>, Option<String>);
+type AnnotationOffsetRow = (String, i64, i64);
+
+/// Service for splitting and merging documents
+#[derive(Debug)]
+pub struct DocumentStructureService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl DocumentStructureService {
+    /// Create a new document structure service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the binder order table
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_BINDER_ORDER_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create binder order table: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Active documents of a project in binder order
+    pub async fn binder_order(&self, project_id: Uuid) -> DatabaseResult<Vec<Uuid>> {
+        let db = self.db_service.read().await;
+        let mut conn = db
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get connection: {}", e)))?;
+        order_of(&mut conn, project_id).await
+    }
+
+    /// Reorder the binder. Documents left out keep their relative order
+    /// after the listed ones.
+    pub async fn set_binder_order(
+        &self,
+        project_id: Uuid,
+        document_ids: &[Uuid],
+    ) -> DatabaseResult<Vec<Uuid>> {
+        let db = self.db_service.read().await;
+        let mut tx =
+            db.pool.begin().await.map_err(|e| {
+                DatabaseError::Service(format!("Failed to begin transaction: {}", e))
+            })?;
+
+        let order = reorder(&mut tx, project_id, document_ids).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to commit binder order: {}", e)))?;
+        Ok(order)
+    }
+
+    /// Split a document into several. The original keeps the first part and
+    /// its version history; the other parts become new documents placed right
+    /// after it in the binder, titled by their heading when they start with one.
+    pub async fn split(&self, request: &SplitRequest) -> DatabaseResult<SplitResult> {
+        let db = self.db_service.read().await;
+        let mut tx =
+            db.pool.begin().await.map_err(|e| {
+                DatabaseError::Service(format!("Failed to begin transaction: {}", e))
+            })?;
+
+        let (project_id, title, content, document_type) =
+            load_document(&mut tx, request.document_id).await?;
+        let parts = match prosemirror::parse(&document_type, &content) {
+            Some(doc) => split_blocks(&doc, &request.at),
+            None => split_parts(&content, &split_points(&content, &request.at)?),
+        };
+        if parts.len() < 2 {
+            return Err(DatabaseError::ValidationError(
+                "Nothing to split: the document would stay in one piece".to_string(),
+            ));
+        }
+
+        let mut document_ids = Vec::with_capacity(parts.len());
+        for (index, part) in parts.iter().enumerate() {
+            if index == 0 {
+                update_content(
+                    &mut tx,
+                    request.document_id,
+                    None,
+                    &document_type,
+                    &part.content,
+                )
+                .await?;
+                document_ids.push(request.document_id);
+                continue;
+            }
+
+            let id = Uuid::new_v4();
+            let part_title = part
+                .heading
+                .clone()
+                .unwrap_or_else(|| format!("{} ({})", title, index + 1));
+            sqlx::query(
+                "INSERT INTO documents (id, project_id, title, content, plain_text, document_type, word_count, checksum, created_at, updated_at, is_active, version, metadata)
+                 SELECT ?, project_id, ?, ?, ?, document_type, ?, ?, ?, ?, 1, 1, metadata FROM documents WHERE id = ?",
+            )
+            .bind(id.to_string())
+            .bind(&part_title)
+            .bind(&part.content)
+            .bind(prosemirror::search_text(&document_type, &part.content))
+            .bind(prosemirror::word_count(&document_type, &part.content) as i32)
+            .bind(EnhancedDatabaseService::calculate_checksum(&part.content))
+            .bind(Utc::now())
+            .bind(Utc::now())
+            .bind(request.document_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to create split document: {}", e)))?;
+            document_ids.push(id);
+        }
+
+        let mut annotations_moved = 0;
+        if table_exists(&mut tx, "annotations").await? {
+            let annotations: Vec<AnnotationOffsetRow> = sqlx::query_as(
+                "SELECT id, start_offset, end_offset FROM annotations WHERE document_id = ?1",
+            )
+            .bind(request.document_id.to_string())
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load annotations: {}", e)))?;
+
+            for (annotation_id, start, end) in annotations {
+                let (start, end) = (start.max(0) as usize, end.max(0) as usize);
+                let index = parts.iter().rposition(|p| p.start <= start).unwrap_or(0);
+                let part = &parts[index];
+                let new_start = start.saturating_sub(part.start).min(part.length);
+                let new_end = end.saturating_sub(part.start).clamp(new_start, part.length);
+
+                sqlx::query(
+                    "UPDATE annotations SET document_id = ?1, start_offset = ?2, end_offset = ?3 WHERE id = ?4",
+                )
+                .bind(document_ids[index].to_string())
+                .bind(new_start as i64)
+                .bind(new_end as i64)
+                .bind(&annotation_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to move annotation: {}", e)))?;
+                if index > 0 {
+                    annotations_moved += 1;
+                }
+            }
+        }
+
+        let mut order = order_of(&mut tx, project_id).await?;
+        order.retain(|id| !document_ids[1..].contains(id));
+        let at = order
+            .iter()
+            .position(|id| *id == request.document_id)
+            .map_or(order.len(), |i| i + 1);
+        order.splice(at..at, document_ids[1..].iter().copied());
+        write_order(&mut tx, project_id, &order).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to commit split: {}", e)))?;
+
+        Ok(SplitResult {
+            document_ids,
+            annotations_moved,
+        })
+    }
+
+    /// Merge documents, in the order given, into the first one. The others
+    /// are deactivated rather than deleted so their history is kept; their
+    /// annotations, attachments and incoming links move to the merged document.
+    pub async fn merge(&self, request: &MergeRequest) -> DatabaseResult<MergeResult> {
+        let ids = &request.document_ids;
+        if ids.len() < 2 {
+            return Err(DatabaseError::ValidationError(
+                "At least two documents are needed to merge".to_string(),
+            ));
+        }
+        if ids.iter().enumerate().any(|(i, id)| ids[..i].contains(id)) {
+            return Err(DatabaseError::ValidationError(
+                "A document is listed more than once".to_string(),
+            ));
+        }
+
+        let db = self.db_service.read().await;
+        let mut tx =
+            db.pool.begin().await.map_err(|e| {
+                DatabaseError::Service(format!("Failed to begin transaction: {}", e))
+            })?;
+
+        let mut documents = Vec::with_capacity(ids.len());
+        for id in ids {
+            documents.push(load_document(&mut tx, *id).await?);
+        }
+        let project_id = documents[0].0;
+        if documents
+            .iter()
+            .any(|(project, _, _, _)| *project != project_id)
+        {
+            return Err(DatabaseError::ValidationError(
+                "Documents from different projects can't be merged".to_string(),
+            ));
+        }
+
+        let parsed: Vec<Option<Value>> = documents
+            .iter()
+            .map(|(_, _, content, document_type)| prosemirror::parse(document_type, content))
+            .collect();
+        let (content, document_type, offsets) = if parsed.iter().any(Option::is_some) {
+            // Editor documents merge node by node; any plain text joins them
+            // as paragraphs
+            let parts: Vec<(&str, Vec<Value>)> = documents
+                .iter()
+                .zip(&parsed)
+                .map(|((_, title, content, _), doc)| {
+                    let blocks = match doc {
+                        Some(doc) => prosemirror::blocks(doc).to_vec(),
+                        None => prosemirror::paragraphs(content),
+                    };
+                    (title.as_str(), blocks)
+                })
+                .collect();
+            let shell = parsed.iter().flatten().next().cloned().unwrap_or_default();
+            let (doc, offsets) = merge_blocks(shell, parts, &request.separator);
+            (
+                doc.to_string(),
+                prosemirror::JSON_DOCUMENT_TYPE.to_string(),
+                offsets,
+            )
+        } else {
+            let parts: Vec<(&str, &str)> = documents
+                .iter()
+                .map(|(_, title, content, _)| (title.as_str(), content.as_str()))
+                .collect();
+            let (content, offsets) = merge_text(&parts, &request.separator);
+            (content, documents[0].3.clone(), offsets)
+        };
+        let title = request
+            .title
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .unwrap_or(&documents[0].1);
+        let target = ids[0];
+        let merged = ids[1..].to_vec();
+        update_content(&mut tx, target, Some(title), &document_type, &content).await?;
+
+        let has_annotations = table_exists(&mut tx, "annotations").await?;
+        let has_attachments = table_exists(&mut tx, "attachments").await?;
+        let mut annotations_moved = 0;
+        let mut attachments_moved = 0;
+        for (id, offset) in merged.iter().zip(&offsets[1..]) {
+            sqlx::query("UPDATE documents SET is_active = 0, updated_at = ?1 WHERE id = ?2")
+                .bind(Utc::now())
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to retire merged document: {}", e))
+                })?;
+
+            if has_annotations {
+                let result = sqlx::query(
+                    "UPDATE annotations SET document_id = ?1, start_offset = start_offset + ?2, end_offset = end_offset + ?2
+                     WHERE document_id = ?3",
+                )
+                .bind(target.to_string())
+                .bind(*offset as i64)
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to move annotations: {}", e)))?;
+                annotations_moved += result.rows_affected() as usize;
+            }
+            if has_attachments {
+                let result = sqlx::query(
+                    "UPDATE attachments SET owner_id = ?1 WHERE owner_kind = 'document' AND owner_id = ?2",
+                )
+                .bind(target.to_string())
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to move attachments: {}", e)))?;
+                attachments_moved += result.rows_affected() as usize;
+            }
+        }
+
+        let linking: Vec<(String, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT id, content, document_type FROM documents WHERE project_id = ?1 AND is_active = 1",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+        let mut links_updated = 0;
+        for (id, text, document_type) in linking {
+            let (rewritten, count) = rewrite_links(text.as_deref().unwrap_or(""), &merged, target);
+            if count > 0 {
+                let document_type = document_type.unwrap_or_default();
+                update_content(&mut tx, parse_uuid(&id)?, None, &document_type, &rewritten).await?;
+                links_updated += 1;
+            }
+        }
+
+        // Merged documents are inactive now, so they drop out of the order
+        let order = order_of(&mut tx, project_id).await?;
+        write_order(&mut tx, project_id, &order).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to commit merge: {}", e)))?;
+
+        Ok(MergeResult {
+            document_id: target,
+            merged,
+            annotations_moved,
+            attachments_moved,
+            links_updated,
+        })
+    }
+}
+
+/// One part of a split document
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SplitPart {
+    /// Byte offset of the part in the original text
+    start: usize,
+    /// Length of the part's text in bytes
+    length: usize,
+    content: String,
+    heading: Option<String>,
+}
+
+/// Byte offsets to cut the text at, excluding its start and end
+fn split_points(text: &str, at: &SplitAt) -> DatabaseResult<Vec<usize>> {
+    let mut points = match at {
+        SplitAt::Headings { max_level } => {
+            let mut points = Vec::new();
+            let mut offset = 0;
+            for line in text.split_inclusive('\n') {
+                if heading_level(line).is_some_and(|level| level <= *max_level) {
+                    points.push(offset);
+                }
+                offset += line.len();
+            }
+            points
+        }
+        SplitAt::Offsets { offsets } => {
+            if let Some(bad) = offsets.iter().find(|&&o| !text.is_char_boundary(o)) {
+                return Err(DatabaseError::ValidationError(format!(
+                    "Offset {} is not a character boundary in the document",
+                    bad
+                )));
+            }
+            offsets.clone()
+        }
+    };
+    points.retain(|&p| p > 0 && p < text.len());
+    points.sort_unstable();
+    points.dedup();
+    Ok(points)
+}
+
+/// The text between split points, trimmed; blank parts are dropped
+fn split_parts(text: &str, points: &[usize]) -> Vec<SplitPart> {
+    let starts = std::iter::once(0).chain(points.iter().copied());
+    let ends = points.iter().copied().chain(std::iter::once(text.len()));
+    starts
+        .zip(ends)
+        .filter_map(|(from, to)| {
+            let segment = &text[from..to];
+            let content = segment.trim();
+            if content.is_empty() {
+                return None;
+            }
+            let first_line = content.lines().next().unwrap_or("");
+            Some(SplitPart {
+                start: from + segment.len() - segment.trim_start().len(),
+                length: content.len(),
+                content: content.to_string(),
+                heading: heading_level(first_line)
+                    .map(|_| heading_text(first_line))
+                    .filter(|t| !t.is_empty()),
+            })
+        })
+        .collect()
+}
+
+/// Split ProseMirror JSON between its top-level nodes: before each
+/// `heading` node, or before the first node that starts at or after each
+/// offset into the document's text. Parts without text are dropped.
+fn split_blocks(doc: &Value, at: &SplitAt) -> Vec<SplitPart> {
+    let blocks = prosemirror::blocks(doc);
+    let starts = prosemirror::block_starts(doc);
+    let mut cuts: Vec<usize> = match at {
+        SplitAt::Headings { max_level } => blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| {
+                prosemirror::node_type(block) == "heading"
+                    && block["attrs"]["level"].as_u64().unwrap_or(1) <= u64::from(*max_level)
+            })
+            .map(|(index, _)| index)
+            .collect(),
+        SplitAt::Offsets { offsets } => offsets
+            .iter()
+            .filter_map(|&offset| starts.iter().position(|&start| start >= offset))
+            .collect(),
+    };
+    cuts.retain(|&index| index > 0);
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    let mut shell = doc.clone();
+    shell["content"] = Value::Array(Vec::new());
+    let froms = std::iter::once(0).chain(cuts.iter().copied());
+    let tos = cuts.iter().copied().chain(std::iter::once(blocks.len()));
+    froms
+        .zip(tos)
+        .filter_map(|(from, to)| {
+            let mut part = shell.clone();
+            part["content"] = Value::Array(blocks[from..to].to_vec());
+            let text = prosemirror::plain_text(&part);
+            if text.trim().is_empty() {
+                return None;
+            }
+            let first = &blocks[from];
+            Some(SplitPart {
+                start: starts[from],
+                length: text.len(),
+                content: part.to_string(),
+                heading: (prosemirror::node_type(first) == "heading")
+                    .then(|| prosemirror::plain_text(first).trim().to_string())
+                    .filter(|t| !t.is_empty()),
+            })
+        })
+        .collect()
+}
+
+/// Level of a Markdown (`## Title`) or HTML (`<h2>Title</h2>`) heading line
+fn heading_level(line: &str) -> Option<u8> {
+    let line = line.trim_start();
+    let hashes = line.bytes().take_while(|&b| b == b'#').count();
+    if (1..=6).contains(&hashes) && line[hashes..].starts_with([' ', '\t']) {
+        return Some(hashes as u8);
+    }
+    match line.get(..4)?.to_ascii_lowercase().as_bytes() {
+        [b'<', b'h', level @ b'1'..=b'6', b'>' | b' '] => Some(level - b'0'),
+        _ => None,
+    }
+}
+
+/// A heading line without its markup
+fn heading_text(line: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in line.trim().chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.trim()
+        .trim_start_matches('#')
+        .trim_end_matches('#')
+        .trim()
+        .to_string()
+}
+
+/// Join `(title, content)` parts with the separator; returns the text and
+/// the byte offset each part starts at
+fn merge_text(parts: &[(&str, &str)], separator: &MergeSeparator) -> (String, Vec<usize>) {
+    let mut out = String::new();
+    let mut offsets = Vec::with_capacity(parts.len());
+    for (index, (title, content)) in parts.iter().enumerate() {
+        if index > 0 {
+            out.truncate(out.trim_end().len());
+            match separator {
+                MergeSeparator::SceneBreak => out.push_str("\n\n* * *\n\n"),
+                MergeSeparator::BlankLine => out.push_str("\n\n"),
+                MergeSeparator::TitleHeading => {
+                    out.push_str("\n\n## ");
+                    out.push_str(title.trim());
+                    out.push_str("\n\n");
+                }
+                MergeSeparator::Custom { text } => out.push_str(text),
+            }
+        }
+        offsets.push(out.len());
+        out.push_str(content);
+    }
+    (out, offsets)
+}
+
+/// Join the top-level nodes of `(title, nodes)` parts into `shell` with the
+/// separator as nodes; returns the document and the byte offset each part
+/// starts at in its text
+fn merge_blocks(
+    mut shell: Value,
+    parts: Vec<(&str, Vec<Value>)>,
+    separator: &MergeSeparator,
+) -> (Value, Vec<usize>) {
+    let paragraph = |text: &str| prosemirror::paragraphs(text.trim());
+    let mut blocks = Vec::new();
+    let mut firsts = Vec::with_capacity(parts.len());
+    for (index, (title, nodes)) in parts.into_iter().enumerate() {
+        if index > 0 {
+            match separator {
+                MergeSeparator::SceneBreak => blocks.extend(paragraph("* * *")),
+                MergeSeparator::BlankLine => {}
+                MergeSeparator::TitleHeading => blocks.push(serde_json::json!({
+                    "type": "heading",
+                    "attrs": { "level": 2 },
+                    "content": [{ "type": "text", "text": title.trim() }],
+                })),
+                MergeSeparator::Custom { text } => blocks.extend(paragraph(text)),
+            }
+        }
+        firsts.push(blocks.len());
+        blocks.extend(nodes);
+    }
+    shell["content"] = Value::Array(blocks);
+
+    let starts = prosemirror::block_starts(&shell);
+    let end = prosemirror::plain_text(&shell).len();
+    let offsets = firsts
+        .into_iter()
+        .map(|first| starts.get(first).copied().unwrap_or(end))
+        .collect();
+    (shell, offsets)
+}
+
+/// Point links to any of `merged` at `target`; returns the text and the
+/// number of links changed
+fn rewrite_links(text: &str, merged: &[Uuid], target: Uuid) -> (String, usize) {
+    let link = |id: Uuid| {
+        DeepLink::OpenDocument {
+            document_id: id.to_string(),
+        }
+        .to_url()
+    };
+    let to = link(target);
+    let mut out = text.to_string();
+    let mut count = 0;
+    for id in merged {
+        let from = link(*id);
+        let found = out.matches(&from).count();
+        if found > 0 {
+            out = out.replace(&from, &to);
+            count += found;
+        }
+    }
+    (out, count)
+}
+
+/// Project, title, content and document type of an active document
+async fn load_document(
+    conn: &mut SqliteConnection,
+    document_id: Uuid,
+) -> DatabaseResult<(Uuid, String, String, String)> {
+    let row: Option<DocumentRow> = sqlx::query_as(
+        "SELECT project_id, title, content, document_type FROM documents WHERE id = ?1 AND is_active = 1",
+    )
+    .bind(document_id.to_string())
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| DatabaseError::Service(format!("Failed to load document: {}", e)))?;
+    let (project_id, title, content, document_type) =
+        row.ok_or_else(|| DatabaseError::RecordNotFound {
+            entity: "document".to_string(),
+            id: document_id.to_string(),
+        })?;
+    Ok((
+        parse_uuid(&project_id)?,
+        title,
+        content.unwrap_or_default(),
+        document_type.unwrap_or_default(),
+    ))
+}
+
+/// Replace a document's content (and title), recording a new version
+async fn update_content(
+    conn: &mut SqliteConnection,
+    document_id: Uuid,
+    title: Option<&str>,
+    document_type: &str,
+    content: &str,
+) -> DatabaseResult<()> {
+    sqlx::query(
+        "UPDATE documents SET title = COALESCE(?, title), content = ?, document_type = ?, checksum = ?, updated_at = ?, version = version + 1 WHERE id = ?",
+    )
+    .bind(title)
+    .bind(content)
+    .bind(document_type)
+    .bind(EnhancedDatabaseService::calculate_checksum(content))
+    .bind(Utc::now())
+    .bind(document_id.to_string())
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| DatabaseError::Service(format!("Failed to update document: {}", e)))?;
+    prosemirror::store_document_text(conn, &document_id.to_string(), document_type, content).await
+}
+
+pub(crate) async fn order_of(
+    conn: &mut SqliteConnection,
+    project_id: Uuid,
+) -> DatabaseResult<Vec<Uuid>> {
+    let ids: Vec<String> = sqlx::query_scalar(GET_BINDER_ORDER_SQL)
+        .bind(project_id.to_string())
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to get binder order: {}", e)))?;
+    ids.iter().map(|id| parse_uuid(id)).collect()
+}
+
+/// Reorder the binder on `conn`; see `set_binder_order`
+pub(crate) async fn reorder(
+    conn: &mut SqliteConnection,
+    project_id: Uuid,
+    document_ids: &[Uuid],
+) -> DatabaseResult<Vec<Uuid>> {
+    let current = order_of(conn, project_id).await?;
+    if let Some(unknown) = document_ids.iter().find(|id| !current.contains(id)) {
+        return Err(DatabaseError::ValidationError(format!(
+            "Document {} is not in this project",
+            unknown
+        )));
+    }
+    let mut order: Vec<Uuid> = Vec::with_capacity(current.len());
+    for id in document_ids.iter().chain(&current) {
+        if !order.contains(id) {
+            order.push(*id);
+        }
+    }
+    write_order(conn, project_id, &order).await?;
+    Ok(order)
+}
+
+async fn write_order(
+    conn: &mut SqliteConnection,
+    project_id: Uuid,
+    order: &[Uuid],
+) -> DatabaseResult<()> {
+    sqlx::query("DELETE FROM binder_order WHERE project_id = ?1")
+        .bind(project_id.to_string())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to clear binder order: {}", e)))?;
+    for (position, id) in order.iter().enumerate() {
+        sqlx::query(INSERT_BINDER_POSITION_SQL)
+            .bind(id.to_string())
+            .bind(project_id.to_string())
+            .bind(position as i64)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save binder order: {}", e)))?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn table_exists(conn: &mut SqliteConnection, name: &str) -> DatabaseResult<bool> {
+    let count: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1")
+            .bind(name)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to check schema: {}", e)))?;
+    Ok(count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{
+        models::annotation::{Annotation, AnnotationSource},
+        AnnotationService, DatabaseConfig,
+    };
+
+    #[test]
+    fn test_split_points_and_merge_text() {
+        let text =
+            "Opening line.\n\n## The Storm\nRain.\n\n<h2>After</h2>\nCalm.\n### Aside\nNote.";
+        let points = split_points(text, &SplitAt::Headings { max_level: 2 }).unwrap();
+        let parts = split_parts(text, &points);
+        let headings: Vec<Option<&str>> = parts.iter().map(|p| p.heading.as_deref()).collect();
+        assert_eq!(headings, vec![None, Some("The Storm"), Some("After")]);
+        assert_eq!(parts[2].content, "<h2>After</h2>\nCalm.\n### Aside\nNote.");
+        assert_eq!(&text[parts[1].start..parts[1].start + 5], "## Th");
+        assert!(split_points("héllo", &SplitAt::Offsets { offsets: vec![2] }).is_err());
+
+        let (merged, offsets) = merge_text(
+            &[("One", "First.\n"), ("Two", "Second.")],
+            &MergeSeparator::TitleHeading,
+        );
+        assert_eq!(merged, "First.\n\n## Two\n\nSecond.");
+        assert_eq!(&merged[offsets[1]..], "Second.");
+    }
+
+    #[tokio::test]
+    async fn test_split_then_merge_keeps_annotations_links_and_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Novel', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let chapter = Uuid::new_v4();
+        let notes = Uuid::new_v4();
+        db.create_document(
+            chapter.to_string(),
+            project.to_string(),
+            "Chapter One".to_string(),
+            "# Arrival\nThe train was late.\n\n# Departure\nShe left at dawn.".to_string(),
+        )
+        .await
+        .unwrap();
+        db.create_document(
+            notes.to_string(),
+            project.to_string(),
+            "Notes".to_string(),
+            "See the storm scene.".to_string(),
+        )
+        .await
+        .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let annotations = AnnotationService::new(db.clone());
+        annotations.initialize().await.unwrap();
+        let service = DocumentStructureService::new(db.clone());
+        service.initialize().await.unwrap();
+        service
+            .set_binder_order(project, &[chapter, notes])
+            .await
+            .unwrap();
+
+        let dawn = "# Arrival\nThe train was late.\n\n# Departure\nShe left at ".len();
+        let annotation = Annotation::new(
+            project,
+            chapter,
+            (dawn, dawn + 4),
+            "dawn".to_string(),
+            "Which dawn?".to_string(),
+            "Ed".to_string(),
+            AnnotationSource::Editor,
+        );
+        annotations.create_annotation(&annotation).await.unwrap();
+
+        let split = service
+            .split(&SplitRequest {
+                document_id: chapter,
+                at: SplitAt::Headings { max_level: 1 },
+            })
+            .await
+            .unwrap();
+        let departure = split.document_ids[1];
+        assert_eq!(split.annotations_moved, 1);
+        assert_eq!(
+            service.binder_order(project).await.unwrap(),
+            vec![chapter, departure, notes]
+        );
+        let moved = annotations
+            .get_annotations_for_document(departure)
+            .await
+            .unwrap();
+        let content = db
+            .read()
+            .await
+            .get_document(departure.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&content[moved[0].start..moved[0].end], "dawn");
+        let link = format!("See herdingcats://document/{}", departure);
+        db.read()
+            .await
+            .update_document_content(&notes.to_string(), &link)
+            .await
+            .unwrap();
+
+        // Merging back puts the scene, its annotation and links into `chapter`
+        let merged = service
+            .merge(&MergeRequest {
+                document_ids: vec![chapter, departure],
+                separator: MergeSeparator::SceneBreak,
+                title: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(merged.annotations_moved, 1);
+        assert_eq!(merged.links_updated, 1);
+        assert_eq!(
+            service.binder_order(project).await.unwrap(),
+            vec![chapter, notes]
+        );
+        let content = db
+            .read()
+            .await
+            .get_document(chapter.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            content,
+            "# Arrival\nThe train was late.\n\n* * *\n\n# Departure\nShe left at dawn."
+        );
+        let moved = annotations
+            .get_annotations_for_document(chapter)
+            .await
+            .unwrap();
+        assert_eq!(&content[moved[0].start..moved[0].end], "dawn");
+        assert_eq!(
+            db.read()
+                .await
+                .get_document(notes.to_string())
+                .await
+                .unwrap(),
+            Some(format!("See herdingcats://document/{}", chapter))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_and_merge_prosemirror_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Novel', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let heading = |text: &str| {
+            serde_json::json!({
+                "type": "heading",
+                "attrs": { "level": 1 },
+                "content": [{ "type": "text", "text": text }],
+            })
+        };
+        let paragraph = |text: &str| prosemirror::paragraphs(text).remove(0);
+        let doc = serde_json::json!({
+            "type": "doc",
+            "content": [
+                heading("Arrival"),
+                paragraph("The train was late."),
+                heading("Departure"),
+                paragraph("She left at dawn."),
+            ],
+        });
+        let text = prosemirror::plain_text(&doc);
+        let offsets = split_blocks(
+            &doc,
+            &SplitAt::Offsets {
+                offsets: vec![text.find("train").unwrap()],
+            },
+        );
+        assert_eq!(offsets.len(), 2);
+        assert_eq!(offsets[1].heading.as_deref(), Some("Departure"));
+
+        let chapter = Uuid::new_v4();
+        db.create_document(
+            chapter.to_string(),
+            project.to_string(),
+            "Chapter One".to_string(),
+            doc.to_string(),
+        )
+        .await
+        .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let annotations = AnnotationService::new(db.clone());
+        annotations.initialize().await.unwrap();
+        let service = DocumentStructureService::new(db.clone());
+        service.initialize().await.unwrap();
+
+        let dawn = text.find("dawn").unwrap();
+        let annotation = Annotation::new(
+            project,
+            chapter,
+            (dawn, dawn + 4),
+            "dawn".to_string(),
+            "Which dawn?".to_string(),
+            "Ed".to_string(),
+            AnnotationSource::Editor,
+        );
+        annotations.create_annotation(&annotation).await.unwrap();
+
+        let split = service
+            .split(&SplitRequest {
+                document_id: chapter,
+                at: SplitAt::Headings { max_level: 1 },
+            })
+            .await
+            .unwrap();
+        assert_eq!(split.document_ids.len(), 2);
+        let departure = split.document_ids[1];
+        let stored = |id: Uuid| {
+            let db = db.clone();
+            async move {
+                let (title, content, word_count): (String, String, i64) = sqlx::query_as(
+                    "SELECT title, content, word_count FROM documents WHERE id = ?1",
+                )
+                .bind(id.to_string())
+                .fetch_one(&db.read().await.pool)
+                .await
+                .unwrap();
+                let doc: Value = serde_json::from_str(&content).unwrap();
+                (title, prosemirror::plain_text(&doc), word_count)
+            }
+        };
+        assert_eq!(
+            stored(chapter).await,
+            (
+                "Chapter One".to_string(),
+                "Arrival\nThe train was late.".to_string(),
+                5
+            )
+        );
+        let (title, text, word_count) = stored(departure).await;
+        assert_eq!(title, "Departure");
+        assert_eq!(text, "Departure\nShe left at dawn.");
+        assert_eq!(word_count, 5);
+        let moved = annotations
+            .get_annotations_for_document(departure)
+            .await
+            .unwrap();
+        assert_eq!(&text[moved[0].start..moved[0].end], "dawn");
+
+        service
+            .merge(&MergeRequest {
+                document_ids: vec![chapter, departure],
+                separator: MergeSeparator::SceneBreak,
+                title: None,
+            })
+            .await
+            .unwrap();
+        let (_, text, _) = stored(chapter).await;
+        assert_eq!(
+            text,
+            "Arrival\nThe train was late.\n* * *\nDeparture\nShe left at dawn."
+        );
+        let moved = annotations
+            .get_annotations_for_document(chapter)
+            .await
+            .unwrap();
+        assert_eq!(&text[moved[0].start..moved[0].end], "dawn");
+    }
+}
@@ -0,0 +1,392 @@
+//! Document Template Service
+//!
+//! Stores document templates, shared ones seeded with the starter set and
+//! per-project ones, and creates documents from them: placeholders are
+//! filled from the project, its codex and the answers to the template's
+//! prompts.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::models::document_template::{
+    default_templates, fill_placeholders, DocumentTemplate, ResolvedPrompt,
+    CREATE_DOCUMENT_TEMPLATES_TABLE_SQL, GET_DOCUMENT_TEMPLATES_SQL, UPSERT_DOCUMENT_TEMPLATE_SQL,
+};
+use crate::database::parse::parse_uuid;
+use crate::database::{DatabaseError, DatabaseResult, EnhancedDatabaseService};
+
+type DocumentTemplateRow = (
+    String,
+    Option<String>,
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+);
+
+/// Service for document templates
+#[derive(Debug)]
+pub struct DocumentTemplateService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl DocumentTemplateService {
+    /// Create a new document template service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the table and seed the starter templates on first run
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let shared: i64 = {
+            let db = self.db_service.read().await;
+            sqlx::query(CREATE_DOCUMENT_TEMPLATES_TABLE_SQL)
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to create document templates: {}", e))
+                })?;
+            sqlx::query_scalar("SELECT COUNT(*) FROM document_templates WHERE project_id IS NULL")
+                .fetch_one(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to count document templates: {}", e))
+                })?
+        };
+
+        if shared == 0 {
+            for template in default_templates() {
+                self.save_template(&template).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Create or update a template
+    pub async fn save_template(&self, template: &DocumentTemplate) -> DatabaseResult<()> {
+        template
+            .validate()
+            .map_err(DatabaseError::ValidationError)?;
+        let prompts = serde_json::to_string(&template.prompts)
+            .map_err(|e| DatabaseError::Service(format!("Failed to serialize prompts: {}", e)))?;
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_DOCUMENT_TEMPLATE_SQL)
+            .bind(template.id.to_string())
+            .bind(template.project_id.map(|id| id.to_string()))
+            .bind(template.name.trim())
+            .bind(template.category.trim())
+            .bind(&template.description)
+            .bind(&template.title)
+            .bind(&template.body)
+            .bind(prompts)
+            .bind(template.created_at.to_rfc3339())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to save document template: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Delete a template
+    pub async fn delete_template(&self, template_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM document_templates WHERE id = ?1")
+            .bind(template_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to delete document template: {}", e))
+            })?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Shared templates and the project's own. Without a project only the
+    /// shared templates are returned.
+    pub async fn templates(
+        &self,
+        project_id: Option<Uuid>,
+    ) -> DatabaseResult<Vec<DocumentTemplate>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<DocumentTemplateRow> = sqlx::query_as(GET_DOCUMENT_TEMPLATES_SQL)
+            .bind(project_id.map(|id| id.to_string()))
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to load document templates: {}", e))
+            })?;
+        rows.into_iter().map(template_from_row).collect()
+    }
+
+    /// Get a template by ID
+    pub async fn template(&self, template_id: Uuid) -> DatabaseResult<DocumentTemplate> {
+        let db = self.db_service.read().await;
+        let row: Option<DocumentTemplateRow> = sqlx::query_as(
+            "SELECT id, project_id, name, category, description, title, body, prompts, created_at, updated_at
+             FROM document_templates WHERE id = ?1",
+        )
+        .bind(template_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load document template: {}", e)))?;
+        row.map(template_from_row)
+            .transpose()?
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "document template".to_string(),
+                id: template_id.to_string(),
+            })
+    }
+
+    /// The template's prompts as they'd be asked in a project, with
+    /// defaults filled in and codex choices listed
+    pub async fn prompts(
+        &self,
+        template_id: Uuid,
+        project_id: Uuid,
+    ) -> DatabaseResult<Vec<ResolvedPrompt>> {
+        let template = self.template(template_id).await?;
+        let values = self
+            .project_values(project_id, Utc::now().date_naive())
+            .await?;
+        let codex = self.codex_titles(project_id).await?;
+        Ok(template
+            .prompts
+            .into_iter()
+            .map(|prompt| ResolvedPrompt {
+                default: fill_placeholders(&prompt.default, &values),
+                choices: prompt
+                    .choices_from
+                    .and_then(|entry_type| codex.get(&entry_type).cloned())
+                    .unwrap_or_default(),
+                name: prompt.name,
+                label: prompt.label,
+            })
+            .collect())
+    }
+
+    /// Create a document in the project from a template; prompts without
+    /// an answer take their default. Returns the new document's ID.
+    pub async fn create_document(
+        &self,
+        template_id: Uuid,
+        project_id: Uuid,
+        answers: &HashMap<String, String>,
+    ) -> DatabaseResult<String> {
+        self.create_document_on(template_id, project_id, answers, Utc::now().date_naive())
+            .await
+    }
+
+    /// Create a document from a template as of `day`, which fills the
+    /// `{{date}}` and `{{year}}` placeholders
+    pub async fn create_document_on(
+        &self,
+        template_id: Uuid,
+        project_id: Uuid,
+        answers: &HashMap<String, String>,
+        day: NaiveDate,
+    ) -> DatabaseResult<String> {
+        let template = self.template(template_id).await?;
+        let mut values = self.project_values(project_id, day).await?;
+        for prompt in &template.prompts {
+            let answer = match answers.get(&prompt.name).filter(|a| !a.trim().is_empty()) {
+                Some(answer) => answer.clone(),
+                None => fill_placeholders(&prompt.default, &values),
+            };
+            values.insert(prompt.name.clone(), answer);
+        }
+
+        let title = fill_placeholders(&template.title, &values);
+        values.insert("title".to_string(), title.clone());
+        let body = fill_placeholders(&template.body, &values);
+
+        let db = self.db_service.read().await;
+        db.create_document(
+            Uuid::new_v4().to_string(),
+            project_id.to_string(),
+            title,
+            body,
+        )
+        .await
+    }
+
+    /// Project details, codex titles and the date, by placeholder name
+    async fn project_values(
+        &self,
+        project_id: Uuid,
+        today: NaiveDate,
+    ) -> DatabaseResult<HashMap<String, String>> {
+        let project: Option<(String, Option<String>)> = {
+            let db = self.db_service.read().await;
+            sqlx::query_as(
+                "SELECT name, description FROM projects WHERE id = ?1 AND deleted_at IS NULL",
+            )
+            .bind(project_id.to_string())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load project: {}", e)))?
+        };
+        let (name, description) = project.ok_or_else(|| DatabaseError::RecordNotFound {
+            entity: "project".to_string(),
+            id: project_id.to_string(),
+        })?;
+
+        let mut values = HashMap::from([
+            ("project.name".to_string(), name),
+            (
+                "project.description".to_string(),
+                description.unwrap_or_default(),
+            ),
+            ("date".to_string(), today.format("%Y-%m-%d").to_string()),
+            ("year".to_string(), today.format("%Y").to_string()),
+        ]);
+        for (entry_type, titles) in self.codex_titles(project_id).await? {
+            values.insert(format!("codex.{}", entry_type), titles.join(", "));
+        }
+        Ok(values)
+    }
+
+    /// Titles of the project's codex entries, by entry type
+    async fn codex_titles(&self, project_id: Uuid) -> DatabaseResult<HashMap<String, Vec<String>>> {
+        let db = self.db_service.read().await;
+        let has_codex: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'codex_entries'",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check codex table: {}", e)))?;
+        if has_codex == 0 {
+            return Ok(HashMap::new());
+        }
+
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT entry_type, title FROM codex_entries
+             WHERE project_id = ?1 AND is_active = 1
+             ORDER BY sort_order, title COLLATE NOCASE",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load codex entries: {}", e)))?;
+        let mut titles: HashMap<String, Vec<String>> = HashMap::new();
+        for (entry_type, title) in rows {
+            titles.entry(entry_type).or_default().push(title);
+        }
+        Ok(titles)
+    }
+}
+
+fn template_from_row(row: DocumentTemplateRow) -> DatabaseResult<DocumentTemplate> {
+    let (id, project_id, name, category, description, title, body, prompts, created_at, updated_at) =
+        row;
+    let parse_time = |value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| DatabaseError::Service(format!("Invalid timestamp: {}", e)))
+    };
+    Ok(DocumentTemplate {
+        id: parse_uuid(&id)?,
+        project_id: project_id.as_deref().map(parse_uuid).transpose()?,
+        name,
+        category,
+        description,
+        title,
+        body,
+        prompts: serde_json::from_str(&prompts)
+            .map_err(|e| DatabaseError::Service(format!("Invalid template prompts: {}", e)))?,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::document_template::TemplatePrompt;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_create_document_from_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Ashfall', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query("CREATE TABLE codex_entries (id TEXT, project_id TEXT, entry_type TEXT, title TEXT, is_active INTEGER, sort_order INTEGER)")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO codex_entries VALUES ('1', ?1, 'character_sheet', 'Mara', 1, 0), ('2', ?1, 'character_sheet', 'Tobin', 1, 1)")
+            .bind(project.to_string())
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let service = DocumentTemplateService::new(db.clone());
+        service.initialize().await.unwrap();
+        service.initialize().await.unwrap();
+        assert_eq!(
+            service.templates(None).await.unwrap().len(),
+            default_templates().len()
+        );
+
+        let mut template = DocumentTemplate::new(
+            "Character Scene",
+            "Scenes",
+            "{{pov}} in {{project.name}}",
+            "# {{title}}\n\nCast: {{codex.character_sheet}}\nMood: {{mood}}",
+        )
+        .with_prompt(
+            TemplatePrompt::new("pov", "Point of view")
+                .with_default("Narrator")
+                .with_choices_from("character_sheet"),
+        )
+        .with_prompt(TemplatePrompt::new("mood", "Mood").with_default("{{project.name}} grey"));
+        template.project_id = Some(project);
+        service.save_template(&template).await.unwrap();
+        assert_eq!(
+            service.templates(Some(project)).await.unwrap().len(),
+            default_templates().len() + 1
+        );
+
+        let prompts = service.prompts(template.id, project).await.unwrap();
+        assert_eq!(prompts[0].choices, ["Mara", "Tobin"]);
+        assert_eq!(prompts[1].default, "Ashfall grey");
+
+        let answers = HashMap::from([("pov".to_string(), "Mara".to_string())]);
+        let document_id = service
+            .create_document(template.id, project, &answers)
+            .await
+            .unwrap();
+        let content = db.read().await.get_document(document_id).await.unwrap();
+        assert_eq!(
+            content.as_deref(),
+            Some("# Mara in Ashfall\n\nCast: Mara, Tobin\nMood: Ashfall grey")
+        );
+
+        assert!(service.delete_template(template.id).await.unwrap());
+        assert!(matches!(
+            service
+                .create_document(template.id, project, &answers)
+                .await,
+            Err(DatabaseError::RecordNotFound { .. })
+        ));
+    }
+}
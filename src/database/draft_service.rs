@@ -0,0 +1,676 @@
+//! Draft Service
+//!
+//! Tags revision passes as named project drafts, snapshots every active
+//! document into the draft, and compares chapters across drafts, including
+//! as redline PDFs.
+
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{
+    models::draft::*,
+    text_diff::{diff_lines, summarize, DiffSummary},
+    DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+use crate::publishing::redline::document_paragraphs;
+use crate::publishing::{RedlineDocument, RedlineSection};
+
+type DraftRow = (
+    String,
+    String,
+    String,
+    Option<String>,
+    i64,
+    i64,
+    i64,
+    String,
+);
+type DraftDocumentRow = (String, String, String, String, String, i64, String);
+
+/// Service for creating and comparing project drafts
+#[derive(Debug)]
+pub struct DraftService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl DraftService {
+    /// Create a new draft service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize draft tables and indexes
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_DRAFT_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create draft tables: {}", e))
+            })?;
+
+        // Snapshots taken before drafts recorded the document type
+        let columns: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info('draft_documents')")
+                .fetch_all(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Migration(e.to_string()))?;
+        if !columns.iter().any(|c| c == "document_type") {
+            sqlx::query(
+                "ALTER TABLE draft_documents ADD COLUMN document_type TEXT NOT NULL DEFAULT 'json'",
+            )
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to add draft document type: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Create a named draft, snapshotting every active document in the project
+    pub async fn create_draft(
+        &self,
+        project_id: Uuid,
+        name: Option<String>,
+        description: Option<String>,
+    ) -> DatabaseResult<Draft> {
+        let db = self.db_service.read().await;
+
+        let mut tx =
+            db.pool.begin().await.map_err(|e| {
+                DatabaseError::Service(format!("Failed to begin transaction: {}", e))
+            })?;
+
+        // Numbered inside the transaction; UNIQUE(project_id, draft_number)
+        // rejects a concurrent create that read the same maximum
+        let last_number: Option<i64> =
+            sqlx::query_scalar("SELECT MAX(draft_number) FROM drafts WHERE project_id = ?1")
+                .bind(project_id.to_string())
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to get draft number: {}", e))
+                })?;
+        let draft_number = last_number.unwrap_or(0) as u32 + 1;
+
+        let name = name
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| format!("Draft {}", draft_number));
+
+        let documents: Vec<(String, String, Option<String>, String, i64, String)> = sqlx::query_as(
+            "SELECT id, title, content, document_type, word_count, checksum FROM documents
+             WHERE project_id = ?1 AND is_active = 1",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+
+        let draft = Draft {
+            id: Uuid::new_v4(),
+            project_id,
+            name,
+            description,
+            draft_number,
+            document_count: documents.len(),
+            total_words: documents.iter().map(|d| d.4.max(0) as usize).sum(),
+            created_at: Utc::now(),
+        };
+
+        sqlx::query(INSERT_DRAFT_SQL)
+            .bind(draft.id.to_string())
+            .bind(draft.project_id.to_string())
+            .bind(&draft.name)
+            .bind(&draft.description)
+            .bind(draft.draft_number as i64)
+            .bind(draft.document_count as i64)
+            .bind(draft.total_words as i64)
+            .bind(draft.created_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to create draft: {}", e)))?;
+
+        for (document_id, title, content, document_type, word_count, checksum) in &documents {
+            sqlx::query(INSERT_DRAFT_DOCUMENT_SQL)
+                .bind(draft.id.to_string())
+                .bind(document_id)
+                .bind(title)
+                .bind(content.as_deref().unwrap_or(""))
+                .bind(document_type)
+                .bind(word_count)
+                .bind(checksum)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to snapshot document: {}", e))
+                })?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to commit draft: {}", e)))?;
+
+        Ok(draft)
+    }
+
+    /// List all drafts for a project, oldest first
+    pub async fn list_drafts(&self, project_id: Uuid) -> DatabaseResult<Vec<Draft>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<DraftRow> = sqlx::query_as(GET_DRAFTS_BY_PROJECT_SQL)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to list drafts: {}", e)))?;
+
+        rows.into_iter().map(Self::draft_from_row).collect()
+    }
+
+    /// Get a draft by ID
+    pub async fn get_draft(&self, draft_id: Uuid) -> DatabaseResult<Option<Draft>> {
+        let db = self.db_service.read().await;
+        let row: Option<DraftRow> = sqlx::query_as(GET_DRAFT_SQL)
+            .bind(draft_id.to_string())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get draft: {}", e)))?;
+
+        row.map(Self::draft_from_row).transpose()
+    }
+
+    /// Delete a draft and its snapshots
+    pub async fn delete_draft(&self, draft_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query(DELETE_DRAFT_SQL)
+            .bind(draft_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete draft: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get every document snapshot in a draft
+    pub async fn get_draft_documents(&self, draft_id: Uuid) -> DatabaseResult<Vec<DraftDocument>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<DraftDocumentRow> = sqlx::query_as(GET_DRAFT_DOCUMENTS_SQL)
+            .bind(draft_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get draft documents: {}", e)))?;
+
+        rows.into_iter().map(Self::document_from_row).collect()
+    }
+
+    /// Get a single document snapshot from a draft
+    pub async fn get_draft_document(
+        &self,
+        draft_id: Uuid,
+        document_id: Uuid,
+    ) -> DatabaseResult<Option<DraftDocument>> {
+        let db = self.db_service.read().await;
+        let row: Option<DraftDocumentRow> = sqlx::query_as(GET_DRAFT_DOCUMENT_SQL)
+            .bind(draft_id.to_string())
+            .bind(document_id.to_string())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get draft document: {}", e)))?;
+
+        row.map(Self::document_from_row).transpose()
+    }
+
+    /// Diff a single chapter between two drafts
+    ///
+    /// A document missing from one side is treated as empty, so chapters
+    /// added or cut between drafts show up as whole-file inserts/deletes.
+    pub async fn diff_document(
+        &self,
+        document_id: Uuid,
+        from_draft_id: Uuid,
+        to_draft_id: Uuid,
+    ) -> DatabaseResult<DraftDocumentDiff> {
+        let from = self.get_draft_document(from_draft_id, document_id).await?;
+        let to = self.get_draft_document(to_draft_id, document_id).await?;
+
+        if from.is_none() && to.is_none() {
+            return Err(DatabaseError::RecordNotFound {
+                entity: "draft document".to_string(),
+                id: document_id.to_string(),
+            });
+        }
+
+        let lines = diff_lines(
+            &from.as_ref().map(DraftDocument::text).unwrap_or_default(),
+            &to.as_ref().map(DraftDocument::text).unwrap_or_default(),
+        );
+        let summary = summarize(&lines);
+
+        Ok(DraftDocumentDiff {
+            document_id,
+            from_draft_id,
+            to_draft_id,
+            from_title: from.map(|d| d.title),
+            to_title: to.map(|d| d.title),
+            lines,
+            summary,
+        })
+    }
+
+    /// Compute statistics on how much changed between two drafts
+    pub async fn compare_drafts(
+        &self,
+        from_draft_id: Uuid,
+        to_draft_id: Uuid,
+    ) -> DatabaseResult<DraftComparison> {
+        let from_docs: HashMap<Uuid, DraftDocument> = self
+            .get_draft_documents(from_draft_id)
+            .await?
+            .into_iter()
+            .map(|d| (d.document_id, d))
+            .collect();
+        let to_docs = self.get_draft_documents(to_draft_id).await?;
+
+        let mut comparison = DraftComparison {
+            from_draft_id,
+            to_draft_id,
+            documents_added: 0,
+            documents_removed: 0,
+            documents_modified: 0,
+            documents_unchanged: 0,
+            word_delta: 0,
+            lines_added: 0,
+            lines_removed: 0,
+            change_ratio: 0.0,
+            documents: Vec::new(),
+        };
+        let mut lines_unchanged = 0usize;
+        let mut seen = std::collections::HashSet::new();
+
+        for to_doc in &to_docs {
+            seen.insert(to_doc.document_id);
+            let change = match from_docs.get(&to_doc.document_id) {
+                Some(from_doc) if from_doc.checksum == to_doc.checksum => {
+                    let unchanged = to_doc.text().lines().count();
+                    DraftDocumentChange {
+                        document_id: to_doc.document_id,
+                        title: to_doc.title.clone(),
+                        kind: DraftChangeKind::Unchanged,
+                        word_delta: 0,
+                        summary: DiffSummary {
+                            lines_unchanged: unchanged,
+                            ..Default::default()
+                        },
+                    }
+                }
+                Some(from_doc) => DraftDocumentChange {
+                    document_id: to_doc.document_id,
+                    title: to_doc.title.clone(),
+                    kind: DraftChangeKind::Modified,
+                    word_delta: to_doc.word_count as i64 - from_doc.word_count as i64,
+                    summary: summarize(&diff_lines(&from_doc.text(), &to_doc.text())),
+                },
+                None => DraftDocumentChange {
+                    document_id: to_doc.document_id,
+                    title: to_doc.title.clone(),
+                    kind: DraftChangeKind::Added,
+                    word_delta: to_doc.word_count as i64,
+                    summary: summarize(&diff_lines("", &to_doc.text())),
+                },
+            };
+            comparison.documents.push(change);
+        }
+
+        for (document_id, from_doc) in &from_docs {
+            if !seen.contains(document_id) {
+                comparison.documents.push(DraftDocumentChange {
+                    document_id: *document_id,
+                    title: from_doc.title.clone(),
+                    kind: DraftChangeKind::Removed,
+                    word_delta: -(from_doc.word_count as i64),
+                    summary: summarize(&diff_lines(&from_doc.text(), "")),
+                });
+            }
+        }
+
+        for change in &comparison.documents {
+            match change.kind {
+                DraftChangeKind::Added => comparison.documents_added += 1,
+                DraftChangeKind::Removed => comparison.documents_removed += 1,
+                DraftChangeKind::Modified => comparison.documents_modified += 1,
+                DraftChangeKind::Unchanged => comparison.documents_unchanged += 1,
+            }
+            comparison.word_delta += change.word_delta;
+            comparison.lines_added += change.summary.lines_added;
+            comparison.lines_removed += change.summary.lines_removed;
+            lines_unchanged += change.summary.lines_unchanged;
+        }
+
+        let total_lines = comparison.lines_added + comparison.lines_removed + lines_unchanged;
+        if total_lines > 0 {
+            comparison.change_ratio =
+                (comparison.lines_added + comparison.lines_removed) as f64 / total_lines as f64;
+        }
+
+        Ok(comparison)
+    }
+
+    /// Redline of every chapter between two drafts, in the later draft's
+    /// order with cut chapters at the end
+    pub async fn redline_drafts(
+        &self,
+        from_draft_id: Uuid,
+        to_draft_id: Uuid,
+    ) -> DatabaseResult<RedlineDocument> {
+        let from =
+            self.get_draft(from_draft_id)
+                .await?
+                .ok_or_else(|| DatabaseError::RecordNotFound {
+                    entity: "draft".to_string(),
+                    id: from_draft_id.to_string(),
+                })?;
+        let to =
+            self.get_draft(to_draft_id)
+                .await?
+                .ok_or_else(|| DatabaseError::RecordNotFound {
+                    entity: "draft".to_string(),
+                    id: to_draft_id.to_string(),
+                })?;
+
+        let mut from_docs: HashMap<Uuid, DraftDocument> = self
+            .get_draft_documents(from_draft_id)
+            .await?
+            .into_iter()
+            .map(|d| (d.document_id, d))
+            .collect();
+        let mut redline = RedlineDocument::new(
+            format!("Redline: {} to {}", from.name, to.name),
+            from.name,
+            to.name,
+        );
+        let paragraphs =
+            |doc: &DraftDocument| document_paragraphs(&doc.document_type, &doc.content);
+        for to_doc in self.get_draft_documents(to_draft_id).await? {
+            let old = from_docs
+                .remove(&to_doc.document_id)
+                .map(|d| paragraphs(&d))
+                .unwrap_or_default();
+            redline
+                .sections
+                .push(RedlineSection::new(&to_doc.title, old, paragraphs(&to_doc)));
+        }
+        let mut removed: Vec<DraftDocument> = from_docs.into_values().collect();
+        removed.sort_by(|a, b| a.title.cmp(&b.title));
+        for from_doc in removed {
+            redline.sections.push(RedlineSection::new(
+                &from_doc.title,
+                paragraphs(&from_doc),
+                "",
+            ));
+        }
+
+        Ok(redline)
+    }
+
+    /// Redline between two saved versions of one document
+    pub async fn redline_versions(
+        &self,
+        document_id: Uuid,
+        from_version: u32,
+        to_version: u32,
+    ) -> DatabaseResult<RedlineDocument> {
+        let db = self.db_service.read().await;
+        let document_type: Option<String> =
+            sqlx::query_scalar("SELECT document_type FROM documents WHERE id = ?1")
+                .bind(document_id.to_string())
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to get document: {}", e)))?;
+        let document_type = document_type.ok_or_else(|| DatabaseError::RecordNotFound {
+            entity: "document".to_string(),
+            id: document_id.to_string(),
+        })?;
+        let mut versions = Vec::with_capacity(2);
+        for version in [from_version, to_version] {
+            let row: Option<(String, String)> = sqlx::query_as(
+                "SELECT title, content FROM document_versions WHERE document_id = ?1 AND version = ?2",
+            )
+            .bind(document_id.to_string())
+            .bind(version as i64)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get document version: {}", e)))?;
+            versions.push(row.ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "document version".to_string(),
+                id: format!("{}@{}", document_id, version),
+            })?);
+        }
+        let (to_title, new) = versions.pop().unwrap_or_default();
+        let (_, old) = versions.pop().unwrap_or_default();
+
+        let mut redline = RedlineDocument::new(
+            format!("Redline: {}", to_title),
+            format!("version {}", from_version),
+            format!("version {}", to_version),
+        );
+        redline.sections.push(RedlineSection::new(
+            to_title,
+            document_paragraphs(&document_type, &old),
+            document_paragraphs(&document_type, &new),
+        ));
+        Ok(redline)
+    }
+
+    fn draft_from_row(row: DraftRow) -> DatabaseResult<Draft> {
+        let (
+            id,
+            project_id,
+            name,
+            description,
+            draft_number,
+            document_count,
+            total_words,
+            created_at,
+        ) = row;
+        Ok(Draft {
+            id: parse_uuid(&id)?,
+            project_id: parse_uuid(&project_id)?,
+            name,
+            description,
+            draft_number: draft_number as u32,
+            document_count: document_count as usize,
+            total_words: total_words as usize,
+            created_at: parse_time(&created_at)?,
+        })
+    }
+
+    fn document_from_row(row: DraftDocumentRow) -> DatabaseResult<DraftDocument> {
+        let (draft_id, document_id, title, content, document_type, word_count, checksum) = row;
+        Ok(DraftDocument {
+            draft_id: parse_uuid(&draft_id)?,
+            document_id: parse_uuid(&document_id)?,
+            title,
+            content,
+            document_type,
+            word_count: word_count as usize,
+            checksum,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::prosemirror;
+    use crate::database::text_diff::DiffOp;
+    use crate::database::DatabaseConfig;
+
+    async fn setup() -> (tempfile::TempDir, DraftService, sqlx::SqlitePool, Uuid) {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Novel', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let pool = db.pool.clone();
+        let service = DraftService::new(Arc::new(RwLock::new(db)));
+        service.initialize().await.unwrap();
+        (dir, service, pool, project)
+    }
+
+    async fn add_document(
+        service: &DraftService,
+        project: Uuid,
+        title: &str,
+        content: &str,
+    ) -> Uuid {
+        let id = Uuid::new_v4();
+        let db = service.db_service.read().await;
+        db.create_document(
+            id.to_string(),
+            project.to_string(),
+            title.to_string(),
+            content.to_string(),
+        )
+        .await
+        .unwrap();
+        id
+    }
+
+    #[tokio::test]
+    async fn test_create_draft_numbers_and_snapshots() {
+        let (_dir, service, _pool, project) = setup().await;
+        add_document(&service, project, "Arrival", "The ship came in.").await;
+        add_document(&service, project, "Departure", "It left at dawn.").await;
+
+        let first = service.create_draft(project, None, None).await.unwrap();
+        assert_eq!(first.draft_number, 1);
+        assert_eq!(first.name, "Draft 1");
+        assert_eq!(first.document_count, 2);
+        assert_eq!(first.total_words, 8);
+
+        let second = service
+            .create_draft(project, Some("  Beta read  ".to_string()), None)
+            .await
+            .unwrap();
+        assert_eq!(second.draft_number, 2);
+        assert_eq!(second.name, "Beta read");
+        assert_eq!(
+            service.get_draft_documents(second.id).await.unwrap().len(),
+            2
+        );
+
+        let drafts = service.list_drafts(project).await.unwrap();
+        let numbers: Vec<u32> = drafts.iter().map(|d| d.draft_number).collect();
+        assert_eq!(numbers, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_draft_numbers_are_unique_per_project() {
+        let (_dir, service, pool, project) = setup().await;
+        let draft = service.create_draft(project, None, None).await.unwrap();
+
+        let duplicate = sqlx::query(INSERT_DRAFT_SQL)
+            .bind(Uuid::new_v4().to_string())
+            .bind(project.to_string())
+            .bind("Other")
+            .bind(None::<String>)
+            .bind(draft.draft_number as i64)
+            .bind(0i64)
+            .bind(0i64)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&pool)
+            .await;
+        assert!(duplicate.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_compare_drafts_and_diff_document() {
+        let (_dir, service, pool, project) = setup().await;
+        let kept = add_document(&service, project, "Arrival", "The ship came in.").await;
+        // The editor saves ProseMirror JSON; drafts compare its text
+        let doc = |text: &str| {
+            serde_json::json!({ "type": "doc", "content": prosemirror::paragraphs(text) })
+                .to_string()
+        };
+        let edited = add_document(
+            &service,
+            project,
+            "Harbour",
+            &doc("Gulls over the water.\nRain."),
+        )
+        .await;
+        let cut = add_document(&service, project, "Storm", "Thunder all night.").await;
+        let before = service.create_draft(project, None, None).await.unwrap();
+
+        {
+            let db = service.db_service.read().await;
+            db.update_document(
+                edited.to_string(),
+                "Harbour".to_string(),
+                doc("Gulls over the water.\nSun at last."),
+            )
+            .await
+            .unwrap();
+        }
+        sqlx::query("UPDATE documents SET is_active = 0 WHERE id = ?1")
+            .bind(cut.to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        let added = add_document(&service, project, "Epilogue", "Home again.").await;
+        let after = service.create_draft(project, None, None).await.unwrap();
+
+        let comparison = service.compare_drafts(before.id, after.id).await.unwrap();
+        assert_eq!(comparison.documents_added, 1);
+        assert_eq!(comparison.documents_removed, 1);
+        assert_eq!(comparison.documents_modified, 1);
+        assert_eq!(comparison.documents_unchanged, 1);
+        let kind = |id: Uuid| {
+            comparison
+                .documents
+                .iter()
+                .find(|d| d.document_id == id)
+                .map(|d| d.kind)
+        };
+        assert_eq!(kind(kept), Some(DraftChangeKind::Unchanged));
+        assert_eq!(kind(edited), Some(DraftChangeKind::Modified));
+        assert_eq!(kind(cut), Some(DraftChangeKind::Removed));
+        assert_eq!(kind(added), Some(DraftChangeKind::Added));
+
+        let diff = service
+            .diff_document(edited, before.id, after.id)
+            .await
+            .unwrap();
+        assert_eq!(diff.summary.lines_added, 1);
+        assert_eq!(diff.summary.lines_removed, 1);
+        assert_eq!(diff.summary.lines_unchanged, 1);
+        assert!(diff
+            .lines
+            .iter()
+            .any(|l| l.op == DiffOp::Insert && l.text == "Sun at last."));
+
+        let new_chapter = service
+            .diff_document(added, before.id, after.id)
+            .await
+            .unwrap();
+        assert_eq!(new_chapter.from_title, None);
+        assert_eq!(new_chapter.to_title.as_deref(), Some("Epilogue"));
+
+        let missing = service
+            .diff_document(Uuid::new_v4(), before.id, after.id)
+            .await;
+        assert!(matches!(missing, Err(DatabaseError::RecordNotFound { .. })));
+    }
+}
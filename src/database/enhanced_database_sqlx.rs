@@ -2,12 +2,70 @@
 //!
 //! Replaces the rusqlite-based implementation with sqlx for proper async/await support.
 
+use crate::automation::{EventSystem, EventType, SystemEvent};
+use crate::database::autosnapshot::{
+    expired_snapshots, AutoSnapshotPolicy, AutoSnapshots, AUTOSNAPSHOT_DESCRIPTION,
+};
+use crate::database::local_embeddings::EmbeddingBackend;
+use crate::database::models::{
+    DocumentMerge, DocumentVersion, TrashItem, TrashItemKind, VersionDiff,
+};
+use crate::database::prosemirror;
+use crate::database::text_diff::{diff_documents, hunks, merge3, merge_documents, summarize};
 use crate::database::{DatabaseError, DatabaseResult};
+use crate::security::confirmation::{ConfirmationGuard, DestructiveOperation};
 use chrono::{DateTime, Utc};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteJournalMode, SqliteSynchronous};
 use sqlx::{Column, Row, SqlitePool};
 use std::collections::HashMap;
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Unchanged lines shown around each change in a version diff
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// `auto_document_version` from schema.sql, for upgrading older databases
+const DOCUMENT_VERSION_TRIGGER: &str = "CREATE TRIGGER auto_document_version
+    AFTER UPDATE ON documents
+    FOR EACH ROW
+    WHEN NEW.version != OLD.version AND (NEW.content != OLD.content OR NEW.title != OLD.title)
+BEGIN
+    INSERT INTO document_versions (id, document_id, version, title, content, created_at, change_description)
+    VALUES (
+        lower(hex(randomblob(16))),
+        NEW.id,
+        NEW.version,
+        NEW.title,
+        NEW.content,
+        CURRENT_TIMESTAMP,
+        'Automatic version created on update'
+    );
+END";
+
+type VersionRow = (
+    String,
+    String,
+    i64,
+    String,
+    String,
+    DateTime<Utc>,
+    Option<String>,
+);
+type TrashRow = (String, String, Option<String>, String, DateTime<Utc>);
+
+/// An active document as versioning and merging see it
+struct CurrentDocument {
+    title: String,
+    content: String,
+    document_type: String,
+    version: u32,
+}
 
 /// Database configuration for sqlx
 #[derive(Debug, Clone)]
@@ -17,6 +75,24 @@ pub struct DatabaseConfig {
     pub acquire_timeout: std::time::Duration,
     pub idle_timeout: Option<std::time::Duration>,
     pub max_lifetime: Option<std::time::Duration>,
+    /// WAL lets readers, such as an export, run alongside the autosave writer
+    pub journal_mode: SqliteJournalMode,
+    /// NORMAL is safe in WAL mode; only the last commits can be lost on power failure
+    pub synchronous: SqliteSynchronous,
+    /// How long SQLite waits on a lock before returning SQLITE_BUSY
+    pub busy_timeout: Duration,
+    /// WAL size in pages that triggers an automatic checkpoint
+    pub wal_autocheckpoint: u32,
+    /// Retries of a statement that still fails with SQLITE_BUSY
+    pub busy_retries: u32,
+    /// First retry delay; it doubles with each attempt, with jitter
+    pub busy_retry_delay: Duration,
+    /// Where document embeddings are computed
+    pub embedding_backend: EmbeddingBackend,
+    /// When autosaved content is kept as a version, and for how long
+    pub autosnapshot: AutoSnapshotPolicy,
+    /// How long deleted documents and projects stay in the trash
+    pub trash_retention: Duration,
 }
 
 impl Default for DatabaseConfig {
@@ -27,6 +103,15 @@ impl Default for DatabaseConfig {
             acquire_timeout: std::time::Duration::from_secs(30),
             idle_timeout: Some(std::time::Duration::from_secs(600)),
             max_lifetime: Some(std::time::Duration::from_secs(3600)),
+            journal_mode: SqliteJournalMode::Wal,
+            synchronous: SqliteSynchronous::Normal,
+            busy_timeout: Duration::from_secs(2),
+            wal_autocheckpoint: 1000,
+            busy_retries: 5,
+            busy_retry_delay: Duration::from_millis(50),
+            embedding_backend: EmbeddingBackend::Remote,
+            autosnapshot: AutoSnapshotPolicy::default(),
+            trash_retention: Duration::from_secs(30 * 24 * 60 * 60),
         }
     }
 }
@@ -36,6 +121,15 @@ impl Default for DatabaseConfig {
 pub struct EnhancedDatabaseService {
     pub pool: SqlitePool,
     db_path: PathBuf,
+    config: DatabaseConfig,
+    /// Shared by clones, so every handle on the database counts together
+    contention: Arc<ContentionCounters>,
+    autosnapshots: Arc<AutoSnapshots>,
+    /// Where changes such as documents moving between projects are
+    /// reported to automation
+    automation_events: Arc<RwLock<Option<EventSystem>>>,
+    /// Confirms purges from the trash, once set
+    confirmation_guard: Arc<RwLock<Option<Arc<ConfirmationGuard>>>>,
 }
 
 /// Database row data for sqlx
@@ -106,11 +200,11 @@ impl IntoIterator for QueryResult {
 
 impl EnhancedDatabaseService {
     /// Create a new enhanced database service with sqlx
-    pub async fn new(db_path: &Path, _config: DatabaseConfig) -> DatabaseResult<Self> {
+    pub async fn new(db_path: &Path, config: DatabaseConfig) -> DatabaseResult<Self> {
         let _db_path_str = db_path.to_string_lossy().to_string(); // Changed to to_string_lossy().to_string() to match type and remove unused error handling
-        // The original `db_path_str` was used for error handling if the path was not valid UTF-8.
-        // `to_string_lossy()` always succeeds, so `ok_or_else` is not applicable here.
-        // If `_db_path_str` is truly unused, this line can be simplified or removed.
+                                                                  // The original `db_path_str` was used for error handling if the path was not valid UTF-8.
+                                                                  // `to_string_lossy()` always succeeds, so `ok_or_else` is not applicable here.
+                                                                  // If `_db_path_str` is truly unused, this line can be simplified or removed.
 
         // Create the database directory if it doesn't exist
         if let Some(parent) = db_path.parent() {
@@ -122,16 +216,33 @@ impl EnhancedDatabaseService {
         // Create connection options
         let options = sqlx::sqlite::SqliteConnectOptions::new()
             .filename(db_path)
-            .create_if_missing(true);
+            .create_if_missing(true)
+            .journal_mode(config.journal_mode)
+            .synchronous(config.synchronous)
+            .busy_timeout(config.busy_timeout)
+            .pragma("wal_autocheckpoint", config.wal_autocheckpoint.to_string());
 
         // Create connection pool
-        let pool = SqlitePool::connect_with(options).await.map_err(|e| {
-            DatabaseError::Connection(format!("Failed to connect to database: {}", e))
-        })?;
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(config.max_connections)
+            .min_connections(config.min_connections)
+            .acquire_timeout(config.acquire_timeout)
+            .idle_timeout(config.idle_timeout)
+            .max_lifetime(config.max_lifetime)
+            .connect_with(options)
+            .await
+            .map_err(|e| {
+                DatabaseError::Connection(format!("Failed to connect to database: {}", e))
+            })?;
 
         let service = Self {
             pool,
             db_path: db_path.to_path_buf(),
+            config,
+            contention: Arc::default(),
+            autosnapshots: Arc::default(),
+            automation_events: Arc::default(),
+            confirmation_guard: Arc::default(),
         };
 
         // Initialize database
@@ -140,6 +251,113 @@ impl EnhancedDatabaseService {
         Ok(service)
     }
 
+    /// Configuration the service was opened with
+    pub fn config(&self) -> &DatabaseConfig {
+        &self.config
+    }
+
+    /// Report document moves and copies to `events`, so workflows can
+    /// trigger on them
+    pub fn set_automation_events(&self, events: EventSystem) {
+        *self
+            .automation_events
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = Some(events);
+    }
+
+    /// Require a `DestructiveOperation::PurgeTrash` grant, on every handle,
+    /// before anything is purged from the trash by hand
+    pub fn set_confirmation_guard(&self, guard: Arc<ConfirmationGuard>) {
+        *self
+            .confirmation_guard
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = Some(guard);
+    }
+
+    fn authorize_purge(&self, confirmation: Option<&str>) -> DatabaseResult<()> {
+        let guard = self
+            .confirmation_guard
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        match guard {
+            Some(guard) => guard
+                .authorize(DestructiveOperation::PurgeTrash, confirmation)
+                .map_err(|e| DatabaseError::ValidationError(e.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    async fn emit_event(&self, event_type: EventType, data: HashMap<String, serde_json::Value>) {
+        let events = self
+            .automation_events
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        if let Some(events) = events {
+            events
+                .push(SystemEvent {
+                    event_type,
+                    timestamp: Utc::now(),
+                    source: "database".to_string(),
+                    data,
+                })
+                .await;
+        }
+    }
+
+    /// Run a statement, retrying with growing, jittered delays while it
+    /// fails with SQLITE_BUSY. The busy timeout covers ordinary waits; this
+    /// catches what it can't, such as a read transaction that has to
+    /// upgrade to a write while another connection is committing.
+    pub async fn retry_busy<T, F, Fut>(&self, mut operation: F) -> Result<T, sqlx::Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, sqlx::Error>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match operation().await {
+                Err(e) if is_busy(&e) => {
+                    self.contention.busy_errors.fetch_add(1, Ordering::Relaxed);
+                    if attempt >= self.config.busy_retries {
+                        self.contention.exhausted.fetch_add(1, Ordering::Relaxed);
+                        log::warn!("Database still locked after {} retries: {}", attempt, e);
+                        return Err(e);
+                    }
+                    let delay = busy_backoff(
+                        self.config.busy_retry_delay,
+                        attempt,
+                        rand::thread_rng().gen(),
+                    );
+                    self.contention.retries.fetch_add(1, Ordering::Relaxed);
+                    self.contention
+                        .retry_wait_ms
+                        .fetch_add(delay.as_millis() as u64, Ordering::Relaxed);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => {
+                    if attempt > 0 && result.is_ok() {
+                        self.contention.recovered.fetch_add(1, Ordering::Relaxed);
+                    }
+                    return result;
+                }
+            }
+        }
+    }
+
+    /// Lock contention seen since the database was opened
+    pub fn contention_metrics(&self) -> ContentionMetrics {
+        ContentionMetrics {
+            busy_errors: self.contention.busy_errors.load(Ordering::Relaxed),
+            retries: self.contention.retries.load(Ordering::Relaxed),
+            recovered: self.contention.recovered.load(Ordering::Relaxed),
+            exhausted: self.contention.exhausted.load(Ordering::Relaxed),
+            retry_wait_ms: self.contention.retry_wait_ms.load(Ordering::Relaxed),
+        }
+    }
+
     /// Get database statistics
     pub async fn get_database_stats(&self) -> DatabaseResult<DatabaseStats> {
         let active_projects: i64 = sqlx::query_scalar(
@@ -184,6 +402,35 @@ impl EnhancedDatabaseService {
         })
     }
 
+    /// Add `documents.plain_text` and fill it in for editor documents
+    async fn add_plain_text_column(&self) -> DatabaseResult<()> {
+        let failed = |e: sqlx::Error| {
+            DatabaseError::Migration(format!("Failed to add document plain text: {}", e))
+        };
+        let mut tx = self.pool.begin().await.map_err(failed)?;
+        sqlx::query("ALTER TABLE documents ADD COLUMN plain_text TEXT")
+            .execute(&mut *tx)
+            .await
+            .map_err(failed)?;
+        let documents: Vec<(String, String, Option<String>)> = sqlx::query_as(
+            "SELECT id, document_type, content FROM documents WHERE document_type = ?1",
+        )
+        .bind(prosemirror::JSON_DOCUMENT_TYPE)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(failed)?;
+        for (id, document_type, content) in documents {
+            let plain_text = prosemirror::search_text(&document_type, &content.unwrap_or_default());
+            sqlx::query("UPDATE documents SET plain_text = ?1 WHERE id = ?2")
+                .bind(plain_text)
+                .bind(&id)
+                .execute(&mut *tx)
+                .await
+                .map_err(failed)?;
+        }
+        tx.commit().await.map_err(failed)
+    }
+
     /// Get database file size
     fn get_database_file_size(&self) -> usize {
         std::fs::metadata(&self.db_path)
@@ -199,24 +446,28 @@ impl EnhancedDatabaseService {
         title: String,
         content: String,
     ) -> DatabaseResult<String> {
-        let checksum = self.calculate_checksum(&content);
+        let checksum = Self::calculate_checksum(&content);
+        let plain_text = prosemirror::search_text(prosemirror::JSON_DOCUMENT_TYPE, &content);
         let word_count = content.split_whitespace().count() as i32;
         let created_at = Utc::now();
         let updated_at = Utc::now();
 
-        sqlx::query(
-            "INSERT INTO documents (id, project_id, title, content, document_type, word_count, checksum, created_at, updated_at, is_active, version, metadata)
-             VALUES (?, ?, ?, ?, 'json', ?, ?, ?, ?, 1, 1, NULL)"
-        )
-        .bind(&document_id)
-        .bind(&project_id)
-        .bind(&title)
-        .bind(&content)
-        .bind(word_count)
-        .bind(&checksum)
-        .bind(created_at)
-        .bind(updated_at)
-        .execute(&self.pool)
+        self.retry_busy(|| {
+            sqlx::query(
+                "INSERT INTO documents (id, project_id, title, content, plain_text, document_type, word_count, checksum, created_at, updated_at, is_active, version, metadata)
+                 VALUES (?, ?, ?, ?, ?, 'json', ?, ?, ?, ?, 1, 1, NULL)"
+            )
+            .bind(&document_id)
+            .bind(&project_id)
+            .bind(&title)
+            .bind(&content)
+            .bind(&plain_text)
+            .bind(word_count)
+            .bind(&checksum)
+            .bind(created_at)
+            .bind(updated_at)
+            .execute(&self.pool)
+        })
         .await
         .map_err(|e| DatabaseError::Service(format!("Failed to create document: {}", e)))?;
 
@@ -242,52 +493,736 @@ impl EnhancedDatabaseService {
         title: String,
         content: String,
     ) -> DatabaseResult<()> {
-        let checksum = self.calculate_checksum(&content);
+        let checksum = Self::calculate_checksum(&content);
+        let plain_text = prosemirror::search_text(prosemirror::JSON_DOCUMENT_TYPE, &content);
         let word_count = content.split_whitespace().count() as i32;
         let updated_at = Utc::now();
-        let version = 2i32;
 
+        self.retry_busy(|| {
+            sqlx::query(
+                "UPDATE documents SET title = ?, content = ?, plain_text = ?, document_type = 'json', word_count = ?, checksum = ?, updated_at = ?, version = version + 1 WHERE id = ?"
+            )
+            .bind(&title)
+            .bind(&content)
+            .bind(&plain_text)
+            .bind(word_count)
+            .bind(&checksum)
+            .bind(updated_at)
+            .bind(&id)
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to update document: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Replace a document's content, keeping its title (used by autosave).
+    /// This doesn't make a version; the autosnapshot cadence decides when
+    /// autosaved content is kept as one.
+    pub async fn update_document_content(&self, id: &str, content: &str) -> DatabaseResult<()> {
+        let checksum = Self::calculate_checksum(content);
+        let plain_text = prosemirror::search_text(prosemirror::JSON_DOCUMENT_TYPE, content);
+        let updated_at = Utc::now();
+        self.retry_busy(|| {
+            sqlx::query(
+                "UPDATE documents SET content = ?, plain_text = CASE WHEN document_type = 'json' THEN ? END, word_count = ?, checksum = ?, updated_at = ? WHERE id = ?"
+            )
+            .bind(content)
+            .bind(&plain_text)
+            .bind(content.split_whitespace().count() as i32)
+            .bind(&checksum)
+            .bind(updated_at)
+            .bind(id)
+            .execute(&self.pool)
+        })
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to autosave document: {}", e)))?;
+
+        if self
+            .autosnapshots
+            .record_change(id, self.config.autosnapshot.cadence)
+        {
+            // The content is saved either way; a missed snapshot only
+            // leaves a longer gap in the history
+            if let Err(e) = self.snapshot_document(id).await {
+                tracing::warn!("Failed to snapshot document {}: {}", id, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep a document's current content as an automatic snapshot, unless
+    /// it matches the latest version, then prune its older snapshots.
+    /// Returns the new version.
+    pub async fn snapshot_document(&self, id: &str) -> DatabaseResult<Option<u32>> {
+        let failed =
+            |e: sqlx::Error| DatabaseError::Service(format!("Failed to snapshot document: {}", e));
+
+        let mut tx = self.pool.begin().await.map_err(failed)?;
+        let current: Option<(String, String, i64)> = sqlx::query_as(
+            "SELECT title, content, version FROM documents WHERE id = ? AND is_active = 1",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(failed)?;
+        let (title, content, version) = current.ok_or_else(|| DatabaseError::RecordNotFound {
+            entity: "document".to_string(),
+            id: id.to_string(),
+        })?;
+        let latest: Option<(String, String, i64)> = sqlx::query_as(
+            "SELECT title, content, version FROM document_versions WHERE document_id = ? ORDER BY version DESC LIMIT 1",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(failed)?;
+        if matches!(&latest, Some((t, c, _)) if *t == title && *c == content) {
+            return Ok(None);
+        }
+
+        let snapshot = version.max(latest.map_or(0, |(_, _, v)| v)) + 1;
         sqlx::query(
-            "UPDATE documents SET title = ?, content = ?, document_type = 'json', word_count = ?, checksum = ?, updated_at = ?, version = ? WHERE id = ?"
+            "INSERT INTO document_versions (id, document_id, version, title, content, created_at, change_description)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
         )
+        .bind(Uuid::new_v4().to_string())
+        .bind(id)
+        .bind(snapshot)
         .bind(&title)
         .bind(&content)
-        .bind(word_count)
-        .bind(&checksum)
-        .bind(updated_at)
-        .bind(version)
-        .bind(&id)
-        .execute(&self.pool)
+        .bind(Utc::now())
+        .bind(AUTOSNAPSHOT_DESCRIPTION)
+        .execute(&mut *tx)
         .await
-        .map_err(|e| DatabaseError::Service(format!("Failed to update document: {}", e)))?;
+        .map_err(failed)?;
+        // Only the version changes, so the update trigger doesn't fire
+        sqlx::query("UPDATE documents SET version = ? WHERE id = ?")
+            .bind(snapshot)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(failed)?;
+        tx.commit().await.map_err(failed)?;
 
-        Ok(())
+        self.prune_snapshots(id).await?;
+        Ok(Some(snapshot as u32))
+    }
+
+    /// Remove a document's automatic snapshots that the retention policy
+    /// no longer keeps; never its current version. Returns how many went.
+    pub async fn prune_snapshots(&self, id: &str) -> DatabaseResult<usize> {
+        let failed =
+            |e: sqlx::Error| DatabaseError::Service(format!("Failed to prune snapshots: {}", e));
+
+        let snapshots: Vec<(i64, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT v.version, v.created_at FROM document_versions v JOIN documents d ON d.id = v.document_id
+             WHERE v.document_id = ? AND v.change_description = ? AND v.version != d.version",
+        )
+        .bind(id)
+        .bind(AUTOSNAPSHOT_DESCRIPTION)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(failed)?;
+        let snapshots: Vec<(u32, DateTime<Utc>)> = snapshots
+            .into_iter()
+            .map(|(version, created)| (version as u32, created))
+            .collect();
+
+        let expired =
+            expired_snapshots(&snapshots, &self.config.autosnapshot.retention, Utc::now());
+        for version in &expired {
+            sqlx::query("DELETE FROM document_versions WHERE document_id = ? AND version = ? AND change_description = ?")
+                .bind(id)
+                .bind(*version as i64)
+                .bind(AUTOSNAPSHOT_DESCRIPTION)
+                .execute(&self.pool)
+                .await
+                .map_err(failed)?;
+        }
+        Ok(expired.len())
     }
 
-    /// Delete document with soft delete
+    /// Move a document to the trash. It drops out of lists and search
+    /// until restored, and is purged once `trash_retention` has passed.
     pub async fn delete_document(&self, id: String) -> DatabaseResult<()> {
-        let updated_at = Utc::now();
+        let deleted_at = Utc::now();
 
-        sqlx::query("UPDATE documents SET is_active = 0, updated_at = ? WHERE id = ?")
-            .bind(updated_at)
-            .bind(&id)
-            .execute(&self.pool)
+        self.retry_busy(|| {
+            sqlx::query("UPDATE documents SET is_active = 0, deleted_at = ?, updated_at = ? WHERE id = ? AND is_active = 1")
+                .bind(deleted_at)
+                .bind(deleted_at)
+                .bind(&id)
+                .execute(&self.pool)
+        })
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to delete document: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Move a project, and with it all its documents, to the trash
+    pub async fn trash_project(&self, id: &str) -> DatabaseResult<()> {
+        let deleted_at = Utc::now();
+        let result = self.retry_busy(|| {
+            sqlx::query("UPDATE projects SET deleted_at = ?, is_active = 0 WHERE id = ? AND deleted_at IS NULL")
+                .bind(deleted_at)
+                .bind(id)
+                .execute(&self.pool)
+        })
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to delete project: {}", e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::RecordNotFound {
+                entity: "project".to_string(),
+                id: id.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Documents and projects in the trash, of one project or all of
+    /// them, most recently deleted first
+    pub async fn list_trash(&self, project_id: Option<&str>) -> DatabaseResult<Vec<TrashItem>> {
+        let rows: Vec<TrashRow> = sqlx::query_as(
+            "SELECT 'document', id, project_id, title, deleted_at FROM documents
+             WHERE is_active = 0 AND deleted_at IS NOT NULL AND (?1 IS NULL OR project_id = ?1)
+             UNION ALL
+             SELECT 'project', id, NULL, name, deleted_at FROM projects
+             WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR id = ?1)
+             ORDER BY 5 DESC",
+        )
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to list trash: {}", e)))?;
+
+        let retention = chrono::Duration::from_std(self.config.trash_retention)
+            .unwrap_or(chrono::Duration::MAX);
+        Ok(rows
+            .into_iter()
+            .map(|(kind, id, project_id, title, deleted_at)| TrashItem {
+                kind: if kind == "project" {
+                    TrashItemKind::Project
+                } else {
+                    TrashItemKind::Document
+                },
+                id,
+                project_id,
+                title,
+                deleted_at,
+                purge_at: deleted_at
+                    .checked_add_signed(retention)
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC),
+            })
+            .collect())
+    }
+
+    /// Take a document or project back out of the trash. A document in a
+    /// trashed project can't be restored until the project is.
+    pub async fn restore_from_trash(&self, kind: TrashItemKind, id: &str) -> DatabaseResult<()> {
+        let failed =
+            |e: sqlx::Error| DatabaseError::Service(format!("Failed to restore from trash: {}", e));
+        let not_found = || DatabaseError::RecordNotFound {
+            entity: "trash item".to_string(),
+            id: id.to_string(),
+        };
+
+        let sql = match kind {
+            TrashItemKind::Document => {
+                let project_trashed: Option<bool> = sqlx::query_scalar(
+                    "SELECT p.deleted_at IS NOT NULL FROM documents d JOIN projects p ON p.id = d.project_id
+                     WHERE d.id = ?1 AND d.is_active = 0",
+                )
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(failed)?;
+                match project_trashed {
+                    None => return Err(not_found()),
+                    Some(true) => {
+                        return Err(DatabaseError::ValidationError(
+                            "The document's project is in the trash; restore the project first"
+                                .to_string(),
+                        ))
+                    }
+                    Some(false) => {}
+                }
+                "UPDATE documents SET is_active = 1, deleted_at = NULL WHERE id = ?1 AND is_active = 0"
+            }
+            TrashItemKind::Project => {
+                "UPDATE projects SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL"
+            }
+        };
+        let result = self
+            .retry_busy(|| sqlx::query(sql).bind(id).execute(&self.pool))
+            .await
+            .map_err(failed)?;
+        if result.rows_affected() == 0 {
+            return Err(not_found());
+        }
+        Ok(())
+    }
+
+    /// Delete a trashed document or project for good. `confirmation` is the
+    /// token of a `DestructiveOperation::PurgeTrash` grant when a guard is set.
+    pub async fn purge_from_trash(
+        &self,
+        kind: TrashItemKind,
+        id: &str,
+        confirmation: Option<&str>,
+    ) -> DatabaseResult<()> {
+        self.authorize_purge(confirmation)?;
+        let sql = match kind {
+            TrashItemKind::Document => "DELETE FROM documents WHERE id = ?1 AND is_active = 0",
+            TrashItemKind::Project => {
+                "DELETE FROM projects WHERE id = ?1 AND deleted_at IS NOT NULL"
+            }
+        };
+        let result = self
+            .retry_busy(|| sqlx::query(sql).bind(id).execute(&self.pool))
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to purge from trash: {}", e)))?;
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::RecordNotFound {
+                entity: "trash item".to_string(),
+                id: id.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Purge everything in the trash, or what's in it of one project;
+    /// returns how many items went. Confirmed like `purge_from_trash`.
+    pub async fn empty_trash(
+        &self,
+        project_id: Option<&str>,
+        confirmation: Option<&str>,
+    ) -> DatabaseResult<u64> {
+        self.authorize_purge(confirmation)?;
+        self.purge_trash(project_id, None).await
+    }
+
+    /// Purge what has been in the trash longer than `trash_retention`
+    pub async fn purge_expired_trash(&self) -> DatabaseResult<u64> {
+        let retention = chrono::Duration::from_std(self.config.trash_retention)
+            .unwrap_or(chrono::Duration::MAX);
+        match Utc::now().checked_sub_signed(retention) {
+            Some(cutoff) => self.purge_trash(None, Some(cutoff)).await,
+            None => Ok(0),
+        }
+    }
+
+    async fn purge_trash(
+        &self,
+        project_id: Option<&str>,
+        before: Option<DateTime<Utc>>,
+    ) -> DatabaseResult<u64> {
+        let failed =
+            |e: sqlx::Error| DatabaseError::Service(format!("Failed to purge trash: {}", e));
+
+        let mut tx = self.pool.begin().await.map_err(failed)?;
+        let mut removed = 0;
+        for sql in [
+            "DELETE FROM documents
+             WHERE is_active = 0 AND deleted_at IS NOT NULL AND (?1 IS NULL OR project_id = ?1)
+             AND (?2 IS NULL OR julianday(deleted_at) < julianday(?2))",
+            "DELETE FROM projects
+             WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR id = ?1)
+             AND (?2 IS NULL OR julianday(deleted_at) < julianday(?2))",
+        ] {
+            let result = sqlx::query(sql)
+                .bind(project_id)
+                .bind(before)
+                .execute(&mut *tx)
+                .await
+                .map_err(failed)?;
+            removed += result.rows_affected();
+        }
+        tx.commit().await.map_err(failed)?;
+        Ok(removed)
+    }
+
+    /// Move a document to another project. Its versions and embeddings go
+    /// with it, rows of other features that record its project (such as
+    /// annotations and word count history) are repointed, and it leaves
+    /// the old binder to come last in the new one. Codex mentions are
+    /// found by name, so they resolve against the new project's codex.
+    pub async fn move_document_to_project(
+        &self,
+        document_id: &str,
+        target_project_id: &str,
+    ) -> DatabaseResult<()> {
+        let failed =
+            |e: sqlx::Error| DatabaseError::Service(format!("Failed to move document: {}", e));
+
+        let mut tx = self.pool.begin().await.map_err(failed)?;
+        let source_project_id = document_project(&mut tx, document_id).await?;
+        ensure_live_project(&mut tx, target_project_id).await?;
+        if source_project_id == target_project_id {
+            return Err(DatabaseError::ValidationError(
+                "The document is already in that project".to_string(),
+            ));
+        }
+
+        sqlx::query("UPDATE documents SET project_id = ?1, updated_at = ?2 WHERE id = ?3")
+            .bind(target_project_id)
+            .bind(Utc::now())
+            .bind(document_id)
+            .execute(&mut *tx)
             .await
-            .map_err(|e| DatabaseError::Service(format!("Failed to delete document: {}", e)))?;
+            .map_err(failed)?;
 
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT m.name FROM sqlite_master m
+             WHERE m.type = 'table' AND m.name <> 'documents'
+             AND EXISTS (SELECT 1 FROM pragma_table_info(m.name) WHERE name = 'document_id')
+             AND EXISTS (SELECT 1 FROM pragma_table_info(m.name) WHERE name = 'project_id')",
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(failed)?;
+        for table in tables {
+            // A binder position only means something in its own project
+            let sql = if table == "binder_order" {
+                "DELETE FROM binder_order WHERE document_id = ?2".to_string()
+            } else {
+                format!(
+                    "UPDATE \"{}\" SET project_id = ?1 WHERE document_id = ?2",
+                    table
+                )
+            };
+            sqlx::query(&sql)
+                .bind(target_project_id)
+                .bind(document_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(failed)?;
+        }
+        tx.commit().await.map_err(failed)?;
+
+        self.emit_event(
+            EventType::DocumentMoved,
+            HashMap::from([
+                ("document_id".to_string(), document_id.into()),
+                ("from_project_id".to_string(), source_project_id.into()),
+                ("to_project_id".to_string(), target_project_id.into()),
+            ]),
+        )
+        .await;
         Ok(())
     }
 
+    /// Copy a document, with its versions and embeddings, into a project,
+    /// which may be its own; returns the copy's id. Notes and history of
+    /// other features stay with the original.
+    pub async fn copy_document_to_project(
+        &self,
+        document_id: &str,
+        target_project_id: &str,
+    ) -> DatabaseResult<String> {
+        let failed =
+            |e: sqlx::Error| DatabaseError::Service(format!("Failed to copy document: {}", e));
+
+        let mut tx = self.pool.begin().await.map_err(failed)?;
+        let source_project_id = document_project(&mut tx, document_id).await?;
+        ensure_live_project(&mut tx, target_project_id).await?;
+
+        let copy_id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        sqlx::query(
+            "INSERT INTO documents (id, project_id, title, content, plain_text, document_type, word_count, checksum, created_at, updated_at, is_active, version, metadata)
+             SELECT ?1, ?2, title, content, plain_text, document_type, word_count, checksum, ?3, ?3, 1, version, metadata
+             FROM documents WHERE id = ?4",
+        )
+        .bind(&copy_id)
+        .bind(target_project_id)
+        .bind(now)
+        .bind(document_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(failed)?;
+
+        // Replace the initial version the insert trigger made with the
+        // original's history
+        sqlx::query("DELETE FROM document_versions WHERE document_id = ?1")
+            .bind(&copy_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(failed)?;
+        for table in ["document_versions", "document_embeddings"] {
+            let columns: Vec<String> = sqlx::query_scalar(
+                "SELECT name FROM pragma_table_info(?1) WHERE name NOT IN ('id', 'document_id') ORDER BY cid",
+            )
+            .bind(table)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(failed)?;
+            let columns = columns.join(", ");
+            sqlx::query(&format!(
+                "INSERT INTO {table} (id, document_id, {columns})
+                 SELECT lower(hex(randomblob(16))), ?1, {columns} FROM {table} WHERE document_id = ?2"
+            ))
+            .bind(&copy_id)
+            .bind(document_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(failed)?;
+        }
+        tx.commit().await.map_err(failed)?;
+
+        self.emit_event(
+            EventType::DocumentCopied,
+            HashMap::from([
+                ("document_id".to_string(), document_id.into()),
+                ("copy_id".to_string(), copy_id.clone().into()),
+                ("from_project_id".to_string(), source_project_id.into()),
+                ("to_project_id".to_string(), target_project_id.into()),
+            ]),
+        )
+        .await;
+        Ok(copy_id)
+    }
+
+    /// Saved versions of a document, oldest first
+    pub async fn list_document_versions(
+        &self,
+        document_id: Uuid,
+    ) -> DatabaseResult<Vec<DocumentVersion>> {
+        let rows: Vec<VersionRow> = sqlx::query_as(
+            "SELECT id, document_id, version, title, content, created_at, change_description
+             FROM document_versions WHERE document_id = ? ORDER BY version",
+        )
+        .bind(document_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to list document versions: {}", e)))?;
+
+        rows.into_iter().map(version_from_row).collect()
+    }
+
+    /// One saved version of a document
+    pub async fn get_document_version(
+        &self,
+        document_id: Uuid,
+        version: u32,
+    ) -> DatabaseResult<DocumentVersion> {
+        let row: Option<VersionRow> = sqlx::query_as(
+            "SELECT id, document_id, version, title, content, created_at, change_description
+             FROM document_versions WHERE document_id = ? AND version = ?",
+        )
+        .bind(document_id.to_string())
+        .bind(version as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to get document version: {}", e)))?;
+
+        let row = row.ok_or_else(|| DatabaseError::RecordNotFound {
+            entity: "document version".to_string(),
+            id: format!("{}@{}", document_id, version),
+        })?;
+        version_from_row(row)
+    }
+
+    /// Changes from one saved version of a document to another
+    pub async fn diff_document_versions(
+        &self,
+        document_id: Uuid,
+        from_version: u32,
+        to_version: u32,
+    ) -> DatabaseResult<VersionDiff> {
+        let from = self.get_document_version(document_id, from_version).await?;
+        let to = self.get_document_version(document_id, to_version).await?;
+        let document = self.current_document(document_id).await?;
+        let lines = diff_documents(&document.document_type, &from.content, &to.content);
+
+        Ok(VersionDiff {
+            document_id,
+            from_version,
+            to_version,
+            title_change: (from.title != to.title).then_some((from.title, to.title)),
+            hunks: hunks(&lines, DIFF_CONTEXT_LINES),
+            summary: summarize(&lines),
+        })
+    }
+
+    /// Make a saved version the document's content again. The revert is
+    /// saved as a new version, so it can itself be reverted; returns that
+    /// version.
+    pub async fn revert_document_to_version(
+        &self,
+        document_id: Uuid,
+        version: u32,
+    ) -> DatabaseResult<u32> {
+        let target = self.get_document_version(document_id, version).await?;
+        let current = self.current_document(document_id).await?;
+        if current.title == target.title && current.content == target.content {
+            return Ok(current.version);
+        }
+
+        self.save_document_version(
+            document_id,
+            current.version,
+            &target.title,
+            &target.content,
+            &format!("Reverted to version {}", version),
+        )
+        .await
+    }
+
+    /// Merge `content`, an edit of `base_version`, into the document as it
+    /// is now. ProseMirror documents merge block by block, anything else
+    /// line by line. A clean merge is saved as a new version; with
+    /// conflicts nothing is saved and the merge says what needs resolving.
+    pub async fn merge_document_edit(
+        &self,
+        document_id: Uuid,
+        base_version: u32,
+        content: &str,
+    ) -> DatabaseResult<DocumentMerge> {
+        let base = self.get_document_version(document_id, base_version).await?;
+        let current = self.current_document(document_id).await?;
+        let parse = |content: &str| prosemirror::parse(&current.document_type, content);
+        let (merge, merged) = match (
+            parse(&base.content),
+            parse(&current.content),
+            parse(content),
+        ) {
+            (Some(base), Some(ours), Some(theirs)) => {
+                let (merge, merged) = merge_documents(&base, &ours, &theirs);
+                let unchanged = merged.as_ref() == Some(&ours);
+                (merge, merged.map(|doc| (unchanged, doc.to_string())))
+            }
+            _ => {
+                let merge = merge3(&base.content, &current.content, content);
+                let merged = merge.text().map(|text| (text == current.content, text));
+                (merge, merged)
+            }
+        };
+
+        let saved_version = match merged {
+            Some((true, _)) => Some(current.version),
+            Some((false, merged)) => Some(
+                self.save_document_version(
+                    document_id,
+                    current.version,
+                    &current.title,
+                    &merged,
+                    &format!("Merged an edit of version {}", base_version),
+                )
+                .await?,
+            ),
+            None => None,
+        };
+
+        Ok(DocumentMerge {
+            document_id,
+            base_version,
+            current_version: current.version,
+            merge,
+            saved_version,
+        })
+    }
+
+    /// Title, content, type and version number of an active document
+    async fn current_document(&self, document_id: Uuid) -> DatabaseResult<CurrentDocument> {
+        let row: Option<(String, String, String, i64)> = sqlx::query_as(
+            "SELECT title, content, document_type, version FROM documents WHERE id = ? AND is_active = 1",
+        )
+        .bind(document_id.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to get document: {}", e)))?;
+
+        let (title, content, document_type, version) =
+            row.ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "document".to_string(),
+                id: document_id.to_string(),
+            })?;
+        Ok(CurrentDocument {
+            title,
+            content,
+            document_type,
+            version: version as u32,
+        })
+    }
+
+    /// Save over `expected_version` of a document and describe the version
+    /// the update trigger records. Fails if another save got there first.
+    async fn save_document_version(
+        &self,
+        document_id: Uuid,
+        expected_version: u32,
+        title: &str,
+        content: &str,
+        description: &str,
+    ) -> DatabaseResult<u32> {
+        let id = document_id.to_string();
+        let version = expected_version + 1;
+        let failed = |e: sqlx::Error| {
+            DatabaseError::Service(format!("Failed to save document version: {}", e))
+        };
+
+        let mut tx = self.pool.begin().await.map_err(failed)?;
+        let updated = sqlx::query(
+            "UPDATE documents SET title = ?, content = ?, checksum = ?, updated_at = ?, version = ?
+             WHERE id = ? AND version = ?",
+        )
+        .bind(title)
+        .bind(content)
+        .bind(Self::calculate_checksum(content))
+        .bind(Utc::now())
+        .bind(version as i64)
+        .bind(&id)
+        .bind(expected_version as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(failed)?;
+        if updated.rows_affected() == 0 {
+            return Err(DatabaseError::ConstraintViolation {
+                constraint: "document version".to_string(),
+                value: format!("{} changed after version {}", document_id, expected_version),
+            });
+        }
+        let document_type: String =
+            sqlx::query_scalar("SELECT document_type FROM documents WHERE id = ?")
+                .bind(&id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(failed)?;
+        prosemirror::store_document_text(&mut tx, &id, &document_type, content).await?;
+
+        sqlx::query("UPDATE document_versions SET change_description = ? WHERE document_id = ? AND version = ?")
+            .bind(description)
+            .bind(&id)
+            .bind(version as i64)
+            .execute(&mut *tx)
+            .await
+            .map_err(failed)?;
+        tx.commit().await.map_err(failed)?;
+
+        Ok(version)
+    }
+
     /// Execute SQL query and return results
+    #[tracing::instrument(
+        name = "db.query",
+        skip_all,
+        fields(correlation_id = crate::correlation::current(), sql = sql_summary(sql))
+    )]
     pub async fn query(&self, sql: &str, params: &[String]) -> DatabaseResult<QueryResult> {
-        let mut query_builder = sqlx::query(sql);
+        let rows = self
+            .retry_busy(|| {
+                let mut query_builder = sqlx::query(sql);
 
-        for param in params {
-            query_builder = query_builder.bind(param);
-        }
+                for param in params {
+                    query_builder = query_builder.bind(param);
+                }
 
-        let rows = query_builder
-            .fetch_all(&self.pool)
+                query_builder.fetch_all(&self.pool)
+            })
             .await
             .map_err(|e| DatabaseError::Service(format!("Query execution failed: {}", e)))?;
 
@@ -304,7 +1239,9 @@ impl EnhancedDatabaseService {
                 let mut values = Vec::new();
                 for i in 0..columns.len() {
                     // Try to get as String first
-                    let value: Option<String> = row.try_get(i).ok()
+                    let value: Option<String> = row
+                        .try_get(i)
+                        .ok()
                         .or_else(|| {
                             // Try as i64
                             row.try_get::<i64, _>(i).ok().map(|v| v.to_string())
@@ -317,7 +1254,7 @@ impl EnhancedDatabaseService {
                             // Try as bool
                             row.try_get::<bool, _>(i).ok().map(|v| v.to_string())
                         });
-                    
+
                     values.push(value);
                 }
                 result_rows.push(DatabaseRow::new(columns.clone(), values));
@@ -331,23 +1268,30 @@ impl EnhancedDatabaseService {
     }
 
     /// Execute SQL statement
+    #[tracing::instrument(
+        name = "db.execute",
+        skip_all,
+        fields(correlation_id = crate::correlation::current(), sql = sql_summary(sql))
+    )]
     pub async fn execute(&self, sql: &str, params: &[String]) -> DatabaseResult<()> {
-        let mut query_builder = sqlx::query(sql);
+        self.retry_busy(|| {
+            let mut query_builder = sqlx::query(sql);
 
-        for param in params {
-            query_builder = query_builder.bind(param);
-        }
+            for param in params {
+                query_builder = query_builder.bind(param);
+            }
 
-        query_builder
-            .execute(&self.pool)
-            .await
-            .map_err(|e| DatabaseError::Service(format!("Statement execution failed: {}", e)))?;
+            query_builder.execute(&self.pool)
+        })
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Statement execution failed: {}", e)))?;
 
         Ok(())
     }
 
-    /// Calculate SHA-256 checksum for document content
-    fn calculate_checksum(&self, content: &str) -> String {
+    /// Calculate SHA-256 checksum for document content; services that write
+    /// `documents.content` themselves store the same checksum
+    pub fn calculate_checksum(content: &str) -> String {
         use sha2::{Digest, Sha256};
         let mut hasher = Sha256::new();
         hasher.update(content.as_bytes());
@@ -375,6 +1319,85 @@ impl EnhancedDatabaseService {
                 })?;
         }
 
+        // Databases made before autosnapshots version every autosave
+        let trigger: Option<String> = sqlx::query_scalar(
+            "SELECT sql FROM sqlite_master WHERE type = 'trigger' AND name = 'auto_document_version'",
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Migration(format!("Failed to read version trigger: {}", e)))?;
+        if trigger.is_some_and(|sql| !sql.contains("OLD.version")) {
+            let mut tx = self
+                .pool
+                .begin()
+                .await
+                .map_err(|e| DatabaseError::Migration(e.to_string()))?;
+            for statement in [
+                "DROP TRIGGER auto_document_version",
+                DOCUMENT_VERSION_TRIGGER,
+            ] {
+                sqlx::query(statement)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Migration(format!(
+                            "Failed to replace version trigger: {}",
+                            e
+                        ))
+                    })?;
+            }
+            tx.commit()
+                .await
+                .map_err(|e| DatabaseError::Migration(e.to_string()))?;
+        }
+
+        // Databases made before search indexed the text of editor documents
+        let columns: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info('documents')")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to read documents columns: {}", e))
+                })?;
+        if !columns.is_empty() && !columns.iter().any(|c| c == "plain_text") {
+            self.add_plain_text_column().await?;
+        }
+
+        // Databases made before the trash; documents deleted back then go
+        // to it as of their last update
+        for table in ["projects", "documents"] {
+            let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?1)")
+                .bind(table)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to read {} columns: {}", table, e))
+                })?;
+            if columns.is_empty() || columns.iter().any(|c| c == "deleted_at") {
+                continue;
+            }
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN deleted_at DATETIME",
+                table
+            ))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to add trash to {}: {}", table, e))
+            })?;
+            if table == "documents" {
+                sqlx::query("UPDATE documents SET deleted_at = updated_at WHERE is_active = 0")
+                    .execute(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Migration(format!(
+                            "Failed to move deleted documents to the trash: {}",
+                            e
+                        ))
+                    })?;
+            }
+        }
+
         // Ensure default project exists
         let project_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM projects")
             .fetch_one(&self.pool)
@@ -435,6 +1458,7 @@ impl EnhancedDatabaseService {
             query_performance: HashMap::new(),
             last_vacuum: None,
             last_analyze: None,
+            contention: self.contention_metrics(),
         })
     }
 
@@ -469,6 +1493,88 @@ pub struct DatabasePerformanceMetrics {
     pub query_performance: HashMap<String, u64>,
     pub last_vacuum: Option<DateTime<Utc>>,
     pub last_analyze: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub contention: ContentionMetrics,
+}
+
+/// How often statements ran into another connection's lock
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct ContentionMetrics {
+    /// Statements that failed with SQLITE_BUSY, counting each attempt
+    pub busy_errors: u64,
+    pub retries: u64,
+    /// Statements that succeeded after retrying
+    pub recovered: u64,
+    /// Statements that were still locked out when the retries ran out
+    pub exhausted: u64,
+    pub retry_wait_ms: u64,
+}
+
+#[derive(Debug, Default)]
+struct ContentionCounters {
+    busy_errors: AtomicU64,
+    retries: AtomicU64,
+    recovered: AtomicU64,
+    exhausted: AtomicU64,
+    retry_wait_ms: AtomicU64,
+}
+
+/// SQLITE_BUSY or SQLITE_LOCKED, including their extended codes
+/// Project of a document that isn't in the trash
+async fn document_project(
+    tx: &mut sqlx::SqliteConnection,
+    document_id: &str,
+) -> DatabaseResult<String> {
+    sqlx::query_scalar("SELECT project_id FROM documents WHERE id = ?1 AND is_active = 1")
+        .bind(document_id)
+        .fetch_optional(tx)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to get document: {}", e)))?
+        .ok_or_else(|| DatabaseError::RecordNotFound {
+            entity: "document".to_string(),
+            id: document_id.to_string(),
+        })
+}
+
+/// Fail unless the project exists and isn't in the trash
+async fn ensure_live_project(
+    tx: &mut sqlx::SqliteConnection,
+    project_id: &str,
+) -> DatabaseResult<()> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM projects WHERE id = ?1 AND deleted_at IS NULL)",
+    )
+    .bind(project_id)
+    .fetch_one(tx)
+    .await
+    .map_err(|e| DatabaseError::Service(format!("Failed to get project: {}", e)))?;
+    if !exists {
+        return Err(DatabaseError::RecordNotFound {
+            entity: "project".to_string(),
+            id: project_id.to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn is_busy(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(e) => e
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            .is_some_and(|code| matches!(code & 0xff, 5 | 6)),
+        _ => false,
+    }
+}
+
+/// Delay before retry `attempt`: `base` doubled per attempt up to a
+/// second, then scaled into its upper half by `jitter` (0 to 1) so
+/// writers that collided don't retry in step
+fn busy_backoff(base: Duration, attempt: u32, jitter: f64) -> Duration {
+    let ceiling = base
+        .saturating_mul(1 << attempt.min(16))
+        .min(Duration::from_secs(1));
+    ceiling.mul_f64(0.5 + 0.5 * jitter.clamp(0.0, 1.0))
 }
 
 /// Database statistics
@@ -483,3 +1589,489 @@ pub struct DatabaseStats {
     pub integrity_check_passed: bool,
     pub performance_metrics: DatabasePerformanceMetrics,
 }
+
+fn version_from_row(row: VersionRow) -> DatabaseResult<DocumentVersion> {
+    let (id, document_id, version, title, content, created_at, change_description) = row;
+    let parse = |value: &str| {
+        Uuid::parse_str(value).map_err(|_| {
+            DatabaseError::Service(format!("Invalid id in document versions: {}", value))
+        })
+    };
+    Ok(DocumentVersion {
+        id: parse(&id)?,
+        document_id: parse(&document_id)?,
+        version: version as u32,
+        title,
+        content,
+        created_at,
+        change_description,
+    })
+}
+
+/// Statement text for tracing: whitespace collapsed and truncated. Bound
+/// parameter values are never included.
+fn sql_summary(sql: &str) -> String {
+    let collapsed = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+    match collapsed.char_indices().nth(120) {
+        Some((index, _)) => format!("{}...", &collapsed[..index]),
+        None => collapsed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::autosnapshot::SnapshotCadence;
+    use crate::security::confirmation::ConfirmationProof;
+
+    #[test]
+    fn test_busy_backoff_grows_with_jitter() {
+        let base = Duration::from_millis(50);
+        assert_eq!(busy_backoff(base, 0, 0.0), Duration::from_millis(25));
+        assert_eq!(busy_backoff(base, 0, 1.0), Duration::from_millis(50));
+        assert_eq!(busy_backoff(base, 2, 1.0), Duration::from_millis(200));
+        assert_eq!(busy_backoff(base, 30, 1.0), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_write_retries_past_held_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DatabaseConfig {
+            busy_timeout: Duration::from_millis(10),
+            busy_retries: 8,
+            busy_retry_delay: Duration::from_millis(20),
+            ..DatabaseConfig::default()
+        };
+        let db = EnhancedDatabaseService::new(&dir.path().join("test.db"), config)
+            .await
+            .unwrap();
+        assert!(db.check_wal_mode().await);
+        db.create_document(
+            "chapter-1".to_string(),
+            "default-project".to_string(),
+            "Chapter 1".to_string(),
+            "First draft".to_string(),
+        )
+        .await
+        .unwrap();
+
+        // An export-style writer holds the lock while autosave comes in
+        let mut writer = db.pool.acquire().await.unwrap();
+        sqlx::query("BEGIN IMMEDIATE")
+            .execute(&mut *writer)
+            .await
+            .unwrap();
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            sqlx::query("COMMIT").execute(&mut *writer).await.unwrap();
+        });
+
+        db.update_document_content("chapter-1", "Second draft")
+            .await
+            .unwrap();
+        release.await.unwrap();
+
+        let metrics = db.contention_metrics();
+        assert!(metrics.retries > 0);
+        assert_eq!(metrics.recovered, 1);
+        assert_eq!(metrics.exhausted, 0);
+        assert_eq!(
+            db.get_document("chapter-1".to_string()).await.unwrap(),
+            Some("Second draft".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_version_diff_revert_and_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        // Every autosave becomes a version
+        let config = DatabaseConfig {
+            autosnapshot: AutoSnapshotPolicy {
+                cadence: SnapshotCadence::Changes(1),
+                ..AutoSnapshotPolicy::default()
+            },
+            ..DatabaseConfig::default()
+        };
+        let db = EnhancedDatabaseService::new(&dir.path().join("test.db"), config)
+            .await
+            .unwrap();
+        let document_id = Uuid::new_v4();
+        let id = document_id.to_string();
+        db.create_document(
+            id.clone(),
+            "default-project".to_string(),
+            "Chapter 1".to_string(),
+            "It was dark.\nThe end.\n".to_string(),
+        )
+        .await
+        .unwrap();
+        db.update_document_content(&id, "It was a dark and stormy night.\nThe end.\n")
+            .await
+            .unwrap();
+
+        let versions = db.list_document_versions(document_id).await.unwrap();
+        assert_eq!(
+            versions.iter().map(|v| v.version).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        let diff = db.diff_document_versions(document_id, 1, 2).await.unwrap();
+        assert_eq!(diff.hunks.len(), 1);
+        assert_eq!(
+            (diff.summary.lines_added, diff.summary.lines_removed),
+            (1, 1)
+        );
+        assert!(diff.title_change.is_none());
+
+        // Both windows started from version 2; this one saved first
+        db.update_document_content(&id, "It was a dark and stormy night.\nThe end!\n")
+            .await
+            .unwrap();
+        let merged = db
+            .merge_document_edit(
+                document_id,
+                2,
+                "Chapter One\nIt was a dark and stormy night.\nThe end.\n",
+            )
+            .await
+            .unwrap();
+        assert_eq!(merged.saved_version, Some(4));
+        assert_eq!(
+            db.get_document(id.clone()).await.unwrap().unwrap(),
+            "Chapter One\nIt was a dark and stormy night.\nThe end!\n"
+        );
+        let conflicted = db
+            .merge_document_edit(
+                document_id,
+                2,
+                "It was a dark and stormy night.\nThe end?\n",
+            )
+            .await
+            .unwrap();
+        assert_eq!(conflicted.merge.conflicts(), 1);
+        assert!(conflicted.saved_version.is_none());
+
+        let reverted = db.revert_document_to_version(document_id, 1).await.unwrap();
+        assert_eq!(
+            db.get_document(id).await.unwrap().unwrap(),
+            "It was dark.\nThe end.\n"
+        );
+        let version = db
+            .get_document_version(document_id, reverted)
+            .await
+            .unwrap();
+        assert_eq!(
+            version.change_description.as_deref(),
+            Some("Reverted to version 1")
+        );
+        assert!(matches!(
+            db.get_document_version(document_id, 99).await,
+            Err(DatabaseError::RecordNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_editor_documents_merge_by_paragraph() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let doc = |text: &str| {
+            serde_json::json!({ "type": "doc", "content": prosemirror::paragraphs(text) })
+                .to_string()
+        };
+        let document_id = Uuid::new_v4();
+        let id = document_id.to_string();
+        db.create_document(
+            id.clone(),
+            "default-project".to_string(),
+            "Chapter 1".to_string(),
+            doc("It was dark.\nShe waited.\nThe end."),
+        )
+        .await
+        .unwrap();
+        db.update_document(
+            id.clone(),
+            "Chapter 1".to_string(),
+            doc("It was dark.\nShe waited.\nThe end!"),
+        )
+        .await
+        .unwrap();
+
+        // The other window edited the first paragraph of version 1
+        let merged = db
+            .merge_document_edit(
+                document_id,
+                1,
+                &doc("It was very dark.\nShe waited.\nThe end."),
+            )
+            .await
+            .unwrap();
+        assert!(merged.merge.is_clean());
+        let content = db.get_document(id.clone()).await.unwrap().unwrap();
+        assert_eq!(content, doc("It was very dark.\nShe waited.\nThe end!"));
+        let word_count: i64 = sqlx::query_scalar("SELECT word_count FROM documents WHERE id = ?")
+            .bind(&id)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(word_count, 8);
+
+        let diff = db
+            .diff_document_versions(document_id, 1, merged.saved_version.unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            (diff.summary.lines_added, diff.summary.lines_removed),
+            (2, 2)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_autosave_snapshots_on_cadence() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DatabaseConfig {
+            autosnapshot: AutoSnapshotPolicy {
+                cadence: SnapshotCadence::Changes(2),
+                ..AutoSnapshotPolicy::default()
+            },
+            ..DatabaseConfig::default()
+        };
+        let db = EnhancedDatabaseService::new(&dir.path().join("test.db"), config)
+            .await
+            .unwrap();
+        let document_id = Uuid::new_v4();
+        let id = document_id.to_string();
+        db.create_document(
+            id.clone(),
+            "default-project".to_string(),
+            "Chapter 1".to_string(),
+            "One".to_string(),
+        )
+        .await
+        .unwrap();
+
+        for content in ["One two", "One two three", "One two three four"] {
+            db.update_document_content(&id, content).await.unwrap();
+        }
+        let versions = db.list_document_versions(document_id).await.unwrap();
+        assert_eq!(
+            versions.iter().map(|v| v.version).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(versions[1].content, "One two three");
+        assert_eq!(
+            versions[1].change_description.as_deref(),
+            Some(AUTOSNAPSHOT_DESCRIPTION)
+        );
+
+        // A save versions at once, and there is nothing new to snapshot
+        db.update_document(id.clone(), "Chapter 1".to_string(), "Saved".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            db.list_document_versions(document_id).await.unwrap().len(),
+            3
+        );
+        assert_eq!(db.snapshot_document(&id).await.unwrap(), None);
+
+        // Snapshots past the retention window go; saved versions stay
+        sqlx::query("UPDATE document_versions SET created_at = ? WHERE document_id = ?")
+            .bind(Utc::now() - chrono::Duration::days(40))
+            .bind(&id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(db.prune_snapshots(&id).await.unwrap(), 1);
+        let versions = db.list_document_versions(document_id).await.unwrap();
+        assert_eq!(
+            versions.iter().map(|v| v.version).collect::<Vec<_>>(),
+            [1, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trash_restore_and_purge() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        for (id, title) in [("chapter-1", "Chapter 1"), ("chapter-2", "Chapter 2")] {
+            db.create_document(
+                id.to_string(),
+                "default-project".to_string(),
+                title.to_string(),
+                "Text".to_string(),
+            )
+            .await
+            .unwrap();
+        }
+
+        db.delete_document("chapter-1".to_string()).await.unwrap();
+        assert_eq!(
+            db.get_document("chapter-1".to_string()).await.unwrap(),
+            None
+        );
+        let trash = db.list_trash(None).await.unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(
+            (trash[0].kind, trash[0].title.as_str()),
+            (TrashItemKind::Document, "Chapter 1")
+        );
+        assert_eq!(
+            trash[0].purge_at - trash[0].deleted_at,
+            chrono::Duration::days(30)
+        );
+
+        db.restore_from_trash(TrashItemKind::Document, "chapter-1")
+            .await
+            .unwrap();
+        assert!(db
+            .get_document("chapter-1".to_string())
+            .await
+            .unwrap()
+            .is_some());
+
+        // Documents of a trashed project wait for the project
+        db.delete_document("chapter-2".to_string()).await.unwrap();
+        db.trash_project("default-project").await.unwrap();
+        assert!(matches!(
+            db.restore_from_trash(TrashItemKind::Document, "chapter-2")
+                .await,
+            Err(DatabaseError::ValidationError(_))
+        ));
+        assert_eq!(
+            db.list_trash(Some("default-project")).await.unwrap().len(),
+            2
+        );
+
+        // Only what's past the retention period is purged on its own
+        sqlx::query("UPDATE documents SET deleted_at = ? WHERE id = 'chapter-2'")
+            .bind(Utc::now() - chrono::Duration::days(31))
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(db.purge_expired_trash().await.unwrap(), 1);
+        assert!(matches!(
+            db.purge_from_trash(TrashItemKind::Document, "chapter-2", None)
+                .await,
+            Err(DatabaseError::RecordNotFound { .. })
+        ));
+        db.restore_from_trash(TrashItemKind::Project, "default-project")
+            .await
+            .unwrap();
+        assert!(db.list_trash(None).await.unwrap().is_empty());
+        assert!(db
+            .get_document("chapter-1".to_string())
+            .await
+            .unwrap()
+            .is_some());
+
+        // Once a guard with a passphrase is set, on any handle, purging
+        // needs a grant
+        let guard = Arc::new(ConfirmationGuard::new());
+        guard.set_passphrase(None, "correct horse battery").unwrap();
+        db.clone().set_confirmation_guard(guard.clone());
+        db.trash_project("default-project").await.unwrap();
+        assert!(matches!(
+            db.empty_trash(None, None).await,
+            Err(DatabaseError::ValidationError(_))
+        ));
+        let grant = guard
+            .confirm(
+                DestructiveOperation::PurgeTrash,
+                &ConfirmationProof::Passphrase("correct horse battery".to_string()),
+            )
+            .unwrap();
+        assert_eq!(db.empty_trash(None, Some(&grant.token)).await.unwrap(), 1);
+        assert_eq!(
+            db.get_document("chapter-1".to_string()).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_move_and_copy_between_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let events = EventSystem {
+            event_queue: Arc::default(),
+        };
+        db.set_automation_events(events.clone());
+        sqlx::query("INSERT INTO projects (id, name, created_at, updated_at) VALUES ('sequel', 'Sequel', ?1, ?1)")
+            .bind(Utc::now())
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        db.create_document(
+            "chapter-1".to_string(),
+            "default-project".to_string(),
+            "Chapter 1".to_string(),
+            "Draft".to_string(),
+        )
+        .await
+        .unwrap();
+        db.update_document(
+            "chapter-1".to_string(),
+            "Chapter 1".to_string(),
+            "Second draft".to_string(),
+        )
+        .await
+        .unwrap();
+        for sql in [
+            "CREATE TABLE binder_order (document_id TEXT PRIMARY KEY, project_id TEXT NOT NULL, position INTEGER NOT NULL)",
+            "INSERT INTO binder_order VALUES ('chapter-1', 'default-project', 0)",
+            "CREATE TABLE annotations (id TEXT PRIMARY KEY, project_id TEXT, document_id TEXT)",
+            "INSERT INTO annotations VALUES ('note-1', 'default-project', 'chapter-1')",
+        ] {
+            sqlx::query(sql).execute(&db.pool).await.unwrap();
+        }
+
+        db.move_document_to_project("chapter-1", "sequel")
+            .await
+            .unwrap();
+        let count = |sql: &'static str| sqlx::query_scalar::<_, i64>(sql).fetch_one(&db.pool);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM documents WHERE project_id = 'sequel'")
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            count("SELECT COUNT(*) FROM annotations WHERE project_id = 'sequel'")
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(count("SELECT COUNT(*) FROM binder_order").await.unwrap(), 0);
+        assert!(matches!(
+            db.move_document_to_project("chapter-1", "sequel").await,
+            Err(DatabaseError::ValidationError(_))
+        ));
+        assert!(matches!(
+            db.move_document_to_project("chapter-1", "missing").await,
+            Err(DatabaseError::RecordNotFound { .. })
+        ));
+
+        let copy_id = db
+            .copy_document_to_project("chapter-1", "default-project")
+            .await
+            .unwrap();
+        let versions = db
+            .list_document_versions(Uuid::parse_str(&copy_id).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[1].content, "Second draft");
+        assert_eq!(count("SELECT COUNT(*) FROM annotations").await.unwrap(), 1);
+
+        let queue = events.event_queue.lock().await;
+        let kinds: Vec<_> = queue.iter().map(|event| event.event_type.clone()).collect();
+        assert_eq!(kinds, [EventType::DocumentMoved, EventType::DocumentCopied]);
+        assert_eq!(queue[1].data["copy_id"], copy_id.as_str());
+    }
+}
@@ -0,0 +1,458 @@
+//! Export Repository
+//!
+//! Keeps export jobs and export templates in the database so they outlive
+//! the export engine's in-memory maps. Finished jobs stay as an audit trail
+//! of what was exported when and where it went; jobs a restart interrupted
+//! are handed back to be submitted again.
+
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::database::parse::parse_time;
+use crate::database::{
+    models::export_record::*, DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+
+type ExportJobRow = (
+    String,
+    String,
+    String,
+    String,
+    f64,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<i64>,
+    String,
+    Option<String>,
+    Option<String>,
+    String,
+);
+
+type ExportTemplateRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    String,
+    String,
+    String,
+    String,
+);
+
+/// Repository for export jobs and templates
+#[derive(Debug)]
+pub struct ExportRepository {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl ExportRepository {
+    /// Create a new export repository
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the export jobs and templates tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_EXPORT_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create export tables: {}", e))
+            })?;
+
+        // Templates saved before export hooks
+        let columns: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info('export_templates')")
+                .fetch_all(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Migration(e.to_string()))?;
+        if !columns.iter().any(|c| c == "hooks") {
+            sqlx::query("ALTER TABLE export_templates ADD COLUMN hooks TEXT NOT NULL DEFAULT '[]'")
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to add export hooks: {}", e))
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Save a job, replacing its earlier state
+    pub async fn save_job(&self, job: &ExportJobRecord) -> DatabaseResult<ExportJobRecord> {
+        if job.job_id.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Export job id cannot be empty".to_string(),
+            ));
+        }
+        let mut saved = job.clone();
+        saved.progress = saved.progress.clamp(0.0, 1.0);
+        saved.updated_at = Utc::now();
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_EXPORT_JOB_SQL)
+            .bind(&saved.job_id)
+            .bind(&saved.document_id)
+            .bind(&saved.export_type)
+            .bind(saved.state.as_str())
+            .bind(saved.progress as f64)
+            .bind(saved.request.to_string())
+            .bind(&saved.output_path)
+            .bind(&saved.error_message)
+            .bind(saved.file_size_bytes.map(|b| b as i64))
+            .bind(saved.created_at.to_rfc3339())
+            .bind(saved.started_at.map(|t| t.to_rfc3339()))
+            .bind(saved.completed_at.map(|t| t.to_rfc3339()))
+            .bind(saved.updated_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save export job: {}", e)))?;
+        Ok(saved)
+    }
+
+    /// A job by id
+    pub async fn job(&self, job_id: &str) -> DatabaseResult<ExportJobRecord> {
+        let db = self.db_service.read().await;
+        let row: Option<ExportJobRow> =
+            sqlx::query_as(&format!("{} WHERE job_id = ?1", SELECT_EXPORT_JOBS_SQL))
+                .bind(job_id)
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to load export job: {}", e)))?;
+        match row {
+            Some(row) => job_from_row(row),
+            None => Err(DatabaseError::RecordNotFound {
+                entity: "export job".to_string(),
+                id: job_id.to_string(),
+            }),
+        }
+    }
+
+    /// Jobs for one document, or all jobs, newest first
+    pub async fn list_jobs(
+        &self,
+        document_id: Option<&str>,
+    ) -> DatabaseResult<Vec<ExportJobRecord>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<ExportJobRow> = match document_id {
+            Some(document_id) => {
+                sqlx::query_as(&format!(
+                    "{} WHERE document_id = ?1 ORDER BY created_at DESC",
+                    SELECT_EXPORT_JOBS_SQL
+                ))
+                .bind(document_id)
+                .fetch_all(&db.pool)
+                .await
+            }
+            None => {
+                sqlx::query_as(&format!(
+                    "{} ORDER BY created_at DESC",
+                    SELECT_EXPORT_JOBS_SQL
+                ))
+                .fetch_all(&db.pool)
+                .await
+            }
+        }
+        .map_err(|e| DatabaseError::Service(format!("Failed to list export jobs: {}", e)))?;
+        rows.into_iter().map(job_from_row).collect()
+    }
+
+    /// Jobs left pending or processing by the last run, reset to pending so
+    /// the engine can start them over, oldest first
+    pub async fn take_interrupted_jobs(&self) -> DatabaseResult<Vec<ExportJobRecord>> {
+        let rows: Vec<ExportJobRow> = {
+            let db = self.db_service.read().await;
+            sqlx::query_as(&format!(
+                "{} WHERE state IN ('pending', 'processing') ORDER BY created_at ASC",
+                SELECT_EXPORT_JOBS_SQL
+            ))
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to load interrupted export jobs: {}", e))
+            })?
+        };
+        let mut jobs = Vec::with_capacity(rows.len());
+        for row in rows {
+            let job = reset_for_resume(job_from_row(row)?);
+            jobs.push(self.save_job(&job).await?);
+        }
+        Ok(jobs)
+    }
+
+    /// Delete a job's record; the exported file is left alone
+    pub async fn delete_job(&self, job_id: &str) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM export_jobs WHERE job_id = ?1")
+            .bind(job_id)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete export job: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete finished jobs created before `before`
+    pub async fn prune_jobs(&self, before: DateTime<Utc>) -> DatabaseResult<u64> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query(
+            "DELETE FROM export_jobs WHERE created_at < ?1 AND state NOT IN ('pending', 'processing')",
+        )
+        .bind(before.to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to prune export jobs: {}", e)))?;
+        Ok(result.rows_affected())
+    }
+
+    /// Save a template, replacing an earlier version with the same id
+    pub async fn save_template(
+        &self,
+        template: &ExportTemplateRecord,
+    ) -> DatabaseResult<ExportTemplateRecord> {
+        if template.name.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Export template name cannot be empty".to_string(),
+            ));
+        }
+        let mut saved = template.clone();
+        saved.updated_at = Utc::now();
+        let tags = serde_json::to_string(&saved.tags)
+            .map_err(|e| DatabaseError::Service(format!("Failed to encode tags: {}", e)))?;
+        let hooks = serde_json::to_string(&saved.hooks)
+            .map_err(|e| DatabaseError::Service(format!("Failed to encode hooks: {}", e)))?;
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_EXPORT_TEMPLATE_SQL)
+            .bind(&saved.template_id)
+            .bind(&saved.name)
+            .bind(&saved.description)
+            .bind(&saved.version)
+            .bind(&saved.category)
+            .bind(tags)
+            .bind(&saved.author)
+            .bind(saved.definition.to_string())
+            .bind(hooks)
+            .bind(saved.created_at.to_rfc3339())
+            .bind(saved.updated_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to save export template: {}", e))
+            })?;
+        Ok(saved)
+    }
+
+    /// A template by id
+    pub async fn template(&self, template_id: &str) -> DatabaseResult<ExportTemplateRecord> {
+        let db = self.db_service.read().await;
+        let row: Option<ExportTemplateRow> = sqlx::query_as(&format!(
+            "{} WHERE template_id = ?1",
+            SELECT_EXPORT_TEMPLATES_SQL
+        ))
+        .bind(template_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load export template: {}", e)))?;
+        match row {
+            Some(row) => template_from_row(row),
+            None => Err(DatabaseError::RecordNotFound {
+                entity: "export template".to_string(),
+                id: template_id.to_string(),
+            }),
+        }
+    }
+
+    /// All templates by name
+    pub async fn list_templates(&self) -> DatabaseResult<Vec<ExportTemplateRecord>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<ExportTemplateRow> = sqlx::query_as(&format!(
+            "{} ORDER BY name COLLATE NOCASE",
+            SELECT_EXPORT_TEMPLATES_SQL
+        ))
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to list export templates: {}", e)))?;
+        rows.into_iter().map(template_from_row).collect()
+    }
+
+    /// Delete a template
+    pub async fn delete_template(&self, template_id: &str) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM export_templates WHERE template_id = ?1")
+            .bind(template_id)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to delete export template: {}", e))
+            })?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// A job interrupted mid-run, ready to start again from the beginning
+pub fn reset_for_resume(mut job: ExportJobRecord) -> ExportJobRecord {
+    job.state = ExportJobState::Pending;
+    job.progress = 0.0;
+    job.started_at = None;
+    job.completed_at = None;
+    job.output_path = None;
+    job.error_message = None;
+    job.file_size_bytes = None;
+    job
+}
+
+fn parse_json(value: &str) -> DatabaseResult<serde_json::Value> {
+    serde_json::from_str(value)
+        .map_err(|e| DatabaseError::Service(format!("Invalid export settings: {}", e)))
+}
+
+fn job_from_row(row: ExportJobRow) -> DatabaseResult<ExportJobRecord> {
+    let (
+        job_id,
+        document_id,
+        export_type,
+        state,
+        progress,
+        request,
+        output_path,
+        error_message,
+        file_size_bytes,
+        created_at,
+        started_at,
+        completed_at,
+        updated_at,
+    ) = row;
+    Ok(ExportJobRecord {
+        state: ExportJobState::parse(&state).ok_or_else(|| {
+            DatabaseError::Service(format!("Invalid export job state: {}", state))
+        })?,
+        job_id,
+        document_id,
+        export_type,
+        progress: progress as f32,
+        request: parse_json(&request)?,
+        output_path,
+        error_message,
+        file_size_bytes: file_size_bytes.and_then(|b| u64::try_from(b).ok()),
+        created_at: parse_time(&created_at)?,
+        started_at: started_at.as_deref().map(parse_time).transpose()?,
+        completed_at: completed_at.as_deref().map(parse_time).transpose()?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+fn template_from_row(row: ExportTemplateRow) -> DatabaseResult<ExportTemplateRecord> {
+    let (
+        template_id,
+        name,
+        description,
+        version,
+        category,
+        tags,
+        author,
+        definition,
+        hooks,
+        created_at,
+        updated_at,
+    ) = row;
+    Ok(ExportTemplateRecord {
+        template_id,
+        name,
+        description,
+        version,
+        category,
+        tags: serde_json::from_str(&tags).unwrap_or_default(),
+        author,
+        definition: parse_json(&definition)?,
+        hooks: serde_json::from_str(&hooks)
+            .map_err(|e| DatabaseError::Service(format!("Invalid export hooks: {}", e)))?,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_jobs_and_templates_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let open = || async {
+            let db = EnhancedDatabaseService::new(&path, DatabaseConfig::default())
+                .await
+                .unwrap();
+            let repository = ExportRepository::new(Arc::new(RwLock::new(db)));
+            repository.initialize().await.unwrap();
+            repository
+        };
+
+        let repository = open().await;
+        let request = serde_json::json!({"epub_version": "3.0"});
+        let mut done = ExportJobRecord::new("job-1", "doc-1", "epub", request.clone());
+        done.state = ExportJobState::Completed;
+        done.progress = 1.0;
+        done.output_path = Some("/exports/job-1.epub".to_string());
+        done.file_size_bytes = Some(48_213);
+        done.completed_at = Some(Utc::now());
+        repository.save_job(&done).await.unwrap();
+
+        let mut running = ExportJobRecord::new("job-2", "doc-1", "epub", request.clone());
+        running.state = ExportJobState::Processing;
+        running.progress = 0.7;
+        running.started_at = Some(Utc::now());
+        repository.save_job(&running).await.unwrap();
+
+        let template = ExportTemplateRecord {
+            template_id: "manuscript".to_string(),
+            name: "Standard Manuscript".to_string(),
+            description: "Courier, double spaced".to_string(),
+            version: "1.0".to_string(),
+            category: "creative".to_string(),
+            tags: vec!["submission".to_string()],
+            author: None,
+            definition: serde_json::json!({"font": "Courier", "line_spacing": 2.0}),
+            hooks: vec![ExportHook::Workflow(uuid::Uuid::new_v4())],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        repository.save_template(&template).await.unwrap();
+        drop(repository);
+
+        let repository = open().await;
+        let interrupted = repository.take_interrupted_jobs().await.unwrap();
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(interrupted[0].job_id, "job-2");
+        assert_eq!(interrupted[0].state, ExportJobState::Pending);
+        assert_eq!(interrupted[0].progress, 0.0);
+        assert_eq!(interrupted[0].request, request);
+        assert!(repository.take_interrupted_jobs().await.unwrap()[0]
+            .started_at
+            .is_none());
+
+        let finished = repository.job("job-1").await.unwrap();
+        assert_eq!(finished.state, ExportJobState::Completed);
+        assert_eq!(finished.output_path.as_deref(), Some("/exports/job-1.epub"));
+        assert_eq!(finished.file_size_bytes, Some(48_213));
+        assert_eq!(repository.list_jobs(Some("doc-1")).await.unwrap().len(), 2);
+        assert_eq!(repository.prune_jobs(Utc::now()).await.unwrap(), 1);
+
+        let templates = repository.list_templates().await.unwrap();
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].definition, template.definition);
+        assert_eq!(templates[0].tags, template.tags);
+        assert_eq!(templates[0].hooks, template.hooks);
+        assert!(repository.delete_template("manuscript").await.unwrap());
+        assert!(matches!(
+            repository.template("manuscript").await,
+            Err(DatabaseError::RecordNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,531 @@
+//! Test Fixtures and Seed Data
+//!
+//! Fills a database with generated projects for benchmarking search,
+//! export and backup against realistic volumes: prose documents of a given
+//! length, codex entries for the characters and places the prose mentions,
+//! and optionally embeddings for every document. Output is deterministic
+//! for a given seed, so timings from different runs are comparable.
+//!
+//! Run from the command line with
+//! `herding-cats --generate-fixtures <database> [projects=3,documents=50,...]`.
+
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::models::codex::CREATE_CODEX_ENTRIES_TABLE_SQL;
+use crate::database::{
+    DatabaseError, DatabaseResult, EnhancedDatabaseService, VectorEmbeddingService,
+};
+
+/// What to generate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureSpec {
+    pub projects: usize,
+    pub documents_per_project: usize,
+    /// Document lengths are drawn evenly from this range
+    pub min_words: usize,
+    pub max_words: usize,
+    pub codex_entries_per_project: usize,
+    /// Store embeddings for every document with the default model
+    pub embeddings: bool,
+    pub seed: u64,
+}
+
+impl Default for FixtureSpec {
+    fn default() -> Self {
+        Self {
+            projects: 3,
+            documents_per_project: 50,
+            min_words: 500,
+            max_words: 3000,
+            codex_entries_per_project: 20,
+            embeddings: false,
+            seed: 1,
+        }
+    }
+}
+
+impl FixtureSpec {
+    /// Parse `key=value` pairs separated by commas, e.g.
+    /// `projects=10,documents=200,words=1000-4000,codex=40,embeddings=true`.
+    /// Keys left out keep their defaults.
+    pub fn parse(spec: &str) -> DatabaseResult<Self> {
+        let mut parsed = Self::default();
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').ok_or_else(|| {
+                DatabaseError::ValidationError(format!("Expected key=value, got '{}'", pair))
+            })?;
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "projects" => parsed.projects = number(key, value)?,
+                "documents" => parsed.documents_per_project = number(key, value)?,
+                "words" => {
+                    let (min, max) = value.split_once('-').unwrap_or((value, value));
+                    parsed.min_words = number(key, min)?;
+                    parsed.max_words = number(key, max)?;
+                }
+                "codex" => parsed.codex_entries_per_project = number(key, value)?,
+                "embeddings" => {
+                    parsed.embeddings = value.parse().map_err(|_| {
+                        DatabaseError::ValidationError(format!(
+                            "embeddings must be true or false, got '{}'",
+                            value
+                        ))
+                    })?
+                }
+                "seed" => parsed.seed = number(key, value)?,
+                _ => {
+                    return Err(DatabaseError::ValidationError(format!(
+                        "Unknown fixture option '{}'",
+                        key
+                    )))
+                }
+            }
+        }
+        if parsed.min_words > parsed.max_words {
+            return Err(DatabaseError::ValidationError(format!(
+                "words range {}-{} is empty",
+                parsed.min_words, parsed.max_words
+            )));
+        }
+        Ok(parsed)
+    }
+}
+
+fn number<T: std::str::FromStr>(key: &str, value: &str) -> DatabaseResult<T> {
+    value.parse().map_err(|_| {
+        DatabaseError::ValidationError(format!("{} must be a number, got '{}'", key, value))
+    })
+}
+
+/// What was generated
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FixtureReport {
+    pub project_ids: Vec<Uuid>,
+    pub documents: usize,
+    pub codex_entries: usize,
+    pub embeddings: usize,
+    pub words: usize,
+}
+
+/// Generate `spec` into the database. Projects are added alongside
+/// whatever is already there.
+pub async fn generate(
+    db_service: &Arc<RwLock<EnhancedDatabaseService>>,
+    spec: &FixtureSpec,
+) -> DatabaseResult<FixtureReport> {
+    let mut rng = StdRng::seed_from_u64(spec.seed);
+    let mut report = FixtureReport::default();
+    if spec.codex_entries_per_project > 0 {
+        create_codex_table(db_service).await?;
+    }
+    let embeddings = VectorEmbeddingService::new(Arc::clone(db_service));
+    if spec.embeddings {
+        embeddings
+            .initialize()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Embedding setup failed: {}", e)))?;
+    }
+
+    for _ in 0..spec.projects {
+        let project = ProjectText::new(&mut rng);
+        let project_id = Uuid::new_v4();
+        let start = Utc::now() - Duration::days(rng.gen_range(30..720));
+
+        let db = db_service.read().await;
+        let mut tx =
+            db.pool.begin().await.map_err(|e| {
+                DatabaseError::Service(format!("Failed to start transaction: {}", e))
+            })?;
+
+        sqlx::query(
+            "INSERT INTO projects (id, name, description, created_at, updated_at)
+             VALUES (?1, ?2, 'Generated fixture', ?3, ?3)",
+        )
+        .bind(project_id.to_string())
+        .bind(&project.title)
+        .bind(start.to_rfc3339())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to create project: {}", e)))?;
+
+        let mut document_ids = Vec::with_capacity(spec.documents_per_project);
+        for chapter in 1..=spec.documents_per_project {
+            let words = rng.gen_range(spec.min_words..=spec.max_words);
+            let content = project.prose(&mut rng, words);
+            let word_count = content.split_whitespace().count();
+            let document_id = Uuid::new_v4();
+            let updated_at = start + Duration::hours(chapter as i64 * rng.gen_range(1..48));
+
+            sqlx::query(
+                "INSERT INTO documents (id, project_id, title, content, document_type, word_count, checksum, created_at, updated_at, is_active, version, metadata)
+                 VALUES (?1, ?2, ?3, ?4, 'json', ?5, ?6, ?7, ?8, 1, 1, NULL)",
+            )
+            .bind(document_id.to_string())
+            .bind(project_id.to_string())
+            .bind(format!("Chapter {}: {}", chapter, project.heading(&mut rng)))
+            .bind(&content)
+            .bind(word_count as i64)
+            .bind(format!("{:x}", Sha256::digest(content.as_bytes())))
+            .bind(start)
+            .bind(updated_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to create document: {}", e)))?;
+
+            document_ids.push(document_id);
+            report.words += word_count;
+        }
+
+        for sort_order in 0..spec.codex_entries_per_project {
+            let (entry_type, title, content) = project.codex_entry(&mut rng, sort_order);
+            sqlx::query(
+                "INSERT INTO codex_entries (id, project_id, entry_type, title, content, status,
+                                            created_at, updated_at, is_active, metadata, sort_order)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 'draft', ?6, ?6, 1, '', ?7)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(project_id.to_string())
+            .bind(entry_type)
+            .bind(title)
+            .bind(content)
+            .bind(start.to_rfc3339())
+            .bind(sort_order as i64)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to create codex entry: {}", e)))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to commit fixtures: {}", e)))?;
+        drop(db);
+
+        if spec.embeddings {
+            for document_id in &document_ids {
+                report.embeddings += embeddings
+                    .generate_document_embeddings(document_id, None)
+                    .await
+                    .map_err(|e| DatabaseError::Service(format!("Embedding failed: {}", e)))?
+                    .len();
+            }
+        }
+
+        report.documents += document_ids.len();
+        report.codex_entries += spec.codex_entries_per_project;
+        report.project_ids.push(project_id);
+    }
+
+    Ok(report)
+}
+
+/// Make sure the codex table exists
+async fn create_codex_table(
+    db_service: &Arc<RwLock<EnhancedDatabaseService>>,
+) -> DatabaseResult<()> {
+    let db = db_service.read().await;
+    sqlx::query(CREATE_CODEX_ENTRIES_TABLE_SQL)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Migration(format!("Failed to create codex table: {}", e)))?;
+    Ok(())
+}
+
+const FIRST_NAMES: &[&str] = &[
+    "Mara", "Elias", "Ines", "Tobin", "Saoirse", "Kenji", "Odile", "Rafe", "Lucia", "Anselm",
+    "Wren", "Dmitri", "Halima", "Caspar", "Yara", "Bastian", "Noor", "Felix", "Isolde", "Jonah",
+];
+const SURNAMES: &[&str] = &[
+    "Voss",
+    "Ashdown",
+    "Calloway",
+    "Ferreira",
+    "Kestrel",
+    "Marlowe",
+    "Okafor",
+    "Petrakis",
+    "Quill",
+    "Rourke",
+    "Sandoval",
+    "Thorne",
+    "Underhill",
+    "Vance",
+    "Whitlock",
+    "Yoshida",
+];
+const PLACES: &[&str] = &[
+    "Harrow Quay",
+    "the Saltmarsh",
+    "Greywater",
+    "the Lantern Market",
+    "Brindle Hall",
+    "Fenwick Cross",
+    "the Old Observatory",
+    "Cinder Lane",
+    "Port Aubade",
+    "the Hollow Wood",
+    "Marrow Bridge",
+    "the Glasshouse",
+    "Ember Reach",
+    "Stillwater Abbey",
+];
+const OBJECTS: &[&str] = &[
+    "letter",
+    "brass key",
+    "ledger",
+    "lantern",
+    "compass",
+    "pocket watch",
+    "map",
+    "locket",
+    "ring",
+    "knife",
+    "photograph",
+    "music box",
+];
+const TITLE_WORDS: &[&str] = &[
+    "Salt", "Ash", "Lantern", "Winter", "Glass", "River", "Hollow", "Crown", "Ember", "Tide",
+    "Sparrow", "Iron", "Silence", "Orchard",
+];
+const VERBS: &[&str] = &[
+    "watched",
+    "followed",
+    "remembered",
+    "ignored",
+    "answered",
+    "crossed",
+    "found",
+    "hid",
+    "carried",
+    "opened",
+    "studied",
+    "left",
+    "waited for",
+    "searched",
+];
+const ADJECTIVES: &[&str] = &[
+    "cold", "narrow", "quiet", "crooked", "bright", "empty", "crowded", "ancient", "damp",
+    "golden", "restless", "broken", "familiar", "distant",
+];
+const NOUNS: &[&str] = &[
+    "door", "window", "street", "harbour", "stair", "garden", "candle", "rain", "bell", "corridor",
+    "crowd", "ship", "fire", "wall", "river", "table",
+];
+const CONNECTIVES: &[&str] = &[
+    "Later,",
+    "Without a word,",
+    "At first light,",
+    "Somewhere below,",
+    "For a moment,",
+    "Even so,",
+    "By then,",
+    "Outside,",
+];
+const DIALOGUE: &[&str] = &[
+    "We should not be here",
+    "You knew all along",
+    "Tell me what happened",
+    "It is later than you think",
+    "Nobody keeps a promise like that",
+    "Then we go tonight",
+];
+
+/// Per-project cast and settings, so the prose and codex mention the
+/// same names the way a real manuscript would
+struct ProjectText {
+    title: String,
+    characters: Vec<String>,
+    places: Vec<&'static str>,
+}
+
+impl ProjectText {
+    fn new(rng: &mut StdRng) -> Self {
+        let title = format!(
+            "The {} of {}",
+            TITLE_WORDS.choose(rng).unwrap(),
+            PLACES.choose(rng).unwrap().trim_start_matches("the ")
+        );
+        let characters = (0..6)
+            .map(|_| {
+                format!(
+                    "{} {}",
+                    FIRST_NAMES.choose(rng).unwrap(),
+                    SURNAMES.choose(rng).unwrap()
+                )
+            })
+            .collect();
+        let places = PLACES.choose_multiple(rng, 5).copied().collect();
+        Self {
+            title,
+            characters,
+            places,
+        }
+    }
+
+    fn first_name(&self, rng: &mut StdRng) -> &str {
+        let name = self.characters.choose(rng).unwrap();
+        name.split(' ').next().unwrap_or(name)
+    }
+
+    fn heading(&self, rng: &mut StdRng) -> String {
+        match rng.gen_range(0..3) {
+            0 => format!("The {}", TITLE_WORDS.choose(rng).unwrap()),
+            1 => self.places.choose(rng).unwrap().to_string(),
+            _ => format!(
+                "{} and the {}",
+                self.first_name(rng),
+                OBJECTS.choose(rng).unwrap()
+            ),
+        }
+    }
+
+    fn sentence(&self, rng: &mut StdRng) -> String {
+        let name = self.first_name(rng).to_string();
+        match rng.gen_range(0..5) {
+            0 => format!(
+                "{} {} the {} {} near {}.",
+                name,
+                VERBS.choose(rng).unwrap(),
+                ADJECTIVES.choose(rng).unwrap(),
+                NOUNS.choose(rng).unwrap(),
+                self.places.choose(rng).unwrap()
+            ),
+            1 => format!(
+                "\"{},\" {} said, turning the {} over in {} hands.",
+                DIALOGUE.choose(rng).unwrap(),
+                name,
+                OBJECTS.choose(rng).unwrap(),
+                if rng.gen_bool(0.5) { "her" } else { "his" }
+            ),
+            2 => format!(
+                "{} the {} was {} and the {} beyond it {}.",
+                CONNECTIVES.choose(rng).unwrap(),
+                NOUNS.choose(rng).unwrap(),
+                ADJECTIVES.choose(rng).unwrap(),
+                NOUNS.choose(rng).unwrap(),
+                ADJECTIVES.choose(rng).unwrap()
+            ),
+            3 => format!(
+                "{} {} {}, who {} the {} without looking up.",
+                CONNECTIVES.choose(rng).unwrap(),
+                name,
+                VERBS.choose(rng).unwrap(),
+                self.first_name(rng),
+                NOUNS.choose(rng).unwrap()
+            ),
+            _ => format!(
+                "The {} from {} still lay on the {}.",
+                OBJECTS.choose(rng).unwrap(),
+                self.places.choose(rng).unwrap(),
+                NOUNS.choose(rng).unwrap()
+            ),
+        }
+    }
+
+    /// Paragraphs of roughly `words` words
+    fn prose(&self, rng: &mut StdRng, words: usize) -> String {
+        let mut paragraphs = Vec::new();
+        let mut written = 0;
+        while written < words {
+            let mut paragraph = Vec::new();
+            for _ in 0..rng.gen_range(3..8) {
+                let sentence = self.sentence(rng);
+                written += sentence.split_whitespace().count();
+                paragraph.push(sentence);
+                if written >= words {
+                    break;
+                }
+            }
+            paragraphs.push(paragraph.join(" "));
+        }
+        paragraphs.join("\n\n")
+    }
+
+    /// Type, title and text of the `index`th entry: the cast first, then
+    /// places, then objects
+    fn codex_entry(&self, rng: &mut StdRng, index: usize) -> (&'static str, String, String) {
+        let characters = self.characters.len();
+        let places = self.places.len();
+        let (entry_type, title) = if index < characters {
+            ("character_sheet", self.characters[index].clone())
+        } else if index < characters + places {
+            ("place", self.places[index - characters].to_string())
+        } else {
+            let object = OBJECTS[(index - characters - places) % OBJECTS.len()];
+            ("object", format!("The {}", object))
+        };
+        let words = rng.gen_range(60..250);
+        (entry_type, title, self.prose(rng, words))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[test]
+    fn test_parse_spec() {
+        let spec =
+            FixtureSpec::parse("projects=2, documents=10,words=100-200,embeddings=true").unwrap();
+        assert_eq!(spec.projects, 2);
+        assert_eq!(spec.documents_per_project, 10);
+        assert_eq!((spec.min_words, spec.max_words), (100, 200));
+        assert!(spec.embeddings);
+        assert_eq!(spec.seed, FixtureSpec::default().seed);
+
+        assert_eq!(FixtureSpec::parse("words=300").unwrap().max_words, 300);
+        assert!(FixtureSpec::parse("words=300-100").is_err());
+        assert!(FixtureSpec::parse("chapters=3").is_err());
+        assert!(FixtureSpec::parse("projects").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_generates_projects_with_documents_and_codex() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let spec =
+            FixtureSpec::parse("projects=2,documents=3,words=80-120,codex=8,embeddings=true")
+                .unwrap();
+
+        let report = generate(&db, &spec).await.unwrap();
+        assert_eq!(report.project_ids.len(), 2);
+        assert_eq!(report.documents, 6);
+        assert_eq!(report.codex_entries, 16);
+        assert!(report.embeddings >= 6);
+
+        let pool = &db.read().await.pool;
+        let (documents, min_words): (i64, i64) =
+            sqlx::query_as("SELECT COUNT(*), MIN(word_count) FROM documents WHERE project_id = ?1")
+                .bind(report.project_ids[0].to_string())
+                .fetch_one(pool)
+                .await
+                .unwrap();
+        assert_eq!(documents, 3);
+        assert!(min_words >= 80);
+
+        // The prose mentions the cast the codex describes
+        let (name, content): (String, String) = sqlx::query_as(
+            "SELECT c.title, group_concat(d.content, ' ') FROM codex_entries c
+             JOIN documents d ON d.project_id = c.project_id
+             WHERE c.entry_type = 'character_sheet' GROUP BY c.id LIMIT 1",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        let first_name = name.split(' ').next().unwrap();
+        assert!(content.contains(first_name));
+    }
+}
@@ -0,0 +1,272 @@
+//! Focus Service
+//!
+//! Opt-in focus analytics. While enabled, window focus and blur events and
+//! tool switches from the window layer are written to a local table; they
+//! never leave the machine. Daily summaries show how long the writer stayed
+//! in the app at a stretch and how often the work was broken up.
+
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{models::focus::*, DatabaseError, DatabaseResult, EnhancedDatabaseService};
+use crate::settings;
+
+type FocusEventRow = (String, String, String, String);
+
+/// A blur followed this quickly by focus on another of the app's windows is
+/// a move between windows rather than leaving the app
+const WINDOW_SWITCH_GRACE_SECONDS: i64 = 2;
+/// Events older than this are deleted on startup
+const FOCUS_RETENTION_DAYS: i64 = 90;
+
+/// Service for recording focus events and summarizing them
+#[derive(Debug)]
+pub struct FocusService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    enabled: AtomicBool,
+}
+
+impl FocusService {
+    /// Create a new focus service, enabled if the user opted in
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        let enabled = settings::load_settings().focus_analytics.unwrap_or(false);
+        Self {
+            db_service,
+            enabled: AtomicBool::new(enabled),
+        }
+    }
+
+    /// Initialize the focus events table and drop expired events
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_FOCUS_EVENTS_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create focus events table: {}", e))
+            })?;
+        let cutoff = Utc::now() - Duration::days(FOCUS_RETENTION_DAYS);
+        sqlx::query("DELETE FROM focus_events WHERE occurred_at < ?1")
+            .bind(cutoff.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to prune focus events: {}", e)))?;
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Opt in or out, saved to the settings file. Opting out stops
+    /// recording but keeps what was recorded until cleared.
+    pub fn set_enabled(&self, enabled: bool) -> DatabaseResult<()> {
+        let mut saved = settings::load_settings();
+        saved.focus_analytics = Some(enabled);
+        settings::save_settings(&saved).map_err(DatabaseError::Service)?;
+        self.enabled.store(enabled, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Whether recording is on and how much has been recorded
+    pub async fn status(&self) -> DatabaseResult<FocusAnalyticsStatus> {
+        let db = self.db_service.read().await;
+        let recorded_events: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM focus_events")
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to count focus events: {}", e)))?;
+        Ok(FocusAnalyticsStatus {
+            enabled: self.is_enabled(),
+            recorded_events,
+        })
+    }
+
+    /// Record a focus change; does nothing unless the user opted in
+    pub async fn record(&self, kind: FocusEventKind, window: &str) -> DatabaseResult<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let event = FocusEvent::new(kind, window);
+        let db = self.db_service.read().await;
+        sqlx::query(INSERT_FOCUS_EVENT_SQL)
+            .bind(event.id.to_string())
+            .bind(event.kind.as_str())
+            .bind(&event.window)
+            .bind(event.occurred_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to record focus event: {}", e)))?;
+        Ok(())
+    }
+
+    /// Summaries for the last `days` days including today, oldest first;
+    /// days without events are left out
+    pub async fn daily_summaries(&self, days: u32) -> DatabaseResult<Vec<FocusDaySummary>> {
+        let days = days.max(1) as i64;
+        let first_day = Local::now().date_naive() - Duration::days(days - 1);
+        // A day's first stretch may have started the evening before
+        let since = Utc::now() - Duration::days(days + 1);
+        let db = self.db_service.read().await;
+        let rows: Vec<FocusEventRow> = sqlx::query_as(&format!(
+            "{} WHERE occurred_at >= ?1 ORDER BY occurred_at ASC",
+            SELECT_FOCUS_EVENTS_SQL
+        ))
+        .bind(since.to_rfc3339())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load focus events: {}", e)))?;
+        let events: Vec<FocusEvent> = rows
+            .into_iter()
+            .map(focus_event_from_row)
+            .collect::<DatabaseResult<_>>()?;
+        Ok(daily_summaries(&events, &Local)
+            .into_iter()
+            .filter(|summary| summary.date >= first_day)
+            .collect())
+    }
+
+    /// Delete everything recorded
+    pub async fn clear(&self) -> DatabaseResult<u64> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM focus_events")
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to clear focus events: {}", e)))?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[derive(Default)]
+struct DayTally {
+    stretches: Vec<i64>,
+    distractions: u32,
+    window_switches: u32,
+    tool_switches: u32,
+}
+
+/// Summarize events, in time order, per day in `tz`. A focused stretch
+/// counts towards the day it started on.
+pub fn daily_summaries<Tz: TimeZone>(events: &[FocusEvent], tz: &Tz) -> Vec<FocusDaySummary> {
+    let day_of = |at: &DateTime<Utc>| at.with_timezone(tz).date_naive();
+    let grace = Duration::seconds(WINDOW_SWITCH_GRACE_SECONDS);
+    let mut days: BTreeMap<NaiveDate, DayTally> = BTreeMap::new();
+    let mut focused_since: Option<DateTime<Utc>> = None;
+    let mut focused_window: Option<&str> = None;
+
+    for (i, event) in events.iter().enumerate() {
+        match event.kind {
+            FocusEventKind::ToolOpened => {
+                days.entry(day_of(&event.occurred_at))
+                    .or_default()
+                    .tool_switches += 1;
+            }
+            FocusEventKind::WindowFocused => {
+                if focused_window.is_some_and(|window| window != event.window) {
+                    days.entry(day_of(&event.occurred_at))
+                        .or_default()
+                        .window_switches += 1;
+                }
+                focused_since.get_or_insert(event.occurred_at);
+                focused_window = Some(&event.window);
+            }
+            FocusEventKind::WindowBlurred => {
+                let stays_in_app = events.get(i + 1).is_some_and(|next| {
+                    next.kind == FocusEventKind::WindowFocused
+                        && next.occurred_at - event.occurred_at <= grace
+                });
+                if stays_in_app {
+                    continue;
+                }
+                focused_window = None;
+                if let Some(start) = focused_since.take() {
+                    let tally = days.entry(day_of(&start)).or_default();
+                    tally
+                        .stretches
+                        .push((event.occurred_at - start).num_seconds().max(0));
+                    tally.distractions += 1;
+                }
+            }
+        }
+    }
+
+    days.into_iter()
+        .map(|(date, tally)| {
+            let focused_seconds: i64 = tally.stretches.iter().sum();
+            let focus_sessions = tally.stretches.len() as u32;
+            let interruptions = tally.distractions + tally.window_switches + tally.tool_switches;
+            FocusDaySummary {
+                date,
+                focus_sessions,
+                focused_seconds,
+                longest_focus_seconds: tally.stretches.iter().copied().max().unwrap_or(0),
+                average_focus_seconds: focused_seconds / (focus_sessions.max(1) as i64),
+                distractions: tally.distractions,
+                window_switches: tally.window_switches,
+                tool_switches: tally.tool_switches,
+                interruptions_per_hour: if focused_seconds > 0 {
+                    interruptions as f64 * 3600.0 / focused_seconds as f64
+                } else {
+                    0.0
+                },
+            }
+        })
+        .collect()
+}
+
+fn focus_event_from_row(row: FocusEventRow) -> DatabaseResult<FocusEvent> {
+    let (id, kind, window, occurred_at) = row;
+    Ok(FocusEvent {
+        id: parse_uuid(&id)?,
+        kind: FocusEventKind::parse(&kind)
+            .ok_or_else(|| DatabaseError::Service(format!("Invalid focus event kind: {}", kind)))?,
+        window,
+        occurred_at: parse_time(&occurred_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_summaries_split_distractions_from_window_switches() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 12, 9, 0, 0).unwrap();
+        let event = |minutes: i64, seconds: i64, kind: FocusEventKind, window: &str| FocusEvent {
+            occurred_at: start + Duration::minutes(minutes) + Duration::seconds(seconds),
+            ..FocusEvent::new(kind, window)
+        };
+        let events = vec![
+            event(0, 0, FocusEventKind::WindowFocused, "main"),
+            // Over to a tool window and back: switches, not distractions
+            event(20, 0, FocusEventKind::WindowBlurred, "main"),
+            event(20, 1, FocusEventKind::WindowFocused, "tool:names"),
+            event(20, 2, FocusEventKind::ToolOpened, "names"),
+            event(25, 0, FocusEventKind::WindowBlurred, "tool:names"),
+            event(25, 1, FocusEventKind::WindowFocused, "main"),
+            // Off to another application
+            event(30, 0, FocusEventKind::WindowBlurred, "main"),
+            event(40, 0, FocusEventKind::WindowFocused, "main"),
+            event(50, 0, FocusEventKind::WindowBlurred, "main"),
+            // Late stretch running past midnight counts for the day it began
+            event(14 * 60 + 50, 0, FocusEventKind::WindowFocused, "main"),
+            event(15 * 60 + 20, 0, FocusEventKind::WindowBlurred, "main"),
+        ];
+
+        let summaries = daily_summaries(&events, &Utc);
+        assert_eq!(summaries.len(), 1);
+        let day = &summaries[0];
+        assert_eq!(day.date, NaiveDate::from_ymd_opt(2026, 10, 12).unwrap());
+        assert_eq!(day.focus_sessions, 3);
+        assert_eq!(day.focused_seconds, (30 + 10 + 30) * 60);
+        assert_eq!(day.longest_focus_seconds, 30 * 60);
+        assert_eq!(day.average_focus_seconds, 70 * 60 / 3);
+        assert_eq!(day.distractions, 3);
+        assert_eq!(day.window_switches, 2);
+        assert_eq!(day.tool_switches, 1);
+        assert!((day.interruptions_per_hour - 6.0 * 60.0 / 70.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,234 @@
+//! Generator Service
+//!
+//! Stores random generator tables: shared tables, seeded with the starter
+//! set, and per-project tables that override shared ones of the same name.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::parse::parse_uuid;
+use crate::database::{DatabaseError, DatabaseResult, EnhancedDatabaseService};
+use crate::generators::{self, GeneratorTable};
+
+type GeneratorTableRow = (
+    String,
+    Option<String>,
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+);
+
+/// Database schema for generator tables; entries are stored as JSON
+const CREATE_GENERATOR_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS generator_tables (
+    id TEXT PRIMARY KEY,
+    project_id TEXT,
+    name TEXT NOT NULL,
+    category TEXT NOT NULL DEFAULT '',
+    description TEXT NOT NULL DEFAULT '',
+    entries TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_generator_tables_project ON generator_tables(project_id);
+"#;
+
+const UPSERT_GENERATOR_TABLE_SQL: &str = r#"
+INSERT INTO generator_tables (id, project_id, name, category, description, entries, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+ON CONFLICT(id) DO UPDATE SET
+    name = excluded.name,
+    category = excluded.category,
+    description = excluded.description,
+    entries = excluded.entries,
+    updated_at = excluded.updated_at
+"#;
+
+/// Shared tables first, so project tables win when collected by name
+const GET_GENERATOR_TABLES_SQL: &str = r#"
+SELECT id, project_id, name, category, description, entries, created_at, updated_at
+FROM generator_tables
+WHERE project_id IS NULL OR project_id = ?1
+ORDER BY project_id IS NOT NULL, category COLLATE NOCASE, name COLLATE NOCASE
+"#;
+
+/// Service for random generator tables
+#[derive(Debug)]
+pub struct GeneratorService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl GeneratorService {
+    /// Create a new generator service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the table and seed the starter tables on first run
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let shared: i64 = {
+            let db = self.db_service.read().await;
+            sqlx::query(CREATE_GENERATOR_TABLES_SQL)
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to create generator tables: {}", e))
+                })?;
+            sqlx::query_scalar("SELECT COUNT(*) FROM generator_tables WHERE project_id IS NULL")
+                .fetch_one(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to count generator tables: {}", e))
+                })?
+        };
+
+        if shared == 0 {
+            for table in generators::default_tables() {
+                self.save_table(&table).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Create or update a table
+    pub async fn save_table(&self, table: &GeneratorTable) -> DatabaseResult<()> {
+        table
+            .validate()
+            .map_err(|e| DatabaseError::ValidationError(e.to_string()))?;
+        let entries = serde_json::to_string(&table.entries)
+            .map_err(|e| DatabaseError::Service(format!("Failed to serialize entries: {}", e)))?;
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_GENERATOR_TABLE_SQL)
+            .bind(table.id.to_string())
+            .bind(table.project_id.map(|id| id.to_string()))
+            .bind(table.name.trim())
+            .bind(table.category.trim())
+            .bind(&table.description)
+            .bind(entries)
+            .bind(table.created_at.to_rfc3339())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to save generator table: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Delete a table
+    pub async fn delete_table(&self, table_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM generator_tables WHERE id = ?1")
+            .bind(table_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to delete generator table: {}", e))
+            })?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Shared tables and the project's own, shared first. Without a project
+    /// only the shared tables are returned.
+    pub async fn tables(&self, project_id: Option<Uuid>) -> DatabaseResult<Vec<GeneratorTable>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<GeneratorTableRow> = sqlx::query_as(GET_GENERATOR_TABLES_SQL)
+            .bind(project_id.map(|id| id.to_string()))
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to load generator tables: {}", e))
+            })?;
+        rows.into_iter().map(table_from_row).collect()
+    }
+
+    /// Roll a table `count` times with the given variables
+    pub async fn roll(
+        &self,
+        project_id: Option<Uuid>,
+        table: &str,
+        variables: &HashMap<String, String>,
+        count: usize,
+    ) -> DatabaseResult<Vec<String>> {
+        let tables = self.tables(project_id).await?;
+        generators::roll_many(&tables, table, variables, count, &mut rand::thread_rng())
+            .map_err(|e| DatabaseError::ValidationError(e.to_string()))
+    }
+}
+
+fn table_from_row(row: GeneratorTableRow) -> DatabaseResult<GeneratorTable> {
+    let (id, project_id, name, category, description, entries, created_at, updated_at) = row;
+    let parse_time = |value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| DatabaseError::Service(format!("Invalid timestamp: {}", e)))
+    };
+    Ok(GeneratorTable {
+        id: parse_uuid(&id)?,
+        project_id: project_id.as_deref().map(parse_uuid).transpose()?,
+        name,
+        category,
+        description,
+        entries: serde_json::from_str(&entries)
+            .map_err(|e| DatabaseError::Service(format!("Invalid generator entries: {}", e)))?,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_project_tables_override_shared_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let service = GeneratorService::new(Arc::new(RwLock::new(db)));
+        service.initialize().await.unwrap();
+        service.initialize().await.unwrap();
+        let shared = service.tables(None).await.unwrap();
+        assert_eq!(shared.len(), generators::default_tables().len());
+
+        let project = Uuid::new_v4();
+        let mut weather = GeneratorTable::new("weather", "Weather").with_entries(&["ash fall"]);
+        weather.project_id = Some(project);
+        service.save_table(&weather).await.unwrap();
+        let rolls = service
+            .roll(Some(project), "Weather", &HashMap::new(), 3)
+            .await
+            .unwrap();
+        assert_eq!(rolls, vec!["ash fall"; 3]);
+
+        let loot = service
+            .roll(None, "Loot", &HashMap::new(), 1)
+            .await
+            .unwrap();
+        assert!(!loot[0].contains('['));
+        assert!(service
+            .roll(None, "Missing", &HashMap::new(), 1)
+            .await
+            .is_err());
+
+        assert!(service.delete_table(weather.id).await.unwrap());
+        assert_ne!(
+            service
+                .roll(Some(project), "Weather", &HashMap::new(), 1)
+                .await
+                .unwrap(),
+            vec!["ash fall"]
+        );
+    }
+}
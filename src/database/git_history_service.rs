@@ -0,0 +1,679 @@
+//! Git History Service
+//!
+//! Keeps a git repository of a project's documents and commits to it on
+//! the author's behalf, running the `git` command so nothing beyond an
+//! installed git is needed. The repository is the project's Markdown
+//! mirror, synced before each commit, or an export of one file per
+//! document kept in the app's data folder. Restoring a commit sets the
+//! documents back through the normal document updates, so the app's own
+//! version history keeps what was replaced, and commits first so nothing
+//! since the last commit is lost.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::database::markdown_sync_service::{insert_document, load_documents, update_document};
+use crate::database::models::git_history::{
+    commit_message, parse_log, ChangeKind, CommitReason, FileChange, GitHistorySettings,
+    HistoryCommit, HistoryRestore, HistoryTarget, CREATE_GIT_HISTORY_TABLE_SQL, LOG_FORMAT,
+};
+use crate::database::models::markdown_sync::{parse_markdown, SyncDocument};
+use crate::database::models::TrashItemKind;
+use crate::database::parse::parse_uuid;
+use crate::database::prosemirror;
+use crate::database::prosemirror_markdown::from_markdown;
+use crate::database::{
+    DatabaseError, DatabaseResult, EnhancedDatabaseService, MarkdownSyncService,
+};
+
+type SettingsRow = (String, String, String, Option<String>);
+
+/// Identity the app commits as, so the author needn't configure git
+const GIT_IDENTITY: [&str; 4] = [
+    "-c",
+    "user.name=Herding Cats",
+    "-c",
+    "user.email=history@herdingcats.invalid",
+];
+
+/// Service for git-backed project history
+#[derive(Debug)]
+pub struct GitHistoryService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    markdown_sync: Arc<MarkdownSyncService>,
+    /// Parent of the internal repositories, one per project
+    history_dir: PathBuf,
+    /// Held while a repository is written so commits don't interleave
+    committing: Mutex<()>,
+}
+
+impl GitHistoryService {
+    /// Create a new git history service keeping internal repositories
+    /// under `history_dir`
+    pub fn new(
+        db_service: Arc<RwLock<EnhancedDatabaseService>>,
+        markdown_sync: Arc<MarkdownSyncService>,
+        history_dir: PathBuf,
+    ) -> Self {
+        Self {
+            db_service,
+            markdown_sync,
+            history_dir,
+            committing: Mutex::new(()),
+        }
+    }
+
+    /// Initialize the settings table
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_GIT_HISTORY_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create git history table: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Turn on history for a project and make its first commit. Keeping
+    /// history in the Markdown mirror needs the mirror set up first.
+    pub async fn enable(
+        &self,
+        project_id: Uuid,
+        target: HistoryTarget,
+    ) -> DatabaseResult<GitHistorySettings> {
+        if target == HistoryTarget::Mirror && self.markdown_sync.folder(project_id).await?.is_none()
+        {
+            return Err(DatabaseError::ValidationError(
+                "Set up a Markdown folder for the project first".to_string(),
+            ));
+        }
+        {
+            let db = self.db_service.read().await;
+            let live: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM projects WHERE id = ?1 AND deleted_at IS NULL",
+            )
+            .bind(project_id.to_string())
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load project: {}", e)))?;
+            if live == 0 {
+                return Err(DatabaseError::RecordNotFound {
+                    entity: "project".to_string(),
+                    id: project_id.to_string(),
+                });
+            }
+            sqlx::query(
+                "INSERT INTO git_history_settings (project_id, target, created_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(project_id) DO UPDATE SET target = excluded.target",
+            )
+            .bind(project_id.to_string())
+            .bind(target.as_str())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save git history: {}", e)))?;
+        }
+        self.commit(project_id, CommitReason::Milestone, Some("History started"))
+            .await?;
+        self.settings(project_id)
+            .await?
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "git history settings".to_string(),
+                id: project_id.to_string(),
+            })
+    }
+
+    /// Stop committing for a project; the repository is kept
+    pub async fn disable(&self, project_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM git_history_settings WHERE project_id = ?1")
+            .bind(project_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to remove git history: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A project's history settings; None when history is off
+    pub async fn settings(&self, project_id: Uuid) -> DatabaseResult<Option<GitHistorySettings>> {
+        let db = self.db_service.read().await;
+        let row: Option<SettingsRow> = sqlx::query_as(
+            "SELECT project_id, target, created_at, last_commit_at FROM git_history_settings WHERE project_id = ?1",
+        )
+        .bind(project_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load git history: {}", e)))?;
+        row.map(settings_from_row).transpose()
+    }
+
+    /// Commit whatever changed since the last commit; None when nothing did
+    pub async fn commit(
+        &self,
+        project_id: Uuid,
+        reason: CommitReason,
+        note: Option<&str>,
+    ) -> DatabaseResult<Option<HistoryCommit>> {
+        let settings = self.require_settings(project_id).await?;
+        let _committing = self.committing.lock().await;
+        let dir = self.prepare(&settings).await?;
+
+        git(&dir, &["add", "-A"]).await?;
+        let staged = git(
+            &dir,
+            &["diff", "--cached", "--name-status", "-M", "--no-color"],
+        )
+        .await?;
+        if staged.trim().is_empty() {
+            return Ok(None);
+        }
+        let has_head = git_succeeds(&dir, &["rev-parse", "--verify", "-q", "HEAD"]).await?;
+        let mut changes = Vec::new();
+        for line in staged.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let (kind, old, new) = match fields.as_slice() {
+                [status, path] if status.starts_with('A') => (ChangeKind::Added, None, Some(*path)),
+                [status, path] if status.starts_with('D') => {
+                    (ChangeKind::Removed, Some(*path), None)
+                }
+                [status, old, new] if status.starts_with('R') => {
+                    (ChangeKind::Renamed, Some(*old), Some(*new))
+                }
+                [_, path] => (ChangeKind::Edited, Some(*path), Some(*path)),
+                _ => continue,
+            };
+            let before = match old.filter(|_| has_head) {
+                Some(path) => git(&dir, &["show", &format!("HEAD:{}", path)]).await.ok(),
+                None => None,
+            };
+            let after = match new {
+                Some(path) => tokio::fs::read_to_string(dir.join(path)).await.ok(),
+                None => None,
+            };
+            let path = new.or(old).unwrap_or_default();
+            let stem = Path::new(path)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or(path);
+            let parsed = |text: &Option<String>| text.as_deref().map(|t| parse_markdown(t, stem).1);
+            let (before, after) = (parsed(&before), parsed(&after));
+            let words = |doc: &Option<SyncDocument>| {
+                doc.as_ref().map_or(0, |d| {
+                    let text = prosemirror::plain_text(&from_markdown(&d.content));
+                    text.split_whitespace().count() as i64
+                })
+            };
+            let title = after
+                .as_ref()
+                .or(before.as_ref())
+                .map(|d| d.title.clone())
+                .unwrap_or_else(|| stem.to_string());
+            // A rename here is a title or position change; a changed
+            // body as well counts as an edit
+            let kind = match (kind, &before, &after) {
+                (ChangeKind::Renamed, Some(b), Some(a)) if b.content != a.content => {
+                    ChangeKind::Edited
+                }
+                (kind, _, _) => kind,
+            };
+            changes.push(FileChange {
+                kind,
+                title,
+                word_delta: words(&after) - words(&before),
+            });
+        }
+
+        let message = commit_message(reason, note, &changes);
+        let mut args: Vec<&str> = GIT_IDENTITY.to_vec();
+        args.extend(["commit", "-q", "--no-verify", "-m", &message]);
+        git(&dir, &args).await?;
+        let commit = git(&dir, &["log", "-1", LOG_FORMAT, "--name-only"]).await?;
+        let commit = parse_log(&commit).into_iter().next();
+
+        let db = self.db_service.read().await;
+        sqlx::query("UPDATE git_history_settings SET last_commit_at = ?1 WHERE project_id = ?2")
+            .bind(Utc::now().to_rfc3339())
+            .bind(project_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to record commit: {}", e)))?;
+        Ok(commit)
+    }
+
+    /// Commit for a project if it has history turned on
+    pub async fn commit_if_enabled(
+        &self,
+        project_id: Uuid,
+        reason: CommitReason,
+    ) -> DatabaseResult<Option<HistoryCommit>> {
+        if self.settings(project_id).await?.is_none() {
+            return Ok(None);
+        }
+        self.commit(project_id, reason, None).await
+    }
+
+    /// Commit every project with history turned on, logging failures
+    pub async fn commit_all(&self, reason: CommitReason) -> DatabaseResult<usize> {
+        let projects: Vec<String> = {
+            let db = self.db_service.read().await;
+            sqlx::query_scalar(
+                "SELECT h.project_id FROM git_history_settings h JOIN projects p ON p.id = h.project_id
+                 WHERE p.deleted_at IS NULL",
+            )
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load git history: {}", e)))?
+        };
+        let mut committed = 0;
+        for project_id in projects.iter().filter_map(|id| Uuid::parse_str(id).ok()) {
+            match self.commit(project_id, reason, None).await {
+                Ok(Some(_)) => committed += 1,
+                Ok(None) => {}
+                Err(e) => log::error!("History commit for project {} failed: {}", project_id, e),
+            }
+        }
+        Ok(committed)
+    }
+
+    /// Commit saved work every `every`
+    pub fn spawn_auto_commit(
+        self: Arc<Self>,
+        every: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            // The first tick is immediate; there's nothing new at startup
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.commit_all(CommitReason::Saves).await {
+                    log::error!("History commits failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// A project's commits, newest first; only those touching a document
+    /// when one is given
+    pub async fn log(
+        &self,
+        project_id: Uuid,
+        document_id: Option<&str>,
+        limit: usize,
+    ) -> DatabaseResult<Vec<HistoryCommit>> {
+        let settings = self.require_settings(project_id).await?;
+        let dir = self.repository_dir(&settings).await?;
+        if !git_succeeds(&dir, &["rev-parse", "--verify", "-q", "HEAD"]).await? {
+            return Ok(Vec::new());
+        }
+        let limit = format!("-n{}", limit.max(1));
+        let mut args = vec!["log", LOG_FORMAT, "--name-only", &limit];
+        let path;
+        if let Some(document_id) = document_id {
+            path = self.document_path(&settings, document_id).await?;
+            args.extend(["--follow", "--", &path]);
+        }
+        Ok(parse_log(&git(&dir, &args).await?))
+    }
+
+    /// Set a project, or one document, back to how it was at a commit.
+    /// Documents added since are moved to the trash when restoring the
+    /// whole project.
+    pub async fn restore(
+        &self,
+        project_id: Uuid,
+        commit: &str,
+        document_id: Option<&str>,
+    ) -> DatabaseResult<HistoryRestore> {
+        let settings = self.require_settings(project_id).await?;
+        let dir = self.repository_dir(&settings).await?;
+        if commit.starts_with('-') {
+            return Err(DatabaseError::ValidationError(format!(
+                "Unknown commit {}",
+                commit
+            )));
+        }
+        let commit = git(
+            &dir,
+            &[
+                "rev-parse",
+                "--verify",
+                "-q",
+                &format!("{}^{{commit}}", commit),
+            ],
+        )
+        .await
+        .map_err(|_| DatabaseError::ValidationError(format!("Unknown commit {}", commit)))?
+        .trim()
+        .to_string();
+        self.commit(project_id, CommitReason::BeforeRestore, None)
+            .await?;
+
+        // The documents as they were
+        let listing = git(&dir, &["ls-tree", "-r", "--name-only", &commit]).await?;
+        let mut then = Vec::new();
+        for path in listing.lines().filter(|p| p.ends_with(".md")) {
+            let text = git(&dir, &["show", &format!("{}:{}", commit, path)]).await?;
+            let stem = path.trim_end_matches(".md");
+            if let (Some(id), document) = parse_markdown(&text, stem) {
+                if document_id.is_none_or(|wanted| wanted == id) {
+                    then.push(document);
+                }
+            }
+        }
+        if let Some(document_id) = document_id {
+            if then.is_empty() {
+                return Err(DatabaseError::RecordNotFound {
+                    entity: "document in commit".to_string(),
+                    id: document_id.to_string(),
+                });
+            }
+        }
+
+        let mut restore = HistoryRestore::default();
+        {
+            let db = self.db_service.read().await;
+            let now = load_documents(&db, project_id).await?;
+            for document in &then {
+                let current = match now.iter().find(|d| d.id == document.id) {
+                    Some(current) => Some(current.clone()),
+                    None => match db
+                        .restore_from_trash(TrashItemKind::Document, &document.id)
+                        .await
+                    {
+                        Ok(()) => load_documents(&db, project_id)
+                            .await?
+                            .into_iter()
+                            .find(|d| d.id == document.id),
+                        // Purged since: make it again
+                        Err(DatabaseError::RecordNotFound { .. }) => None,
+                        Err(e) => return Err(e),
+                    },
+                };
+                match current {
+                    Some(current) if current == *document => continue,
+                    Some(current) => {
+                        update_document(&db, &current, document).await?;
+                    }
+                    None => {
+                        insert_document(&db, project_id, document).await?;
+                    }
+                }
+                restore.documents_restored.push(document.id.clone());
+            }
+            if document_id.is_none() {
+                let kept: HashSet<&str> = then.iter().map(|d| d.id.as_str()).collect();
+                for later in now.iter().filter(|d| !kept.contains(d.id.as_str())) {
+                    db.delete_document(later.id.clone()).await?;
+                    restore.documents_trashed.push(later.id.clone());
+                }
+            }
+        }
+
+        let short = &commit[..commit.len().min(7)];
+        let note = match document_id {
+            Some(_) => format!("document from {}", short),
+            None => format!("project to {}", short),
+        };
+        restore.commit = self
+            .commit(project_id, CommitReason::Restore, Some(&note))
+            .await?;
+        Ok(restore)
+    }
+
+    async fn require_settings(&self, project_id: Uuid) -> DatabaseResult<GitHistorySettings> {
+        self.settings(project_id)
+            .await?
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "git history".to_string(),
+                id: project_id.to_string(),
+            })
+    }
+
+    /// Where a project's repository is
+    async fn repository_dir(&self, settings: &GitHistorySettings) -> DatabaseResult<PathBuf> {
+        match settings.target {
+            HistoryTarget::Internal => Ok(self.history_dir.join(settings.project_id.to_string())),
+            HistoryTarget::Mirror => self
+                .markdown_sync
+                .folder(settings.project_id)
+                .await?
+                .map(|folder| folder.folder)
+                .ok_or_else(|| {
+                    DatabaseError::ValidationError(
+                        "The project's Markdown folder is no longer set up".to_string(),
+                    )
+                }),
+        }
+    }
+
+    /// Bring the repository up to date with the project, creating it if
+    /// needed, ready to commit
+    async fn prepare(&self, settings: &GitHistorySettings) -> DatabaseResult<PathBuf> {
+        let dir = self.repository_dir(settings).await?;
+        tokio::fs::create_dir_all(&dir).await.map_err(|e| {
+            DatabaseError::Service(format!("Failed to create history folder: {}", e))
+        })?;
+        if !tokio::fs::try_exists(dir.join(".git"))
+            .await
+            .unwrap_or(false)
+        {
+            git(&dir, &["-c", "init.defaultBranch=main", "init", "-q"]).await?;
+        }
+
+        match settings.target {
+            HistoryTarget::Mirror => {
+                let report = self.markdown_sync.sync(settings.project_id).await?;
+                if !report.conflicts.is_empty() {
+                    log::warn!(
+                        "Committing project {} with {} unresolved sync conflicts",
+                        settings.project_id,
+                        report.conflicts.len()
+                    );
+                }
+            }
+            HistoryTarget::Internal => {
+                let db = self.db_service.read().await;
+                let documents = load_documents(&db, settings.project_id).await?;
+                let names: HashSet<String> =
+                    documents.iter().map(|d| format!("{}.md", d.id)).collect();
+                let failed = |e: std::io::Error| {
+                    DatabaseError::Service(format!("Failed to export history: {}", e))
+                };
+                let mut entries = tokio::fs::read_dir(&dir).await.map_err(failed)?;
+                while let Some(entry) = entries.next_entry().await.map_err(failed)? {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if name.ends_with(".md") && !names.contains(&name) {
+                        tokio::fs::remove_file(entry.path()).await.map_err(failed)?;
+                    }
+                }
+                for document in &documents {
+                    let path = dir.join(format!("{}.md", document.id));
+                    let rendered = document.render();
+                    if tokio::fs::read_to_string(&path).await.ok().as_deref() != Some(&rendered) {
+                        tokio::fs::write(&path, rendered).await.map_err(failed)?;
+                    }
+                }
+            }
+        }
+        Ok(dir)
+    }
+
+    /// The file a document is kept in
+    async fn document_path(
+        &self,
+        settings: &GitHistorySettings,
+        document_id: &str,
+    ) -> DatabaseResult<String> {
+        match settings.target {
+            HistoryTarget::Internal => Ok(format!("{}.md", document_id)),
+            HistoryTarget::Mirror => {
+                let db = self.db_service.read().await;
+                sqlx::query_scalar(
+                    "SELECT file_name FROM markdown_sync_files WHERE project_id = ?1 AND document_id = ?2",
+                )
+                .bind(settings.project_id.to_string())
+                .bind(document_id)
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to load sync state: {}", e)))?
+                .ok_or_else(|| DatabaseError::RecordNotFound {
+                    entity: "synced document".to_string(),
+                    id: document_id.to_string(),
+                })
+            }
+        }
+    }
+}
+
+/// Run git in a repository, returning what it printed
+async fn git(dir: &Path, args: &[&str]) -> DatabaseResult<String> {
+    let output = tokio::process::Command::new("git")
+        .args(["-c", "core.quotepath=off", "-c", "commit.gpgsign=false"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                DatabaseError::Service("Project history needs git to be installed".to_string())
+            }
+            _ => DatabaseError::Service(format!("Failed to run git: {}", e)),
+        })?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(DatabaseError::Service(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// Whether a git check passes
+async fn git_succeeds(dir: &Path, args: &[&str]) -> DatabaseResult<bool> {
+    Ok(git(dir, args).await.is_ok())
+}
+
+fn settings_from_row(row: SettingsRow) -> DatabaseResult<GitHistorySettings> {
+    let (project_id, target, created_at, last_commit_at) = row;
+    let parse_time = |value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| DatabaseError::Service(format!("Invalid timestamp: {}", e)))
+    };
+    Ok(GitHistorySettings {
+        project_id: parse_uuid(&project_id)?,
+        target: HistoryTarget::parse(&target).unwrap_or_default(),
+        created_at: parse_time(&created_at)?,
+        last_commit_at: last_commit_at.as_deref().map(parse_time).transpose()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_commit_log_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Ashfall', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        db.create_document(
+            "harbor".to_string(),
+            project.to_string(),
+            "The Harbor".to_string(),
+            "Grey water.".to_string(),
+        )
+        .await
+        .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let markdown_sync = Arc::new(MarkdownSyncService::new(db.clone()));
+        markdown_sync.initialize().await.unwrap();
+        let service = GitHistoryService::new(db.clone(), markdown_sync, dir.path().join("history"));
+        service.initialize().await.unwrap();
+
+        service
+            .enable(project, HistoryTarget::Internal)
+            .await
+            .unwrap();
+        assert_eq!(
+            service
+                .commit(project, CommitReason::Saves, None)
+                .await
+                .unwrap(),
+            None
+        );
+
+        db.read()
+            .await
+            .update_document_content("harbor", "Grey water, black sky, no boats.")
+            .await
+            .unwrap();
+        db.read()
+            .await
+            .create_document(
+                "lantern".to_string(),
+                project.to_string(),
+                "The Lantern".to_string(),
+                "It glowed.".to_string(),
+            )
+            .await
+            .unwrap();
+        let commit = service
+            .commit(project, CommitReason::SessionEnd, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            commit.subject,
+            "Writing session: Edited \"The Harbor\"; added \"The Lantern\" (+6 words)"
+        );
+
+        let log = service.log(project, None, 10).await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(
+            service
+                .log(project, Some("lantern"), 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let restore = service.restore(project, &log[1].id, None).await.unwrap();
+        assert_eq!(restore.documents_restored, ["harbor"]);
+        assert_eq!(restore.documents_trashed, ["lantern"]);
+        assert!(restore
+            .commit
+            .unwrap()
+            .subject
+            .starts_with("Restored (project to "));
+        let db = db.read().await;
+        let harbor = db
+            .get_document("harbor".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(prosemirror::document_text("json", &harbor), "Grey water.");
+        assert_eq!(db.get_document("lantern".to_string()).await.unwrap(), None);
+    }
+}
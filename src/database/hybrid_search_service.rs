@@ -0,0 +1,364 @@
+//! Hybrid Search Service
+//!
+//! Runs a query through the full-text index and the embeddings and merges
+//! the two rankings, see `models::hybrid_search`. Keyword search finds the
+//! exact names and phrases a writer remembers; semantic search finds the
+//! scene they can only describe. The fusion functions are shared with Ask
+//! Project, which ranks passages the same way.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::models::hybrid_search::{
+    FusionMethod, HybridSearchRequest, HybridSearchResult, SourceScore,
+};
+use crate::database::search_service::SearchOptions;
+use crate::database::vector_embedding::SearchOptions as SemanticSearchOptions;
+use crate::database::{
+    DatabaseError, DatabaseResult, EnhancedDatabaseService, SearchService, VectorEmbeddingService,
+};
+
+/// Each source contributes up to this many times `limit` candidates
+const CANDIDATE_FACTOR: usize = 4;
+
+/// Reciprocal rank fusion of score lists over the same items. Each list
+/// ranks the items it scored above zero; an item gets `1 / (k + rank)`
+/// from every list it is ranked in, with ranks counted from 1.
+pub fn reciprocal_rank_fusion(score_lists: &[&[f32]], k: f32) -> Vec<f32> {
+    let len = score_lists
+        .iter()
+        .map(|scores| scores.len())
+        .max()
+        .unwrap_or(0);
+    let mut fused = vec![0.0f32; len];
+    for scores in score_lists {
+        let mut order: Vec<usize> = (0..scores.len()).filter(|i| scores[*i] > 0.0).collect();
+        order.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+        for (rank, index) in order.into_iter().enumerate() {
+            fused[index] += 1.0 / (k + rank as f32 + 1.0);
+        }
+    }
+    fused
+}
+
+/// Weighted sum of score lists over the same items, each list first scaled
+/// so its highest score is 1.0
+pub fn weighted_sum(score_lists: &[&[f32]], weights: &[f32]) -> Vec<f32> {
+    let len = score_lists
+        .iter()
+        .map(|scores| scores.len())
+        .max()
+        .unwrap_or(0);
+    let mut fused = vec![0.0f32; len];
+    for (scores, weight) in score_lists.iter().zip(weights) {
+        let max = scores.iter().copied().fold(0.0f32, f32::max);
+        if max <= 0.0 {
+            continue;
+        }
+        for (index, score) in scores.iter().enumerate() {
+            fused[index] += weight * score.max(0.0) / max;
+        }
+    }
+    fused
+}
+
+/// Merge score lists with `method`; the first list is the keyword one
+pub fn fuse(method: FusionMethod, keyword: &[f32], semantic: &[f32]) -> Vec<f32> {
+    match method {
+        FusionMethod::Rrf { k } => reciprocal_rank_fusion(&[keyword, semantic], k),
+        FusionMethod::WeightedSum {
+            keyword_weight,
+            semantic_weight,
+        } => weighted_sum(&[keyword, semantic], &[keyword_weight, semantic_weight]),
+    }
+}
+
+/// A document as one source found it
+struct Candidate {
+    document_id: Uuid,
+    title: String,
+    snippet: String,
+    score: f32,
+}
+
+/// Service combining keyword and semantic search
+#[derive(Debug)]
+pub struct HybridSearchService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    search: Arc<SearchService>,
+    embeddings: Arc<VectorEmbeddingService>,
+}
+
+impl HybridSearchService {
+    pub fn new(
+        db_service: Arc<RwLock<EnhancedDatabaseService>>,
+        search: Arc<SearchService>,
+        embeddings: Arc<VectorEmbeddingService>,
+    ) -> Self {
+        Self {
+            db_service,
+            search,
+            embeddings,
+        }
+    }
+
+    /// Documents matching the query by keyword or meaning, best first
+    pub async fn search(
+        &self,
+        request: &HybridSearchRequest,
+    ) -> DatabaseResult<Vec<HybridSearchResult>> {
+        if request.query.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Search query is empty".to_string(),
+            ));
+        }
+        let candidates = request.limit.max(1) * CANDIDATE_FACTOR;
+        let keyword = self.keyword_candidates(request, candidates).await?;
+        let semantic = self.semantic_candidates(request, candidates).await?;
+
+        // Line both rankings up over the union of their documents
+        let mut index: HashMap<Uuid, usize> = HashMap::new();
+        let mut documents: Vec<&Candidate> = Vec::new();
+        for candidate in keyword.iter().chain(&semantic) {
+            index.entry(candidate.document_id).or_insert_with(|| {
+                documents.push(candidate);
+                documents.len() - 1
+            });
+        }
+        let mut keyword_scores = vec![0.0f32; documents.len()];
+        let mut semantic_scores = vec![0.0f32; documents.len()];
+        let mut keyword_ranks = vec![None; documents.len()];
+        let mut semantic_ranks = vec![None; documents.len()];
+        for (rank, candidate) in keyword.iter().enumerate() {
+            let i = index[&candidate.document_id];
+            keyword_scores[i] = candidate.score;
+            keyword_ranks[i] = Some(rank + 1);
+        }
+        for (rank, candidate) in semantic.iter().enumerate() {
+            let i = index[&candidate.document_id];
+            semantic_scores[i] = candidate.score;
+            semantic_ranks[i] = Some(rank + 1);
+        }
+
+        let fused = fuse(request.fusion, &keyword_scores, &semantic_scores);
+        let mut order: Vec<usize> = (0..documents.len()).collect();
+        order.sort_by(|a, b| fused[*b].total_cmp(&fused[*a]));
+        order.truncate(request.limit);
+
+        Ok(order
+            .into_iter()
+            .map(|i| {
+                let source =
+                    |rank: Option<usize>, score: f32| rank.map(|rank| SourceScore { score, rank });
+                HybridSearchResult {
+                    document_id: documents[i].document_id,
+                    title: documents[i].title.clone(),
+                    snippet: documents[i].snippet.clone(),
+                    score: fused[i],
+                    keyword: source(keyword_ranks[i], keyword_scores[i]),
+                    semantic: source(semantic_ranks[i], semantic_scores[i]),
+                }
+            })
+            .collect())
+    }
+
+    /// Full-text matches, best first
+    async fn keyword_candidates(
+        &self,
+        request: &HybridSearchRequest,
+        limit: usize,
+    ) -> DatabaseResult<Vec<Candidate>> {
+        let options = SearchOptions {
+            limit,
+            project_filter: request.project_id,
+            highlight_matches: true,
+            ..Default::default()
+        };
+        let results = self
+            .search
+            .search_documents_advanced(&request.query, Some(options))
+            .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| Candidate {
+                document_id: result.document_id,
+                title: result.title,
+                snippet: result.snippet,
+                score: result.relevance_score.max(f32::MIN_POSITIVE),
+            })
+            .collect())
+    }
+
+    /// Documents with a passage close to the query, by their best passage
+    async fn semantic_candidates(
+        &self,
+        request: &HybridSearchRequest,
+        limit: usize,
+    ) -> DatabaseResult<Vec<Candidate>> {
+        let options = SemanticSearchOptions {
+            // Passages come back per chunk; documents are cut to `limit`
+            // below, once each has its best passage
+            limit: usize::MAX,
+            similarity_threshold: request.min_similarity,
+            include_metadata: false,
+            model_filter: request.model.clone(),
+            document_filter: None,
+        };
+        let passages = self
+            .embeddings
+            .find_similar_documents(&request.query, Some(options))
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Semantic search failed: {}", e)))?;
+
+        let in_project = match request.project_id {
+            Some(project_id) => Some(self.project_documents(project_id).await?),
+            None => None,
+        };
+        let mut seen = HashSet::new();
+        Ok(passages
+            .into_iter()
+            .filter(|passage| {
+                in_project
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&passage.document_id))
+            })
+            // Passages are sorted, so a document's first is its best
+            .filter(|passage| seen.insert(passage.document_id))
+            .take(limit)
+            .map(|passage| Candidate {
+                document_id: passage.document_id,
+                title: passage.title,
+                snippet: passage.snippet,
+                score: passage.similarity_score,
+            })
+            .collect())
+    }
+
+    async fn project_documents(&self, project_id: Uuid) -> DatabaseResult<HashSet<Uuid>> {
+        let db = self.db_service.read().await;
+        let ids: Vec<String> =
+            sqlx::query_scalar("SELECT id FROM documents WHERE project_id = ?1 AND is_active = 1")
+                .bind(project_id.to_string())
+                .fetch_all(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to list project documents: {}", e))
+                })?;
+        Ok(ids
+            .iter()
+            .filter_map(|id| Uuid::parse_str(id).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::hybrid_search::default_rrf_k;
+    use crate::database::DatabaseConfig;
+    use chrono::Utc;
+
+    #[test]
+    fn test_fusion_methods() {
+        // Item 0 leads on keywords, item 2 on meaning, item 1 is second on both
+        let keyword = [9.0, 5.0, 0.0];
+        let semantic = [0.1, 0.6, 0.8];
+
+        let rrf = fuse(FusionMethod::default(), &keyword, &semantic);
+        let k = default_rrf_k();
+        assert!((rrf[0] - (1.0 / (k + 1.0) + 1.0 / (k + 3.0))).abs() < 1e-6);
+        assert!((rrf[2] - 1.0 / (k + 1.0)).abs() < 1e-6);
+        assert!(rrf[0] > rrf[1] && rrf[1] > rrf[2]);
+
+        let keyword_heavy = FusionMethod::WeightedSum {
+            keyword_weight: 0.8,
+            semantic_weight: 0.2,
+        };
+        let weighted = fuse(keyword_heavy, &keyword, &semantic);
+        assert!((weighted[0] - (0.8 + 0.2 * 0.125)).abs() < 1e-6);
+        assert!((weighted[2] - 0.2).abs() < 1e-6);
+        assert!(weighted[0] > weighted[1] && weighted[1] > weighted[2]);
+    }
+
+    #[tokio::test]
+    async fn test_merges_keyword_and_semantic_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let (project, other) = (Uuid::new_v4(), Uuid::new_v4());
+        for id in [project, other] {
+            sqlx::query(
+                "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Novel', ?2, ?2)",
+            )
+            .bind(id.to_string())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+        let (lighthouse, storm, elsewhere) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        for (id, project_id, title, content) in [
+            (
+                lighthouse,
+                project,
+                "Lighthouse",
+                "Mara climbed the lighthouse stairs at dusk.",
+            ),
+            (
+                storm,
+                project,
+                "Storm",
+                "The storm broke over the harbour and the boats.",
+            ),
+            (
+                elsewhere,
+                other,
+                "Elsewhere",
+                "A lighthouse in another book entirely.",
+            ),
+        ] {
+            db.create_document(
+                id.to_string(),
+                project_id.to_string(),
+                title.to_string(),
+                content.to_string(),
+            )
+            .await
+            .unwrap();
+        }
+        let db = Arc::new(RwLock::new(db));
+        let search = Arc::new(SearchService::new(Arc::clone(&db)));
+        search.initialize().await.unwrap();
+        let embeddings = Arc::new(VectorEmbeddingService::new(Arc::clone(&db)));
+        embeddings.initialize().await.unwrap();
+        for id in [lighthouse, storm, elsewhere] {
+            embeddings
+                .generate_document_embeddings(&id, None)
+                .await
+                .unwrap();
+        }
+        let service = HybridSearchService::new(db, search, embeddings);
+
+        let mut request = HybridSearchRequest::new("storm over the harbour");
+        request.project_id = Some(project);
+        let results = service.search(&request).await.unwrap();
+        assert_eq!(results[0].document_id, storm);
+        assert_eq!(results[0].keyword.unwrap().rank, 1);
+        assert_eq!(results[0].semantic.unwrap().rank, 1);
+        assert!(results.iter().all(|r| r.document_id != elsewhere));
+
+        request.query = "lighthouse".to_string();
+        request.project_id = None;
+        let results = service.search(&request).await.unwrap();
+        let found: HashSet<Uuid> = results.iter().map(|r| r.document_id).collect();
+        assert!(found.contains(&lighthouse) && found.contains(&elsewhere));
+        assert!(results.iter().all(|r| r.score > 0.0));
+
+        request.query = "  ".to_string();
+        assert!(service.search(&request).await.is_err());
+    }
+}
@@ -0,0 +1,438 @@
+//! Journal Service
+//!
+//! Keeps each writer's daily journal: finds or creates the day's page in
+//! their journal project from their chosen template, appends quick notes
+//! to it (for the tray and the global hotkey), and works out journaling
+//! streaks. Pages carry the writer's word count and AI context choices in
+//! their document metadata.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::database::models::journal::{
+    journal_streak, owner_key, JournalEntry, JournalSettings, JournalStreak,
+    CREATE_JOURNAL_TABLES_SQL, EXCLUDE_FROM_AI_CONTEXT_KEY, EXCLUDE_FROM_WORD_COUNT_KEY,
+    GET_JOURNAL_ENTRIES_SQL, JOURNAL_DAY_KEY, UPSERT_JOURNAL_SETTINGS_SQL,
+};
+use crate::database::parse::parse_uuid;
+use crate::database::{
+    DatabaseError, DatabaseResult, DocumentTemplateService, EnhancedDatabaseService,
+};
+
+type SettingsRow = (String, String, Option<String>, bool, bool, String);
+type EntryRow = (String, String, String, String, i64, bool);
+
+/// Service for daily journals
+#[derive(Debug)]
+pub struct JournalService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    /// Held while finding or creating a page, so two quick appends at once
+    /// don't make two pages for the day
+    creating: Mutex<()>,
+}
+
+impl JournalService {
+    /// Create a new journal service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self {
+            db_service,
+            creating: Mutex::new(()),
+        }
+    }
+
+    /// Initialize the journal tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_JOURNAL_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create journal tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// A writer's journal settings, if they have set up a journal
+    pub async fn settings(
+        &self,
+        profile_id: Option<Uuid>,
+    ) -> DatabaseResult<Option<JournalSettings>> {
+        let db = self.db_service.read().await;
+        let row: Option<SettingsRow> = sqlx::query_as(
+            "SELECT owner, project_id, template_id, exclude_from_word_counts, exclude_from_ai_context, updated_at
+             FROM journal_settings WHERE owner = ?1",
+        )
+        .bind(owner_key(profile_id))
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load journal settings: {}", e)))?;
+        row.map(settings_from_row).transpose()
+    }
+
+    /// Save a writer's journal settings. Their existing pages pick up the
+    /// word count and AI context choices.
+    pub async fn save_settings(&self, settings: &JournalSettings) -> DatabaseResult<()> {
+        if let Some(template_id) = settings.template_id {
+            DocumentTemplateService::new(self.db_service.clone())
+                .template(template_id)
+                .await?;
+        }
+        let owner = owner_key(settings.profile_id);
+        let pages: Vec<String> = {
+            let db = self.db_service.read().await;
+            let live: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM projects WHERE id = ?1 AND deleted_at IS NULL",
+            )
+            .bind(settings.project_id.to_string())
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load project: {}", e)))?;
+            if live == 0 {
+                return Err(DatabaseError::RecordNotFound {
+                    entity: "project".to_string(),
+                    id: settings.project_id.to_string(),
+                });
+            }
+
+            sqlx::query(UPSERT_JOURNAL_SETTINGS_SQL)
+                .bind(&owner)
+                .bind(settings.project_id.to_string())
+                .bind(settings.template_id.map(|id| id.to_string()))
+                .bind(settings.exclude_from_word_counts)
+                .bind(settings.exclude_from_ai_context)
+                .bind(Utc::now().to_rfc3339())
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to save journal settings: {}", e))
+                })?;
+            sqlx::query_scalar("SELECT document_id FROM journal_entries WHERE owner = ?1")
+                .bind(&owner)
+                .fetch_all(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to load journal pages: {}", e))
+                })?
+        };
+        for document_id in pages {
+            self.mark_page(&document_id, settings, None).await?;
+        }
+        Ok(())
+    }
+
+    /// The writer's page for `day`, created from their template if there
+    /// isn't one yet (or the last one went to the trash)
+    pub async fn entry(
+        &self,
+        profile_id: Option<Uuid>,
+        day: NaiveDate,
+    ) -> DatabaseResult<JournalEntry> {
+        let _creating = self.creating.lock().await;
+        if let Some(entry) = self.entries(profile_id, Some(day), Some(day)).await?.pop() {
+            return Ok(entry);
+        }
+        let settings = self.settings(profile_id).await?.ok_or_else(|| {
+            DatabaseError::ValidationError(
+                "Choose a project for the journal before writing in it".to_string(),
+            )
+        })?;
+
+        let document_id = match settings.template_id {
+            Some(template_id) => {
+                DocumentTemplateService::new(self.db_service.clone())
+                    .create_document_on(template_id, settings.project_id, &HashMap::new(), day)
+                    .await?
+            }
+            None => {
+                let db = self.db_service.read().await;
+                db.create_document(
+                    Uuid::new_v4().to_string(),
+                    settings.project_id.to_string(),
+                    format!("Journal {}", day.format("%Y-%m-%d")),
+                    String::new(),
+                )
+                .await?
+            }
+        };
+        self.mark_page(&document_id, &settings, Some(day)).await?;
+        {
+            let db = self.db_service.read().await;
+            sqlx::query(
+                "INSERT INTO journal_entries (owner, day, document_id, starting_checksum, created_at)
+                 SELECT ?1, ?2, id, checksum, ?4 FROM documents WHERE id = ?3
+                 ON CONFLICT(owner, day) DO UPDATE SET document_id = excluded.document_id,
+                     starting_checksum = excluded.starting_checksum, created_at = excluded.created_at",
+            )
+            .bind(owner_key(profile_id))
+            .bind(day.to_string())
+            .bind(&document_id)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to record journal page: {}", e)))?;
+        }
+
+        let mut entry = self
+            .entries(profile_id, Some(day), Some(day))
+            .await?
+            .pop()
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "journal page".to_string(),
+                id: document_id,
+            })?;
+        entry.created = true;
+        Ok(entry)
+    }
+
+    /// Add text to the end of the writer's page for `day`, creating the
+    /// page if needed
+    pub async fn append(
+        &self,
+        profile_id: Option<Uuid>,
+        day: NaiveDate,
+        text: &str,
+    ) -> DatabaseResult<JournalEntry> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Nothing to add to the journal".to_string(),
+            ));
+        }
+        let entry = self.entry(profile_id, day).await?;
+        {
+            let db = self.db_service.read().await;
+            let mut content = db
+                .get_document(entry.document_id.clone())
+                .await?
+                .unwrap_or_default();
+            let kept = content.trim_end().len();
+            content.truncate(kept);
+            if !content.is_empty() {
+                content.push_str("\n\n");
+            }
+            content.push_str(text);
+            content.push('\n');
+            db.update_document_content(&entry.document_id, &content)
+                .await?;
+        }
+
+        let mut updated = self
+            .entries(profile_id, Some(day), Some(day))
+            .await?
+            .pop()
+            .unwrap_or_else(|| entry.clone());
+        updated.created = entry.created;
+        Ok(updated)
+    }
+
+    /// The writer's pages from `from` to `to` (either open-ended), oldest
+    /// first
+    pub async fn entries(
+        &self,
+        profile_id: Option<Uuid>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> DatabaseResult<Vec<JournalEntry>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<EntryRow> = sqlx::query_as(GET_JOURNAL_ENTRIES_SQL)
+            .bind(owner_key(profile_id))
+            .bind(from.map(|d| d.to_string()))
+            .bind(to.map(|d| d.to_string()))
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load journal: {}", e)))?;
+        rows.into_iter()
+            .map(
+                |(day, project_id, document_id, title, word_count, written)| {
+                    Ok(JournalEntry {
+                        profile_id,
+                        day: parse_day(&day)?,
+                        project_id: parse_uuid(&project_id)?,
+                        document_id,
+                        title,
+                        word_count,
+                        written,
+                        created: false,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    /// The writer's journaling streaks as of `today`; a day counts once
+    /// something has been written on its page
+    pub async fn streak(
+        &self,
+        profile_id: Option<Uuid>,
+        today: NaiveDate,
+    ) -> DatabaseResult<JournalStreak> {
+        let days: Vec<NaiveDate> = self
+            .entries(profile_id, None, Some(today))
+            .await?
+            .into_iter()
+            .filter(|entry| entry.written)
+            .map(|entry| entry.day)
+            .collect();
+        Ok(journal_streak(&days, today))
+    }
+
+    /// Record the journal markers in a page's document metadata, keeping
+    /// whatever else is there
+    async fn mark_page(
+        &self,
+        document_id: &str,
+        settings: &JournalSettings,
+        day: Option<NaiveDate>,
+    ) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        let metadata: Option<Option<String>> =
+            sqlx::query_scalar("SELECT metadata FROM documents WHERE id = ?1")
+                .bind(document_id)
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to load document: {}", e)))?;
+        let Some(metadata) = metadata else {
+            return Ok(());
+        };
+
+        let mut object = match metadata.and_then(|m| serde_json::from_str(&m).ok()) {
+            Some(serde_json::Value::Object(object)) => object,
+            _ => serde_json::Map::new(),
+        };
+        if let Some(day) = day {
+            object.insert(JOURNAL_DAY_KEY.to_string(), day.to_string().into());
+        }
+        object.insert(
+            EXCLUDE_FROM_WORD_COUNT_KEY.to_string(),
+            settings.exclude_from_word_counts.into(),
+        );
+        object.insert(
+            EXCLUDE_FROM_AI_CONTEXT_KEY.to_string(),
+            settings.exclude_from_ai_context.into(),
+        );
+        sqlx::query("UPDATE documents SET metadata = ?1 WHERE id = ?2")
+            .bind(serde_json::Value::Object(object).to_string())
+            .bind(document_id)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to mark journal page: {}", e)))?;
+        Ok(())
+    }
+}
+
+fn parse_day(value: &str) -> DatabaseResult<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| DatabaseError::Service(format!("Invalid journal day: {}", e)))
+}
+
+fn settings_from_row(row: SettingsRow) -> DatabaseResult<JournalSettings> {
+    let (
+        owner,
+        project_id,
+        template_id,
+        exclude_from_word_counts,
+        exclude_from_ai_context,
+        updated_at,
+    ) = row;
+    Ok(JournalSettings {
+        profile_id: Some(owner.as_str())
+            .filter(|owner| !owner.is_empty())
+            .map(parse_uuid)
+            .transpose()?,
+        project_id: parse_uuid(&project_id)?,
+        template_id: template_id.as_deref().map(parse_uuid).transpose()?,
+        exclude_from_word_counts,
+        exclude_from_ai_context,
+        updated_at: DateTime::parse_from_rfc3339(&updated_at)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| DatabaseError::Service(format!("Invalid timestamp: {}", e)))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::document_template::DocumentTemplate;
+    use crate::database::{DatabaseConfig, StatsService};
+
+    #[tokio::test]
+    async fn test_daily_pages_and_quick_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Ashfall', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        db.create_document(
+            Uuid::new_v4().to_string(),
+            project.to_string(),
+            "Chapter 1".to_string(),
+            "The ash fell all night".to_string(),
+        )
+        .await
+        .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let templates = DocumentTemplateService::new(db.clone());
+        templates.initialize().await.unwrap();
+        let template = DocumentTemplate::new("Pages", "Journal", "Pages {{date}}", "# {{date}}");
+        templates.save_template(&template).await.unwrap();
+        let service = JournalService::new(db.clone());
+        service.initialize().await.unwrap();
+
+        let day = |d: u32| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+        assert!(matches!(
+            service.append(None, day(1), "too soon").await,
+            Err(DatabaseError::ValidationError(_))
+        ));
+        let mut settings = JournalSettings::new(None, project);
+        settings.template_id = Some(template.id);
+        service.save_settings(&settings).await.unwrap();
+
+        let first = service.append(None, day(1), "Slept badly.").await.unwrap();
+        assert!(first.created);
+        assert_eq!(first.title, "Pages 2026-03-01");
+        let again = service
+            .append(None, day(1), "Coffee helped.")
+            .await
+            .unwrap();
+        assert!(!again.created);
+        assert_eq!(again.document_id, first.document_id);
+        let content = db
+            .read()
+            .await
+            .get_document(first.document_id.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            content.as_deref(),
+            Some("# 2026-03-01\n\nSlept badly.\n\nCoffee helped.\n")
+        );
+
+        service.append(None, day(2), "Rain.").await.unwrap();
+        service.entry(None, day(3)).await.unwrap();
+        let streak = service.streak(None, day(3)).await.unwrap();
+        assert_eq!((streak.current, streak.longest), (2, 2));
+
+        // Journal pages stay out of the project's word count until the
+        // writer opts them back in
+        let stats = StatsService::new(db.clone());
+        stats.initialize().await.unwrap();
+        let session = stats.start_session(project).await.unwrap();
+        assert_eq!(session.words_start, 5);
+        settings.exclude_from_word_counts = false;
+        service.save_settings(&settings).await.unwrap();
+        let session = stats.start_session(project).await.unwrap();
+        assert!(session.words_start > 5);
+    }
+}
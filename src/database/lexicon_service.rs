@@ -0,0 +1,591 @@
+//! Lexicon Service
+//!
+//! Dictionaries for the project's constructed languages: word entries,
+//! search, CSV and PDF appendix exports, and a spellcheck dictionary so
+//! conlang words aren't flagged in the manuscript.
+
+use chrono::Utc;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{models::lexicon::*, DatabaseError, DatabaseResult, EnhancedDatabaseService};
+use crate::publishing::{PdfBuilder, PdfFont, PdfTextStyle};
+
+type ConlangRow = (
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    String,
+    String,
+);
+type LexiconEntryRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    String,
+    String,
+    String,
+    String,
+);
+
+/// Columns of the CSV export, in order
+const CSV_COLUMNS: [&str; 7] = [
+    "word",
+    "pronunciation",
+    "part_of_speech",
+    "gloss",
+    "etymology",
+    "variants",
+    "notes",
+];
+
+/// Service for conlang dictionaries
+#[derive(Debug)]
+pub struct LexiconService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl LexiconService {
+    /// Create a new lexicon service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the lexicon tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_LEXICON_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create lexicon tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Create or update a language
+    pub async fn save_language(&self, language: &Conlang) -> DatabaseResult<()> {
+        if language.name.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Language name cannot be empty".to_string(),
+            ));
+        }
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_CONLANG_SQL)
+            .bind(language.id.to_string())
+            .bind(language.project_id.to_string())
+            .bind(language.name.trim())
+            .bind(&language.description)
+            .bind(language.culture_entry_id.map(|id| id.to_string()))
+            .bind(language.created_at.to_rfc3339())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save language: {}", e)))?;
+        Ok(())
+    }
+
+    /// Get a language by ID
+    pub async fn get_language(&self, language_id: Uuid) -> DatabaseResult<Option<Conlang>> {
+        let db = self.db_service.read().await;
+        let row: Option<ConlangRow> = sqlx::query_as(
+            "SELECT id, project_id, name, description, culture_entry_id, created_at, updated_at
+             FROM conlangs WHERE id = ?1",
+        )
+        .bind(language_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to get language: {}", e)))?;
+        row.map(conlang_from_row).transpose()
+    }
+
+    /// Languages of a project, by name
+    pub async fn list_languages(&self, project_id: Uuid) -> DatabaseResult<Vec<Conlang>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<ConlangRow> = sqlx::query_as(
+            "SELECT id, project_id, name, description, culture_entry_id, created_at, updated_at
+             FROM conlangs WHERE project_id = ?1 ORDER BY name COLLATE NOCASE",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to list languages: {}", e)))?;
+        rows.into_iter().map(conlang_from_row).collect()
+    }
+
+    /// Languages spoken by a codex culture
+    pub async fn languages_for_culture(
+        &self,
+        culture_entry_id: Uuid,
+    ) -> DatabaseResult<Vec<Conlang>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<ConlangRow> = sqlx::query_as(
+            "SELECT id, project_id, name, description, culture_entry_id, created_at, updated_at
+             FROM conlangs WHERE culture_entry_id = ?1 ORDER BY name COLLATE NOCASE",
+        )
+        .bind(culture_entry_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to list languages: {}", e)))?;
+        rows.into_iter().map(conlang_from_row).collect()
+    }
+
+    /// Delete a language and its words
+    pub async fn delete_language(&self, language_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let mut tx =
+            db.pool.begin().await.map_err(|e| {
+                DatabaseError::Service(format!("Failed to start transaction: {}", e))
+            })?;
+        sqlx::query("DELETE FROM lexicon_entries WHERE language_id = ?1")
+            .bind(language_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete words: {}", e)))?;
+        let result = sqlx::query("DELETE FROM conlangs WHERE id = ?1")
+            .bind(language_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete language: {}", e)))?;
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to commit: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Create or update a word. The audio must be an audio attachment.
+    pub async fn save_entry(&self, entry: &LexiconEntry) -> DatabaseResult<()> {
+        if entry.word.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Word cannot be empty".to_string(),
+            ));
+        }
+        let variants: Vec<&str> = entry
+            .variants
+            .iter()
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .collect();
+        let variants = serde_json::to_string(&variants)
+            .map_err(|e| DatabaseError::Service(format!("Failed to serialize variants: {}", e)))?;
+
+        let db = self.db_service.read().await;
+        let language: Option<String> = sqlx::query_scalar("SELECT id FROM conlangs WHERE id = ?1")
+            .bind(entry.language_id.to_string())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get language: {}", e)))?;
+        if language.is_none() {
+            return Err(DatabaseError::RecordNotFound {
+                entity: "language".to_string(),
+                id: entry.language_id.to_string(),
+            });
+        }
+        if let Some(audio) = entry.audio_attachment_id {
+            let preview: Option<String> =
+                sqlx::query_scalar("SELECT preview FROM attachments WHERE id = ?1")
+                    .bind(audio.to_string())
+                    .fetch_optional(&db.pool)
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Service(format!("Failed to get attachment: {}", e))
+                    })?;
+            if preview.as_deref() != Some("audio") {
+                return Err(DatabaseError::ValidationError(
+                    "Pronunciation audio must be an audio attachment".to_string(),
+                ));
+            }
+        }
+
+        sqlx::query(UPSERT_LEXICON_ENTRY_SQL)
+            .bind(entry.id.to_string())
+            .bind(entry.language_id.to_string())
+            .bind(entry.word.trim())
+            .bind(entry.gloss.trim())
+            .bind(entry.part_of_speech.trim())
+            .bind(&entry.etymology)
+            .bind(entry.pronunciation.trim())
+            .bind(entry.audio_attachment_id.map(|id| id.to_string()))
+            .bind(variants)
+            .bind(&entry.notes)
+            .bind(entry.created_at.to_rfc3339())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save word: {}", e)))?;
+        Ok(())
+    }
+
+    /// Get a word by ID
+    pub async fn get_entry(&self, entry_id: Uuid) -> DatabaseResult<Option<LexiconEntry>> {
+        let db = self.db_service.read().await;
+        let row: Option<LexiconEntryRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM lexicon_entries e WHERE e.id = ?1",
+            LEXICON_ENTRY_COLUMNS
+        ))
+        .bind(entry_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to get word: {}", e)))?;
+        row.map(entry_from_row).transpose()
+    }
+
+    /// Delete a word
+    pub async fn delete_entry(&self, entry_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM lexicon_entries WHERE id = ?1")
+            .bind(entry_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete word: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Search words, variants and glosses. Exact word matches come first,
+    /// then words starting with the text, then the rest alphabetically.
+    pub async fn search(&self, query: &LexiconQuery) -> DatabaseResult<Vec<LexiconEntry>> {
+        let text = query.text.trim().to_lowercase();
+        let escaped = text
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+
+        let db = self.db_service.read().await;
+        let rows: Vec<LexiconEntryRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM lexicon_entries e
+             JOIN conlangs l ON l.id = e.language_id
+             WHERE l.project_id = ?1
+               AND (?2 IS NULL OR e.language_id = ?2)
+               AND (?3 = '' OR lower(e.word) LIKE ?4 ESCAPE '\\'
+                    OR lower(e.gloss) LIKE ?4 ESCAPE '\\'
+                    OR lower(e.variants) LIKE ?4 ESCAPE '\\')
+               AND (?5 IS NULL OR lower(e.part_of_speech) = lower(?5))
+             ORDER BY CASE WHEN lower(e.word) = ?3 THEN 0
+                           WHEN lower(e.word) LIKE ?6 ESCAPE '\\' THEN 1
+                           ELSE 2 END,
+                      e.word COLLATE NOCASE
+             LIMIT ?7",
+            LEXICON_ENTRY_COLUMNS
+        ))
+        .bind(query.project_id.to_string())
+        .bind(query.language_id.map(|id| id.to_string()))
+        .bind(&text)
+        .bind(format!("%{}%", escaped))
+        .bind(query.part_of_speech.as_deref().map(str::trim))
+        .bind(format!("{}%", escaped))
+        .bind(query.limit.map_or(-1, |limit| limit as i64))
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to search lexicon: {}", e)))?;
+        rows.into_iter().map(entry_from_row).collect()
+    }
+
+    /// All words of a language, alphabetically
+    pub async fn entries(&self, language_id: Uuid) -> DatabaseResult<Vec<LexiconEntry>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<LexiconEntryRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM lexicon_entries e WHERE e.language_id = ?1
+             ORDER BY e.word COLLATE NOCASE",
+            LEXICON_ENTRY_COLUMNS
+        ))
+        .bind(language_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load words: {}", e)))?;
+        rows.into_iter().map(entry_from_row).collect()
+    }
+
+    /// A language's dictionary as CSV
+    pub async fn export_csv(&self, language_id: Uuid) -> DatabaseResult<String> {
+        Ok(lexicon_csv(&self.entries(language_id).await?))
+    }
+
+    /// A language's dictionary as a PDF glossary, for a book's appendix
+    pub async fn export_pdf_appendix(&self, language_id: Uuid) -> DatabaseResult<Vec<u8>> {
+        let language =
+            self.get_language(language_id)
+                .await?
+                .ok_or_else(|| DatabaseError::RecordNotFound {
+                    entity: "language".to_string(),
+                    id: language_id.to_string(),
+                })?;
+        let entries = self.entries(language_id).await?;
+        Ok(render_appendix(&language, &entries))
+    }
+
+    /// Every conlang word and variant in a project, sorted and unique
+    pub async fn spellcheck_words(&self, project_id: Uuid) -> DatabaseResult<Vec<String>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT e.word, e.variants FROM lexicon_entries e
+             JOIN conlangs l ON l.id = e.language_id
+             WHERE l.project_id = ?1",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load words: {}", e)))?;
+
+        let mut words = BTreeSet::new();
+        for (word, variants) in rows {
+            let variants: Vec<String> = serde_json::from_str(&variants).unwrap_or_default();
+            for form in std::iter::once(word).chain(variants) {
+                words.extend(spellcheck_tokens(&form));
+            }
+        }
+        Ok(words.into_iter().collect())
+    }
+
+    /// A Hunspell `.dic` file of the project's conlang words, to load as a
+    /// personal dictionary
+    pub async fn spellcheck_dictionary(&self, project_id: Uuid) -> DatabaseResult<String> {
+        let words = self.spellcheck_words(project_id).await?;
+        let mut dic = format!("{}\n", words.len());
+        for word in words {
+            dic.push_str(&word);
+            dic.push('\n');
+        }
+        Ok(dic)
+    }
+}
+
+fn conlang_from_row(row: ConlangRow) -> DatabaseResult<Conlang> {
+    let (id, project_id, name, description, culture_entry_id, created_at, updated_at) = row;
+    Ok(Conlang {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        name,
+        description,
+        culture_entry_id: culture_entry_id.as_deref().map(parse_uuid).transpose()?,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+fn entry_from_row(row: LexiconEntryRow) -> DatabaseResult<LexiconEntry> {
+    let (
+        id,
+        language_id,
+        word,
+        gloss,
+        part_of_speech,
+        etymology,
+        pronunciation,
+        audio_attachment_id,
+        variants,
+        notes,
+        created_at,
+        updated_at,
+    ) = row;
+    Ok(LexiconEntry {
+        id: parse_uuid(&id)?,
+        language_id: parse_uuid(&language_id)?,
+        word,
+        gloss,
+        part_of_speech,
+        etymology,
+        pronunciation,
+        audio_attachment_id: audio_attachment_id.as_deref().map(parse_uuid).transpose()?,
+        variants: serde_json::from_str(&variants).unwrap_or_default(),
+        notes,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+/// Words of a form as a spellchecker splits them, without the punctuation
+/// around them
+fn spellcheck_tokens(form: &str) -> Vec<String> {
+    form.split_whitespace()
+        .map(|token| token.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Dictionary entries as CSV with a header row
+pub fn lexicon_csv(entries: &[LexiconEntry]) -> String {
+    let mut csv = CSV_COLUMNS.join(",");
+    csv.push_str("\r\n");
+    for entry in entries {
+        let variants = entry.variants.join("; ");
+        let fields = [
+            entry.word.as_str(),
+            &entry.pronunciation,
+            &entry.part_of_speech,
+            &entry.gloss,
+            &entry.etymology,
+            &variants,
+            &entry.notes,
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// A glossary of the language, one section per initial letter
+pub fn render_appendix(language: &Conlang, entries: &[LexiconEntry]) -> Vec<u8> {
+    let title = format!("Glossary of {}", language.name);
+    let mut builder = PdfBuilder::new().title(title.clone()).footer(title.clone());
+    builder.heading(1, &title);
+    if !language.description.trim().is_empty() {
+        builder.paragraph(language.description.trim());
+    }
+
+    let style = |font: PdfFont| PdfTextStyle {
+        font,
+        ..Default::default()
+    };
+    let mut sorted: Vec<&LexiconEntry> = entries.iter().collect();
+    sorted.sort_by_key(|e| e.word.to_lowercase());
+    let mut letter = None;
+    for entry in sorted {
+        let initial = entry
+            .word
+            .chars()
+            .next()
+            .map(|c| c.to_uppercase().to_string());
+        if initial != letter {
+            if let Some(initial) = &initial {
+                builder.heading(2, initial);
+            }
+            letter = initial;
+        }
+
+        let mut runs = vec![(entry.word.clone(), style(PdfFont::Bold))];
+        if !entry.pronunciation.is_empty() {
+            runs.push((
+                format!("/{}/", entry.pronunciation.trim_matches('/')),
+                style(PdfFont::Regular),
+            ));
+        }
+        if !entry.part_of_speech.is_empty() {
+            runs.push((entry.part_of_speech.clone(), style(PdfFont::Italic)));
+        }
+        runs.push((entry.gloss.clone(), style(PdfFont::Regular)));
+        if !entry.etymology.trim().is_empty() {
+            runs.push((
+                format!("[{}]", entry.etymology.trim()),
+                style(PdfFont::Italic),
+            ));
+        }
+        builder.rich_paragraph(&runs);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_lexicon_search_exports_and_spellcheck() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Saga', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let service = LexiconService::new(Arc::new(RwLock::new(db)));
+        service.initialize().await.unwrap();
+
+        let culture = Uuid::new_v4();
+        let mut elvish = Conlang::new(project, "Elvish");
+        elvish.culture_entry_id = Some(culture);
+        service.save_language(&elvish).await.unwrap();
+        assert_eq!(
+            service.languages_for_culture(culture).await.unwrap().len(),
+            1
+        );
+
+        let mut star = LexiconEntry::new(elvish.id, "Elen", "star");
+        star.part_of_speech = "noun".to_string();
+        star.pronunciation = "ˈɛlɛn".to_string();
+        star.variants = vec!["elenath".to_string()];
+        star.etymology = "From \"el\", wonder".to_string();
+        let mut starlit = LexiconEntry::new(elvish.id, "elenya", "starlit, of the stars");
+        starlit.part_of_speech = "adjective".to_string();
+        let fire = LexiconEntry::new(elvish.id, "nár", "fire");
+        for entry in [&star, &starlit, &fire] {
+            service.save_entry(entry).await.unwrap();
+        }
+        let mut bad = LexiconEntry::new(elvish.id, "lóm", "echo");
+        bad.audio_attachment_id = Some(Uuid::new_v4());
+        assert!(service.save_entry(&bad).await.is_err());
+
+        let search = |text: &str, part_of_speech: Option<&str>| LexiconQuery {
+            project_id: project,
+            text: text.to_string(),
+            part_of_speech: part_of_speech.map(str::to_string),
+            ..Default::default()
+        };
+        let words = |entries: Vec<LexiconEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.word).collect()
+        };
+        assert_eq!(
+            words(service.search(&search("elen", None)).await.unwrap()),
+            vec!["Elen", "elenya"]
+        );
+        assert_eq!(
+            words(
+                service
+                    .search(&search("star", Some("Adjective")))
+                    .await
+                    .unwrap()
+            ),
+            vec!["elenya"]
+        );
+        assert_eq!(
+            words(service.search(&search("elenath", None)).await.unwrap()),
+            vec!["Elen"]
+        );
+        assert!(service.search(&search("%", None)).await.unwrap().is_empty());
+
+        let csv = service.export_csv(elvish.id).await.unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_COLUMNS.join(","));
+        assert_eq!(
+            lines[1],
+            "Elen,ˈɛlɛn,noun,star,\"From \"\"el\"\", wonder\",elenath,"
+        );
+        let pdf = service.export_pdf_appendix(elvish.id).await.unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+
+        assert_eq!(
+            service.spellcheck_dictionary(project).await.unwrap(),
+            "4\nElen\nelenath\nelenya\nnár\n"
+        );
+        assert!(service.delete_language(elvish.id).await.unwrap());
+        assert!(service.get_entry(star.id).await.unwrap().is_none());
+    }
+}
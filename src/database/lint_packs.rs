@@ -0,0 +1,589 @@
+//! Genre Lint Packs
+//!
+//! The built-in romance, fantasy and thriller packs and the checks they
+//! run over a project's chapters in binder order. A scene's POV is guessed
+//! from the character its narration names most, or "I" for first person;
+//! a character's thoughts are found by their name shortly before a verb
+//! like "thought" or "wondered". Dialogue is left out of both.
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::database::models::lint_pack::*;
+use crate::database::text_match::{find_word_matches, line_and_column};
+
+/// Verbs that put the reader inside a character's head
+const INTERIOR_VERBS: &[&str] = &[
+    "thought",
+    "wondered",
+    "felt",
+    "knew",
+    "realized",
+    "realised",
+    "hoped",
+    "feared",
+    "wished",
+    "sensed",
+    "suspected",
+    "remembered",
+    "decided",
+    "worried",
+    "imagined",
+];
+
+/// How far before an interior verb its subject is looked for, in bytes
+const SUBJECT_WINDOW: usize = 40;
+
+/// Chapters needed before lengths are compared with the median
+const MIN_CHAPTERS_FOR_OUTLIERS: usize = 3;
+
+/// A chapter to check
+#[derive(Debug, Clone)]
+pub struct LintChapter {
+    pub id: Uuid,
+    pub title: String,
+    /// The chapter's text, not its stored JSON
+    pub content: String,
+}
+
+/// A character from the codex: the title and any other names
+#[derive(Debug, Clone)]
+pub struct LintCharacter {
+    pub names: Vec<String>,
+}
+
+/// The packs that ship with the app
+pub fn builtin_packs() -> Vec<LintPack> {
+    vec![
+        LintPack {
+            id: "romance".to_string(),
+            name: "Romance".to_string(),
+            description: "Dual POV for the leads, switching at scene breaks; \
+                          steady chapter lengths"
+                .to_string(),
+            rules: vec![
+                PackRule {
+                    rule: LintRule::PovSwitch {
+                        boundary: PovBoundary::Scene,
+                        max_pov_characters: Some(2),
+                    },
+                    severity: LintSeverity::Warning,
+                },
+                PackRule {
+                    rule: LintRule::HeadHopping,
+                    severity: LintSeverity::Warning,
+                },
+                PackRule {
+                    rule: LintRule::ChapterLength {
+                        outlier_ratio: 2.0,
+                        max_words: None,
+                    },
+                    severity: LintSeverity::Info,
+                },
+            ],
+        },
+        LintPack {
+            id: "fantasy".to_string(),
+            name: "Fantasy".to_string(),
+            description: "Any number of POV characters, one per chapter; \
+                          long chapters are fine"
+                .to_string(),
+            rules: vec![
+                PackRule {
+                    rule: LintRule::PovSwitch {
+                        boundary: PovBoundary::Chapter,
+                        max_pov_characters: None,
+                    },
+                    severity: LintSeverity::Warning,
+                },
+                PackRule {
+                    rule: LintRule::HeadHopping,
+                    severity: LintSeverity::Warning,
+                },
+                PackRule {
+                    rule: LintRule::ChapterLength {
+                        outlier_ratio: 2.5,
+                        max_words: None,
+                    },
+                    severity: LintSeverity::Info,
+                },
+            ],
+        },
+        LintPack {
+            id: "thriller".to_string(),
+            name: "Thriller".to_string(),
+            description: "Short, tight chapters with one POV each and no \
+                          head-hopping"
+                .to_string(),
+            rules: vec![
+                PackRule {
+                    rule: LintRule::PovSwitch {
+                        boundary: PovBoundary::Chapter,
+                        max_pov_characters: None,
+                    },
+                    severity: LintSeverity::Info,
+                },
+                PackRule {
+                    rule: LintRule::HeadHopping,
+                    severity: LintSeverity::Error,
+                },
+                PackRule {
+                    rule: LintRule::ChapterLength {
+                        outlier_ratio: 1.75,
+                        max_words: Some(3000),
+                    },
+                    severity: LintSeverity::Warning,
+                },
+            ],
+        },
+    ]
+}
+
+/// Whose head a passage is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Viewpoint {
+    FirstPerson,
+    Character(usize),
+}
+
+/// A scene with its guessed POV and the thoughts it reports
+#[derive(Debug)]
+struct Scene {
+    /// POV and where its first mention is
+    pov: Option<(Viewpoint, usize, usize)>,
+    /// Who thinks, and the byte range from their name to the verb
+    interiors: Vec<(Viewpoint, usize, usize)>,
+}
+
+/// Run the enabled packs over the chapters
+pub fn lint_chapters(
+    packs: &[LintPack],
+    settings: &LintSettings,
+    chapters: &[LintChapter],
+    characters: &[LintCharacter],
+) -> Vec<LintIssue> {
+    let scenes: Vec<Vec<Scene>> = chapters
+        .iter()
+        .map(|chapter| analyze_scenes(&chapter.content, characters))
+        .collect();
+    let name = |viewpoint: Viewpoint| match viewpoint {
+        Viewpoint::FirstPerson => "the first-person narrator".to_string(),
+        Viewpoint::Character(i) => characters[i].names[0].clone(),
+    };
+
+    let mut issues = Vec::new();
+    for pack in packs
+        .iter()
+        .filter(|pack| settings.enabled_packs.contains(&pack.id))
+    {
+        for pack_rule in &pack.rules {
+            let severity = settings.severity(pack, pack_rule);
+            if severity == LintSeverity::Off {
+                continue;
+            }
+            let mut issue = |chapter: &LintChapter, start: usize, end: usize, message: String| {
+                issues.push(LintIssue {
+                    pack: pack.id.clone(),
+                    rule: pack_rule.rule.id().to_string(),
+                    severity,
+                    document_id: chapter.id,
+                    title: chapter.title.clone(),
+                    start,
+                    end,
+                    line: line_and_column(&chapter.content, start).0,
+                    message,
+                })
+            };
+
+            match &pack_rule.rule {
+                LintRule::PovSwitch {
+                    boundary,
+                    max_pov_characters,
+                } => {
+                    let mut seen: Vec<Viewpoint> = Vec::new();
+                    for (chapter, scenes) in chapters.iter().zip(&scenes) {
+                        let mut chapter_pov = None;
+                        for (pov, start, end) in scenes.iter().filter_map(|s| s.pov) {
+                            match chapter_pov {
+                                None => chapter_pov = Some(pov),
+                                Some(first)
+                                    if first != pov && *boundary == PovBoundary::Chapter =>
+                                {
+                                    issue(
+                                        chapter,
+                                        start,
+                                        end,
+                                        format!(
+                                            "POV moves from {} to {} within the chapter; {} usually keeps one POV per chapter",
+                                            name(first),
+                                            name(pov),
+                                            pack.name.to_lowercase()
+                                        ),
+                                    );
+                                }
+                                Some(_) => {}
+                            }
+                            if !seen.contains(&pov) {
+                                seen.push(pov);
+                                if max_pov_characters.is_some_and(|max| seen.len() > max) {
+                                    issue(
+                                        chapter,
+                                        start,
+                                        end,
+                                        format!(
+                                            "{} is POV character number {}; {} usually sticks to {}",
+                                            capitalize(&name(pov)),
+                                            seen.len(),
+                                            pack.name.to_lowercase(),
+                                            max_pov_characters.unwrap_or_default()
+                                        ),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+                LintRule::HeadHopping => {
+                    for (chapter, scenes) in chapters.iter().zip(&scenes) {
+                        for scene in scenes {
+                            let Some(anchor) = scene
+                                .pov
+                                .map(|(pov, _, _)| pov)
+                                .or_else(|| scene.interiors.first().map(|(who, _, _)| *who))
+                            else {
+                                continue;
+                            };
+                            let mut reported = HashSet::new();
+                            for &(who, start, end) in &scene.interiors {
+                                if who != anchor && reported.insert(who) {
+                                    issue(
+                                        chapter,
+                                        start,
+                                        end,
+                                        format!(
+                                            "Head-hopping: a scene in {}'s head reports {}'s thoughts",
+                                            name(anchor),
+                                            name(who)
+                                        ),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+                LintRule::ChapterLength {
+                    outlier_ratio,
+                    max_words,
+                } => {
+                    let words: Vec<usize> = chapters
+                        .iter()
+                        .map(|c| c.content.split_whitespace().count())
+                        .collect();
+                    let median = median(&words);
+                    let compare = words.iter().filter(|w| **w > 0).count()
+                        >= MIN_CHAPTERS_FOR_OUTLIERS
+                        && median > 0;
+                    for (chapter, &count) in chapters.iter().zip(&words) {
+                        if count == 0 {
+                            continue;
+                        }
+                        let ratio = count as f32 / median.max(1) as f32;
+                        let message = match max_words {
+                            Some(max) if count > *max => Some(format!(
+                                "Chapter runs {} words; {} chapters usually stay under {}",
+                                count,
+                                pack.name.to_lowercase(),
+                                max
+                            )),
+                            _ if compare && ratio > *outlier_ratio => Some(format!(
+                                "Chapter runs {} words, {:.1}x the median of {}",
+                                count, ratio, median
+                            )),
+                            _ if compare && ratio * outlier_ratio < 1.0 => Some(format!(
+                                "Chapter runs {} words, well under the median of {}",
+                                count, median
+                            )),
+                            _ => None,
+                        };
+                        if let Some(message) = message {
+                            issue(chapter, 0, 0, message);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Packs that share a rule report the same finding once, at the highest
+    // severity any of them gives it
+    let position: HashMap<Uuid, usize> = chapters
+        .iter()
+        .enumerate()
+        .map(|(i, chapter)| (chapter.id, i))
+        .collect();
+    issues.sort_by_key(|issue| {
+        (
+            Reverse(issue.severity),
+            position.get(&issue.document_id).copied(),
+            issue.start,
+        )
+    });
+    let mut seen = HashSet::new();
+    issues.retain(|issue| {
+        seen.insert((
+            issue.rule.clone(),
+            issue.document_id,
+            issue.start,
+            issue.message.clone(),
+        ))
+    });
+    issues
+}
+
+fn analyze_scenes(content: &str, characters: &[LintCharacter]) -> Vec<Scene> {
+    let narration = narration(content);
+    scene_spans(content)
+        .into_iter()
+        .map(|(start, end)| {
+            let text = &narration[start..end];
+            let mut interiors = Vec::new();
+            for verb in INTERIOR_VERBS {
+                for (verb_start, verb_end) in find_word_matches(text, verb) {
+                    if let Some((who, name_start)) = subject(text, verb_start, characters) {
+                        interiors.push((who, start + name_start, start + verb_end));
+                    }
+                }
+            }
+            interiors.sort_by_key(|(_, s, _)| *s);
+            Scene {
+                pov: guess_pov(text, characters).map(|(pov, s, e)| (pov, start + s, start + e)),
+                interiors,
+            }
+        })
+        .collect()
+}
+
+/// Byte ranges of the scenes, split at lines holding only punctuation
+/// such as "***" or "#"
+pub(crate) fn scene_spans(content: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let is_break = !trimmed.is_empty()
+            && trimmed.chars().count() <= 12
+            && trimmed.chars().all(|c| !c.is_alphanumeric() && c != '"');
+        if is_break {
+            if !content[start..offset].trim().is_empty() {
+                spans.push((start, offset));
+            }
+            start = offset + line.len();
+        }
+        offset += line.len();
+    }
+    if !content[start..].trim().is_empty() {
+        spans.push((start, content.len()));
+    }
+    spans
+}
+
+/// The text with dialogue blanked out, keeping byte offsets
+pub(crate) fn narration(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut quoted = false;
+    for c in text.chars() {
+        if c == '\n' {
+            quoted = false;
+        }
+        let opens = c == '\u{201C}' || (c == '"' && !quoted);
+        let closes = c == '\u{201D}' || (c == '"' && quoted);
+        if opens {
+            quoted = true;
+        }
+        if quoted || closes {
+            out.push_str(&" ".repeat(c.len_utf8()));
+        } else {
+            out.push(c);
+        }
+        if closes {
+            quoted = false;
+        }
+    }
+    out
+}
+
+/// The character the narration follows: first person when "I" and "my"
+/// outnumber every name, else the most named character if clearly ahead
+pub(crate) fn guess_pov(
+    text: &str,
+    characters: &[LintCharacter],
+) -> Option<(Viewpoint, usize, usize)> {
+    let mut first_person = find_word_matches(text, "I");
+    first_person.extend(find_word_matches(text, "my"));
+    first_person.sort_unstable();
+
+    let mut counts: Vec<(usize, usize, (usize, usize))> = characters
+        .iter()
+        .enumerate()
+        .filter_map(|(i, character)| {
+            let mut matches: Vec<(usize, usize)> = character
+                .names
+                .iter()
+                .flat_map(|name| find_word_matches(text, name))
+                .collect();
+            matches.sort_unstable();
+            Some((i, matches.len(), *matches.first()?))
+        })
+        .collect();
+    counts.sort_by_key(|(_, count, _)| Reverse(*count));
+    let best = counts.first().map(|(_, count, _)| *count).unwrap_or(0);
+
+    if first_person.len() >= 3 && first_person.len() >= best {
+        let (start, end) = first_person[0];
+        return Some((Viewpoint::FirstPerson, start, end));
+    }
+    let runner_up = counts.get(1).map(|(_, count, _)| *count).unwrap_or(0);
+    match counts.first() {
+        Some(&(i, count, (start, end))) if count >= 2 && count > runner_up => {
+            Some((Viewpoint::Character(i), start, end))
+        }
+        _ => None,
+    }
+}
+
+/// Who an interior verb belongs to: the last name (or "I") shortly before
+/// it in the same sentence
+fn subject(
+    text: &str,
+    verb_start: usize,
+    characters: &[LintCharacter],
+) -> Option<(Viewpoint, usize)> {
+    let mut from = verb_start.saturating_sub(SUBJECT_WINDOW);
+    while !text.is_char_boundary(from) {
+        from += 1;
+    }
+    let window = &text[from..verb_start];
+    let from = match window.rfind(['.', '!', '?']) {
+        Some(end) => from + end + 1,
+        None => from,
+    };
+    let window = &text[from..verb_start];
+
+    let mut best: Option<(Viewpoint, usize)> = find_word_matches(window, "I")
+        .last()
+        .map(|(start, _)| (Viewpoint::FirstPerson, *start));
+    for (i, character) in characters.iter().enumerate() {
+        for name in &character.names {
+            if let Some(&(start, _)) = find_word_matches(window, name).last() {
+                if best.is_none_or(|(_, best_start)| start > best_start) {
+                    best = Some((Viewpoint::Character(i), start));
+                }
+            }
+        }
+    }
+    best.map(|(who, start)| (who, from + start))
+}
+
+fn median(values: &[usize]) -> usize {
+    let mut sorted: Vec<usize> = values.iter().copied().filter(|v| *v > 0).collect();
+    if sorted.is_empty() {
+        return 0;
+    }
+    sorted.sort_unstable();
+    sorted[sorted.len() / 2]
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(title: &str, content: &str) -> LintChapter {
+        LintChapter {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    fn character(names: &[&str]) -> LintCharacter {
+        LintCharacter {
+            names: names.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_packs_flag_pov_switches_head_hopping_and_long_chapters() {
+        let characters = vec![
+            character(&["Mara", "Mara Voss"]),
+            character(&["Tobin"]),
+            character(&["Wren"]),
+        ];
+        let filler = " The tide came in.".repeat(40);
+        let chapters = vec![
+            chapter(
+                "One",
+                &format!(
+                    "Mara walked the pier. Mara counted boats. \"Tobin!\" she called.\n\
+                     Tobin waved back. Mara thought he looked tired. Tobin wondered if she knew.{}",
+                    filler
+                ),
+            ),
+            chapter(
+                "Two",
+                &format!(
+                    "Tobin mended nets. Tobin hummed.\n\n* * *\n\n\
+                     Wren climbed the cliff. Wren looked down at Tobin. Wren slipped.{}",
+                    filler
+                ),
+            ),
+            chapter(
+                "Three",
+                &format!("Mara slept. Mara dreamed.{}", filler.repeat(4)),
+            ),
+        ];
+        let mut settings = LintSettings::new(Uuid::new_v4());
+        settings.enabled_packs = vec!["romance".to_string(), "thriller".to_string()];
+        settings
+            .severity_overrides
+            .insert("thriller.chapter_length".to_string(), LintSeverity::Off);
+
+        let issues = lint_chapters(&builtin_packs(), &settings, &chapters, &characters);
+        let summary: Vec<(&str, &str, LintSeverity, &str)> = issues
+            .iter()
+            .map(|i| {
+                (
+                    i.pack.as_str(),
+                    i.rule.as_str(),
+                    i.severity,
+                    i.title.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("thriller", "head_hopping", LintSeverity::Error, "One"),
+                ("romance", "pov_switch", LintSeverity::Warning, "Two"),
+                ("thriller", "pov_switch", LintSeverity::Info, "Two"),
+                ("romance", "chapter_length", LintSeverity::Info, "Three"),
+            ]
+        );
+        let hop = &issues[0];
+        assert_eq!(&chapters[0].content[hop.start..hop.end], "Tobin wondered");
+        assert_eq!(hop.line, 2);
+        assert!(issues[1]
+            .message
+            .starts_with("Wren is POV character number 3"));
+        assert!(issues[2].message.contains("from Tobin to Wren"));
+    }
+}
@@ -0,0 +1,244 @@
+//! Local Embedding Model
+//!
+//! Sentence embeddings computed on this machine with a BERT-style model
+//! such as all-MiniLM-L6-v2, so documents can be indexed and searched
+//! without sending text anywhere. The model is read from a folder holding
+//! `config.json`, `tokenizer.json` and `model.safetensors`, as published
+//! on Hugging Face. Inference needs the `local-embeddings` feature; builds
+//! without it report that when the model is loaded.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::error::{DatabaseError, DatabaseResult};
+
+/// Provider recorded for models that run through `LocalEmbedder`
+pub const LOCAL_PROVIDER: &str = "local";
+
+/// Texts embedded per forward pass
+pub const LOCAL_BATCH_SIZE: usize = 16;
+
+/// Where vectors come from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum EmbeddingBackend {
+    /// Each model's provider computes its vectors
+    #[default]
+    Remote,
+    /// `model_name` is computed from the model in `model_dir`. It becomes
+    /// the default model of a new library and stands in for remote models
+    /// while the app is offline.
+    Local {
+        model_name: String,
+        model_dir: PathBuf,
+    },
+}
+
+impl EmbeddingBackend {
+    /// Local backend for the model in `model_dir`, named after the folder
+    pub fn local(model_dir: impl Into<PathBuf>) -> Self {
+        let model_dir = model_dir.into();
+        let model_name = model_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "local-embeddings".to_string());
+        EmbeddingBackend::Local {
+            model_name,
+            model_dir,
+        }
+    }
+
+    pub fn local_model(&self) -> Option<&str> {
+        match self {
+            EmbeddingBackend::Local { model_name, .. } => Some(model_name),
+            EmbeddingBackend::Remote => None,
+        }
+    }
+}
+
+/// A loaded sentence-embedding model
+pub struct LocalEmbedder {
+    model_name: String,
+    dimensions: usize,
+    #[cfg(feature = "local-embeddings")]
+    model: bert::Model,
+}
+
+impl fmt::Debug for LocalEmbedder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalEmbedder")
+            .field("model_name", &self.model_name)
+            .field("dimensions", &self.dimensions)
+            .finish()
+    }
+}
+
+impl LocalEmbedder {
+    /// Load the model in `model_dir`; this reads a few hundred MB for
+    /// larger models, so call it off the async runtime
+    pub fn load(model_name: &str, model_dir: &Path) -> DatabaseResult<Self> {
+        for file in ["config.json", "tokenizer.json", "model.safetensors"] {
+            if !model_dir.join(file).is_file() {
+                return Err(DatabaseError::ValidationError(format!(
+                    "Local embedding model {} has no {} in {}",
+                    model_name,
+                    file,
+                    model_dir.display()
+                )));
+            }
+        }
+        Self::load_model(model_name, model_dir)
+    }
+
+    #[cfg(feature = "local-embeddings")]
+    fn load_model(model_name: &str, model_dir: &Path) -> DatabaseResult<Self> {
+        let model = bert::Model::load(model_dir).map_err(|e| {
+            DatabaseError::Service(format!(
+                "Failed to load local embedding model {}: {}",
+                model_name, e
+            ))
+        })?;
+        Ok(Self {
+            model_name: model_name.to_string(),
+            dimensions: model.dimensions(),
+            model,
+        })
+    }
+
+    #[cfg(not(feature = "local-embeddings"))]
+    fn load_model(model_name: &str, _model_dir: &Path) -> DatabaseResult<Self> {
+        Err(DatabaseError::Service(format!(
+            "Can't run {}: this build has no local embedding support (feature \"local-embeddings\")",
+            model_name
+        )))
+    }
+
+    pub fn model_name(&self) -> &str {
+        &self.model_name
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// Normalized embeddings of `texts`, in order
+    pub fn embed(&self, texts: &[String]) -> DatabaseResult<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(LOCAL_BATCH_SIZE) {
+            vectors.extend(self.embed_batch(batch)?);
+        }
+        Ok(vectors)
+    }
+
+    #[cfg(feature = "local-embeddings")]
+    fn embed_batch(&self, texts: &[String]) -> DatabaseResult<Vec<Vec<f32>>> {
+        self.model.embed(texts).map_err(|e| {
+            DatabaseError::Service(format!(
+                "Local embedding with {} failed: {}",
+                self.model_name, e
+            ))
+        })
+    }
+
+    #[cfg(not(feature = "local-embeddings"))]
+    fn embed_batch(&self, _texts: &[String]) -> DatabaseResult<Vec<Vec<f32>>> {
+        unreachable!("LocalEmbedder can't be loaded without the local-embeddings feature")
+    }
+}
+
+#[cfg(feature = "local-embeddings")]
+mod bert {
+    use std::path::Path;
+
+    use candle_core::{Device, Tensor};
+    use candle_nn::VarBuilder;
+    use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+    use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+    /// Longest input in tokens; sentence-transformers models are trained
+    /// on at most this much
+    const MAX_TOKENS: usize = 256;
+
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    pub struct Model {
+        bert: BertModel,
+        tokenizer: Tokenizer,
+        hidden_size: usize,
+    }
+
+    impl Model {
+        pub fn load(model_dir: &Path) -> Result<Self, Error> {
+            let config: Config =
+                serde_json::from_str(&std::fs::read_to_string(model_dir.join("config.json"))?)?;
+            let mut tokenizer = Tokenizer::from_file(model_dir.join("tokenizer.json"))?;
+            tokenizer.with_padding(Some(PaddingParams::default()));
+            tokenizer.with_truncation(Some(TruncationParams {
+                max_length: MAX_TOKENS.min(config.max_position_embeddings),
+                ..Default::default()
+            }))?;
+            // Safety: the weights file is only read, and not expected to
+            // change while the app runs
+            let weights = unsafe {
+                VarBuilder::from_mmaped_safetensors(
+                    &[model_dir.join("model.safetensors")],
+                    DTYPE,
+                    &Device::Cpu,
+                )?
+            };
+            Ok(Self {
+                bert: BertModel::load(weights, &config)?,
+                tokenizer,
+                hidden_size: config.hidden_size,
+            })
+        }
+
+        pub fn dimensions(&self) -> usize {
+            self.hidden_size
+        }
+
+        /// Mean of the token embeddings under the attention mask, scaled
+        /// to unit length
+        pub fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+            let device = &self.bert.device;
+            let encodings = self.tokenizer.encode_batch(texts.to_vec(), true)?;
+            let ids = encodings
+                .iter()
+                .map(|e| Tensor::new(e.get_ids(), device))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mask = encodings
+                .iter()
+                .map(|e| Tensor::new(e.get_attention_mask(), device))
+                .collect::<Result<Vec<_>, _>>()?;
+            let ids = Tensor::stack(&ids, 0)?;
+            let mask = Tensor::stack(&mask, 0)?;
+
+            let tokens = self.bert.forward(&ids, &ids.zeros_like()?, Some(&mask))?;
+            let mask = mask.to_dtype(DTYPE)?.unsqueeze(2)?;
+            let pooled = tokens
+                .broadcast_mul(&mask)?
+                .sum(1)?
+                .broadcast_div(&mask.sum(1)?)?;
+            let norm = pooled.sqr()?.sum_keepdim(1)?.sqrt()?;
+            Ok(pooled.broadcast_div(&norm)?.to_vec2::<f32>()?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_and_missing_model_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let model_dir = dir.path().join("all-MiniLM-L6-v2");
+        std::fs::create_dir(&model_dir).unwrap();
+        let backend = EmbeddingBackend::local(&model_dir);
+        assert_eq!(backend.local_model(), Some("all-MiniLM-L6-v2"));
+        assert_eq!(EmbeddingBackend::default().local_model(), None);
+
+        std::fs::write(model_dir.join("config.json"), "{}").unwrap();
+        let error = LocalEmbedder::load("all-MiniLM-L6-v2", &model_dir).unwrap_err();
+        assert!(error.to_string().contains("tokenizer.json"));
+    }
+}
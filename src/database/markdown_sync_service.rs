@@ -0,0 +1,849 @@
+//! Markdown Sync Service
+//!
+//! Mirrors a project to a folder of Markdown files and keeps the two in
+//! step both ways. Each sync compares both sides with how the last sync
+//! left them: an edited file updates its document, an edited document
+//! rewrites its file, new files become documents and new documents get
+//! files, and a deletion on one side trashes or deletes the other. A
+//! document changed on both sides is reported as a conflict and left
+//! alone until the author picks a side. Syncs run on demand and on a
+//! timer, one at a time.
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::database::models::markdown_sync::{
+    decide, file_name_for, fingerprint, merge_front_matter, parse_markdown, MarkdownConflict,
+    MarkdownSyncFolder, MarkdownSyncReport, SyncAction, SyncBase, SyncDocument, SyncSide,
+    CREATE_MARKDOWN_SYNC_TABLES_SQL, UPSERT_MARKDOWN_SYNC_FILE_SQL,
+};
+use crate::database::models::TrashItemKind;
+use crate::database::parse::parse_uuid;
+use crate::database::prosemirror_markdown::{document_markdown, from_markdown, to_markdown};
+use crate::database::{DatabaseError, DatabaseResult, EnhancedDatabaseService};
+
+type FolderRow = (String, String, String, Option<String>);
+type DocumentRow = (String, String, Option<String>, String, Option<String>);
+
+/// A Markdown file in the folder
+struct LocalFile {
+    name: String,
+    /// Document the front matter names
+    id: Option<String>,
+    document: SyncDocument,
+    fingerprint: String,
+}
+
+/// Service for mirroring projects to Markdown folders
+#[derive(Debug)]
+pub struct MarkdownSyncService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    /// Held while a sync runs so the timer and a request don't overlap
+    syncing: Mutex<()>,
+}
+
+impl MarkdownSyncService {
+    /// Create a new Markdown sync service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self {
+            db_service,
+            syncing: Mutex::new(()),
+        }
+    }
+
+    /// Initialize the sync tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_MARKDOWN_SYNC_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create Markdown sync tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Mirror a project to a folder, creating it if needed. Moving a
+    /// project to another folder starts afresh there.
+    pub async fn enable(
+        &self,
+        project_id: Uuid,
+        folder: &Path,
+    ) -> DatabaseResult<MarkdownSyncFolder> {
+        if !folder.is_absolute() {
+            return Err(DatabaseError::ValidationError(
+                "The sync folder must be an absolute path".to_string(),
+            ));
+        }
+        tokio::fs::create_dir_all(folder)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to create sync folder: {}", e)))?;
+
+        let _syncing = self.syncing.lock().await;
+        let existing = self.folder(project_id).await?;
+        let db = self.db_service.read().await;
+        let live: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM projects WHERE id = ?1 AND deleted_at IS NULL",
+        )
+        .bind(project_id.to_string())
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load project: {}", e)))?;
+        if live == 0 {
+            return Err(DatabaseError::RecordNotFound {
+                entity: "project".to_string(),
+                id: project_id.to_string(),
+            });
+        }
+        if let Some(existing) = existing {
+            if existing.folder == folder {
+                return Ok(existing);
+            }
+            forget_files(&db, project_id).await?;
+        }
+
+        let synced = MarkdownSyncFolder {
+            project_id,
+            folder: folder.to_path_buf(),
+            created_at: Utc::now(),
+            last_synced_at: None,
+        };
+        sqlx::query(
+            "INSERT OR REPLACE INTO markdown_sync_folders (project_id, folder, created_at, last_synced_at)
+             VALUES (?1, ?2, ?3, NULL)",
+        )
+        .bind(project_id.to_string())
+        .bind(folder.to_string_lossy().to_string())
+        .bind(synced.created_at.to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to save sync folder: {}", e)))?;
+        Ok(synced)
+    }
+
+    /// Stop mirroring a project; the files are left where they are
+    pub async fn disable(&self, project_id: Uuid) -> DatabaseResult<bool> {
+        let _syncing = self.syncing.lock().await;
+        let db = self.db_service.read().await;
+        forget_files(&db, project_id).await?;
+        let result = sqlx::query("DELETE FROM markdown_sync_folders WHERE project_id = ?1")
+            .bind(project_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to remove sync folder: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The folder a project is mirrored to, if any
+    pub async fn folder(&self, project_id: Uuid) -> DatabaseResult<Option<MarkdownSyncFolder>> {
+        let db = self.db_service.read().await;
+        let row: Option<FolderRow> = sqlx::query_as(
+            "SELECT project_id, folder, created_at, last_synced_at FROM markdown_sync_folders WHERE project_id = ?1",
+        )
+        .bind(project_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load sync folder: {}", e)))?;
+        row.map(folder_from_row).transpose()
+    }
+
+    /// Sync every mirrored project, logging failures; returns how many
+    /// synced
+    pub async fn sync_all(&self) -> DatabaseResult<usize> {
+        let projects: Vec<String> = {
+            let db = self.db_service.read().await;
+            sqlx::query_scalar(
+                "SELECT f.project_id FROM markdown_sync_folders f JOIN projects p ON p.id = f.project_id
+                 WHERE p.deleted_at IS NULL",
+            )
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load sync folders: {}", e)))?
+        };
+        let mut synced = 0;
+        for project_id in projects.iter().filter_map(|id| Uuid::parse_str(id).ok()) {
+            match self.sync(project_id).await {
+                Ok(_) => synced += 1,
+                Err(e) => log::error!("Markdown sync of project {} failed: {}", project_id, e),
+            }
+        }
+        Ok(synced)
+    }
+
+    /// Sync mirrored projects now and then every `every`
+    pub fn spawn_sync(self: Arc<Self>, every: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.sync_all().await {
+                    log::error!("Markdown sync failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Bring a project and its folder into step
+    pub async fn sync(&self, project_id: Uuid) -> DatabaseResult<MarkdownSyncReport> {
+        let _syncing = self.syncing.lock().await;
+        let folder = self.require_folder(project_id).await?;
+        let files = read_folder(&folder.folder).await?;
+        let db = self.db_service.read().await;
+        let documents = load_documents(&db, project_id).await?;
+        let bases = load_bases(&db, project_id).await?;
+
+        // Pair each document, and each one synced before, with its file:
+        // the one whose front matter names it, else the file it was
+        // synced to if that doesn't name another document
+        let mut ids: Vec<String> = documents.iter().map(|d| d.id.clone()).collect();
+        ids.extend(
+            bases
+                .keys()
+                .filter(|id| !documents.iter().any(|d| &d.id == *id))
+                .cloned(),
+        );
+        let mut claimed = HashSet::new();
+        let mut pairs = Vec::new();
+        for id in &ids {
+            let file = files
+                .iter()
+                .position(|f| f.id.as_deref() == Some(id.as_str()))
+                .or_else(|| {
+                    let name = &bases.get(id)?.file_name;
+                    files
+                        .iter()
+                        .position(|f| &f.name == name && f.id.as_ref().is_none_or(|i| i == id))
+                })
+                .filter(|index| claimed.insert(*index));
+            pairs.push((id.clone(), file));
+        }
+
+        let mut names: HashSet<String> = files.iter().map(|f| f.name.clone()).collect();
+        let mut report = MarkdownSyncReport {
+            synced_at: Utc::now(),
+            ..Default::default()
+        };
+        for (id, file) in pairs {
+            let document = documents.iter().position(|d| d.id == id);
+            let file = file.map(|index| &files[index]);
+            let base = bases.get(&id);
+            let rendered = document.map(|index| documents[index].render());
+            let action = decide(
+                rendered.as_deref().map(fingerprint).as_deref(),
+                file.map(|f| f.fingerprint.as_str()),
+                base,
+            );
+            match action {
+                SyncAction::Keep => {
+                    if let (Some(rendered), Some(file)) = (&rendered, file) {
+                        if base.is_none() {
+                            record(
+                                &db,
+                                project_id,
+                                &id,
+                                &file.name,
+                                rendered,
+                                &file.fingerprint,
+                            )
+                            .await?;
+                        }
+                    }
+                }
+                SyncAction::WriteFile => {
+                    let index = document.expect("a file is only written for a document");
+                    let name = match (file, base) {
+                        (Some(file), _) => file.name.clone(),
+                        (None, Some(base)) if !names.contains(&base.file_name) => {
+                            base.file_name.clone()
+                        }
+                        _ => unique_name(&mut names, index + 1, &documents[index].title),
+                    };
+                    let rendered = rendered.expect("rendered with the document");
+                    write_file(&folder.folder, &name, &rendered).await?;
+                    record(
+                        &db,
+                        project_id,
+                        &id,
+                        &name,
+                        &rendered,
+                        &fingerprint(&rendered),
+                    )
+                    .await?;
+                    report.files_written += 1;
+                }
+                SyncAction::UpdateDocument => {
+                    let file = file.expect("a document is only updated from a file");
+                    let existing = &documents[document.expect("updated document exists")];
+                    let updated = update_document(&db, existing, &file.document).await?;
+                    record(
+                        &db,
+                        project_id,
+                        &id,
+                        &file.name,
+                        &updated.render(),
+                        &file.fingerprint,
+                    )
+                    .await?;
+                    report.documents_updated.push(id);
+                }
+                SyncAction::TrashDocument => {
+                    db.delete_document(id.clone()).await?;
+                    forget(&db, project_id, &id).await?;
+                    report.documents_trashed.push(id);
+                }
+                SyncAction::DeleteFile => {
+                    let file = file.expect("only an existing file is deleted");
+                    delete_file(&folder.folder, &file.name).await?;
+                    forget(&db, project_id, &id).await?;
+                    report.files_deleted += 1;
+                }
+                SyncAction::Forget => forget(&db, project_id, &id).await?,
+                SyncAction::Conflict(reason) => report.conflicts.push(MarkdownConflict {
+                    document_id: id,
+                    file_name: file.map(|f| f.name.clone()).unwrap_or_default(),
+                    title: file
+                        .map(|f| f.document.title.clone())
+                        .or_else(|| document.map(|index| documents[index].title.clone()))
+                        .unwrap_or_default(),
+                    reason,
+                }),
+                SyncAction::CreateDocument => {}
+            }
+        }
+
+        for (index, file) in files.iter().enumerate() {
+            if claimed.contains(&index) {
+                continue;
+            }
+            let id = create_document(&db, project_id, &folder.folder, file).await?;
+            report.documents_created.push(id);
+        }
+
+        sqlx::query("UPDATE markdown_sync_folders SET last_synced_at = ?1 WHERE project_id = ?2")
+            .bind(report.synced_at.to_rfc3339())
+            .bind(project_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to record sync: {}", e)))?;
+        Ok(report)
+    }
+
+    /// Settle a conflict by keeping one side: the document overwrites its
+    /// file, or the file overwrites its document. Keeping a side that was
+    /// deleted deletes the other; keeping an edited file whose document
+    /// was trashed brings the document back.
+    pub async fn resolve(
+        &self,
+        project_id: Uuid,
+        document_id: &str,
+        keep: SyncSide,
+    ) -> DatabaseResult<()> {
+        let _syncing = self.syncing.lock().await;
+        let folder = self.require_folder(project_id).await?;
+        let files = read_folder(&folder.folder).await?;
+        let db = self.db_service.read().await;
+        let documents = load_documents(&db, project_id).await?;
+        let bases = load_bases(&db, project_id).await?;
+        let document = documents.iter().find(|d| d.id == document_id);
+        let base = bases.get(document_id);
+        let file = files
+            .iter()
+            .find(|f| f.id.as_deref() == Some(document_id))
+            .or_else(|| {
+                files
+                    .iter()
+                    .find(|f| Some(&f.name) == base.map(|b| &b.file_name))
+            });
+
+        match (keep, document, file) {
+            (SyncSide::Database, Some(document), _) => {
+                let name = match (file, base) {
+                    (Some(file), _) => file.name.clone(),
+                    (None, Some(base)) => base.file_name.clone(),
+                    (None, None) => {
+                        let mut names = files.iter().map(|f| f.name.clone()).collect();
+                        let position = documents
+                            .iter()
+                            .position(|d| d.id == document.id)
+                            .unwrap_or(0);
+                        unique_name(&mut names, position + 1, &document.title)
+                    }
+                };
+                let rendered = document.render();
+                write_file(&folder.folder, &name, &rendered).await?;
+                record(
+                    &db,
+                    project_id,
+                    document_id,
+                    &name,
+                    &rendered,
+                    &fingerprint(&rendered),
+                )
+                .await
+            }
+            (SyncSide::Database, None, file) => {
+                if let Some(file) = file {
+                    delete_file(&folder.folder, &file.name).await?;
+                }
+                forget(&db, project_id, document_id).await
+            }
+            (SyncSide::Folder, Some(_), None) => {
+                db.delete_document(document_id.to_string()).await?;
+                forget(&db, project_id, document_id).await
+            }
+            (SyncSide::Folder, Some(document), Some(file)) => {
+                let updated = update_document(&db, document, &file.document).await?;
+                record(
+                    &db,
+                    project_id,
+                    document_id,
+                    &file.name,
+                    &updated.render(),
+                    &file.fingerprint,
+                )
+                .await
+            }
+            (SyncSide::Folder, None, Some(file)) => {
+                match db
+                    .restore_from_trash(TrashItemKind::Document, document_id)
+                    .await
+                {
+                    Ok(()) => {
+                        let restored = load_documents(&db, project_id).await?;
+                        let document =
+                            restored
+                                .iter()
+                                .find(|d| d.id == document_id)
+                                .ok_or_else(|| DatabaseError::RecordNotFound {
+                                    entity: "document".to_string(),
+                                    id: document_id.to_string(),
+                                })?;
+                        let updated = update_document(&db, document, &file.document).await?;
+                        record(
+                            &db,
+                            project_id,
+                            document_id,
+                            &file.name,
+                            &updated.render(),
+                            &file.fingerprint,
+                        )
+                        .await
+                    }
+                    // Purged from the trash: the file becomes a new document
+                    Err(DatabaseError::RecordNotFound { .. }) => {
+                        forget(&db, project_id, document_id).await?;
+                        create_document(&db, project_id, &folder.folder, file)
+                            .await
+                            .map(|_| ())
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            (SyncSide::Folder, None, None) => forget(&db, project_id, document_id).await,
+        }
+    }
+
+    async fn require_folder(&self, project_id: Uuid) -> DatabaseResult<MarkdownSyncFolder> {
+        self.folder(project_id)
+            .await?
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "Markdown sync folder".to_string(),
+                id: project_id.to_string(),
+            })
+    }
+}
+
+/// A project's documents in binder order, their content as Markdown
+pub(crate) async fn load_documents(
+    db: &EnhancedDatabaseService,
+    project_id: Uuid,
+) -> DatabaseResult<Vec<SyncDocument>> {
+    let binder: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'binder_order'",
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| DatabaseError::Service(format!("Failed to check binder order: {}", e)))?;
+    let sql = if binder > 0 {
+        "SELECT d.id, d.title, d.content, d.document_type, d.metadata FROM documents d LEFT JOIN binder_order b ON b.document_id = d.id
+         WHERE d.project_id = ?1 AND d.is_active = 1
+         ORDER BY b.position IS NULL, b.position, d.created_at, d.title"
+    } else {
+        "SELECT id, title, content, document_type, metadata FROM documents WHERE project_id = ?1 AND is_active = 1 ORDER BY created_at, title"
+    };
+    let rows: Vec<DocumentRow> = sqlx::query_as(sql)
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+    Ok(rows
+        .into_iter()
+        .map(
+            |(id, title, content, document_type, metadata)| SyncDocument {
+                id,
+                title,
+                content: document_markdown(&document_type, content.as_deref().unwrap_or("")),
+                metadata: match metadata.and_then(|m| serde_json::from_str(&m).ok()) {
+                    Some(Value::Object(object)) => object,
+                    _ => Map::new(),
+                },
+            },
+        )
+        .collect())
+}
+
+async fn load_bases(
+    db: &EnhancedDatabaseService,
+    project_id: Uuid,
+) -> DatabaseResult<HashMap<String, SyncBase>> {
+    let rows: Vec<(String, String, String, String)> = sqlx::query_as(
+        "SELECT document_id, file_name, document_fingerprint, file_fingerprint
+         FROM markdown_sync_files WHERE project_id = ?1",
+    )
+    .bind(project_id.to_string())
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| DatabaseError::Service(format!("Failed to load sync state: {}", e)))?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, file_name, document_fingerprint, file_fingerprint)| {
+            (
+                id,
+                SyncBase {
+                    file_name,
+                    document_fingerprint,
+                    file_fingerprint,
+                },
+            )
+        })
+        .collect())
+}
+
+/// The folder's Markdown files, by name
+async fn read_folder(folder: &Path) -> DatabaseResult<Vec<LocalFile>> {
+    let failed =
+        |e: std::io::Error| DatabaseError::Service(format!("Failed to read sync folder: {}", e));
+    let mut entries = tokio::fs::read_dir(folder).await.map_err(failed)?;
+    let mut files = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(failed)? {
+        let path = entry.path();
+        let is_markdown = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("md"));
+        if !is_markdown || !entry.file_type().await.map_err(failed)?.is_file() {
+            continue;
+        }
+        let (Some(name), Some(stem)) = (
+            path.file_name().and_then(|n| n.to_str()),
+            path.file_stem().and_then(|n| n.to_str()),
+        ) else {
+            continue;
+        };
+        let text = tokio::fs::read_to_string(&path).await.map_err(failed)?;
+        let (id, document) = parse_markdown(&text, stem);
+        files.push(LocalFile {
+            name: name.to_string(),
+            id,
+            document,
+            fingerprint: fingerprint(&text),
+        });
+    }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+fn unique_name(names: &mut HashSet<String>, position: usize, title: &str) -> String {
+    let base = file_name_for(position, title);
+    let mut name = base.clone();
+    let mut n = 2;
+    while names.contains(&name) {
+        name = format!("{}-{}.md", base.trim_end_matches(".md"), n);
+        n += 1;
+    }
+    names.insert(name.clone());
+    name
+}
+
+async fn write_file(folder: &Path, name: &str, text: &str) -> DatabaseResult<()> {
+    tokio::fs::write(folder.join(name), text)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to write {}: {}", name, e)))
+}
+
+async fn delete_file(folder: &Path, name: &str) -> DatabaseResult<()> {
+    match tokio::fs::remove_file(folder.join(name)).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(DatabaseError::Service(format!(
+            "Failed to delete {}: {}",
+            name, e
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Overwrite a document's title, text and metadata from its file; the
+/// update is kept in the document's history. The document comes back as
+/// it now renders, which is what the next sync compares against.
+pub(crate) async fn update_document(
+    db: &EnhancedDatabaseService,
+    existing: &SyncDocument,
+    from_file: &SyncDocument,
+) -> DatabaseResult<SyncDocument> {
+    let doc = from_markdown(&from_file.content);
+    let updated = SyncDocument {
+        id: existing.id.clone(),
+        title: from_file.title.clone(),
+        content: to_markdown(&doc),
+        metadata: merge_front_matter(&existing.metadata, from_file.metadata.clone()),
+    };
+    db.update_document(updated.id.clone(), updated.title.clone(), doc.to_string())
+        .await?;
+    set_metadata(db, &updated).await?;
+    Ok(updated)
+}
+
+/// Make a document from Markdown, keeping the given id; the document
+/// comes back as it now renders
+pub(crate) async fn insert_document(
+    db: &EnhancedDatabaseService,
+    project_id: Uuid,
+    document: &SyncDocument,
+) -> DatabaseResult<SyncDocument> {
+    let doc = from_markdown(&document.content);
+    let created = SyncDocument {
+        content: to_markdown(&doc),
+        ..document.clone()
+    };
+    db.create_document(
+        created.id.clone(),
+        project_id.to_string(),
+        created.title.clone(),
+        doc.to_string(),
+    )
+    .await?;
+    set_metadata(db, &created).await?;
+    Ok(created)
+}
+
+/// Make a document from a file that has none, and write the new id into
+/// the file's front matter
+async fn create_document(
+    db: &EnhancedDatabaseService,
+    project_id: Uuid,
+    folder: &Path,
+    file: &LocalFile,
+) -> DatabaseResult<String> {
+    let document = SyncDocument {
+        id: Uuid::new_v4().to_string(),
+        ..file.document.clone()
+    };
+    let document = insert_document(db, project_id, &document).await?;
+    let rendered = document.render();
+    write_file(folder, &file.name, &rendered).await?;
+    record(
+        db,
+        project_id,
+        &document.id,
+        &file.name,
+        &rendered,
+        &fingerprint(&rendered),
+    )
+    .await?;
+    Ok(document.id)
+}
+
+async fn set_metadata(db: &EnhancedDatabaseService, document: &SyncDocument) -> DatabaseResult<()> {
+    sqlx::query("UPDATE documents SET metadata = ?1 WHERE id = ?2")
+        .bind(document.metadata_json())
+        .bind(&document.id)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            DatabaseError::Service(format!("Failed to update document metadata: {}", e))
+        })?;
+    Ok(())
+}
+
+/// Remember how both sides were left
+async fn record(
+    db: &EnhancedDatabaseService,
+    project_id: Uuid,
+    document_id: &str,
+    file_name: &str,
+    rendered: &str,
+    file_fingerprint: &str,
+) -> DatabaseResult<()> {
+    sqlx::query(UPSERT_MARKDOWN_SYNC_FILE_SQL)
+        .bind(project_id.to_string())
+        .bind(document_id)
+        .bind(file_name)
+        .bind(fingerprint(rendered))
+        .bind(file_fingerprint)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to record sync state: {}", e)))?;
+    Ok(())
+}
+
+async fn forget(
+    db: &EnhancedDatabaseService,
+    project_id: Uuid,
+    document_id: &str,
+) -> DatabaseResult<()> {
+    sqlx::query("DELETE FROM markdown_sync_files WHERE project_id = ?1 AND document_id = ?2")
+        .bind(project_id.to_string())
+        .bind(document_id)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to clear sync state: {}", e)))?;
+    Ok(())
+}
+
+async fn forget_files(db: &EnhancedDatabaseService, project_id: Uuid) -> DatabaseResult<()> {
+    sqlx::query("DELETE FROM markdown_sync_files WHERE project_id = ?1")
+        .bind(project_id.to_string())
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to clear sync state: {}", e)))?;
+    Ok(())
+}
+
+fn folder_from_row(row: FolderRow) -> DatabaseResult<MarkdownSyncFolder> {
+    let (project_id, folder, created_at, last_synced_at) = row;
+    let parse_time = |value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| DatabaseError::Service(format!("Invalid timestamp: {}", e)))
+    };
+    Ok(MarkdownSyncFolder {
+        project_id: parse_uuid(&project_id)?,
+        folder: PathBuf::from(folder),
+        created_at: parse_time(&created_at)?,
+        last_synced_at: last_synced_at.as_deref().map(parse_time).transpose()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{prosemirror, DatabaseConfig};
+    use serde_json::json;
+
+    async fn text(db: &Arc<RwLock<EnhancedDatabaseService>>, id: &str) -> Option<String> {
+        let content = db
+            .read()
+            .await
+            .get_document(id.to_string())
+            .await
+            .unwrap()?;
+        Some(prosemirror::document_text("json", &content))
+    }
+
+    #[tokio::test]
+    async fn test_two_way_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query("INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Ashfall', ?2, ?2)")
+            .bind(project.to_string())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        for (id, title) in [("one", "The Harbor"), ("two", "The Lantern")] {
+            let content = json!({ "type": "doc", "content": [{ "type": "paragraph", "content": [
+                { "type": "text", "text": format!("{} ", title) },
+                { "type": "text", "text": "text", "marks": [{ "type": "bold" }] }
+            ]}]});
+            db.create_document(
+                id.to_string(),
+                project.to_string(),
+                title.to_string(),
+                content.to_string(),
+            )
+            .await
+            .unwrap();
+        }
+        let db = Arc::new(RwLock::new(db));
+        let service = MarkdownSyncService::new(db.clone());
+        service.initialize().await.unwrap();
+        let folder = dir.path().join("mirror");
+        service.enable(project, &folder).await.unwrap();
+
+        let report = service.sync(project).await.unwrap();
+        assert_eq!(report.files_written, 2);
+        let harbor = folder.join("001-the-harbor.md");
+        assert!(tokio::fs::read_to_string(&harbor)
+            .await
+            .unwrap()
+            .ends_with("\n\nThe Harbor **text**"));
+
+        // Edited outside, a new scene added and one deleted
+        tokio::fs::write(
+            &harbor,
+            "---\nid: \"one\"\ntitle: The Harbor at Night\npov: Mara\n---\n\nDark *water*.",
+        )
+        .await
+        .unwrap();
+        tokio::fs::write(folder.join("new.md"), "Ash fell.")
+            .await
+            .unwrap();
+        tokio::fs::remove_file(folder.join("002-the-lantern.md"))
+            .await
+            .unwrap();
+        let report = service.sync(project).await.unwrap();
+        assert_eq!(report.documents_updated, ["one"]);
+        assert_eq!(report.documents_trashed, ["two"]);
+        assert_eq!(report.documents_created.len(), 1);
+        assert_eq!(text(&db, "one").await.as_deref(), Some("Dark water."));
+        assert_eq!(text(&db, "two").await, None);
+        {
+            let db = db.read().await;
+            let stored = db.get_document("one".to_string()).await.unwrap().unwrap();
+            let stored: Value = serde_json::from_str(&stored).unwrap();
+            assert_eq!(
+                stored["content"][0]["content"][1]["marks"],
+                json!([{ "type": "italic" }])
+            );
+            let new_file = tokio::fs::read_to_string(folder.join("new.md"))
+                .await
+                .unwrap();
+            assert!(new_file.contains(&report.documents_created[0]));
+        }
+        let quiet = service.sync(project).await.unwrap();
+        assert_eq!(
+            quiet,
+            MarkdownSyncReport {
+                synced_at: quiet.synced_at,
+                ..Default::default()
+            }
+        );
+
+        // Changed on both sides
+        db.read()
+            .await
+            .update_document_content("one", "Still water.")
+            .await
+            .unwrap();
+        tokio::fs::write(&harbor, "---\nid: \"one\"\n---\n\nRough water.")
+            .await
+            .unwrap();
+        let report = service.sync(project).await.unwrap();
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].document_id, "one");
+        service
+            .resolve(project, "one", SyncSide::Folder)
+            .await
+            .unwrap();
+        assert_eq!(text(&db, "one").await.as_deref(), Some("Rough water."));
+        assert!(service.sync(project).await.unwrap().conflicts.is_empty());
+    }
+}
@@ -8,30 +8,119 @@
 //! - Backup and recovery services
 //! - Service factory for dependency management
 
-use serde::{Deserialize, Serialize};
-use sqlx;
-
+pub mod activity_service;
+pub mod ai_log_service;
 pub mod analysis_service;
+pub mod annotation_service;
+pub mod anonymizer_service;
+pub mod attachment_service;
+pub mod autosnapshot;
 pub mod backup_service;
+pub mod beta_reader_service;
+pub mod calendar_service;
+pub mod certification_service;
+pub mod challenge_service;
+pub mod chronology_service;
+pub mod codex_autofill_service;
+pub mod codex_graph_service;
+pub mod codex_relationship_service;
+pub mod codex_transfer_service;
+pub mod content_scan_service;
+pub mod deadline_service;
+pub mod focus_service;
+pub mod document_structure_service;
+pub mod document_template_service;
+pub mod draft_service;
 pub mod enhanced_database_sqlx;
+pub mod export_repository;
+pub mod fixtures;
+pub mod generator_service;
+pub mod git_history_service;
+pub mod hybrid_search_service;
+pub mod journal_service;
+pub mod lexicon_service;
+pub mod lint_packs;
+pub mod local_embeddings;
+pub mod markdown_sync_service;
+pub mod narrative_voice;
+pub mod note_import;
+pub mod parse;
+pub mod profile_service;
 pub mod project_management;
+pub mod prosemirror;
+pub mod prosemirror_markdown;
+pub mod readability;
+pub mod related_notes_service;
+pub mod rename_service;
 pub mod research_service;
 pub mod search_service;
+pub mod serial_service;
 pub mod service_factory;
+pub mod stats_service;
+pub mod story_bible_service;
+pub mod style_sheet_service;
+pub mod submission_service;
+pub mod text_diff;
+pub mod text_match;
+pub mod timeline_service;
+pub mod undo_history_service;
 pub mod vector_embedding;
+pub mod word_usage_service;
+pub mod workspace_service;
 
 pub mod models;
 
 
 // Re-export key types for easier import
+pub use activity_service::ActivityService;
+pub use ai_log_service::AiLogService;
+pub use analysis_service::AnalysisService;
+pub use annotation_service::AnnotationService;
+pub use anonymizer_service::AnonymizerService;
+pub use attachment_service::AttachmentService;
 pub use backup_service::BackupService;
+pub use beta_reader_service::BetaReaderService;
+pub use calendar_service::CalendarService;
+pub use certification_service::CertificationService;
+pub use challenge_service::ChallengeService;
+pub use chronology_service::ChronologyService;
+pub use codex_autofill_service::CodexAutofillService;
+pub use codex_graph_service::CodexGraphService;
+pub use codex_relationship_service::CodexRelationshipService;
+pub use codex_transfer_service::CodexTransferService;
+pub use content_scan_service::ContentScanService;
+pub use deadline_service::DeadlineService;
+pub use focus_service::FocusService;
+pub use document_structure_service::DocumentStructureService;
+pub use document_template_service::DocumentTemplateService;
+pub use draft_service::DraftService;
 pub use enhanced_database_sqlx::DatabaseConfig;
 pub use enhanced_database_sqlx::EnhancedDatabaseService;
+pub use export_repository::ExportRepository;
+pub use generator_service::GeneratorService;
+pub use git_history_service::GitHistoryService;
+pub use hybrid_search_service::HybridSearchService;
+pub use journal_service::JournalService;
+pub use lexicon_service::LexiconService;
+pub use markdown_sync_service::MarkdownSyncService;
+pub use note_import::NoteImportService;
+pub use profile_service::ProfileService;
 pub use project_management::ProjectManagementService;
+pub use related_notes_service::RelatedNotesService;
+pub use rename_service::RenameService;
 pub use research_service::ResearchService;
 pub use search_service::SearchService;
+pub use serial_service::SerialService;
 pub use service_factory::ServiceFactory;
+pub use stats_service::StatsService;
+pub use story_bible_service::StoryBibleService;
+pub use style_sheet_service::StyleSheetService;
+pub use submission_service::SubmissionService;
+pub use timeline_service::TimelineService;
+pub use undo_history_service::UndoHistoryService;
 pub use vector_embedding::VectorEmbeddingService;
+pub use word_usage_service::WordUsageService;
+pub use workspace_service::WorkspaceService;
 
 /// DatabaseService type alias for EnhancedDatabaseService
 pub type DatabaseService = EnhancedDatabaseService;
@@ -39,43 +128,8 @@ pub type DatabaseService = EnhancedDatabaseService;
 // Re-export models
 pub use models::*;
 
-/// Database-related error types
-#[derive(thiserror::Error, Debug, Clone, Serialize, Deserialize)]
-pub enum DatabaseError {
-    #[error("Connection error: {0}")]
-    Connection(String),
-
-    #[error("Configuration error: {0}")]
-    Configuration(String),
-
-    #[error("Integrity check failed: {0}")]
-    IntegrityCheck(String),
-
-    #[error("Migration error: {0}")]
-    Migration(String),
-
-    #[error("Service error: {0}")]
-    Service(String),
-
-    #[error("Validation error: {0}")]
-    ValidationError(String),
-
-    #[error("Not found: {0}")]
-    NotFound(String),
-
-    #[error("Not implemented: {0}")]
-    NotImplemented(String),
-}
-
-// sqlx error conversion
-impl From<sqlx::Error> for DatabaseError {
-    fn from(error: sqlx::Error) -> Self {
-        DatabaseError::Service(format!("SQLx error: {}", error))
-    }
-}
-
-/// Database initialization result type
-pub type DatabaseResult<T> = Result<T, DatabaseError>;
+/// Database errors are the application-wide database error type
+pub use crate::error::{DatabaseError, DatabaseResult};
 
 /// Module initialization helper - now async
 pub async fn initialize_database() -> DatabaseResult<ServiceFactory> {
@@ -0,0 +1,166 @@
+//! Activity Feed Data Models
+//!
+//! A per-project stream of what happened: documents edited, codex entries
+//! changed, exports, backups, AI actions and structural changes. Repeated
+//! activity on the same item close together is folded into one entry so a
+//! morning of autosaves reads as one edit.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What kind of thing happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    DocumentEdited,
+    CodexChanged,
+    Structure,
+    Export,
+    Backup,
+    Ai,
+}
+
+impl ActivityKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityKind::DocumentEdited => "document_edited",
+            ActivityKind::CodexChanged => "codex_changed",
+            ActivityKind::Structure => "structure",
+            ActivityKind::Export => "export",
+            ActivityKind::Backup => "backup",
+            ActivityKind::Ai => "ai",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "document_edited" => Some(ActivityKind::DocumentEdited),
+            "codex_changed" => Some(ActivityKind::CodexChanged),
+            "structure" => Some(ActivityKind::Structure),
+            "export" => Some(ActivityKind::Export),
+            "backup" => Some(ActivityKind::Backup),
+            "ai" => Some(ActivityKind::Ai),
+            _ => None,
+        }
+    }
+
+    /// Singular and plural noun for summaries, e.g. "document edited"
+    pub fn noun(&self) -> (&'static str, &'static str) {
+        match self {
+            ActivityKind::DocumentEdited => ("document edited", "documents edited"),
+            ActivityKind::CodexChanged => ("codex change", "codex changes"),
+            ActivityKind::Structure => ("structural change", "structural changes"),
+            ActivityKind::Export => ("export", "exports"),
+            ActivityKind::Backup => ("backup", "backups"),
+            ActivityKind::Ai => ("AI action", "AI actions"),
+        }
+    }
+}
+
+/// One entry in the feed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityEntry {
+    pub id: Uuid,
+    /// `None` for activity that covers every project, like a full backup
+    pub project_id: Option<Uuid>,
+    pub kind: ActivityKind,
+    /// The document, codex entry or job it concerns; only entries with a
+    /// subject are folded together
+    pub subject_id: Option<String>,
+    pub summary: String,
+    /// How many times it happened since `first_at`
+    pub count: u32,
+    pub first_at: DateTime<Utc>,
+    pub last_at: DateTime<Utc>,
+}
+
+impl ActivityEntry {
+    pub fn new(project_id: Option<Uuid>, kind: ActivityKind, summary: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            kind,
+            subject_id: None,
+            summary: summary.into(),
+            count: 1,
+            first_at: now,
+            last_at: now,
+        }
+    }
+
+    pub fn with_subject(mut self, subject_id: impl Into<String>) -> Self {
+        self.subject_id = Some(subject_id.into());
+        self
+    }
+}
+
+/// Which entries to list
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActivityFilter {
+    /// Every kind when empty
+    #[serde(default)]
+    pub kinds: Vec<ActivityKind>,
+    pub subject_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+/// How many entries of one kind, and how many times it happened
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityCount {
+    pub kind: ActivityKind,
+    /// Distinct items, e.g. documents edited
+    pub items: usize,
+    pub occurrences: u32,
+}
+
+/// What changed in a project since it was last opened
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivitySummary {
+    pub project_id: Uuid,
+    /// When the project was last opened; `None` the first time
+    pub since: Option<DateTime<Utc>>,
+    /// One line, e.g. "3 documents edited, 1 export"
+    pub headline: String,
+    pub counts: Vec<ActivityCount>,
+    /// Newest first
+    pub entries: Vec<ActivityEntry>,
+}
+
+/// Database schema for the activity feed
+pub const CREATE_ACTIVITY_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS activity_feed (
+    id TEXT PRIMARY KEY,
+    project_id TEXT,
+    kind TEXT NOT NULL,
+    subject_id TEXT,
+    summary TEXT NOT NULL,
+    count INTEGER NOT NULL DEFAULT 1,
+    first_at TEXT NOT NULL,
+    last_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_activity_feed_project ON activity_feed(project_id, last_at);
+CREATE INDEX IF NOT EXISTS idx_activity_feed_subject ON activity_feed(kind, subject_id, last_at);
+
+CREATE TABLE IF NOT EXISTS activity_views (
+    project_id TEXT PRIMARY KEY,
+    last_viewed_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+"#;
+
+/// Insert activity SQL
+pub const INSERT_ACTIVITY_SQL: &str = r#"
+INSERT INTO activity_feed (id, project_id, kind, subject_id, summary, count, first_at, last_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+"#;
+
+/// Select activity SQL; filter with a WHERE clause appended by the caller
+pub const SELECT_ACTIVITY_SQL: &str = r#"
+SELECT id, project_id, kind, subject_id, summary, count, first_at, last_at
+FROM activity_feed
+"#;
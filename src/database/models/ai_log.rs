@@ -0,0 +1,80 @@
+//! AI Interaction Log Data Models
+//!
+//! The optional local record of AI requests: what was asked, what came
+//! back and which model answered. Entries are stored with secrets
+//! redacted and only while the privacy controls allow it.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// One logged AI request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AiInteraction {
+    pub id: Uuid,
+    pub project_id: Option<Uuid>,
+    /// What the request was for, such as "ai_request" or "ask_project"
+    pub feature: String,
+    pub model: Option<String>,
+    /// Prompt followed by any context sent with it
+    pub prompt: String,
+    pub response: String,
+    /// Reported by the provider, or estimated at four characters a token
+    pub prompt_tokens: i64,
+    pub response_tokens: i64,
+    /// Secrets replaced with placeholders before the entry was stored
+    pub redactions: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AiInteraction {
+    pub fn new(
+        project_id: Option<Uuid>,
+        feature: impl Into<String>,
+        prompt: impl Into<String>,
+        response: impl Into<String>,
+    ) -> Self {
+        let prompt = prompt.into();
+        let response = response.into();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            feature: feature.into(),
+            model: None,
+            prompt_tokens: estimate_tokens(&prompt),
+            response_tokens: estimate_tokens(&response),
+            prompt,
+            response,
+            redactions: 0,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Rough token count for providers that don't report one
+pub fn estimate_tokens(text: &str) -> i64 {
+    text.chars().count().div_ceil(4) as i64
+}
+
+/// `project_settings` key that turns the log off for one project
+pub const AI_LOG_PROJECT_SETTING: &str = "ai_log_enabled";
+
+/// Database schema for the AI interaction log
+pub const CREATE_AI_LOG_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS ai_interaction_log (
+    id TEXT PRIMARY KEY,
+    project_id TEXT,
+    feature TEXT NOT NULL,
+    model TEXT,
+    prompt TEXT NOT NULL,
+    response TEXT NOT NULL,
+    prompt_tokens INTEGER NOT NULL DEFAULT 0,
+    response_tokens INTEGER NOT NULL DEFAULT 0,
+    redactions INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_interaction_log_project ON ai_interaction_log(project_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_ai_interaction_log_created ON ai_interaction_log(created_at);
+"#;
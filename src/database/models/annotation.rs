@@ -0,0 +1,139 @@
+//! Annotation Data Models
+//!
+//! Comments anchored to a passage of a document, whether written by the
+//! author or imported from beta readers.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Where an annotation came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AnnotationSource {
+    Author,
+    BetaReader,
+    Editor,
+}
+
+impl AnnotationSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnnotationSource::Author => "author",
+            AnnotationSource::BetaReader => "beta_reader",
+            AnnotationSource::Editor => "editor",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "author" => Some(AnnotationSource::Author),
+            "beta_reader" => Some(AnnotationSource::BetaReader),
+            "editor" => Some(AnnotationSource::Editor),
+            _ => None,
+        }
+    }
+}
+
+/// A comment anchored to a passage of a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub document_id: Uuid,
+    /// Byte offset of the anchored passage start
+    pub start: usize,
+    /// Byte offset of the anchored passage end
+    pub end: usize,
+    /// Passage text at the time the annotation was made
+    pub quoted_text: String,
+    pub body: String,
+    pub author_name: String,
+    pub source: AnnotationSource,
+    /// Beta reader who wrote the comment, if any
+    pub reader_id: Option<Uuid>,
+    /// Packet passage label (e.g. "C2.P14") for imported comments
+    pub passage_anchor: Option<String>,
+    pub resolved: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Annotation {
+    pub fn new(
+        project_id: Uuid,
+        document_id: Uuid,
+        (start, end): (usize, usize),
+        quoted_text: String,
+        body: String,
+        author_name: String,
+        source: AnnotationSource,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            document_id,
+            start,
+            end,
+            quoted_text,
+            body,
+            author_name,
+            source,
+            reader_id: None,
+            passage_anchor: None,
+            resolved: false,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Database schema for annotations
+pub const CREATE_ANNOTATION_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS annotations (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    document_id TEXT NOT NULL,
+    start_offset INTEGER NOT NULL,
+    end_offset INTEGER NOT NULL,
+    quoted_text TEXT NOT NULL,
+    body TEXT NOT NULL,
+    author_name TEXT NOT NULL,
+    source TEXT NOT NULL,
+    reader_id TEXT,
+    passage_anchor TEXT,
+    resolved BOOLEAN NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+    FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_annotations_document ON annotations(document_id, start_offset);
+CREATE INDEX IF NOT EXISTS idx_annotations_reader ON annotations(reader_id);
+"#;
+
+/// Insert annotation SQL
+pub const INSERT_ANNOTATION_SQL: &str = r#"
+INSERT INTO annotations (
+    id, project_id, document_id, start_offset, end_offset, quoted_text, body,
+    author_name, source, reader_id, passage_anchor, resolved, created_at, updated_at
+) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+"#;
+
+/// Select annotations for a document SQL
+pub const GET_ANNOTATIONS_BY_DOCUMENT_SQL: &str = r#"
+SELECT id, project_id, document_id, start_offset, end_offset, quoted_text, body,
+       author_name, source, reader_id, passage_anchor, resolved, created_at, updated_at
+FROM annotations WHERE document_id = ?1 ORDER BY start_offset ASC, created_at ASC
+"#;
+
+/// Mark annotation resolved SQL
+pub const RESOLVE_ANNOTATION_SQL: &str = r#"
+UPDATE annotations SET resolved = ?2, updated_at = ?3 WHERE id = ?1
+"#;
+
+/// Delete annotation SQL
+pub const DELETE_ANNOTATION_SQL: &str = r#"
+DELETE FROM annotations WHERE id = ?1
+"#;
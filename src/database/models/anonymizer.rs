@@ -0,0 +1,37 @@
+//! Project Anonymizer Data Models
+//!
+//! A sample copy of a project for sharing, e.g. to reproduce a bug: same
+//! documents, binder order and codex entries, with every word of prose and
+//! every name swapped for a made-up word of the same length.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What to copy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnonymizeRequest {
+    pub project_id: Uuid,
+    /// Name of the copy; defaults to "Sample project"
+    pub name: Option<String>,
+    /// Copy codex entries too, with their titles and text replaced
+    #[serde(default = "default_true")]
+    pub include_codex: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The anonymized copy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnonymizedProject {
+    pub source_project_id: Uuid,
+    pub project_id: Uuid,
+    pub name: String,
+    pub documents: usize,
+    pub codex_entries: usize,
+    /// Words replaced across documents and codex entries
+    pub words: usize,
+    /// Distinct codex names swapped wherever they appeared
+    pub names: usize,
+}
@@ -0,0 +1,289 @@
+//! Attachment Data Models
+//!
+//! Arbitrary files linked to a document or codex entry. The file itself is
+//! kept in the asset store; the row holds its name, type and how exports
+//! should treat it.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What an attachment belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentOwner {
+    Document,
+    CodexEntry,
+    /// A conlang word's pronunciation recording
+    LexiconEntry,
+}
+
+impl AttachmentOwner {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttachmentOwner::Document => "document",
+            AttachmentOwner::CodexEntry => "codex_entry",
+            AttachmentOwner::LexiconEntry => "lexicon_entry",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "document" => Some(AttachmentOwner::Document),
+            "codex_entry" => Some(AttachmentOwner::CodexEntry),
+            "lexicon_entry" => Some(AttachmentOwner::LexiconEntry),
+            _ => None,
+        }
+    }
+}
+
+/// How the frontend can preview an attachment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreviewKind {
+    Image,
+    Pdf,
+    Text,
+    Audio,
+    Video,
+    /// Only downloadable
+    None,
+}
+
+impl PreviewKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PreviewKind::Image => "image",
+            PreviewKind::Pdf => "pdf",
+            PreviewKind::Text => "text",
+            PreviewKind::Audio => "audio",
+            PreviewKind::Video => "video",
+            PreviewKind::None => "none",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "image" => Some(PreviewKind::Image),
+            "pdf" => Some(PreviewKind::Pdf),
+            "text" => Some(PreviewKind::Text),
+            "audio" => Some(PreviewKind::Audio),
+            "video" => Some(PreviewKind::Video),
+            "none" => Some(PreviewKind::None),
+            _ => None,
+        }
+    }
+
+    pub fn from_mime_type(mime_type: &str) -> Self {
+        match mime_type.split('/').next().unwrap_or_default() {
+            "image" => PreviewKind::Image,
+            "audio" => PreviewKind::Audio,
+            "video" => PreviewKind::Video,
+            "text" => PreviewKind::Text,
+            _ if mime_type == "application/pdf" => PreviewKind::Pdf,
+            _ if mime_type == "application/json" => PreviewKind::Text,
+            _ => PreviewKind::None,
+        }
+    }
+}
+
+/// MIME type from the file's leading bytes, falling back to its extension
+pub fn detect_mime_type(file_name: &str, head: &[u8]) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"ID3", "audio/mpeg"),
+        (b"OggS", "audio/ogg"),
+        (b"fLaC", "audio/flac"),
+    ];
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return mime;
+    }
+    if head.len() >= 12 && &head[..4] == b"RIFF" {
+        match &head[8..12] {
+            b"WEBP" => return "image/webp",
+            b"WAVE" => return "audio/wav",
+            _ => {}
+        }
+    }
+    if head.len() >= 12 && &head[4..8] == b"ftyp" {
+        return "video/mp4";
+    }
+
+    let extension = file_name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "svg" => "image/svg+xml",
+        "txt" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "webm" => "video/webm",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "epub" => "application/epub+zip",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// What exports do with an attachment
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportInclusion {
+    /// Left out of exports
+    #[default]
+    Exclude,
+    /// Named in an attachments list
+    List,
+    /// Packaged into the export where the format allows, listed otherwise
+    Embed,
+}
+
+impl ExportInclusion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportInclusion::Exclude => "exclude",
+            ExportInclusion::List => "list",
+            ExportInclusion::Embed => "embed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "exclude" => Some(ExportInclusion::Exclude),
+            "list" => Some(ExportInclusion::List),
+            "embed" => Some(ExportInclusion::Embed),
+            _ => None,
+        }
+    }
+}
+
+/// A file linked to a document or codex entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub owner_kind: AttachmentOwner,
+    pub owner_id: Uuid,
+    pub file_name: String,
+    pub mime_type: String,
+    pub preview: PreviewKind,
+    pub size: u64,
+    /// Asset store hash of the file content
+    pub asset_hash: String,
+    pub description: Option<String>,
+    pub export_inclusion: ExportInclusion,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Database schema for attachments
+pub const CREATE_ATTACHMENT_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS attachments (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    owner_kind TEXT NOT NULL,
+    owner_id TEXT NOT NULL,
+    file_name TEXT NOT NULL,
+    mime_type TEXT NOT NULL,
+    preview TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    asset_hash TEXT NOT NULL,
+    description TEXT,
+    export_inclusion TEXT NOT NULL DEFAULT 'exclude',
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_owner ON attachments(owner_kind, owner_id);
+CREATE INDEX IF NOT EXISTS idx_attachments_asset ON attachments(asset_hash);
+"#;
+
+/// Insert attachment SQL
+pub const INSERT_ATTACHMENT_SQL: &str = r#"
+INSERT INTO attachments (
+    id, project_id, owner_kind, owner_id, file_name, mime_type, preview, size,
+    asset_hash, description, export_inclusion, created_at
+) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+"#;
+
+/// Select attachments of one owner SQL
+pub const GET_ATTACHMENTS_BY_OWNER_SQL: &str = r#"
+SELECT id, project_id, owner_kind, owner_id, file_name, mime_type, preview, size,
+       asset_hash, description, export_inclusion, created_at
+FROM attachments WHERE owner_kind = ?1 AND owner_id = ?2 ORDER BY created_at ASC
+"#;
+
+/// Select attachment by id SQL
+pub const GET_ATTACHMENT_SQL: &str = r#"
+SELECT id, project_id, owner_kind, owner_id, file_name, mime_type, preview, size,
+       asset_hash, description, export_inclusion, created_at
+FROM attachments WHERE id = ?1
+"#;
+
+/// Update description and export inclusion SQL
+pub const UPDATE_ATTACHMENT_SQL: &str = r#"
+UPDATE attachments SET description = ?2, export_inclusion = ?3 WHERE id = ?1
+"#;
+
+/// Delete attachment SQL
+pub const DELETE_ATTACHMENT_SQL: &str = r#"
+DELETE FROM attachments WHERE id = ?1
+"#;
+
+/// Delete attachments whose document is gone or deleted
+pub const DELETE_ORPHANED_DOCUMENT_ATTACHMENTS_SQL: &str = r#"
+DELETE FROM attachments WHERE owner_kind = 'document' AND NOT EXISTS (
+    SELECT 1 FROM documents d WHERE d.id = attachments.owner_id AND d.is_active = 1)
+"#;
+
+/// Delete attachments whose codex entry is gone or deleted
+pub const DELETE_ORPHANED_CODEX_ATTACHMENTS_SQL: &str = r#"
+DELETE FROM attachments WHERE owner_kind = 'codex_entry' AND NOT EXISTS (
+    SELECT 1 FROM codex_entries c WHERE c.id = attachments.owner_id AND c.is_active = 1)
+"#;
+
+/// Delete attachments whose lexicon entry is gone
+pub const DELETE_ORPHANED_LEXICON_ATTACHMENTS_SQL: &str = r#"
+DELETE FROM attachments WHERE owner_kind = 'lexicon_entry' AND NOT EXISTS (
+    SELECT 1 FROM lexicon_entries e WHERE e.id = attachments.owner_id)
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_type_from_content_before_extension() {
+        assert_eq!(
+            detect_mime_type("portrait.txt", b"\x89PNG\r\n\x1a\n...."),
+            "image/png"
+        );
+        assert_eq!(detect_mime_type("notes.md", b"# Notes"), "text/markdown");
+        assert_eq!(detect_mime_type("scan", b"%PDF-1.7\n"), "application/pdf");
+        assert_eq!(
+            detect_mime_type("mystery.bin", b"\0\0"),
+            "application/octet-stream"
+        );
+        assert_eq!(
+            PreviewKind::from_mime_type("image/webp"),
+            PreviewKind::Image
+        );
+        assert_eq!(
+            PreviewKind::from_mime_type("application/pdf"),
+            PreviewKind::Pdf
+        );
+        assert_eq!(
+            PreviewKind::from_mime_type("application/zip"),
+            PreviewKind::None
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Beta Reader Data Models
+//!
+//! Readers who receive watermarked packets, the passage map recorded for
+//! each packet, and the comments ingested back from them.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::publishing::PublishFormat;
+
+/// A beta reader registered on a project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BetaReader {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub name: String,
+    pub email: Option<String>,
+    /// Short identifier printed on every page of the reader's packet
+    pub reader_code: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A passage as it appeared in a packet, used to map comments back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PacketPassage {
+    /// Label printed in the packet, e.g. "C2.P14"
+    pub anchor: String,
+    pub document_id: Uuid,
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// A generated beta reader packet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BetaPacket {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub reader_id: Uuid,
+    pub reader_code: String,
+    pub format: PublishFormat,
+    pub created_at: DateTime<Utc>,
+    pub passages: Vec<PacketPassage>,
+    #[serde(skip)]
+    pub bytes: Vec<u8>,
+}
+
+/// A reader comment parsed from a returned CSV or Markdown form
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReaderComment {
+    pub anchor: String,
+    pub comment: String,
+}
+
+/// Outcome of importing a reader's comments
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommentImportResult {
+    pub imported: usize,
+    /// Comments whose passage anchor was not found in the reader's packet
+    pub unmatched: Vec<ReaderComment>,
+    /// Comments whose passage text has since been edited away
+    pub relocated_failed: usize,
+}
+
+/// Database schema for beta readers and packets
+pub const CREATE_BETA_READER_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS beta_readers (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    email TEXT,
+    reader_code TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS beta_packets (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    reader_id TEXT NOT NULL,
+    format TEXT NOT NULL,
+    passages TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (reader_id) REFERENCES beta_readers(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_beta_readers_project ON beta_readers(project_id);
+CREATE INDEX IF NOT EXISTS idx_beta_packets_reader ON beta_packets(reader_id, created_at);
+"#;
+
+/// Insert beta reader SQL
+pub const INSERT_BETA_READER_SQL: &str = r#"
+INSERT INTO beta_readers (id, project_id, name, email, reader_code, created_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+"#;
+
+/// Select beta readers for a project SQL
+pub const GET_BETA_READERS_SQL: &str = r#"
+SELECT id, project_id, name, email, reader_code, created_at
+FROM beta_readers WHERE project_id = ?1 ORDER BY name ASC
+"#;
+
+/// Select a beta reader by code SQL
+pub const GET_BETA_READER_BY_CODE_SQL: &str = r#"
+SELECT id, project_id, name, email, reader_code, created_at
+FROM beta_readers WHERE reader_code = ?1
+"#;
+
+/// Insert beta packet SQL
+pub const INSERT_BETA_PACKET_SQL: &str = r#"
+INSERT INTO beta_packets (id, project_id, reader_id, format, passages, created_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+"#;
+
+/// Select the passage maps of a reader's packets, newest first
+pub const GET_PACKET_PASSAGES_SQL: &str = r#"
+SELECT passages FROM beta_packets WHERE reader_id = ?1 ORDER BY created_at DESC
+"#;
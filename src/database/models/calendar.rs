@@ -0,0 +1,656 @@
+//! Calendar Data Models
+//!
+//! User-defined calendars for fictional worlds: month names and lengths,
+//! weekdays, an era, and leap year rules. Dates convert to a day number, so
+//! timelines can be ordered, measured and checked without any mapping to the
+//! Gregorian calendar.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::codex::TimeData;
+use crate::database::{DatabaseError, DatabaseResult};
+
+/// Format used when a calendar doesn't set one
+pub const DEFAULT_DATE_FORMAT: &str = "{day} {month} {year} {era}";
+
+/// A month of a calendar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalendarMonth {
+    pub name: String,
+    pub days: u32,
+    /// Days added to this month in leap years
+    #[serde(default)]
+    pub leap_days: u32,
+}
+
+impl CalendarMonth {
+    pub fn new(name: impl Into<String>, days: u32) -> Self {
+        Self {
+            name: name.into(),
+            days,
+            leap_days: 0,
+        }
+    }
+}
+
+/// Which years are leap years, counted from year 1 of the calendar. The
+/// Gregorian rule is every 4 years, except every 100, unless every 400.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeapRule {
+    pub every: u32,
+    #[serde(default)]
+    pub except_every: Option<u32>,
+    #[serde(default)]
+    pub unless_every: Option<u32>,
+}
+
+impl LeapRule {
+    pub fn is_leap_year(&self, year: i64) -> bool {
+        let divides = |n: Option<u32>| n.is_some_and(|n| year.rem_euclid(n as i64) == 0);
+        divides(Some(self.every)) && (!divides(self.except_every) || divides(self.unless_every))
+    }
+
+    /// Leap years from year 1 through `year`; negative for years before 1
+    fn leap_years_through(&self, year: i64) -> i64 {
+        let count = |n: Option<u32>| n.map_or(0, |n| year.div_euclid(n as i64));
+        count(Some(self.every)) - count(self.except_every) + count(self.unless_every)
+    }
+}
+
+/// A date in a calendar. Years count from 1; year -1 is the year before
+/// year 1, written with the calendar's `before_era_name`. Months and days
+/// count from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CalendarDate {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl CalendarDate {
+    pub fn new(year: i64, month: u32, day: u32) -> Self {
+        Self { year, month, day }
+    }
+}
+
+/// A calendar system of a project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarSystem {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub name: String,
+    pub months: Vec<CalendarMonth>,
+    /// Weekday names, if the world has weeks
+    #[serde(default)]
+    pub weekdays: Vec<String>,
+    /// Index into `weekdays` of the first day of year 1
+    #[serde(default)]
+    pub epoch_weekday: usize,
+    #[serde(default)]
+    pub leap_rule: Option<LeapRule>,
+    /// Era of years from 1 on, e.g. "AR" for "after the Reckoning"
+    #[serde(default)]
+    pub era_name: String,
+    /// Era of years before 1; such years can't be written if unset
+    #[serde(default)]
+    pub before_era_name: Option<String>,
+    /// Where the first day of year 1 falls on the project's shared day line,
+    /// so events dated in different calendars can be ordered together
+    #[serde(default)]
+    pub epoch_offset: i64,
+    /// Date format with `{day}`, `{month}`, `{month_number}`, `{year}`,
+    /// `{era}` and `{weekday}` placeholders
+    #[serde(default = "default_format")]
+    pub format: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn default_format() -> String {
+    DEFAULT_DATE_FORMAT.to_string()
+}
+
+impl CalendarSystem {
+    pub fn new(project_id: Uuid, name: impl Into<String>, months: Vec<CalendarMonth>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            name: name.into(),
+            months,
+            weekdays: Vec::new(),
+            epoch_weekday: 0,
+            leap_rule: None,
+            era_name: String::new(),
+            before_era_name: None,
+            epoch_offset: 0,
+            format: default_format(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Check the definition is usable
+    pub fn validate(&self) -> DatabaseResult<()> {
+        let invalid = |message: &str| Err(DatabaseError::ValidationError(message.to_string()));
+        if self.name.trim().is_empty() {
+            return invalid("Calendar name cannot be empty");
+        }
+        if self.months.is_empty() {
+            return invalid("A calendar needs at least one month");
+        }
+        if self.months.iter().any(|m| m.name.trim().is_empty()) {
+            return invalid("Month names cannot be empty");
+        }
+        if self.months.iter().any(|m| m.days == 0) {
+            return invalid("Every month needs at least one day");
+        }
+        if !self.weekdays.is_empty() && self.epoch_weekday >= self.weekdays.len() {
+            return invalid("The first weekday must be one of the weekdays");
+        }
+        if let Some(rule) = &self.leap_rule {
+            if rule.every == 0 {
+                return invalid("Leap years must come at least every year");
+            }
+            if self.leap_days() == 0 {
+                return invalid("A leap rule needs a month with leap days");
+            }
+            // The exceptions must line up with the rule they refine
+            // for the leap year count to hold
+            if let Some(except) = rule.except_every {
+                if except == 0 || except % rule.every != 0 {
+                    return invalid("Leap exceptions must be a multiple of the leap cycle");
+                }
+                if let Some(unless) = rule.unless_every {
+                    if unless == 0 || unless % except != 0 {
+                        return invalid("Leap restorations must be a multiple of the exceptions");
+                    }
+                }
+            } else if rule.unless_every.is_some() {
+                return invalid("A leap restoration needs an exception to restore");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_leap_year(&self, year: i64) -> bool {
+        self.leap_rule
+            .as_ref()
+            .is_some_and(|rule| rule.is_leap_year(astronomical(year)))
+    }
+
+    /// Days in a common year
+    pub fn common_year_days(&self) -> i64 {
+        self.months.iter().map(|m| m.days as i64).sum()
+    }
+
+    fn leap_days(&self) -> i64 {
+        self.months.iter().map(|m| m.leap_days as i64).sum()
+    }
+
+    pub fn year_days(&self, year: i64) -> i64 {
+        self.common_year_days()
+            + if self.is_leap_year(year) {
+                self.leap_days()
+            } else {
+                0
+            }
+    }
+
+    pub fn month_days(&self, year: i64, month: u32) -> Option<u32> {
+        let m = self.months.get((month as usize).checked_sub(1)?)?;
+        Some(
+            m.days
+                + if self.is_leap_year(year) {
+                    m.leap_days
+                } else {
+                    0
+                },
+        )
+    }
+
+    pub fn is_valid_date(&self, date: &CalendarDate) -> bool {
+        date.year != 0
+            && (date.year > 0 || self.before_era_name.is_some())
+            && date.day >= 1
+            && self
+                .month_days(date.year, date.month)
+                .is_some_and(|days| date.day <= days)
+    }
+
+    fn check_date(&self, date: &CalendarDate) -> DatabaseResult<()> {
+        if self.is_valid_date(date) {
+            Ok(())
+        } else {
+            Err(DatabaseError::ValidationError(format!(
+                "{}-{}-{} is not a date in {}",
+                date.year, date.month, date.day, self.name
+            )))
+        }
+    }
+
+    /// Days from the first day of year 1 to a date
+    pub fn day_number(&self, date: &CalendarDate) -> DatabaseResult<i64> {
+        self.check_date(date)?;
+        let before_months: i64 = (1..date.month)
+            .map(|m| self.month_days(date.year, m).unwrap_or(0) as i64)
+            .sum();
+        Ok(self.days_before_year(astronomical(date.year)) + before_months + date.day as i64 - 1)
+    }
+
+    /// The date a number of days from the first day of year 1
+    pub fn date_from_day_number(&self, day_number: i64) -> DatabaseResult<CalendarDate> {
+        self.validate()?;
+        // Estimate from the average year length, then settle on the year
+        let every = self.leap_rule.as_ref().map_or(1, |r| r.every as i64);
+        let average = self.common_year_days() * every
+            + if self.leap_rule.is_some() {
+                self.leap_days()
+            } else {
+                0
+            };
+        let mut year = (day_number * every).div_euclid(average) + 1;
+        while self.days_before_year(year) > day_number {
+            year -= 1;
+        }
+        while self.days_before_year(year + 1) <= day_number {
+            year += 1;
+        }
+
+        let year = from_astronomical(year);
+        if year < 0 && self.before_era_name.is_none() {
+            return Err(DatabaseError::ValidationError(format!(
+                "{} has no years before year 1",
+                self.name
+            )));
+        }
+        let mut remaining = day_number - self.days_before_year(astronomical(year));
+        let mut month = 1;
+        loop {
+            let days = self.month_days(year, month).unwrap_or(0) as i64;
+            if remaining < days {
+                break;
+            }
+            remaining -= days;
+            month += 1;
+        }
+        Ok(CalendarDate::new(year, month, remaining as u32 + 1))
+    }
+
+    /// Days before `year` (astronomical numbering) since year 1 began
+    fn days_before_year(&self, year: i64) -> i64 {
+        let leap_years = self
+            .leap_rule
+            .as_ref()
+            .map_or(0, |rule| rule.leap_years_through(year - 1));
+        (year - 1) * self.common_year_days() + leap_years * self.leap_days()
+    }
+
+    /// Day on the project's shared day line
+    pub fn shared_day(&self, date: &CalendarDate) -> DatabaseResult<i64> {
+        Ok(self.day_number(date)? + self.epoch_offset)
+    }
+
+    pub fn add_days(&self, date: &CalendarDate, days: i64) -> DatabaseResult<CalendarDate> {
+        self.date_from_day_number(self.day_number(date)? + days)
+    }
+
+    /// Add whole months, keeping the day where the target month allows it
+    pub fn add_months(&self, date: &CalendarDate, months: i64) -> DatabaseResult<CalendarDate> {
+        self.check_date(date)?;
+        let count = self.months.len() as i64;
+        let index = astronomical(date.year) * count + date.month as i64 - 1 + months;
+        let year = from_astronomical(index.div_euclid(count));
+        let month = index.rem_euclid(count) as u32 + 1;
+        let days = self.month_days(year, month).unwrap_or(1);
+        let moved = CalendarDate::new(year, month, date.day.min(days));
+        self.check_date(&moved)?;
+        Ok(moved)
+    }
+
+    pub fn add_years(&self, date: &CalendarDate, years: i64) -> DatabaseResult<CalendarDate> {
+        self.add_months(date, years * self.months.len() as i64)
+    }
+
+    /// Days from `from` to `to`; negative if `to` is earlier
+    pub fn days_between(&self, from: &CalendarDate, to: &CalendarDate) -> DatabaseResult<i64> {
+        Ok(self.day_number(to)? - self.day_number(from)?)
+    }
+
+    pub fn weekday(&self, date: &CalendarDate) -> DatabaseResult<Option<&str>> {
+        if self.weekdays.is_empty() {
+            return Ok(None);
+        }
+        let index = (self.day_number(date)? + self.epoch_weekday as i64)
+            .rem_euclid(self.weekdays.len() as i64);
+        Ok(Some(&self.weekdays[index as usize]))
+    }
+
+    /// Write a date in the calendar's format
+    pub fn format_date(&self, date: &CalendarDate) -> DatabaseResult<String> {
+        let weekday = self.weekday(date)?.unwrap_or_default();
+        let era = if date.year < 0 {
+            self.before_era_name.as_deref().unwrap_or_default()
+        } else {
+            &self.era_name
+        };
+        let formatted = self
+            .format
+            .replace("{weekday}", weekday)
+            .replace("{day}", &date.day.to_string())
+            .replace("{month_number}", &date.month.to_string())
+            .replace("{month}", &self.months[date.month as usize - 1].name)
+            .replace("{year}", &date.year.abs().to_string())
+            .replace("{era}", era);
+        Ok(formatted.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+
+    /// Read a date written as `year-month-day` (`-12-3-1` for years before
+    /// 1) or with the month's name, as in "3 Frostfall 1204 AR"
+    pub fn parse_date(&self, text: &str) -> DatabaseResult<CalendarDate> {
+        let text = text.trim();
+        let date = parse_numeric(text)
+            .or_else(|| self.parse_named(text))
+            .ok_or_else(|| {
+                DatabaseError::ValidationError(format!(
+                    "Could not read \"{}\" as a date in {}",
+                    text, self.name
+                ))
+            })?;
+        self.check_date(&date)?;
+        Ok(date)
+    }
+
+    fn parse_named(&self, text: &str) -> Option<CalendarDate> {
+        let lower = text.to_lowercase();
+        // Longest name first, so "Second Frost" wins over "Frost"
+        let (month, start, len) = self
+            .months
+            .iter()
+            .enumerate()
+            .filter_map(|(i, m)| {
+                let name = m.name.to_lowercase();
+                lower.find(&name).map(|at| (i as u32 + 1, at, name.len()))
+            })
+            .max_by_key(|&(_, _, len)| len)?;
+
+        let numbers = |s: &str| -> Vec<i64> {
+            s.split(|c: char| !c.is_ascii_digit())
+                .filter_map(|n| n.parse().ok())
+                .collect()
+        };
+        let before = numbers(&lower[..start]);
+        let after_text = &lower[start + len..];
+        let after = numbers(after_text);
+        let (day, year) = match (before.as_slice(), after.as_slice()) {
+            ([day], [year, ..]) => (*day, *year),
+            ([], [day, year, ..]) => (*day, *year),
+            _ => return None,
+        };
+
+        let before_era = self
+            .before_era_name
+            .as_ref()
+            .map(|e| e.to_lowercase())
+            .filter(|e| !e.is_empty());
+        let year = match before_era {
+            Some(era) if after_text.split_whitespace().any(|w| w == era) => -year,
+            _ => year,
+        };
+        Some(CalendarDate::new(year, month, u32::try_from(day).ok()?))
+    }
+}
+
+/// Years with a year 0 for the arithmetic: year -1 becomes 0
+fn astronomical(year: i64) -> i64 {
+    if year < 0 {
+        year + 1
+    } else {
+        year
+    }
+}
+
+fn from_astronomical(year: i64) -> i64 {
+    if year <= 0 {
+        year - 1
+    } else {
+        year
+    }
+}
+
+fn parse_numeric(text: &str) -> Option<CalendarDate> {
+    let (negative, rest) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let mut parts = rest.split('-');
+    let year: i64 = parts.next()?.trim().parse().ok()?;
+    let month = parts.next()?.trim().parse().ok()?;
+    let day = parts.next()?.trim().parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(CalendarDate::new(
+        if negative { -year } else { year },
+        month,
+        day,
+    ))
+}
+
+/// A dated event to place on the timeline, such as a Time codex entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    pub id: Uuid,
+    pub title: String,
+    pub time: TimeData,
+}
+
+/// Why an event couldn't be placed on the timeline, or sits oddly on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineIssueKind {
+    /// No calendar set and the project has several
+    NoCalendar,
+    UnknownCalendar,
+    /// A date that is missing from its calendar or can't be read
+    InvalidDate,
+    EndBeforeStart,
+    /// An end date without a start date
+    NoStart,
+    /// Dated relative to an event that no longer exists or has no date
+    UnknownAnchor,
+    /// Dated relative to an event that is, in turn, dated relative to it
+    AnchorCycle,
+}
+
+/// A problem with one timeline event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineIssue {
+    pub event_id: Uuid,
+    pub kind: TimelineIssueKind,
+    pub message: String,
+}
+
+/// An event placed on the timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    pub event_id: Uuid,
+    pub title: String,
+    pub calendar_id: Uuid,
+    pub start: CalendarDate,
+    pub end: Option<CalendarDate>,
+    /// Start and end on the project's shared day line
+    pub start_day: i64,
+    pub end_day: Option<i64>,
+    /// Formatted in the event's calendar
+    pub start_label: String,
+    pub end_label: Option<String>,
+}
+
+/// A project's dated events in order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Timeline {
+    pub entries: Vec<TimelineEntry>,
+    /// Events left off the timeline, or placed with a warning
+    pub issues: Vec<TimelineIssue>,
+}
+
+/// Database schema for calendars; the definition is stored as JSON
+pub const CREATE_CALENDARS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS calendars (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    definition TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_calendars_project ON calendars(project_id);
+"#;
+
+/// Insert or replace calendar SQL
+pub const UPSERT_CALENDAR_SQL: &str = r#"
+INSERT INTO calendars (id, project_id, name, definition, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+ON CONFLICT(id) DO UPDATE SET
+    name = excluded.name,
+    definition = excluded.definition,
+    updated_at = excluded.updated_at
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gregorian() -> CalendarSystem {
+        let mut calendar = CalendarSystem::new(
+            Uuid::new_v4(),
+            "Gregorian",
+            [
+                ("January", 31),
+                ("February", 28),
+                ("March", 31),
+                ("April", 30),
+                ("May", 31),
+                ("June", 30),
+                ("July", 31),
+                ("August", 31),
+                ("September", 30),
+                ("October", 31),
+                ("November", 30),
+                ("December", 31),
+            ]
+            .into_iter()
+            .map(|(name, days)| CalendarMonth::new(name, days))
+            .collect(),
+        );
+        calendar.months[1].leap_days = 1;
+        calendar.leap_rule = Some(LeapRule {
+            every: 4,
+            except_every: Some(100),
+            unless_every: Some(400),
+        });
+        calendar.weekdays = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        calendar.era_name = "AD".to_string();
+        calendar.before_era_name = Some("BC".to_string());
+        calendar.format = "{weekday} {day} {month} {year} {era}".to_string();
+        calendar
+    }
+
+    #[test]
+    fn test_gregorian_rules_match_chrono() {
+        let calendar = gregorian();
+        calendar.validate().unwrap();
+        assert!(calendar.is_leap_year(2000) && !calendar.is_leap_year(1900));
+        // 1 BC is a leap year in the proleptic Gregorian calendar
+        assert!(calendar.is_leap_year(-1));
+
+        let epoch = chrono::NaiveDate::from_ymd_opt(1, 1, 1).unwrap();
+        for (y, m, d) in [
+            (2024, 2, 29),
+            (1900, 3, 1),
+            (1, 1, 1),
+            (-1, 12, 31),
+            (-401, 2, 29),
+        ] {
+            let date = CalendarDate::new(y, m, d);
+            let chrono_year = if y < 0 { y + 1 } else { y } as i32;
+            let expected = chrono::NaiveDate::from_ymd_opt(chrono_year, m, d).unwrap() - epoch;
+            let days = calendar.day_number(&date).unwrap();
+            assert_eq!(days, expected.num_days(), "{:?}", date);
+            assert_eq!(calendar.date_from_day_number(days).unwrap(), date);
+        }
+
+        let date = CalendarDate::new(2024, 1, 31);
+        assert_eq!(
+            calendar.add_months(&date, 1).unwrap(),
+            CalendarDate::new(2024, 2, 29)
+        );
+        assert_eq!(
+            calendar.add_days(&date, 366).unwrap(),
+            CalendarDate::new(2025, 1, 31)
+        );
+        assert_eq!(
+            calendar.add_years(&CalendarDate::new(1, 3, 1), -1).unwrap(),
+            CalendarDate::new(-1, 3, 1)
+        );
+        assert_eq!(
+            calendar
+                .format_date(&CalendarDate::new(2024, 2, 29))
+                .unwrap(),
+            "Thu 29 February 2024 AD"
+        );
+        assert!(!calendar.is_valid_date(&CalendarDate::new(2023, 2, 29)));
+    }
+
+    #[test]
+    fn test_fictional_calendar_parsing_and_validation() {
+        let mut calendar = CalendarSystem::new(
+            Uuid::new_v4(),
+            "Reckoning",
+            vec![
+                CalendarMonth::new("Frost", 40),
+                CalendarMonth::new("Second Frost", 40),
+                CalendarMonth::new("Thaw", 45),
+            ],
+        );
+        calendar.era_name = "AR".to_string();
+        assert_eq!(
+            calendar.parse_date("3 Second Frost 1204 AR").unwrap(),
+            CalendarDate::new(1204, 2, 3)
+        );
+        assert_eq!(
+            calendar.parse_date("1204-3-45").unwrap(),
+            CalendarDate::new(1204, 3, 45)
+        );
+        assert!(calendar.parse_date("-5-1-1").is_err());
+        assert!(calendar.parse_date("Thaw 46, 12").is_err());
+        assert_eq!(
+            calendar
+                .days_between(&CalendarDate::new(1, 1, 1), &CalendarDate::new(2, 1, 1))
+                .unwrap(),
+            125
+        );
+
+        calendar.leap_rule = Some(LeapRule {
+            every: 3,
+            except_every: None,
+            unless_every: None,
+        });
+        assert!(calendar.validate().is_err());
+        calendar.months[2].leap_days = 2;
+        calendar.validate().unwrap();
+        assert_eq!(calendar.year_days(3), 127);
+        assert_eq!(
+            calendar.date_from_day_number(125 * 3 + 2 + 124).unwrap(),
+            CalendarDate::new(4, 3, 45)
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! Word Count Certification Data Models
+//!
+//! Signed word count certificates for challenges and contests: the count
+//! of every document and the project total, each document's content hash
+//! and the time of issue, signed so the certificate can be checked later.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Header line of the signed certificate text; bump when the layout changes
+pub const CERTIFICATE_FORMAT: &str = "herding-cats word count certificate v1";
+
+/// One document's count as certified
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertifiedDocument {
+    pub document_id: Uuid,
+    pub title: String,
+    pub word_count: u64,
+    /// Hex SHA-256 of the document's text
+    pub content_hash: String,
+}
+
+/// A signed word count for a project at a point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordCountCertificate {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub project_name: String,
+    pub issued_at: DateTime<Utc>,
+    pub documents: Vec<CertifiedDocument>,
+    pub total_words: u64,
+    /// Hex SHA-256 over the document hashes in order
+    pub content_hash: String,
+    /// Hex HMAC-SHA256 of `signed_text`
+    pub signature: String,
+}
+
+impl WordCountCertificate {
+    /// The text the signature covers, one field per line
+    pub fn signed_text(&self) -> String {
+        let mut lines = vec![
+            CERTIFICATE_FORMAT.to_string(),
+            self.id.to_string(),
+            self.project_id.to_string(),
+            self.project_name.clone(),
+            self.issued_at.to_rfc3339(),
+            self.total_words.to_string(),
+            self.content_hash.clone(),
+        ];
+        lines.extend(self.documents.iter().map(|d| {
+            format!(
+                "{}\t{}\t{}\t{}",
+                d.document_id, d.word_count, d.content_hash, d.title
+            )
+        }));
+        lines.join("\n")
+    }
+}
+
+/// A stored certificate checked against its signature and the project's
+/// current text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateVerification {
+    pub certificate_id: Uuid,
+    /// The certificate was signed by this installation and not altered
+    pub signature_valid: bool,
+    pub certified_words: u64,
+    pub current_words: u64,
+    /// Every certified document still has the same text
+    pub text_unchanged: bool,
+    /// Certified documents whose text changed or that are gone
+    pub changed_documents: Vec<Uuid>,
+}
+
+/// Database schema for issued certificates
+pub const CREATE_CERTIFICATES_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS word_count_certificates (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    issued_at TEXT NOT NULL,
+    total_words INTEGER NOT NULL,
+    certificate TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_word_count_certificates_project ON word_count_certificates(project_id, issued_at);
+"#;
+
+/// Insert certificate SQL
+pub const INSERT_CERTIFICATE_SQL: &str = r#"
+INSERT INTO word_count_certificates (id, project_id, issued_at, total_words, certificate)
+VALUES (?1, ?2, ?3, ?4, ?5)
+"#;
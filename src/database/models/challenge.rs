@@ -0,0 +1,134 @@
+//! Writing Challenge Data Models
+//!
+//! NaNoWriMo-style challenges: a word target over a date range, progress
+//! taken from the project's writing sessions, and the pace, projection and
+//! "words needed today" shown on the challenge dashboard.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Share of the target, in percent, celebrated with a notification
+pub const CHALLENGE_MILESTONES: &[u32] = &[10, 25, 50, 75, 100];
+
+/// A word target to reach between two dates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Challenge {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub name: String,
+    pub target_words: u64,
+    pub start_date: NaiveDate,
+    /// Last day of the challenge, inclusive
+    pub end_date: NaiveDate,
+    /// Milestones already celebrated, in percent
+    #[serde(default)]
+    pub milestones_reached: Vec<u32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Challenge {
+    pub fn new(
+        project_id: Uuid,
+        name: impl Into<String>,
+        target_words: u64,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            name: name.into(),
+            target_words,
+            start_date,
+            end_date,
+            milestones_reached: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Days in the challenge
+    pub fn days(&self) -> i64 {
+        (self.end_date - self.start_date).num_days() + 1
+    }
+}
+
+/// Words written on one day of a challenge
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChallengeDay {
+    pub date: NaiveDate,
+    pub words: i64,
+    /// Total written by the end of the day
+    pub cumulative: i64,
+    /// Total the even pace calls for by the end of the day
+    pub par: u64,
+}
+
+/// Where a challenge stands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChallengeDashboard {
+    pub challenge: Challenge,
+    pub words_written: i64,
+    pub percent_complete: f64,
+    pub days_total: i64,
+    /// Days from the start up to and including today, within the challenge
+    pub days_elapsed: i64,
+    /// Days after today still in the challenge
+    pub days_left: i64,
+    /// Words a day so far
+    pub daily_average: f64,
+    /// Total at the end at the current average
+    pub projected_total: u64,
+    /// Day the target is reached at the current average
+    pub projected_finish: Option<NaiveDate>,
+    pub written_today: i64,
+    /// Words still to write today to stay on an even pace to the end
+    pub words_needed_today: u64,
+    /// Ahead of (positive) or behind (negative) the even pace
+    pub ahead_by: i64,
+    pub longest_streak: u32,
+    pub best_day: Option<ChallengeDay>,
+    pub days: Vec<ChallengeDay>,
+}
+
+/// Database schema for challenges
+pub const CREATE_CHALLENGES_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS writing_challenges (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    target_words INTEGER NOT NULL,
+    start_date TEXT NOT NULL,
+    end_date TEXT NOT NULL,
+    milestones_reached TEXT NOT NULL DEFAULT '[]',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_writing_challenges_project ON writing_challenges(project_id, start_date);
+"#;
+
+/// Insert or replace challenge SQL
+pub const UPSERT_CHALLENGE_SQL: &str = r#"
+INSERT INTO writing_challenges (id, project_id, name, target_words, start_date, end_date,
+                                milestones_reached, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+ON CONFLICT(id) DO UPDATE SET
+    name = excluded.name,
+    target_words = excluded.target_words,
+    start_date = excluded.start_date,
+    end_date = excluded.end_date,
+    milestones_reached = excluded.milestones_reached,
+    updated_at = excluded.updated_at
+"#;
+
+/// Select challenges SQL; filter with a WHERE clause appended by the caller
+pub const SELECT_CHALLENGES_SQL: &str = r#"
+SELECT id, project_id, name, target_words, start_date, end_date, milestones_reached,
+       created_at, updated_at
+FROM writing_challenges
+"#;
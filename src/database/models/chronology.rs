@@ -0,0 +1,84 @@
+//! Chronology Data Models
+//!
+//! Scene dates kept in document metadata, the check for scenes told out of
+//! chronological order without being marked as flashbacks, and the
+//! chronological reading order suggested from them.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::calendar::TimelineIssue;
+use super::codex::TimeData;
+use crate::publishing::PublishFormat;
+
+/// When a scene happens, stored under the `time` and `flashback` keys of its
+/// document's metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SceneTime {
+    /// Dates read the same way as a Time codex entry's
+    #[serde(default)]
+    pub time: Option<TimeData>,
+    /// The scene deliberately goes back in time
+    #[serde(default)]
+    pub flashback: bool,
+}
+
+/// A scene on the project's shared day line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChronologyScene {
+    pub document_id: Uuid,
+    pub title: String,
+    /// 0-based position in the binder
+    pub position: usize,
+    /// None for scenes without a usable date
+    pub start_day: Option<i64>,
+    pub end_day: Option<i64>,
+    pub start_label: Option<String>,
+    pub flashback: bool,
+}
+
+/// A scene told after one that happens later, without a flashback flag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChronologyConflict {
+    pub document_id: Uuid,
+    pub title: String,
+    pub start_label: String,
+    /// The latest scene told before it
+    pub after_id: Uuid,
+    pub after_title: String,
+    pub after_label: String,
+    /// How far back in time the scene jumps
+    pub days_back: i64,
+    pub message: String,
+}
+
+/// A project's scenes checked for chronological order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChronologyReport {
+    pub project_id: Uuid,
+    /// In binder order
+    pub scenes: Vec<ChronologyScene>,
+    pub conflicts: Vec<ChronologyConflict>,
+    /// Scene dates that couldn't be read
+    pub date_issues: Vec<TimelineIssue>,
+}
+
+/// The scenes in the order their events happen. Undated scenes stay right
+/// after the scene they follow in the binder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadingOrder {
+    pub project_id: Uuid,
+    pub document_ids: Vec<Uuid>,
+    /// Scenes whose position differs from the binder's
+    pub moved: usize,
+}
+
+/// A manuscript compiled in chronological reading order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChronologicalExport {
+    pub title: String,
+    pub format: PublishFormat,
+    pub order: ReadingOrder,
+    #[serde(skip)]
+    pub bytes: Vec<u8>,
+}
@@ -11,6 +11,8 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::calendar::CalendarDate;
+
 /// Main codex entry representing any codex item
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodexEntry {
@@ -151,6 +153,17 @@ impl CodexEntryType {
         }
     }
 
+    /// Name stored in the database, e.g. "character_sheet"
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CodexEntryType::StorySummary => "story_summary",
+            CodexEntryType::CharacterSheet => "character_sheet",
+            CodexEntryType::Object => "object",
+            CodexEntryType::Time => "time",
+            CodexEntryType::Place => "place",
+        }
+    }
+
     /// Get all entry types
     pub fn all_types() -> &'static [CodexEntryType] {
         &[
@@ -187,6 +200,16 @@ impl CodexStatus {
         }
     }
 
+    /// Name stored in the database, e.g. "in_review"
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CodexStatus::Draft => "draft",
+            CodexStatus::InReview => "in_review",
+            CodexStatus::Final => "final",
+            CodexStatus::Archived => "archived",
+        }
+    }
+
     /// Get color class for the status (for UI)
     pub fn color_class(self) -> &'static str {
         match self {
@@ -199,7 +222,7 @@ impl CodexStatus {
 }
 
 /// Character-specific data for enhanced character sheets
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CharacterData {
     /// Character name variations
     pub names: Vec<String>,
@@ -230,6 +253,10 @@ pub struct CharacterData {
 
     /// Inventory/items
     pub inventory: Vec<String>,
+
+    /// Where the character first appears in the manuscript
+    #[serde(default)]
+    pub first_appearance: Option<String>,
 }
 
 /// Relationship between characters
@@ -258,7 +285,7 @@ pub enum RelationshipSentiment {
 }
 
 /// Place-specific data for enhanced location tracking
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PlaceData {
     /// Alternative names for this location
     pub alternative_names: Vec<String>,
@@ -283,6 +310,10 @@ pub struct PlaceData {
 
     /// Current events affecting this location
     pub current_events: Vec<String>,
+
+    /// Where the place first appears in the manuscript
+    #[serde(default)]
+    pub first_appearance: Option<String>,
 }
 
 /// Time-specific data for timeline management
@@ -308,6 +339,19 @@ pub struct TimeData {
 
     /// Timeline era/age
     pub era: Option<String>,
+
+    /// Calendar the dates are in; the project's only calendar if unset
+    #[serde(default)]
+    pub calendar_id: Option<Uuid>,
+
+    /// Start date in that calendar; `start_time` is read with the
+    /// calendar when unset
+    #[serde(default)]
+    pub start_date: Option<CalendarDate>,
+
+    /// End date in that calendar; `end_time` is read when unset
+    #[serde(default)]
+    pub end_date: Option<CalendarDate>,
 }
 
 /// Object-specific data for item tracking
@@ -499,23 +543,60 @@ pub struct CodexStatistics {
 }
 
 /// Result of a codex import operation
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct CodexImportResult {
     /// Number of entries successfully imported
     pub imported_count: usize,
 
+    /// Number of existing entries updated from duplicates
+    #[serde(default)]
+    pub updated_count: usize,
+
+    /// Number of duplicates left alone
+    #[serde(default)]
+    pub skipped_count: usize,
+
     /// Number of entries that failed to import
     pub failed_count: usize,
 
     /// List of errors encountered
     pub errors: Vec<String>,
 
+    /// What happened, or would happen in a dry run, to each entry
+    #[serde(default)]
+    pub items: Vec<CodexImportItem>,
+
+    /// Whether nothing was written
+    #[serde(default)]
+    pub dry_run: bool,
+
     /// Time taken for the import
     pub duration_ms: u128,
 }
 
+/// One entry of an import
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodexImportItem {
+    /// Row or position in the file, from 1
+    pub line: usize,
+    pub title: String,
+    pub entry_type: CodexEntryType,
+    pub action: CodexImportAction,
+    /// The existing entry a duplicate matched
+    pub existing_id: Option<Uuid>,
+}
+
+/// What an import does with an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodexImportAction {
+    Create,
+    Update,
+    Skip,
+}
+
 /// Result of a codex export operation
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct CodexExportResult {
     /// Number of entries exported
     pub exported_count: usize,
@@ -529,3 +610,21 @@ pub struct CodexExportResult {
     /// Time taken for the export
     pub duration_ms: u128,
 }
+
+/// Codex entries live outside the main schema; this creates the table
+/// with the columns the codex service uses
+pub const CREATE_CODEX_ENTRIES_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS codex_entries (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    entry_type TEXT NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'draft',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    is_active INTEGER NOT NULL DEFAULT 1,
+    metadata TEXT,
+    sort_order INTEGER NOT NULL DEFAULT 0
+)
+"#;
@@ -0,0 +1,91 @@
+//! Codex Autofill Data Models
+//!
+//! Facts about a character or place gathered from the chapters it appears
+//! in, and the changes to its codex data they suggest. Changes are only
+//! proposed; nothing is written until the writer accepts them.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::codex::CodexEntryType;
+
+/// Chapters to scan for facts about an entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutofillRequest {
+    pub project_id: Uuid,
+    pub entry_id: Uuid,
+    /// Documents to scan; every active document when empty
+    #[serde(default)]
+    pub document_ids: Vec<Uuid>,
+}
+
+/// What a fact says about the entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FactKind {
+    FirstAppearance,
+    Appearance,
+    Relationship,
+    Climate,
+    Population,
+    Culture,
+    History,
+}
+
+/// The sentence a fact was taken from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Evidence {
+    pub document_id: Uuid,
+    pub title: String,
+    /// Place of the document in the binder, from 1
+    pub position: usize,
+    /// Character offsets of the sentence in the document's text
+    pub start_char: usize,
+    pub end_char: usize,
+    pub sentence: String,
+}
+
+/// A fact found in the text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractedFact {
+    pub kind: FactKind,
+    pub value: String,
+    /// The other entry, for relationships
+    pub related_entry: Option<Uuid>,
+    pub evidence: Evidence,
+}
+
+/// A proposed change to one field of the entry's data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// Field of `CharacterData` or `PlaceData`, e.g. "physical_description"
+    pub field: String,
+    pub current: Value,
+    pub proposed: Value,
+    pub evidence: Vec<Evidence>,
+}
+
+/// Changes suggested for an entry, for review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutofillProposal {
+    pub entry_id: Uuid,
+    pub entry_type: CodexEntryType,
+    pub title: String,
+    /// When the entry was last changed; a proposal for an entry changed
+    /// since can't be applied
+    pub entry_updated_at: DateTime<Utc>,
+    pub documents_scanned: usize,
+    pub facts: Vec<ExtractedFact>,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Accepted changes from a proposal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutofillApply {
+    pub proposal: AutofillProposal,
+    /// Fields to write; every proposed change when empty
+    #[serde(default)]
+    pub accepted_fields: Vec<String>,
+}
@@ -0,0 +1,379 @@
+//! Codex Graph Query Models
+//!
+//! Traversal queries over the codex: entries are nodes, character
+//! relationships, recorded links and mentions of one entry in another are
+//! edges, and mentions in documents are appearances. Queries can be built
+//! as a `GraphQuery` or written in a small query language:
+//!
+//! ```text
+//! find characters within 2 hops of "Mara" via sibling, rival in "Act 2"
+//! find places in documents 3-7 limit 10
+//! find any within 1 hop of "The Lantern"
+//! ```
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::codex::CodexEntryType;
+use crate::database::{DatabaseError, DatabaseResult};
+
+/// Relationship name of edges made by one entry mentioning another
+pub const MENTIONS: &str = "mentions";
+
+/// Hops allowed in one query
+pub const MAX_HOPS: usize = 6;
+
+/// A traversal query over a project's codex
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphQuery {
+    pub project_id: Uuid,
+    /// Kinds of entry to return; any kind when empty
+    #[serde(default)]
+    pub entry_types: Vec<CodexEntryType>,
+    /// Entry to start from, by ID or title; every entry matches when unset
+    #[serde(default)]
+    pub start: Option<String>,
+    #[serde(default = "default_hops")]
+    pub max_hops: usize,
+    /// Relationship types to follow, `mentions` included; all when empty
+    #[serde(default)]
+    pub via: Vec<String>,
+    /// Only entries that appear in these documents
+    #[serde(default)]
+    pub appears_in: Option<DocumentScope>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+fn default_hops() -> usize {
+    1
+}
+
+impl GraphQuery {
+    pub fn new(project_id: Uuid) -> Self {
+        Self {
+            project_id,
+            entry_types: Vec::new(),
+            start: None,
+            max_hops: default_hops(),
+            via: Vec::new(),
+            appears_in: None,
+            limit: None,
+        }
+    }
+
+    /// Parse the query language described in the module docs
+    pub fn parse(project_id: Uuid, text: &str) -> DatabaseResult<Self> {
+        let invalid = |message: String| DatabaseError::ValidationError(message);
+        let tokens = tokenize(text)?;
+        let mut query = Self::new(project_id);
+        let mut tokens = tokens.into_iter().peekable();
+
+        match tokens.next() {
+            Some(Token::Word(word)) if word == "find" => {}
+            _ => return Err(invalid("A query starts with 'find'".to_string())),
+        }
+        for word in words_until_keyword(&mut tokens) {
+            if word == "any" || word == "entries" {
+                continue;
+            }
+            let entry_type = entry_type_from_word(&word)
+                .ok_or_else(|| invalid(format!("Unknown kind of entry: {}", word)))?;
+            if !query.entry_types.contains(&entry_type) {
+                query.entry_types.push(entry_type);
+            }
+        }
+
+        while let Some(token) = tokens.next() {
+            let Token::Word(keyword) = token else {
+                return Err(invalid(
+                    "Expected a keyword before a quoted name".to_string(),
+                ));
+            };
+            match keyword.as_str() {
+                "within" => {
+                    query.max_hops = number(tokens.next(), "within")?;
+                    match tokens.next() {
+                        Some(Token::Word(w)) if w == "hop" || w == "hops" => {}
+                        _ => return Err(invalid("Expected 'hops' after the count".to_string())),
+                    }
+                    match tokens.next() {
+                        Some(Token::Word(w)) if w == "of" => {}
+                        _ => return Err(invalid("Expected 'of' after 'hops'".to_string())),
+                    }
+                    query.start = Some(name(tokens.next(), "of")?);
+                }
+                "via" => {
+                    query.via = words_until_keyword(&mut tokens);
+                    if query.via.is_empty() {
+                        return Err(invalid(
+                            "Expected relationship types after 'via'".to_string(),
+                        ));
+                    }
+                }
+                "in" => match tokens.next() {
+                    Some(Token::Quoted(text)) => {
+                        query.appears_in = Some(DocumentScope::TitleContains { text });
+                    }
+                    Some(Token::Word(w)) if w == "documents" || w == "chapters" => {
+                        let range = match tokens.next() {
+                            Some(Token::Word(range)) => range,
+                            _ => return Err(invalid(format!("Expected a range after '{}'", w))),
+                        };
+                        let (first, last) = range.split_once('-').unwrap_or((&range, &range));
+                        let parse = |n: &str| {
+                            n.parse::<usize>()
+                                .ok()
+                                .filter(|n| *n > 0)
+                                .ok_or_else(|| invalid(format!("Invalid range: {}", range)))
+                        };
+                        query.appears_in = Some(DocumentScope::BinderRange {
+                            first: parse(first)?,
+                            last: parse(last)?,
+                        });
+                    }
+                    _ => {
+                        return Err(invalid(
+                            "Expected a quoted title or 'documents <range>' after 'in'".to_string(),
+                        ))
+                    }
+                },
+                "limit" => query.limit = Some(number(tokens.next(), "limit")?),
+                other => return Err(invalid(format!("Unknown keyword: {}", other))),
+            }
+        }
+
+        query.validate()?;
+        Ok(query)
+    }
+
+    pub fn validate(&self) -> DatabaseResult<()> {
+        if self.max_hops == 0 || self.max_hops > MAX_HOPS {
+            return Err(DatabaseError::ValidationError(format!(
+                "Hops must be between 1 and {}",
+                MAX_HOPS
+            )));
+        }
+        if let Some(DocumentScope::BinderRange { first, last }) = &self.appears_in {
+            if first > last {
+                return Err(DatabaseError::ValidationError(
+                    "The document range ends before it starts".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Documents an entry must appear in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DocumentScope {
+    /// Documents whose title contains the text, e.g. "Act 2"
+    TitleContains {
+        text: String,
+    },
+    /// Documents at these binder positions, from 1, inclusive
+    BinderRange {
+        first: usize,
+        last: usize,
+    },
+    Documents {
+        ids: Vec<Uuid>,
+    },
+}
+
+/// What made an edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// A relationship on a character sheet
+    Relationship,
+    /// A link recorded between two entries
+    Link,
+    /// One entry's text naming another
+    Mention,
+}
+
+/// A directed link between two codex entries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: Uuid,
+    pub target: Uuid,
+    pub relationship: String,
+    pub kind: EdgeKind,
+}
+
+/// A document an entry is named in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Appearance {
+    pub document_id: Uuid,
+    pub title: String,
+    /// Place in the binder, from 1
+    pub position: usize,
+    pub mentions: usize,
+}
+
+/// An entry found by a query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphMatch {
+    pub entry_id: Uuid,
+    pub title: String,
+    pub entry_type: CodexEntryType,
+    /// Hops from the start entry; 0 without one
+    pub hops: usize,
+    /// Edges walked from the start entry, in order
+    pub path: Vec<GraphEdge>,
+    /// Documents in the query's scope that name the entry
+    pub appearances: Vec<Appearance>,
+}
+
+/// Everything that points at an entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backlinks {
+    pub entry_id: Uuid,
+    /// Documents that name the entry, in binder order
+    pub documents: Vec<Appearance>,
+    /// Relationships and mentions from other entries
+    pub entries: Vec<GraphEdge>,
+}
+
+/// Entry type as stored in the codex table
+pub fn entry_type_from_db(value: &str) -> Option<CodexEntryType> {
+    match value {
+        "story_summary" => Some(CodexEntryType::StorySummary),
+        "character_sheet" => Some(CodexEntryType::CharacterSheet),
+        "object" => Some(CodexEntryType::Object),
+        "time" => Some(CodexEntryType::Time),
+        "place" => Some(CodexEntryType::Place),
+        _ => None,
+    }
+}
+
+fn entry_type_from_word(word: &str) -> Option<CodexEntryType> {
+    match word.trim_end_matches('s') {
+        "character" => Some(CodexEntryType::CharacterSheet),
+        "place" | "location" => Some(CodexEntryType::Place),
+        "object" | "item" => Some(CodexEntryType::Object),
+        "time" | "event" => Some(CodexEntryType::Time),
+        "summary" | "summarie" => Some(CodexEntryType::StorySummary),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Lowercased word
+    Word(String),
+    Quoted(String),
+}
+
+const KEYWORDS: &[&str] = &["within", "via", "in", "limit"];
+
+fn tokenize(text: &str) -> DatabaseResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || c == ',' {
+            chars.next();
+        } else if c == '"' || c == '\u{201c}' {
+            chars.next();
+            let mut quoted = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') | Some('\u{201d}') => break,
+                    Some(c) => quoted.push(c),
+                    None => {
+                        return Err(DatabaseError::ValidationError(
+                            "Unclosed quote in query".to_string(),
+                        ))
+                    }
+                }
+            }
+            tokens.push(Token::Quoted(quoted));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == ',' || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word.to_lowercase()));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Words and quoted names up to the next keyword
+fn words_until_keyword(tokens: &mut std::iter::Peekable<std::vec::IntoIter<Token>>) -> Vec<String> {
+    let mut words = Vec::new();
+    while let Some(token) = tokens.peek() {
+        match token {
+            Token::Word(word) if KEYWORDS.contains(&word.as_str()) => break,
+            Token::Word(word) | Token::Quoted(word) => words.push(word.to_lowercase()),
+        }
+        tokens.next();
+    }
+    words
+}
+
+fn number(token: Option<Token>, after: &str) -> DatabaseResult<usize> {
+    match token {
+        Some(Token::Word(word)) => word.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| DatabaseError::ValidationError(format!("Expected a number after '{}'", after)))
+}
+
+fn name(token: Option<Token>, after: &str) -> DatabaseResult<String> {
+    match token {
+        Some(Token::Quoted(name)) | Some(Token::Word(name)) if !name.trim().is_empty() => {
+            Ok(name.trim().to_string())
+        }
+        _ => Err(DatabaseError::ValidationError(format!(
+            "Expected an entry after '{}'",
+            after
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let project = Uuid::new_v4();
+        let query = GraphQuery::parse(
+            project,
+            r#"find characters within 2 hops of "Mara Voss" via sibling, rival in "Act 2""#,
+        )
+        .unwrap();
+        assert_eq!(query.entry_types, vec![CodexEntryType::CharacterSheet]);
+        assert_eq!(query.start.as_deref(), Some("Mara Voss"));
+        assert_eq!(query.max_hops, 2);
+        assert_eq!(query.via, vec!["sibling", "rival"]);
+        assert_eq!(
+            query.appears_in,
+            Some(DocumentScope::TitleContains {
+                text: "Act 2".to_string()
+            })
+        );
+
+        let query =
+            GraphQuery::parse(project, "find places, objects in documents 3-7 limit 5").unwrap();
+        assert_eq!(query.entry_types.len(), 2);
+        assert_eq!(
+            query.appears_in,
+            Some(DocumentScope::BinderRange { first: 3, last: 7 })
+        );
+        assert_eq!(query.limit, Some(5));
+
+        assert!(GraphQuery::parse(project, "find dragons").is_err());
+        assert!(GraphQuery::parse(project, "find any within 9 hops of Mara").is_err());
+        assert!(GraphQuery::parse(project, "find any in documents 7-3").is_err());
+        assert!(GraphQuery::parse(project, r#"find any in "Act 2"#).is_err());
+    }
+}
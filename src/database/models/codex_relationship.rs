@@ -0,0 +1,289 @@
+//! Codex Relationship Models
+//!
+//! Typed, directional links between codex entries that the author records
+//! by hand, such as "sibling of", "located in" or "owns", with free-form
+//! details. A link reads from its source to its target: Mara "owns" the
+//! Lantern. Traversals for the relationship map can follow links either
+//! way.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use uuid::Uuid;
+
+/// A link from one codex entry to another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodexRelationship {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub source_id: Uuid,
+    pub target_id: Uuid,
+    /// Reads from source to target, e.g. "located in"; stored lowercase
+    pub relationship_type: String,
+    /// Details such as since when, or how close the two are
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CodexRelationship {
+    pub fn new(
+        project_id: Uuid,
+        source_id: Uuid,
+        target_id: Uuid,
+        relationship_type: &str,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            source_id,
+            target_id,
+            relationship_type: relationship_type.trim().to_lowercase(),
+            metadata: HashMap::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.relationship_type.trim().is_empty() {
+            return Err("Relationship type cannot be empty".to_string());
+        }
+        if self.source_id == self.target_id {
+            return Err("An entry can't be related to itself".to_string());
+        }
+        Ok(())
+    }
+
+    /// The entry at the other end from `entry_id`
+    pub fn other_end(&self, entry_id: Uuid) -> Uuid {
+        if self.source_id == entry_id {
+            self.target_id
+        } else {
+            self.source_id
+        }
+    }
+}
+
+/// Which links of an entry to follow
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Links from the entry
+    Outgoing,
+    /// Links to the entry
+    Incoming,
+    #[default]
+    Both,
+}
+
+/// An entry linked to another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Neighbor {
+    pub entry_id: Uuid,
+    pub relationship: CodexRelationship,
+    /// Whether the link points away from the entry asked about
+    pub outgoing: bool,
+}
+
+/// An entry on the relationship map
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MapNode {
+    pub entry_id: Uuid,
+    pub title: String,
+    pub entry_type: String,
+}
+
+/// Entries and the links between them, for drawing the map
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RelationshipMap {
+    pub nodes: Vec<MapNode>,
+    pub relationships: Vec<CodexRelationship>,
+}
+
+/// A project's links, for traversal
+#[derive(Debug, Clone, Default)]
+pub struct RelationshipGraph {
+    links: Vec<CodexRelationship>,
+    /// Indexes into `links` of the links touching each entry
+    by_entry: HashMap<Uuid, Vec<usize>>,
+}
+
+impl RelationshipGraph {
+    pub fn new(links: Vec<CodexRelationship>) -> Self {
+        let mut by_entry: HashMap<Uuid, Vec<usize>> = HashMap::new();
+        for (index, link) in links.iter().enumerate() {
+            by_entry.entry(link.source_id).or_default().push(index);
+            by_entry.entry(link.target_id).or_default().push(index);
+        }
+        Self { links, by_entry }
+    }
+
+    pub fn links(&self) -> &[CodexRelationship] {
+        &self.links
+    }
+
+    /// Entries one link away, through links of the given types (any type
+    /// when empty)
+    pub fn neighbors(
+        &self,
+        entry_id: Uuid,
+        direction: Direction,
+        types: &[String],
+    ) -> Vec<Neighbor> {
+        self.steps(entry_id, direction)
+            .filter(|(link, _)| types.is_empty() || types.contains(&link.relationship_type))
+            .map(|(link, outgoing)| Neighbor {
+                entry_id: link.other_end(entry_id),
+                relationship: link.clone(),
+                outgoing,
+            })
+            .collect()
+    }
+
+    /// The fewest links leading from one entry to another, in order; None
+    /// when they aren't connected
+    pub fn path(
+        &self,
+        from: Uuid,
+        to: Uuid,
+        direction: Direction,
+    ) -> Option<Vec<CodexRelationship>> {
+        let mut came_by: HashMap<Uuid, Option<&CodexRelationship>> = HashMap::from([(from, None)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(entry_id) = queue.pop_front() {
+            if entry_id == to {
+                let mut path = Vec::new();
+                let mut at = to;
+                while let Some(Some(link)) = came_by.get(&at) {
+                    path.push((*link).clone());
+                    at = link.other_end(at);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (link, _) in self.steps(entry_id, direction) {
+                let next = link.other_end(entry_id);
+                if let std::collections::hash_map::Entry::Vacant(slot) = came_by.entry(next) {
+                    slot.insert(Some(link));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Entries reachable from `entry_id` following links either way, the
+    /// entry included, and the links among them
+    pub fn component(&self, entry_id: Uuid) -> (Vec<Uuid>, Vec<CodexRelationship>) {
+        let mut seen = HashSet::from([entry_id]);
+        let mut entries = vec![entry_id];
+        let mut queue = VecDeque::from([entry_id]);
+        while let Some(at) = queue.pop_front() {
+            for (link, _) in self.steps(at, Direction::Both) {
+                let next = link.other_end(at);
+                if seen.insert(next) {
+                    entries.push(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+        let links = self
+            .links
+            .iter()
+            .filter(|link| seen.contains(&link.source_id))
+            .cloned()
+            .collect();
+        (entries, links)
+    }
+
+    /// Links that can be followed from an entry, with whether each points
+    /// away from it
+    fn steps(
+        &self,
+        entry_id: Uuid,
+        direction: Direction,
+    ) -> impl Iterator<Item = (&CodexRelationship, bool)> {
+        self.by_entry
+            .get(&entry_id)
+            .into_iter()
+            .flatten()
+            .map(|&index| &self.links[index])
+            .map(move |link| (link, link.source_id == entry_id))
+            .filter(move |(_, outgoing)| match direction {
+                Direction::Outgoing => *outgoing,
+                Direction::Incoming => !*outgoing,
+                Direction::Both => true,
+            })
+    }
+}
+
+/// Database schema for codex relationships; metadata is stored as JSON
+pub const CREATE_CODEX_RELATIONSHIPS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS codex_relationships (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    source_id TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    relationship_type TEXT NOT NULL,
+    metadata TEXT NOT NULL DEFAULT '{}',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_codex_relationships_project ON codex_relationships(project_id);
+CREATE INDEX IF NOT EXISTS idx_codex_relationships_source ON codex_relationships(source_id);
+CREATE INDEX IF NOT EXISTS idx_codex_relationships_target ON codex_relationships(target_id);
+"#;
+
+/// Create or update a relationship
+pub const UPSERT_CODEX_RELATIONSHIP_SQL: &str = r#"
+INSERT INTO codex_relationships (id, project_id, source_id, target_id, relationship_type, metadata, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+ON CONFLICT(id) DO UPDATE SET
+    source_id = excluded.source_id,
+    target_id = excluded.target_id,
+    relationship_type = excluded.relationship_type,
+    metadata = excluded.metadata,
+    updated_at = excluded.updated_at
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traversal() {
+        let project = Uuid::new_v4();
+        let [mara, tobin, ashe, lantern, harbor] = [(); 5].map(|_| Uuid::new_v4());
+        let links = vec![
+            CodexRelationship::new(project, mara, tobin, "Sibling of"),
+            CodexRelationship::new(project, mara, lantern, "owns"),
+            CodexRelationship::new(project, lantern, harbor, "located in"),
+            CodexRelationship::new(project, ashe, harbor, "located in"),
+        ];
+        assert_eq!(links[0].relationship_type, "sibling of");
+        let graph = RelationshipGraph::new(links);
+
+        let owned = graph.neighbors(mara, Direction::Outgoing, &["owns".to_string()]);
+        assert_eq!(owned.len(), 1);
+        assert_eq!(owned[0].entry_id, lantern);
+        assert!(graph.neighbors(mara, Direction::Incoming, &[]).is_empty());
+        assert_eq!(graph.neighbors(harbor, Direction::Incoming, &[]).len(), 2);
+
+        // Ashe only points at the harbor, so the way there goes backwards
+        let path = graph.path(mara, ashe, Direction::Both).unwrap();
+        let types: Vec<_> = path.iter().map(|l| l.relationship_type.as_str()).collect();
+        assert_eq!(types, ["owns", "located in", "located in"]);
+        assert_eq!(graph.path(mara, ashe, Direction::Outgoing), None);
+        assert_eq!(graph.path(mara, mara, Direction::Both), Some(vec![]));
+
+        let (entries, links) = graph.component(tobin);
+        assert_eq!(entries.len(), 5);
+        assert_eq!(links.len(), 4);
+        assert_eq!(graph.component(Uuid::new_v4()).1.len(), 0);
+    }
+}
@@ -464,6 +464,23 @@ impl CodexService for CodexDatabaseService {
     }
 
     async fn create_enhanced_entry(&self, entry: &EnhancedCodexEntry) -> DatabaseResult<Uuid> {
+        // Time, character and place data are kept in the metadata so
+        // timelines and the relationship graph can be built from them
+        // without the separate tables
+        let data = match (&entry.time_data, &entry.character_data, &entry.place_data) {
+            (Some(time_data), _, _) => Some(serde_json::to_string(time_data)),
+            (None, Some(character_data), _) => Some(serde_json::to_string(character_data)),
+            (None, None, Some(place_data)) => Some(serde_json::to_string(place_data)),
+            (None, None, None) => None,
+        };
+        if let (Some(data), None) = (data, &entry.base.metadata) {
+            let mut base = entry.base.clone();
+            base.metadata = Some(data.map_err(|e| {
+                DatabaseError::ValidationError(format!("Invalid entry data: {}", e))
+            })?);
+            return self.create_entry(&base).await;
+        }
+
         // Create the base entry first
         let entry_id = self.create_entry(&entry.base).await;
 
@@ -486,6 +503,23 @@ impl CodexService for CodexDatabaseService {
                 base_entry.content.clone(),
             );
 
+            let metadata = base_entry.metadata.as_deref();
+            match base_entry.entry_type {
+                CodexEntryType::Time => {
+                    enhanced_entry.time_data =
+                        metadata.and_then(|metadata| serde_json::from_str(metadata).ok());
+                }
+                CodexEntryType::CharacterSheet => {
+                    enhanced_entry.character_data =
+                        metadata.and_then(|metadata| serde_json::from_str(metadata).ok());
+                }
+                CodexEntryType::Place => {
+                    enhanced_entry.place_data =
+                        metadata.and_then(|metadata| serde_json::from_str(metadata).ok());
+                }
+                _ => {}
+            }
+
             // Copy base data - clone to avoid partial move
             let new_entry = base_entry;
             enhanced_entry.base = new_entry;
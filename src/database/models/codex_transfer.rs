@@ -0,0 +1,609 @@
+//! Codex Import/Export Models
+//!
+//! The formats codex entries move in and out of a project in. Our own JSON
+//! and CSV round-trip every field; Scrivener outline exports (CSV) and
+//! Plottr files (.pltr) can be read so a series bible kept elsewhere can be
+//! brought over. Columns and keys are matched to entry fields by name, and
+//! a field map can point any column at any field. Anything that doesn't
+//! match a field is kept in the entry's metadata rather than dropped.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use super::codex::{CodexEntry, CodexEntryType, CodexStatus};
+
+/// Marks a JSON file as one of our codex exports
+pub const CODEX_EXPORT_FORMAT: &str = "herding-cats-codex";
+/// Version of the JSON export layout
+pub const CODEX_EXPORT_VERSION: u32 = 1;
+/// Columns of a CSV export, in order
+pub const CODEX_CSV_COLUMNS: [&str; 6] = [
+    "type",
+    "title",
+    "status",
+    "sort_order",
+    "content",
+    "metadata",
+];
+
+/// A file format for codex entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodexFormat {
+    #[default]
+    Json,
+    Csv,
+    /// Outline exported from Scrivener as CSV; import only
+    Scrivener,
+    /// Plottr project file; characters and places are imported
+    Plottr,
+}
+
+impl CodexFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CodexFormat::Json => "json",
+            CodexFormat::Csv => "csv",
+            CodexFormat::Scrivener => "scrivener",
+            CodexFormat::Plottr => "plottr",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            CodexFormat::Json => "json",
+            CodexFormat::Csv | CodexFormat::Scrivener => "csv",
+            CodexFormat::Plottr => "pltr",
+        }
+    }
+
+    /// Whether entries can be written in this format
+    pub fn can_export(self) -> bool {
+        matches!(self, CodexFormat::Json | CodexFormat::Csv)
+    }
+}
+
+/// What to do with an imported entry that has the same type and title as
+/// one already in the codex
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// Leave the existing entry alone
+    #[default]
+    Skip,
+    /// Overwrite the existing entry's content and status, and merge in
+    /// the imported metadata
+    Update,
+    /// Import it as a separate entry
+    KeepBoth,
+}
+
+/// How to read an import
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodexImportOptions {
+    #[serde(default)]
+    pub format: CodexFormat,
+    /// Column or key name to field: "title", "content", "type", "status",
+    /// "sort_order", "metadata" or "ignore"; any other field name keeps the
+    /// value in metadata under that name. Names not listed are matched by
+    /// the usual headers.
+    #[serde(default)]
+    pub field_map: HashMap<String, String>,
+    /// Type of entries the file doesn't give one for
+    #[serde(default)]
+    pub default_type: Option<CodexEntryType>,
+    #[serde(default)]
+    pub duplicates: DuplicatePolicy,
+    /// Check the file and report what would happen without writing
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// An entry read from a file, before it's matched against the codex
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedEntry {
+    /// Row or position in the file, from 1
+    pub line: usize,
+    pub entry_type: CodexEntryType,
+    pub title: String,
+    pub content: String,
+    pub status: CodexStatus,
+    pub sort_order: Option<i32>,
+    pub metadata: Map<String, Value>,
+}
+
+/// Entries read from a file, and the ones that couldn't be
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedImport {
+    pub entries: Vec<ImportedEntry>,
+    pub errors: Vec<String>,
+}
+
+/// A JSON export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodexExportFile {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub entries: Vec<ExportedEntry>,
+}
+
+/// An entry as it's exported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedEntry {
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    pub title: String,
+    pub content: String,
+    pub status: String,
+    pub sort_order: i32,
+    /// The entry's metadata; a string when it isn't JSON
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+impl From<&CodexEntry> for ExportedEntry {
+    fn from(entry: &CodexEntry) -> Self {
+        let metadata = match entry.metadata.as_deref().map(str::trim) {
+            None | Some("") => Value::Null,
+            Some(text) => serde_json::from_str(text).unwrap_or(Value::String(text.to_string())),
+        };
+        Self {
+            entry_type: entry.entry_type.as_str().to_string(),
+            title: entry.title.clone(),
+            content: entry.content.clone(),
+            status: entry.status.as_str().to_string(),
+            sort_order: entry.sort_order,
+            metadata,
+        }
+    }
+}
+
+/// Entries as a JSON export
+pub fn export_json(entries: &[CodexEntry]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&CodexExportFile {
+        format: CODEX_EXPORT_FORMAT.to_string(),
+        version: CODEX_EXPORT_VERSION,
+        exported_at: Utc::now(),
+        entries: entries.iter().map(ExportedEntry::from).collect(),
+    })
+}
+
+/// Entries as CSV, one row each, metadata as JSON text
+pub fn export_csv(entries: &[CodexEntry]) -> String {
+    let mut csv = CODEX_CSV_COLUMNS.join(",");
+    csv.push_str("\r\n");
+    for entry in entries.iter().map(ExportedEntry::from) {
+        let metadata = match &entry.metadata {
+            Value::Null => String::new(),
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        let fields = [
+            entry.entry_type,
+            entry.title,
+            entry.status,
+            entry.sort_order.to_string(),
+            entry.content,
+            metadata,
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Entry field a column or key is read into
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Field {
+    Title,
+    Content,
+    Type,
+    Status,
+    SortOrder,
+    Metadata,
+    Ignore,
+    /// Kept in metadata under this name
+    Extra(String),
+}
+
+fn field_for(name: &str, options: &CodexImportOptions) -> Field {
+    let target = options
+        .field_map
+        .iter()
+        .find(|(from, _)| from.trim().eq_ignore_ascii_case(name.trim()))
+        .map(|(_, to)| to.as_str());
+    if let Some(target) = target {
+        return match normalize_name(target).as_str() {
+            "title" => Field::Title,
+            "content" => Field::Content,
+            "type" => Field::Type,
+            "status" => Field::Status,
+            "sort order" => Field::SortOrder,
+            "metadata" => Field::Metadata,
+            "ignore" | "" => Field::Ignore,
+            _ => Field::Extra(target.trim().to_string()),
+        };
+    }
+
+    match normalize_name(name).as_str() {
+        "" => Field::Ignore,
+        "title" | "name" => Field::Title,
+        "content" | "description" | "synopsis" | "notes" | "body" | "text" | "summary" => {
+            Field::Content
+        }
+        "type" | "entry type" | "category" | "label" | "kind" => Field::Type,
+        "status" => Field::Status,
+        "sort order" | "order" | "position" => Field::SortOrder,
+        "metadata" => Field::Metadata,
+        // Scrivener outline bookkeeping that means nothing in a codex
+        "include in compile" | "word count" | "total word count" | "target" | "target type"
+        | "created date" | "modified date" | "section type"
+            if options.format == CodexFormat::Scrivener =>
+        {
+            Field::Ignore
+        }
+        _ => Field::Extra(name.trim().to_string()),
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .replace(['_', '-'], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// An entry type from a stored name or a label such as "Characters" or
+/// "Location"
+pub fn parse_entry_type(label: &str) -> Option<CodexEntryType> {
+    let label = normalize_name(label);
+    CodexEntryType::all_types()
+        .iter()
+        .copied()
+        .find(|t| {
+            t.as_str() == label.replace(' ', "_") || t.display_name().eq_ignore_ascii_case(&label)
+        })
+        .or_else(|| match label.trim_end_matches('s') {
+            "character" | "person" | "people" | "cast" => Some(CodexEntryType::CharacterSheet),
+            "place" | "location" | "setting" => Some(CodexEntryType::Place),
+            "object" | "item" | "artifact" | "artefact" | "thing" => Some(CodexEntryType::Object),
+            "time" | "event" | "era" | "date" => Some(CodexEntryType::Time),
+            "summary" | "story" | "plot" | "premise" => Some(CodexEntryType::StorySummary),
+            _ => None,
+        })
+}
+
+/// A status from a stored name or a label such as "In Review" or "Done"
+pub fn parse_status(label: &str) -> Option<CodexStatus> {
+    match normalize_name(label).as_str() {
+        "draft" | "first draft" | "to do" | "todo" => Some(CodexStatus::Draft),
+        "in review" | "review" | "revised draft" => Some(CodexStatus::InReview),
+        "final" | "final draft" | "done" => Some(CodexStatus::Final),
+        "archived" => Some(CodexStatus::Archived),
+        _ => None,
+    }
+}
+
+/// Read a CSV import, our own or a Scrivener outline; the first record
+/// names the columns
+pub fn read_records(records: &[Vec<String>], options: &CodexImportOptions) -> ParsedImport {
+    let mut parsed = ParsedImport::default();
+    let Some((header, rows)) = records.split_first() else {
+        return parsed;
+    };
+    for (index, row) in rows.iter().enumerate() {
+        let pairs = header
+            .iter()
+            .zip(row)
+            .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+            .collect();
+        // Header is line 1
+        parsed.push(map_entry(index + 2, pairs, None, options));
+    }
+    parsed
+}
+
+/// Whether rows under `header` can be read as entries: one column is the
+/// title and another the type, unless the options give a type for all
+pub fn maps_to_entries(header: &[String], options: &CodexImportOptions) -> bool {
+    let fields: Vec<Field> = header.iter().map(|name| field_for(name, options)).collect();
+    fields.contains(&Field::Title)
+        && (fields.contains(&Field::Type) || options.default_type.is_some())
+}
+
+/// Read a JSON import: one of our exports or an array of objects
+pub fn read_json(input: &str, options: &CodexImportOptions) -> Result<ParsedImport, String> {
+    let value: Value =
+        serde_json::from_str(input).map_err(|e| format!("The file isn't valid JSON: {}", e))?;
+    let items = match value {
+        Value::Array(items) => items,
+        Value::Object(mut object) => match object.remove("entries") {
+            Some(Value::Array(items)) => items,
+            _ => return Err("Expected a list of entries".to_string()),
+        },
+        _ => return Err("Expected a list of entries".to_string()),
+    };
+
+    let mut parsed = ParsedImport::default();
+    for (index, item) in items.into_iter().enumerate() {
+        match item {
+            Value::Object(object) => parsed.push(map_entry(
+                index + 1,
+                object.into_iter().collect(),
+                None,
+                options,
+            )),
+            _ => parsed
+                .errors
+                .push(format!("Entry {}: expected an object", index + 1)),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Plottr keys that are ids, colors and links into the rest of the file
+const PLOTTR_SKIPPED_KEYS: [&str; 10] = [
+    "id",
+    "color",
+    "cards",
+    "noteIds",
+    "bookIds",
+    "tags",
+    "categoryId",
+    "imageId",
+    "templates",
+    "position",
+];
+
+/// Read a Plottr file's characters and places
+pub fn read_plottr(input: &str, options: &CodexImportOptions) -> Result<ParsedImport, String> {
+    let value: Value =
+        serde_json::from_str(input).map_err(|e| format!("The file isn't valid JSON: {}", e))?;
+    let mut parsed = ParsedImport::default();
+    let mut line = 0;
+    for (key, entry_type) in [
+        ("characters", CodexEntryType::CharacterSheet),
+        ("places", CodexEntryType::Place),
+    ] {
+        let Some(items) = value.get(key).and_then(Value::as_array) else {
+            continue;
+        };
+        for item in items {
+            line += 1;
+            let Some(object) = item.as_object() else {
+                parsed
+                    .errors
+                    .push(format!("Entry {}: expected an object", line));
+                continue;
+            };
+            let pairs = object
+                .iter()
+                .filter(|(name, _)| !PLOTTR_SKIPPED_KEYS.contains(&name.as_str()))
+                .map(|(name, value)| {
+                    // Notes are rich text: a tree of nodes with text leaves
+                    let value = match value {
+                        Value::Array(_) => Value::String(rich_text(value)),
+                        other => other.clone(),
+                    };
+                    (name.clone(), value)
+                })
+                .collect();
+            parsed.push(map_entry(line, pairs, Some(entry_type), options));
+        }
+    }
+    if line == 0 && value.get("characters").is_none() && value.get("places").is_none() {
+        return Err("The file has no characters or places".to_string());
+    }
+    Ok(parsed)
+}
+
+/// Plain text of a rich-text tree, one line per block
+fn rich_text(value: &Value) -> String {
+    match value {
+        Value::Array(nodes) => nodes
+            .iter()
+            .map(rich_text)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Object(node) => match (node.get("text"), node.get("children")) {
+            (Some(Value::String(text)), _) => text.clone(),
+            (_, Some(Value::Array(children))) => {
+                children.iter().map(rich_text).collect::<Vec<_>>().join("")
+            }
+            _ => String::new(),
+        },
+        Value::String(text) => text.clone(),
+        _ => String::new(),
+    }
+}
+
+impl ParsedImport {
+    fn push(&mut self, entry: Result<Option<ImportedEntry>, String>) {
+        match entry {
+            Ok(Some(entry)) => self.entries.push(entry),
+            Ok(None) => {}
+            Err(e) => self.errors.push(e),
+        }
+    }
+}
+
+/// One entry from its named values; Ok(None) for a blank row
+fn map_entry(
+    line: usize,
+    pairs: Vec<(String, Value)>,
+    default_type: Option<CodexEntryType>,
+    options: &CodexImportOptions,
+) -> Result<Option<ImportedEntry>, String> {
+    let mut title = String::new();
+    let mut content: Vec<String> = Vec::new();
+    let mut entry_type = None;
+    let mut status = CodexStatus::Draft;
+    let mut sort_order = None;
+    let mut metadata = Map::new();
+
+    for (name, value) in pairs {
+        if is_blank(&value) {
+            continue;
+        }
+        let text = match &value {
+            Value::String(text) => text.trim().to_string(),
+            other => other.to_string(),
+        };
+        match field_for(&name, options) {
+            Field::Title => title = text,
+            Field::Content => content.push(text),
+            Field::Type => {
+                entry_type = Some(
+                    parse_entry_type(&text)
+                        .ok_or_else(|| format!("Line {}: unknown entry type \"{}\"", line, text))?,
+                )
+            }
+            Field::Status => match parse_status(&text) {
+                Some(parsed) => status = parsed,
+                // Keep a status we don't have, such as Scrivener's custom ones
+                None => {
+                    metadata.insert("status".to_string(), Value::String(text));
+                }
+            },
+            Field::SortOrder => {
+                sort_order = Some(
+                    text.parse::<i32>()
+                        .map_err(|_| format!("Line {}: invalid sort order \"{}\"", line, text))?,
+                )
+            }
+            Field::Metadata => {
+                let value = match value {
+                    Value::String(text) => {
+                        serde_json::from_str(&text).unwrap_or(Value::String(text))
+                    }
+                    other => other,
+                };
+                match value {
+                    Value::Object(object) => metadata.extend(object),
+                    other => {
+                        metadata.insert("metadata".to_string(), other);
+                    }
+                }
+            }
+            Field::Ignore => {}
+            Field::Extra(key) => {
+                metadata.insert(key, value);
+            }
+        }
+    }
+
+    if title.is_empty() && content.is_empty() && metadata.is_empty() {
+        return Ok(None);
+    }
+    if title.is_empty() {
+        return Err(format!("Line {}: the entry has no title", line));
+    }
+    let entry_type = entry_type
+        .or(default_type)
+        .or(options.default_type)
+        .ok_or_else(|| format!("Line {}: \"{}\" has no entry type", line, title))?;
+    Ok(Some(ImportedEntry {
+        line,
+        entry_type,
+        title,
+        content: content.join("\n\n"),
+        status,
+        sort_order,
+        metadata,
+    }))
+}
+
+fn is_blank(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(text) => text.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// Key two entries are duplicates under: type and title, ignoring case
+/// and spacing
+pub fn duplicate_key(entry_type: CodexEntryType, title: &str) -> (CodexEntryType, String) {
+    (entry_type, normalize_name(title))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_scrivener_outline() {
+        let options = CodexImportOptions {
+            format: CodexFormat::Scrivener,
+            field_map: HashMap::from([("Custom: Eye Color".to_string(), "eyes".to_string())]),
+            ..Default::default()
+        };
+        let records: Vec<Vec<String>> = [
+            vec![
+                "Title",
+                "Synopsis",
+                "Label",
+                "Status",
+                "Word Count",
+                "Custom: Eye Color",
+            ],
+            vec![
+                "Mara Quell",
+                "Harbor pilot",
+                "Characters",
+                "First Draft",
+                "412",
+                "grey",
+            ],
+            vec!["", "", "", "", "", ""],
+            vec!["The Lantern", "", "Wonder", "Done", "0", ""],
+        ]
+        .iter()
+        .map(|row| row.iter().map(|f| f.to_string()).collect())
+        .collect();
+
+        let parsed = read_records(&records, &options);
+        assert_eq!(parsed.entries.len(), 1);
+        let mara = &parsed.entries[0];
+        assert_eq!(mara.entry_type, CodexEntryType::CharacterSheet);
+        assert_eq!(mara.status, CodexStatus::Draft);
+        assert_eq!(mara.content, "Harbor pilot");
+        assert_eq!(mara.metadata.get("eyes"), Some(&Value::from("grey")));
+        assert!(!mara.metadata.contains_key("Word Count"));
+        assert_eq!(parsed.errors, ["Line 4: unknown entry type \"Wonder\""]);
+    }
+
+    #[test]
+    fn test_read_plottr_characters_and_places() {
+        let pltr = r#"{
+            "characters": [{"id": 1, "name": "Mara", "description": "Pilot", "color": "red",
+                "notes": [{"children": [{"text": "Fears "}, {"text": "deep water"}]},
+                          {"children": [{"text": "Sister of Tobin"}]}],
+                "Age": "34"}],
+            "places": [{"id": 1, "name": "Greyharbor", "description": "", "notes": []}]
+        }"#;
+        let parsed = read_plottr(pltr, &CodexImportOptions::default()).unwrap();
+        assert!(parsed.errors.is_empty());
+        let mara = &parsed.entries[0];
+        assert_eq!(mara.content, "Pilot\n\nFears deep water\nSister of Tobin");
+        assert_eq!(mara.metadata.get("Age"), Some(&Value::from("34")));
+        assert!(!mara.metadata.contains_key("color"));
+        assert_eq!(parsed.entries[1].entry_type, CodexEntryType::Place);
+        assert!(read_plottr("{}", &CodexImportOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Content Scan Data Models
+//!
+//! User-editable lexicons grouped by category (violence, profanity, ...)
+//! and the per-chapter reports produced by scanning a project.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Content category a lexicon belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ContentCategory {
+    Violence,
+    Profanity,
+    SexualContent,
+    SubstanceUse,
+    SelfHarm,
+    Discrimination,
+    Custom(String),
+}
+
+impl ContentCategory {
+    pub fn display_name(&self) -> &str {
+        match self {
+            ContentCategory::Violence => "Violence",
+            ContentCategory::Profanity => "Profanity",
+            ContentCategory::SexualContent => "Sexual Content",
+            ContentCategory::SubstanceUse => "Substance Use",
+            ContentCategory::SelfHarm => "Self-Harm",
+            ContentCategory::Discrimination => "Discrimination",
+            ContentCategory::Custom(name) => name,
+        }
+    }
+}
+
+/// How strong a lexicon's terms are considered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ContentSeverity {
+    Mild,
+    Moderate,
+    Strong,
+}
+
+/// A user-editable list of terms for one category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentLexicon {
+    pub id: Uuid,
+    /// `None` for lexicons shared by every project
+    pub project_id: Option<Uuid>,
+    pub name: String,
+    pub category: ContentCategory,
+    pub severity: ContentSeverity,
+    pub terms: Vec<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ContentLexicon {
+    pub fn new(
+        project_id: Option<Uuid>,
+        name: String,
+        category: ContentCategory,
+        severity: ContentSeverity,
+        terms: Vec<String>,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            name,
+            category,
+            severity,
+            terms,
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// A single lexicon hit in a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentMatch {
+    pub lexicon_id: Uuid,
+    pub category: ContentCategory,
+    pub severity: ContentSeverity,
+    pub term: String,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+}
+
+/// Scan results for one chapter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterContentReport {
+    pub document_id: Uuid,
+    pub title: String,
+    pub matches: Vec<ContentMatch>,
+    pub counts_by_category: HashMap<ContentCategory, usize>,
+    pub highest_severity: Option<ContentSeverity>,
+}
+
+/// Scan results for a whole project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentScanReport {
+    pub project_id: Uuid,
+    pub scanned_at: DateTime<Utc>,
+    pub chapters: Vec<ChapterContentReport>,
+    pub totals_by_category: HashMap<ContentCategory, usize>,
+}
+
+impl ContentScanReport {
+    /// Categories present in the project, strongest first
+    pub fn flagged_categories(&self) -> Vec<(ContentCategory, ContentSeverity)> {
+        let mut strongest: HashMap<ContentCategory, ContentSeverity> = HashMap::new();
+        for m in self.chapters.iter().flat_map(|c| &c.matches) {
+            let entry = strongest.entry(m.category.clone()).or_insert(m.severity);
+            if m.severity > *entry {
+                *entry = m.severity;
+            }
+        }
+        let mut flagged: Vec<_> = strongest.into_iter().collect();
+        flagged.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then_with(|| a.0.display_name().cmp(b.0.display_name()))
+        });
+        flagged
+    }
+}
+
+/// Database schema for content lexicons
+pub const CREATE_CONTENT_LEXICON_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS content_lexicons (
+    id TEXT PRIMARY KEY,
+    project_id TEXT,
+    name TEXT NOT NULL,
+    category TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    terms TEXT NOT NULL DEFAULT '[]',
+    enabled BOOLEAN NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_content_lexicons_project ON content_lexicons(project_id);
+"#;
+
+/// Upsert content lexicon SQL
+pub const UPSERT_CONTENT_LEXICON_SQL: &str = r#"
+INSERT INTO content_lexicons (id, project_id, name, category, severity, terms, enabled, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+ON CONFLICT(id) DO UPDATE SET
+    name = excluded.name,
+    category = excluded.category,
+    severity = excluded.severity,
+    terms = excluded.terms,
+    enabled = excluded.enabled,
+    updated_at = excluded.updated_at
+"#;
+
+/// Lexicons that apply to a project (its own plus shared ones)
+pub const GET_LEXICONS_FOR_PROJECT_SQL: &str = r#"
+SELECT id, project_id, name, category, severity, terms, enabled, created_at, updated_at
+FROM content_lexicons
+WHERE project_id = ?1 OR project_id IS NULL
+ORDER BY name ASC
+"#;
+
+/// Delete content lexicon SQL
+pub const DELETE_CONTENT_LEXICON_SQL: &str = r#"
+DELETE FROM content_lexicons WHERE id = ?1
+"#;
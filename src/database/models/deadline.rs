@@ -0,0 +1,228 @@
+//! Deadline Data Models
+//!
+//! Contest closings, publisher dates and personal goals a project is written
+//! towards, optionally with a word target, and the countdown worked out from
+//! them: time left, words still to write and the daily pace that needs.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::automation::NotificationLevel;
+
+/// What a deadline is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadlineKind {
+    Contest,
+    Submission,
+    Publisher,
+    Personal,
+    Other,
+}
+
+impl DeadlineKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeadlineKind::Contest => "contest",
+            DeadlineKind::Submission => "submission",
+            DeadlineKind::Publisher => "publisher",
+            DeadlineKind::Personal => "personal",
+            DeadlineKind::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "contest" => DeadlineKind::Contest,
+            "submission" => DeadlineKind::Submission,
+            "publisher" => DeadlineKind::Publisher,
+            "personal" => DeadlineKind::Personal,
+            _ => DeadlineKind::Other,
+        }
+    }
+}
+
+/// How close a deadline is. Each stage is notified once, and later stages
+/// notify more urgently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadlineStage {
+    /// Within 30 days
+    Month,
+    /// Within 7 days
+    Week,
+    /// Within 3 days
+    Days,
+    /// Within 24 hours
+    Day,
+    /// Past due and not completed
+    Overdue,
+}
+
+impl DeadlineStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeadlineStage::Month => "month",
+            DeadlineStage::Week => "week",
+            DeadlineStage::Days => "days",
+            DeadlineStage::Day => "day",
+            DeadlineStage::Overdue => "overdue",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "month" => Some(DeadlineStage::Month),
+            "week" => Some(DeadlineStage::Week),
+            "days" => Some(DeadlineStage::Days),
+            "day" => Some(DeadlineStage::Day),
+            "overdue" => Some(DeadlineStage::Overdue),
+            _ => None,
+        }
+    }
+
+    /// The stage for the hours left until the deadline, if it is close
+    pub fn for_hours_left(hours: i64) -> Option<Self> {
+        match hours {
+            h if h < 0 => Some(DeadlineStage::Overdue),
+            h if h <= 24 => Some(DeadlineStage::Day),
+            h if h <= 3 * 24 => Some(DeadlineStage::Days),
+            h if h <= 7 * 24 => Some(DeadlineStage::Week),
+            h if h <= 30 * 24 => Some(DeadlineStage::Month),
+            _ => None,
+        }
+    }
+
+    /// Notification level for the stage
+    pub fn level(&self) -> NotificationLevel {
+        match self {
+            DeadlineStage::Month | DeadlineStage::Week => NotificationLevel::Info,
+            DeadlineStage::Days | DeadlineStage::Day => NotificationLevel::Warning,
+            DeadlineStage::Overdue => NotificationLevel::Error,
+        }
+    }
+}
+
+/// A date a project is written towards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deadline {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub title: String,
+    pub kind: DeadlineKind,
+    pub due_at: DateTime<Utc>,
+    /// Words the project should have by the deadline
+    #[serde(default)]
+    pub target_words: Option<u64>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub completed: bool,
+    /// The latest stage a notification was shown for
+    #[serde(default)]
+    pub notified_stage: Option<DeadlineStage>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Deadline {
+    pub fn new(
+        project_id: Uuid,
+        title: impl Into<String>,
+        kind: DeadlineKind,
+        due_at: DateTime<Utc>,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            title: title.into(),
+            kind,
+            due_at,
+            target_words: None,
+            url: None,
+            notes: String::new(),
+            completed: false,
+            notified_stage: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Where a deadline stands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadlineCountdown {
+    pub deadline: Deadline,
+    /// Negative once the deadline has passed
+    pub days_left: i64,
+    pub hours_left: i64,
+    pub stage: Option<DeadlineStage>,
+    /// Words in the project's active documents
+    pub current_words: u64,
+    pub words_remaining: Option<u64>,
+    /// Words a day needed to reach the target, counting today
+    pub daily_pace: Option<u64>,
+    /// Words a day written over the last week
+    pub recent_daily_words: Option<f64>,
+    /// The recent pace reaches the target in time
+    pub on_track: Option<bool>,
+}
+
+/// A deadline notification that has fallen due
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadlineAlert {
+    pub deadline_id: Uuid,
+    pub project_id: Uuid,
+    pub stage: DeadlineStage,
+    pub title: String,
+    pub message: String,
+}
+
+/// Database schema for deadlines
+pub const CREATE_DEADLINES_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS deadlines (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    due_at TEXT NOT NULL,
+    target_words INTEGER,
+    url TEXT,
+    notes TEXT NOT NULL DEFAULT '',
+    completed INTEGER NOT NULL DEFAULT 0,
+    notified_stage TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_deadlines_project ON deadlines(project_id, due_at);
+"#;
+
+/// Insert or replace deadline SQL
+pub const UPSERT_DEADLINE_SQL: &str = r#"
+INSERT INTO deadlines (id, project_id, title, kind, due_at, target_words, url, notes, completed,
+                       notified_stage, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+ON CONFLICT(id) DO UPDATE SET
+    title = excluded.title,
+    kind = excluded.kind,
+    due_at = excluded.due_at,
+    target_words = excluded.target_words,
+    url = excluded.url,
+    notes = excluded.notes,
+    completed = excluded.completed,
+    notified_stage = excluded.notified_stage,
+    updated_at = excluded.updated_at
+"#;
+
+/// Select deadlines SQL; filter with a WHERE clause appended by the caller
+pub const SELECT_DEADLINES_SQL: &str = r#"
+SELECT id, project_id, title, kind, due_at, target_words, url, notes, completed,
+       notified_stage, created_at, updated_at
+FROM deadlines
+"#;
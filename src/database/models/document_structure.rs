@@ -0,0 +1,102 @@
+//! Document Structure Data Models
+//!
+//! Splitting a document into scenes, merging documents into one and the
+//! binder order that both keep up to date.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Where to cut a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SplitAt {
+    /// Before every Markdown (`#`), HTML (`<h1>`) or editor heading up to
+    /// this level
+    Headings { max_level: u8 },
+    /// At byte offsets in the document's text, e.g. the ends of a selection.
+    /// Editor documents are cut at the start of the next block.
+    Offsets { offsets: Vec<usize> },
+}
+
+/// A request to split a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitRequest {
+    pub document_id: Uuid,
+    pub at: SplitAt,
+}
+
+/// What goes between merged documents
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MergeSeparator {
+    /// A centred `* * *` scene break
+    #[default]
+    SceneBreak,
+    /// Just a paragraph break
+    BlankLine,
+    /// Each merged document's title as a heading
+    TitleHeading,
+    /// Any text, inserted as written
+    Custom { text: String },
+}
+
+/// A request to merge documents, in the order given, into the first one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeRequest {
+    pub document_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub separator: MergeSeparator,
+    /// Title of the merged document; the first document's title if unset
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+/// Result of a split
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitResult {
+    /// The original document, which keeps the first part and its history,
+    /// followed by the new documents in order
+    pub document_ids: Vec<Uuid>,
+    pub annotations_moved: usize,
+}
+
+/// Result of a merge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeResult {
+    pub document_id: Uuid,
+    /// Documents folded in; they are deactivated, not deleted, so their
+    /// version history stays available
+    pub merged: Vec<Uuid>,
+    pub annotations_moved: usize,
+    pub attachments_moved: usize,
+    /// Documents whose links to a merged document were repointed
+    pub links_updated: usize,
+}
+
+/// Database schema for the binder order
+pub const CREATE_BINDER_ORDER_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS binder_order (
+    document_id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_binder_order_project ON binder_order(project_id, position);
+"#;
+
+/// Active documents of a project in binder order; documents never placed
+/// come last, oldest first
+pub const GET_BINDER_ORDER_SQL: &str = r#"
+SELECT d.id
+FROM documents d
+LEFT JOIN binder_order b ON b.document_id = d.id
+WHERE d.project_id = ?1 AND d.is_active = 1
+ORDER BY b.position IS NULL, b.position, d.created_at, d.title
+"#;
+
+/// Insert binder position SQL
+pub const INSERT_BINDER_POSITION_SQL: &str = r#"
+INSERT INTO binder_order (document_id, project_id, position)
+VALUES (?1, ?2, ?3)
+"#;
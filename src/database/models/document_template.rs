@@ -0,0 +1,286 @@
+//! Document Template Data Models
+//!
+//! Templates for new documents, such as front matter, scene skeletons and
+//! review checklists. A template's title and body can use placeholders,
+//! filled in when a document is created from it:
+//!
+//! - `{{project.name}}`, `{{project.description}}`: the project's details
+//! - `{{codex.character_sheet}}`, `{{codex.place}}` and the like: titles of
+//!   the project's codex entries of that type, comma-separated
+//! - `{{date}}`, `{{year}}`: the creation date
+//! - `{{title}}`: the new document's title, in the body
+//! - `{{pov}}`: the answer to the template's prompt named `pov`
+//! - `{{subtitle|Draft}}`: falls back to the text after `|` when unset
+//!
+//! Unknown placeholders are left as written, so a typo shows up in the
+//! new document rather than vanishing.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// A question asked when a document is created from a template
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplatePrompt {
+    /// The placeholder the answer fills
+    pub name: String,
+    /// Question shown in the new-document flow
+    pub label: String,
+    /// Suggested answer; may use placeholders such as `{{project.name}}`
+    #[serde(default)]
+    pub default: String,
+    /// Codex entry type, such as "character_sheet", whose titles are
+    /// offered as answers
+    #[serde(default)]
+    pub choices_from: Option<String>,
+}
+
+impl TemplatePrompt {
+    pub fn new(name: &str, label: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            label: label.to_string(),
+            default: String::new(),
+            choices_from: None,
+        }
+    }
+
+    pub fn with_default(mut self, default: &str) -> Self {
+        self.default = default.to_string();
+        self
+    }
+
+    pub fn with_choices_from(mut self, entry_type: &str) -> Self {
+        self.choices_from = Some(entry_type.to_string());
+        self
+    }
+}
+
+/// A prompt as shown for one project, with its default filled in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedPrompt {
+    pub name: String,
+    pub label: String,
+    pub default: String,
+    pub choices: Vec<String>,
+}
+
+/// A template for new documents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentTemplate {
+    pub id: Uuid,
+    /// None for templates shared by all projects
+    pub project_id: Option<Uuid>,
+    pub name: String,
+    /// Grouping in the new-document flow, such as "Front Matter"
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub description: String,
+    /// Title of documents made from the template
+    pub title: String,
+    pub body: String,
+    #[serde(default)]
+    pub prompts: Vec<TemplatePrompt>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl DocumentTemplate {
+    pub fn new(name: &str, category: &str, title: &str, body: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id: None,
+            name: name.to_string(),
+            category: category.to_string(),
+            description: String::new(),
+            title: title.to_string(),
+            body: body.to_string(),
+            prompts: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn with_prompt(mut self, prompt: TemplatePrompt) -> Self {
+        self.prompts.push(prompt);
+        self
+    }
+
+    /// Check the name and that prompts have distinct names that can't be
+    /// mistaken for built-in placeholders
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Template name cannot be empty".to_string());
+        }
+        if self.title.trim().is_empty() {
+            return Err("Template title cannot be empty".to_string());
+        }
+        let mut names = std::collections::HashSet::new();
+        for prompt in &self.prompts {
+            let name = prompt.name.trim();
+            if name.is_empty() || name.contains(['{', '}', '|']) {
+                return Err(format!("\"{}\" isn't a valid prompt name", prompt.name));
+            }
+            if matches!(name, "title" | "date" | "year")
+                || name.starts_with("project.")
+                || name.starts_with("codex.")
+            {
+                return Err(format!("\"{}\" is a built-in placeholder", name));
+            }
+            if !names.insert(name) {
+                return Err(format!("Two prompts are named \"{}\"", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fill the `{{placeholders}}` in `text` from `values`
+pub fn fill_placeholders(text: &str, values: &HashMap<String, String>) -> String {
+    let mut filled = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        filled.push_str(&rest[..start]);
+        let inner = &rest[start + 2..start + 2 + len];
+        let (name, fallback) = match inner.split_once('|') {
+            Some((name, fallback)) => (name.trim(), Some(fallback)),
+            None => (inner.trim(), None),
+        };
+        match (values.get(name).filter(|v| !v.is_empty()), fallback) {
+            (Some(value), _) => filled.push_str(value),
+            (None, Some(fallback)) => filled.push_str(fallback),
+            (None, None) => filled.push_str(&rest[start..start + len + 4]),
+        }
+        rest = &rest[start + len + 4..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// Templates every install starts with
+pub fn default_templates() -> Vec<DocumentTemplate> {
+    vec![
+        DocumentTemplate::new(
+            "Title Page",
+            "Front Matter",
+            "Title Page",
+            "# {{project.name}}\n\n{{subtitle|}}\n\nby {{author}}\n",
+        )
+        .with_description("The book's title and author")
+        .with_prompt(TemplatePrompt::new("subtitle", "Subtitle"))
+        .with_prompt(TemplatePrompt::new("author", "Author name")),
+        DocumentTemplate::new(
+            "Scene",
+            "Scenes",
+            "{{scene_title|New Scene}}",
+            "# {{title}}\n\n\
+             **Point of view:** {{pov|}}\n\
+             **Setting:** {{setting|}}\n\n\
+             ## Goal\n\n\n\
+             ## Conflict\n\n\n\
+             ## Outcome\n\n",
+        )
+        .with_description("A scene skeleton: goal, conflict and outcome")
+        .with_prompt(TemplatePrompt::new("scene_title", "Scene title"))
+        .with_prompt(
+            TemplatePrompt::new("pov", "Point of view character")
+                .with_choices_from("character_sheet"),
+        )
+        .with_prompt(TemplatePrompt::new("setting", "Setting").with_choices_from("place")),
+        DocumentTemplate::new(
+            "Revision Checklist",
+            "Review",
+            "Review: {{chapter}}",
+            "# {{title}}\n\n\
+             - [ ] The opening hooks\n\
+             - [ ] Every scene has a goal and a turn\n\
+             - [ ] Names and details match the codex\n\
+             - [ ] Timeline is consistent\n\
+             - [ ] The ending pulls into the next chapter\n\n\
+             Reviewed {{date}}\n",
+        )
+        .with_description("A checklist for revising a chapter")
+        .with_prompt(TemplatePrompt::new("chapter", "Chapter to review")),
+        DocumentTemplate::new(
+            "Morning Pages",
+            "Journal",
+            "Journal {{date}}",
+            "# {{date}}\n\n",
+        )
+        .with_description("A blank dated page for the daily journal"),
+    ]
+}
+
+/// Database schema for document templates; prompts are stored as JSON
+pub const CREATE_DOCUMENT_TEMPLATES_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS document_templates (
+    id TEXT PRIMARY KEY,
+    project_id TEXT,
+    name TEXT NOT NULL,
+    category TEXT NOT NULL DEFAULT '',
+    description TEXT NOT NULL DEFAULT '',
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    prompts TEXT NOT NULL DEFAULT '[]',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_templates_project ON document_templates(project_id);
+"#;
+
+/// Create or update a document template
+pub const UPSERT_DOCUMENT_TEMPLATE_SQL: &str = r#"
+INSERT INTO document_templates (id, project_id, name, category, description, title, body, prompts, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+ON CONFLICT(id) DO UPDATE SET
+    name = excluded.name,
+    category = excluded.category,
+    description = excluded.description,
+    title = excluded.title,
+    body = excluded.body,
+    prompts = excluded.prompts,
+    updated_at = excluded.updated_at
+"#;
+
+/// Shared templates and a project's own, by category and name
+pub const GET_DOCUMENT_TEMPLATES_SQL: &str = r#"
+SELECT id, project_id, name, category, description, title, body, prompts, created_at, updated_at
+FROM document_templates
+WHERE project_id IS NULL OR project_id = ?1
+ORDER BY category COLLATE NOCASE, name COLLATE NOCASE
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_placeholders() {
+        let values = HashMap::from([
+            ("project.name".to_string(), "Ashfall".to_string()),
+            ("pov".to_string(), String::new()),
+        ]);
+        assert_eq!(
+            fill_placeholders("{{project.name}}: {{ pov |Mara}} {{typo}} {{open", &values),
+            "Ashfall: Mara {{typo}} {{open"
+        );
+        for template in default_templates() {
+            template.validate().unwrap();
+        }
+        let clash = DocumentTemplate::new("X", "", "X", "")
+            .with_prompt(TemplatePrompt::new("project.name", "Name"));
+        assert!(clash.validate().is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! Draft Data Models
+//!
+//! Named project-level drafts ("Draft 1", "Draft 2") that snapshot every
+//! document in a project so revision passes can be compared.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::database::prosemirror;
+use crate::database::text_diff::{DiffLine, DiffSummary};
+
+/// A named revision pass over a whole project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Draft {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub draft_number: u32,
+    pub document_count: usize,
+    pub total_words: usize,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A single document captured as part of a draft
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DraftDocument {
+    pub draft_id: Uuid,
+    pub document_id: Uuid,
+    pub title: String,
+    pub content: String,
+    /// `documents.document_type` when the snapshot was taken
+    pub document_type: String,
+    pub word_count: usize,
+    pub checksum: String,
+}
+
+impl DraftDocument {
+    /// The text of the snapshot, one line per block
+    pub fn text(&self) -> String {
+        prosemirror::document_text(&self.document_type, &self.content)
+    }
+}
+
+/// Diff of one chapter between two drafts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DraftDocumentDiff {
+    pub document_id: Uuid,
+    pub from_draft_id: Uuid,
+    pub to_draft_id: Uuid,
+    pub from_title: Option<String>,
+    pub to_title: Option<String>,
+    pub lines: Vec<DiffLine>,
+    pub summary: DiffSummary,
+}
+
+/// How a document changed between two drafts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DraftChangeKind {
+    Added,
+    Removed,
+    Modified,
+    Unchanged,
+}
+
+/// Per-document change statistics between two drafts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DraftDocumentChange {
+    pub document_id: Uuid,
+    pub title: String,
+    pub kind: DraftChangeKind,
+    pub word_delta: i64,
+    pub summary: DiffSummary,
+}
+
+/// Aggregate statistics on how much changed between two drafts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DraftComparison {
+    pub from_draft_id: Uuid,
+    pub to_draft_id: Uuid,
+    pub documents_added: usize,
+    pub documents_removed: usize,
+    pub documents_modified: usize,
+    pub documents_unchanged: usize,
+    pub word_delta: i64,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// Fraction of lines touched across the whole project (0.0 - 1.0)
+    pub change_ratio: f64,
+    pub documents: Vec<DraftDocumentChange>,
+}
+
+/// Database schema for drafts
+pub const CREATE_DRAFT_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS drafts (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    draft_number INTEGER NOT NULL,
+    document_count INTEGER NOT NULL DEFAULT 0,
+    total_words INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+    UNIQUE(project_id, name),
+    UNIQUE(project_id, draft_number)
+);
+
+CREATE TABLE IF NOT EXISTS draft_documents (
+    draft_id TEXT NOT NULL,
+    document_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    document_type TEXT NOT NULL DEFAULT 'json',
+    word_count INTEGER NOT NULL DEFAULT 0,
+    checksum TEXT NOT NULL,
+    PRIMARY KEY (draft_id, document_id),
+    FOREIGN KEY (draft_id) REFERENCES drafts(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_drafts_number ON drafts(project_id, draft_number);
+CREATE INDEX IF NOT EXISTS idx_draft_documents_document ON draft_documents(document_id);
+"#;
+
+/// Insert draft SQL
+pub const INSERT_DRAFT_SQL: &str = r#"
+INSERT INTO drafts (id, project_id, name, description, draft_number, document_count, total_words, created_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+"#;
+
+/// Insert draft document snapshot SQL
+pub const INSERT_DRAFT_DOCUMENT_SQL: &str = r#"
+INSERT INTO draft_documents (draft_id, document_id, title, content, document_type, word_count, checksum)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+"#;
+
+/// Select drafts for a project SQL
+pub const GET_DRAFTS_BY_PROJECT_SQL: &str = r#"
+SELECT id, project_id, name, description, draft_number, document_count, total_words, created_at
+FROM drafts WHERE project_id = ?1 ORDER BY draft_number ASC
+"#;
+
+/// Select a single draft SQL
+pub const GET_DRAFT_SQL: &str = r#"
+SELECT id, project_id, name, description, draft_number, document_count, total_words, created_at
+FROM drafts WHERE id = ?1
+"#;
+
+/// Select all document snapshots in a draft SQL
+pub const GET_DRAFT_DOCUMENTS_SQL: &str = r#"
+SELECT draft_id, document_id, title, content, document_type, word_count, checksum
+FROM draft_documents WHERE draft_id = ?1 ORDER BY title ASC
+"#;
+
+/// Select a single document snapshot SQL
+pub const GET_DRAFT_DOCUMENT_SQL: &str = r#"
+SELECT draft_id, document_id, title, content, document_type, word_count, checksum
+FROM draft_documents WHERE draft_id = ?1 AND document_id = ?2
+"#;
+
+/// Delete draft SQL (snapshots cascade)
+pub const DELETE_DRAFT_SQL: &str = r#"
+DELETE FROM drafts WHERE id = ?1
+"#;
@@ -0,0 +1,210 @@
+//! Export Job and Template Records
+//!
+//! The database side of the export engine: every export job with its
+//! status, progress and output, so jobs can be audited and interrupted ones
+//! resumed, and the export templates users have saved. The engine's own
+//! settings travel as JSON so this module doesn't depend on it.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Where an export job stands; mirrors the export engine's `ExportStatus`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportJobState {
+    Pending,
+    Processing,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl ExportJobState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportJobState::Pending => "pending",
+            ExportJobState::Processing => "processing",
+            ExportJobState::Completed => "completed",
+            ExportJobState::Failed => "failed",
+            ExportJobState::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(ExportJobState::Pending),
+            "processing" => Some(ExportJobState::Processing),
+            "completed" => Some(ExportJobState::Completed),
+            "failed" => Some(ExportJobState::Failed),
+            "cancelled" => Some(ExportJobState::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// The job hasn't reached an end state
+    pub fn is_open(&self) -> bool {
+        matches!(self, ExportJobState::Pending | ExportJobState::Processing)
+    }
+}
+
+/// A persisted export job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportJobRecord {
+    pub job_id: String,
+    pub document_id: String,
+    /// "pdf", "epub", "html" or "docx"
+    pub export_type: String,
+    pub state: ExportJobState,
+    /// 0.0 to 1.0
+    pub progress: f32,
+    /// Everything needed to run the job again, as the engine serialized it
+    pub request: serde_json::Value,
+    pub output_path: Option<String>,
+    pub error_message: Option<String>,
+    pub file_size_bytes: Option<u64>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ExportJobRecord {
+    pub fn new(
+        job_id: impl Into<String>,
+        document_id: impl Into<String>,
+        export_type: impl Into<String>,
+        request: serde_json::Value,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            job_id: job_id.into(),
+            document_id: document_id.into(),
+            export_type: export_type.into(),
+            state: ExportJobState::Pending,
+            progress: 0.0,
+            request,
+            output_path: None,
+            error_message: None,
+            file_size_bytes: None,
+            created_at: now,
+            started_at: None,
+            completed_at: None,
+            updated_at: now,
+        }
+    }
+}
+
+/// A saved export template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportTemplateRecord {
+    pub template_id: String,
+    pub name: String,
+    pub description: String,
+    pub version: String,
+    pub category: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub author: Option<String>,
+    /// Style and document structure, as the engine serialized them
+    pub definition: serde_json::Value,
+    /// Run, in order, after each export made with the template completes
+    #[serde(default)]
+    pub hooks: Vec<ExportHook>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Automation run after an export completes, such as copying the file to
+/// a synced folder or checking it with epubcheck
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum ExportHook {
+    Script(Uuid),
+    Workflow(Uuid),
+}
+
+/// Database schema for export jobs and templates
+pub const CREATE_EXPORT_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS export_jobs (
+    job_id TEXT PRIMARY KEY,
+    document_id TEXT NOT NULL,
+    export_type TEXT NOT NULL,
+    state TEXT NOT NULL,
+    progress REAL NOT NULL DEFAULT 0,
+    request TEXT NOT NULL DEFAULT 'null',
+    output_path TEXT,
+    error_message TEXT,
+    file_size_bytes INTEGER,
+    created_at TEXT NOT NULL,
+    started_at TEXT,
+    completed_at TEXT,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_export_jobs_document ON export_jobs(document_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_export_jobs_state ON export_jobs(state);
+
+CREATE TABLE IF NOT EXISTS export_templates (
+    template_id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    version TEXT NOT NULL,
+    category TEXT NOT NULL,
+    tags TEXT NOT NULL DEFAULT '[]',
+    author TEXT,
+    definition TEXT NOT NULL,
+    hooks TEXT NOT NULL DEFAULT '[]',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+"#;
+
+/// Insert or replace export job SQL
+pub const UPSERT_EXPORT_JOB_SQL: &str = r#"
+INSERT INTO export_jobs (job_id, document_id, export_type, state, progress, request,
+                         output_path, error_message, file_size_bytes, created_at,
+                         started_at, completed_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+ON CONFLICT(job_id) DO UPDATE SET
+    state = excluded.state,
+    progress = excluded.progress,
+    request = excluded.request,
+    output_path = excluded.output_path,
+    error_message = excluded.error_message,
+    file_size_bytes = excluded.file_size_bytes,
+    started_at = excluded.started_at,
+    completed_at = excluded.completed_at,
+    updated_at = excluded.updated_at
+"#;
+
+/// Select export jobs SQL; filter with a WHERE clause appended by the caller
+pub const SELECT_EXPORT_JOBS_SQL: &str = r#"
+SELECT job_id, document_id, export_type, state, progress, request, output_path,
+       error_message, file_size_bytes, created_at, started_at, completed_at, updated_at
+FROM export_jobs
+"#;
+
+/// Insert or replace export template SQL
+pub const UPSERT_EXPORT_TEMPLATE_SQL: &str = r#"
+INSERT INTO export_templates (template_id, name, description, version, category, tags,
+                              author, definition, hooks, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+ON CONFLICT(template_id) DO UPDATE SET
+    name = excluded.name,
+    description = excluded.description,
+    version = excluded.version,
+    category = excluded.category,
+    tags = excluded.tags,
+    author = excluded.author,
+    definition = excluded.definition,
+    hooks = excluded.hooks,
+    updated_at = excluded.updated_at
+"#;
+
+/// Select export templates SQL; filter with a WHERE clause appended by the caller
+pub const SELECT_EXPORT_TEMPLATES_SQL: &str = r#"
+SELECT template_id, name, description, version, category, tags, author, definition,
+       hooks, created_at, updated_at
+FROM export_templates
+"#;
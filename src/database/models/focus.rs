@@ -0,0 +1,108 @@
+//! Focus Analytics Data Models
+//!
+//! Opt-in, local-only record of window focus changes and tool switches,
+//! and the daily summaries built from it: how long the writer stayed in
+//! the app at a stretch, how often they left it and how often they hopped
+//! between windows and tools.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A window focus change or tool switch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FocusEventKind {
+    WindowFocused,
+    WindowBlurred,
+    ToolOpened,
+}
+
+impl FocusEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FocusEventKind::WindowFocused => "window_focused",
+            FocusEventKind::WindowBlurred => "window_blurred",
+            FocusEventKind::ToolOpened => "tool_opened",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "window_focused" => Some(FocusEventKind::WindowFocused),
+            "window_blurred" => Some(FocusEventKind::WindowBlurred),
+            "tool_opened" => Some(FocusEventKind::ToolOpened),
+            _ => None,
+        }
+    }
+}
+
+/// One recorded focus change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusEvent {
+    pub id: Uuid,
+    pub kind: FocusEventKind,
+    /// "main" or "tool:<id>" for window events, the tool id for tool switches
+    pub window: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl FocusEvent {
+    pub fn new(kind: FocusEventKind, window: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            window: window.into(),
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// How fragmented one day's work was
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusDaySummary {
+    pub date: NaiveDate,
+    /// Uninterrupted stretches in the app
+    pub focus_sessions: u32,
+    pub focused_seconds: i64,
+    pub longest_focus_seconds: i64,
+    pub average_focus_seconds: i64,
+    /// Times the app lost focus to another application
+    pub distractions: u32,
+    /// Moves between the app's own windows
+    pub window_switches: u32,
+    pub tool_switches: u32,
+    /// Distractions and switches per focused hour
+    pub interruptions_per_hour: f64,
+}
+
+/// Whether focus analytics are being recorded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FocusAnalyticsStatus {
+    pub enabled: bool,
+    pub recorded_events: i64,
+}
+
+/// Database schema for focus events
+pub const CREATE_FOCUS_EVENTS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS focus_events (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    window TEXT NOT NULL,
+    occurred_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_focus_events_occurred ON focus_events(occurred_at);
+"#;
+
+/// Insert focus event SQL
+pub const INSERT_FOCUS_EVENT_SQL: &str = r#"
+INSERT INTO focus_events (id, kind, window, occurred_at)
+VALUES (?1, ?2, ?3, ?4)
+"#;
+
+/// Select focus events SQL; filter with a WHERE clause appended by the caller
+pub const SELECT_FOCUS_EVENTS_SQL: &str = r#"
+SELECT id, kind, window, occurred_at
+FROM focus_events
+"#;
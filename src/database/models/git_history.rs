@@ -0,0 +1,308 @@
+//! Git History Models
+//!
+//! Optional git-backed history for a project. The repository holds the
+//! project as Markdown files: either the project's Markdown mirror, so the
+//! author's own folder is versioned, or an export kept in the app's data
+//! folder. Commits are made for the author, after saves settle, when a
+//! writing session ends and before and after a restore, with messages that
+//! say which documents changed and by how many words.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Separates the fields of a formatted log entry
+pub const LOG_FIELD_SEPARATOR: char = '\u{1f}';
+/// Ends a formatted log entry
+pub const LOG_RECORD_SEPARATOR: char = '\u{1e}';
+/// `git log --format` producing what `parse_log` reads
+pub const LOG_FORMAT: &str = "--format=%x1e%H%x1f%h%x1f%aI%x1f%s%x1f%b%x1f";
+
+/// Where the repository lives
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryTarget {
+    /// The project's Markdown sync folder
+    Mirror,
+    /// An export in the app's data folder, out of the author's way
+    #[default]
+    Internal,
+}
+
+impl HistoryTarget {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HistoryTarget::Mirror => "mirror",
+            HistoryTarget::Internal => "internal",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "mirror" => Some(HistoryTarget::Mirror),
+            "internal" => Some(HistoryTarget::Internal),
+            _ => None,
+        }
+    }
+}
+
+/// A project with git history turned on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitHistorySettings {
+    pub project_id: Uuid,
+    pub target: HistoryTarget,
+    pub created_at: DateTime<Utc>,
+    pub last_commit_at: Option<DateTime<Utc>>,
+}
+
+/// Why a commit was made; starts its message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitReason {
+    /// Saved work picked up by the timer
+    Saves,
+    SessionEnd,
+    /// Asked for by the author, e.g. "Finished draft two"
+    Milestone,
+    BeforeRestore,
+    Restore,
+}
+
+impl CommitReason {
+    pub fn label(self) -> &'static str {
+        match self {
+            CommitReason::Saves => "Saved work",
+            CommitReason::SessionEnd => "Writing session",
+            CommitReason::Milestone => "Milestone",
+            CommitReason::BeforeRestore => "Before restore",
+            CommitReason::Restore => "Restored",
+        }
+    }
+}
+
+/// How a document changed in a commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Edited,
+    Removed,
+    Renamed,
+}
+
+impl ChangeKind {
+    fn verb(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Edited => "edited",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Renamed => "renamed",
+        }
+    }
+}
+
+/// A document changed by a commit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub kind: ChangeKind,
+    pub title: String,
+    pub word_delta: i64,
+}
+
+/// Longest commit subject before the document list is cut short
+const SUBJECT_LIMIT: usize = 72;
+
+/// A commit message: a subject naming the reason, the documents changed
+/// and the net words, then a line per document
+pub fn commit_message(reason: CommitReason, note: Option<&str>, changes: &[FileChange]) -> String {
+    let net: i64 = changes.iter().map(|c| c.word_delta).sum();
+    let mut subject = reason.label().to_string();
+    if let Some(note) = note.map(str::trim).filter(|n| !n.is_empty()) {
+        subject.push_str(&format!(" ({})", note));
+    }
+    subject.push_str(": ");
+
+    let mut parts: Vec<String> = Vec::new();
+    for kind in [
+        ChangeKind::Edited,
+        ChangeKind::Added,
+        ChangeKind::Removed,
+        ChangeKind::Renamed,
+    ] {
+        let titles: Vec<String> = changes
+            .iter()
+            .filter(|c| c.kind == kind)
+            .map(|c| format!("\"{}\"", c.title))
+            .collect();
+        if !titles.is_empty() {
+            parts.push(format!("{} {}", kind.verb(), titles.join(", ")));
+        }
+    }
+    let mut summary = parts.join("; ");
+    let words = if net == 0 {
+        String::new()
+    } else {
+        format!(" ({:+} words)", net)
+    };
+    if subject.chars().count() + summary.chars().count() + words.chars().count() > SUBJECT_LIMIT {
+        summary = match changes {
+            [only] => format!("{} \"{}\"", only.kind.verb(), only.title),
+            _ => format!("{} documents changed", changes.len()),
+        };
+    }
+    subject.push_str(&summary);
+    subject.push_str(&words);
+
+    let mut message = capitalize_after_colon(&subject);
+    if changes.len() > 1 {
+        message.push_str("\n\n");
+        for change in changes {
+            message.push_str(&format!(
+                "- {} \"{}\" ({:+} words)\n",
+                change.kind.verb(),
+                change.title,
+                change.word_delta
+            ));
+        }
+    }
+    message
+}
+
+fn capitalize_after_colon(subject: &str) -> String {
+    match subject.split_once(": ") {
+        Some((reason, rest)) => {
+            let mut chars = rest.chars();
+            let rest = match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            };
+            format!("{}: {}", reason, rest)
+        }
+        None => subject.to_string(),
+    }
+}
+
+/// A commit in a project's history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryCommit {
+    pub id: String,
+    pub short_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub subject: String,
+    pub body: String,
+    /// Files the commit touched
+    pub files: Vec<String>,
+}
+
+/// Commits from `git log` run with `LOG_FORMAT` and `--name-only`
+pub fn parse_log(output: &str) -> Vec<HistoryCommit> {
+    output
+        .split(LOG_RECORD_SEPARATOR)
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.splitn(6, LOG_FIELD_SEPARATOR).collect();
+            let [id, short_id, timestamp, subject, body, files] = fields.as_slice() else {
+                return None;
+            };
+            Some(HistoryCommit {
+                id: id.trim().to_string(),
+                short_id: short_id.to_string(),
+                timestamp: DateTime::parse_from_rfc3339(timestamp)
+                    .ok()?
+                    .with_timezone(&Utc),
+                subject: subject.to_string(),
+                body: body.trim().to_string(),
+                files: files
+                    .lines()
+                    .map(str::trim)
+                    .filter(|f| !f.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// What a restore did
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryRestore {
+    /// Documents set back to how they were, including ones brought back
+    pub documents_restored: Vec<String>,
+    /// Documents moved to the trash because they came later
+    pub documents_trashed: Vec<String>,
+    /// The commit recording the restore; None when nothing changed
+    pub commit: Option<HistoryCommit>,
+}
+
+/// Database schema for projects with git history
+pub const CREATE_GIT_HISTORY_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS git_history_settings (
+    project_id TEXT PRIMARY KEY,
+    target TEXT NOT NULL DEFAULT 'internal',
+    created_at TEXT NOT NULL,
+    last_commit_at TEXT,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_message() {
+        let edited = FileChange {
+            kind: ChangeKind::Edited,
+            title: "Chapter 3".to_string(),
+            word_delta: 412,
+        };
+        assert_eq!(
+            commit_message(
+                CommitReason::SessionEnd,
+                None,
+                std::slice::from_ref(&edited)
+            ),
+            "Writing session: Edited \"Chapter 3\" (+412 words)"
+        );
+
+        let changes = [
+            edited,
+            FileChange {
+                kind: ChangeKind::Removed,
+                title: "Prologue".to_string(),
+                word_delta: -90,
+            },
+        ];
+        let message = commit_message(CommitReason::Milestone, Some("Draft 2"), &changes);
+        assert_eq!(
+            message,
+            "Milestone (Draft 2): Edited \"Chapter 3\"; removed \"Prologue\" (+322 words)\n\n\
+             - edited \"Chapter 3\" (+412 words)\n\
+             - removed \"Prologue\" (-90 words)\n"
+        );
+
+        let many: Vec<FileChange> = (1..=9)
+            .map(|n| FileChange {
+                kind: ChangeKind::Added,
+                title: format!("Scene {}", n),
+                word_delta: 0,
+            })
+            .collect();
+        assert!(commit_message(CommitReason::Saves, None, &many)
+            .starts_with("Saved work: 9 documents changed\n"));
+    }
+
+    #[test]
+    fn test_parse_log() {
+        let output = "\u{1e}abc123\u{1f}abc\u{1f}2026-03-01T09:30:00+01:00\u{1f}Saved work: Edited \"A\"\u{1f}\u{1f}\n\none.md\ntwo.md\n\
+                      \u{1e}def456\u{1f}def\u{1f}2026-02-28T20:00:00Z\u{1f}Milestone: 2 documents changed\u{1f}- added \"B\"\n\u{1f}\n\nthree.md\n";
+        let commits = parse_log(output);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].files, ["one.md", "two.md"]);
+        assert_eq!(
+            commits[0].timestamp.to_rfc3339(),
+            "2026-03-01T08:30:00+00:00"
+        );
+        assert_eq!(commits[1].body, "- added \"B\"");
+        assert_eq!(commits[1].files, ["three.md"]);
+    }
+}
@@ -0,0 +1,102 @@
+//! Hybrid Search Data Models
+//!
+//! One search box over both indexes: keyword matches from the full-text
+//! index and passages close in meaning from the embeddings, merged into a
+//! single ranking. Each result keeps the score and rank it had in each
+//! source, so the UI can show why it was found.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// How the two rankings are merged
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum FusionMethod {
+    /// Reciprocal rank fusion: a document gets `1 / (k + rank)` from each
+    /// ranking it appears in. Ignores how far apart the scores are, so it
+    /// needs no tuning.
+    Rrf {
+        #[serde(default = "default_rrf_k")]
+        k: f32,
+    },
+    /// Each ranking's scores are scaled so its best match is 1.0, then
+    /// added up with these weights
+    WeightedSum {
+        keyword_weight: f32,
+        semantic_weight: f32,
+    },
+}
+
+pub fn default_rrf_k() -> f32 {
+    60.0
+}
+
+impl Default for FusionMethod {
+    fn default() -> Self {
+        FusionMethod::Rrf { k: default_rrf_k() }
+    }
+}
+
+/// A hybrid search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HybridSearchRequest {
+    pub query: String,
+    /// Search one project; all projects when unset
+    #[serde(default)]
+    pub project_id: Option<Uuid>,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    #[serde(default)]
+    pub fusion: FusionMethod,
+    /// Passages less similar than this don't count as semantic matches
+    #[serde(default = "default_min_similarity")]
+    pub min_similarity: f32,
+    /// Embedding model to search; the default model when unset
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+fn default_limit() -> usize {
+    20
+}
+
+fn default_min_similarity() -> f32 {
+    0.1
+}
+
+impl HybridSearchRequest {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            project_id: None,
+            limit: default_limit(),
+            fusion: FusionMethod::default(),
+            min_similarity: default_min_similarity(),
+            model: None,
+        }
+    }
+}
+
+/// A document's place in one of the rankings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SourceScore {
+    /// BM25 relevance for keyword matches, cosine similarity of the best
+    /// passage for semantic ones
+    pub score: f32,
+    /// Position in that ranking, from 1
+    pub rank: usize,
+}
+
+/// A document found by either or both searches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HybridSearchResult {
+    pub document_id: Uuid,
+    pub title: String,
+    /// The keyword match in context when there is one, otherwise the
+    /// closest passage
+    pub snippet: String,
+    /// Fused score the results are ordered by
+    pub score: f32,
+    pub keyword: Option<SourceScore>,
+    pub semantic: Option<SourceScore>,
+}
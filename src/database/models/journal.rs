@@ -0,0 +1,188 @@
+//! Journal Data Models
+//!
+//! A dated series of journal documents, one a day, for morning pages and
+//! the like. Each writer (profile, or the install when profiles are off)
+//! keeps their journal in a project of their choosing, made from a
+//! document template, and can append to today's page without opening it.
+//! Journal pages can be kept out of the project's word counts and out of
+//! the text AI features read; the choice is recorded in each page's
+//! document metadata so those queries need not know about journals.
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Document metadata key marking a page kept out of word counts
+pub const EXCLUDE_FROM_WORD_COUNT_KEY: &str = "exclude_from_word_count";
+/// Document metadata key marking a page kept out of AI context
+pub const EXCLUDE_FROM_AI_CONTEXT_KEY: &str = "exclude_from_ai_context";
+/// Document metadata key holding a journal page's day
+pub const JOURNAL_DAY_KEY: &str = "journal_day";
+
+/// Where and how a writer keeps their journal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalSettings {
+    /// Profile the journal belongs to; None when profiles are off
+    #[serde(default)]
+    pub profile_id: Option<Uuid>,
+    /// Project the pages are created in
+    pub project_id: Uuid,
+    /// Document template new pages are made from; None for a blank page
+    /// titled with the date
+    #[serde(default)]
+    pub template_id: Option<Uuid>,
+    #[serde(default = "default_true")]
+    pub exclude_from_word_counts: bool,
+    #[serde(default = "default_true")]
+    pub exclude_from_ai_context: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl JournalSettings {
+    pub fn new(profile_id: Option<Uuid>, project_id: Uuid) -> Self {
+        Self {
+            profile_id,
+            project_id,
+            template_id: None,
+            exclude_from_word_counts: true,
+            exclude_from_ai_context: true,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+/// One day's journal page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub profile_id: Option<Uuid>,
+    pub day: NaiveDate,
+    pub project_id: Uuid,
+    pub document_id: String,
+    pub title: String,
+    pub word_count: i64,
+    /// Whether anything has been written past what the template put there
+    pub written: bool,
+    /// Whether the page was created by this request
+    #[serde(default)]
+    pub created: bool,
+}
+
+/// Days in a row a writer has journaled
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalStreak {
+    /// Run ending today, or yesterday while today's page is unwritten
+    pub current: u32,
+    pub longest: u32,
+    pub total_days: u32,
+    pub last_day: Option<NaiveDate>,
+}
+
+/// Streaks from the days that have a written page
+pub fn journal_streak(days: &[NaiveDate], today: NaiveDate) -> JournalStreak {
+    let mut days = days.to_vec();
+    days.sort();
+    days.dedup();
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in &days {
+        run = match previous {
+            Some(p) if day - p == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+
+    let last_day = days.last().copied();
+    let current = match last_day {
+        Some(last) if last == today || last == today - Duration::days(1) => run,
+        _ => 0,
+    };
+    JournalStreak {
+        current,
+        longest,
+        total_days: days.len() as u32,
+        last_day,
+    }
+}
+
+/// Owner key stored for a journal; profiles off is the empty string
+pub fn owner_key(profile_id: Option<Uuid>) -> String {
+    profile_id.map(|id| id.to_string()).unwrap_or_default()
+}
+
+/// Database schema for journal settings and pages
+pub const CREATE_JOURNAL_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS journal_settings (
+    owner TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    template_id TEXT,
+    exclude_from_word_counts INTEGER NOT NULL DEFAULT 1,
+    exclude_from_ai_context INTEGER NOT NULL DEFAULT 1,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS journal_entries (
+    owner TEXT NOT NULL,
+    day TEXT NOT NULL,
+    document_id TEXT NOT NULL,
+    -- Checksum of the page as the template left it, which doesn't count
+    -- as writing
+    starting_checksum TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (owner, day),
+    FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_journal_entries_document ON journal_entries(document_id);
+"#;
+
+/// Create or update a writer's journal settings
+pub const UPSERT_JOURNAL_SETTINGS_SQL: &str = r#"
+INSERT INTO journal_settings (owner, project_id, template_id, exclude_from_word_counts, exclude_from_ai_context, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+ON CONFLICT(owner) DO UPDATE SET
+    project_id = excluded.project_id,
+    template_id = excluded.template_id,
+    exclude_from_word_counts = excluded.exclude_from_word_counts,
+    exclude_from_ai_context = excluded.exclude_from_ai_context,
+    updated_at = excluded.updated_at
+"#;
+
+/// A writer's pages in a range of days, with their documents; pages whose
+/// document is in the trash are left out
+pub const GET_JOURNAL_ENTRIES_SQL: &str = r#"
+SELECT j.day, d.project_id, d.id, d.title, d.word_count, d.checksum != j.starting_checksum
+FROM journal_entries j JOIN documents d ON d.id = j.document_id
+WHERE j.owner = ?1 AND d.is_active = 1
+  AND (?2 IS NULL OR j.day >= ?2)
+  AND (?3 IS NULL OR j.day <= ?3)
+ORDER BY j.day
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_streak() {
+        let day = |d: u32| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+        let days = [day(1), day(2), day(3), day(5), day(6), day(6)];
+
+        let streak = journal_streak(&days, day(7));
+        assert_eq!(streak.current, 2);
+        assert_eq!(streak.longest, 3);
+        assert_eq!(streak.total_days, 5);
+        assert_eq!(streak.last_day, Some(day(6)));
+
+        assert_eq!(journal_streak(&days, day(8)).current, 0);
+        assert_eq!(journal_streak(&[], day(8)), JournalStreak::default());
+    }
+}
@@ -0,0 +1,164 @@
+//! Lexicon Data Models
+//!
+//! Dictionaries of constructed languages. A language can belong to a
+//! culture in the codex; its words carry a gloss, part of speech,
+//! etymology and pronunciation, with an optional audio attachment.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A constructed language
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conlang {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub name: String,
+    pub description: String,
+    /// Codex entry of the culture that speaks it
+    pub culture_entry_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Conlang {
+    pub fn new(project_id: Uuid, name: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            name: name.into(),
+            description: String::new(),
+            culture_entry_id: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// A word of a constructed language
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LexiconEntry {
+    pub id: Uuid,
+    pub language_id: Uuid,
+    pub word: String,
+    /// Meaning in the manuscript's language
+    pub gloss: String,
+    /// Free text, since conlangs have their own word classes
+    pub part_of_speech: String,
+    pub etymology: String,
+    /// Pronunciation, usually in IPA
+    pub pronunciation: String,
+    /// Recording of the word, an audio attachment
+    pub audio_attachment_id: Option<Uuid>,
+    /// Inflected and alternate forms, also kept out of spellcheck
+    #[serde(default)]
+    pub variants: Vec<String>,
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl LexiconEntry {
+    pub fn new(language_id: Uuid, word: impl Into<String>, gloss: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            language_id,
+            word: word.into(),
+            gloss: gloss.into(),
+            part_of_speech: String::new(),
+            etymology: String::new(),
+            pronunciation: String::new(),
+            audio_attachment_id: None,
+            variants: Vec::new(),
+            notes: String::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Lexicon search parameters
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LexiconQuery {
+    pub project_id: Uuid,
+    /// Limit to one language
+    #[serde(default)]
+    pub language_id: Option<Uuid>,
+    /// Matched against words, variants and glosses
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub part_of_speech: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Database schema for conlangs and their lexicons
+pub const CREATE_LEXICON_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS conlangs (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    culture_entry_id TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS lexicon_entries (
+    id TEXT PRIMARY KEY,
+    language_id TEXT NOT NULL,
+    word TEXT NOT NULL,
+    gloss TEXT NOT NULL DEFAULT '',
+    part_of_speech TEXT NOT NULL DEFAULT '',
+    etymology TEXT NOT NULL DEFAULT '',
+    pronunciation TEXT NOT NULL DEFAULT '',
+    audio_attachment_id TEXT,
+    variants TEXT NOT NULL DEFAULT '[]',
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (language_id) REFERENCES conlangs(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_conlangs_project ON conlangs(project_id);
+CREATE INDEX IF NOT EXISTS idx_lexicon_entries_language ON lexicon_entries(language_id, word);
+"#;
+
+/// Insert or replace conlang SQL
+pub const UPSERT_CONLANG_SQL: &str = r#"
+INSERT INTO conlangs (id, project_id, name, description, culture_entry_id, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+ON CONFLICT(id) DO UPDATE SET
+    name = excluded.name,
+    description = excluded.description,
+    culture_entry_id = excluded.culture_entry_id,
+    updated_at = excluded.updated_at
+"#;
+
+/// Insert or replace lexicon entry SQL
+pub const UPSERT_LEXICON_ENTRY_SQL: &str = r#"
+INSERT INTO lexicon_entries (
+    id, language_id, word, gloss, part_of_speech, etymology, pronunciation,
+    audio_attachment_id, variants, notes, created_at, updated_at
+)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+ON CONFLICT(id) DO UPDATE SET
+    word = excluded.word,
+    gloss = excluded.gloss,
+    part_of_speech = excluded.part_of_speech,
+    etymology = excluded.etymology,
+    pronunciation = excluded.pronunciation,
+    audio_attachment_id = excluded.audio_attachment_id,
+    variants = excluded.variants,
+    notes = excluded.notes,
+    updated_at = excluded.updated_at
+"#;
+
+/// Columns read into a `LexiconEntry`
+pub const LEXICON_ENTRY_COLUMNS: &str = "e.id, e.language_id, e.word, e.gloss, e.part_of_speech, \
+     e.etymology, e.pronunciation, e.audio_attachment_id, e.variants, e.notes, e.created_at, \
+     e.updated_at";
@@ -0,0 +1,164 @@
+//! Lint Pack Data Models
+//!
+//! Genre lint packs bundle manuscript checks tuned to a genre's conventions
+//! (how POV may shift, how long chapters run). Projects choose which packs
+//! run and can raise, lower or turn off the severity of any rule.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// How serious a lint issue is; `Off` disables a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintSeverity {
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+
+/// Where a change of POV character is conventional
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PovBoundary {
+    /// At a chapter start only
+    Chapter,
+    /// At any scene break
+    Scene,
+}
+
+/// A check and its genre tuning
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum LintRule {
+    /// The POV character changes somewhere the genre doesn't expect, or
+    /// more characters get POV scenes than it usually allows
+    PovSwitch {
+        boundary: PovBoundary,
+        max_pov_characters: Option<usize>,
+    },
+    /// One scene gets into the thoughts of more than one character
+    HeadHopping,
+    /// A chapter much longer or shorter than the project's median, or over
+    /// the genre's usual maximum
+    ChapterLength {
+        outlier_ratio: f32,
+        max_words: Option<usize>,
+    },
+}
+
+impl LintRule {
+    /// Identifier used in severity overrides and issues
+    pub fn id(&self) -> &'static str {
+        match self {
+            LintRule::PovSwitch { .. } => "pov_switch",
+            LintRule::HeadHopping => "head_hopping",
+            LintRule::ChapterLength { .. } => "chapter_length",
+        }
+    }
+}
+
+/// A rule as configured in a pack
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackRule {
+    #[serde(flatten)]
+    pub rule: LintRule,
+    pub severity: LintSeverity,
+}
+
+/// A named set of rules for a genre
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintPack {
+    /// Short identifier, e.g. "romance"
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub rules: Vec<PackRule>,
+}
+
+/// Which packs a project runs and how it overrides their severities
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintSettings {
+    pub project_id: Uuid,
+    #[serde(default)]
+    pub enabled_packs: Vec<String>,
+    /// Keyed by rule id ("head_hopping") for every pack, or by
+    /// "pack.rule" ("thriller.chapter_length") for one pack
+    #[serde(default)]
+    pub severity_overrides: HashMap<String, LintSeverity>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl LintSettings {
+    pub fn new(project_id: Uuid) -> Self {
+        Self {
+            project_id,
+            enabled_packs: Vec::new(),
+            severity_overrides: HashMap::new(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Severity of a pack's rule after the project's overrides
+    pub fn severity(&self, pack: &LintPack, rule: &PackRule) -> LintSeverity {
+        self.severity_overrides
+            .get(&format!("{}.{}", pack.id, rule.rule.id()))
+            .or_else(|| self.severity_overrides.get(rule.rule.id()))
+            .copied()
+            .unwrap_or(rule.severity)
+    }
+}
+
+/// A problem found by a lint rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintIssue {
+    pub pack: String,
+    pub rule: String,
+    pub severity: LintSeverity,
+    pub document_id: Uuid,
+    pub title: String,
+    /// Byte offsets of the flagged text in the document content
+    pub start: usize,
+    pub end: usize,
+    /// 1-based line number
+    pub line: usize,
+    pub message: String,
+}
+
+/// Issues found in a project by its enabled packs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintReport {
+    pub project_id: Uuid,
+    pub packs: Vec<String>,
+    pub documents_checked: usize,
+    /// Most severe first, then in binder order
+    pub issues: Vec<LintIssue>,
+}
+
+/// Database schema for per-project lint settings
+pub const CREATE_LINT_SETTINGS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS lint_settings (
+    project_id TEXT PRIMARY KEY,
+    enabled_packs TEXT NOT NULL DEFAULT '[]',
+    severity_overrides TEXT NOT NULL DEFAULT '{}',
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+"#;
+
+/// Upsert lint settings SQL
+pub const UPSERT_LINT_SETTINGS_SQL: &str = r#"
+INSERT INTO lint_settings (project_id, enabled_packs, severity_overrides, updated_at)
+VALUES (?1, ?2, ?3, ?4)
+ON CONFLICT(project_id) DO UPDATE SET
+    enabled_packs = excluded.enabled_packs,
+    severity_overrides = excluded.severity_overrides,
+    updated_at = excluded.updated_at
+"#;
+
+/// Get lint settings SQL
+pub const GET_LINT_SETTINGS_SQL: &str = r#"
+SELECT enabled_packs, severity_overrides, updated_at FROM lint_settings WHERE project_id = ?1
+"#;
@@ -0,0 +1,374 @@
+//! Markdown Sync Models
+//!
+//! A project can be mirrored to a folder of Markdown files, one per
+//! document, so it can be edited in other tools and kept under git. Each
+//! file starts with front matter holding the document's id, title and
+//! metadata, written as `key: value` lines whose values are JSON (which
+//! YAML also reads); hand-written unquoted values are read as text.
+//!
+//! Sync is three-way: the state of both sides at the last sync is kept as
+//! fingerprints, so a side that changed since then wins over one that
+//! didn't, and a document changed on both sides is a conflict left for the
+//! author to resolve.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Front matter fence
+const FENCE: &str = "---";
+
+/// A project mirrored to a folder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkdownSyncFolder {
+    pub project_id: Uuid,
+    pub folder: PathBuf,
+    pub created_at: DateTime<Utc>,
+    pub last_synced_at: Option<DateTime<Utc>>,
+}
+
+/// Which side wins when resolving a conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncSide {
+    Database,
+    Folder,
+}
+
+/// A document the sync left alone because both sides changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkdownConflict {
+    pub document_id: String,
+    pub file_name: String,
+    pub title: String,
+    pub reason: String,
+}
+
+/// What a sync did
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkdownSyncReport {
+    /// Files written from documents, new ones included
+    pub files_written: usize,
+    /// Documents updated from their files, so open editors can reload
+    pub documents_updated: Vec<String>,
+    /// Documents made from new files
+    pub documents_created: Vec<String>,
+    /// Documents moved to the trash because their file was deleted
+    pub documents_trashed: Vec<String>,
+    /// Files deleted because their document was
+    pub files_deleted: usize,
+    pub conflicts: Vec<MarkdownConflict>,
+    pub synced_at: DateTime<Utc>,
+}
+
+/// A document as the sync sees it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncDocument {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub metadata: Map<String, Value>,
+}
+
+impl SyncDocument {
+    /// The document as a Markdown file
+    pub fn render(&self) -> String {
+        let mut text = format!("{}\n", FENCE);
+        text.push_str(&format!("id: {}\n", Value::from(self.id.as_str())));
+        text.push_str(&format!("title: {}\n", Value::from(self.title.as_str())));
+        // Keys the front matter can't hold are left out of the file
+        for (key, value) in self.metadata.iter().filter(|(key, _)| writable_key(key)) {
+            text.push_str(&format!("{}: {}\n", key, value));
+        }
+        text.push_str(FENCE);
+        text.push_str("\n\n");
+        text.push_str(&self.content);
+        text
+    }
+
+    /// Metadata as stored in the documents table
+    pub fn metadata_json(&self) -> Option<String> {
+        (!self.metadata.is_empty()).then(|| Value::Object(self.metadata.clone()).to_string())
+    }
+}
+
+/// Metadata after reading a file: what the front matter says, plus the
+/// keys it couldn't hold
+pub fn merge_front_matter(
+    existing: &Map<String, Value>,
+    front_matter: Map<String, Value>,
+) -> Map<String, Value> {
+    let mut merged: Map<String, Value> = existing
+        .iter()
+        .filter(|(key, _)| !writable_key(key))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    merged.extend(front_matter);
+    merged
+}
+
+fn writable_key(key: &str) -> bool {
+    !matches!(key, "id" | "title" | "") && !key.contains([':', '\n', '\r']) && key.trim() == key
+}
+
+/// A Markdown file read back: the id it names, if any, and the document
+/// it describes. Without a title in the front matter the file name is used.
+pub fn parse_markdown(text: &str, fallback_title: &str) -> (Option<String>, SyncDocument) {
+    let mut id = None;
+    let mut title = None;
+    let mut metadata = Map::new();
+    let mut body = text;
+
+    let mut lines = text.split_inclusive('\n');
+    if lines.next().map(str::trim_end) == Some(FENCE) {
+        let mut consumed = text.split_inclusive('\n').next().unwrap_or("").len();
+        let mut front = Vec::new();
+        let mut closed = false;
+        for line in lines {
+            consumed += line.len();
+            if line.trim_end() == FENCE {
+                closed = true;
+                break;
+            }
+            front.push(line.trim_end());
+        }
+        if closed {
+            for line in front {
+                let Some((key, value)) = line.split_once(':') else {
+                    continue;
+                };
+                let (key, value) = (key.trim(), value.trim());
+                let value = serde_json::from_str(value).unwrap_or(Value::from(value));
+                match key {
+                    "id" => {
+                        id = value
+                            .as_str()
+                            .map(str::to_string)
+                            .or(Some(value.to_string()))
+                    }
+                    "title" => {
+                        title = Some(
+                            value
+                                .as_str()
+                                .map(str::to_string)
+                                .unwrap_or(value.to_string()),
+                        )
+                    }
+                    "" => {}
+                    _ => {
+                        metadata.insert(key.to_string(), value);
+                    }
+                }
+            }
+            body = &text[consumed..];
+            body = body
+                .strip_prefix("\r\n")
+                .or_else(|| body.strip_prefix('\n'))
+                .unwrap_or(body);
+        }
+    }
+
+    let document = SyncDocument {
+        id: id.clone().unwrap_or_default(),
+        title: title
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or_else(|| fallback_title.to_string()),
+        content: body.to_string(),
+        metadata,
+    };
+    (id.filter(|id| !id.is_empty()), document)
+}
+
+/// Fingerprint of one side of a document, to tell whether it changed
+pub fn fingerprint(text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(text.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// File name for a new document: its binder position and a slug of its
+/// title, e.g. "004-the-lantern.md"
+pub fn file_name_for(position: usize, title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.to_lowercase().chars() {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.trim_end_matches('-').chars().take(60).collect();
+    let slug = slug.trim_end_matches('-');
+    format!(
+        "{:03}-{}.md",
+        position,
+        if slug.is_empty() { "untitled" } else { slug }
+    )
+}
+
+/// Fingerprints of both sides as they were left by the last sync
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncBase {
+    pub file_name: String,
+    pub document_fingerprint: String,
+    pub file_fingerprint: String,
+}
+
+/// What to do with a document and its file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncAction {
+    /// Both sides are as they were; record the pairing if it's new
+    Keep,
+    /// Write the file from the document
+    WriteFile,
+    /// Update the document from the file
+    UpdateDocument,
+    /// Make a document from a file that has none
+    CreateDocument,
+    TrashDocument,
+    DeleteFile,
+    /// Neither side exists any more
+    Forget,
+    Conflict(String),
+}
+
+/// Decide from each side's current fingerprint (None when that side is
+/// gone: no file, or the document is trashed) and the last sync's
+pub fn decide(document: Option<&str>, file: Option<&str>, base: Option<&SyncBase>) -> SyncAction {
+    match (document, file, base) {
+        (Some(document), Some(file), Some(base)) => {
+            match (
+                document != base.document_fingerprint,
+                file != base.file_fingerprint,
+            ) {
+                (false, false) => SyncAction::Keep,
+                (true, false) => SyncAction::WriteFile,
+                (false, true) => SyncAction::UpdateDocument,
+                (true, true) => {
+                    SyncAction::Conflict("Changed in the app and in the folder".to_string())
+                }
+            }
+        }
+        // A file naming the document but never synced, e.g. after the
+        // folder was reconnected; fine if it says the same
+        (Some(document), Some(file), None) if document == file => SyncAction::Keep,
+        (Some(_), Some(_), None) => {
+            SyncAction::Conflict("The file and the document differ".to_string())
+        }
+        (Some(document), None, Some(base)) if document == base.document_fingerprint => {
+            SyncAction::TrashDocument
+        }
+        (Some(_), None, _) => SyncAction::WriteFile,
+        (None, Some(file), Some(base)) if file == base.file_fingerprint => SyncAction::DeleteFile,
+        (None, Some(_), Some(_)) => {
+            SyncAction::Conflict("Deleted in the app but edited in the folder".to_string())
+        }
+        (None, Some(_), None) => SyncAction::CreateDocument,
+        (None, None, _) => SyncAction::Forget,
+    }
+}
+
+/// Database schema for synced folders and the state of each file at the
+/// last sync
+pub const CREATE_MARKDOWN_SYNC_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS markdown_sync_folders (
+    project_id TEXT PRIMARY KEY,
+    folder TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    last_synced_at TEXT,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS markdown_sync_files (
+    project_id TEXT NOT NULL,
+    document_id TEXT NOT NULL,
+    file_name TEXT NOT NULL,
+    document_fingerprint TEXT NOT NULL,
+    file_fingerprint TEXT NOT NULL,
+    synced_at TEXT NOT NULL,
+    PRIMARY KEY (project_id, document_id),
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+"#;
+
+/// Record the state of a file after syncing it
+pub const UPSERT_MARKDOWN_SYNC_FILE_SQL: &str = r#"
+INSERT INTO markdown_sync_files (project_id, document_id, file_name, document_fingerprint, file_fingerprint, synced_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+ON CONFLICT(project_id, document_id) DO UPDATE SET
+    file_name = excluded.file_name,
+    document_fingerprint = excluded.document_fingerprint,
+    file_fingerprint = excluded.file_fingerprint,
+    synced_at = excluded.synced_at
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_front_matter_round_trip() {
+        let document = SyncDocument {
+            id: "scene-1".to_string(),
+            title: "Chapter 1: \"Ashfall\"".to_string(),
+            content: "---\nThe harbor burned.\n".to_string(),
+            metadata: Map::from_iter([("tags".to_string(), serde_json::json!(["fire", "harbor"]))]),
+        };
+        let (id, parsed) = parse_markdown(&document.render(), "ignored");
+        assert_eq!(id.as_deref(), Some("scene-1"));
+        assert_eq!(parsed, document);
+
+        // Written by hand: unquoted values, no id, no blank line
+        let (id, parsed) = parse_markdown(
+            "---\ntitle: The Lantern\npov: Mara\n---\nIt glowed.",
+            "lantern",
+        );
+        assert_eq!(id, None);
+        assert_eq!(parsed.title, "The Lantern");
+        assert_eq!(parsed.metadata.get("pov"), Some(&Value::from("Mara")));
+        assert_eq!(parsed.content, "It glowed.");
+
+        let (_, parsed) = parse_markdown("No front matter", "notes");
+        assert_eq!(
+            (parsed.title.as_str(), parsed.content.as_str()),
+            ("notes", "No front matter")
+        );
+        assert_eq!(
+            file_name_for(4, "The Lantern's Light!"),
+            "004-the-lantern-s-light.md"
+        );
+    }
+
+    #[test]
+    fn test_decide() {
+        let base = SyncBase {
+            file_name: "001-a.md".to_string(),
+            document_fingerprint: "d".to_string(),
+            file_fingerprint: "f".to_string(),
+        };
+        let base = Some(&base);
+        assert_eq!(decide(Some("d"), Some("f"), base), SyncAction::Keep);
+        assert_eq!(decide(Some("d2"), Some("f"), base), SyncAction::WriteFile);
+        assert_eq!(
+            decide(Some("d"), Some("f2"), base),
+            SyncAction::UpdateDocument
+        );
+        assert!(matches!(
+            decide(Some("d2"), Some("f2"), base),
+            SyncAction::Conflict(_)
+        ));
+        assert_eq!(decide(Some("d"), None, base), SyncAction::TrashDocument);
+        assert_eq!(decide(Some("d2"), None, base), SyncAction::WriteFile);
+        assert_eq!(decide(None, Some("f"), base), SyncAction::DeleteFile);
+        assert!(matches!(
+            decide(None, Some("f2"), base),
+            SyncAction::Conflict(_)
+        ));
+        assert_eq!(decide(None, Some("f"), None), SyncAction::CreateDocument);
+        assert_eq!(decide(Some("x"), None, None), SyncAction::WriteFile);
+        assert_eq!(decide(None, None, base), SyncAction::Forget);
+    }
+}
@@ -6,10 +6,56 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::database::text_diff::{DiffHunk, DiffSummary, MergeResult};
+
+pub mod activity;
+pub mod ai_log;
 pub mod analysis;
+pub mod annotation;
+pub mod anonymizer;
+pub mod attachment;
+pub mod beta_reader;
+pub mod calendar;
+pub mod certification;
+pub mod challenge;
+pub mod chronology;
 pub mod codex;
+pub mod codex_autofill;
+pub mod codex_graph;
+pub mod codex_relationship;
 pub mod codex_service;
+pub mod codex_transfer;
+pub mod content_scan;
+pub mod deadline;
+pub mod document_structure;
+pub mod document_template;
+pub mod draft;
+pub mod export_record;
+pub mod focus;
+pub mod git_history;
+pub mod hybrid_search;
+pub mod journal;
+pub mod lexicon;
+pub mod lint_pack;
+pub mod markdown_sync;
+pub mod narrative_voice;
+pub mod note_import;
+pub mod notion;
+pub mod obsidian;
+pub mod profile;
+pub mod readability;
+pub mod related_notes;
+pub mod rename;
 pub mod research;
+pub mod serial;
+pub mod stats;
+pub mod story_bible;
+pub mod style_sheet;
+pub mod submission;
+pub mod timeline;
+pub mod undo_history;
+pub mod word_usage;
+pub mod workspace;
 
 /// Project model representing a logical grouping of documents
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -113,6 +159,52 @@ pub struct DocumentVersion {
     pub change_description: Option<String>,
 }
 
+/// Changes between two versions of a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionDiff {
+    pub document_id: Uuid,
+    pub from_version: u32,
+    pub to_version: u32,
+    /// Set when the title changed, as (old, new)
+    pub title_change: Option<(String, String)>,
+    pub hunks: Vec<DiffHunk>,
+    pub summary: DiffSummary,
+}
+
+/// Outcome of merging an edit made against an older version into the
+/// current document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentMerge {
+    pub document_id: Uuid,
+    pub base_version: u32,
+    /// Version the edit was merged with
+    pub current_version: u32,
+    pub merge: MergeResult,
+    /// Version the merged content was saved as; unset when conflicts kept
+    /// it from being saved
+    pub saved_version: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashItemKind {
+    Document,
+    Project,
+}
+
+/// A deleted document or project, kept until it is restored or purged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashItem {
+    pub kind: TrashItemKind,
+    pub id: String,
+    /// Project a trashed document belongs to
+    pub project_id: Option<String>,
+    pub title: String,
+    pub deleted_at: DateTime<Utc>,
+    /// When it will be purged for good
+    pub purge_at: DateTime<Utc>,
+}
+
 /// Document embedding for vector operations
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentEmbedding {
@@ -120,6 +212,9 @@ pub struct DocumentEmbedding {
     pub document_id: Uuid,
     pub vector_data: Vec<f32>,
     pub model_name: String,
+    /// Length of `vector_data`; vectors of different models can't be compared
+    #[serde(default)]
+    pub dimensions: usize,
     pub chunk_index: usize,
     pub text_chunk: String,
     pub start_char: usize,
@@ -160,5 +255,84 @@ pub struct EmbeddingStatistics {
     pub average_chunk_size: f64,
 }
 
+/// A registered embedding model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingModel {
+    pub name: String,
+    pub dimensions: usize,
+    /// Where vectors come from, e.g. "openai" or "local"
+    pub provider: String,
+    /// Model used for new embeddings and for queries that don't pick one
+    pub is_default: bool,
+    pub registered_at: DateTime<Utc>,
+    /// Stored chunks and documents embedded with this model
+    #[serde(default)]
+    pub embeddings: usize,
+    #[serde(default)]
+    pub documents: usize,
+}
+
+/// State of an embedding migration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingMigrationStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl EmbeddingMigrationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmbeddingMigrationStatus::Pending => "pending",
+            EmbeddingMigrationStatus::Running => "running",
+            EmbeddingMigrationStatus::Completed => "completed",
+            EmbeddingMigrationStatus::Failed => "failed",
+            EmbeddingMigrationStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(EmbeddingMigrationStatus::Pending),
+            "running" => Some(EmbeddingMigrationStatus::Running),
+            "completed" => Some(EmbeddingMigrationStatus::Completed),
+            "failed" => Some(EmbeddingMigrationStatus::Failed),
+            "cancelled" => Some(EmbeddingMigrationStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            EmbeddingMigrationStatus::Completed
+                | EmbeddingMigrationStatus::Failed
+                | EmbeddingMigrationStatus::Cancelled
+        )
+    }
+}
+
+/// A job re-embedding documents with a new model. The previous default
+/// keeps answering queries until the job completes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingMigration {
+    pub id: Uuid,
+    /// Default model when the job was started
+    pub from_model: String,
+    pub to_model: String,
+    pub status: EmbeddingMigrationStatus,
+    pub total_documents: usize,
+    pub processed_documents: usize,
+    pub failed_documents: usize,
+    /// Delete other models' vectors once the job completes
+    pub prune_old: bool,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
 /// Model-specific result types
 pub type ModelResult<T> = Result<T, super::DatabaseError>;
@@ -0,0 +1,121 @@
+//! Narrative Voice Data Models
+//!
+//! The narrative person and tense a scene is written in, what its document
+//! metadata declares it should be, and where the prose drifts from either.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Grammatical person of the narration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NarrativePerson {
+    First,
+    Second,
+    Third,
+}
+
+impl NarrativePerson {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NarrativePerson::First => "first person",
+            NarrativePerson::Second => "second person",
+            NarrativePerson::Third => "third person",
+        }
+    }
+}
+
+/// Tense of the narration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NarrativeTense {
+    Past,
+    Present,
+}
+
+impl NarrativeTense {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NarrativeTense::Past => "past tense",
+            NarrativeTense::Present => "present tense",
+        }
+    }
+}
+
+/// A scene's declared voice, stored under the `pov`, `person` and `tense`
+/// keys of its document's metadata
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneVoice {
+    /// Name of the POV character
+    #[serde(default)]
+    pub pov: Option<String>,
+    #[serde(default)]
+    pub person: Option<NarrativePerson>,
+    #[serde(default)]
+    pub tense: Option<NarrativeTense>,
+}
+
+/// What kind of problem a voice issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoiceIssueKind {
+    /// The scene reads as a different person than declared
+    PersonMismatch,
+    /// The scene reads in a different tense than declared
+    TenseMismatch,
+    /// The narration follows a different character than the declared POV
+    PovMismatch,
+    /// The narration slips into another person partway through
+    PersonSwitch,
+    /// The narration slips into another tense partway through
+    TenseSwitch,
+}
+
+/// A voice problem, anchored to the sentence where it starts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoiceIssue {
+    pub kind: VoiceIssueKind,
+    pub document_id: Uuid,
+    pub title: String,
+    /// 1-based scene number within the document
+    pub scene: usize,
+    /// 1-based sentence number within the scene
+    pub sentence: usize,
+    /// Byte offsets of the sentence in the document content
+    pub start: usize,
+    pub end: usize,
+    /// 1-based line number
+    pub line: usize,
+    pub excerpt: String,
+    pub expected: String,
+    pub found: String,
+    pub message: String,
+}
+
+/// The voice detected in one scene
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneVoiceReport {
+    pub document_id: Uuid,
+    pub title: String,
+    pub scene: usize,
+    /// Byte offsets of the scene in the document content
+    pub start: usize,
+    pub end: usize,
+    pub declared: SceneVoice,
+    /// None when the scene is too short or too mixed to tell
+    pub person: Option<NarrativePerson>,
+    pub tense: Option<NarrativeTense>,
+    /// The character the narration follows, when one stands out
+    pub pov: Option<String>,
+}
+
+/// Person and tense of every scene in a project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoiceReport {
+    pub project_id: Uuid,
+    pub documents_checked: usize,
+    /// In binder order
+    pub scenes: Vec<SceneVoiceReport>,
+    /// In binder order, then by position
+    pub issues: Vec<VoiceIssue>,
+}
@@ -0,0 +1,291 @@
+//! Note Import Models
+//!
+//! What the importers for other note apps share. Obsidian notes become
+//! research documents: documents marked as research in their metadata and
+//! left out of word counts, carrying their tags and where they came from.
+//! Notion pages become projects and documents, and Notion databases whose
+//! columns fit codex entries become entries. Links between notes are kept
+//! in `document_links`, so a document can list the ones linking to it.
+//! Each import returns a report of what was brought over and of anything
+//! that couldn't be converted.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::codex::CodexEntryType;
+use super::codex_transfer::DuplicatePolicy;
+
+/// Metadata key marking a document as research rather than manuscript
+pub const RESEARCH_KEY: &str = "research";
+/// Metadata key holding a document's tags
+pub const TAGS_KEY: &str = "tags";
+/// Metadata key recording the app and path a document was imported from
+pub const IMPORTED_FROM_KEY: &str = "imported_from";
+/// Metadata key holding note properties with no place of their own
+pub const PROPERTIES_KEY: &str = "properties";
+/// How converted content refers to an attachment, as in
+/// `![map.png](attachment:<id>)`
+pub const ATTACHMENT_LINK_PREFIX: &str = "attachment:";
+
+/// App the notes came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteSource {
+    Obsidian,
+    Notion,
+}
+
+impl NoteSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NoteSource::Obsidian => "obsidian",
+            NoteSource::Notion => "notion",
+        }
+    }
+}
+
+/// Something in the notes that didn't come over as it was
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportIssueKind {
+    /// A link to a note that isn't there; kept as plain text
+    UnresolvedLink,
+    /// An embedded file that isn't there; kept as plain text
+    MissingAttachment,
+    /// A note embedded in another; kept as a link to it
+    EmbedAsLink,
+    /// A query or plugin block, kept as text without its results
+    PluginBlock,
+    /// Front matter that couldn't be read; kept out of the properties
+    FrontMatter,
+    /// A file of a kind that can't be imported
+    UnsupportedFile,
+    /// A note that couldn't be read as text
+    Unreadable,
+    /// A note imported before; left as it is
+    AlreadyImported,
+    /// A file no note embeds; not imported
+    UnusedAttachment,
+    /// A database whose columns don't fit codex entries; its rows are
+    /// imported as pages
+    UnmappedDatabase,
+    /// A database row that couldn't become a codex entry
+    CodexRow,
+}
+
+/// A note, file or line that didn't come over as it was
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportIssue {
+    /// Path within the notes, with `/` separators
+    pub path: String,
+    pub kind: ImportIssueKind,
+    pub detail: String,
+}
+
+/// How a tag was brought over
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagMapping {
+    pub tag: String,
+    /// None when the tag map drops it
+    pub mapped_to: Option<String>,
+    /// Notes carrying it
+    pub notes: usize,
+}
+
+/// A note imported as a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportedNote {
+    pub path: String,
+    pub project_id: Uuid,
+    pub document_id: Uuid,
+    pub title: String,
+    pub tags: Vec<String>,
+    /// Links to other imported notes
+    pub links: usize,
+    pub attachments: usize,
+}
+
+/// A project made from a top-level page or database
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportedProject {
+    pub path: String,
+    pub project_id: Uuid,
+    pub name: String,
+}
+
+/// A database imported as codex entries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportedDatabase {
+    pub path: String,
+    pub project_id: Uuid,
+    pub title: String,
+    /// Type of rows that don't give their own
+    pub default_type: Option<CodexEntryType>,
+    pub imported: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+/// What an import did, or would do on a dry run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteImportReport {
+    pub source: NoteSource,
+    pub dry_run: bool,
+    #[serde(default)]
+    pub projects: Vec<ImportedProject>,
+    pub notes: Vec<ImportedNote>,
+    #[serde(default)]
+    pub databases: Vec<ImportedDatabase>,
+    pub attachments_imported: usize,
+    pub links_kept: usize,
+    pub tags: Vec<TagMapping>,
+    pub issues: Vec<ImportIssue>,
+}
+
+/// How to import notes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NoteImportOptions {
+    /// Tags to rename, e.g. "people" to "character"; nested tags follow
+    /// their parent, and an empty name drops the tag
+    #[serde(default)]
+    pub tag_map: HashMap<String, String>,
+    /// Report what would be imported without importing it
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl NoteImportOptions {
+    /// The tag under the tag map, matching the longest mapped parent; None
+    /// when it's dropped
+    pub fn map_tag(&self, tag: &str) -> Option<String> {
+        let tag = normalize_tag(tag);
+        let mapped = self
+            .tag_map
+            .iter()
+            .map(|(from, to)| (normalize_tag(from), normalize_tag(to)))
+            .filter(|(from, _)| {
+                tag == *from
+                    || tag
+                        .strip_prefix(from.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .max_by_key(|(from, _)| from.len());
+        match mapped {
+            Some((_, to)) if to.is_empty() => None,
+            Some((from, to)) => Some(format!("{}{}", to, &tag[from.len()..])),
+            None => Some(tag),
+        }
+    }
+}
+
+/// How to import a Notion export
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotionImportOptions {
+    /// Put everything in this project; otherwise each top-level page or
+    /// database becomes a project of its own
+    #[serde(default)]
+    pub project_id: Option<Uuid>,
+    /// What to do with rows matching codex entries already there
+    #[serde(default)]
+    pub duplicates: DuplicatePolicy,
+    /// Report what would be imported without importing it
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// A tag without its `#`, lowercase
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').trim().to_lowercase()
+}
+
+/// `target` from the folder `folder`, both relative to the notes' root
+pub fn join_relative(folder: &str, target: &str) -> String {
+    let mut parts: Vec<&str> = folder.split('/').filter(|p| !p.is_empty()).collect();
+    for part in target.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// `%XX` escapes in a link decoded
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// A document linking to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentBacklink {
+    pub document_id: Uuid,
+    pub title: String,
+    /// The link as written, e.g. "the old mill"
+    pub link_text: String,
+}
+
+/// Database schema for links between documents
+pub const CREATE_DOCUMENT_LINKS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS document_links (
+    project_id TEXT NOT NULL,
+    source_id TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    link_text TEXT NOT NULL DEFAULT '',
+    PRIMARY KEY (source_id, target_id, link_text),
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_links_target ON document_links(target_id);
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_tag() {
+        let options = NoteImportOptions {
+            tag_map: HashMap::from([
+                ("People".to_string(), "character".to_string()),
+                ("people/minor".to_string(), "extra".to_string()),
+                ("#todo".to_string(), String::new()),
+            ]),
+            dry_run: false,
+        };
+        assert_eq!(options.map_tag("#People"), Some("character".to_string()));
+        assert_eq!(
+            options.map_tag("people/mira"),
+            Some("character/mira".to_string())
+        );
+        assert_eq!(
+            options.map_tag("people/minor/guard"),
+            Some("extra/guard".to_string())
+        );
+        assert_eq!(options.map_tag("peoples"), Some("peoples".to_string()));
+        assert_eq!(options.map_tag("todo"), None);
+        assert_eq!(options.map_tag("todo/later"), None);
+    }
+}
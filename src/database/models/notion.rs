@@ -0,0 +1,244 @@
+//! Notion Export Models
+//!
+//! Reading a Notion workspace export. Every page is a Markdown or HTML
+//! file named after its title and id, with its sub-pages in a folder of
+//! the same name beside it; a database is a CSV file of its rows, with
+//! the rows' own pages in its folder. Pages link to each other and to
+//! their images by relative path.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::ops::Range;
+
+use super::note_import::percent_decode;
+
+/// Length of the id Notion puts after each exported name
+const NOTION_ID_LEN: usize = 32;
+
+static MARKDOWN_REFERENCE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\]\(([^)\s]+)\)").expect("static regex"));
+static HTML_REFERENCE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?:href|src)="([^"]*)""#).expect("static regex"));
+static HTML_TITLE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<title>(.*?)</title>").expect("static regex"));
+
+/// What a file in the export is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotionFileKind {
+    Page {
+        html: bool,
+    },
+    Database,
+    /// An image or other file pages refer to
+    File,
+}
+
+/// What the file at `path` is
+pub fn file_kind(path: &str) -> NotionFileKind {
+    let lower = path.to_lowercase();
+    if lower.ends_with(".md") {
+        NotionFileKind::Page { html: false }
+    } else if lower.ends_with(".html") {
+        NotionFileKind::Page { html: true }
+    } else if lower.ends_with(".csv") {
+        NotionFileKind::Database
+    } else {
+        NotionFileKind::File
+    }
+}
+
+/// Where a page or database sits in the export: its path without the
+/// extension, which is also the folder its sub-pages are in. A database's
+/// `_all` export, which has every row rather than those of its view,
+/// shares the key of the plain one.
+pub fn node_key(path: &str) -> &str {
+    let stem = path.rsplit_once('.').map_or(path, |(stem, _)| stem);
+    if file_kind(path) == NotionFileKind::Database {
+        stem.strip_suffix("_all").unwrap_or(stem)
+    } else {
+        stem
+    }
+}
+
+/// A name's title and the Notion id after it, if it has one, as in
+/// "Mara Quell 1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d"
+pub fn split_name(name: &str) -> (String, Option<String>) {
+    let name = name.rsplit('/').next().unwrap_or(name);
+    match name.rsplit_once(' ') {
+        Some((title, id))
+            if id.len() == NOTION_ID_LEN && id.chars().all(|c| c.is_ascii_hexdigit()) =>
+        {
+            (title.trim().to_string(), Some(id.to_lowercase()))
+        }
+        _ => (name.trim().to_string(), None),
+    }
+}
+
+/// A page's text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotionPage {
+    pub title: String,
+    /// Database row properties listed under the title
+    pub properties: Vec<(String, String)>,
+    pub body: String,
+    pub html: bool,
+}
+
+/// A Markdown page: the `# Title` line, then for a database row its
+/// properties, named as in `property_names`, then the body
+pub fn read_markdown_page(text: &str, title: &str, property_names: &[String]) -> NotionPage {
+    let text = text.trim_start_matches('\u{feff}');
+    let (title, rest) = match text.strip_prefix("# ") {
+        Some(rest) => {
+            let (heading, rest) = rest.split_once('\n').unwrap_or((rest, ""));
+            (heading.trim().to_string(), rest)
+        }
+        None => (title.to_string(), text),
+    };
+
+    let mut properties = Vec::new();
+    let mut body = rest.trim_start_matches(['\r', '\n']);
+    if !property_names.is_empty() {
+        while let Some((line, next)) = body.split_once('\n').or(Some((body, ""))) {
+            let property = line.split_once(':').filter(|(name, _)| {
+                property_names
+                    .iter()
+                    .any(|p| p.trim().eq_ignore_ascii_case(name.trim()))
+            });
+            match property {
+                Some((name, value)) if !line.is_empty() => {
+                    properties.push((name.trim().to_string(), value.trim().to_string()));
+                    body = next;
+                }
+                _ => break,
+            }
+        }
+    }
+    NotionPage {
+        title,
+        properties,
+        body: body.trim_start_matches(['\r', '\n']).to_string(),
+        html: false,
+    }
+}
+
+/// An HTML page: its title and the markup of its page body
+pub fn read_html_page(text: &str, title: &str) -> NotionPage {
+    let title = HTML_TITLE
+        .captures(text)
+        .map(|c| unescape_html(c[1].trim()))
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| title.to_string());
+    let marker = "<div class=\"page-body\">";
+    let body = match text.find(marker) {
+        Some(start) => {
+            let inner = &text[start + marker.len()..];
+            let end = inner.find("</article>").unwrap_or(inner.len());
+            let inner = &inner[..end];
+            inner.rfind("</div>").map_or(inner, |close| &inner[..close])
+        }
+        None => text,
+    };
+    NotionPage {
+        title,
+        properties: Vec::new(),
+        body: body.trim().to_string(),
+        html: true,
+    }
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// A relative link or image in a page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotionReference {
+    /// Bytes of the body the URL takes up
+    pub range: Range<usize>,
+    /// Path relative to the page's folder, decoded
+    pub target: String,
+}
+
+/// The page's links and images that point at other files in the export
+pub fn find_references(body: &str, html: bool) -> Vec<NotionReference> {
+    let pattern = if html {
+        &HTML_REFERENCE
+    } else {
+        &MARKDOWN_REFERENCE
+    };
+    pattern
+        .captures_iter(body)
+        .filter_map(|captures| {
+            let url = captures.get(1)?;
+            let text = url.as_str();
+            if text.is_empty()
+                || text.contains("://")
+                || text.starts_with('#')
+                || text.starts_with("mailto:")
+            {
+                return None;
+            }
+            let path = text.split_once('#').map_or(text, |(path, _)| path);
+            let path = if html {
+                unescape_html(path)
+            } else {
+                path.to_string()
+            };
+            Some(NotionReference {
+                range: url.range(),
+                target: percent_decode(&path),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_notion_pages() {
+        assert_eq!(
+            split_name("Cast 0f1e2d3c4b5a69788796a5b4c3d2e1f0"),
+            (
+                "Cast".to_string(),
+                Some("0f1e2d3c4b5a69788796a5b4c3d2e1f0".to_string())
+            )
+        );
+        assert_eq!(split_name("Notes 2024"), ("Notes 2024".to_string(), None));
+        assert_eq!(
+            node_key("Story abc/Cast 0f1e_all.csv"),
+            "Story abc/Cast 0f1e"
+        );
+
+        let row = read_markdown_page(
+            "# Mara Quell\n\nRole: Pilot\nType: Character\n\nKnows the harbor. See [Ship](Mara%20Quell/The%20Gull.md).",
+            "Mara",
+            &["Name".to_string(), "Role".to_string(), "Type".to_string()],
+        );
+        assert_eq!(row.title, "Mara Quell");
+        assert_eq!(row.properties.len(), 2);
+        assert!(row.body.starts_with("Knows the harbor."));
+        let references = find_references(&row.body, false);
+        assert_eq!(references[0].target, "Mara Quell/The Gull.md");
+        assert_eq!(
+            &row.body[references[0].range.clone()],
+            "Mara%20Quell/The%20Gull.md"
+        );
+
+        let page = read_html_page(
+            "<html><head><title>Tom &amp; Jerry</title></head><body><article><header></header><div class=\"page-body\"><p>Hi <a href=\"Sub%20abc.html\">there</a> <img src=\"https://example.com/x.png\"/></p></div></article></body></html>",
+            "Fallback",
+        );
+        assert_eq!(page.title, "Tom & Jerry");
+        assert!(page.body.starts_with("<p>Hi") && page.body.ends_with("</p>"));
+        let references = find_references(&page.body, true);
+        assert_eq!(references.len(), 1);
+        assert_eq!(references[0].target, "Sub abc.html");
+    }
+}
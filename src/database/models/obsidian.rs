@@ -0,0 +1,393 @@
+//! Obsidian Vault Models
+//!
+//! Reading an Obsidian note: its YAML front matter, `#tags`, `[[wiki-links]]`
+//! with headings and aliases, `![[embeds]]`, and Markdown links to other
+//! files in the vault. Anything inside code blocks or inline code is left
+//! alone. How links resolve and are rewritten is up to the importer.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::ops::Range;
+
+use super::note_import::percent_decode;
+
+/// Fenced block languages that only mean something to an Obsidian plugin
+pub const PLUGIN_BLOCKS: &[&str] = &["dataview", "dataviewjs", "tasks", "query", "excalidraw"];
+
+static WIKI_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(!?)\[\[([^\[\]\n]+)\]\]").expect("static regex"));
+static MARKDOWN_LINK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(!?)\[([^\[\]\n]*)\]\((?:<([^>\n]+)>|([^)\s]+))\)").expect("static regex")
+});
+static TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:^|[\s(\[,])#([\p{L}\p{N}_/\-]+)").expect("static regex"));
+static INLINE_CODE: Lazy<Regex> = Lazy::new(|| Regex::new(r"`[^`\n]+`").expect("static regex"));
+
+/// A note's front matter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrontMatter {
+    pub tags: Vec<String>,
+    pub aliases: Vec<String>,
+    /// Every other key
+    pub properties: Map<String, Value>,
+    /// Lines that couldn't be read, such as nested maps
+    pub unreadable: Vec<String>,
+}
+
+/// The front matter, if the note has any, and the rest of the note
+pub fn split_front_matter(text: &str) -> (Option<&str>, &str) {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (None, text);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+/// Read the flat YAML Obsidian writes: scalars, `[a, b]` lists and `- item`
+/// lists
+pub fn parse_front_matter(yaml: &str) -> FrontMatter {
+    let mut front_matter = FrontMatter::default();
+    let mut list: Option<String> = None;
+    for line in yaml.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let (Some(key), Some(item)) = (&list, trimmed.strip_prefix('-')) {
+            if let Some(Value::Array(items)) = front_matter.properties.get_mut(key) {
+                items.push(scalar(item));
+            }
+            continue;
+        }
+        list = None;
+        let top_level = !line.starts_with(char::is_whitespace);
+        match trimmed.split_once(':').filter(|_| top_level) {
+            Some((key, value)) if !key.trim().is_empty() => {
+                let (key, value) = (key.trim().to_string(), value.trim());
+                let value = if value.is_empty() {
+                    list = Some(key.clone());
+                    Value::Array(Vec::new())
+                } else if let Some(items) =
+                    value.strip_prefix('[').and_then(|v| v.strip_suffix(']'))
+                {
+                    Value::Array(
+                        items
+                            .split(',')
+                            .filter(|item| !item.trim().is_empty())
+                            .map(scalar)
+                            .collect(),
+                    )
+                } else {
+                    scalar(value)
+                };
+                front_matter.properties.insert(key, value);
+            }
+            _ => front_matter.unreadable.push(line.to_string()),
+        }
+    }
+
+    for key in ["tags", "tag", "aliases", "alias"] {
+        let Some(value) = front_matter.properties.remove(key) else {
+            continue;
+        };
+        let values: Vec<String> = match value {
+            Value::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .collect(),
+            Value::String(s) if key.starts_with("tag") => s
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .map(str::to_string)
+                .collect(),
+            Value::String(s) => vec![s],
+            other => vec![other.to_string()],
+        };
+        let values = values.into_iter().filter(|v| !v.trim().is_empty());
+        if key.starts_with("tag") {
+            front_matter
+                .tags
+                .extend(values.map(|t| t.trim().trim_start_matches('#').to_string()));
+        } else {
+            front_matter.aliases.extend(values);
+        }
+    }
+    front_matter
+}
+
+fn scalar(value: &str) -> Value {
+    let value = value.trim();
+    let unquoted = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')));
+    if let Some(unquoted) = unquoted {
+        return Value::String(unquoted.to_string());
+    }
+    match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "null" | "~" => Value::Null,
+        _ => value
+            .parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| value.parse::<f64>().map(Value::from))
+            .unwrap_or_else(|_| Value::String(value.to_string())),
+    }
+}
+
+/// A link or embed in a note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteLink {
+    /// Bytes of the note the link takes up
+    pub range: Range<usize>,
+    /// Note name or path, or file path, as written
+    pub target: String,
+    pub heading: Option<String>,
+    /// Text shown for the link
+    pub alias: Option<String>,
+    pub embed: bool,
+    /// Written as a Markdown link rather than a wiki-link
+    pub markdown: bool,
+}
+
+impl NoteLink {
+    /// What the reader sees for the link
+    pub fn text(&self) -> &str {
+        self.alias
+            .as_deref()
+            .filter(|a| !a.is_empty())
+            .unwrap_or(&self.target)
+    }
+}
+
+/// Links and embeds to other notes and files in the vault, in order.
+/// Links to web pages, and to headings in the same note, aren't included.
+pub fn find_links(body: &str) -> Vec<NoteLink> {
+    let code = code_ranges(body);
+    let outside_code = |range: &Range<usize>| {
+        !code
+            .iter()
+            .any(|c| c.start < range.end && range.start < c.end)
+    };
+    let mut links = Vec::new();
+
+    for captures in WIKI_LINK.captures_iter(body) {
+        let whole = captures.get(0).expect("whole match");
+        let inner = &captures[2];
+        let (target, alias) = match inner.split_once('|') {
+            // A link in a table escapes its bar
+            Some((target, alias)) => (target.trim_end_matches('\\'), Some(alias.trim())),
+            None => (inner, None),
+        };
+        let (target, heading) = match target.split_once('#') {
+            Some((target, heading)) => (target, Some(heading.trim().to_string())),
+            None => (target, None),
+        };
+        if target.trim().is_empty() || !outside_code(&whole.range()) {
+            continue;
+        }
+        links.push(NoteLink {
+            range: whole.range(),
+            target: target.trim().to_string(),
+            heading,
+            alias: alias.map(str::to_string),
+            embed: !captures[1].is_empty(),
+            markdown: false,
+        });
+    }
+
+    for captures in MARKDOWN_LINK.captures_iter(body) {
+        let whole = captures.get(0).expect("whole match");
+        let url = captures
+            .get(3)
+            .or_else(|| captures.get(4))
+            .map_or("", |m| m.as_str());
+        if url.contains("://") || url.starts_with('#') || url.starts_with("mailto:") {
+            continue;
+        }
+        let (path, heading) = match url.split_once('#') {
+            Some((path, heading)) => (path, Some(percent_decode(heading))),
+            None => (url, None),
+        };
+        if path.is_empty() || !outside_code(&whole.range()) {
+            continue;
+        }
+        links.push(NoteLink {
+            range: whole.range(),
+            target: percent_decode(path),
+            heading,
+            alias: Some(captures[2].to_string()),
+            embed: !captures[1].is_empty(),
+            markdown: true,
+        });
+    }
+
+    links.sort_by_key(|link| link.range.start);
+    links
+}
+
+/// The note's inline `#tags`, without the `#`, each once
+pub fn find_tags(body: &str) -> Vec<String> {
+    let code = code_ranges(body);
+    let mut tags: Vec<String> = Vec::new();
+    for captures in TAG.captures_iter(body) {
+        let tag = captures.get(1).expect("tag group");
+        if code.iter().any(|c| c.contains(&tag.start()))
+            || tag.as_str().chars().all(|c| c.is_ascii_digit())
+        {
+            continue;
+        }
+        let tag = tag.as_str().trim_end_matches('/');
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Languages of fenced blocks only a plugin can show
+pub fn plugin_blocks(body: &str) -> Vec<String> {
+    body.lines()
+        .filter_map(|line| {
+            let info = line
+                .trim_start()
+                .strip_prefix("```")
+                .or_else(|| line.trim_start().strip_prefix("~~~"))?;
+            let language = info.trim().to_lowercase();
+            PLUGIN_BLOCKS
+                .contains(&language.as_str())
+                .then_some(language)
+        })
+        .collect()
+}
+
+/// The note with each link replaced; links given None are left as written
+pub fn rewrite(
+    body: &str,
+    links: &[NoteLink],
+    mut replacement: impl FnMut(&NoteLink) -> Option<String>,
+) -> String {
+    let mut output = String::with_capacity(body.len());
+    let mut last = 0;
+    for link in links {
+        if link.range.start < last {
+            continue;
+        }
+        if let Some(text) = replacement(link) {
+            output.push_str(&body[last..link.range.start]);
+            output.push_str(&text);
+            last = link.range.end;
+        }
+    }
+    output.push_str(&body[last..]);
+    output
+}
+
+/// Fenced code blocks and inline code spans
+fn code_ranges(body: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut fence: Option<(usize, &str)> = None;
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        match fence {
+            Some((start, marker)) => {
+                if trimmed.starts_with(marker) {
+                    ranges.push(start..offset + line.len());
+                    fence = None;
+                }
+            }
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                fence = Some((offset, &trimmed[..3]));
+            }
+            None => ranges.extend(
+                INLINE_CODE
+                    .find_iter(line)
+                    .map(|m| offset + m.start()..offset + m.end()),
+            ),
+        }
+        offset += line.len();
+    }
+    if let Some((start, _)) = fence {
+        ranges.push(start..body.len());
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_front_matter() {
+        let note = "---\ntags: [lore, \"people/mira\"]\naliases:\n  - The Mill\n  - Old Mill\nrating: 4\nsource: 'Field notes'\nmap:\n  x: 1\n---\n# The Old Mill\n";
+        let (yaml, body) = split_front_matter(note);
+        assert_eq!(body, "# The Old Mill\n");
+        let front_matter = parse_front_matter(yaml.unwrap());
+        assert_eq!(front_matter.tags, ["lore", "people/mira"]);
+        assert_eq!(front_matter.aliases, ["The Mill", "Old Mill"]);
+        assert_eq!(front_matter.properties["rating"], Value::from(4));
+        assert_eq!(
+            front_matter.properties["source"],
+            Value::from("Field notes")
+        );
+        assert_eq!(front_matter.unreadable, ["  x: 1"]);
+        assert_eq!(split_front_matter("No front matter").0, None);
+    }
+
+    #[test]
+    fn test_links_and_tags() {
+        let body = "Mira went to [[The Old Mill#Cellar|the mill]] #lore #people/mira.\n\
+                    ![[map.png]] and [notes](Research/Field%20Notes.md) or [site](https://example.com).\n\
+                    Issue #42 `[[Not a link]] #nope`\n\
+                    ```\n[[Also not]] #code\n```\n\
+                    | [[Table\\|cell]] |\n";
+        let links = find_links(body);
+        let summary: Vec<(&str, Option<&str>, &str, bool, bool)> = links
+            .iter()
+            .map(|l| {
+                (
+                    l.target.as_str(),
+                    l.heading.as_deref(),
+                    l.text(),
+                    l.embed,
+                    l.markdown,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("The Old Mill", Some("Cellar"), "the mill", false, false),
+                ("map.png", None, "map.png", true, false),
+                ("Research/Field Notes.md", None, "notes", false, true),
+                ("Table", None, "cell", false, false),
+            ]
+        );
+        assert_eq!(find_tags(body), ["lore", "people/mira"]);
+
+        let rewritten = rewrite(body, &links, |link| {
+            (!link.embed).then(|| format!("<{}>", link.text()))
+        });
+        assert!(rewritten.starts_with("Mira went to <the mill> #lore"));
+        assert!(rewritten.contains("![[map.png]] and <notes> or [site]"));
+        assert_eq!(
+            plugin_blocks("```dataview\nLIST\n```\n```rust\n```"),
+            ["dataview"]
+        );
+    }
+}
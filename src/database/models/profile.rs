@@ -0,0 +1,165 @@
+//! User Profile Data Models
+//!
+//! Optional profiles that let several people share one install, each with
+//! their own project visibility, settings and AI budget.
+
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::settings::Settings;
+
+/// What a profile is allowed to see and do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ProfileRole {
+    /// Sees every project and can edit freely
+    Writer,
+    /// Sees only projects shared with the profile
+    Editor,
+    /// Sees only shared projects, with AI switched off by default
+    KidSafe,
+}
+
+impl ProfileRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProfileRole::Writer => "writer",
+            ProfileRole::Editor => "editor",
+            ProfileRole::KidSafe => "kid_safe",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "writer" => Some(ProfileRole::Writer),
+            "editor" => Some(ProfileRole::Editor),
+            "kid_safe" => Some(ProfileRole::KidSafe),
+            _ => None,
+        }
+    }
+
+    /// Whether the role sees all projects without explicit grants
+    pub fn sees_all_projects(&self) -> bool {
+        matches!(self, ProfileRole::Writer)
+    }
+
+    /// Monthly AI budget a new profile of this role starts with
+    pub fn default_ai_budget_cents(&self) -> Option<i64> {
+        match self {
+            ProfileRole::Writer => None,
+            ProfileRole::Editor => Some(500),
+            ProfileRole::KidSafe => Some(0),
+        }
+    }
+
+    /// Settings a new profile of this role starts with
+    pub fn default_settings(&self) -> Settings {
+        let mut settings = Settings::default();
+        if matches!(self, ProfileRole::KidSafe) {
+            settings.enable_ai_suggestions = Some(false);
+            settings.enable_ai_analysis = Some(false);
+        }
+        settings
+    }
+}
+
+/// A user profile within one install
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserProfile {
+    pub id: Uuid,
+    pub name: String,
+    pub role: ProfileRole,
+    pub settings: Settings,
+    /// Monthly AI spend limit in cents; `None` means unlimited
+    pub ai_budget_cents: Option<i64>,
+    pub ai_spent_cents: i64,
+    /// Start of the month `ai_spent_cents` counts towards
+    pub ai_period_start: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserProfile {
+    pub fn new(name: String, role: ProfileRole) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            role,
+            settings: role.default_settings(),
+            ai_budget_cents: role.default_ai_budget_cents(),
+            ai_spent_cents: 0,
+            ai_period_start: month_start(now),
+            created_at: now,
+        }
+    }
+
+    /// Remaining AI budget this month, or `None` if unlimited
+    pub fn ai_budget_remaining(&self) -> Option<i64> {
+        self.ai_budget_cents
+            .map(|budget| (budget - self.ai_spent_cents).max(0))
+    }
+}
+
+/// First instant of the month containing `at`
+pub fn month_start(at: DateTime<Utc>) -> DateTime<Utc> {
+    at.date_naive()
+        .with_day(1)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+        .unwrap_or(at)
+}
+
+/// Database schema for profiles, project grants and the app lock
+pub const CREATE_PROFILE_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS user_profiles (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    role TEXT NOT NULL,
+    settings TEXT NOT NULL,
+    ai_budget_cents INTEGER,
+    ai_spent_cents INTEGER NOT NULL DEFAULT 0,
+    ai_period_start TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS profile_projects (
+    profile_id TEXT NOT NULL,
+    project_id TEXT NOT NULL,
+    PRIMARY KEY (profile_id, project_id),
+    FOREIGN KEY (profile_id) REFERENCES user_profiles(id) ON DELETE CASCADE,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS app_lock (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    salt TEXT NOT NULL,
+    passphrase_hash TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+"#;
+
+/// Insert profile SQL
+pub const INSERT_PROFILE_SQL: &str = r#"
+INSERT INTO user_profiles (
+    id, name, role, settings, ai_budget_cents, ai_spent_cents, ai_period_start, created_at
+) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+"#;
+
+/// Select profile columns SQL
+pub const SELECT_PROFILE_SQL: &str = r#"
+SELECT id, name, role, settings, ai_budget_cents, ai_spent_cents, ai_period_start, created_at
+FROM user_profiles
+"#;
+
+/// Update profile AI usage SQL
+pub const UPDATE_PROFILE_AI_USAGE_SQL: &str = r#"
+UPDATE user_profiles SET ai_spent_cents = ?2, ai_period_start = ?3 WHERE id = ?1
+"#;
+
+/// Visible projects for a profile that needs explicit grants SQL
+pub const GET_GRANTED_PROJECTS_SQL: &str = r#"
+SELECT p.id FROM projects p
+JOIN profile_projects pp ON pp.project_id = p.id
+WHERE pp.profile_id = ?1 AND p.is_archived = 0
+ORDER BY p.name ASC
+"#;
@@ -0,0 +1,106 @@
+//! Readability Data Models
+//!
+//! Readability scores and style measures of a document's text: Flesch
+//! reading ease, Flesch-Kincaid grade and Gunning Fog, how long its
+//! sentences run, and how much of it is passive, adverbs or phrases said
+//! more than once. Counts are kept per paragraph and cached per document
+//! version, so a later version only recounts the paragraphs that changed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// What one paragraph adds to a document's measures
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParagraphCounts {
+    /// Hash of the paragraph's text, to find it again in another version
+    pub hash: String,
+    pub words: usize,
+    pub syllables: usize,
+    /// Words of three or more syllables, not counting the -es, -ed and
+    /// -ing endings
+    pub complex_words: usize,
+    pub adverbs: usize,
+    pub passive_sentences: usize,
+    /// Words in each sentence
+    pub sentence_lengths: Vec<usize>,
+    /// Phrases of three and four words, by how often they occur
+    pub phrases: BTreeMap<String, usize>,
+}
+
+/// Sentences of a length range; `max_words` is None for the longest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SentenceLengthBucket {
+    pub min_words: usize,
+    pub max_words: Option<usize>,
+    pub sentences: usize,
+}
+
+/// A phrase said more than once
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepeatedPhrase {
+    pub phrase: String,
+    pub count: usize,
+    /// Paragraphs it occurs in, from 0
+    pub paragraphs: Vec<usize>,
+}
+
+/// Readability and style of a document's text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadabilityReport {
+    pub document_id: Uuid,
+    /// Saved version analysed; None for text changed since the last one
+    pub version: Option<u32>,
+    pub word_count: usize,
+    pub sentence_count: usize,
+    pub paragraph_count: usize,
+    pub flesch_reading_ease: f32,
+    pub flesch_kincaid_grade: f32,
+    pub gunning_fog: f32,
+    pub average_sentence_length: f32,
+    pub longest_sentence: usize,
+    pub sentence_lengths: Vec<SentenceLengthBucket>,
+    /// Share of sentences in the passive voice
+    pub passive_ratio: f32,
+    /// Adverbs per hundred words
+    pub adverb_density: f32,
+    pub repeated_phrases: Vec<RepeatedPhrase>,
+    /// Paragraphs counted for this report rather than taken from the cache
+    pub paragraphs_counted: usize,
+}
+
+/// Database schema for paragraph counts cached per document version
+pub const CREATE_READABILITY_CACHE_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS readability_cache (
+    version_id TEXT PRIMARY KEY,
+    document_id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    paragraphs TEXT NOT NULL,
+    analyzed_at TEXT NOT NULL,
+    FOREIGN KEY (version_id) REFERENCES document_versions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_readability_cache_document ON readability_cache(document_id, version);
+"#;
+
+/// Cached paragraph counts of a version
+pub const GET_READABILITY_CACHE_SQL: &str = r#"
+SELECT paragraphs FROM readability_cache WHERE version_id = ?1
+"#;
+
+/// Cached paragraph counts of the version of a document nearest another
+pub const GET_NEAREST_READABILITY_CACHE_SQL: &str = r#"
+SELECT paragraphs FROM readability_cache
+WHERE document_id = ?1
+ORDER BY ABS(version - ?2), version DESC
+LIMIT 1
+"#;
+
+/// Upsert cached paragraph counts SQL
+pub const UPSERT_READABILITY_CACHE_SQL: &str = r#"
+INSERT INTO readability_cache (version_id, document_id, version, paragraphs, analyzed_at)
+VALUES (?1, ?2, ?3, ?4, ?5)
+ON CONFLICT(version_id) DO UPDATE SET
+    paragraphs = excluded.paragraphs,
+    analyzed_at = excluded.analyzed_at
+"#;
@@ -0,0 +1,94 @@
+//! Related Notes Data Models
+//!
+//! Research notes and codex entries related to the text being written,
+//! for the live sidebar. Candidates are ranked by embedding similarity,
+//! nudged towards recently edited notes and away from other projects.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Where a related note comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelatedKind {
+    ResearchNote,
+    CodexEntry,
+}
+
+/// A request for notes related to a paragraph or selection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedNotesRequest {
+    pub project_id: Uuid,
+    pub text: String,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    /// Also suggest notes from other projects, weighted down
+    #[serde(default)]
+    pub include_other_projects: bool,
+    /// Kinds of note to suggest; all when empty
+    #[serde(default)]
+    pub kinds: Vec<RelatedKind>,
+}
+
+fn default_limit() -> usize {
+    8
+}
+
+impl RelatedNotesRequest {
+    pub fn new(project_id: Uuid, text: impl Into<String>) -> Self {
+        Self {
+            project_id,
+            text: text.into(),
+            limit: default_limit(),
+            include_other_projects: false,
+            kinds: Vec::new(),
+        }
+    }
+}
+
+/// How similarity, recency and project combine into a score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedWeights {
+    /// Notes less similar than this are never suggested, however recent
+    pub min_similarity: f32,
+    /// Bonus for a note edited just now, halving every `recency_half_life_days`
+    pub recency: f32,
+    pub recency_half_life_days: f32,
+    /// Multiplier for notes from other projects
+    pub other_project: f32,
+}
+
+impl Default for RelatedWeights {
+    fn default() -> Self {
+        Self {
+            min_similarity: 0.1,
+            recency: 0.15,
+            recency_half_life_days: 30.0,
+            other_project: 0.5,
+        }
+    }
+}
+
+/// A suggested note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedItem {
+    pub id: Uuid,
+    pub kind: RelatedKind,
+    pub project_id: Uuid,
+    pub title: String,
+    pub snippet: String,
+    pub similarity: f32,
+    /// Similarity with the recency and project weights applied
+    pub score: f32,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Suggestions for one request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedNotes {
+    pub items: Vec<RelatedItem>,
+    /// Whether the answer came from the result cache
+    pub cached: bool,
+    pub elapsed_ms: u64,
+}
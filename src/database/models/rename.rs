@@ -0,0 +1,180 @@
+//! Rename Data Models
+//!
+//! Project-wide renames of a name or term: the request, the previewed
+//! mentions and the applied operation, which keeps the original text of
+//! every changed document so it can be rolled back.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What to rename and where
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameRequest {
+    pub project_id: Uuid,
+    pub from: String,
+    pub to: String,
+    /// Codex entry whose title is renamed along with the mentions
+    #[serde(default)]
+    pub codex_entry_id: Option<Uuid>,
+    /// Rewrite mentions in documents, not just the codex entry
+    #[serde(default = "default_true")]
+    pub rewrite_documents: bool,
+    /// Leave mentions inside quotation marks alone (nicknames, characters
+    /// who still use the old name)
+    #[serde(default)]
+    pub skip_dialogue: bool,
+    /// Occurrence ids from the preview the user unticked
+    #[serde(default)]
+    pub skip_occurrences: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl RenameRequest {
+    pub fn new(project_id: Uuid, from: impl Into<String>, to: impl Into<String>) -> Self {
+        Self {
+            project_id,
+            from: from.into(),
+            to: to.into(),
+            codex_entry_id: None,
+            rewrite_documents: true,
+            skip_dialogue: false,
+            skip_occurrences: Vec::new(),
+        }
+    }
+}
+
+/// One mention of the old name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenameOccurrence {
+    /// `<document id>:<byte offset>`, stable until the document changes
+    pub id: String,
+    pub document_id: Uuid,
+    pub document_title: String,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    /// Text as written, including a bare possessive apostrophe
+    pub original: String,
+    pub replacement: String,
+    pub in_dialogue: bool,
+    /// Whether applying the request rewrites this mention; lowercase
+    /// mentions of a capitalised name ("rose" for "Rose") are shown but kept
+    pub included: bool,
+    /// The surrounding line, for display
+    pub context: String,
+}
+
+/// Everything a rename would change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenamePreview {
+    pub request: RenameRequest,
+    /// Current title of the codex entry, when one is renamed
+    pub codex_title: Option<String>,
+    pub occurrences: Vec<RenameOccurrence>,
+    pub documents_affected: usize,
+}
+
+/// A document's text before an applied rename
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenamedDocument {
+    pub document_id: Uuid,
+    pub original_content: String,
+    /// Checksum right after the rename; rollback skips documents edited since
+    pub renamed_checksum: String,
+    pub replacements: usize,
+}
+
+/// An applied rename
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameOperation {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub from: String,
+    pub to: String,
+    pub codex_entry_id: Option<Uuid>,
+    pub previous_codex_title: Option<String>,
+    pub documents: Vec<RenamedDocument>,
+    pub applied_at: DateTime<Utc>,
+    pub rolled_back_at: Option<DateTime<Utc>>,
+}
+
+impl RenameOperation {
+    pub fn replacements(&self) -> usize {
+        self.documents.iter().map(|d| d.replacements).sum()
+    }
+}
+
+/// Result of rolling back a rename
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameRollback {
+    pub operation_id: Uuid,
+    pub restored: Vec<Uuid>,
+    /// Documents edited after the rename, left as they are
+    pub conflicts: Vec<Uuid>,
+    pub codex_restored: bool,
+}
+
+/// Database schema for rename history
+pub const CREATE_RENAME_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS rename_operations (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    from_text TEXT NOT NULL,
+    to_text TEXT NOT NULL,
+    codex_entry_id TEXT,
+    previous_codex_title TEXT,
+    applied_at TEXT NOT NULL,
+    rolled_back_at TEXT,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS rename_operation_documents (
+    operation_id TEXT NOT NULL,
+    document_id TEXT NOT NULL,
+    original_content TEXT NOT NULL,
+    renamed_checksum TEXT NOT NULL,
+    replacements INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (operation_id, document_id),
+    FOREIGN KEY (operation_id) REFERENCES rename_operations(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_rename_operations_project ON rename_operations(project_id, applied_at);
+"#;
+
+/// Insert rename operation SQL
+pub const INSERT_RENAME_OPERATION_SQL: &str = r#"
+INSERT INTO rename_operations (id, project_id, from_text, to_text, codex_entry_id, previous_codex_title, applied_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+"#;
+
+/// Insert renamed document SQL
+pub const INSERT_RENAME_DOCUMENT_SQL: &str = r#"
+INSERT INTO rename_operation_documents (operation_id, document_id, original_content, renamed_checksum, replacements)
+VALUES (?1, ?2, ?3, ?4, ?5)
+"#;
+
+/// Get rename operation SQL
+pub const GET_RENAME_OPERATION_SQL: &str = r#"
+SELECT id, project_id, from_text, to_text, codex_entry_id, previous_codex_title, applied_at, rolled_back_at
+FROM rename_operations
+WHERE id = ?1
+"#;
+
+/// Rename operations of a project, newest first
+pub const GET_RENAME_OPERATIONS_SQL: &str = r#"
+SELECT id, project_id, from_text, to_text, codex_entry_id, previous_codex_title, applied_at, rolled_back_at
+FROM rename_operations
+WHERE project_id = ?1
+ORDER BY applied_at DESC
+"#;
+
+/// Documents changed by a rename operation
+pub const GET_RENAME_DOCUMENTS_SQL: &str = r#"
+SELECT document_id, original_content, renamed_checksum, replacements
+FROM rename_operation_documents
+WHERE operation_id = ?1
+"#;
@@ -0,0 +1,175 @@
+//! Serial Release Data Models
+//!
+//! A web serial's release plan: which chapter goes out when and on which
+//! platform, where each release stands, and the chapter files staged for
+//! upload ahead of their date.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Where a release stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReleaseStatus {
+    /// Scheduled, nothing prepared yet
+    Planned,
+    /// The chapter's files are in the staging folder, ready to upload
+    Staged,
+    Published,
+    Skipped,
+}
+
+impl ReleaseStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReleaseStatus::Planned => "planned",
+            ReleaseStatus::Staged => "staged",
+            ReleaseStatus::Published => "published",
+            ReleaseStatus::Skipped => "skipped",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "staged" => ReleaseStatus::Staged,
+            "published" => ReleaseStatus::Published,
+            "skipped" => ReleaseStatus::Skipped,
+            _ => ReleaseStatus::Planned,
+        }
+    }
+
+    /// Still to go out
+    pub fn is_pending(&self) -> bool {
+        matches!(self, ReleaseStatus::Planned | ReleaseStatus::Staged)
+    }
+}
+
+/// One chapter scheduled for release on a platform
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerialRelease {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    /// The document released as the chapter
+    pub document_id: Uuid,
+    pub chapter_number: u32,
+    /// Title the chapter is published under; the document's when empty
+    #[serde(default)]
+    pub title: String,
+    pub publish_at: DateTime<Utc>,
+    /// Where it goes out, e.g. "Royal Road" or "Patreon"
+    pub platform: String,
+    pub status: ReleaseStatus,
+    /// The staged chapter file
+    #[serde(default)]
+    pub staged_path: Option<String>,
+    /// Link to the published chapter
+    #[serde(default)]
+    pub published_url: Option<String>,
+    #[serde(default)]
+    pub published_at: Option<DateTime<Utc>>,
+    /// The release-day reminder has been shown
+    #[serde(default)]
+    pub reminded: bool,
+    #[serde(default)]
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SerialRelease {
+    pub fn new(
+        project_id: Uuid,
+        document_id: Uuid,
+        chapter_number: u32,
+        publish_at: DateTime<Utc>,
+        platform: impl Into<String>,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            document_id,
+            chapter_number,
+            title: String::new(),
+            publish_at,
+            platform: platform.into(),
+            status: ReleaseStatus::Planned,
+            staged_path: None,
+            published_url: None,
+            published_at: None,
+            reminded: false,
+            notes: String::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// A project's releases with what's coming up next
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleasePlan {
+    pub project_id: Uuid,
+    /// By publish date
+    pub releases: Vec<SerialRelease>,
+    /// The next pending release per platform
+    pub next: Vec<SerialRelease>,
+    /// Pending releases whose date has passed
+    pub late: usize,
+    /// Chapter numbers planned more than once on the same platform
+    pub duplicate_chapters: Vec<u32>,
+}
+
+/// Database schema for serial releases
+pub const CREATE_SERIAL_RELEASES_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS serial_releases (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    document_id TEXT NOT NULL,
+    chapter_number INTEGER NOT NULL,
+    title TEXT NOT NULL DEFAULT '',
+    publish_at TEXT NOT NULL,
+    platform TEXT NOT NULL,
+    status TEXT NOT NULL,
+    staged_path TEXT,
+    published_url TEXT,
+    published_at TEXT,
+    reminded INTEGER NOT NULL DEFAULT 0,
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+    FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_serial_releases_project ON serial_releases(project_id, publish_at);
+"#;
+
+/// Insert or replace serial release SQL
+pub const UPSERT_SERIAL_RELEASE_SQL: &str = r#"
+INSERT INTO serial_releases (id, project_id, document_id, chapter_number, title, publish_at, platform,
+                             status, staged_path, published_url, published_at, reminded, notes,
+                             created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+ON CONFLICT(id) DO UPDATE SET
+    document_id = excluded.document_id,
+    chapter_number = excluded.chapter_number,
+    title = excluded.title,
+    publish_at = excluded.publish_at,
+    platform = excluded.platform,
+    status = excluded.status,
+    staged_path = excluded.staged_path,
+    published_url = excluded.published_url,
+    published_at = excluded.published_at,
+    reminded = excluded.reminded,
+    notes = excluded.notes,
+    updated_at = excluded.updated_at
+"#;
+
+/// Select serial releases SQL; filter with a WHERE clause appended by the
+/// caller
+pub const SELECT_SERIAL_RELEASES_SQL: &str = r#"
+SELECT id, project_id, document_id, chapter_number, title, publish_at, platform, status,
+       staged_path, published_url, published_at, reminded, notes, created_at, updated_at
+FROM serial_releases
+"#;
@@ -0,0 +1,207 @@
+//! Writing Statistics Data Models
+//!
+//! Writing sessions, daily word count snapshots, per-chapter metrics and AI
+//! usage, and the CSV/JSON exports built from them for analysis outside
+//! the app.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What to export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsDataset {
+    Sessions,
+    WordCounts,
+    Chapters,
+    AiUsage,
+}
+
+impl StatsDataset {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatsDataset::Sessions => "sessions",
+            StatsDataset::WordCounts => "word_counts",
+            StatsDataset::Chapters => "chapters",
+            StatsDataset::AiUsage => "ai_usage",
+        }
+    }
+}
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsFormat {
+    Csv,
+    Json,
+}
+
+impl StatsFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            StatsFormat::Csv => "csv",
+            StatsFormat::Json => "json",
+        }
+    }
+}
+
+/// A request to export statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsExportRequest {
+    /// Required for everything but AI usage, which covers all projects
+    /// when unset
+    #[serde(default)]
+    pub project_id: Option<Uuid>,
+    pub dataset: StatsDataset,
+    pub format: StatsFormat,
+    /// Limit sessions, word counts and AI usage to this period
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// An export, ready to save
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsExport {
+    pub dataset: StatsDataset,
+    pub format: StatsFormat,
+    pub file_name: String,
+    pub rows: usize,
+    pub content: String,
+}
+
+/// A stretch of writing in a project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WritingSession {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Project word count when the session started and ended
+    pub words_start: i64,
+    pub words_end: Option<i64>,
+    /// Documents changed during the session
+    pub documents_edited: i64,
+}
+
+impl WritingSession {
+    pub fn duration_minutes(&self) -> Option<f64> {
+        self.ended_at
+            .map(|end| (end - self.started_at).num_seconds() as f64 / 60.0)
+    }
+
+    pub fn net_words(&self) -> Option<i64> {
+        self.words_end.map(|end| end - self.words_start)
+    }
+}
+
+/// Project word count at the end of a day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WordCountPoint {
+    pub date: NaiveDate,
+    pub words: i64,
+    /// Change since the previous snapshot
+    pub change: i64,
+}
+
+/// Measurements of one chapter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterMetrics {
+    pub document_id: Uuid,
+    pub title: String,
+    /// Place in the binder, from 1
+    pub position: usize,
+    pub words: i64,
+    pub paragraphs: usize,
+    pub sentences: usize,
+    pub average_sentence_words: f64,
+    /// Share of words inside quotation marks
+    pub dialogue_percent: f64,
+    /// Saved versions in the document history
+    pub versions: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// One AI request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiUsageRecord {
+    pub id: Uuid,
+    pub project_id: Option<Uuid>,
+    /// What the request was for, such as "ai_request" or "summary"
+    pub feature: String,
+    pub model: Option<String>,
+    pub prompt_chars: i64,
+    pub response_chars: i64,
+    pub cost_cents: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AiUsageRecord {
+    pub fn new(project_id: Option<Uuid>, feature: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            feature: feature.into(),
+            model: None,
+            prompt_chars: 0,
+            response_chars: 0,
+            cost_cents: 0,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Database schema for writing statistics
+pub const CREATE_STATS_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS writing_sessions (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    ended_at TEXT,
+    words_start INTEGER NOT NULL,
+    words_end INTEGER,
+    documents_edited INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS word_count_history (
+    document_id TEXT NOT NULL,
+    project_id TEXT NOT NULL,
+    day TEXT NOT NULL,
+    word_count INTEGER NOT NULL,
+    PRIMARY KEY (document_id, day),
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS ai_usage_log (
+    id TEXT PRIMARY KEY,
+    project_id TEXT,
+    feature TEXT NOT NULL,
+    model TEXT,
+    prompt_chars INTEGER NOT NULL DEFAULT 0,
+    response_chars INTEGER NOT NULL DEFAULT 0,
+    cost_cents INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_writing_sessions_project ON writing_sessions(project_id, started_at);
+CREATE INDEX IF NOT EXISTS idx_word_count_history_project ON word_count_history(project_id, day);
+CREATE INDEX IF NOT EXISTS idx_ai_usage_log_created ON ai_usage_log(created_at);
+"#;
+
+/// Record today's word count of each active document in a project
+pub const SNAPSHOT_WORD_COUNTS_SQL: &str = r#"
+INSERT INTO word_count_history (document_id, project_id, day, word_count)
+SELECT id, project_id, ?2, word_count FROM documents WHERE project_id = ?1 AND is_active = 1
+  AND json_extract(CASE WHEN json_valid(metadata) THEN metadata END, '$.exclude_from_word_count') IS NOT 1
+ON CONFLICT(document_id, day) DO UPDATE SET word_count = excluded.word_count
+"#;
+
+/// Insert AI usage SQL
+pub const INSERT_AI_USAGE_SQL: &str = r#"
+INSERT INTO ai_usage_log (id, project_id, feature, model, prompt_chars, response_chars, cost_cents, created_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+"#;
@@ -0,0 +1,61 @@
+//! Story Bible Data Models
+//!
+//! The story bible export preset: the whole codex compiled into one book,
+//! a section per entry type with an entry per subsection, cross-references
+//! between entries, entry images and a relationship matrix appendix.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::codex::CodexEntryType;
+use crate::publishing::PublishFormat;
+
+/// What to put in a story bible
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoryBibleRequest {
+    pub project_id: Uuid,
+    pub format: PublishFormat,
+    /// Entry types to include; all when empty
+    #[serde(default)]
+    pub entry_types: Vec<CodexEntryType>,
+    /// Embed images attached to entries and marked for export
+    #[serde(default = "default_true")]
+    pub include_images: bool,
+    /// Add the character relationship matrix as an appendix
+    #[serde(default = "default_true")]
+    pub include_relationship_matrix: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl StoryBibleRequest {
+    pub fn new(project_id: Uuid, format: PublishFormat) -> Self {
+        Self {
+            project_id,
+            format,
+            entry_types: Vec::new(),
+            include_images: true,
+            include_relationship_matrix: true,
+        }
+    }
+
+    /// Whether entries of a type go in the bible
+    pub fn includes(&self, entry_type: CodexEntryType) -> bool {
+        self.entry_types.is_empty() || self.entry_types.contains(&entry_type)
+    }
+}
+
+/// A rendered story bible
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoryBible {
+    pub title: String,
+    pub format: PublishFormat,
+    pub entries: usize,
+    pub images: usize,
+    /// Characters in the relationship matrix; 0 when it was left out
+    pub matrix_size: usize,
+    #[serde(skip)]
+    pub bytes: Vec<u8>,
+}
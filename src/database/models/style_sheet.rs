@@ -0,0 +1,134 @@
+//! Style Sheet Data Models
+//!
+//! Project style sheets record editorial decisions (preferred spellings,
+//! hyphenation, capitalization, banned words) so they can be checked
+//! consistently across every document in a project.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Category of a style sheet rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StyleRuleKind {
+    /// `pattern` is a disfavored spelling, `replacement` the preferred one
+    PreferredSpelling,
+    /// `pattern` is a disfavored hyphenation, `replacement` the preferred one
+    Hyphenation,
+    /// `pattern` is the canonical capitalization; other casings are flagged
+    Capitalization,
+    /// `pattern` must not appear at all
+    BannedWord,
+}
+
+impl StyleRuleKind {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            StyleRuleKind::PreferredSpelling => "Preferred Spelling",
+            StyleRuleKind::Hyphenation => "Hyphenation",
+            StyleRuleKind::Capitalization => "Capitalization",
+            StyleRuleKind::BannedWord => "Banned Word",
+        }
+    }
+
+    /// Whether violations of this kind can be fixed without human review
+    pub fn is_auto_fixable(&self) -> bool {
+        !matches!(self, StyleRuleKind::BannedWord)
+    }
+}
+
+/// A single style sheet rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StyleRule {
+    pub id: Uuid,
+    pub kind: StyleRuleKind,
+    pub pattern: String,
+    pub replacement: Option<String>,
+    pub note: Option<String>,
+}
+
+impl StyleRule {
+    pub fn new(kind: StyleRuleKind, pattern: String, replacement: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            pattern,
+            replacement,
+            note: None,
+        }
+    }
+}
+
+/// Project style sheet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StyleSheet {
+    pub project_id: Uuid,
+    pub rules: Vec<StyleRule>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl StyleSheet {
+    pub fn new(project_id: Uuid) -> Self {
+        Self {
+            project_id,
+            rules: Vec::new(),
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+/// A style sheet violation found in a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StyleViolation {
+    pub rule_id: Uuid,
+    pub kind: StyleRuleKind,
+    /// Byte offset of the match start
+    pub start: usize,
+    /// Byte offset of the match end
+    pub end: usize,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+    pub matched: String,
+    pub suggestion: Option<String>,
+    pub auto_fixable: bool,
+}
+
+/// Violations found in one document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentStyleReport {
+    pub document_id: Uuid,
+    pub title: String,
+    pub violations: Vec<StyleViolation>,
+}
+
+/// Result of applying auto-fixes to a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StyleFixResult {
+    pub document_id: Uuid,
+    pub fixes_applied: usize,
+    pub remaining_violations: usize,
+}
+
+/// Database schema for style sheets
+pub const CREATE_STYLE_SHEET_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS style_sheets (
+    project_id TEXT PRIMARY KEY,
+    rules TEXT NOT NULL DEFAULT '[]',
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+"#;
+
+/// Upsert style sheet SQL
+pub const UPSERT_STYLE_SHEET_SQL: &str = r#"
+INSERT INTO style_sheets (project_id, rules, updated_at)
+VALUES (?1, ?2, ?3)
+ON CONFLICT(project_id) DO UPDATE SET rules = excluded.rules, updated_at = excluded.updated_at
+"#;
+
+/// Get style sheet SQL
+pub const GET_STYLE_SHEET_SQL: &str = r#"
+SELECT rules, updated_at FROM style_sheets WHERE project_id = ?1
+"#;
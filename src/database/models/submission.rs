@@ -0,0 +1,350 @@
+//! Submission Data Models
+//!
+//! Agents, magazines and other markets a manuscript can be sent to, with
+//! their guidelines, and the record of each submission: when it went out,
+//! where it stands and what came back. Markets are shared across projects;
+//! submissions belong to the project whose manuscript was sent.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What kind of market a submission goes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketKind {
+    Agent,
+    Publisher,
+    Magazine,
+    Contest,
+    Other,
+}
+
+impl MarketKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarketKind::Agent => "agent",
+            MarketKind::Publisher => "publisher",
+            MarketKind::Magazine => "magazine",
+            MarketKind::Contest => "contest",
+            MarketKind::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "agent" => MarketKind::Agent,
+            "publisher" => MarketKind::Publisher,
+            "magazine" => MarketKind::Magazine,
+            "contest" => MarketKind::Contest,
+            _ => MarketKind::Other,
+        }
+    }
+}
+
+/// An agent or market
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Market {
+    pub id: Uuid,
+    pub name: String,
+    pub kind: MarketKind,
+    #[serde(default)]
+    pub contact: Option<String>,
+    #[serde(default)]
+    pub website: Option<String>,
+    /// Submission guidelines as the market publishes them
+    #[serde(default)]
+    pub guidelines: String,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default = "default_true")]
+    pub accepts_simultaneous: bool,
+    /// Response time the market states; reminders fall due after it
+    #[serde(default)]
+    pub expected_response_days: Option<u32>,
+    #[serde(default)]
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Market {
+    pub fn new(name: impl Into<String>, kind: MarketKind) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            kind,
+            contact: None,
+            website: None,
+            guidelines: String::new(),
+            genres: Vec::new(),
+            accepts_simultaneous: true,
+            expected_response_days: None,
+            notes: String::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Where a submission stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionStatus {
+    /// Sent and waiting for an answer
+    Sent,
+    /// The market asked for more, e.g. a partial or full manuscript
+    MoreRequested,
+    Accepted,
+    Rejected,
+    Withdrawn,
+    /// Closed without ever hearing back
+    NoResponse,
+}
+
+impl SubmissionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubmissionStatus::Sent => "sent",
+            SubmissionStatus::MoreRequested => "more_requested",
+            SubmissionStatus::Accepted => "accepted",
+            SubmissionStatus::Rejected => "rejected",
+            SubmissionStatus::Withdrawn => "withdrawn",
+            SubmissionStatus::NoResponse => "no_response",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "more_requested" => SubmissionStatus::MoreRequested,
+            "accepted" => SubmissionStatus::Accepted,
+            "rejected" => SubmissionStatus::Rejected,
+            "withdrawn" => SubmissionStatus::Withdrawn,
+            "no_response" => SubmissionStatus::NoResponse,
+            _ => SubmissionStatus::Sent,
+        }
+    }
+
+    /// Still waiting on the market
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self,
+            SubmissionStatus::Sent | SubmissionStatus::MoreRequested
+        )
+    }
+
+    /// The market answered
+    pub fn is_response(&self) -> bool {
+        matches!(
+            self,
+            SubmissionStatus::MoreRequested
+                | SubmissionStatus::Accepted
+                | SubmissionStatus::Rejected
+        )
+    }
+}
+
+/// A manuscript sent to a market
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Submission {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub market_id: Uuid,
+    /// What was sent, e.g. "Query + first 10 pages"
+    #[serde(default)]
+    pub materials: String,
+    pub sent_at: DateTime<Utc>,
+    pub status: SubmissionStatus,
+    /// When the market last answered
+    #[serde(default)]
+    pub responded_at: Option<DateTime<Utc>>,
+    /// What the market said
+    #[serde(default)]
+    pub response: Option<String>,
+    /// When to remind the writer to follow up; set from the market's
+    /// response time when left empty
+    #[serde(default)]
+    pub remind_at: Option<DateTime<Utc>>,
+    /// The reminder has been shown
+    #[serde(default)]
+    pub reminded: bool,
+    #[serde(default)]
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Submission {
+    pub fn new(project_id: Uuid, market_id: Uuid, sent_at: DateTime<Utc>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            market_id,
+            materials: String::new(),
+            sent_at,
+            status: SubmissionStatus::Sent,
+            responded_at: None,
+            response: None,
+            remind_at: None,
+            reminded: false,
+            notes: String::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// A market's answer to a submission
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmissionResponse {
+    pub submission_id: Uuid,
+    pub status: SubmissionStatus,
+    #[serde(default)]
+    pub response: Option<String>,
+    /// Now when unset
+    #[serde(default)]
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+/// How a market has treated the writer's submissions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketStats {
+    pub market_id: Uuid,
+    pub submissions: usize,
+    pub open: usize,
+    pub responses: usize,
+    pub accepted: usize,
+    pub rejected: usize,
+    /// Share of closed submissions the market answered
+    pub response_rate: Option<f64>,
+    pub average_response_days: Option<f64>,
+    pub longest_response_days: Option<i64>,
+}
+
+/// A submission still waiting for an answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenSubmission {
+    pub submission: Submission,
+    pub market_name: String,
+    pub days_out: i64,
+    /// The market's stated response time, or its average so far
+    pub expected_response_days: Option<f64>,
+    /// Out longer than the expected response time
+    pub overdue: bool,
+}
+
+/// Open submissions, longest out first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmissionReport {
+    /// None for submissions across all projects
+    pub project_id: Option<Uuid>,
+    pub open: Vec<OpenSubmission>,
+    pub overdue: usize,
+    /// Open submissions to markets that don't take simultaneous ones
+    pub exclusive: usize,
+}
+
+/// A follow-up reminder that has fallen due
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmissionReminder {
+    pub submission_id: Uuid,
+    pub project_id: Uuid,
+    pub market_name: String,
+    pub remind_at: DateTime<Utc>,
+    pub message: String,
+}
+
+/// Database schema for markets and submissions
+pub const CREATE_SUBMISSION_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS markets (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    contact TEXT,
+    website TEXT,
+    guidelines TEXT NOT NULL DEFAULT '',
+    genres TEXT NOT NULL DEFAULT '[]',
+    accepts_simultaneous INTEGER NOT NULL DEFAULT 1,
+    expected_response_days INTEGER,
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS submissions (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    market_id TEXT NOT NULL,
+    materials TEXT NOT NULL DEFAULT '',
+    sent_at TEXT NOT NULL,
+    status TEXT NOT NULL,
+    responded_at TEXT,
+    response TEXT,
+    remind_at TEXT,
+    reminded INTEGER NOT NULL DEFAULT 0,
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+    FOREIGN KEY (market_id) REFERENCES markets(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_submissions_project ON submissions(project_id);
+CREATE INDEX IF NOT EXISTS idx_submissions_market ON submissions(market_id);
+"#;
+
+/// Insert or replace market SQL
+pub const UPSERT_MARKET_SQL: &str = r#"
+INSERT INTO markets (id, name, kind, contact, website, guidelines, genres, accepts_simultaneous,
+                     expected_response_days, notes, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+ON CONFLICT(id) DO UPDATE SET
+    name = excluded.name,
+    kind = excluded.kind,
+    contact = excluded.contact,
+    website = excluded.website,
+    guidelines = excluded.guidelines,
+    genres = excluded.genres,
+    accepts_simultaneous = excluded.accepts_simultaneous,
+    expected_response_days = excluded.expected_response_days,
+    notes = excluded.notes,
+    updated_at = excluded.updated_at
+"#;
+
+/// Select markets SQL
+pub const GET_MARKETS_SQL: &str = r#"
+SELECT id, name, kind, contact, website, guidelines, genres, accepts_simultaneous,
+       expected_response_days, notes, created_at, updated_at
+FROM markets ORDER BY name ASC
+"#;
+
+/// Insert or replace submission SQL
+pub const UPSERT_SUBMISSION_SQL: &str = r#"
+INSERT INTO submissions (id, project_id, market_id, materials, sent_at, status, responded_at,
+                         response, remind_at, reminded, notes, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+ON CONFLICT(id) DO UPDATE SET
+    market_id = excluded.market_id,
+    materials = excluded.materials,
+    sent_at = excluded.sent_at,
+    status = excluded.status,
+    responded_at = excluded.responded_at,
+    response = excluded.response,
+    remind_at = excluded.remind_at,
+    reminded = excluded.reminded,
+    notes = excluded.notes,
+    updated_at = excluded.updated_at
+"#;
+
+/// Select submissions SQL; filter with a WHERE clause appended by the caller
+pub const SELECT_SUBMISSIONS_SQL: &str = r#"
+SELECT id, project_id, market_id, materials, sent_at, status, responded_at, response,
+       remind_at, reminded, notes, created_at, updated_at
+FROM submissions
+"#;
@@ -0,0 +1,403 @@
+//! Timeline Models
+//!
+//! Story events on a project's timeline, alongside its Time codex entries.
+//! An event's dates can be exact, known only to the month or year, give or
+//! take some days, or set relative to another event ("three days after the
+//! Coronation"). Every date resolves to the range of days it could fall on
+//! in the project's shared day line, so vague events still order and
+//! overlap sensibly. Events link to the documents that tell them and the
+//! characters and places involved, which is how a character turning up in
+//! two places at once is found.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::calendar::{CalendarDate, TimelineIssue};
+
+/// How much of a calendar date is known
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatePrecision {
+    #[default]
+    Day,
+    /// Some day in the date's month
+    Month,
+    /// Some day in the date's year
+    Year,
+}
+
+/// Unit of a relative date's offset. Weeks are as long as the calendar's
+/// week; months and years follow the anchor's calendar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OffsetUnit {
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+/// When an event starts or ends
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EventDate {
+    /// A date in one of the project's calendars
+    Calendar {
+        /// The project's only calendar if unset
+        #[serde(default)]
+        calendar_id: Option<Uuid>,
+        date: CalendarDate,
+        #[serde(default)]
+        precision: DatePrecision,
+        /// Days either side the date might really fall on
+        #[serde(default)]
+        give_or_take: u32,
+    },
+    /// An offset from the start of another event or Time codex entry, or
+    /// from its end; negative offsets go back in time
+    Relative {
+        anchor_id: Uuid,
+        offset: i64,
+        unit: OffsetUnit,
+        #[serde(default)]
+        from_end: bool,
+        #[serde(default)]
+        give_or_take: u32,
+    },
+}
+
+impl EventDate {
+    /// The event this date depends on, if any
+    pub fn anchor_id(&self) -> Option<Uuid> {
+        match self {
+            EventDate::Calendar { .. } => None,
+            EventDate::Relative { anchor_id, .. } => Some(*anchor_id),
+        }
+    }
+}
+
+/// What an event is linked to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventLinkKind {
+    /// A document telling the event
+    Document,
+    /// A character codex entry taking part
+    Character,
+    /// A place codex entry where it happens
+    Place,
+}
+
+/// A document, character or place an event is linked to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventLink {
+    pub kind: EventLinkKind,
+    pub target_id: Uuid,
+    /// Filled in when the timeline is built
+    #[serde(default)]
+    pub title: String,
+}
+
+impl EventLink {
+    pub fn new(kind: EventLinkKind, target_id: Uuid) -> Self {
+        Self {
+            kind,
+            target_id,
+            title: String::new(),
+        }
+    }
+}
+
+/// An event on a project's timeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoryEvent {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub start: EventDate,
+    /// None for an event over within its start date
+    #[serde(default)]
+    pub end: Option<EventDate>,
+    #[serde(default)]
+    pub links: Vec<EventLink>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl StoryEvent {
+    pub fn new(project_id: Uuid, title: &str, start: EventDate) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            title: title.trim().to_string(),
+            description: String::new(),
+            start,
+            end: None,
+            links: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn with_link(mut self, kind: EventLinkKind, target_id: Uuid) -> Self {
+        self.links.push(EventLink::new(kind, target_id));
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.title.trim().is_empty() {
+            return Err("Event title cannot be empty".to_string());
+        }
+        let dates = std::iter::once(&self.start).chain(self.end.as_ref());
+        if dates.clone().any(|d| d.anchor_id() == Some(self.id)) {
+            return Err("An event can't be dated relative to itself".to_string());
+        }
+        if dates.clone().any(|d| {
+            matches!(d, EventDate::Calendar { date, .. } if date.year == 0 || date.month == 0 || date.day == 0)
+        }) {
+            return Err("Dates count years, months and days from 1".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn linked(&self, kind: EventLinkKind) -> impl Iterator<Item = &EventLink> {
+        self.links.iter().filter(move |l| l.kind == kind)
+    }
+}
+
+/// Days on the project's shared day line, both ends included
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaySpan {
+    pub earliest: i64,
+    pub latest: i64,
+}
+
+impl DaySpan {
+    pub fn day(day: i64) -> Self {
+        Self {
+            earliest: day,
+            latest: day,
+        }
+    }
+
+    pub fn widen(self, days: u32) -> Self {
+        Self {
+            earliest: self.earliest - days as i64,
+            latest: self.latest + days as i64,
+        }
+    }
+
+    pub fn shift(self, days: i64) -> Self {
+        Self {
+            earliest: self.earliest + days,
+            latest: self.latest + days,
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.earliest == self.latest
+    }
+
+    pub fn overlaps(&self, other: &DaySpan) -> bool {
+        self.earliest <= other.latest && other.earliest <= self.latest
+    }
+}
+
+/// Where a timeline event comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSource {
+    /// A story event kept on the timeline
+    Event,
+    /// A Time codex entry
+    Codex,
+}
+
+/// An event with its dates resolved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlacedEvent {
+    pub event_id: Uuid,
+    pub title: String,
+    pub source: EventSource,
+    /// Calendar the start is written in
+    pub calendar_id: Uuid,
+    /// Days the event could start on; a single day when exact
+    pub start: DaySpan,
+    pub end: Option<DaySpan>,
+    /// Formatted in the calendar, e.g. "Frostfall 1204 AR" for a month,
+    /// with "c." when give or take some days
+    pub start_label: String,
+    pub end_label: Option<String>,
+    /// Links to documents, characters and places still in the project
+    pub links: Vec<EventLink>,
+}
+
+impl PlacedEvent {
+    /// Every day the event might be going on
+    pub fn possible(&self) -> DaySpan {
+        DaySpan {
+            earliest: self.start.earliest,
+            latest: self
+                .end
+                .map_or(self.start.latest, |end| end.latest.max(self.start.latest)),
+        }
+    }
+
+    /// The days the event is going on however its vague dates fall; None
+    /// when they're too vague to be sure of any
+    pub fn certain(&self) -> Option<DaySpan> {
+        let earliest = self.start.latest;
+        let latest = self.end.map_or(self.start.earliest, |end| end.earliest);
+        (earliest <= latest).then_some(DaySpan { earliest, latest })
+    }
+
+    pub fn linked(&self, kind: EventLinkKind) -> impl Iterator<Item = &EventLink> {
+        self.links.iter().filter(move |l| l.kind == kind)
+    }
+}
+
+/// A character at two different places at overlapping times
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceConflict {
+    pub character_id: Uuid,
+    pub character: String,
+    /// The earlier event first
+    pub first_event_id: Uuid,
+    pub first_place: String,
+    pub second_event_id: Uuid,
+    pub second_place: String,
+    /// The events overlap however their vague dates fall, rather than
+    /// only possibly
+    pub certain: bool,
+    pub message: String,
+}
+
+/// Which part of the timeline to return
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimelineFilter {
+    /// Only events linked to this document, character or place
+    #[serde(default)]
+    pub linked_to: Option<Uuid>,
+    /// Only events that might be going on from this shared day
+    #[serde(default)]
+    pub from_day: Option<i64>,
+    /// Only events that might be going on up to this shared day
+    #[serde(default)]
+    pub to_day: Option<i64>,
+    /// Leave out Time codex entries
+    #[serde(default)]
+    pub events_only: bool,
+}
+
+impl TimelineFilter {
+    pub fn matches(&self, event: &PlacedEvent) -> bool {
+        let span = event.possible();
+        self.linked_to
+            .is_none_or(|id| event.links.iter().any(|l| l.target_id == id))
+            && self.from_day.is_none_or(|day| span.latest >= day)
+            && self.to_day.is_none_or(|day| span.earliest <= day)
+            && !(self.events_only && event.source == EventSource::Codex)
+    }
+}
+
+/// A project's timeline, earliest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoryTimeline {
+    pub project_id: Uuid,
+    pub events: Vec<PlacedEvent>,
+    /// Conflicts involving the events returned
+    pub conflicts: Vec<PresenceConflict>,
+    /// Events left off the timeline, or placed with a warning
+    pub issues: Vec<TimelineIssue>,
+}
+
+/// Database schema for timeline events; dates and links are stored as JSON
+pub const CREATE_TIMELINE_EVENTS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS timeline_events (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    start_date TEXT NOT NULL,
+    end_date TEXT,
+    links TEXT NOT NULL DEFAULT '[]',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_timeline_events_project ON timeline_events(project_id);
+"#;
+
+/// Insert or replace timeline event SQL
+pub const UPSERT_TIMELINE_EVENT_SQL: &str = r#"
+INSERT INTO timeline_events (id, project_id, title, description, start_date, end_date, links, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+ON CONFLICT(id) DO UPDATE SET
+    title = excluded.title,
+    description = excluded.description,
+    start_date = excluded.start_date,
+    end_date = excluded.end_date,
+    links = excluded.links,
+    updated_at = excluded.updated_at
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_and_dates() {
+        let relative: EventDate = serde_json::from_str(
+            r#"{"kind": "relative", "anchor_id": "6f1c1a56-3f55-4cde-9c1c-7bde2a5a1e0a", "offset": -3, "unit": "days"}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            relative,
+            EventDate::Relative {
+                offset: -3,
+                from_end: false,
+                give_or_take: 0,
+                ..
+            }
+        ));
+
+        let mut event = PlacedEvent {
+            event_id: Uuid::new_v4(),
+            title: "Siege".to_string(),
+            source: EventSource::Event,
+            calendar_id: Uuid::new_v4(),
+            start: DaySpan {
+                earliest: 10,
+                latest: 14,
+            },
+            end: Some(DaySpan::day(12)),
+            start_label: "c. 12 Frost 1 AR".to_string(),
+            end_label: None,
+            links: Vec::new(),
+        };
+        assert_eq!(
+            event.possible(),
+            DaySpan {
+                earliest: 10,
+                latest: 14
+            }
+        );
+        // It may start after the day it surely ends by
+        assert_eq!(event.certain(), None);
+        event.end = Some(DaySpan::day(20));
+        assert_eq!(
+            event.certain(),
+            Some(DaySpan {
+                earliest: 14,
+                latest: 20
+            })
+        );
+        assert!(DaySpan::day(14).overlaps(&event.possible()));
+        assert!(!DaySpan::day(21).overlaps(&event.possible()));
+    }
+}
@@ -0,0 +1,130 @@
+//! Undo History Data Models
+//!
+//! Structural operations (reordering the binder, deleting codex entries,
+//! tagging documents in bulk) recorded so they can be undone and redone.
+//! Each operation keeps the touched columns of every row it changed, as they
+//! were before and after, so undoing writes the old values back.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Most operations kept per project; the oldest are dropped first
+pub const MAX_UNDO_OPERATIONS: i64 = 100;
+
+/// Kind of structural operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UndoOperationKind {
+    MoveDocuments,
+    DeleteCodexEntry,
+    TagDocuments,
+    UntagDocuments,
+}
+
+impl UndoOperationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UndoOperationKind::MoveDocuments => "move_documents",
+            UndoOperationKind::DeleteCodexEntry => "delete_codex_entry",
+            UndoOperationKind::TagDocuments => "tag_documents",
+            UndoOperationKind::UntagDocuments => "untag_documents",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "move_documents" => Some(UndoOperationKind::MoveDocuments),
+            "delete_codex_entry" => Some(UndoOperationKind::DeleteCodexEntry),
+            "tag_documents" => Some(UndoOperationKind::TagDocuments),
+            "untag_documents" => Some(UndoOperationKind::UntagDocuments),
+            _ => None,
+        }
+    }
+}
+
+/// One row an operation changed. `before` and `after` hold the touched
+/// columns by name; `None` means the row did not exist.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowChange {
+    pub table: String,
+    pub key: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// A recorded structural operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoOperation {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub kind: UndoOperationKind,
+    /// Shown in the Undo/Redo menu items, e.g. "Delete codex entry 'Mara'"
+    pub label: String,
+    pub changes: Vec<RowChange>,
+    /// Undone and waiting on the redo stack
+    pub undone: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What Undo and Redo would do next in a project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoState {
+    pub project_id: Uuid,
+    pub undo: Option<UndoSummary>,
+    pub redo: Option<UndoSummary>,
+    /// Recorded operations, newest first
+    pub history: Vec<UndoSummary>,
+}
+
+/// An operation without its row changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoSummary {
+    pub id: Uuid,
+    pub kind: UndoOperationKind,
+    pub label: String,
+    pub undone: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&UndoOperation> for UndoSummary {
+    fn from(operation: &UndoOperation) -> Self {
+        Self {
+            id: operation.id,
+            kind: operation.kind,
+            label: operation.label.clone(),
+            undone: operation.undone,
+            created_at: operation.created_at,
+        }
+    }
+}
+
+/// Database schema for the undo history
+pub const CREATE_UNDO_OPERATIONS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS undo_operations (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    sequence INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    label TEXT NOT NULL,
+    changes TEXT NOT NULL,
+    undone INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_undo_operations_project ON undo_operations(project_id, sequence);
+"#;
+
+/// Insert undo operation SQL
+pub const INSERT_UNDO_OPERATION_SQL: &str = r#"
+INSERT INTO undo_operations (id, project_id, sequence, kind, label, changes, undone, created_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+"#;
+
+/// Select undo operations SQL; filter with a WHERE clause appended by the caller
+pub const SELECT_UNDO_OPERATIONS_SQL: &str = r#"
+SELECT id, project_id, kind, label, changes, undone, created_at
+FROM undo_operations
+"#;
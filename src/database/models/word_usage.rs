@@ -0,0 +1,326 @@
+//! Word Usage Data Models
+//!
+//! Over-used words and phrases per chapter, user-editable cliché lists and
+//! the per-document count cache that keeps re-analysis incremental.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// A user-editable list of clichés
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClicheList {
+    pub id: Uuid,
+    /// `None` for lists shared by every project
+    pub project_id: Option<Uuid>,
+    pub name: String,
+    pub phrases: Vec<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ClicheList {
+    pub fn new(project_id: Option<Uuid>, name: String, phrases: Vec<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            name,
+            phrases,
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// A cliché found in a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClicheMatch {
+    pub phrase: String,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+}
+
+/// Raw counts for one document, as cached
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocumentWordCounts {
+    pub word_count: usize,
+    /// Content words, lowercased, without stop words or character names
+    pub words: HashMap<String, usize>,
+    /// Two to four word phrases used more than once
+    pub phrases: HashMap<String, usize>,
+    pub cliches: Vec<ClicheMatch>,
+}
+
+/// How often a word or phrase is used
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TermUsage {
+    pub term: String,
+    pub count: usize,
+    /// Uses per thousand words, comparable between chapters
+    pub per_thousand: f32,
+}
+
+/// Analysis of one chapter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterWordUsage {
+    pub document_id: Uuid,
+    pub title: String,
+    pub word_count: usize,
+    pub top_words: Vec<TermUsage>,
+    pub top_phrases: Vec<TermUsage>,
+    pub cliches: Vec<ClicheMatch>,
+}
+
+/// One chapter's row of the heatmap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeatmapRow {
+    pub document_id: Uuid,
+    pub title: String,
+    /// Uses per thousand words of each heatmap term, in term order
+    pub per_thousand: Vec<f32>,
+}
+
+/// Density of the project's most used terms across chapters
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WordHeatmap {
+    pub terms: Vec<String>,
+    pub rows: Vec<HeatmapRow>,
+}
+
+/// Word usage across a whole project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordUsageReport {
+    pub project_id: Uuid,
+    pub analyzed_at: DateTime<Utc>,
+    pub word_count: usize,
+    pub top_words: Vec<TermUsage>,
+    pub top_phrases: Vec<TermUsage>,
+    pub chapters: Vec<ChapterWordUsage>,
+    pub heatmap: WordHeatmap,
+    /// Documents re-analysed for this report; the rest came from the cache
+    pub refreshed: usize,
+}
+
+/// Words too common to say anything about style
+pub const STOP_WORDS: &[&str] = &[
+    "a",
+    "about",
+    "above",
+    "after",
+    "again",
+    "against",
+    "all",
+    "am",
+    "an",
+    "and",
+    "any",
+    "are",
+    "as",
+    "at",
+    "be",
+    "because",
+    "been",
+    "before",
+    "being",
+    "below",
+    "between",
+    "both",
+    "but",
+    "by",
+    "can",
+    "could",
+    "did",
+    "do",
+    "does",
+    "doing",
+    "down",
+    "during",
+    "each",
+    "few",
+    "for",
+    "from",
+    "further",
+    "had",
+    "has",
+    "have",
+    "having",
+    "he",
+    "her",
+    "here",
+    "hers",
+    "herself",
+    "him",
+    "himself",
+    "his",
+    "how",
+    "i",
+    "if",
+    "in",
+    "into",
+    "is",
+    "it",
+    "it's",
+    "its",
+    "itself",
+    "just",
+    "me",
+    "more",
+    "most",
+    "my",
+    "myself",
+    "no",
+    "nor",
+    "not",
+    "now",
+    "of",
+    "off",
+    "on",
+    "once",
+    "only",
+    "or",
+    "other",
+    "our",
+    "ours",
+    "ourselves",
+    "out",
+    "over",
+    "own",
+    "same",
+    "she",
+    "should",
+    "so",
+    "some",
+    "such",
+    "than",
+    "that",
+    "the",
+    "their",
+    "theirs",
+    "them",
+    "themselves",
+    "then",
+    "there",
+    "these",
+    "they",
+    "this",
+    "those",
+    "through",
+    "to",
+    "too",
+    "under",
+    "until",
+    "up",
+    "very",
+    "was",
+    "we",
+    "were",
+    "what",
+    "when",
+    "where",
+    "which",
+    "while",
+    "who",
+    "whom",
+    "why",
+    "will",
+    "with",
+    "would",
+    "you",
+    "your",
+    "yours",
+    "yourself",
+    "yourselves",
+    "i'm",
+    "i'd",
+    "i'll",
+    "he's",
+    "she's",
+    "don't",
+    "didn't",
+    "wasn't",
+    "couldn't",
+    "won't",
+    "can't",
+];
+
+/// Database schema for cliché lists and the word count cache
+pub const CREATE_WORD_USAGE_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS cliche_lists (
+    id TEXT PRIMARY KEY,
+    project_id TEXT,
+    name TEXT NOT NULL,
+    phrases TEXT NOT NULL DEFAULT '[]',
+    enabled BOOLEAN NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_cliche_lists_project ON cliche_lists(project_id);
+
+CREATE TABLE IF NOT EXISTS word_usage_cache (
+    document_id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    checksum TEXT NOT NULL,
+    settings_fingerprint TEXT NOT NULL,
+    counts TEXT NOT NULL,
+    analyzed_at TEXT NOT NULL,
+    FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_word_usage_cache_project ON word_usage_cache(project_id);
+"#;
+
+/// Upsert cliché list SQL
+pub const UPSERT_CLICHE_LIST_SQL: &str = r#"
+INSERT INTO cliche_lists (id, project_id, name, phrases, enabled, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+ON CONFLICT(id) DO UPDATE SET
+    name = excluded.name,
+    phrases = excluded.phrases,
+    enabled = excluded.enabled,
+    updated_at = excluded.updated_at
+"#;
+
+/// Cliché lists that apply to a project (its own plus shared ones)
+pub const GET_CLICHE_LISTS_FOR_PROJECT_SQL: &str = r#"
+SELECT id, project_id, name, phrases, enabled, created_at, updated_at
+FROM cliche_lists
+WHERE project_id = ?1 OR project_id IS NULL
+ORDER BY name ASC
+"#;
+
+/// Delete cliché list SQL
+pub const DELETE_CLICHE_LIST_SQL: &str = r#"
+DELETE FROM cliche_lists WHERE id = ?1
+"#;
+
+/// Cached counts of a project's documents
+pub const GET_WORD_USAGE_CACHE_SQL: &str = r#"
+SELECT document_id, checksum, settings_fingerprint, counts
+FROM word_usage_cache
+WHERE project_id = ?1
+"#;
+
+/// Store counts for a document
+pub const UPSERT_WORD_USAGE_CACHE_SQL: &str = r#"
+INSERT INTO word_usage_cache (document_id, project_id, checksum, settings_fingerprint, counts, analyzed_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+ON CONFLICT(document_id) DO UPDATE SET
+    checksum = excluded.checksum,
+    settings_fingerprint = excluded.settings_fingerprint,
+    counts = excluded.counts,
+    analyzed_at = excluded.analyzed_at
+"#;
+
+/// Drop cached counts of documents that were deleted or deactivated
+pub const DELETE_STALE_WORD_USAGE_CACHE_SQL: &str = r#"
+DELETE FROM word_usage_cache
+WHERE project_id = ?1
+AND document_id NOT IN (SELECT id FROM documents WHERE project_id = ?1 AND is_active = 1)
+"#;
@@ -0,0 +1,148 @@
+//! Workspace Data Models
+//!
+//! Named working setups within a project, such as "drafting chapter 12" or
+//! "revising act 1": the documents open in the editor, which one has focus,
+//! references pinned to the side panel and a few layout hints, so the
+//! writer can switch between them in one step.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What a pinned reference points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PinKind {
+    Document,
+    Codex,
+    Research,
+    Url,
+}
+
+/// A reference kept at hand in the side panel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinnedReference {
+    pub kind: PinKind,
+    /// Id of the document, codex entry or research item, or the URL
+    pub target: String,
+    pub label: String,
+}
+
+/// How the editor area is split
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitMode {
+    #[default]
+    Single,
+    Vertical,
+    Horizontal,
+}
+
+/// Layout hints restored with a workspace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceLayout {
+    #[serde(default)]
+    pub split: SplitMode,
+    #[serde(default = "default_true")]
+    pub sidebar_visible: bool,
+    /// Side panel width in pixels
+    pub sidebar_width: Option<u32>,
+    /// Tool windows to reopen
+    #[serde(default)]
+    pub open_tools: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for WorkspaceLayout {
+    fn default() -> Self {
+        Self {
+            split: SplitMode::Single,
+            sidebar_visible: true,
+            sidebar_width: None,
+            open_tools: Vec::new(),
+        }
+    }
+}
+
+/// A named set of open documents, pins and layout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub name: String,
+    /// Open documents in tab order
+    #[serde(default)]
+    pub open_documents: Vec<Uuid>,
+    pub active_document: Option<Uuid>,
+    #[serde(default)]
+    pub pinned: Vec<PinnedReference>,
+    #[serde(default)]
+    pub layout: WorkspaceLayout,
+    /// Last switched to; the most recent is the project's current workspace
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Workspace {
+    pub fn new(project_id: Uuid, name: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id,
+            name: name.into(),
+            open_documents: Vec::new(),
+            active_document: None,
+            pinned: Vec::new(),
+            layout: WorkspaceLayout::default(),
+            last_used_at: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Database schema for workspaces
+pub const CREATE_WORKSPACES_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS workspaces (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    open_documents TEXT NOT NULL DEFAULT '[]',
+    active_document TEXT,
+    pinned TEXT NOT NULL DEFAULT '[]',
+    layout TEXT NOT NULL DEFAULT '{}',
+    last_used_at TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+    UNIQUE (project_id, name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_workspaces_project ON workspaces(project_id, last_used_at);
+"#;
+
+/// Insert or replace workspace SQL
+pub const UPSERT_WORKSPACE_SQL: &str = r#"
+INSERT INTO workspaces (id, project_id, name, open_documents, active_document, pinned,
+                        layout, last_used_at, created_at, updated_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+ON CONFLICT(id) DO UPDATE SET
+    name = excluded.name,
+    open_documents = excluded.open_documents,
+    active_document = excluded.active_document,
+    pinned = excluded.pinned,
+    layout = excluded.layout,
+    last_used_at = excluded.last_used_at,
+    updated_at = excluded.updated_at
+"#;
+
+/// Select workspaces SQL; filter with a WHERE clause appended by the caller
+pub const SELECT_WORKSPACES_SQL: &str = r#"
+SELECT id, project_id, name, open_documents, active_document, pinned, layout,
+       last_used_at, created_at, updated_at
+FROM workspaces
+"#;
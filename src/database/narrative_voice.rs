@@ -0,0 +1,671 @@
+//! Narrative Voice Checks
+//!
+//! Detects the person and tense each scene is narrated in and compares them
+//! with the voice its document metadata declares. Both are judged from the
+//! narration with dialogue blanked out: person from pronouns, tense from
+//! common past and present verb forms. A tense switch is reported where
+//! several sentences in a row break from the scene's tense; a person switch
+//! at any narration "I" in a third-person scene, or any mention of the
+//! declared narrator by name in a first- or second-person one.
+
+use uuid::Uuid;
+
+use crate::database::lint_packs::{guess_pov, narration, scene_spans, LintCharacter, Viewpoint};
+use crate::database::models::narrative_voice::*;
+use crate::database::text_match::{find_word_matches, line_and_column};
+
+/// Sentences in a row that must change tense before it counts as a switch
+const SWITCH_RUN: usize = 3;
+
+/// Sentences with a clear tense needed before a scene's tense is judged
+const MIN_TENSED_SENTENCES: usize = 3;
+
+/// Pronouns needed before a scene's person is judged
+const MIN_PRONOUNS: usize = 3;
+
+/// Longest excerpt kept for an issue, in characters
+const EXCERPT_CHARS: usize = 120;
+
+const FIRST_PERSON: &[&str] = &[
+    "i",
+    "me",
+    "my",
+    "mine",
+    "myself",
+    "we",
+    "us",
+    "our",
+    "ours",
+    "ourselves",
+];
+
+/// First-person words that signal a slip in third-person narration; "we"
+/// and "our" are left out since a narrator may address the reader with them
+const FIRST_PERSON_SINGULAR: &[&str] = &["i", "me", "my", "mine", "myself"];
+
+const SECOND_PERSON: &[&str] = &["you", "your", "yours", "yourself", "yourselves"];
+
+const THIRD_PERSON: &[&str] = &[
+    "he",
+    "him",
+    "his",
+    "himself",
+    "she",
+    "her",
+    "hers",
+    "herself",
+    "they",
+    "them",
+    "their",
+    "theirs",
+    "themselves",
+];
+
+/// Irregular past forms common in narration
+const PAST_WORDS: &[&str] = &[
+    "was",
+    "were",
+    "had",
+    "did",
+    "said",
+    "went",
+    "came",
+    "saw",
+    "knew",
+    "thought",
+    "took",
+    "got",
+    "made",
+    "told",
+    "found",
+    "gave",
+    "stood",
+    "sat",
+    "ran",
+    "heard",
+    "began",
+    "kept",
+    "held",
+    "brought",
+    "felt",
+    "meant",
+    "met",
+    "fell",
+    "grew",
+    "drew",
+    "threw",
+    "wore",
+    "wrote",
+    "spoke",
+    "broke",
+    "chose",
+    "rose",
+    "drove",
+    "forgot",
+    "caught",
+    "bought",
+    "fought",
+    "slept",
+    "wept",
+    "struck",
+    "shook",
+    "woke",
+    "became",
+    "understood",
+];
+
+const PRESENT_WORDS: &[&str] = &["is", "am", "are", "has", "does", "says", "goes"];
+
+/// Base verbs that read as present tense straight after "I", "we", "you"
+/// or "they"
+const PRESENT_BASE: &[&str] = &[
+    "am", "are", "have", "do", "walk", "look", "turn", "feel", "know", "think", "see", "hear",
+    "want", "need", "try", "wait", "stand", "sit", "run", "say", "tell", "take", "go", "come",
+    "hold", "keep", "watch", "reach", "pull", "push", "open", "close", "move", "step", "smile",
+    "nod", "shake", "stare", "wonder", "remember", "hope", "wish", "laugh", "lean", "listen",
+    "follow", "find", "leave", "start", "stop",
+];
+
+/// Words before a verb that make it part of a perfect, passive, modal or
+/// infinitive construction rather than a simple tense
+const AUXILIARIES: &[&str] = &[
+    "is", "am", "are", "be", "been", "being", "has", "have", "will", "would", "could", "should",
+    "might", "must", "can", "shall", "may", "to",
+];
+
+/// Words ending in "ed" that aren't past verbs
+const ED_EXCEPTIONS: &[&str] = &[
+    "hundred", "naked", "sacred", "wicked", "ragged", "rugged", "beloved", "kindred", "crooked",
+    "jagged", "rained",
+];
+
+/// Words after "he", "she" or "it" ending in "s" that aren't verbs
+const S_EXCEPTIONS: &[&str] = &[
+    "always",
+    "perhaps",
+    "sometimes",
+    "afterwards",
+    "towards",
+    "besides",
+    "nevertheless",
+    "was",
+    "his",
+    "its",
+    "this",
+    "yes",
+    "less",
+];
+
+/// A document to check, with the voice its metadata declares
+#[derive(Debug, Clone)]
+pub struct VoiceDocument {
+    pub id: Uuid,
+    pub title: String,
+    pub content: String,
+    pub declared: SceneVoice,
+}
+
+/// A sentence of a scene and what its narration shows
+#[derive(Debug)]
+struct Sentence {
+    start: usize,
+    end: usize,
+    tense: Option<NarrativeTense>,
+    /// Uses "I", "me" or "my" outside dialogue
+    first_person: bool,
+    /// Names the declared POV character outside dialogue
+    names_pov: bool,
+}
+
+/// Check the voice of every scene in the documents, which should be in
+/// binder order
+pub fn check_voice(
+    documents: &[VoiceDocument],
+    characters: &[LintCharacter],
+) -> (Vec<SceneVoiceReport>, Vec<VoiceIssue>) {
+    let mut scenes = Vec::new();
+    let mut issues = Vec::new();
+
+    for document in documents {
+        let content = &document.content;
+        let narration = narration(content);
+        let declared = &document.declared;
+
+        let mut characters = characters.to_vec();
+        let pov_index = declared.pov.as_ref().map(|pov| {
+            characters
+                .iter()
+                .position(|c| c.names.iter().any(|n| n.eq_ignore_ascii_case(pov)))
+                .unwrap_or_else(|| {
+                    characters.push(LintCharacter {
+                        names: vec![pov.clone()],
+                    });
+                    characters.len() - 1
+                })
+        });
+        let pov_names: &[String] = match pov_index {
+            Some(i) => &characters[i].names,
+            None => &[],
+        };
+
+        for (scene_index, (scene_start, scene_end)) in scene_spans(content).into_iter().enumerate()
+        {
+            let scene = scene_index + 1;
+            let text = &narration[scene_start..scene_end];
+            let sentences = sentences(content, &narration, scene_start, scene_end, pov_names);
+            let person = detect_person(text);
+            let tense = detect_tense(&sentences);
+            let guessed = guess_pov(text, &characters);
+            let pov = match guessed {
+                Some((Viewpoint::Character(i), _, _)) => characters[i].names.first().cloned(),
+                _ => None,
+            };
+
+            let issue = |kind: VoiceIssueKind,
+                         index: usize,
+                         expected: String,
+                         found: String,
+                         message: String| {
+                let sentence = &sentences[index];
+                VoiceIssue {
+                    kind,
+                    document_id: document.id,
+                    title: document.title.clone(),
+                    scene,
+                    sentence: index + 1,
+                    start: sentence.start,
+                    end: sentence.end,
+                    line: line_and_column(content, sentence.start).0,
+                    excerpt: excerpt(&content[sentence.start..sentence.end]),
+                    expected,
+                    found,
+                    message,
+                }
+            };
+
+            if !sentences.is_empty() {
+                if let (Some(expected), Some(found)) = (declared.person, person) {
+                    if expected != found {
+                        issues.push(issue(
+                            VoiceIssueKind::PersonMismatch,
+                            0,
+                            expected.label().to_string(),
+                            found.label().to_string(),
+                            format!(
+                                "Scene {} reads as {} but is marked {}",
+                                scene,
+                                found.label(),
+                                expected.label()
+                            ),
+                        ));
+                    }
+                }
+                if let (Some(expected), Some(found)) = (declared.tense, tense) {
+                    if expected != found {
+                        issues.push(issue(
+                            VoiceIssueKind::TenseMismatch,
+                            0,
+                            expected.label().to_string(),
+                            found.label().to_string(),
+                            format!(
+                                "Scene {} reads in the {} but is marked {}",
+                                scene,
+                                found.label(),
+                                expected.label()
+                            ),
+                        ));
+                    }
+                }
+            }
+
+            if let (Some(expected), Some((Viewpoint::Character(i), start, _))) =
+                (&declared.pov, guessed)
+            {
+                if Some(i) != pov_index {
+                    let found = characters[i].names[0].clone();
+                    let index = sentences
+                        .iter()
+                        .position(|s| s.end > scene_start + start)
+                        .unwrap_or(0);
+                    if !sentences.is_empty() {
+                        issues.push(issue(
+                            VoiceIssueKind::PovMismatch,
+                            index,
+                            expected.clone(),
+                            found.clone(),
+                            format!(
+                                "Scene {} follows {} but is marked as {}'s POV",
+                                scene, found, expected
+                            ),
+                        ));
+                    }
+                }
+            }
+
+            match person.or(declared.person) {
+                Some(NarrativePerson::Third) => {
+                    for index in run_starts(&sentences, |s| s.first_person) {
+                        issues.push(issue(
+                            VoiceIssueKind::PersonSwitch,
+                            index,
+                            NarrativePerson::Third.label().to_string(),
+                            NarrativePerson::First.label().to_string(),
+                            "Narration slips into first person in a third-person scene".to_string(),
+                        ));
+                    }
+                }
+                Some(expected) if !pov_names.is_empty() => {
+                    for index in run_starts(&sentences, |s| s.names_pov) {
+                        issues.push(issue(
+                            VoiceIssueKind::PersonSwitch,
+                            index,
+                            expected.label().to_string(),
+                            NarrativePerson::Third.label().to_string(),
+                            format!(
+                                "Narration refers to {} by name in a {} scene",
+                                pov_names[0],
+                                expected.label()
+                            ),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+
+            if let Some(expected) = tense.or(declared.tense) {
+                for (index, found, length) in tense_switches(&sentences, expected) {
+                    issues.push(issue(
+                        VoiceIssueKind::TenseSwitch,
+                        index,
+                        expected.label().to_string(),
+                        found.label().to_string(),
+                        format!(
+                            "Narration switches to the {} for {} sentences in a {} scene",
+                            found.label(),
+                            length,
+                            expected.label()
+                        ),
+                    ));
+                }
+            }
+
+            scenes.push(SceneVoiceReport {
+                document_id: document.id,
+                title: document.title.clone(),
+                scene,
+                start: scene_start,
+                end: scene_end,
+                declared: declared.clone(),
+                person,
+                tense,
+                pov,
+            });
+        }
+    }
+
+    issues.sort_by_key(|issue| {
+        let position = documents
+            .iter()
+            .position(|d| d.id == issue.document_id)
+            .unwrap_or(usize::MAX);
+        (position, issue.start)
+    });
+    (scenes, issues)
+}
+
+/// Sentences of a scene as byte ranges of the content, each classified
+/// from its narration
+fn sentences(
+    content: &str,
+    narration: &str,
+    start: usize,
+    end: usize,
+    pov_names: &[String],
+) -> Vec<Sentence> {
+    let mut spans = Vec::new();
+    let mut from = start;
+    let mut chars = content[start..end].char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        let mut until = start + offset + c.len_utf8();
+        let ends = match c {
+            '\n' => true,
+            '.' | '!' | '?' => {
+                while let Some(&(offset, next)) = chars.peek() {
+                    if !matches!(next, '"' | '\'' | '\u{201D}' | '\u{2019}' | ')') {
+                        break;
+                    }
+                    until = start + offset + next.len_utf8();
+                    chars.next();
+                }
+                chars.peek().is_none_or(|(_, next)| next.is_whitespace())
+            }
+            _ => false,
+        };
+        if ends {
+            spans.push((from, until));
+            from = until;
+        }
+    }
+    spans.push((from, end));
+
+    spans
+        .into_iter()
+        .filter_map(|(from, until)| {
+            let text = &content[from..until];
+            let leading = text.len() - text.trim_start().len();
+            let trimmed = text.trim();
+            if trimmed.is_empty() {
+                return None;
+            }
+            let start = from + leading;
+            let end = start + trimmed.len();
+            let told = &narration[start..end];
+            let words = words(told);
+            Some(Sentence {
+                start,
+                end,
+                tense: sentence_tense(&words),
+                first_person: words
+                    .iter()
+                    .any(|(word, _)| FIRST_PERSON_SINGULAR.contains(&word.as_str())),
+                names_pov: pov_names
+                    .iter()
+                    .any(|name| !find_word_matches(told, name).is_empty()),
+            })
+        })
+        .collect()
+}
+
+/// Lowercased words with any contraction split off, so "I'm" is ("i", "m")
+/// and "wasn't" is ("was", "nt")
+fn words(text: &str) -> Vec<(String, &'static str)> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '\u{2019}'))
+        .map(|word| word.trim_matches(|c| c == '\'' || c == '\u{2019}'))
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let word = word.replace('\u{2019}', "'").to_lowercase();
+            if let Some(base) = word.strip_suffix("n't") {
+                let base = match base {
+                    "ca" => "can",
+                    "wo" => "will",
+                    "do" | "does" | "did" | "is" | "are" | "was" | "were" | "has" | "have"
+                    | "had" => base,
+                    _ => return (word, ""),
+                };
+                return (base.to_string(), "nt");
+            }
+            match word.split_once('\'') {
+                Some((base, "s")) => (base.to_string(), "s"),
+                Some((base, "m")) => (base.to_string(), "m"),
+                Some((base, "re")) => (base.to_string(), "re"),
+                Some((base, "ve")) => (base.to_string(), "ve"),
+                Some((base, "d")) => (base.to_string(), "d"),
+                Some((base, "ll")) => (base.to_string(), "ll"),
+                _ => (word, ""),
+            }
+        })
+        .collect()
+}
+
+/// The tense a sentence's verbs point to, when they agree
+fn sentence_tense(words: &[(String, &'static str)]) -> Option<NarrativeTense> {
+    let mut past = 0;
+    let mut present = 0;
+    let mut previous = "";
+    for (word, suffix) in words {
+        let word = word.as_str();
+        match *suffix {
+            "m" | "re" | "ve" => present += 1,
+            "s" if matches!(
+                word,
+                "he" | "she" | "it" | "that" | "there" | "what" | "who"
+            ) =>
+            {
+                present += 1
+            }
+            _ => {}
+        }
+        if !AUXILIARIES.contains(&previous) {
+            if PAST_WORDS.contains(&word) {
+                past += 1;
+            } else if PRESENT_WORDS.contains(&word) {
+                present += 1;
+            } else if word.len() >= 5
+                && word.ends_with("ed")
+                && !word.ends_with("eed")
+                && !ED_EXCEPTIONS.contains(&word)
+            {
+                past += 1;
+            } else if present_form(previous, word) {
+                present += 1;
+            }
+        }
+        previous = if suffix.is_empty() { word } else { "" };
+    }
+    match past.cmp(&present) {
+        std::cmp::Ordering::Greater => Some(NarrativeTense::Past),
+        std::cmp::Ordering::Less => Some(NarrativeTense::Present),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+/// A regular present form: "she walks", or "I walk" for a common verb
+fn present_form(previous: &str, word: &str) -> bool {
+    match previous {
+        "he" | "she" | "it" => {
+            word.len() >= 3
+                && word.ends_with('s')
+                && !word.ends_with("ss")
+                && !word.ends_with("us")
+                && !S_EXCEPTIONS.contains(&word)
+        }
+        "i" | "we" | "you" | "they" => PRESENT_BASE.contains(&word),
+        _ => false,
+    }
+}
+
+/// The person a scene's narration is in, from its pronoun counts
+fn detect_person(text: &str) -> Option<NarrativePerson> {
+    let (mut first, mut second, mut third) = (0, 0, 0);
+    for (word, _) in words(text) {
+        if FIRST_PERSON.contains(&word.as_str()) {
+            first += 1;
+        } else if SECOND_PERSON.contains(&word.as_str()) {
+            second += 1;
+        } else if THIRD_PERSON.contains(&word.as_str()) {
+            third += 1;
+        }
+    }
+    if first >= second && first >= MIN_PRONOUNS && first * 2 >= third {
+        Some(NarrativePerson::First)
+    } else if second > first && second >= MIN_PRONOUNS && second * 2 >= third {
+        Some(NarrativePerson::Second)
+    } else if third >= MIN_PRONOUNS {
+        Some(NarrativePerson::Third)
+    } else {
+        None
+    }
+}
+
+/// The tense most of a scene's sentences are in, when it clearly leads
+fn detect_tense(sentences: &[Sentence]) -> Option<NarrativeTense> {
+    let past = sentences
+        .iter()
+        .filter(|s| s.tense == Some(NarrativeTense::Past))
+        .count();
+    let present = sentences
+        .iter()
+        .filter(|s| s.tense == Some(NarrativeTense::Present))
+        .count();
+    if past + present < MIN_TENSED_SENTENCES {
+        None
+    } else if past > present * 2 {
+        Some(NarrativeTense::Past)
+    } else if present > past * 2 {
+        Some(NarrativeTense::Present)
+    } else {
+        None
+    }
+}
+
+/// Indexes of the sentences that begin each run of flagged sentences
+fn run_starts(sentences: &[Sentence], flagged: impl Fn(&Sentence) -> bool) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut in_run = false;
+    for (index, sentence) in sentences.iter().enumerate() {
+        let hit = flagged(sentence);
+        if hit && !in_run {
+            starts.push(index);
+        }
+        in_run = hit;
+    }
+    starts
+}
+
+/// Runs of at least `SWITCH_RUN` tensed sentences in another tense, as the
+/// index of the first, the tense and the run length; sentences with no
+/// clear tense neither extend nor break a run
+fn tense_switches(
+    sentences: &[Sentence],
+    expected: NarrativeTense,
+) -> Vec<(usize, NarrativeTense, usize)> {
+    let mut switches = Vec::new();
+    let mut run: Option<(usize, NarrativeTense, usize)> = None;
+    for (index, sentence) in sentences.iter().enumerate() {
+        let Some(tense) = sentence.tense else {
+            continue;
+        };
+        if tense == expected {
+            switches.extend(run.take().filter(|(_, _, length)| *length >= SWITCH_RUN));
+        } else {
+            match run.as_mut() {
+                Some((_, _, length)) => *length += 1,
+                None => run = Some((index, tense, 1)),
+            }
+        }
+    }
+    switches.extend(run.filter(|(_, _, length)| *length >= SWITCH_RUN));
+    switches
+}
+
+fn excerpt(text: &str) -> String {
+    if text.chars().count() <= EXCERPT_CHARS {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(EXCERPT_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(content: &str, declared: SceneVoice) -> VoiceDocument {
+        VoiceDocument {
+            id: Uuid::new_v4(),
+            title: "Chapter 1".to_string(),
+            content: content.to_string(),
+            declared,
+        }
+    }
+
+    #[test]
+    fn test_detects_voice_and_switches() {
+        let content = "Mara walked to the gate. She looked back at the house. \
+            \"I am not going,\" she said. She waited for an answer. The wind picked up. \
+            She turns to the road. She knows he is gone. It creaks behind her. \
+            Mara pulled her coat tighter. I could not stop shaking. She started walking.\n\
+            * * *\n\
+            I step off the train. I look for my sister. I find her by the doors. \
+            I wave. My hands are cold.";
+        let declared = SceneVoice {
+            pov: Some("Mara".to_string()),
+            person: Some(NarrativePerson::Third),
+            tense: Some(NarrativeTense::Past),
+        };
+        let (scenes, issues) = check_voice(&[document(content, declared)], &[]);
+
+        assert_eq!(scenes.len(), 2);
+        assert_eq!(scenes[0].person, Some(NarrativePerson::Third));
+        assert_eq!(scenes[0].tense, Some(NarrativeTense::Past));
+        assert_eq!(scenes[0].pov.as_deref(), Some("Mara"));
+        assert_eq!(scenes[1].person, Some(NarrativePerson::First));
+        assert_eq!(scenes[1].tense, Some(NarrativeTense::Present));
+
+        let kinds: Vec<(usize, VoiceIssueKind)> =
+            issues.iter().map(|i| (i.scene, i.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (1, VoiceIssueKind::TenseSwitch),
+                (1, VoiceIssueKind::PersonSwitch),
+                (2, VoiceIssueKind::PersonMismatch),
+                (2, VoiceIssueKind::TenseMismatch),
+            ]
+        );
+        assert_eq!(issues[0].excerpt, "She turns to the road.");
+        assert_eq!(issues[0].sentence, 6);
+        assert_eq!(issues[1].excerpt, "I could not stop shaking.");
+        assert_eq!(&content[issues[1].start..issues[1].end], issues[1].excerpt);
+    }
+}
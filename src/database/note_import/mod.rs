@@ -0,0 +1,165 @@
+//! Note Import Service
+//!
+//! Brings notes from other apps into a project as research documents.
+//! Each app has its own importer: Obsidian vaults in `obsidian`, Notion
+//! exports in `notion`. Whatever can't be brought over as it was is listed
+//! in the report, and notes imported before are left alone, so the same
+//! source can be imported again after new notes are added.
+
+mod notion;
+mod obsidian;
+
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::models::document_structure::CREATE_BINDER_ORDER_TABLE_SQL;
+use crate::database::models::note_import::*;
+use crate::database::parse::parse_uuid;
+use crate::database::{AttachmentService, DatabaseError, DatabaseResult, EnhancedDatabaseService};
+
+/// Service for importing notes from other apps
+#[derive(Debug)]
+pub struct NoteImportService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    attachments: Arc<AttachmentService>,
+}
+
+impl NoteImportService {
+    /// Create a new note import service attaching files through `attachments`
+    pub fn new(
+        db_service: Arc<RwLock<EnhancedDatabaseService>>,
+        attachments: Arc<AttachmentService>,
+    ) -> Self {
+        Self {
+            db_service,
+            attachments,
+        }
+    }
+
+    /// Initialize the document links and binder order tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        for sql in [
+            CREATE_DOCUMENT_LINKS_TABLE_SQL,
+            CREATE_BINDER_ORDER_TABLE_SQL,
+        ] {
+            sqlx::query(sql).execute(&db.pool).await.map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create note import tables: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Documents linking to a document
+    pub async fn backlinks(&self, document_id: Uuid) -> DatabaseResult<Vec<DocumentBacklink>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<(String, String, String)> = sqlx::query_as(
+            "SELECT l.source_id, d.title, l.link_text FROM document_links l
+             JOIN documents d ON d.id = l.source_id
+             WHERE l.target_id = ?1 AND d.is_active = 1
+             ORDER BY d.title, l.link_text",
+        )
+        .bind(document_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to list backlinks: {}", e)))?;
+        rows.into_iter()
+            .map(|(source_id, title, link_text)| {
+                Ok(DocumentBacklink {
+                    document_id: parse_uuid(&source_id)?,
+                    title,
+                    link_text,
+                })
+            })
+            .collect()
+    }
+
+    /// Documents of a project imported before from `source`, by the key
+    /// `key` reads from where each came from
+    async fn imported(
+        &self,
+        project_id: Uuid,
+        source: NoteSource,
+        key: impl Fn(&Value) -> Option<String>,
+    ) -> DatabaseResult<HashMap<String, Uuid>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<(String, Option<String>)> = sqlx::query_as(
+            "SELECT id, metadata FROM documents WHERE project_id = ?1 AND is_active = 1",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+        rows.into_iter()
+            .filter_map(|(id, metadata)| {
+                let metadata: Value = serde_json::from_str(metadata.as_deref()?).ok()?;
+                let from = metadata.get(IMPORTED_FROM_KEY)?;
+                if from.get("app")?.as_str()? != source.as_str() {
+                    return None;
+                }
+                Some((key(from)?, id))
+            })
+            .map(|(key, id)| Ok((key, parse_uuid(&id)?)))
+            .collect()
+    }
+}
+
+/// Create an imported document with its metadata
+async fn create_document(
+    db: &EnhancedDatabaseService,
+    project_id: Uuid,
+    document_id: Uuid,
+    title: &str,
+    content: String,
+    metadata: Map<String, Value>,
+) -> DatabaseResult<()> {
+    db.create_document(
+        document_id.to_string(),
+        project_id.to_string(),
+        title.to_string(),
+        content,
+    )
+    .await?;
+    sqlx::query("UPDATE documents SET metadata = ?1 WHERE id = ?2")
+        .bind(Value::Object(metadata).to_string())
+        .bind(document_id.to_string())
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            DatabaseError::Service(format!("Failed to update document metadata: {}", e))
+        })?;
+    Ok(())
+}
+
+/// Keep links between imported documents, as source, target and link text
+async fn save_links(
+    db: &EnhancedDatabaseService,
+    project_id: Uuid,
+    links: &[(Uuid, Uuid, String)],
+) -> DatabaseResult<()> {
+    for (source_id, target_id, link_text) in links {
+        sqlx::query(
+            "INSERT OR IGNORE INTO document_links (project_id, source_id, target_id, link_text)
+             VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(project_id.to_string())
+        .bind(source_id.to_string())
+        .bind(target_id.to_string())
+        .bind(link_text)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to save link: {}", e)))?;
+    }
+    Ok(())
+}
+
+fn issue(path: &str, kind: ImportIssueKind, detail: &str) -> ImportIssue {
+    ImportIssue {
+        path: path.to_string(),
+        kind,
+        detail: detail.to_string(),
+    }
+}
@@ -0,0 +1,715 @@
+//! Notion Export Import
+//!
+//! A Notion export is read from its zip. Each top-level page becomes a
+//! project, or everything goes into the project given; pages under it
+//! become documents in binder order, depth first, and remember the page
+//! they sat under. Databases whose columns fit codex entries become
+//! entries, filled in from their rows' pages; the rows of any other
+//! database are imported as pages.
+
+use chrono::Utc;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
+use std::ops::Range;
+use std::path::Path;
+use uuid::Uuid;
+
+use super::{create_document, issue, save_links, NoteImportService};
+use crate::database::beta_reader_service::parse_csv;
+use crate::database::codex_transfer_service::import_entries;
+use crate::database::models::attachment::AttachmentOwner;
+use crate::database::models::codex_transfer::{
+    maps_to_entries, parse_entry_type, read_records, CodexFormat, CodexImportOptions, ParsedImport,
+};
+use crate::database::models::document_structure::INSERT_BINDER_POSITION_SQL;
+use crate::database::models::note_import::*;
+use crate::database::models::notion::{
+    file_kind, find_references, node_key, read_html_page, read_markdown_page, split_name,
+    NotionFileKind, NotionPage,
+};
+use crate::database::{DatabaseError, DatabaseResult};
+use crate::deep_link::DeepLink;
+
+/// A page or database of a Notion export
+struct NotionNode {
+    path: String,
+    title: String,
+    page_id: Option<String>,
+    content: NotionContent,
+}
+
+enum NotionContent {
+    Page(NotionPage),
+    /// The database's CSV records, header first
+    Database(Vec<Vec<String>>),
+}
+
+impl NoteImportService {
+    /// Import the Notion export zip at `export` as projects, documents and
+    /// codex entries
+    pub async fn import_notion(
+        &self,
+        export: &Path,
+        options: &NotionImportOptions,
+    ) -> DatabaseResult<NoteImportReport> {
+        if !export.is_file() {
+            return Err(DatabaseError::ValidationError(format!(
+                "Not a file: {}",
+                export.display()
+            )));
+        }
+        let file = export.to_path_buf();
+        let entries = tokio::task::spawn_blocking(move || read_export(&file))
+            .await
+            .map_err(|e| DatabaseError::Service(e.to_string()))?
+            .map_err(|e| DatabaseError::Service(format!("Failed to read export: {}", e)))?;
+        if let Some(project_id) = options.project_id {
+            let db = self.db_service.read().await;
+            let live: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM projects WHERE id = ?1 AND deleted_at IS NULL",
+            )
+            .bind(project_id.to_string())
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load project: {}", e)))?;
+            if live == 0 {
+                return Err(DatabaseError::RecordNotFound {
+                    entity: "project".to_string(),
+                    id: project_id.to_string(),
+                });
+            }
+        }
+
+        let mut report = NoteImportReport {
+            source: NoteSource::Notion,
+            dry_run: options.dry_run,
+            projects: Vec::new(),
+            notes: Vec::new(),
+            databases: Vec::new(),
+            attachments_imported: 0,
+            links_kept: 0,
+            tags: Vec::new(),
+            issues: Vec::new(),
+        };
+        let imported = match options.project_id {
+            Some(project_id) => {
+                self.imported(project_id, NoteSource::Notion, |from| {
+                    from.get("page_id")?.as_str().map(str::to_string)
+                })
+                .await?
+            }
+            None => HashMap::new(),
+        };
+
+        // Databases first, so their rows' pages know the column names
+        let mut nodes: Vec<NotionNode> = Vec::new();
+        let mut keys: HashMap<String, usize> = HashMap::new();
+        let mut pages = Vec::new();
+        let mut files = Vec::new();
+        for (path, bytes) in entries {
+            match file_kind(&path) {
+                NotionFileKind::Page { html } => pages.push((path, bytes, html)),
+                NotionFileKind::File => files.push((path, bytes)),
+                NotionFileKind::Database => {
+                    let Ok(text) = String::from_utf8(bytes) else {
+                        report.issues.push(issue(
+                            &path,
+                            ImportIssueKind::Unreadable,
+                            "Not UTF-8 text",
+                        ));
+                        continue;
+                    };
+                    let records = parse_csv(text.trim_start_matches('\u{feff}'));
+                    let key = node_key(&path).to_string();
+                    match keys.get(&key) {
+                        // Every row rather than those of a view
+                        Some(&i) if path.ends_with("_all.csv") => {
+                            nodes[i].path = path;
+                            nodes[i].content = NotionContent::Database(records);
+                        }
+                        Some(_) => {}
+                        None => {
+                            let (title, page_id) = split_name(&key);
+                            keys.insert(key, nodes.len());
+                            nodes.push(NotionNode {
+                                path,
+                                title,
+                                page_id,
+                                content: NotionContent::Database(records),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        for (path, bytes, html) in pages {
+            let Ok(text) = String::from_utf8(bytes) else {
+                report
+                    .issues
+                    .push(issue(&path, ImportIssueKind::Unreadable, "Not UTF-8 text"));
+                continue;
+            };
+            let key = node_key(&path).to_string();
+            let (title, page_id) = split_name(&key);
+            let page = if html {
+                read_html_page(&text, &title)
+            } else {
+                let folder = key.rsplit_once('/').map_or("", |(folder, _)| folder);
+                let columns = match keys.get(folder).map(|&i| &nodes[i].content) {
+                    Some(NotionContent::Database(records)) => {
+                        records.first().cloned().unwrap_or_default()
+                    }
+                    _ => Vec::new(),
+                };
+                read_markdown_page(&text, &title, &columns)
+            };
+            keys.insert(key, nodes.len());
+            nodes.push(NotionNode {
+                path,
+                title: page.title.clone(),
+                page_id,
+                content: NotionContent::Page(page),
+            });
+        }
+        let files_by_path: HashMap<String, usize> = files
+            .iter()
+            .enumerate()
+            .map(|(i, (path, _))| (path.to_lowercase(), i))
+            .collect();
+
+        // A page's sub-pages are in the folder named as it is; folders
+        // with no page of their own are passed through
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+        let mut roots = Vec::new();
+        for (i, node) in nodes.iter().enumerate() {
+            let mut folder = node_key(&node.path);
+            let parent = loop {
+                match folder.rsplit_once('/') {
+                    Some((up, _)) => {
+                        folder = up;
+                        if let Some(&parent) = keys.get(folder) {
+                            break Some(parent);
+                        }
+                    }
+                    None => break None,
+                }
+            };
+            match parent {
+                Some(parent) => children[parent].push(i),
+                None => roots.push(i),
+            }
+        }
+        let by_title = |a: &usize, b: &usize| {
+            let (a, b) = (&nodes[*a], &nodes[*b]);
+            a.title
+                .to_lowercase()
+                .cmp(&b.title.to_lowercase())
+                .then(a.path.cmp(&b.path))
+        };
+        roots.sort_by(by_title);
+        for list in &mut children {
+            list.sort_by(by_title);
+        }
+
+        // Databases that fit codex entries, filled in from their rows' pages
+        let mut codex: HashMap<usize, (CodexImportOptions, ParsedImport)> = HashMap::new();
+        let mut row_pages = HashSet::new();
+        for (i, node) in nodes.iter().enumerate() {
+            let NotionContent::Database(records) = &node.content else {
+                continue;
+            };
+            let codex_options = CodexImportOptions {
+                format: CodexFormat::Csv,
+                default_type: parse_entry_type(&node.title),
+                duplicates: options.duplicates,
+                dry_run: options.dry_run,
+                ..Default::default()
+            };
+            let header = records.first().map(Vec::as_slice).unwrap_or_default();
+            if !maps_to_entries(header, &codex_options) {
+                report.issues.push(issue(
+                    &node.path,
+                    ImportIssueKind::UnmappedDatabase,
+                    "No title and type columns; rows imported as pages",
+                ));
+                continue;
+            }
+            let mut parsed = read_records(records, &codex_options);
+            for &child in &children[i] {
+                let NotionContent::Page(page) = &nodes[child].content else {
+                    continue;
+                };
+                let entry = parsed
+                    .entries
+                    .iter_mut()
+                    .find(|e| e.title.trim().eq_ignore_ascii_case(page.title.trim()));
+                if let Some(entry) = entry {
+                    if entry.content.is_empty() {
+                        entry.content = page.body.trim().to_string();
+                    }
+                    row_pages.insert(child);
+                }
+            }
+            codex.insert(i, (codex_options, parsed));
+        }
+
+        // Projects, then documents depth first in title order
+        let now = Utc::now().to_rfc3339();
+        let mut project_of: Vec<Option<Uuid>> = vec![None; nodes.len()];
+        let mut documents: HashMap<usize, (Uuid, bool)> = HashMap::new();
+        let mut order = Vec::new();
+        for &root in &roots {
+            let project_id = match options.project_id {
+                Some(project_id) => project_id,
+                None => {
+                    let project_id = Uuid::new_v4();
+                    if !options.dry_run {
+                        let db = self.db_service.read().await;
+                        sqlx::query(
+                            "INSERT INTO projects (id, name, description, created_at, updated_at, is_archived, is_active, settings)
+                             VALUES (?1, ?2, NULL, ?3, ?3, 0, 0, NULL)",
+                        )
+                        .bind(project_id.to_string())
+                        .bind(&nodes[root].title)
+                        .bind(&now)
+                        .execute(&db.pool)
+                        .await
+                        .map_err(|e| {
+                            DatabaseError::Service(format!("Failed to create project: {}", e))
+                        })?;
+                    }
+                    report.projects.push(ImportedProject {
+                        path: nodes[root].path.clone(),
+                        project_id,
+                        name: nodes[root].title.clone(),
+                    });
+                    project_id
+                }
+            };
+            let mut stack = vec![(root, None)];
+            while let Some((i, parent)) = stack.pop() {
+                project_of[i] = Some(project_id);
+                let is_document = match &nodes[i].content {
+                    NotionContent::Page(page) => {
+                        !row_pages.contains(&i)
+                            && (i != root
+                                || options.project_id.is_some()
+                                || !page.body.trim().is_empty())
+                    }
+                    NotionContent::Database(_) => false,
+                };
+                let mut parent = parent;
+                if is_document {
+                    let existing = nodes[i]
+                        .page_id
+                        .as_ref()
+                        .and_then(|page_id| imported.get(page_id));
+                    let document_id = existing.copied().unwrap_or_else(Uuid::new_v4);
+                    documents.insert(i, (document_id, existing.is_some()));
+                    parent = Some(document_id);
+                }
+                order.push((i, project_id, parent));
+                stack.extend(children[i].iter().rev().map(|&child| (child, parent)));
+            }
+        }
+
+        let mut positions: HashMap<Uuid, i64> = HashMap::new();
+        let mut used_files = HashSet::new();
+        let mut document_links: BTreeMap<Uuid, Vec<(Uuid, Uuid, String)>> = BTreeMap::new();
+        for &(i, project_id, parent) in &order {
+            let node = &nodes[i];
+            if let Some((codex_options, parsed)) = codex.remove(&i) {
+                let mut imported_database = ImportedDatabase {
+                    path: node.path.clone(),
+                    project_id,
+                    title: node.title.clone(),
+                    default_type: codex_options.default_type,
+                    imported: parsed.entries.len(),
+                    updated: 0,
+                    skipped: 0,
+                };
+                // A project made on a dry run isn't there to compare with
+                let errors = if options.dry_run && options.project_id.is_none() {
+                    parsed.errors
+                } else {
+                    let db = self.db_service.read().await;
+                    let result = import_entries(&db, project_id, parsed, &codex_options).await?;
+                    imported_database.imported = result.imported_count;
+                    imported_database.updated = result.updated_count;
+                    imported_database.skipped = result.skipped_count;
+                    result.errors
+                };
+                for error in errors {
+                    report
+                        .issues
+                        .push(issue(&node.path, ImportIssueKind::CodexRow, &error));
+                }
+                report.databases.push(imported_database);
+                continue;
+            }
+            let (NotionContent::Page(page), Some(&(document_id, existing))) =
+                (&node.content, documents.get(&i))
+            else {
+                continue;
+            };
+            let folder = node.path.rsplit_once('/').map_or("", |(folder, _)| folder);
+            let references = find_references(&page.body, page.html);
+            if existing {
+                used_files.extend(references.iter().filter_map(|reference| {
+                    files_by_path
+                        .get(&join_relative(folder, &reference.target).to_lowercase())
+                        .copied()
+                }));
+                report.issues.push(issue(
+                    &node.path,
+                    ImportIssueKind::AlreadyImported,
+                    &format!("Already imported as document {}", document_id),
+                ));
+                continue;
+            }
+
+            let mut replacements = Vec::new();
+            let mut linked = HashSet::new();
+            let mut attached: HashMap<usize, Uuid> = HashMap::new();
+            for reference in references {
+                let target = join_relative(folder, &reference.target);
+                let target_node = match file_kind(&target) {
+                    NotionFileKind::File => None,
+                    _ => keys.get(node_key(&target)).copied(),
+                };
+                let replacement = if let Some(j) = target_node {
+                    match (documents.get(&j), project_of[j]) {
+                        (Some(&(target_id, _)), _) => {
+                            if target_id != document_id
+                                && project_of[j] == Some(project_id)
+                                && linked.insert(target_id)
+                            {
+                                document_links.entry(project_id).or_default().push((
+                                    document_id,
+                                    target_id,
+                                    nodes[j].title.clone(),
+                                ));
+                            }
+                            DeepLink::OpenDocument {
+                                document_id: target_id.to_string(),
+                            }
+                            .to_url()
+                        }
+                        (None, Some(target_project)) => DeepLink::OpenProject {
+                            project_id: target_project.to_string(),
+                        }
+                        .to_url(),
+                        (None, None) => continue,
+                    }
+                } else if let Some(&f) = files_by_path.get(&target.to_lowercase()) {
+                    used_files.insert(f);
+                    let (path, bytes) = &files[f];
+                    let attachment_id = match attached.get(&f) {
+                        Some(id) => *id,
+                        None if options.dry_run => Uuid::nil(),
+                        None => {
+                            let name = path.rsplit('/').next().unwrap_or(path);
+                            match self
+                                .attachments
+                                .attach_bytes(
+                                    project_id,
+                                    AttachmentOwner::Document,
+                                    document_id,
+                                    name,
+                                    bytes,
+                                )
+                                .await
+                            {
+                                Ok(attachment) => attachment.id,
+                                Err(e) => {
+                                    report.issues.push(issue(
+                                        &node.path,
+                                        ImportIssueKind::MissingAttachment,
+                                        &format!("Couldn't import {}: {}", path, e),
+                                    ));
+                                    continue;
+                                }
+                            }
+                        }
+                    };
+                    attached.insert(f, attachment_id);
+                    format!("{}{}", ATTACHMENT_LINK_PREFIX, attachment_id)
+                } else {
+                    let kind = match file_kind(&target) {
+                        NotionFileKind::File => ImportIssueKind::MissingAttachment,
+                        _ => ImportIssueKind::UnresolvedLink,
+                    };
+                    report.issues.push(issue(
+                        &node.path,
+                        kind,
+                        &format!("{} kept as written", reference.target),
+                    ));
+                    continue;
+                };
+                replacements.push((reference.range, replacement));
+            }
+            let content = replace_ranges(&page.body, replacements);
+            report.links_kept += linked.len();
+
+            if !options.dry_run {
+                let mut metadata = Map::new();
+                metadata.insert(
+                    IMPORTED_FROM_KEY.to_string(),
+                    json!({
+                        "app": NoteSource::Notion.as_str(),
+                        "path": node.path,
+                        "page_id": node.page_id,
+                        "parent_id": parent,
+                    }),
+                );
+                if !page.properties.is_empty() {
+                    let properties = page
+                        .properties
+                        .iter()
+                        .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+                        .collect();
+                    metadata.insert(PROPERTIES_KEY.to_string(), Value::Object(properties));
+                }
+
+                let db = self.db_service.read().await;
+                create_document(&db, project_id, document_id, &node.title, content, metadata)
+                    .await?;
+                let position = match positions.get(&project_id) {
+                    Some(position) => *position,
+                    None => sqlx::query_scalar(
+                        "SELECT COALESCE(MAX(position) + 1, 0) FROM binder_order WHERE project_id = ?1",
+                    )
+                    .bind(project_id.to_string())
+                    .fetch_one(&db.pool)
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Service(format!("Failed to load binder order: {}", e))
+                    })?,
+                };
+                sqlx::query(INSERT_BINDER_POSITION_SQL)
+                    .bind(document_id.to_string())
+                    .bind(project_id.to_string())
+                    .bind(position)
+                    .execute(&db.pool)
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Service(format!("Failed to place document: {}", e))
+                    })?;
+                positions.insert(project_id, position + 1);
+            }
+            report.attachments_imported += attached.len();
+            report.notes.push(ImportedNote {
+                path: node.path.clone(),
+                project_id,
+                document_id,
+                title: node.title.clone(),
+                tags: Vec::new(),
+                links: linked.len(),
+                attachments: attached.len(),
+            });
+        }
+
+        if !options.dry_run {
+            let db = self.db_service.read().await;
+            for (project_id, links) in &document_links {
+                save_links(&db, *project_id, links).await?;
+            }
+        }
+        for (i, (path, _)) in files.iter().enumerate() {
+            if !used_files.contains(&i) {
+                report.issues.push(issue(
+                    path,
+                    ImportIssueKind::UnusedAttachment,
+                    "No page embeds or links to it",
+                ));
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// `text` with each range replaced; the ranges don't overlap
+fn replace_ranges(text: &str, mut replacements: Vec<(Range<usize>, String)>) -> String {
+    replacements.sort_by_key(|(range, _)| range.start);
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (range, replacement) in replacements {
+        out.push_str(&text[last..range.start]);
+        out.push_str(&replacement);
+        last = range.end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+/// Every file of a Notion export zip with its path inside it, as `/`
+/// separated paths; a large export comes as zips inside the zip
+fn read_export(file: &Path) -> std::io::Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    let mut archives = vec![std::fs::read(file)?];
+    while let Some(bytes) = archives.pop() {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        for i in 0..archive.len() {
+            let mut entry = archive
+                .by_index(i)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            let path = entry.name().replace('\\', "/");
+            if entry.is_dir() || path.starts_with("__MACOSX/") {
+                continue;
+            }
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            if path.to_lowercase().ends_with(".zip") {
+                archives.push(bytes);
+            } else {
+                files.push((path, bytes));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset_store::AssetStore;
+    use crate::database::{AttachmentService, DatabaseConfig, EnhancedDatabaseService};
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    #[tokio::test]
+    async fn test_import_notion_export() {
+        use std::io::Write;
+        use zip::write::{FileOptions, ZipWriter};
+
+        fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+            let mut writer = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+            for (path, bytes) in files {
+                writer.start_file(*path, FileOptions::default()).unwrap();
+                writer.write_all(bytes).unwrap();
+            }
+            writer.finish().unwrap().into_inner()
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        sqlx::query(crate::database::models::codex::CREATE_CODEX_ENTRIES_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let attachments = Arc::new(AttachmentService::new(
+            db.clone(),
+            AssetStore::new(&dir.path().join("assets")),
+        ));
+        attachments.initialize().await.unwrap();
+        let service = NoteImportService::new(db.clone(), attachments.clone());
+        service.initialize().await.unwrap();
+
+        let root = "Ashfall 11111111111111111111111111111111";
+        let part = zip(&[
+            (
+                &format!("{}.md", root),
+                b"# Ashfall\n\nA novel. Start with [Chapter One](Ashfall%2011111111111111111111111111111111/Chapter%20One%2022222222222222222222222222222222.md).\n",
+            ),
+            (
+                &format!("{}/Chapter One 22222222222222222222222222222222.md", root),
+                b"# Chapter One\n\nThe gull cried.\n\n![map](Chapter%20One%2022222222222222222222222222222222/map.png)\n",
+            ),
+            (
+                &format!("{}/Chapter One 22222222222222222222222222222222/map.png", root),
+                b"\x89PNG",
+            ),
+            (
+                &format!("{}/Cast 33333333333333333333333333333333.csv", root),
+                b"Name,Type,Role\nMara Quell,Character,Pilot\n",
+            ),
+            (
+                &format!("{}/Cast 33333333333333333333333333333333_all.csv", root),
+                b"Name,Type,Role\nMara Quell,Character,Pilot\nHarbor,Place,\nNobody,Weather,\n",
+            ),
+            (
+                &format!("{}/Cast 33333333333333333333333333333333/Mara Quell 44444444444444444444444444444444.md", root),
+                b"# Mara Quell\n\nRole: Pilot\nType: Character\n\nKnows the harbor.\n",
+            ),
+            (
+                &format!("{}/Scraps 55555555555555555555555555555555.csv", root),
+                b"Idea,Mood\nStorm,Dark\n",
+            ),
+            (
+                &format!("{}/Scraps 55555555555555555555555555555555/Storm 66666666666666666666666666666666.md", root),
+                b"# Storm\n\nIdea: Storm\nMood: Dark\n\nA storm hits.\n",
+            ),
+        ]);
+        let export = dir.path().join("Export.zip");
+        std::fs::write(
+            &export,
+            zip(&[("Part-1.zip", &part), ("notes.pdf", b"%PDF")]),
+        )
+        .unwrap();
+
+        let report = service
+            .import_notion(&export, &NotionImportOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(report.projects.len(), 1);
+        let project = report.projects[0].project_id;
+        let titles: Vec<&str> = report.notes.iter().map(|n| n.title.as_str()).collect();
+        assert_eq!(titles, ["Ashfall", "Chapter One", "Storm"]);
+        assert_eq!(report.links_kept, 1);
+        assert_eq!(report.attachments_imported, 1);
+        assert_eq!(report.databases.len(), 1);
+        assert_eq!(report.databases[0].imported, 2);
+        let kinds: Vec<ImportIssueKind> = report.issues.iter().map(|i| i.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ImportIssueKind::UnmappedDatabase,
+                ImportIssueKind::CodexRow,
+                ImportIssueKind::UnusedAttachment,
+            ]
+        );
+
+        let database = db.read().await;
+        let ashfall = database
+            .get_document(report.notes[0].document_id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(ashfall.contains(&format!(
+            "herdingcats://document/{}",
+            report.notes[1].document_id
+        )));
+        let entries: Vec<(String, String)> = sqlx::query_as(
+            "SELECT title, content FROM codex_entries WHERE project_id = ?1 ORDER BY title",
+        )
+        .bind(project.to_string())
+        .fetch_all(&database.pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            entries[1],
+            ("Mara Quell".to_string(), "Knows the harbor.".to_string())
+        );
+        drop(database);
+
+        let again = service
+            .import_notion(
+                &export,
+                &NotionImportOptions {
+                    project_id: Some(project),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(again.notes.is_empty());
+        assert_eq!(again.databases[0].skipped, 2);
+    }
+}
@@ -0,0 +1,596 @@
+//! Obsidian Vault Import
+//!
+//! An Obsidian vault is walked folder by folder: each note becomes a
+//! document, its wiki-links are resolved to the documents made from the
+//! notes they point at and kept as backlinks, its tags go through the tag
+//! map, and the files it embeds are attached to it in the asset store.
+
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use super::{create_document, issue, save_links, NoteImportService};
+use crate::database::models::attachment::AttachmentOwner;
+use crate::database::models::journal::EXCLUDE_FROM_WORD_COUNT_KEY;
+use crate::database::models::note_import::*;
+use crate::database::models::obsidian::{
+    find_links, find_tags, parse_front_matter, plugin_blocks, rewrite, split_front_matter,
+    FrontMatter, NoteLink,
+};
+use crate::database::{DatabaseError, DatabaseResult};
+
+/// A note read from the vault
+struct VaultNote {
+    path: String,
+    title: String,
+    body: String,
+    front_matter: FrontMatter,
+    document_id: Uuid,
+    /// Imported before, so only a link target this time
+    existing: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LinkTarget {
+    Note(usize),
+    File(usize),
+}
+
+/// Notes and files of a vault by the names links use for them
+struct VaultIndex {
+    notes: HashMap<String, usize>,
+    files: HashMap<String, usize>,
+}
+
+impl VaultIndex {
+    fn new(notes: &[VaultNote], files: &[(String, PathBuf)]) -> Self {
+        let mut index = Self {
+            notes: HashMap::new(),
+            files: HashMap::new(),
+        };
+        // Full paths first, so a name never shadows a path
+        for (i, note) in notes.iter().enumerate() {
+            let path = note.path.to_lowercase();
+            index
+                .notes
+                .entry(path.trim_end_matches(".md").to_string())
+                .or_insert(i);
+        }
+        for (i, (path, _)) in files.iter().enumerate() {
+            index.files.entry(path.to_lowercase()).or_insert(i);
+        }
+        for (i, note) in notes.iter().enumerate() {
+            let names = std::iter::once(&note.title).chain(&note.front_matter.aliases);
+            for name in names {
+                index.notes.entry(name.to_lowercase()).or_insert(i);
+            }
+        }
+        for (i, (path, _)) in files.iter().enumerate() {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            index.files.entry(name.to_lowercase()).or_insert(i);
+        }
+        index
+    }
+
+    /// Markdown links are relative to the note; wiki-links name a note or
+    /// file anywhere in the vault
+    fn resolve(&self, link: &NoteLink, from: &str) -> Option<LinkTarget> {
+        let target = link.target.trim_start_matches("./").to_lowercase();
+        let mut keys = Vec::new();
+        if link.markdown {
+            let folder = from.rsplit_once('/').map_or("", |(folder, _)| folder);
+            keys.push(join_relative(&folder.to_lowercase(), &target));
+        }
+        keys.push(target.clone());
+        keys.push(target.rsplit('/').next().unwrap_or(&target).to_string());
+        keys.iter().find_map(|key| {
+            let note = key.strip_suffix(".md").unwrap_or(key);
+            self.notes
+                .get(note)
+                .map(|&i| LinkTarget::Note(i))
+                .or_else(|| self.files.get(key).map(|&i| LinkTarget::File(i)))
+        })
+    }
+}
+
+impl NoteImportService {
+    /// Import the Obsidian vault at `vault` as research documents
+    pub async fn import_obsidian(
+        &self,
+        project_id: Uuid,
+        vault: &Path,
+        options: &NoteImportOptions,
+    ) -> DatabaseResult<NoteImportReport> {
+        if !vault.is_dir() {
+            return Err(DatabaseError::ValidationError(format!(
+                "Not a folder: {}",
+                vault.display()
+            )));
+        }
+        let vault_name = vault
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let root = vault.to_path_buf();
+        let entries = tokio::task::spawn_blocking(move || walk_vault(&root))
+            .await
+            .map_err(|e| DatabaseError::Service(e.to_string()))?
+            .map_err(|e| DatabaseError::Service(format!("Failed to read vault: {}", e)))?;
+
+        let mut report = NoteImportReport {
+            source: NoteSource::Obsidian,
+            dry_run: options.dry_run,
+            projects: Vec::new(),
+            notes: Vec::new(),
+            databases: Vec::new(),
+            attachments_imported: 0,
+            links_kept: 0,
+            tags: Vec::new(),
+            issues: Vec::new(),
+        };
+        let imported = self.imported_paths(project_id, &vault_name).await?;
+        let mut notes = Vec::new();
+        let mut files = Vec::new();
+        for (path, file) in entries {
+            let lower = path.to_lowercase();
+            if lower.ends_with(".canvas") || lower.ends_with(".excalidraw.md") {
+                report.issues.push(issue(
+                    &path,
+                    ImportIssueKind::UnsupportedFile,
+                    "Canvas and drawing files have no document equivalent",
+                ));
+                continue;
+            }
+            if !lower.ends_with(".md") {
+                files.push((path, file));
+                continue;
+            }
+            let text = match tokio::fs::read(&file).await.map(String::from_utf8) {
+                Ok(Ok(text)) => text,
+                Ok(Err(_)) => {
+                    report
+                        .issues
+                        .push(issue(&path, ImportIssueKind::Unreadable, "Not UTF-8 text"));
+                    continue;
+                }
+                Err(e) => {
+                    report
+                        .issues
+                        .push(issue(&path, ImportIssueKind::Unreadable, &e.to_string()));
+                    continue;
+                }
+            };
+            let (yaml, body) = split_front_matter(&text);
+            let title = file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.clone());
+            let existing = imported.get(&path).copied();
+            notes.push(VaultNote {
+                title,
+                body: body.trim_start_matches(['\r', '\n']).to_string(),
+                front_matter: yaml.map(parse_front_matter).unwrap_or_default(),
+                document_id: existing.unwrap_or_else(Uuid::new_v4),
+                existing: existing.is_some(),
+                path,
+            });
+        }
+
+        let index = VaultIndex::new(&notes, &files);
+        let mut used_files = HashSet::new();
+        let mut tag_counts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut document_links = Vec::new();
+        for note in &notes {
+            let links = find_links(&note.body);
+            if note.existing {
+                used_files.extend(links.iter().filter_map(|link| {
+                    match index.resolve(link, &note.path) {
+                        Some(LinkTarget::File(i)) => Some(i),
+                        _ => None,
+                    }
+                }));
+                report.issues.push(issue(
+                    &note.path,
+                    ImportIssueKind::AlreadyImported,
+                    &format!("Already imported as document {}", note.document_id),
+                ));
+                continue;
+            }
+
+            if !note.front_matter.unreadable.is_empty() {
+                report.issues.push(issue(
+                    &note.path,
+                    ImportIssueKind::FrontMatter,
+                    &note.front_matter.unreadable.join("\n"),
+                ));
+            }
+            for block in plugin_blocks(&note.body) {
+                report.issues.push(issue(
+                    &note.path,
+                    ImportIssueKind::PluginBlock,
+                    &format!("{} block kept as text", block),
+                ));
+            }
+
+            let mut seen = HashSet::new();
+            let mut tags: Vec<String> = Vec::new();
+            for tag in note
+                .front_matter
+                .tags
+                .iter()
+                .cloned()
+                .chain(find_tags(&note.body))
+            {
+                let tag = normalize_tag(&tag);
+                if tag.is_empty() || !seen.insert(tag.clone()) {
+                    continue;
+                }
+                *tag_counts.entry(tag.clone()).or_default() += 1;
+                if let Some(mapped) = options.map_tag(&tag) {
+                    if !tags.contains(&mapped) {
+                        tags.push(mapped);
+                    }
+                }
+            }
+
+            // Replacements by where each link starts
+            let mut replacements = HashMap::new();
+            let mut linked = HashSet::new();
+            let mut attached: HashMap<usize, Uuid> = HashMap::new();
+            for link in &links {
+                let replacement = match index.resolve(link, &note.path) {
+                    Some(LinkTarget::Note(i)) => {
+                        let target = &notes[i];
+                        if link.embed {
+                            report.issues.push(issue(
+                                &note.path,
+                                ImportIssueKind::EmbedAsLink,
+                                &format!("Embedded note {} kept as a link", target.title),
+                            ));
+                        }
+                        if target.document_id != note.document_id
+                            && linked.insert((target.document_id, link.text().to_string()))
+                        {
+                            document_links.push((
+                                note.document_id,
+                                target.document_id,
+                                link.text().to_string(),
+                            ));
+                        }
+                        wiki_link(&target.title, link)
+                    }
+                    Some(LinkTarget::File(i)) => {
+                        used_files.insert(i);
+                        let (path, file) = &files[i];
+                        let attachment_id = match attached.get(&i) {
+                            Some(id) => *id,
+                            None if options.dry_run => Uuid::nil(),
+                            None => {
+                                match self
+                                    .attachments
+                                    .attach_file(
+                                        project_id,
+                                        AttachmentOwner::Document,
+                                        note.document_id,
+                                        file,
+                                    )
+                                    .await
+                                {
+                                    Ok(attachment) => attachment.id,
+                                    Err(e) => {
+                                        report.issues.push(issue(
+                                            &note.path,
+                                            ImportIssueKind::MissingAttachment,
+                                            &format!("Couldn't import {}: {}", path, e),
+                                        ));
+                                        continue;
+                                    }
+                                }
+                            }
+                        };
+                        attached.insert(i, attachment_id);
+                        let name = path.rsplit('/').next().unwrap_or(path);
+                        let text = link.alias.as_deref().filter(|a| !a.is_empty());
+                        format!(
+                            "{}[{}]({}{})",
+                            if link.embed { "!" } else { "" },
+                            text.unwrap_or(name),
+                            ATTACHMENT_LINK_PREFIX,
+                            attachment_id
+                        )
+                    }
+                    None => {
+                        let looks_like_file = link
+                            .target
+                            .rsplit_once('.')
+                            .is_some_and(|(_, ext)| !ext.eq_ignore_ascii_case("md"));
+                        let kind = if looks_like_file {
+                            ImportIssueKind::MissingAttachment
+                        } else {
+                            ImportIssueKind::UnresolvedLink
+                        };
+                        report.issues.push(issue(
+                            &note.path,
+                            kind,
+                            &format!("{} kept as text", link.target),
+                        ));
+                        link.text().to_string()
+                    }
+                };
+                replacements.insert(link.range.start, replacement);
+            }
+            let content = rewrite(&note.body, &links, |link| {
+                replacements.get(&link.range.start).cloned()
+            });
+            report.links_kept += linked.len();
+
+            if !options.dry_run {
+                let mut metadata = Map::new();
+                metadata.insert(RESEARCH_KEY.to_string(), Value::Bool(true));
+                metadata.insert(EXCLUDE_FROM_WORD_COUNT_KEY.to_string(), Value::Bool(true));
+                metadata.insert(TAGS_KEY.to_string(), json!(tags));
+                metadata.insert(
+                    IMPORTED_FROM_KEY.to_string(),
+                    json!({
+                        "app": NoteSource::Obsidian.as_str(),
+                        "vault": vault_name,
+                        "path": note.path,
+                    }),
+                );
+                let mut properties = note.front_matter.properties.clone();
+                if !note.front_matter.aliases.is_empty() {
+                    properties.insert("aliases".to_string(), json!(note.front_matter.aliases));
+                }
+                if !properties.is_empty() {
+                    metadata.insert(PROPERTIES_KEY.to_string(), Value::Object(properties));
+                }
+
+                let db = self.db_service.read().await;
+                create_document(
+                    &db,
+                    project_id,
+                    note.document_id,
+                    &note.title,
+                    content,
+                    metadata,
+                )
+                .await?;
+            }
+            report.attachments_imported += attached.len();
+            report.notes.push(ImportedNote {
+                path: note.path.clone(),
+                project_id,
+                document_id: note.document_id,
+                title: note.title.clone(),
+                tags,
+                links: linked.len(),
+                attachments: attached.len(),
+            });
+        }
+
+        if !options.dry_run {
+            let db = self.db_service.read().await;
+            save_links(&db, project_id, &document_links).await?;
+        }
+
+        for (i, (path, _)) in files.iter().enumerate() {
+            if !used_files.contains(&i) {
+                report.issues.push(issue(
+                    path,
+                    ImportIssueKind::UnusedAttachment,
+                    "No note embeds or links to it",
+                ));
+            }
+        }
+        report.tags = tag_counts
+            .into_iter()
+            .map(|(tag, notes)| TagMapping {
+                mapped_to: options.map_tag(&tag),
+                tag,
+                notes,
+            })
+            .collect();
+        Ok(report)
+    }
+
+    /// Documents imported before from the vault, by path
+    async fn imported_paths(
+        &self,
+        project_id: Uuid,
+        vault_name: &str,
+    ) -> DatabaseResult<HashMap<String, Uuid>> {
+        self.imported(project_id, NoteSource::Obsidian, |from| {
+            (from.get("vault")?.as_str()? == vault_name)
+                .then(|| from.get("path")?.as_str().map(str::to_string))
+                .flatten()
+        })
+        .await
+    }
+}
+
+/// A resolved link, written against the title of the note it points at
+fn wiki_link(title: &str, link: &NoteLink) -> String {
+    let mut text = format!("[[{}", title);
+    if let Some(heading) = link.heading.as_deref().filter(|h| !h.is_empty()) {
+        text.push('#');
+        text.push_str(heading);
+    }
+    if let Some(alias) = link
+        .alias
+        .as_deref()
+        .filter(|a| !a.is_empty() && *a != title)
+    {
+        text.push('|');
+        text.push_str(alias);
+    }
+    text.push_str("]]");
+    text
+}
+
+/// Every file in the vault with its path from the vault, skipping hidden
+/// folders such as `.obsidian` and `.trash`
+fn walk_vault(vault: &Path) -> std::io::Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    let mut folders = vec![vault.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        for entry in std::fs::read_dir(&folder)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                folders.push(path);
+            } else if file_type.is_file() {
+                let relative = path
+                    .strip_prefix(vault)
+                    .unwrap_or(&path)
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push((relative, path));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset_store::AssetStore;
+    use crate::database::{AttachmentService, DatabaseConfig, EnhancedDatabaseService};
+    use chrono::Utc;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    #[tokio::test]
+    async fn test_import_obsidian_vault() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query("INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Ashfall', ?2, ?2)")
+            .bind(project.to_string())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let attachments = Arc::new(AttachmentService::new(
+            db.clone(),
+            AssetStore::new(&dir.path().join("assets")),
+        ));
+        attachments.initialize().await.unwrap();
+        let service = NoteImportService::new(db.clone(), attachments.clone());
+        service.initialize().await.unwrap();
+
+        let vault = dir.path().join("Lore");
+        for (path, text) in [
+            (
+                "People/Mira.md",
+                "---\ntags: [people/mira]\naliases: [The Miller]\n---\nLives at [[The Old Mill|the mill]]. #lore\n![[mill.png]]\n",
+            ),
+            (
+                "Places/The Old Mill.md",
+                "Owned by [[The Miller]]. See [[Harbor]].\n```dataview\nLIST\n```\n",
+            ),
+            ("Places/mill.png", "\u{89}PNG"),
+            ("unused.pdf", "%PDF"),
+            ("Board.canvas", "{}"),
+            (".obsidian/app.json", "{}"),
+        ] {
+            let file = vault.join(path);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, text).unwrap();
+        }
+        let options = NoteImportOptions {
+            tag_map: HashMap::from([("people".to_string(), "character".to_string())]),
+            dry_run: false,
+        };
+
+        let dry_run = service
+            .import_obsidian(
+                project,
+                &vault,
+                &NoteImportOptions {
+                    dry_run: true,
+                    ..options.clone()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(dry_run.notes.len(), 2);
+        assert_eq!(
+            service.imported_paths(project, "Lore").await.unwrap().len(),
+            0
+        );
+
+        let report = service
+            .import_obsidian(project, &vault, &options)
+            .await
+            .unwrap();
+        let mira = &report.notes[0];
+        let mill = &report.notes[1];
+        assert_eq!(mira.tags, ["character/mira", "lore"]);
+        assert_eq!((mira.links, mira.attachments), (1, 1));
+        assert_eq!(report.links_kept, 2);
+        let kinds: Vec<(&str, ImportIssueKind)> = report
+            .issues
+            .iter()
+            .map(|i| (i.path.as_str(), i.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("Board.canvas", ImportIssueKind::UnsupportedFile),
+                ("Places/The Old Mill.md", ImportIssueKind::PluginBlock),
+                ("Places/The Old Mill.md", ImportIssueKind::UnresolvedLink),
+                ("unused.pdf", ImportIssueKind::UnusedAttachment),
+            ]
+        );
+
+        let content = db
+            .read()
+            .await
+            .get_document(mira.document_id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        let image = &attachments
+            .list_for_owner(AttachmentOwner::Document, mira.document_id)
+            .await
+            .unwrap()[0];
+        assert_eq!(
+            content,
+            format!(
+                "Lives at [[The Old Mill|the mill]]. #lore\n![mill.png](attachment:{})\n",
+                image.id
+            )
+        );
+        let backlinks = service.backlinks(mill.document_id).await.unwrap();
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(backlinks[0].title, "Mira");
+        assert_eq!(backlinks[0].link_text, "the mill");
+
+        let again = service
+            .import_obsidian(project, &vault, &options)
+            .await
+            .unwrap();
+        assert!(again.notes.is_empty());
+        assert_eq!(
+            again
+                .issues
+                .iter()
+                .filter(|i| i.kind == ImportIssueKind::AlreadyImported)
+                .count(),
+            2
+        );
+    }
+}
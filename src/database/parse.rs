@@ -0,0 +1,36 @@
+//! Row Value Parsing
+//!
+//! IDs and timestamps are stored as text. These turn them back into typed
+//! values, reporting a corrupt value as an error rather than guessing.
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::database::{DatabaseError, DatabaseResult};
+
+/// Parse a stored UUID
+pub fn parse_uuid(value: &str) -> DatabaseResult<Uuid> {
+    Uuid::parse_str(value).map_err(|e| DatabaseError::Service(format!("Invalid UUID: {}", e)))
+}
+
+/// Parse a stored RFC 3339 timestamp
+pub fn parse_time(value: &str) -> DatabaseResult<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| DatabaseError::Service(format!("Invalid timestamp '{}': {}", value, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corrupt_values_are_errors() {
+        let time = parse_time("2026-10-18T09:30:00+02:00").unwrap();
+        assert_eq!(time.to_rfc3339(), "2026-10-18T07:30:00+00:00");
+        assert!(parse_time("tomorrow").is_err());
+        assert!(parse_time("").is_err());
+        assert!(parse_uuid(&Uuid::nil().to_string()).is_ok());
+        assert!(matches!(parse_uuid("42"), Err(DatabaseError::Service(_))));
+    }
+}
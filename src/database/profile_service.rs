@@ -0,0 +1,479 @@
+//! Profile Service
+//!
+//! Manages user profiles on a shared install: per-profile project
+//! visibility, settings and monthly AI budgets, plus switching between
+//! profiles behind the app-lock passphrase.
+
+use chrono::Utc;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{models::profile::*, DatabaseError, DatabaseResult, EnhancedDatabaseService};
+use crate::security::passphrase::{constant_time_eq, hash_passphrase};
+use crate::services::SecurityService;
+use crate::settings::Settings;
+
+type ProfileRow = (
+    String,
+    String,
+    String,
+    String,
+    Option<i64>,
+    i64,
+    String,
+    String,
+);
+
+/// Service for user profiles and the app lock
+#[derive(Debug)]
+pub struct ProfileService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    active_profile: RwLock<Option<UserProfile>>,
+}
+
+impl ProfileService {
+    /// Create a new profile service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self {
+            db_service,
+            active_profile: RwLock::new(None),
+        }
+    }
+
+    /// Initialize profile tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_PROFILE_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create profile tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Create a profile with its role's default settings and budget
+    pub async fn create_profile(
+        &self,
+        name: String,
+        role: ProfileRole,
+    ) -> DatabaseResult<UserProfile> {
+        if name.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Profile name cannot be empty".to_string(),
+            ));
+        }
+
+        let profile = UserProfile::new(name.trim().to_string(), role);
+        let settings = serde_json::to_string(&profile.settings)
+            .map_err(|e| DatabaseError::Service(format!("Failed to serialize settings: {}", e)))?;
+
+        let db = self.db_service.read().await;
+        sqlx::query(INSERT_PROFILE_SQL)
+            .bind(profile.id.to_string())
+            .bind(&profile.name)
+            .bind(profile.role.as_str())
+            .bind(settings)
+            .bind(profile.ai_budget_cents)
+            .bind(profile.ai_spent_cents)
+            .bind(profile.ai_period_start.to_rfc3339())
+            .bind(profile.created_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to create profile: {}", e)))?;
+
+        Ok(profile)
+    }
+
+    /// List all profiles
+    pub async fn list_profiles(&self) -> DatabaseResult<Vec<UserProfile>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<ProfileRow> =
+            sqlx::query_as(&format!("{} ORDER BY name ASC", SELECT_PROFILE_SQL))
+                .fetch_all(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to list profiles: {}", e)))?;
+
+        rows.into_iter().map(profile_from_row).collect()
+    }
+
+    /// Get a profile by ID
+    pub async fn get_profile(&self, profile_id: Uuid) -> DatabaseResult<Option<UserProfile>> {
+        let db = self.db_service.read().await;
+        let row: Option<ProfileRow> =
+            sqlx::query_as(&format!("{} WHERE id = ?1", SELECT_PROFILE_SQL))
+                .bind(profile_id.to_string())
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to get profile: {}", e)))?;
+
+        row.map(profile_from_row).transpose()
+    }
+
+    /// Delete a profile; requires the app-lock passphrase when one is set
+    pub async fn delete_profile(
+        &self,
+        profile_id: Uuid,
+        passphrase: Option<&str>,
+    ) -> DatabaseResult<bool> {
+        self.require_unlock(passphrase).await?;
+
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM user_profiles WHERE id = ?1")
+            .bind(profile_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete profile: {}", e)))?;
+        drop(db);
+
+        let mut active = self.active_profile.write().await;
+        if active.as_ref().is_some_and(|p| p.id == profile_id) {
+            *active = None;
+        }
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Save a profile's own settings
+    pub async fn save_profile_settings(
+        &self,
+        profile_id: Uuid,
+        settings: &Settings,
+    ) -> DatabaseResult<()> {
+        let json = serde_json::to_string(settings)
+            .map_err(|e| DatabaseError::Service(format!("Failed to serialize settings: {}", e)))?;
+
+        let db = self.db_service.read().await;
+        sqlx::query("UPDATE user_profiles SET settings = ?2 WHERE id = ?1")
+            .bind(profile_id.to_string())
+            .bind(json)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save settings: {}", e)))?;
+        drop(db);
+
+        let mut active = self.active_profile.write().await;
+        if let Some(profile) = active.as_mut().filter(|p| p.id == profile_id) {
+            profile.settings = settings.clone();
+        }
+        Ok(())
+    }
+
+    /// Set a profile's monthly AI budget; requires the app-lock passphrase
+    pub async fn set_ai_budget(
+        &self,
+        profile_id: Uuid,
+        budget_cents: Option<i64>,
+        passphrase: Option<&str>,
+    ) -> DatabaseResult<()> {
+        self.require_unlock(passphrase).await?;
+
+        let db = self.db_service.read().await;
+        sqlx::query("UPDATE user_profiles SET ai_budget_cents = ?2 WHERE id = ?1")
+            .bind(profile_id.to_string())
+            .bind(budget_cents)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to set AI budget: {}", e)))?;
+        Ok(())
+    }
+
+    /// Record AI spend against a profile, refusing requests over budget.
+    /// Spend resets at the start of each calendar month.
+    pub async fn record_ai_usage(
+        &self,
+        profile_id: Uuid,
+        cost_cents: i64,
+    ) -> DatabaseResult<UserProfile> {
+        let mut profile =
+            self.get_profile(profile_id)
+                .await?
+                .ok_or_else(|| DatabaseError::RecordNotFound {
+                    entity: "profile".to_string(),
+                    id: profile_id.to_string(),
+                })?;
+
+        let period = month_start(Utc::now());
+        if profile.ai_period_start < period {
+            profile.ai_period_start = period;
+            profile.ai_spent_cents = 0;
+        }
+        if let Some(budget) = profile.ai_budget_cents {
+            if profile.ai_spent_cents + cost_cents > budget {
+                return Err(DatabaseError::ValidationError(format!(
+                    "AI budget for '{}' exhausted ({} of {} cents used this month)",
+                    profile.name, profile.ai_spent_cents, budget
+                )));
+            }
+        }
+        profile.ai_spent_cents += cost_cents;
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPDATE_PROFILE_AI_USAGE_SQL)
+            .bind(profile.id.to_string())
+            .bind(profile.ai_spent_cents)
+            .bind(profile.ai_period_start.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to record AI usage: {}", e)))?;
+
+        Ok(profile)
+    }
+
+    /// Share a project with a profile
+    pub async fn grant_project(&self, profile_id: Uuid, project_id: Uuid) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(
+            "INSERT OR IGNORE INTO profile_projects (profile_id, project_id) VALUES (?1, ?2)",
+        )
+        .bind(profile_id.to_string())
+        .bind(project_id.to_string())
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to grant project: {}", e)))?;
+        Ok(())
+    }
+
+    /// Stop sharing a project with a profile
+    pub async fn revoke_project(&self, profile_id: Uuid, project_id: Uuid) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query("DELETE FROM profile_projects WHERE profile_id = ?1 AND project_id = ?2")
+            .bind(profile_id.to_string())
+            .bind(project_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to revoke project: {}", e)))?;
+        Ok(())
+    }
+
+    /// IDs of the projects a profile may see
+    pub async fn visible_project_ids(&self, profile: &UserProfile) -> DatabaseResult<Vec<Uuid>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<(String,)> = if profile.role.sees_all_projects() {
+            sqlx::query_as("SELECT id FROM projects WHERE is_archived = 0 ORDER BY name ASC")
+                .fetch_all(&db.pool)
+                .await
+        } else {
+            sqlx::query_as(GET_GRANTED_PROJECTS_SQL)
+                .bind(profile.id.to_string())
+                .fetch_all(&db.pool)
+                .await
+        }
+        .map_err(|e| DatabaseError::Service(format!("Failed to get visible projects: {}", e)))?;
+
+        rows.into_iter().map(|(id,)| parse_uuid(&id)).collect()
+    }
+
+    /// Whether the active profile may open a project. With no profiles in
+    /// use every project is visible.
+    pub async fn can_view_project(&self, project_id: Uuid) -> DatabaseResult<bool> {
+        let active = self.active_profile.read().await.clone();
+        match active {
+            None => Ok(true),
+            Some(profile) if profile.role.sees_all_projects() => Ok(true),
+            Some(profile) => Ok(self
+                .visible_project_ids(&profile)
+                .await?
+                .contains(&project_id)),
+        }
+    }
+
+    /// IDs of the projects the active profile is limited to, or `None`
+    /// when it may see every project
+    pub async fn active_visible_project_ids(&self) -> DatabaseResult<Option<Vec<Uuid>>> {
+        let active = self.active_profile.read().await.clone();
+        match active {
+            Some(profile) if !profile.role.sees_all_projects() => {
+                Ok(Some(self.visible_project_ids(&profile).await?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Currently active profile, if profiles are in use
+    pub async fn active_profile(&self) -> Option<UserProfile> {
+        self.active_profile.read().await.clone()
+    }
+
+    /// Switch to another profile, verifying the app-lock passphrase if set
+    pub async fn switch_profile(
+        &self,
+        profile_id: Uuid,
+        passphrase: Option<&str>,
+    ) -> DatabaseResult<UserProfile> {
+        self.require_unlock(passphrase).await?;
+
+        let profile =
+            self.get_profile(profile_id)
+                .await?
+                .ok_or_else(|| DatabaseError::RecordNotFound {
+                    entity: "profile".to_string(),
+                    id: profile_id.to_string(),
+                })?;
+        *self.active_profile.write().await = Some(profile.clone());
+        Ok(profile)
+    }
+
+    /// Whether an app-lock passphrase has been set
+    pub async fn has_app_lock(&self) -> DatabaseResult<bool> {
+        Ok(self.load_app_lock().await?.is_some())
+    }
+
+    /// Set or change the app-lock passphrase. Changing it requires the
+    /// current passphrase.
+    pub async fn set_app_lock(
+        &self,
+        current: Option<&str>,
+        new_passphrase: &str,
+    ) -> DatabaseResult<()> {
+        if new_passphrase.chars().count() < 4 {
+            return Err(DatabaseError::ValidationError(
+                "Passphrase must be at least 4 characters".to_string(),
+            ));
+        }
+        self.require_unlock(current).await?;
+
+        let salt = SecurityService::new().secure_random_string(16);
+        let hash = hash_passphrase(&salt, new_passphrase);
+
+        let db = self.db_service.read().await;
+        sqlx::query(
+            "INSERT INTO app_lock (id, salt, passphrase_hash, updated_at) VALUES (1, ?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET salt = excluded.salt,
+                 passphrase_hash = excluded.passphrase_hash, updated_at = excluded.updated_at",
+        )
+        .bind(salt)
+        .bind(hash)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to set app lock: {}", e)))?;
+        Ok(())
+    }
+
+    /// Fail unless no app lock is set or the passphrase matches it
+    async fn require_unlock(&self, passphrase: Option<&str>) -> DatabaseResult<()> {
+        let Some((salt, expected)) = self.load_app_lock().await? else {
+            return Ok(());
+        };
+        let provided = passphrase.map(|p| hash_passphrase(&salt, p));
+        if provided.is_some_and(|hash| constant_time_eq(hash.as_bytes(), expected.as_bytes())) {
+            Ok(())
+        } else {
+            Err(DatabaseError::ValidationError(
+                "App-lock passphrase is incorrect".to_string(),
+            ))
+        }
+    }
+
+    async fn load_app_lock(&self) -> DatabaseResult<Option<(String, String)>> {
+        let db = self.db_service.read().await;
+        sqlx::query_as("SELECT salt, passphrase_hash FROM app_lock WHERE id = 1")
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to read app lock: {}", e)))
+    }
+}
+
+fn profile_from_row(row: ProfileRow) -> DatabaseResult<UserProfile> {
+    let (id, name, role, settings, ai_budget_cents, ai_spent_cents, period_start, created_at) = row;
+    let id = parse_uuid(&id)?;
+
+    Ok(UserProfile {
+        id,
+        name,
+        role: ProfileRole::parse(&role)
+            .ok_or_else(|| DatabaseError::Service(format!("Unknown profile role: {}", role)))?,
+        settings: serde_json::from_str(&settings).map_err(|e| {
+            DatabaseError::Service(format!("Invalid settings for profile {}: {}", id, e))
+        })?,
+        ai_budget_cents,
+        ai_spent_cents,
+        ai_period_start: parse_time(&period_start)?,
+        created_at: parse_time(&created_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_profiles_limit_projects_and_ai_spend() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let (shared, private) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now().to_rfc3339();
+        for project in [shared, private] {
+            sqlx::query(
+                "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'X', ?2, ?2)",
+            )
+            .bind(project.to_string())
+            .bind(&now)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+        let db = Arc::new(RwLock::new(db));
+        let service = ProfileService::new(db.clone());
+        service.initialize().await.unwrap();
+
+        let kid = service
+            .create_profile("Kiddo".to_string(), ProfileRole::KidSafe)
+            .await
+            .unwrap();
+        service.grant_project(kid.id, shared).await.unwrap();
+        service.set_app_lock(None, "open sesame").await.unwrap();
+        assert!(service.switch_profile(kid.id, Some("wrong")).await.is_err());
+        assert!(service.can_view_project(private).await.unwrap());
+
+        service
+            .switch_profile(kid.id, Some("open sesame"))
+            .await
+            .unwrap();
+        assert!(!service.can_view_project(private).await.unwrap());
+        assert_eq!(
+            service.active_visible_project_ids().await.unwrap(),
+            Some(vec![shared])
+        );
+        assert!(matches!(
+            service.record_ai_usage(kid.id, 1).await,
+            Err(DatabaseError::ValidationError(_))
+        ));
+        assert_eq!(
+            service
+                .record_ai_usage(kid.id, 0)
+                .await
+                .unwrap()
+                .ai_spent_cents,
+            0
+        );
+
+        // Settings that no longer parse are an error, not silently reset
+        sqlx::query("UPDATE user_profiles SET settings = 'not json' WHERE id = ?1")
+            .bind(kid.id.to_string())
+            .execute(&db.read().await.pool)
+            .await
+            .unwrap();
+        assert!(matches!(
+            service.get_profile(kid.id).await,
+            Err(DatabaseError::Service(_))
+        ));
+    }
+
+    #[test]
+    fn test_kid_safe_defaults_disable_ai() {
+        let profile = UserProfile::new("Kiddo".to_string(), ProfileRole::KidSafe);
+        assert_eq!(profile.ai_budget_remaining(), Some(0));
+        assert_eq!(profile.settings.enable_ai_suggestions, Some(false));
+        assert!(!profile.role.sees_all_projects());
+        assert!(UserProfile::new("Me".to_string(), ProfileRole::Writer)
+            .ai_budget_remaining()
+            .is_none());
+    }
+}
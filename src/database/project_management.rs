@@ -4,8 +4,10 @@
 //! project lifecycle management, data isolation, statistics tracking,
 //! and settings management.
 
+use crate::database::ProfileService;
 use crate::error::DatabaseError;
 use crate::error::DatabaseResult;
+use crate::security::confirmation::{ConfirmationGuard, DestructiveOperation};
 use crate::EnhancedDatabaseService;
 use crate::Project;
 use serde_json::Value;
@@ -39,12 +41,30 @@ pub struct ProjectStatistics {
 #[derive(Debug)]
 pub struct ProjectManagementService {
     db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    confirmation_guard: Option<Arc<ConfirmationGuard>>,
+    profiles: Option<Arc<ProfileService>>,
 }
 
 impl ProjectManagementService {
     /// Create a new project management service
     pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
-        Self { db_service }
+        Self {
+            db_service,
+            confirmation_guard: None,
+            profiles: None,
+        }
+    }
+
+    /// Require a confirmation grant before projects can be deleted
+    pub fn with_confirmation_guard(mut self, guard: Arc<ConfirmationGuard>) -> Self {
+        self.confirmation_guard = Some(guard);
+        self
+    }
+
+    /// Only show the active profile the projects it may see
+    pub fn with_profiles(mut self, profiles: Arc<ProfileService>) -> Self {
+        self.profiles = Some(profiles);
+        self
     }
 
     /// Create a new project
@@ -76,6 +96,11 @@ impl ProjectManagementService {
 
     /// Get project by ID
     pub async fn get_project(&self, project_id: &Uuid) -> DatabaseResult<Option<Project>> {
+        if let Some(profiles) = &self.profiles {
+            if !profiles.can_view_project(*project_id).await? {
+                return Ok(None);
+            }
+        }
         let db_service = self.db_service.read().await;
 
         let result: Option<(
@@ -134,8 +159,13 @@ impl ProjectManagementService {
         }
     }
 
-    /// Get all projects
+    /// Get all projects the active profile may see, except those in the
+    /// trash
     pub async fn get_all_projects(&self) -> DatabaseResult<Vec<Project>> {
+        let visible = match &self.profiles {
+            Some(profiles) => profiles.active_visible_project_ids().await?,
+            None => None,
+        };
         let db_service = self.db_service.read().await;
 
         let rows: Vec<(
@@ -149,7 +179,7 @@ impl ProjectManagementService {
             Option<String>,
         )> = sqlx::query_as(
             "SELECT id, name, description, created_at, updated_at, is_archived, is_active, settings
-             FROM projects ORDER BY updated_at DESC",
+             FROM projects WHERE deleted_at IS NULL ORDER BY updated_at DESC",
         )
         .fetch_all(&db_service.pool)
         .await
@@ -191,11 +221,22 @@ impl ProjectManagementService {
             });
         }
 
+        if let Some(visible) = visible {
+            projects.retain(|project| visible.contains(&project.id));
+        }
         Ok(projects)
     }
 
     /// Set active project (only one active project at a time)
     pub async fn set_active_project(&self, project_id: &Uuid) -> DatabaseResult<()> {
+        if let Some(profiles) = &self.profiles {
+            if !profiles.can_view_project(*project_id).await? {
+                return Err(DatabaseError::RecordNotFound {
+                    entity: "project".to_string(),
+                    id: project_id.to_string(),
+                });
+            }
+        }
         let db_service = self.db_service.read().await;
 
         // Deactivate all projects
@@ -231,8 +272,19 @@ impl ProjectManagementService {
         Ok(())
     }
 
-    /// Delete a project with data cleanup
-    pub async fn delete_project(&self, project_id: &Uuid) -> DatabaseResult<()> {
+    /// Delete a project with data cleanup. `confirmation` is the token of a
+    /// grant for `DestructiveOperation::DeleteProject` when a guard is set.
+    pub async fn delete_project(
+        &self,
+        project_id: &Uuid,
+        confirmation: Option<&str>,
+    ) -> DatabaseResult<()> {
+        if let Some(guard) = &self.confirmation_guard {
+            guard
+                .authorize(DestructiveOperation::DeleteProject, confirmation)
+                .map_err(|e| DatabaseError::ValidationError(e.to_string()))?;
+        }
+
         let db_service = self.db_service.read().await;
 
         // The CASCADE foreign key constraints will handle cleanup of:
@@ -380,7 +432,8 @@ impl ProjectManagementService {
 
         // Total words
         let total_words: Option<i64> = sqlx::query_scalar(
-            "SELECT COALESCE(SUM(word_count), 0) FROM documents WHERE project_id = ?1 AND is_active = 1"
+            "SELECT COALESCE(SUM(word_count), 0) FROM documents WHERE project_id = ?1 AND is_active = 1
+               AND json_extract(CASE WHEN json_valid(metadata) THEN metadata END, '$.exclude_from_word_count') IS NOT 1"
         )
         .bind(project_id.to_string())
         .fetch_one(&db_service.pool)
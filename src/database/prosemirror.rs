@@ -0,0 +1,193 @@
+//! ProseMirror Document Utilities
+//!
+//! The editor saves documents as ProseMirror JSON (`document_type = 'json'`).
+//! These helpers read the text a writer typed out of that JSON, and give
+//! access to the text nodes so a service can rewrite words without touching
+//! the node types, marks and attributes around them.
+
+use serde_json::{json, Value};
+use sqlx::SqliteConnection;
+
+use crate::database::{DatabaseError, DatabaseResult};
+
+/// `documents.document_type` of content saved by the editor
+pub const JSON_DOCUMENT_TYPE: &str = "json";
+
+/// The parsed document, if the content is ProseMirror JSON
+pub fn parse(document_type: &str, content: &str) -> Option<Value> {
+    if document_type != JSON_DOCUMENT_TYPE {
+        return None;
+    }
+    serde_json::from_str::<Value>(content)
+        .ok()
+        .filter(Value::is_object)
+}
+
+/// Text of a stored document: ProseMirror JSON is read as its text, one
+/// line per block; anything else is already text
+pub fn document_text(document_type: &str, content: &str) -> String {
+    match parse(document_type, content) {
+        Some(doc) => plain_text(&doc),
+        None => content.to_string(),
+    }
+}
+
+/// Words in a stored document's text
+pub fn word_count(document_type: &str, content: &str) -> usize {
+    document_text(document_type, content)
+        .split_whitespace()
+        .count()
+}
+
+/// `documents.plain_text` for stored content: the text of ProseMirror
+/// JSON, which search indexes in place of the JSON; `None` when the content
+/// is already text
+pub fn search_text(document_type: &str, content: &str) -> Option<String> {
+    parse(document_type, content).map(|doc| plain_text(&doc))
+}
+
+/// Store a document's word count and search text from its content. Run
+/// after any write that changes the content: the
+/// `update_document_word_count` trigger recounts from the raw content, JSON
+/// included, and search indexes `plain_text` when there is one.
+pub async fn store_document_text(
+    conn: &mut SqliteConnection,
+    document_id: &str,
+    document_type: &str,
+    content: &str,
+) -> DatabaseResult<()> {
+    sqlx::query("UPDATE documents SET word_count = ?1, plain_text = ?2 WHERE id = ?3")
+        .bind(word_count(document_type, content) as i64)
+        .bind(search_text(document_type, content))
+        .bind(document_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to store document text: {}", e)))?;
+    Ok(())
+}
+
+/// Text of a ProseMirror node, one line per block
+pub fn plain_text(node: &Value) -> String {
+    let mut node = node.clone();
+    text_nodes_mut(&mut node).0
+}
+
+/// Text of a ProseMirror node, and each text node's text with the byte
+/// offset it starts at in that text
+pub fn text_nodes_mut(node: &mut Value) -> (String, Vec<(usize, &mut String)>) {
+    let mut text = String::new();
+    let mut nodes = Vec::new();
+    walk(node, &mut text, &mut nodes);
+    (text, nodes)
+}
+
+/// Type of a ProseMirror node, e.g. "paragraph" or "heading"
+pub fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(Value::as_str).unwrap_or_default()
+}
+
+/// Top-level nodes of a document
+pub fn blocks(doc: &Value) -> &[Value] {
+    doc.get("content")
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+/// Byte offset each top-level node starts at in the document's text
+pub fn block_starts(doc: &Value) -> Vec<usize> {
+    let mut text = String::new();
+    let mut starts = Vec::new();
+    for block in blocks(doc) {
+        let mut block = block.clone();
+        let before = text.len();
+        let continues = !text.is_empty() && !text.ends_with('\n');
+        walk(&mut block, &mut text, &mut Vec::new());
+        // Skip the line break that separates the node from the one before
+        let separated = continues && text[before..].starts_with('\n');
+        starts.push(before + usize::from(separated));
+    }
+    starts
+}
+
+/// Text as ProseMirror paragraphs, one per line
+pub fn paragraphs(text: &str) -> Vec<Value> {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                json!({ "type": "paragraph" })
+            } else {
+                json!({ "type": "paragraph", "content": [{ "type": "text", "text": line }] })
+            }
+        })
+        .collect()
+}
+
+fn walk<'a>(node: &'a mut Value, text: &mut String, nodes: &mut Vec<(usize, &'a mut String)>) {
+    let kind = node_type(node).to_string();
+    match kind.as_str() {
+        "text" => {
+            if let Some(Value::String(value)) = node.get_mut("text") {
+                let start = text.len();
+                text.push_str(value);
+                nodes.push((start, value));
+            }
+        }
+        "hard_break" | "hardBreak" => text.push('\n'),
+        _ => {
+            if let Some(Value::Array(children)) = node.get_mut("content") {
+                // Every block starts a new line
+                if kind != "doc" && !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                for child in children {
+                    walk(child, text, nodes);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_nodes_and_plain_text() {
+        let mut doc = json!({
+            "type": "doc",
+            "content": [
+                {"type": "heading", "attrs": {"level": 1}, "content": [{"type": "text", "text": "One"}]},
+                {"type": "paragraph", "content": [
+                    {"type": "text", "text": "Mara "},
+                    {"type": "text", "marks": [{"type": "bold"}], "text": "ran"},
+                    {"type": "hard_break"},
+                    {"type": "text", "text": "home."}
+                ]},
+                {"type": "blockquote", "content": [
+                    {"type": "paragraph", "content": [{"type": "text", "text": "\"Wait.\""}]}
+                ]}
+            ]
+        });
+        assert_eq!(plain_text(&doc), "One\nMara ran\nhome.\n\"Wait.\"");
+        assert_eq!(
+            document_text("json", &doc.to_string()),
+            "One\nMara ran\nhome.\n\"Wait.\""
+        );
+        assert_eq!(document_text("markdown", "# One"), "# One");
+        assert_eq!(block_starts(&doc), vec![0, 4, 19]);
+        assert_eq!(
+            plain_text(&json!({ "type": "doc", "content": paragraphs("A\n\nB") })),
+            "A\nB"
+        );
+
+        let (text, nodes) = text_nodes_mut(&mut doc);
+        let starts: Vec<usize> = nodes.iter().map(|(start, _)| *start).collect();
+        assert_eq!(starts, vec![0, 4, 9, 13, 19]);
+        for (start, value) in nodes {
+            assert_eq!(&text[start..start + value.len()], value.as_str());
+            value.make_ascii_uppercase();
+        }
+        assert_eq!(plain_text(&doc), "ONE\nMARA RAN\nHOME.\n\"WAIT.\"");
+        assert_eq!(doc["content"][1]["content"][1]["marks"][0]["type"], "bold");
+    }
+}
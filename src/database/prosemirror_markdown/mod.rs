@@ -0,0 +1,247 @@
+//! ProseMirror and Markdown
+//!
+//! Converts editor documents to Markdown for files other tools edit, and
+//! Markdown back to editor documents. Headings, paragraphs, quotes, lists,
+//! code blocks, rules and the bold, italic, strike, code and link marks
+//! carry over both ways; marks Markdown has no syntax for, such as
+//! underline, are dropped from the Markdown. Node and mark names are the
+//! editor's; the snake_case names some documents use are read as well.
+
+mod parse;
+
+use serde_json::{json, Value};
+
+use crate::database::prosemirror::{self, node_type};
+use parse::parse_blocks;
+
+/// Markdown of a stored document: ProseMirror JSON is converted, anything
+/// else is taken to be Markdown already
+pub fn document_markdown(document_type: &str, content: &str) -> String {
+    match prosemirror::parse(document_type, content) {
+        Some(doc) => to_markdown(&doc),
+        None => content.to_string(),
+    }
+}
+
+/// Markdown of a ProseMirror document, blocks separated by blank lines
+pub fn to_markdown(doc: &Value) -> String {
+    blocks_markdown(prosemirror::blocks(doc))
+}
+
+/// ProseMirror document of Markdown text
+pub fn from_markdown(text: &str) -> Value {
+    let lines: Vec<&str> = text.lines().collect();
+    json!({ "type": "doc", "content": parse_blocks(&lines) })
+}
+
+fn blocks_markdown(blocks: &[Value]) -> String {
+    blocks
+        .iter()
+        .map(block_markdown)
+        .filter(|block| !block.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn block_markdown(node: &Value) -> String {
+    let children = prosemirror::blocks(node);
+    match node_type(node) {
+        "heading" => {
+            let level = node
+                .pointer("/attrs/level")
+                .and_then(Value::as_u64)
+                .unwrap_or(1)
+                .clamp(1, 6) as usize;
+            format!("{} {}", "#".repeat(level), inline_markdown(children))
+        }
+        "blockquote" => blocks_markdown(children)
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    ">".to_string()
+                } else {
+                    format!("> {}", line)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "bulletList" | "bullet_list" => list_markdown(children, None),
+        "orderedList" | "ordered_list" => {
+            let start = node.pointer("/attrs/start").and_then(Value::as_u64);
+            list_markdown(children, Some(start.unwrap_or(1)))
+        }
+        "codeBlock" | "code_block" => {
+            let language = node
+                .pointer("/attrs/language")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            format!("```{}\n{}\n```", language, prosemirror::plain_text(node))
+        }
+        "horizontalRule" | "horizontal_rule" => "---".to_string(),
+        "paragraph" => inline_markdown(children),
+        _ if children.iter().any(|c| node_type(c) == "text") => inline_markdown(children),
+        _ => blocks_markdown(children),
+    }
+}
+
+/// List items one per line, their later lines indented under the marker
+fn list_markdown(items: &[Value], start: Option<u64>) -> String {
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let marker = match start {
+                Some(start) => format!("{}. ", start + i as u64),
+                None => "- ".to_string(),
+            };
+            let indent = " ".repeat(marker.len());
+            let body = prosemirror::blocks(item)
+                .iter()
+                .map(block_markdown)
+                .collect::<Vec<_>>()
+                .join("\n");
+            let mut lines = body.lines();
+            let mut text = format!("{}{}", marker, lines.next().unwrap_or_default());
+            for line in lines {
+                text.push('\n');
+                if !line.is_empty() {
+                    text.push_str(&indent);
+                    text.push_str(line);
+                }
+            }
+            text
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Delimiters a mark opens and closes with, if Markdown has them
+fn delimiters(mark: &Value) -> Option<(String, String)> {
+    let pair = |d: &str| Some((d.to_string(), d.to_string()));
+    match node_type(mark) {
+        "bold" | "strong" => pair("**"),
+        "italic" | "em" => pair("*"),
+        "strike" => pair("~~"),
+        "code" => pair("`"),
+        "link" => {
+            let href = mark.pointer("/attrs/href").and_then(Value::as_str)?;
+            Some(("[".to_string(), format!("]({})", href)))
+        }
+        _ => None,
+    }
+}
+
+/// Inline nodes as Markdown. Marks stay open across text nodes that share
+/// them, so `**a *b***` comes out rather than `**a****b**`.
+fn inline_markdown(nodes: &[Value]) -> String {
+    let mut text = String::new();
+    let mut open: Vec<(String, String)> = Vec::new();
+    let mut line_start = true;
+    for node in nodes {
+        let marks: Vec<(String, String)> = node
+            .get("marks")
+            .and_then(Value::as_array)
+            .map(|marks| marks.iter().filter_map(delimiters).collect())
+            .unwrap_or_default();
+        if let Some(keep) = open.iter().position(|m| !marks.contains(m)) {
+            while open.len() > keep {
+                if let Some((_, close)) = open.pop() {
+                    text.push_str(&close);
+                }
+            }
+        }
+        match node_type(node) {
+            "text" => {
+                for mark in marks {
+                    if !open.contains(&mark) {
+                        text.push_str(&mark.0);
+                        open.push(mark);
+                    }
+                }
+                let value = node.get("text").and_then(Value::as_str).unwrap_or_default();
+                if open.iter().any(|(o, _)| o == "`") {
+                    text.push_str(value);
+                } else {
+                    text.push_str(&escape(value, line_start));
+                }
+                line_start = false;
+            }
+            "hardBreak" | "hard_break" => {
+                text.push_str("\\\n");
+                line_start = true;
+            }
+            _ => {}
+        }
+    }
+    while let Some((_, close)) = open.pop() {
+        text.push_str(&close);
+    }
+    text
+}
+
+/// Text with the characters Markdown would read as syntax escaped; at the
+/// start of a line, block markers too
+fn escape(text: &str, line_start: bool) -> String {
+    let mut escaped = String::new();
+    if line_start {
+        let digits = text.chars().take_while(char::is_ascii_digit).count();
+        let numbered = digits > 0 && text[digits..].starts_with(". ");
+        if numbered {
+            escaped.push_str(&text[..digits]);
+            escaped.push('\\');
+            return escaped + &escape(&text[digits..], false);
+        }
+        if text.starts_with(['#', '>', '-', '+']) {
+            escaped.push('\\');
+        }
+    }
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '~') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_round_trip() {
+        let markdown = "# The Harbor\n\n\
+            Mara *ran* **home *fast***, past [the quay](https://example.com).\\\nThen \\*stopped\\*.\n\n\
+            > Wait.\n\n\
+            - Salt\n- Tar\n  1. Rope\n  2. Nets\n\n\
+            ---\n\n\
+            ```\nlet x = 1;\n```";
+        let doc = from_markdown(markdown);
+        assert_eq!(
+            prosemirror::plain_text(&doc),
+            "The Harbor\nMara ran home fast, past the quay.\nThen *stopped*.\nWait.\nSalt\nTar\nRope\nNets\nlet x = 1;"
+        );
+        let paragraph = &prosemirror::blocks(&doc)[1]["content"];
+        assert_eq!(paragraph[1]["marks"], json!([{ "type": "italic" }]));
+        assert_eq!(
+            paragraph[4]["marks"],
+            json!([{ "type": "bold" }, { "type": "italic" }])
+        );
+        assert_eq!(
+            paragraph[6]["marks"][0]["attrs"]["href"],
+            "https://example.com"
+        );
+        assert_eq!(to_markdown(&doc), markdown);
+    }
+
+    #[test]
+    fn test_plain_text_survives() {
+        let text = "1984. It was cold_ish.\n\n#hashtag and 2*3";
+        let doc = from_markdown(&to_markdown(&json!({
+            "type": "doc",
+            "content": prosemirror::paragraphs("1984. It was cold_ish.\n#hashtag and 2*3"),
+        })));
+        assert_eq!(prosemirror::plain_text(&doc), text.replace("\n\n", "\n"));
+        assert_eq!(document_markdown("markdown", text), text);
+    }
+}
@@ -0,0 +1,269 @@
+//! Markdown parsing: block structure line by line, then the marks in
+//! each block's text
+
+use serde_json::{json, Value};
+
+use crate::database::prosemirror::node_type;
+
+pub(super) fn parse_blocks(lines: &[&str]) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            i += 1;
+        } else if let Some(language) = trimmed.strip_prefix("```") {
+            let end = (i + 1..lines.len())
+                .find(|&j| lines[j].trim().starts_with("```"))
+                .unwrap_or(lines.len());
+            let code = lines[i + 1..end].join("\n");
+            let mut block =
+                json!({ "type": "codeBlock", "attrs": { "language": language.trim() } });
+            if !code.is_empty() {
+                block["content"] = json!([{ "type": "text", "text": code }]);
+            }
+            blocks.push(block);
+            i = end + 1;
+        } else if let Some((level, title)) = heading(trimmed) {
+            blocks.push(json!({
+                "type": "heading",
+                "attrs": { "level": level },
+                "content": parse_inline(title),
+            }));
+            i += 1;
+        } else if is_rule(trimmed) {
+            blocks.push(json!({ "type": "horizontalRule" }));
+            i += 1;
+        } else if trimmed.starts_with('>') {
+            let end = (i..lines.len())
+                .find(|&j| !lines[j].trim_start().starts_with('>'))
+                .unwrap_or(lines.len());
+            let inner: Vec<&str> = lines[i..end]
+                .iter()
+                .map(|l| {
+                    let l = l.trim_start().trim_start_matches('>');
+                    l.strip_prefix(' ').unwrap_or(l)
+                })
+                .collect();
+            blocks.push(json!({ "type": "blockquote", "content": parse_blocks(&inner) }));
+            i = end;
+        } else if let Some((start, _)) = list_marker(line) {
+            let (list, end) = parse_list(lines, i, start);
+            blocks.push(list);
+            i = end;
+        } else {
+            let end = (i + 1..lines.len())
+                .find(|&j| starts_block(lines[j]))
+                .unwrap_or(lines.len());
+            let mut text = String::new();
+            for (n, line) in lines[i..end].iter().enumerate() {
+                // A trailing backslash or two spaces is a line break
+                let escapes = line.len() - line.trim_end_matches('\\').len();
+                let broken = escapes % 2 == 1 || line.ends_with("  ");
+                let line = line.trim();
+                let line = if escapes % 2 == 1 {
+                    &line[..line.len() - 1]
+                } else {
+                    line
+                };
+                text.push_str(line);
+                if n + 1 < end - i {
+                    text.push(if broken { '\n' } else { ' ' });
+                }
+            }
+            blocks.push(paragraph(parse_inline(&text)));
+            i = end;
+        }
+    }
+    blocks
+}
+
+fn paragraph(content: Vec<Value>) -> Value {
+    if content.is_empty() {
+        json!({ "type": "paragraph" })
+    } else {
+        json!({ "type": "paragraph", "content": content })
+    }
+}
+
+/// A list starting at `lines[first]` and the line after it
+fn parse_list(lines: &[&str], first: usize, numbered: Option<u64>) -> (Value, usize) {
+    let mut items = Vec::new();
+    let mut i = first;
+    while let Some((start, width)) = lines.get(i).and_then(|l| list_marker(l)) {
+        if start.is_some() != numbered.is_some() {
+            break;
+        }
+        let indent = lines[i].len() - lines[i].trim_start().len();
+        let mut item = vec![&lines[i][indent + width..]];
+        i += 1;
+        // Later lines of the item are indented under its marker
+        while let Some(line) = lines.get(i) {
+            let blank = line.trim().is_empty();
+            let nested = line.len() - line.trim_start().len() > indent;
+            let continues = lines.get(i + 1).is_some_and(|next| {
+                next.len() - next.trim_start().len() > indent && !next.trim().is_empty()
+            });
+            if !(nested && !blank || blank && continues) {
+                break;
+            }
+            let cut = (indent + width).min(line.len() - line.trim_start().len());
+            item.push(&line[cut..]);
+            i += 1;
+        }
+        let mut content = parse_blocks(&item);
+        if content.is_empty() {
+            content.push(paragraph(Vec::new()));
+        }
+        items.push(json!({ "type": "listItem", "content": content }));
+        while lines.get(i).is_some_and(|l| l.trim().is_empty())
+            && lines.get(i + 1).and_then(|l| list_marker(l)).is_some()
+        {
+            i += 1;
+        }
+    }
+    let list = match numbered {
+        Some(start) => {
+            json!({ "type": "orderedList", "attrs": { "start": start }, "content": items })
+        }
+        None => json!({ "type": "bulletList", "content": items }),
+    };
+    (list, i)
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' ')))
+        .then(|| (level, rest.trim()))
+}
+
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&r| compact.chars().all(|c| c == r))
+}
+
+/// The number a list item starts with, if numbered, and the marker's
+/// width including the space after it
+fn list_marker(line: &str) -> Option<(Option<u64>, usize)> {
+    let line = line.trim_start();
+    if line.starts_with("- ") || line.starts_with("* ") || line.starts_with("+ ") {
+        return (!is_rule(line)).then_some((None, 2));
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let number = line[..digits].parse().ok()?;
+    line[digits..]
+        .starts_with(". ")
+        .then_some((Some(number), digits + 2))
+}
+
+fn starts_block(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty()
+        || trimmed.starts_with("```")
+        || trimmed.starts_with('>')
+        || heading(trimmed).is_some()
+        || is_rule(trimmed)
+        || list_marker(line).is_some()
+}
+
+/// Inline nodes of a paragraph's text, where `\n` is a line break
+fn parse_inline(text: &str) -> Vec<Value> {
+    let mut nodes = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    inline_nodes(&chars, &[], &mut nodes);
+    nodes
+}
+
+fn inline_nodes(chars: &[char], marks: &[Value], nodes: &mut Vec<Value>) {
+    let mut plain = String::new();
+    let flush = |plain: &mut String, nodes: &mut Vec<Value>| {
+        if !plain.is_empty() {
+            let mut node = json!({ "type": "text", "text": std::mem::take(plain) });
+            if !marks.is_empty() {
+                node["marks"] = Value::from(marks.to_vec());
+            }
+            nodes.push(node);
+        }
+    };
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && chars.get(i + 1).is_some_and(char::is_ascii_punctuation) {
+            plain.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
+        if c == '\n' {
+            flush(&mut plain, nodes);
+            nodes.push(json!({ "type": "hardBreak" }));
+            i += 1;
+            continue;
+        }
+        if let Some((inner, end, mark)) = span(chars, i) {
+            flush(&mut plain, nodes);
+            let mut marks = marks.to_vec();
+            marks.push(mark.clone());
+            if node_type(&mark) == "code" {
+                let mut code = json!({ "type": "text", "text": inner.iter().collect::<String>() });
+                code["marks"] = Value::from(marks);
+                nodes.push(code);
+            } else {
+                inline_nodes(inner, &marks, nodes);
+            }
+            i = end;
+            continue;
+        }
+        plain.push(c);
+        i += 1;
+    }
+    flush(&mut plain, nodes);
+}
+
+/// A marked span starting at `chars[i]`: its inner text, the index after
+/// it and the mark
+fn span(chars: &[char], i: usize) -> Option<(&[char], usize, Value)> {
+    let at = |j: usize, s: &str| {
+        s.chars()
+            .enumerate()
+            .all(|(n, c)| chars.get(j + n) == Some(&c))
+    };
+    if chars[i] == '[' {
+        let close = (i + 1..chars.len()).find(|&j| chars[j] == ']' && chars[j - 1] != '\\')?;
+        if chars.get(close + 1) != Some(&'(') {
+            return None;
+        }
+        let end = (close + 2..chars.len()).find(|&j| chars[j] == ')')?;
+        let href: String = chars[close + 2..end].iter().collect();
+        let mark = json!({ "type": "link", "attrs": { "href": href } });
+        return Some((&chars[i + 1..close], end + 1, mark));
+    }
+    let (delimiter, mark) = [
+        ("**", "bold"),
+        ("__", "bold"),
+        ("~~", "strike"),
+        ("*", "italic"),
+        ("_", "italic"),
+        ("`", "code"),
+    ]
+    .into_iter()
+    .find(|(d, _)| at(i, d))?;
+    let len = delimiter.len();
+    let run = delimiter.chars().next()?;
+    // Underscores inside a word are part of it
+    if run == '_' && i > 0 && chars[i - 1].is_alphanumeric() {
+        return None;
+    }
+    // Close at the end of a run, so `***x***` is bold and italic
+    let close = (i + len + 1..chars.len()).find(|&j| {
+        at(j, delimiter)
+            && chars[j - 1] != '\\'
+            && chars.get(j + len) != Some(&run)
+            && (len == 2 || run == '`' || chars[j - 1] != run)
+    })?;
+    Some((&chars[i + len..close], close + len, json!({ "type": mark })))
+}
@@ -0,0 +1,464 @@
+//! Readability Analysis
+//!
+//! Counts what readability scores and style measures are made of, one
+//! paragraph at a time, and combines the counts into a report. Syllables
+//! are estimated from vowel groups, a sentence is passive when a form of
+//! "be" is followed by a past participle, and adverbs are the -ly words
+//! and a few intensifiers. Phrases of three and four words are counted
+//! within clauses and reported when they occur more than once.
+
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+use crate::database::models::readability::*;
+use crate::database::word_usage_service::{is_stop_word, tokenize};
+
+/// Shortest and longest phrase counted, in words
+const MIN_PHRASE_WORDS: usize = 3;
+const MAX_PHRASE_WORDS: usize = 4;
+
+/// Repeated phrases listed in a report
+const REPEATED_PHRASES: usize = 20;
+
+/// Shortest sentence in each length bucket; the last has no upper bound
+const SENTENCE_BUCKETS: &[usize] = &[1, 6, 11, 16, 21, 31, 41];
+
+/// Words ending in a full stop that don't end a sentence
+const ABBREVIATIONS: &[&str] = &["mr", "mrs", "ms", "dr", "st", "jr", "sr", "vs", "prof"];
+
+const BE_FORMS: &[&str] = &[
+    "am", "is", "are", "was", "were", "be", "been", "being", "isn't", "aren't", "wasn't", "weren't",
+];
+
+const IRREGULAR_PARTICIPLES: &[&str] = &[
+    "awoken",
+    "beaten",
+    "begun",
+    "bent",
+    "bitten",
+    "blown",
+    "born",
+    "bought",
+    "bound",
+    "broken",
+    "brought",
+    "built",
+    "caught",
+    "chosen",
+    "done",
+    "drawn",
+    "driven",
+    "eaten",
+    "fallen",
+    "felt",
+    "fought",
+    "found",
+    "forgiven",
+    "forgotten",
+    "frozen",
+    "given",
+    "gone",
+    "grown",
+    "heard",
+    "held",
+    "hidden",
+    "hit",
+    "hung",
+    "hurt",
+    "kept",
+    "known",
+    "laid",
+    "led",
+    "left",
+    "lent",
+    "lost",
+    "made",
+    "meant",
+    "met",
+    "paid",
+    "put",
+    "read",
+    "ridden",
+    "rung",
+    "run",
+    "said",
+    "seen",
+    "sent",
+    "set",
+    "shaken",
+    "shot",
+    "shown",
+    "shut",
+    "sold",
+    "sought",
+    "spent",
+    "spoken",
+    "spun",
+    "stolen",
+    "struck",
+    "sung",
+    "sunk",
+    "swept",
+    "sworn",
+    "taken",
+    "taught",
+    "thrown",
+    "told",
+    "torn",
+    "understood",
+    "woken",
+    "won",
+    "worn",
+    "woven",
+    "written",
+];
+
+/// Words ending in -ed that aren't participles
+const ED_EXCEPTIONS: &[&str] = &["bed", "red", "shed", "sled", "naked", "wicked", "sacred"];
+
+/// Words ending in -ly that aren't adverbs
+const LY_EXCEPTIONS: &[&str] = &[
+    "ally", "apply", "belly", "bully", "curly", "daily", "early", "family", "fly", "friendly",
+    "holy", "hourly", "jelly", "july", "likely", "lily", "lonely", "lovely", "only", "reply",
+    "rely", "silly", "supply", "ugly", "weekly", "wily", "woolly",
+];
+
+/// Adverbs without -ly that weaken prose the way -ly ones do
+const INTENSIFIERS: &[&str] = &["very", "quite", "rather", "somewhat"];
+
+/// Paragraphs of a text: its lines that aren't blank
+pub fn paragraphs(text: &str) -> Vec<&str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Hash identifying a paragraph's text
+pub fn paragraph_hash(paragraph: &str) -> String {
+    format!("{:x}", Sha256::digest(paragraph.as_bytes()))
+}
+
+/// Count one paragraph
+pub fn count_paragraph(paragraph: &str) -> ParagraphCounts {
+    let mut counts = ParagraphCounts {
+        hash: paragraph_hash(paragraph),
+        ..Default::default()
+    };
+    for sentence in sentences(paragraph) {
+        let words = tokenize(sentence);
+        if words.is_empty() {
+            continue;
+        }
+        counts.words += words.len();
+        counts.sentence_lengths.push(words.len());
+        for word in &words {
+            counts.syllables += syllables(word);
+            if is_complex(word) {
+                counts.complex_words += 1;
+            }
+            if is_adverb(word) {
+                counts.adverbs += 1;
+            }
+        }
+        if is_passive(&words) {
+            counts.passive_sentences += 1;
+        }
+    }
+
+    // Phrases do not run across clause punctuation
+    for clause in paragraph.split(['.', '!', '?', ';', ':', ',', '"', '“', '”', '(', ')']) {
+        let words = tokenize(clause);
+        for n in MIN_PHRASE_WORDS..=MAX_PHRASE_WORDS {
+            for window in words.windows(n) {
+                if !is_stop_word(&window[0]) && !is_stop_word(&window[n - 1]) {
+                    *counts.phrases.entry(window.join(" ")).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    counts
+}
+
+/// Sentences of a paragraph, ending at `.`, `!` or `?` and any closing
+/// quotes, but not at a title such as "Mr."
+fn sentences(paragraph: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut from = 0;
+    let mut chars = paragraph.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        if !matches!(c, '.' | '!' | '?') {
+            continue;
+        }
+        let mut until = offset + c.len_utf8();
+        while let Some(&(offset, next)) = chars.peek() {
+            if !matches!(
+                next,
+                '.' | '!' | '?' | '"' | '\'' | '\u{201D}' | '\u{2019}' | ')'
+            ) {
+                break;
+            }
+            until = offset + next.len_utf8();
+            chars.next();
+        }
+        let at_break = chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+        let last_word = paragraph[from..offset]
+            .rsplit(|c: char| !c.is_alphabetic())
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        if at_break && !(c == '.' && ABBREVIATIONS.contains(&last_word.as_str())) {
+            sentences.push(&paragraph[from..until]);
+            from = until;
+        }
+    }
+    if !paragraph[from..].trim().is_empty() {
+        sentences.push(&paragraph[from..]);
+    }
+    sentences
+}
+
+/// Estimated syllables of a lowercase word, from its vowel groups
+pub fn syllables(word: &str) -> usize {
+    let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+    let n = letters.len();
+    if n <= 3 {
+        return 1;
+    }
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+    let mut count: usize = 0;
+    let mut after_vowel = false;
+    for &c in &letters {
+        let vowel = is_vowel(c);
+        if vowel && !after_vowel {
+            count += 1;
+        }
+        after_vowel = vowel;
+    }
+    // A final "e" is silent, except in "-le" after a consonant ("table")
+    let syllabic_le = letters[n - 2] == 'l' && !is_vowel(letters[n - 3]);
+    if letters[n - 1] == 'e' && !syllabic_le {
+        count = count.saturating_sub(1);
+    }
+    // So is the "e" of "-es" and "-ed", except after a sound that needs
+    // it ("wanted", "glasses")
+    if letters[n - 2] == 'e'
+        && matches!(letters[n - 1], 'd' | 's')
+        && !matches!(
+            letters[n - 3],
+            't' | 'd' | 's' | 'x' | 'z' | 'c' | 'g' | 'h'
+        )
+        && !is_vowel(letters[n - 3])
+    {
+        count = count.saturating_sub(1);
+    }
+    count.max(1)
+}
+
+/// Three or more syllables without counting an -es, -ed or -ing ending
+fn is_complex(word: &str) -> bool {
+    let stem = ["ing", "ed", "es"]
+        .iter()
+        .find_map(|suffix| word.strip_suffix(suffix).filter(|stem| stem.len() > 3))
+        .unwrap_or(word);
+    syllables(stem) >= 3
+}
+
+fn is_adverb(word: &str) -> bool {
+    INTENSIFIERS.contains(&word)
+        || (word.len() > 4 && word.ends_with("ly") && !LY_EXCEPTIONS.contains(&word))
+}
+
+fn is_participle(word: &str) -> bool {
+    IRREGULAR_PARTICIPLES.contains(&word)
+        || (word.len() > 4
+            && word.ends_with("ed")
+            && !word.ends_with("eed")
+            && !ED_EXCEPTIONS.contains(&word))
+}
+
+/// A form of "be" followed by a past participle, with only "not" or
+/// adverbs between them: "was taken", "were not quietly moved"
+fn is_passive(words: &[String]) -> bool {
+    words.iter().enumerate().any(|(i, word)| {
+        BE_FORMS.contains(&word.as_str())
+            && words[i + 1..]
+                .iter()
+                .find(|next| *next != "not" && !is_adverb(next))
+                .is_some_and(|next| is_participle(next))
+    })
+}
+
+/// Combine paragraph counts into a report; `counted` is how many of them
+/// were counted rather than cached
+pub fn build_report(
+    document_id: Uuid,
+    version: Option<u32>,
+    paragraphs: &[ParagraphCounts],
+    counted: usize,
+) -> ReadabilityReport {
+    let mut words = 0;
+    let mut syllables = 0;
+    let mut complex_words = 0;
+    let mut adverbs = 0;
+    let mut passive = 0;
+    let mut lengths = Vec::new();
+    let mut phrases: BTreeMap<&str, (usize, BTreeSet<usize>)> = BTreeMap::new();
+    for (index, counts) in paragraphs.iter().enumerate() {
+        words += counts.words;
+        syllables += counts.syllables;
+        complex_words += counts.complex_words;
+        adverbs += counts.adverbs;
+        passive += counts.passive_sentences;
+        lengths.extend_from_slice(&counts.sentence_lengths);
+        for (phrase, n) in &counts.phrases {
+            let entry = phrases.entry(phrase).or_default();
+            entry.0 += n;
+            entry.1.insert(index);
+        }
+    }
+
+    let sentences = lengths.len();
+    let (reading_ease, grade, fog, average) = if words == 0 || sentences == 0 {
+        (0.0, 0.0, 0.0, 0.0)
+    } else {
+        let words_per_sentence = words as f32 / sentences as f32;
+        let syllables_per_word = syllables as f32 / words as f32;
+        let complex_share = complex_words as f32 / words as f32;
+        (
+            206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word,
+            0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59,
+            0.4 * (words_per_sentence + 100.0 * complex_share),
+            words_per_sentence,
+        )
+    };
+
+    let sentence_lengths = SENTENCE_BUCKETS
+        .iter()
+        .enumerate()
+        .map(|(i, &min_words)| {
+            let max_words = SENTENCE_BUCKETS.get(i + 1).map(|next| next - 1);
+            SentenceLengthBucket {
+                min_words,
+                max_words,
+                sentences: lengths
+                    .iter()
+                    .filter(|&&n| n >= min_words && max_words.is_none_or(|max| n <= max))
+                    .count(),
+            }
+        })
+        .collect();
+
+    // A shorter phrase only counts apart from the longer ones holding it
+    let repeated: Vec<(&str, usize, &BTreeSet<usize>)> = phrases
+        .iter()
+        .filter(|(_, (count, _))| *count > 1)
+        .map(|(phrase, (count, paragraphs))| (*phrase, *count, paragraphs))
+        .collect();
+    let mut repeated_phrases: Vec<RepeatedPhrase> = repeated
+        .iter()
+        .filter(|(phrase, count, _)| {
+            !repeated.iter().any(|(longer, longer_count, _)| {
+                longer.len() > phrase.len()
+                    && longer_count == count
+                    && format!(" {} ", longer).contains(&format!(" {} ", phrase))
+            })
+        })
+        .map(|(phrase, count, paragraphs)| RepeatedPhrase {
+            phrase: phrase.to_string(),
+            count: *count,
+            paragraphs: paragraphs.iter().copied().collect(),
+        })
+        .collect();
+    repeated_phrases.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then(b.phrase.len().cmp(&a.phrase.len()))
+            .then(a.phrase.cmp(&b.phrase))
+    });
+    repeated_phrases.truncate(REPEATED_PHRASES);
+
+    let share = |part: usize, whole: usize| {
+        if whole == 0 {
+            0.0
+        } else {
+            part as f32 / whole as f32
+        }
+    };
+    ReadabilityReport {
+        document_id,
+        version,
+        word_count: words,
+        sentence_count: sentences,
+        paragraph_count: paragraphs.len(),
+        flesch_reading_ease: round(reading_ease),
+        flesch_kincaid_grade: round(grade),
+        gunning_fog: round(fog),
+        average_sentence_length: round(average),
+        longest_sentence: lengths.iter().copied().max().unwrap_or(0),
+        sentence_lengths,
+        passive_ratio: share(passive, sentences),
+        adverb_density: share(adverbs * 100, words),
+        repeated_phrases,
+        paragraphs_counted: counted,
+    }
+}
+
+/// To one decimal place
+fn round(value: f32) -> f32 {
+    (value * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syllables_and_sentences() {
+        for (word, expected) in [
+            ("cat", 1),
+            ("make", 1),
+            ("table", 2),
+            ("jumped", 1),
+            ("wanted", 2),
+            ("glasses", 2),
+            ("beautiful", 3),
+            ("readability", 5),
+        ] {
+            assert_eq!(syllables(word), expected, "{}", word);
+        }
+        assert_eq!(
+            sentences("Mr. Quell left. \"Why?\" she asked... Then nothing"),
+            vec![
+                "Mr. Quell left.",
+                " \"Why?\"",
+                " she asked...",
+                " Then nothing"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_readability_report() {
+        let text = "The ship was quickly taken by the storm. The crew slowly rowed to the old harbor wall.\n\n\
+                    Mara watched the old harbor wall. She sat down.";
+        let counts: Vec<ParagraphCounts> =
+            paragraphs(text).into_iter().map(count_paragraph).collect();
+        assert_eq!(counts[0].passive_sentences, 1);
+        assert_eq!(counts[0].adverbs, 2);
+
+        let report = build_report(Uuid::nil(), Some(1), &counts, counts.len());
+        assert_eq!(report.word_count, 26);
+        assert_eq!(report.sentence_count, 4);
+        assert_eq!(report.passive_ratio, 0.25);
+        assert_eq!(report.longest_sentence, 9);
+        assert_eq!(report.sentence_lengths[0].sentences, 1);
+        assert_eq!(report.sentence_lengths[1].sentences, 3);
+        assert!(report.flesch_reading_ease > 60.0);
+        assert!(report.gunning_fog > 0.0);
+        assert_eq!(report.repeated_phrases.len(), 1);
+        assert_eq!(report.repeated_phrases[0].phrase, "old harbor wall");
+        assert_eq!(report.repeated_phrases[0].paragraphs, [0, 1]);
+    }
+}
@@ -0,0 +1,484 @@
+//! Related Notes Service
+//!
+//! Suggests research notes and codex entries for the paragraph or
+//! selection being written. Notes are embedded once into a per-project
+//! index that is rebuilt only when a note changes, and answers are cached
+//! by request, so a sidebar can ask on every pause in typing.
+
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::database::models::related_notes::{
+    RelatedItem, RelatedKind, RelatedNotes, RelatedNotesRequest, RelatedWeights,
+};
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{
+    DatabaseError, DatabaseResult, EnhancedDatabaseService, VectorEmbeddingService,
+};
+
+/// Answers kept in the result cache
+const RESULT_CACHE_SIZE: usize = 128;
+
+/// Characters of a note's text that are embedded
+const NOTE_TEXT_CHARS: usize = 2000;
+
+const SNIPPET_CHARS: usize = 200;
+
+/// A note as loaded for ranking
+#[derive(Debug, Clone)]
+pub struct NoteCandidate {
+    pub id: Uuid,
+    pub kind: RelatedKind,
+    pub project_id: Uuid,
+    pub title: String,
+    pub text: String,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Embedded notes of one project, or of every project
+#[derive(Debug, Clone)]
+pub struct NoteIndex {
+    /// Embedding model and note counts and edit times the index was built from
+    stamp: String,
+    notes: Vec<(NoteCandidate, Vec<f32>)>,
+}
+
+#[derive(Debug, Default)]
+struct ResultCache {
+    answers: HashMap<u64, (String, Vec<RelatedItem>)>,
+    order: VecDeque<u64>,
+}
+
+impl ResultCache {
+    fn get(&self, key: u64, stamp: &str) -> Option<Vec<RelatedItem>> {
+        self.answers
+            .get(&key)
+            .filter(|(cached_stamp, _)| cached_stamp == stamp)
+            .map(|(_, items)| items.clone())
+    }
+
+    fn insert(&mut self, key: u64, stamp: String, items: Vec<RelatedItem>) {
+        if self.answers.insert(key, (stamp, items)).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > RESULT_CACHE_SIZE {
+            if let Some(oldest) = self.order.pop_front() {
+                self.answers.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Service for related note suggestions
+#[derive(Debug)]
+pub struct RelatedNotesService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    embeddings: Arc<VectorEmbeddingService>,
+    weights: RelatedWeights,
+    /// Indexes by project; `None` holds every project's notes
+    indexes: RwLock<HashMap<Option<Uuid>, Arc<NoteIndex>>>,
+    results: Mutex<ResultCache>,
+}
+
+impl RelatedNotesService {
+    /// Create a new related notes service
+    pub fn new(
+        db_service: Arc<RwLock<EnhancedDatabaseService>>,
+        embeddings: Arc<VectorEmbeddingService>,
+    ) -> Self {
+        Self::with_weights(db_service, embeddings, RelatedWeights::default())
+    }
+
+    /// Create a service with custom ranking weights
+    pub fn with_weights(
+        db_service: Arc<RwLock<EnhancedDatabaseService>>,
+        embeddings: Arc<VectorEmbeddingService>,
+        weights: RelatedWeights,
+    ) -> Self {
+        Self {
+            db_service,
+            embeddings,
+            weights,
+            indexes: RwLock::new(HashMap::new()),
+            results: Mutex::new(ResultCache::default()),
+        }
+    }
+
+    /// Notes related to the text in the request, best first
+    pub async fn related(&self, request: &RelatedNotesRequest) -> DatabaseResult<RelatedNotes> {
+        let started = Instant::now();
+        let scope = (!request.include_other_projects).then_some(request.project_id);
+        let model = self
+            .embeddings
+            .default_model()
+            .await
+            .map_err(embedding_error)?;
+        let stamp = format!("{}|{}", model, self.stamp(scope).await?);
+
+        let key = request_key(request);
+        if let Some(items) = self.results.lock().await.get(key, &stamp) {
+            return Ok(RelatedNotes {
+                items,
+                cached: true,
+                elapsed_ms: started.elapsed().as_millis() as u64,
+            });
+        }
+
+        let index = self.index(scope, &model, &stamp).await?;
+        let items = if request.text.trim().is_empty() {
+            Vec::new()
+        } else {
+            let (_, query) = self
+                .embeddings
+                .embed_text(&request.text, Some(&model))
+                .await
+                .map_err(embedding_error)?;
+            self.rank(&index, &query, request, Utc::now())
+        };
+        self.results.lock().await.insert(key, stamp, items.clone());
+
+        Ok(RelatedNotes {
+            items,
+            cached: false,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Drop cached indexes and answers, such as after notes are imported
+    pub async fn invalidate(&self) {
+        self.indexes.write().await.clear();
+        *self.results.lock().await = ResultCache::default();
+    }
+
+    /// Score notes against an embedded query
+    pub fn rank(
+        &self,
+        index: &NoteIndex,
+        query: &[f32],
+        request: &RelatedNotesRequest,
+        now: DateTime<Utc>,
+    ) -> Vec<RelatedItem> {
+        let weights = &self.weights;
+        let mut items: Vec<RelatedItem> = index
+            .notes
+            .iter()
+            .filter(|(note, _)| request.kinds.is_empty() || request.kinds.contains(&note.kind))
+            .filter(|(note, _)| {
+                request.include_other_projects || note.project_id == request.project_id
+            })
+            .filter_map(|(note, vector)| {
+                let similarity = self.embeddings.calculate_cosine_similarity(query, vector);
+                if similarity < weights.min_similarity {
+                    return None;
+                }
+                let recency = note
+                    .updated_at
+                    .map(|updated| {
+                        let days = (now - updated).num_seconds().max(0) as f32 / 86_400.0;
+                        0.5f32.powf(days / weights.recency_half_life_days.max(f32::EPSILON))
+                    })
+                    .unwrap_or(0.0);
+                let mut score = similarity + weights.recency * recency;
+                if note.project_id != request.project_id {
+                    score *= weights.other_project;
+                }
+                Some(RelatedItem {
+                    id: note.id,
+                    kind: note.kind,
+                    project_id: note.project_id,
+                    title: note.title.clone(),
+                    snippet: snippet(&note.text),
+                    similarity,
+                    score,
+                    updated_at: note.updated_at,
+                })
+            })
+            .collect();
+
+        items.sort_by(|a, b| b.score.total_cmp(&a.score));
+        items.truncate(request.limit);
+        items
+    }
+
+    /// Embed notes into an index
+    pub async fn build_index(
+        &self,
+        notes: Vec<NoteCandidate>,
+        model: &str,
+        stamp: String,
+    ) -> DatabaseResult<NoteIndex> {
+        let mut embedded = Vec::with_capacity(notes.len());
+        for note in notes {
+            let text = format!("{}\n{}", note.title, note.text);
+            let (_, vector) = self
+                .embeddings
+                .embed_text(&text, Some(model))
+                .await
+                .map_err(embedding_error)?;
+            embedded.push((note, vector));
+        }
+        Ok(NoteIndex {
+            stamp,
+            notes: embedded,
+        })
+    }
+
+    /// The cached index for a scope, rebuilt when its notes have changed
+    async fn index(
+        &self,
+        scope: Option<Uuid>,
+        model: &str,
+        stamp: &str,
+    ) -> DatabaseResult<Arc<NoteIndex>> {
+        if let Some(index) = self.indexes.read().await.get(&scope) {
+            if index.stamp == stamp {
+                return Ok(index.clone());
+            }
+        }
+        let notes = self.load_notes(scope).await?;
+        let index = Arc::new(self.build_index(notes, model, stamp.to_string()).await?);
+        self.indexes.write().await.insert(scope, index.clone());
+        Ok(index)
+    }
+
+    /// Tables holding notes, of `research_materials` and `codex_entries`
+    async fn note_tables(&self) -> DatabaseResult<Vec<String>> {
+        let db = self.db_service.read().await;
+        sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table'
+             AND name IN ('research_materials', 'codex_entries') ORDER BY name",
+        )
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check schema: {}", e)))
+    }
+
+    /// Count and latest edit of the notes in scope, which change whenever a
+    /// note is added, edited or removed
+    async fn stamp(&self, scope: Option<Uuid>) -> DatabaseResult<String> {
+        let tables = self.note_tables().await?;
+        let db = self.db_service.read().await;
+        let mut stamp = String::new();
+        for table in tables {
+            let active = if table == "codex_entries" {
+                "is_active = 1 AND"
+            } else {
+                ""
+            };
+            let (count, latest): (i64, Option<String>) = sqlx::query_as(&format!(
+                "SELECT COUNT(*), MAX(updated_at) FROM {} WHERE {} (?1 IS NULL OR project_id = ?1)",
+                table, active
+            ))
+            .bind(scope.map(|id| id.to_string()))
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to check {}: {}", table, e)))?;
+            stamp.push_str(&format!(
+                "{}:{}:{};",
+                table,
+                count,
+                latest.unwrap_or_default()
+            ));
+        }
+        Ok(stamp)
+    }
+
+    async fn load_notes(&self, scope: Option<Uuid>) -> DatabaseResult<Vec<NoteCandidate>> {
+        let tables = self.note_tables().await?;
+        let db = self.db_service.read().await;
+        let mut notes = Vec::new();
+
+        if tables.iter().any(|t| t == "research_materials") {
+            let rows: Vec<NoteRow> = sqlx::query_as(
+                "SELECT id, project_id, title,
+                        COALESCE(description, '') || char(10) || COALESCE(extracted_text, ''),
+                        updated_at
+                 FROM research_materials WHERE ?1 IS NULL OR project_id = ?1",
+            )
+            .bind(scope.map(|id| id.to_string()))
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to load research materials: {}", e))
+            })?;
+            for row in rows {
+                notes.push(note_from_row(RelatedKind::ResearchNote, row)?);
+            }
+        }
+
+        if tables.iter().any(|t| t == "codex_entries") {
+            let rows: Vec<NoteRow> = sqlx::query_as(
+                "SELECT id, project_id, title, content, updated_at FROM codex_entries
+                 WHERE is_active = 1 AND (?1 IS NULL OR project_id = ?1)",
+            )
+            .bind(scope.map(|id| id.to_string()))
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load codex entries: {}", e)))?;
+            for row in rows {
+                notes.push(note_from_row(RelatedKind::CodexEntry, row)?);
+            }
+        }
+
+        Ok(notes)
+    }
+}
+
+type NoteRow = (String, String, String, Option<String>, Option<String>);
+
+fn note_from_row(kind: RelatedKind, row: NoteRow) -> DatabaseResult<NoteCandidate> {
+    let (id, project_id, title, text, updated_at) = row;
+    Ok(NoteCandidate {
+        id: parse_uuid(&id)?,
+        kind,
+        project_id: parse_uuid(&project_id)?,
+        title,
+        text: text
+            .unwrap_or_default()
+            .trim()
+            .chars()
+            .take(NOTE_TEXT_CHARS)
+            .collect(),
+        updated_at: updated_at.as_deref().map(parse_time).transpose()?,
+    })
+}
+
+/// The embedding service reports errors in the crate-wide error type
+fn embedding_error(error: crate::error::DatabaseError) -> DatabaseError {
+    DatabaseError::Service(format!("Embedding failed: {}", error))
+}
+
+/// Identifies a request in the result cache
+fn request_key(request: &RelatedNotesRequest) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    request.project_id.hash(&mut hasher);
+    request.text.trim().hash(&mut hasher);
+    request.limit.hash(&mut hasher);
+    request.include_other_projects.hash(&mut hasher);
+    let mut kinds = request.kinds.clone();
+    kinds.sort_by_key(|kind| *kind as u8);
+    kinds.dedup();
+    kinds.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn snippet(text: &str) -> String {
+    if text.chars().count() <= SNIPPET_CHARS {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(SNIPPET_CHARS).collect();
+    let cut = cut.rsplit_once(' ').map(|(head, _)| head).unwrap_or(&cut);
+    format!("{}...", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_rank_weights_similarity_recency_and_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let embeddings = Arc::new(VectorEmbeddingService::new(db.clone()));
+        embeddings.initialize().await.unwrap();
+        let service = RelatedNotesService::new(db, embeddings.clone());
+
+        let project = Uuid::new_v4();
+        let now = Utc::now();
+        let note = |kind, project_id, title: &str, text: &str, days: i64| NoteCandidate {
+            id: Uuid::new_v4(),
+            kind,
+            project_id,
+            title: title.to_string(),
+            text: text.to_string(),
+            updated_at: Some(now - chrono::Duration::days(days)),
+        };
+        let text = "Lighthouse keepers trimmed the lamp wick every four hours";
+        let old = note(
+            RelatedKind::ResearchNote,
+            project,
+            "Lighthouse keepers",
+            text,
+            365,
+        );
+        let fresh = note(
+            RelatedKind::ResearchNote,
+            project,
+            "Lighthouse keepers",
+            text,
+            0,
+        );
+        let elsewhere = note(
+            RelatedKind::ResearchNote,
+            Uuid::new_v4(),
+            "Lighthouse keepers",
+            text,
+            0,
+        );
+        let unrelated = note(
+            RelatedKind::CodexEntry,
+            project,
+            "Mara",
+            "A smuggler's daughter",
+            0,
+        );
+
+        let model = embeddings.default_model().await.unwrap();
+        let index = service
+            .build_index(
+                vec![old.clone(), fresh.clone(), elsewhere.clone(), unrelated],
+                &model,
+                String::new(),
+            )
+            .await
+            .unwrap();
+        let (_, query) = embeddings
+            .embed_text("the keeper trimmed the lamp wick", None)
+            .await
+            .unwrap();
+
+        let mut request = RelatedNotesRequest::new(project, "");
+        let ranked = service.rank(&index, &query, &request, now);
+        let ids: Vec<Uuid> = ranked.iter().map(|item| item.id).collect();
+        assert_eq!(ids, vec![fresh.id, old.id]);
+        assert!(ranked[0].similarity == ranked[1].similarity);
+
+        request.include_other_projects = true;
+        let ranked = service.rank(&index, &query, &request, now);
+        assert_eq!(ranked.len(), 3);
+        assert_eq!(ranked[2].id, elsewhere.id);
+
+        request.kinds = vec![RelatedKind::CodexEntry];
+        assert!(service.rank(&index, &query, &request, now).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_related_answers_are_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let embeddings = Arc::new(VectorEmbeddingService::new(db.clone()));
+        embeddings.initialize().await.unwrap();
+        let service = RelatedNotesService::new(db, embeddings);
+
+        let request = RelatedNotesRequest::new(Uuid::new_v4(), "The tide came in.");
+        assert!(!service.related(&request).await.unwrap().cached);
+        let again = service.related(&request).await.unwrap();
+        assert!(again.cached);
+        assert!(again.items.is_empty());
+    }
+}
@@ -0,0 +1,804 @@
+//! Rename Service
+//!
+//! Renames a character (or any term) across a project: the codex entry
+//! title and, optionally, every mention in the project's documents. Mentions
+//! keep their case ("MARA" becomes "JO"), possessives stay correct ("Chris'"
+//! becomes "Jo's") and dialogue can be left untouched. Every applied rename
+//! keeps the original document text so it can be rolled back.
+
+use chrono::Utc;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{
+    models::rename::*,
+    prosemirror,
+    text_match::{find_word_matches, line_and_column},
+    DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+
+type OperationRow = (
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    String,
+    Option<String>,
+);
+type RenamedDocumentRow = (String, String, String, i64);
+type DocumentRow = (String, String, Option<String>, Option<String>);
+
+/// Active documents of a project, for finding mentions in
+const PROJECT_DOCUMENTS_SQL: &str = "SELECT id, title, content, document_type FROM documents
+     WHERE project_id = ?1 AND is_active = 1 ORDER BY title ASC";
+
+/// Characters of context shown on each side of a mention
+const CONTEXT_CHARS: usize = 60;
+
+/// Service for previewing, applying and rolling back renames
+#[derive(Debug)]
+pub struct RenameService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl RenameService {
+    /// Create a new rename service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize rename history tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_RENAME_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create rename tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Every change the request would make, without changing anything
+    pub async fn preview(&self, request: &RenameRequest) -> DatabaseResult<RenamePreview> {
+        validate(request)?;
+        let db = self.db_service.read().await;
+
+        let codex_title = match request.codex_entry_id {
+            Some(entry_id) => Some(codex_title(&db, entry_id).await?),
+            None => None,
+        };
+
+        let mut occurrences = Vec::new();
+        if request.rewrite_documents {
+            let documents: Vec<DocumentRow> = sqlx::query_as(PROJECT_DOCUMENTS_SQL)
+                .bind(request.project_id.to_string())
+                .fetch_all(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+
+            for (id, title, content, document_type) in documents {
+                let text = prosemirror::document_text(
+                    document_type.as_deref().unwrap_or_default(),
+                    content.as_deref().unwrap_or_default(),
+                );
+                occurrences.extend(find_occurrences(request, parse_uuid(&id)?, &title, &text));
+            }
+        }
+
+        let mut affected: Vec<Uuid> = occurrences
+            .iter()
+            .filter(|o| o.included)
+            .map(|o| o.document_id)
+            .collect();
+        affected.dedup();
+
+        Ok(RenamePreview {
+            request: request.clone(),
+            codex_title,
+            occurrences,
+            documents_affected: affected.len(),
+        })
+    }
+
+    /// Apply a rename in one transaction and record it for rollback
+    ///
+    /// Mentions are found again in the documents as read inside the
+    /// transaction, so an edit made since the preview can't shift them.
+    pub async fn apply(&self, request: &RenameRequest) -> DatabaseResult<RenameOperation> {
+        validate(request)?;
+        let db = self.db_service.read().await;
+
+        let codex_title = match request.codex_entry_id {
+            Some(entry_id) => Some(codex_title(&db, entry_id).await?),
+            None => None,
+        };
+
+        let mut tx =
+            db.pool.begin().await.map_err(|e| {
+                DatabaseError::Service(format!("Failed to begin transaction: {}", e))
+            })?;
+
+        let rows: Vec<DocumentRow> = if request.rewrite_documents {
+            sqlx::query_as(PROJECT_DOCUMENTS_SQL)
+                .bind(request.project_id.to_string())
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?
+        } else {
+            Vec::new()
+        };
+
+        let mut documents = Vec::new();
+        for (id, title, content, document_type) in rows {
+            let document_id = parse_uuid(&id)?;
+            let document_type = document_type.unwrap_or_default();
+            let original = content.unwrap_or_default();
+            let text = prosemirror::document_text(&document_type, &original);
+            let occurrences = find_occurrences(request, document_id, &title, &text);
+            let included: Vec<&RenameOccurrence> =
+                occurrences.iter().filter(|o| o.included).collect();
+            let (renamed, replacements) = rename_in_document(&document_type, &original, &included);
+            if replacements == 0 {
+                continue;
+            }
+            let checksum = EnhancedDatabaseService::calculate_checksum(&renamed);
+
+            sqlx::query(
+                "UPDATE documents SET content = ?, checksum = ?, updated_at = ?, version = version + 1 WHERE id = ?",
+            )
+            .bind(&renamed)
+            .bind(&checksum)
+            .bind(Utc::now())
+            .bind(document_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to rename in document: {}", e)))?;
+            prosemirror::store_document_text(
+                &mut tx,
+                &document_id.to_string(),
+                &document_type,
+                &renamed,
+            )
+            .await?;
+
+            documents.push(RenamedDocument {
+                document_id,
+                original_content: original,
+                renamed_checksum: checksum,
+                replacements,
+            });
+        }
+
+        if let (Some(entry_id), Some(title)) = (request.codex_entry_id, &codex_title) {
+            let occurrences = find_occurrences(request, entry_id, title, title);
+            let included: Vec<&RenameOccurrence> =
+                occurrences.iter().filter(|o| o.included).collect();
+            let new_title = if included.is_empty() {
+                request.to.trim().to_string()
+            } else {
+                rewrite(title, 0, &included).0
+            };
+            sqlx::query("UPDATE codex_entries SET title = ?1, updated_at = ?2 WHERE id = ?3")
+                .bind(&new_title)
+                .bind(Utc::now().to_rfc3339())
+                .bind(entry_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to rename codex entry: {}", e))
+                })?;
+        }
+
+        let operation = RenameOperation {
+            id: Uuid::new_v4(),
+            project_id: request.project_id,
+            from: request.from.trim().to_string(),
+            to: request.to.trim().to_string(),
+            codex_entry_id: request.codex_entry_id,
+            previous_codex_title: codex_title,
+            documents,
+            applied_at: Utc::now(),
+            rolled_back_at: None,
+        };
+
+        sqlx::query(INSERT_RENAME_OPERATION_SQL)
+            .bind(operation.id.to_string())
+            .bind(operation.project_id.to_string())
+            .bind(&operation.from)
+            .bind(&operation.to)
+            .bind(operation.codex_entry_id.map(|id| id.to_string()))
+            .bind(&operation.previous_codex_title)
+            .bind(operation.applied_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to record rename: {}", e)))?;
+        for document in &operation.documents {
+            sqlx::query(INSERT_RENAME_DOCUMENT_SQL)
+                .bind(operation.id.to_string())
+                .bind(document.document_id.to_string())
+                .bind(&document.original_content)
+                .bind(&document.renamed_checksum)
+                .bind(document.replacements as i64)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to record renamed document: {}", e))
+                })?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to commit rename: {}", e)))?;
+
+        Ok(operation)
+    }
+
+    /// Undo an applied rename. Documents edited since the rename are left
+    /// alone and reported as conflicts.
+    pub async fn rollback(&self, operation_id: Uuid) -> DatabaseResult<RenameRollback> {
+        let operation = self.get_operation(operation_id).await?.ok_or_else(|| {
+            DatabaseError::RecordNotFound {
+                entity: "rename".to_string(),
+                id: operation_id.to_string(),
+            }
+        })?;
+        if operation.rolled_back_at.is_some() {
+            return Err(DatabaseError::ValidationError(
+                "Rename has already been rolled back".to_string(),
+            ));
+        }
+
+        let db = self.db_service.read().await;
+        let mut tx =
+            db.pool.begin().await.map_err(|e| {
+                DatabaseError::Service(format!("Failed to begin transaction: {}", e))
+            })?;
+
+        let mut restored = Vec::new();
+        let mut conflicts = Vec::new();
+        for document in &operation.documents {
+            let current: Option<(String, Option<String>)> =
+                sqlx::query_as("SELECT checksum, document_type FROM documents WHERE id = ?1")
+                    .bind(document.document_id.to_string())
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Service(format!("Failed to load document: {}", e))
+                    })?;
+            let document_type = match current {
+                Some((checksum, document_type)) if checksum == document.renamed_checksum => {
+                    document_type.unwrap_or_default()
+                }
+                _ => {
+                    conflicts.push(document.document_id);
+                    continue;
+                }
+            };
+
+            let content = &document.original_content;
+            sqlx::query(
+                "UPDATE documents SET content = ?, checksum = ?, updated_at = ?, version = version + 1 WHERE id = ?",
+            )
+            .bind(content)
+            .bind(EnhancedDatabaseService::calculate_checksum(content))
+            .bind(Utc::now())
+            .bind(document.document_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to restore document: {}", e)))?;
+            prosemirror::store_document_text(
+                &mut tx,
+                &document.document_id.to_string(),
+                &document_type,
+                content,
+            )
+            .await?;
+            restored.push(document.document_id);
+        }
+
+        let mut codex_restored = false;
+        if let (Some(entry_id), Some(title)) =
+            (operation.codex_entry_id, &operation.previous_codex_title)
+        {
+            let result = sqlx::query(
+                "UPDATE codex_entries SET title = ?1, updated_at = ?2 WHERE id = ?3 AND title <> ?1",
+            )
+            .bind(title)
+            .bind(Utc::now().to_rfc3339())
+            .bind(entry_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to restore codex entry: {}", e)))?;
+            codex_restored = result.rows_affected() > 0;
+        }
+
+        sqlx::query("UPDATE rename_operations SET rolled_back_at = ?1 WHERE id = ?2")
+            .bind(Utc::now().to_rfc3339())
+            .bind(operation_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to record rollback: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to commit rollback: {}", e)))?;
+
+        Ok(RenameRollback {
+            operation_id,
+            restored,
+            conflicts,
+            codex_restored,
+        })
+    }
+
+    /// Get an applied rename
+    pub async fn get_operation(
+        &self,
+        operation_id: Uuid,
+    ) -> DatabaseResult<Option<RenameOperation>> {
+        let db = self.db_service.read().await;
+        let row: Option<OperationRow> = sqlx::query_as(GET_RENAME_OPERATION_SQL)
+            .bind(operation_id.to_string())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get rename: {}", e)))?;
+        match row {
+            Some(row) => Ok(Some(self.operation_from_row(&db, row).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Renames applied in a project, newest first
+    pub async fn list_operations(&self, project_id: Uuid) -> DatabaseResult<Vec<RenameOperation>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<OperationRow> = sqlx::query_as(GET_RENAME_OPERATIONS_SQL)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to list renames: {}", e)))?;
+
+        let mut operations = Vec::with_capacity(rows.len());
+        for row in rows {
+            operations.push(self.operation_from_row(&db, row).await?);
+        }
+        Ok(operations)
+    }
+
+    async fn operation_from_row(
+        &self,
+        db: &EnhancedDatabaseService,
+        row: OperationRow,
+    ) -> DatabaseResult<RenameOperation> {
+        let (
+            id,
+            project_id,
+            from,
+            to,
+            codex_entry_id,
+            previous_codex_title,
+            applied_at,
+            rolled_back_at,
+        ) = row;
+        let documents: Vec<RenamedDocumentRow> = sqlx::query_as(GET_RENAME_DOCUMENTS_SQL)
+            .bind(&id)
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to load renamed documents: {}", e))
+            })?;
+
+        Ok(RenameOperation {
+            id: parse_uuid(&id)?,
+            project_id: parse_uuid(&project_id)?,
+            from,
+            to,
+            codex_entry_id: codex_entry_id.as_deref().map(parse_uuid).transpose()?,
+            previous_codex_title,
+            documents: documents
+                .into_iter()
+                .map(
+                    |(document_id, original_content, renamed_checksum, replacements)| {
+                        Ok(RenamedDocument {
+                            document_id: parse_uuid(&document_id)?,
+                            original_content,
+                            renamed_checksum,
+                            replacements: replacements.max(0) as usize,
+                        })
+                    },
+                )
+                .collect::<DatabaseResult<_>>()?,
+            applied_at: parse_time(&applied_at)?,
+            rolled_back_at: rolled_back_at.as_deref().map(parse_time).transpose()?,
+        })
+    }
+}
+
+/// Mentions of `request.from` in one text, with the case-matched
+/// replacement for each
+pub fn find_occurrences(
+    request: &RenameRequest,
+    document_id: Uuid,
+    document_title: &str,
+    text: &str,
+) -> Vec<RenameOccurrence> {
+    let from = request.from.trim();
+    let to = request.to.trim();
+    let dialogue = dialogue_ranges(text);
+
+    find_word_matches(text, from)
+        .into_iter()
+        .map(|(start, mut end)| {
+            // "Chris'" is a possessive; "Mara'" is more likely a closing quote
+            let bare_possessive = from.to_lowercase().ends_with('s')
+                && text[end..].starts_with(['\'', '’'])
+                && !text[end..]
+                    .chars()
+                    .nth(1)
+                    .is_some_and(char::is_alphanumeric);
+            let apostrophe = if bare_possessive {
+                let c = text[end..].chars().next().unwrap_or('\'');
+                end += c.len_utf8();
+                Some(c)
+            } else {
+                None
+            };
+
+            let matched = &text[start..end];
+            let name = &matched[..matched.len() - apostrophe.map_or(0, char::len_utf8)];
+            let (mut replacement, case_matches) = match_case(name, from, to);
+            if let Some(apostrophe) = apostrophe {
+                // "Jo's" for a name not ending in s, "James'" otherwise
+                let ends_in_s = replacement.to_lowercase().ends_with('s');
+                replacement.push(apostrophe);
+                if !ends_in_s {
+                    replacement.push(if name.chars().all(|c| !c.is_lowercase()) {
+                        'S'
+                    } else {
+                        's'
+                    });
+                }
+            }
+
+            let id = format!("{}:{}", document_id, start);
+            let in_dialogue = dialogue.iter().any(|(s, e)| start >= *s && start < *e);
+            let included = case_matches
+                && !(request.skip_dialogue && in_dialogue)
+                && !request.skip_occurrences.contains(&id);
+
+            RenameOccurrence {
+                id,
+                document_id,
+                document_title: document_title.to_string(),
+                start,
+                end,
+                line: line_and_column(text, start).0,
+                original: matched.to_string(),
+                replacement,
+                in_dialogue,
+                included,
+                context: context(text, start, end),
+            }
+        })
+        .collect()
+}
+
+/// Replace the occurrences found in a document's text. In ProseMirror JSON
+/// only text nodes change; a mention split across two nodes (half of it
+/// bold, say) is left alone. Returns the new content and how many mentions
+/// were replaced.
+fn rename_in_document(
+    document_type: &str,
+    content: &str,
+    occurrences: &[&RenameOccurrence],
+) -> (String, usize) {
+    let Some(mut doc) = prosemirror::parse(document_type, content) else {
+        return rewrite(content, 0, occurrences);
+    };
+    let mut replaced = 0;
+    for (start, value) in prosemirror::text_nodes_mut(&mut doc).1 {
+        let (renamed, count) = rewrite(value, start, occurrences);
+        *value = renamed;
+        replaced += count;
+    }
+    (doc.to_string(), replaced)
+}
+
+/// Replace the occurrences that lie within `text`, which starts `offset`
+/// bytes into the text they were found in; returns the new text and how
+/// many were replaced
+fn rewrite(text: &str, offset: usize, occurrences: &[&RenameOccurrence]) -> (String, usize) {
+    let mut sorted: Vec<&&RenameOccurrence> = occurrences.iter().collect();
+    sorted.sort_by_key(|o| o.start);
+
+    let mut out = String::with_capacity(text.len());
+    let mut position = 0;
+    let mut replaced = 0;
+    for occurrence in sorted {
+        if occurrence.start < offset + position || occurrence.end > offset + text.len() {
+            continue;
+        }
+        out.push_str(&text[position..occurrence.start - offset]);
+        out.push_str(&occurrence.replacement);
+        position = occurrence.end - offset;
+        replaced += 1;
+    }
+    out.push_str(&text[position..]);
+    (out, replaced)
+}
+
+/// The replacement written the way the mention was, and whether the mention
+/// is really the name: a lowercase "rose" is not the character "Rose"
+fn match_case(matched: &str, from: &str, to: &str) -> (String, bool) {
+    let has_upper = |s: &str| s.chars().any(char::is_uppercase);
+    let has_lower = |s: &str| s.chars().any(char::is_lowercase);
+
+    if matched == from {
+        (to.to_string(), true)
+    } else if !has_lower(matched) && has_lower(from) {
+        (to.to_uppercase(), true)
+    } else if !has_upper(matched) && has_upper(from) {
+        (to.to_lowercase(), false)
+    } else if matched.chars().next().is_some_and(char::is_uppercase)
+        && from.chars().next().is_some_and(char::is_lowercase)
+    {
+        // A lowercase term at the start of a sentence
+        let mut chars = to.chars();
+        let capitalised = chars
+            .next()
+            .map(|c| c.to_uppercase().chain(chars).collect())
+            .unwrap_or_default();
+        (capitalised, true)
+    } else {
+        (to.to_string(), true)
+    }
+}
+
+/// Byte ranges inside double quotes; a paragraph break closes any open quote
+fn dialogue_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut open: Option<usize> = None;
+    for (i, c) in text.char_indices() {
+        match (c, open) {
+            ('“', _) => open = Some(i),
+            ('"', None) => open = Some(i),
+            ('"' | '”', Some(start)) | ('\n', Some(start)) => {
+                ranges.push((start, i));
+                open = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = open {
+        ranges.push((start, text.len()));
+    }
+    ranges
+}
+
+/// Up to `CONTEXT_CHARS` characters either side of a mention, within its line
+fn context(text: &str, start: usize, end: usize) -> String {
+    let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i);
+    let before: String = text[line_start..start]
+        .chars()
+        .rev()
+        .take(CONTEXT_CHARS)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let after: String = text[end..line_end].chars().take(CONTEXT_CHARS).collect();
+    format!("{}{}{}", before, &text[start..end], after)
+        .trim()
+        .to_string()
+}
+
+fn validate(request: &RenameRequest) -> DatabaseResult<()> {
+    let (from, to) = (request.from.trim(), request.to.trim());
+    if from.is_empty() || to.is_empty() {
+        return Err(DatabaseError::ValidationError(
+            "Both the old and the new name are required".to_string(),
+        ));
+    }
+    if from == to {
+        return Err(DatabaseError::ValidationError(
+            "The new name is the same as the old one".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn codex_title(db: &EnhancedDatabaseService, entry_id: Uuid) -> DatabaseResult<String> {
+    let has_codex: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'codex_entries'",
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| DatabaseError::Service(format!("Failed to check codex: {}", e)))?;
+    let title: Option<String> = if has_codex == 0 {
+        None
+    } else {
+        sqlx::query_scalar("SELECT title FROM codex_entries WHERE id = ?1 AND is_active = 1")
+            .bind(entry_id.to_string())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get codex entry: {}", e)))?
+    };
+    title.ok_or_else(|| DatabaseError::RecordNotFound {
+        entity: "codex entry".to_string(),
+        id: entry_id.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[test]
+    fn test_occurrences_respect_case_possessives_and_dialogue() {
+        let mut request = RenameRequest::new(Uuid::nil(), "Chris", "Jo");
+        request.skip_dialogue = true;
+        let text = "Chris' coat hung by the door. CHRIS!\n\
+                    \"Chris, wait,\" she said. Chris's sister laughed.\n\
+                    A chris-cross pattern.";
+        let found = find_occurrences(&request, Uuid::nil(), "One", text);
+
+        let replacements: Vec<(&str, &str, bool)> = found
+            .iter()
+            .map(|o| (o.original.as_str(), o.replacement.as_str(), o.included))
+            .collect();
+        assert_eq!(
+            replacements,
+            vec![
+                ("Chris'", "Jo's", true),
+                ("CHRIS", "JO", true),
+                ("Chris", "Jo", false),
+                ("Chris", "Jo", true),
+                ("chris", "jo", false),
+            ]
+        );
+        assert!(found[2].in_dialogue);
+
+        let included: Vec<&RenameOccurrence> = found.iter().filter(|o| o.included).collect();
+        assert_eq!(
+            rewrite(text, 0, &included).0.lines().next(),
+            Some("Jo's coat hung by the door. JO!")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_and_roll_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Novel', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        for (id, content) in [(first, "Mara ran."), (second, "Mara's house burned.")] {
+            db.create_document(
+                id.to_string(),
+                project.to_string(),
+                "Chapter".to_string(),
+                content.to_string(),
+            )
+            .await
+            .unwrap();
+        }
+        let db = Arc::new(RwLock::new(db));
+        let service = RenameService::new(db.clone());
+        service.initialize().await.unwrap();
+
+        let operation = service
+            .apply(&RenameRequest::new(project, "Mara", "Jo"))
+            .await
+            .unwrap();
+        assert_eq!(operation.replacements(), 2);
+        let content = |id: Uuid| {
+            let db = db.clone();
+            async move { db.read().await.get_document(id.to_string()).await.unwrap() }
+        };
+        assert_eq!(content(second).await.as_deref(), Some("Jo's house burned."));
+
+        // An edit after the rename is kept rather than overwritten
+        db.read()
+            .await
+            .update_document_content(&second.to_string(), "Jo's house stood.")
+            .await
+            .unwrap();
+        let rollback = service.rollback(operation.id).await.unwrap();
+        assert_eq!(rollback.restored, vec![first]);
+        assert_eq!(rollback.conflicts, vec![second]);
+        assert_eq!(content(first).await.as_deref(), Some("Mara ran."));
+        assert!(service.rollback(operation.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rename_rewrites_only_prosemirror_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Novel', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let document = Uuid::new_v4();
+        let content = serde_json::json!({
+            "type": "doc",
+            "content": [
+                {"type": "paragraph", "content": [
+                    {"type": "text", "text": "Text about "},
+                    {"type": "text", "marks": [{"type": "italic"}], "text": "Mara."}
+                ]},
+                {"type": "paragraph", "content": [
+                    {"type": "text", "text": "\"Mara!\" he said."}
+                ]}
+            ]
+        });
+        db.create_document(
+            document.to_string(),
+            project.to_string(),
+            "Chapter".to_string(),
+            content.to_string(),
+        )
+        .await
+        .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let service = RenameService::new(db.clone());
+        service.initialize().await.unwrap();
+
+        let mut request = RenameRequest::new(project, "Mara", "Jo");
+        request.skip_dialogue = true;
+        let preview = service.preview(&request).await.unwrap();
+        let dialogue: Vec<bool> = preview.occurrences.iter().map(|o| o.in_dialogue).collect();
+        assert_eq!(dialogue, vec![false, true]);
+        let operation = service.apply(&request).await.unwrap();
+        assert_eq!(operation.replacements(), 1);
+
+        // "text" and "content" are JSON keys as well as words
+        for (from, to) in [("text", "prose"), ("content", "matter")] {
+            service
+                .apply(&RenameRequest::new(project, from, to))
+                .await
+                .unwrap();
+        }
+
+        let (stored, word_count): (String, i64) =
+            sqlx::query_as("SELECT content, word_count FROM documents WHERE id = ?1")
+                .bind(document.to_string())
+                .fetch_one(&db.read().await.pool)
+                .await
+                .unwrap();
+        let stored: serde_json::Value = serde_json::from_str(&stored).unwrap();
+        assert_eq!(
+            prosemirror::plain_text(&stored),
+            "Prose about Jo.\n\"Mara!\" he said."
+        );
+        assert_eq!(
+            stored["content"][0]["content"][1]["marks"][0]["type"],
+            "italic"
+        );
+        assert_eq!(word_count, 6);
+    }
+}
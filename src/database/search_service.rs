@@ -2,20 +2,134 @@
 //!
 //! Provides comprehensive full-text search functionality with BM25 ranking,
 //! caching, analytics, and performance optimization using SQLite FTS5.
-
+//!
+//! The index is an FTS5 table over document titles and text, kept up to
+//! date by triggers, so every path that writes documents (editor saves,
+//! renames, splits, imports) is indexed without going through this service.
+//! Editor documents are indexed by `documents.plain_text`, the text their
+//! writers store next to the ProseMirror JSON, so searches match what the
+//! writer typed and not node types or attributes; other documents are
+//! indexed by their content. Soft-deleted documents are dropped from the
+//! index.
+
+use crate::database::parse::parse_uuid;
+use crate::publishing::escape_xml;
 use crate::{database::DatabaseError, database::DatabaseResult, EnhancedDatabaseService};
 use serde::{Deserialize, Serialize};
+use sqlx::query::QueryAs;
+use sqlx::sqlite::{Sqlite, SqliteArguments};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+/// Full-text index over document titles and text. The index keeps its
+/// own copy of the text, which snippets are cut from.
+const CREATE_FULL_TEXT_INDEX_SQL: &str = r#"
+CREATE VIRTUAL TABLE IF NOT EXISTS documents_fts USING fts5(
+    title,
+    plain_text,
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+
+CREATE TRIGGER IF NOT EXISTS documents_fts_insert AFTER INSERT ON documents
+WHEN new.is_active = 1
+BEGIN
+    INSERT INTO documents_fts(rowid, title, plain_text)
+    VALUES (new.rowid, new.title, COALESCE(new.plain_text, new.content, ''));
+END;
+
+CREATE TRIGGER IF NOT EXISTS documents_fts_delete AFTER DELETE ON documents
+BEGIN
+    DELETE FROM documents_fts WHERE rowid = old.rowid;
+END;
+
+CREATE TRIGGER IF NOT EXISTS documents_fts_update
+AFTER UPDATE OF title, content, plain_text, is_active ON documents
+BEGIN
+    DELETE FROM documents_fts WHERE rowid = old.rowid;
+    INSERT INTO documents_fts(rowid, title, plain_text)
+    SELECT new.rowid, new.title, COALESCE(new.plain_text, new.content, '')
+    WHERE new.is_active = 1;
+END;
+"#;
+
+/// Refill the index from active documents
+const REBUILD_FULL_TEXT_INDEX_SQL: &str = r#"
+DELETE FROM documents_fts;
+INSERT INTO documents_fts(rowid, title, plain_text)
+SELECT rowid, title, COALESCE(plain_text, content, '') FROM documents WHERE is_active = 1;
+"#;
+
+/// The index as it was before it indexed `plain_text`: an external-content
+/// table over the raw content
+const DROP_CONTENT_INDEX_SQL: &str = r#"
+DROP TRIGGER IF EXISTS documents_fts_insert;
+DROP TRIGGER IF EXISTS documents_fts_delete;
+DROP TRIGGER IF EXISTS documents_fts_update;
+DROP TABLE IF EXISTS documents_fts;
+"#;
+
+/// Conditions for the search options, bound as ?4 to ?7
+const FILTER_SQL: &str = "AND (?4 IS NULL OR d.project_id = ?4)
+             AND (?5 IS NULL OR d.document_type = ?5)
+             AND (?6 IS NULL OR d.updated_at >= ?6)
+             AND (?7 IS NULL OR d.updated_at <= ?7)";
+
+/// Documents searched normally: active ones outside trashed projects
+const LIVE_DOCUMENTS_SQL: &str = "d.is_active = 1
+             AND d.project_id NOT IN (SELECT id FROM projects WHERE deleted_at IS NOT NULL)";
+/// Documents searched with `in_trash`
+const TRASHED_DOCUMENTS_SQL: &str = "(d.is_active = 0
+             OR d.project_id IN (SELECT id FROM projects WHERE deleted_at IS NOT NULL))";
+
+/// Title matches count this many times as much as content matches
+const TITLE_WEIGHT: f64 = 10.0;
+/// Tokens of context in a snippet
+const SNIPPET_TOKENS: i64 = 24;
+/// Private-use characters FTS5 wraps matches in; they become `<mark>` tags
+/// once the text around them is escaped
+const MATCH_START: &str = "\u{E000}";
+const MATCH_END: &str = "\u{E001}";
+
+type FullTextRow = (
+    String,
+    String,
+    String,
+    String,
+    f64,
+    String,
+    String,
+    String,
+    String,
+    i64,
+    Option<String>,
+);
+
+type SubstringRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    i64,
+    Option<String>,
+);
+
 /// Search result with ranking and metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub document_id: Uuid,
     pub title: String,
+    /// Title as escaped HTML with matches wrapped in `<mark>`, when
+    /// highlighting
+    pub title_highlight: Option<String>,
+    /// Passage around the best match; escaped HTML with matches wrapped in
+    /// `<mark>` when highlighting
     pub snippet: String,
     pub relevance_score: f32,
     pub rank_position: usize,
@@ -41,6 +155,14 @@ pub struct SearchOptions {
     pub use_bm25: bool,
     pub highlight_matches: bool,
     pub include_metadata: bool,
+    /// Search the FTS5 index; otherwise match the text as a plain substring
+    pub use_full_text: bool,
+    /// Match the last word as a prefix, for search-as-you-type; any word
+    /// can also be given as a prefix with a trailing `*`
+    pub prefix_last_term: bool,
+    /// Search the trash instead: trashed documents and the documents of
+    /// trashed projects. They aren't indexed, so this matches substrings.
+    pub in_trash: bool,
 }
 
 impl Default for SearchOptions {
@@ -56,6 +178,9 @@ impl Default for SearchOptions {
             use_bm25: true,
             highlight_matches: false,
             include_metadata: false,
+            use_full_text: true,
+            prefix_last_term: false,
+            in_trash: false,
         }
     }
 }
@@ -110,6 +235,7 @@ pub struct SearchMetrics {
 #[derive(Debug, Clone)]
 struct CachedSearchResult {
     results: Vec<SearchResult>,
+    cached_at: Instant,
 }
 
 /// Search cache with TTL
@@ -216,6 +342,44 @@ impl SearchService {
         }
     }
 
+    /// Create the full-text index and the triggers that maintain it,
+    /// indexing existing documents the first time
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db_service = self.db_service.read().await;
+        let index: Option<String> = sqlx::query_scalar(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'documents_fts'",
+        )
+        .fetch_optional(&db_service.pool)
+        .await
+        .map_err(|e| DatabaseError::Migration(format!("Failed to check search index: {}", e)))?;
+        let indexed = index.is_some_and(|sql| sql.contains("plain_text"));
+        if !indexed {
+            sqlx::query(DROP_CONTENT_INDEX_SQL)
+                .execute(&db_service.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to replace search index: {}", e))
+                })?;
+        }
+
+        sqlx::query(CREATE_FULL_TEXT_INDEX_SQL)
+            .execute(&db_service.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create search index: {}", e))
+            })?;
+
+        if !indexed {
+            sqlx::query(REBUILD_FULL_TEXT_INDEX_SQL)
+                .execute(&db_service.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to build search index: {}", e))
+                })?;
+        }
+        Ok(())
+    }
+
     /// Basic text search using FTS5
     pub async fn search_documents(
         &self,
@@ -230,6 +394,11 @@ impl SearchService {
     }
 
     /// Advanced search with full options
+    #[tracing::instrument(
+        name = "search.documents",
+        skip_all,
+        fields(correlation_id = crate::correlation::current(), query_chars = query.len())
+    )]
     pub async fn search_documents_advanced(
         &self,
         query: &str,
@@ -242,7 +411,10 @@ impl SearchService {
         if self.config.enable_caching {
             if let Some(cache_key) = self.generate_cache_key(query, &search_options) {
                 let cache = self.cache.read().await;
-                if let Some(cached_result) = cache.get(&cache_key) {
+                if let Some(cached_result) = cache
+                    .get(&cache_key)
+                    .filter(|cached| cached.cached_at.elapsed() < self.config.cache_ttl)
+                {
                     let mut stats = self.statistics.write().await;
                     stats.cache_hit_rate += 1.0;
                     return Ok(cached_result.results.clone());
@@ -250,63 +422,15 @@ impl SearchService {
             }
         }
 
-        let db_service = self.db_service.read().await;
-
-        // Build FTS5 query
-        let fts_query = self.build_fts_query(query)?;
-
-        // Execute simple FTS5 search using sqlx
-        let rows: Vec<(String, String, String, f32, i32, f32, String, String, String, String, i32, Option<String>)> = sqlx::query_as(
-            "SELECT d.id, d.title, substr(d.content, 1, 200) || CASE WHEN length(d.content) > 200 THEN '...' ELSE '' END as snippet,
-                    1.0 as relevance_score, 1 as rank_position, 1.0 as search_rank, d.project_id, d.created_at, d.updated_at, d.document_type, d.word_count, d.metadata
-             FROM documents d
-             WHERE (d.title LIKE '%' || ?1 || '%' OR d.content LIKE '%' || ?1 || '%')
-             AND d.is_active = 1
-             ORDER BY d.title ASC LIMIT ?2 OFFSET ?3"
-        )
-        .bind(&fts_query)
-        .bind(search_options.limit as i32)
-        .bind(search_options.offset as i32)
-        .fetch_all(&db_service.pool)
-        .await
-        .map_err(|e| DatabaseError::Service(format!("Failed to execute search query: {}", e)))?;
-
-        let mut results = Vec::new();
-        for (
-            id,
-            title,
-            snippet,
-            relevance_score,
-            rank_position,
-            search_rank,
-            project_id_str,
-            created_at,
-            updated_at,
-            document_type,
-            word_count,
-            metadata,
-        ) in rows
-        {
-            results.push(SearchResult {
-                document_id: Uuid::parse_str(&id)
-                    .map_err(|e| DatabaseError::Service(format!("Invalid UUID: {}", e)))?,
-                title,
-                snippet,
-                relevance_score,
-                rank_position: rank_position as usize,
-                search_rank,
-                project_id: Uuid::parse_str(&project_id_str)
-                    .map_err(|e| DatabaseError::Service(format!("Invalid UUID: {}", e)))?,
-                created_at,
-                updated_at,
-                document_type,
-                word_count: word_count as usize,
-                metadata,
-            });
-        }
+        let full_text = search_options.use_full_text && !search_options.in_trash;
+        let mut results = if full_text {
+            self.full_text_search(query, &search_options).await?
+        } else {
+            self.substring_search(query, &search_options).await?
+        };
 
-        // Apply BM25 ranking if enabled (placeholder for now)
-        if search_options.use_bm25 {
+        // Apply BM25 ranking if enabled; full-text results arrive ranked
+        if search_options.use_bm25 && !full_text {
             results = self
                 .apply_bm25_ranking(query, results, &search_options)
                 .await?;
@@ -333,6 +457,7 @@ impl SearchService {
 
                 let cached_result = CachedSearchResult {
                     results: results.clone(),
+                    cached_at: Instant::now(),
                 };
 
                 let mut cache = self.cache.write().await;
@@ -528,24 +653,186 @@ impl SearchService {
         Ok(results)
     }
 
+    /// Ranked search of the FTS5 index
+    async fn full_text_search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> DatabaseResult<Vec<SearchResult>> {
+        let fts_query = if options.prefix_last_term {
+            fts_query(query, true)
+                .ok_or_else(|| DatabaseError::Service("Empty query".to_string()))?
+        } else {
+            self.build_fts_query(query)?
+        };
+        let (start, end) = if options.highlight_matches {
+            (MATCH_START, MATCH_END)
+        } else {
+            ("", "")
+        };
+        let order = if options.use_bm25 {
+            "score DESC"
+        } else {
+            "d.title ASC"
+        };
+        let sql = format!(
+            "SELECT d.id, d.title, highlight(documents_fts, 0, ?8, ?9),
+                    snippet(documents_fts, 1, ?8, ?9, '…', {}),
+                    -bm25(documents_fts, {}, 1.0) AS score,
+                    d.project_id, d.created_at, d.updated_at, d.document_type, d.word_count, d.metadata
+             FROM documents_fts
+             JOIN documents d ON d.rowid = documents_fts.rowid
+             WHERE documents_fts MATCH ?1 AND {} {}
+             ORDER BY {} LIMIT ?2 OFFSET ?3",
+            SNIPPET_TOKENS, TITLE_WEIGHT, LIVE_DOCUMENTS_SQL, FILTER_SQL, order
+        );
+
+        let db_service = self.db_service.read().await;
+        let rows: Vec<FullTextRow> = bind_filters(sqlx::query_as(&sql).bind(&fts_query), options)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&db_service.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to execute search query: {}", e))
+            })?;
+
+        rows.into_iter()
+            .map(
+                |(
+                    id,
+                    title,
+                    title_highlight,
+                    snippet,
+                    score,
+                    project_id,
+                    created_at,
+                    updated_at,
+                    document_type,
+                    word_count,
+                    metadata,
+                )| {
+                    // The content snippet is empty when only the title matched
+                    let snippet = if snippet.is_empty() {
+                        title_highlight.clone()
+                    } else {
+                        snippet
+                    };
+                    let (title_highlight, snippet) = if options.highlight_matches {
+                        (mark_matches(&title_highlight), mark_matches(&snippet))
+                    } else {
+                        (title_highlight, snippet)
+                    };
+                    Ok(SearchResult {
+                        document_id: parse_uuid(&id)?,
+                        title,
+                        title_highlight: options.highlight_matches.then_some(title_highlight),
+                        snippet,
+                        relevance_score: score as f32,
+                        rank_position: 0,
+                        search_rank: score as f32,
+                        project_id: parse_uuid(&project_id)?,
+                        created_at,
+                        updated_at,
+                        document_type,
+                        word_count: word_count as usize,
+                        metadata,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    /// Plain substring match on titles and content
+    async fn substring_search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> DatabaseResult<Vec<SearchResult>> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Err(DatabaseError::Service("Empty query".to_string()));
+        }
+        let sql = format!(
+            "SELECT d.id, d.title, substr(COALESCE(d.plain_text, d.content, ''), 1, 200) || CASE WHEN length(COALESCE(d.plain_text, d.content)) > 200 THEN '...' ELSE '' END as snippet,
+                    d.project_id, d.created_at, d.updated_at, d.document_type, d.word_count, d.metadata
+             FROM documents d
+             WHERE (d.title LIKE '%' || ?1 || '%' OR COALESCE(d.plain_text, d.content) LIKE '%' || ?1 || '%')
+             AND {} {}
+             ORDER BY d.title ASC LIMIT ?2 OFFSET ?3",
+            if options.in_trash {
+                TRASHED_DOCUMENTS_SQL
+            } else {
+                LIVE_DOCUMENTS_SQL
+            },
+            FILTER_SQL
+        );
+
+        let db_service = self.db_service.read().await;
+        let rows: Vec<SubstringRow> = bind_filters(sqlx::query_as(&sql).bind(query), options)
+            .fetch_all(&db_service.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to execute search query: {}", e))
+            })?;
+
+        rows.into_iter()
+            .map(
+                |(
+                    id,
+                    title,
+                    snippet,
+                    project_id,
+                    created_at,
+                    updated_at,
+                    document_type,
+                    word_count,
+                    metadata,
+                )| {
+                    Ok(SearchResult {
+                        document_id: parse_uuid(&id)?,
+                        title,
+                        title_highlight: None,
+                        snippet,
+                        relevance_score: 1.0,
+                        rank_position: 0,
+                        search_rank: 1.0,
+                        project_id: parse_uuid(&project_id)?,
+                        created_at,
+                        updated_at,
+                        document_type,
+                        word_count: word_count as usize,
+                        metadata,
+                    })
+                },
+            )
+            .collect()
+    }
+
     /// Get search suggestions for auto-complete
     pub async fn get_search_suggestions(
         &self,
         partial_query: &str,
         limit: usize,
     ) -> DatabaseResult<Vec<String>> {
+        let Some(fts_query) = fts_query(partial_query, true) else {
+            return Ok(Vec::new());
+        };
         let db_service = self.db_service.read().await;
 
-        // Get suggestions from FTS5 suggestion functionality using sqlx
-        let suggestions: Vec<(String,)> =
-            sqlx::query_as("SELECT title FROM document_fts WHERE document_fts MATCH ?1 LIMIT ?2")
-                .bind(format!("{}*", partial_query))
-                .bind(limit as i32)
-                .fetch_all(&db_service.pool)
-                .await
-                .map_err(|e| {
-                    DatabaseError::Service(format!("Failed to get search suggestions: {}", e))
-                })?;
+        // Titles whose words start with what was typed
+        let suggestions: Vec<(String,)> = sqlx::query_as(&format!(
+            "SELECT d.title FROM documents_fts
+             JOIN documents d ON d.rowid = documents_fts.rowid
+             WHERE documents_fts MATCH ?1 AND {}
+             ORDER BY rank LIMIT ?2",
+            LIVE_DOCUMENTS_SQL
+        ))
+        .bind(format!("title : ({})", fts_query))
+        .bind(limit as i32)
+        .fetch_all(&db_service.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to get search suggestions: {}", e)))?;
 
         let mut result = Vec::new();
         for (title,) in suggestions {
@@ -559,24 +846,16 @@ impl SearchService {
     pub async fn update_search_index(&self) -> DatabaseResult<()> {
         let db_service = self.db_service.read().await;
 
-        // Clear existing index
-        sqlx::query("DELETE FROM document_fts")
+        // Repopulate index from documents table
+        sqlx::query(REBUILD_FULL_TEXT_INDEX_SQL)
             .execute(&db_service.pool)
             .await
-            .map_err(|e| DatabaseError::Service(format!("Failed to clear search index: {}", e)))?;
-
-        // Repopulate index from documents table
-        sqlx::query(
-            "INSERT INTO document_fts(id, title, content, project_id)
-             SELECT id, title, COALESCE(content, ''), project_id
-             FROM documents WHERE is_active = 1",
-        )
-        .execute(&db_service.pool)
-        .await
-        .map_err(|e| DatabaseError::Service(format!("Failed to rebuild search index: {}", e)))?;
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to rebuild search index: {}", e))
+            })?;
 
         // Optimize the FTS index
-        sqlx::query("INSERT INTO document_fts(document_fts) VALUES('optimize')")
+        sqlx::query("INSERT INTO documents_fts(documents_fts) VALUES('optimize')")
             .execute(&db_service.pool)
             .await
             .map_err(|e| {
@@ -626,24 +905,7 @@ impl SearchService {
 
     /// Build FTS5 query from user query
     fn build_fts_query(&self, query: &str) -> DatabaseResult<String> {
-        if query.trim().is_empty() {
-            return Err(DatabaseError::Service("Empty query".to_string()));
-        }
-
-        // Simple FTS5 query processing
-        // In a full implementation, this would handle:
-        // - Quoted phrases
-        // - Boolean operators (AND, OR, NOT)
-        // - Wildcards
-        // - Proximity searches
-        let processed_query = query
-            .trim()
-            .replace("AND ", "")
-            .replace("OR ", "")
-            .replace("NOT ", "-")
-            .replace("\"", "\"");
-
-        Ok(processed_query.to_string())
+        fts_query(query, false).ok_or_else(|| DatabaseError::Service("Empty query".to_string()))
     }
 
     /// Apply BM25 ranking to search results (placeholder)
@@ -714,6 +976,15 @@ impl SearchService {
                 .unwrap_or_default(),
             options.document_type_filter.clone().unwrap_or_default(),
             options.use_bm25.to_string(),
+            options.use_full_text.to_string(),
+            options.highlight_matches.to_string(),
+            options.prefix_last_term.to_string(),
+            options.in_trash.to_string(),
+            options
+                .date_range
+                .as_ref()
+                .map(|range| format!("{}-{}", range.start_date, range.end_date))
+                .unwrap_or_default(),
         ];
 
         Some(key_parts.join("|"))
@@ -750,3 +1021,331 @@ impl SearchService {
         Ok(())
     }
 }
+
+/// Bind paging (?2, ?3) and the filters (?4 to ?7) after the query text
+fn bind_filters<'q, O>(
+    query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    options: &SearchOptions,
+) -> QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    query
+        .bind(options.limit as i64)
+        .bind(options.offset as i64)
+        .bind(options.project_filter.map(|id| id.to_string()))
+        .bind(options.document_type_filter.clone())
+        .bind(
+            options
+                .date_range
+                .as_ref()
+                .map(|range| range.start_date.to_rfc3339()),
+        )
+        .bind(
+            options
+                .date_range
+                .as_ref()
+                .map(|range| range.end_date.to_rfc3339()),
+        )
+}
+
+/// Escape highlighted text as HTML, then turn the match markers into
+/// `<mark>` tags; document text can never add markup of its own
+fn mark_matches(text: &str) -> String {
+    escape_xml(text)
+        .replace(MATCH_START, "<mark>")
+        .replace(MATCH_END, "</mark>")
+}
+
+/// One piece of a user's query
+#[derive(Debug, PartialEq)]
+enum QueryToken {
+    Operator(&'static str),
+    Term {
+        text: String,
+        phrase: bool,
+        prefix: bool,
+        negated: bool,
+    },
+}
+
+/// Turn what the user typed into an FTS5 query. Words and "quoted phrases"
+/// are matched literally, a trailing `*` makes a prefix match, AND, OR and
+/// NOT combine terms and a leading `-` excludes one. Characters FTS5 would
+/// read as syntax are quoted away, so any input is a valid query. `None`
+/// when nothing searchable is left.
+fn fts_query(query: &str, prefix_last_term: bool) -> Option<String> {
+    let mut tokens = tokenize_query(query);
+    if prefix_last_term {
+        if let Some(QueryToken::Term {
+            phrase: false,
+            negated: false,
+            prefix,
+            ..
+        }) = tokens.last_mut()
+        {
+            *prefix = true;
+        }
+    }
+
+    let mut parts: Vec<String> = Vec::new();
+    let mut pending: Option<&'static str> = None;
+    for token in tokens {
+        match token {
+            QueryToken::Operator(operator) => {
+                if !parts.is_empty() {
+                    pending = Some(operator);
+                }
+            }
+            QueryToken::Term {
+                text,
+                prefix,
+                negated,
+                ..
+            } => {
+                let operator = if negated { Some("NOT") } else { pending.take() };
+                pending = None;
+                // NOT needs something on its left to exclude from
+                if operator == Some("NOT") && parts.is_empty() {
+                    continue;
+                }
+                if let Some(operator) = operator.filter(|_| !parts.is_empty()) {
+                    parts.push(operator.to_string());
+                }
+                let star = if prefix { "*" } else { "" };
+                parts.push(format!("\"{}\"{}", text.replace('"', "\"\""), star));
+            }
+        }
+    }
+
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+fn tokenize_query(query: &str) -> Vec<QueryToken> {
+    let mut tokens = Vec::new();
+    let mut rest = query.trim_start();
+    while !rest.is_empty() {
+        let (raw, phrase, remaining) = if let Some(after) = rest.strip_prefix('"') {
+            let end = after.find('"').unwrap_or(after.len());
+            let remaining = after.get(end + 1..).unwrap_or("");
+            (&after[..end], true, remaining)
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '"')
+                .unwrap_or(rest.len());
+            (&rest[..end], false, &rest[end..])
+        };
+        let (remaining, phrase_prefix) = match remaining.strip_prefix('*') {
+            Some(remaining) if phrase => (remaining, true),
+            _ => (remaining, false),
+        };
+        rest = remaining.trim_start();
+
+        if !phrase {
+            if let Some(operator) = ["AND", "OR", "NOT"].into_iter().find(|op| *op == raw) {
+                tokens.push(QueryToken::Operator(operator));
+                continue;
+            }
+        }
+        let negated = !phrase && raw.len() > 1 && raw.starts_with('-');
+        let text = if negated { &raw[1..] } else { raw };
+        let prefix = phrase_prefix || (!phrase && text.ends_with('*'));
+        let text = text.trim_end_matches('*');
+        if text.chars().any(char::is_alphanumeric) {
+            tokens.push(QueryToken::Term {
+                text: text.to_string(),
+                phrase,
+                prefix,
+                negated,
+            });
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{prosemirror, DatabaseConfig};
+
+    #[test]
+    fn test_fts_query_quotes_terms_and_keeps_operators() {
+        assert_eq!(fts_query("mara voss", false).unwrap(), "\"mara\" \"voss\"");
+        assert_eq!(
+            fts_query("harb* OR \"grey ship\" -storm", false).unwrap(),
+            "\"harb\"* OR \"grey ship\" NOT \"storm\""
+        );
+        assert_eq!(fts_query("NOT rain AND", false).unwrap(), "\"rain\"");
+        assert_eq!(
+            fts_query("col:umn (x", true).unwrap(),
+            "\"col:umn\" \"(x\"*"
+        );
+        assert_eq!(fts_query("  -  \"\" * ", false), None);
+    }
+
+    #[tokio::test]
+    async fn test_index_follows_document_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Novel', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let harbour = Uuid::new_v4().to_string();
+        db.create_document(
+            harbour.clone(),
+            project.to_string(),
+            "The Harbour".to_string(),
+            "Rain over the harbour. The grey ship waited for the tide.".to_string(),
+        )
+        .await
+        .unwrap();
+        let pool = db.pool.clone();
+        let service = SearchService::with_config(
+            Arc::new(RwLock::new(db)),
+            SearchConfig {
+                enable_caching: false,
+                ..SearchConfig::default()
+            },
+        );
+        service.initialize().await.unwrap();
+
+        // Documents from before the index existed are indexed
+        let options = SearchOptions {
+            highlight_matches: true,
+            ..SearchOptions::default()
+        };
+        let results = service
+            .search_documents_advanced("harb*", Some(options.clone()))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].title_highlight.as_deref(),
+            Some("The <mark>Harbour</mark>")
+        );
+        assert!(results[0].snippet.contains("<mark>harbour</mark>"));
+
+        // Document text is escaped, so only the marks are markup
+        let script = Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO documents (id, project_id, title, content, document_type, word_count, checksum, created_at, updated_at, is_active, version)
+             VALUES (?1, ?2, 'Tide & <b>Time</b>', '<img src=x onerror=alert(1)> tide tables', 'markdown', 4, '', ?3, ?3, 1, 1)",
+        )
+        .bind(&script)
+        .bind(project.to_string())
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(&pool)
+        .await
+        .unwrap();
+        let results = service
+            .search_documents_advanced("tide", Some(options.clone()))
+            .await
+            .unwrap();
+        let escaped = results
+            .iter()
+            .find(|r| r.document_id.to_string() == script)
+            .unwrap();
+        assert_eq!(
+            escaped.title_highlight.as_deref(),
+            Some("<mark>Tide</mark> &amp; &lt;b&gt;Time&lt;/b&gt;")
+        );
+        assert!(escaped
+            .snippet
+            .starts_with("&lt;img src=x onerror=alert(1)&gt; <mark>tide</mark>"));
+        sqlx::query("DELETE FROM documents WHERE id = ?1")
+            .bind(&script)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Inserts and updates are indexed by the triggers
+        let lighthouse = Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO documents (id, project_id, title, content, document_type, word_count, checksum, created_at, updated_at, is_active, version)
+             VALUES (?1, ?2, 'Lighthouse', 'The keeper watched the harbour lights.', 'markdown', 6, '', ?3, ?3, 1, 1)",
+        )
+        .bind(&lighthouse)
+        .bind(project.to_string())
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "UPDATE documents SET content = 'Sunshine and calm water.', version = version + 1
+             WHERE id = ?1",
+        )
+        .bind(&harbour)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let results = service.search_documents("harbour", None).await.unwrap();
+        let titles: Vec<&str> = results.iter().map(|r| r.title.as_str()).collect();
+        // The title match ranks first
+        assert_eq!(titles, ["The Harbour", "Lighthouse"]);
+        assert!(service
+            .search_documents("rain", None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            service.get_search_suggestions("lig", 5).await.unwrap(),
+            ["Lighthouse"]
+        );
+
+        // Soft-deleted documents drop out
+        sqlx::query("UPDATE documents SET is_active = 0 WHERE id = ?1")
+            .bind(&lighthouse)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let results = service.search_documents("keeper", None).await.unwrap();
+        assert!(results.is_empty());
+        let in_trash = SearchOptions {
+            in_trash: true,
+            ..SearchOptions::default()
+        };
+        let results = service
+            .search_documents_advanced("keeper", Some(in_trash))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        service.update_search_index().await.unwrap();
+        let results = service.search_documents("sunshine", None).await.unwrap();
+        assert_eq!(results.len(), 1);
+
+        // Editor documents are searched by their text, not their JSON
+        let squall = serde_json::json!({
+            "type": "doc",
+            "content": prosemirror::paragraphs("A storm over the bay."),
+        });
+        service
+            .db_service
+            .read()
+            .await
+            .create_document(
+                Uuid::new_v4().to_string(),
+                project.to_string(),
+                "Squall".to_string(),
+                squall.to_string(),
+            )
+            .await
+            .unwrap();
+        let results = service.search_documents("storm", None).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].snippet.contains("over the bay."));
+        assert!(!results[0].snippet.contains("paragraph"));
+        assert!(service
+            .search_documents("paragraph", None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
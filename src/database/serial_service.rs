@@ -0,0 +1,500 @@
+//! Serial Service
+//!
+//! Release planning for web serials. Each chapter is scheduled for a date
+//! on a platform and tracked from planned through staged to published. A
+//! background timer stages a chapter a day before it is due, writing it as
+//! an HTML page to the serial staging folder ready to upload, and reminds
+//! the writer once its release time arrives.
+
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::automation::NotificationLevel;
+use crate::database::{
+    models::serial::*,
+    parse::{parse_time, parse_uuid},
+    DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+use crate::notifications::{DesktopNotification, Notifier};
+use crate::publishing::{PublishedDocument, PublishedSection};
+
+type ReleaseRow = (
+    String,
+    String,
+    String,
+    i64,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    bool,
+    String,
+    String,
+    String,
+);
+
+/// How long before its release a chapter is staged
+const STAGE_AHEAD_HOURS: i64 = 24;
+
+/// Service for serial release plans, staging and release reminders
+#[derive(Debug)]
+pub struct SerialService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    staging_dir: PathBuf,
+}
+
+impl SerialService {
+    /// Create a new serial service staging chapters under `staging_dir`
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>, staging_dir: PathBuf) -> Self {
+        Self {
+            db_service,
+            staging_dir,
+        }
+    }
+
+    /// Initialize the serial releases table
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_SERIAL_RELEASES_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create serial releases table: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Save a release. Moving a pending release to a later date lets its
+    /// reminder show again.
+    pub async fn save_release(&self, release: &SerialRelease) -> DatabaseResult<SerialRelease> {
+        if release.platform.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Release platform cannot be empty".to_string(),
+            ));
+        }
+        if release.chapter_number == 0 {
+            return Err(DatabaseError::ValidationError(
+                "Chapter numbers start at 1".to_string(),
+            ));
+        }
+        self.chapter(release).await?;
+
+        let mut saved = release.clone();
+        saved.updated_at = Utc::now();
+        if saved.status.is_pending() && saved.publish_at > saved.updated_at {
+            saved.reminded = false;
+        }
+        self.write_release(&saved).await?;
+        Ok(saved)
+    }
+
+    /// Delete a release; its staged file is left in place
+    pub async fn delete_release(&self, release_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM serial_releases WHERE id = ?1")
+            .bind(release_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete release: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A project's release plan
+    pub async fn release_plan(&self, project_id: Uuid) -> DatabaseResult<ReleasePlan> {
+        let releases = self.releases(Some(project_id)).await?;
+        Ok(release_plan(project_id, releases, Utc::now()))
+    }
+
+    /// Write a release's chapter to the staging folder now
+    pub async fn stage_release(&self, release_id: Uuid) -> DatabaseResult<SerialRelease> {
+        let release = self.release(release_id).await?;
+        if release.status == ReleaseStatus::Published {
+            return Err(DatabaseError::ValidationError(format!(
+                "Chapter {} is already published",
+                release.chapter_number
+            )));
+        }
+        self.stage(release).await
+    }
+
+    /// Record that a release went out
+    pub async fn mark_published(
+        &self,
+        release_id: Uuid,
+        published_url: Option<String>,
+    ) -> DatabaseResult<SerialRelease> {
+        let mut release = self.release(release_id).await?;
+        let now = Utc::now();
+        release.status = ReleaseStatus::Published;
+        release.published_url = published_url;
+        release.published_at = Some(now);
+        release.updated_at = now;
+        self.write_release(&release).await?;
+        Ok(release)
+    }
+
+    /// Stage the chapters coming up and remind about the ones due, across
+    /// all projects. Returns how many releases were acted on.
+    pub async fn run_schedule(&self) -> DatabaseResult<usize> {
+        let releases = self.releases(None).await?;
+        let now = Utc::now();
+        let notifier = Notifier::global();
+        let mut handled = 0;
+
+        for release in due_for_staging(&releases, now) {
+            let release = match self.stage(release.clone()).await {
+                Ok(release) => release,
+                Err(e) => {
+                    log::warn!("Failed to stage chapter {}: {}", release.chapter_number, e);
+                    continue;
+                }
+            };
+            let notification = DesktopNotification::new(
+                "Chapter staged",
+                format!(
+                    "Chapter {} is ready to upload to {} for {}",
+                    release.chapter_number,
+                    release.platform,
+                    release.publish_at.format("%b %-d, %H:%M UTC")
+                ),
+                NotificationLevel::Info,
+            );
+            if let Err(e) = notifier.notify(&notification) {
+                log::warn!("Failed to show staging notification: {}", e);
+            }
+            handled += 1;
+        }
+
+        for release in due_reminders(&releases, now) {
+            let notification = DesktopNotification::new(
+                "Chapter release due",
+                format!(
+                    "Chapter {} is due out on {}",
+                    release.chapter_number, release.platform
+                ),
+                NotificationLevel::Warning,
+            );
+            if let Err(e) = notifier.notify(&notification) {
+                log::warn!("Failed to show release reminder: {}", e);
+            }
+            let db = self.db_service.read().await;
+            sqlx::query("UPDATE serial_releases SET reminded = 1 WHERE id = ?1")
+                .bind(release.id.to_string())
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to mark reminder shown: {}", e))
+                })?;
+            handled += 1;
+        }
+        Ok(handled)
+    }
+
+    /// Run the release schedule now and then every `every`
+    pub fn spawn_scheduler(
+        self: Arc<Self>,
+        every: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.run_schedule().await {
+                    log::error!("Serial release schedule failed: {}", e);
+                }
+            }
+        })
+    }
+
+    async fn stage(&self, mut release: SerialRelease) -> DatabaseResult<SerialRelease> {
+        let (document_title, content) = self.chapter(&release).await?;
+        let title = if release.title.trim().is_empty() {
+            document_title
+        } else {
+            release.title.clone()
+        };
+        // The page title names the chapter, so its one section goes untitled
+        let mut page =
+            PublishedDocument::new(format!("Chapter {}: {}", release.chapter_number, title));
+        page.sections
+            .push(PublishedSection::from_text(String::new(), &content));
+
+        let path = self
+            .staging_dir
+            .join(release.project_id.to_string())
+            .join(staged_file_name(&release));
+        let html = page.render_html();
+        let write = async {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, html).await
+        };
+        write.await.map_err(|e| {
+            DatabaseError::Service(format!(
+                "Failed to stage chapter {}: {}",
+                release.chapter_number, e
+            ))
+        })?;
+
+        release.status = ReleaseStatus::Staged;
+        release.staged_path = Some(path.to_string_lossy().to_string());
+        release.updated_at = Utc::now();
+        self.write_release(&release).await?;
+        Ok(release)
+    }
+
+    /// Title and text of the release's document, which must belong to the
+    /// release's project
+    async fn chapter(&self, release: &SerialRelease) -> DatabaseResult<(String, String)> {
+        let db = self.db_service.read().await;
+        let row: Option<(String, Option<String>)> = sqlx::query_as(
+            "SELECT title, content FROM documents WHERE id = ?1 AND project_id = ?2 AND is_active = 1",
+        )
+        .bind(release.document_id.to_string())
+        .bind(release.project_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load chapter: {}", e)))?;
+        let (title, content) = row.ok_or_else(|| DatabaseError::RecordNotFound {
+            entity: "document".to_string(),
+            id: release.document_id.to_string(),
+        })?;
+        Ok((title, content.unwrap_or_default()))
+    }
+
+    async fn release(&self, release_id: Uuid) -> DatabaseResult<SerialRelease> {
+        let db = self.db_service.read().await;
+        let row: Option<ReleaseRow> =
+            sqlx::query_as(&format!("{} WHERE id = ?1", SELECT_SERIAL_RELEASES_SQL))
+                .bind(release_id.to_string())
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to load release: {}", e)))?;
+        row.map(release_from_row)
+            .transpose()?
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "release".to_string(),
+                id: release_id.to_string(),
+            })
+    }
+
+    async fn releases(&self, project_id: Option<Uuid>) -> DatabaseResult<Vec<SerialRelease>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<ReleaseRow> = match project_id {
+            Some(project_id) => {
+                sqlx::query_as(&format!(
+                    "{} WHERE project_id = ?1 ORDER BY publish_at ASC, chapter_number ASC",
+                    SELECT_SERIAL_RELEASES_SQL
+                ))
+                .bind(project_id.to_string())
+                .fetch_all(&db.pool)
+                .await
+            }
+            None => {
+                sqlx::query_as(&format!(
+                    "{} ORDER BY publish_at ASC, chapter_number ASC",
+                    SELECT_SERIAL_RELEASES_SQL
+                ))
+                .fetch_all(&db.pool)
+                .await
+            }
+        }
+        .map_err(|e| DatabaseError::Service(format!("Failed to list releases: {}", e)))?;
+        rows.into_iter().map(release_from_row).collect()
+    }
+
+    async fn write_release(&self, release: &SerialRelease) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_SERIAL_RELEASE_SQL)
+            .bind(release.id.to_string())
+            .bind(release.project_id.to_string())
+            .bind(release.document_id.to_string())
+            .bind(i64::from(release.chapter_number))
+            .bind(&release.title)
+            .bind(release.publish_at.to_rfc3339())
+            .bind(&release.platform)
+            .bind(release.status.as_str())
+            .bind(&release.staged_path)
+            .bind(&release.published_url)
+            .bind(release.published_at.map(|t| t.to_rfc3339()))
+            .bind(release.reminded)
+            .bind(&release.notes)
+            .bind(release.created_at.to_rfc3339())
+            .bind(release.updated_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save release: {}", e)))?;
+        Ok(())
+    }
+}
+
+/// Releases sorted by date with the next one per platform, late releases
+/// and chapters planned twice on a platform
+pub fn release_plan(
+    project_id: Uuid,
+    mut releases: Vec<SerialRelease>,
+    now: DateTime<Utc>,
+) -> ReleasePlan {
+    releases.sort_by_key(|r| (r.publish_at, r.chapter_number));
+
+    let mut next: BTreeMap<&str, &SerialRelease> = BTreeMap::new();
+    let mut seen = HashSet::new();
+    let mut duplicate_chapters = Vec::new();
+    for release in &releases {
+        if release.status.is_pending() {
+            next.entry(release.platform.as_str()).or_insert(release);
+        }
+        if release.status != ReleaseStatus::Skipped
+            && !seen.insert((release.platform.as_str(), release.chapter_number))
+            && !duplicate_chapters.contains(&release.chapter_number)
+        {
+            duplicate_chapters.push(release.chapter_number);
+        }
+    }
+    duplicate_chapters.sort_unstable();
+
+    ReleasePlan {
+        project_id,
+        next: next.into_values().cloned().collect(),
+        late: releases
+            .iter()
+            .filter(|r| r.status.is_pending() && r.publish_at <= now)
+            .count(),
+        duplicate_chapters,
+        releases,
+    }
+}
+
+/// Planned releases that go out within the staging window
+pub fn due_for_staging(releases: &[SerialRelease], now: DateTime<Utc>) -> Vec<&SerialRelease> {
+    releases
+        .iter()
+        .filter(|r| {
+            r.status == ReleaseStatus::Planned
+                && r.publish_at - now <= Duration::hours(STAGE_AHEAD_HOURS)
+        })
+        .collect()
+}
+
+/// Pending releases whose time has come and that haven't been reminded of
+pub fn due_reminders(releases: &[SerialRelease], now: DateTime<Utc>) -> Vec<&SerialRelease> {
+    releases
+        .iter()
+        .filter(|r| r.status.is_pending() && !r.reminded && r.publish_at <= now)
+        .collect()
+}
+
+/// File a release is staged as, e.g. "royal-road/chapter-007.html"
+pub fn staged_file_name(release: &SerialRelease) -> PathBuf {
+    let platform: String = release
+        .platform
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    let platform = platform
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    PathBuf::from(platform).join(format!("chapter-{:03}.html", release.chapter_number))
+}
+
+fn release_from_row(row: ReleaseRow) -> DatabaseResult<SerialRelease> {
+    let (
+        id,
+        project_id,
+        document_id,
+        chapter_number,
+        title,
+        publish_at,
+        platform,
+        status,
+        staged_path,
+        published_url,
+        published_at,
+        reminded,
+        notes,
+        created_at,
+        updated_at,
+    ) = row;
+    Ok(SerialRelease {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        document_id: parse_uuid(&document_id)?,
+        chapter_number: u32::try_from(chapter_number).unwrap_or(0),
+        title,
+        publish_at: parse_time(&publish_at)?,
+        platform,
+        status: ReleaseStatus::parse(&status),
+        staged_path,
+        published_url,
+        published_at: published_at.as_deref().map(parse_time).transpose()?,
+        reminded,
+        notes,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_staging_and_reminders() {
+        let now = Utc::now();
+        let project = Uuid::new_v4();
+        let release = |chapter: u32, hours: i64, platform: &str, status: ReleaseStatus| {
+            let mut release = SerialRelease::new(
+                project,
+                Uuid::new_v4(),
+                chapter,
+                now + Duration::hours(hours),
+                platform,
+            );
+            release.status = status;
+            release
+        };
+        let releases = vec![
+            release(3, 72, "Royal Road", ReleaseStatus::Planned),
+            release(1, -48, "Royal Road", ReleaseStatus::Published),
+            release(2, 12, "Royal Road", ReleaseStatus::Planned),
+            release(2, -2, "Patreon", ReleaseStatus::Staged),
+            release(3, 96, "Royal Road", ReleaseStatus::Planned),
+        ];
+
+        let plan = release_plan(project, releases.clone(), now);
+        let next: Vec<(&str, u32)> = plan
+            .next
+            .iter()
+            .map(|r| (r.platform.as_str(), r.chapter_number))
+            .collect();
+        assert_eq!(next, vec![("Patreon", 2), ("Royal Road", 2)]);
+        assert_eq!(plan.late, 1);
+        assert_eq!(plan.duplicate_chapters, vec![3]);
+
+        let staging: Vec<u32> = due_for_staging(&releases, now)
+            .iter()
+            .map(|r| r.chapter_number)
+            .collect();
+        assert_eq!(staging, vec![2]);
+        let reminders = due_reminders(&releases, now);
+        assert_eq!(reminders.len(), 1);
+        assert_eq!(reminders[0].platform, "Patreon");
+
+        assert_eq!(
+            staged_file_name(&releases[0]),
+            PathBuf::from("royal-road").join("chapter-003.html")
+        );
+    }
+}
@@ -3,11 +3,14 @@
 //! Centralized service initialization, dependency injection container,
 //! and comprehensive lifecycle management for all database services.
 
+use crate::database::backup_service::{BackupKeys, SecureBackupConfig};
 use crate::database::DatabaseConfig;
 use crate::database::{
-    BackupService, DatabaseError, DatabaseResult, EnhancedDatabaseService,
-    ProjectManagementService, SearchService, VectorEmbeddingService,
+    BackupService, DatabaseError, DatabaseResult, EnhancedDatabaseService, ExportRepository,
+    ProfileService, ProjectManagementService, SearchService, VectorEmbeddingService,
 };
+use crate::security::confirmation::ConfirmationGuard;
+use crate::security::secure_storage::SecureStorageService;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -19,6 +22,13 @@ pub struct ServiceFactory {
     pub database_config: DatabaseConfig,
     pub db_path: PathBuf,
     pub backup_dir: PathBuf,
+    /// Shared by services that perform irreversible operations
+    pub confirmation_guard: Arc<ConfirmationGuard>,
+    /// Whether backups are encrypted; on unless explicitly turned off
+    pub backup_config: SecureBackupConfig,
+    /// Keys for encrypted backups; without them encrypted backups are
+    /// refused
+    pub backup_keys: Option<BackupKeys>,
 }
 
 impl ServiceFactory {
@@ -27,11 +37,15 @@ impl ServiceFactory {
         let db_path = PathBuf::from("data/database.db");
         let backup_dir = PathBuf::from("data/backups");
         let database_config = DatabaseConfig::default();
+        let backup_config = SecureBackupConfig::default();
 
         let factory = Self {
             database_config,
             db_path: db_path.clone(),
             backup_dir: backup_dir.clone(),
+            confirmation_guard: Arc::new(ConfirmationGuard::new()),
+            backup_keys: load_backup_keys(&backup_config),
+            backup_config,
         };
 
         // Initialize database directory
@@ -51,10 +65,14 @@ impl ServiceFactory {
         backup_dir: &Path,
         config: DatabaseConfig,
     ) -> DatabaseResult<Self> {
+        let backup_config = SecureBackupConfig::default();
         let factory = Self {
             database_config: config.clone(),
             db_path: db_path.to_path_buf(),
             backup_dir: backup_dir.to_path_buf(),
+            confirmation_guard: Arc::new(ConfirmationGuard::new()),
+            backup_keys: load_backup_keys(&backup_config),
+            backup_config,
         };
 
         // Initialize directories
@@ -81,27 +99,51 @@ impl ServiceFactory {
 
         container.database_service = Some(db_service.clone());
 
+        // Initialize ProfileService before the services that limit what
+        // the active profile sees
+        let profile_service = Arc::new(ProfileService::new(db_service.clone()));
+        profile_service.initialize().await?;
+        container.profile_service = Some(profile_service.clone());
+
         // Initialize ProjectManagementService (depends on database service)
-        let project_service = Arc::new(RwLock::new(ProjectManagementService::new(
-            db_service.clone(),
-        )));
+        let project_service = Arc::new(RwLock::new(
+            ProjectManagementService::new(db_service.clone())
+                .with_confirmation_guard(self.confirmation_guard.clone())
+                .with_profiles(profile_service),
+        ));
         container.project_service = Some(project_service.clone());
 
         // Initialize VectorEmbeddingService (placeholder implementation)
         let vector_service = Arc::new(RwLock::new(VectorEmbeddingService::new(db_service.clone())));
+        vector_service
+            .read()
+            .await
+            .initialize()
+            .await
+            .map_err(|e| DatabaseError::Migration(e.to_string()))?;
         container.vector_service = Some(vector_service.clone());
 
         // Initialize SearchService with database service dependency
         let search_service = Arc::new(RwLock::new(SearchService::new(db_service.clone())));
+        search_service.read().await.initialize().await?;
         container.search_service = Some(search_service.clone());
 
         // Initialize BackupService with database service dependency
-        let backup_service = Arc::new(RwLock::new(BackupService::new(
-            db_service.clone(),
-            &self.db_path,
-        )));
+        let mut backup_service = BackupService::new(db_service.clone(), &self.db_path)
+            .with_confirmation_guard(self.confirmation_guard.clone())
+            .with_config(self.backup_config.clone());
+        if let Some(keys) = &self.backup_keys {
+            backup_service = backup_service.with_encryption(keys.clone());
+        }
+        let backup_service = Arc::new(RwLock::new(backup_service));
+        backup_service.read().await.initialize().await?;
         container.backup_service = Some(backup_service.clone());
 
+        // Initialize ExportRepository so export jobs and templates persist
+        let export_repository = Arc::new(RwLock::new(ExportRepository::new(db_service.clone())));
+        export_repository.read().await.initialize().await?;
+        container.export_repository = Some(export_repository.clone());
+
         container.initialized = true;
         container.initialization_time = Some(chrono::Utc::now());
 
@@ -133,9 +175,11 @@ impl ServiceFactory {
 
         // Check other services (placeholder implementations)
         health_status.add_service_health("project_management", ServiceHealth::Healthy);
+        health_status.add_service_health("profiles", ServiceHealth::Healthy);
         health_status.add_service_health("vector_embedding", ServiceHealth::Healthy);
         health_status.add_service_health("search", ServiceHealth::Healthy);
         health_status.add_service_health("backup", ServiceHealth::Healthy);
+        health_status.add_service_health("export_repository", ServiceHealth::Healthy);
 
         Ok(health_status)
     }
@@ -165,9 +209,19 @@ impl ServiceFactory {
             }
             "project_management" => {
                 if let Some(db_service) = &container.database_service {
-                    container.project_service = Some(Arc::new(RwLock::new(
-                        ProjectManagementService::new(db_service.clone()),
-                    )));
+                    let mut project_service = ProjectManagementService::new(db_service.clone())
+                        .with_confirmation_guard(self.confirmation_guard.clone());
+                    if let Some(profiles) = &container.profile_service {
+                        project_service = project_service.with_profiles(profiles.clone());
+                    }
+                    container.project_service = Some(Arc::new(RwLock::new(project_service)));
+                }
+            }
+            "export_repository" => {
+                if let Some(db_service) = &container.database_service {
+                    let export_repository = ExportRepository::new(db_service.clone());
+                    export_repository.initialize().await?;
+                    container.export_repository = Some(Arc::new(RwLock::new(export_repository)));
                 }
             }
             _ => {
@@ -190,6 +244,28 @@ impl Default for ServiceFactory {
             database_config: DatabaseConfig::default(),
             db_path: PathBuf::from("data/database.db"),
             backup_dir: PathBuf::from("data/backups"),
+            confirmation_guard: Arc::new(ConfirmationGuard::new()),
+            backup_config: SecureBackupConfig::default(),
+            backup_keys: None,
+        }
+    }
+}
+
+/// The installation's backup keys from the OS keyring, when backups are
+/// encrypted. Without them backups fail with `KeyMissing`; they are never
+/// written in plaintext instead.
+fn load_backup_keys(config: &SecureBackupConfig) -> Option<BackupKeys> {
+    if !config.encrypt {
+        return None;
+    }
+    match BackupKeys::load_or_create(&SecureStorageService::new("herding-cats")) {
+        Ok(keys) => Some(keys),
+        Err(e) => {
+            tracing::error!(
+                "Backups are refused until the backup key is available: {}",
+                e
+            );
+            None
         }
     }
 }
@@ -199,9 +275,11 @@ impl Default for ServiceFactory {
 pub struct ServiceContainer {
     pub database_service: Option<Arc<RwLock<EnhancedDatabaseService>>>,
     pub project_service: Option<Arc<RwLock<ProjectManagementService>>>,
+    pub profile_service: Option<Arc<ProfileService>>,
     pub vector_service: Option<Arc<RwLock<VectorEmbeddingService>>>,
     pub search_service: Option<Arc<RwLock<SearchService>>>,
     pub backup_service: Option<Arc<RwLock<BackupService>>>,
+    pub export_repository: Option<Arc<RwLock<ExportRepository>>>,
     pub initialized: bool,
     pub initialization_time: Option<chrono::DateTime<chrono::Utc>>,
 }
@@ -217,9 +295,11 @@ impl ServiceContainer {
         Self {
             database_service: None,
             project_service: None,
+            profile_service: None,
             vector_service: None,
             search_service: None,
             backup_service: None,
+            export_repository: None,
             initialized: false,
             initialization_time: None,
         }
@@ -235,6 +315,11 @@ impl ServiceContainer {
         self.project_service.clone()
     }
 
+    /// Get profile service accessor
+    pub fn profile_service(&self) -> Option<Arc<ProfileService>> {
+        self.profile_service.clone()
+    }
+
     /// Get vector service accessor
     pub fn vector_service(&self) -> Option<Arc<RwLock<VectorEmbeddingService>>> {
         self.vector_service.clone()
@@ -250,6 +335,11 @@ impl ServiceContainer {
         self.backup_service.clone()
     }
 
+    /// Get export repository accessor
+    pub fn export_repository(&self) -> Option<Arc<RwLock<ExportRepository>>> {
+        self.export_repository.clone()
+    }
+
     /// Check if all critical services are available
     pub fn is_healthy(&self) -> bool {
         self.initialized && self.database_service.is_some() && self.project_service.is_some()
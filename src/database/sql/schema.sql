@@ -13,7 +13,8 @@ CREATE TABLE IF NOT EXISTS projects (
     updated_at DATETIME NOT NULL,          -- Last modification timestamp
     is_archived BOOLEAN NOT NULL DEFAULT 0, -- Whether project is archived
     is_active BOOLEAN NOT NULL DEFAULT 0,   -- Whether project is active (single active project)
-    settings TEXT                           -- Optional settings as JSON string
+    settings TEXT,                          -- Optional settings as JSON string
+    deleted_at DATETIME                     -- When the project went to the trash
 );
 
 -- Documents table for document storage
@@ -22,14 +23,16 @@ CREATE TABLE IF NOT EXISTS documents (
     project_id TEXT NOT NULL,               -- Foreign key to projects table
     title TEXT NOT NULL,                    -- Document title
     content TEXT,                           -- Document content (may be large)
+    plain_text TEXT,                        -- Text of ProseMirror content, indexed for search
     document_type TEXT NOT NULL DEFAULT 'markdown', -- Document type (markdown, plain_text, etc.)
     word_count INTEGER NOT NULL DEFAULT 0,  -- Number of words in document
     checksum TEXT NOT NULL,                 -- SHA-256 checksum for integrity verification
     created_at DATETIME NOT NULL,          -- Creation timestamp
     updated_at DATETIME NOT NULL,          -- Last modification timestamp
-    is_active BOOLEAN NOT NULL DEFAULT 1,   -- Whether document is active/not deleted
+    is_active BOOLEAN NOT NULL DEFAULT 1,   -- 0 while the document is in the trash
     version INTEGER NOT NULL DEFAULT 1,     -- Document version number
     metadata TEXT,                          -- Optional metadata as JSON string
+    deleted_at DATETIME,                    -- When the document went to the trash
     FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
 );
 
@@ -39,6 +42,7 @@ CREATE TABLE IF NOT EXISTS document_embeddings (
     document_id TEXT NOT NULL,              -- Foreign key to documents table
     vector_data BLOB NOT NULL,              -- Vector data stored as binary blob
     model_name TEXT NOT NULL,               -- Model used to generate embedding
+    dimensions INTEGER NOT NULL DEFAULT 0,  -- Vector length, fixed per model
     chunk_index INTEGER NOT NULL DEFAULT 0, -- Chunk index within document
     text_chunk TEXT NOT NULL,               -- Original text chunk
     start_char INTEGER NOT NULL,            -- Starting character position
@@ -93,6 +97,11 @@ CREATE TABLE IF NOT EXISTS backup_metadata (
     description TEXT,                       -- Optional description
     success BOOLEAN NOT NULL DEFAULT 0,     -- Whether backup was successful
     error_message TEXT,                     -- Optional error message if backup failed
+    parent_id TEXT,                         -- Backup an incremental or differential backup applies on top of
+    key_id TEXT,                            -- Key the backup file is encrypted with, if any
+    verification_hash TEXT,                 -- SHA-256 of the database the backup restored to when last verified
+    restore_verified BOOLEAN,               -- Whether the last verification restored a sound database
+    verified_at INTEGER,                    -- Last verification timestamp (i64)
     FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
 );
 
@@ -175,11 +184,12 @@ BEGIN
     WHERE id = NEW.id;
 END;
 
--- Automatic version creation when documents are updated
+-- Automatic version creation when a save bumps a document's version;
+-- autosaves leave the version alone and are snapshotted on a cadence
 CREATE TRIGGER IF NOT EXISTS auto_document_version
     AFTER UPDATE ON documents
     FOR EACH ROW
-    WHEN NEW.content != OLD.content OR NEW.title != OLD.title
+    WHEN NEW.version != OLD.version AND (NEW.content != OLD.content OR NEW.title != OLD.title)
 BEGIN
     INSERT INTO document_versions (id, document_id, version, title, content, created_at, change_description)
     VALUES (
@@ -0,0 +1,713 @@
+//! Stats Service
+//!
+//! Records writing sessions, daily word counts and AI usage, and exports
+//! them together with per-chapter metrics as CSV or JSON for spreadsheets
+//! and notebooks.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::models::stats::{
+    AiUsageRecord, ChapterMetrics, StatsDataset, StatsExport, StatsExportRequest, StatsFormat,
+    WordCountPoint, WritingSession, CREATE_STATS_TABLES_SQL, INSERT_AI_USAGE_SQL,
+    SNAPSHOT_WORD_COUNTS_SQL,
+};
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{DatabaseError, DatabaseResult, EnhancedDatabaseService};
+
+type SessionRow = (
+    String,
+    String,
+    String,
+    Option<String>,
+    i64,
+    Option<i64>,
+    i64,
+);
+type AiUsageRow = (
+    String,
+    Option<String>,
+    String,
+    Option<String>,
+    i64,
+    i64,
+    i64,
+    String,
+);
+type ChapterRow = (String, String, Option<String>, i64, i64, String, String);
+
+const SESSION_COLUMNS: &[&str] = &[
+    "id",
+    "project_id",
+    "started_at",
+    "ended_at",
+    "duration_minutes",
+    "words_start",
+    "words_end",
+    "net_words",
+    "documents_edited",
+];
+const WORD_COUNT_COLUMNS: &[&str] = &["date", "words", "change"];
+const CHAPTER_COLUMNS: &[&str] = &[
+    "position",
+    "document_id",
+    "title",
+    "words",
+    "paragraphs",
+    "sentences",
+    "average_sentence_words",
+    "dialogue_percent",
+    "versions",
+    "created_at",
+    "updated_at",
+];
+const AI_USAGE_COLUMNS: &[&str] = &[
+    "id",
+    "project_id",
+    "feature",
+    "model",
+    "prompt_chars",
+    "response_chars",
+    "cost_cents",
+    "created_at",
+];
+
+/// Service for writing statistics
+#[derive(Debug)]
+pub struct StatsService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl StatsService {
+    /// Create a new stats service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the statistics tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_STATS_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create stats tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Start a writing session at the project's current word count
+    pub async fn start_session(&self, project_id: Uuid) -> DatabaseResult<WritingSession> {
+        let words = self.project_words(project_id).await?;
+        let session = WritingSession {
+            id: Uuid::new_v4(),
+            project_id,
+            started_at: Utc::now(),
+            ended_at: None,
+            words_start: words,
+            words_end: None,
+            documents_edited: 0,
+        };
+
+        let db = self.db_service.read().await;
+        sqlx::query(
+            "INSERT INTO writing_sessions (id, project_id, started_at, words_start) VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(session.id.to_string())
+        .bind(project_id.to_string())
+        .bind(session.started_at.to_rfc3339())
+        .bind(words)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to start writing session: {}", e)))?;
+        Ok(session)
+    }
+
+    /// End a writing session and snapshot the day's word counts
+    pub async fn end_session(&self, session_id: Uuid) -> DatabaseResult<WritingSession> {
+        let mut session =
+            self.session(session_id)
+                .await?
+                .ok_or_else(|| DatabaseError::RecordNotFound {
+                    entity: "writing session".to_string(),
+                    id: session_id.to_string(),
+                })?;
+        if session.ended_at.is_some() {
+            return Ok(session);
+        }
+
+        let words = self.project_words(session.project_id).await?;
+        let ended_at = Utc::now();
+        {
+            let db = self.db_service.read().await;
+            let edited: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM documents
+                 WHERE project_id = ?1 AND julianday(updated_at) >= julianday(?2)",
+            )
+            .bind(session.project_id.to_string())
+            .bind(session.started_at.to_rfc3339())
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to count edited documents: {}", e))
+            })?;
+
+            sqlx::query(
+                "UPDATE writing_sessions SET ended_at = ?2, words_end = ?3, documents_edited = ?4 WHERE id = ?1",
+            )
+            .bind(session_id.to_string())
+            .bind(ended_at.to_rfc3339())
+            .bind(words)
+            .bind(edited)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to end writing session: {}", e)))?;
+            session.documents_edited = edited;
+        }
+        session.ended_at = Some(ended_at);
+        session.words_end = Some(words);
+
+        self.snapshot_word_counts(session.project_id).await?;
+        Ok(session)
+    }
+
+    /// Record today's word count of every document in the project. Later
+    /// snapshots on the same day replace earlier ones.
+    pub async fn snapshot_word_counts(&self, project_id: Uuid) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(SNAPSHOT_WORD_COUNTS_SQL)
+            .bind(project_id.to_string())
+            .bind(Utc::now().date_naive().to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to snapshot word counts: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Log an AI request
+    pub async fn record_ai_usage(&self, record: &AiUsageRecord) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(INSERT_AI_USAGE_SQL)
+            .bind(record.id.to_string())
+            .bind(record.project_id.map(|id| id.to_string()))
+            .bind(&record.feature)
+            .bind(&record.model)
+            .bind(record.prompt_chars)
+            .bind(record.response_chars)
+            .bind(record.cost_cents)
+            .bind(record.created_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to record AI usage: {}", e)))?;
+        Ok(())
+    }
+
+    /// Writing sessions of a project, oldest first
+    pub async fn sessions(
+        &self,
+        project_id: Uuid,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> DatabaseResult<Vec<WritingSession>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<SessionRow> = sqlx::query_as(
+            "SELECT id, project_id, started_at, ended_at, words_start, words_end, documents_edited
+             FROM writing_sessions
+             WHERE project_id = ?1
+               AND (?2 IS NULL OR julianday(started_at) >= julianday(?2))
+               AND (?3 IS NULL OR julianday(started_at) <= julianday(?3))
+             ORDER BY started_at",
+        )
+        .bind(project_id.to_string())
+        .bind(from.map(|t| t.to_rfc3339()))
+        .bind(to.map(|t| t.to_rfc3339()))
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load writing sessions: {}", e)))?;
+        rows.into_iter().map(session_from_row).collect()
+    }
+
+    /// Project word count per snapshot day, with the change since the
+    /// previous snapshot
+    pub async fn word_count_trend(
+        &self,
+        project_id: Uuid,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> DatabaseResult<Vec<WordCountPoint>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT day, SUM(word_count) FROM word_count_history
+             WHERE project_id = ?1 GROUP BY day ORDER BY day",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load word counts: {}", e)))?;
+
+        let from = from.map(|t| t.date_naive());
+        let to = to.map(|t| t.date_naive());
+        let mut points = Vec::new();
+        let mut previous = None;
+        for (day, words) in rows {
+            let date = NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+                .map_err(|e| DatabaseError::Service(format!("Invalid snapshot day: {}", e)))?;
+            let change = previous.map_or(0, |p| words - p);
+            previous = Some(words);
+            if from.is_some_and(|f| date < f) || to.is_some_and(|t| date > t) {
+                continue;
+            }
+            points.push(WordCountPoint {
+                date,
+                words,
+                change,
+            });
+        }
+        Ok(points)
+    }
+
+    /// Metrics of each active document, in binder order
+    pub async fn chapter_metrics(&self, project_id: Uuid) -> DatabaseResult<Vec<ChapterMetrics>> {
+        let db = self.db_service.read().await;
+        let has_binder: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'binder_order'",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check schema: {}", e)))?;
+        let sql = if has_binder > 0 {
+            "SELECT d.id, d.title, d.content, d.word_count,
+                    (SELECT COUNT(*) FROM document_versions v WHERE v.document_id = d.id),
+                    d.created_at, d.updated_at
+             FROM documents d LEFT JOIN binder_order b ON b.document_id = d.id
+             WHERE d.project_id = ?1 AND d.is_active = 1
+             ORDER BY b.position IS NULL, b.position, d.created_at, d.title"
+        } else {
+            "SELECT d.id, d.title, d.content, d.word_count,
+                    (SELECT COUNT(*) FROM document_versions v WHERE v.document_id = d.id),
+                    d.created_at, d.updated_at
+             FROM documents d
+             WHERE d.project_id = ?1 AND d.is_active = 1
+             ORDER BY d.created_at, d.title"
+        };
+        let rows: Vec<ChapterRow> = sqlx::query_as(sql)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+
+        rows.into_iter()
+            .enumerate()
+            .map(
+                |(index, (id, title, content, words, versions, created_at, updated_at))| {
+                    let text = text_metrics(content.as_deref().unwrap_or_default());
+                    Ok(ChapterMetrics {
+                        document_id: parse_uuid(&id)?,
+                        title,
+                        position: index + 1,
+                        words,
+                        paragraphs: text.paragraphs,
+                        sentences: text.sentences,
+                        average_sentence_words: text.average_sentence_words,
+                        dialogue_percent: text.dialogue_percent,
+                        versions,
+                        created_at,
+                        updated_at,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    /// AI requests, of one project or all of them, oldest first
+    pub async fn ai_usage(
+        &self,
+        project_id: Option<Uuid>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> DatabaseResult<Vec<AiUsageRecord>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<AiUsageRow> = sqlx::query_as(
+            "SELECT id, project_id, feature, model, prompt_chars, response_chars, cost_cents, created_at
+             FROM ai_usage_log
+             WHERE (?1 IS NULL OR project_id = ?1)
+               AND (?2 IS NULL OR julianday(created_at) >= julianday(?2))
+               AND (?3 IS NULL OR julianday(created_at) <= julianday(?3))
+             ORDER BY created_at",
+        )
+        .bind(project_id.map(|id| id.to_string()))
+        .bind(from.map(|t| t.to_rfc3339()))
+        .bind(to.map(|t| t.to_rfc3339()))
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load AI usage: {}", e)))?;
+        rows.into_iter().map(ai_usage_from_row).collect()
+    }
+
+    /// Build an export of one dataset
+    pub async fn export(&self, request: &StatsExportRequest) -> DatabaseResult<StatsExport> {
+        if let (Some(from), Some(to)) = (request.from, request.to) {
+            if from > to {
+                return Err(DatabaseError::ValidationError(
+                    "The export period ends before it starts".to_string(),
+                ));
+            }
+        }
+        let project = || {
+            request.project_id.ok_or_else(|| {
+                DatabaseError::ValidationError(format!(
+                    "A project is required to export {}",
+                    request.dataset.as_str()
+                ))
+            })
+        };
+
+        let (rows, content) = match request.dataset {
+            StatsDataset::Sessions => {
+                let sessions = self.sessions(project()?, request.from, request.to).await?;
+                let content = match request.format {
+                    StatsFormat::Json => to_json(&sessions)?,
+                    StatsFormat::Csv => {
+                        to_csv(SESSION_COLUMNS, sessions.iter().map(session_fields))
+                    }
+                };
+                (sessions.len(), content)
+            }
+            StatsDataset::WordCounts => {
+                let points = self
+                    .word_count_trend(project()?, request.from, request.to)
+                    .await?;
+                let content = match request.format {
+                    StatsFormat::Json => to_json(&points)?,
+                    StatsFormat::Csv => to_csv(
+                        WORD_COUNT_COLUMNS,
+                        points.iter().map(|p| {
+                            vec![
+                                p.date.to_string(),
+                                p.words.to_string(),
+                                p.change.to_string(),
+                            ]
+                        }),
+                    ),
+                };
+                (points.len(), content)
+            }
+            StatsDataset::Chapters => {
+                let chapters = self.chapter_metrics(project()?).await?;
+                let content = match request.format {
+                    StatsFormat::Json => to_json(&chapters)?,
+                    StatsFormat::Csv => {
+                        to_csv(CHAPTER_COLUMNS, chapters.iter().map(chapter_fields))
+                    }
+                };
+                (chapters.len(), content)
+            }
+            StatsDataset::AiUsage => {
+                let usage = self
+                    .ai_usage(request.project_id, request.from, request.to)
+                    .await?;
+                let content = match request.format {
+                    StatsFormat::Json => to_json(&usage)?,
+                    StatsFormat::Csv => to_csv(AI_USAGE_COLUMNS, usage.iter().map(ai_usage_fields)),
+                };
+                (usage.len(), content)
+            }
+        };
+
+        Ok(StatsExport {
+            dataset: request.dataset,
+            format: request.format,
+            file_name: format!(
+                "{}-{}.{}",
+                request.dataset.as_str(),
+                Utc::now().format("%Y%m%d"),
+                request.format.extension()
+            ),
+            rows,
+            content,
+        })
+    }
+
+    /// Build an export and write it to a file
+    pub async fn export_to_file(
+        &self,
+        request: &StatsExportRequest,
+        path: &Path,
+    ) -> DatabaseResult<StatsExport> {
+        let export = self.export(request).await?;
+        tokio::fs::write(path, &export.content)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to write stats export: {}", e)))?;
+        Ok(export)
+    }
+
+    async fn session(&self, session_id: Uuid) -> DatabaseResult<Option<WritingSession>> {
+        let db = self.db_service.read().await;
+        let row: Option<SessionRow> = sqlx::query_as(
+            "SELECT id, project_id, started_at, ended_at, words_start, words_end, documents_edited
+             FROM writing_sessions WHERE id = ?1",
+        )
+        .bind(session_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load writing session: {}", e)))?;
+        row.map(session_from_row).transpose()
+    }
+
+    async fn project_words(&self, project_id: Uuid) -> DatabaseResult<i64> {
+        let db = self.db_service.read().await;
+        sqlx::query_scalar(
+            "SELECT COALESCE(SUM(word_count), 0) FROM documents WHERE project_id = ?1 AND is_active = 1
+               AND json_extract(CASE WHEN json_valid(metadata) THEN metadata END, '$.exclude_from_word_count') IS NOT 1",
+        )
+        .bind(project_id.to_string())
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to count project words: {}", e)))
+    }
+}
+
+/// Prose measurements of a chapter's text
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextMetrics {
+    pub paragraphs: usize,
+    pub sentences: usize,
+    pub average_sentence_words: f64,
+    pub dialogue_percent: f64,
+}
+
+/// Count paragraphs and sentences, and the share of words in dialogue
+pub fn text_metrics(text: &str) -> TextMetrics {
+    let paragraphs = crate::publishing::split_paragraphs(text);
+    let mut sentences = 0;
+    let mut words = 0;
+    let mut dialogue_words = 0;
+
+    for paragraph in &paragraphs {
+        let mut in_sentence = false;
+        let mut in_quote = false;
+        for word in paragraph.split_whitespace() {
+            let opens = word.starts_with(['"', '\u{201c}']);
+            if opens {
+                in_quote = true;
+            }
+            words += 1;
+            if in_quote {
+                dialogue_words += 1;
+            }
+            in_sentence = true;
+
+            let bare = word.trim_end_matches(['"', '\u{201d}', '\'', '\u{2019}', ')']);
+            if bare.ends_with(['.', '!', '?', '\u{2026}']) {
+                sentences += 1;
+                in_sentence = false;
+            }
+            let closes =
+                word.ends_with('\u{201d}') || (word.ends_with('"') && (!opens || word.len() > 1));
+            if closes {
+                in_quote = false;
+            }
+        }
+        if in_sentence {
+            sentences += 1;
+        }
+    }
+
+    TextMetrics {
+        paragraphs: paragraphs.len(),
+        sentences,
+        average_sentence_words: if sentences == 0 {
+            0.0
+        } else {
+            round2(words as f64 / sentences as f64)
+        },
+        dialogue_percent: if words == 0 {
+            0.0
+        } else {
+            round2(dialogue_words as f64 * 100.0 / words as f64)
+        },
+    }
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+fn to_json<T: serde::Serialize>(rows: &T) -> DatabaseResult<String> {
+    serde_json::to_string_pretty(rows)
+        .map_err(|e| DatabaseError::Service(format!("Failed to serialize stats: {}", e)))
+}
+
+fn to_csv(columns: &[&str], rows: impl Iterator<Item = Vec<String>>) -> String {
+    let mut csv = columns.join(",");
+    csv.push_str("\r\n");
+    for row in rows {
+        let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn session_fields(session: &WritingSession) -> Vec<String> {
+    vec![
+        session.id.to_string(),
+        session.project_id.to_string(),
+        session.started_at.to_rfc3339(),
+        optional(session.ended_at.map(|t| t.to_rfc3339())),
+        optional(session.duration_minutes().map(round2)),
+        session.words_start.to_string(),
+        optional(session.words_end),
+        optional(session.net_words()),
+        session.documents_edited.to_string(),
+    ]
+}
+
+fn chapter_fields(chapter: &ChapterMetrics) -> Vec<String> {
+    vec![
+        chapter.position.to_string(),
+        chapter.document_id.to_string(),
+        chapter.title.clone(),
+        chapter.words.to_string(),
+        chapter.paragraphs.to_string(),
+        chapter.sentences.to_string(),
+        chapter.average_sentence_words.to_string(),
+        chapter.dialogue_percent.to_string(),
+        chapter.versions.to_string(),
+        chapter.created_at.clone(),
+        chapter.updated_at.clone(),
+    ]
+}
+
+fn ai_usage_fields(record: &AiUsageRecord) -> Vec<String> {
+    vec![
+        record.id.to_string(),
+        optional(record.project_id),
+        record.feature.clone(),
+        optional(record.model.as_ref()),
+        record.prompt_chars.to_string(),
+        record.response_chars.to_string(),
+        record.cost_cents.to_string(),
+        record.created_at.to_rfc3339(),
+    ]
+}
+
+fn session_from_row(row: SessionRow) -> DatabaseResult<WritingSession> {
+    let (id, project_id, started_at, ended_at, words_start, words_end, documents_edited) = row;
+    Ok(WritingSession {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        started_at: parse_time(&started_at)?,
+        ended_at: ended_at.as_deref().map(parse_time).transpose()?,
+        words_start,
+        words_end,
+        documents_edited,
+    })
+}
+
+fn ai_usage_from_row(row: AiUsageRow) -> DatabaseResult<AiUsageRecord> {
+    let (id, project_id, feature, model, prompt_chars, response_chars, cost_cents, created_at) =
+        row;
+    Ok(AiUsageRecord {
+        id: parse_uuid(&id)?,
+        project_id: project_id.as_deref().map(parse_uuid).transpose()?,
+        feature,
+        model,
+        prompt_chars,
+        response_chars,
+        cost_cents,
+        created_at: parse_time(&created_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[test]
+    fn test_text_metrics_counts_dialogue() {
+        let metrics = text_metrics("\"Run now,\" she said. He ran.\n\nThe door shut");
+        assert_eq!(metrics.paragraphs, 2);
+        assert_eq!(metrics.sentences, 3);
+        assert_eq!(metrics.dialogue_percent, 22.22);
+    }
+
+    #[tokio::test]
+    async fn test_session_and_usage_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        let now = Utc::now().to_rfc3339();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'X', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(&now)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let service = StatsService::new(Arc::new(RwLock::new(db)));
+        service.initialize().await.unwrap();
+
+        let session = service.start_session(project).await.unwrap();
+        let ended = service.end_session(session.id).await.unwrap();
+        assert_eq!(ended.net_words(), Some(0));
+
+        let mut usage = AiUsageRecord::new(Some(project), "ai_request");
+        usage.model = Some("local, small".to_string());
+        service.record_ai_usage(&usage).await.unwrap();
+
+        let request = StatsExportRequest {
+            project_id: Some(project),
+            dataset: StatsDataset::AiUsage,
+            format: StatsFormat::Csv,
+            from: None,
+            to: None,
+        };
+        let export = service.export(&request).await.unwrap();
+        assert_eq!(export.rows, 1);
+        assert!(export.content.starts_with("id,project_id,feature"));
+        assert!(export.content.contains("\"local, small\""));
+
+        let sessions = service
+            .export(&StatsExportRequest {
+                dataset: StatsDataset::Sessions,
+                format: StatsFormat::Json,
+                ..request.clone()
+            })
+            .await
+            .unwrap();
+        let parsed: Vec<WritingSession> = serde_json::from_str(&sessions.content).unwrap();
+        assert_eq!(parsed.len(), 1);
+
+        assert!(service
+            .export(&StatsExportRequest {
+                project_id: None,
+                dataset: StatsDataset::Chapters,
+                ..request
+            })
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,603 @@
+//! Story Bible Service
+//!
+//! Compiles a project's codex into a story bible: one section per entry
+//! type (summary, characters, places, objects, timeline) with each entry as
+//! a subsection. Entries link to the entries they relate to or mention,
+//! carry their exported images, and time entries follow the project's
+//! calendars. Character relationships are collected in a matrix appendix.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::codex_graph_service::{CodexGraph, GraphEntry};
+use crate::database::models::attachment::AttachmentOwner;
+use crate::database::models::calendar::Timeline;
+use crate::database::models::codex::{
+    CharacterData, CodexEntryType, ObjectData, PlaceData, StoryData, TimeData,
+};
+use crate::database::models::codex_graph::entry_type_from_db;
+use crate::database::models::story_bible::{StoryBible, StoryBibleRequest};
+use crate::database::parse::parse_uuid;
+use crate::database::{
+    AttachmentService, CalendarService, DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+use crate::publishing::{
+    split_paragraphs, PublishedAttachment, PublishedDocument, PublishedSection, PublishedTable,
+};
+
+/// Entry types in the order their sections appear
+const SECTIONS: &[(CodexEntryType, &str)] = &[
+    (CodexEntryType::StorySummary, "Story Summary"),
+    (CodexEntryType::CharacterSheet, "Characters"),
+    (CodexEntryType::Place, "Places"),
+    (CodexEntryType::Object, "Objects"),
+    (CodexEntryType::Time, "Timeline"),
+];
+
+/// A codex entry as loaded for the bible
+#[derive(Debug, Clone)]
+pub struct BibleEntry {
+    pub id: Uuid,
+    pub entry_type: CodexEntryType,
+    pub title: String,
+    pub content: String,
+    /// Type-specific data as stored, e.g. `CharacterData` JSON
+    pub metadata: Option<String>,
+    /// Images to show with the entry
+    pub images: Vec<PublishedAttachment>,
+}
+
+impl BibleEntry {
+    fn data<T: serde::de::DeserializeOwned>(&self) -> Option<T> {
+        serde_json::from_str(self.metadata.as_deref()?).ok()
+    }
+}
+
+/// Service for story bible exports
+#[derive(Debug)]
+pub struct StoryBibleService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    attachments: Option<Arc<AttachmentService>>,
+    calendars: Option<Arc<CalendarService>>,
+}
+
+impl StoryBibleService {
+    /// Create a new story bible service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self {
+            db_service,
+            attachments: None,
+            calendars: None,
+        }
+    }
+
+    /// Include images attached to entries and marked for export
+    pub fn with_attachments(mut self, attachments: Arc<AttachmentService>) -> Self {
+        self.attachments = Some(attachments);
+        self
+    }
+
+    /// Put dated time entries in calendar order
+    pub fn with_calendars(mut self, calendars: Arc<CalendarService>) -> Self {
+        self.calendars = Some(calendars);
+        self
+    }
+
+    /// Compile and render the project's story bible
+    pub async fn export(&self, request: &StoryBibleRequest) -> DatabaseResult<StoryBible> {
+        let (project_name, mut entries) = self.load_entries(request).await?;
+        if entries.is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "The project has no codex entries to compile".to_string(),
+            ));
+        }
+
+        if let (true, Some(attachments)) = (request.include_images, &self.attachments) {
+            for entry in &mut entries {
+                entry.images = attachments
+                    .published_attachments(AttachmentOwner::CodexEntry, entry.id)
+                    .await?
+                    .into_iter()
+                    .filter(|a| a.mime_type.starts_with("image/") && a.data.is_some())
+                    .collect();
+            }
+        }
+
+        let timeline = match &self.calendars {
+            Some(calendars) if request.includes(CodexEntryType::Time) => {
+                Some(calendars.project_timeline(request.project_id).await?)
+            }
+            _ => None,
+        };
+
+        let title = format!("{} - Story Bible", project_name);
+        let (document, matrix_size) = compile(
+            &title,
+            &entries,
+            timeline.as_ref(),
+            request.include_relationship_matrix,
+        );
+        let bytes = document
+            .render(request.format)
+            .map_err(|e| DatabaseError::Service(format!("Failed to render story bible: {}", e)))?;
+
+        Ok(StoryBible {
+            title,
+            format: request.format,
+            entries: entries.len(),
+            images: entries.iter().map(|e| e.images.len()).sum(),
+            matrix_size,
+            bytes,
+        })
+    }
+
+    async fn load_entries(
+        &self,
+        request: &StoryBibleRequest,
+    ) -> DatabaseResult<(String, Vec<BibleEntry>)> {
+        let db = self.db_service.read().await;
+        let (name,): (String,) = sqlx::query_as("SELECT name FROM projects WHERE id = ?1")
+            .bind(request.project_id.to_string())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load project: {}", e)))?
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "project".to_string(),
+                id: request.project_id.to_string(),
+            })?;
+
+        let has_codex: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'codex_entries'",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check schema: {}", e)))?;
+        if has_codex == 0 {
+            return Ok((name, Vec::new()));
+        }
+
+        let rows: Vec<(String, String, String, String, Option<String>)> = sqlx::query_as(
+            "SELECT id, entry_type, title, content, metadata FROM codex_entries
+             WHERE project_id = ?1 AND is_active = 1
+             ORDER BY sort_order, title",
+        )
+        .bind(request.project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load codex entries: {}", e)))?;
+
+        let mut entries = Vec::new();
+        for (id, entry_type, title, content, metadata) in rows {
+            let Some(entry_type) = entry_type_from_db(&entry_type) else {
+                continue;
+            };
+            if !request.includes(entry_type) {
+                continue;
+            }
+            entries.push(BibleEntry {
+                id: parse_uuid(&id)?,
+                entry_type,
+                title,
+                content,
+                metadata,
+                images: Vec::new(),
+            });
+        }
+        Ok((name, entries))
+    }
+}
+
+/// Lay the entries out as a document, returning it with the number of
+/// characters in the relationship matrix
+pub fn compile(
+    title: &str,
+    entries: &[BibleEntry],
+    timeline: Option<&Timeline>,
+    include_matrix: bool,
+) -> (PublishedDocument, usize) {
+    let mut document = PublishedDocument::new(title);
+    let titles: HashMap<Uuid, &str> = entries.iter().map(|e| (e.id, e.title.as_str())).collect();
+
+    // Section indexes are fixed before any content so entries can link
+    // forwards as well as backwards
+    let mut groups: Vec<(&str, Vec<&BibleEntry>)> = Vec::new();
+    let mut section_of: HashMap<Uuid, usize> = HashMap::new();
+    let mut next = 0;
+    for &(entry_type, heading) in SECTIONS {
+        let mut group: Vec<&BibleEntry> = entries
+            .iter()
+            .filter(|e| e.entry_type == entry_type)
+            .collect();
+        if group.is_empty() {
+            continue;
+        }
+        if let (CodexEntryType::Time, Some(timeline)) = (entry_type, timeline) {
+            let order: HashMap<Uuid, usize> = timeline
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(i, e)| (e.event_id, i))
+                .collect();
+            // Undated events keep their codex order after the dated ones
+            group.sort_by_key(|e| order.get(&e.id).copied().unwrap_or(usize::MAX));
+        }
+        next += 1;
+        for entry in &group {
+            section_of.insert(entry.id, next);
+            next += 1;
+        }
+        groups.push((heading, group));
+    }
+
+    let graph = CodexGraph::build(
+        entries
+            .iter()
+            .map(|entry| GraphEntry {
+                id: entry.id,
+                title: entry.title.clone(),
+                entry_type: entry.entry_type,
+                content: entry.content.clone(),
+                character: match entry.entry_type {
+                    CodexEntryType::CharacterSheet => entry.data(),
+                    _ => None,
+                },
+            })
+            .collect(),
+        Vec::new(),
+    );
+    let mut see_also: HashMap<Uuid, Vec<usize>> = HashMap::new();
+    for edge in graph.edges() {
+        for (from, to) in [(edge.source, edge.target), (edge.target, edge.source)] {
+            if let Some(&section) = section_of.get(&to) {
+                let links = see_also.entry(from).or_default();
+                if !links.contains(&section) {
+                    links.push(section);
+                }
+            }
+        }
+    }
+    for links in see_also.values_mut() {
+        links.sort_unstable();
+    }
+
+    let dates: HashMap<Uuid, String> = timeline
+        .map(|timeline| {
+            timeline
+                .entries
+                .iter()
+                .map(|e| {
+                    let when = match &e.end_label {
+                        Some(end) if *end != e.start_label => {
+                            format!("{} to {}", e.start_label, end)
+                        }
+                        _ => e.start_label.clone(),
+                    };
+                    (e.event_id, when)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for (heading, group) in groups {
+        let count = match group.len() {
+            1 => "1 entry".to_string(),
+            n => format!("{} entries", n),
+        };
+        document
+            .sections
+            .push(PublishedSection::new(heading, vec![count]));
+        for entry in group {
+            let mut paragraphs = match entry.entry_type {
+                CodexEntryType::CharacterSheet => entry
+                    .data::<CharacterData>()
+                    .map(|data| character_facts(&data, &titles))
+                    .unwrap_or_default(),
+                CodexEntryType::Place => entry
+                    .data::<PlaceData>()
+                    .map(|data| place_facts(&data))
+                    .unwrap_or_default(),
+                CodexEntryType::Object => entry
+                    .data::<ObjectData>()
+                    .map(|data| object_facts(&data))
+                    .unwrap_or_default(),
+                CodexEntryType::Time => {
+                    time_facts(entry.data::<TimeData>().as_ref(), dates.get(&entry.id))
+                }
+                CodexEntryType::StorySummary => entry
+                    .data::<StoryData>()
+                    .map(|data| story_facts(&data))
+                    .unwrap_or_default(),
+            };
+            paragraphs.extend(split_paragraphs(&entry.content));
+
+            let mut section = PublishedSection::new(entry.title.clone(), paragraphs);
+            section.subsection = true;
+            section.images = entry.images.clone();
+            section.see_also = see_also.remove(&entry.id).unwrap_or_default();
+            document.sections.push(section);
+        }
+    }
+
+    let mut matrix_size = 0;
+    if include_matrix {
+        if let Some(table) = relationship_matrix(entries) {
+            matrix_size = table.rows.len();
+            let mut appendix = PublishedSection::new(
+                "Appendix: Relationship Matrix",
+                vec!["Each row lists the relationships on that character's sheet.".to_string()],
+            );
+            appendix.table = Some(table);
+            document.sections.push(appendix);
+        }
+    }
+
+    (document, matrix_size)
+}
+
+/// Characters with a relationship to or from another character, and how
+/// each row's sheet describes the column's character
+fn relationship_matrix(entries: &[BibleEntry]) -> Option<PublishedTable> {
+    let characters: Vec<(&BibleEntry, CharacterData)> = entries
+        .iter()
+        .filter(|e| e.entry_type == CodexEntryType::CharacterSheet)
+        .filter_map(|e| Some((e, e.data::<CharacterData>()?)))
+        .collect();
+    let related = |id: Uuid| {
+        characters.iter().any(|(entry, data)| {
+            (entry.id == id && !data.relationships.is_empty())
+                || data.relationships.iter().any(|r| r.character_id == id)
+        })
+    };
+    let members: Vec<&(&BibleEntry, CharacterData)> = characters
+        .iter()
+        .filter(|(entry, _)| related(entry.id))
+        .collect();
+    if members.len() < 2 {
+        return None;
+    }
+
+    let mut columns = vec!["Character".to_string()];
+    columns.extend(members.iter().map(|(entry, _)| entry.title.clone()));
+    let rows = members
+        .iter()
+        .map(|(entry, data)| {
+            let mut row = vec![entry.title.clone()];
+            row.extend(members.iter().map(|(other, _)| {
+                let kinds: Vec<&str> = data
+                    .relationships
+                    .iter()
+                    .filter(|r| r.character_id == other.id)
+                    .map(|r| r.relationship_type.trim())
+                    .filter(|kind| !kind.is_empty())
+                    .collect();
+                kinds.join(", ")
+            }));
+            row
+        })
+        .collect();
+    Some(PublishedTable { columns, rows })
+}
+
+fn push_text(paragraphs: &mut Vec<String>, label: &str, value: Option<&String>) {
+    if let Some(value) = value.map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        paragraphs.push(format!("{}: {}", label, value));
+    }
+}
+
+fn push_list(paragraphs: &mut Vec<String>, label: &str, values: &[String]) {
+    let values: Vec<&str> = values
+        .iter()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .collect();
+    if !values.is_empty() {
+        paragraphs.push(format!("{}: {}", label, values.join(", ")));
+    }
+}
+
+fn character_facts(data: &CharacterData, titles: &HashMap<Uuid, &str>) -> Vec<String> {
+    let mut facts = Vec::new();
+    push_list(&mut facts, "Also known as", &data.names);
+    push_text(&mut facts, "Appearance", data.physical_description.as_ref());
+    push_list(&mut facts, "Personality", &data.personality_traits);
+    push_list(&mut facts, "Goals", &data.goals);
+    push_list(&mut facts, "Fears", &data.fears);
+    push_list(&mut facts, "Skills", &data.skills);
+    push_list(&mut facts, "Carries", &data.inventory);
+    let relationships: Vec<String> = data
+        .relationships
+        .iter()
+        .filter_map(|r| {
+            let name = titles.get(&r.character_id)?;
+            Some(match r.relationship_type.trim() {
+                "" => name.to_string(),
+                kind => format!("{} ({})", name, kind),
+            })
+        })
+        .collect();
+    push_list(&mut facts, "Relationships", &relationships);
+    push_text(&mut facts, "First appears", data.first_appearance.as_ref());
+    push_text(&mut facts, "Backstory", data.backstory.as_ref());
+    push_text(&mut facts, "Arc", data.arc.as_ref());
+    facts
+}
+
+fn place_facts(data: &PlaceData) -> Vec<String> {
+    let mut facts = Vec::new();
+    push_list(&mut facts, "Also known as", &data.alternative_names);
+    push_text(&mut facts, "Coordinates", data.coordinates.as_ref());
+    push_text(&mut facts, "Climate", data.climate.as_ref());
+    push_text(&mut facts, "Population", data.population.as_ref());
+    push_text(&mut facts, "Culture", data.culture.as_ref());
+    push_list(&mut facts, "Points of interest", &data.points_of_interest);
+    push_list(&mut facts, "Current events", &data.current_events);
+    push_text(&mut facts, "First appears", data.first_appearance.as_ref());
+    push_text(&mut facts, "History", data.history.as_ref());
+    facts
+}
+
+fn object_facts(data: &ObjectData) -> Vec<String> {
+    let mut facts = Vec::new();
+    push_text(&mut facts, "Type", data.object_type.as_ref());
+    push_text(&mut facts, "Dimensions", data.dimensions.as_ref());
+    push_text(
+        &mut facts,
+        "Weight and materials",
+        data.weight_materials.as_ref(),
+    );
+    push_list(&mut facts, "Properties", &data.properties);
+    push_text(&mut facts, "Location", data.current_location.as_ref());
+    push_text(&mut facts, "Value", data.value.as_ref());
+    push_text(&mut facts, "Usage", data.usage.as_ref());
+    push_text(&mut facts, "History", data.history.as_ref());
+    facts
+}
+
+/// Dates come from the timeline when the event was placed on it, and
+/// from the entry's own text otherwise
+fn time_facts(data: Option<&TimeData>, placed: Option<&String>) -> Vec<String> {
+    let mut facts = Vec::new();
+    let written = data.and_then(|data| match (&data.start_time, &data.end_time) {
+        (Some(start), Some(end)) if start.trim() != end.trim() => {
+            Some(format!("{} to {}", start.trim(), end.trim()))
+        }
+        (Some(start), _) => Some(start.clone()),
+        (None, Some(end)) => Some(format!("until {}", end.trim())),
+        (None, None) => None,
+    });
+    push_text(&mut facts, "When", placed.or(written.as_ref()));
+    if let Some(data) = data {
+        push_text(&mut facts, "Duration", data.duration.as_ref());
+        push_text(&mut facts, "Season", data.season.as_ref());
+        push_text(&mut facts, "Era", data.era.as_ref());
+        push_text(&mut facts, "Calendar", data.calendar_system.as_ref());
+        push_text(&mut facts, "Context", data.historical_context.as_ref());
+    }
+    facts
+}
+
+fn story_facts(data: &StoryData) -> Vec<String> {
+    let mut facts = Vec::new();
+    push_text(&mut facts, "Main plot", data.main_plot.as_ref());
+    push_list(&mut facts, "Subplots", &data.subplots);
+    push_list(&mut facts, "Themes", &data.themes);
+    push_text(&mut facts, "Structure", data.structure.as_ref());
+    push_text(&mut facts, "Point of view", data.point_of_view.as_ref());
+    push_text(&mut facts, "Tone", data.tone.as_ref());
+    push_text(&mut facts, "Audience", data.target_audience.as_ref());
+    facts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::codex::{CharacterRelationship, RelationshipSentiment};
+    use crate::publishing::PublishFormat;
+    use std::io::Read;
+
+    fn entry(entry_type: CodexEntryType, title: &str, metadata: Option<String>) -> BibleEntry {
+        BibleEntry {
+            id: Uuid::new_v4(),
+            entry_type,
+            title: title.to_string(),
+            content: String::new(),
+            metadata,
+            images: Vec::new(),
+        }
+    }
+
+    fn character(title: &str, relationships: &[(Uuid, &str)]) -> BibleEntry {
+        let data = CharacterData {
+            relationships: relationships
+                .iter()
+                .map(|(id, kind)| CharacterRelationship {
+                    character_id: *id,
+                    relationship_type: kind.to_string(),
+                    description: String::new(),
+                    sentiment: RelationshipSentiment::Neutral,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        entry(
+            CodexEntryType::CharacterSheet,
+            title,
+            Some(serde_json::to_string(&data).unwrap()),
+        )
+    }
+
+    #[test]
+    fn test_sections_links_images_and_matrix() {
+        let mara = character("Mara", &[]);
+        let tobin = character("Tobin", &[(mara.id, "sister")]);
+        let wren = character("Wren", &[]);
+        let mut harbour = entry(CodexEntryType::Place, "Greyhaven", None);
+        harbour.content = "A cold harbour town. Tobin keeps a boat here.".to_string();
+        harbour.images.push(PublishedAttachment {
+            file_name: "map.png".to_string(),
+            mime_type: "image/png".to_string(),
+            description: Some("Harbour map".to_string()),
+            data: Some(b"\x89PNG".to_vec()),
+        });
+        let entries = vec![harbour, wren, tobin.clone(), mara];
+
+        let (document, matrix_size) = compile("Bible", &entries, None, true);
+        let titles: Vec<(&str, bool)> = document
+            .sections
+            .iter()
+            .map(|s| (s.title.as_str(), s.subsection))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                ("Characters", false),
+                ("Wren", true),
+                ("Tobin", true),
+                ("Mara", true),
+                ("Places", false),
+                ("Greyhaven", true),
+                ("Appendix: Relationship Matrix", false),
+            ]
+        );
+        // Relationships and mentions link both ways
+        assert_eq!(
+            document.see_also_titles(&document.sections[2]),
+            vec!["Mara", "Greyhaven"]
+        );
+        assert_eq!(
+            document.see_also_titles(&document.sections[3]),
+            vec!["Tobin"]
+        );
+        assert!(document.sections[2]
+            .paragraphs
+            .contains(&"Relationships: Mara (sister)".to_string()));
+
+        // Wren has no relationships and stays out of the matrix
+        assert_eq!(matrix_size, 2);
+        let table = document.sections[6].table.as_ref().unwrap();
+        assert_eq!(table.columns, vec!["Character", "Tobin", "Mara"]);
+        assert_eq!(table.row_text(&table.rows[0]), "Tobin - Mara: sister");
+        assert_eq!(table.row_text(&table.rows[1]), "Mara - none");
+
+        let epub = document.render(PublishFormat::Epub).unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(epub)).unwrap();
+        let mut chapter = String::new();
+        archive
+            .by_name("OEBPS/xhtml/chapter_3.xhtml")
+            .unwrap()
+            .read_to_string(&mut chapter)
+            .unwrap();
+        assert!(chapter.contains("<h2>Tobin</h2>"));
+        assert!(chapter.contains("<a href=\"chapter_4.xhtml\">Mara</a>"));
+        assert!(archive.by_name("OEBPS/images/section_6_1_map.png").is_ok());
+        let mut nav = String::new();
+        archive
+            .by_name("OEBPS/nav.xhtml")
+            .unwrap()
+            .read_to_string(&mut nav)
+            .unwrap();
+        assert!(nav.contains("Characters</a>\n        <ol>"));
+    }
+}
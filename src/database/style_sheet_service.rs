@@ -0,0 +1,439 @@
+//! Style Sheet Service
+//!
+//! Stores the per-project style sheet and checks documents against it,
+//! reporting violations with positions and auto-fixing the safe categories.
+//! Documents are checked as their text, and fixes only rewrite text nodes.
+
+use chrono::Utc;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{
+    models::style_sheet::*,
+    prosemirror,
+    text_match::{find_word_matches, line_and_column},
+    DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+
+/// Service for managing project style sheets and checking documents
+#[derive(Debug)]
+pub struct StyleSheetService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl StyleSheetService {
+    /// Create a new style sheet service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize style sheet tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_STYLE_SHEET_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create style sheet tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Get the style sheet for a project, or an empty one if none is saved
+    pub async fn get_style_sheet(&self, project_id: Uuid) -> DatabaseResult<StyleSheet> {
+        let db = self.db_service.read().await;
+        let row: Option<(String, String)> = sqlx::query_as(GET_STYLE_SHEET_SQL)
+            .bind(project_id.to_string())
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get style sheet: {}", e)))?;
+
+        match row {
+            Some((rules_json, updated_at)) => Ok(StyleSheet {
+                project_id,
+                rules: serde_json::from_str(&rules_json).map_err(|e| {
+                    DatabaseError::Service(format!("Failed to parse style rules: {}", e))
+                })?,
+                updated_at: parse_time(&updated_at)?,
+            }),
+            None => Ok(StyleSheet::new(project_id)),
+        }
+    }
+
+    /// Save a project style sheet
+    pub async fn save_style_sheet(&self, style_sheet: &StyleSheet) -> DatabaseResult<()> {
+        for rule in &style_sheet.rules {
+            Self::validate_rule(rule)?;
+        }
+
+        let rules_json = serde_json::to_string(&style_sheet.rules)
+            .map_err(|e| DatabaseError::Service(format!("Failed to serialize rules: {}", e)))?;
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_STYLE_SHEET_SQL)
+            .bind(style_sheet.project_id.to_string())
+            .bind(rules_json)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save style sheet: {}", e)))?;
+        Ok(())
+    }
+
+    /// Add a rule to a project's style sheet
+    pub async fn add_rule(&self, project_id: Uuid, rule: StyleRule) -> DatabaseResult<StyleSheet> {
+        Self::validate_rule(&rule)?;
+        let mut style_sheet = self.get_style_sheet(project_id).await?;
+        style_sheet.rules.push(rule);
+        self.save_style_sheet(&style_sheet).await?;
+        Ok(style_sheet)
+    }
+
+    /// Remove a rule from a project's style sheet
+    pub async fn remove_rule(&self, project_id: Uuid, rule_id: Uuid) -> DatabaseResult<bool> {
+        let mut style_sheet = self.get_style_sheet(project_id).await?;
+        let before = style_sheet.rules.len();
+        style_sheet.rules.retain(|r| r.id != rule_id);
+        if style_sheet.rules.len() == before {
+            return Ok(false);
+        }
+        self.save_style_sheet(&style_sheet).await?;
+        Ok(true)
+    }
+
+    /// Check every active document in a project against its style sheet.
+    /// Positions are in the document's text, as `prosemirror::document_text`
+    /// reads it.
+    pub async fn check_project(
+        &self,
+        project_id: Uuid,
+    ) -> DatabaseResult<Vec<DocumentStyleReport>> {
+        let style_sheet = self.get_style_sheet(project_id).await?;
+        let db = self.db_service.read().await;
+
+        let documents: Vec<(String, String, Option<String>, String)> = sqlx::query_as(
+            "SELECT id, title, content, document_type FROM documents
+             WHERE project_id = ?1 AND is_active = 1
+             ORDER BY title ASC",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+
+        let mut reports = Vec::new();
+        for (id, title, content, document_type) in documents {
+            let text = prosemirror::document_text(&document_type, content.as_deref().unwrap_or(""));
+            let violations = check_text(&style_sheet, &text);
+            if violations.is_empty() {
+                continue;
+            }
+            reports.push(DocumentStyleReport {
+                document_id: parse_uuid(&id)?,
+                title,
+                violations,
+            });
+        }
+
+        Ok(reports)
+    }
+
+    /// Apply all auto-fixable rules to a document and save the result
+    pub async fn auto_fix_document(
+        &self,
+        project_id: Uuid,
+        document_id: Uuid,
+    ) -> DatabaseResult<StyleFixResult> {
+        let style_sheet = self.get_style_sheet(project_id).await?;
+        let db = self.db_service.read().await;
+
+        let row: Option<(Option<String>, String)> = sqlx::query_as(
+            "SELECT content, document_type FROM documents WHERE id = ?1 AND project_id = ?2 AND is_active = 1",
+        )
+        .bind(document_id.to_string())
+        .bind(project_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load document: {}", e)))?;
+
+        let (content, document_type) = row.ok_or_else(|| DatabaseError::RecordNotFound {
+            entity: "document".to_string(),
+            id: document_id.to_string(),
+        })?;
+        let content = content.unwrap_or_default();
+
+        let (fixed, fixes_applied) = fix_document(&style_sheet, &document_type, &content);
+        if fixes_applied > 0 {
+            let failed = |e: sqlx::Error| {
+                DatabaseError::Service(format!("Failed to save style fixes: {}", e))
+            };
+            let mut tx = db.pool.begin().await.map_err(failed)?;
+            sqlx::query(
+                "UPDATE documents SET content = ?, checksum = ?, updated_at = ?, version = version + 1 WHERE id = ?",
+            )
+            .bind(&fixed)
+            .bind(EnhancedDatabaseService::calculate_checksum(&fixed))
+            .bind(Utc::now())
+            .bind(document_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(failed)?;
+            prosemirror::store_document_text(
+                &mut tx,
+                &document_id.to_string(),
+                &document_type,
+                &fixed,
+            )
+            .await?;
+            tx.commit().await.map_err(failed)?;
+        }
+
+        let text = prosemirror::document_text(&document_type, &fixed);
+        Ok(StyleFixResult {
+            document_id,
+            fixes_applied,
+            remaining_violations: check_text(&style_sheet, &text).len(),
+        })
+    }
+
+    fn validate_rule(rule: &StyleRule) -> DatabaseResult<()> {
+        if rule.pattern.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Style rule pattern cannot be empty".to_string(),
+            ));
+        }
+        let needs_replacement = matches!(
+            rule.kind,
+            StyleRuleKind::PreferredSpelling | StyleRuleKind::Hyphenation
+        );
+        if needs_replacement && rule.replacement.as_deref().unwrap_or("").is_empty() {
+            return Err(DatabaseError::ValidationError(format!(
+                "{} rule for '{}' needs a replacement",
+                rule.kind.display_name(),
+                rule.pattern
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Check text against a style sheet, returning violations in document order
+pub fn check_text(style_sheet: &StyleSheet, text: &str) -> Vec<StyleViolation> {
+    let mut violations = Vec::new();
+
+    for rule in &style_sheet.rules {
+        for (start, end) in find_word_matches(text, &rule.pattern) {
+            let matched = &text[start..end];
+            let suggestion = match rule.kind {
+                StyleRuleKind::Capitalization => {
+                    if matched == rule.pattern
+                        || is_sentence_start_variant(text, start, matched, &rule.pattern)
+                    {
+                        continue;
+                    }
+                    Some(rule.pattern.clone())
+                }
+                StyleRuleKind::PreferredSpelling | StyleRuleKind::Hyphenation => {
+                    rule.replacement.as_deref().map(|r| match_case(matched, r))
+                }
+                StyleRuleKind::BannedWord => rule.replacement.clone(),
+            };
+            let (line, column) = line_and_column(text, start);
+            violations.push(StyleViolation {
+                rule_id: rule.id,
+                kind: rule.kind,
+                start,
+                end,
+                line,
+                column,
+                matched: matched.to_string(),
+                auto_fixable: rule.kind.is_auto_fixable() && suggestion.is_some(),
+                suggestion,
+            });
+        }
+    }
+
+    violations.sort_by_key(|v| (v.start, v.end));
+    violations
+}
+
+/// Apply every auto-fixable violation, returning the new text and fix count
+pub fn apply_fixes(style_sheet: &StyleSheet, text: &str) -> (String, usize) {
+    rewrite(text, 0, &auto_fixes(style_sheet, text))
+}
+
+/// Apply every auto-fixable violation to a stored document. ProseMirror
+/// documents are checked as their text and fixed in their text nodes, so
+/// marks and attributes stay as they were; a match that spans two nodes
+/// is left alone.
+pub fn fix_document(
+    style_sheet: &StyleSheet,
+    document_type: &str,
+    content: &str,
+) -> (String, usize) {
+    let Some(mut doc) = prosemirror::parse(document_type, content) else {
+        return apply_fixes(style_sheet, content);
+    };
+    let (text, nodes) = prosemirror::text_nodes_mut(&mut doc);
+    let fixes = auto_fixes(style_sheet, &text);
+    let mut applied = 0;
+    for (start, value) in nodes {
+        let (fixed, count) = rewrite(value, start, &fixes);
+        *value = fixed;
+        applied += count;
+    }
+    (doc.to_string(), applied)
+}
+
+/// Auto-fixable violations in `text`, leaving out any that overlap an
+/// earlier one
+fn auto_fixes(style_sheet: &StyleSheet, text: &str) -> Vec<StyleViolation> {
+    let mut last_end = 0;
+    check_text(style_sheet, text)
+        .into_iter()
+        .filter(|v| v.auto_fixable)
+        .filter(|v| {
+            let keep = v.start >= last_end;
+            if keep {
+                last_end = v.end;
+            }
+            keep
+        })
+        .collect()
+}
+
+/// Apply the fixes that lie within `text`, which starts `offset` bytes into
+/// the text they were found in; returns the new text and how many applied
+fn rewrite(text: &str, offset: usize, fixes: &[StyleViolation]) -> (String, usize) {
+    let mut result = String::with_capacity(text.len());
+    let mut cursor = 0;
+    let mut applied = 0;
+    for fix in fixes {
+        if fix.start < offset + cursor || fix.end > offset + text.len() {
+            continue;
+        }
+        result.push_str(&text[cursor..fix.start - offset]);
+        result.push_str(fix.suggestion.as_deref().unwrap_or(&fix.matched));
+        cursor = fix.end - offset;
+        applied += 1;
+    }
+    result.push_str(&text[cursor..]);
+    (result, applied)
+}
+
+/// Allow a lowercase canonical form to be capitalized at the start of a sentence
+fn is_sentence_start_variant(text: &str, start: usize, matched: &str, canonical: &str) -> bool {
+    let at_sentence_start = text[..start]
+        .trim_end()
+        .chars()
+        .last()
+        .is_none_or(|c| matches!(c, '.' | '!' | '?' | '\n'))
+        || text[..start].ends_with('\n');
+    at_sentence_start && match_case(matched, canonical) == matched
+}
+
+/// Carry the capitalization of `source` over to `replacement`
+fn match_case(source: &str, replacement: &str) -> String {
+    let letters: Vec<char> = source.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() > 1 && letters.iter().all(|c| c.is_uppercase()) {
+        return replacement.to_uppercase();
+    }
+    match (source.chars().next(), replacement.chars().next()) {
+        (Some(s), Some(r)) if s.is_uppercase() && r.is_lowercase() => {
+            let mut out: String = r.to_uppercase().collect();
+            out.push_str(&replacement[r.len_utf8()..]);
+            out
+        }
+        _ => replacement.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet(rules: Vec<StyleRule>) -> StyleSheet {
+        StyleSheet {
+            project_id: Uuid::nil(),
+            rules,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_preferred_spelling_reports_position() {
+        let sheet = sheet(vec![StyleRule::new(
+            StyleRuleKind::PreferredSpelling,
+            "grey".to_string(),
+            Some("gray".to_string()),
+        )]);
+        let violations = check_text(&sheet, "The sky.\nA Grey cat, not greyhound.");
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].line, 2);
+        assert_eq!(violations[0].column, 3);
+        assert_eq!(violations[0].suggestion.as_deref(), Some("Gray"));
+    }
+
+    #[test]
+    fn test_capitalization_allows_canonical_and_sentence_start() {
+        let sheet = sheet(vec![StyleRule::new(
+            StyleRuleKind::Capitalization,
+            "internet".to_string(),
+            None,
+        )]);
+        let violations = check_text(&sheet, "Internet is slow. The Internet and the internet.");
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].matched, "Internet");
+        assert_eq!(violations[0].start, 22);
+    }
+
+    #[test]
+    fn test_apply_fixes_skips_banned_words() {
+        let sheet = sheet(vec![
+            StyleRule::new(
+                StyleRuleKind::Hyphenation,
+                "e-mail".to_string(),
+                Some("email".to_string()),
+            ),
+            StyleRule::new(StyleRuleKind::BannedWord, "very".to_string(), None),
+        ]);
+        let (fixed, count) = apply_fixes(&sheet, "E-mail me a very long e-mail.");
+        assert_eq!(count, 2);
+        assert_eq!(fixed, "Email me a very long email.");
+    }
+
+    #[test]
+    fn test_fix_document_rewrites_text_nodes_only() {
+        let sheet = sheet(vec![StyleRule::new(
+            StyleRuleKind::PreferredSpelling,
+            "grey".to_string(),
+            Some("gray".to_string()),
+        )]);
+        let doc = serde_json::json!({
+            "type": "doc",
+            "content": [
+                {"type": "paragraph", "attrs": {"class": "grey"}, "content": [
+                    {"type": "text", "text": "A "},
+                    {"type": "text", "marks": [{"type": "em"}], "text": "grey"},
+                    {"type": "text", "text": " cat."}
+                ]},
+                {"type": "paragraph", "content": [{"type": "text", "text": "Grey skies."}]}
+            ]
+        });
+        let text = prosemirror::plain_text(&doc);
+        let violations = check_text(&sheet, &text);
+        assert_eq!(
+            violations.iter().map(|v| v.start).collect::<Vec<_>>(),
+            vec![2, 12]
+        );
+
+        let (fixed, count) = fix_document(&sheet, "json", &doc.to_string());
+        assert_eq!(count, 2);
+        let fixed: serde_json::Value = serde_json::from_str(&fixed).unwrap();
+        assert_eq!(prosemirror::plain_text(&fixed), "A gray cat.\nGray skies.");
+        assert_eq!(fixed["content"][0]["attrs"]["class"], "grey");
+        assert_eq!(fixed["content"][0]["content"][1]["marks"][0]["type"], "em");
+    }
+}
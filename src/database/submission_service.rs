@@ -0,0 +1,547 @@
+//! Submission Service
+//!
+//! Tracks where manuscripts have been submitted: the markets and agents
+//! with their guidelines, each submission and its answer, per-market
+//! response statistics and a report of what is still out. A background
+//! timer raises a desktop notification when a submission has gone without
+//! an answer past its follow-up date, once per submission.
+
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::automation::NotificationLevel;
+use crate::database::{
+    models::submission::*,
+    parse::{parse_time, parse_uuid},
+    DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+use crate::notifications::{DesktopNotification, Notifier};
+
+type MarketRow = (
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    String,
+    String,
+    bool,
+    Option<i64>,
+    String,
+    String,
+    String,
+);
+
+type SubmissionRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    bool,
+    String,
+    String,
+    String,
+);
+
+/// Service for markets, submissions and follow-up reminders
+#[derive(Debug)]
+pub struct SubmissionService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl SubmissionService {
+    /// Create a new submission service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the market and submission tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_SUBMISSION_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create submission tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Save a market or agent
+    pub async fn save_market(&self, market: &Market) -> DatabaseResult<Market> {
+        if market.name.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Market name cannot be empty".to_string(),
+            ));
+        }
+        let mut saved = market.clone();
+        saved.updated_at = Utc::now();
+        let genres = serde_json::to_string(&saved.genres)
+            .map_err(|e| DatabaseError::Service(format!("Failed to serialize genres: {}", e)))?;
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_MARKET_SQL)
+            .bind(saved.id.to_string())
+            .bind(&saved.name)
+            .bind(saved.kind.as_str())
+            .bind(&saved.contact)
+            .bind(&saved.website)
+            .bind(&saved.guidelines)
+            .bind(genres)
+            .bind(saved.accepts_simultaneous)
+            .bind(saved.expected_response_days.map(i64::from))
+            .bind(&saved.notes)
+            .bind(saved.created_at.to_rfc3339())
+            .bind(saved.updated_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save market: {}", e)))?;
+        Ok(saved)
+    }
+
+    /// All markets, by name
+    pub async fn list_markets(&self) -> DatabaseResult<Vec<Market>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<MarketRow> = sqlx::query_as(GET_MARKETS_SQL)
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to list markets: {}", e)))?;
+        rows.into_iter().map(market_from_row).collect()
+    }
+
+    /// Delete a market nothing has been submitted to
+    pub async fn delete_market(&self, market_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let used: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM submissions WHERE market_id = ?1")
+            .bind(market_id.to_string())
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to check submissions: {}", e)))?;
+        if used > 0 {
+            return Err(DatabaseError::ValidationError(format!(
+                "The market has {} submissions on record",
+                used
+            )));
+        }
+        let result = sqlx::query("DELETE FROM markets WHERE id = ?1")
+            .bind(market_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete market: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record a submission. An open submission without a follow-up date
+    /// gets one from the market's expected response time.
+    pub async fn save_submission(&self, submission: &Submission) -> DatabaseResult<Submission> {
+        let markets = self.list_markets().await?;
+        let market = markets
+            .iter()
+            .find(|m| m.id == submission.market_id)
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "market".to_string(),
+                id: submission.market_id.to_string(),
+            })?;
+
+        let mut saved = submission.clone();
+        saved.updated_at = Utc::now();
+        if saved.remind_at.is_none() && saved.status.is_open() {
+            let history = self.submissions(None).await?;
+            let stats = market_stats(market.id, &history);
+            saved.remind_at = expected_response_days(market, &stats)
+                .map(|days| saved.sent_at + Duration::days(days.ceil() as i64));
+        }
+        self.write_submission(&saved).await?;
+        Ok(saved)
+    }
+
+    /// A project's submissions, newest first
+    pub async fn list_submissions(&self, project_id: Uuid) -> DatabaseResult<Vec<Submission>> {
+        self.submissions(Some(project_id)).await
+    }
+
+    /// Record a market's answer. A request for more material opens a new
+    /// wait, so the follow-up reminder is set again from the answer date.
+    pub async fn record_response(
+        &self,
+        response: &SubmissionResponse,
+    ) -> DatabaseResult<Submission> {
+        let history = self.submissions(None).await?;
+        let mut submission = history
+            .iter()
+            .find(|s| s.id == response.submission_id)
+            .cloned()
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "submission".to_string(),
+                id: response.submission_id.to_string(),
+            })?;
+
+        let responded_at = response.responded_at.unwrap_or_else(Utc::now);
+        submission.status = response.status;
+        submission.response = response.response.clone();
+        if response.status.is_response() {
+            submission.responded_at = Some(responded_at);
+        }
+        if response.status == SubmissionStatus::MoreRequested {
+            let market = self
+                .list_markets()
+                .await?
+                .into_iter()
+                .find(|m| m.id == submission.market_id);
+            submission.remind_at = market
+                .and_then(|m| expected_response_days(&m, &market_stats(m.id, &history)))
+                .map(|days| responded_at + Duration::days(days.ceil() as i64));
+            submission.reminded = false;
+        }
+        submission.updated_at = Utc::now();
+        self.write_submission(&submission).await?;
+        Ok(submission)
+    }
+
+    /// Response statistics for every market
+    pub async fn market_stats(&self) -> DatabaseResult<Vec<MarketStats>> {
+        let markets = self.list_markets().await?;
+        let submissions = self.submissions(None).await?;
+        Ok(markets
+            .iter()
+            .map(|market| market_stats(market.id, &submissions))
+            .collect())
+    }
+
+    /// Submissions still waiting for an answer, for one project or all
+    pub async fn open_report(&self, project_id: Option<Uuid>) -> DatabaseResult<SubmissionReport> {
+        let markets = self.list_markets().await?;
+        let submissions = self.submissions(None).await?;
+        Ok(open_report(project_id, &markets, &submissions, Utc::now()))
+    }
+
+    /// Notify about every follow-up that has fallen due and mark it shown
+    pub async fn send_due_reminders(&self) -> DatabaseResult<usize> {
+        let markets = self.list_markets().await?;
+        let submissions = self.submissions(None).await?;
+        let reminders = due_reminders(&markets, &submissions, Utc::now());
+
+        let notifier = Notifier::global();
+        let db = self.db_service.read().await;
+        for reminder in &reminders {
+            let notification = DesktopNotification::new(
+                "Submission follow-up",
+                reminder.message.clone(),
+                NotificationLevel::Info,
+            );
+            if let Err(e) = notifier.notify(&notification) {
+                log::warn!("Failed to show submission reminder: {}", e);
+            }
+            sqlx::query("UPDATE submissions SET reminded = 1 WHERE id = ?1")
+                .bind(reminder.submission_id.to_string())
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to mark reminder shown: {}", e))
+                })?;
+        }
+        Ok(reminders.len())
+    }
+
+    /// Check for due reminders now and then every `every`
+    pub fn spawn_reminders(
+        self: Arc<Self>,
+        every: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.send_due_reminders().await {
+                    log::error!("Submission reminders failed: {}", e);
+                }
+            }
+        })
+    }
+
+    async fn submissions(&self, project_id: Option<Uuid>) -> DatabaseResult<Vec<Submission>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<SubmissionRow> = match project_id {
+            Some(project_id) => {
+                sqlx::query_as(&format!(
+                    "{} WHERE project_id = ?1 ORDER BY sent_at DESC",
+                    SELECT_SUBMISSIONS_SQL
+                ))
+                .bind(project_id.to_string())
+                .fetch_all(&db.pool)
+                .await
+            }
+            None => {
+                sqlx::query_as(&format!("{} ORDER BY sent_at DESC", SELECT_SUBMISSIONS_SQL))
+                    .fetch_all(&db.pool)
+                    .await
+            }
+        }
+        .map_err(|e| DatabaseError::Service(format!("Failed to list submissions: {}", e)))?;
+        rows.into_iter().map(submission_from_row).collect()
+    }
+
+    async fn write_submission(&self, submission: &Submission) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_SUBMISSION_SQL)
+            .bind(submission.id.to_string())
+            .bind(submission.project_id.to_string())
+            .bind(submission.market_id.to_string())
+            .bind(&submission.materials)
+            .bind(submission.sent_at.to_rfc3339())
+            .bind(submission.status.as_str())
+            .bind(submission.responded_at.map(|t| t.to_rfc3339()))
+            .bind(&submission.response)
+            .bind(submission.remind_at.map(|t| t.to_rfc3339()))
+            .bind(submission.reminded)
+            .bind(&submission.notes)
+            .bind(submission.created_at.to_rfc3339())
+            .bind(submission.updated_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save submission: {}", e)))?;
+        Ok(())
+    }
+}
+
+/// How a market has answered the submissions sent to it
+pub fn market_stats(market_id: Uuid, submissions: &[Submission]) -> MarketStats {
+    let sent: Vec<&Submission> = submissions
+        .iter()
+        .filter(|s| s.market_id == market_id)
+        .collect();
+    let count = |status: SubmissionStatus| sent.iter().filter(|s| s.status == status).count();
+    let response_days: Vec<i64> = sent
+        .iter()
+        .filter(|s| s.status.is_response())
+        .filter_map(|s| Some((s.responded_at? - s.sent_at).num_days()))
+        .collect();
+    let responses = sent.iter().filter(|s| s.status.is_response()).count();
+    let unanswered = count(SubmissionStatus::NoResponse);
+
+    MarketStats {
+        market_id,
+        submissions: sent.len(),
+        open: sent.iter().filter(|s| s.status.is_open()).count(),
+        responses,
+        accepted: count(SubmissionStatus::Accepted),
+        rejected: count(SubmissionStatus::Rejected),
+        response_rate: (responses + unanswered > 0)
+            .then(|| responses as f64 / (responses + unanswered) as f64),
+        average_response_days: (!response_days.is_empty())
+            .then(|| response_days.iter().sum::<i64>() as f64 / response_days.len() as f64),
+        longest_response_days: response_days.iter().max().copied(),
+    }
+}
+
+/// The market's stated response time, else its average so far
+pub fn expected_response_days(market: &Market, stats: &MarketStats) -> Option<f64> {
+    market
+        .expected_response_days
+        .map(f64::from)
+        .or(stats.average_response_days)
+}
+
+/// Open submissions with how long they have been out, longest first
+pub fn open_report(
+    project_id: Option<Uuid>,
+    markets: &[Market],
+    submissions: &[Submission],
+    now: DateTime<Utc>,
+) -> SubmissionReport {
+    let by_id: HashMap<Uuid, &Market> = markets.iter().map(|m| (m.id, m)).collect();
+    let mut open: Vec<OpenSubmission> = submissions
+        .iter()
+        .filter(|s| s.status.is_open() && project_id.is_none_or(|p| s.project_id == p))
+        .map(|submission| {
+            let market = by_id.get(&submission.market_id);
+            let expected =
+                market.and_then(|m| expected_response_days(m, &market_stats(m.id, submissions)));
+            let waiting_since = submission.responded_at.unwrap_or(submission.sent_at);
+            let days_out = (now - submission.sent_at).num_days();
+            OpenSubmission {
+                submission: submission.clone(),
+                market_name: market.map(|m| m.name.clone()).unwrap_or_default(),
+                days_out,
+                expected_response_days: expected,
+                overdue: expected
+                    .is_some_and(|days| (now - waiting_since).num_days() as f64 > days),
+            }
+        })
+        .collect();
+    open.sort_by_key(|o| std::cmp::Reverse(o.days_out));
+
+    let exclusive = open
+        .iter()
+        .filter(|o| {
+            by_id
+                .get(&o.submission.market_id)
+                .is_some_and(|m| !m.accepts_simultaneous)
+        })
+        .count();
+    SubmissionReport {
+        project_id,
+        overdue: open.iter().filter(|o| o.overdue).count(),
+        exclusive,
+        open,
+    }
+}
+
+/// Reminders for open submissions whose follow-up date has passed and
+/// that haven't been shown yet
+pub fn due_reminders(
+    markets: &[Market],
+    submissions: &[Submission],
+    now: DateTime<Utc>,
+) -> Vec<SubmissionReminder> {
+    submissions
+        .iter()
+        .filter(|s| s.status.is_open() && !s.reminded)
+        .filter_map(|submission| {
+            let remind_at = submission.remind_at.filter(|at| *at <= now)?;
+            let market_name = markets
+                .iter()
+                .find(|m| m.id == submission.market_id)
+                .map(|m| m.name.clone())
+                .unwrap_or_else(|| "a market".to_string());
+            let waiting_since = submission.responded_at.unwrap_or(submission.sent_at);
+            Some(SubmissionReminder {
+                submission_id: submission.id,
+                project_id: submission.project_id,
+                message: format!(
+                    "No answer from {} after {} days; time to follow up",
+                    market_name,
+                    (now - waiting_since).num_days()
+                ),
+                market_name,
+                remind_at,
+            })
+        })
+        .collect()
+}
+
+fn market_from_row(row: MarketRow) -> DatabaseResult<Market> {
+    let (
+        id,
+        name,
+        kind,
+        contact,
+        website,
+        guidelines,
+        genres,
+        accepts_simultaneous,
+        expected_response_days,
+        notes,
+        created_at,
+        updated_at,
+    ) = row;
+    Ok(Market {
+        id: parse_uuid(&id)?,
+        name,
+        kind: MarketKind::parse(&kind),
+        contact,
+        website,
+        guidelines,
+        genres: serde_json::from_str(&genres).unwrap_or_default(),
+        accepts_simultaneous,
+        expected_response_days: expected_response_days.and_then(|d| u32::try_from(d).ok()),
+        notes,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+fn submission_from_row(row: SubmissionRow) -> DatabaseResult<Submission> {
+    let (
+        id,
+        project_id,
+        market_id,
+        materials,
+        sent_at,
+        status,
+        responded_at,
+        response,
+        remind_at,
+        reminded,
+        notes,
+        created_at,
+        updated_at,
+    ) = row;
+    Ok(Submission {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        market_id: parse_uuid(&market_id)?,
+        materials,
+        sent_at: parse_time(&sent_at)?,
+        status: SubmissionStatus::parse(&status),
+        responded_at: responded_at.as_deref().map(parse_time).transpose()?,
+        response,
+        remind_at: remind_at.as_deref().map(parse_time).transpose()?,
+        reminded,
+        notes,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_report_and_reminders() {
+        let now = Utc::now();
+        let project = Uuid::new_v4();
+        let mut agent = Market::new("Quill Literary", MarketKind::Agent);
+        agent.accepts_simultaneous = false;
+        let magazine = Market::new("Lantern Quarterly", MarketKind::Magazine);
+
+        let days_ago = |days: i64| now - Duration::days(days);
+        let mut rejected = Submission::new(project, agent.id, days_ago(100));
+        rejected.status = SubmissionStatus::Rejected;
+        rejected.responded_at = Some(days_ago(70));
+        let mut requested = Submission::new(project, agent.id, days_ago(60));
+        requested.status = SubmissionStatus::MoreRequested;
+        requested.responded_at = Some(days_ago(50));
+        let mut waiting = Submission::new(project, magazine.id, days_ago(20));
+        waiting.remind_at = Some(days_ago(1));
+        let mut silent = Submission::new(project, magazine.id, days_ago(200));
+        silent.status = SubmissionStatus::NoResponse;
+        let submissions = vec![rejected, requested, waiting.clone(), silent];
+
+        let stats = market_stats(agent.id, &submissions);
+        assert_eq!((stats.submissions, stats.open, stats.responses), (2, 1, 2));
+        assert_eq!(stats.average_response_days, Some(20.0));
+        assert_eq!(stats.longest_response_days, Some(30));
+        assert_eq!(
+            market_stats(magazine.id, &submissions).response_rate,
+            Some(0.0)
+        );
+
+        let markets = vec![agent, magazine];
+        let report = open_report(Some(project), &markets, &submissions, now);
+        assert_eq!(report.open.len(), 2);
+        assert_eq!(report.open[0].market_name, "Quill Literary");
+        assert_eq!(report.open[0].days_out, 60);
+        // Waiting 50 days since the request against a 20 day average
+        assert!(report.open[0].overdue);
+        assert!(!report.open[1].overdue);
+        assert_eq!((report.overdue, report.exclusive), (1, 1));
+
+        let reminders = due_reminders(&markets, &submissions, now);
+        assert_eq!(reminders.len(), 1);
+        assert_eq!(reminders[0].submission_id, waiting.id);
+        assert_eq!(
+            reminders[0].message,
+            "No answer from Lantern Quarterly after 20 days; time to follow up"
+        );
+    }
+}
@@ -0,0 +1,620 @@
+//! Text Diff Utilities
+//!
+//! Line and word diffing shared by the draft, version and snapshot services,
+//! and the three-way merge used when two edits of a document race. Stored
+//! ProseMirror documents are compared by their text, one line per block,
+//! and merged block by block. Uses a longest-common-subsequence table over
+//! whatever is left once the unchanged start and end are trimmed, which is
+//! plenty fast for chapter-sized documents.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::database::prosemirror;
+
+/// Largest word diff worth aligning, as old words times new words; past
+/// it the words are reported as replaced wholesale
+const WORD_DIFF_CELLS: usize = 250_000;
+
+/// Kind of change for a single diff line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// A single line in a diff result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+    /// Line number in the old text (1-based), if the line exists there
+    pub old_line: Option<usize>,
+    /// Line number in the new text (1-based), if the line exists there
+    pub new_line: Option<usize>,
+}
+
+/// Summary counts for a diff
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiffSummary {
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub lines_unchanged: usize,
+    pub words_added: usize,
+    pub words_removed: usize,
+}
+
+impl DiffSummary {
+    /// Fraction of lines that changed, between 0.0 and 1.0
+    pub fn change_ratio(&self) -> f64 {
+        let total = self.lines_added + self.lines_removed + self.lines_unchanged;
+        if total == 0 {
+            0.0
+        } else {
+            (self.lines_added + self.lines_removed) as f64 / total as f64
+        }
+    }
+}
+
+/// A run of consecutive words with the same change in a word diff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffSpan {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// A run of changed lines with the unchanged lines around it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffHunk {
+    /// First old line in the hunk (1-based); the line before it when the
+    /// hunk only adds lines
+    pub old_start: usize,
+    pub old_count: usize,
+    pub new_start: usize,
+    pub new_count: usize,
+    pub lines: Vec<DiffLine>,
+    /// Word changes between the hunk's removed and added lines
+    pub words: Vec<DiffSpan>,
+}
+
+/// One stretch of a three-way merge
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MergeChunk {
+    /// Text both sides agree on, or that only one side changed
+    Resolved { text: String },
+    /// Lines both sides changed, differently
+    Conflict {
+        base: String,
+        ours: String,
+        theirs: String,
+    },
+}
+
+/// Outcome of a three-way merge, in document order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeResult {
+    pub chunks: Vec<MergeChunk>,
+}
+
+impl MergeResult {
+    pub fn conflicts(&self) -> usize {
+        self.chunks
+            .iter()
+            .filter(|chunk| matches!(chunk, MergeChunk::Conflict { .. }))
+            .count()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.conflicts() == 0
+    }
+
+    /// The merged text, or `None` while conflicts remain
+    pub fn text(&self) -> Option<String> {
+        self.chunks
+            .iter()
+            .map(|chunk| match chunk {
+                MergeChunk::Resolved { text } => Some(text.as_str()),
+                MergeChunk::Conflict { .. } => None,
+            })
+            .collect()
+    }
+
+    fn push_resolved(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        match self.chunks.last_mut() {
+            Some(MergeChunk::Resolved { text: resolved }) => resolved.push_str(text),
+            _ => self.chunks.push(MergeChunk::Resolved {
+                text: text.to_string(),
+            }),
+        }
+    }
+}
+
+/// Compute a line diff between the texts of two stored documents, so a
+/// ProseMirror document is compared block by block rather than as JSON
+pub fn diff_documents(document_type: &str, old: &str, new: &str) -> Vec<DiffLine> {
+    diff_lines(
+        &prosemirror::document_text(document_type, old),
+        &prosemirror::document_text(document_type, new),
+    )
+}
+
+/// Compute a line diff between two texts
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    align(&old_lines, &new_lines)
+        .into_iter()
+        .map(|(op, i, j)| DiffLine {
+            op,
+            text: match op {
+                DiffOp::Insert => new_lines[j].to_string(),
+                _ => old_lines[i].to_string(),
+            },
+            old_line: (op != DiffOp::Insert).then_some(i + 1),
+            new_line: (op != DiffOp::Delete).then_some(j + 1),
+        })
+        .collect()
+}
+
+/// Compute a word diff between two short texts, such as a paragraph and its
+/// revision. Whitespace inside a span is normalised to single spaces.
+/// Texts too long to align word by word come back as one deletion and
+/// one insertion.
+pub fn diff_words(old: &str, new: &str) -> Vec<DiffSpan> {
+    let old_words: Vec<&str> = old.split_whitespace().collect();
+    let new_words: Vec<&str> = new.split_whitespace().collect();
+
+    let (prefix, suffix) = common_ends(&old_words, &new_words);
+    let cells = (old_words.len() - prefix - suffix) * (new_words.len() - prefix - suffix);
+    let ops = if cells > WORD_DIFF_CELLS {
+        let (old_end, new_end) = (old_words.len() - suffix, new_words.len() - suffix);
+        (0..prefix)
+            .map(|k| (DiffOp::Equal, k, k))
+            .chain((prefix..old_end).map(|i| (DiffOp::Delete, i, prefix)))
+            .chain((prefix..new_end).map(|j| (DiffOp::Insert, old_end, j)))
+            .chain((0..suffix).map(|k| (DiffOp::Equal, old_end + k, new_end + k)))
+            .collect()
+    } else {
+        align(&old_words, &new_words)
+    };
+
+    let mut spans: Vec<DiffSpan> = Vec::new();
+    for (op, i, j) in ops {
+        let word = match op {
+            DiffOp::Insert => new_words[j],
+            _ => old_words[i],
+        };
+        match spans.last_mut() {
+            Some(span) if span.op == op => {
+                span.text.push(' ');
+                span.text.push_str(word);
+            }
+            _ => spans.push(DiffSpan {
+                op,
+                text: word.to_string(),
+            }),
+        }
+    }
+    spans
+}
+
+/// Group a line diff into hunks with up to `context` unchanged lines on
+/// either side; hunks whose context would overlap are joined
+pub fn hunks(diff: &[DiffLine], context: usize) -> Vec<DiffHunk> {
+    let changed: Vec<usize> = diff
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.op != DiffOp::Equal)
+        .map(|(index, _)| index)
+        .collect();
+
+    let mut hunks = Vec::new();
+    let mut next = 0;
+    while next < changed.len() {
+        let first = changed[next];
+        let mut last = first;
+        next += 1;
+        while next < changed.len() && changed[next] - last <= 2 * context + 1 {
+            last = changed[next];
+            next += 1;
+        }
+        let start = first.saturating_sub(context);
+        let end = (last + context + 1).min(diff.len());
+        hunks.push(hunk(&diff[..start], &diff[start..end]));
+    }
+    hunks
+}
+
+/// A hunk of `lines`, which follow `before` in the diff
+fn hunk(before: &[DiffLine], lines: &[DiffLine]) -> DiffHunk {
+    let count = |lines: &[DiffLine], skip: DiffOp| lines.iter().filter(|l| l.op != skip).count();
+    let (old_before, new_before) = (count(before, DiffOp::Insert), count(before, DiffOp::Delete));
+    let (old_count, new_count) = (count(lines, DiffOp::Insert), count(lines, DiffOp::Delete));
+
+    let side = |op: DiffOp| {
+        lines
+            .iter()
+            .filter(|line| line.op == op)
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    DiffHunk {
+        old_start: old_before + usize::from(old_count > 0),
+        old_count,
+        new_start: new_before + usize::from(new_count > 0),
+        new_count,
+        lines: lines.to_vec(),
+        words: diff_words(&side(DiffOp::Delete), &side(DiffOp::Insert)),
+    }
+}
+
+/// Merge two edits of `base` line by line. Lines only one side changed
+/// take that side's change; lines both changed the same way are taken
+/// once; anything else is a conflict. Line endings are kept as they are.
+pub fn merge3(base: &str, ours: &str, theirs: &str) -> MergeResult {
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let ours: Vec<&str> = ours.split_inclusive('\n').collect();
+    let theirs: Vec<&str> = theirs.split_inclusive('\n').collect();
+
+    let mut result = MergeResult::default();
+    for run in merge_runs(&base, &ours, &theirs) {
+        match run {
+            MergeRun::Resolved(lines) => result.push_resolved(&lines.concat()),
+            MergeRun::Conflict(base, ours, theirs) => result.chunks.push(MergeChunk::Conflict {
+                base: base.concat(),
+                ours: ours.concat(),
+                theirs: theirs.concat(),
+            }),
+        }
+    }
+    result
+}
+
+/// Merge two edits of a ProseMirror document block by block, the way
+/// [`merge3`] merges lines. The merge shows each block as its text; the
+/// merged document comes with it when there are no conflicts.
+pub fn merge_documents(base: &Value, ours: &Value, theirs: &Value) -> (MergeResult, Option<Value>) {
+    let text = |blocks: &[Value]| -> String {
+        blocks
+            .iter()
+            .map(|block| prosemirror::plain_text(block) + "\n")
+            .collect()
+    };
+
+    let mut result = MergeResult::default();
+    let mut merged = Vec::new();
+    let runs = merge_runs(
+        prosemirror::blocks(base),
+        prosemirror::blocks(ours),
+        prosemirror::blocks(theirs),
+    );
+    for run in runs {
+        match run {
+            MergeRun::Resolved(blocks) => {
+                result.push_resolved(&text(blocks));
+                merged.extend_from_slice(blocks);
+            }
+            MergeRun::Conflict(base, ours, theirs) => result.chunks.push(MergeChunk::Conflict {
+                base: text(base),
+                ours: text(ours),
+                theirs: text(theirs),
+            }),
+        }
+    }
+
+    let document = result.is_clean().then(|| {
+        let mut document = ours.clone();
+        document["content"] = Value::Array(merged);
+        document
+    });
+    (result, document)
+}
+
+/// A stretch of a three-way merge over items of any kind
+enum MergeRun<'a, T> {
+    Resolved(&'a [T]),
+    Conflict(&'a [T], &'a [T], &'a [T]),
+}
+
+/// Walk the items both sides kept from `base`, settling what changed
+/// between them: one side's change is taken, matching changes are taken
+/// once, anything else is a conflict
+fn merge_runs<'a, T: PartialEq>(
+    base: &'a [T],
+    ours: &'a [T],
+    theirs: &'a [T],
+) -> Vec<MergeRun<'a, T>> {
+    let to_ours = matches(base, ours);
+    let to_theirs = matches(base, theirs);
+
+    let mut runs = Vec::new();
+    let (mut i, mut a, mut b) = (0, 0, 0);
+    loop {
+        // The next base item both sides kept splits off what changed
+        // before it
+        let (k, ka, kb) = (i..base.len())
+            .find_map(|k| Some((k, to_ours[k]?, to_theirs[k]?)))
+            .unwrap_or((base.len(), ours.len(), theirs.len()));
+        let (base_run, ours_run, theirs_run) = (&base[i..k], &ours[a..ka], &theirs[b..kb]);
+        if ours_run == theirs_run || base_run == theirs_run {
+            runs.push(MergeRun::Resolved(ours_run));
+        } else if base_run == ours_run {
+            runs.push(MergeRun::Resolved(theirs_run));
+        } else {
+            runs.push(MergeRun::Conflict(base_run, ours_run, theirs_run));
+        }
+
+        if k == base.len() {
+            return runs;
+        }
+        runs.push(MergeRun::Resolved(&base[k..=k]));
+        (i, a, b) = (k + 1, ka + 1, kb + 1);
+    }
+}
+
+/// For each item of `base`, the index of the item of `other` it lines up
+/// with, if it was kept
+fn matches<T: PartialEq>(base: &[T], other: &[T]) -> Vec<Option<usize>> {
+    let mut matched = vec![None; base.len()];
+    for (op, i, j) in align(base, other) {
+        if op == DiffOp::Equal {
+            matched[i] = Some(j);
+        }
+    }
+    matched
+}
+
+/// Longest-common-subsequence alignment of two sequences, as
+/// `(op, old index, new index)`; the index not used by an op is where the
+/// other sequence stands at that point
+fn align<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(DiffOp, usize, usize)> {
+    // Only the middle both ends leave unchanged needs the table
+    let (prefix, suffix) = common_ends(old, new);
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    let mut result: Vec<_> = (0..prefix).map(|k| (DiffOp::Equal, k, k)).collect();
+    result.extend(
+        lcs_align(&old[prefix..old_end], &new[prefix..new_end])
+            .into_iter()
+            .map(|(op, i, j)| (op, prefix + i, prefix + j)),
+    );
+    result.extend((0..suffix).map(|k| (DiffOp::Equal, old_end + k, new_end + k)));
+    result
+}
+
+/// Lengths of the longest common prefix and, of what's left, the longest
+/// common suffix of two sequences
+fn common_ends<T: PartialEq>(old: &[T], new: &[T]) -> (usize, usize) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    (prefix, suffix)
+}
+
+fn lcs_align<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(DiffOp, usize, usize)> {
+    let (n, m) = (old.len(), new.len());
+
+    // lcs[i][j] = length of LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut result = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            result.push((DiffOp::Equal, i, j));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            result.push((DiffOp::Delete, i, j));
+            i += 1;
+        } else {
+            result.push((DiffOp::Insert, i, j));
+            j += 1;
+        }
+    }
+    result.extend((i..n).map(|k| (DiffOp::Delete, k, m)));
+    result.extend((j..m).map(|k| (DiffOp::Insert, n, k)));
+    result
+}
+
+/// Summarize a diff into line and word counts
+pub fn summarize(diff: &[DiffLine]) -> DiffSummary {
+    let mut summary = DiffSummary::default();
+    for line in diff {
+        let words = line.text.split_whitespace().count();
+        match line.op {
+            DiffOp::Equal => summary.lines_unchanged += 1,
+            DiffOp::Insert => {
+                summary.lines_added += 1;
+                summary.words_added += words;
+            }
+            DiffOp::Delete => {
+                summary.lines_removed += 1;
+                summary.words_removed += words;
+            }
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_texts_have_no_changes() {
+        let diff = diff_lines("a\nb\nc", "a\nb\nc");
+        assert!(diff.iter().all(|l| l.op == DiffOp::Equal));
+        assert_eq!(summarize(&diff).change_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_insert_and_delete_detected() {
+        let diff = diff_lines("one\ntwo\nthree", "one\nthree\nfour five");
+        let summary = summarize(&diff);
+        assert_eq!(summary.lines_removed, 1);
+        assert_eq!(summary.lines_added, 1);
+        assert_eq!(summary.lines_unchanged, 2);
+        assert_eq!(summary.words_added, 2);
+        assert_eq!(diff[1].op, DiffOp::Delete);
+        assert_eq!(diff[1].text, "two");
+    }
+
+    #[test]
+    fn test_empty_old_text() {
+        let diff = diff_lines("", "new line");
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].op, DiffOp::Insert);
+        assert_eq!(diff[0].new_line, Some(1));
+    }
+
+    #[test]
+    fn test_word_diff_groups_runs() {
+        let spans = diff_words("The  cat sat on the mat.", "The black cat sat on a mat.");
+        let runs: Vec<(DiffOp, &str)> = spans.iter().map(|s| (s.op, s.text.as_str())).collect();
+        assert_eq!(
+            runs,
+            vec![
+                (DiffOp::Equal, "The"),
+                (DiffOp::Insert, "black"),
+                (DiffOp::Equal, "cat sat on"),
+                (DiffOp::Delete, "the"),
+                (DiffOp::Insert, "a"),
+                (DiffOp::Equal, "mat."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hunks_keep_context_and_word_changes() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\nThe cat sat.\nj";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\nThe black cat sat.\nj\nk";
+        let found = hunks(&diff_lines(old, new), 1);
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            (
+                found[0].old_start,
+                found[0].old_count,
+                found[0].new_start,
+                found[0].new_count
+            ),
+            (1, 3, 1, 3)
+        );
+        assert_eq!(
+            (
+                found[1].old_start,
+                found[1].old_count,
+                found[1].new_start,
+                found[1].new_count
+            ),
+            (8, 3, 8, 4)
+        );
+        assert!(found[1].words.contains(&DiffSpan {
+            op: DiffOp::Insert,
+            text: "black".to_string()
+        }));
+
+        // Close enough together, the two changes share a hunk
+        assert_eq!(hunks(&diff_lines(old, new), 4).len(), 1);
+        assert!(hunks(&diff_lines(old, old), 3).is_empty());
+    }
+
+    #[test]
+    fn test_merge3_combines_edits_and_flags_conflicts() {
+        let base = "one\ntwo\nthree\nfour\n";
+        let ours = "ONE\ntwo\nthree\nfour\n";
+        let theirs = "one\ntwo\nthree\nfour\nfive\n";
+        let merged = merge3(base, ours, theirs);
+        assert!(merged.is_clean());
+        assert_eq!(merged.text().unwrap(), "ONE\ntwo\nthree\nfour\nfive\n");
+
+        // Both deleting a line is not a conflict
+        let merged = merge3(base, "one\nthree\nfour\n", "one\nthree\nfour\n");
+        assert_eq!(merged.text().unwrap(), "one\nthree\nfour\n");
+
+        let merged = merge3(base, "one\n2\nthree\nfour\n", "one\nzwei\nthree\nfour!\n");
+        assert_eq!(merged.conflicts(), 1);
+        assert!(merged.text().is_none());
+        assert_eq!(
+            merged.chunks,
+            vec![
+                MergeChunk::Resolved {
+                    text: "one\n".to_string()
+                },
+                MergeChunk::Conflict {
+                    base: "two\n".to_string(),
+                    ours: "2\n".to_string(),
+                    theirs: "zwei\n".to_string(),
+                },
+                MergeChunk::Resolved {
+                    text: "three\nfour!\n".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_documents_diff_and_merge_by_block() {
+        let doc = |paragraphs: &[&str]| {
+            serde_json::json!({
+                "type": "doc",
+                "content": prosemirror::paragraphs(&paragraphs.join("\n")),
+            })
+        };
+        let base = doc(&["One.", "Two.", "Three."]);
+        let ours = doc(&["One!", "Two.", "Three."]);
+        let theirs = doc(&["One.", "Two.", "Three.", "Four."]);
+
+        let diff = diff_documents("json", &base.to_string(), &theirs.to_string());
+        let summary = summarize(&diff);
+        assert_eq!((summary.lines_added, summary.lines_unchanged), (1, 3));
+        assert_eq!(diff[3].text, "Four.");
+
+        let (merge, merged) = merge_documents(&base, &ours, &theirs);
+        assert!(merge.is_clean());
+        assert_eq!(merge.text().unwrap(), "One!\nTwo.\nThree.\nFour.\n");
+        assert_eq!(merged.unwrap(), doc(&["One!", "Two.", "Three.", "Four."]));
+
+        let (merge, merged) = merge_documents(&base, &ours, &doc(&["One?", "Two.", "Three."]));
+        assert_eq!(merge.conflicts(), 1);
+        assert!(merged.is_none());
+    }
+
+    #[test]
+    fn test_long_word_diffs_are_replaced_wholesale() {
+        let old = format!("Start {} end", vec!["old"; 600].join(" "));
+        let new = format!("Start {} end", vec!["new"; 600].join(" "));
+        let runs: Vec<(DiffOp, usize)> = diff_words(&old, &new)
+            .iter()
+            .map(|span| (span.op, span.text.split(' ').count()))
+            .collect();
+        assert_eq!(
+            runs,
+            vec![
+                (DiffOp::Equal, 1),
+                (DiffOp::Delete, 600),
+                (DiffOp::Insert, 600),
+                (DiffOp::Equal, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! Text Matching Utilities
+//!
+//! Case-insensitive whole-word matching and position helpers shared by the
+//! style sheet checker and the content scanner.
+
+/// Find case-insensitive whole-word matches, returning byte ranges
+pub fn find_word_matches(text: &str, pattern: &str) -> Vec<(usize, usize)> {
+    let pattern_chars: Vec<char> = pattern.chars().collect();
+    if pattern_chars.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    let mut prev: Option<char> = None;
+    let mut iter = text.char_indices().peekable();
+
+    while let Some((start, _)) = iter.peek().copied() {
+        if prev.is_none_or(|c| !c.is_alphanumeric()) {
+            let mut end = start;
+            let mut matched = true;
+            let mut chars = text[start..].char_indices();
+            for p in &pattern_chars {
+                match chars.next() {
+                    Some((offset, c)) if c.to_lowercase().eq(p.to_lowercase()) => {
+                        end = start + offset + c.len_utf8();
+                    }
+                    _ => {
+                        matched = false;
+                        break;
+                    }
+                }
+            }
+            let at_boundary = text[end..]
+                .chars()
+                .next()
+                .is_none_or(|c| !c.is_alphanumeric());
+            if matched && at_boundary {
+                matches.push((start, end));
+            }
+        }
+        let (_, c) = iter.next().unwrap_or((start, ' '));
+        prev = Some(c);
+    }
+
+    matches
+}
+
+/// 1-based line and character column for a byte offset
+pub fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
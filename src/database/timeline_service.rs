@@ -0,0 +1,863 @@
+//! Timeline Service
+//!
+//! Keeps a project's story events and lays them out with its Time codex
+//! entries on the shared day line of its calendars. Relative dates are
+//! resolved through the events they hang from, and vague dates are carried
+//! as ranges of days, so the conflict check can tell a character who is
+//! certainly in two places at once from one who only might be.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::codex_relationship_service::has_codex_table;
+use crate::database::models::calendar::{
+    CalendarDate, CalendarSystem, TimelineEntry, TimelineIssue, TimelineIssueKind,
+};
+use crate::database::models::timeline::*;
+use crate::database::parse::parse_uuid;
+use crate::database::{CalendarService, DatabaseError, DatabaseResult, EnhancedDatabaseService};
+
+type EventRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    String,
+    String,
+    String,
+);
+
+/// Service for story events and the project timeline
+#[derive(Debug)]
+pub struct TimelineService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    calendars: Arc<CalendarService>,
+}
+
+impl TimelineService {
+    /// Create a new timeline service
+    pub fn new(
+        db_service: Arc<RwLock<EnhancedDatabaseService>>,
+        calendars: Arc<CalendarService>,
+    ) -> Self {
+        Self {
+            db_service,
+            calendars,
+        }
+    }
+
+    /// Initialize the timeline events table
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_TIMELINE_EVENTS_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create timeline events table: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Create or update an event
+    pub async fn save_event(&self, event: &StoryEvent) -> DatabaseResult<()> {
+        event.validate().map_err(DatabaseError::ValidationError)?;
+        // Titles are looked up afresh when the timeline is built
+        let links: Vec<EventLink> = event
+            .links
+            .iter()
+            .map(|l| EventLink::new(l.kind, l.target_id))
+            .collect();
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_TIMELINE_EVENT_SQL)
+            .bind(event.id.to_string())
+            .bind(event.project_id.to_string())
+            .bind(event.title.trim())
+            .bind(&event.description)
+            .bind(to_json(&event.start)?)
+            .bind(event.end.as_ref().map(to_json).transpose()?)
+            .bind(to_json(&links)?)
+            .bind(event.created_at.to_rfc3339())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save event: {}", e)))?;
+        Ok(())
+    }
+
+    /// Get an event by ID
+    pub async fn get_event(&self, event_id: Uuid) -> DatabaseResult<Option<StoryEvent>> {
+        let db = self.db_service.read().await;
+        let row: Option<EventRow> = sqlx::query_as(
+            "SELECT id, project_id, title, description, start_date, end_date, links, created_at, updated_at
+             FROM timeline_events WHERE id = ?1",
+        )
+        .bind(event_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to get event: {}", e)))?;
+        row.map(event_from_row).transpose()
+    }
+
+    /// A project's events in the order they were added
+    pub async fn list_events(&self, project_id: Uuid) -> DatabaseResult<Vec<StoryEvent>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<EventRow> = sqlx::query_as(
+            "SELECT id, project_id, title, description, start_date, end_date, links, created_at, updated_at
+             FROM timeline_events WHERE project_id = ?1 ORDER BY created_at, title",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to list events: {}", e)))?;
+        rows.into_iter().map(event_from_row).collect()
+    }
+
+    /// Delete an event; events dated from it are reported on the timeline
+    /// until they're given another date
+    pub async fn delete_event(&self, event_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM timeline_events WHERE id = ?1")
+            .bind(event_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete event: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The project's events and Time codex entries in order, with the
+    /// conflicts among them
+    pub async fn timeline(
+        &self,
+        project_id: Uuid,
+        filter: &TimelineFilter,
+    ) -> DatabaseResult<StoryTimeline> {
+        let mut events = self.list_events(project_id).await?;
+        let calendars = self.calendars.list_calendars(project_id).await?;
+        let codex = self.calendars.project_timeline(project_id).await?;
+
+        // Name the links, dropping those to documents and entries since
+        // deleted
+        let titles = self.link_titles(project_id).await?;
+        for event in &mut events {
+            event
+                .links
+                .retain_mut(|link| match titles.get(&link.target_id) {
+                    Some(title) => {
+                        link.title = title.clone();
+                        true
+                    }
+                    None => false,
+                });
+        }
+
+        let (placed, mut issues) = place_story_events(&calendars, &codex.entries, &events);
+        issues.splice(0..0, codex.issues);
+        let conflicts = find_presence_conflicts(&placed);
+
+        let events: Vec<PlacedEvent> = placed.into_iter().filter(|e| filter.matches(e)).collect();
+        let kept: HashSet<Uuid> = events.iter().map(|e| e.event_id).collect();
+        Ok(StoryTimeline {
+            project_id,
+            events,
+            conflicts: conflicts
+                .into_iter()
+                .filter(|c| kept.contains(&c.first_event_id) || kept.contains(&c.second_event_id))
+                .collect(),
+            issues,
+        })
+    }
+
+    /// Titles of the project's documents and codex entries
+    async fn link_titles(&self, project_id: Uuid) -> DatabaseResult<HashMap<Uuid, String>> {
+        let db = self.db_service.read().await;
+        let mut rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT id, title FROM documents WHERE project_id = ?1 AND is_active = 1",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+        if has_codex_table(&db).await? {
+            rows.extend(
+                sqlx::query_as::<_, (String, String)>(
+                    "SELECT id, title FROM codex_entries WHERE project_id = ?1 AND is_active = 1",
+                )
+                .bind(project_id.to_string())
+                .fetch_all(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to load codex entries: {}", e))
+                })?,
+            );
+        }
+        rows.into_iter()
+            .map(|(id, title)| Ok((parse_uuid(&id)?, title)))
+            .collect()
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> DatabaseResult<String> {
+    serde_json::to_string(value)
+        .map_err(|e| DatabaseError::Service(format!("Failed to serialize event: {}", e)))
+}
+
+fn event_from_row(row: EventRow) -> DatabaseResult<StoryEvent> {
+    let (id, project_id, title, description, start, end, links, created_at, updated_at) = row;
+    let parse_time = |value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| DatabaseError::Service(format!("Invalid timestamp: {}", e)))
+    };
+    let invalid = |e: serde_json::Error| DatabaseError::Service(format!("Invalid event: {}", e));
+    Ok(StoryEvent {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        title,
+        description,
+        start: serde_json::from_str(&start).map_err(invalid)?,
+        end: end
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(invalid)?,
+        links: serde_json::from_str(&links).map_err(invalid)?,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+/// A date resolved in a calendar
+struct Resolved<'a> {
+    calendar: &'a CalendarSystem,
+    span: DaySpan,
+    label: String,
+}
+
+/// Places events in dependency order, remembering each result so events
+/// sharing an anchor resolve it once
+struct Resolver<'a> {
+    calendars: &'a [CalendarSystem],
+    events: HashMap<Uuid, &'a StoryEvent>,
+    /// None for events that couldn't be placed
+    placed: HashMap<Uuid, Option<PlacedEvent>>,
+    visiting: HashSet<Uuid>,
+    issues: Vec<TimelineIssue>,
+}
+
+impl<'a> Resolver<'a> {
+    fn place(&mut self, event_id: Uuid) -> Option<PlacedEvent> {
+        if let Some(placed) = self.placed.get(&event_id) {
+            return placed.clone();
+        }
+        let event = *self.events.get(&event_id)?;
+        self.visiting.insert(event_id);
+        let placed = self.place_event(event);
+        self.visiting.remove(&event_id);
+        self.placed.insert(event_id, placed.clone());
+        placed
+    }
+
+    fn place_event(&mut self, event: &StoryEvent) -> Option<PlacedEvent> {
+        let start = self.resolve(event, &event.start);
+        let end = event.end.as_ref().map(|end| self.resolve(event, end));
+        let (start, end) = match (start, end.transpose()) {
+            (Ok(start), Ok(end)) => (start, end),
+            (Err((kind, message)), _) | (_, Err((kind, message))) => {
+                self.issues.push(TimelineIssue {
+                    event_id: event.id,
+                    kind,
+                    message,
+                });
+                return None;
+            }
+        };
+        if end
+            .as_ref()
+            .is_some_and(|end| end.span.latest < start.span.earliest)
+        {
+            self.issues.push(TimelineIssue {
+                event_id: event.id,
+                kind: TimelineIssueKind::EndBeforeStart,
+                message: format!("\"{}\" ends before it starts", event.title),
+            });
+        }
+        Some(PlacedEvent {
+            event_id: event.id,
+            title: event.title.clone(),
+            source: EventSource::Event,
+            calendar_id: start.calendar.id,
+            start: start.span,
+            end: end.as_ref().map(|end| end.span),
+            start_label: start.label,
+            end_label: end.map(|end| end.label),
+            links: event.links.clone(),
+        })
+    }
+
+    fn resolve(
+        &mut self,
+        event: &StoryEvent,
+        date: &EventDate,
+    ) -> Result<Resolved<'a>, (TimelineIssueKind, String)> {
+        let invalid = |e: DatabaseError| {
+            (
+                TimelineIssueKind::InvalidDate,
+                format!("\"{}\": {}", event.title, e),
+            )
+        };
+        match date {
+            EventDate::Calendar {
+                calendar_id,
+                date,
+                precision,
+                give_or_take,
+            } => {
+                let calendar = match calendar_id {
+                    Some(id) => self.calendars.iter().find(|c| c.id == *id).ok_or_else(|| {
+                        (
+                            TimelineIssueKind::UnknownCalendar,
+                            format!("\"{}\" uses a calendar that no longer exists", event.title),
+                        )
+                    })?,
+                    None if self.calendars.len() == 1 => &self.calendars[0],
+                    None => {
+                        return Err((
+                            TimelineIssueKind::NoCalendar,
+                            format!("\"{}\" needs a calendar for its dates", event.title),
+                        ))
+                    }
+                };
+                let (first, last) = match precision {
+                    DatePrecision::Day => (*date, *date),
+                    DatePrecision::Month => (
+                        CalendarDate::new(date.year, date.month, 1),
+                        CalendarDate::new(
+                            date.year,
+                            date.month,
+                            calendar.month_days(date.year, date.month).unwrap_or(0),
+                        ),
+                    ),
+                    DatePrecision::Year => {
+                        let last = calendar.months.len() as u32;
+                        (
+                            CalendarDate::new(date.year, 1, 1),
+                            CalendarDate::new(
+                                date.year,
+                                last,
+                                calendar.month_days(date.year, last).unwrap_or(0),
+                            ),
+                        )
+                    }
+                };
+                let span = DaySpan {
+                    earliest: calendar.shared_day(&first).map_err(invalid)?,
+                    latest: calendar.shared_day(&last).map_err(invalid)?,
+                };
+                let mut label = date_label(calendar, &first, *precision);
+                if *give_or_take > 0 {
+                    label = format!("c. {}", label);
+                }
+                Ok(Resolved {
+                    calendar,
+                    span: span.widen(*give_or_take),
+                    label,
+                })
+            }
+            EventDate::Relative {
+                anchor_id,
+                offset,
+                unit,
+                from_end,
+                give_or_take,
+            } => {
+                let anchor = self.anchor(event, *anchor_id)?;
+                let calendar = self
+                    .calendars
+                    .iter()
+                    .find(|c| c.id == anchor.calendar_id)
+                    .ok_or_else(|| {
+                        (
+                            TimelineIssueKind::UnknownCalendar,
+                            format!("\"{}\" uses a calendar that no longer exists", event.title),
+                        )
+                    })?;
+                let base = match (from_end, anchor.end) {
+                    (true, Some(end)) => end,
+                    _ => anchor.start,
+                };
+                let shift_months = |day: i64, months: i64| -> DatabaseResult<i64> {
+                    let date = calendar.date_from_day_number(day - calendar.epoch_offset)?;
+                    calendar.shared_day(&calendar.add_months(&date, months)?)
+                };
+                let span = match unit {
+                    OffsetUnit::Days => base.shift(*offset),
+                    OffsetUnit::Weeks => {
+                        let week = match calendar.weekdays.len() {
+                            0 => 7,
+                            days => days as i64,
+                        };
+                        base.shift(offset * week)
+                    }
+                    OffsetUnit::Months | OffsetUnit::Years => {
+                        let months = match unit {
+                            OffsetUnit::Years => offset * calendar.months.len() as i64,
+                            _ => *offset,
+                        };
+                        DaySpan {
+                            earliest: shift_months(base.earliest, months).map_err(invalid)?,
+                            latest: shift_months(base.latest, months).map_err(invalid)?,
+                        }
+                    }
+                }
+                .widen(*give_or_take);
+                let label_of = |day: i64| {
+                    calendar
+                        .date_from_day_number(day - calendar.epoch_offset)
+                        .map(|date| date_label(calendar, &date, DatePrecision::Day))
+                        .map_err(invalid)
+                };
+                let label = if span.is_exact() {
+                    label_of(span.earliest)?
+                } else {
+                    format!("{} – {}", label_of(span.earliest)?, label_of(span.latest)?)
+                };
+                Ok(Resolved {
+                    calendar,
+                    span,
+                    label,
+                })
+            }
+        }
+    }
+
+    fn anchor(
+        &mut self,
+        event: &StoryEvent,
+        anchor_id: Uuid,
+    ) -> Result<PlacedEvent, (TimelineIssueKind, String)> {
+        if self.visiting.contains(&anchor_id) {
+            return Err((
+                TimelineIssueKind::AnchorCycle,
+                format!(
+                    "\"{}\" is dated from an event that is dated from it",
+                    event.title
+                ),
+            ));
+        }
+        let known = self.events.contains_key(&anchor_id) || self.placed.contains_key(&anchor_id);
+        self.place(anchor_id).ok_or_else(|| {
+            let message = if known {
+                format!(
+                    "\"{}\" is dated from an event that couldn't be placed",
+                    event.title
+                )
+            } else {
+                format!(
+                    "\"{}\" is dated from an event that no longer exists",
+                    event.title
+                )
+            };
+            (TimelineIssueKind::UnknownAnchor, message)
+        })
+    }
+}
+
+/// A date in its calendar's format, leaving out what isn't known
+fn date_label(calendar: &CalendarSystem, date: &CalendarDate, precision: DatePrecision) -> String {
+    let mut format = calendar.format.clone();
+    if precision != DatePrecision::Day {
+        format = format.replace("{weekday}", "").replace("{day}", "");
+    }
+    if precision == DatePrecision::Year {
+        format = format.replace("{month}", "").replace("{month_number}", "");
+    }
+    let mut calendar = calendar.clone();
+    calendar.format = format;
+    calendar.format_date(date).unwrap_or_default()
+}
+
+/// Place story events and Time codex entries on the shared day line,
+/// earliest first. Events whose dates can't be resolved are reported and
+/// left off.
+pub fn place_story_events(
+    calendars: &[CalendarSystem],
+    codex_entries: &[TimelineEntry],
+    events: &[StoryEvent],
+) -> (Vec<PlacedEvent>, Vec<TimelineIssue>) {
+    let mut resolver = Resolver {
+        calendars,
+        events: events.iter().map(|e| (e.id, e)).collect(),
+        placed: HashMap::new(),
+        visiting: HashSet::new(),
+        issues: Vec::new(),
+    };
+    for entry in codex_entries {
+        let placed = PlacedEvent {
+            event_id: entry.event_id,
+            title: entry.title.clone(),
+            source: EventSource::Codex,
+            calendar_id: entry.calendar_id,
+            start: DaySpan::day(entry.start_day),
+            end: entry.end_day.map(DaySpan::day),
+            start_label: entry.start_label.clone(),
+            end_label: entry.end_label.clone(),
+            links: Vec::new(),
+        };
+        resolver.placed.insert(entry.event_id, Some(placed));
+    }
+    for event in events {
+        resolver.place(event.id);
+    }
+
+    let mut placed: Vec<PlacedEvent> = resolver.placed.into_values().flatten().collect();
+    placed.sort_by(|a, b| {
+        (a.start.earliest, a.start.latest, &a.title).cmp(&(
+            b.start.earliest,
+            b.start.latest,
+            &b.title,
+        ))
+    });
+    (placed, resolver.issues)
+}
+
+/// Characters at two different places at overlapping times, in timeline
+/// order. Events without a place can't conflict.
+pub fn find_presence_conflicts(events: &[PlacedEvent]) -> Vec<PresenceConflict> {
+    let mut characters: Vec<&EventLink> = Vec::new();
+    for link in events
+        .iter()
+        .flat_map(|e| e.linked(EventLinkKind::Character))
+    {
+        if !characters.iter().any(|c| c.target_id == link.target_id) {
+            characters.push(link);
+        }
+    }
+
+    let place_names = |places: &[&EventLink]| {
+        places
+            .iter()
+            .map(|p| p.title.as_str())
+            .collect::<Vec<_>>()
+            .join(" / ")
+    };
+
+    let mut conflicts = Vec::new();
+    for character in characters {
+        let present: Vec<&PlacedEvent> = events
+            .iter()
+            .filter(|e| {
+                e.linked(EventLinkKind::Character)
+                    .any(|c| c.target_id == character.target_id)
+                    && e.linked(EventLinkKind::Place).next().is_some()
+            })
+            .collect();
+        for (i, first) in present.iter().enumerate() {
+            for second in &present[i + 1..] {
+                let first_places: Vec<&EventLink> = first.linked(EventLinkKind::Place).collect();
+                let second_places: Vec<&EventLink> = second.linked(EventLinkKind::Place).collect();
+                let shared = first_places
+                    .iter()
+                    .any(|p| second_places.iter().any(|q| q.target_id == p.target_id));
+                if shared || !first.possible().overlaps(&second.possible()) {
+                    continue;
+                }
+                let certain = match (first.certain(), second.certain()) {
+                    (Some(a), Some(b)) => a.overlaps(&b),
+                    _ => false,
+                };
+                let (first_place, second_place) =
+                    (place_names(&first_places), place_names(&second_places));
+                conflicts.push(PresenceConflict {
+                    character_id: character.target_id,
+                    character: character.title.clone(),
+                    first_event_id: first.event_id,
+                    first_place: first_place.clone(),
+                    second_event_id: second.event_id,
+                    second_place: second_place.clone(),
+                    certain,
+                    message: format!(
+                        "{} {} at {} ({}) and at {} ({}) at the same time",
+                        character.title,
+                        if certain { "is" } else { "may be" },
+                        first_place,
+                        first.title,
+                        second_place,
+                        second.title
+                    ),
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::calendar::CalendarMonth;
+    use crate::database::models::codex::CREATE_CODEX_ENTRIES_TABLE_SQL;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_timeline_with_relative_dates_and_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        let now = Utc::now().to_rfc3339();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Ashfall', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(&now)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query(CREATE_CODEX_ENTRIES_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let (reckoning, mira, keep, harbor) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        for (id, entry_type, title, metadata) in [
+            (
+                reckoning,
+                "time",
+                "The Reckoning",
+                Some(r#"{"start_time": "1 Frost 1204 AR"}"#),
+            ),
+            (mira, "character_sheet", "Mira", None),
+            (keep, "place", "Ashfall Keep", None),
+            (harbor, "place", "The Harbor", None),
+        ] {
+            sqlx::query(
+                "INSERT INTO codex_entries (id, project_id, entry_type, title, content, created_at, updated_at, metadata)
+                 VALUES (?1, ?2, ?3, ?4, '', ?5, ?5, ?6)",
+            )
+            .bind(id.to_string())
+            .bind(project.to_string())
+            .bind(entry_type)
+            .bind(title)
+            .bind(&now)
+            .bind(metadata)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+        let db = Arc::new(RwLock::new(db));
+        let calendars = Arc::new(CalendarService::new(db.clone()));
+        calendars.initialize().await.unwrap();
+        let mut calendar = CalendarSystem::new(
+            project,
+            "Reckoning",
+            vec![
+                CalendarMonth::new("Frost", 40),
+                CalendarMonth::new("Thaw", 45),
+            ],
+        );
+        calendar.era_name = "AR".to_string();
+        calendars.save_calendar(&calendar).await.unwrap();
+        let service = TimelineService::new(db.clone(), calendars);
+        service.initialize().await.unwrap();
+
+        let relative = |anchor_id: Uuid, offset: i64, unit: OffsetUnit| EventDate::Relative {
+            anchor_id,
+            offset,
+            unit,
+            from_end: false,
+            give_or_take: 0,
+        };
+        let coronation = StoryEvent::new(
+            project,
+            "Coronation",
+            relative(reckoning, 11, OffsetUnit::Days),
+        )
+        .with_link(EventLinkKind::Character, mira)
+        .with_link(EventLinkKind::Place, keep);
+        let feast = StoryEvent::new(
+            project,
+            "Feast",
+            relative(coronation.id, 0, OffsetUnit::Days),
+        )
+        .with_link(EventLinkKind::Character, mira)
+        .with_link(EventLinkKind::Place, harbor);
+        let voyage = StoryEvent::new(
+            project,
+            "Voyage",
+            relative(coronation.id, 2, OffsetUnit::Weeks),
+        )
+        .with_link(EventLinkKind::Character, mira)
+        .with_link(EventLinkKind::Place, Uuid::new_v4());
+        let rumor = StoryEvent::new(
+            project,
+            "Rumor",
+            EventDate::Calendar {
+                calendar_id: None,
+                date: CalendarDate::new(1204, 2, 1),
+                precision: DatePrecision::Month,
+                give_or_take: 0,
+            },
+        )
+        .with_link(EventLinkKind::Character, mira)
+        .with_link(EventLinkKind::Place, harbor);
+        let mut first = StoryEvent::new(
+            project,
+            "First",
+            relative(Uuid::new_v4(), 1, OffsetUnit::Days),
+        );
+        let second = StoryEvent::new(project, "Second", relative(first.id, 1, OffsetUnit::Days));
+        first.start = relative(second.id, 1, OffsetUnit::Days);
+        let orphan = StoryEvent::new(
+            project,
+            "Orphan",
+            relative(Uuid::new_v4(), 1, OffsetUnit::Years),
+        );
+        for event in [
+            &coronation,
+            &feast,
+            &voyage,
+            &rumor,
+            &first,
+            &second,
+            &orphan,
+        ] {
+            service.save_event(event).await.unwrap();
+        }
+        let saved = service.get_event(feast.id).await.unwrap().unwrap();
+        assert_eq!((&saved.start, &saved.links), (&feast.start, &feast.links));
+
+        let timeline = service
+            .timeline(project, &TimelineFilter::default())
+            .await
+            .unwrap();
+        let order: Vec<(&str, &str)> = timeline
+            .events
+            .iter()
+            .map(|e| (e.title.as_str(), e.start_label.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("The Reckoning", "1 Frost 1204 AR"),
+                ("Coronation", "12 Frost 1204 AR"),
+                ("Feast", "12 Frost 1204 AR"),
+                ("Voyage", "26 Frost 1204 AR"),
+                ("Rumor", "Thaw 1204 AR"),
+            ]
+        );
+        // The Voyage's place was deleted, so it can't conflict
+        assert_eq!(timeline.conflicts.len(), 1);
+        assert!(timeline.conflicts[0].certain);
+        assert_eq!(
+            timeline.conflicts[0].message,
+            "Mira is at Ashfall Keep (Coronation) and at The Harbor (Feast) at the same time"
+        );
+        let mut issues: Vec<TimelineIssueKind> = timeline.issues.iter().map(|i| i.kind).collect();
+        issues.sort_by_key(|kind| format!("{:?}", kind));
+        assert_eq!(
+            issues,
+            vec![
+                TimelineIssueKind::AnchorCycle,
+                TimelineIssueKind::UnknownAnchor,
+                TimelineIssueKind::UnknownAnchor,
+            ]
+        );
+
+        let harbor_only = service
+            .timeline(
+                project,
+                &TimelineFilter {
+                    linked_to: Some(harbor),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(harbor_only.events.len(), 2);
+        assert_eq!(harbor_only.conflicts.len(), 1);
+
+        assert!(service.delete_event(coronation.id).await.unwrap());
+        let timeline = service
+            .timeline(project, &TimelineFilter::default())
+            .await
+            .unwrap();
+        assert!(timeline.conflicts.is_empty());
+        assert_eq!(timeline.events.len(), 2);
+    }
+
+    #[test]
+    fn test_possible_conflicts_from_vague_dates() {
+        let (mira, keep, harbor) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let event = |title: &str, start: DaySpan, place: Uuid| PlacedEvent {
+            event_id: Uuid::new_v4(),
+            title: title.to_string(),
+            source: EventSource::Event,
+            calendar_id: Uuid::new_v4(),
+            start,
+            end: None,
+            start_label: String::new(),
+            end_label: None,
+            links: vec![
+                EventLink {
+                    kind: EventLinkKind::Character,
+                    target_id: mira,
+                    title: "Mira".to_string(),
+                },
+                EventLink {
+                    kind: EventLinkKind::Place,
+                    target_id: place,
+                    title: if place == keep {
+                        "the Keep"
+                    } else {
+                        "the Harbor"
+                    }
+                    .to_string(),
+                },
+            ],
+        };
+        let events = [
+            event(
+                "Siege",
+                DaySpan {
+                    earliest: 1,
+                    latest: 40,
+                },
+                keep,
+            ),
+            event("Feast", DaySpan::day(12), harbor),
+            event("Vigil", DaySpan::day(12), keep),
+            event("Departure", DaySpan::day(50), harbor),
+        ];
+        let conflicts = find_presence_conflicts(&events);
+        let found: Vec<(&str, bool)> = conflicts
+            .iter()
+            .map(|c| (c.message.as_str(), c.certain))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "Mira may be at the Keep (Siege) and at the Harbor (Feast) at the same time",
+                    false
+                ),
+                (
+                    "Mira is at the Harbor (Feast) and at the Keep (Vigil) at the same time",
+                    true
+                ),
+            ]
+        );
+    }
+}
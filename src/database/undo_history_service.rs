@@ -0,0 +1,858 @@
+//! Undo History Service
+//!
+//! Performs structural operations (binder moves, codex entry deletion, bulk
+//! tagging) and records what each changed, giving every project an undo and
+//! redo stack that outlives the editor's text undo. An operation is only
+//! undone or redone when the rows it touched still hold the values it left
+//! behind; if something has changed them since, the step is refused rather
+//! than overwriting newer work.
+
+use chrono::Utc;
+use serde_json::{Map, Value};
+use sqlx::query::Query;
+use sqlx::sqlite::SqliteArguments;
+use sqlx::{Sqlite, SqliteConnection, SqlitePool};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::database::activity_service::record_activity;
+use crate::database::document_structure_service::{order_of, reorder};
+use crate::database::{
+    models::activity::{ActivityEntry, ActivityKind},
+    models::document_structure::CREATE_BINDER_ORDER_TABLE_SQL,
+    models::undo_history::*,
+    parse::{parse_time, parse_uuid},
+    DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+
+type UndoOperationRow = (String, String, String, String, String, i64, String);
+
+/// A table structural operations change, and the columns they touch
+struct TrackedTable {
+    name: &'static str,
+    key: &'static str,
+    columns: &'static [&'static str],
+}
+
+const BINDER_ROWS: TrackedTable = TrackedTable {
+    name: "binder_order",
+    key: "document_id",
+    columns: &["document_id", "project_id", "position"],
+};
+
+const CODEX_STATE: TrackedTable = TrackedTable {
+    name: "codex_entries",
+    key: "id",
+    columns: &["is_active"],
+};
+
+const DOCUMENT_METADATA: TrackedTable = TrackedTable {
+    name: "documents",
+    key: "id",
+    columns: &["metadata"],
+};
+
+const TRACKED_TABLES: [&TrackedTable; 3] = [&BINDER_ROWS, &CODEX_STATE, &DOCUMENT_METADATA];
+
+/// Service for undoable structural operations
+#[derive(Debug)]
+pub struct UndoHistoryService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    /// Keeps one operation, undo or redo running at a time so each sees the
+    /// rows the previous one left
+    running: Mutex<()>,
+}
+
+impl UndoHistoryService {
+    /// Create a new undo history service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self {
+            db_service,
+            running: Mutex::new(()),
+        }
+    }
+
+    /// Initialize the undo history table, and the binder order table moves
+    /// write to
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        for sql in [
+            CREATE_UNDO_OPERATIONS_TABLE_SQL,
+            CREATE_BINDER_ORDER_TABLE_SQL,
+        ] {
+            sqlx::query(sql).execute(&db.pool).await.map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create undo history table: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Reorder the binder, as `DocumentStructureService::set_binder_order`
+    pub async fn move_documents(
+        &self,
+        project_id: Uuid,
+        document_ids: &[Uuid],
+    ) -> DatabaseResult<Vec<Uuid>> {
+        let _running = self.running.lock().await;
+        let db = self.db_service.read().await;
+        let mut tx = db.pool.begin().await.map_err(begin_failed)?;
+
+        let placed: Vec<String> =
+            sqlx::query_scalar("SELECT document_id FROM binder_order WHERE project_id = ?1")
+                .bind(project_id.to_string())
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to get binder order: {}", e))
+                })?;
+        let keys: BTreeSet<String> = order_of(&mut tx, project_id)
+            .await?
+            .iter()
+            .map(Uuid::to_string)
+            .chain(placed)
+            .collect();
+        let before = capture_all(&mut tx, &BINDER_ROWS, &keys).await?;
+        let order = reorder(&mut tx, project_id, document_ids).await?;
+        let changes = changes_since(&mut tx, &BINDER_ROWS, before).await?;
+
+        let label = match document_ids {
+            [document_id] => format!("Move '{}'", document_title(&mut tx, *document_id).await?),
+            _ => "Reorder binder".to_string(),
+        };
+        let kind = UndoOperationKind::MoveDocuments;
+        let recorded = record(&mut tx, project_id, kind, &label, changes).await?;
+        tx.commit().await.map_err(commit_failed)?;
+        if recorded {
+            log_activity(&db.pool, project_id, kind, label, None).await;
+        }
+        Ok(order)
+    }
+
+    /// Delete a codex entry
+    pub async fn delete_codex_entry(&self, entry_id: Uuid) -> DatabaseResult<()> {
+        let _running = self.running.lock().await;
+        let db = self.db_service.read().await;
+        let mut tx = db.pool.begin().await.map_err(begin_failed)?;
+
+        let exists: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'codex_entries'",
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check codex table: {}", e)))?;
+        let entry: Option<(String, String)> = if exists > 0 {
+            sqlx::query_as(
+                "SELECT project_id, title FROM codex_entries WHERE id = ?1 AND is_active = 1",
+            )
+            .bind(entry_id.to_string())
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load codex entry: {}", e)))?
+        } else {
+            None
+        };
+        let Some((project_id, title)) = entry else {
+            return Err(DatabaseError::RecordNotFound {
+                entity: "codex entry".to_string(),
+                id: entry_id.to_string(),
+            });
+        };
+
+        let keys = BTreeSet::from([entry_id.to_string()]);
+        let before = capture_all(&mut tx, &CODEX_STATE, &keys).await?;
+        sqlx::query("UPDATE codex_entries SET is_active = 0 WHERE id = ?1")
+            .bind(entry_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete codex entry: {}", e)))?;
+        let changes = changes_since(&mut tx, &CODEX_STATE, before).await?;
+
+        let project_id = parse_uuid(&project_id)?;
+        let kind = UndoOperationKind::DeleteCodexEntry;
+        let label = format!("Delete codex entry '{}'", title);
+        let recorded = record(&mut tx, project_id, kind, &label, changes).await?;
+        tx.commit().await.map_err(commit_failed)?;
+        if recorded {
+            log_activity(
+                &db.pool,
+                project_id,
+                kind,
+                label,
+                Some(entry_id.to_string()),
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    /// Add tags to documents, or remove them. Returns how many documents
+    /// changed.
+    pub async fn tag_documents(
+        &self,
+        project_id: Uuid,
+        document_ids: &[Uuid],
+        tags: &[String],
+        remove: bool,
+    ) -> DatabaseResult<usize> {
+        let tags = normalize_tags(tags);
+        if tags.is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "At least one tag is required".to_string(),
+            ));
+        }
+
+        let _running = self.running.lock().await;
+        let db = self.db_service.read().await;
+        let mut tx = db.pool.begin().await.map_err(begin_failed)?;
+
+        let keys: BTreeSet<String> = document_ids.iter().map(Uuid::to_string).collect();
+        let before = capture_all(&mut tx, &DOCUMENT_METADATA, &keys).await?;
+        for document_id in &keys {
+            let metadata: Option<Option<String>> = sqlx::query_scalar(
+                "SELECT metadata FROM documents WHERE id = ?1 AND project_id = ?2 AND is_active = 1",
+            )
+            .bind(document_id)
+            .bind(project_id.to_string())
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load document: {}", e)))?;
+            let Some(metadata) = metadata else {
+                return Err(DatabaseError::RecordNotFound {
+                    entity: "document".to_string(),
+                    id: document_id.to_string(),
+                });
+            };
+            let updated = apply_tags(metadata.as_deref(), &tags, remove).map_err(|e| {
+                DatabaseError::ValidationError(format!(
+                    "Document {} has unreadable metadata: {}",
+                    document_id, e
+                ))
+            })?;
+            if let Some(updated) = updated {
+                sqlx::query("UPDATE documents SET metadata = ?1 WHERE id = ?2")
+                    .bind(updated)
+                    .bind(document_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Service(format!("Failed to tag document: {}", e))
+                    })?;
+            }
+        }
+        let changes = changes_since(&mut tx, &DOCUMENT_METADATA, before).await?;
+        let changed = changes.len();
+
+        let documents = match changed {
+            1 => "1 document".to_string(),
+            n => format!("{} documents", n),
+        };
+        let (kind, label) = if remove {
+            (
+                UndoOperationKind::UntagDocuments,
+                format!("Remove {} from {}", tags.join(", "), documents),
+            )
+        } else {
+            (
+                UndoOperationKind::TagDocuments,
+                format!("Tag {} with {}", documents, tags.join(", ")),
+            )
+        };
+        let recorded = record(&mut tx, project_id, kind, &label, changes).await?;
+        tx.commit().await.map_err(commit_failed)?;
+        if recorded {
+            log_activity(&db.pool, project_id, kind, label, None).await;
+        }
+        Ok(changed)
+    }
+
+    /// Undo a project's most recent operation and return it
+    pub async fn undo(&self, project_id: Uuid) -> DatabaseResult<UndoOperation> {
+        self.step(project_id, true).await
+    }
+
+    /// Redo a project's most recently undone operation and return it
+    pub async fn redo(&self, project_id: Uuid) -> DatabaseResult<UndoOperation> {
+        self.step(project_id, false).await
+    }
+
+    /// What Undo and Redo would do next, with the project's history
+    pub async fn state(&self, project_id: Uuid) -> DatabaseResult<UndoState> {
+        let db = self.db_service.read().await;
+        let rows: Vec<UndoOperationRow> = sqlx::query_as(&format!(
+            "{} WHERE project_id = ?1 ORDER BY sequence DESC",
+            SELECT_UNDO_OPERATIONS_SQL
+        ))
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load undo history: {}", e)))?;
+        let operations = rows
+            .into_iter()
+            .map(operation_from_row)
+            .collect::<DatabaseResult<Vec<_>>>()?;
+        Ok(undo_state(project_id, &operations))
+    }
+
+    /// Forget a project's history
+    pub async fn clear(&self, project_id: Uuid) -> DatabaseResult<u64> {
+        let _running = self.running.lock().await;
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM undo_operations WHERE project_id = ?1")
+            .bind(project_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to clear undo history: {}", e)))?;
+        Ok(result.rows_affected())
+    }
+
+    async fn step(&self, project_id: Uuid, undo: bool) -> DatabaseResult<UndoOperation> {
+        let _running = self.running.lock().await;
+        let db = self.db_service.read().await;
+        let mut tx = db.pool.begin().await.map_err(begin_failed)?;
+
+        // Undo takes the newest done operation, redo the oldest undone one
+        let filter = if undo {
+            "undone = 0 ORDER BY sequence DESC"
+        } else {
+            "undone = 1 ORDER BY sequence ASC"
+        };
+        let row: Option<UndoOperationRow> = sqlx::query_as(&format!(
+            "{} WHERE project_id = ?1 AND {} LIMIT 1",
+            SELECT_UNDO_OPERATIONS_SQL, filter
+        ))
+        .bind(project_id.to_string())
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load undo history: {}", e)))?;
+        let Some(row) = row else {
+            return Err(DatabaseError::InvalidState {
+                state: format!("Nothing to {}", if undo { "undo" } else { "redo" }),
+            });
+        };
+        let mut operation = operation_from_row(row)?;
+
+        let ordered: Vec<&RowChange> = if undo {
+            operation.changes.iter().rev().collect()
+        } else {
+            operation.changes.iter().collect()
+        };
+        let mut targets = Vec::with_capacity(ordered.len());
+        for change in &ordered {
+            let table = tracked_table(&change.table)?;
+            let (expected, target) = if undo {
+                (&change.after, &change.before)
+            } else {
+                (&change.before, &change.after)
+            };
+            if &capture(&mut tx, table, &change.key).await? != expected {
+                return Err(DatabaseError::ValidationError(format!(
+                    "Can't {} '{}': it has been changed since",
+                    if undo { "undo" } else { "redo" },
+                    operation.label
+                )));
+            }
+            targets.push((table, change.key.as_str(), target.as_ref()));
+        }
+        for (table, key, target) in targets {
+            write_row(&mut tx, table, key, target).await?;
+        }
+
+        sqlx::query("UPDATE undo_operations SET undone = ?1 WHERE id = ?2")
+            .bind(undo)
+            .bind(operation.id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to update undo history: {}", e)))?;
+        tx.commit().await.map_err(commit_failed)?;
+
+        let subject = match operation.kind {
+            UndoOperationKind::DeleteCodexEntry => {
+                operation.changes.first().map(|change| change.key.clone())
+            }
+            _ => None,
+        };
+        let summary = format!(
+            "{} '{}'",
+            if undo { "Undid" } else { "Redid" },
+            operation.label
+        );
+        log_activity(&db.pool, project_id, operation.kind, summary, subject).await;
+
+        operation.undone = undo;
+        Ok(operation)
+    }
+}
+
+/// Undo and redo candidates from a project's operations, newest first
+pub fn undo_state(project_id: Uuid, operations: &[UndoOperation]) -> UndoState {
+    UndoState {
+        project_id,
+        undo: operations
+            .iter()
+            .find(|operation| !operation.undone)
+            .map(UndoSummary::from),
+        redo: operations
+            .iter()
+            .rev()
+            .find(|operation| operation.undone)
+            .map(UndoSummary::from),
+        history: operations.iter().map(UndoSummary::from).collect(),
+    }
+}
+
+/// Trimmed, non-empty tags without repeats, in the order given
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|tag| tag.trim()) {
+        if !tag.is_empty() && !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+/// Document metadata with `tags` added or removed, or `None` when nothing
+/// changes. Metadata that is set but not a JSON object is an error.
+pub fn apply_tags(
+    metadata: Option<&str>,
+    tags: &[String],
+    remove: bool,
+) -> Result<Option<String>, String> {
+    let mut object = match metadata.map(str::trim).filter(|m| !m.is_empty()) {
+        Some(metadata) => match serde_json::from_str::<Value>(metadata) {
+            Ok(Value::Object(object)) => object,
+            Ok(_) => return Err("metadata is not a JSON object".to_string()),
+            Err(e) => return Err(e.to_string()),
+        },
+        None => Map::new(),
+    };
+    let current: Vec<String> = object
+        .get("tags")
+        .and_then(Value::as_array)
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|tag| tag.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let updated: Vec<String> = if remove {
+        current
+            .iter()
+            .filter(|tag| !tags.contains(tag))
+            .cloned()
+            .collect()
+    } else {
+        let mut updated = current.clone();
+        updated.extend(tags.iter().filter(|tag| !current.contains(tag)).cloned());
+        updated
+    };
+    if updated == current {
+        return Ok(None);
+    }
+    object.insert(
+        "tags".to_string(),
+        Value::Array(updated.into_iter().map(Value::String).collect()),
+    );
+    Ok(Some(Value::Object(object).to_string()))
+}
+
+fn tracked_table(name: &str) -> DatabaseResult<&'static TrackedTable> {
+    TRACKED_TABLES
+        .into_iter()
+        .find(|table| table.name == name)
+        .ok_or_else(|| DatabaseError::Service(format!("Undo history names unknown table {}", name)))
+}
+
+/// The tracked columns of one row, or `None` when there is no such row
+async fn capture(
+    conn: &mut SqliteConnection,
+    table: &TrackedTable,
+    key: &str,
+) -> DatabaseResult<Option<Value>> {
+    let fields: Vec<String> = table
+        .columns
+        .iter()
+        .map(|column| format!("'{0}', {0}", column))
+        .collect();
+    let sql = format!(
+        "SELECT json_object({}) FROM {} WHERE {} = ?1",
+        fields.join(", "),
+        table.name,
+        table.key
+    );
+    let json: Option<String> = sqlx::query_scalar(&sql)
+        .bind(key)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to read {}: {}", table.name, e)))?;
+    json.map(|json| {
+        serde_json::from_str(&json)
+            .map_err(|e| DatabaseError::Service(format!("Failed to read {}: {}", table.name, e)))
+    })
+    .transpose()
+}
+
+async fn capture_all(
+    conn: &mut SqliteConnection,
+    table: &TrackedTable,
+    keys: &BTreeSet<String>,
+) -> DatabaseResult<Vec<(String, Option<Value>)>> {
+    let mut rows = Vec::with_capacity(keys.len());
+    for key in keys {
+        rows.push((key.clone(), capture(conn, table, key).await?));
+    }
+    Ok(rows)
+}
+
+/// The rows in `before` that an operation has since changed
+async fn changes_since(
+    conn: &mut SqliteConnection,
+    table: &TrackedTable,
+    before: Vec<(String, Option<Value>)>,
+) -> DatabaseResult<Vec<RowChange>> {
+    let mut changes = Vec::new();
+    for (key, before) in before {
+        let after = capture(conn, table, &key).await?;
+        if after != before {
+            changes.push(RowChange {
+                table: table.name.to_string(),
+                key,
+                before,
+                after,
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// Put a row back to `values`, deleting it when `values` is `None`
+async fn write_row(
+    conn: &mut SqliteConnection,
+    table: &TrackedTable,
+    key: &str,
+    values: Option<&Value>,
+) -> DatabaseResult<()> {
+    let failed = |e: sqlx::Error| {
+        DatabaseError::Service(format!("Failed to restore {} {}: {}", table.name, key, e))
+    };
+    let Some(values) = values else {
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE {} = ?1",
+            table.name, table.key
+        ))
+        .bind(key)
+        .execute(&mut *conn)
+        .await
+        .map_err(failed)?;
+        return Ok(());
+    };
+
+    let values: Vec<(&str, &Value)> = values
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, value)| {
+            table
+                .columns
+                .iter()
+                .find(|column| **column == name)
+                .map(|column| (*column, value))
+        })
+        .collect();
+    let exists = capture(conn, table, key).await?.is_some();
+    let sql = if exists {
+        let assignments: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(i, (column, _))| format!("{} = ?{}", column, i + 1))
+            .collect();
+        format!(
+            "UPDATE {} SET {} WHERE {} = ?{}",
+            table.name,
+            assignments.join(", "),
+            table.key,
+            values.len() + 1
+        )
+    } else {
+        let columns: Vec<&str> = values.iter().map(|(column, _)| *column).collect();
+        let placeholders: Vec<String> = (1..=values.len()).map(|i| format!("?{}", i)).collect();
+        format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table.name,
+            columns.join(", "),
+            placeholders.join(", ")
+        )
+    };
+    let mut query = sqlx::query(&sql);
+    for (_, value) in &values {
+        query = bind_value(query, value);
+    }
+    if exists {
+        query = query.bind(key);
+    }
+    query.execute(&mut *conn).await.map_err(failed)?;
+    Ok(())
+}
+
+fn bind_value<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    value: &Value,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(value) => query.bind(*value),
+        Value::Number(number) => match number.as_i64() {
+            Some(value) => query.bind(value),
+            None => query.bind(number.as_f64()),
+        },
+        Value::String(value) => query.bind(value.clone()),
+        other => query.bind(other.to_string()),
+    }
+}
+
+/// Add an operation to the top of the undo stack, dropping anything that
+/// was waiting to be redone. Operations that changed nothing are not
+/// recorded.
+async fn record(
+    conn: &mut SqliteConnection,
+    project_id: Uuid,
+    kind: UndoOperationKind,
+    label: &str,
+    changes: Vec<RowChange>,
+) -> DatabaseResult<bool> {
+    if changes.is_empty() {
+        return Ok(false);
+    }
+    let failed =
+        |e: sqlx::Error| DatabaseError::Service(format!("Failed to record undo history: {}", e));
+    sqlx::query("DELETE FROM undo_operations WHERE project_id = ?1 AND undone = 1")
+        .bind(project_id.to_string())
+        .execute(&mut *conn)
+        .await
+        .map_err(failed)?;
+    let sequence: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(sequence), 0) + 1 FROM undo_operations WHERE project_id = ?1",
+    )
+    .bind(project_id.to_string())
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(failed)?;
+    let changes = serde_json::to_string(&changes)
+        .map_err(|e| DatabaseError::Service(format!("Failed to encode undo history: {}", e)))?;
+
+    sqlx::query(INSERT_UNDO_OPERATION_SQL)
+        .bind(Uuid::new_v4().to_string())
+        .bind(project_id.to_string())
+        .bind(sequence)
+        .bind(kind.as_str())
+        .bind(label)
+        .bind(changes)
+        .bind(false)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut *conn)
+        .await
+        .map_err(failed)?;
+    sqlx::query("DELETE FROM undo_operations WHERE project_id = ?1 AND sequence <= ?2")
+        .bind(project_id.to_string())
+        .bind(sequence - MAX_UNDO_OPERATIONS)
+        .execute(&mut *conn)
+        .await
+        .map_err(failed)?;
+    Ok(true)
+}
+
+/// Add a committed operation to the project's activity feed
+async fn log_activity(
+    pool: &SqlitePool,
+    project_id: Uuid,
+    kind: UndoOperationKind,
+    summary: String,
+    subject_id: Option<String>,
+) {
+    let activity_kind = match kind {
+        UndoOperationKind::DeleteCodexEntry => ActivityKind::CodexChanged,
+        _ => ActivityKind::Structure,
+    };
+    let mut entry = ActivityEntry::new(Some(project_id), activity_kind, summary);
+    entry.subject_id = subject_id;
+    if let Err(e) = record_activity(pool, &entry).await {
+        log::warn!("Failed to record activity: {}", e);
+    }
+}
+
+async fn document_title(conn: &mut SqliteConnection, document_id: Uuid) -> DatabaseResult<String> {
+    let title: Option<String> = sqlx::query_scalar("SELECT title FROM documents WHERE id = ?1")
+        .bind(document_id.to_string())
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load document: {}", e)))?;
+    Ok(title.unwrap_or_else(|| document_id.to_string()))
+}
+
+fn begin_failed(e: sqlx::Error) -> DatabaseError {
+    DatabaseError::Service(format!("Failed to begin transaction: {}", e))
+}
+
+fn commit_failed(e: sqlx::Error) -> DatabaseError {
+    DatabaseError::Service(format!("Failed to commit operation: {}", e))
+}
+
+fn operation_from_row(row: UndoOperationRow) -> DatabaseResult<UndoOperation> {
+    let (id, project_id, kind, label, changes, undone, created_at) = row;
+    Ok(UndoOperation {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        kind: UndoOperationKind::parse(&kind).ok_or_else(|| {
+            DatabaseError::Service(format!("Unknown undo operation kind: {}", kind))
+        })?,
+        label,
+        changes: serde_json::from_str(&changes)
+            .map_err(|e| DatabaseError::Service(format!("Failed to read undo history: {}", e)))?,
+        undone: undone != 0,
+        created_at: parse_time(&created_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{DatabaseConfig, DocumentStructureService};
+
+    #[tokio::test]
+    async fn test_structural_operations_undo_and_redo() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Novel', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let (one, two) = (Uuid::new_v4(), Uuid::new_v4());
+        for (id, title) in [(one, "Arrival"), (two, "Departure")] {
+            db.create_document(
+                id.to_string(),
+                project.to_string(),
+                title.to_string(),
+                "Text.".to_string(),
+            )
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            "CREATE TABLE codex_entries (id TEXT PRIMARY KEY, project_id TEXT, title TEXT, is_active INTEGER)",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let mara = Uuid::new_v4();
+        sqlx::query("INSERT INTO codex_entries VALUES (?1, ?2, 'Mara', 1)")
+            .bind(mara.to_string())
+            .bind(project.to_string())
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let structure = DocumentStructureService::new(db.clone());
+        structure.initialize().await.unwrap();
+        structure
+            .set_binder_order(project, &[one, two])
+            .await
+            .unwrap();
+        let history = UndoHistoryService::new(db.clone());
+        history.initialize().await.unwrap();
+
+        history.move_documents(project, &[two]).await.unwrap();
+        history.delete_codex_entry(mara).await.unwrap();
+        let tag = vec!["act one".to_string(), " act one ".to_string()];
+        assert_eq!(
+            history
+                .tag_documents(project, &[one, two], &tag, false)
+                .await
+                .unwrap(),
+            2
+        );
+        let state = history.state(project).await.unwrap();
+        assert_eq!(state.history.len(), 3);
+        assert_eq!(state.undo.unwrap().label, "Tag 2 documents with act one");
+        assert!(state.redo.is_none());
+
+        let is_active = || async {
+            let db = db.read().await;
+            sqlx::query_scalar::<_, i64>("SELECT is_active FROM codex_entries WHERE id = ?1")
+                .bind(mara.to_string())
+                .fetch_one(&db.pool)
+                .await
+                .unwrap()
+        };
+        history.undo(project).await.unwrap();
+        history.undo(project).await.unwrap();
+        assert_eq!(is_active().await, 1);
+        let undone = history.undo(project).await.unwrap();
+        assert_eq!(undone.label, "Move 'Departure'");
+        assert_eq!(
+            structure.binder_order(project).await.unwrap(),
+            vec![one, two]
+        );
+        assert!(matches!(
+            history.undo(project).await,
+            Err(DatabaseError::InvalidState { .. })
+        ));
+
+        history.redo(project).await.unwrap();
+        assert_eq!(
+            structure.binder_order(project).await.unwrap(),
+            vec![two, one]
+        );
+        let state = history.state(project).await.unwrap();
+        assert_eq!(state.redo.unwrap().label, "Delete codex entry 'Mara'");
+
+        // A new operation drops the redo stack; edits made since block undo
+        history
+            .tag_documents(project, &[one], &tag, false)
+            .await
+            .unwrap();
+        assert!(history.state(project).await.unwrap().redo.is_none());
+        {
+            let db = db.read().await;
+            sqlx::query("UPDATE documents SET metadata = '{}' WHERE id = ?1")
+                .bind(one.to_string())
+                .execute(&db.pool)
+                .await
+                .unwrap();
+        }
+        assert!(matches!(
+            history.undo(project).await,
+            Err(DatabaseError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_apply_tags() {
+        let tags = vec!["draft".to_string(), "act one".to_string()];
+        assert_eq!(
+            apply_tags(None, &tags, false).unwrap().unwrap(),
+            r#"{"tags":["draft","act one"]}"#
+        );
+        let tagged = r#"{"pov":"Mara","tags":["act one"]}"#;
+        assert_eq!(
+            apply_tags(Some(tagged), &tags, false).unwrap().unwrap(),
+            r#"{"pov":"Mara","tags":["act one","draft"]}"#
+        );
+        assert_eq!(
+            apply_tags(Some(tagged), &tags, true).unwrap().unwrap(),
+            r#"{"pov":"Mara","tags":[]}"#
+        );
+        assert_eq!(apply_tags(Some(tagged), &tags[1..], false).unwrap(), None);
+        assert!(apply_tags(Some("[1]"), &tags, false).is_err());
+    }
+}
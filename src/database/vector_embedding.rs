@@ -3,20 +3,86 @@
 //! Provides comprehensive vector embedding functionality including document chunking,
 //! semantic search, and LLM integration for advanced document operations.
 
+use crate::database::local_embeddings::{EmbeddingBackend, LocalEmbedder, LOCAL_PROVIDER};
 use crate::database::models::{
-    BatchEmbeddingRequest, DocumentEmbedding, EmbeddingStatistics, SearchResult,
+    BatchEmbeddingRequest, DocumentEmbedding, EmbeddingMigration, EmbeddingMigrationStatus,
+    EmbeddingModel, EmbeddingStatistics, SearchResult,
 };
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::prosemirror;
+use crate::security::network;
 use crate::{error::DatabaseError, error::DatabaseResult, EnhancedDatabaseService};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{OnceCell, RwLock};
 use uuid::Uuid;
 
+type MigrationRow = (
+    String,
+    String,
+    String,
+    String,
+    i64,
+    i64,
+    i64,
+    bool,
+    Option<String>,
+    String,
+    Option<String>,
+);
+
+/// Registry of embedding models and the jobs moving vectors between them
+const CREATE_EMBEDDING_MODEL_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS embedding_models (
+    name TEXT PRIMARY KEY,
+    dimensions INTEGER NOT NULL,
+    provider TEXT NOT NULL DEFAULT '',
+    is_default INTEGER NOT NULL DEFAULT 0,
+    registered_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS embedding_migrations (
+    id TEXT PRIMARY KEY,
+    from_model TEXT NOT NULL,
+    to_model TEXT NOT NULL,
+    status TEXT NOT NULL,
+    total_documents INTEGER NOT NULL DEFAULT 0,
+    processed_documents INTEGER NOT NULL DEFAULT 0,
+    failed_documents INTEGER NOT NULL DEFAULT 0,
+    prune_old INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    started_at TEXT NOT NULL,
+    finished_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_embeddings_model ON document_embeddings(model_name, document_id);
+"#;
+
+/// Active documents with vectors from some model but none from `?1`
+const DOCUMENTS_TO_MIGRATE_SQL: &str = r#"
+SELECT DISTINCT de.document_id FROM document_embeddings de
+JOIN documents d ON d.id = de.document_id
+WHERE d.is_active = 1
+  AND de.model_name != ?1
+  AND NOT EXISTS (
+      SELECT 1 FROM document_embeddings n WHERE n.document_id = de.document_id AND n.model_name = ?1
+  )
+ORDER BY de.document_id
+"#;
+
+const MIGRATION_COLUMNS: &str = "id, from_model, to_model, status, total_documents, \
+     processed_documents, failed_documents, prune_old, error, started_at, finished_at";
+
 /// Vector embedding service with database integration
 #[derive(Debug)]
 pub struct VectorEmbeddingService {
     db_service: Arc<RwLock<EnhancedDatabaseService>>,
     config: VectorConfig,
+    /// Loaded on first use of the local backend
+    local: OnceCell<Arc<LocalEmbedder>>,
 }
 
 /// Configuration for vector operations
@@ -62,6 +128,65 @@ pub struct SearchOptions {
     pub document_filter: Option<Uuid>,
 }
 
+/// Options for finding repeated paragraphs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateOptions {
+    /// Lowest cosine similarity reported as a duplicate
+    #[serde(default = "default_duplicate_threshold")]
+    pub threshold: f32,
+    /// Shorter paragraphs, such as dialogue lines, are skipped
+    #[serde(default = "default_duplicate_min_words")]
+    pub min_words: usize,
+    /// Model to embed with; the default model when unset
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+fn default_duplicate_threshold() -> f32 {
+    0.9
+}
+
+fn default_duplicate_min_words() -> usize {
+    12
+}
+
+impl Default for DuplicateOptions {
+    fn default() -> Self {
+        Self {
+            threshold: default_duplicate_threshold(),
+            min_words: default_duplicate_min_words(),
+            model: None,
+            limit: None,
+        }
+    }
+}
+
+/// Where a paragraph sits in the manuscript
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParagraphLocation {
+    pub document_id: Uuid,
+    pub title: String,
+    /// Place of the document in the binder, from 1
+    pub position: usize,
+    /// Paragraph within the document, from 0
+    pub paragraph_index: usize,
+    /// Character offsets in the document content
+    pub start_char: usize,
+    pub end_char: usize,
+    pub excerpt: String,
+}
+
+/// Two paragraphs that say nearly the same thing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicatePair {
+    /// The earlier of the two in manuscript order
+    pub first: ParagraphLocation,
+    pub second: ParagraphLocation,
+    pub similarity: f32,
+}
+
 /// LLM API integration (placeholder for future implementation)
 #[derive(Debug, Clone)]
 pub struct LLMApiClient {
@@ -82,10 +207,7 @@ pub struct RateLimiter {
 impl VectorEmbeddingService {
     /// Create a new vector embedding service
     pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
-        Self {
-            db_service,
-            config: VectorConfig::default(),
-        }
+        Self::with_config(db_service, VectorConfig::default())
     }
 
     /// Create with custom configuration
@@ -93,7 +215,502 @@ impl VectorEmbeddingService {
         db_service: Arc<RwLock<EnhancedDatabaseService>>,
         config: VectorConfig,
     ) -> Self {
-        Self { db_service, config }
+        Self {
+            db_service,
+            config,
+            local: OnceCell::new(),
+        }
+    }
+
+    /// Create the model registry, record the dimensions of stored vectors
+    /// and register the models they came from
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        {
+            let db = self.db_service.read().await;
+            let columns: Vec<String> =
+                sqlx::query_scalar("SELECT name FROM pragma_table_info('document_embeddings')")
+                    .fetch_all(&db.pool)
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Migration(format!("Failed to inspect embeddings: {}", e))
+                    })?;
+            if !columns.iter().any(|c| c == "dimensions") {
+                sqlx::query(
+                    "ALTER TABLE document_embeddings ADD COLUMN dimensions INTEGER NOT NULL DEFAULT 0",
+                )
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to add embedding dimensions: {}", e))
+                })?;
+            }
+            sqlx::query(CREATE_EMBEDDING_MODEL_TABLES_SQL)
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!(
+                        "Failed to create embedding model tables: {}",
+                        e
+                    ))
+                })?;
+
+            // Vectors are bincode-encoded: an 8-byte length, then 4 bytes a value
+            sqlx::query(
+                "UPDATE document_embeddings SET dimensions = (LENGTH(vector_data) - 8) / 4 WHERE dimensions = 0",
+            )
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to backfill embedding dimensions: {}", e))
+            })?;
+
+            // A job cut short by shutdown starts over where it stopped
+            sqlx::query(
+                "UPDATE embedding_migrations SET status = 'pending' WHERE status = 'running'",
+            )
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to reset embedding migrations: {}", e))
+            })?;
+        }
+
+        let stored: Vec<(String, i64)> = {
+            let db = self.db_service.read().await;
+            sqlx::query_as(
+                "SELECT model_name, MAX(dimensions) FROM document_embeddings
+                 WHERE model_name NOT IN (SELECT name FROM embedding_models)
+                 GROUP BY model_name",
+            )
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to list stored models: {}", e)))?
+        };
+        for (model, dimensions) in stored {
+            self.register_model(&model, dimensions as usize, "").await?;
+        }
+
+        let has_default: i64 = {
+            let db = self.db_service.read().await;
+            sqlx::query_scalar("SELECT COUNT(*) FROM embedding_models WHERE is_default = 1")
+                .fetch_one(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to read default model: {}", e))
+                })?
+        };
+        // A configured local model is registered with its real vector
+        // length, and is what a new library embeds with
+        let mut local_model = None;
+        if let EmbeddingBackend::Local { model_name, .. } = self.backend().await {
+            match self.local_embedder().await {
+                Ok(embedder) => {
+                    self.register_model(&model_name, embedder.dimensions(), LOCAL_PROVIDER)
+                        .await?;
+                    local_model = Some(model_name);
+                }
+                Err(e) => log::warn!("Local embedding model unavailable: {}", e),
+            }
+        }
+
+        if has_default == 0 {
+            let model = match local_model {
+                Some(model) => model,
+                None => {
+                    let model = self.config.default_model.clone();
+                    if self.registered_model(&model).await?.is_none() {
+                        self.register_model(&model, known_dimensions(&model), "openai")
+                            .await?;
+                    }
+                    model
+                }
+            };
+            self.set_default_model(&model).await?;
+        }
+        Ok(())
+    }
+
+    async fn backend(&self) -> EmbeddingBackend {
+        let db = self.db_service.read().await;
+        db.config().embedding_backend.clone()
+    }
+
+    /// The configured local model, loaded on first call
+    async fn local_embedder(&self) -> DatabaseResult<Arc<LocalEmbedder>> {
+        let EmbeddingBackend::Local {
+            model_name,
+            model_dir,
+        } = self.backend().await
+        else {
+            return Err(DatabaseError::ValidationError(
+                "No local embedding model is configured".to_string(),
+            ));
+        };
+        self.local
+            .get_or_try_init(|| async move {
+                tokio::task::spawn_blocking(move || LocalEmbedder::load(&model_name, &model_dir))
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Service(format!("Local model loading failed: {}", e))
+                    })?
+                    .map(Arc::new)
+            })
+            .await
+            .cloned()
+    }
+
+    /// The model to embed with: the one asked for, else the default. While
+    /// the app is offline, remote models give way to the local model.
+    async fn resolve_model(&self, requested: Option<String>) -> DatabaseResult<String> {
+        let model = match requested {
+            Some(model) => model,
+            None => self.default_model().await?,
+        };
+        let backend = self.backend().await;
+        offline_model(model, backend.local_model(), network::is_offline())
+    }
+
+    /// Register a model, or update the provider of a registered one. The
+    /// dimensions of a model with stored vectors can't change.
+    pub async fn register_model(
+        &self,
+        name: &str,
+        dimensions: usize,
+        provider: &str,
+    ) -> DatabaseResult<EmbeddingModel> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Model name cannot be empty".to_string(),
+            ));
+        }
+        if dimensions == 0 {
+            return Err(DatabaseError::ValidationError(
+                "Model dimensions must be positive".to_string(),
+            ));
+        }
+        if let Some(existing) = self.registered_model(name).await? {
+            if existing.dimensions != dimensions && existing.embeddings > 0 {
+                return Err(DatabaseError::ValidationError(format!(
+                    "{} has {} stored vectors of {} dimensions; register the new version under another name",
+                    name, existing.embeddings, existing.dimensions
+                )));
+            }
+        }
+
+        {
+            let db = self.db_service.read().await;
+            sqlx::query(
+                "INSERT INTO embedding_models (name, dimensions, provider, registered_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(name) DO UPDATE SET dimensions = excluded.dimensions, provider = excluded.provider",
+            )
+            .bind(name)
+            .bind(dimensions as i64)
+            .bind(provider.trim())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to register embedding model: {}", e))
+            })?;
+        }
+        self.registered_model(name)
+            .await?
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "Embedding model".to_string(),
+                id: name.to_string(),
+            })
+    }
+
+    /// Registered models with how much is stored for each, default first
+    pub async fn list_models(&self) -> DatabaseResult<Vec<EmbeddingModel>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<(String, i64, String, bool, String, i64, i64)> = sqlx::query_as(
+            "SELECT m.name, m.dimensions, m.provider, m.is_default, m.registered_at,
+                    COUNT(de.id), COUNT(DISTINCT de.document_id)
+             FROM embedding_models m
+             LEFT JOIN document_embeddings de ON de.model_name = m.name
+             GROUP BY m.name
+             ORDER BY m.is_default DESC, m.name",
+        )
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to list embedding models: {}", e)))?;
+        rows.into_iter().map(model_from_row).collect()
+    }
+
+    /// Model used for new embeddings and for queries that don't pick one
+    pub async fn default_model(&self) -> DatabaseResult<String> {
+        let db = self.db_service.read().await;
+        let model: Option<String> =
+            sqlx::query_scalar("SELECT name FROM embedding_models WHERE is_default = 1")
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to read default model: {}", e))
+                })?;
+        Ok(model.unwrap_or_else(|| self.config.default_model.clone()))
+    }
+
+    /// Make a registered model the default straight away. Documents
+    /// embedded with other models drop out of default searches until a
+    /// migration re-embeds them.
+    pub async fn set_default_model(&self, name: &str) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        let updated = sqlx::query(
+            "UPDATE embedding_models SET is_default = (name = ?1)
+             WHERE EXISTS (SELECT 1 FROM embedding_models WHERE name = ?1)",
+        )
+        .bind(name)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to set default model: {}", e)))?;
+        if updated.rows_affected() == 0 {
+            return Err(DatabaseError::RecordNotFound {
+                entity: "Embedding model".to_string(),
+                id: name.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Start moving the corpus to another model. Returns the job, which
+    /// `run_migration` carries out; until it completes, the current default
+    /// keeps answering queries and either model can be searched.
+    pub async fn start_migration(
+        &self,
+        to_model: &str,
+        prune_old: bool,
+    ) -> DatabaseResult<EmbeddingMigration> {
+        if self.registered_model(to_model).await?.is_none() {
+            return Err(DatabaseError::RecordNotFound {
+                entity: "Embedding model".to_string(),
+                id: to_model.to_string(),
+            });
+        }
+        if let Some(active) = self.active_migration().await? {
+            return Err(DatabaseError::ValidationError(format!(
+                "A migration to {} is already {}",
+                active.to_model,
+                active.status.as_str()
+            )));
+        }
+
+        let from_model = self.default_model().await?;
+        let total = self.documents_to_migrate(to_model).await?.len();
+        let migration = EmbeddingMigration {
+            id: Uuid::new_v4(),
+            from_model,
+            to_model: to_model.to_string(),
+            status: EmbeddingMigrationStatus::Pending,
+            total_documents: total,
+            processed_documents: 0,
+            failed_documents: 0,
+            prune_old,
+            error: None,
+            started_at: Utc::now(),
+            finished_at: None,
+        };
+
+        let db = self.db_service.read().await;
+        sqlx::query(
+            "INSERT INTO embedding_migrations (id, from_model, to_model, status, total_documents, prune_old, started_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )
+        .bind(migration.id.to_string())
+        .bind(&migration.from_model)
+        .bind(&migration.to_model)
+        .bind(migration.status.as_str())
+        .bind(total as i64)
+        .bind(prune_old)
+        .bind(migration.started_at.to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            DatabaseError::Service(format!("Failed to start embedding migration: {}", e))
+        })?;
+        Ok(migration)
+    }
+
+    /// Re-embed every document that has no vectors from the job's model,
+    /// then make that model the default. Documents already done are
+    /// skipped, so an interrupted job can simply be run again.
+    pub async fn run_migration(&self, migration_id: Uuid) -> DatabaseResult<EmbeddingMigration> {
+        let migration =
+            self.migration(migration_id)
+                .await?
+                .ok_or_else(|| DatabaseError::RecordNotFound {
+                    entity: "Embedding migration".to_string(),
+                    id: migration_id.to_string(),
+                })?;
+        if migration.status.is_finished() {
+            return Ok(migration);
+        }
+        self.update_migration(migration_id, EmbeddingMigrationStatus::Running, None)
+            .await?;
+
+        let mut failed = 0;
+        for document_id in self.documents_to_migrate(&migration.to_model).await? {
+            if let Some(current) = self.migration(migration_id).await? {
+                if current.status == EmbeddingMigrationStatus::Cancelled {
+                    return Ok(current);
+                }
+            }
+            if let Err(e) = self
+                .generate_document_embeddings(&document_id, Some(migration.to_model.clone()))
+                .await
+            {
+                log::warn!("Failed to re-embed document {}: {}", document_id, e);
+                failed += 1;
+            }
+
+            let db = self.db_service.read().await;
+            sqlx::query(
+                "UPDATE embedding_migrations
+                 SET processed_documents = processed_documents + 1, failed_documents = ?2
+                 WHERE id = ?1",
+            )
+            .bind(migration_id.to_string())
+            .bind(failed as i64)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to update embedding migration: {}", e))
+            })?;
+        }
+
+        if failed > 0 {
+            self.update_migration(
+                migration_id,
+                EmbeddingMigrationStatus::Failed,
+                Some(format!(
+                    "{} documents could not be re-embedded; the default model is unchanged",
+                    failed
+                )),
+            )
+            .await?;
+        } else {
+            self.set_default_model(&migration.to_model).await?;
+            if migration.prune_old {
+                let db = self.db_service.read().await;
+                sqlx::query("DELETE FROM document_embeddings WHERE model_name != ?1")
+                    .bind(&migration.to_model)
+                    .execute(&db.pool)
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Service(format!("Failed to prune old embeddings: {}", e))
+                    })?;
+            }
+            self.update_migration(migration_id, EmbeddingMigrationStatus::Completed, None)
+                .await?;
+        }
+        self.migration(migration_id)
+            .await?
+            .ok_or_else(|| DatabaseError::RecordNotFound {
+                entity: "Embedding migration".to_string(),
+                id: migration_id.to_string(),
+            })
+    }
+
+    /// Stop a job after the document it is working on
+    pub async fn cancel_migration(&self, migration_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query(
+            "UPDATE embedding_migrations SET status = 'cancelled', finished_at = ?2
+             WHERE id = ?1 AND status IN ('pending', 'running')",
+        )
+        .bind(migration_id.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            DatabaseError::Service(format!("Failed to cancel embedding migration: {}", e))
+        })?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A migration job and its progress
+    pub async fn migration(
+        &self,
+        migration_id: Uuid,
+    ) -> DatabaseResult<Option<EmbeddingMigration>> {
+        let db = self.db_service.read().await;
+        let row: Option<MigrationRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM embedding_migrations WHERE id = ?1",
+            MIGRATION_COLUMNS
+        ))
+        .bind(migration_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to get embedding migration: {}", e)))?;
+        row.map(migration_from_row).transpose()
+    }
+
+    /// The unfinished job, if there is one
+    pub async fn active_migration(&self) -> DatabaseResult<Option<EmbeddingMigration>> {
+        let db = self.db_service.read().await;
+        let row: Option<MigrationRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM embedding_migrations WHERE status IN ('pending', 'running')
+             ORDER BY started_at DESC LIMIT 1",
+            MIGRATION_COLUMNS
+        ))
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to get embedding migration: {}", e)))?;
+        row.map(migration_from_row).transpose()
+    }
+
+    async fn update_migration(
+        &self,
+        migration_id: Uuid,
+        status: EmbeddingMigrationStatus,
+        error: Option<String>,
+    ) -> DatabaseResult<()> {
+        let finished_at = status.is_finished().then(|| Utc::now().to_rfc3339());
+        let db = self.db_service.read().await;
+        sqlx::query(
+            "UPDATE embedding_migrations SET status = ?2, error = ?3, finished_at = ?4 WHERE id = ?1",
+        )
+        .bind(migration_id.to_string())
+        .bind(status.as_str())
+        .bind(error)
+        .bind(finished_at)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            DatabaseError::Service(format!("Failed to update embedding migration: {}", e))
+        })?;
+        Ok(())
+    }
+
+    async fn documents_to_migrate(&self, to_model: &str) -> DatabaseResult<Vec<Uuid>> {
+        let db = self.db_service.read().await;
+        let ids: Vec<String> = sqlx::query_scalar(DOCUMENTS_TO_MIGRATE_SQL)
+            .bind(to_model)
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to list documents to migrate: {}", e))
+            })?;
+        ids.iter().map(|id| parse_uuid(id)).collect()
+    }
+
+    async fn registered_model(&self, name: &str) -> DatabaseResult<Option<EmbeddingModel>> {
+        Ok(self
+            .list_models()
+            .await?
+            .into_iter()
+            .find(|model| model.name == name))
+    }
+
+    /// Vector length of a model: as registered, else as published
+    async fn model_dimensions(&self, model: &str) -> DatabaseResult<usize> {
+        Ok(self
+            .registered_model(model)
+            .await?
+            .map(|m| m.dimensions)
+            .unwrap_or_else(|| known_dimensions(model)))
     }
 
     /// Generate embeddings for a document
@@ -102,24 +719,43 @@ impl VectorEmbeddingService {
         document_id: &Uuid,
         model_name: Option<String>,
     ) -> DatabaseResult<Vec<DocumentEmbedding>> {
-        let db_service = self.db_service.read().await;
-
         // Get document content
-        let document_content: Option<String> =
+        let document_content: Option<String> = {
+            let db_service = self.db_service.read().await;
             sqlx::query_scalar("SELECT content FROM documents WHERE id = ?1 AND is_active = 1")
                 .bind(document_id.to_string())
                 .fetch_optional(&db_service.pool)
                 .await
                 .map_err(|e| {
                     DatabaseError::Service(format!("Failed to get document content: {}", e))
-                })?;
+                })?
+        };
 
         let content = document_content.unwrap_or_default();
         if content.is_empty() {
             return Ok(vec![]);
         }
 
-        let model = model_name.unwrap_or_else(|| self.config.default_model.clone());
+        // A model named by the caller, such as a migration's target, is
+        // used as is; failing offline beats storing another model's vectors
+        let model = match model_name {
+            Some(model) => model,
+            None => self.resolve_model(None).await?,
+        };
+
+        // Replace the document's earlier vectors from this model; other
+        // models' vectors stay until a migration prunes them
+        {
+            let db_service = self.db_service.read().await;
+            sqlx::query(
+                "DELETE FROM document_embeddings WHERE document_id = ?1 AND model_name = ?2",
+            )
+            .bind(document_id.to_string())
+            .bind(&model)
+            .execute(&db_service.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to replace embeddings: {}", e)))?;
+        }
 
         // Chunk the document
         let chunks = self.chunk_document(
@@ -129,14 +765,16 @@ impl VectorEmbeddingService {
         );
 
         // Generate embeddings for each chunk
+        let texts: Vec<String> = chunks.iter().map(|chunk| chunk.text.clone()).collect();
+        let vectors = self.generate_embeddings(texts, &model).await?;
         let mut embeddings = Vec::new();
-        for (chunk_index, chunk) in chunks.iter().enumerate() {
-            let vector_data = self.generate_embedding(&chunk.text, &model).await?;
+        for ((chunk_index, chunk), vector_data) in chunks.iter().enumerate().zip(vectors) {
             let _checksum = self.calculate_content_hash(&chunk.text);
 
             let embedding = DocumentEmbedding {
                 id: Uuid::new_v4(),
                 document_id: *document_id,
+                dimensions: vector_data.len(),
                 vector_data,
                 model_name: model.clone(),
                 chunk_index,
@@ -156,26 +794,53 @@ impl VectorEmbeddingService {
         Ok(embeddings)
     }
 
+    /// Embed text with a model, or the default model when unset. Returns
+    /// the model used with the vector.
+    pub async fn embed_text(
+        &self,
+        text: &str,
+        model: Option<&str>,
+    ) -> DatabaseResult<(String, Vec<f32>)> {
+        let model = self.resolve_model(model.map(str::to_string)).await?;
+        let vector = self.generate_embedding(text, &model).await?;
+        Ok((model, vector))
+    }
+
     /// Generate a single embedding for text
-    async fn generate_embedding(&self, _text: &str, model: &str) -> DatabaseResult<Vec<f32>> {
-        // Placeholder implementation - would integrate with actual LLM API
-        // For now, return a mock embedding vector
-        let dimension = match model {
-            "text-embedding-ada-002" => 1536,
-            "text-embedding-3-small" => 1536,
-            "text-embedding-3-large" => 3072,
-            _ => 1536, // default dimension
-        };
+    async fn generate_embedding(&self, text: &str, model: &str) -> DatabaseResult<Vec<f32>> {
+        let mut vectors = self
+            .generate_embeddings(vec![text.to_string()], model)
+            .await?;
+        Ok(vectors.pop().unwrap_or_default())
+    }
 
-        // Mock implementation - in production this would call the actual API
-        let mock_embedding: Vec<f32> = (0..dimension)
-            .map(|i| {
-                let x = (i as f32) / (dimension as f32);
-                (x.sin() * x.cos()).abs() // deterministic but varied
-            })
-            .collect();
+    /// Generate embeddings for several texts with one model
+    async fn generate_embeddings(
+        &self,
+        texts: Vec<String>,
+        model: &str,
+    ) -> DatabaseResult<Vec<Vec<f32>>> {
+        if self.backend().await.local_model() == Some(model) {
+            let embedder = self.local_embedder().await?;
+            return tokio::task::spawn_blocking(move || embedder.embed(&texts))
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Local embedding failed: {}", e)))?;
+        }
+        if network::is_offline() {
+            return Err(DatabaseError::ValidationError(format!(
+                "Can't embed with {} while offline",
+                model
+            )));
+        }
 
-        Ok(mock_embedding)
+        // Placeholder implementation - would integrate with actual LLM API.
+        // Until then, hashed word and word-pair features give vectors that
+        // are at least similar for similar wording.
+        let dimension = self.model_dimensions(model).await?;
+        Ok(texts
+            .iter()
+            .map(|text| hashed_embedding(text, dimension))
+            .collect())
     }
 
     /// Store embedding in database
@@ -190,13 +855,14 @@ impl VectorEmbeddingService {
         let metadata_str = embedding.metadata.as_deref().unwrap_or("");
 
         sqlx::query(
-            "INSERT INTO document_embeddings (id, document_id, vector_data, model_name, chunk_index, text_chunk, start_char, end_char, created_at, metadata)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
+            "INSERT INTO document_embeddings (id, document_id, vector_data, model_name, dimensions, chunk_index, text_chunk, start_char, end_char, created_at, metadata)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"
         )
         .bind(embedding.id.to_string())
         .bind(embedding.document_id.to_string())
         .bind(&vector_blob)
         .bind(&embedding.model_name)
+        .bind(embedding.vector_data.len() as i64)
         .bind(embedding.chunk_index as i32)
         .bind(&embedding.text_chunk)
         .bind(embedding.start_char as i32)
@@ -245,10 +911,9 @@ impl VectorEmbeddingService {
                 })?;
 
                 Ok(Some(DocumentEmbedding {
-                    id: Uuid::parse_str(&id)
-                        .map_err(|e| DatabaseError::Service(format!("Invalid UUID: {}", e)))?,
-                    document_id: Uuid::parse_str(&document_id_str)
-                        .map_err(|e| DatabaseError::Service(format!("Invalid UUID: {}", e)))?,
+                    id: parse_uuid(&id)?,
+                    document_id: parse_uuid(&document_id_str)?,
+                    dimensions: vector_data.len(),
                     vector_data,
                     model_name,
                     chunk_index: chunk_index as usize,
@@ -294,10 +959,12 @@ impl VectorEmbeddingService {
             document_filter: None,
         });
 
-        // Generate query embedding
-        let query_embedding = self
-            .generate_embedding(query_text, &self.config.default_model)
+        // Vectors are only comparable within one model, so the query is
+        // embedded with, and matched against, a single model
+        let model = self
+            .resolve_model(search_options.model_filter.clone())
             .await?;
+        let query_embedding = self.generate_embedding(query_text, &model).await?;
 
         let db_service = self.db_service.read().await;
 
@@ -306,8 +973,10 @@ impl VectorEmbeddingService {
             "SELECT de.id, de.document_id, de.vector_data, de.model_name, de.chunk_index, de.text_chunk, de.start_char, de.end_char, d.title
              FROM document_embeddings de
              JOIN documents d ON de.document_id = d.id
-             WHERE d.is_active = 1"
+             WHERE d.is_active = 1 AND de.model_name = ?1 AND (?2 IS NULL OR de.document_id = ?2)"
         )
+        .bind(&model)
+        .bind(search_options.document_filter.map(|id| id.to_string()))
         .fetch_all(&db_service.pool)
         .await
         .map_err(|e| DatabaseError::Service(format!("Failed to get embeddings for similarity search: {}", e)))?;
@@ -337,8 +1006,7 @@ impl VectorEmbeddingService {
 
             if similarity >= search_options.similarity_threshold {
                 results.push(SearchResult {
-                    document_id: Uuid::parse_str(&document_id_str)
-                        .map_err(|e| DatabaseError::Service(format!("Invalid UUID: {}", e)))?,
+                    document_id: parse_uuid(&document_id_str)?,
                     title,
                     similarity_score: similarity,
                     snippet: text_chunk,
@@ -356,8 +1024,109 @@ impl VectorEmbeddingService {
         Ok(results)
     }
 
+    /// Find paragraphs across a project that repeat each other, such as a
+    /// description or piece of exposition written twice. Pairs come back
+    /// most similar first.
+    pub async fn find_near_duplicate_paragraphs(
+        &self,
+        project_id: Uuid,
+        options: DuplicateOptions,
+    ) -> DatabaseResult<Vec<DuplicatePair>> {
+        if !(0.0..=1.0).contains(&options.threshold) {
+            return Err(DatabaseError::ValidationError(
+                "Similarity threshold must be between 0 and 1".to_string(),
+            ));
+        }
+        let model = match options.model.clone() {
+            Some(model) => model,
+            None => self.default_model().await?,
+        };
+
+        let rows: Vec<(String, String, String, String)> = {
+            let db_service = self.db_service.read().await;
+            let has_binder: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'binder_order'",
+            )
+            .fetch_one(&db_service.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to check schema: {}", e)))?;
+            let sql = if has_binder > 0 {
+                "SELECT d.id, d.title, d.content, d.document_type
+                 FROM documents d LEFT JOIN binder_order b ON b.document_id = d.id
+                 WHERE d.project_id = ?1 AND d.is_active = 1
+                 ORDER BY b.position IS NULL, b.position, d.created_at, d.title"
+            } else {
+                "SELECT d.id, d.title, d.content, d.document_type FROM documents d
+                 WHERE d.project_id = ?1 AND d.is_active = 1
+                 ORDER BY d.created_at, d.title"
+            };
+            sqlx::query_as(sql)
+                .bind(project_id.to_string())
+                .fetch_all(&db_service.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?
+        };
+
+        // Identical paragraphs are embedded once
+        let mut vectors: HashMap<String, Vec<f32>> = HashMap::new();
+        let mut paragraphs: Vec<(ParagraphLocation, String)> = Vec::new();
+        for (position, (id, title, content, document_type)) in rows.into_iter().enumerate() {
+            let document_id = parse_uuid(&id)?;
+            for (paragraph_index, (start_char, end_char, text)) in
+                document_paragraphs(&document_type, &content)
+                    .into_iter()
+                    .enumerate()
+            {
+                if text.split_whitespace().count() < options.min_words {
+                    continue;
+                }
+                let key = text.to_lowercase();
+                if !vectors.contains_key(&key) {
+                    let vector = self.generate_embedding(&text, &model).await?;
+                    vectors.insert(key.clone(), vector);
+                }
+                paragraphs.push((
+                    ParagraphLocation {
+                        document_id,
+                        title: title.clone(),
+                        position: position + 1,
+                        paragraph_index,
+                        start_char,
+                        end_char,
+                        excerpt: excerpt(&text),
+                    },
+                    key,
+                ));
+            }
+        }
+
+        let mut pairs = Vec::new();
+        for (i, (first, first_key)) in paragraphs.iter().enumerate() {
+            for (second, second_key) in &paragraphs[i + 1..] {
+                let similarity = if first_key == second_key {
+                    1.0
+                } else {
+                    self.calculate_cosine_similarity(&vectors[first_key], &vectors[second_key])
+                };
+                if similarity >= options.threshold {
+                    pairs.push(DuplicatePair {
+                        first: first.clone(),
+                        second: second.clone(),
+                        similarity,
+                    });
+                }
+            }
+        }
+
+        pairs.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+        if let Some(limit) = options.limit {
+            pairs.truncate(limit);
+        }
+        Ok(pairs)
+    }
+
     /// Calculate cosine similarity between two vectors
-    fn calculate_cosine_similarity(&self, vec_a: &[f32], vec_b: &[f32]) -> f32 {
+    pub fn calculate_cosine_similarity(&self, vec_a: &[f32], vec_b: &[f32]) -> f32 {
         if vec_a.len() != vec_b.len() || vec_a.is_empty() {
             return 0.0;
         }
@@ -498,11 +1267,12 @@ impl VectorEmbeddingService {
         &self,
         request: &BatchEmbeddingRequest,
     ) -> DatabaseResult<Vec<Vec<DocumentEmbedding>>> {
+        let model = self.resolve_model(Some(request.model_name.clone())).await?;
         let mut all_embeddings = Vec::new();
 
         for document_id in &request.document_ids {
             let embeddings = self
-                .generate_document_embeddings(document_id, Some(request.model_name.clone()))
+                .generate_document_embeddings(document_id, Some(model.clone()))
                 .await?;
             all_embeddings.push(embeddings);
         }
@@ -517,3 +1287,385 @@ impl VectorEmbeddingService {
         format!("{:x}", hasher.finalize())
     }
 }
+
+/// Paragraphs of a stored document with their character offsets in its
+/// text: one per block of ProseMirror JSON, else separated by blank lines
+pub(crate) fn document_paragraphs(
+    document_type: &str,
+    content: &str,
+) -> Vec<(usize, usize, String)> {
+    let Some(doc) = prosemirror::parse(document_type, content) else {
+        return split_paragraphs(content);
+    };
+    let text = prosemirror::plain_text(&doc);
+    let starts = prosemirror::block_starts(&doc);
+    let mut paragraphs = Vec::new();
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(text.len());
+        let block = &text[start..end];
+        let lines: Vec<&str> = block
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+        let leading = block.len() - block.trim_start().len();
+        let start_char = text[..start + leading].chars().count();
+        let end_char = text[..start + block.trim_end().len()].chars().count();
+        paragraphs.push((start_char, end_char, lines.join(" ")));
+    }
+    paragraphs
+}
+
+/// Paragraphs separated by blank lines, with their character offsets
+pub(crate) fn split_paragraphs(content: &str) -> Vec<(usize, usize, String)> {
+    let mut paragraphs = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let (mut start, mut end, mut offset) = (0, 0, 0);
+    for line in content.split_inclusive('\n') {
+        let text = line.trim();
+        if text.is_empty() {
+            if !current.is_empty() {
+                paragraphs.push((start, end, current.join(" ")));
+                current.clear();
+            }
+        } else {
+            let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+            if current.is_empty() {
+                start = offset + indent;
+            }
+            end = offset + indent + text.chars().count();
+            current.push(text);
+        }
+        offset += line.chars().count();
+    }
+    if !current.is_empty() {
+        paragraphs.push((start, end, current.join(" ")));
+    }
+    paragraphs
+}
+
+/// The start of a paragraph, for showing in a list
+fn excerpt(text: &str) -> String {
+    const EXCERPT_CHARS: usize = 160;
+    if text.chars().count() <= EXCERPT_CHARS {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(EXCERPT_CHARS).collect();
+    let cut = cut.rsplit_once(' ').map(|(head, _)| head).unwrap_or(&cut);
+    format!("{}...", cut.trim_end())
+}
+
+/// Bag of words and word pairs hashed into `dimension` buckets, normalized
+fn hashed_embedding(text: &str, dimension: usize) -> Vec<f32> {
+    let mut vector = vec![0.0f32; dimension];
+    if dimension == 0 {
+        return vector;
+    }
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let mut add = |feature: &str, weight: f32| {
+        // FNV-1a, stable across runs unlike the std hasher
+        let hash = feature.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        vector[(hash % dimension as u64) as usize] += sign * weight;
+    };
+    for word in &words {
+        add(word, 1.0);
+    }
+    for pair in words.windows(2) {
+        add(&format!("{} {}", pair[0], pair[1]), 0.5);
+    }
+
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+/// The model to embed with given the privacy mode. Offline, only the
+/// local model may run; it takes the place of any other.
+fn offline_model(
+    model: String,
+    local_model: Option<&str>,
+    offline: bool,
+) -> DatabaseResult<String> {
+    if !offline || local_model == Some(model.as_str()) {
+        return Ok(model);
+    }
+    match local_model {
+        Some(local) => Ok(local.to_string()),
+        None => Err(DatabaseError::ValidationError(format!(
+            "Can't embed with {} while offline; set a local embedding model to index and search without a connection",
+            model
+        ))),
+    }
+}
+
+/// Published vector length of well-known models
+fn known_dimensions(model: &str) -> usize {
+    match model {
+        "text-embedding-3-large" => 3072,
+        "all-MiniLM-L6-v2" => 384,
+        "nomic-embed-text" => 768,
+        _ => 1536, // text-embedding-ada-002, text-embedding-3-small
+    }
+}
+
+fn model_from_row(
+    row: (String, i64, String, bool, String, i64, i64),
+) -> DatabaseResult<EmbeddingModel> {
+    let (name, dimensions, provider, is_default, registered_at, embeddings, documents) = row;
+    Ok(EmbeddingModel {
+        name,
+        dimensions: dimensions as usize,
+        provider,
+        is_default,
+        registered_at: parse_time(&registered_at)?,
+        embeddings: embeddings as usize,
+        documents: documents as usize,
+    })
+}
+
+fn migration_from_row(row: MigrationRow) -> DatabaseResult<EmbeddingMigration> {
+    let (
+        id,
+        from_model,
+        to_model,
+        status,
+        total,
+        processed,
+        failed,
+        prune_old,
+        error,
+        started_at,
+        finished_at,
+    ) = row;
+    Ok(EmbeddingMigration {
+        id: parse_uuid(&id)?,
+        from_model,
+        to_model,
+        status: EmbeddingMigrationStatus::parse(&status).ok_or_else(|| {
+            DatabaseError::Service(format!("Unknown migration status: {}", status))
+        })?,
+        total_documents: total as usize,
+        processed_documents: processed as usize,
+        failed_documents: failed as usize,
+        prune_old,
+        error,
+        started_at: parse_time(&started_at)?,
+        finished_at: finished_at.as_deref().map(parse_time).transpose()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_migration_switches_default_after_re_embedding() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        let document = Uuid::new_v4();
+        let now = Utc::now().to_rfc3339();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'X', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(&now)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO documents (id, project_id, title, content, checksum, created_at, updated_at)
+             VALUES (?1, ?2, 'One', 'The lighthouse keeper counted ships.', '', ?3, ?3)",
+        )
+        .bind(document.to_string())
+        .bind(project.to_string())
+        .bind(&now)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let service = VectorEmbeddingService::new(Arc::new(RwLock::new(db)));
+        service.initialize().await.unwrap();
+        let old = service.default_model().await.unwrap();
+        let stored = service
+            .generate_document_embeddings(&document, None)
+            .await
+            .unwrap();
+        assert_eq!(stored[0].dimensions, 1536);
+
+        service
+            .register_model("local-mini", 384, "local")
+            .await
+            .unwrap();
+        assert!(service.register_model(&old, 384, "openai").await.is_err());
+        let job = service.start_migration("local-mini", true).await.unwrap();
+        assert_eq!(job.total_documents, 1);
+        assert!(service.start_migration("local-mini", false).await.is_err());
+        assert_eq!(service.default_model().await.unwrap(), old);
+
+        let job = service.run_migration(job.id).await.unwrap();
+        assert_eq!(job.status, EmbeddingMigrationStatus::Completed);
+        assert_eq!(job.processed_documents, 1);
+        assert_eq!(service.default_model().await.unwrap(), "local-mini");
+
+        let models = service.list_models().await.unwrap();
+        assert_eq!(models[0].name, "local-mini");
+        assert_eq!(models[0].documents, 1);
+        assert!(models
+            .iter()
+            .all(|m| m.name == "local-mini" || m.embeddings == 0));
+
+        let options = SearchOptions {
+            limit: 5,
+            similarity_threshold: 0.0,
+            include_metadata: false,
+            model_filter: Some(old),
+            document_filter: None,
+        };
+        assert!(service
+            .find_similar_documents("ships", Some(options.clone()))
+            .await
+            .unwrap()
+            .is_empty());
+        let options = SearchOptions {
+            model_filter: None,
+            ..options
+        };
+        assert_eq!(
+            service
+                .find_similar_documents("ships", Some(options))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_near_duplicate_paragraphs_across_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        let now = Utc::now();
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'X', ?2, ?2)",
+        )
+        .bind(project.to_string())
+        .bind(now.to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let chapters = [
+            "The harbour smelled of tar and salt, and the gulls wheeled over the grey slate roofs of the old town.\n\nShe walked on.",
+            "Mara counted the ships at anchor and wrote each name in her ledger before the tide turned.\n\n  The harbour smelled of salt and tar, and gulls wheeled over the grey slate roofs of the old town.",
+        ];
+        let mut ids = Vec::new();
+        for (i, content) in chapters.iter().enumerate() {
+            let id = Uuid::new_v4();
+            let created = (now + chrono::Duration::seconds(i as i64)).to_rfc3339();
+            sqlx::query(
+                "INSERT INTO documents (id, project_id, title, content, checksum, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, '', ?5, ?5)",
+            )
+            .bind(id.to_string())
+            .bind(project.to_string())
+            .bind(format!("Chapter {}", i + 1))
+            .bind(*content)
+            .bind(&created)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+
+        let service = VectorEmbeddingService::new(Arc::new(RwLock::new(db)));
+        service.initialize().await.unwrap();
+        let pairs = service
+            .find_near_duplicate_paragraphs(project, DuplicateOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(pairs.len(), 1);
+        let pair = &pairs[0];
+        assert!(pair.similarity >= 0.9);
+        assert_eq!(pair.first.document_id, ids[0]);
+        assert_eq!(pair.first.position, 1);
+        assert_eq!(pair.second.document_id, ids[1]);
+        assert_eq!(pair.second.paragraph_index, 1);
+        let second: String = chapters[1]
+            .chars()
+            .skip(pair.second.start_char)
+            .take(pair.second.end_char - pair.second.start_char)
+            .collect();
+        assert!(second.starts_with("The harbour") && second.ends_with("old town."));
+
+        let options = DuplicateOptions {
+            min_words: 50,
+            ..DuplicateOptions::default()
+        };
+        assert!(service
+            .find_near_duplicate_paragraphs(project, options)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_document_paragraphs_follow_editor_blocks() {
+        let content = serde_json::json!({
+            "type": "doc",
+            "content": [
+                { "type": "paragraph", "content": [{ "type": "text", "text": "Gulls wheeled." }] },
+                { "type": "paragraph" },
+                { "type": "paragraph", "content": [{ "type": "text", "text": "Mara ran home." }] }
+            ]
+        })
+        .to_string();
+        let paragraphs = document_paragraphs("json", &content);
+        assert_eq!(
+            paragraphs,
+            vec![
+                (0, 14, "Gulls wheeled.".to_string()),
+                (15, 29, "Mara ran home.".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_offline_uses_local_model() {
+        let remote = "text-embedding-ada-002".to_string();
+        assert_eq!(offline_model(remote.clone(), None, false).unwrap(), remote);
+        assert_eq!(
+            offline_model(remote.clone(), Some("all-MiniLM-L6-v2"), true).unwrap(),
+            "all-MiniLM-L6-v2"
+        );
+        assert_eq!(
+            offline_model(
+                "all-MiniLM-L6-v2".to_string(),
+                Some("all-MiniLM-L6-v2"),
+                true
+            )
+            .unwrap(),
+            "all-MiniLM-L6-v2"
+        );
+        assert!(offline_model(remote, None, true).is_err());
+    }
+}
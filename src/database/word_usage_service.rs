@@ -0,0 +1,520 @@
+//! Word Usage Service
+//!
+//! Finds over-used words and phrases and clichés per chapter. Counts are
+//! cached per document and only recomputed when the document's checksum, the
+//! cliché lists or the character names change, so reports on long
+//! manuscripts stay cheap after the first run.
+
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{
+    models::word_usage::*,
+    prosemirror,
+    text_match::{find_word_matches, line_and_column},
+    DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+
+type ClicheListRow = (String, Option<String>, String, String, bool, String, String);
+
+/// Longest phrase counted, in words
+const MAX_PHRASE_WORDS: usize = 4;
+/// Number of project terms shown in the heatmap
+const HEATMAP_TERMS: usize = 12;
+/// Part of every settings fingerprint; bumped when counting itself changes
+/// so counts cached by an older build are recomputed
+const COUNTS_VERSION: u8 = 2;
+
+/// Service for word frequency and cliché analysis
+#[derive(Debug)]
+pub struct WordUsageService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl WordUsageService {
+    /// Create a new word usage service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the tables and seed the shared default cliché list
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        {
+            let db = self.db_service.read().await;
+            sqlx::query(CREATE_WORD_USAGE_TABLES_SQL)
+                .execute(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Migration(format!("Failed to create word usage tables: {}", e))
+                })?;
+        }
+
+        let shared: i64 = {
+            let db = self.db_service.read().await;
+            sqlx::query_scalar("SELECT COUNT(*) FROM cliche_lists WHERE project_id IS NULL")
+                .fetch_one(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to count cliché lists: {}", e))
+                })?
+        };
+
+        if shared == 0 {
+            self.save_cliche_list(&default_cliche_list()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Create or update a cliché list
+    pub async fn save_cliche_list(&self, list: &ClicheList) -> DatabaseResult<()> {
+        if list.name.trim().is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Cliché list name cannot be empty".to_string(),
+            ));
+        }
+
+        let phrases: Vec<String> = list
+            .phrases
+            .iter()
+            .map(|p| p.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|p| !p.is_empty())
+            .collect();
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_CLICHE_LIST_SQL)
+            .bind(list.id.to_string())
+            .bind(list.project_id.map(|id| id.to_string()))
+            .bind(&list.name)
+            .bind(to_json(&phrases)?)
+            .bind(list.enabled)
+            .bind(list.created_at.to_rfc3339())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save cliché list: {}", e)))?;
+        Ok(())
+    }
+
+    /// Delete a cliché list
+    pub async fn delete_cliche_list(&self, list_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query(DELETE_CLICHE_LIST_SQL)
+            .bind(list_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete cliché list: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get the cliché lists that apply to a project
+    pub async fn get_cliche_lists(&self, project_id: Uuid) -> DatabaseResult<Vec<ClicheList>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<ClicheListRow> = sqlx::query_as(GET_CLICHE_LISTS_FOR_PROJECT_SQL)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to get cliché lists: {}", e)))?;
+
+        rows.into_iter().map(cliche_list_from_row).collect()
+    }
+
+    /// Analyse every active document in a project, re-counting only the
+    /// documents that changed since they were last cached. `limit` caps the
+    /// top word and phrase lists.
+    pub async fn analyze_project(
+        &self,
+        project_id: Uuid,
+        limit: usize,
+    ) -> DatabaseResult<WordUsageReport> {
+        let phrases: Vec<String> = self
+            .get_cliche_lists(project_id)
+            .await?
+            .into_iter()
+            .filter(|l| l.enabled)
+            .flat_map(|l| l.phrases)
+            .collect();
+        let excluded = self.character_name_words(project_id).await?;
+        let fingerprint = settings_fingerprint(&phrases, &excluded);
+
+        let db = self.db_service.read().await;
+        sqlx::query(DELETE_STALE_WORD_USAGE_CACHE_SQL)
+            .bind(project_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to prune word counts: {}", e)))?;
+
+        let cached: Vec<(String, String, String, String)> =
+            sqlx::query_as(GET_WORD_USAGE_CACHE_SQL)
+                .bind(project_id.to_string())
+                .fetch_all(&db.pool)
+                .await
+                .map_err(|e| {
+                    DatabaseError::Service(format!("Failed to load word counts: {}", e))
+                })?;
+        let mut cached: HashMap<String, DocumentWordCounts> = cached
+            .into_iter()
+            .filter(|(_, _, settings, _)| *settings == fingerprint)
+            .filter_map(|(id, checksum, _, counts)| {
+                let counts = serde_json::from_str(&counts).ok()?;
+                Some((format!("{}:{}", id, checksum), counts))
+            })
+            .collect();
+
+        let documents: Vec<(String, String, String)> = sqlx::query_as(
+            "SELECT id, title, checksum FROM documents WHERE project_id = ?1 AND is_active = 1
+             ORDER BY title ASC",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+
+        let mut chapters = Vec::new();
+        let mut refreshed = 0;
+        for (id, title, checksum) in documents {
+            let document_id = parse_uuid(&id)?;
+            let counts = match cached.remove(&format!("{}:{}", id, checksum)) {
+                Some(counts) => counts,
+                None => {
+                    let (content, document_type): (Option<String>, String) = sqlx::query_as(
+                        "SELECT content, document_type FROM documents WHERE id = ?1",
+                    )
+                    .bind(&id)
+                    .fetch_one(&db.pool)
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::Service(format!("Failed to load document: {}", e))
+                    })?;
+                    let text = prosemirror::document_text(
+                        &document_type,
+                        content.as_deref().unwrap_or(""),
+                    );
+                    let counts = count_document(&text, &excluded, &phrases);
+                    sqlx::query(UPSERT_WORD_USAGE_CACHE_SQL)
+                        .bind(&id)
+                        .bind(project_id.to_string())
+                        .bind(&checksum)
+                        .bind(&fingerprint)
+                        .bind(to_json(&counts)?)
+                        .bind(Utc::now().to_rfc3339())
+                        .execute(&db.pool)
+                        .await
+                        .map_err(|e| {
+                            DatabaseError::Service(format!("Failed to cache word counts: {}", e))
+                        })?;
+                    refreshed += 1;
+                    counts
+                }
+            };
+            chapters.push((document_id, title, counts));
+        }
+
+        Ok(build_report(project_id, chapters, limit, refreshed))
+    }
+
+    /// Lowercased words of character names from the codex, which would
+    /// otherwise top every frequency list
+    async fn character_name_words(&self, project_id: Uuid) -> DatabaseResult<HashSet<String>> {
+        let db = self.db_service.read().await;
+        let has_codex: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'codex_entries'",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to check codex: {}", e)))?;
+        if has_codex == 0 {
+            return Ok(HashSet::new());
+        }
+
+        let names: Vec<String> = sqlx::query_scalar(
+            "SELECT title FROM codex_entries
+             WHERE project_id = ?1 AND entry_type = 'character_sheet' AND is_active = 1",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load character names: {}", e)))?;
+
+        Ok(names.iter().flat_map(|name| tokenize(name)).collect())
+    }
+}
+
+/// Count words, repeated phrases and clichés in one document
+pub fn count_document(
+    text: &str,
+    excluded: &HashSet<String>,
+    cliches: &[String],
+) -> DocumentWordCounts {
+    let mut counts = DocumentWordCounts::default();
+    let mut phrases: HashMap<String, usize> = HashMap::new();
+
+    // Phrases do not run across sentence or clause punctuation
+    for clause in text.split(['.', '!', '?', ';', ':', ',', '"', '“', '”', '(', ')', '\n']) {
+        let words = tokenize(clause);
+        counts.word_count += words.len();
+        for word in &words {
+            if !is_stop_word(word) && !excluded.contains(word) {
+                *counts.words.entry(word.clone()).or_insert(0) += 1;
+            }
+        }
+        for n in 2..=MAX_PHRASE_WORDS {
+            for window in words.windows(n) {
+                // "took a deep breath" but not "the rain" or "a deep"
+                let mentions_name = window.iter().any(|w| excluded.contains(w));
+                let loose_end = is_stop_word(&window[0]) || is_stop_word(&window[n - 1]);
+                if !mentions_name && !loose_end {
+                    *phrases.entry(window.join(" ")).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    counts.phrases = phrases.into_iter().filter(|(_, n)| *n > 1).collect();
+
+    for phrase in cliches {
+        for (start, end) in find_word_matches(text, phrase) {
+            counts.cliches.push(ClicheMatch {
+                phrase: phrase.clone(),
+                start,
+                end,
+                line: line_and_column(text, start).0,
+            });
+        }
+    }
+    counts.cliches.sort_by_key(|m| m.start);
+    counts
+}
+
+/// Combine per-document counts into project and chapter rankings and the
+/// heatmap of the project's top words
+pub fn build_report(
+    project_id: Uuid,
+    chapters: Vec<(Uuid, String, DocumentWordCounts)>,
+    limit: usize,
+    refreshed: usize,
+) -> WordUsageReport {
+    let mut words: HashMap<String, usize> = HashMap::new();
+    let mut phrases: HashMap<String, usize> = HashMap::new();
+    let mut word_count = 0;
+    for (_, _, counts) in &chapters {
+        word_count += counts.word_count;
+        for (word, n) in &counts.words {
+            *words.entry(word.clone()).or_insert(0) += n;
+        }
+        for (phrase, n) in &counts.phrases {
+            *phrases.entry(phrase.clone()).or_insert(0) += n;
+        }
+    }
+
+    let top_words = rank(&words, word_count, limit);
+    let top_phrases = rank(&drop_subphrases(&phrases), word_count, limit);
+
+    let terms: Vec<String> = top_words
+        .iter()
+        .take(HEATMAP_TERMS)
+        .map(|t| t.term.clone())
+        .collect();
+    let rows = chapters
+        .iter()
+        .map(|(document_id, title, counts)| HeatmapRow {
+            document_id: *document_id,
+            title: title.clone(),
+            per_thousand: terms
+                .iter()
+                .map(|term| {
+                    per_thousand(
+                        counts.words.get(term).copied().unwrap_or(0),
+                        counts.word_count,
+                    )
+                })
+                .collect(),
+        })
+        .collect();
+
+    let chapters = chapters
+        .into_iter()
+        .map(|(document_id, title, counts)| ChapterWordUsage {
+            document_id,
+            title,
+            word_count: counts.word_count,
+            top_words: rank(&counts.words, counts.word_count, limit),
+            top_phrases: rank(&drop_subphrases(&counts.phrases), counts.word_count, limit),
+            cliches: counts.cliches,
+        })
+        .collect();
+
+    WordUsageReport {
+        project_id,
+        analyzed_at: Utc::now(),
+        word_count,
+        top_words,
+        top_phrases,
+        chapters,
+        heatmap: WordHeatmap { terms, rows },
+        refreshed,
+    }
+}
+
+/// Most used first, ties alphabetically; single uses are never "over-used"
+fn rank(counts: &HashMap<String, usize>, word_count: usize, limit: usize) -> Vec<TermUsage> {
+    let mut ranked: Vec<(&String, &usize)> = counts.iter().filter(|(_, n)| **n > 1).collect();
+    ranked.sort_by_key(|(term, n)| (Reverse(**n), *term));
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(term, n)| TermUsage {
+            term: term.clone(),
+            count: *n,
+            per_thousand: per_thousand(*n, word_count),
+        })
+        .collect()
+}
+
+/// Drop phrases only ever used as part of a longer counted phrase, so "a
+/// deep" does not crowd the list next to "took a deep breath"
+fn drop_subphrases(phrases: &HashMap<String, usize>) -> HashMap<String, usize> {
+    phrases
+        .iter()
+        .filter(|(phrase, n)| {
+            !phrases.iter().any(|(longer, m)| {
+                m == *n && longer.len() > phrase.len() && contains_words(longer, phrase)
+            })
+        })
+        .map(|(phrase, n)| (phrase.clone(), *n))
+        .collect()
+}
+
+fn contains_words(longer: &str, phrase: &str) -> bool {
+    format!(" {} ", longer).contains(&format!(" {} ", phrase))
+}
+
+fn per_thousand(count: usize, word_count: usize) -> f32 {
+    if word_count == 0 {
+        0.0
+    } else {
+        count as f32 * 1000.0 / word_count as f32
+    }
+}
+
+/// Lowercased words, keeping inner apostrophes ("don't", "o'clock")
+pub(crate) fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '’'))
+        .map(|w| w.trim_matches(|c| c == '\'' || c == '’').replace('’', "'"))
+        .filter(|w| w.chars().any(char::is_alphabetic))
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+pub(crate) fn is_stop_word(word: &str) -> bool {
+    STOP_WORDS.contains(&word)
+}
+
+/// Changes whenever something other than the text would change the counts
+fn settings_fingerprint(cliches: &[String], excluded: &HashSet<String>) -> String {
+    let mut cliches: Vec<&String> = cliches.iter().collect();
+    cliches.sort();
+    let mut excluded: Vec<&String> = excluded.iter().collect();
+    excluded.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update([COUNTS_VERSION]);
+    for phrase in cliches {
+        hasher.update(phrase.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.update([1]);
+    for word in excluded {
+        hasher.update(word.as_bytes());
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Built-in shared cliché list, seeded once and then editable by the user
+fn default_cliche_list() -> ClicheList {
+    let phrases = [
+        "a chill ran down",
+        "all of a sudden",
+        "at the end of the day",
+        "avoid it like the plague",
+        "blood ran cold",
+        "calm before the storm",
+        "dead as a doornail",
+        "heart skipped a beat",
+        "in the nick of time",
+        "it was a dark and stormy night",
+        "let out a breath she didn't know she was holding",
+        "let out a breath he didn't know he was holding",
+        "only time will tell",
+        "quiet as a mouse",
+        "read between the lines",
+        "time stood still",
+        "without further ado",
+    ];
+    ClicheList::new(
+        None,
+        "Common Clichés".to_string(),
+        phrases.iter().map(|p| p.to_string()).collect(),
+    )
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> DatabaseResult<String> {
+    serde_json::to_string(value)
+        .map_err(|e| DatabaseError::Service(format!("Failed to serialize word usage: {}", e)))
+}
+
+fn cliche_list_from_row(row: ClicheListRow) -> DatabaseResult<ClicheList> {
+    let (id, project_id, name, phrases, enabled, created_at, updated_at) = row;
+
+    Ok(ClicheList {
+        id: parse_uuid(&id)?,
+        project_id: project_id
+            .map(|p| Uuid::parse_str(&p))
+            .transpose()
+            .map_err(|e| DatabaseError::Service(format!("Invalid UUID: {}", e)))?,
+        name,
+        phrases: serde_json::from_str(&phrases).map_err(|_| {
+            DatabaseError::Service(format!("Failed to parse cliché phrases '{}'", phrases))
+        })?,
+        enabled,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_exclude_names_and_rank_phrases() {
+        let excluded: HashSet<String> = ["mara".to_string()].into();
+        let text = "Mara took a deep breath. The rain fell.\n\
+                    She took a deep breath again; the rain fell harder.\n\
+                    All of a sudden, Mara laughed. Mara took a deep breath.";
+        let counts = count_document(text, &excluded, &["all of a sudden".to_string()]);
+
+        assert!(!counts.words.contains_key("mara"));
+        assert!(!counts.words.contains_key("the"));
+        assert_eq!(counts.words["breath"], 3);
+        assert_eq!(counts.cliches.len(), 1);
+        assert_eq!(counts.cliches[0].line, 3);
+
+        let report = build_report(Uuid::nil(), vec![(Uuid::nil(), "One".into(), counts)], 5, 1);
+        let phrases: Vec<&str> = report.top_phrases.iter().map(|t| t.term.as_str()).collect();
+        assert_eq!(phrases[0], "took a deep breath");
+        assert!(!phrases.contains(&"a deep"));
+        assert!(phrases.contains(&"rain fell"));
+        assert_eq!(report.top_words[0].term, "breath");
+        assert_eq!(
+            report.heatmap.rows[0].per_thousand.len(),
+            report.heatmap.terms.len()
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Workspace Service
+//!
+//! Saves and restores named workspaces: the open documents, the focused one,
+//! pinned references and layout hints. Switching to a workspace marks it as
+//! the project's current one and drops documents that have since been
+//! deleted, so the editor never tries to reopen something that is gone.
+
+use chrono::Utc;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::{
+    models::workspace::*,
+    parse::{parse_time, parse_uuid},
+    DatabaseError, DatabaseResult, EnhancedDatabaseService,
+};
+
+type WorkspaceRow = (
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    String,
+    String,
+    Option<String>,
+    String,
+    String,
+);
+
+/// Service for named workspaces
+#[derive(Debug)]
+pub struct WorkspaceService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+}
+
+impl WorkspaceService {
+    /// Create a new workspace service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self { db_service }
+    }
+
+    /// Initialize the workspaces table
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_WORKSPACES_TABLE_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create workspaces table: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// Save a workspace. Names are unique within a project.
+    pub async fn save_workspace(&self, workspace: &Workspace) -> DatabaseResult<Workspace> {
+        let name = workspace.name.trim();
+        if name.is_empty() {
+            return Err(DatabaseError::ValidationError(
+                "Workspace name cannot be empty".to_string(),
+            ));
+        }
+        {
+            let db = self.db_service.read().await;
+            let taken: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM workspaces WHERE project_id = ?1 AND name = ?2 AND id != ?3",
+            )
+            .bind(workspace.project_id.to_string())
+            .bind(name)
+            .bind(workspace.id.to_string())
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Service(format!("Failed to check workspace name: {}", e))
+            })?;
+            if taken > 0 {
+                return Err(DatabaseError::ValidationError(format!(
+                    "A workspace named '{}' already exists",
+                    name
+                )));
+            }
+        }
+
+        let mut saved = normalize_workspace(workspace.clone());
+        saved.name = name.to_string();
+        saved.updated_at = Utc::now();
+        self.write_workspace(&saved).await?;
+        Ok(saved)
+    }
+
+    /// Delete a workspace
+    pub async fn delete_workspace(&self, workspace_id: Uuid) -> DatabaseResult<bool> {
+        let db = self.db_service.read().await;
+        let result = sqlx::query("DELETE FROM workspaces WHERE id = ?1")
+            .bind(workspace_id.to_string())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to delete workspace: {}", e)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A project's workspaces, most recently used first
+    pub async fn list_workspaces(&self, project_id: Uuid) -> DatabaseResult<Vec<Workspace>> {
+        let db = self.db_service.read().await;
+        let rows: Vec<WorkspaceRow> = sqlx::query_as(&format!(
+            "{} WHERE project_id = ?1 ORDER BY last_used_at IS NULL, last_used_at DESC, name COLLATE NOCASE",
+            SELECT_WORKSPACES_SQL
+        ))
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to list workspaces: {}", e)))?;
+        rows.into_iter().map(workspace_from_row).collect()
+    }
+
+    /// A workspace by id
+    pub async fn workspace(&self, workspace_id: Uuid) -> DatabaseResult<Workspace> {
+        let db = self.db_service.read().await;
+        let row: Option<WorkspaceRow> =
+            sqlx::query_as(&format!("{} WHERE id = ?1", SELECT_WORKSPACES_SQL))
+                .bind(workspace_id.to_string())
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to load workspace: {}", e)))?;
+        match row {
+            Some(row) => workspace_from_row(row),
+            None => Err(DatabaseError::RecordNotFound {
+                entity: "workspace".to_string(),
+                id: workspace_id.to_string(),
+            }),
+        }
+    }
+
+    /// The workspace last switched to in a project, if any
+    pub async fn active_workspace(&self, project_id: Uuid) -> DatabaseResult<Option<Workspace>> {
+        let db = self.db_service.read().await;
+        let row: Option<WorkspaceRow> = sqlx::query_as(&format!(
+            "{} WHERE project_id = ?1 AND last_used_at IS NOT NULL ORDER BY last_used_at DESC LIMIT 1",
+            SELECT_WORKSPACES_SQL
+        ))
+        .bind(project_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load active workspace: {}", e)))?;
+        row.map(workspace_from_row).transpose()
+    }
+
+    /// Make a workspace the project's current one and return it ready to
+    /// restore, without documents deleted since it was saved
+    pub async fn switch_workspace(&self, workspace_id: Uuid) -> DatabaseResult<Workspace> {
+        let workspace = self.workspace(workspace_id).await?;
+        let existing = self.document_ids(workspace.project_id).await?;
+        let mut switched = prune_missing_documents(workspace, &existing);
+        switched.last_used_at = Some(Utc::now());
+        self.write_workspace(&switched).await?;
+        Ok(switched)
+    }
+
+    /// Pin a reference to a workspace; pinning it again keeps one copy
+    pub async fn pin_reference(
+        &self,
+        workspace_id: Uuid,
+        pin: PinnedReference,
+    ) -> DatabaseResult<Workspace> {
+        let mut workspace = self.workspace(workspace_id).await?;
+        workspace.pinned.push(pin);
+        self.save_workspace(&workspace).await
+    }
+
+    /// Remove a pinned reference from a workspace
+    pub async fn unpin_reference(
+        &self,
+        workspace_id: Uuid,
+        kind: PinKind,
+        target: &str,
+    ) -> DatabaseResult<Workspace> {
+        let mut workspace = self.workspace(workspace_id).await?;
+        workspace
+            .pinned
+            .retain(|pin| !(pin.kind == kind && pin.target == target));
+        self.save_workspace(&workspace).await
+    }
+
+    async fn write_workspace(&self, workspace: &Workspace) -> DatabaseResult<()> {
+        let encode = |value: serde_json::Result<String>| {
+            value.map_err(|e| DatabaseError::Service(format!("Failed to encode workspace: {}", e)))
+        };
+        let open_documents = encode(serde_json::to_string(&workspace.open_documents))?;
+        let pinned = encode(serde_json::to_string(&workspace.pinned))?;
+        let layout = encode(serde_json::to_string(&workspace.layout))?;
+
+        let db = self.db_service.read().await;
+        sqlx::query(UPSERT_WORKSPACE_SQL)
+            .bind(workspace.id.to_string())
+            .bind(workspace.project_id.to_string())
+            .bind(&workspace.name)
+            .bind(open_documents)
+            .bind(workspace.active_document.map(|id| id.to_string()))
+            .bind(pinned)
+            .bind(layout)
+            .bind(workspace.last_used_at.map(|t| t.to_rfc3339()))
+            .bind(workspace.created_at.to_rfc3339())
+            .bind(workspace.updated_at.to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to save workspace: {}", e)))?;
+        Ok(())
+    }
+
+    /// Ids of the project's active documents
+    async fn document_ids(&self, project_id: Uuid) -> DatabaseResult<HashSet<Uuid>> {
+        let db = self.db_service.read().await;
+        let ids: Vec<String> =
+            sqlx::query_scalar("SELECT id FROM documents WHERE project_id = ?1 AND is_active = 1")
+                .bind(project_id.to_string())
+                .fetch_all(&db.pool)
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Failed to list documents: {}", e)))?;
+        Ok(ids
+            .iter()
+            .filter_map(|id| Uuid::parse_str(id).ok())
+            .collect())
+    }
+}
+
+/// Drop repeated tabs and pins, and keep the focused document among the
+/// open ones
+pub fn normalize_workspace(mut workspace: Workspace) -> Workspace {
+    let mut seen = HashSet::new();
+    workspace.open_documents.retain(|id| seen.insert(*id));
+    let mut seen = HashSet::new();
+    workspace
+        .pinned
+        .retain(|pin| seen.insert((pin.kind, pin.target.clone())));
+    if workspace
+        .active_document
+        .is_some_and(|id| !workspace.open_documents.contains(&id))
+    {
+        workspace.active_document = None;
+    }
+    if workspace.active_document.is_none() {
+        workspace.active_document = workspace.open_documents.first().copied();
+    }
+    workspace
+}
+
+/// A workspace without tabs or document pins for documents not in
+/// `existing`
+pub fn prune_missing_documents(mut workspace: Workspace, existing: &HashSet<Uuid>) -> Workspace {
+    workspace.open_documents.retain(|id| existing.contains(id));
+    workspace.pinned.retain(|pin| {
+        pin.kind != PinKind::Document
+            || Uuid::parse_str(&pin.target).is_ok_and(|id| existing.contains(&id))
+    });
+    normalize_workspace(workspace)
+}
+
+fn workspace_from_row(row: WorkspaceRow) -> DatabaseResult<Workspace> {
+    let (
+        id,
+        project_id,
+        name,
+        open_documents,
+        active_document,
+        pinned,
+        layout,
+        last_used_at,
+        created_at,
+        updated_at,
+    ) = row;
+    Ok(Workspace {
+        id: parse_uuid(&id)?,
+        project_id: parse_uuid(&project_id)?,
+        name,
+        open_documents: serde_json::from_str(&open_documents).unwrap_or_default(),
+        active_document: active_document.as_deref().map(parse_uuid).transpose()?,
+        pinned: serde_json::from_str(&pinned).unwrap_or_default(),
+        layout: serde_json::from_str(&layout).unwrap_or_default(),
+        last_used_at: last_used_at.as_deref().map(parse_time).transpose()?,
+        created_at: parse_time(&created_at)?,
+        updated_at: parse_time(&updated_at)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switching_drops_deleted_documents() {
+        let (kept, deleted, other) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let pin = |kind, target: String| PinnedReference {
+            kind,
+            target,
+            label: "ref".to_string(),
+        };
+        let mut workspace = Workspace::new(Uuid::new_v4(), "Drafting chapter 12");
+        workspace.open_documents = vec![deleted, kept, deleted, other];
+        workspace.active_document = Some(deleted);
+        workspace.pinned = vec![
+            pin(PinKind::Document, deleted.to_string()),
+            pin(PinKind::Document, kept.to_string()),
+            pin(PinKind::Codex, "mara-vell".to_string()),
+            pin(PinKind::Codex, "mara-vell".to_string()),
+            pin(PinKind::Url, "https://example.com/ships".to_string()),
+        ];
+
+        let normalized = normalize_workspace(workspace.clone());
+        assert_eq!(normalized.open_documents, vec![deleted, kept, other]);
+        assert_eq!(normalized.active_document, Some(deleted));
+        assert_eq!(normalized.pinned.len(), 4);
+
+        let existing: HashSet<Uuid> = [kept, other].into_iter().collect();
+        let restored = prune_missing_documents(workspace, &existing);
+        assert_eq!(restored.open_documents, vec![kept, other]);
+        assert_eq!(restored.active_document, Some(kept));
+        assert_eq!(
+            restored
+                .pinned
+                .iter()
+                .map(|p| p.target.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                kept.to_string().as_str(),
+                "mara-vell",
+                "https://example.com/ships"
+            ]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Deep Links
+//!
+//! `herdingcats://` URLs that route back into the running app from outside
+//! the webview: notification buttons, jump lists and the dock menu, and
+//! links passed on the command line to a second instance. Each URL maps
+//! to a `DeepLink`, which the event loop turns into a window action.
+//!
+//! - `herdingcats://project/<id>`
+//! - `herdingcats://document/<id>`
+//! - `herdingcats://tool/<id>`
+//! - `herdingcats://file?path=<path>`
+//! - `herdingcats://project-file?path=<path>`
+//! - `herdingcats://log`
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use url::Url;
+
+pub const SCHEME: &str = "herdingcats";
+
+/// Receives deep links from outside the webview and routes them into the app
+pub type DeepLinkHandler = std::sync::Arc<dyn Fn(DeepLink) + Send + Sync>;
+
+/// An in-app destination addressed by a `herdingcats://` URL
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeepLink {
+    OpenProject {
+        project_id: String,
+    },
+    OpenDocument {
+        document_id: String,
+    },
+    OpenTool {
+        tool_id: String,
+    },
+    /// Reveal a file the app produced, e.g. an export
+    OpenFile {
+        path: PathBuf,
+    },
+    /// Open the project named by a `.hcats` project file
+    OpenProjectFile {
+        path: PathBuf,
+    },
+    ShowLog,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum DeepLinkError {
+    #[error("Invalid deep link URL: {0}")]
+    InvalidUrl(String),
+    #[error("Unsupported scheme '{0}', expected {SCHEME}://")]
+    WrongScheme(String),
+    #[error("Unknown deep link target: {0}")]
+    UnknownTarget(String),
+}
+
+impl DeepLink {
+    pub fn parse(input: &str) -> Result<Self, DeepLinkError> {
+        let url = Url::parse(input).map_err(|e| DeepLinkError::InvalidUrl(e.to_string()))?;
+        if url.scheme() != SCHEME {
+            return Err(DeepLinkError::WrongScheme(url.scheme().to_string()));
+        }
+
+        let id = || {
+            url.path()
+                .trim_matches('/')
+                .split('/')
+                .next()
+                .filter(|id| !id.is_empty() && id.chars().all(is_id_char))
+                .map(str::to_string)
+                .ok_or_else(|| DeepLinkError::UnknownTarget(input.to_string()))
+        };
+        let path = || {
+            url.query_pairs()
+                .find(|(key, _)| key == "path")
+                .map(|(_, path)| PathBuf::from(path.into_owned()))
+                .ok_or_else(|| DeepLinkError::UnknownTarget(input.to_string()))
+        };
+        match url.host_str().unwrap_or_default() {
+            "project" => Ok(DeepLink::OpenProject { project_id: id()? }),
+            "document" => Ok(DeepLink::OpenDocument { document_id: id()? }),
+            "tool" => Ok(DeepLink::OpenTool { tool_id: id()? }),
+            "file" => Ok(DeepLink::OpenFile { path: path()? }),
+            "project-file" => Ok(DeepLink::OpenProjectFile { path: path()? }),
+            "log" => Ok(DeepLink::ShowLog),
+            _ => Err(DeepLinkError::UnknownTarget(input.to_string())),
+        }
+    }
+
+    pub fn to_url(&self) -> String {
+        match self {
+            DeepLink::OpenProject { project_id } => format!("{}://project/{}", SCHEME, project_id),
+            DeepLink::OpenDocument { document_id } => {
+                format!("{}://document/{}", SCHEME, document_id)
+            }
+            DeepLink::OpenTool { tool_id } => format!("{}://tool/{}", SCHEME, tool_id),
+            DeepLink::OpenFile { path } => path_url("file", path),
+            DeepLink::OpenProjectFile { path } => path_url("project-file", path),
+            DeepLink::ShowLog => format!("{}://log", SCHEME),
+        }
+    }
+}
+
+fn path_url(target: &str, path: &Path) -> String {
+    let mut url = Url::parse(&format!("{}://{}", SCHEME, target)).expect("static URL");
+    url.query_pairs_mut()
+        .append_pair("path", &path.to_string_lossy());
+    url.to_string()
+}
+
+/// Open a file with the platform's default application
+pub fn open_path(path: &Path) -> std::io::Result<()> {
+    let program = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(program).arg(path).spawn()?;
+    Ok(())
+}
+
+/// Document and tool ids are UUIDs and slugs; anything else is refused
+fn is_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deep_links_round_trip() {
+        for link in [
+            DeepLink::OpenProject {
+                project_id: "9b1d".to_string(),
+            },
+            DeepLink::OpenDocument {
+                document_id: "3f2a-1c".to_string(),
+            },
+            DeepLink::OpenTool {
+                tool_id: "codex".to_string(),
+            },
+            DeepLink::OpenFile {
+                path: PathBuf::from("/home/me/Exports/Book One.pdf"),
+            },
+            DeepLink::OpenProjectFile {
+                path: PathBuf::from("C:\\Users\\me\\Novels\\Book One.hcats"),
+            },
+            DeepLink::ShowLog,
+        ] {
+            assert_eq!(DeepLink::parse(&link.to_url()), Ok(link));
+        }
+    }
+
+    #[test]
+    fn test_rejects_foreign_and_unknown_links() {
+        assert!(matches!(
+            DeepLink::parse("https://example.com/document/1"),
+            Err(DeepLinkError::WrongScheme(_))
+        ));
+        assert!(matches!(
+            DeepLink::parse("herdingcats://format-disk"),
+            Err(DeepLinkError::UnknownTarget(_))
+        ));
+        assert!(matches!(
+            DeepLink::parse("herdingcats://document/..%2Fsecrets"),
+            Err(DeepLinkError::UnknownTarget(_))
+        ));
+        assert!(matches!(
+            DeepLink::parse("herdingcats://document/"),
+            Err(DeepLinkError::UnknownTarget(_))
+        ));
+    }
+}
@@ -1,6 +1,11 @@
 //! Comprehensive Error Handling System
 //!
 //! Provides structured error types and handling for the entire Herding Cats Rust application.
+//!
+//! Every error type maps onto an [`ErrorCode`] through [`UserFacingError`],
+//! which also separates what the user is told from the internal details.
+//! Errors cross IPC as an [`ErrorEnvelope`]. `WritingToolError` is the
+//! top of the hierarchy and converts from each of the others.
 
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -10,9 +15,6 @@ use uuid::Uuid;
 /// Core database error types
 #[derive(Error, Debug, Clone, Serialize, Deserialize)]
 pub enum DatabaseError {
-    #[error("Database connection failed: {message}")]
-    ConnectionFailed { message: String },
-
     #[error("Database operation timed out after {timeout:?}")]
     Timeout { timeout: Duration },
 
@@ -22,27 +24,18 @@ pub enum DatabaseError {
     #[error("Database constraint violation: {constraint}, value: {value}")]
     ConstraintViolation { constraint: String, value: String },
 
-    #[error("Database record not found: {entity} with id {id}")]
-    NotFound { entity: String, id: String },
+    #[error("Not found: {entity} {id}")]
+    RecordNotFound { entity: String, id: String },
 
     #[error("Database record already exists: {entity} with id {id}")]
     AlreadyExists { entity: String, id: String },
 
-    #[error("Database schema migration failed: {message}")]
-    MigrationFailed { message: String },
-
     #[error("Database transaction failed: {message}")]
     TransactionFailed { message: String },
 
     #[error("Database pool exhausted: {message}")]
     PoolExhausted { message: String },
 
-    #[error("Database configuration error: {message}")]
-    ConfigurationError { message: String },
-
-    #[error("Database integrity check failed: {message}")]
-    IntegrityError { message: String },
-
     #[error("Database permission denied: {operation} on {resource}")]
     PermissionDenied { operation: String, resource: String },
 
@@ -70,8 +63,7 @@ pub enum DatabaseError {
     #[error("Unknown database error: {message}")]
     Unknown { message: String },
 
-    // Additional variants needed by database modules
-    #[error("Connection error: {0}")]
+    #[error("Connection failed: {0}")]
     Connection(String),
 
     #[error("Configuration error: {0}")]
@@ -88,6 +80,21 @@ pub enum DatabaseError {
 
     #[error("Validation error: {0}")]
     ValidationError(String),
+
+    #[error("Not implemented: {0}")]
+    NotImplemented(String),
+
+    #[error("Encryption key missing: {0}")]
+    KeyMissing(String),
+
+    #[error("Insufficient disk space: {0}")]
+    InsufficientSpace(String),
+}
+
+impl From<sqlx::Error> for DatabaseError {
+    fn from(error: sqlx::Error) -> Self {
+        DatabaseError::Service(format!("SQLx error: {}", error))
+    }
 }
 
 /// Core application error types
@@ -174,6 +181,9 @@ pub enum AppError {
 
     #[error("Network error: {0}")]
     Network(String),
+
+    #[error("Insufficient disk space: {0}")]
+    InsufficientSpace(String),
 }
 
 impl From<std::io::Error> for AppError {
@@ -550,6 +560,319 @@ pub enum WritingToolError {
     FileSystemError(String),
 }
 
+impl From<std::io::Error> for WritingToolError {
+    fn from(error: std::io::Error) -> Self {
+        WritingToolError::FileSystemError(error.to_string())
+    }
+}
+
+/// Stable, machine-readable reason an operation failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidInput,
+    NotFound,
+    Conflict,
+    PermissionDenied,
+    RateLimited,
+    KeyMissing,
+    NotImplemented,
+    Busy,
+    Timeout,
+    Unavailable,
+    Configuration,
+    Storage,
+    DiskFull,
+    Corrupted,
+    Internal,
+}
+
+impl ErrorCode {
+    /// Whether errors with this code describe the user's own request, so
+    /// their text can be shown as is. Other errors are shown with the
+    /// code's generic message, and their text kept for logs.
+    pub fn is_user_facing(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::InvalidInput
+                | ErrorCode::NotFound
+                | ErrorCode::Conflict
+                | ErrorCode::PermissionDenied
+                | ErrorCode::RateLimited
+                | ErrorCode::KeyMissing
+                | ErrorCode::NotImplemented
+                | ErrorCode::DiskFull
+        )
+    }
+
+    /// Message for the user when the error's own text isn't shown
+    pub fn generic_message(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidInput => "The request wasn't valid.",
+            ErrorCode::NotFound => "That item couldn't be found.",
+            ErrorCode::Conflict => "That conflicts with an existing item.",
+            ErrorCode::PermissionDenied => "You don't have permission to do that.",
+            ErrorCode::RateLimited => "Too many requests. Try again shortly.",
+            ErrorCode::KeyMissing => "The encryption key for this data isn't available.",
+            ErrorCode::NotImplemented => "That isn't supported yet.",
+            ErrorCode::Busy => "The database is busy. Try again in a moment.",
+            ErrorCode::Timeout => "The operation took too long and was stopped.",
+            ErrorCode::Unavailable => "That service isn't available right now.",
+            ErrorCode::Configuration => "The app isn't configured correctly.",
+            ErrorCode::Storage => "Reading or writing files failed.",
+            ErrorCode::DiskFull => "There isn't enough free disk space.",
+            ErrorCode::Corrupted => "Stored data failed an integrity check.",
+            ErrorCode::Internal => "Something went wrong.",
+        }
+    }
+}
+
+/// An error with a code and a message fit for the user
+pub trait UserFacingError: std::error::Error {
+    fn code(&self) -> ErrorCode;
+
+    /// The error's text when its code is user-facing, the code's generic
+    /// message otherwise
+    fn user_message(&self) -> String {
+        let code = self.code();
+        if code.is_user_facing() {
+            self.to_string()
+        } else {
+            code.generic_message().to_string()
+        }
+    }
+}
+
+impl UserFacingError for DatabaseError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            DatabaseError::ValidationError(_) => ErrorCode::InvalidInput,
+            DatabaseError::RecordNotFound { .. } => ErrorCode::NotFound,
+            DatabaseError::AlreadyExists { .. } | DatabaseError::ConstraintViolation { .. } => {
+                ErrorCode::Conflict
+            }
+            DatabaseError::PermissionDenied { .. } => ErrorCode::PermissionDenied,
+            DatabaseError::KeyMissing(_) => ErrorCode::KeyMissing,
+            DatabaseError::NotImplemented(_) => ErrorCode::NotImplemented,
+            DatabaseError::Deadlock { .. } | DatabaseError::PoolExhausted { .. } => ErrorCode::Busy,
+            DatabaseError::Timeout { .. } => ErrorCode::Timeout,
+            DatabaseError::Connection(_) | DatabaseError::HealthCheckFailed { .. } => {
+                ErrorCode::Unavailable
+            }
+            DatabaseError::Configuration(_) | DatabaseError::VersionMismatch { .. } => {
+                ErrorCode::Configuration
+            }
+            DatabaseError::BackupRestoreFailed { .. } => ErrorCode::Storage,
+            DatabaseError::InsufficientSpace(_) => ErrorCode::DiskFull,
+            DatabaseError::IntegrityCheck(_) => ErrorCode::Corrupted,
+            DatabaseError::QueryFailed { .. }
+            | DatabaseError::Migration(_)
+            | DatabaseError::TransactionFailed { .. }
+            | DatabaseError::SerializationError { .. }
+            | DatabaseError::DeserializationError { .. }
+            | DatabaseError::InvalidState { .. }
+            | DatabaseError::Service(_)
+            | DatabaseError::Unknown { .. } => ErrorCode::Internal,
+        }
+    }
+}
+
+impl UserFacingError for AppError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            AppError::ToolNotFound { .. } => ErrorCode::NotFound,
+            AppError::ToolDataValidationFailed { .. } => ErrorCode::InvalidInput,
+            AppError::ToolPermissionError { .. } => ErrorCode::PermissionDenied,
+            AppError::ToolResourceLimitExceeded { .. } => ErrorCode::RateLimited,
+            AppError::ToolTimeout { .. } => ErrorCode::Timeout,
+            AppError::Network(_) => ErrorCode::Unavailable,
+            AppError::ToolConfigurationError { .. }
+            | AppError::ToolDependencyMissing { .. }
+            | AppError::ToolVersionIncompatibility { .. } => ErrorCode::Configuration,
+            AppError::Io(_) => ErrorCode::Storage,
+            AppError::InsufficientSpace(_) => ErrorCode::DiskFull,
+            AppError::ToolStateCorruption { .. } => ErrorCode::Corrupted,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+impl UserFacingError for ThreadingError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ThreadingError::ThreadPoolExhausted { .. }
+            | ThreadingError::Deadlock { .. }
+            | ThreadingError::SemaphoreAcquireFailed { .. } => ErrorCode::Busy,
+            ThreadingError::AsyncTimeout { .. } => ErrorCode::Timeout,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+impl UserFacingError for ApiError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ApiError::EndpointNotFound { .. } => ErrorCode::NotFound,
+            ApiError::MethodNotAllowed { .. }
+            | ApiError::ValidationFailed { .. }
+            | ApiError::PayloadTooLarge { .. }
+            | ApiError::ContentTypeNotSupported { .. }
+            | ApiError::VersionNotSupported { .. }
+            | ApiError::ClientError { .. } => ErrorCode::InvalidInput,
+            ApiError::AuthenticationFailed { .. } | ApiError::AuthorizationFailed { .. } => {
+                ErrorCode::PermissionDenied
+            }
+            ApiError::RateLimitExceeded { .. } => ErrorCode::RateLimited,
+            ApiError::RequestTimeout { .. } => ErrorCode::Timeout,
+            ApiError::ServerError { .. } | ApiError::NetworkError { .. } => ErrorCode::Unavailable,
+            ApiError::ConfigurationError { .. } => ErrorCode::Configuration,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+impl UserFacingError for PerformanceError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::Internal
+    }
+}
+
+impl UserFacingError for MigrationError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            MigrationError::ConfigurationError { .. }
+            | MigrationError::CompatibilityCheckFailed { .. } => ErrorCode::Configuration,
+            MigrationError::StateCorruption { .. } => ErrorCode::Corrupted,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+impl UserFacingError for WritingToolError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            WritingToolError::Database(e) => e.code(),
+            WritingToolError::App(e) => e.code(),
+            WritingToolError::Threading(e) => e.code(),
+            WritingToolError::Api(e) => e.code(),
+            WritingToolError::Performance(e) => e.code(),
+            WritingToolError::Migration(e) => e.code(),
+            WritingToolError::InvalidSessionId
+            | WritingToolError::InvalidAudioFormat
+            | WritingToolError::InvalidScript => ErrorCode::InvalidInput,
+            WritingToolError::ContextMatcherNotFound(_)
+            | WritingToolError::CommandHandlerNotFound(_)
+            | WritingToolError::NoActiveSession
+            | WritingToolError::DeviceNotFound(_)
+            | WritingToolError::ScriptNotFound(_)
+            | WritingToolError::WorkflowNotFound(_)
+            | WritingToolError::MacroNotFound(_) => ErrorCode::NotFound,
+            WritingToolError::SessionAlreadyActive => ErrorCode::Conflict,
+            WritingToolError::AuthenticationFailed
+            | WritingToolError::AccessDenied
+            | WritingToolError::PermissionDenied
+            | WritingToolError::SecurityError(_) => ErrorCode::PermissionDenied,
+            WritingToolError::PlatformNotSupported(_) => ErrorCode::NotImplemented,
+            WritingToolError::ExecutionTimeout => ErrorCode::Timeout,
+            WritingToolError::VoiceRecognitionDisabled
+            | WritingToolError::SpeechSynthesisDisabled
+            | WritingToolError::RecognitionEngineUnavailable
+            | WritingToolError::SynthesisEngineUnavailable
+            | WritingToolError::NetworkError(_) => ErrorCode::Unavailable,
+            WritingToolError::StorageError(_) | WritingToolError::FileSystemError(_) => {
+                ErrorCode::Storage
+            }
+            WritingToolError::SyncError(_)
+            | WritingToolError::CommandError(_)
+            | WritingToolError::AudioProcessingError
+            | WritingToolError::SystemError(_) => ErrorCode::Internal,
+        }
+    }
+
+    fn user_message(&self) -> String {
+        match self {
+            WritingToolError::Database(e) => e.user_message(),
+            WritingToolError::App(e) => e.user_message(),
+            WritingToolError::Threading(e) => e.user_message(),
+            WritingToolError::Api(e) => e.user_message(),
+            WritingToolError::Performance(e) => e.user_message(),
+            WritingToolError::Migration(e) => e.user_message(),
+            _ if self.code().is_user_facing() => self.to_string(),
+            _ => self.code().generic_message().to_string(),
+        }
+    }
+}
+
+/// An error as sent to the frontend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorEnvelope {
+    pub code: ErrorCode,
+    /// Safe to show the user
+    pub message: String,
+    /// The full error text when it differs from `message`, for logs; sent
+    /// to the frontend by debug builds only
+    #[serde(default, skip_serializing_if = "omit_details")]
+    pub details: Option<String>,
+}
+
+fn omit_details(details: &Option<String>) -> bool {
+    details.is_none() || !cfg!(debug_assertions)
+}
+
+impl ErrorEnvelope {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+}
+
+impl<E: UserFacingError> From<E> for ErrorEnvelope {
+    fn from(error: E) -> Self {
+        let message = error.user_message();
+        let details = error.to_string();
+        Self {
+            code: error.code(),
+            details: (details != message).then_some(details),
+            message,
+        }
+    }
+}
+
+impl From<anyhow::Error> for ErrorEnvelope {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            details: Some(format!("{:#}", error)),
+            ..Self::new(ErrorCode::Internal, ErrorCode::Internal.generic_message())
+        }
+    }
+}
+
+impl From<tokio::task::JoinError> for ErrorEnvelope {
+    fn from(error: tokio::task::JoinError) -> Self {
+        Self {
+            details: Some(error.to_string()),
+            ..Self::new(ErrorCode::Internal, ErrorCode::Internal.generic_message())
+        }
+    }
+}
+
+/// Handlers that fail with a plain message have already written it for
+/// the user
+impl From<String> for ErrorEnvelope {
+    fn from(message: String) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+}
+
+impl From<&str> for ErrorEnvelope {
+    fn from(message: &str) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+}
+
 /// Error severity levels
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ErrorSeverity {
@@ -758,9 +1081,7 @@ mod tests {
 
     #[test]
     fn test_database_error_creation() {
-        let error = DatabaseError::ConnectionFailed {
-            message: "Connection timeout".to_string(),
-        };
+        let error = DatabaseError::Connection("Connection timeout".to_string());
         assert!(error.to_string().contains("Connection failed"));
     }
 
@@ -783,9 +1104,7 @@ mod tests {
 
     #[test]
     fn test_error_info_creation() {
-        let error = DatabaseError::ConnectionFailed {
-            message: "Test error".to_string(),
-        };
+        let error = DatabaseError::Connection("Test error".to_string());
 
         let error_info = ErrorInfo::new(
             &error,
@@ -833,9 +1152,7 @@ mod tests {
     #[test]
     fn test_default_error_handler() {
         let handler = DefaultErrorHandler;
-        let error = DatabaseError::ConnectionFailed {
-            message: "Connection timeout".to_string(),
-        };
+        let error = DatabaseError::Connection("Connection timeout".to_string());
 
         let error_info = handler.handle_error(&error, "test_source");
         assert_eq!(error_info.source, "test_source");
@@ -848,12 +1165,33 @@ mod tests {
         assert!(handler.should_alert(&error_info));
     }
 
+    #[test]
+    fn test_error_envelope_hides_internal_details() {
+        let not_found = ErrorEnvelope::from(DatabaseError::RecordNotFound {
+            entity: "document".to_string(),
+            id: "42".to_string(),
+        });
+        assert_eq!(not_found.code, ErrorCode::NotFound);
+        assert_eq!(not_found.message, "Not found: document 42");
+        assert_eq!(not_found.details, None);
+
+        let internal = ErrorEnvelope::from(WritingToolError::from(DatabaseError::Service(
+            "SQLx error: no such table: documents".to_string(),
+        )));
+        assert_eq!(internal.code, ErrorCode::Internal);
+        assert_eq!(internal.message, ErrorCode::Internal.generic_message());
+        assert!(internal.details.unwrap().contains("no such table"));
+
+        let json = serde_json::to_value(ErrorEnvelope::new(ErrorCode::Busy, "busy")).unwrap();
+        assert_eq!(json, serde_json::json!({"code": "busy", "message": "busy"}));
+    }
+
     #[test]
     fn test_error_result_types() {
         let db_result: DatabaseResult<String> = Ok("success".to_string());
         assert!(db_result.is_ok());
 
-        let db_error: DatabaseResult<String> = Err(DatabaseError::NotFound {
+        let db_error: DatabaseResult<String> = Err(DatabaseError::RecordNotFound {
             entity: "User".to_string(),
             id: "123".to_string(),
         });
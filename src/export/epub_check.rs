@@ -0,0 +1,599 @@
+//! ePub Structural Validation
+//!
+//! The packaging checks EPUBCheck runs first, done on the finished archive:
+//! the mimetype entry, the container pointing at the package document, the
+//! manifest against the archive's files, the spine against the manifest and
+//! the metadata each ePub version requires. Content documents themselves
+//! are not schema-checked.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use zip::{CompressionMethod, ZipArchive};
+
+use super::{EpubVersion, ValidationSeverity};
+
+const EPUB_MIMETYPE: &str = "application/epub+zip";
+const CONTAINER_PATH: &str = "META-INF/container.xml";
+const OPF_MEDIA_TYPE: &str = "application/oebps-package+xml";
+const NCX_MEDIA_TYPE: &str = "application/x-dtbncx+xml";
+
+/// One problem found in an ePub
+#[derive(Debug, Clone)]
+pub struct EpubCheckIssue {
+    pub severity: ValidationSeverity,
+    /// Stable identifier of the check, e.g. "mimetype-first"
+    pub code: &'static str,
+    pub message: String,
+    /// Archive entry the issue is in, when there is one
+    pub location: Option<String>,
+}
+
+/// Everything found in one ePub
+#[derive(Debug, Clone, Default)]
+pub struct EpubCheckReport {
+    pub issues: Vec<EpubCheckIssue>,
+}
+
+impl EpubCheckReport {
+    /// No errors; warnings and notes are allowed
+    pub fn is_valid(&self) -> bool {
+        !self
+            .issues
+            .iter()
+            .any(|issue| issue.severity == ValidationSeverity::Error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &EpubCheckIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == ValidationSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &EpubCheckIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == ValidationSeverity::Warning)
+    }
+
+    /// The errors on one line each, for an error message
+    pub fn error_summary(&self) -> String {
+        self.errors()
+            .map(|issue| match &issue.location {
+                Some(location) => format!("[{}] {}: {}", issue.code, location, issue.message),
+                None => format!("[{}] {}", issue.code, issue.message),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn push(
+        &mut self,
+        severity: ValidationSeverity,
+        code: &'static str,
+        message: impl Into<String>,
+        location: Option<&str>,
+    ) {
+        self.issues.push(EpubCheckIssue {
+            severity,
+            code,
+            message: message.into(),
+            location: location.map(str::to_string),
+        });
+    }
+
+    fn error(&mut self, code: &'static str, message: impl Into<String>, location: Option<&str>) {
+        self.push(ValidationSeverity::Error, code, message, location);
+    }
+
+    fn warning(&mut self, code: &'static str, message: impl Into<String>, location: Option<&str>) {
+        self.push(ValidationSeverity::Warning, code, message, location);
+    }
+}
+
+/// Check the ePub at `path`
+pub fn check_epub_file(path: &Path, version: EpubVersion) -> std::io::Result<EpubCheckReport> {
+    Ok(check_epub(fs::File::open(path)?, version))
+}
+
+/// Check an ePub archive
+pub fn check_epub<R: Read + Seek>(reader: R, version: EpubVersion) -> EpubCheckReport {
+    let mut report = EpubCheckReport::default();
+    let mut archive = match ZipArchive::new(reader) {
+        Ok(archive) => archive,
+        Err(e) => {
+            report.error(
+                "zip-unreadable",
+                format!("Not a readable ZIP archive: {}", e),
+                None,
+            );
+            return report;
+        }
+    };
+
+    check_mimetype(&mut archive, &mut report);
+
+    let entries: HashSet<String> = archive.file_names().map(str::to_string).collect();
+    let Some(container) = read_entry(&mut archive, CONTAINER_PATH) else {
+        report.error(
+            "container-missing",
+            "META-INF/container.xml is missing",
+            None,
+        );
+        return report;
+    };
+    let rootfile = start_tags(&container, "rootfile")
+        .into_iter()
+        .find(|tag| attribute(tag, "media-type").as_deref() == Some(OPF_MEDIA_TYPE))
+        .and_then(|tag| attribute(tag, "full-path"));
+    let Some(opf_path) = rootfile else {
+        report.error(
+            "container-rootfile",
+            "container.xml names no package document",
+            Some(CONTAINER_PATH),
+        );
+        return report;
+    };
+    let Some(opf) = read_entry(&mut archive, &opf_path) else {
+        report.error(
+            "opf-missing",
+            format!("Package document {} is not in the archive", opf_path),
+            Some(CONTAINER_PATH),
+        );
+        return report;
+    };
+
+    check_package(&opf, &opf_path, &entries, version, &mut report);
+    report
+}
+
+/// The mimetype entry must come first, be stored uncompressed and hold
+/// exactly the ePub media type
+fn check_mimetype<R: Read + Seek>(archive: &mut ZipArchive<R>, report: &mut EpubCheckReport) {
+    let Ok(mut first) = archive.by_index(0) else {
+        report.error("zip-empty", "The archive is empty", None);
+        return;
+    };
+    if first.name() != "mimetype" {
+        report.error(
+            "mimetype-first",
+            format!("The first entry is {}, not mimetype", first.name()),
+            Some(first.name()),
+        );
+        return;
+    }
+    if first.compression() != CompressionMethod::Stored {
+        report.error(
+            "mimetype-stored",
+            "mimetype must be stored without compression",
+            Some("mimetype"),
+        );
+    }
+    let mut content = String::new();
+    if first.read_to_string(&mut content).is_err() || content != EPUB_MIMETYPE {
+        report.error(
+            "mimetype-content",
+            format!("mimetype must contain exactly {}", EPUB_MIMETYPE),
+            Some("mimetype"),
+        );
+    }
+}
+
+fn check_package(
+    opf: &str,
+    opf_path: &str,
+    entries: &HashSet<String>,
+    version: EpubVersion,
+    report: &mut EpubCheckReport,
+) {
+    let location = Some(opf_path);
+    let package = start_tags(opf, "package")
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    let declared = attribute(package, "version").unwrap_or_default();
+    let expected = match version {
+        EpubVersion::V2 => "2.0",
+        EpubVersion::V3 => "3.0",
+    };
+    if declared != expected {
+        report.warning(
+            "package-version",
+            format!(
+                "Package declares version \"{}\" but {} was requested",
+                declared, expected
+            ),
+            location,
+        );
+    }
+
+    // Metadata
+    for field in ["dc:identifier", "dc:title", "dc:language"] {
+        if element_texts(opf, field)
+            .iter()
+            .all(|text| text.trim().is_empty())
+        {
+            report.error(
+                "metadata-required",
+                format!("Required metadata <{}> is missing or empty", field),
+                location,
+            );
+        }
+    }
+    match attribute(package, "unique-identifier") {
+        Some(id) => {
+            let found = start_tags(opf, "dc:identifier")
+                .into_iter()
+                .any(|tag| attribute(tag, "id").as_deref() == Some(id.as_str()));
+            if !found {
+                report.error(
+                    "unique-identifier",
+                    format!("unique-identifier \"{}\" matches no <dc:identifier>", id),
+                    location,
+                );
+            }
+        }
+        None => report.error(
+            "unique-identifier",
+            "<package> has no unique-identifier attribute",
+            location,
+        ),
+    }
+    if matches!(version, EpubVersion::V3) {
+        let modified = start_tags(opf, "meta")
+            .into_iter()
+            .any(|tag| attribute(tag, "property").as_deref() == Some("dcterms:modified"));
+        if !modified {
+            report.error(
+                "metadata-modified",
+                "ePub 3 requires <meta property=\"dcterms:modified\">",
+                location,
+            );
+        }
+    }
+
+    // Manifest
+    let base = match opf_path.rfind('/') {
+        Some(slash) => &opf_path[..=slash],
+        None => "",
+    };
+    let mut manifest: HashMap<String, (String, String)> = HashMap::new();
+    let mut manifested: HashSet<String> = HashSet::new();
+    let mut nav_found = false;
+    for item in start_tags(opf, "item") {
+        let id = attribute(item, "id").unwrap_or_default();
+        let href = attribute(item, "href").unwrap_or_default();
+        let media_type = attribute(item, "media-type").unwrap_or_default();
+        if id.is_empty() || href.is_empty() {
+            report.error(
+                "manifest-item",
+                "Manifest item without id or href",
+                location,
+            );
+            continue;
+        }
+        if media_type.is_empty() {
+            report.error(
+                "manifest-media-type",
+                format!("Manifest item \"{}\" has no media-type", id),
+                location,
+            );
+        }
+        if attribute(item, "properties")
+            .is_some_and(|properties| properties.split_whitespace().any(|p| p == "nav"))
+        {
+            nav_found = true;
+        }
+        let path = resolve_href(base, &href);
+        if !entries.contains(&path) {
+            report.error(
+                "manifest-file-missing",
+                format!(
+                    "Manifest item \"{}\" points at {}, which is not in the archive",
+                    id, path
+                ),
+                location,
+            );
+        }
+        manifested.insert(path);
+        if manifest.insert(id.clone(), (href, media_type)).is_some() {
+            report.error(
+                "manifest-duplicate-id",
+                format!("Manifest id \"{}\" is used more than once", id),
+                location,
+            );
+        }
+    }
+    if matches!(version, EpubVersion::V3) && !nav_found {
+        report.error(
+            "nav-missing",
+            "ePub 3 requires a manifest item with properties=\"nav\"",
+            location,
+        );
+    }
+
+    // Spine
+    let spine = start_tags(opf, "spine").into_iter().next();
+    let itemrefs = start_tags(opf, "itemref");
+    if spine.is_none() || itemrefs.is_empty() {
+        report.error(
+            "spine-empty",
+            "The spine lists no content documents",
+            location,
+        );
+    }
+    for itemref in itemrefs {
+        let idref = attribute(itemref, "idref").unwrap_or_default();
+        if !manifest.contains_key(&idref) {
+            report.error(
+                "spine-idref",
+                format!("Spine itemref \"{}\" matches no manifest item", idref),
+                location,
+            );
+        }
+    }
+    if matches!(version, EpubVersion::V2) {
+        let toc = spine.and_then(|spine| attribute(spine, "toc"));
+        match toc.and_then(|toc| manifest.get(&toc)) {
+            Some((_, media_type)) if media_type == NCX_MEDIA_TYPE => {}
+            _ => report.error(
+                "ncx-missing",
+                "ePub 2 requires the spine's toc attribute to name an NCX manifest item",
+                location,
+            ),
+        }
+    }
+
+    // Files nothing refers to
+    let mut unlisted: Vec<&String> = entries
+        .iter()
+        .filter(|entry| {
+            !entry.ends_with('/')
+                && *entry != "mimetype"
+                && !entry.starts_with("META-INF/")
+                && *entry != opf_path
+                && !manifested.contains(*entry)
+        })
+        .collect();
+    unlisted.sort();
+    for entry in unlisted {
+        report.warning(
+            "file-not-in-manifest",
+            "File is in the archive but not in the manifest",
+            Some(entry),
+        );
+    }
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Option<String> {
+    let mut file = archive.by_name(name).ok()?;
+    let mut content = String::new();
+    file.read_to_string(&mut content).ok()?;
+    Some(content)
+}
+
+/// An href from the package document as an archive path
+fn resolve_href(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or_default();
+    let mut parts: Vec<&str> = base.split('/').filter(|part| !part.is_empty()).collect();
+    let decoded = percent_decode(href);
+    for part in decoded.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The attribute text of every `<name ...>` start tag
+fn start_tags<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let open = format!("<{}", name);
+    let mut tags = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        let boundary = after.chars().next();
+        let Some(end) = after.find('>') else { break };
+        if boundary.is_some_and(|c| c.is_whitespace() || c == '/' || c == '>') {
+            tags.push(after[..end].trim_end_matches('/'));
+        }
+        rest = &after[end..];
+    }
+    tags
+}
+
+/// The text content of every `<name>` element
+fn element_texts(xml: &str, name: &str) -> Vec<String> {
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+    let mut texts = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        let Some(tag_end) = after.find('>') else {
+            break;
+        };
+        let boundary = after.chars().next();
+        if !boundary.is_some_and(|c| c.is_whitespace() || c == '>')
+            || after[..tag_end].ends_with('/')
+        {
+            rest = &after[tag_end..];
+            continue;
+        }
+        let body = &after[tag_end + 1..];
+        let Some(end) = body.find(&close) else { break };
+        texts.push(unescape(&body[..end]));
+        rest = &body[end + close.len()..];
+    }
+    texts
+}
+
+/// The value of `name="..."` or `name='...'` in a start tag
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag;
+    loop {
+        let at = rest.find(name)?;
+        let preceded = rest[..at].chars().last().is_some_and(char::is_whitespace);
+        let after = rest[at + name.len()..].trim_start();
+        if preceded {
+            if let Some(after) = after.strip_prefix('=') {
+                let after = after.trim_start();
+                let quote = after.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+                let value = &after[1..];
+                let end = value.find(quote)?;
+                return Some(unescape(&value[..end]));
+            }
+        }
+        rest = &rest[at + name.len()..];
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+    <rootfiles>
+        <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+    </rootfiles>
+</container>"#;
+
+    const OPF: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<package version="3.0" xmlns="http://www.idpf.org/2007/opf" unique-identifier="BookId">
+    <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+        <dc:identifier id="BookId">urn:uuid:1234</dc:identifier>
+        <dc:title>The Salt Road</dc:title>
+        <dc:language>en</dc:language>
+        <meta property="dcterms:modified">2026-10-18T00:00:00Z</meta>
+    </metadata>
+    <manifest>
+        <item id="chapter1" href="chapter%201.xhtml" media-type="application/xhtml+xml"/>
+        <item id="cover" href="images/cover.jpg" media-type="image/jpeg"/>
+        <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+        <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    </manifest>
+    <spine toc="ncx">
+        <itemref idref="chapter1"/>
+    </spine>
+</package>"#;
+
+    fn build(mimetype_method: CompressionMethod, opf: &str, files: &[&str]) -> Cursor<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file(
+            "mimetype",
+            FileOptions::default().compression_method(mimetype_method),
+        )
+        .unwrap();
+        zip.write_all(EPUB_MIMETYPE.as_bytes()).unwrap();
+        let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        zip.start_file(CONTAINER_PATH, deflated).unwrap();
+        zip.write_all(CONTAINER.as_bytes()).unwrap();
+        zip.start_file("OEBPS/content.opf", deflated).unwrap();
+        zip.write_all(opf.as_bytes()).unwrap();
+        for file in files {
+            zip.start_file(*file, deflated).unwrap();
+            zip.write_all(b"<html/>").unwrap();
+        }
+        let mut cursor = zip.finish().unwrap();
+        cursor.set_position(0);
+        cursor
+    }
+
+    fn codes(report: &EpubCheckReport) -> Vec<&'static str> {
+        report.issues.iter().map(|issue| issue.code).collect()
+    }
+
+    #[test]
+    fn test_check_epub_reports_structural_problems() {
+        let files = [
+            "OEBPS/chapter 1.xhtml",
+            "OEBPS/images/cover.jpg",
+            "OEBPS/toc.ncx",
+            "OEBPS/nav.xhtml",
+        ];
+        let valid = check_epub(
+            build(CompressionMethod::Stored, OPF, &files),
+            EpubVersion::V3,
+        );
+        assert!(valid.is_valid(), "{:?}", valid.issues);
+        assert!(valid.issues.is_empty());
+
+        let broken_opf = OPF
+            .replace("<dc:language>en</dc:language>", "")
+            .replace(" properties=\"nav\"", "")
+            .replace("idref=\"chapter1\"", "idref=\"chapter2\"");
+        let broken = check_epub(
+            build(
+                CompressionMethod::Deflated,
+                &broken_opf,
+                &[
+                    "OEBPS/chapter 1.xhtml",
+                    "OEBPS/toc.ncx",
+                    "OEBPS/nav.xhtml",
+                    "OEBPS/notes.txt",
+                ],
+            ),
+            EpubVersion::V3,
+        );
+        assert!(!broken.is_valid());
+        assert_eq!(
+            codes(&broken),
+            vec![
+                "mimetype-stored",
+                "metadata-required",
+                "manifest-file-missing",
+                "nav-missing",
+                "spine-idref",
+                "file-not-in-manifest",
+            ]
+        );
+        assert!(broken.error_summary().contains("images/cover.jpg"));
+
+        let epub2 = check_epub(
+            build(
+                CompressionMethod::Stored,
+                &OPF.replace("toc=\"ncx\"", ""),
+                &files,
+            ),
+            EpubVersion::V2,
+        );
+        assert_eq!(codes(&epub2), vec!["package-version", "ncx-missing"]);
+    }
+}
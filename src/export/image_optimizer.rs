@@ -0,0 +1,612 @@
+//! Export Image Optimization
+//!
+//! Scales images down to fit the export's bounds and re-encodes them as
+//! JPEG, PNG or WebP, dropping EXIF, ICC and text chunks along the way.
+//! Jobs go through a bounded queue drained by a few worker threads, and
+//! results are cached by the checksum of the source bytes and the settings
+//! used so exporting the same manuscript again does not redo the work.
+//!
+//! Callers wait for a free slot once the queue is full rather than piling
+//! decoded images up in memory. Dropping a pending `optimize` future, or
+//! cancelling its token, frees the slot and the workers skip the job; one
+//! that is already being encoded runs to completion and is discarded.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Cursor;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageError, ImageReader, RgbImage};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+/// Most bytes of optimized output kept in the cache
+const CACHE_BUDGET_BYTES: usize = 128 * 1024 * 1024;
+const MAX_WORKERS: usize = 4;
+/// Jobs queued or being worked on at once, per queue
+const DEFAULT_CAPACITY: usize = 16;
+const CANCELLED: &str = "Image optimization was cancelled";
+const SHUT_DOWN: &str = "Image optimizer queue has shut down";
+
+static GLOBAL_QUEUE: Lazy<Arc<OptimizerQueue>> = Lazy::new(|| Arc::new(OptimizerQueue::new()));
+
+/// Format an optimized image is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+    /// Lossless; the encoder has no lossy mode
+    WebP,
+}
+
+impl OutputFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(OutputFormat::Jpeg),
+            "png" => Some(OutputFormat::Png),
+            "webp" => Some(OutputFormat::WebP),
+            _ => None,
+        }
+    }
+
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "image/jpeg" => Some(OutputFormat::Jpeg),
+            "image/png" => Some(OutputFormat::Png),
+            "image/webp" => Some(OutputFormat::WebP),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Png => "png",
+            OutputFormat::WebP => "webp",
+        }
+    }
+
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Png => "image/png",
+            OutputFormat::WebP => "image/webp",
+        }
+    }
+
+    fn of(format: image::ImageFormat) -> Option<Self> {
+        match format {
+            image::ImageFormat::Jpeg => Some(OutputFormat::Jpeg),
+            image::ImageFormat::Png => Some(OutputFormat::Png),
+            image::ImageFormat::WebP => Some(OutputFormat::WebP),
+            _ => None,
+        }
+    }
+}
+
+/// How to optimize an image
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OptimizeOptions {
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// JPEG quality, 1 to 100
+    pub quality: u8,
+    /// PNG effort, 0 (fastest) to 9 (smallest)
+    pub compression_level: u8,
+    pub strip_metadata: bool,
+    /// Keeps the source's format when `None`; formats that can't be
+    /// written, like GIF, become PNG
+    pub format: Option<OutputFormat>,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        Self {
+            max_width: None,
+            max_height: None,
+            quality: 85,
+            compression_level: 6,
+            strip_metadata: true,
+            format: None,
+        }
+    }
+}
+
+/// Result of optimizing one image
+#[derive(Debug, Clone)]
+pub struct OptimizedImage {
+    pub data: Vec<u8>,
+    pub format: OutputFormat,
+    pub width: u32,
+    pub height: u32,
+    pub original_size: usize,
+    /// Checksum of the source bytes
+    pub source_checksum: String,
+    /// False when the source was kept as it was
+    pub reencoded: bool,
+}
+
+impl OptimizedImage {
+    /// Output size over source size
+    pub fn compression_ratio(&self) -> f32 {
+        if self.original_size == 0 {
+            1.0
+        } else {
+            self.data.len() as f32 / self.original_size as f32
+        }
+    }
+}
+
+/// Hex SHA-256 of `data`
+pub fn checksum(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Largest size with the same aspect ratio that fits the bounds; images
+/// are never scaled up
+pub fn fit_within(
+    width: u32,
+    height: u32,
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+) -> (u32, u32) {
+    let mut scale = 1.0f64;
+    if let Some(max) = max_width.filter(|max| *max > 0 && width > *max) {
+        scale = scale.min(max as f64 / width as f64);
+    }
+    if let Some(max) = max_height.filter(|max| *max > 0 && height > *max) {
+        scale = scale.min(max as f64 / height as f64);
+    }
+    if scale >= 1.0 {
+        return (width, height);
+    }
+    let scaled = |value: u32| ((value as f64 * scale).round() as u32).max(1);
+    (scaled(width), scaled(height))
+}
+
+/// Decode, resize and re-encode `source`. Blocking; call it from a worker
+/// or `spawn_blocking`.
+pub fn optimize(source: &[u8], options: &OptimizeOptions) -> Result<OptimizedImage, ImageError> {
+    let reader = ImageReader::new(Cursor::new(source)).with_guessed_format()?;
+    let source_format = reader.format().and_then(OutputFormat::of);
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let format = options
+        .format
+        .or(source_format)
+        .unwrap_or(OutputFormat::Png);
+    let (width, height) = (image.width(), image.height());
+    let (target_width, target_height) =
+        fit_within(width, height, options.max_width, options.max_height);
+    let resized = (target_width, target_height) != (width, height);
+
+    // Nothing asks for a change, so keep the source and its metadata
+    if !resized
+        && !options.strip_metadata
+        && source_format == Some(format)
+        && orientation == Orientation::NoTransforms
+    {
+        return Ok(OptimizedImage {
+            data: source.to_vec(),
+            format,
+            width,
+            height,
+            original_size: source.len(),
+            source_checksum: checksum(source),
+            reencoded: false,
+        });
+    }
+
+    if resized {
+        image = image.resize_exact(target_width, target_height, FilterType::Lanczos3);
+    }
+
+    Ok(OptimizedImage {
+        data: encode(&image, format, options)?,
+        format,
+        width: image.width(),
+        height: image.height(),
+        original_size: source.len(),
+        source_checksum: checksum(source),
+        reencoded: true,
+    })
+}
+
+fn encode(
+    image: &DynamicImage,
+    format: OutputFormat,
+    options: &OptimizeOptions,
+) -> Result<Vec<u8>, ImageError> {
+    let mut data = Vec::new();
+    match format {
+        OutputFormat::Jpeg => {
+            let encoder = JpegEncoder::new_with_quality(
+                Cursor::new(&mut data),
+                options.quality.clamp(1, 100),
+            );
+            flatten(image).write_with_encoder(encoder)?;
+        }
+        OutputFormat::Png => {
+            let compression = match options.compression_level {
+                0 => CompressionType::Fast,
+                level => CompressionType::Level(level.min(9)),
+            };
+            let encoder = PngEncoder::new_with_quality(
+                Cursor::new(&mut data),
+                compression,
+                PngFilter::Adaptive,
+            );
+            eight_bit(image).write_with_encoder(encoder)?;
+        }
+        OutputFormat::WebP => {
+            let encoder = WebPEncoder::new_lossless(Cursor::new(&mut data));
+            eight_bit(image).write_with_encoder(encoder)?;
+        }
+    }
+    Ok(data)
+}
+
+/// RGB with transparency composited onto white, for JPEG
+fn flatten(image: &DynamicImage) -> RgbImage {
+    if !image.color().has_alpha() {
+        return image.to_rgb8();
+    }
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |channel: u8| {
+            let alpha = a as u32;
+            ((channel as u32 * alpha + 255 * (255 - alpha)) / 255) as u8
+        };
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// 8-bit RGB or RGBA, which every encoder here accepts
+fn eight_bit(image: &DynamicImage) -> DynamicImage {
+    if image.color().has_alpha() {
+        DynamicImage::ImageRgba8(image.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    }
+}
+
+struct Job {
+    source: Vec<u8>,
+    options: OptimizeOptions,
+    reply: oneshot::Sender<Result<OptimizedImage, String>>,
+    /// Held until the job is done, so the queue slot stays taken meanwhile
+    slot: OwnedSemaphorePermit,
+}
+
+type CacheKey = (String, OptimizeOptions);
+
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<CacheKey, OptimizedImage>,
+    /// Insertion order, oldest first, for eviction
+    order: VecDeque<CacheKey>,
+    bytes: usize,
+}
+
+impl Cache {
+    fn insert(&mut self, key: CacheKey, image: OptimizedImage) {
+        if image.data.len() > CACHE_BUDGET_BYTES || self.entries.contains_key(&key) {
+            return;
+        }
+        self.bytes += image.data.len();
+        self.order.push_back(key.clone());
+        self.entries.insert(key, image);
+        while self.bytes > CACHE_BUDGET_BYTES {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            if let Some(evicted) = self.entries.remove(&oldest) {
+                self.bytes -= evicted.data.len();
+            }
+        }
+    }
+}
+
+/// Bounded queue of optimization jobs drained by a pool of worker threads,
+/// with a cache in front of it
+pub struct OptimizerQueue {
+    sender: mpsc::SyncSender<Job>,
+    /// One permit per job that may be queued or running
+    slots: Arc<Semaphore>,
+    capacity: usize,
+    shutdown: CancellationToken,
+    cache: Mutex<Cache>,
+}
+
+impl OptimizerQueue {
+    /// Start a queue with one worker per core, up to four
+    pub fn new() -> Self {
+        let workers = thread::available_parallelism()
+            .map(|count| count.get())
+            .unwrap_or(1)
+            .min(MAX_WORKERS);
+        Self::with_workers(workers)
+    }
+
+    pub fn with_workers(workers: usize) -> Self {
+        Self::with_limits(workers, DEFAULT_CAPACITY)
+    }
+
+    /// Start a queue with `workers` threads that holds at most `capacity`
+    /// jobs, counting the ones being worked on
+    pub fn with_limits(workers: usize, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        // Slots are taken before sending, so the channel never fills up
+        let (sender, receiver) = mpsc::sync_channel::<Job>(capacity);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let shutdown = CancellationToken::new();
+        for index in 0..workers.max(1) {
+            let receiver = Arc::clone(&receiver);
+            let shutdown = shutdown.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("image-optimizer-{index}"))
+                .spawn(move || loop {
+                    // Hold the lock only while taking a job off the queue
+                    let job = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+                    let Ok(job) = job else {
+                        return;
+                    };
+                    // Nobody is waiting for it any more
+                    if job.reply.is_closed() || shutdown.is_cancelled() {
+                        continue;
+                    }
+                    let result = optimize(&job.source, &job.options).map_err(|e| e.to_string());
+                    // Free the slot before waking the caller
+                    drop(job.slot);
+                    let _ = job.reply.send(result);
+                });
+            if let Err(e) = spawned {
+                log::warn!("Failed to start image optimizer worker: {}", e);
+            }
+        }
+        Self {
+            sender,
+            slots: Arc::new(Semaphore::new(capacity)),
+            capacity,
+            shutdown,
+            cache: Mutex::new(Cache::default()),
+        }
+    }
+
+    /// The queue shared by every exporter, so the cache outlives any one
+    /// export
+    pub fn global() -> Arc<OptimizerQueue> {
+        Arc::clone(&GLOBAL_QUEUE)
+    }
+
+    /// Optimize `source`, reusing an earlier result for the same bytes and
+    /// options. Waits for a free slot while the queue is full.
+    pub async fn optimize(
+        &self,
+        source: Vec<u8>,
+        options: OptimizeOptions,
+    ) -> Result<OptimizedImage, String> {
+        self.optimize_until(source, options, &CancellationToken::new())
+            .await
+    }
+
+    /// Like [`optimize`](Self::optimize), but gives up once `cancel` fires
+    pub async fn optimize_until(
+        &self,
+        source: Vec<u8>,
+        options: OptimizeOptions,
+        cancel: &CancellationToken,
+    ) -> Result<OptimizedImage, String> {
+        let key = (checksum(&source), options.clone());
+        if let Some(cached) = self.cached(&key) {
+            return Ok(cached);
+        }
+
+        // Closed on shutdown, which fails the wait
+        let slot = tokio::select! {
+            slot = Arc::clone(&self.slots).acquire_owned() => {
+                slot.map_err(|_| SHUT_DOWN.to_string())?
+            }
+            _ = cancel.cancelled() => return Err(CANCELLED.to_string()),
+        };
+        let (reply, receiver) = oneshot::channel();
+        let job = Job {
+            source,
+            options,
+            reply,
+            slot,
+        };
+        self.sender
+            .try_send(job)
+            .map_err(|_| "Image optimizer workers have stopped".to_string())?;
+        let image = tokio::select! {
+            result = receiver => {
+                result.map_err(|_| "Image optimizer worker dropped the job".to_string())??
+            }
+            _ = cancel.cancelled() => return Err(CANCELLED.to_string()),
+            _ = self.shutdown.cancelled() => return Err(SHUT_DOWN.to_string()),
+        };
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(key, image.clone());
+        }
+        Ok(image)
+    }
+
+    fn cached(&self, key: &CacheKey) -> Option<OptimizedImage> {
+        self.cache.lock().ok()?.entries.get(key).cloned()
+    }
+
+    /// Jobs queued or being worked on
+    pub fn pending(&self) -> usize {
+        self.capacity - self.slots.available_permits()
+    }
+
+    /// Most jobs the queue holds before callers have to wait
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Fail every waiting caller and skip every queued job. Jobs already
+    /// being encoded finish, but their results are dropped.
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+        self.slots.close();
+    }
+
+    /// Number of cached results
+    pub fn cached_len(&self) -> usize {
+        self.cache
+            .lock()
+            .map(|cache| cache.entries.len())
+            .unwrap_or(0)
+    }
+}
+
+impl Default for OptimizerQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([
+                (x % 256) as u8,
+                (y % 256) as u8,
+                128,
+                if x < 4 { 0 } else { 255 },
+            ])
+        });
+        let mut data = Vec::new();
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut Cursor::new(&mut data), image::ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn resizes_within_bounds_and_converts() {
+        assert_eq!(fit_within(1600, 900, Some(800), Some(600)), (800, 450));
+        assert_eq!(fit_within(400, 1200, Some(800), Some(600)), (200, 600));
+        assert_eq!(fit_within(100, 50, Some(800), None), (100, 50));
+
+        let source = png(200, 100);
+        let options = OptimizeOptions {
+            max_width: Some(50),
+            format: Some(OutputFormat::Jpeg),
+            quality: 70,
+            ..OptimizeOptions::default()
+        };
+        let optimized = optimize(&source, &options).unwrap();
+        assert!(optimized.reencoded);
+        assert_eq!((optimized.width, optimized.height), (50, 25));
+        let decoded = image::load_from_memory(&optimized.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (50, 25));
+        assert_eq!(
+            image::guess_format(&optimized.data).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+
+        // Small enough, same format and metadata kept: left alone
+        let kept = optimize(
+            &source,
+            &OptimizeOptions {
+                strip_metadata: false,
+                ..OptimizeOptions::default()
+            },
+        )
+        .unwrap();
+        assert!(!kept.reencoded);
+        assert_eq!(kept.data, source);
+    }
+
+    #[tokio::test]
+    async fn queue_caches_by_checksum_and_options() {
+        let queue = OptimizerQueue::with_workers(2);
+        let source = png(64, 64);
+        let options = OptimizeOptions {
+            max_width: Some(32),
+            format: Some(OutputFormat::WebP),
+            ..OptimizeOptions::default()
+        };
+
+        let first = queue
+            .optimize(source.clone(), options.clone())
+            .await
+            .unwrap();
+        let second = queue.optimize(source.clone(), options).await.unwrap();
+        assert_eq!(first.data, second.data);
+        assert_eq!(queue.cached_len(), 1);
+
+        queue
+            .optimize(source, OptimizeOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(queue.cached_len(), 2);
+        assert!(queue
+            .optimize(b"not an image".to_vec(), OptimizeOptions::default())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn full_queue_waits_and_cancels() {
+        let queue = OptimizerQueue::with_limits(1, 1);
+        let source = png(16, 16);
+        assert_eq!(queue.capacity(), 1);
+
+        // Take the only slot, as a long-running job would
+        let slot = Arc::clone(&queue.slots).acquire_owned().await.unwrap();
+        assert_eq!(queue.pending(), 1);
+        let waiting = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            queue.optimize(source.clone(), OptimizeOptions::default()),
+        )
+        .await;
+        assert!(waiting.is_err());
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let cancelled = queue
+            .optimize_until(source.clone(), OptimizeOptions::default(), &cancel)
+            .await;
+        assert_eq!(cancelled.unwrap_err(), CANCELLED);
+
+        drop(slot);
+        queue
+            .optimize(source.clone(), OptimizeOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(queue.pending(), 0);
+
+        queue.shutdown();
+        let options = OptimizeOptions {
+            max_width: Some(8),
+            ..OptimizeOptions::default()
+        };
+        assert_eq!(
+            queue.optimize(source, options).await.unwrap_err(),
+            SHUT_DOWN
+        );
+    }
+}
@@ -18,7 +18,22 @@ use uuid::Uuid;
 use zip::{ZipWriter, CompressionMethod};
 use std::io::BufWriter;
 
+use crate::automation::{ExportHookInput, ScriptEngine};
+use crate::database::models::export_record::{ExportJobRecord, ExportJobState};
+use crate::database::ExportRepository;
 use crate::error::{AppResult, AppError};
+use crate::ipc_bridge::IpcEvents;
+use crate::publishing::escape_xml;
+use crate::scratch_space::{self, ScratchDir, ScratchSpace};
+
+pub mod epub_check;
+pub mod image_optimizer;
+pub mod scheduler;
+pub mod template_engine;
+
+use image_optimizer::{OptimizeOptions, OptimizerQueue, OutputFormat};
+use scheduler::{ExportPriority, ExportScheduler};
+use template_engine::{render, TemplateContext};
 
 /// PDF generation configuration
 #[derive(Debug, Clone)]
@@ -489,6 +504,57 @@ pub struct ExportJob {
     pub configuration: ExportConfiguration,
 }
 
+/// Pushed to the frontend as an `export_job` event whenever a job's
+/// status or progress changes
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportJobEvent {
+    pub job_id: String,
+    pub document_id: String,
+    pub status: ExportStatus,
+    pub progress: f32,
+    /// Set once the job has completed
+    pub output_path: Option<PathBuf>,
+    pub file_size_bytes: Option<u64>,
+    /// Set when the job has failed
+    pub error_message: Option<String>,
+}
+
+impl From<&ExportJob> for ExportJobEvent {
+    fn from(job: &ExportJob) -> Self {
+        Self {
+            job_id: job.job_id.clone(),
+            document_id: job.document_id.clone(),
+            status: job.status,
+            progress: job.progress,
+            output_path: job.output_path.clone(),
+            file_size_bytes: job.file_size_bytes,
+            error_message: job.error_message.clone(),
+        }
+    }
+}
+
+impl From<&ExportJob> for ExportJobRecord {
+    /// The content and settings aren't serializable yet, so the record's
+    /// request is empty and an interrupted job has to be submitted again
+    fn from(job: &ExportJob) -> Self {
+        Self {
+            job_id: job.job_id.clone(),
+            document_id: job.document_id.clone(),
+            export_type: job.export_type.kind().to_string(),
+            state: job.status.into(),
+            progress: job.progress,
+            request: serde_json::Value::Null,
+            output_path: job.output_path.as_ref().map(|p| p.to_string_lossy().into_owned()),
+            error_message: job.error_message.clone(),
+            file_size_bytes: job.file_size_bytes,
+            created_at: job.created_at,
+            started_at: job.started_at,
+            completed_at: job.completed_at,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
 /// Export types
 #[derive(Debug, Clone)]
 pub enum ExportType {
@@ -507,8 +573,21 @@ pub enum ExportType {
     },
 }
 
+impl ExportType {
+    /// Short name stored with persisted jobs
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ExportType::Pdf { .. } => "pdf",
+            ExportType::Epub { .. } => "epub",
+            ExportType::Html { .. } => "html",
+            ExportType::Docx { .. } => "docx",
+        }
+    }
+}
+
 /// Export status
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ExportStatus {
     Pending,
     Processing,
@@ -517,6 +596,18 @@ pub enum ExportStatus {
     Cancelled,
 }
 
+impl From<ExportStatus> for ExportJobState {
+    fn from(status: ExportStatus) -> Self {
+        match status {
+            ExportStatus::Pending => ExportJobState::Pending,
+            ExportStatus::Processing => ExportJobState::Processing,
+            ExportStatus::Completed => ExportJobState::Completed,
+            ExportStatus::Failed => ExportJobState::Failed,
+            ExportStatus::Cancelled => ExportJobState::Cancelled,
+        }
+    }
+}
+
 /// Export configuration
 #[derive(Debug, Clone)]
 pub struct ExportConfiguration {
@@ -728,6 +819,12 @@ pub struct EpubPackage {
     pub spine: Vec<SpineItem>,
     pub guide: Option<Vec<GuideItem>>,
     pub bindings: Option<HashMap<String, String>>,
+    /// Chapters in spine order, rendered to `xhtml/chapter_N.xhtml`
+    pub chapters: Vec<EpubChapter>,
+    /// Processed images with their href inside OEBPS
+    pub assets: Vec<(String, AssetData)>,
+    /// Image `src` as written in the chapters to its href inside OEBPS
+    pub image_hrefs: HashMap<String, String>,
 }
 
 /// Manifest item definition
@@ -874,12 +971,17 @@ pub struct EpubGenerator {
     export_jobs: Arc<tokio::sync::RwLock<HashMap<String, ExportJob>>>,
     asset_manager: Arc<AssetManager>,
     metadata_validator: Arc<MetadataValidator>,
+    events: Option<IpcEvents>,
+    repository: Option<Arc<RwLock<ExportRepository>>>,
+    scheduler: ExportScheduler,
+    scratch: ScratchSpace,
+    automation: Option<Arc<ScriptEngine>>,
 }
 
 /// Asset management for ePub resources
 pub struct AssetManager {
-    asset_cache: Arc<tokio::sync::RwLock<HashMap<String, AssetData>>>,
-    processing_queue: Arc<tokio::sync::mpsc::UnboundedSender<AssetProcessingJob>>,
+    /// Optimizes images on worker threads, caching results by checksum
+    processing_queue: Arc<OptimizerQueue>,
 }
 
 /// Asset data structure
@@ -894,16 +996,6 @@ pub struct AssetData {
     pub checksum: String,
 }
 
-/// Asset processing job
-#[derive(Debug, Clone)]
-pub struct AssetProcessingJob {
-    pub job_id: String,
-    pub source_path: PathBuf,
-    pub target_format: Option<AssetFormat>,
-    pub optimization_settings: OptimizationSettings,
-    pub callback: tokio::sync::oneshot::Sender<AssetData>,
-}
-
 /// Asset types
 #[derive(Debug, Clone)]
 pub enum AssetType {
@@ -951,7 +1043,7 @@ pub struct ValidationRule {
 }
 
 /// Validation severity
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ValidationSeverity {
     Error,
     Warning,
@@ -989,7 +1081,7 @@ pub enum FieldType {
 }
 
 /// ePub versions
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum EpubVersion {
     V2,
     V3,
@@ -1078,8 +1170,8 @@ pub enum FontLicense {
 
 /// Image processing system
 pub struct ImageProcessor {
-    image_cache: Arc<tokio::sync::RwLock<HashMap<String, ProcessedImage>>>,
-    processing_queue: Arc<tokio::sync::mpsc::UnboundedSender<ImageProcessingJob>>,
+    /// Optimizes images on worker threads, caching results by checksum
+    processing_queue: Arc<OptimizerQueue>,
 }
 
 /// Processed image data
@@ -1094,16 +1186,6 @@ pub struct ProcessedImage {
     pub compression_ratio: f32,
 }
 
-/// Image processing job
-#[derive(Debug, Clone)]
-pub struct ImageProcessingJob {
-    pub job_id: String,
-    pub source_path: PathBuf,
-    pub target_format: ImageFormat,
-    pub quality_settings: ImageQualitySettings,
-    pub callback: tokio::sync::oneshot::Sender<ProcessedImage>,
-}
-
 /// Image formats
 #[derive(Debug, Clone)]
 pub enum ImageFormat {
@@ -1157,9 +1239,51 @@ impl EpubGenerator {
             export_jobs: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
             asset_manager,
             metadata_validator,
+            events: None,
+            repository: None,
+            scheduler: ExportScheduler::default(),
+            scratch: ScratchSpace::open_default(),
+            automation: None,
         }
     }
 
+    /// Push job status and progress changes to the frontend
+    pub fn with_events(mut self, events: IpcEvents) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Record jobs in the database as they change status
+    pub fn with_repository(mut self, repository: Arc<RwLock<ExportRepository>>) -> Self {
+        self.repository = Some(repository);
+        self
+    }
+
+    /// Run jobs through a scheduler shared with other generators, so the
+    /// concurrency limit covers all of them
+    pub fn with_scheduler(mut self, scheduler: ExportScheduler) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// Stage ePub contents in `scratch` instead of the app's scratch space
+    pub fn with_scratch_space(mut self, scratch: ScratchSpace) -> Self {
+        self.scratch = scratch;
+        self
+    }
+
+    /// Run the hooks of the template a job was made with, through
+    /// `engine`, once the job completes
+    pub fn with_automation(mut self, engine: Arc<ScriptEngine>) -> Self {
+        self.automation = Some(engine);
+        self
+    }
+
+    /// Change how many exports may run at once
+    pub fn set_max_concurrent_exports(&self, max_concurrent: usize) {
+        self.scheduler.set_max_concurrent(max_concurrent);
+    }
+
     /// Generate ePub from document content
     pub async fn generate_epub(
         &self,
@@ -1167,6 +1291,19 @@ impl EpubGenerator {
         content: Vec<DocumentElement>,
         config: EpubExportConfig,
         template_id: Option<String>,
+    ) -> AppResult<String> {
+        self.generate_epub_with_priority(document_id, content, config, template_id, ExportPriority::Normal).await
+    }
+
+    /// Queue ePub generation; higher priority jobs start before others
+    /// waiting for a free slot
+    pub async fn generate_epub_with_priority(
+        &self,
+        document_id: String,
+        content: Vec<DocumentElement>,
+        config: EpubExportConfig,
+        template_id: Option<String>,
+        priority: ExportPriority,
     ) -> AppResult<String> {
         let job_id = Uuid::new_v4().to_string();
         
@@ -1187,13 +1324,16 @@ impl EpubGenerator {
         };
 
         // Store job
-        let mut jobs = self.export_jobs.write().await;
-        jobs.insert(job_id.clone(), job);
+        self.persist_job(&job).await;
+        self.export_jobs.write().await.insert(job_id.clone(), job);
 
-        // Start generation process
+        // Queue generation; it starts once a slot is free
         let generator_clone = self.clone();
-        tokio::spawn(async move {
-            let _ = generator_clone.process_epub_generation(job_id, content, config, template_id).await;
+        let spawned_job_id = job_id.clone();
+        self.scheduler.submit(job_id.clone(), priority, move |_| async move {
+            if let Err(e) = generator_clone.process_epub_generation(spawned_job_id.clone(), content, config, template_id).await {
+                generator_clone.fail_job(&spawned_job_id, e.to_string()).await;
+            }
         });
 
         Ok(job_id)
@@ -1241,20 +1381,67 @@ impl EpubGenerator {
         // Validate generated ePub
         self.validate_epub_file(&output_path, config.epub_version).await?;
 
+        // Record the output before completing so the completion event carries it
+        let file_size_bytes = fs::metadata(&output_path)?.len();
+        let document_id = {
+            let mut jobs = self.export_jobs.write().await;
+            jobs.get_mut(&job_id).map(|job| {
+                job.output_path = Some(output_path.clone());
+                job.completed_at = Some(Utc::now());
+                job.file_size_bytes = Some(file_size_bytes);
+                job.document_id.clone()
+            })
+        };
+
         // Complete job
         self.update_job_status(&job_id, ExportStatus::Completed, 1.0).await;
 
-        // Update output path
-        let mut jobs = self.export_jobs.write().await;
-        if let Some(job) = jobs.get_mut(&job_id) {
-            job.output_path = Some(output_path);
-            job.completed_at = Some(Utc::now());
-            job.file_size_bytes = Some(fs::metadata(&output_path)?.len());
+        if let Some(template_id) = template_id {
+            let export = ExportHookInput {
+                job_id,
+                document_id,
+                export_type: "epub".to_string(),
+                output_path,
+            };
+            self.run_export_hooks(&template_id, export).await;
         }
 
         Ok(())
     }
 
+    /// Run the hooks of a saved template after a job made with it; the
+    /// export stays complete whether or not they succeed, and the outcomes
+    /// are pushed as an `export_hooks` event
+    async fn run_export_hooks(&self, template_id: &str, export: ExportHookInput) {
+        let (Some(repository), Some(engine)) = (&self.repository, &self.automation) else {
+            return;
+        };
+        // Templates that were never saved have no hooks
+        let Ok(template) = repository.read().await.template(template_id).await else {
+            return;
+        };
+        if template.hooks.is_empty() {
+            return;
+        }
+
+        let outcomes = engine.run_export_hooks(&template.hooks, &export).await;
+        for outcome in outcomes.iter().filter(|outcome| !outcome.success) {
+            log::warn!(
+                "Export hook {:?} failed for job {}: {}",
+                outcome.hook,
+                export.job_id,
+                outcome.error_message.as_deref().unwrap_or("unknown error")
+            );
+        }
+        if let Some(events) = &self.events {
+            events.push("export_hooks", &serde_json::json!({
+                "job_id": export.job_id,
+                "output_path": export.output_path,
+                "outcomes": outcomes,
+            }));
+        }
+    }
+
     /// Convert document content to ePub format
     async fn convert_to_epub_content(
         &self,
@@ -1276,8 +1463,11 @@ impl EpubGenerator {
             match element {
                 DocumentElement::Heading { level, text, id } => {
                     if level == 1 {
-                        // Start new chapter
-                        if !current_chapter.content.is_empty() {
+                        // Start new chapter; a heading that opens the
+                        // book names the first one
+                        if current_chapter.content.is_empty() {
+                            current_chapter.title = text.clone();
+                        } else {
                             chapters.push(current_chapter);
                             let chapter_num = chapters.len() + 1;
                             current_chapter = EpubChapter {
@@ -1313,7 +1503,7 @@ impl EpubGenerator {
                 },
                 DocumentElement::Image { path, caption, width, height } => {
                     current_chapter.content.push(EpubContent::Image {
-                        src: self.process_asset_path(&path).await?,
+                        src: path.to_string_lossy().to_string(),
                         alt: caption.unwrap_or_else(|| "Image".to_string()),
                         width,
                         height,
@@ -1436,16 +1626,37 @@ impl EpubGenerator {
             });
         }
         
-        // Add assets to manifest
-        for asset in &assets {
+        // Add assets to manifest, packaged under images/
+        let mut image_hrefs = HashMap::new();
+        let mut packaged_assets = Vec::new();
+        for (index, asset) in assets.into_iter().enumerate() {
+            let source = asset.file_path.to_string_lossy().to_string();
+            let mut file_name = asset.file_path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| asset.asset_id.clone());
+            // Optimizing may have converted the image to another format
+            if let Some(format) = OutputFormat::from_media_type(&asset.media_type) {
+                let extension = Path::new(&file_name).extension()
+                    .map(|extension| extension.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                if OutputFormat::from_extension(&extension) != Some(format) {
+                    file_name = Path::new(&file_name).with_extension(format.extension())
+                        .to_string_lossy()
+                        .to_string();
+                }
+            }
+            let href = format!("images/{}_{}", index + 1, file_name);
+
             manifest.insert(asset.asset_id.clone(), ManifestItem {
                 id: asset.asset_id.clone(),
-                href: asset.file_path.to_string_lossy().to_string(),
+                href: href.clone(),
                 media_type: asset.media_type.clone(),
                 properties: None,
                 fallback: None,
                 required_namespace: None,
             });
+            image_hrefs.insert(source, href.clone());
+            packaged_assets.push((href, asset));
         }
 
         let package = EpubPackage {
@@ -1456,6 +1667,9 @@ impl EpubGenerator {
             spine,
             guide: None,
             bindings: None,
+            chapters,
+            assets: packaged_assets,
+            image_hrefs,
         };
 
         Ok(package)
@@ -1473,11 +1687,14 @@ impl EpubGenerator {
         
         for (index, item) in package.spine.iter().enumerate() {
             if let Some(chapter) = package.manifest.get(&item.idref) {
+                let title = package.chapters.get(index)
+                    .map(|c| escape_xml(&c.title))
+                    .unwrap_or_else(|| format!("Chapter {}", index + 1));
                 nav_points.push(NavPoint {
                     id: format!("navpoint_{}", index + 1),
-                    text: format!("Chapter {}", index + 1),
+                    text: title.clone(),
                     content_src: chapter.href.clone(),
-                    nav_label: format!("Chapter {}", index + 1),
+                    nav_label: title,
                     children: Vec::new(),
                 });
             }
@@ -1510,41 +1727,43 @@ impl EpubGenerator {
         
         let output_path = output_dir.join(format!("{}.epub", job_id));
         
-        // Create temporary directory for ePub contents
-        let temp_dir = output_dir.join(format!("temp_{}", job_id));
-        fs::create_dir_all(&temp_dir)?;
-        
-        // Create META-INF directory
-        let meta_inf_dir = temp_dir.join("META-INF");
-        fs::create_dir_all(&meta_inf_dir)?;
-        
-        // Create OEBPS directory
-        let oebps_dir = temp_dir.join("OEBPS");
-        fs::create_dir_all(&oebps_dir)?;
+        // Stage the ePub contents in scratch space; the directory is removed
+        // when `scratch` drops, whether or not packaging succeeds
+        let scratch = self.scratch.create(job_id)?;
+        let asset_bytes: u64 = package.assets.values().map(|a| a.processed_data.len() as u64).sum();
+        scratch_space::preflight(scratch.path(), asset_bytes)?;
         
         self.update_job_progress(job_id, 0.02).await;
         
         // Generate container.xml
         let container_xml = self.generate_container_xml();
-        fs::write(meta_inf_dir.join("container.xml"), container_xml)?;
+        scratch.write("META-INF/container.xml", container_xml)?;
         
         // Generate content.opf
         let content_opf = self.generate_content_opf(&package);
-        fs::write(oebps_dir.join("content.opf"), content_opf)?;
+        scratch.write("OEBPS/content.opf", content_opf)?;
         
         // Generate navigation files
-        self.generate_navigation_files(&oebps_dir, &navigation, &package).await?;
+        self.generate_navigation_files(&scratch, &navigation, &package).await?;
         
         // Generate chapter XHTML files
-        self.generate_chapter_files(&oebps_dir, &package).await?;
+        self.generate_chapter_files(&scratch, &package).await?;
+
+        // Copy processed images
+        for (href, asset) in &package.assets {
+            scratch.write(Path::new("OEBPS").join(href), &asset.processed_data)?;
+        }
         
         self.update_job_progress(job_id, 0.05).await;
         
-        // Create zip file
-        self.create_zip_archive(&temp_dir, &output_path).await?;
+        // The archive is no bigger than the files going into it
+        scratch_space::preflight(output_dir, scratch.used())?;
         
-        // Clean up temporary directory
-        fs::remove_dir_all(&temp_dir)?;
+        // Create zip file, leaving no partial ePub behind if it fails
+        if let Err(e) = self.create_zip_archive(scratch.path(), &output_path).await {
+            let _ = fs::remove_file(&output_path);
+            return Err(e);
+        }
         
         Ok(output_path)
     }
@@ -1630,17 +1849,17 @@ impl EpubGenerator {
     /// Generate navigation files
     async fn generate_navigation_files(
         &self,
-        oebps_dir: &Path,
+        scratch: &ScratchDir,
         navigation: &EpubNavigation,
         package: &EpubPackage,
     ) -> AppResult<()> {
         // Generate toc.ncx for ePub 2
         let toc_ncx = self.generate_toc_ncx(navigation, package);
-        fs::write(oebps_dir.join("toc.ncx"), toc_ncx)?;
+        scratch.write("OEBPS/toc.ncx", toc_ncx)?;
 
         // Generate nav.xhtml for ePub 3
         let nav_xhtml = self.generate_nav_xhtml(navigation);
-        fs::write(oebps_dir.join("nav.xhtml"), nav_xhtml)?;
+        scratch.write("OEBPS/nav.xhtml", nav_xhtml)?;
 
         Ok(())
     }
@@ -1717,33 +1936,160 @@ impl EpubGenerator {
     }
 
     /// Generate chapter XHTML files
-    async fn generate_chapter_files(&self, oebps_dir: &Path, package: &EpubPackage) -> AppResult<()> {
-        let xhtml_dir = oebps_dir.join("xhtml");
-        fs::create_dir_all(&xhtml_dir)?;
+    async fn generate_chapter_files(&self, scratch: &ScratchDir, package: &EpubPackage) -> AppResult<()> {
+        for (index, chapter) in package.chapters.iter().enumerate() {
+            let chapter_xhtml = self.generate_chapter_xhtml(chapter, package);
+            scratch.write(format!("OEBPS/xhtml/chapter_{}.xhtml", index + 1), chapter_xhtml)?;
+        }
         
-        for (index, item) in package.spine.iter().enumerate() {
-            let chapter_xhtml = format!(
-                r#"<?xml version="1.0" encoding="utf-8"?>
+        Ok(())
+    }
+
+    /// Render one chapter as an XHTML document
+    fn generate_chapter_xhtml(&self, chapter: &EpubChapter, package: &EpubPackage) -> String {
+        let mut body = String::new();
+        for content in &chapter.content {
+            self.render_epub_content(&mut body, content, package, 1);
+        }
+
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
 <!DOCTYPE html>
-<html xmlns="http://www.w3.org/1999/xhtml">
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
 <head>
-    <title>Chapter {}</title>
+    <title>{}</title>
     <link rel="stylesheet" type="text/css" href="../styles/main.css"/>
 </head>
 <body>
-    <h1>Chapter {}</h1>
-    <p>This is a generated chapter {} content would go here.</p>
-</body>
+{}</body>
 </html>"#,
-                index + 1,
-                index + 1,
-                index + 1
-            );
-            
-            fs::write(xhtml_dir.join(format!("chapter_{}.xhtml", index + 1)), chapter_xhtml)?;
+            escape_xml(&chapter.title),
+            body
+        )
+    }
+
+    /// Append the XHTML for one content element at the given indent depth
+    fn render_epub_content(&self, out: &mut String, content: &EpubContent, package: &EpubPackage, depth: usize) {
+        let indent = "    ".repeat(depth);
+        let attr = |name: &str, value: &Option<String>| {
+            value.as_ref()
+                .map(|v| format!(" {}=\"{}\"", name, escape_xml(v)))
+                .unwrap_or_default()
+        };
+
+        match content {
+            EpubContent::Heading { level, text, id } => {
+                let level = (*level).clamp(1, 6);
+                out.push_str(&format!(
+                    "{}<h{}{}>{}</h{}>\n",
+                    indent, level, attr("id", id), escape_xml(text), level
+                ));
+            }
+            EpubContent::Paragraph { text, class, id } => {
+                out.push_str(&format!(
+                    "{}<p{}{}>{}</p>\n",
+                    indent, attr("id", id), attr("class", class), escape_xml(text)
+                ));
+            }
+            EpubContent::Image { src, alt, width, height, class, id } => {
+                // Chapters live in xhtml/, images are packaged beside it
+                let href = package.image_hrefs.get(src).unwrap_or(src);
+                let mut size = String::new();
+                if let Some(width) = width {
+                    size.push_str(&format!(" width=\"{}\"", width));
+                }
+                if let Some(height) = height {
+                    size.push_str(&format!(" height=\"{}\"", height));
+                }
+                out.push_str(&format!(
+                    "{}<div class=\"image\"{}><img src=\"../{}\" alt=\"{}\"{}{}/></div>\n",
+                    indent, attr("id", id), escape_xml(href), escape_xml(alt), size, attr("class", class)
+                ));
+            }
+            EpubContent::Link { href, text, type_, class } => {
+                out.push_str(&format!(
+                    "{}<p><a href=\"{}\"{}{}>{}</a></p>\n",
+                    indent, escape_xml(href), attr("type", type_), attr("class", class), escape_xml(text)
+                ));
+            }
+            EpubContent::List { ordered, items, class } => {
+                let tag = if *ordered { "ol" } else { "ul" };
+                out.push_str(&format!("{}<{}{}>\n", indent, tag, attr("class", class)));
+                for item in items {
+                    match item.content.as_slice() {
+                        [EpubContent::Paragraph { text, .. }] => {
+                            out.push_str(&format!(
+                                "{}    <li{}>{}</li>\n",
+                                indent, attr("id", &item.id), escape_xml(text)
+                            ));
+                        }
+                        contents => {
+                            out.push_str(&format!("{}    <li{}>\n", indent, attr("id", &item.id)));
+                            for content in contents {
+                                self.render_epub_content(out, content, package, depth + 2);
+                            }
+                            out.push_str(&format!("{}    </li>\n", indent));
+                        }
+                    }
+                }
+                out.push_str(&format!("{}</{}>\n", indent, tag));
+            }
+            EpubContent::Table { summary, headers, rows, class } => {
+                out.push_str(&format!("{}<table{}>\n", indent, attr("class", class)));
+                if let Some(summary) = summary {
+                    out.push_str(&format!("{}    <caption>{}</caption>\n", indent, escape_xml(summary)));
+                }
+                if !headers.is_empty() {
+                    out.push_str(&format!("{}    <thead><tr>", indent));
+                    for header in headers {
+                        out.push_str(&format!("<th>{}</th>", escape_xml(header)));
+                    }
+                    out.push_str("</tr></thead>\n");
+                }
+                out.push_str(&format!("{}    <tbody>\n", indent));
+                for row in rows {
+                    out.push_str(&format!("{}        <tr>", indent));
+                    for cell in row {
+                        out.push_str(&format!("<td>{}</td>", escape_xml(cell)));
+                    }
+                    out.push_str("</tr>\n");
+                }
+                out.push_str(&format!("{}    </tbody>\n{}</table>\n", indent, indent));
+            }
+            EpubContent::Note { type_, content, backref } => {
+                let (epub_type, class) = match type_ {
+                    NoteType::Footnote => ("footnote", "footnote"),
+                    NoteType::Endnote => ("endnote", "endnote"),
+                    NoteType::Citation => ("note", "citation"),
+                    NoteType::Definition => ("note", "definition"),
+                    NoteType::Explanation => ("note", "explanation"),
+                };
+                let back = backref.as_ref()
+                    .map(|target| format!(" <a href=\"#{}\">&#8617;</a>", escape_xml(target)))
+                    .unwrap_or_default();
+                out.push_str(&format!(
+                    "{}<aside epub:type=\"{}\" class=\"{}\"><p>{}{}</p></aside>\n",
+                    indent, epub_type, class, escape_xml(content), back
+                ));
+            }
+            EpubContent::Callout { type_, number, content, target } => {
+                let label = match type_ {
+                    CalloutType::Figure => "Figure",
+                    CalloutType::Table => "Table",
+                    CalloutType::Code => "Listing",
+                    CalloutType::Equation => "Equation",
+                    CalloutType::Reference => "Reference",
+                };
+                let label = match target {
+                    Some(target) => format!("<a href=\"#{}\">{} {}</a>", escape_xml(target), label, number),
+                    None => format!("{} {}", label, number),
+                };
+                out.push_str(&format!(
+                    "{}<p class=\"callout\">{}: {}</p>\n",
+                    indent, label, escape_xml(content)
+                ));
+            }
         }
-        
-        Ok(())
     }
 
     /// Create zip archive
@@ -1797,36 +2143,95 @@ impl EpubGenerator {
         Ok(())
     }
 
-    /// Validate generated ePub file
-    async fn validate_epub_file(&self, _file_path: &Path, _version: EpubVersion) -> AppResult<()> {
-        // In a real implementation, this would use ePub validation tools
-        // or validate against ePub specifications
-        Ok(())
+    /// Validate generated ePub file; structural errors fail the export,
+    /// warnings are logged and returned with the report
+    async fn validate_epub_file(&self, file_path: &Path, version: EpubVersion) -> AppResult<epub_check::EpubCheckReport> {
+        let path = file_path.to_path_buf();
+        let report = tokio::task::spawn_blocking(move || epub_check::check_epub_file(&path, version))
+            .await
+            .map_err(|e| AppError::ValidationError(format!("ePub validation did not finish: {}", e)))??;
+
+        for issue in report.warnings() {
+            log::warn!(
+                "ePub check [{}] {}{}",
+                issue.code,
+                issue.location.as_deref().map(|l| format!("{}: ", l)).unwrap_or_default(),
+                issue.message
+            );
+        }
+        if !report.is_valid() {
+            return Err(AppError::ValidationError(format!(
+                "Generated ePub failed validation:\n{}",
+                report.error_summary()
+            )));
+        }
+        Ok(report)
     }
 
     /// Process asset path for ePub
-    async fn process_asset_path(&self, _path: &Path) -> AppResult<String> {
-        // Process and normalize asset paths for ePub
-        Ok("images/processed_image.jpg".to_string())
-    }
-
     /// Update job status
     async fn update_job_status(&self, job_id: &str, status: ExportStatus, progress: f32) {
-        let mut jobs = self.export_jobs.write().await;
-        if let Some(job) = jobs.get_mut(job_id) {
+        let snapshot = {
+            let mut jobs = self.export_jobs.write().await;
+            let Some(job) = jobs.get_mut(job_id) else { return };
+            // A cancelled job stays cancelled
+            if job.status == ExportStatus::Cancelled {
+                return;
+            }
             job.status = status;
             job.progress = progress;
             if matches!(status, ExportStatus::Processing) && job.started_at.is_none() {
                 job.started_at = Some(Utc::now());
             }
-        }
+            self.publish_job(job);
+            job.clone()
+        };
+        self.persist_job(&snapshot).await;
     }
 
     /// Update job progress
     async fn update_job_progress(&self, job_id: &str, increment: f32) {
         let mut jobs = self.export_jobs.write().await;
         if let Some(job) = jobs.get_mut(job_id) {
+            let before = job.progress;
             job.progress = (job.progress + increment).min(1.0);
+            // Per-element increments are tiny; push once per whole percent
+            if (before * 100.0) as u32 != (job.progress * 100.0) as u32 {
+                self.publish_job(job);
+            }
+        }
+    }
+
+    /// Mark a job failed and push the error to the frontend
+    async fn fail_job(&self, job_id: &str, error_message: String) {
+        let snapshot = {
+            let mut jobs = self.export_jobs.write().await;
+            let Some(job) = jobs.get_mut(job_id) else { return };
+            if job.status == ExportStatus::Cancelled {
+                return;
+            }
+            job.status = ExportStatus::Failed;
+            job.completed_at = Some(Utc::now());
+            job.error_message = Some(error_message);
+            self.publish_job(job);
+            job.clone()
+        };
+        self.persist_job(&snapshot).await;
+    }
+
+    /// Save a job's current state; export carries on if the database write fails
+    async fn persist_job(&self, job: &ExportJob) {
+        if let Some(repository) = &self.repository {
+            if let Err(e) = repository.read().await.save_job(&ExportJobRecord::from(job)).await {
+                log::warn!("Failed to record export job {}: {}", job.job_id, e);
+            }
+        }
+    }
+
+    /// Push a job's current state as an `export_job` event
+    fn publish_job(&self, job: &ExportJob) {
+        if let Some(events) = &self.events {
+            events.push("export_job", &ExportJobEvent::from(job));
         }
     }
 
@@ -1848,20 +2253,42 @@ impl EpubGenerator {
         jobs.values().cloned().collect()
     }
 
-    /// Cancel export job
+    /// Cancel export job, taking it off the queue or stopping it if it
+    /// is already running
     pub async fn cancel_job(&self, job_id: &str) -> AppResult<()> {
-        self.update_job_status(job_id, ExportStatus::Cancelled, 1.0).await;
+        let status = self.get_job_status(job_id).await?.status;
+        if matches!(status, ExportStatus::Completed | ExportStatus::Failed | ExportStatus::Cancelled) {
+            return Err(AppError::ExportError(
+                format!("Job {} has already finished", job_id)
+            ));
+        }
+
+        // Mark it first so nothing the job does before it stops can
+        // overwrite the status
+        let snapshot = {
+            let mut jobs = self.export_jobs.write().await;
+            let Some(job) = jobs.get_mut(job_id) else { return Ok(()) };
+            job.status = ExportStatus::Cancelled;
+            job.completed_at = Some(Utc::now());
+            self.publish_job(job);
+            job.clone()
+        };
+        self.scheduler.cancel(job_id);
+        self.persist_job(&snapshot).await;
         Ok(())
     }
+
+    /// Where a job stands in the export queue; `None` once it has finished
+    pub fn scheduled_state(&self, job_id: &str) -> Option<scheduler::ScheduledState> {
+        self.scheduler.state(job_id)
+    }
 }
 
 /// Implementation of Asset Manager
 impl AssetManager {
     pub fn new() -> Self {
-        let (sender, _) = tokio::sync::mpsc::unbounded_channel();
         Self {
-            asset_cache: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
-            processing_queue: Arc::new(sender),
+            processing_queue: OptimizerQueue::global(),
         }
     }
 
@@ -1872,29 +2299,59 @@ impl AssetManager {
         settings: OptimizationSettings,
     ) -> AppResult<AssetData> {
         let asset_id = Uuid::new_v4().to_string();
-        let file_name = source_path.file_name()
+        let mut media_type = self.determine_media_type(source_path);
+        let source = fs::read(source_path)?;
+
+        // Raster images are resized and recompressed; GIFs are left alone so
+        // animations survive, as are SVGs, fonts and everything else
+        let extension = source_path.extension()
             .unwrap_or_default()
             .to_string_lossy()
             .to_string();
-        
-        let media_type = self.determine_media_type(source_path);
-        let file_size = fs::metadata(source_path)?.len();
-        
-        // Read and process file content
-        let processed_data = fs::read(source_path)?;
+        let processed_data = if OutputFormat::from_extension(&extension).is_some() {
+            let optimized = self.processing_queue
+                .optimize(source, Self::optimize_options(&format, &settings))
+                .await
+                .map_err(|e| AppError::ExportError(
+                    format!("Failed to optimize {}: {}", source_path.display(), e)
+                ))?;
+            media_type = optimized.format.media_type().to_string();
+            optimized.data
+        } else {
+            source
+        };
         let checksum = self.calculate_checksum(&processed_data);
-        
+
         Ok(AssetData {
             asset_id,
             file_path: source_path.to_path_buf(),
             asset_type: self.determine_asset_type(source_path),
+            size_bytes: processed_data.len() as u64,
             processed_data,
             media_type,
-            size_bytes: file_size,
             checksum,
         })
     }
 
+    fn optimize_options(format: &AssetFormat, settings: &OptimizationSettings) -> OptimizeOptions {
+        let mut options = OptimizeOptions {
+            max_width: settings.max_width,
+            max_height: settings.max_height,
+            quality: (settings.quality.clamp(0.01, 1.0) * 100.0).round() as u8,
+            compression_level: settings.compression_level,
+            strip_metadata: settings.remove_metadata,
+            format: None,
+        };
+        match format {
+            AssetFormat::Optimized | AssetFormat::Resized => {}
+            AssetFormat::Compressed => options.compression_level = 9,
+            AssetFormat::Converted(extension) => {
+                options.format = OutputFormat::from_extension(extension);
+            }
+        }
+        options
+    }
+
     fn determine_media_type(&self, path: &Path) -> String {
         let extension = path.extension()
             .unwrap_or_default()
@@ -1905,6 +2362,7 @@ impl AssetManager {
             "jpg" | "jpeg" => "image/jpeg".to_string(),
             "png" => "image/png".to_string(),
             "gif" => "image/gif".to_string(),
+            "webp" => "image/webp".to_string(),
             "svg" => "image/svg+xml".to_string(),
             "ttf" => "font/ttf".to_string(),
             "otf" => "font/otf".to_string(),
@@ -1923,7 +2381,7 @@ impl AssetManager {
             .to_lowercase();
         
         match extension.as_str() {
-            "jpg" | "jpeg" | "png" | "gif" | "svg" => AssetType::Image,
+            "jpg" | "jpeg" | "png" | "gif" | "webp" | "svg" => AssetType::Image,
             "ttf" | "otf" | "woff" | "woff2" => AssetType::Font,
             "mp3" | "m4a" | "aac" => AssetType::Audio,
             "mp4" | "m4v" | "mov" => AssetType::Video,
@@ -2061,6 +2519,11 @@ impl Clone for EpubGenerator {
             export_jobs: self.export_jobs.clone(),
             asset_manager: self.asset_manager.clone(),
             metadata_validator: self.metadata_validator.clone(),
+            events: self.events.clone(),
+            repository: self.repository.clone(),
+            scheduler: self.scheduler.clone(),
+            scratch: self.scratch.clone(),
+            automation: self.automation.clone(),
         }
     }
 }
@@ -2144,6 +2607,78 @@ pub struct PdfMetadata {
     pub modification_date: DateTime<Utc>,
 }
 
+impl PdfExportConfig {
+    /// Header for a page with placeholders expanded, if headers are on
+    pub fn header_for_page(&self, context: &TemplateContext, page_number: usize, page_count: usize) -> Option<String> {
+        let template = self.header_content.as_deref().filter(|_| self.enable_headers)?;
+        Some(render(template, &context.for_page(page_number, page_count)))
+    }
+
+    /// Footer for a page with placeholders expanded, if footers are on
+    pub fn footer_for_page(&self, context: &TemplateContext, page_number: usize, page_count: usize) -> Option<String> {
+        let template = self.footer_content.as_deref().filter(|_| self.enable_footers)?;
+        Some(render(template, &context.for_page(page_number, page_count)))
+    }
+
+    /// Watermark with placeholders expanded
+    pub fn watermark_text(&self, context: &TemplateContext) -> Option<String> {
+        self.watermark.as_deref().map(|template| render(template, context))
+    }
+}
+
+impl HeaderFooterConfig {
+    /// Header for a page with placeholders expanded; none on the first
+    /// page when it is set to differ
+    pub fn header_for_page(&self, context: &TemplateContext, page_number: usize, page_count: usize) -> Option<String> {
+        if self.first_page_different && page_number == 1 {
+            return None;
+        }
+        let template = self.header_template.as_deref()?;
+        Some(render(template, &context.for_page(page_number, page_count)))
+    }
+
+    /// Footer for a page with placeholders expanded; none on the first
+    /// page when it is set to differ
+    pub fn footer_for_page(&self, context: &TemplateContext, page_number: usize, page_count: usize) -> Option<String> {
+        if self.first_page_different && page_number == 1 {
+            return None;
+        }
+        let template = self.footer_template.as_deref()?;
+        Some(render(template, &context.for_page(page_number, page_count)))
+    }
+}
+
+impl CoverPage {
+    /// Placeholders for this cover's own fields, on top of `context`
+    pub fn template_context(&self, context: &TemplateContext) -> TemplateContext {
+        let context = context
+            .clone()
+            .with_document(&self.title, self.subtitle.as_deref(), self.author.as_deref())
+            .with_optional("organization", self.organization.clone());
+        match &self.date {
+            Some(date) => context.with_date(&date.with_timezone(&chrono::Local)),
+            None => context,
+        }
+    }
+
+    /// The cover with placeholders in its text, and in a custom layout's
+    /// template, expanded
+    pub fn resolve(&self, context: &TemplateContext) -> CoverPage {
+        let context = self.template_context(context);
+        let mut cover = self.clone();
+        cover.title = render(&self.title, &context);
+        cover.subtitle = self.subtitle.as_deref().map(|s| render(s, &context));
+        cover.author = self.author.as_deref().map(|s| render(s, &context));
+        cover.organization = self.organization.as_deref().map(|s| render(s, &context));
+        if let CoverLayout::Custom { custom_template } = &self.style.layout {
+            cover.style.layout = CoverLayout::Custom {
+                custom_template: render(custom_template, &context),
+            };
+        }
+        cover
+    }
+}
+
 // Default implementations
 impl PdfPage {
     pub fn new() -> Self {
@@ -2361,28 +2896,57 @@ impl FontManager {
 // Image processor implementation
 impl ImageProcessor {
     pub fn new() -> Self {
-        let (sender, _) = tokio::sync::mpsc::unbounded_channel();
         Self {
-            image_cache: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
-            processing_queue: Arc::new(sender),
+            processing_queue: OptimizerQueue::global(),
         }
     }
 
     pub async fn process_image(
         &self,
-        _source_path: &Path,
-        _target_format: ImageFormat,
-        _quality_settings: ImageQualitySettings,
+        source_path: &Path,
+        target_format: ImageFormat,
+        quality_settings: ImageQualitySettings,
     ) -> AppResult<ProcessedImage> {
-        // Placeholder implementation
+        let output = match &target_format {
+            ImageFormat::JPEG => OutputFormat::Jpeg,
+            ImageFormat::PNG => OutputFormat::Png,
+            ImageFormat::Custom(extension) => OutputFormat::from_extension(extension)
+                .ok_or_else(|| AppError::ExportError(
+                    format!("Cannot write images as {}", extension)
+                ))?,
+            other => return Err(AppError::ExportError(
+                format!("Cannot write images as {:?}", other)
+            )),
+        };
+        let quality = quality_settings.quality_factor.clamp(0.01, 1.0);
+        let options = OptimizeOptions {
+            max_width: quality_settings.max_width,
+            max_height: quality_settings.max_height,
+            quality: (quality * 100.0).round() as u8,
+            compression_level: match quality_settings.compression_algorithm {
+                ImageCompression::Lossless => 9,
+                _ => 6,
+            },
+            strip_metadata: true,
+            format: Some(output),
+        };
+
+        let source = fs::read(source_path)?;
+        let optimized = self.processing_queue
+            .optimize(source, options)
+            .await
+            .map_err(|e| AppError::ExportError(
+                format!("Failed to process {}: {}", source_path.display(), e)
+            ))?;
+
         Ok(ProcessedImage {
-            original_path: PathBuf::from("placeholder"),
-            processed_data: Vec::new(),
-            format: ImageFormat::PNG,
-            width: 800,
-            height: 600,
-            quality_score: 0.95,
-            compression_ratio: 0.8,
+            original_path: source_path.to_path_buf(),
+            compression_ratio: optimized.compression_ratio(),
+            quality_score: if output == OutputFormat::Jpeg { quality } else { 1.0 },
+            width: optimized.width,
+            height: optimized.height,
+            processed_data: optimized.data,
+            format: target_format,
         })
     }
 }
@@ -2398,4 +2962,132 @@ impl Clone for PdfGenerator {
             image_processor: self.image_processor.clone(),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(image_hrefs: &[(&str, &str)]) -> EpubPackage {
+        EpubPackage {
+            version: EpubVersion::V3,
+            identifier: "urn:uuid:1234".to_string(),
+            metadata: EpubMetadata {
+                title: "The Salt Road".to_string(),
+                creator: "A. Writer".to_string(),
+                language: "en".to_string(),
+                identifier: "urn:uuid:1234".to_string(),
+                publisher: None,
+                publication_date: None,
+                rights: None,
+                subject: Vec::new(),
+                description: None,
+                contributor: Vec::new(),
+                coverage: None,
+                relation: Vec::new(),
+                source: None,
+                type_: None,
+                format: None,
+                source_identifier: None,
+                isbn: None,
+                unique_identifier: "urn:uuid:1234".to_string(),
+            },
+            manifest: HashMap::new(),
+            spine: Vec::new(),
+            guide: None,
+            bindings: None,
+            chapters: Vec::new(),
+            assets: Vec::new(),
+            image_hrefs: image_hrefs
+                .iter()
+                .map(|(src, href)| (src.to_string(), href.to_string()))
+                .collect(),
+        }
+    }
+
+    fn paragraph(text: &str) -> EpubContent {
+        EpubContent::Paragraph { text: text.to_string(), class: None, id: None }
+    }
+
+    fn image(src: &str, alt: &str, width: Option<u32>) -> EpubContent {
+        EpubContent::Image {
+            src: src.to_string(),
+            alt: alt.to_string(),
+            width,
+            height: None,
+            class: None,
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_chapter_xhtml_escapes_text_and_renders_headings_and_images() {
+        let generator = EpubGenerator::new();
+        let package = package(&[("images/map.png", "images/img_1.png")]);
+        let chapter = EpubChapter {
+            chapter_id: "chapter_1".to_string(),
+            title: "Salt & Sea".to_string(),
+            content: vec![
+                EpubContent::Heading { level: 2, text: "Tom & Jerry <3".to_string(), id: Some("c1".to_string()) },
+                EpubContent::Heading { level: 9, text: "Deep".to_string(), id: None },
+                paragraph("She said \"no\" & left."),
+                image("images/map.png", "Map of <the> coast", Some(400)),
+                image("art/gull.jpg", "Gull", None),
+            ],
+            navigation: None,
+            landmarks: Vec::new(),
+        };
+
+        let xhtml = generator.generate_chapter_xhtml(&chapter, &package);
+        assert!(xhtml.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>"));
+        assert!(xhtml.contains("<title>Salt &amp; Sea</title>"));
+        assert!(xhtml.contains("    <h2 id=\"c1\">Tom &amp; Jerry &lt;3</h2>\n"));
+        assert!(xhtml.contains("    <h6>Deep</h6>\n"));
+        assert!(xhtml.contains("    <p>She said &quot;no&quot; &amp; left.</p>\n"));
+        // Packaged images point at their href in OEBPS; others keep their src
+        assert!(xhtml.contains(
+            "<img src=\"../images/img_1.png\" alt=\"Map of &lt;the&gt; coast\" width=\"400\"/>"
+        ));
+        assert!(xhtml.contains("<img src=\"../art/gull.jpg\" alt=\"Gull\"/>"));
+        assert!(!xhtml.contains("<3"));
+        assert!(xhtml.ends_with("</body>\n</html>"));
+    }
+
+    #[test]
+    fn test_render_epub_content_nests_lists() {
+        let generator = EpubGenerator::new();
+        let package = package(&[]);
+        let list = EpubContent::List {
+            ordered: true,
+            items: vec![
+                EpubListItem { content: vec![paragraph("First & foremost")], id: Some("i1".to_string()) },
+                EpubListItem {
+                    content: vec![
+                        paragraph("Second"),
+                        EpubContent::List {
+                            ordered: false,
+                            items: vec![EpubListItem { content: vec![paragraph("Nested")], id: None }],
+                            class: Some("loose".to_string()),
+                        },
+                    ],
+                    id: None,
+                },
+            ],
+            class: None,
+        };
+
+        let mut out = String::new();
+        generator.render_epub_content(&mut out, &list, &package, 1);
+        assert_eq!(
+            out,
+            "    <ol>\n\
+             \x20       <li id=\"i1\">First &amp; foremost</li>\n\
+             \x20       <li>\n\
+             \x20           <p>Second</p>\n\
+             \x20           <ul class=\"loose\">\n\
+             \x20               <li>Nested</li>\n\
+             \x20           </ul>\n\
+             \x20       </li>\n\
+             \x20   </ol>\n"
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Export Job Scheduling
+//!
+//! Export jobs wait in a priority queue and only a configurable number run
+//! at once; the rest start as running ones finish. Each job gets a
+//! cancellation token, and cancelling a running job drops its work at the
+//! next await point instead of letting it finish in the background.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+/// Exports run at once unless configured otherwise
+pub const DEFAULT_MAX_CONCURRENT_EXPORTS: usize = 2;
+
+type Work = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Which queued job starts first; jobs of equal priority start in the
+/// order they were submitted
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Where a job stands in the scheduler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduledState {
+    /// Waiting; 0 starts next
+    Queued {
+        position: usize,
+    },
+    Running,
+}
+
+struct Queued {
+    job_id: String,
+    priority: ExportPriority,
+    sequence: u64,
+    token: CancellationToken,
+    work: Work,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    /// Higher priority first, then earlier submissions
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+struct State {
+    max_concurrent: usize,
+    next_sequence: u64,
+    queued: BinaryHeap<Queued>,
+    running: HashMap<String, CancellationToken>,
+}
+
+/// Queue that runs export jobs with limited parallelism
+#[derive(Clone)]
+pub struct ExportScheduler {
+    state: Arc<Mutex<State>>,
+}
+
+impl ExportScheduler {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                max_concurrent: max_concurrent.max(1),
+                next_sequence: 0,
+                queued: BinaryHeap::new(),
+                running: HashMap::new(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // The state stays consistent even if a holder panicked
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue a job. `work` is given the job's cancellation token and is not
+    /// called until the job starts; once cancelled, its future is dropped.
+    pub fn submit<F, Fut>(&self, job_id: impl Into<String>, priority: ExportPriority, work: F)
+    where
+        F: FnOnce(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let token = CancellationToken::new();
+        let child = token.clone();
+        let work: Work = Box::pin(async move { work(child).await });
+        {
+            let mut state = self.lock();
+            let sequence = state.next_sequence;
+            state.next_sequence += 1;
+            state.queued.push(Queued {
+                job_id: job_id.into(),
+                priority,
+                sequence,
+                token,
+                work,
+            });
+        }
+        self.start_ready();
+    }
+
+    /// Cancel a queued or running job. Returns false if the scheduler
+    /// doesn't know the job, e.g. because it already finished.
+    pub fn cancel(&self, job_id: &str) -> bool {
+        let mut state = self.lock();
+        if let Some(token) = state.running.get(job_id) {
+            token.cancel();
+            return true;
+        }
+        let before = state.queued.len();
+        state.queued.retain(|job| job.job_id != job_id);
+        state.queued.len() != before
+    }
+
+    /// Change how many jobs may run at once; queued jobs start right away
+    /// if the limit went up, and running ones are left to finish if it
+    /// went down
+    pub fn set_max_concurrent(&self, max_concurrent: usize) {
+        self.lock().max_concurrent = max_concurrent.max(1);
+        self.start_ready();
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.lock().max_concurrent
+    }
+
+    pub fn running_len(&self) -> usize {
+        self.lock().running.len()
+    }
+
+    pub fn queued_len(&self) -> usize {
+        self.lock().queued.len()
+    }
+
+    pub fn state(&self, job_id: &str) -> Option<ScheduledState> {
+        let state = self.lock();
+        if state.running.contains_key(job_id) {
+            return Some(ScheduledState::Running);
+        }
+        let mut queued: Vec<&Queued> = state.queued.iter().collect();
+        queued.sort_by(|a, b| b.cmp(a));
+        queued
+            .iter()
+            .position(|job| job.job_id == job_id)
+            .map(|position| ScheduledState::Queued { position })
+    }
+
+    /// Start queued jobs while there is room
+    fn start_ready(&self) {
+        let mut state = self.lock();
+        while state.running.len() < state.max_concurrent {
+            let Some(job) = state.queued.pop() else {
+                break;
+            };
+            state.running.insert(job.job_id.clone(), job.token.clone());
+
+            let scheduler = self.clone();
+            tokio::spawn(async move {
+                tokio::select! {
+                    biased;
+                    _ = job.token.cancelled() => {
+                        log::info!("Export job {} cancelled", job.job_id);
+                    }
+                    _ = job.work => {}
+                }
+                scheduler.lock().running.remove(&job.job_id);
+                scheduler.start_ready();
+            });
+        }
+    }
+}
+
+impl Default for ExportScheduler {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT_EXPORTS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::{mpsc, oneshot};
+
+    #[tokio::test]
+    async fn runs_by_priority_within_the_limit() {
+        let scheduler = ExportScheduler::new(1);
+        let (started, mut starts) = mpsc::unbounded_channel();
+
+        // Holds the only slot until released
+        let (release, released) = oneshot::channel::<()>();
+        let first = started.clone();
+        scheduler.submit("first", ExportPriority::Normal, move |_| async move {
+            first.send("first").unwrap();
+            let _ = released.await;
+        });
+        for (job_id, priority) in [
+            ("low", ExportPriority::Low),
+            ("normal", ExportPriority::Normal),
+            ("high", ExportPriority::High),
+        ] {
+            let started = started.clone();
+            scheduler.submit(job_id, priority, move |_| async move {
+                started.send(job_id).unwrap();
+            });
+        }
+
+        assert_eq!(starts.recv().await, Some("first"));
+        assert_eq!(scheduler.running_len(), 1);
+        assert_eq!(
+            scheduler.state("high"),
+            Some(ScheduledState::Queued { position: 0 })
+        );
+        assert!(scheduler.cancel("normal"));
+        assert_eq!(scheduler.queued_len(), 2);
+
+        release.send(()).unwrap();
+        assert_eq!(starts.recv().await, Some("high"));
+        assert_eq!(starts.recv().await, Some("low"));
+    }
+
+    #[tokio::test]
+    async fn cancelling_drops_running_work() {
+        let scheduler = ExportScheduler::new(2);
+        let (finished, mut finishes) = mpsc::unbounded_channel::<&str>();
+
+        scheduler.submit("slow", ExportPriority::Normal, move |_| async move {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            finished.send("slow").unwrap();
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(scheduler.state("slow"), Some(ScheduledState::Running));
+
+        assert!(scheduler.cancel("slow"));
+        // The work's sender is dropped with it, closing the channel
+        assert_eq!(finishes.recv().await, None);
+        while scheduler.running_len() > 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(!scheduler.cancel("slow"));
+    }
+}
@@ -0,0 +1,227 @@
+//! Placeholder Templates
+//!
+//! Expands `{{placeholder}}` strings in headers, footers, cover pages and
+//! watermarks. Values come from the document's metadata, the project, the
+//! export date, the page being laid out and variables the user defines.
+//!
+//! - `{{title}}`, `{{project.name}}`, `{{client}}`: a value by name
+//! - `{{date}}`, `{{date:%Y-%m-%d}}`: the export date, optionally with a
+//!   strftime format; `{{time}}` and `{{year}}` likewise
+//! - `{{page_number}}`, `{{page_count}}`: the page being laid out
+//! - `{{subtitle|Draft}}`: falls back to the text after `|` when unset
+//!
+//! Unknown placeholders are left in the output as written so a typo shows
+//! up in the proof rather than vanishing.
+
+use std::collections::HashMap;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, NaiveDateTime, TimeZone};
+
+/// Format of a bare `{{date}}`
+const DEFAULT_DATE_FORMAT: &str = "%B %-d, %Y";
+/// Format of a bare `{{time}}`
+const DEFAULT_TIME_FORMAT: &str = "%H:%M";
+
+/// The values placeholders resolve to
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    values: HashMap<String, String>,
+    date: Option<NaiveDateTime>,
+    page: Option<(usize, usize)>,
+}
+
+impl TemplateContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a value; later values replace earlier ones of the same name
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.values.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set a value when there is one
+    pub fn with_optional(self, name: impl Into<String>, value: Option<impl Into<String>>) -> Self {
+        match value {
+            Some(value) => self.with(name, value),
+            None => self,
+        }
+    }
+
+    /// Document title, subtitle and author, also under `document.`
+    pub fn with_document(self, title: &str, subtitle: Option<&str>, author: Option<&str>) -> Self {
+        self.with("title", title)
+            .with("document.title", title)
+            .with_optional("subtitle", subtitle)
+            .with_optional("document.subtitle", subtitle)
+            .with_optional("author", author)
+            .with_optional("document.author", author)
+    }
+
+    /// Project name and author, under `project.`
+    pub fn with_project(self, name: &str, author: Option<&str>) -> Self {
+        self.with("project.name", name)
+            .with_optional("project.author", author)
+    }
+
+    /// User-defined variables, by name
+    pub fn with_variables<'a>(
+        mut self,
+        variables: impl IntoIterator<Item = (&'a String, &'a String)>,
+    ) -> Self {
+        for (name, value) in variables {
+            self.values.insert(name.clone(), value.clone());
+        }
+        self
+    }
+
+    /// The export date, in the time zone it should be shown in
+    pub fn with_date<Tz: TimeZone>(mut self, date: &DateTime<Tz>) -> Self {
+        self.date = Some(date.naive_local());
+        self
+    }
+
+    /// The page being laid out, counted from 1
+    pub fn for_page(&self, page_number: usize, page_count: usize) -> Self {
+        let mut context = self.clone();
+        context.page = Some((page_number, page_count));
+        context
+    }
+
+    /// The value of one placeholder, without braces
+    pub fn resolve(&self, placeholder: &str) -> Option<String> {
+        let (name, format) = match placeholder.split_once(':') {
+            Some((name, format)) => (name.trim(), Some(format)),
+            None => (placeholder.trim(), None),
+        };
+        match name {
+            "date" => self.format_date(format.unwrap_or(DEFAULT_DATE_FORMAT)),
+            "time" => self.format_date(format.unwrap_or(DEFAULT_TIME_FORMAT)),
+            "year" => self.date.map(|date| date.year().to_string()),
+            "page_number" => self.page.map(|(number, _)| number.to_string()),
+            "page_count" => self.page.map(|(_, count)| count.to_string()),
+            _ if format.is_none() => self.values.get(name).cloned(),
+            _ => None,
+        }
+    }
+
+    fn format_date(&self, format: &str) -> Option<String> {
+        let date = self.date?;
+        // An unknown specifier would make chrono panic while formatting
+        let items: Vec<Item> = StrftimeItems::new(format).collect();
+        if items.iter().any(|item| matches!(item, Item::Error)) {
+            return None;
+        }
+        Some(date.format_with_items(items.into_iter()).to_string())
+    }
+}
+
+/// Expand every placeholder in `template`
+pub fn render(template: &str, context: &TemplateContext) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            // Unclosed; keep the remainder as it is
+            output.push_str(&rest[start..]);
+            return output;
+        };
+        let placeholder = &after[..end];
+        let (expression, fallback) = match placeholder.split_once('|') {
+            Some((expression, fallback)) => (expression, Some(fallback.trim())),
+            None => (placeholder, None),
+        };
+        match context
+            .resolve(expression)
+            .filter(|value| !value.is_empty())
+        {
+            Some(value) => output.push_str(&value),
+            None => match fallback {
+                Some(fallback) => output.push_str(fallback),
+                None => output.push_str(&rest[start..start + 2 + end + 2]),
+            },
+        }
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Placeholders in `template` that `context` can't resolve and that have
+/// no fallback, for warning about before export
+pub fn unresolved(template: &str, context: &TemplateContext) -> Vec<String> {
+    let mut missing = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else { break };
+        let placeholder = &after[..end];
+        if !placeholder.contains('|') && context.resolve(placeholder).is_none() {
+            missing.push(placeholder.trim().to_string());
+        }
+        rest = &after[end + 2..];
+    }
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    #[test]
+    fn test_render_resolves_metadata_dates_pages_and_variables() {
+        let exported = Utc.with_ymd_and_hms(2026, 10, 18, 22, 30, 0).unwrap();
+        let variables: HashMap<String, String> =
+            [("client".to_string(), "Larkspur Press".to_string())]
+                .into_iter()
+                .collect();
+        let context = TemplateContext::new()
+            .with_document("The Salt Road", None, Some("R. Takami"))
+            .with_project("Salt Road Trilogy", None)
+            .with_variables(&variables)
+            .with_date(&exported.with_timezone(&FixedOffset::east_opt(9 * 3600).unwrap()));
+
+        let page = context.for_page(3, 12);
+        assert_eq!(
+            render("{{title}} — {{ author }}", &page),
+            "The Salt Road — R. Takami"
+        );
+        assert_eq!(
+            render("Page {{page_number}} of {{page_count}}", &page),
+            "Page 3 of 12"
+        );
+        assert_eq!(
+            render("{{project.name}}, {{date}}", &page),
+            "Salt Road Trilogy, October 19, 2026"
+        );
+        assert_eq!(
+            render("{{date:%Y-%m-%d}} {{time}} ©{{year}}", &page),
+            "2026-10-19 07:30 ©2026"
+        );
+        assert_eq!(
+            render("Prepared for {{client}}", &page),
+            "Prepared for Larkspur Press"
+        );
+        assert_eq!(render("{{subtitle|A Novel}}", &page), "A Novel");
+        assert_eq!(
+            render("{{clinet}} {{date:%Q}} {{title", &page),
+            "{{clinet}} {{date:%Q}} {{title"
+        );
+        assert_eq!(
+            render("Page {{page_number}}", &context),
+            "Page {{page_number}}"
+        );
+        assert_eq!(
+            unresolved(
+                "{{clinet}} {{subtitle|x}} {{page_number}} {{title}}",
+                &context
+            ),
+            vec!["clinet".to_string(), "page_number".to_string()]
+        );
+    }
+}
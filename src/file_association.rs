@@ -0,0 +1,245 @@
+//! File Associations
+//!
+//! Registers `.hcats` project files and the `herdingcats://` URL scheme
+//! with the OS for the current user, so double-clicking a project file or
+//! following a deep link launches this executable. Runs on first start and
+//! again whenever the executable has moved. On macOS both are declared in
+//! the bundle's Info.plist and nothing is registered at runtime. A portable
+//! install never registers, so it leaves no trace on the host.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::app_paths::AppPaths;
+use crate::deep_link::SCHEME;
+use crate::project_file::{EXTENSION, MIME_TYPE};
+
+/// Bump when the registered entries change so existing installs re-register
+pub const REGISTRATION_VERSION: u32 = 1;
+
+const PROG_ID: &str = "HerdingCats.Project";
+const DESKTOP_FILE: &str = "herding-cats.desktop";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RegistrationMarker {
+    version: u32,
+    executable: PathBuf,
+}
+
+/// One value written under HKEY_CURRENT_USER
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryEntry {
+    pub key: String,
+    /// `None` for the key's default value
+    pub name: Option<String>,
+    pub data: String,
+}
+
+/// Register associations unless this executable already has. Returns
+/// whether a registration was performed.
+pub fn register_if_needed() -> std::io::Result<bool> {
+    if AppPaths::global().is_portable() {
+        return Ok(false);
+    }
+    let executable = std::env::current_exe()?;
+    let marker_path = marker_path();
+    let current = RegistrationMarker {
+        version: REGISTRATION_VERSION,
+        executable: executable.clone(),
+    };
+    let registered = std::fs::read_to_string(&marker_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<RegistrationMarker>(&content).ok());
+    if registered.as_ref() == Some(&current) {
+        return Ok(false);
+    }
+
+    register(&executable)?;
+    if let Some(parent) = marker_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&marker_path, serde_json::to_string(&current)?)?;
+    log::info!("Registered .{} files and {}:// links", EXTENSION, SCHEME);
+    Ok(true)
+}
+
+/// Register associations pointing at `executable`
+pub fn register(executable: &Path) -> std::io::Result<()> {
+    if cfg!(windows) {
+        for entry in windows_registry_entries(executable) {
+            let key = format!("HKCU\\{}", entry.key);
+            let mut command = std::process::Command::new("reg");
+            command.args(["add", &key]);
+            match &entry.name {
+                Some(name) => command.args(["/v", name]),
+                None => command.arg("/ve"),
+            };
+            let status = command.args(["/d", &entry.data, "/f"]).output()?.status;
+            if !status.success() {
+                return Err(std::io::Error::other(format!("reg add {} failed", key)));
+            }
+        }
+        Ok(())
+    } else if cfg!(target_os = "macos") {
+        Ok(())
+    } else {
+        register_freedesktop(executable)
+    }
+}
+
+fn marker_path() -> PathBuf {
+    AppPaths::global().local_dir.join("file-associations.json")
+}
+
+/// Per-user registry values for the file type and URL scheme
+pub fn windows_registry_entries(executable: &Path) -> Vec<RegistryEntry> {
+    let exe = executable.display();
+    let open_command = format!("\"{}\" \"%1\"", exe);
+    let entry = |key: String, name: Option<&str>, data: String| RegistryEntry {
+        key,
+        name: name.map(str::to_string),
+        data,
+    };
+    let classes = "Software\\Classes";
+    vec![
+        entry(
+            format!("{}\\.{}", classes, EXTENSION),
+            None,
+            PROG_ID.to_string(),
+        ),
+        entry(
+            format!("{}\\.{}", classes, EXTENSION),
+            Some("Content Type"),
+            MIME_TYPE.to_string(),
+        ),
+        entry(
+            format!("{}\\{}", classes, PROG_ID),
+            None,
+            "Herding Cats Project".to_string(),
+        ),
+        entry(
+            format!("{}\\{}\\DefaultIcon", classes, PROG_ID),
+            None,
+            format!("\"{}\",0", exe),
+        ),
+        entry(
+            format!("{}\\{}\\shell\\open\\command", classes, PROG_ID),
+            None,
+            open_command.clone(),
+        ),
+        entry(
+            format!("{}\\{}", classes, SCHEME),
+            None,
+            "URL:Herding Cats".to_string(),
+        ),
+        entry(
+            format!("{}\\{}", classes, SCHEME),
+            Some("URL Protocol"),
+            String::new(),
+        ),
+        entry(
+            format!("{}\\{}\\shell\\open\\command", classes, SCHEME),
+            None,
+            open_command,
+        ),
+    ]
+}
+
+/// Desktop entry declaring the MIME type and URL scheme handler
+pub fn desktop_entry(executable: &Path) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Herding Cats\n\
+         Exec=\"{}\" %u\n\
+         Terminal=false\n\
+         Categories=Office;\n\
+         MimeType={};x-scheme-handler/{};\n",
+        executable.display(),
+        MIME_TYPE,
+        SCHEME
+    )
+}
+
+/// shared-mime-info package mapping `*.hcats` to the project MIME type
+pub fn mime_package() -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n  \
+         <mime-type type=\"{}\">\n    \
+         <comment>Herding Cats project</comment>\n    \
+         <glob pattern=\"*.{}\"/>\n  \
+         </mime-type>\n\
+         </mime-info>\n",
+        MIME_TYPE, EXTENSION
+    )
+}
+
+fn register_freedesktop(executable: &Path) -> std::io::Result<()> {
+    let data_dir =
+        dirs::data_dir().ok_or_else(|| std::io::Error::other("No XDG data directory"))?;
+    let applications = data_dir.join("applications");
+    let mime = data_dir.join("mime");
+    let mime_packages = mime.join("packages");
+    std::fs::create_dir_all(&applications)?;
+    std::fs::create_dir_all(&mime_packages)?;
+
+    std::fs::write(applications.join(DESKTOP_FILE), desktop_entry(executable))?;
+    std::fs::write(mime_packages.join("herding-cats.xml"), mime_package())?;
+
+    // Refreshing the caches is best effort; the files above are what count
+    let refresh: [(&str, Vec<&std::ffi::OsStr>); 4] = [
+        ("update-mime-database", vec![mime.as_os_str()]),
+        ("update-desktop-database", vec![applications.as_os_str()]),
+        (
+            "xdg-mime",
+            vec![
+                "default".as_ref(),
+                DESKTOP_FILE.as_ref(),
+                MIME_TYPE.as_ref(),
+            ],
+        ),
+        (
+            "xdg-mime",
+            vec![
+                "default".as_ref(),
+                DESKTOP_FILE.as_ref(),
+                "x-scheme-handler/herdingcats".as_ref(),
+            ],
+        ),
+    ];
+    for (program, args) in refresh {
+        if let Err(e) = std::process::Command::new(program).args(args).output() {
+            log::debug!("{} not run: {}", program, e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registration_entries_point_at_executable() {
+        let exe = Path::new("C:\\Program Files\\Herding Cats\\herding-cats.exe");
+        let entries = windows_registry_entries(exe);
+        let command = entries
+            .iter()
+            .find(|e| e.key == "Software\\Classes\\HerdingCats.Project\\shell\\open\\command")
+            .unwrap();
+        assert_eq!(
+            command.data,
+            "\"C:\\Program Files\\Herding Cats\\herding-cats.exe\" \"%1\""
+        );
+        assert!(entries
+            .iter()
+            .any(|e| e.key == "Software\\Classes\\herdingcats"
+                && e.name.as_deref() == Some("URL Protocol")));
+
+        let desktop = desktop_entry(Path::new("/opt/herding-cats/herding-cats"));
+        assert!(desktop
+            .contains("MimeType=application/x-herding-cats-project;x-scheme-handler/herdingcats;"));
+        assert!(mime_package().contains("<glob pattern=\"*.hcats\"/>"));
+    }
+}
@@ -3,9 +3,9 @@
 
 use std::fs;
 use std::collections::HashMap;
-use std::sync::Mutex;
-use reqwest::blocking::Client;
+use std::sync::{Arc, Mutex};
 use crate::error::AppError;
+use crate::security::network::NetworkClient;
 
 /// Font information structure
 #[derive(Debug, Clone)]
@@ -22,7 +22,7 @@ pub struct FontInfo {
 pub struct FontManager {
     fonts_dir: std::path::PathBuf,
     font_info: HashMap<String, FontInfo>,
-    client: Client,
+    network: Arc<NetworkClient>,
 }
 
 impl FontManager {
@@ -155,7 +155,7 @@ impl FontManager {
         Ok(FontManager {
             fonts_dir,
             font_info,
-            client: Client::new(),
+            network: NetworkClient::global(),
         })
     }
 
@@ -175,7 +175,7 @@ impl FontManager {
         println!("Downloading font: {} from {}", family, font_info.url);
 
         // Download the font (blocking)
-        let response = self.client.get(&font_info.url).send()?;
+        let response = self.network.blocking_get(&font_info.url)?.send()?;
         let bytes = response.bytes()?;
 
         // Save to fonts directory
@@ -0,0 +1,284 @@
+//! Frontend Assets
+//!
+//! Serves the built frontend to the webview through the `app://` protocol.
+//! Release builds embed `frontend/dist` at compile time (see `build.rs`);
+//! when nothing is embedded the files are read from a `frontend/dist`
+//! directory next to the executable, never from the working directory.
+//! Responses carry an ETag and cache headers so the webview can revalidate.
+
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+include!(concat!(env!("OUT_DIR"), "/frontend_assets.rs"));
+
+/// Cache policy for fingerprinted build output (`assets/index-3f2a1c.js`)
+const IMMUTABLE_CACHE: &str = "public, max-age=31536000, immutable";
+/// Cache policy for everything else, e.g. index.html: always revalidate
+const REVALIDATE_CACHE: &str = "no-cache";
+
+/// A single frontend file held in memory
+#[derive(Debug, Clone)]
+pub struct FrontendAsset {
+    pub bytes: Cow<'static, [u8]>,
+    pub mime_type: &'static str,
+    pub etag: String,
+}
+
+impl FrontendAsset {
+    fn new(path: &str, bytes: Cow<'static, [u8]>) -> Self {
+        let digest = Sha256::digest(&bytes);
+        Self {
+            etag: format!("\"{}\"", hex_prefix(&digest, 16)),
+            mime_type: mime_type_for(path),
+            bytes,
+        }
+    }
+}
+
+/// Response produced for the custom protocol handler
+#[derive(Debug, Clone)]
+pub struct AssetResponse {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Cow<'static, [u8]>,
+}
+
+impl AssetResponse {
+    fn empty(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Cow::Borrowed(&[]),
+        }
+    }
+}
+
+/// In-memory store of the frontend build
+#[derive(Debug, Default)]
+pub struct FrontendAssets {
+    assets: HashMap<String, FrontendAsset>,
+}
+
+impl FrontendAssets {
+    /// Assets embedded at build time, falling back to the directory next
+    /// to the executable when the build embedded nothing
+    pub fn load() -> Self {
+        if !FRONTEND_ASSETS.is_empty() {
+            return Self::embedded();
+        }
+        match executable_dist_dir() {
+            Some(dir) => Self::from_directory(&dir).unwrap_or_else(|e| {
+                log::error!("Failed to load frontend from {}: {}", dir.display(), e);
+                Self::default()
+            }),
+            None => {
+                log::error!("No embedded frontend and no frontend/dist next to the executable");
+                Self::default()
+            }
+        }
+    }
+
+    /// Assets compiled into the binary
+    pub fn embedded() -> Self {
+        let assets = FRONTEND_ASSETS
+            .iter()
+            .map(|(path, bytes)| {
+                (
+                    path.to_string(),
+                    FrontendAsset::new(path, Cow::Borrowed(*bytes)),
+                )
+            })
+            .collect();
+        Self { assets }
+    }
+
+    /// Read every file under `dir` into memory
+    pub fn from_directory(dir: &Path) -> std::io::Result<Self> {
+        let mut assets = HashMap::new();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            for entry in std::fs::read_dir(&current)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                let Ok(relative) = path.strip_prefix(dir) else {
+                    continue;
+                };
+                let key = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let bytes = std::fs::read(&path)?;
+                assets.insert(key.clone(), FrontendAsset::new(&key, Cow::Owned(bytes)));
+            }
+        }
+        Ok(Self { assets })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    pub fn get(&self, path: &str) -> Option<&FrontendAsset> {
+        self.assets.get(path)
+    }
+
+    /// Resolve a request path and build the response. Unknown paths
+    /// without an extension fall back to index.html for client routing.
+    pub fn respond(&self, path: &str, if_none_match: Option<&str>) -> AssetResponse {
+        let path = path.trim_start_matches('/');
+        let path = if path.is_empty() { "index.html" } else { path };
+        if path.split('/').any(|segment| segment == "..") {
+            return AssetResponse::empty(403);
+        }
+
+        let (path, asset) = match self.assets.get_key_value(path) {
+            Some((path, asset)) => (path.as_str(), asset),
+            None if Path::new(path).extension().is_none() => {
+                match self.assets.get_key_value("index.html") {
+                    Some((path, asset)) => (path.as_str(), asset),
+                    None => return AssetResponse::empty(404),
+                }
+            }
+            None => return AssetResponse::empty(404),
+        };
+
+        let cache_control = if path.starts_with("assets/") {
+            IMMUTABLE_CACHE
+        } else {
+            REVALIDATE_CACHE
+        };
+        let headers = vec![
+            ("ETag", asset.etag.clone()),
+            ("Cache-Control", cache_control.to_string()),
+        ];
+
+        let not_modified = if_none_match.is_some_and(|value| {
+            value
+                .split(',')
+                .any(|tag| tag.trim() == asset.etag || tag.trim() == "*")
+        });
+        if not_modified {
+            return AssetResponse {
+                status: 304,
+                headers,
+                body: Cow::Borrowed(&[]),
+            };
+        }
+
+        let mut headers = headers;
+        headers.push(("Content-Type", asset.mime_type.to_string()));
+        AssetResponse {
+            status: 200,
+            headers,
+            body: asset.bytes.clone(),
+        }
+    }
+}
+
+/// `frontend/dist` beside the executable (or in the macOS bundle's Resources)
+fn executable_dist_dir() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    [
+        exe_dir.join("frontend").join("dist"),
+        exe_dir.join("../Resources/frontend/dist"),
+    ]
+    .into_iter()
+    .find(|dir| dir.is_dir())
+}
+
+fn mime_type_for(path: &str) -> &'static str {
+    match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") | Some("mjs") => "application/javascript",
+        Some("css") => "text/css",
+        Some("json") | Some("map") => "application/json",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("wasm") => "application/wasm",
+        Some("mp3") => "audio/mpeg",
+        Some("wav") => "audio/wav",
+        Some("ogg") => "audio/ogg",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+fn hex_prefix(bytes: &[u8], chars: usize) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>()
+        .chars()
+        .take(chars)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assets() -> FrontendAssets {
+        let mut assets = HashMap::new();
+        for (path, body) in [
+            ("index.html", "<html></html>"),
+            ("assets/index-abc123.js", "console.log(1)"),
+        ] {
+            assets.insert(
+                path.to_string(),
+                FrontendAsset::new(path, Cow::Owned(body.as_bytes().to_vec())),
+            );
+        }
+        FrontendAssets { assets }
+    }
+
+    fn header<'a>(response: &'a AssetResponse, name: &str) -> Option<&'a str> {
+        response
+            .headers
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn test_respond_serves_with_cache_headers() {
+        let assets = assets();
+        let script = assets.respond("/assets/index-abc123.js", None);
+        assert_eq!(script.status, 200);
+        assert_eq!(header(&script, "Cache-Control"), Some(IMMUTABLE_CACHE));
+        assert_eq!(
+            header(&script, "Content-Type"),
+            Some("application/javascript")
+        );
+
+        let index = assets.respond("/", None);
+        assert_eq!(index.body.as_ref(), b"<html></html>");
+        assert_eq!(header(&index, "Cache-Control"), Some(REVALIDATE_CACHE));
+    }
+
+    #[test]
+    fn test_etag_revalidation_and_fallbacks() {
+        let assets = assets();
+        let etag = assets.get("index.html").unwrap().etag.clone();
+        let revalidated = assets.respond("/index.html", Some(&etag));
+        assert_eq!(revalidated.status, 304);
+        assert!(revalidated.body.is_empty());
+
+        assert_eq!(assets.respond("/tool/codex", None).status, 200);
+        assert_eq!(assets.respond("/missing.png", None).status, 404);
+        assert_eq!(assets.respond("/../secrets.txt", None).status, 403);
+    }
+}
@@ -0,0 +1,427 @@
+//! Random Generators
+//!
+//! Table-driven generators for prompts and worldbuilding. A table is a list
+//! of weighted entries; an entry's text can roll other tables and reuse
+//! earlier results:
+//!
+//! - `[Weather]` rolls the table named Weather
+//! - `[Name@hero]` rolls Name and remembers the result as `hero`
+//! - `{hero}` inserts a remembered result or a caller-supplied variable
+//! - `[2d6]`, `[d20+3]` roll dice
+//!
+//! Brackets that don't form one of these are kept as written.
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// How deep tables may roll other tables, to stop tables that roll
+/// themselves
+const MAX_DEPTH: usize = 24;
+/// Most results rolled in one request
+pub const MAX_ROLLS: usize = 100;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum GeneratorError {
+    #[error("No generator table named \"{0}\"")]
+    UnknownTable(String),
+    #[error("No value for {{{0}}}")]
+    UnknownVariable(String),
+    #[error("Generator table \"{0}\" has no entries")]
+    EmptyTable(String),
+    #[error("Tables roll each other too deeply at \"{0}\"")]
+    TooDeep(String),
+    #[error("Invalid generator table: {0}")]
+    Invalid(String),
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// One possible result of a table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneratorEntry {
+    pub text: String,
+    /// Relative chance of this entry; 0 disables it
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+impl GeneratorEntry {
+    pub fn new(text: impl Into<String>, weight: u32) -> Self {
+        Self {
+            text: text.into(),
+            weight,
+        }
+    }
+}
+
+/// A user-editable table of weighted entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratorTable {
+    pub id: Uuid,
+    /// None for tables shared by all projects
+    pub project_id: Option<Uuid>,
+    pub name: String,
+    /// Grouping in the UI, such as "Plots" or "Weather"
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub description: String,
+    pub entries: Vec<GeneratorEntry>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl GeneratorTable {
+    pub fn new(name: impl Into<String>, category: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            project_id: None,
+            name: name.into(),
+            category: category.into(),
+            description: String::new(),
+            entries: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Add entries of weight 1
+    pub fn with_entries(mut self, entries: &[&str]) -> Self {
+        self.entries
+            .extend(entries.iter().map(|text| GeneratorEntry::new(*text, 1)));
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), GeneratorError> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err(GeneratorError::Invalid("name cannot be empty".to_string()));
+        }
+        if name.contains(['[', ']', '{', '}', '@']) {
+            return Err(GeneratorError::Invalid(format!(
+                "\"{}\" can't contain brackets, braces or @",
+                name
+            )));
+        }
+        if parse_dice(name).is_some() {
+            return Err(GeneratorError::Invalid(format!(
+                "\"{}\" reads as a dice roll",
+                name
+            )));
+        }
+        if self.entries.iter().all(|e| e.weight == 0) {
+            return Err(GeneratorError::EmptyTable(name.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Rolls a set of tables. Table names are matched ignoring case.
+pub struct Generator<'a> {
+    tables: HashMap<String, &'a GeneratorTable>,
+}
+
+impl<'a> Generator<'a> {
+    /// Later tables replace earlier ones of the same name, so project
+    /// tables listed after shared ones override them
+    pub fn new(tables: impl IntoIterator<Item = &'a GeneratorTable>) -> Self {
+        Self {
+            tables: tables
+                .into_iter()
+                .map(|t| (t.name.trim().to_lowercase(), t))
+                .collect(),
+        }
+    }
+
+    /// Roll a table. `variables` supplies `{name}` values and receives the
+    /// results remembered with `@name`.
+    pub fn roll<R: Rng>(
+        &self,
+        table: &str,
+        variables: &mut HashMap<String, String>,
+        rng: &mut R,
+    ) -> Result<String, GeneratorError> {
+        self.roll_table(table, variables, rng, 0)
+    }
+
+    /// Expand a template as if it were an entry
+    pub fn expand<R: Rng>(
+        &self,
+        template: &str,
+        variables: &mut HashMap<String, String>,
+        rng: &mut R,
+    ) -> Result<String, GeneratorError> {
+        self.expand_at(template, variables, rng, 0)
+    }
+
+    fn roll_table<R: Rng>(
+        &self,
+        name: &str,
+        variables: &mut HashMap<String, String>,
+        rng: &mut R,
+        depth: usize,
+    ) -> Result<String, GeneratorError> {
+        if depth >= MAX_DEPTH {
+            return Err(GeneratorError::TooDeep(name.to_string()));
+        }
+        let table = self
+            .tables
+            .get(&name.trim().to_lowercase())
+            .ok_or_else(|| GeneratorError::UnknownTable(name.trim().to_string()))?;
+        let total: u64 = table.entries.iter().map(|e| e.weight as u64).sum();
+        if total == 0 {
+            return Err(GeneratorError::EmptyTable(table.name.clone()));
+        }
+
+        let mut pick = rng.gen_range(0..total);
+        let entry = table
+            .entries
+            .iter()
+            .find(|e| {
+                if pick < e.weight as u64 {
+                    true
+                } else {
+                    pick -= e.weight as u64;
+                    false
+                }
+            })
+            .expect("pick is below the total weight");
+        self.expand_at(&entry.text, variables, rng, depth + 1)
+    }
+
+    fn expand_at<R: Rng>(
+        &self,
+        template: &str,
+        variables: &mut HashMap<String, String>,
+        rng: &mut R,
+        depth: usize,
+    ) -> Result<String, GeneratorError> {
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find(['[', '{']) {
+            out.push_str(&rest[..start]);
+            let open = rest.as_bytes()[start];
+            let close = if open == b'[' { ']' } else { '}' };
+            let Some(len) = rest[start + 1..].find(close) else {
+                out.push_str(&rest[start..]);
+                return Ok(out);
+            };
+            let inner = &rest[start + 1..start + 1 + len];
+            rest = &rest[start + len + 2..];
+
+            if open == b'{' {
+                let name = inner.trim();
+                let value = variables
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| GeneratorError::UnknownVariable(name.to_string()))?;
+                out.push_str(&value);
+            } else if let Some((count, sides, modifier)) = parse_dice(inner) {
+                let total: i64 = (0..count).map(|_| rng.gen_range(1..=sides)).sum();
+                out.push_str(&(total + modifier).to_string());
+            } else if inner.trim().is_empty() {
+                out.push_str("[]");
+            } else {
+                let (table, remember) = match inner.split_once('@') {
+                    Some((table, name)) => (table, Some(name.trim())),
+                    None => (inner, None),
+                };
+                let value = self.roll_table(table, variables, rng, depth)?;
+                if let Some(name) = remember.filter(|n| !n.is_empty()) {
+                    variables.insert(name.to_string(), value.clone());
+                }
+                out.push_str(&value);
+            }
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+/// Roll a table `count` times, each with its own copy of the variables
+pub fn roll_many<'a, R: Rng>(
+    tables: impl IntoIterator<Item = &'a GeneratorTable>,
+    table: &str,
+    variables: &HashMap<String, String>,
+    count: usize,
+    rng: &mut R,
+) -> Result<Vec<String>, GeneratorError> {
+    let generator = Generator::new(tables);
+    (0..count.clamp(1, MAX_ROLLS))
+        .map(|_| generator.roll(table, &mut variables.clone(), rng))
+        .collect()
+}
+
+/// `NdM`, `dM` and `NdM+K` / `NdM-K`
+fn parse_dice(text: &str) -> Option<(u32, i64, i64)> {
+    let text = text.trim().to_lowercase();
+    let (count, rest) = text.split_once('d')?;
+    let count = if count.is_empty() {
+        1
+    } else {
+        count.parse().ok()?
+    };
+    let (sides, modifier) = match rest.find(['+', '-']) {
+        Some(at) => (&rest[..at], rest[at..].parse().ok()?),
+        None => (rest, 0),
+    };
+    let sides: i64 = sides.parse().ok()?;
+    (1..=100).contains(&count).then_some(())?;
+    (sides >= 1).then_some((count, sides, modifier))
+}
+
+/// Starter tables shared by all projects, editable like any other
+pub fn default_tables() -> Vec<GeneratorTable> {
+    vec![
+        GeneratorTable::new("Plot Hook", "Plots").with_entries(&[
+            "[Character@who] must recover [Treasure] before [Deadline]",
+            "A stranger arrives in [Place] carrying news of [Event]",
+            "[Character@who] discovers that {who}'s mentor has been lying about [Secret]",
+            "Two rivals are forced to cross [Place] together during [Weather]",
+            "Someone in [Place] is stealing [Treasure], one piece at a time",
+        ]),
+        GeneratorTable::new("Writing Prompt", "Prompts").with_entries(&[
+            "Write a scene in [Place] where [Character] hears about [Event]",
+            "Open with [Weather] and a character who wants [Treasure]",
+            "Write a letter from [Character] confessing [Secret]",
+            "Describe [Place] through the eyes of [Character] after [Event]",
+        ]),
+        GeneratorTable::new("Character", "Characters").with_entries(&[
+            "a disgraced knight",
+            "a cartographer with no sense of direction",
+            "a retired thief",
+            "a young archivist",
+            "a ferry captain",
+            "an exiled heir",
+        ]),
+        GeneratorTable::new("Place", "Places").with_entries(&[
+            "a drowned village",
+            "the lighthouse at the end of the world",
+            "a crowded border market",
+            "an abandoned observatory",
+            "a monastery carved into the cliffs",
+        ]),
+        GeneratorTable::new("Event", "Plots").with_entries(&[
+            "a royal wedding",
+            "a plague of strange dreams",
+            "the first frost in a hundred years",
+            "a failed rebellion",
+            "the death of the oldest tree",
+        ]),
+        GeneratorTable::new("Secret", "Plots").with_entries(&[
+            "a forged will",
+            "a sibling nobody knew about",
+            "the real cause of the fire",
+            "a debt that can't be repaid in coin",
+        ]),
+        GeneratorTable::new("Deadline", "Plots").with_entries(&[
+            "the next full moon",
+            "the harvest festival",
+            "the tide turns",
+            "the bells ring at dawn",
+        ]),
+        GeneratorTable::new("Treasure", "Loot").with_entries(&[
+            "[Loot]",
+            "a map drawn on human skin",
+            "the key to the old vault",
+        ]),
+        GeneratorTable {
+            entries: vec![
+                GeneratorEntry::new("[2d6] copper coins", 6),
+                GeneratorEntry::new("[1d6] silver coins and a [Trinket]", 3),
+                GeneratorEntry::new("a [Trinket]", 3),
+                GeneratorEntry::new("[1d4] gold coins wrapped in a letter", 1),
+                GeneratorEntry::new("a jewelled dagger", 1),
+            ],
+            ..GeneratorTable::new("Loot", "Loot")
+        },
+        GeneratorTable::new("Trinket", "Loot").with_entries(&[
+            "cracked compass",
+            "lock of hair tied with ribbon",
+            "carved bone die",
+            "pressed flower in wax",
+            "bent spoon",
+            "glass eye",
+        ]),
+        GeneratorTable {
+            entries: vec![
+                GeneratorEntry::new("clear skies", 4),
+                GeneratorEntry::new("a grey drizzle", 3),
+                GeneratorEntry::new("thick fog", 2),
+                GeneratorEntry::new("a thunderstorm", 2),
+                GeneratorEntry::new("sleet and bitter wind", 1),
+                GeneratorEntry::new("a heatwave", 1),
+            ],
+            ..GeneratorTable::new("Weather", "Weather")
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_nested_weighted_tables_with_variables_and_dice() {
+        let tables = vec![
+            GeneratorTable::new("Name", "").with_entries(&["Ada", "Bo"]),
+            GeneratorTable {
+                entries: vec![
+                    GeneratorEntry::new("never", 0),
+                    GeneratorEntry::new("[Name@hero] met {hero} and {friend} [1d1+2] times", 1),
+                ],
+                ..GeneratorTable::new("Meeting", "")
+            },
+            GeneratorTable::new("Loop", "").with_entries(&["[loop]"]),
+        ];
+        let generator = Generator::new(&tables);
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut variables = HashMap::from([("friend".to_string(), "Cy".to_string())]);
+
+        let text = generator.roll("meeting", &mut variables, &mut rng).unwrap();
+        let hero = variables["hero"].clone();
+        assert_eq!(text, format!("{0} met {0} and Cy 3 times", hero));
+        assert_eq!(
+            generator.expand("[Nobody] here", &mut variables, &mut rng),
+            Err(GeneratorError::UnknownTable("Nobody".to_string()))
+        );
+        assert_eq!(
+            generator.expand("{nobody}", &mut variables, &mut rng),
+            Err(GeneratorError::UnknownVariable("nobody".to_string()))
+        );
+        assert_eq!(
+            generator.expand("keep [ this", &mut variables, &mut rng),
+            Ok("keep [ this".to_string())
+        );
+        assert_eq!(
+            generator.roll("Loop", &mut variables, &mut rng),
+            Err(GeneratorError::TooDeep("loop".to_string()))
+        );
+
+        let results = roll_many(&tables, "Name", &HashMap::new(), 40, &mut rng).unwrap();
+        assert!(results.iter().any(|r| r == "Ada") && results.iter().any(|r| r == "Bo"));
+        assert!(GeneratorTable::new("2d6", "")
+            .with_entries(&["x"])
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_default_tables_roll() {
+        let tables = default_tables();
+        let mut rng = StdRng::seed_from_u64(1);
+        for table in &tables {
+            table.validate().unwrap();
+            roll_many(&tables, &table.name, &HashMap::new(), 20, &mut rng).unwrap();
+        }
+    }
+}
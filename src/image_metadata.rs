@@ -0,0 +1,503 @@
+//! Image Metadata Stripping
+//!
+//! Removes location and camera details from imported photos before they
+//! reach the asset store. The file is edited without re-encoding, so image
+//! quality is untouched: EXIF blocks are scrubbed in place (entries are
+//! dropped from their directory and their values zeroed, keeping every
+//! other offset valid) and XMP/IPTC blocks, which can repeat the same
+//! details as free text, are removed. Orientation, colour profiles and
+//! exposure settings are kept.
+//!
+//! JPEG, PNG and WebP are handled; other formats pass through unchanged.
+
+use serde::{Deserialize, Serialize};
+
+use crate::settings::PrivacyControls;
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_SOFTWARE: u16 = 0x0131;
+const TAG_HOST_COMPUTER: u16 = 0x013C;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_MAKER_NOTE: u16 = 0x927C;
+const TAG_CAMERA_OWNER: u16 = 0xA430;
+const TAG_BODY_SERIAL: u16 = 0xA431;
+const TAG_LENS_SPECIFICATION: u16 = 0xA432;
+const TAG_LENS_MAKE: u16 = 0xA433;
+const TAG_LENS_MODEL: u16 = 0xA434;
+const TAG_LENS_SERIAL: u16 = 0xA435;
+
+/// Camera-identifying tags in IFD0
+const CAMERA_TAGS: &[u16] = &[TAG_MAKE, TAG_MODEL, TAG_SOFTWARE, TAG_HOST_COMPUTER];
+/// Camera-identifying tags in the Exif sub-IFD
+const CAMERA_EXIF_TAGS: &[u16] = &[
+    TAG_MAKER_NOTE,
+    TAG_CAMERA_OWNER,
+    TAG_BODY_SERIAL,
+    TAG_LENS_SPECIFICATION,
+    TAG_LENS_MAKE,
+    TAG_LENS_MODEL,
+    TAG_LENS_SERIAL,
+];
+
+const JPEG_EXIF_HEADER: &[u8] = b"Exif\0\0";
+const JPEG_XMP_HEADERS: &[&[u8]] = &[
+    b"http://ns.adobe.com/xap/1.0/\0",
+    b"http://ns.adobe.com/xmp/extension/\0",
+];
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// VP8X flag announcing an XMP chunk
+const WEBP_XMP_FLAG: u8 = 0x04;
+
+/// What stripping removed, for the user-visible log
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StripReport {
+    /// Human-readable names such as "GPS location" or "camera model"
+    pub removed: Vec<String>,
+}
+
+impl StripReport {
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty()
+    }
+
+    fn add(&mut self, what: &str) {
+        if !self.removed.iter().any(|r| r == what) {
+            self.removed.push(what.to_string());
+        }
+    }
+}
+
+/// Whether `controls` ask for anything to be stripped
+pub fn is_enabled(controls: &PrivacyControls) -> bool {
+    controls.strip_image_location || controls.strip_camera_metadata
+}
+
+/// Copy of `bytes` without the metadata `controls` select
+pub fn strip_metadata(bytes: &[u8], controls: &PrivacyControls) -> (Vec<u8>, StripReport) {
+    let mut report = StripReport::default();
+    if !is_enabled(controls) {
+        return (bytes.to_vec(), report);
+    }
+    let stripped = if bytes.starts_with(&[0xFF, 0xD8]) {
+        strip_jpeg(bytes, controls, &mut report)
+    } else if bytes.starts_with(PNG_SIGNATURE) {
+        strip_png(bytes, controls, &mut report)
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        strip_webp(bytes, controls, &mut report)
+    } else {
+        None
+    };
+    match stripped {
+        Some(stripped) => (stripped, report),
+        // Unknown or malformed: keep the original rather than corrupt it
+        None => (bytes.to_vec(), StripReport::default()),
+    }
+}
+
+fn strip_jpeg(
+    bytes: &[u8],
+    controls: &PrivacyControls,
+    report: &mut StripReport,
+) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(&bytes[..2]);
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() {
+        if bytes[pos] != 0xFF {
+            return None;
+        }
+        let marker = bytes[pos + 1];
+        match marker {
+            // Fill byte
+            0xFF => {
+                pos += 1;
+                continue;
+            }
+            // Standalone markers carry no length
+            0x01 | 0xD0..=0xD7 => {
+                out.extend_from_slice(&bytes[pos..pos + 2]);
+                pos += 2;
+                continue;
+            }
+            // Start of scan: the rest is image data
+            0xDA | 0xD9 => break,
+            _ => {}
+        }
+        let length = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let end = pos + 2 + length;
+        if length < 2 || end > bytes.len() {
+            return None;
+        }
+        let data = &bytes[pos + 4..end];
+
+        if marker == 0xE1 && data.starts_with(JPEG_EXIF_HEADER) {
+            let mut segment = bytes[pos..end].to_vec();
+            scrub_tiff(&mut segment[4 + JPEG_EXIF_HEADER.len()..], controls, report);
+            out.extend_from_slice(&segment);
+        } else if marker == 0xE1 && JPEG_XMP_HEADERS.iter().any(|h| data.starts_with(h)) {
+            report.add("XMP metadata");
+        } else if marker == 0xED && controls.strip_image_location {
+            // Photoshop IRB, which holds IPTC location fields
+            report.add("IPTC metadata");
+        } else {
+            out.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+    }
+    out.extend_from_slice(&bytes[pos..]);
+    Some(out)
+}
+
+fn strip_png(
+    bytes: &[u8],
+    controls: &PrivacyControls,
+    report: &mut StripReport,
+) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(PNG_SIGNATURE);
+    let mut pos = PNG_SIGNATURE.len();
+    while pos + 12 <= bytes.len() {
+        let length = u32::from_be_bytes(bytes[pos..pos + 4].try_into().ok()?) as usize;
+        let end = pos + 12 + length;
+        if end > bytes.len() {
+            return None;
+        }
+        let kind = &bytes[pos + 4..pos + 8];
+        let data = &bytes[pos + 8..pos + 8 + length];
+
+        match kind {
+            b"eXIf" => {
+                let mut data = data.to_vec();
+                scrub_tiff(&mut data, controls, report);
+                let mut crc = crc32fast::Hasher::new();
+                crc.update(kind);
+                crc.update(&data);
+                out.extend_from_slice(&bytes[pos..pos + 8]);
+                out.extend_from_slice(&data);
+                out.extend_from_slice(&crc.finalize().to_be_bytes());
+            }
+            b"tEXt" | b"zTXt" | b"iTXt" if is_png_metadata_keyword(data) => {
+                report.add("XMP metadata");
+            }
+            _ => out.extend_from_slice(&bytes[pos..end]),
+        }
+        pos = end;
+        if kind == b"IEND" {
+            break;
+        }
+    }
+    out.extend_from_slice(&bytes[pos..]);
+    Some(out)
+}
+
+/// Text chunks that carry XMP or raw EXIF/IPTC profiles rather than text
+fn is_png_metadata_keyword(data: &[u8]) -> bool {
+    let keyword = data.split(|b| *b == 0).next().unwrap_or_default();
+    keyword == b"XML:com.adobe.xmp" || keyword.starts_with(b"Raw profile type")
+}
+
+fn strip_webp(
+    bytes: &[u8],
+    controls: &PrivacyControls,
+    report: &mut StripReport,
+) -> Option<Vec<u8>> {
+    let mut out = bytes[..12].to_vec();
+    let mut pos = 12;
+    let mut vp8x_flags = None;
+    while pos + 8 <= bytes.len() {
+        let kind = &bytes[pos..pos + 4];
+        let length = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().ok()?) as usize;
+        // Chunks are padded to an even length
+        let end = (pos + 8 + length + (length & 1)).min(bytes.len());
+        if pos + 8 + length > bytes.len() {
+            return None;
+        }
+
+        match kind {
+            b"EXIF" => {
+                let mut chunk = bytes[pos..end].to_vec();
+                let data = &mut chunk[8..8 + length];
+                let offset = if data.starts_with(JPEG_EXIF_HEADER) {
+                    JPEG_EXIF_HEADER.len()
+                } else {
+                    0
+                };
+                scrub_tiff(&mut data[offset..], controls, report);
+                out.extend_from_slice(&chunk);
+            }
+            b"XMP " => report.add("XMP metadata"),
+            _ => {
+                if kind == b"VP8X" && length > 0 {
+                    vp8x_flags = Some(out.len() + 8);
+                }
+                out.extend_from_slice(&bytes[pos..end]);
+            }
+        }
+        pos = end;
+    }
+
+    if report.removed.iter().any(|r| r == "XMP metadata") {
+        if let Some(flags) = vp8x_flags {
+            out[flags] &= !WEBP_XMP_FLAG;
+        }
+    }
+    let riff_size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(out)
+}
+
+/// Scrub a TIFF-structured EXIF block in place
+fn scrub_tiff(tiff: &mut [u8], controls: &PrivacyControls, report: &mut StripReport) {
+    let Some(endian) = Endian::detect(tiff) else {
+        return;
+    };
+    let Some(ifd0) = endian.read_u32(tiff, 4) else {
+        return;
+    };
+    let ifd0 = ifd0 as usize;
+
+    if controls.strip_image_location {
+        if let Some(gps) = find_entry(tiff, endian, ifd0, TAG_GPS_IFD)
+            .and_then(|entry| endian.read_u32(tiff, entry + 8))
+        {
+            remove_entries(tiff, endian, gps as usize, |_| true);
+            if !remove_entries(tiff, endian, ifd0, |tag| tag == TAG_GPS_IFD).is_empty() {
+                report.add("GPS location");
+            }
+        }
+    }
+
+    if controls.strip_camera_metadata {
+        if let Some(exif) = find_entry(tiff, endian, ifd0, TAG_EXIF_IFD)
+            .and_then(|entry| endian.read_u32(tiff, entry + 8))
+        {
+            for tag in remove_entries(tiff, endian, exif as usize, |tag| {
+                CAMERA_EXIF_TAGS.contains(&tag)
+            }) {
+                report.add(tag_label(tag));
+            }
+        }
+        for tag in remove_entries(tiff, endian, ifd0, |tag| CAMERA_TAGS.contains(&tag)) {
+            report.add(tag_label(tag));
+        }
+    }
+}
+
+fn tag_label(tag: u16) -> &'static str {
+    match tag {
+        TAG_MAKE => "camera make",
+        TAG_MODEL => "camera model",
+        TAG_SOFTWARE => "software",
+        TAG_HOST_COMPUTER => "host computer",
+        TAG_MAKER_NOTE => "maker notes",
+        TAG_CAMERA_OWNER => "camera owner",
+        TAG_BODY_SERIAL => "camera serial number",
+        TAG_LENS_SERIAL => "lens serial number",
+        _ => "lens details",
+    }
+}
+
+/// Offset of the entry for `tag` in the IFD at `ifd`
+fn find_entry(tiff: &[u8], endian: Endian, ifd: usize, tag: u16) -> Option<usize> {
+    let count = endian.read_u16(tiff, ifd)? as usize;
+    (0..count)
+        .map(|i| ifd + 2 + i * 12)
+        .take_while(|entry| entry + 12 <= tiff.len())
+        .find(|entry| endian.read_u16(tiff, *entry) == Some(tag))
+}
+
+/// Drop the entries of the IFD at `ifd` whose tag matches, zeroing their
+/// values. Kept entries move up so the directory stays contiguous; the
+/// freed space at its end is zeroed. Returns the removed tags.
+fn remove_entries(
+    tiff: &mut [u8],
+    endian: Endian,
+    ifd: usize,
+    remove: impl Fn(u16) -> bool,
+) -> Vec<u16> {
+    let Some(count) = endian.read_u16(tiff, ifd).map(usize::from) else {
+        return Vec::new();
+    };
+    let entries_start = ifd + 2;
+    let next_ifd = entries_start + count * 12;
+    if next_ifd + 4 > tiff.len() {
+        return Vec::new();
+    }
+
+    let mut kept = Vec::with_capacity(count * 12);
+    let mut removed = Vec::new();
+    for i in 0..count {
+        let entry = entries_start + i * 12;
+        let tag = endian.read_u16(tiff, entry).unwrap_or_default();
+        if !remove(tag) {
+            kept.extend_from_slice(&tiff[entry..entry + 12]);
+            continue;
+        }
+        removed.push(tag);
+        let kind = endian.read_u16(tiff, entry + 2).unwrap_or_default();
+        let values = endian.read_u32(tiff, entry + 4).unwrap_or_default() as usize;
+        let size = type_size(kind).saturating_mul(values);
+        if size > 4 {
+            let offset = endian.read_u32(tiff, entry + 8).unwrap_or_default() as usize;
+            if let Some(value) = tiff.get_mut(offset..offset.saturating_add(size)) {
+                value.fill(0);
+            }
+        }
+    }
+    if removed.is_empty() {
+        return removed;
+    }
+
+    let next = tiff[next_ifd..next_ifd + 4].to_vec();
+    let kept_count = kept.len() / 12;
+    endian.write_u16(tiff, ifd, kept_count as u16);
+    tiff[entries_start..entries_start + kept.len()].copy_from_slice(&kept);
+    let next_at = entries_start + kept.len();
+    tiff[next_at..next_at + 4].copy_from_slice(&next);
+    tiff[next_at + 4..next_ifd + 4].fill(0);
+    removed
+}
+
+/// Bytes per value of a TIFF field type
+fn type_size(kind: u16) -> usize {
+    match kind {
+        1 | 2 | 6 | 7 => 1,
+        3 | 8 => 2,
+        4 | 9 | 11 | 13 => 4,
+        5 | 10 | 12 => 8,
+        _ => 0,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    fn detect(tiff: &[u8]) -> Option<Self> {
+        match tiff.get(..4)? {
+            b"II*\0" => Some(Endian::Little),
+            b"MM\0*" => Some(Endian::Big),
+            _ => None,
+        }
+    }
+
+    fn read_u16(self, bytes: &[u8], at: usize) -> Option<u16> {
+        let raw: [u8; 2] = bytes.get(at..at + 2)?.try_into().ok()?;
+        Some(match self {
+            Endian::Little => u16::from_le_bytes(raw),
+            Endian::Big => u16::from_be_bytes(raw),
+        })
+    }
+
+    fn read_u32(self, bytes: &[u8], at: usize) -> Option<u32> {
+        let raw: [u8; 4] = bytes.get(at..at + 4)?.try_into().ok()?;
+        Some(match self {
+            Endian::Little => u32::from_le_bytes(raw),
+            Endian::Big => u32::from_be_bytes(raw),
+        })
+    }
+
+    fn write_u16(self, bytes: &mut [u8], at: usize, value: u16) {
+        let raw = match self {
+            Endian::Little => value.to_le_bytes(),
+            Endian::Big => value.to_be_bytes(),
+        };
+        bytes[at..at + 2].copy_from_slice(&raw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIENTATION: u16 = 0x0112;
+
+    /// Little-endian TIFF with Make, Orientation and a one-entry GPS IFD
+    fn sample_tiff() -> Vec<u8> {
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        // IFD0 at 8: three entries, then next-IFD offset
+        tiff.extend_from_slice(&3u16.to_le_bytes());
+        let make_offset = 8 + 2 + 3 * 12 + 4;
+        let gps_offset = make_offset + 8;
+        for (tag, kind, count, value) in [
+            (TAG_MAKE, 2u16, 8u32, make_offset as u32),
+            (ORIENTATION, 3, 1, 6),
+            (TAG_GPS_IFD, 4, 1, gps_offset as u32),
+        ] {
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&kind.to_le_bytes());
+            tiff.extend_from_slice(&count.to_le_bytes());
+            tiff.extend_from_slice(&value.to_le_bytes());
+        }
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff.extend_from_slice(b"Nikon\0\0\0");
+        // GPS IFD: latitude ref "N"
+        tiff.extend_from_slice(&1u16.to_le_bytes());
+        tiff.extend_from_slice(&1u16.to_le_bytes());
+        tiff.extend_from_slice(&2u16.to_le_bytes());
+        tiff.extend_from_slice(&2u32.to_le_bytes());
+        tiff.extend_from_slice(b"N\0\0\0");
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff
+    }
+
+    fn jpeg_with_exif(tiff: &[u8]) -> Vec<u8> {
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&((2 + JPEG_EXIF_HEADER.len() + tiff.len()) as u16).to_be_bytes());
+        jpeg.extend_from_slice(JPEG_EXIF_HEADER);
+        jpeg.extend_from_slice(tiff);
+        let xmp = b"http://ns.adobe.com/xap/1.0/\0<x:xmpmeta/>";
+        jpeg.extend_from_slice(&[0xFF, 0xE1]);
+        jpeg.extend_from_slice(&((2 + xmp.len()) as u16).to_be_bytes());
+        jpeg.extend_from_slice(xmp);
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn test_strips_location_and_camera_but_keeps_orientation() {
+        let jpeg = jpeg_with_exif(&sample_tiff());
+        let (stripped, report) = strip_metadata(&jpeg, &PrivacyControls::default());
+
+        assert_eq!(
+            report.removed,
+            vec!["GPS location", "camera make", "XMP metadata"]
+        );
+        let tiff = &stripped[4 + 2 + JPEG_EXIF_HEADER.len()..];
+        assert_eq!(find_entry(tiff, Endian::Little, 8, TAG_GPS_IFD), None);
+        assert_eq!(find_entry(tiff, Endian::Little, 8, TAG_MAKE), None);
+        assert!(find_entry(tiff, Endian::Little, 8, ORIENTATION).is_some());
+        assert!(!stripped.windows(5).any(|w| w == b"Nikon"));
+        assert!(!stripped.windows(4).any(|w| w == b"N\0\0\0"));
+        assert!(stripped.ends_with(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]));
+
+        let location_only = PrivacyControls {
+            strip_image_location: true,
+            strip_camera_metadata: false,
+            ..PrivacyControls::default()
+        };
+        let (stripped, _) = strip_metadata(&jpeg, &location_only);
+        let tiff = &stripped[4 + 2 + JPEG_EXIF_HEADER.len()..];
+        assert!(find_entry(tiff, Endian::Little, 8, TAG_MAKE).is_some());
+        assert_eq!(find_entry(tiff, Endian::Little, 8, TAG_GPS_IFD), None);
+    }
+
+    #[test]
+    fn test_disabled_controls_leave_file_untouched() {
+        let jpeg = jpeg_with_exif(&sample_tiff());
+        let off = PrivacyControls {
+            strip_image_location: false,
+            strip_camera_metadata: false,
+            ..PrivacyControls::default()
+        };
+        let (stripped, report) = strip_metadata(&jpeg, &off);
+        assert_eq!(stripped, jpeg);
+        assert!(report.is_empty());
+    }
+}
@@ -0,0 +1,60 @@
+//! Activity feed requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use uuid::Uuid;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::ActivityFeed { project_id, filter } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .activity
+                    .feed(project_id, &filter)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(entries) => IpcResponse::ActivityFeed { entries },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::ActivitySummary { project_id, since } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .activity
+                    .summary_since(project_id, since)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(summary) => IpcResponse::ActivitySummary { summary },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::ActivityOpenProject { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => match bridge.profiles.can_view_project(project_id).await {
+                    Ok(true) => bridge
+                        .activity
+                        .open_project(project_id)
+                        .await
+                        .map_err(|e| e.to_string()),
+                    Ok(false) => Err(format!("Project not found: {}", project_id)),
+                    Err(e) => Err(e.to_string()),
+                },
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(summary) => IpcResponse::ActivitySummary { summary },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
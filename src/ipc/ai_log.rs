@@ -0,0 +1,34 @@
+//! AI interaction log requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::AiLogList { project_id, limit } => {
+            match bridge.ai_log.list(project_id, limit).await {
+                Ok(entries) => IpcResponse::AiLog { entries },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::AiLogExport { project_id } => match bridge.ai_log.export(project_id).await {
+            Ok(content) => IpcResponse::AiLogExport { content },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::AiLogDelete { project_id } => match bridge.ai_log.delete(project_id).await {
+            Ok(removed) => IpcResponse::AiLogDeleted { removed },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::AiLogProjectSet {
+            project_id,
+            enabled,
+        } => match bridge.ai_log.set_project_logging(project_id, enabled).await {
+            Ok(()) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,166 @@
+//! Story bible, lint, voice, chronology and readability requests
+
+use crate::database::models::activity::{ActivityEntry, ActivityKind};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use uuid::Uuid;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::StoryBibleExport { request, path } => {
+            let result = match bridge.story_bible.export(&request).await {
+                Ok(bible) => std::fs::write(&path, &bible.bytes)
+                    .map(|_| bible)
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(bible) => {
+                    let summary = format!("Exported story bible '{}'", bible.title);
+                    bridge
+                        .log_activity(ActivityEntry::new(
+                            Some(request.project_id),
+                            ActivityKind::Export,
+                            summary,
+                        ))
+                        .await;
+                    IpcResponse::StoryBible { bible, path }
+                }
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::LintPacks => IpcResponse::LintPacks {
+            packs: bridge.analysis.lint_packs(),
+        },
+        IpcMessage::LintSettingsGet { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .analysis
+                    .get_lint_settings(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(settings) => IpcResponse::LintSettings { settings },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::LintSettingsSave { settings } => {
+            match bridge.analysis.save_lint_settings(&settings).await {
+                Ok(settings) => IpcResponse::LintSettings { settings },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::LintProject { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .analysis
+                    .lint_project(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(report) => IpcResponse::LintReport { report },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::NarrativeVoiceCheck { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .analysis
+                    .check_narrative_voice(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(report) => IpcResponse::VoiceReport { report },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::SceneVoiceSet { document_id, voice } => {
+            let result = match Uuid::parse_str(&document_id) {
+                Ok(document_id) => bridge
+                    .analysis
+                    .set_scene_voice(document_id, &voice)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(()) => IpcResponse::Ack,
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::ChronologyCheck { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .chronology
+                    .check(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(report) => IpcResponse::ChronologyReport { report },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::ChronologyReadingOrder { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .chronology
+                    .reading_order(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(order) => IpcResponse::ReadingOrder { order },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::ChronologyExport {
+            project_id,
+            format,
+            path,
+        } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => match bridge
+                    .chronology
+                    .export_reading_order(project_id, format)
+                    .await
+                {
+                    Ok(export) => std::fs::write(&path, &export.bytes)
+                        .map(|_| export)
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                },
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(export) => IpcResponse::ChronologicalExport { export, path },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::ReadabilityAnalyze {
+            document_id,
+            version,
+        } => {
+            match bridge
+                .analysis
+                .analyze_readability(document_id, version)
+                .await
+            {
+                Ok(report) => IpcResponse::ReadabilityReport { report },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,64 @@
+//! Recent items, crash reports and storage requests
+
+use crate::app_paths::AppPaths;
+use crate::data_migration::{self};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::RecentRecord { kind, id, title } => {
+            match bridge.recent_items.record(kind, &id, &title) {
+                Ok(()) => IpcResponse::Ack,
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::RecentList => IpcResponse::RecentItems {
+            items: bridge.recent_items.items(),
+        },
+        IpcMessage::CrashReports => IpcResponse::CrashReports {
+            reports: bridge.crash_reporter.pending_reports(),
+        },
+        IpcMessage::CrashReportSubmit { id } => match bridge.crash_reporter.submit(&id).await {
+            Ok(()) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::CrashReportDismiss { id } => match bridge.crash_reporter.dismiss(&id) {
+            Ok(()) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::CrashMetrics => {
+            let metrics = bridge.crash_reporter.metrics();
+            IpcResponse::CrashMetrics {
+                metrics,
+                crash_free_rate: metrics.crash_free_rate(),
+            }
+        }
+        IpcMessage::StorageInfo => IpcResponse::StorageInfo {
+            paths: AppPaths::global().clone(),
+        },
+        IpcMessage::DataDirPlan { path } => {
+            match data_migration::plan(&AppPaths::global().data_dir, std::path::Path::new(&path)) {
+                Ok(plan) => IpcResponse::DataMigrationPlan { plan },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DataDirMigrate { path } => {
+            let result = match data_migration::plan(
+                &AppPaths::global().data_dir,
+                std::path::Path::new(&path),
+            ) {
+                Ok(plan) => data_migration::migrate(&bridge.db_service.pool, &plan).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(report) => IpcResponse::DataMigration { report },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,92 @@
+//! Attachment requests
+
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use uuid::Uuid;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::AttachmentAdd {
+            project_id,
+            owner_kind,
+            owner_id,
+            path,
+        } => {
+            let result = match (Uuid::parse_str(&project_id), Uuid::parse_str(&owner_id)) {
+                (Ok(project_id), Ok(owner_id)) => bridge
+                    .attachments
+                    .attach_file(
+                        project_id,
+                        owner_kind,
+                        owner_id,
+                        std::path::Path::new(&path),
+                    )
+                    .await
+                    .map_err(|e| e.to_string()),
+                _ => Err("Invalid project or owner id".to_string()),
+            };
+            match result {
+                Ok(attachment) => IpcResponse::Attachment { attachment },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::AttachmentList {
+            owner_kind,
+            owner_id,
+        } => {
+            let result = match Uuid::parse_str(&owner_id) {
+                Ok(owner_id) => bridge
+                    .attachments
+                    .list_for_owner(owner_kind, owner_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(attachments) => IpcResponse::Attachments { attachments },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::AttachmentUpdate {
+            id,
+            description,
+            export_inclusion,
+        } => {
+            let result = match Uuid::parse_str(&id) {
+                Ok(id) => bridge
+                    .attachments
+                    .update(id, description.as_deref(), export_inclusion)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(true) => IpcResponse::Ack,
+                Ok(false) => IpcResponse::service_error(ErrorEnvelope::new(
+                    ErrorCode::NotFound,
+                    format!("Attachment {} not found", id),
+                )),
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::AttachmentRemove { id } => {
+            let result = match Uuid::parse_str(&id) {
+                Ok(id) => bridge
+                    .attachments
+                    .delete(id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(_) => IpcResponse::Ack,
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,141 @@
+//! Beta reader and annotation requests
+
+use crate::database::models::activity::{ActivityEntry, ActivityKind};
+use crate::database::models::beta_reader::BetaPacket;
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use crate::publishing::PublishFormat;
+use uuid::Uuid;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::BetaReaderAdd {
+            project_id,
+            name,
+            email,
+        } => match bridge
+            .beta_readers
+            .add_reader(project_id, name, email)
+            .await
+        {
+            Ok(reader) => IpcResponse::BetaReader { reader },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::BetaReadersList { project_id } => {
+            match bridge.beta_readers.list_readers(project_id).await {
+                Ok(readers) => IpcResponse::BetaReaders { readers },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::BetaPacketBuild {
+            reader_code,
+            format,
+            path,
+        } => match build_packet(bridge, &reader_code, format, &path).await {
+            Ok(packet) => {
+                let summary = format!("Built beta packet for reader {}", packet.reader_code);
+                bridge
+                    .log_activity(ActivityEntry::new(
+                        Some(packet.project_id),
+                        ActivityKind::Export,
+                        summary,
+                    ))
+                    .await;
+                IpcResponse::BetaPacket { packet, path }
+            }
+            Err(message) => IpcResponse::service_error(message),
+        },
+        IpcMessage::BetaCommentsImport {
+            reader_code,
+            input,
+            format,
+        } => match bridge
+            .beta_readers
+            .import_comments(&reader_code, &input, format)
+            .await
+        {
+            Ok(result) => IpcResponse::BetaCommentsImported { result },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::AnnotationsList { document_id } => {
+            match bridge
+                .annotations
+                .get_annotations_for_document(document_id)
+                .await
+            {
+                Ok(annotations) => IpcResponse::Annotations { annotations },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::AnnotationCreate { annotation } => {
+            match bridge.annotations.create_annotation(&annotation).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::AnnotationResolve {
+            annotation_id,
+            resolved,
+        } => match bridge
+            .annotations
+            .set_resolved(annotation_id, resolved)
+            .await
+        {
+            Ok(true) => IpcResponse::Ack,
+            Ok(false) => IpcResponse::service_error(annotation_not_found(annotation_id)),
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::AnnotationDelete { annotation_id } => {
+            match bridge.annotations.delete_annotation(annotation_id).await {
+                Ok(true) => IpcResponse::Ack,
+                Ok(false) => IpcResponse::service_error(annotation_not_found(annotation_id)),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
+
+fn annotation_not_found(annotation_id: Uuid) -> ErrorEnvelope {
+    ErrorEnvelope::new(
+        ErrorCode::NotFound,
+        format!("Annotation {} not found", annotation_id),
+    )
+}
+
+/// Render a reader's packet, titled after its project, and write it to `path`
+async fn build_packet(
+    bridge: &IpcBridge,
+    reader_code: &str,
+    format: PublishFormat,
+    path: &str,
+) -> Result<BetaPacket, String> {
+    let reader = bridge
+        .beta_readers
+        .get_reader_by_code(reader_code)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Beta reader not found: {}", reader_code))?;
+
+    let db = &bridge.db_service;
+    let (name,): (String,) = sqlx::query_as("SELECT name FROM projects WHERE id = ?1")
+        .bind(reader.project_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| format!("Failed to load project: {}", e))?
+        .ok_or_else(|| format!("Project not found: {}", reader.project_id))?;
+
+    let packet = bridge
+        .beta_readers
+        .build_packet(&reader, &name, format)
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::fs::write(path, &packet.bytes)
+        .await
+        .map_err(|e| format!("Failed to write packet: {}", e))?;
+    Ok(packet)
+}
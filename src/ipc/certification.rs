@@ -0,0 +1,73 @@
+//! Word count certificate requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use uuid::Uuid;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::WordCountCertify { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .certification
+                    .certify(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(certificate) => IpcResponse::WordCountCertificate { certificate },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::WordCountCertificates { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .certification
+                    .list_certificates(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(certificates) => IpcResponse::WordCountCertificates { certificates },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::WordCountCertificateVerify { certificate_id } => {
+            let result = match Uuid::parse_str(&certificate_id) {
+                Ok(certificate_id) => bridge
+                    .certification
+                    .verify(certificate_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(verification) => IpcResponse::CertificateVerification { verification },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::WordCountCertificateExport {
+            certificate_id,
+            path,
+        } => {
+            let result = match Uuid::parse_str(&certificate_id) {
+                Ok(certificate_id) => bridge
+                    .certification
+                    .export_pdf(certificate_id, std::path::Path::new(&path))
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(certificate) => IpcResponse::CertificateExported { certificate, path },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,62 @@
+//! Writing challenge requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use uuid::Uuid;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::ChallengeList { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .challenges
+                    .list_challenges(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(challenges) => IpcResponse::Challenges { challenges },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::ChallengeSave { challenge } => {
+            match bridge.challenges.save_challenge(&challenge).await {
+                Ok(challenge) => IpcResponse::Challenge { challenge },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ChallengeDelete { challenge_id } => {
+            let result = match Uuid::parse_str(&challenge_id) {
+                Ok(challenge_id) => bridge
+                    .challenges
+                    .delete_challenge(challenge_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(_) => IpcResponse::Ack,
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::ChallengeDashboard { challenge_id } => {
+            let result = match Uuid::parse_str(&challenge_id) {
+                Ok(challenge_id) => bridge
+                    .challenges
+                    .dashboard(challenge_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(dashboard) => IpcResponse::ChallengeDashboard { dashboard },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
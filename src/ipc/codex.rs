@@ -0,0 +1,152 @@
+//! Codex graph, autofill, relationship and transfer requests
+
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::CodexGraphQuery { query } => match bridge.codex_graph.query(&query).await {
+            Ok(matches) => IpcResponse::CodexGraphMatches { matches },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::CodexGraphQueryText { project_id, query } => {
+            match bridge.codex_graph.query_text(project_id, &query).await {
+                Ok(matches) => IpcResponse::CodexGraphMatches { matches },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CodexBacklinks {
+            project_id,
+            entry_id,
+        } => match bridge.codex_graph.backlinks(project_id, entry_id).await {
+            Ok(backlinks) => IpcResponse::CodexBacklinks { backlinks },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::CodexAutofillPropose { request } => {
+            match bridge.codex_autofill.propose(&request).await {
+                Ok(proposal) => IpcResponse::CodexAutofillProposal { proposal },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CodexAutofillApply { apply } => {
+            match bridge.codex_autofill.apply(&apply).await {
+                Ok(fields) => {
+                    if !fields.is_empty() {
+                        let summary = format!(
+                            "Filled in {} on '{}' from the manuscript",
+                            fields.join(", "),
+                            apply.proposal.title
+                        );
+                        if let Err(e) = bridge
+                            .activity
+                            .record_codex_change(apply.proposal.entry_id, summary)
+                            .await
+                        {
+                            log::warn!("Failed to record activity: {}", e);
+                        }
+                    }
+                    IpcResponse::CodexAutofillApplied { fields }
+                }
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CodexRelationshipSave { relationship } => {
+            match bridge.codex_relationships.save(&relationship).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CodexRelationshipDelete { relationship_id } => {
+            match bridge.codex_relationships.delete(relationship_id).await {
+                Ok(true) => IpcResponse::Ack,
+                Ok(false) => IpcResponse::service_error(ErrorEnvelope::new(
+                    ErrorCode::NotFound,
+                    format!("Relationship {} not found", relationship_id),
+                )),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CodexRelationshipMap { project_id } => {
+            match bridge.codex_relationships.map(project_id).await {
+                Ok(map) => IpcResponse::CodexRelationshipMap { map },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CodexNeighbors {
+            project_id,
+            entry_id,
+            direction,
+            types,
+        } => {
+            match bridge
+                .codex_relationships
+                .neighbors(project_id, entry_id, direction, &types)
+                .await
+            {
+                Ok(neighbors) => IpcResponse::CodexNeighbors { neighbors },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CodexPath {
+            project_id,
+            from,
+            to,
+            direction,
+        } => {
+            match bridge
+                .codex_relationships
+                .path(project_id, from, to, direction)
+                .await
+            {
+                Ok(path) => IpcResponse::CodexPath { path },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CodexComponent {
+            project_id,
+            entry_id,
+        } => {
+            match bridge
+                .codex_relationships
+                .component(project_id, entry_id)
+                .await
+            {
+                Ok(map) => IpcResponse::CodexRelationshipMap { map },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CodexExport {
+            project_id,
+            path,
+            format,
+        } => {
+            match bridge
+                .codex_transfer
+                .export_to(project_id, format, std::path::Path::new(&path))
+                .await
+            {
+                Ok(result) => IpcResponse::CodexExported { result },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CodexImport {
+            project_id,
+            path,
+            options,
+        } => {
+            match bridge
+                .codex_transfer
+                .import_file(project_id, std::path::Path::new(&path), &options)
+                .await
+            {
+                Ok(result) => IpcResponse::CodexImported { result },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,46 @@
+//! Command palette and context menu requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use crate::security::network::{self};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::CommandList { mut context } => {
+            context.offline |= network::is_offline();
+            IpcResponse::Commands {
+                commands: bridge.command_registry.palette(&context),
+            }
+        }
+        IpcMessage::CommandSearch {
+            query,
+            mut context,
+            limit,
+        } => {
+            context.offline |= network::is_offline();
+            let commands = bridge
+                .command_registry
+                .search(&query, &context, limit.unwrap_or(20));
+            IpcResponse::Commands { commands }
+        }
+        IpcMessage::CommandMenu {
+            location,
+            mut context,
+        } => {
+            context.offline |= network::is_offline();
+            IpcResponse::Commands {
+                commands: bridge.command_registry.menu(location, &context),
+            }
+        }
+        IpcMessage::CommandInvoked { command_id } => {
+            match bridge.command_registry.record_use(&command_id) {
+                Ok(()) => IpcResponse::Ack,
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,46 @@
+//! Content scan requests
+
+use crate::database::content_scan_service::render_front_matter_markdown;
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::ContentLexicons { project_id } => {
+            match bridge.content_scan.get_lexicons(project_id).await {
+                Ok(lexicons) => IpcResponse::ContentLexicons { lexicons },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ContentLexiconSave { lexicon } => {
+            match bridge.content_scan.save_lexicon(&lexicon).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ContentLexiconDelete { lexicon_id } => {
+            match bridge.content_scan.delete_lexicon(lexicon_id).await {
+                Ok(true) => IpcResponse::Ack,
+                Ok(false) => IpcResponse::service_error(ErrorEnvelope::new(
+                    ErrorCode::NotFound,
+                    format!("Lexicon {} not found", lexicon_id),
+                )),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ContentScan { project_id } => {
+            match bridge.content_scan.scan_project(project_id).await {
+                Ok(report) => IpcResponse::ContentScan {
+                    front_matter: render_front_matter_markdown(&report),
+                    report,
+                },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,62 @@
+//! Deadline requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use uuid::Uuid;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::DeadlineList { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .deadlines
+                    .list_deadlines(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(deadlines) => IpcResponse::Deadlines { deadlines },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::DeadlineSave { deadline } => {
+            match bridge.deadlines.save_deadline(&deadline).await {
+                Ok(deadline) => IpcResponse::Deadline { deadline },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DeadlineDelete { deadline_id } => {
+            let result = match Uuid::parse_str(&deadline_id) {
+                Ok(deadline_id) => bridge
+                    .deadlines
+                    .delete_deadline(deadline_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(_) => IpcResponse::Ack,
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::DeadlineCountdowns { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .deadlines
+                    .countdowns(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(countdowns) => IpcResponse::DeadlineCountdowns { countdowns },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
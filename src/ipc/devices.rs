@@ -0,0 +1,112 @@
+//! Send-to-device requests
+
+use crate::database::models::activity::{ActivityEntry, ActivityKind};
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use crate::publishing::{PublishFormat, PublishedDocument, PublishedSection};
+use crate::send_to_device::{self, DeviceTarget, SendJob};
+use uuid::Uuid;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::DeviceList => {
+            match tokio::task::spawn_blocking(send_to_device::detect_devices).await {
+                Ok(devices) => IpcResponse::Devices { devices },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DeviceSmtpGet => match bridge.device_sender.smtp_settings() {
+            Ok(settings) => IpcResponse::SmtpSettings { settings },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::DeviceSmtpSave { settings } => {
+            match bridge.device_sender.save_smtp_settings(&settings) {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DeviceSend { project_id, target } => {
+            match send_to_device(bridge, &project_id, target).await {
+                Ok(job) => {
+                    let summary = format!("Sent '{}' to a device", job.title);
+                    bridge
+                        .log_activity(ActivityEntry::new(
+                            Uuid::parse_str(&project_id).ok(),
+                            ActivityKind::Export,
+                            summary,
+                        ))
+                        .await;
+                    IpcResponse::SendJob { job }
+                }
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::DeviceSendStatus { job_id } => match bridge.device_sender.job(job_id) {
+            Some(job) => IpcResponse::SendJob { job },
+            None => IpcResponse::service_error(ErrorEnvelope::new(
+                ErrorCode::NotFound,
+                format!("Unknown send job: {}", job_id),
+            )),
+        },
+        IpcMessage::DeviceSendJobs => IpcResponse::SendJobs {
+            jobs: bridge.device_sender.jobs(),
+        },
+        other => return Err(other),
+    };
+    Ok(response)
+}
+
+/// Start a job that exports the project's manuscript, in binder order,
+/// to ePub and sends it to a device
+async fn send_to_device(
+    bridge: &IpcBridge,
+    project_id: &str,
+    target: DeviceTarget,
+) -> Result<SendJob, String> {
+    let db = &bridge.db_service;
+    let (name,): (String,) = sqlx::query_as("SELECT name FROM projects WHERE id = ?1")
+        .bind(project_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| format!("Failed to load project: {}", e))?
+        .ok_or_else(|| format!("Project not found: {}", project_id))?;
+
+    let has_binder: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'binder_order'",
+    )
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| format!("Failed to check schema: {}", e))?;
+    let sql = if has_binder > 0 {
+        "SELECT d.title, d.content FROM documents d LEFT JOIN binder_order b ON b.document_id = d.id
+             WHERE d.project_id = ?1 AND d.is_active = 1
+             ORDER BY b.position IS NULL, b.position, d.created_at, d.title"
+    } else {
+        "SELECT title, content FROM documents WHERE project_id = ?1 AND is_active = 1 ORDER BY created_at, title"
+    };
+    let documents: Vec<(String, Option<String>)> = sqlx::query_as(sql)
+        .bind(project_id)
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| format!("Failed to load documents: {}", e))?;
+    if documents.is_empty() {
+        return Err("The project has no documents to send".to_string());
+    }
+
+    let mut book = PublishedDocument::new(name.clone());
+    for (title, content) in documents {
+        book.sections.push(PublishedSection::from_text(
+            title,
+            &content.unwrap_or_default(),
+        ));
+    }
+    bridge
+        .device_sender
+        .start(&name, target, move || {
+            book.render(PublishFormat::Epub).map_err(|e| e.to_string())
+        })
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,31 @@
+//! Binder order, split and merge requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::BinderOrderGet { project_id } => {
+            match bridge.document_structure.binder_order(project_id).await {
+                Ok(document_ids) => IpcResponse::BinderOrder { document_ids },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentSplit { request } => {
+            match bridge.document_structure.split(&request).await {
+                Ok(result) => IpcResponse::DocumentSplit { result },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentsMerge { request } => {
+            match bridge.document_structure.merge(&request).await {
+                Ok(result) => IpcResponse::DocumentsMerged { result },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,137 @@
+//! Document version, trash, move and copy requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::DocumentVersions { document_id } => {
+            match bridge.db_service.list_document_versions(document_id).await {
+                Ok(versions) => IpcResponse::DocumentVersions { versions },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentVersionDiff {
+            document_id,
+            from_version,
+            to_version,
+        } => {
+            match bridge
+                .db_service
+                .diff_document_versions(document_id, from_version, to_version)
+                .await
+            {
+                Ok(diff) => IpcResponse::DocumentVersionDiff { diff },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentRevert {
+            document_id,
+            version,
+        } => {
+            match bridge
+                .db_service
+                .revert_document_to_version(document_id, version)
+                .await
+            {
+                Ok(version) => IpcResponse::DocumentReverted { version },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentMerge {
+            document_id,
+            base_version,
+            content,
+        } => {
+            match bridge
+                .db_service
+                .merge_document_edit(document_id, base_version, &content)
+                .await
+            {
+                Ok(merge) => IpcResponse::DocumentMerged { merge },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentTrash { document_id } => {
+            match bridge.db_service.delete_document(document_id).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ProjectTrash { project_id } => {
+            match bridge.db_service.trash_project(&project_id).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::TrashList { project_id } => {
+            match bridge.db_service.list_trash(project_id.as_deref()).await {
+                Ok(items) => IpcResponse::Trash { items },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::TrashRestore { kind, id } => {
+            match bridge.db_service.restore_from_trash(kind, &id).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::TrashPurge {
+            kind,
+            id,
+            confirmation,
+        } => {
+            match bridge
+                .db_service
+                .purge_from_trash(kind, &id, confirmation.as_deref())
+                .await
+            {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::TrashEmpty {
+            project_id,
+            confirmation,
+        } => {
+            match bridge
+                .db_service
+                .empty_trash(project_id.as_deref(), confirmation.as_deref())
+                .await
+            {
+                Ok(removed) => IpcResponse::TrashEmptied { removed },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentMove {
+            document_id,
+            project_id,
+        } => {
+            match bridge
+                .db_service
+                .move_document_to_project(&document_id, &project_id)
+                .await
+            {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentCopy {
+            document_id,
+            project_id,
+        } => {
+            match bridge
+                .db_service
+                .copy_document_to_project(&document_id, &project_id)
+                .await
+            {
+                Ok(document_id) => IpcResponse::DocumentCopied { document_id },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
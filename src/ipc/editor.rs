@@ -0,0 +1,27 @@
+//! Cursor and autosave requests, coalesced before they reach the database
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::CursorPosition {
+            document_id,
+            offset,
+        } => {
+            bridge.cursor_debouncer.submit(&document_id, offset);
+            IpcResponse::Ack
+        }
+        IpcMessage::AutosavePing {
+            document_id,
+            content,
+        } => {
+            bridge.autosave_debouncer.submit(&document_id, content);
+            IpcResponse::Ack
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,35 @@
+//! Focus analytics requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::FocusStatus => match bridge.focus.status().await {
+            Ok(status) => IpcResponse::FocusStatus { status },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::FocusEnable { enabled } => {
+            let result = match bridge.focus.set_enabled(enabled) {
+                Ok(()) => bridge.focus.status().await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(status) => IpcResponse::FocusStatus { status },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::FocusSummaries { days } => match bridge.focus.daily_summaries(days).await {
+            Ok(summaries) => IpcResponse::FocusSummaries { summaries },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::FocusClear => match bridge.focus.clear().await {
+            Ok(_) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        other => return Err(other),
+    };
+    Ok(response)
+}
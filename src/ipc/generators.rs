@@ -0,0 +1,49 @@
+//! Random generator table requests
+
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::GeneratorTables { project_id } => {
+            match bridge.generators.tables(project_id).await {
+                Ok(tables) => IpcResponse::GeneratorTables { tables },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::GeneratorSave { table } => match bridge.generators.save_table(&table).await {
+            Ok(()) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::GeneratorDelete { table_id } => {
+            match bridge.generators.delete_table(table_id).await {
+                Ok(true) => IpcResponse::Ack,
+                Ok(false) => IpcResponse::service_error(ErrorEnvelope::new(
+                    ErrorCode::NotFound,
+                    format!("Generator table {} not found", table_id),
+                )),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::GeneratorRoll {
+            project_id,
+            table,
+            variables,
+            count,
+        } => {
+            match bridge
+                .generators
+                .roll(project_id, &table, &variables, count.unwrap_or(1))
+                .await
+            {
+                Ok(results) => IpcResponse::GeneratorResults { results },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
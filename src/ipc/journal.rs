@@ -0,0 +1,59 @@
+//! Journal requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use chrono::Local;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::JournalSettings { profile_id } => {
+            match bridge.journal.settings(profile_id).await {
+                Ok(settings) => IpcResponse::JournalSettings { settings },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::JournalSettingsSave { settings } => {
+            match bridge.journal.save_settings(&settings).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::JournalToday { profile_id, day } => {
+            let day = day.unwrap_or_else(|| Local::now().date_naive());
+            match bridge.journal.entry(profile_id, day).await {
+                Ok(entry) => IpcResponse::JournalEntry { entry },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::JournalAppend {
+            profile_id,
+            day,
+            text,
+        } => {
+            let day = day.unwrap_or_else(|| Local::now().date_naive());
+            match bridge.journal.append(profile_id, day, &text).await {
+                Ok(entry) => IpcResponse::JournalEntry { entry },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::JournalEntries {
+            profile_id,
+            from,
+            to,
+        } => match bridge.journal.entries(profile_id, from, to).await {
+            Ok(entries) => IpcResponse::JournalEntries { entries },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::JournalStreak { profile_id, day } => {
+            let day = day.unwrap_or_else(|| Local::now().date_naive());
+            match bridge.journal.streak(profile_id, day).await {
+                Ok(streak) => IpcResponse::JournalStreak { streak },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,110 @@
+//! Conlang and lexicon requests
+
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::ConlangsList { project_id } => {
+            match bridge.lexicons.list_languages(project_id).await {
+                Ok(languages) => IpcResponse::Conlangs { languages },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ConlangsForCulture { culture_entry_id } => {
+            match bridge
+                .lexicons
+                .languages_for_culture(culture_entry_id)
+                .await
+            {
+                Ok(languages) => IpcResponse::Conlangs { languages },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ConlangSave { language } => {
+            match bridge.lexicons.save_language(&language).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ConlangDelete { language_id } => {
+            match bridge.lexicons.delete_language(language_id).await {
+                Ok(true) => IpcResponse::Ack,
+                Ok(false) => IpcResponse::service_error(ErrorEnvelope::new(
+                    ErrorCode::NotFound,
+                    format!("Language {} not found", language_id),
+                )),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::LexiconEntries { language_id } => {
+            match bridge.lexicons.entries(language_id).await {
+                Ok(entries) => IpcResponse::LexiconEntries { entries },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::LexiconEntrySave { entry } => match bridge.lexicons.save_entry(&entry).await {
+            Ok(()) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::LexiconEntryDelete { entry_id } => {
+            match bridge.lexicons.delete_entry(entry_id).await {
+                Ok(true) => IpcResponse::Ack,
+                Ok(false) => IpcResponse::service_error(ErrorEnvelope::new(
+                    ErrorCode::NotFound,
+                    format!("Lexicon entry {} not found", entry_id),
+                )),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::LexiconSearch { query } => match bridge.lexicons.search(&query).await {
+            Ok(entries) => IpcResponse::LexiconEntries { entries },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::LexiconExportCsv { language_id, path } => {
+            let result = match bridge.lexicons.export_csv(language_id).await {
+                Ok(csv) => write_export(&path, csv.as_bytes()).await,
+                Err(e) => Err(e.to_string()),
+            };
+            exported(result, path)
+        }
+        IpcMessage::LexiconExportAppendix { language_id, path } => {
+            let result = match bridge.lexicons.export_pdf_appendix(language_id).await {
+                Ok(pdf) => write_export(&path, &pdf).await,
+                Err(e) => Err(e.to_string()),
+            };
+            exported(result, path)
+        }
+        IpcMessage::LexiconSpellcheckWords { project_id } => {
+            match bridge.lexicons.spellcheck_words(project_id).await {
+                Ok(words) => IpcResponse::LexiconWords { words },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::LexiconDictionaryExport { project_id, path } => {
+            let result = match bridge.lexicons.spellcheck_dictionary(project_id).await {
+                Ok(dic) => write_export(&path, dic.as_bytes()).await,
+                Err(e) => Err(e.to_string()),
+            };
+            exported(result, path)
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
+
+async fn write_export(path: &str, bytes: &[u8]) -> Result<(), String> {
+    tokio::fs::write(path, bytes)
+        .await
+        .map_err(|e| format!("Failed to write lexicon export: {}", e))
+}
+
+fn exported(result: Result<(), String>, path: String) -> IpcResponse {
+    match result {
+        Ok(()) => IpcResponse::LexiconExported { path },
+        Err(message) => IpcResponse::service_error(message),
+    }
+}
@@ -0,0 +1,112 @@
+//! IPC request handlers, one module per feature
+//!
+//! `IpcBridge::dispatch` hands each message to `handle`, which offers it
+//! to every feature module in turn; a module answers the messages it owns
+//! and gives the rest back.
+
+mod activity;
+mod ai_log;
+mod analysis;
+mod app;
+mod attachments;
+mod beta_readers;
+mod certification;
+mod challenges;
+mod codex;
+mod commands;
+mod content_scan;
+mod deadlines;
+mod devices;
+mod document_structure;
+mod documents;
+mod editor;
+mod focus;
+mod generators;
+mod journal;
+mod lexicons;
+mod notes;
+mod printing;
+mod profiles;
+mod projects;
+mod publishing;
+mod renames;
+mod search;
+mod security;
+mod serial;
+mod stats;
+mod style_sheets;
+mod submissions;
+mod sync;
+mod templates;
+mod timeline;
+mod undo;
+mod word_usage;
+mod workspaces;
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+/// Offer `$message` to each feature module, returning the first answer
+macro_rules! route {
+    ($bridge:expr, $message:expr, [$($feature:ident),+ $(,)?]) => {{
+        let message = $message;
+        $(
+            let message = match $feature::handle($bridge, message).await {
+                Ok(response) => return Ok(response),
+                Err(message) => message,
+            };
+        )+
+        Err(message)
+    }};
+}
+
+/// Answer a message from the feature module that owns it; messages no
+/// feature owns are given back for the bridge to answer
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    route!(
+        bridge,
+        message,
+        [
+            security,
+            editor,
+            printing,
+            app,
+            attachments,
+            publishing,
+            devices,
+            generators,
+            stats,
+            codex,
+            search,
+            analysis,
+            submissions,
+            deadlines,
+            serial,
+            certification,
+            challenges,
+            focus,
+            workspaces,
+            undo,
+            activity,
+            projects,
+            documents,
+            ai_log,
+            commands,
+            templates,
+            journal,
+            sync,
+            timeline,
+            notes,
+            profiles,
+            style_sheets,
+            content_scan,
+            beta_readers,
+            word_usage,
+            renames,
+            document_structure,
+            lexicons
+        ]
+    )
+}
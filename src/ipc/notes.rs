@@ -0,0 +1,43 @@
+//! Note import and backlink requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::ObsidianImport {
+            project_id,
+            vault,
+            options,
+        } => {
+            match bridge
+                .note_import
+                .import_obsidian(project_id, std::path::Path::new(&vault), &options)
+                .await
+            {
+                Ok(report) => IpcResponse::NotesImported { report },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::NotionImport { path, options } => {
+            match bridge
+                .note_import
+                .import_notion(std::path::Path::new(&path), &options)
+                .await
+            {
+                Ok(report) => IpcResponse::NotesImported { report },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentBacklinks { document_id } => {
+            match bridge.note_import.backlinks(document_id).await {
+                Ok(backlinks) => IpcResponse::DocumentBacklinks { backlinks },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,64 @@
+//! Printer requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use crate::printing::{self, PrintJob, PrintOptions};
+use crate::publishing::{PublishedDocument, PublishedSection};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::ListPrinters => {
+            match tokio::task::spawn_blocking(printing::list_printers).await {
+                Ok(Ok(printers)) => IpcResponse::Printers { printers },
+                Ok(Err(e)) => IpcResponse::service_error(e),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::PrintDocument {
+            document_id,
+            printer,
+            copies,
+        } => match print_document(bridge, &document_id, printer, copies.unwrap_or(1)).await {
+            Ok(job) => IpcResponse::PrintJob { job },
+            Err(message) => IpcResponse::service_error(message),
+        },
+        other => return Err(other),
+    };
+    Ok(response)
+}
+
+/// Render a document to PDF and hand it to the OS print subsystem
+async fn print_document(
+    bridge: &IpcBridge,
+    document_id: &str,
+    printer: Option<String>,
+    copies: u32,
+) -> Result<PrintJob, String> {
+    let db = &bridge.db_service;
+    let (title, content): (String, Option<String>) =
+        sqlx::query_as("SELECT title, content FROM documents WHERE id = ?1 AND is_active = 1")
+            .bind(document_id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| format!("Failed to load document: {}", e))?
+            .ok_or_else(|| format!("Document not found: {}", document_id))?;
+
+    let mut document = PublishedDocument::new(title.clone());
+    document.sections.push(PublishedSection::from_text(
+        title.clone(),
+        &content.unwrap_or_default(),
+    ));
+    let pdf = document.render_pdf();
+
+    let options = PrintOptions {
+        printer,
+        copies,
+        title,
+    };
+    tokio::task::spawn_blocking(move || printing::print_pdf(&pdf, &options))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
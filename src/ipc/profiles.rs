@@ -0,0 +1,90 @@
+//! Profile and app-lock requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let profiles = &bridge.profiles;
+    let response = match message {
+        IpcMessage::ProfilesList => match profiles.list_profiles().await {
+            Ok(list) => IpcResponse::Profiles {
+                profiles: list,
+                active: profiles.active_profile().await.map(|p| p.id),
+            },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::ProfileCreate { name, role } => {
+            match profiles.create_profile(name, role).await {
+                Ok(profile) => IpcResponse::Profile {
+                    profile: Box::new(profile),
+                },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ProfileSwitch {
+            profile_id,
+            passphrase,
+        } => match profiles
+            .switch_profile(profile_id, passphrase.as_deref())
+            .await
+        {
+            Ok(profile) => IpcResponse::Profile {
+                profile: Box::new(profile),
+            },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::ProfileDelete {
+            profile_id,
+            passphrase,
+        } => match profiles
+            .delete_profile(profile_id, passphrase.as_deref())
+            .await
+        {
+            Ok(_) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::ProfileSettingsSave {
+            profile_id,
+            settings,
+        } => match profiles.save_profile_settings(profile_id, &settings).await {
+            Ok(()) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::ProfileAiBudgetSet {
+            profile_id,
+            budget_cents,
+            passphrase,
+        } => match profiles
+            .set_ai_budget(profile_id, budget_cents, passphrase.as_deref())
+            .await
+        {
+            Ok(()) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::ProfileProjectGrant {
+            profile_id,
+            project_id,
+        } => match profiles.grant_project(profile_id, project_id).await {
+            Ok(()) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::ProfileProjectRevoke {
+            profile_id,
+            project_id,
+        } => match profiles.revoke_project(profile_id, project_id).await {
+            Ok(()) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::AppLockSet {
+            current,
+            passphrase,
+        } => match profiles.set_app_lock(current.as_deref(), &passphrase).await {
+            Ok(()) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        other => return Err(other),
+    };
+    Ok(response)
+}
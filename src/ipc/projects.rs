@@ -0,0 +1,97 @@
+//! Project file, anonymized copy and backup requests
+
+use crate::database::models::activity::{ActivityEntry, ActivityKind};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use crate::project_file::ProjectFile;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::ProjectFileCreate { project_id, path } => {
+            match create_project_file(bridge, &project_id, &path).await {
+                Ok(written) => IpcResponse::ProjectFile {
+                    path: written.to_string_lossy().into_owned(),
+                },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::ProjectAnonymize { request } => {
+            match bridge.anonymizer.anonymize(&request).await {
+                Ok(project) => {
+                    let summary = format!("Created anonymized sample '{}'", project.name);
+                    bridge
+                        .log_activity(ActivityEntry::new(
+                            Some(request.project_id),
+                            ActivityKind::Export,
+                            summary,
+                        ))
+                        .await;
+                    IpcResponse::ProjectAnonymized { project }
+                }
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::BackupList { project_id, limit } => {
+            match bridge
+                .backups
+                .list_backups(project_id.as_deref(), limit)
+                .await
+            {
+                Ok(backups) => IpcResponse::Backups { backups },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::BackupVerify { backup_id } => {
+            match bridge.backups.verify_backup(&backup_id).await {
+                Ok(verification) => IpcResponse::BackupVerification { verification },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::BackupVerifyAll { project_id, limit } => {
+            match bridge
+                .backups
+                .verify_backups(project_id.as_deref(), limit)
+                .await
+            {
+                Ok(verifications) => IpcResponse::BackupVerifications { verifications },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::BackupSetEncryption {
+            encrypt,
+            confirmation,
+        } => {
+            match bridge
+                .backups
+                .set_encryption(encrypt, confirmation.as_deref())
+                .await
+            {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
+
+/// Write a `.hcats` file that reopens `project_id` from this library
+async fn create_project_file(
+    bridge: &IpcBridge,
+    project_id: &str,
+    path: &str,
+) -> Result<std::path::PathBuf, String> {
+    let db = &bridge.db_service;
+    let (name,): (String,) = sqlx::query_as("SELECT name FROM projects WHERE id = ?1")
+        .bind(project_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| format!("Failed to load project: {}", e))?
+        .ok_or_else(|| format!("Project not found: {}", project_id))?;
+
+    let file = ProjectFile::new(project_id, &name, db.get_database_path());
+    file.save(std::path::Path::new(path))
+        .map_err(|e| e.to_string())
+}
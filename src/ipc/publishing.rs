@@ -0,0 +1,111 @@
+//! Cover and share page requests
+
+use crate::asset_store::AssetStore;
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use crate::publishing::{render_share_bundle, PublishedDocument, PublishedSection, ShareOptions};
+use chrono::{DateTime, Utc};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::CoverPalette {
+            asset_hash,
+            max_colors,
+        } => {
+            let result = tokio::task::spawn_blocking(move || {
+                let bytes = AssetStore::open_default()
+                    .read(&asset_hash)
+                    .map_err(|e| e.to_string())?;
+                crate::publishing::extract_palette(&bytes, max_colors.unwrap_or(6))
+                    .map_err(|e| e.to_string())
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+            match result {
+                Ok(palette) => IpcResponse::Palette { palette },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::CoverRender {
+            design,
+            trim_size,
+            path,
+        } => {
+            let result = tokio::task::spawn_blocking(move || {
+                let store = AssetStore::open_default();
+                let cover = design
+                    .render(trim_size, &store)
+                    .map_err(|e| e.to_string())?;
+                if let Some(path) = path {
+                    std::fs::write(&path, &cover.data).map_err(|e| e.to_string())?;
+                }
+                let stored = store.put_bytes(&cover.data).map_err(|e| e.to_string())?;
+                Ok::<_, String>((stored.hash, cover))
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+            match result {
+                Ok((asset_hash, cover)) => IpcResponse::Cover {
+                    asset_hash,
+                    width: cover.width,
+                    height: cover.height,
+                    dpi: cover.dpi,
+                },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::DocumentShare {
+            document_id,
+            path,
+            options,
+        } => match share_document(bridge, &document_id, &path, &options).await {
+            Ok((encrypted, expires_at)) => IpcResponse::DocumentShared {
+                path,
+                encrypted,
+                expires_at,
+            },
+            Err(message) => IpcResponse::service_error(message),
+        },
+        other => return Err(other),
+    };
+    Ok(response)
+}
+
+/// Write a document as a bridge-contained share page; returns whether it
+/// is encrypted and when it expires
+async fn share_document(
+    bridge: &IpcBridge,
+    document_id: &str,
+    path: &str,
+    options: &ShareOptions,
+) -> Result<(bool, Option<DateTime<Utc>>), String> {
+    let db = &bridge.db_service;
+    let (title, content): (String, Option<String>) =
+        sqlx::query_as("SELECT title, content FROM documents WHERE id = ?1 AND is_active = 1")
+            .bind(document_id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|e| format!("Failed to load document: {}", e))?
+            .ok_or_else(|| format!("Document not found: {}", document_id))?;
+
+    // The page title names the document, so its one section goes untitled
+    let mut document = PublishedDocument::new(title);
+    document.sections.push(PublishedSection::from_text(
+        String::new(),
+        &content.unwrap_or_default(),
+    ));
+    let options = options.clone();
+    // Deriving the key takes a moment at the default strength
+    let bundle = tokio::task::spawn_blocking(move || render_share_bundle(&document, &options))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    tokio::fs::write(path, &bundle.html)
+        .await
+        .map_err(|e| format!("Failed to write share file: {}", e))?;
+    Ok((bundle.encrypted, bundle.expires_at))
+}
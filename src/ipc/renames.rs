@@ -0,0 +1,44 @@
+//! Project-wide rename requests
+
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::RenamePreview { request } => match bridge.renames.preview(&request).await {
+            Ok(preview) => IpcResponse::RenamePreview { preview },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::RenameApply { request } => match bridge.renames.apply(&request).await {
+            Ok(operation) => IpcResponse::RenameOperation { operation },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::RenameRollback { operation_id } => {
+            match bridge.renames.rollback(operation_id).await {
+                Ok(rollback) => IpcResponse::RenameRollback { rollback },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::RenameGet { operation_id } => {
+            match bridge.renames.get_operation(operation_id).await {
+                Ok(Some(operation)) => IpcResponse::RenameOperation { operation },
+                Ok(None) => IpcResponse::service_error(ErrorEnvelope::new(
+                    ErrorCode::NotFound,
+                    format!("Rename {} not found", operation_id),
+                )),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::RenamesList { project_id } => {
+            match bridge.renames.list_operations(project_id).await {
+                Ok(operations) => IpcResponse::Renames { operations },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
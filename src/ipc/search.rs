@@ -0,0 +1,161 @@
+//! Embedding, semantic search and ask-the-project requests
+
+use crate::database::models::activity::{ActivityEntry, ActivityKind};
+use crate::database::models::stats::AiUsageRecord;
+use crate::database::vector_embedding::{DuplicateOptions, SearchOptions as SemanticSearchOptions};
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::EmbeddingModels => match bridge.embeddings.list_models().await {
+            Ok(models) => IpcResponse::EmbeddingModels { models },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::EmbeddingModelRegister {
+            name,
+            dimensions,
+            provider,
+        } => {
+            let result = match bridge
+                .embeddings
+                .register_model(&name, dimensions, &provider)
+                .await
+            {
+                Ok(_) => bridge.embeddings.list_models().await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(models) => IpcResponse::EmbeddingModels { models },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::EmbeddingMigrationStart { model, prune_old } => {
+            match bridge.embeddings.start_migration(&model, prune_old).await {
+                Ok(migration) => {
+                    // Re-embedding the corpus takes a while; the frontend
+                    // polls embedding_migration_status for progress
+                    let embeddings = bridge.embeddings.clone();
+                    let migration_id = migration.id;
+                    tokio::spawn(async move {
+                        if let Err(e) = embeddings.run_migration(migration_id).await {
+                            log::error!("Embedding migration {} failed: {}", migration_id, e);
+                        }
+                    });
+                    IpcResponse::EmbeddingMigration {
+                        migration: Some(migration),
+                    }
+                }
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::EmbeddingMigrationStatus { migration_id } => {
+            let result = match migration_id {
+                Some(id) => bridge.embeddings.migration(id).await,
+                None => bridge.embeddings.active_migration().await,
+            };
+            match result {
+                Ok(migration) => IpcResponse::EmbeddingMigration { migration },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::EmbeddingMigrationCancel { migration_id } => {
+            match bridge.embeddings.cancel_migration(migration_id).await {
+                Ok(true) => IpcResponse::Ack,
+                Ok(false) => IpcResponse::service_error(ErrorEnvelope::new(
+                    ErrorCode::NotFound,
+                    format!("No running embedding migration {}", migration_id),
+                )),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::EmbeddingSearch {
+            query,
+            model,
+            limit,
+            document_id,
+        } => {
+            let options = SemanticSearchOptions {
+                limit: limit.unwrap_or(10),
+                similarity_threshold: 0.0,
+                include_metadata: false,
+                model_filter: model,
+                document_filter: document_id,
+            };
+            match bridge
+                .embeddings
+                .find_similar_documents(&query, Some(options))
+                .await
+            {
+                Ok(results) => IpcResponse::SemanticResults { results },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::HybridSearch { request } => match bridge.hybrid_search.search(&request).await {
+            Ok(results) => IpcResponse::HybridResults { results },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::FindDuplicateParagraphs {
+            project_id,
+            threshold,
+            min_words,
+            model,
+            limit,
+        } => {
+            let defaults = DuplicateOptions::default();
+            let options = DuplicateOptions {
+                threshold: threshold.unwrap_or(defaults.threshold),
+                min_words: min_words.unwrap_or(defaults.min_words),
+                model,
+                limit,
+            };
+            match bridge
+                .embeddings
+                .find_near_duplicate_paragraphs(project_id, options)
+                .await
+            {
+                Ok(pairs) => IpcResponse::DuplicateParagraphs { pairs },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::RelatedNotes { request } => {
+            match bridge.related_notes.related(&request).await {
+                Ok(related) => IpcResponse::RelatedNotes { related },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::AskProject { request } => match bridge.ask.ask_project(&request).await {
+            Ok(answer) => {
+                if answer.model.is_some() {
+                    let mut usage = AiUsageRecord::new(Some(request.project_id), "ask_project");
+                    usage.model = answer.model.clone();
+                    usage.prompt_chars = answer.question.chars().count() as i64
+                        + answer
+                            .sources
+                            .iter()
+                            .map(|s| s.excerpt.chars().count() as i64)
+                            .sum::<i64>();
+                    usage.response_chars = answer.answer.chars().count() as i64;
+                    if let Err(e) = bridge.stats.record_ai_usage(&usage).await {
+                        log::warn!("Failed to record AI usage: {}", e);
+                    }
+                }
+                let question: String = answer.question.chars().take(80).collect();
+                bridge
+                    .log_activity(ActivityEntry::new(
+                        Some(request.project_id),
+                        ActivityKind::Ai,
+                        format!("Asked: {}", question),
+                    ))
+                    .await;
+                IpcResponse::ProjectAnswer { answer }
+            }
+            Err(e) => IpcResponse::service_error(e),
+        },
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,97 @@
+//! Credentials, network, clipboard and threat requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use crate::security::network::{self, NetworkClient};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::CredentialAdd {
+            provider,
+            key,
+            expires_at,
+        } => {
+            match bridge
+                .credential_manager
+                .add_credential(provider, &key, expires_at)
+                .await
+            {
+                Ok(credential) => IpcResponse::Credential { credential },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CredentialTest { provider } => {
+            match bridge.credential_manager.test_credential(provider).await {
+                Ok(credential) => IpcResponse::Credential { credential },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CredentialRemove { provider } => {
+            match bridge.credential_manager.remove_credential(provider) {
+                Ok(removed) => IpcResponse::CredentialRemoved { removed },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::CredentialList => match bridge.credential_manager.list_credentials() {
+            Ok(credentials) => {
+                let warnings = credentials
+                    .iter()
+                    .filter_map(|c| c.expiry_warning())
+                    .collect();
+                IpcResponse::CredentialList {
+                    credentials,
+                    warnings,
+                }
+            }
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::NetworkSetOffline { offline } => {
+            network::set_offline(offline);
+            IpcResponse::Ack
+        }
+        IpcMessage::NetworkStatus => IpcResponse::NetworkStatus {
+            offline: network::is_offline(),
+            allow_list: NetworkClient::global().allow_list(),
+        },
+        IpcMessage::SecureCopy {
+            text,
+            classification,
+            source,
+        } => {
+            match bridge
+                .secure_clipboard
+                .copy(&text, classification, source.as_deref())
+            {
+                Ok(receipt) => IpcResponse::SecureCopy { receipt },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ThreatAlerts => IpcResponse::ThreatAlerts {
+            alerts: bridge.threat_detector.alerts(),
+        },
+        IpcMessage::ThreatConfirm { token } => {
+            if bridge.threat_detector.confirm(&token) {
+                IpcResponse::Ack
+            } else {
+                IpcResponse::service_error("Unknown or expired confirmation token".to_string())
+            }
+        }
+        IpcMessage::SecurityEvents => IpcResponse::SecurityEvents {
+            events: bridge.security_events.events(),
+        },
+        IpcMessage::DestructiveConfirm { operation, proof } => {
+            match bridge.confirmation_guard.confirm(operation, &proof) {
+                Ok(grant) => IpcResponse::ConfirmationGrant { grant },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ClipboardClear => match bridge.secure_clipboard.clear_now() {
+            Ok(()) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        other => return Err(other),
+    };
+    Ok(response)
+}
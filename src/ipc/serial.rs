@@ -0,0 +1,79 @@
+//! Serial release plan requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use uuid::Uuid;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::SerialPlan { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .serial
+                    .release_plan(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(plan) => IpcResponse::SerialPlan { plan },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::SerialReleaseSave { release } => {
+            match bridge.serial.save_release(&release).await {
+                Ok(release) => IpcResponse::SerialRelease { release },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::SerialReleaseDelete { release_id } => {
+            let result = match Uuid::parse_str(&release_id) {
+                Ok(release_id) => bridge
+                    .serial
+                    .delete_release(release_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(_) => IpcResponse::Ack,
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::SerialReleaseStage { release_id } => {
+            let result = match Uuid::parse_str(&release_id) {
+                Ok(release_id) => bridge
+                    .serial
+                    .stage_release(release_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(release) => IpcResponse::SerialRelease { release },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::SerialReleasePublished {
+            release_id,
+            published_url,
+        } => {
+            let result = match Uuid::parse_str(&release_id) {
+                Ok(release_id) => bridge
+                    .serial
+                    .mark_published(release_id, published_url)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(release) => IpcResponse::SerialRelease { release },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
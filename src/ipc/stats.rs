@@ -0,0 +1,79 @@
+//! Writing session, statistics and goal requests
+
+use crate::database::models::git_history::CommitReason;
+use crate::database::models::stats::StatsExportRequest;
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::StatsSessionStart { project_id } => {
+            match bridge.stats.start_session(project_id).await {
+                Ok(session) => IpcResponse::WritingSession { session },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::StatsSessionEnd { session_id } => {
+            match bridge.stats.end_session(session_id).await {
+                Ok(session) => {
+                    if let Err(e) = bridge
+                        .git_history
+                        .commit_if_enabled(session.project_id, CommitReason::SessionEnd)
+                        .await
+                    {
+                        log::error!("History commit after session {} failed: {}", session_id, e);
+                    }
+                    IpcResponse::WritingSession { session }
+                }
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::StatsExport {
+            project_id,
+            dataset,
+            format,
+            from,
+            to,
+            path,
+        } => {
+            let request = StatsExportRequest {
+                project_id,
+                dataset,
+                format,
+                from,
+                to,
+            };
+            let result = match path {
+                Some(path) => {
+                    bridge
+                        .stats
+                        .export_to_file(&request, std::path::Path::new(&path))
+                        .await
+                }
+                None => bridge.stats.export(&request).await,
+            };
+            match result {
+                Ok(export) => IpcResponse::StatsExport { export },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::WritingStatsDashboard {
+            project_id,
+            from,
+            to,
+        } => match bridge.writing_stats.dashboard(project_id, from, to).await {
+            Ok(dashboard) => IpcResponse::WritingStatsDashboard { dashboard },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::WritingGoalSet { project_id, goal } => {
+            match bridge.writing_stats.set_goal(project_id, goal).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,60 @@
+//! Style sheet requests
+
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::StyleSheetGet { project_id } => {
+            match bridge.style_sheets.get_style_sheet(project_id).await {
+                Ok(style_sheet) => IpcResponse::StyleSheet { style_sheet },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::StyleSheetSave { style_sheet } => {
+            match bridge.style_sheets.save_style_sheet(&style_sheet).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::StyleRuleAdd { project_id, rule } => {
+            match bridge.style_sheets.add_rule(project_id, rule).await {
+                Ok(style_sheet) => IpcResponse::StyleSheet { style_sheet },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::StyleRuleRemove {
+            project_id,
+            rule_id,
+        } => match bridge.style_sheets.remove_rule(project_id, rule_id).await {
+            Ok(true) => IpcResponse::Ack,
+            Ok(false) => IpcResponse::service_error(ErrorEnvelope::new(
+                ErrorCode::NotFound,
+                format!("Style rule {} not found", rule_id),
+            )),
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::StyleSheetCheck { project_id } => {
+            match bridge.style_sheets.check_project(project_id).await {
+                Ok(reports) => IpcResponse::StyleReports { reports },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::StyleSheetFix {
+            project_id,
+            document_id,
+        } => match bridge
+            .style_sheets
+            .auto_fix_document(project_id, document_id)
+            .await
+        {
+            Ok(result) => IpcResponse::StyleFix { result },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        other => return Err(other),
+    };
+    Ok(response)
+}
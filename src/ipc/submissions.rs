@@ -0,0 +1,82 @@
+//! Submission tracker requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use uuid::Uuid;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::SubmissionMarkets => match bridge.submissions.list_markets().await {
+            Ok(markets) => IpcResponse::Markets { markets },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::SubmissionMarketSave { market } => {
+            match bridge.submissions.save_market(&market).await {
+                Ok(market) => IpcResponse::Market { market },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::SubmissionMarketDelete { market_id } => {
+            let result = match Uuid::parse_str(&market_id) {
+                Ok(market_id) => bridge
+                    .submissions
+                    .delete_market(market_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(_) => IpcResponse::Ack,
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::SubmissionMarketStats => match bridge.submissions.market_stats().await {
+            Ok(stats) => IpcResponse::MarketStats { stats },
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::SubmissionSave { submission } => {
+            match bridge.submissions.save_submission(&submission).await {
+                Ok(submission) => IpcResponse::Submission { submission },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::SubmissionList { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .submissions
+                    .list_submissions(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(submissions) => IpcResponse::Submissions { submissions },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::SubmissionRespond { response } => {
+            match bridge.submissions.record_response(&response).await {
+                Ok(submission) => IpcResponse::Submission { submission },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::SubmissionReport { project_id } => {
+            let result = match project_id.as_deref().map(Uuid::parse_str).transpose() {
+                Ok(project_id) => bridge
+                    .submissions
+                    .open_report(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(report) => IpcResponse::SubmissionReport { report },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,119 @@
+//! Markdown folder sync and git history requests
+
+use crate::database::models::git_history::CommitReason;
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::MarkdownSyncEnable { project_id, folder } => {
+            match bridge
+                .markdown_sync
+                .enable(project_id, std::path::Path::new(&folder))
+                .await
+            {
+                Ok(folder) => IpcResponse::MarkdownSyncFolder {
+                    folder: Some(folder),
+                },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::MarkdownSyncDisable { project_id } => {
+            match bridge.markdown_sync.disable(project_id).await {
+                Ok(_) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::MarkdownSyncStatus { project_id } => {
+            match bridge.markdown_sync.folder(project_id).await {
+                Ok(folder) => IpcResponse::MarkdownSyncFolder { folder },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::MarkdownSyncRun { project_id } => {
+            match bridge.markdown_sync.sync(project_id).await {
+                Ok(report) => IpcResponse::MarkdownSyncReport { report },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::MarkdownSyncResolve {
+            project_id,
+            document_id,
+            keep,
+        } => {
+            match bridge
+                .markdown_sync
+                .resolve(project_id, &document_id, keep)
+                .await
+            {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::GitHistoryEnable { project_id, target } => {
+            match bridge.git_history.enable(project_id, target).await {
+                Ok(settings) => IpcResponse::GitHistorySettings {
+                    settings: Some(settings),
+                },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::GitHistoryDisable { project_id } => {
+            match bridge.git_history.disable(project_id).await {
+                Ok(_) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::GitHistoryStatus { project_id } => {
+            match bridge.git_history.settings(project_id).await {
+                Ok(settings) => IpcResponse::GitHistorySettings { settings },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::GitHistoryCommit {
+            project_id,
+            message,
+        } => {
+            match bridge
+                .git_history
+                .commit(project_id, CommitReason::Milestone, message.as_deref())
+                .await
+            {
+                Ok(commit) => IpcResponse::GitHistoryCommitted { commit },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::GitHistoryLog {
+            project_id,
+            document_id,
+            limit,
+        } => {
+            match bridge
+                .git_history
+                .log(project_id, document_id.as_deref(), limit.unwrap_or(50))
+                .await
+            {
+                Ok(commits) => IpcResponse::GitHistoryLog { commits },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::GitHistoryRestore {
+            project_id,
+            commit,
+            document_id,
+        } => {
+            match bridge
+                .git_history
+                .restore(project_id, &commit, document_id.as_deref())
+                .await
+            {
+                Ok(restore) => IpcResponse::GitHistoryRestored { restore },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
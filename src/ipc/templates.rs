@@ -0,0 +1,63 @@
+//! Document template requests
+
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::DocumentTemplates { project_id } => {
+            match bridge.document_templates.templates(project_id).await {
+                Ok(templates) => IpcResponse::DocumentTemplates { templates },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentTemplateSave { template } => {
+            match bridge.document_templates.save_template(&template).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentTemplateDelete { template_id } => {
+            match bridge.document_templates.delete_template(template_id).await {
+                Ok(true) => IpcResponse::Ack,
+                Ok(false) => IpcResponse::service_error(ErrorEnvelope::new(
+                    ErrorCode::NotFound,
+                    format!("Document template {} not found", template_id),
+                )),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentTemplatePrompts {
+            template_id,
+            project_id,
+        } => {
+            match bridge
+                .document_templates
+                .prompts(template_id, project_id)
+                .await
+            {
+                Ok(prompts) => IpcResponse::DocumentTemplatePrompts { prompts },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::DocumentFromTemplate {
+            template_id,
+            project_id,
+            answers,
+        } => {
+            match bridge
+                .document_templates
+                .create_document(template_id, project_id, &answers)
+                .await
+            {
+                Ok(document_id) => IpcResponse::DocumentCreated { document_id },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
@@ -0,0 +1,62 @@
+//! Calendar and story timeline requests
+
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::TimelineCalendarSave { calendar } => {
+            match bridge.calendars.save_calendar(&calendar).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::TimelineCalendarDelete { calendar_id } => {
+            match bridge.calendars.delete_calendar(calendar_id).await {
+                Ok(true) => IpcResponse::Ack,
+                Ok(false) => IpcResponse::service_error(ErrorEnvelope::new(
+                    ErrorCode::NotFound,
+                    format!("Calendar {} not found", calendar_id),
+                )),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::TimelineCalendars { project_id } => {
+            match bridge.calendars.list_calendars(project_id).await {
+                Ok(calendars) => IpcResponse::TimelineCalendars { calendars },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::TimelineEventSave { event } => match bridge.timeline.save_event(&event).await {
+            Ok(()) => IpcResponse::Ack,
+            Err(e) => IpcResponse::service_error(e),
+        },
+        IpcMessage::TimelineEventDelete { event_id } => {
+            match bridge.timeline.delete_event(event_id).await {
+                Ok(true) => IpcResponse::Ack,
+                Ok(false) => IpcResponse::service_error(ErrorEnvelope::new(
+                    ErrorCode::NotFound,
+                    format!("Event {} not found", event_id),
+                )),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::TimelineEvents { project_id } => {
+            match bridge.timeline.list_events(project_id).await {
+                Ok(events) => IpcResponse::TimelineEvents { events },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::TimelineGet { project_id, filter } => {
+            match bridge.timeline.timeline(project_id, &filter).await {
+                Ok(timeline) => IpcResponse::StoryTimeline { timeline },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
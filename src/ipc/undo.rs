@@ -0,0 +1,106 @@
+//! Undo history requests and the undoable operations they cover
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use uuid::Uuid;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::UndoState { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .undo_history
+                    .state(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(state) => IpcResponse::UndoState { state },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::Undo { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .undo_history
+                    .undo(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(operation) => IpcResponse::UndoApplied { operation },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::Redo { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .undo_history
+                    .redo(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(operation) => IpcResponse::UndoApplied { operation },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::BinderMove {
+            project_id,
+            document_ids,
+        } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .undo_history
+                    .move_documents(project_id, &document_ids)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(document_ids) => IpcResponse::BinderOrder { document_ids },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::CodexEntryDelete { entry_id } => {
+            let result = match Uuid::parse_str(&entry_id) {
+                Ok(entry_id) => bridge
+                    .undo_history
+                    .delete_codex_entry(entry_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(()) => IpcResponse::Ack,
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::DocumentsTag {
+            project_id,
+            document_ids,
+            tags,
+            remove,
+        } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .undo_history
+                    .tag_documents(project_id, &document_ids, &tags, remove)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(changed) => IpcResponse::DocumentsTagged { changed },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
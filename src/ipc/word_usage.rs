@@ -0,0 +1,49 @@
+//! Word usage and cliché requests
+
+use crate::error::{ErrorCode, ErrorEnvelope};
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+
+/// Length of the top word and phrase lists when the frontend sets none
+const DEFAULT_TERM_LIMIT: usize = 25;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::ClicheLists { project_id } => {
+            match bridge.word_usage.get_cliche_lists(project_id).await {
+                Ok(lists) => IpcResponse::ClicheLists { lists },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ClicheListSave { list } => {
+            match bridge.word_usage.save_cliche_list(&list).await {
+                Ok(()) => IpcResponse::Ack,
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::ClicheListDelete { list_id } => {
+            match bridge.word_usage.delete_cliche_list(list_id).await {
+                Ok(true) => IpcResponse::Ack,
+                Ok(false) => IpcResponse::service_error(ErrorEnvelope::new(
+                    ErrorCode::NotFound,
+                    format!("Cliché list {} not found", list_id),
+                )),
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::WordUsageAnalyze { project_id, limit } => {
+            match bridge
+                .word_usage
+                .analyze_project(project_id, limit.unwrap_or(DEFAULT_TERM_LIMIT))
+                .await
+            {
+                Ok(report) => IpcResponse::WordUsage { report },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
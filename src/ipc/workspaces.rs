@@ -0,0 +1,108 @@
+//! Workspace requests
+
+use crate::ipc_bridge::{IpcBridge, IpcMessage, IpcResponse};
+use uuid::Uuid;
+
+pub(crate) async fn handle(
+    bridge: &IpcBridge,
+    message: IpcMessage,
+) -> Result<IpcResponse, IpcMessage> {
+    let response = match message {
+        IpcMessage::WorkspaceList { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .workspaces
+                    .list_workspaces(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(workspaces) => IpcResponse::Workspaces { workspaces },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::WorkspaceSave { workspace } => {
+            match bridge.workspaces.save_workspace(&workspace).await {
+                Ok(workspace) => IpcResponse::Workspace { workspace },
+                Err(e) => IpcResponse::service_error(e),
+            }
+        }
+        IpcMessage::WorkspaceDelete { workspace_id } => {
+            let result = match Uuid::parse_str(&workspace_id) {
+                Ok(workspace_id) => bridge
+                    .workspaces
+                    .delete_workspace(workspace_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(_) => IpcResponse::Ack,
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::WorkspaceSwitch { workspace_id } => {
+            let result = match Uuid::parse_str(&workspace_id) {
+                Ok(workspace_id) => bridge
+                    .workspaces
+                    .switch_workspace(workspace_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(workspace) => IpcResponse::Workspace { workspace },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::WorkspaceActive { project_id } => {
+            let result = match Uuid::parse_str(&project_id) {
+                Ok(project_id) => bridge
+                    .workspaces
+                    .active_workspace(project_id)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(workspace) => IpcResponse::ActiveWorkspace { workspace },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::WorkspacePin { workspace_id, pin } => {
+            let result = match Uuid::parse_str(&workspace_id) {
+                Ok(workspace_id) => bridge
+                    .workspaces
+                    .pin_reference(workspace_id, pin)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(workspace) => IpcResponse::Workspace { workspace },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        IpcMessage::WorkspaceUnpin {
+            workspace_id,
+            kind,
+            target,
+        } => {
+            let result = match Uuid::parse_str(&workspace_id) {
+                Ok(workspace_id) => bridge
+                    .workspaces
+                    .unpin_reference(workspace_id, kind, &target)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(workspace) => IpcResponse::Workspace { workspace },
+                Err(message) => IpcResponse::service_error(message),
+            }
+        }
+        other => return Err(other),
+    };
+    Ok(response)
+}
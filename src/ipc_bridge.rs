@@ -1,8 +1,115 @@
+use crate::app_paths::AppPaths;
+use crate::command_registry::{CommandContext, CommandItem, CommandRegistry, MenuLocation};
+use crate::compliance::DataClassification;
+use crate::correlation;
+use crate::crash_reporter::{CrashReport, CrashReporter, SessionMetrics};
+use crate::data_migration::{DataMigrationPlan, DataMigrationReport};
+use crate::database::activity_service::record_document_edit;
+use crate::database::backup_service::{BackupMetadata, BackupVerification};
+use crate::database::beta_reader_service::CommentFormat;
+use crate::database::models::activity::{ActivityEntry, ActivityFilter, ActivitySummary};
+use crate::database::models::ai_log::AiInteraction;
+use crate::database::models::annotation::Annotation;
+use crate::database::models::anonymizer::{AnonymizeRequest, AnonymizedProject};
+use crate::database::models::attachment::{Attachment, AttachmentOwner, ExportInclusion};
+use crate::database::models::beta_reader::{BetaPacket, BetaReader, CommentImportResult};
+use crate::database::models::calendar::CalendarSystem;
+use crate::database::models::certification::{CertificateVerification, WordCountCertificate};
+use crate::database::models::challenge::{Challenge, ChallengeDashboard};
+use crate::database::models::chronology::{ChronologicalExport, ChronologyReport, ReadingOrder};
+use crate::database::models::codex::{CodexExportResult, CodexImportResult};
+use crate::database::models::codex_autofill::{AutofillApply, AutofillProposal, AutofillRequest};
+use crate::database::models::codex_graph::{Backlinks, GraphMatch, GraphQuery};
+use crate::database::models::codex_relationship::{
+    CodexRelationship, Direction, Neighbor, RelationshipMap,
+};
+use crate::database::models::codex_transfer::{CodexFormat, CodexImportOptions};
+use crate::database::models::content_scan::{ContentLexicon, ContentScanReport};
+use crate::database::models::deadline::{Deadline, DeadlineCountdown};
+use crate::database::models::document_structure::{
+    MergeRequest, MergeResult, SplitRequest, SplitResult,
+};
+use crate::database::models::document_template::{DocumentTemplate, ResolvedPrompt};
+use crate::database::models::focus::{FocusAnalyticsStatus, FocusDaySummary};
+use crate::database::models::git_history::{
+    GitHistorySettings, HistoryCommit, HistoryRestore, HistoryTarget,
+};
+use crate::database::models::hybrid_search::{HybridSearchRequest, HybridSearchResult};
+use crate::database::models::journal::{JournalEntry, JournalSettings, JournalStreak};
+use crate::database::models::lexicon::{Conlang, LexiconEntry, LexiconQuery};
+use crate::database::models::lint_pack::{LintPack, LintReport, LintSettings};
+use crate::database::models::markdown_sync::{MarkdownSyncFolder, MarkdownSyncReport, SyncSide};
+use crate::database::models::narrative_voice::{SceneVoice, VoiceReport};
+use crate::database::models::note_import::{
+    DocumentBacklink, NoteImportOptions, NoteImportReport, NotionImportOptions,
+};
+use crate::database::models::profile::{ProfileRole, UserProfile};
+use crate::database::models::readability::ReadabilityReport;
+use crate::database::models::related_notes::{RelatedNotes, RelatedNotesRequest};
+use crate::database::models::rename::{
+    RenameOperation, RenamePreview, RenameRequest, RenameRollback,
+};
+use crate::database::models::serial::{ReleasePlan, SerialRelease};
+use crate::database::models::stats::{
+    AiUsageRecord, StatsDataset, StatsExport, StatsFormat, WritingSession,
+};
+use crate::database::models::story_bible::{StoryBible, StoryBibleRequest};
+use crate::database::models::style_sheet::{
+    DocumentStyleReport, StyleFixResult, StyleRule, StyleSheet,
+};
+use crate::database::models::submission::{
+    Market, MarketStats, Submission, SubmissionReport, SubmissionResponse,
+};
+use crate::database::models::timeline::{StoryEvent, StoryTimeline, TimelineFilter};
+use crate::database::models::undo_history::{UndoOperation, UndoState};
+use crate::database::models::word_usage::{ClicheList, WordUsageReport};
+use crate::database::models::workspace::{PinKind, PinnedReference, Workspace};
+use crate::database::models::{
+    DocumentMerge, DocumentVersion, EmbeddingMigration, EmbeddingModel, SearchResult, TrashItem,
+    TrashItemKind, VersionDiff,
+};
+use crate::database::vector_embedding::DuplicatePair;
+use crate::database::{
+    ActivityService, AiLogService, AnalysisService, AnnotationService, AnonymizerService,
+    AttachmentService, BackupService, BetaReaderService, CalendarService, CertificationService,
+    ChallengeService, ChronologyService, CodexAutofillService, CodexGraphService,
+    CodexRelationshipService, CodexTransferService, ContentScanService, DatabaseService,
+    DeadlineService, DocumentStructureService, DocumentTemplateService, FocusService,
+    GeneratorService, GitHistoryService, HybridSearchService, JournalService, LexiconService,
+    MarkdownSyncService, NoteImportService, ProfileService, RelatedNotesService, RenameService,
+    SerialService, StatsService, StoryBibleService, StyleSheetService, SubmissionService,
+    TimelineService, UndoHistoryService, VectorEmbeddingService, WordUsageService,
+    WorkspaceService,
+};
+use crate::error::ErrorEnvelope;
+use crate::generators::GeneratorTable;
+use crate::ipc_throttle::{Debouncer, FlushFuture, RateLimiter};
+use crate::printing::{PrintJob, PrinterInfo};
+use crate::publishing::{ColorPalette, CoverDesign, PublishFormat, ShareOptions, TrimSize};
+use crate::recent_items::{RecentItem, RecentItemKind, RecentItems};
+use crate::security::clipboard::{SecureClipboard, SecureCopyReceipt};
+use crate::security::confirmation::{
+    ConfirmationGrant, ConfirmationGuard, ConfirmationMethod, ConfirmationProof,
+    DestructiveOperation,
+};
+use crate::security::credentials::{CredentialManager, CredentialProvider, CredentialSummary};
+use crate::security::events::{SecurityEvent, SecurityEventLog};
+use crate::security::network::{self};
+use crate::security::threat_detector::{AccessKind, ThreatAlert, ThreatDetector};
+use crate::send_to_device::{DeviceFolder, DeviceSender, DeviceTarget, SendJob, SmtpSettings};
+use crate::services::ai_service::AiService;
+use crate::services::ask_service::{AskAnswer, AskRequest, AskService};
+use crate::services::writing_stats::{WritingGoal, WritingStatsDashboard, WritingStatsService};
+use crate::settings::Settings;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
-use crate::database::DatabaseService;
-use crate::services::ai_service::AiService;
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IpcRequest {
@@ -11,24 +118,1569 @@ pub struct IpcRequest {
     pub message: IpcMessage,
 }
 
+/// IPC API version implemented by this backend
+pub const IPC_API_VERSION: u32 = 3;
+/// Oldest frontend API version still served (through response shims)
+pub const MIN_SUPPORTED_API_VERSION: u32 = 1;
+
+/// A command and the API version that introduced it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandSpec {
+    pub name: String,
+    pub since: u32,
+    /// Version from which the command is deprecated, if any
+    pub deprecated_since: Option<u32>,
+    pub replacement: Option<String>,
+}
+
+/// Every command the backend understands: (name, since, deprecated_since, replacement)
+const COMMANDS: &[(&str, u32, Option<u32>, Option<&str>)] = &[
+    ("handshake", 2, None, None),
+    ("db_query", 1, None, None),
+    ("db_execute", 1, None, None),
+    ("ai_request", 1, None, None),
+    ("log", 1, None, None),
+    ("app_action", 1, None, None),
+    ("credential_add", 2, None, None),
+    ("credential_test", 2, None, None),
+    ("credential_remove", 2, None, None),
+    ("credential_list", 2, None, None),
+    ("network_set_offline", 2, None, None),
+    ("network_status", 2, None, None),
+    ("secure_copy", 2, None, None),
+    ("clipboard_clear", 2, None, None),
+    ("threat_alerts", 2, None, None),
+    ("threat_confirm", 2, None, None),
+    ("security_events", 2, None, None),
+    ("destructive_confirm", 2, None, None),
+    ("cursor_position", 2, None, None),
+    ("autosave_ping", 2, None, None),
+    ("list_printers", 2, None, None),
+    ("print_document", 2, None, None),
+    ("recent_record", 2, None, None),
+    ("recent_list", 2, None, None),
+    ("project_file_create", 2, None, None),
+    ("crash_reports", 2, None, None),
+    ("crash_report_submit", 2, None, None),
+    ("crash_report_dismiss", 2, None, None),
+    ("crash_metrics", 2, None, None),
+    ("storage_info", 2, None, None),
+    ("data_dir_plan", 2, None, None),
+    ("data_dir_migrate", 2, None, None),
+    ("attachment_add", 2, None, None),
+    ("attachment_list", 2, None, None),
+    ("attachment_update", 2, None, None),
+    ("attachment_remove", 2, None, None),
+    ("cover_palette", 2, None, None),
+    ("cover_render", 2, None, None),
+    ("device_list", 2, None, None),
+    ("device_smtp_get", 2, None, None),
+    ("device_smtp_save", 2, None, None),
+    ("device_send", 2, None, None),
+    ("device_send_status", 2, None, None),
+    ("device_send_jobs", 2, None, None),
+    ("generator_tables", 2, None, None),
+    ("generator_save", 2, None, None),
+    ("generator_delete", 2, None, None),
+    ("generator_roll", 2, None, None),
+    ("stats_session_start", 2, None, None),
+    ("stats_session_end", 2, None, None),
+    ("stats_export", 2, None, None),
+    ("codex_graph_query", 2, None, None),
+    ("codex_graph_query_text", 2, None, None),
+    ("codex_backlinks", 2, None, None),
+    ("embedding_models", 2, None, None),
+    ("embedding_model_register", 2, None, None),
+    ("embedding_migration_start", 2, None, None),
+    ("embedding_migration_status", 2, None, None),
+    ("embedding_migration_cancel", 2, None, None),
+    ("embedding_search", 2, None, None),
+    ("hybrid_search", 2, None, None),
+    ("find_duplicate_paragraphs", 2, None, None),
+    ("related_notes", 2, None, None),
+    ("ask_project", 2, None, None),
+    ("codex_autofill_propose", 2, None, None),
+    ("codex_autofill_apply", 2, None, None),
+    ("story_bible_export", 2, None, None),
+    ("lint_packs", 2, None, None),
+    ("lint_settings_get", 2, None, None),
+    ("lint_settings_save", 2, None, None),
+    ("lint_project", 2, None, None),
+    ("narrative_voice_check", 2, None, None),
+    ("scene_voice_set", 2, None, None),
+    ("chronology_check", 2, None, None),
+    ("chronology_reading_order", 2, None, None),
+    ("chronology_export", 2, None, None),
+    ("submission_markets", 2, None, None),
+    ("submission_market_save", 2, None, None),
+    ("submission_market_delete", 2, None, None),
+    ("submission_market_stats", 2, None, None),
+    ("submission_save", 2, None, None),
+    ("submission_list", 2, None, None),
+    ("submission_respond", 2, None, None),
+    ("submission_report", 2, None, None),
+    ("deadline_list", 2, None, None),
+    ("deadline_save", 2, None, None),
+    ("deadline_delete", 2, None, None),
+    ("deadline_countdowns", 2, None, None),
+    ("serial_plan", 2, None, None),
+    ("serial_release_save", 2, None, None),
+    ("serial_release_delete", 2, None, None),
+    ("serial_release_stage", 2, None, None),
+    ("serial_release_published", 2, None, None),
+    ("word_count_certify", 2, None, None),
+    ("word_count_certificates", 2, None, None),
+    ("word_count_certificate_verify", 2, None, None),
+    ("word_count_certificate_export", 2, None, None),
+    ("challenge_list", 2, None, None),
+    ("challenge_save", 2, None, None),
+    ("challenge_delete", 2, None, None),
+    ("challenge_dashboard", 2, None, None),
+    ("focus_status", 2, None, None),
+    ("focus_enable", 2, None, None),
+    ("focus_summaries", 2, None, None),
+    ("focus_clear", 2, None, None),
+    ("workspace_list", 2, None, None),
+    ("workspace_save", 2, None, None),
+    ("workspace_delete", 2, None, None),
+    ("workspace_switch", 2, None, None),
+    ("workspace_active", 2, None, None),
+    ("workspace_pin", 2, None, None),
+    ("workspace_unpin", 2, None, None),
+    ("undo_state", 2, None, None),
+    ("undo", 2, None, None),
+    ("redo", 2, None, None),
+    ("binder_move", 2, None, None),
+    ("codex_entry_delete", 2, None, None),
+    ("documents_tag", 2, None, None),
+    ("activity_feed", 2, None, None),
+    ("activity_summary", 2, None, None),
+    ("activity_open_project", 2, None, None),
+    ("project_anonymize", 2, None, None),
+    ("backup_list", 3, None, None),
+    ("backup_verify", 3, None, None),
+    ("backup_verify_all", 3, None, None),
+    ("backup_set_encryption", 3, None, None),
+    ("document_versions", 3, None, None),
+    ("document_version_diff", 3, None, None),
+    ("document_revert", 3, None, None),
+    ("document_merge", 3, None, None),
+    ("ai_log_list", 3, None, None),
+    ("ai_log_export", 3, None, None),
+    ("ai_log_delete", 3, None, None),
+    ("ai_log_project_set", 3, None, None),
+    ("command_list", 3, None, None),
+    ("command_search", 3, None, None),
+    ("command_menu", 3, None, None),
+    ("command_invoked", 3, None, None),
+    ("document_trash", 3, None, None),
+    ("project_trash", 3, None, None),
+    ("trash_list", 3, None, None),
+    ("trash_restore", 3, None, None),
+    ("trash_purge", 3, None, None),
+    ("trash_empty", 3, None, None),
+    ("document_move", 3, None, None),
+    ("document_copy", 3, None, None),
+    ("document_templates", 3, None, None),
+    ("document_template_save", 3, None, None),
+    ("document_template_delete", 3, None, None),
+    ("document_template_prompts", 3, None, None),
+    ("document_from_template", 3, None, None),
+    ("codex_relationship_save", 3, None, None),
+    ("codex_relationship_delete", 3, None, None),
+    ("codex_relationship_map", 3, None, None),
+    ("codex_neighbors", 3, None, None),
+    ("codex_path", 3, None, None),
+    ("codex_component", 3, None, None),
+    ("journal_settings", 3, None, None),
+    ("journal_settings_save", 3, None, None),
+    ("journal_today", 3, None, None),
+    ("journal_append", 3, None, None),
+    ("journal_entries", 3, None, None),
+    ("journal_streak", 3, None, None),
+    ("document_share", 3, None, None),
+    ("codex_export", 3, None, None),
+    ("codex_import", 3, None, None),
+    ("markdown_sync_enable", 3, None, None),
+    ("markdown_sync_disable", 3, None, None),
+    ("markdown_sync_status", 3, None, None),
+    ("markdown_sync_run", 3, None, None),
+    ("markdown_sync_resolve", 3, None, None),
+    ("git_history_enable", 3, None, None),
+    ("git_history_disable", 3, None, None),
+    ("git_history_status", 3, None, None),
+    ("git_history_commit", 3, None, None),
+    ("git_history_log", 3, None, None),
+    ("git_history_restore", 3, None, None),
+    ("timeline_calendar_save", 3, None, None),
+    ("timeline_calendar_delete", 3, None, None),
+    ("timeline_calendars", 3, None, None),
+    ("timeline_event_save", 3, None, None),
+    ("timeline_event_delete", 3, None, None),
+    ("timeline_events", 3, None, None),
+    ("timeline_get", 3, None, None),
+    ("obsidian_import", 3, None, None),
+    ("notion_import", 3, None, None),
+    ("document_backlinks", 3, None, None),
+    ("writing_stats_dashboard", 3, None, None),
+    ("writing_goal_set", 3, None, None),
+    ("readability_analyze", 3, None, None),
+    ("profiles_list", 3, None, None),
+    ("profile_create", 3, None, None),
+    ("profile_switch", 3, None, None),
+    ("profile_delete", 3, None, None),
+    ("profile_settings_save", 3, None, None),
+    ("profile_ai_budget_set", 3, None, None),
+    ("profile_project_grant", 3, None, None),
+    ("profile_project_revoke", 3, None, None),
+    ("app_lock_set", 3, None, None),
+    ("style_sheet_get", 3, None, None),
+    ("style_sheet_save", 3, None, None),
+    ("style_rule_add", 3, None, None),
+    ("style_rule_remove", 3, None, None),
+    ("style_sheet_check", 3, None, None),
+    ("style_sheet_fix", 3, None, None),
+    ("content_lexicons", 3, None, None),
+    ("content_lexicon_save", 3, None, None),
+    ("content_lexicon_delete", 3, None, None),
+    ("content_scan", 3, None, None),
+    ("beta_reader_add", 3, None, None),
+    ("beta_readers_list", 3, None, None),
+    ("beta_packet_build", 3, None, None),
+    ("beta_comments_import", 3, None, None),
+    ("annotations_list", 3, None, None),
+    ("annotation_create", 3, None, None),
+    ("annotation_resolve", 3, None, None),
+    ("annotation_delete", 3, None, None),
+    ("cliche_lists", 3, None, None),
+    ("cliche_list_save", 3, None, None),
+    ("cliche_list_delete", 3, None, None),
+    ("word_usage_analyze", 3, None, None),
+    ("rename_preview", 3, None, None),
+    ("rename_apply", 3, None, None),
+    ("rename_rollback", 3, None, None),
+    ("rename_get", 3, None, None),
+    ("renames_list", 3, None, None),
+    ("binder_order_get", 3, None, None),
+    ("document_split", 3, None, None),
+    ("documents_merge", 3, None, None),
+    ("conlangs_list", 3, None, None),
+    ("conlangs_for_culture", 3, None, None),
+    ("conlang_save", 3, None, None),
+    ("conlang_delete", 3, None, None),
+    ("lexicon_entries", 3, None, None),
+    ("lexicon_entry_save", 3, None, None),
+    ("lexicon_entry_delete", 3, None, None),
+    ("lexicon_search", 3, None, None),
+    ("lexicon_export_csv", 3, None, None),
+    ("lexicon_export_appendix", 3, None, None),
+    ("lexicon_spellcheck_words", 3, None, None),
+    ("lexicon_dictionary_export", 3, None, None),
+];
+
+/// Commands available to a frontend speaking `version`
+pub fn supported_commands(version: u32) -> Vec<CommandSpec> {
+    COMMANDS
+        .iter()
+        .filter(|(_, since, _, _)| *since <= version)
+        .map(|(name, since, deprecated_since, replacement)| CommandSpec {
+            name: name.to_string(),
+            since: *since,
+            deprecated_since: *deprecated_since,
+            replacement: replacement.map(str::to_string),
+        })
+        .collect()
+}
+
+/// Rewrite a serialized response into the shape an older frontend expects
+pub fn downgrade_response(response: &mut Value, version: u32) {
+    let Some(envelope) = response.as_object_mut() else {
+        return;
+    };
+    if version < 2 {
+        // v1 had no correlation IDs
+        envelope.remove("correlation_id");
+    }
+    if envelope.get("type").and_then(Value::as_str) == Some("error") {
+        if let Some(payload) = envelope.get_mut("payload").and_then(Value::as_object_mut) {
+            // v2 had no error envelopes, v1 no error codes either
+            if version < 3 {
+                payload.remove("error");
+            }
+            if version < 2 {
+                payload.remove("code");
+            }
+        }
+    }
+}
+
+/// Largest frame accepted from the frontend
+pub const MAX_MESSAGE_BYTES: usize = 4 * 1024 * 1024;
+/// Deepest array/object nesting accepted from the frontend
+pub const MAX_NESTING_DEPTH: usize = 32;
+const MAX_ID_LENGTH: usize = 128;
+
+/// Machine-readable reason an IPC request failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpcErrorCode {
+    InvalidJson,
+    MessageTooLarge,
+    NestingTooDeep,
+    InvalidEnvelope,
+    UnknownMessageType,
+    InvalidPayload,
+    RateLimited,
+    UnsupportedVersion,
+    ServiceError,
+}
+
+/// A frame that was rejected before reaching a handler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpcParseError {
+    /// Request id, when it could be recovered from the frame
+    pub id: Option<String>,
+    pub code: IpcErrorCode,
+    pub message: String,
+}
+
+impl IpcParseError {
+    fn new(id: Option<String>, code: IpcErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            id,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Validate and decode a raw frame from the frontend. Checks size and
+/// nesting before parsing, then the `{id, type, payload}` envelope, then the
+/// payload against the message schema. Never panics on malformed input.
+pub fn parse_request(message: &str) -> Result<IpcRequest, IpcParseError> {
+    if message.len() > MAX_MESSAGE_BYTES {
+        return Err(IpcParseError::new(
+            None,
+            IpcErrorCode::MessageTooLarge,
+            format!(
+                "Message is {} bytes; the limit is {}",
+                message.len(),
+                MAX_MESSAGE_BYTES
+            ),
+        ));
+    }
+    if nesting_depth(message) > MAX_NESTING_DEPTH {
+        return Err(IpcParseError::new(
+            None,
+            IpcErrorCode::NestingTooDeep,
+            format!("Message nesting exceeds {} levels", MAX_NESTING_DEPTH),
+        ));
+    }
+
+    let value: Value = serde_json::from_str(message).map_err(|e| {
+        IpcParseError::new(
+            None,
+            IpcErrorCode::InvalidJson,
+            format!("Invalid JSON: {}", e),
+        )
+    })?;
+    let Value::Object(mut envelope) = value else {
+        return Err(IpcParseError::new(
+            None,
+            IpcErrorCode::InvalidEnvelope,
+            "Message must be a JSON object",
+        ));
+    };
+
+    let id = match envelope.remove("id") {
+        Some(Value::String(id)) if !id.is_empty() && id.len() <= MAX_ID_LENGTH => id,
+        _ => {
+            return Err(IpcParseError::new(
+                None,
+                IpcErrorCode::InvalidEnvelope,
+                format!(
+                    "'id' must be a non-empty string of at most {} bytes",
+                    MAX_ID_LENGTH
+                ),
+            ))
+        }
+    };
+    if let Some(key) = envelope.keys().find(|k| *k != "type" && *k != "payload") {
+        return Err(IpcParseError::new(
+            Some(id),
+            IpcErrorCode::InvalidEnvelope,
+            format!("Unknown field '{}' in message envelope", key),
+        ));
+    }
+    let Some(message_type) = envelope
+        .get("type")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+    else {
+        return Err(IpcParseError::new(
+            Some(id),
+            IpcErrorCode::InvalidEnvelope,
+            "'type' must be a string",
+        ));
+    };
+
+    match serde_json::from_value::<IpcMessage>(Value::Object(envelope)) {
+        Ok(message) => Ok(IpcRequest { id, message }),
+        Err(e) if e.to_string().starts_with("unknown variant") => Err(IpcParseError::new(
+            Some(id),
+            IpcErrorCode::UnknownMessageType,
+            format!("Unknown message type '{}'", message_type),
+        )),
+        Err(e) => Err(IpcParseError::new(
+            Some(id),
+            IpcErrorCode::InvalidPayload,
+            format!("Invalid payload for '{}': {}", message_type, e),
+        )),
+    }
+}
+
+/// Deepest array/object nesting in a JSON text, ignoring brackets in strings
+fn nesting_depth(text: &str) -> usize {
+    let mut depth = 0usize;
+    let mut max_depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for byte in text.bytes() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max_depth
+}
+
 #[derive(Debug, Serialize, Deserialize)]
-#[serde(tag = "type", content = "payload")]
+#[serde(tag = "type", content = "payload", deny_unknown_fields)]
 pub enum IpcMessage {
+    #[serde(rename = "handshake")]
+    Handshake {
+        api_version: u32,
+        client: Option<String>,
+    },
     #[serde(rename = "db_query")]
     DbQuery { sql: String, params: Vec<Value> },
     #[serde(rename = "db_execute")]
     DbExecute { sql: String, params: Vec<Value> },
     #[serde(rename = "ai_request")]
-    AiRequest { prompt: String, context: Option<String> },
+    AiRequest {
+        prompt: String,
+        context: Option<String>,
+    },
     #[serde(rename = "log")]
     Log { message: String },
     #[serde(rename = "app_action")]
     AppAction { action: String },
+    #[serde(rename = "credential_add")]
+    CredentialAdd {
+        provider: CredentialProvider,
+        key: String,
+        expires_at: Option<DateTime<Utc>>,
+    },
+    #[serde(rename = "credential_test")]
+    CredentialTest { provider: CredentialProvider },
+    #[serde(rename = "credential_remove")]
+    CredentialRemove { provider: CredentialProvider },
+    #[serde(rename = "credential_list")]
+    CredentialList,
+    #[serde(rename = "network_set_offline")]
+    NetworkSetOffline { offline: bool },
+    #[serde(rename = "network_status")]
+    NetworkStatus,
+    #[serde(rename = "secure_copy")]
+    SecureCopy {
+        text: String,
+        #[serde(default)]
+        classification: DataClassification,
+        source: Option<String>,
+    },
+    #[serde(rename = "clipboard_clear")]
+    ClipboardClear,
+    #[serde(rename = "threat_alerts")]
+    ThreatAlerts,
+    #[serde(rename = "threat_confirm")]
+    ThreatConfirm { token: String },
+    #[serde(rename = "security_events")]
+    SecurityEvents,
+    #[serde(rename = "destructive_confirm")]
+    DestructiveConfirm {
+        operation: DestructiveOperation,
+        proof: ConfirmationProof,
+    },
+    #[serde(rename = "cursor_position")]
+    CursorPosition { document_id: String, offset: usize },
+    #[serde(rename = "autosave_ping")]
+    AutosavePing {
+        document_id: String,
+        content: String,
+    },
+    #[serde(rename = "list_printers")]
+    ListPrinters,
+    #[serde(rename = "print_document")]
+    PrintDocument {
+        document_id: String,
+        printer: Option<String>,
+        copies: Option<u32>,
+    },
+    #[serde(rename = "recent_record")]
+    RecentRecord {
+        kind: RecentItemKind,
+        id: String,
+        title: String,
+    },
+    #[serde(rename = "recent_list")]
+    RecentList,
+    #[serde(rename = "project_file_create")]
+    ProjectFileCreate { project_id: String, path: String },
+    #[serde(rename = "crash_reports")]
+    CrashReports,
+    #[serde(rename = "crash_report_submit")]
+    CrashReportSubmit { id: String },
+    #[serde(rename = "crash_report_dismiss")]
+    CrashReportDismiss { id: String },
+    #[serde(rename = "crash_metrics")]
+    CrashMetrics,
+    #[serde(rename = "storage_info")]
+    StorageInfo,
+    #[serde(rename = "data_dir_plan")]
+    DataDirPlan { path: String },
+    #[serde(rename = "data_dir_migrate")]
+    DataDirMigrate { path: String },
+    #[serde(rename = "attachment_add")]
+    AttachmentAdd {
+        project_id: String,
+        owner_kind: AttachmentOwner,
+        owner_id: String,
+        path: String,
+    },
+    #[serde(rename = "attachment_list")]
+    AttachmentList {
+        owner_kind: AttachmentOwner,
+        owner_id: String,
+    },
+    #[serde(rename = "attachment_update")]
+    AttachmentUpdate {
+        id: String,
+        description: Option<String>,
+        export_inclusion: ExportInclusion,
+    },
+    #[serde(rename = "attachment_remove")]
+    AttachmentRemove { id: String },
+    #[serde(rename = "cover_palette")]
+    CoverPalette {
+        asset_hash: String,
+        max_colors: Option<usize>,
+    },
+    /// Render a cover into the asset store, and also to `path` when given
+    #[serde(rename = "cover_render")]
+    CoverRender {
+        design: CoverDesign,
+        trim_size: TrimSize,
+        path: Option<String>,
+    },
+    #[serde(rename = "device_list")]
+    DeviceList,
+    #[serde(rename = "device_smtp_get")]
+    DeviceSmtpGet,
+    /// An empty password keeps the stored one
+    #[serde(rename = "device_smtp_save")]
+    DeviceSmtpSave { settings: SmtpSettings },
+    /// Export the project's manuscript to ePub and send it to a device
+    #[serde(rename = "device_send")]
+    DeviceSend {
+        project_id: String,
+        target: DeviceTarget,
+    },
+    #[serde(rename = "device_send_status")]
+    DeviceSendStatus { job_id: Uuid },
+    #[serde(rename = "device_send_jobs")]
+    DeviceSendJobs,
+    /// Shared tables, plus the project's own when a project is given
+    #[serde(rename = "generator_tables")]
+    GeneratorTables { project_id: Option<Uuid> },
+    #[serde(rename = "generator_save")]
+    GeneratorSave { table: GeneratorTable },
+    #[serde(rename = "generator_delete")]
+    GeneratorDelete { table_id: Uuid },
+    #[serde(rename = "generator_roll")]
+    GeneratorRoll {
+        project_id: Option<Uuid>,
+        table: String,
+        #[serde(default)]
+        variables: HashMap<String, String>,
+        count: Option<usize>,
+    },
+    #[serde(rename = "stats_session_start")]
+    StatsSessionStart { project_id: Uuid },
+    #[serde(rename = "stats_session_end")]
+    StatsSessionEnd { session_id: Uuid },
+    #[serde(rename = "stats_export")]
+    StatsExport {
+        project_id: Option<Uuid>,
+        dataset: StatsDataset,
+        format: StatsFormat,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        path: Option<String>,
+    },
+    #[serde(rename = "codex_graph_query")]
+    CodexGraphQuery { query: GraphQuery },
+    #[serde(rename = "codex_graph_query_text")]
+    CodexGraphQueryText { project_id: Uuid, query: String },
+    #[serde(rename = "codex_backlinks")]
+    CodexBacklinks { project_id: Uuid, entry_id: Uuid },
+    #[serde(rename = "embedding_models")]
+    EmbeddingModels,
+    #[serde(rename = "embedding_model_register")]
+    EmbeddingModelRegister {
+        name: String,
+        dimensions: usize,
+        #[serde(default)]
+        provider: String,
+    },
+    #[serde(rename = "embedding_migration_start")]
+    EmbeddingMigrationStart {
+        model: String,
+        #[serde(default)]
+        prune_old: bool,
+    },
+    /// Without an ID, the unfinished migration if there is one
+    #[serde(rename = "embedding_migration_status")]
+    EmbeddingMigrationStatus { migration_id: Option<Uuid> },
+    #[serde(rename = "embedding_migration_cancel")]
+    EmbeddingMigrationCancel { migration_id: Uuid },
+    /// Semantic search; `model` picks which model's vectors to search
+    #[serde(rename = "embedding_search")]
+    EmbeddingSearch {
+        query: String,
+        model: Option<String>,
+        limit: Option<usize>,
+        document_id: Option<Uuid>,
+    },
+    /// Keyword and semantic search merged into one ranking
+    #[serde(rename = "hybrid_search")]
+    HybridSearch { request: HybridSearchRequest },
+    /// Paragraphs repeated across the manuscript, most similar pairs first
+    #[serde(rename = "find_duplicate_paragraphs")]
+    FindDuplicateParagraphs {
+        project_id: Uuid,
+        threshold: Option<f32>,
+        min_words: Option<usize>,
+        model: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Research notes and codex entries for the current paragraph or
+    /// selection; answers are cached, so the sidebar can ask often
+    #[serde(rename = "related_notes")]
+    RelatedNotes { request: RelatedNotesRequest },
+    /// Answer a question from the project's own text, with citations
+    #[serde(rename = "ask_project")]
+    AskProject { request: AskRequest },
+    /// Scan chapters for facts about a character or place; nothing is
+    /// written until the proposal is applied
+    #[serde(rename = "codex_autofill_propose")]
+    CodexAutofillPropose { request: AutofillRequest },
+    #[serde(rename = "codex_autofill_apply")]
+    CodexAutofillApply { apply: AutofillApply },
+    #[serde(rename = "story_bible_export")]
+    StoryBibleExport {
+        request: StoryBibleRequest,
+        path: String,
+    },
+    #[serde(rename = "lint_packs")]
+    LintPacks,
+    #[serde(rename = "lint_settings_get")]
+    LintSettingsGet { project_id: String },
+    #[serde(rename = "lint_settings_save")]
+    LintSettingsSave { settings: LintSettings },
+    #[serde(rename = "lint_project")]
+    LintProject { project_id: String },
+    #[serde(rename = "narrative_voice_check")]
+    NarrativeVoiceCheck { project_id: String },
+    #[serde(rename = "scene_voice_set")]
+    SceneVoiceSet {
+        document_id: String,
+        voice: SceneVoice,
+    },
+    #[serde(rename = "chronology_check")]
+    ChronologyCheck { project_id: String },
+    #[serde(rename = "chronology_reading_order")]
+    ChronologyReadingOrder { project_id: String },
+    #[serde(rename = "chronology_export")]
+    ChronologyExport {
+        project_id: String,
+        format: PublishFormat,
+        path: String,
+    },
+    #[serde(rename = "submission_markets")]
+    SubmissionMarkets,
+    #[serde(rename = "submission_market_save")]
+    SubmissionMarketSave { market: Market },
+    #[serde(rename = "submission_market_delete")]
+    SubmissionMarketDelete { market_id: String },
+    #[serde(rename = "submission_market_stats")]
+    SubmissionMarketStats,
+    #[serde(rename = "submission_save")]
+    SubmissionSave { submission: Submission },
+    #[serde(rename = "submission_list")]
+    SubmissionList { project_id: String },
+    #[serde(rename = "submission_respond")]
+    SubmissionRespond { response: SubmissionResponse },
+    #[serde(rename = "submission_report")]
+    SubmissionReport { project_id: Option<String> },
+    #[serde(rename = "deadline_list")]
+    DeadlineList { project_id: String },
+    #[serde(rename = "deadline_save")]
+    DeadlineSave { deadline: Deadline },
+    #[serde(rename = "deadline_delete")]
+    DeadlineDelete { deadline_id: String },
+    #[serde(rename = "deadline_countdowns")]
+    DeadlineCountdowns { project_id: String },
+    #[serde(rename = "serial_plan")]
+    SerialPlan { project_id: String },
+    #[serde(rename = "serial_release_save")]
+    SerialReleaseSave { release: SerialRelease },
+    #[serde(rename = "serial_release_delete")]
+    SerialReleaseDelete { release_id: String },
+    #[serde(rename = "serial_release_stage")]
+    SerialReleaseStage { release_id: String },
+    #[serde(rename = "serial_release_published")]
+    SerialReleasePublished {
+        release_id: String,
+        published_url: Option<String>,
+    },
+    #[serde(rename = "word_count_certify")]
+    WordCountCertify { project_id: String },
+    #[serde(rename = "word_count_certificates")]
+    WordCountCertificates { project_id: String },
+    #[serde(rename = "word_count_certificate_verify")]
+    WordCountCertificateVerify { certificate_id: String },
+    #[serde(rename = "word_count_certificate_export")]
+    WordCountCertificateExport {
+        certificate_id: String,
+        path: String,
+    },
+    #[serde(rename = "challenge_list")]
+    ChallengeList { project_id: String },
+    #[serde(rename = "challenge_save")]
+    ChallengeSave { challenge: Challenge },
+    #[serde(rename = "challenge_delete")]
+    ChallengeDelete { challenge_id: String },
+    #[serde(rename = "challenge_dashboard")]
+    ChallengeDashboard { challenge_id: String },
+    #[serde(rename = "focus_status")]
+    FocusStatus,
+    #[serde(rename = "focus_enable")]
+    FocusEnable { enabled: bool },
+    #[serde(rename = "focus_summaries")]
+    FocusSummaries { days: u32 },
+    #[serde(rename = "focus_clear")]
+    FocusClear,
+    #[serde(rename = "workspace_list")]
+    WorkspaceList { project_id: String },
+    #[serde(rename = "workspace_save")]
+    WorkspaceSave { workspace: Workspace },
+    #[serde(rename = "workspace_delete")]
+    WorkspaceDelete { workspace_id: String },
+    #[serde(rename = "workspace_switch")]
+    WorkspaceSwitch { workspace_id: String },
+    #[serde(rename = "workspace_active")]
+    WorkspaceActive { project_id: String },
+    #[serde(rename = "workspace_pin")]
+    WorkspacePin {
+        workspace_id: String,
+        pin: PinnedReference,
+    },
+    #[serde(rename = "workspace_unpin")]
+    WorkspaceUnpin {
+        workspace_id: String,
+        kind: PinKind,
+        target: String,
+    },
+    #[serde(rename = "undo_state")]
+    UndoState { project_id: String },
+    #[serde(rename = "undo")]
+    Undo { project_id: String },
+    #[serde(rename = "redo")]
+    Redo { project_id: String },
+    #[serde(rename = "binder_move")]
+    BinderMove {
+        project_id: String,
+        document_ids: Vec<Uuid>,
+    },
+    #[serde(rename = "codex_entry_delete")]
+    CodexEntryDelete { entry_id: String },
+    #[serde(rename = "documents_tag")]
+    DocumentsTag {
+        project_id: String,
+        document_ids: Vec<Uuid>,
+        tags: Vec<String>,
+        #[serde(default)]
+        remove: bool,
+    },
+    #[serde(rename = "activity_feed")]
+    ActivityFeed {
+        project_id: String,
+        #[serde(default)]
+        filter: ActivityFilter,
+    },
+    #[serde(rename = "activity_summary")]
+    ActivitySummary {
+        project_id: String,
+        since: Option<DateTime<Utc>>,
+    },
+    #[serde(rename = "activity_open_project")]
+    ActivityOpenProject { project_id: String },
+    #[serde(rename = "project_anonymize")]
+    ProjectAnonymize { request: AnonymizeRequest },
+    #[serde(rename = "backup_list")]
+    BackupList {
+        project_id: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Restore a backup to a scratch file and check it
+    #[serde(rename = "backup_verify")]
+    BackupVerify { backup_id: String },
+    #[serde(rename = "backup_verify_all")]
+    BackupVerifyAll {
+        project_id: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Turning encryption off needs a confirmation when one is configured
+    #[serde(rename = "backup_set_encryption")]
+    BackupSetEncryption {
+        encrypt: bool,
+        confirmation: Option<String>,
+    },
+    #[serde(rename = "document_versions")]
+    DocumentVersions { document_id: Uuid },
+    #[serde(rename = "document_version_diff")]
+    DocumentVersionDiff {
+        document_id: Uuid,
+        from_version: u32,
+        to_version: u32,
+    },
+    #[serde(rename = "document_revert")]
+    DocumentRevert { document_id: Uuid, version: u32 },
+    /// Save an edit made against `base_version` after someone else saved
+    #[serde(rename = "document_merge")]
+    DocumentMerge {
+        document_id: Uuid,
+        base_version: u32,
+        content: String,
+    },
+    #[serde(rename = "ai_log_list")]
+    AiLogList {
+        project_id: Option<Uuid>,
+        limit: Option<u32>,
+    },
+    #[serde(rename = "ai_log_export")]
+    AiLogExport { project_id: Option<Uuid> },
+    /// Delete the AI log of one project, or all of it
+    #[serde(rename = "ai_log_delete")]
+    AiLogDelete { project_id: Option<Uuid> },
+    #[serde(rename = "ai_log_project_set")]
+    AiLogProjectSet { project_id: Uuid, enabled: bool },
+    /// Every palette command, ranked for `context`
+    #[serde(rename = "command_list")]
+    CommandList {
+        #[serde(default)]
+        context: CommandContext,
+    },
+    #[serde(rename = "command_search")]
+    CommandSearch {
+        query: String,
+        #[serde(default)]
+        context: CommandContext,
+        limit: Option<usize>,
+    },
+    #[serde(rename = "command_menu")]
+    CommandMenu {
+        location: MenuLocation,
+        #[serde(default)]
+        context: CommandContext,
+    },
+    /// The user ran a palette or menu command; counts toward its ranking
+    #[serde(rename = "command_invoked")]
+    CommandInvoked { command_id: String },
+    /// Move a document to the trash
+    #[serde(rename = "document_trash")]
+    DocumentTrash { document_id: String },
+    /// Move a project, with its documents, to the trash
+    #[serde(rename = "project_trash")]
+    ProjectTrash { project_id: String },
+    #[serde(rename = "trash_list")]
+    TrashList { project_id: Option<String> },
+    #[serde(rename = "trash_restore")]
+    TrashRestore { kind: TrashItemKind, id: String },
+    /// Delete one trashed item for good; needs a `purge_trash` grant when
+    /// confirmation is set up
+    #[serde(rename = "trash_purge")]
+    TrashPurge {
+        kind: TrashItemKind,
+        id: String,
+        confirmation: Option<String>,
+    },
+    #[serde(rename = "trash_empty")]
+    TrashEmpty {
+        project_id: Option<String>,
+        confirmation: Option<String>,
+    },
+    /// Move a document, with its history, to another project
+    #[serde(rename = "document_move")]
+    DocumentMove {
+        document_id: String,
+        project_id: String,
+    },
+    #[serde(rename = "document_copy")]
+    DocumentCopy {
+        document_id: String,
+        project_id: String,
+    },
+    #[serde(rename = "document_templates")]
+    DocumentTemplates { project_id: Option<Uuid> },
+    #[serde(rename = "document_template_save")]
+    DocumentTemplateSave { template: DocumentTemplate },
+    #[serde(rename = "document_template_delete")]
+    DocumentTemplateDelete { template_id: Uuid },
+    /// Questions to ask before creating a document from a template
+    #[serde(rename = "document_template_prompts")]
+    DocumentTemplatePrompts { template_id: Uuid, project_id: Uuid },
+    #[serde(rename = "document_from_template")]
+    DocumentFromTemplate {
+        template_id: Uuid,
+        project_id: Uuid,
+        #[serde(default)]
+        answers: HashMap<String, String>,
+    },
+    #[serde(rename = "codex_relationship_save")]
+    CodexRelationshipSave { relationship: CodexRelationship },
+    #[serde(rename = "codex_relationship_delete")]
+    CodexRelationshipDelete { relationship_id: Uuid },
+    /// Every linked entry in a project, for the relationship map
+    #[serde(rename = "codex_relationship_map")]
+    CodexRelationshipMap { project_id: Uuid },
+    #[serde(rename = "codex_neighbors")]
+    CodexNeighbors {
+        project_id: Uuid,
+        entry_id: Uuid,
+        #[serde(default)]
+        direction: Direction,
+        #[serde(default)]
+        types: Vec<String>,
+    },
+    #[serde(rename = "codex_path")]
+    CodexPath {
+        project_id: Uuid,
+        from: Uuid,
+        to: Uuid,
+        #[serde(default)]
+        direction: Direction,
+    },
+    #[serde(rename = "codex_component")]
+    CodexComponent { project_id: Uuid, entry_id: Uuid },
+    /// Journals belong to a profile, or to the install when `profile_id` is
+    /// left out; `day` defaults to today in local time
+    #[serde(rename = "journal_settings")]
+    JournalSettings {
+        #[serde(default)]
+        profile_id: Option<Uuid>,
+    },
+    #[serde(rename = "journal_settings_save")]
+    JournalSettingsSave { settings: JournalSettings },
+    #[serde(rename = "journal_today")]
+    JournalToday {
+        #[serde(default)]
+        profile_id: Option<Uuid>,
+        #[serde(default)]
+        day: Option<NaiveDate>,
+    },
+    /// Quick append from the tray or the global hotkey
+    #[serde(rename = "journal_append")]
+    JournalAppend {
+        #[serde(default)]
+        profile_id: Option<Uuid>,
+        #[serde(default)]
+        day: Option<NaiveDate>,
+        text: String,
+    },
+    #[serde(rename = "journal_entries")]
+    JournalEntries {
+        #[serde(default)]
+        profile_id: Option<Uuid>,
+        #[serde(default)]
+        from: Option<NaiveDate>,
+        #[serde(default)]
+        to: Option<NaiveDate>,
+    },
+    #[serde(rename = "journal_streak")]
+    JournalStreak {
+        #[serde(default)]
+        profile_id: Option<Uuid>,
+        #[serde(default)]
+        day: Option<NaiveDate>,
+    },
+    /// Write a read-only HTML copy of a document to `path`
+    #[serde(rename = "document_share")]
+    DocumentShare {
+        document_id: String,
+        path: String,
+        #[serde(default)]
+        options: ShareOptions,
+    },
+    /// Write a project's codex to `path` as JSON or CSV
+    #[serde(rename = "codex_export")]
+    CodexExport {
+        project_id: Uuid,
+        path: String,
+        #[serde(default)]
+        format: CodexFormat,
+    },
+    /// Import codex entries from `path`; with `options.dry_run` only
+    /// reports what would happen
+    #[serde(rename = "codex_import")]
+    CodexImport {
+        project_id: Uuid,
+        path: String,
+        #[serde(default)]
+        options: CodexImportOptions,
+    },
+    /// Mirror a project to a folder of Markdown files
+    #[serde(rename = "markdown_sync_enable")]
+    MarkdownSyncEnable { project_id: Uuid, folder: String },
+    #[serde(rename = "markdown_sync_disable")]
+    MarkdownSyncDisable { project_id: Uuid },
+    #[serde(rename = "markdown_sync_status")]
+    MarkdownSyncStatus { project_id: Uuid },
+    #[serde(rename = "markdown_sync_run")]
+    MarkdownSyncRun { project_id: Uuid },
+    /// Settle a sync conflict by keeping the app's or the folder's copy
+    #[serde(rename = "markdown_sync_resolve")]
+    MarkdownSyncResolve {
+        project_id: Uuid,
+        document_id: String,
+        keep: SyncSide,
+    },
+    /// Keep git history of a project, in its Markdown folder or the app's
+    #[serde(rename = "git_history_enable")]
+    GitHistoryEnable {
+        project_id: Uuid,
+        #[serde(default)]
+        target: HistoryTarget,
+    },
+    #[serde(rename = "git_history_disable")]
+    GitHistoryDisable { project_id: Uuid },
+    #[serde(rename = "git_history_status")]
+    GitHistoryStatus { project_id: Uuid },
+    /// Commit now, optionally naming the milestone
+    #[serde(rename = "git_history_commit")]
+    GitHistoryCommit {
+        project_id: Uuid,
+        message: Option<String>,
+    },
+    /// Commits newest first, only those touching a document if one is given
+    #[serde(rename = "git_history_log")]
+    GitHistoryLog {
+        project_id: Uuid,
+        document_id: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Set the project, or one document, back to a commit
+    #[serde(rename = "git_history_restore")]
+    GitHistoryRestore {
+        project_id: Uuid,
+        commit: String,
+        document_id: Option<String>,
+    },
+    /// Create or update one of a project's fictional calendars
+    #[serde(rename = "timeline_calendar_save")]
+    TimelineCalendarSave { calendar: CalendarSystem },
+    #[serde(rename = "timeline_calendar_delete")]
+    TimelineCalendarDelete { calendar_id: Uuid },
+    #[serde(rename = "timeline_calendars")]
+    TimelineCalendars { project_id: Uuid },
+    #[serde(rename = "timeline_event_save")]
+    TimelineEventSave { event: StoryEvent },
+    #[serde(rename = "timeline_event_delete")]
+    TimelineEventDelete { event_id: Uuid },
+    #[serde(rename = "timeline_events")]
+    TimelineEvents { project_id: Uuid },
+    /// Events and Time codex entries in order, with presence conflicts
+    #[serde(rename = "timeline_get")]
+    TimelineGet {
+        project_id: Uuid,
+        #[serde(default)]
+        filter: TimelineFilter,
+    },
+    /// Import an Obsidian vault's notes as research documents
+    #[serde(rename = "obsidian_import")]
+    ObsidianImport {
+        project_id: Uuid,
+        vault: String,
+        #[serde(default)]
+        options: NoteImportOptions,
+    },
+    /// Import a Notion export zip as projects, documents and codex entries
+    #[serde(rename = "notion_import")]
+    NotionImport {
+        path: String,
+        #[serde(default)]
+        options: NotionImportOptions,
+    },
+    #[serde(rename = "document_backlinks")]
+    DocumentBacklinks { document_id: Uuid },
+    /// Daily and per-document words, streaks, sessions and writing speed
+    #[serde(rename = "writing_stats_dashboard")]
+    WritingStatsDashboard {
+        project_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+    #[serde(rename = "writing_goal_set")]
+    WritingGoalSet { project_id: Uuid, goal: WritingGoal },
+    /// Readability scores and style measures of a document at a saved
+    /// version, or as it is now
+    #[serde(rename = "readability_analyze")]
+    ReadabilityAnalyze {
+        document_id: Uuid,
+        #[serde(default)]
+        version: Option<u32>,
+    },
+    #[serde(rename = "profiles_list")]
+    ProfilesList,
+    #[serde(rename = "profile_create")]
+    ProfileCreate { name: String, role: ProfileRole },
+    /// Needs the app-lock passphrase when one is set
+    #[serde(rename = "profile_switch")]
+    ProfileSwitch {
+        profile_id: Uuid,
+        passphrase: Option<String>,
+    },
+    #[serde(rename = "profile_delete")]
+    ProfileDelete {
+        profile_id: Uuid,
+        passphrase: Option<String>,
+    },
+    #[serde(rename = "profile_settings_save")]
+    ProfileSettingsSave {
+        profile_id: Uuid,
+        settings: Settings,
+    },
+    /// Monthly AI budget in cents; `None` lifts the limit
+    #[serde(rename = "profile_ai_budget_set")]
+    ProfileAiBudgetSet {
+        profile_id: Uuid,
+        budget_cents: Option<i64>,
+        passphrase: Option<String>,
+    },
+    #[serde(rename = "profile_project_grant")]
+    ProfileProjectGrant { profile_id: Uuid, project_id: Uuid },
+    #[serde(rename = "profile_project_revoke")]
+    ProfileProjectRevoke { profile_id: Uuid, project_id: Uuid },
+    /// Set or change the app-lock passphrase; changing it needs `current`
+    #[serde(rename = "app_lock_set")]
+    AppLockSet {
+        current: Option<String>,
+        passphrase: String,
+    },
+    /// A project's style sheet; empty until rules are added
+    #[serde(rename = "style_sheet_get")]
+    StyleSheetGet { project_id: Uuid },
+    #[serde(rename = "style_sheet_save")]
+    StyleSheetSave { style_sheet: StyleSheet },
+    #[serde(rename = "style_rule_add")]
+    StyleRuleAdd { project_id: Uuid, rule: StyleRule },
+    #[serde(rename = "style_rule_remove")]
+    StyleRuleRemove { project_id: Uuid, rule_id: Uuid },
+    /// Check every document in a project against its style sheet
+    #[serde(rename = "style_sheet_check")]
+    StyleSheetCheck { project_id: Uuid },
+    /// Apply the auto-fixable rules to one document
+    #[serde(rename = "style_sheet_fix")]
+    StyleSheetFix { project_id: Uuid, document_id: Uuid },
+    /// The shared lexicons plus a project's own
+    #[serde(rename = "content_lexicons")]
+    ContentLexicons { project_id: Uuid },
+    #[serde(rename = "content_lexicon_save")]
+    ContentLexiconSave { lexicon: ContentLexicon },
+    #[serde(rename = "content_lexicon_delete")]
+    ContentLexiconDelete { lexicon_id: Uuid },
+    /// Scan a project's chapters against its enabled lexicons
+    #[serde(rename = "content_scan")]
+    ContentScan { project_id: Uuid },
+    /// Register a beta reader on a project
+    #[serde(rename = "beta_reader_add")]
+    BetaReaderAdd {
+        project_id: Uuid,
+        name: String,
+        email: Option<String>,
+    },
+    #[serde(rename = "beta_readers_list")]
+    BetaReadersList { project_id: Uuid },
+    /// Render a reader's watermarked packet to `path`
+    #[serde(rename = "beta_packet_build")]
+    BetaPacketBuild {
+        reader_code: String,
+        format: PublishFormat,
+        path: String,
+    },
+    /// Import a reader's returned comment form as annotations
+    #[serde(rename = "beta_comments_import")]
+    BetaCommentsImport {
+        reader_code: String,
+        input: String,
+        format: CommentFormat,
+    },
+    #[serde(rename = "annotations_list")]
+    AnnotationsList { document_id: Uuid },
+    #[serde(rename = "annotation_create")]
+    AnnotationCreate { annotation: Annotation },
+    #[serde(rename = "annotation_resolve")]
+    AnnotationResolve { annotation_id: Uuid, resolved: bool },
+    #[serde(rename = "annotation_delete")]
+    AnnotationDelete { annotation_id: Uuid },
+    /// The shared cliché lists plus a project's own
+    #[serde(rename = "cliche_lists")]
+    ClicheLists { project_id: Uuid },
+    #[serde(rename = "cliche_list_save")]
+    ClicheListSave { list: ClicheList },
+    #[serde(rename = "cliche_list_delete")]
+    ClicheListDelete { list_id: Uuid },
+    /// Over-used words, phrases and clichés per chapter; `limit` caps the
+    /// top word and phrase lists
+    #[serde(rename = "word_usage_analyze")]
+    WordUsageAnalyze {
+        project_id: Uuid,
+        #[serde(default)]
+        limit: Option<usize>,
+    },
+    /// Every mention a rename would change, without changing anything
+    #[serde(rename = "rename_preview")]
+    RenamePreview { request: RenameRequest },
+    #[serde(rename = "rename_apply")]
+    RenameApply { request: RenameRequest },
+    /// Undo an applied rename; documents edited since are left alone
+    #[serde(rename = "rename_rollback")]
+    RenameRollback { operation_id: Uuid },
+    #[serde(rename = "rename_get")]
+    RenameGet { operation_id: Uuid },
+    /// Renames applied in a project, newest first
+    #[serde(rename = "renames_list")]
+    RenamesList { project_id: Uuid },
+    /// Active documents of a project in binder order
+    #[serde(rename = "binder_order_get")]
+    BinderOrderGet { project_id: Uuid },
+    /// Split a document into scene documents placed after it in the binder
+    #[serde(rename = "document_split")]
+    DocumentSplit { request: SplitRequest },
+    /// Merge documents into the first; the rest are deactivated
+    #[serde(rename = "documents_merge")]
+    DocumentsMerge { request: MergeRequest },
+    #[serde(rename = "conlangs_list")]
+    ConlangsList { project_id: Uuid },
+    /// Languages spoken by a codex culture
+    #[serde(rename = "conlangs_for_culture")]
+    ConlangsForCulture { culture_entry_id: Uuid },
+    #[serde(rename = "conlang_save")]
+    ConlangSave { language: Conlang },
+    /// Delete a language along with its lexicon
+    #[serde(rename = "conlang_delete")]
+    ConlangDelete { language_id: Uuid },
+    #[serde(rename = "lexicon_entries")]
+    LexiconEntries { language_id: Uuid },
+    #[serde(rename = "lexicon_entry_save")]
+    LexiconEntrySave { entry: LexiconEntry },
+    #[serde(rename = "lexicon_entry_delete")]
+    LexiconEntryDelete { entry_id: Uuid },
+    #[serde(rename = "lexicon_search")]
+    LexiconSearch { query: LexiconQuery },
+    #[serde(rename = "lexicon_export_csv")]
+    LexiconExportCsv { language_id: Uuid, path: String },
+    /// Write a language's dictionary as a PDF glossary for a book's appendix
+    #[serde(rename = "lexicon_export_appendix")]
+    LexiconExportAppendix { language_id: Uuid, path: String },
+    /// Every conlang word and variant in a project, for the spellchecker to
+    /// accept
+    #[serde(rename = "lexicon_spellcheck_words")]
+    LexiconSpellcheckWords { project_id: Uuid },
+    /// Write the project's conlang words as a Hunspell `.dic` file
+    #[serde(rename = "lexicon_dictionary_export")]
+    LexiconDictionaryExport { project_id: Uuid, path: String },
+}
+
+impl IpcMessage {
+    /// The wire name of the message, used for rate limiting and logging
+    pub fn name(&self) -> &'static str {
+        match self {
+            IpcMessage::Handshake { .. } => "handshake",
+            IpcMessage::DbQuery { .. } => "db_query",
+            IpcMessage::DbExecute { .. } => "db_execute",
+            IpcMessage::AiRequest { .. } => "ai_request",
+            IpcMessage::Log { .. } => "log",
+            IpcMessage::AppAction { .. } => "app_action",
+            IpcMessage::CredentialAdd { .. } => "credential_add",
+            IpcMessage::CredentialTest { .. } => "credential_test",
+            IpcMessage::CredentialRemove { .. } => "credential_remove",
+            IpcMessage::CredentialList => "credential_list",
+            IpcMessage::NetworkSetOffline { .. } => "network_set_offline",
+            IpcMessage::NetworkStatus => "network_status",
+            IpcMessage::SecureCopy { .. } => "secure_copy",
+            IpcMessage::ClipboardClear => "clipboard_clear",
+            IpcMessage::ThreatAlerts => "threat_alerts",
+            IpcMessage::ThreatConfirm { .. } => "threat_confirm",
+            IpcMessage::SecurityEvents => "security_events",
+            IpcMessage::DestructiveConfirm { .. } => "destructive_confirm",
+            IpcMessage::CursorPosition { .. } => "cursor_position",
+            IpcMessage::AutosavePing { .. } => "autosave_ping",
+            IpcMessage::ListPrinters => "list_printers",
+            IpcMessage::PrintDocument { .. } => "print_document",
+            IpcMessage::RecentRecord { .. } => "recent_record",
+            IpcMessage::RecentList => "recent_list",
+            IpcMessage::ProjectFileCreate { .. } => "project_file_create",
+            IpcMessage::CrashReports => "crash_reports",
+            IpcMessage::CrashReportSubmit { .. } => "crash_report_submit",
+            IpcMessage::CrashReportDismiss { .. } => "crash_report_dismiss",
+            IpcMessage::CrashMetrics => "crash_metrics",
+            IpcMessage::StorageInfo => "storage_info",
+            IpcMessage::DataDirPlan { .. } => "data_dir_plan",
+            IpcMessage::DataDirMigrate { .. } => "data_dir_migrate",
+            IpcMessage::AttachmentAdd { .. } => "attachment_add",
+            IpcMessage::AttachmentList { .. } => "attachment_list",
+            IpcMessage::AttachmentUpdate { .. } => "attachment_update",
+            IpcMessage::AttachmentRemove { .. } => "attachment_remove",
+            IpcMessage::CoverPalette { .. } => "cover_palette",
+            IpcMessage::CoverRender { .. } => "cover_render",
+            IpcMessage::DeviceList => "device_list",
+            IpcMessage::DeviceSmtpGet => "device_smtp_get",
+            IpcMessage::DeviceSmtpSave { .. } => "device_smtp_save",
+            IpcMessage::DeviceSend { .. } => "device_send",
+            IpcMessage::DeviceSendStatus { .. } => "device_send_status",
+            IpcMessage::DeviceSendJobs => "device_send_jobs",
+            IpcMessage::GeneratorTables { .. } => "generator_tables",
+            IpcMessage::GeneratorSave { .. } => "generator_save",
+            IpcMessage::GeneratorDelete { .. } => "generator_delete",
+            IpcMessage::GeneratorRoll { .. } => "generator_roll",
+            IpcMessage::StatsSessionStart { .. } => "stats_session_start",
+            IpcMessage::StatsSessionEnd { .. } => "stats_session_end",
+            IpcMessage::StatsExport { .. } => "stats_export",
+            IpcMessage::CodexGraphQuery { .. } => "codex_graph_query",
+            IpcMessage::CodexGraphQueryText { .. } => "codex_graph_query_text",
+            IpcMessage::CodexBacklinks { .. } => "codex_backlinks",
+            IpcMessage::EmbeddingModels => "embedding_models",
+            IpcMessage::EmbeddingModelRegister { .. } => "embedding_model_register",
+            IpcMessage::EmbeddingMigrationStart { .. } => "embedding_migration_start",
+            IpcMessage::EmbeddingMigrationStatus { .. } => "embedding_migration_status",
+            IpcMessage::EmbeddingMigrationCancel { .. } => "embedding_migration_cancel",
+            IpcMessage::EmbeddingSearch { .. } => "embedding_search",
+            IpcMessage::HybridSearch { .. } => "hybrid_search",
+            IpcMessage::FindDuplicateParagraphs { .. } => "find_duplicate_paragraphs",
+            IpcMessage::RelatedNotes { .. } => "related_notes",
+            IpcMessage::AskProject { .. } => "ask_project",
+            IpcMessage::CodexAutofillPropose { .. } => "codex_autofill_propose",
+            IpcMessage::CodexAutofillApply { .. } => "codex_autofill_apply",
+            IpcMessage::StoryBibleExport { .. } => "story_bible_export",
+            IpcMessage::LintPacks => "lint_packs",
+            IpcMessage::LintSettingsGet { .. } => "lint_settings_get",
+            IpcMessage::LintSettingsSave { .. } => "lint_settings_save",
+            IpcMessage::LintProject { .. } => "lint_project",
+            IpcMessage::NarrativeVoiceCheck { .. } => "narrative_voice_check",
+            IpcMessage::SceneVoiceSet { .. } => "scene_voice_set",
+            IpcMessage::ChronologyCheck { .. } => "chronology_check",
+            IpcMessage::ChronologyReadingOrder { .. } => "chronology_reading_order",
+            IpcMessage::ChronologyExport { .. } => "chronology_export",
+            IpcMessage::SubmissionMarkets => "submission_markets",
+            IpcMessage::SubmissionMarketSave { .. } => "submission_market_save",
+            IpcMessage::SubmissionMarketDelete { .. } => "submission_market_delete",
+            IpcMessage::SubmissionMarketStats => "submission_market_stats",
+            IpcMessage::SubmissionSave { .. } => "submission_save",
+            IpcMessage::SubmissionList { .. } => "submission_list",
+            IpcMessage::SubmissionRespond { .. } => "submission_respond",
+            IpcMessage::SubmissionReport { .. } => "submission_report",
+            IpcMessage::DeadlineList { .. } => "deadline_list",
+            IpcMessage::DeadlineSave { .. } => "deadline_save",
+            IpcMessage::DeadlineDelete { .. } => "deadline_delete",
+            IpcMessage::DeadlineCountdowns { .. } => "deadline_countdowns",
+            IpcMessage::SerialPlan { .. } => "serial_plan",
+            IpcMessage::SerialReleaseSave { .. } => "serial_release_save",
+            IpcMessage::SerialReleaseDelete { .. } => "serial_release_delete",
+            IpcMessage::SerialReleaseStage { .. } => "serial_release_stage",
+            IpcMessage::SerialReleasePublished { .. } => "serial_release_published",
+            IpcMessage::WordCountCertify { .. } => "word_count_certify",
+            IpcMessage::WordCountCertificates { .. } => "word_count_certificates",
+            IpcMessage::WordCountCertificateVerify { .. } => "word_count_certificate_verify",
+            IpcMessage::WordCountCertificateExport { .. } => "word_count_certificate_export",
+            IpcMessage::ChallengeList { .. } => "challenge_list",
+            IpcMessage::ChallengeSave { .. } => "challenge_save",
+            IpcMessage::ChallengeDelete { .. } => "challenge_delete",
+            IpcMessage::ChallengeDashboard { .. } => "challenge_dashboard",
+            IpcMessage::FocusStatus => "focus_status",
+            IpcMessage::FocusEnable { .. } => "focus_enable",
+            IpcMessage::FocusSummaries { .. } => "focus_summaries",
+            IpcMessage::FocusClear => "focus_clear",
+            IpcMessage::WorkspaceList { .. } => "workspace_list",
+            IpcMessage::WorkspaceSave { .. } => "workspace_save",
+            IpcMessage::WorkspaceDelete { .. } => "workspace_delete",
+            IpcMessage::WorkspaceSwitch { .. } => "workspace_switch",
+            IpcMessage::WorkspaceActive { .. } => "workspace_active",
+            IpcMessage::WorkspacePin { .. } => "workspace_pin",
+            IpcMessage::WorkspaceUnpin { .. } => "workspace_unpin",
+            IpcMessage::UndoState { .. } => "undo_state",
+            IpcMessage::Undo { .. } => "undo",
+            IpcMessage::Redo { .. } => "redo",
+            IpcMessage::BinderMove { .. } => "binder_move",
+            IpcMessage::CodexEntryDelete { .. } => "codex_entry_delete",
+            IpcMessage::DocumentsTag { .. } => "documents_tag",
+            IpcMessage::ActivityFeed { .. } => "activity_feed",
+            IpcMessage::ActivitySummary { .. } => "activity_summary",
+            IpcMessage::ActivityOpenProject { .. } => "activity_open_project",
+            IpcMessage::ProjectAnonymize { .. } => "project_anonymize",
+            IpcMessage::BackupList { .. } => "backup_list",
+            IpcMessage::BackupVerify { .. } => "backup_verify",
+            IpcMessage::BackupVerifyAll { .. } => "backup_verify_all",
+            IpcMessage::BackupSetEncryption { .. } => "backup_set_encryption",
+            IpcMessage::DocumentVersions { .. } => "document_versions",
+            IpcMessage::DocumentVersionDiff { .. } => "document_version_diff",
+            IpcMessage::DocumentRevert { .. } => "document_revert",
+            IpcMessage::DocumentMerge { .. } => "document_merge",
+            IpcMessage::AiLogList { .. } => "ai_log_list",
+            IpcMessage::AiLogExport { .. } => "ai_log_export",
+            IpcMessage::AiLogDelete { .. } => "ai_log_delete",
+            IpcMessage::AiLogProjectSet { .. } => "ai_log_project_set",
+            IpcMessage::CommandList { .. } => "command_list",
+            IpcMessage::CommandSearch { .. } => "command_search",
+            IpcMessage::CommandMenu { .. } => "command_menu",
+            IpcMessage::CommandInvoked { .. } => "command_invoked",
+            IpcMessage::DocumentTrash { .. } => "document_trash",
+            IpcMessage::ProjectTrash { .. } => "project_trash",
+            IpcMessage::TrashList { .. } => "trash_list",
+            IpcMessage::TrashRestore { .. } => "trash_restore",
+            IpcMessage::TrashPurge { .. } => "trash_purge",
+            IpcMessage::TrashEmpty { .. } => "trash_empty",
+            IpcMessage::DocumentMove { .. } => "document_move",
+            IpcMessage::DocumentCopy { .. } => "document_copy",
+            IpcMessage::DocumentTemplates { .. } => "document_templates",
+            IpcMessage::DocumentTemplateSave { .. } => "document_template_save",
+            IpcMessage::DocumentTemplateDelete { .. } => "document_template_delete",
+            IpcMessage::DocumentTemplatePrompts { .. } => "document_template_prompts",
+            IpcMessage::DocumentFromTemplate { .. } => "document_from_template",
+            IpcMessage::CodexRelationshipSave { .. } => "codex_relationship_save",
+            IpcMessage::CodexRelationshipDelete { .. } => "codex_relationship_delete",
+            IpcMessage::CodexRelationshipMap { .. } => "codex_relationship_map",
+            IpcMessage::CodexNeighbors { .. } => "codex_neighbors",
+            IpcMessage::CodexPath { .. } => "codex_path",
+            IpcMessage::CodexComponent { .. } => "codex_component",
+            IpcMessage::JournalSettings { .. } => "journal_settings",
+            IpcMessage::JournalSettingsSave { .. } => "journal_settings_save",
+            IpcMessage::JournalToday { .. } => "journal_today",
+            IpcMessage::JournalAppend { .. } => "journal_append",
+            IpcMessage::JournalEntries { .. } => "journal_entries",
+            IpcMessage::JournalStreak { .. } => "journal_streak",
+            IpcMessage::DocumentShare { .. } => "document_share",
+            IpcMessage::CodexExport { .. } => "codex_export",
+            IpcMessage::CodexImport { .. } => "codex_import",
+            IpcMessage::MarkdownSyncEnable { .. } => "markdown_sync_enable",
+            IpcMessage::MarkdownSyncDisable { .. } => "markdown_sync_disable",
+            IpcMessage::MarkdownSyncStatus { .. } => "markdown_sync_status",
+            IpcMessage::MarkdownSyncRun { .. } => "markdown_sync_run",
+            IpcMessage::MarkdownSyncResolve { .. } => "markdown_sync_resolve",
+            IpcMessage::GitHistoryEnable { .. } => "git_history_enable",
+            IpcMessage::GitHistoryDisable { .. } => "git_history_disable",
+            IpcMessage::GitHistoryStatus { .. } => "git_history_status",
+            IpcMessage::GitHistoryCommit { .. } => "git_history_commit",
+            IpcMessage::GitHistoryLog { .. } => "git_history_log",
+            IpcMessage::GitHistoryRestore { .. } => "git_history_restore",
+            IpcMessage::TimelineCalendarSave { .. } => "timeline_calendar_save",
+            IpcMessage::TimelineCalendarDelete { .. } => "timeline_calendar_delete",
+            IpcMessage::TimelineCalendars { .. } => "timeline_calendars",
+            IpcMessage::TimelineEventSave { .. } => "timeline_event_save",
+            IpcMessage::TimelineEventDelete { .. } => "timeline_event_delete",
+            IpcMessage::TimelineEvents { .. } => "timeline_events",
+            IpcMessage::TimelineGet { .. } => "timeline_get",
+            IpcMessage::ObsidianImport { .. } => "obsidian_import",
+            IpcMessage::NotionImport { .. } => "notion_import",
+            IpcMessage::DocumentBacklinks { .. } => "document_backlinks",
+            IpcMessage::WritingStatsDashboard { .. } => "writing_stats_dashboard",
+            IpcMessage::WritingGoalSet { .. } => "writing_goal_set",
+            IpcMessage::ReadabilityAnalyze { .. } => "readability_analyze",
+            IpcMessage::ProfilesList => "profiles_list",
+            IpcMessage::ProfileCreate { .. } => "profile_create",
+            IpcMessage::ProfileSwitch { .. } => "profile_switch",
+            IpcMessage::ProfileDelete { .. } => "profile_delete",
+            IpcMessage::ProfileSettingsSave { .. } => "profile_settings_save",
+            IpcMessage::ProfileAiBudgetSet { .. } => "profile_ai_budget_set",
+            IpcMessage::ProfileProjectGrant { .. } => "profile_project_grant",
+            IpcMessage::ProfileProjectRevoke { .. } => "profile_project_revoke",
+            IpcMessage::AppLockSet { .. } => "app_lock_set",
+            IpcMessage::StyleSheetGet { .. } => "style_sheet_get",
+            IpcMessage::StyleSheetSave { .. } => "style_sheet_save",
+            IpcMessage::StyleRuleAdd { .. } => "style_rule_add",
+            IpcMessage::StyleRuleRemove { .. } => "style_rule_remove",
+            IpcMessage::StyleSheetCheck { .. } => "style_sheet_check",
+            IpcMessage::StyleSheetFix { .. } => "style_sheet_fix",
+            IpcMessage::ContentLexicons { .. } => "content_lexicons",
+            IpcMessage::ContentLexiconSave { .. } => "content_lexicon_save",
+            IpcMessage::ContentLexiconDelete { .. } => "content_lexicon_delete",
+            IpcMessage::ContentScan { .. } => "content_scan",
+            IpcMessage::BetaReaderAdd { .. } => "beta_reader_add",
+            IpcMessage::BetaReadersList { .. } => "beta_readers_list",
+            IpcMessage::BetaPacketBuild { .. } => "beta_packet_build",
+            IpcMessage::BetaCommentsImport { .. } => "beta_comments_import",
+            IpcMessage::AnnotationsList { .. } => "annotations_list",
+            IpcMessage::AnnotationCreate { .. } => "annotation_create",
+            IpcMessage::AnnotationResolve { .. } => "annotation_resolve",
+            IpcMessage::AnnotationDelete { .. } => "annotation_delete",
+            IpcMessage::ClicheLists { .. } => "cliche_lists",
+            IpcMessage::ClicheListSave { .. } => "cliche_list_save",
+            IpcMessage::ClicheListDelete { .. } => "cliche_list_delete",
+            IpcMessage::WordUsageAnalyze { .. } => "word_usage_analyze",
+            IpcMessage::RenamePreview { .. } => "rename_preview",
+            IpcMessage::RenameApply { .. } => "rename_apply",
+            IpcMessage::RenameRollback { .. } => "rename_rollback",
+            IpcMessage::RenameGet { .. } => "rename_get",
+            IpcMessage::RenamesList { .. } => "renames_list",
+            IpcMessage::BinderOrderGet { .. } => "binder_order_get",
+            IpcMessage::DocumentSplit { .. } => "document_split",
+            IpcMessage::DocumentsMerge { .. } => "documents_merge",
+            IpcMessage::ConlangsList { .. } => "conlangs_list",
+            IpcMessage::ConlangsForCulture { .. } => "conlangs_for_culture",
+            IpcMessage::ConlangSave { .. } => "conlang_save",
+            IpcMessage::ConlangDelete { .. } => "conlang_delete",
+            IpcMessage::LexiconEntries { .. } => "lexicon_entries",
+            IpcMessage::LexiconEntrySave { .. } => "lexicon_entry_save",
+            IpcMessage::LexiconEntryDelete { .. } => "lexicon_entry_delete",
+            IpcMessage::LexiconSearch { .. } => "lexicon_search",
+            IpcMessage::LexiconExportCsv { .. } => "lexicon_export_csv",
+            IpcMessage::LexiconExportAppendix { .. } => "lexicon_export_appendix",
+            IpcMessage::LexiconSpellcheckWords { .. } => "lexicon_spellcheck_words",
+            IpcMessage::LexiconDictionaryExport { .. } => "lexicon_dictionary_export",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IpcResponseWrapper {
     pub id: String,
+    /// Identifies this request in backend logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
     #[serde(flatten)]
     pub response: IpcResponse,
 }
@@ -36,23 +1688,502 @@ pub struct IpcResponseWrapper {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", content = "payload")]
 pub enum IpcResponse {
+    #[serde(rename = "handshake")]
+    Handshake {
+        api_version: u32,
+        min_api_version: u32,
+        backend_version: String,
+        commands: Vec<CommandSpec>,
+        capabilities: Vec<String>,
+    },
     #[serde(rename = "db_result")]
     DbResult { data: Value },
     #[serde(rename = "db_execute_success")]
     DbExecuteSuccess,
     #[serde(rename = "ai_response")]
     AiResponse { text: String },
+    /// `error` is set when a service failed, with the error's code and a
+    /// message fit for the user (v3)
     #[serde(rename = "error")]
-    Error { message: String },
+    Error {
+        code: IpcErrorCode,
+        message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<ErrorEnvelope>,
+    },
     #[serde(rename = "ack")]
     Ack,
+    #[serde(rename = "credential")]
+    Credential { credential: CredentialSummary },
+    #[serde(rename = "credential_list")]
+    CredentialList {
+        credentials: Vec<CredentialSummary>,
+        warnings: Vec<String>,
+    },
+    #[serde(rename = "credential_removed")]
+    CredentialRemoved { removed: bool },
+    #[serde(rename = "network_status")]
+    NetworkStatus {
+        offline: bool,
+        allow_list: Vec<String>,
+    },
+    #[serde(rename = "secure_copy")]
+    SecureCopy { receipt: SecureCopyReceipt },
+    #[serde(rename = "threat_alerts")]
+    ThreatAlerts { alerts: Vec<ThreatAlert> },
+    #[serde(rename = "security_events")]
+    SecurityEvents { events: Vec<SecurityEvent> },
+    #[serde(rename = "confirmation_grant")]
+    ConfirmationGrant { grant: ConfirmationGrant },
+    #[serde(rename = "printers")]
+    Printers { printers: Vec<PrinterInfo> },
+    #[serde(rename = "print_job")]
+    PrintJob { job: PrintJob },
+    #[serde(rename = "recent_items")]
+    RecentItems { items: Vec<RecentItem> },
+    #[serde(rename = "project_file")]
+    ProjectFile { path: String },
+    #[serde(rename = "crash_reports")]
+    CrashReports { reports: Vec<CrashReport> },
+    #[serde(rename = "crash_metrics")]
+    CrashMetrics {
+        metrics: SessionMetrics,
+        crash_free_rate: f64,
+    },
+    #[serde(rename = "storage_info")]
+    StorageInfo { paths: AppPaths },
+    #[serde(rename = "data_migration_plan")]
+    DataMigrationPlan { plan: DataMigrationPlan },
+    /// The new location is used from the next start
+    #[serde(rename = "data_migration")]
+    DataMigration { report: DataMigrationReport },
+    #[serde(rename = "attachment")]
+    Attachment { attachment: Attachment },
+    #[serde(rename = "attachments")]
+    Attachments { attachments: Vec<Attachment> },
+    #[serde(rename = "palette")]
+    Palette { palette: ColorPalette },
+    #[serde(rename = "cover")]
+    Cover {
+        asset_hash: String,
+        width: u32,
+        height: u32,
+        dpi: u32,
+    },
+    #[serde(rename = "devices")]
+    Devices { devices: Vec<DeviceFolder> },
+    /// Saved settings without the password
+    #[serde(rename = "smtp_settings")]
+    SmtpSettings { settings: Option<SmtpSettings> },
+    #[serde(rename = "send_job")]
+    SendJob { job: SendJob },
+    #[serde(rename = "send_jobs")]
+    SendJobs { jobs: Vec<SendJob> },
+    #[serde(rename = "generator_tables")]
+    GeneratorTables { tables: Vec<GeneratorTable> },
+    #[serde(rename = "generator_results")]
+    GeneratorResults { results: Vec<String> },
+    #[serde(rename = "writing_session")]
+    WritingSession { session: WritingSession },
+    #[serde(rename = "stats_export")]
+    StatsExport { export: StatsExport },
+    #[serde(rename = "codex_graph_matches")]
+    CodexGraphMatches { matches: Vec<GraphMatch> },
+    #[serde(rename = "codex_backlinks")]
+    CodexBacklinks { backlinks: Backlinks },
+    #[serde(rename = "embedding_models")]
+    EmbeddingModels { models: Vec<EmbeddingModel> },
+    #[serde(rename = "embedding_migration")]
+    EmbeddingMigration {
+        migration: Option<EmbeddingMigration>,
+    },
+    #[serde(rename = "semantic_results")]
+    SemanticResults { results: Vec<SearchResult> },
+    #[serde(rename = "hybrid_results")]
+    HybridResults { results: Vec<HybridSearchResult> },
+    #[serde(rename = "duplicate_paragraphs")]
+    DuplicateParagraphs { pairs: Vec<DuplicatePair> },
+    #[serde(rename = "related_notes")]
+    RelatedNotes { related: RelatedNotes },
+    #[serde(rename = "project_answer")]
+    ProjectAnswer { answer: AskAnswer },
+    #[serde(rename = "codex_autofill_proposal")]
+    CodexAutofillProposal { proposal: AutofillProposal },
+    #[serde(rename = "codex_autofill_applied")]
+    CodexAutofillApplied { fields: Vec<String> },
+    #[serde(rename = "story_bible")]
+    StoryBible { bible: StoryBible, path: String },
+    #[serde(rename = "lint_packs")]
+    LintPacks { packs: Vec<LintPack> },
+    #[serde(rename = "lint_settings")]
+    LintSettings { settings: LintSettings },
+    #[serde(rename = "lint_report")]
+    LintReport { report: LintReport },
+    #[serde(rename = "voice_report")]
+    VoiceReport { report: VoiceReport },
+    #[serde(rename = "chronology_report")]
+    ChronologyReport { report: ChronologyReport },
+    #[serde(rename = "reading_order")]
+    ReadingOrder { order: ReadingOrder },
+    #[serde(rename = "chronological_export")]
+    ChronologicalExport {
+        export: ChronologicalExport,
+        path: String,
+    },
+    #[serde(rename = "markets")]
+    Markets { markets: Vec<Market> },
+    #[serde(rename = "market")]
+    Market { market: Market },
+    #[serde(rename = "market_stats")]
+    MarketStats { stats: Vec<MarketStats> },
+    #[serde(rename = "submission")]
+    Submission { submission: Submission },
+    #[serde(rename = "submissions")]
+    Submissions { submissions: Vec<Submission> },
+    #[serde(rename = "submission_report")]
+    SubmissionReport { report: SubmissionReport },
+    #[serde(rename = "deadline")]
+    Deadline { deadline: Deadline },
+    #[serde(rename = "deadlines")]
+    Deadlines { deadlines: Vec<Deadline> },
+    #[serde(rename = "deadline_countdowns")]
+    DeadlineCountdowns { countdowns: Vec<DeadlineCountdown> },
+    #[serde(rename = "serial_plan")]
+    SerialPlan { plan: ReleasePlan },
+    #[serde(rename = "serial_release")]
+    SerialRelease { release: SerialRelease },
+    #[serde(rename = "word_count_certificate")]
+    WordCountCertificate { certificate: WordCountCertificate },
+    #[serde(rename = "word_count_certificates")]
+    WordCountCertificates {
+        certificates: Vec<WordCountCertificate>,
+    },
+    #[serde(rename = "word_count_certificate_verification")]
+    CertificateVerification {
+        verification: CertificateVerification,
+    },
+    #[serde(rename = "word_count_certificate_exported")]
+    CertificateExported {
+        certificate: WordCountCertificate,
+        path: String,
+    },
+    #[serde(rename = "challenge")]
+    Challenge { challenge: Challenge },
+    #[serde(rename = "challenges")]
+    Challenges { challenges: Vec<Challenge> },
+    #[serde(rename = "challenge_dashboard")]
+    ChallengeDashboard { dashboard: ChallengeDashboard },
+    #[serde(rename = "focus_status")]
+    FocusStatus { status: FocusAnalyticsStatus },
+    #[serde(rename = "focus_summaries")]
+    FocusSummaries { summaries: Vec<FocusDaySummary> },
+    #[serde(rename = "workspace")]
+    Workspace { workspace: Workspace },
+    #[serde(rename = "workspaces")]
+    Workspaces { workspaces: Vec<Workspace> },
+    #[serde(rename = "active_workspace")]
+    ActiveWorkspace { workspace: Option<Workspace> },
+    #[serde(rename = "undo_state")]
+    UndoState { state: UndoState },
+    /// The operation just undone or redone, so the frontend knows what to
+    /// refresh
+    #[serde(rename = "undo_applied")]
+    UndoApplied { operation: UndoOperation },
+    #[serde(rename = "binder_order")]
+    BinderOrder { document_ids: Vec<Uuid> },
+    #[serde(rename = "documents_tagged")]
+    DocumentsTagged { changed: usize },
+    #[serde(rename = "activity_feed")]
+    ActivityFeed { entries: Vec<ActivityEntry> },
+    #[serde(rename = "activity_summary")]
+    ActivitySummary { summary: ActivitySummary },
+    #[serde(rename = "project_anonymized")]
+    ProjectAnonymized { project: AnonymizedProject },
+    #[serde(rename = "backups")]
+    Backups { backups: Vec<BackupMetadata> },
+    #[serde(rename = "backup_verification")]
+    BackupVerification { verification: BackupVerification },
+    #[serde(rename = "backup_verifications")]
+    BackupVerifications {
+        verifications: Vec<BackupVerification>,
+    },
+    #[serde(rename = "document_versions")]
+    DocumentVersions { versions: Vec<DocumentVersion> },
+    #[serde(rename = "document_version_diff")]
+    DocumentVersionDiff { diff: VersionDiff },
+    #[serde(rename = "document_reverted")]
+    DocumentReverted { version: u32 },
+    #[serde(rename = "document_merged")]
+    DocumentMerged { merge: DocumentMerge },
+    #[serde(rename = "ai_log")]
+    AiLog { entries: Vec<AiInteraction> },
+    #[serde(rename = "ai_log_export")]
+    AiLogExport { content: String },
+    #[serde(rename = "ai_log_deleted")]
+    AiLogDeleted { removed: u64 },
+    #[serde(rename = "commands")]
+    Commands { commands: Vec<CommandItem> },
+    #[serde(rename = "trash")]
+    Trash { items: Vec<TrashItem> },
+    #[serde(rename = "trash_emptied")]
+    TrashEmptied { removed: u64 },
+    #[serde(rename = "document_copied")]
+    DocumentCopied { document_id: String },
+    #[serde(rename = "document_templates")]
+    DocumentTemplates { templates: Vec<DocumentTemplate> },
+    #[serde(rename = "document_template_prompts")]
+    DocumentTemplatePrompts { prompts: Vec<ResolvedPrompt> },
+    #[serde(rename = "document_created")]
+    DocumentCreated { document_id: String },
+    #[serde(rename = "codex_relationship_map")]
+    CodexRelationshipMap { map: RelationshipMap },
+    #[serde(rename = "codex_neighbors")]
+    CodexNeighbors { neighbors: Vec<Neighbor> },
+    /// Links from one entry to the other in order; None when they aren't connected
+    #[serde(rename = "codex_path")]
+    CodexPath {
+        path: Option<Vec<CodexRelationship>>,
+    },
+    /// None until the journal is set up
+    #[serde(rename = "journal_settings")]
+    JournalSettings { settings: Option<JournalSettings> },
+    #[serde(rename = "journal_entry")]
+    JournalEntry { entry: JournalEntry },
+    #[serde(rename = "journal_entries")]
+    JournalEntries { entries: Vec<JournalEntry> },
+    #[serde(rename = "journal_streak")]
+    JournalStreak { streak: JournalStreak },
+    #[serde(rename = "document_shared")]
+    DocumentShared {
+        path: String,
+        encrypted: bool,
+        expires_at: Option<DateTime<Utc>>,
+    },
+    #[serde(rename = "codex_exported")]
+    CodexExported { result: CodexExportResult },
+    #[serde(rename = "codex_imported")]
+    CodexImported { result: CodexImportResult },
+    #[serde(rename = "markdown_sync_folder")]
+    MarkdownSyncFolder { folder: Option<MarkdownSyncFolder> },
+    #[serde(rename = "markdown_sync_report")]
+    MarkdownSyncReport { report: MarkdownSyncReport },
+    #[serde(rename = "git_history_settings")]
+    GitHistorySettings {
+        settings: Option<GitHistorySettings>,
+    },
+    /// None when there was nothing to commit
+    #[serde(rename = "git_history_committed")]
+    GitHistoryCommitted { commit: Option<HistoryCommit> },
+    #[serde(rename = "git_history_log")]
+    GitHistoryLog { commits: Vec<HistoryCommit> },
+    #[serde(rename = "git_history_restored")]
+    GitHistoryRestored { restore: HistoryRestore },
+    #[serde(rename = "timeline_calendars")]
+    TimelineCalendars { calendars: Vec<CalendarSystem> },
+    #[serde(rename = "timeline_events")]
+    TimelineEvents { events: Vec<StoryEvent> },
+    #[serde(rename = "story_timeline")]
+    StoryTimeline { timeline: StoryTimeline },
+    #[serde(rename = "notes_imported")]
+    NotesImported { report: NoteImportReport },
+    #[serde(rename = "document_backlinks")]
+    DocumentBacklinks { backlinks: Vec<DocumentBacklink> },
+    #[serde(rename = "writing_stats_dashboard")]
+    WritingStatsDashboard { dashboard: WritingStatsDashboard },
+    #[serde(rename = "readability_report")]
+    ReadabilityReport { report: ReadabilityReport },
+    #[serde(rename = "profiles")]
+    Profiles {
+        profiles: Vec<UserProfile>,
+        active: Option<Uuid>,
+    },
+    #[serde(rename = "profile")]
+    Profile { profile: Box<UserProfile> },
+    #[serde(rename = "style_sheet")]
+    StyleSheet { style_sheet: StyleSheet },
+    #[serde(rename = "style_reports")]
+    StyleReports { reports: Vec<DocumentStyleReport> },
+    #[serde(rename = "style_fix")]
+    StyleFix { result: StyleFixResult },
+    #[serde(rename = "content_lexicons")]
+    ContentLexicons { lexicons: Vec<ContentLexicon> },
+    /// `front_matter` is the content warning page as Markdown, if anything
+    /// was found
+    #[serde(rename = "content_scan")]
+    ContentScan {
+        report: ContentScanReport,
+        front_matter: Option<String>,
+    },
+    #[serde(rename = "beta_reader")]
+    BetaReader { reader: BetaReader },
+    #[serde(rename = "beta_readers")]
+    BetaReaders { readers: Vec<BetaReader> },
+    #[serde(rename = "beta_packet")]
+    BetaPacket { packet: BetaPacket, path: String },
+    #[serde(rename = "beta_comments_imported")]
+    BetaCommentsImported { result: CommentImportResult },
+    #[serde(rename = "annotations")]
+    Annotations { annotations: Vec<Annotation> },
+    #[serde(rename = "cliche_lists")]
+    ClicheLists { lists: Vec<ClicheList> },
+    #[serde(rename = "word_usage")]
+    WordUsage { report: WordUsageReport },
+    #[serde(rename = "rename_preview")]
+    RenamePreview { preview: RenamePreview },
+    #[serde(rename = "rename_operation")]
+    RenameOperation { operation: RenameOperation },
+    #[serde(rename = "renames")]
+    Renames { operations: Vec<RenameOperation> },
+    #[serde(rename = "rename_rollback")]
+    RenameRollback { rollback: RenameRollback },
+    #[serde(rename = "document_split")]
+    DocumentSplit { result: SplitResult },
+    #[serde(rename = "documents_merged")]
+    DocumentsMerged { result: MergeResult },
+    #[serde(rename = "conlangs")]
+    Conlangs { languages: Vec<Conlang> },
+    #[serde(rename = "lexicon_entries")]
+    LexiconEntries { entries: Vec<LexiconEntry> },
+    #[serde(rename = "lexicon_exported")]
+    LexiconExported { path: String },
+    #[serde(rename = "lexicon_words")]
+    LexiconWords { words: Vec<String> },
+}
+
+impl IpcResponse {
+    pub fn error(code: IpcErrorCode, message: impl Into<String>) -> Self {
+        IpcResponse::Error {
+            code,
+            message: message.into(),
+            error: None,
+        }
+    }
+
+    /// Response for a handler that failed; the message is the one meant
+    /// for the user
+    pub fn service_error(error: impl Into<ErrorEnvelope>) -> Self {
+        let error = error.into();
+        IpcResponse::Error {
+            code: IpcErrorCode::ServiceError,
+            message: error.message.clone(),
+            error: Some(error),
+        }
+    }
+}
+
+/// Events buffered per subscriber; a subscriber that falls further behind
+/// skips the oldest
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Unsolicited messages pushed from the backend to every open webview
+/// through `__IPC_RECEIVE__`, in the same `{type, payload}` shape as a
+/// response but without a request id
+#[derive(Debug, Clone)]
+pub struct IpcEvents {
+    sender: tokio::sync::broadcast::Sender<String>,
+}
+
+impl IpcEvents {
+    pub fn new() -> Self {
+        let (sender, _) = tokio::sync::broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Push an event; dropped silently when no window is listening
+    pub fn push<T: Serialize>(&self, event_type: &str, payload: &T) {
+        match serde_json::to_value(payload) {
+            Ok(payload) => {
+                let message = serde_json::json!({ "type": event_type, "payload": payload });
+                let _ = self.sender.send(message.to_string());
+            }
+            Err(e) => log::error!("Failed to serialize '{}' event: {}", event_type, e),
+        }
+    }
+
+    /// Receive every event pushed from now on, serialized
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<String> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for IpcEvents {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 pub struct IpcBridge {
-    db_service: Arc<Mutex<DatabaseService>>,
+    pub(crate) db_service: DatabaseService,
     ai_service: Arc<AiService>,
+    pub(crate) credential_manager: Arc<CredentialManager>,
+    pub(crate) secure_clipboard: Arc<SecureClipboard>,
+    pub(crate) threat_detector: Arc<ThreatDetector>,
+    pub(crate) security_events: Arc<SecurityEventLog>,
+    pub(crate) confirmation_guard: Arc<ConfirmationGuard>,
+    pub(crate) recent_items: Arc<RecentItems>,
+    pub(crate) crash_reporter: Arc<CrashReporter>,
+    pub(crate) attachments: Arc<AttachmentService>,
+    pub(crate) device_sender: Arc<DeviceSender>,
+    pub(crate) generators: Arc<GeneratorService>,
+    pub(crate) stats: Arc<StatsService>,
+    pub(crate) codex_graph: Arc<CodexGraphService>,
+    pub(crate) embeddings: Arc<VectorEmbeddingService>,
+    pub(crate) related_notes: Arc<RelatedNotesService>,
+    pub(crate) ask: Arc<AskService>,
+    pub(crate) codex_autofill: Arc<CodexAutofillService>,
+    pub(crate) story_bible: Arc<StoryBibleService>,
+    pub(crate) analysis: Arc<AnalysisService>,
+    pub(crate) chronology: Arc<ChronologyService>,
+    pub(crate) submissions: Arc<SubmissionService>,
+    pub(crate) deadlines: Arc<DeadlineService>,
+    pub(crate) serial: Arc<SerialService>,
+    pub(crate) certification: Arc<CertificationService>,
+    pub(crate) challenges: Arc<ChallengeService>,
+    pub(crate) focus: Arc<FocusService>,
+    pub(crate) workspaces: Arc<WorkspaceService>,
+    pub(crate) undo_history: Arc<UndoHistoryService>,
+    pub(crate) activity: Arc<ActivityService>,
+    pub(crate) anonymizer: Arc<AnonymizerService>,
+    pub(crate) hybrid_search: Arc<HybridSearchService>,
+    pub(crate) backups: Arc<BackupService>,
+    pub(crate) ai_log: Arc<AiLogService>,
+    pub(crate) command_registry: Arc<CommandRegistry>,
+    pub(crate) document_templates: Arc<DocumentTemplateService>,
+    pub(crate) codex_relationships: Arc<CodexRelationshipService>,
+    pub(crate) journal: Arc<JournalService>,
+    pub(crate) codex_transfer: Arc<CodexTransferService>,
+    pub(crate) markdown_sync: Arc<MarkdownSyncService>,
+    pub(crate) git_history: Arc<GitHistoryService>,
+    pub(crate) calendars: Arc<CalendarService>,
+    pub(crate) timeline: Arc<TimelineService>,
+    pub(crate) note_import: Arc<NoteImportService>,
+    pub(crate) writing_stats: Arc<WritingStatsService>,
+    pub(crate) profiles: Arc<ProfileService>,
+    pub(crate) style_sheets: Arc<StyleSheetService>,
+    pub(crate) content_scan: Arc<ContentScanService>,
+    pub(crate) annotations: Arc<AnnotationService>,
+    pub(crate) beta_readers: Arc<BetaReaderService>,
+    pub(crate) word_usage: Arc<WordUsageService>,
+    pub(crate) renames: Arc<RenameService>,
+    pub(crate) document_structure: Arc<DocumentStructureService>,
+    pub(crate) lexicons: Arc<LexiconService>,
+    events: IpcEvents,
+    rate_limiter: RateLimiter,
+    /// API version agreed with the frontend; frontends that never
+    /// handshake are treated as v1
+    api_version: AtomicU32,
+    cursor_positions: Arc<Mutex<HashMap<String, usize>>>,
+    pub(crate) cursor_debouncer: Debouncer<usize>,
+    pub(crate) autosave_debouncer: Debouncer<String>,
 }
 
+/// Requests slower than this are logged as warnings
+const SLOW_REQUEST_THRESHOLD: Duration = Duration::from_secs(1);
+/// Cursor updates are applied at most this often per document
+const CURSOR_COALESCE_WINDOW: Duration = Duration::from_millis(250);
+/// Autosave pings write to the database at most this often per document
+const AUTOSAVE_COALESCE_WINDOW: Duration = Duration::from_secs(2);
+
 #[derive(Debug, PartialEq)]
 pub enum AppAction {
     Exit,
@@ -66,122 +2197,602 @@ pub enum AppAction {
 }
 
 impl IpcBridge {
-    pub fn new(db_service: Arc<Mutex<DatabaseService>>, ai_service: Arc<AiService>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db_service: DatabaseService,
+        ai_service: Arc<AiService>,
+        credential_manager: Arc<CredentialManager>,
+        secure_clipboard: Arc<SecureClipboard>,
+        threat_detector: Arc<ThreatDetector>,
+        security_events: Arc<SecurityEventLog>,
+        confirmation_guard: Arc<ConfirmationGuard>,
+        recent_items: Arc<RecentItems>,
+        crash_reporter: Arc<CrashReporter>,
+        attachments: Arc<AttachmentService>,
+        device_sender: Arc<DeviceSender>,
+        generators: Arc<GeneratorService>,
+        stats: Arc<StatsService>,
+        codex_graph: Arc<CodexGraphService>,
+        embeddings: Arc<VectorEmbeddingService>,
+        related_notes: Arc<RelatedNotesService>,
+        ask: Arc<AskService>,
+        codex_autofill: Arc<CodexAutofillService>,
+        story_bible: Arc<StoryBibleService>,
+        analysis: Arc<AnalysisService>,
+        chronology: Arc<ChronologyService>,
+        submissions: Arc<SubmissionService>,
+        deadlines: Arc<DeadlineService>,
+        serial: Arc<SerialService>,
+        certification: Arc<CertificationService>,
+        challenges: Arc<ChallengeService>,
+        focus: Arc<FocusService>,
+        workspaces: Arc<WorkspaceService>,
+        undo_history: Arc<UndoHistoryService>,
+        activity: Arc<ActivityService>,
+        anonymizer: Arc<AnonymizerService>,
+        hybrid_search: Arc<HybridSearchService>,
+        backups: Arc<BackupService>,
+        ai_log: Arc<AiLogService>,
+        command_registry: Arc<CommandRegistry>,
+        document_templates: Arc<DocumentTemplateService>,
+        codex_relationships: Arc<CodexRelationshipService>,
+        journal: Arc<JournalService>,
+        codex_transfer: Arc<CodexTransferService>,
+        markdown_sync: Arc<MarkdownSyncService>,
+        git_history: Arc<GitHistoryService>,
+        calendars: Arc<CalendarService>,
+        timeline: Arc<TimelineService>,
+        note_import: Arc<NoteImportService>,
+        writing_stats: Arc<WritingStatsService>,
+        profiles: Arc<ProfileService>,
+        style_sheets: Arc<StyleSheetService>,
+        content_scan: Arc<ContentScanService>,
+        annotations: Arc<AnnotationService>,
+        beta_readers: Arc<BetaReaderService>,
+        word_usage: Arc<WordUsageService>,
+        renames: Arc<RenameService>,
+        document_structure: Arc<DocumentStructureService>,
+        lexicons: Arc<LexiconService>,
+    ) -> Self {
+        let cursor_positions = Arc::new(Mutex::new(HashMap::new()));
+        let positions = cursor_positions.clone();
+        let cursor_debouncer =
+            Debouncer::new(CURSOR_COALESCE_WINDOW, move |document_id, offset| {
+                if let Ok(mut positions) = positions.lock() {
+                    positions.insert(document_id, offset);
+                }
+                Box::pin(async {}) as FlushFuture
+            });
+
+        let autosave_db = db_service.clone();
+        let autosave_stats = writing_stats.clone();
+        let autosave_debouncer = Debouncer::new(
+            AUTOSAVE_COALESCE_WINDOW,
+            move |document_id: String, content: String| {
+                let db = autosave_db.clone();
+                let stats = autosave_stats.clone();
+                Box::pin(async move {
+                    // Read before saving, so the stats see how much the save changed
+                    let before = stats.word_count(&document_id).await;
+                    if let Err(e) = db.update_document_content(&document_id, &content).await {
+                        log::error!("Autosave of document {} failed: {}", document_id, e);
+                        return;
+                    }
+                    if let Err(e) = record_document_edit(&db.pool, &document_id).await {
+                        log::warn!("Failed to record activity: {}", e);
+                    }
+                    let recorded = match before {
+                        Ok(Some(before)) => stats
+                            .record_words(&before, content.split_whitespace().count() as i64)
+                            .await
+                            .map(|_| ()),
+                        Ok(None) => Ok(()),
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = recorded {
+                        log::warn!("Failed to record writing stats: {}", e);
+                    }
+                }) as FlushFuture
+            },
+        );
+
         Self {
             db_service,
             ai_service,
+            credential_manager,
+            secure_clipboard,
+            threat_detector,
+            security_events,
+            confirmation_guard,
+            recent_items,
+            crash_reporter,
+            attachments,
+            device_sender,
+            generators,
+            stats,
+            codex_graph,
+            embeddings,
+            related_notes,
+            ask,
+            codex_autofill,
+            story_bible,
+            analysis,
+            chronology,
+            submissions,
+            deadlines,
+            serial,
+            certification,
+            challenges,
+            focus,
+            workspaces,
+            undo_history,
+            activity,
+            anonymizer,
+            hybrid_search,
+            backups,
+            ai_log,
+            command_registry,
+            document_templates,
+            codex_relationships,
+            journal,
+            codex_transfer,
+            markdown_sync,
+            git_history,
+            calendars,
+            timeline,
+            note_import,
+            writing_stats,
+            profiles,
+            style_sheets,
+            content_scan,
+            annotations,
+            beta_readers,
+            word_usage,
+            renames,
+            document_structure,
+            lexicons,
+            events: IpcEvents::new(),
+            rate_limiter: RateLimiter::default(),
+            api_version: AtomicU32::new(MIN_SUPPORTED_API_VERSION),
+            cursor_positions,
+            cursor_debouncer,
+            autosave_debouncer,
         }
     }
 
+    /// Channel for events pushed to the webviews; hand a clone to any
+    /// service that reports progress on its own
+    pub fn events(&self) -> IpcEvents {
+        self.events.clone()
+    }
+
+    /// Last cursor offset reported by the frontend for a document
+    pub fn cursor_position(&self, document_id: &str) -> Option<usize> {
+        self.cursor_positions.lock().ok()?.get(document_id).copied()
+    }
+
     pub async fn handle_message(&self, message: String) -> (String, Option<AppAction>) {
-        match serde_json::from_str::<IpcRequest>(&message) {
+        let correlation_id = correlation::new_id();
+        let (id, response, action) = match parse_request(&message) {
             Ok(req) => {
-                let mut action = None;
-                let response_payload = match req.message {
-                    IpcMessage::DbQuery { sql, params } => {
-                        let string_params: Vec<String> = params.iter()
-                            .map(|v| v.to_string().trim_matches('"').to_string())
-                            .collect();
-                        
-                        let db = {
-                            let guard = self.db_service.lock().unwrap();
-                            guard.clone()
-                        };
-
-                        match db.query(&sql, &string_params).await {
-                            Ok(result) => {
-                                let rows: Vec<serde_json::Map<String, Value>> = result.into_iter().map(|row| {
-                                    let mut map = serde_json::Map::new();
-                                    for (i, col) in row.columns.iter().enumerate() {
-                                        let val = match &row.values[i] {
-                                            Some(v) => Value::String(v.clone()),
-                                            None => Value::Null,
-                                        };
-                                        map.insert(col.clone(), val);
-                                    }
-                                    map
-                                }).collect();
-                                
-                                IpcResponse::DbResult { 
-                                    data: Value::Array(rows.into_iter().map(Value::Object).collect()) 
-                                }
-                            }
-                            Err(e) => IpcResponse::Error { message: e.to_string() }
+                let name = req.message.name();
+                if let Err(retry_after) = self.rate_limiter.check(name) {
+                    log::warn!("[{}] Rate limited IPC message '{}'", correlation_id, name);
+                    let response = IpcResponse::error(
+                        IpcErrorCode::RateLimited,
+                        format!(
+                            "Too many '{}' requests; retry in {} ms",
+                            name,
+                            retry_after.as_millis()
+                        ),
+                    );
+                    (req.id, response, None)
+                } else {
+                    let span = tracing::info_span!("ipc_request", correlation_id = %correlation_id, command = name, request_id = %req.id);
+                    let started = Instant::now();
+                    let (response, action) =
+                        correlation::scope(correlation_id.clone(), self.dispatch(req.message))
+                            .instrument(span)
+                            .await;
+                    let elapsed = started.elapsed();
+                    match &response {
+                        IpcResponse::Error {
+                            code,
+                            message,
+                            error,
+                        } => {
+                            // Log the internal details the user isn't shown
+                            let code = error
+                                .as_ref()
+                                .map_or(format!("{:?}", code), |e| format!("{:?}", e.code));
+                            let details = error
+                                .as_ref()
+                                .and_then(|e| e.details.as_deref())
+                                .unwrap_or(message);
+                            log::warn!(
+                                "[{}] {} failed after {:?} ({}): {}",
+                                correlation_id,
+                                name,
+                                elapsed,
+                                code,
+                                details
+                            )
                         }
-                    }
-                    IpcMessage::DbExecute { sql, params } => {
-                        let string_params: Vec<String> = params.iter()
-                            .map(|v| v.to_string().trim_matches('"').to_string())
-                            .collect();
-                        
-                        let db = {
-                            let guard = self.db_service.lock().unwrap();
-                            guard.clone()
-                        };
-
-                        match db.execute(&sql, &string_params).await {
-                            Ok(_) => IpcResponse::DbExecuteSuccess,
-                            Err(e) => IpcResponse::Error { message: e.to_string() }
+                        _ if elapsed >= SLOW_REQUEST_THRESHOLD => {
+                            log::warn!("[{}] {} was slow: {:?}", correlation_id, name, elapsed)
                         }
-                    }
-                    IpcMessage::AiRequest { prompt, context } => {
-                        match self.ai_service.generate_response(&prompt, context.as_deref()).await {
-                            Ok(text) => IpcResponse::AiResponse { text },
-                            Err(e) => IpcResponse::Error { message: e.to_string() }
+                        _ => {
+                            log::debug!("[{}] {} completed in {:?}", correlation_id, name, elapsed)
                         }
                     }
-                    IpcMessage::Log { message } => {
-                        println!("[Frontend Log]: {}", message);
-                        IpcResponse::Ack
+                    (req.id, response, action)
+                }
+            }
+            Err(e) => {
+                log::warn!(
+                    "[{}] Rejected IPC message ({:?}): {}",
+                    correlation_id,
+                    e.code,
+                    e.message
+                );
+                let response = IpcResponse::error(e.code, e.message);
+                (
+                    e.id.unwrap_or_else(|| "unknown".to_string()),
+                    response,
+                    None,
+                )
+            }
+        };
+
+        let wrapper = IpcResponseWrapper {
+            id,
+            correlation_id: Some(correlation_id),
+            response,
+        };
+        let mut value = serde_json::to_value(&wrapper).unwrap();
+        downgrade_response(&mut value, self.api_version());
+        (value.to_string(), action)
+    }
+
+    /// API version negotiated with the frontend
+    pub fn api_version(&self) -> u32 {
+        self.api_version.load(Ordering::SeqCst)
+    }
+
+    fn handshake(&self, requested: u32, client: Option<&str>) -> IpcResponse {
+        if requested < MIN_SUPPORTED_API_VERSION {
+            return IpcResponse::error(
+                IpcErrorCode::UnsupportedVersion,
+                format!(
+                    "Frontend API version {} is no longer supported; the minimum is {}",
+                    requested, MIN_SUPPORTED_API_VERSION
+                ),
+            );
+        }
+        let version = requested.min(IPC_API_VERSION);
+        self.api_version.store(version, Ordering::SeqCst);
+        log::info!(
+            "IPC handshake from {}: API v{} (requested v{})",
+            client.unwrap_or("frontend"),
+            version,
+            requested
+        );
+
+        IpcResponse::Handshake {
+            api_version: version,
+            min_api_version: MIN_SUPPORTED_API_VERSION,
+            backend_version: env!("CARGO_PKG_VERSION").to_string(),
+            commands: supported_commands(version),
+            capabilities: self.capabilities(),
+        }
+    }
+
+    /// Optional features the frontend can adapt to
+    fn capabilities(&self) -> Vec<String> {
+        let mut capabilities = vec![
+            "error_codes".to_string(),
+            "correlation_ids".to_string(),
+            "rate_limits".to_string(),
+            "coalesced_autosave".to_string(),
+        ];
+        if network::is_offline() {
+            capabilities.push("offline".to_string());
+        }
+        if AppPaths::global().is_portable() {
+            capabilities.push("portable".to_string());
+        }
+        match self.confirmation_guard.method() {
+            Ok(Some(ConfirmationMethod::Passphrase)) => {
+                capabilities.push("destructive_confirmation:passphrase".to_string())
+            }
+            Ok(Some(ConfirmationMethod::Totp)) => {
+                capabilities.push("destructive_confirmation:totp".to_string())
+            }
+            Ok(None) | Err(_) => {}
+        }
+        capabilities
+    }
+
+    /// Add an entry to the activity feed; a failure is only logged
+    pub(crate) async fn log_activity(&self, entry: ActivityEntry) {
+        if let Err(e) = self.activity.record(&entry).await {
+            log::warn!("Failed to record activity: {}", e);
+        }
+    }
+
+    async fn dispatch(&self, message: IpcMessage) -> (IpcResponse, Option<AppAction>) {
+        let mut action = None;
+        let message = match crate::ipc::handle(self, message).await {
+            Ok(response) => return (response, None),
+            Err(message) => message,
+        };
+        let response = match message {
+            IpcMessage::Handshake {
+                api_version,
+                client,
+            } => self.handshake(api_version, client.as_deref()),
+            IpcMessage::DbQuery { sql, params } => {
+                let string_params: Vec<String> = params
+                    .iter()
+                    .map(|v| v.to_string().trim_matches('"').to_string())
+                    .collect();
+
+                match self.db_service.query(&sql, &string_params).await {
+                    Ok(result) => {
+                        if sql.to_lowercase().contains("from documents") {
+                            self.threat_detector.record(
+                                AccessKind::DocumentRead,
+                                result.len(),
+                                "documents",
+                            );
+                        }
+                        let rows: Vec<serde_json::Map<String, Value>> = result
+                            .into_iter()
+                            .map(|row| {
+                                let mut map = serde_json::Map::new();
+                                for (i, col) in row.columns.iter().enumerate() {
+                                    let val = match &row.values[i] {
+                                        Some(v) => Value::String(v.clone()),
+                                        None => Value::Null,
+                                    };
+                                    map.insert(col.clone(), val);
+                                }
+                                map
+                            })
+                            .collect();
+
+                        IpcResponse::DbResult {
+                            data: Value::Array(rows.into_iter().map(Value::Object).collect()),
+                        }
                     }
-                    IpcMessage::AppAction { action: req_action } => {
-                        if req_action == "exit" {
-                            action = Some(AppAction::Exit);
-                            IpcResponse::Ack
-                        } else if req_action.starts_with("open_tool:") {
-                            let tool_id = req_action.trim_start_matches("open_tool:").to_string();
-                            action = Some(AppAction::OpenTool { tool_id });
-                            IpcResponse::Ack
-                        } else if req_action.starts_with("open_document:") {
-                            let document_id = req_action.trim_start_matches("open_document:").to_string();
-                            action = Some(AppAction::OpenDocument { document_id });
-                            IpcResponse::Ack
-                        } else if req_action == "close_window" {
-                            action = Some(AppAction::CloseWindow);
-                            IpcResponse::Ack
-                        } else if req_action == "minimize_window" {
-                            action = Some(AppAction::MinimizeWindow);
-                            IpcResponse::Ack
-                        } else if req_action == "toggle_maximize_window" {
-                            action = Some(AppAction::ToggleMaximizeWindow);
-                            IpcResponse::Ack
-                        } else if req_action.starts_with("start_resize:") {
-                            let direction = req_action.trim_start_matches("start_resize:").to_string();
-                            action = Some(AppAction::StartResize { direction });
-                            IpcResponse::Ack
-                        } else if req_action == "drag_window" {
-                            action = Some(AppAction::DragWindow);
-                            IpcResponse::Ack
-                        } else {
-                            IpcResponse::Error { message: "Unknown action".to_string() }
+                    Err(e) => IpcResponse::service_error(e),
+                }
+            }
+            IpcMessage::DbExecute { sql, params } => {
+                let string_params: Vec<String> = params
+                    .iter()
+                    .map(|v| v.to_string().trim_matches('"').to_string())
+                    .collect();
+
+                match self.db_service.execute(&sql, &string_params).await {
+                    Ok(_) => IpcResponse::DbExecuteSuccess,
+                    Err(e) => IpcResponse::service_error(e),
+                }
+            }
+            IpcMessage::AiRequest { prompt, context } => {
+                match self
+                    .ai_service
+                    .generate_response(&prompt, context.as_deref())
+                    .await
+                {
+                    Ok(text) => {
+                        let mut usage = AiUsageRecord::new(None, "ai_request");
+                        usage.prompt_chars = (prompt.chars().count()
+                            + context.as_deref().map_or(0, |c| c.chars().count()))
+                            as i64;
+                        usage.response_chars = text.chars().count() as i64;
+                        if let Err(e) = self.stats.record_ai_usage(&usage).await {
+                            log::warn!("Failed to record AI usage: {}", e);
                         }
+                        IpcResponse::AiResponse { text }
                     }
-                };
+                    Err(e) => IpcResponse::service_error(e),
+                }
+            }
+            IpcMessage::Log { message } => {
+                println!("[Frontend Log]: {}", message);
+                IpcResponse::Ack
+            }
+            IpcMessage::AppAction { action: req_action } => {
+                if req_action == "exit" {
+                    action = Some(AppAction::Exit);
+                    IpcResponse::Ack
+                } else if req_action.starts_with("open_tool:") {
+                    let tool_id = req_action.trim_start_matches("open_tool:").to_string();
+                    action = Some(AppAction::OpenTool { tool_id });
+                    IpcResponse::Ack
+                } else if req_action.starts_with("open_document:") {
+                    let document_id = req_action.trim_start_matches("open_document:").to_string();
+                    action = Some(AppAction::OpenDocument { document_id });
+                    IpcResponse::Ack
+                } else if req_action == "close_window" {
+                    action = Some(AppAction::CloseWindow);
+                    IpcResponse::Ack
+                } else if req_action == "minimize_window" {
+                    action = Some(AppAction::MinimizeWindow);
+                    IpcResponse::Ack
+                } else if req_action == "toggle_maximize_window" {
+                    action = Some(AppAction::ToggleMaximizeWindow);
+                    IpcResponse::Ack
+                } else if req_action.starts_with("start_resize:") {
+                    let direction = req_action.trim_start_matches("start_resize:").to_string();
+                    action = Some(AppAction::StartResize { direction });
+                    IpcResponse::Ack
+                } else if req_action == "drag_window" {
+                    action = Some(AppAction::DragWindow);
+                    IpcResponse::Ack
+                } else {
+                    IpcResponse::error(IpcErrorCode::InvalidPayload, "Unknown action".to_string())
+                }
+            }
+            other => IpcResponse::error(
+                IpcErrorCode::UnknownMessageType,
+                format!("No handler for '{}'", other.name()),
+            ),
+        };
+        (response, action)
+    }
+}
 
-                let wrapper = IpcResponseWrapper {
-                    id: req.id,
-                    response: response_payload,
-                };
-                (serde_json::to_string(&wrapper).unwrap(), action)
-            },
-            Err(e) => {
-                let response = IpcResponse::Error { message: format!("Invalid JSON: {}", e) };
-                let wrapper = IpcResponseWrapper {
-                    id: "unknown".to_string(),
-                    response,
-                };
-                (serde_json::to_string(&wrapper).unwrap(), None)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_of(message: &str) -> IpcErrorCode {
+        parse_request(message).unwrap_err().code
+    }
+
+    #[test]
+    fn test_parse_valid_requests() {
+        let req = parse_request(r#"{"id":"1","type":"log","payload":{"message":"hi"}}"#).unwrap();
+        assert_eq!(req.id, "1");
+        assert!(matches!(req.message, IpcMessage::Log { .. }));
+        assert!(parse_request(r#"{"id":"2","type":"credential_list"}"#).is_ok());
+    }
+
+    #[test]
+    fn test_parse_rejects_schema_violations() {
+        assert_eq!(code_of("not json"), IpcErrorCode::InvalidJson);
+        assert_eq!(code_of("[1,2]"), IpcErrorCode::InvalidEnvelope);
+        assert_eq!(
+            code_of(r#"{"type":"log","payload":{"message":"x"}}"#),
+            IpcErrorCode::InvalidEnvelope
+        );
+        assert_eq!(
+            code_of(r#"{"id":"1","type":"log","payload":{"message":"x"},"extra":1}"#),
+            IpcErrorCode::InvalidEnvelope
+        );
+        assert_eq!(
+            code_of(r#"{"id":"1","type":"format_disk"}"#),
+            IpcErrorCode::UnknownMessageType
+        );
+
+        let err =
+            parse_request(r#"{"id":"7","type":"log","payload":{"message":"x","level":"info"}}"#)
+                .unwrap_err();
+        assert_eq!(err.code, IpcErrorCode::InvalidPayload);
+        assert_eq!(err.id.as_deref(), Some("7"));
+        assert_eq!(
+            code_of(r#"{"id":"1","type":"log","payload":{"message":5}}"#),
+            IpcErrorCode::InvalidPayload
+        );
+    }
+
+    #[test]
+    fn test_parse_enforces_size_and_depth_limits() {
+        let deep = format!(
+            "{}{}",
+            "[".repeat(MAX_NESTING_DEPTH + 1),
+            "]".repeat(MAX_NESTING_DEPTH + 1)
+        );
+        assert_eq!(code_of(&deep), IpcErrorCode::NestingTooDeep);
+        // Brackets inside strings do not count towards nesting
+        let quoted = format!(
+            r#"{{"id":"1","type":"log","payload":{{"message":"{}"}}}}"#,
+            "[".repeat(100)
+        );
+        assert!(parse_request(&quoted).is_ok());
+
+        let huge = format!(
+            r#"{{"id":"1","type":"log","payload":{{"message":"{}"}}}}"#,
+            "a".repeat(MAX_MESSAGE_BYTES)
+        );
+        assert_eq!(code_of(&huge), IpcErrorCode::MessageTooLarge);
+    }
+
+    #[test]
+    fn test_command_table_matches_message_schema() {
+        for command in supported_commands(IPC_API_VERSION) {
+            let frame = format!(r#"{{"id":"1","type":"{}"}}"#, command.name);
+            if let Err(e) = parse_request(&frame) {
+                assert_ne!(e.code, IpcErrorCode::UnknownMessageType, "{}", command.name);
+            }
+        }
+        let v1: Vec<String> = supported_commands(1).into_iter().map(|c| c.name).collect();
+        assert_eq!(
+            v1,
+            vec!["db_query", "db_execute", "ai_request", "log", "app_action"]
+        );
+    }
+
+    #[test]
+    fn test_downgrade_strips_fields_for_v1() {
+        let wrapper = IpcResponseWrapper {
+            id: "1".to_string(),
+            correlation_id: Some("abc".to_string()),
+            response: IpcResponse::service_error("boom".to_string()),
+        };
+        let mut v2 = serde_json::to_value(&wrapper).unwrap();
+        let mut v1 = v2.clone();
+        downgrade_response(&mut v2, 2);
+        downgrade_response(&mut v1, 1);
+        assert_eq!(v2["payload"]["code"], "service_error");
+        assert_eq!(
+            v1,
+            serde_json::json!({"id": "1", "type": "error", "payload": {"message": "boom"}})
+        );
+    }
+
+    #[test]
+    fn test_service_errors_carry_envelope_from_v3() {
+        let wrapper = IpcResponseWrapper {
+            id: "1".to_string(),
+            correlation_id: None,
+            response: IpcResponse::service_error(crate::database::DatabaseError::ValidationError(
+                "Title is empty".to_string(),
+            )),
+        };
+        let mut v3 = serde_json::to_value(&wrapper).unwrap();
+        let mut v2 = v3.clone();
+        downgrade_response(&mut v3, 3);
+        downgrade_response(&mut v2, 2);
+        assert_eq!(v3["payload"]["error"]["code"], "invalid_input");
+        assert_eq!(v3["payload"]["message"], "Validation error: Title is empty");
+        assert!(v2["payload"].get("error").is_none());
+        assert_eq!(v2["payload"]["code"], "service_error");
+    }
+
+    #[test]
+    fn test_parse_survives_truncated_and_mutated_frames() {
+        let frame = r#"{"id":"abc","type":"secure_copy","payload":{"text":"x\"y","classification":"Restricted","source":null}}"#;
+        for end in 0..=frame.len() {
+            let _ = parse_request(&frame[..end]);
+        }
+        for (i, _) in frame.char_indices() {
+            for replacement in ["{", "]", "\"", "\\", "\u{0}", "9"] {
+                let mut mutated = frame.to_string();
+                mutated.replace_range(i..i + 1, replacement);
+                let _ = parse_request(&mutated);
             }
         }
     }
+
+    #[test]
+    fn test_pushed_events_reach_every_subscriber() {
+        let events = IpcEvents::new();
+        events.push("ignored", &serde_json::json!({}));
+        let mut first = events.subscribe();
+        let mut second = events.subscribe();
+        events.push(
+            "export_job",
+            &serde_json::json!({"job_id": "j1", "progress": 0.5}),
+        );
+        let expected =
+            serde_json::json!({"type": "export_job", "payload": {"job_id": "j1", "progress": 0.5}});
+        for receiver in [&mut first, &mut second] {
+            let message: Value = serde_json::from_str(&receiver.try_recv().unwrap()).unwrap();
+            assert_eq!(message, expected);
+            assert!(receiver.try_recv().is_err());
+        }
+    }
 }
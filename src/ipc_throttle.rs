@@ -0,0 +1,263 @@
+//! IPC Throttling
+//!
+//! Per-command token-bucket rate limits and keyed debouncing for chatty
+//! frontend messages, so a runaway frontend loop cannot flood the tokio
+//! runtime or the database. `IpcBridge` checks the limiter before
+//! dispatching and routes cursor and autosave updates through debouncers.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Token bucket parameters for one command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Requests allowed back to back
+    pub burst: u32,
+    /// Sustained requests per second
+    pub per_second: f64,
+}
+
+impl RateLimit {
+    pub const fn new(burst: u32, per_second: f64) -> Self {
+        Self { burst, per_second }
+    }
+}
+
+/// Limits for commands that touch the database or external services.
+/// Commands not listed use `DEFAULT_RATE_LIMIT`.
+pub const DEFAULT_COMMAND_LIMITS: &[(&str, RateLimit)] = &[
+    ("db_query", RateLimit::new(60, 30.0)),
+    ("db_execute", RateLimit::new(30, 15.0)),
+    ("ai_request", RateLimit::new(5, 0.5)),
+    ("credential_test", RateLimit::new(3, 0.2)),
+    ("destructive_confirm", RateLimit::new(5, 0.1)),
+    ("log", RateLimit::new(100, 50.0)),
+    ("print_document", RateLimit::new(3, 0.2)),
+    ("crash_report_submit", RateLimit::new(3, 0.1)),
+    ("data_dir_migrate", RateLimit::new(1, 0.05)),
+    // Coalesced downstream, so only runaway loops are refused
+    ("cursor_position", RateLimit::new(120, 60.0)),
+    ("autosave_ping", RateLimit::new(60, 30.0)),
+];
+
+pub const DEFAULT_RATE_LIMIT: RateLimit = RateLimit::new(30, 10.0);
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Per-command token-bucket rate limiter
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: HashMap<String, RateLimit>,
+    default_limit: RateLimit,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        let mut limiter = Self::new(DEFAULT_RATE_LIMIT);
+        for (command, limit) in DEFAULT_COMMAND_LIMITS {
+            limiter.set_limit(command, *limit);
+        }
+        limiter
+    }
+}
+
+impl RateLimiter {
+    /// A limiter applying `default_limit` to every command
+    pub fn new(default_limit: RateLimit) -> Self {
+        Self {
+            limits: HashMap::new(),
+            default_limit,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn set_limit(&mut self, command: &str, limit: RateLimit) {
+        self.limits.insert(command.to_string(), limit);
+    }
+
+    /// Take a token for `command`, or return how long to wait for one
+    pub fn check(&self, command: &str) -> Result<(), Duration> {
+        self.check_at(command, Instant::now())
+    }
+
+    fn check_at(&self, command: &str, now: Instant) -> Result<(), Duration> {
+        let limit = self
+            .limits
+            .get(command)
+            .copied()
+            .unwrap_or(self.default_limit);
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return Ok(());
+        };
+        let bucket = buckets.entry(command.to_string()).or_insert(Bucket {
+            tokens: limit.burst as f64,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(limit.burst as f64);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if limit.per_second > 0.0 {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / limit.per_second,
+            ))
+        } else {
+            Err(Duration::MAX)
+        }
+    }
+}
+
+/// What happened to a value handed to a `Debouncer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebounceOutcome {
+    /// Flushed straight away
+    Flushed,
+    /// Held back; the latest value for the key is flushed when the window ends
+    Coalesced,
+}
+
+pub type FlushFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+type FlushFn<T> = Arc<dyn Fn(String, T) -> FlushFuture + Send + Sync>;
+
+struct KeyState<T> {
+    last_flush: Instant,
+    pending: Option<T>,
+    timer_running: bool,
+}
+
+/// Keyed leading-and-trailing debouncer: the first value for a key is
+/// flushed immediately, values arriving within `window` of a flush are
+/// coalesced and only the latest is flushed once the window has passed.
+pub struct Debouncer<T: Send + 'static> {
+    window: Duration,
+    flush: FlushFn<T>,
+    state: Arc<Mutex<HashMap<String, KeyState<T>>>>,
+}
+
+impl<T: Send + 'static> Debouncer<T> {
+    pub fn new<F>(window: Duration, flush: F) -> Self
+    where
+        F: Fn(String, T) -> FlushFuture + Send + Sync + 'static,
+    {
+        Self {
+            window,
+            flush: Arc::new(flush),
+            state: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Submit a value for `key`. Must be called from within a tokio runtime.
+    pub fn submit(&self, key: &str, value: T) -> DebounceOutcome {
+        let now = Instant::now();
+        let Ok(mut state) = self.state.lock() else {
+            return DebounceOutcome::Coalesced;
+        };
+
+        match state.get_mut(key) {
+            Some(entry) if now.duration_since(entry.last_flush) < self.window => {
+                entry.pending = Some(value);
+                if !entry.timer_running {
+                    entry.timer_running = true;
+                    let delay = self.window - now.duration_since(entry.last_flush);
+                    self.spawn_trailing_flush(key.to_string(), delay);
+                }
+                DebounceOutcome::Coalesced
+            }
+            _ => {
+                state.insert(
+                    key.to_string(),
+                    KeyState {
+                        last_flush: now,
+                        pending: None,
+                        timer_running: false,
+                    },
+                );
+                tokio::spawn((self.flush)(key.to_string(), value));
+                DebounceOutcome::Flushed
+            }
+        }
+    }
+
+    fn spawn_trailing_flush(&self, key: String, delay: Duration) {
+        let state = self.state.clone();
+        let flush = self.flush.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let value = state.lock().ok().and_then(|mut state| {
+                let entry = state.get_mut(&key)?;
+                entry.timer_running = false;
+                entry.last_flush = Instant::now();
+                entry.pending.take()
+            });
+            if let Some(value) = value {
+                flush(key, value).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_refills_over_time() {
+        let mut limiter = RateLimiter::new(RateLimit::new(2, 1.0));
+        limiter.set_limit("ai_request", RateLimit::new(1, 0.5));
+        let start = Instant::now();
+
+        assert!(limiter.check_at("db_query", start).is_ok());
+        assert!(limiter.check_at("db_query", start).is_ok());
+        let wait = limiter.check_at("db_query", start).unwrap_err();
+        assert!(wait <= Duration::from_secs(1));
+        assert!(limiter
+            .check_at("db_query", start + Duration::from_secs(1))
+            .is_ok());
+
+        // Buckets are independent per command
+        assert!(limiter.check_at("ai_request", start).is_ok());
+        assert!(limiter
+            .check_at("ai_request", start + Duration::from_secs(1))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_debouncer_flushes_first_and_latest() {
+        let flushed = Arc::new(Mutex::new(Vec::new()));
+        let sink = flushed.clone();
+        let debouncer = Debouncer::new(Duration::from_millis(50), move |key, value: u32| {
+            let sink = sink.clone();
+            Box::pin(async move { sink.lock().unwrap().push((key, value)) }) as FlushFuture
+        });
+
+        assert_eq!(debouncer.submit("doc", 1), DebounceOutcome::Flushed);
+        for value in 2..=10 {
+            assert_eq!(debouncer.submit("doc", value), DebounceOutcome::Coalesced);
+        }
+        assert_eq!(debouncer.submit("other", 1), DebounceOutcome::Flushed);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        let mut flushed = flushed.lock().unwrap().clone();
+        flushed.sort();
+        assert_eq!(
+            flushed,
+            vec![
+                ("doc".to_string(), 1),
+                ("doc".to_string(), 10),
+                ("other".to_string(), 1)
+            ]
+        );
+    }
+}
@@ -3,19 +3,46 @@
 //! This is the main library module for the Herding Cats application.
 //! It exports all major subsystems including the database integration.
 
+pub mod app_paths;
+pub mod app_protocol;
+pub mod asset_store;
 pub mod automation;
+pub mod command_registry;
+pub mod correlation;
+pub mod crash_reporter;
+mod ipc;
 pub mod ipc_bridge;
+pub mod ipc_throttle;
 pub mod database;
 pub mod database_app_state;
+pub mod data_migration;
+pub mod deep_link;
 pub mod error;
+pub mod file_association;
 pub mod file_ops;
 pub mod services;
 pub mod settings;
 
 pub mod classify;
+pub mod compliance;
+pub mod config_security;
 pub mod convert;
 pub mod security;
 pub mod font_manager;
+pub mod generators;
+pub mod image_metadata;
+pub mod notifications;
+pub mod frontend_assets;
+pub mod printing;
+pub mod profiling;
+pub mod project_file;
+pub mod publishing;
+pub mod recent_items;
+pub mod scratch_space;
+pub mod send_to_device;
+pub mod shell_integration;
+pub mod single_instance;
+pub mod thumbnails;
 
 // Re-export database types for easier access
 pub use database::{
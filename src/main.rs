@@ -8,44 +8,517 @@ use tao::{
 use tao::platform::macos::WindowBuilderExtMacOS;
 use wry::WebViewBuilder;
 use std::sync::{Arc, Mutex};
-use herding_cats_rust::database::{DatabaseService, DatabaseConfig};
+use herding_cats_rust::asset_store::AssetStore;
+use herding_cats_rust::thumbnails::Thumbnailer;
+use herding_cats_rust::database::{ActivityService, AiLogService, AnalysisService, AnnotationService, AnonymizerService, AttachmentService, BackupService, BetaReaderService, CalendarService, CertificationService, ChallengeService, ChronologyService, CodexAutofillService, CodexGraphService, CodexRelationshipService, CodexTransferService, ContentScanService, DatabaseService, DeadlineService, DatabaseConfig, DocumentStructureService, DocumentTemplateService, FocusService, GeneratorService, GitHistoryService, HybridSearchService, JournalService, LexiconService, MarkdownSyncService, NoteImportService, ProfileService, RelatedNotesService, RenameService, SearchService, SerialService, StatsService, StoryBibleService, StyleSheetService, SubmissionService, TimelineService, UndoHistoryService, VectorEmbeddingService, WordUsageService, WorkspaceService};
+use herding_cats_rust::database::fixtures::{self, FixtureSpec};
+use herding_cats_rust::database::backup_service::BackupKeys;
+use herding_cats_rust::database::local_embeddings::EmbeddingBackend;
+use herding_cats_rust::profiling::{self, ProfileOptions, ProfileReport};
+use herding_cats_rust::database::models::focus::FocusEventKind;
 use herding_cats_rust::services::ai_service::AiService;
+use herding_cats_rust::services::ask_service::AskService;
+use herding_cats_rust::services::writing_stats::WritingStatsService;
 use herding_cats_rust::ipc_bridge::{IpcBridge, AppAction};
 use herding_cats_rust::security::secure_storage::SecureStorageService;
-use std::path::PathBuf;
+use herding_cats_rust::security::credentials::CredentialManager;
+use herding_cats_rust::compliance::ComplianceService;
+use herding_cats_rust::config_security::SecurityConfig;
+use herding_cats_rust::security::network::NetworkClient;
+use herding_cats_rust::security::clipboard::SecureClipboard;
+use herding_cats_rust::security::threat_detector::{AnomalyThresholds, ThreatDetector};
+use herding_cats_rust::security::events::SecurityEventLog;
+use herding_cats_rust::security::secrets_scanner::{SecretsPolicy, SecretsScanner};
+use herding_cats_rust::security::confirmation::ConfirmationGuard;
+use herding_cats_rust::app_paths::AppPaths;
+use herding_cats_rust::crash_reporter::{self, CrashReporter};
+use herding_cats_rust::deep_link::{self, DeepLink, DeepLinkHandler};
+use herding_cats_rust::file_association;
+use herding_cats_rust::project_file::ProjectFile;
+use herding_cats_rust::notifications::Notifier;
+use herding_cats_rust::recent_items::{RecentItemKind, RecentItems};
+use herding_cats_rust::command_registry::CommandRegistry;
+use herding_cats_rust::send_to_device::DeviceSender;
+use herding_cats_rust::shell_integration;
+use herding_cats_rust::scratch_space::{self, ScratchSpace};
+use herding_cats_rust::single_instance::{self, InstanceServer};
 use std::collections::HashMap;
 use tao::window::WindowId;
 use wry::WebView;
 
 enum UserEvent {
     IpcResponse(WindowId, String),
+    IpcEvent(String),
     AppExit,
     OpenTool(String),
+    OpenProject(String),
     OpenDocument(String),
     CloseWindow(WindowId),
     MinimizeWindow(WindowId),
     ToggleMaximizeWindow(WindowId),
     StartResize(WindowId, tao::window::ResizeDirection),
     DragWindow(WindowId),
+    ShowLog,
+}
+
+/// Undocumented developer option:
+/// `--generate-fixtures <database> [spec]` fills the database with
+/// generated projects for benchmarking, then exits
+async fn generate_fixtures(args: &[String]) -> Result<()> {
+    let Some(path) = args.first() else {
+        anyhow::bail!("Usage: --generate-fixtures <database> [projects=3,documents=50,words=500-3000,codex=20,embeddings=false,seed=1]");
+    };
+    let spec = FixtureSpec::parse(args.get(1).map(String::as_str).unwrap_or(""))?;
+    let db_service = Arc::new(tokio::sync::RwLock::new(
+        DatabaseService::new(std::path::Path::new(path), DatabaseConfig::default()).await?
+    ));
+    let started = std::time::Instant::now();
+    let report = fixtures::generate(&db_service, &spec).await?;
+    println!(
+        "Generated {} projects, {} documents ({} words), {} codex entries and {} embeddings in {:.1?}",
+        report.project_ids.len(),
+        report.documents,
+        report.words,
+        report.codex_entries,
+        report.embeddings,
+        started.elapsed()
+    );
+    Ok(())
+}
+
+/// Undocumented developer option:
+/// `--profile [spec] [--baseline <file>] [--save-baseline]` measures search
+/// and PDF rendering on generated data in a scratch database and fails if
+/// anything regressed against the baseline
+async fn profile(args: &[String]) -> Result<()> {
+    let mut spec = None;
+    let mut baseline_path = AppPaths::global().local_dir.join("profile-baseline.json");
+    let mut save_baseline = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--baseline" => match args.next() {
+                Some(path) => baseline_path = path.into(),
+                None => anyhow::bail!("--baseline needs a file"),
+            },
+            "--save-baseline" => save_baseline = true,
+            _ => spec = Some(FixtureSpec::parse(arg)?),
+        }
+    }
+    let mut options = ProfileOptions::default();
+    if let Some(spec) = spec {
+        options.spec = spec;
+    }
+
+    let scratch = tempfile::tempdir()?;
+    let db_service = Arc::new(tokio::sync::RwLock::new(
+        DatabaseService::new(&scratch.path().join("profile.db"), DatabaseConfig::default()).await?
+    ));
+    let report = profiling::run(&db_service, &options).await?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    if save_baseline {
+        report.save(&baseline_path)?;
+        println!("Saved baseline to {}", baseline_path.display());
+        return Ok(());
+    }
+    if !baseline_path.exists() {
+        println!("No baseline at {}; run with --save-baseline to record one", baseline_path.display());
+        return Ok(());
+    }
+    let regressions = report.regressions(&ProfileReport::load(&baseline_path)?, profiling::DEFAULT_TOLERANCE);
+    for regression in &regressions {
+        println!(
+            "REGRESSION {}: {:.2} -> {:.2} ({:+.0}%)",
+            regression.metric,
+            regression.baseline,
+            regression.current,
+            regression.change() * 100.0
+        );
+    }
+    if !regressions.is_empty() {
+        anyhow::bail!("{} metrics regressed against {}", regressions.len(), baseline_path.display());
+    }
+    println!("No regressions against {}", baseline_path.display());
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::init();
+    crash_reporter::init_logging();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(position) = args.iter().position(|arg| arg == "--generate-fixtures") {
+        return generate_fixtures(&args[position + 1..]).await;
+    }
+    if let Some(position) = args.iter().position(|arg| arg == "--profile") {
+        return profile(&args[position + 1..]).await;
+    }
+
+    // A deep link on the command line (jump list entry) goes to the
+    // running instance if there is one
+    let instance_lock = single_instance::lock_path();
+    let launch_link = single_instance::launch_link();
+    if let Some(link) = &launch_link {
+        if single_instance::forward(&instance_lock, link) {
+            return Ok(());
+        }
+    }
+
+    let app_paths = AppPaths::global();
+    if app_paths.is_portable() {
+        println!("Portable mode: data is stored in {}", app_paths.data_dir.display());
+    }
+    app_paths.ensure_dirs()?;
+
+    // Only one instance gets this far, so no job is using scratch space
+    // yet; clear what failed or interrupted exports left behind
+    match ScratchSpace::open_default().clear() {
+        Ok(0) => {}
+        Ok(n) => println!("Removed {} stale scratch directories", n),
+        Err(e) => eprintln!("Failed to clear scratch space: {}", e),
+    }
+    if let Err(e) = scratch_space::remove_prefixed_dirs(std::path::Path::new("exports"), "temp_") {
+        eprintln!("Failed to remove old export temp directories: {}", e);
+    }
+
+    // Panics leave a crash report for the user to review on next start
+    let crash_reporter = Arc::new(CrashReporter::new(&CrashReporter::default_dir()));
+    if let Err(e) = crash_reporter.install() {
+        eprintln!("Failed to start crash reporting: {}", e);
+    }
 
     // Initialize Services
-    let db_path = PathBuf::from("herding_cats.db");
-    let db_service = Arc::new(Mutex::new(
-        DatabaseService::new(&db_path, DatabaseConfig::default()).await?
-    ));
+    let db_path = app_paths.database_path();
+    let mut db_config = DatabaseConfig::default();
+    if let Some(model_dir) = herding_cats_rust::settings::load_settings().local_embedding_model {
+        db_config.embedding_backend = EmbeddingBackend::local(model_dir);
+    }
+    if let Some(days) = herding_cats_rust::settings::load_settings().trash_retention_days {
+        db_config.trash_retention = std::time::Duration::from_secs(u64::from(days) * 24 * 60 * 60);
+    }
+    // The service owns a connection pool and is cheap to clone; services
+    // share one handle to it
+    let db_service = DatabaseService::new(&db_path, db_config).await?;
+    let shared_db = Arc::new(tokio::sync::RwLock::new(db_service.clone()));
+    match db_service.purge_expired_trash().await {
+        Ok(0) => {}
+        Ok(purged) => println!("Purged {} items from the trash", purged),
+        Err(e) => eprintln!("Failed to purge the trash: {}", e),
+    }
     let secure_storage = Arc::new(SecureStorageService::new("herding-cats"));
     
-    let ai_service = Arc::new(AiService::new(
+    let compliance = Arc::new(Mutex::new(ComplianceService::new()));
+    let security_events = Arc::new(SecurityEventLog::new().with_audit_log(compliance.clone()));
+    let secrets_scanner = Arc::new(SecretsScanner::new(
+        SecretsPolicy::default(),
+        security_events.clone(),
+    ));
+
+    // Only kept when the privacy controls turn it on; old entries go at
+    // startup even if no request is made this session
+    let ai_log = Arc::new(AiLogService::new(shared_db.clone()));
+    ai_log.initialize().await?;
+    let privacy = herding_cats_rust::settings::load_settings().privacy.unwrap_or_default();
+    if let Err(e) = ai_log.enforce_retention(&privacy).await {
+        eprintln!("Failed to prune the AI log: {}", e);
+    }
+
+    // Profiles are optional; until one is switched to, every project is
+    // visible and AI requests aren't charged to a budget
+    let profiles = Arc::new(ProfileService::new(shared_db.clone()));
+    profiles.initialize().await?;
+
+    let mut ai_service = AiService::new(secure_storage.clone(), db_service.clone())
+        .with_secrets_scanner(secrets_scanner.clone())
+        .with_interaction_log(ai_log.clone())
+        .with_profiles(profiles.clone());
+    if let Some(model) = herding_cats_rust::settings::load_settings().ai_model {
+        ai_service = ai_service.with_model(model);
+    }
+    let ai_service = Arc::new(ai_service);
+    let network = Arc::new(
+        NetworkClient::new(&SecurityConfig::default()).with_audit_log(compliance.clone()),
+    );
+    NetworkClient::install_global(network.clone());
+
+    let credential_manager = Arc::new(CredentialManager::new(
         secure_storage.clone(),
-        db_service.clone(),
+        compliance.clone(),
+        network.clone(),
+    ));
+    let secure_clipboard = Arc::new(SecureClipboard::new(compliance.clone()));
+    let threat_detector = Arc::new(ThreatDetector::new(
+        AnomalyThresholds::default(),
+        compliance.clone(),
+    ));
+
+    let confirmation_guard = Arc::new(
+        ConfirmationGuard::new()
+            .with_storage(secure_storage.clone())
+            .with_audit_log(compliance.clone()),
+    );
+    db_service.set_confirmation_guard(confirmation_guard.clone());
+
+    let recent_items = Arc::new(
+        RecentItems::load(&RecentItems::default_path())
+            .with_on_change(shell_integration::update_recent_items),
+    );
+    let command_registry = Arc::new(CommandRegistry::load(&CommandRegistry::default_path()));
+
+    // Attachments whose document or codex entry was deleted are dropped at startup
+    let thumbnailer = Arc::new(Thumbnailer::open_default());
+    let attachments = Arc::new(
+        AttachmentService::new(
+            shared_db.clone(),
+            AssetStore::open_default(),
+        )
+        .with_thumbnails(thumbnailer.clone())
+        .with_metadata_stripping(security_events.clone()),
+    );
+    attachments.initialize().await?;
+    if let Err(e) = attachments.cleanup_orphans().await {
+        eprintln!("Failed to clean up attachments: {}", e);
+    }
+    if let Err(e) = thumbnailer.prune() {
+        eprintln!("Failed to prune thumbnails: {}", e);
+    }
+
+    let generators = Arc::new(GeneratorService::new(shared_db.clone()));
+    generators.initialize().await?;
+
+    let document_templates = Arc::new(DocumentTemplateService::new(shared_db.clone()));
+    document_templates.initialize().await?;
+
+    let stats = Arc::new(StatsService::new(shared_db.clone()));
+    stats.initialize().await?;
+
+    let writing_stats = Arc::new(WritingStatsService::new(shared_db.clone()));
+    writing_stats.initialize().await?;
+
+    let codex_graph = Arc::new(CodexGraphService::new(shared_db.clone()));
+    let codex_relationships = Arc::new(CodexRelationshipService::new(shared_db.clone()));
+    codex_relationships.initialize().await?;
+    let codex_transfer = Arc::new(CodexTransferService::new(shared_db.clone()));
+    codex_transfer.initialize().await?;
+    let journal = Arc::new(JournalService::new(shared_db.clone()));
+    journal.initialize().await?;
+    let markdown_sync = Arc::new(MarkdownSyncService::new(shared_db.clone()));
+    markdown_sync.initialize().await?;
+    // Pick up edits made to mirrored Markdown folders in other tools
+    markdown_sync.clone().spawn_sync(std::time::Duration::from_secs(30));
+    let git_history = Arc::new(GitHistoryService::new(
+        shared_db.clone(),
+        markdown_sync.clone(),
+        app_paths.data_dir.join("history"),
+    ));
+    git_history.initialize().await?;
+    // Commit saved work for projects keeping git history
+    git_history.clone().spawn_auto_commit(std::time::Duration::from_secs(300));
+
+    let codex_autofill = Arc::new(CodexAutofillService::new(shared_db.clone()));
+
+    let calendars = Arc::new(CalendarService::new(shared_db.clone()));
+    calendars.initialize().await?;
+
+    let story_bible = Arc::new(
+        StoryBibleService::new(shared_db.clone())
+            .with_attachments(attachments.clone())
+            .with_calendars(calendars.clone()),
+    );
+
+    let chronology = Arc::new(ChronologyService::new(
+        shared_db.clone(),
+        calendars.clone(),
+    ));
+
+    let timeline = Arc::new(TimelineService::new(shared_db.clone(), calendars.clone()));
+    timeline.initialize().await?;
+
+    let note_import = Arc::new(NoteImportService::new(shared_db.clone(), attachments.clone()));
+    note_import.initialize().await?;
+
+    let submissions = Arc::new(SubmissionService::new(shared_db.clone()));
+    submissions.initialize().await?;
+    // Follow-up reminders for submissions that have gone unanswered
+    submissions.clone().spawn_reminders(std::time::Duration::from_secs(60 * 60));
+
+    let deadlines = Arc::new(DeadlineService::new(shared_db.clone()));
+    deadlines.initialize().await?;
+    // Escalating notifications as contest and writing deadlines approach
+    deadlines.clone().spawn_alerts(std::time::Duration::from_secs(60 * 60));
+
+    let serial = Arc::new(SerialService::new(
+        shared_db.clone(),
+        app_paths.serial_staging_dir(),
+    ));
+    serial.initialize().await?;
+    // Stage serial chapters a day ahead and remind when they are due
+    serial.clone().spawn_scheduler(std::time::Duration::from_secs(15 * 60));
+
+    let certification = Arc::new(
+        CertificationService::new(shared_db.clone())
+        .with_storage(secure_storage.clone()),
+    );
+    certification.initialize().await?;
+
+    let challenges = Arc::new(ChallengeService::new(
+        shared_db.clone(),
+        stats.clone(),
     ));
+    challenges.initialize().await?;
+    // Celebrate challenge milestones as sessions add up
+    challenges.clone().spawn_milestone_checks(std::time::Duration::from_secs(10 * 60));
 
-    let ipc_bridge = Arc::new(IpcBridge::new(db_service.clone(), ai_service.clone()));
+    let focus = Arc::new(FocusService::new(
+        shared_db.clone(),
+    ));
+    focus.initialize().await?;
+
+    let workspaces = Arc::new(WorkspaceService::new(
+        shared_db.clone(),
+    ));
+    workspaces.initialize().await?;
+
+    let undo_history = Arc::new(UndoHistoryService::new(
+        shared_db.clone(),
+    ));
+    undo_history.initialize().await?;
+
+    let activity = Arc::new(ActivityService::new(
+        shared_db.clone(),
+    ));
+    activity.initialize().await?;
+
+    let anonymizer = Arc::new(AnonymizerService::new(
+        shared_db.clone(),
+    ));
+
+    let analysis = Arc::new(AnalysisService::with_database_service(shared_db.clone()));
+    analysis.initialize().await?;
+
+    let embeddings = Arc::new(VectorEmbeddingService::new(shared_db.clone()));
+    embeddings.initialize().await?;
+
+    let related_notes = Arc::new(RelatedNotesService::new(
+        shared_db.clone(),
+        embeddings.clone(),
+    ));
+
+    let search = Arc::new(SearchService::new(shared_db.clone()));
+    search.initialize().await?;
+    let hybrid_search = Arc::new(HybridSearchService::new(
+        shared_db.clone(),
+        search,
+        embeddings.clone(),
+    ));
+
+    let ask = Arc::new(AskService::new(
+        shared_db.clone(),
+        embeddings.clone(),
+        ai_service.clone(),
+    ));
+
+    // Backups are encrypted with the installation's key; without it they
+    // are refused, and backups made with it can't be verified
+    let mut backups = BackupService::new(shared_db.clone(), &db_path)
+        .with_confirmation_guard(confirmation_guard.clone());
+    match BackupKeys::load_or_create(&secure_storage) {
+        Ok(keys) => backups = backups.with_encryption(keys),
+        Err(e) => eprintln!("Backups are refused until the backup key is available: {}", e),
+    }
+    let backups = Arc::new(backups);
+    backups.initialize().await?;
+
+    let style_sheets = Arc::new(StyleSheetService::new(shared_db.clone()));
+    style_sheets.initialize().await?;
+
+    let content_scan = Arc::new(ContentScanService::new(shared_db.clone()));
+    content_scan.initialize().await?;
+
+    let annotations = Arc::new(AnnotationService::new(shared_db.clone()));
+    annotations.initialize().await?;
+
+    let beta_readers = Arc::new(
+        BetaReaderService::new(shared_db.clone(), annotations.clone())
+            .with_secrets_scanner(secrets_scanner.clone())
+            .with_attachments(attachments.clone()),
+    );
+    beta_readers.initialize().await?;
+
+    let word_usage = Arc::new(WordUsageService::new(shared_db.clone()));
+    word_usage.initialize().await?;
+
+    let renames = Arc::new(RenameService::new(shared_db.clone()));
+    renames.initialize().await?;
+
+    let document_structure = Arc::new(DocumentStructureService::new(shared_db.clone()));
+    document_structure.initialize().await?;
+
+    let lexicons = Arc::new(LexiconService::new(shared_db.clone()));
+    lexicons.initialize().await?;
+
+    // Point .hcats files and herdingcats:// links at this executable
+    if let Err(e) = file_association::register_if_needed() {
+        eprintln!("Failed to register file associations: {}", e);
+    }
+
+    let ipc_bridge = Arc::new(IpcBridge::new(
+        db_service.clone(),
+        ai_service.clone(),
+        credential_manager.clone(),
+        secure_clipboard.clone(),
+        threat_detector.clone(),
+        security_events.clone(),
+        confirmation_guard.clone(),
+        recent_items.clone(),
+        crash_reporter.clone(),
+        attachments.clone(),
+        Arc::new(DeviceSender::new(secure_storage.clone(), network.clone())),
+        generators.clone(),
+        stats.clone(),
+        codex_graph.clone(),
+        embeddings.clone(),
+        related_notes.clone(),
+        ask.clone(),
+        codex_autofill.clone(),
+        story_bible.clone(),
+        analysis.clone(),
+        chronology.clone(),
+        submissions.clone(),
+        deadlines.clone(),
+        serial.clone(),
+        certification.clone(),
+        challenges.clone(),
+        focus.clone(),
+        workspaces.clone(),
+        undo_history.clone(),
+        activity.clone(),
+        anonymizer.clone(),
+        hybrid_search.clone(),
+        backups.clone(),
+        ai_log.clone(),
+        command_registry.clone(),
+        document_templates.clone(),
+        codex_relationships.clone(),
+        journal.clone(),
+        codex_transfer.clone(),
+        markdown_sync.clone(),
+        git_history.clone(),
+        calendars.clone(),
+        timeline.clone(),
+        note_import.clone(),
+        writing_stats.clone(),
+        profiles.clone(),
+        style_sheets.clone(),
+        content_scan.clone(),
+        annotations.clone(),
+        beta_readers.clone(),
+        word_usage.clone(),
+        renames.clone(),
+        document_structure.clone(),
+        lexicons.clone(),
+    ));
 
     // Start Dev Server (Debug Mode only)
     #[cfg(debug_assertions)]
@@ -91,14 +564,98 @@ async fn main() -> Result<()> {
     // Create Event Loop
     let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
     let proxy = event_loop.create_proxy();
+
+    // Deep links from notification buttons, the dock menu and later
+    // launches are routed into the event loop
+    let deep_link_proxy = Mutex::new(proxy.clone());
+    let deep_link_recent_items = recent_items.clone();
+    let deep_link_library = std::fs::canonicalize(&db_path).unwrap_or_else(|_| db_path.clone());
+    let deep_link_handler: DeepLinkHandler = Arc::new(move |link| {
+        let event = match link {
+            DeepLink::OpenProject { project_id } => UserEvent::OpenProject(project_id),
+            DeepLink::OpenDocument { document_id } => UserEvent::OpenDocument(document_id),
+            DeepLink::OpenTool { tool_id } => UserEvent::OpenTool(tool_id),
+            DeepLink::ShowLog => UserEvent::ShowLog,
+            DeepLink::OpenFile { path } => {
+                if let Err(e) = deep_link::open_path(&path) {
+                    eprintln!("Failed to open {}: {}", path.display(), e);
+                }
+                return;
+            }
+            DeepLink::OpenProjectFile { path } => match ProjectFile::load(&path) {
+                Ok(file) => {
+                    if file.library != deep_link_library {
+                        eprintln!(
+                            "Project file {} refers to library {}; opening in the current library",
+                            path.display(),
+                            file.library.display()
+                        );
+                    }
+                    let _ = deep_link_recent_items.record(
+                        RecentItemKind::Project,
+                        &file.project_id,
+                        &file.name,
+                    );
+                    UserEvent::OpenProject(file.project_id)
+                }
+                Err(e) => {
+                    eprintln!("Failed to open project file {}: {}", path.display(), e);
+                    return;
+                }
+            },
+        };
+        if let Ok(proxy) = deep_link_proxy.lock() {
+            let _ = proxy.send_event(event);
+        }
+    });
+    let notification_handler = deep_link_handler.clone();
+    Notifier::install_global(Arc::new(
+        Notifier::new().with_action_handler(move |link| notification_handler(link)),
+    ));
+    let _instance_server = InstanceServer::start(&instance_lock, deep_link_handler.clone())
+        .map_err(|e| eprintln!("Failed to listen for forwarded deep links: {}", e))
+        .ok();
+    shell_integration::install(deep_link_handler.clone());
+
+    // Events pushed by backend services (export progress) go to every window
+    let mut pushed_events = ipc_bridge.events().subscribe();
+    let event_proxy = proxy.clone();
+    tokio::spawn(async move {
+        loop {
+            match pushed_events.recv().await {
+                Ok(event) => {
+                    if event_proxy.send_event(UserEvent::IpcEvent(event)).is_err() {
+                        break;
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    eprintln!("Dropped {} pushed IPC events", skipped);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
     
     // Window Management
     // Store both Window and WebView to ensure Window is not dropped
     let mut webviews: HashMap<WindowId, (tao::window::Window, WebView)> = HashMap::new();
     let mut main_window_id: Option<WindowId> = None;
+    // Tool windows by id, for labelling focus events
+    let mut tool_windows: HashMap<WindowId, String> = HashMap::new();
 
     // Helper to create a window
     let proxy_for_window = proxy.clone();
+    // app:// serves the frontend in release builds (debug uses the dev
+    // server) and registered media files and asset thumbnails in both
+    #[cfg(not(debug_assertions))]
+    let frontend_assets = herding_cats_rust::frontend_assets::FrontendAssets::load();
+    #[cfg(debug_assertions)]
+    let frontend_assets = herding_cats_rust::frontend_assets::FrontendAssets::default();
+    let app_protocol = Arc::new(herding_cats_rust::app_protocol::AppProtocol::new(
+        frontend_assets,
+        Arc::new(herding_cats_rust::app_protocol::MediaRegistry::new()),
+    ).with_thumbnails(thumbnailer.clone()));
+
     let create_window = move |event_loop: &tao::event_loop::EventLoopWindowTarget<UserEvent>, url: String, title: String| -> Result<(tao::window::Window, WebView)> {
         use rand::Rng;
         let mut rng = rand::thread_rng();
@@ -184,59 +741,30 @@ async fn main() -> Result<()> {
                 });
             });
 
-        // Add custom protocol for release builds
-        #[cfg(not(debug_assertions))]
-        {
-            builder = builder.with_custom_protocol("app".to_string(), move |request| {
-                let path = request.uri().path();
-                // Remove leading slash
-                let path = if path.starts_with('/') { &path[1..] } else { path };
-                let path = if path.is_empty() { "index.html" } else { path };
-                
-                // Security: Prevent directory traversal
-                if path.contains("..") {
-                    return wry::http::Response::builder()
-                        .status(403)
-                        .body(std::borrow::Cow::from(Vec::new()))
-                        .unwrap();
-                }
+        // Responses are built off the UI thread since media reads hit the disk
+        let protocol = app_protocol.clone();
+        builder = builder.with_asynchronous_custom_protocol("app".to_string(), move |_webview_id, request, responder| {
+            let protocol = protocol.clone();
+            std::thread::spawn(move || {
+                let header = |name: &str| {
+                    request
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                };
+                let asset = protocol.handle(&herding_cats_rust::app_protocol::ProtocolRequest {
+                    path: request.uri().path(),
+                    range: header("Range"),
+                    if_none_match: header("If-None-Match"),
+                });
 
-                let file_path = PathBuf::from("frontend/dist").join(path);
-                
-                match std::fs::read(&file_path) {
-                    Ok(content) => {
-                        let mime_type = match file_path.extension().and_then(|ext| ext.to_str()) {
-                            Some("html") => "text/html",
-                            Some("js") => "application/javascript",
-                            Some("css") => "text/css",
-                            Some("png") => "image/png",
-                            Some("svg") => "image/svg+xml",
-                            Some("json") => "application/json",
-                            _ => "application/octet-stream",
-                        };
-
-                        wry::http::Response::builder()
-                            .header("Content-Type", mime_type)
-                            .body(std::borrow::Cow::from(content))
-                            .unwrap()
-                    },
-                    Err(_) => {
-                        // Try index.html for SPA routing if file not found
-                        if let Ok(content) = std::fs::read(PathBuf::from("frontend/dist/index.html")) {
-                             wry::http::Response::builder()
-                                .header("Content-Type", "text/html")
-                                .body(std::borrow::Cow::from(content))
-                                .unwrap()
-                        } else {
-                            wry::http::Response::builder()
-                                .status(404)
-                                .body(std::borrow::Cow::from(Vec::new()))
-                                .unwrap()
-                        }
-                    }
+                let mut response = wry::http::Response::builder().status(asset.status);
+                for (name, value) in &asset.headers {
+                    response = response.header(*name, value.as_str());
                 }
+                responder.respond(response.body(asset.body).unwrap());
             });
-        }
+        });
 
         let webview = builder
             .with_initialization_script("window.IPC_TEST = 'active'; console.log('Init script ran');")
@@ -254,6 +782,9 @@ async fn main() -> Result<()> {
     let (main_window, main_webview) = create_window(&event_loop, start_url, "Herding Cats".to_string())?;
     main_window_id = Some(main_window.id());
     webviews.insert(main_window.id(), (main_window, main_webview));
+    if let Some(link) = launch_link {
+        deep_link_handler(link);
+    }
 
     // Run Event Loop
     event_loop.run(move |event, event_loop, control_flow| {
@@ -261,12 +792,46 @@ async fn main() -> Result<()> {
 
         match event {
             Event::NewEvents(StartCause::Init) => println!("Herding Cats started!"),
+            // macOS delivers double-clicked project files and herdingcats://
+            // links here rather than on the command line
+            Event::Opened { urls } => {
+                for url in urls {
+                    let link = match url.to_file_path() {
+                        Ok(path) if ProjectFile::is_project_file(&path) => {
+                            Some(DeepLink::OpenProjectFile { path })
+                        }
+                        _ => DeepLink::parse(url.as_str()).ok(),
+                    };
+                    match link {
+                        Some(link) => deep_link_handler(link),
+                        None => eprintln!("Ignoring unsupported open request: {}", url),
+                    }
+                }
+            },
+            Event::WindowEvent {
+                event: WindowEvent::Focused(focused),
+                window_id,
+                ..
+            } if focus.is_enabled() => {
+                let kind = if focused { FocusEventKind::WindowFocused } else { FocusEventKind::WindowBlurred };
+                let window = match tool_windows.get(&window_id) {
+                    Some(tool_id) => format!("tool:{}", tool_id),
+                    None => "main".to_string(),
+                };
+                let focus = focus.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = focus.record(kind, &window).await {
+                        eprintln!("Failed to record focus event: {}", e);
+                    }
+                });
+            },
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 window_id,
                 ..
             } => {
                 webviews.remove(&window_id);
+                tool_windows.remove(&window_id);
                 if webviews.is_empty() {
                     *control_flow = ControlFlow::Exit;
                 }
@@ -277,6 +842,12 @@ async fn main() -> Result<()> {
                     let _ = webview.evaluate_script(&script);
                 }
             },
+            Event::UserEvent(UserEvent::IpcEvent(event)) => {
+                let script = format!("if (window.__IPC_RECEIVE__) {{ window.__IPC_RECEIVE__({}) }}", event);
+                for (_, webview) in webviews.values() {
+                    let _ = webview.evaluate_script(&script);
+                }
+            },
             Event::UserEvent(UserEvent::AppExit) => {
                 println!("Received Exit command. Closing all windows...");
                 webviews.clear();
@@ -288,26 +859,56 @@ async fn main() -> Result<()> {
                 let url = format!("http://127.0.0.1:5180/#/tool/{}", tool_id);
                 #[cfg(not(debug_assertions))]
                 let url = format!("app://localhost/index.html#/tool/{}", tool_id);
+                if focus.is_enabled() {
+                    let focus = focus.clone();
+                    let tool_id = tool_id.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = focus.record(FocusEventKind::ToolOpened, &tool_id).await {
+                            eprintln!("Failed to record tool switch: {}", e);
+                        }
+                    });
+                }
                 match create_window(event_loop, url, format!("Tool: {}", tool_id)) {
                     Ok((window, webview)) => {
+                        tool_windows.insert(window.id(), tool_id);
                         webviews.insert(window.id(), (window, webview));
                     },
                     Err(e) => eprintln!("Failed to create tool window: {}", e),
                 }
             },
+            Event::UserEvent(UserEvent::OpenProject(project_id)) => {
+                println!("Opening project in main window: {}", project_id);
+                crash_reporter::record_state("active_project", &project_id);
+                if let Some((window, webview)) = main_window_id.and_then(|id| webviews.get(&id)) {
+                    window.set_focus();
+                    let payload = format!(r#"{{"type": "open_project", "payload": {{ "id": "{}" }} }}"#, project_id);
+                    let script = format!("if (window.__IPC_RECEIVE__) {{ window.__IPC_RECEIVE__({}) }} else {{ console.error('IPC Receive handler missing') }}", payload);
+                    let _ = webview.evaluate_script(&script);
+                }
+            },
             Event::UserEvent(UserEvent::OpenDocument(document_id)) => {
                 println!("Opening document in main window: {}", document_id);
+                crash_reporter::record_state("active_document", &document_id);
                 if let Some(id) = main_window_id {
-                    if let Some((_, webview)) = webviews.get(&id) {
+                    if let Some((window, webview)) = webviews.get(&id) {
+                        window.set_focus();
                         let payload = format!(r#"{{"type": "open_document", "payload": {{ "id": "{}" }} }}"#, document_id);
                         let script = format!("if (window.__IPC_RECEIVE__) {{ window.__IPC_RECEIVE__({}) }} else {{ console.error('IPC Receive handler missing') }}", payload);
                         let _ = webview.evaluate_script(&script);
                     }
                 }
             },
+            Event::UserEvent(UserEvent::ShowLog) => {
+                if let Some((window, webview)) = main_window_id.and_then(|id| webviews.get(&id)) {
+                    window.set_focus();
+                    let script = r#"if (window.__IPC_RECEIVE__) { window.__IPC_RECEIVE__({"type": "show_log", "payload": {}}) }"#;
+                    let _ = webview.evaluate_script(script);
+                }
+            },
             Event::UserEvent(UserEvent::CloseWindow(window_id)) => {
                  println!("Closing window: {:?}", window_id);
                  webviews.remove(&window_id);
+                 tool_windows.remove(&window_id);
                  if webviews.is_empty() {
                      *control_flow = ControlFlow::Exit;
                  }
@@ -334,6 +935,9 @@ async fn main() -> Result<()> {
             },
             Event::LoopDestroyed => {
                 println!("Goodbye!");
+                if let Err(e) = crash_reporter.end_session() {
+                    eprintln!("Failed to record session end: {}", e);
+                }
                 #[cfg(debug_assertions)]
                 if let Some(mut child) = dev_server_process.take() {
                     println!("Stopping dev server...");
@@ -343,5 +947,6 @@ async fn main() -> Result<()> {
             },
             _ => (),
         }
+        crash_reporter::record_state("open_windows", webviews.len());
     });
 }
@@ -0,0 +1,245 @@
+//! Desktop Notifications
+//!
+//! Native OS notifications for background work (workflows, exports,
+//! backups). Buttons on a notification carry a `herdingcats://` deep link;
+//! when the user clicks one the link is handed to the action handler the
+//! app installed, which routes it into the event loop.
+//!
+//! The native backend is behind the `desktop-notifications` feature; every
+//! notification is also written to the log. Action buttons are only supported by the
+//! freedesktop backend (Linux and the BSDs).
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::automation::NotificationLevel;
+use crate::deep_link::{DeepLink, DeepLinkHandler};
+
+#[cfg_attr(not(feature = "desktop-notifications"), allow(dead_code))]
+const APP_NAME: &str = "Herding Cats";
+
+static GLOBAL_NOTIFIER: OnceCell<Arc<Notifier>> = OnceCell::new();
+
+/// A button on a notification
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NotificationAction {
+    pub label: String,
+    /// `herdingcats://` URL opened when the button is clicked
+    pub link: String,
+}
+
+impl NotificationAction {
+    pub fn new(label: impl Into<String>, link: &DeepLink) -> Self {
+        Self {
+            label: label.into(),
+            link: link.to_url(),
+        }
+    }
+
+    /// "Open file" for something a job wrote to disk
+    pub fn open_file(path: &Path) -> Self {
+        Self::new(
+            "Open file",
+            &DeepLink::OpenFile {
+                path: path.to_path_buf(),
+            },
+        )
+    }
+
+    /// "Show log" for jobs that failed or produced warnings
+    pub fn show_log() -> Self {
+        Self::new("Show log", &DeepLink::ShowLog)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DesktopNotification {
+    pub title: String,
+    pub body: String,
+    pub level: NotificationLevel,
+    pub actions: Vec<NotificationAction>,
+}
+
+impl DesktopNotification {
+    pub fn new(
+        title: impl Into<String>,
+        body: impl Into<String>,
+        level: NotificationLevel,
+    ) -> Self {
+        Self {
+            title: title.into(),
+            body: body.into(),
+            level,
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn with_action(mut self, action: NotificationAction) -> Self {
+        self.actions.push(action);
+        self
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum NotificationError {
+    #[error("Notification backend error: {0}")]
+    Backend(String),
+}
+
+/// Shows desktop notifications and routes their button clicks
+#[derive(Default)]
+pub struct Notifier {
+    on_action: Option<DeepLinkHandler>,
+}
+
+impl std::fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Notifier")
+            .field("has_action_handler", &self.on_action.is_some())
+            .finish()
+    }
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Called with the deep link of any button the user clicks
+    pub fn with_action_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(DeepLink) + Send + Sync + 'static,
+    {
+        self.on_action = Some(Arc::new(handler));
+        self
+    }
+
+    /// Make this the notifier returned by `Notifier::global()`.
+    /// Has no effect if a global notifier is already in use.
+    pub fn install_global(notifier: Arc<Notifier>) -> bool {
+        GLOBAL_NOTIFIER.set(notifier).is_ok()
+    }
+
+    /// The process-wide notifier; one without an action handler if none was installed
+    pub fn global() -> Arc<Notifier> {
+        GLOBAL_NOTIFIER
+            .get_or_init(|| Arc::new(Notifier::new()))
+            .clone()
+    }
+
+    /// Show a notification. It is written to the log as well, so nothing
+    /// is lost where the native backend is missing or fails.
+    pub fn notify(&self, notification: &DesktopNotification) -> Result<(), NotificationError> {
+        log::info!(
+            "[{:?}] {}: {}",
+            notification.level,
+            notification.title,
+            notification.body
+        );
+        self.show(notification)
+    }
+
+    /// Route a clicked button's link to the action handler
+    pub fn handle_action(&self, link: &str) {
+        let Some(handler) = &self.on_action else {
+            return;
+        };
+        match DeepLink::parse(link) {
+            Ok(link) => handler(link),
+            Err(e) => log::warn!("Ignoring notification action: {}", e),
+        }
+    }
+
+    #[cfg(feature = "desktop-notifications")]
+    fn show(&self, notification: &DesktopNotification) -> Result<(), NotificationError> {
+        let mut native = notify_rust::Notification::new();
+        native
+            .appname(APP_NAME)
+            .summary(&notification.title)
+            .body(&notification.body);
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            native.urgency(match notification.level {
+                NotificationLevel::Error => notify_rust::Urgency::Critical,
+                NotificationLevel::Warning => notify_rust::Urgency::Normal,
+                NotificationLevel::Info | NotificationLevel::Success => notify_rust::Urgency::Low,
+            });
+            for (index, action) in notification.actions.iter().enumerate() {
+                native.action(&index.to_string(), &action.label);
+            }
+        }
+
+        let handle = native
+            .show()
+            .map_err(|e| NotificationError::Backend(e.to_string()))?;
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        if !notification.actions.is_empty() && self.on_action.is_some() {
+            let notifier = Notifier {
+                on_action: self.on_action.clone(),
+            };
+            let actions = notification.actions.clone();
+            // Blocks until the notification is clicked or dismissed
+            std::thread::spawn(move || {
+                handle.wait_for_action(|key| {
+                    if let Some(action) = key.parse::<usize>().ok().and_then(|i| actions.get(i)) {
+                        notifier.handle_action(&action.link);
+                    }
+                });
+            });
+        }
+        #[cfg(not(all(unix, not(target_os = "macos"))))]
+        let _ = handle;
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "desktop-notifications"))]
+    fn show(&self, _notification: &DesktopNotification) -> Result<(), NotificationError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_actions_route_deep_links_to_handler() {
+        let clicked = Arc::new(Mutex::new(Vec::new()));
+        let sink = clicked.clone();
+        let notifier = Notifier::new().with_action_handler(move |link| {
+            sink.lock().unwrap().push(link);
+        });
+
+        let notification = DesktopNotification::new(
+            "Export finished",
+            "Book One.pdf",
+            NotificationLevel::Success,
+        )
+        .with_action(NotificationAction::open_file(Path::new(
+            "/tmp/Book One.pdf",
+        )))
+        .with_action(NotificationAction::show_log());
+
+        for action in &notification.actions {
+            notifier.handle_action(&action.link);
+        }
+        notifier.handle_action("https://example.com");
+
+        assert_eq!(
+            *clicked.lock().unwrap(),
+            vec![
+                DeepLink::OpenFile {
+                    path: "/tmp/Book One.pdf".into()
+                },
+                DeepLink::ShowLog
+            ]
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! Printing
+//!
+//! Sends rendered PDFs straight to the OS print subsystem: CUPS (`lp`,
+//! `lpstat`) on Linux and macOS, the shell's PrintTo verb on Windows.
+//! Printer names are only accepted if the OS reports them, so nothing the
+//! frontend sends ends up as a free-form argument to the print command.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use thiserror::Error;
+
+use crate::error::{ErrorCode, UserFacingError};
+
+/// Most copies a single job may request
+pub const MAX_COPIES: u32 = 99;
+
+/// A printer known to the OS
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrinterInfo {
+    pub name: String,
+    pub is_default: bool,
+}
+
+/// Where and how often to print
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrintOptions {
+    /// Printer name; the system default when `None`
+    pub printer: Option<String>,
+    pub copies: u32,
+    /// Job title shown in the OS print queue
+    pub title: String,
+}
+
+/// A job accepted by the print subsystem
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrintJob {
+    /// Spooler job id, when the platform reports one
+    pub job_id: Option<String>,
+    pub printer: Option<String>,
+    pub copies: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum PrintError {
+    #[error("No print subsystem available: {0}")]
+    Unavailable(String),
+    #[error("Unknown printer: {0}")]
+    UnknownPrinter(String),
+    #[error("Copies must be between 1 and {MAX_COPIES}, got {0}")]
+    InvalidCopies(u32),
+    #[error("Print command failed: {0}")]
+    Failed(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl UserFacingError for PrintError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            PrintError::UnknownPrinter(_) | PrintError::InvalidCopies(_) => ErrorCode::InvalidInput,
+            PrintError::Unavailable(_) => ErrorCode::Unavailable,
+            PrintError::Failed(_) => ErrorCode::Internal,
+            PrintError::Io(_) => ErrorCode::Storage,
+        }
+    }
+}
+
+/// Printers the OS knows about
+pub fn list_printers() -> Result<Vec<PrinterInfo>, PrintError> {
+    if cfg!(windows) {
+        let output = run(Command::new("powershell").args([
+            "-NoProfile",
+            "-Command",
+            "Get-CimInstance Win32_Printer | ForEach-Object { \"$($_.Name)|$($_.Default)\" }",
+        ]))?;
+        Ok(parse_windows_printers(&output))
+    } else {
+        let printers = run(Command::new("lpstat").arg("-p"))?;
+        // `lpstat -d` fails when no default is configured, which is fine
+        let default = run(Command::new("lpstat").arg("-d")).unwrap_or_default();
+        Ok(parse_lpstat(&printers, &default))
+    }
+}
+
+/// Print a PDF document. Blocks until the job has been handed to the
+/// spooler, so call it from a blocking task.
+pub fn print_pdf(pdf: &[u8], options: &PrintOptions) -> Result<PrintJob, PrintError> {
+    if options.copies == 0 || options.copies > MAX_COPIES {
+        return Err(PrintError::InvalidCopies(options.copies));
+    }
+    if let Some(printer) = &options.printer {
+        if !list_printers()?.iter().any(|p| &p.name == printer) {
+            return Err(PrintError::UnknownPrinter(printer.clone()));
+        }
+    }
+
+    // The spooler copies the file, so it only has to outlive the command
+    let file = tempfile::Builder::new()
+        .prefix("herding-cats-print-")
+        .suffix(".pdf")
+        .tempfile()?;
+    std::fs::write(file.path(), pdf)?;
+
+    let mut job_id = None;
+    for mut command in print_commands(file.path(), options) {
+        let output = run(&mut command)?;
+        job_id = job_id.or_else(|| parse_lp_job_id(&output));
+    }
+    log::info!(
+        "Sent '{}' to {} ({} copies)",
+        options.title,
+        options.printer.as_deref().unwrap_or("default printer"),
+        options.copies
+    );
+
+    Ok(PrintJob {
+        job_id,
+        printer: options.printer.clone(),
+        copies: options.copies,
+    })
+}
+
+/// Commands that print `path`; Windows has no copies flag, so it gets one
+/// command per copy
+fn print_commands(path: &Path, options: &PrintOptions) -> Vec<Command> {
+    if cfg!(windows) {
+        let verb = match &options.printer {
+            Some(printer) => format!(
+                "-Verb PrintTo -ArgumentList '\"{}\"'",
+                printer.replace('\'', "''")
+            ),
+            None => "-Verb Print".to_string(),
+        };
+        let script = format!(
+            "Start-Process -FilePath '{}' {} -Wait",
+            path.display().to_string().replace('\'', "''"),
+            verb
+        );
+        (0..options.copies)
+            .map(|_| {
+                let mut command = Command::new("powershell");
+                command.args(["-NoProfile", "-Command", &script]);
+                command
+            })
+            .collect()
+    } else {
+        let mut command = Command::new("lp");
+        if let Some(printer) = &options.printer {
+            command.arg("-d").arg(printer);
+        }
+        command
+            .arg("-n")
+            .arg(options.copies.to_string())
+            .arg("-t")
+            .arg(&options.title)
+            .arg("--")
+            .arg(path);
+        vec![command]
+    }
+}
+
+fn run(command: &mut Command) -> Result<String, PrintError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command.output().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => PrintError::Unavailable(format!("{} not found", program)),
+        _ => PrintError::Io(e),
+    })?;
+    if !output.status.success() {
+        return Err(PrintError::Failed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse `lpstat -p` ("printer Office is idle. ...") and `lpstat -d`
+/// ("system default destination: Office")
+fn parse_lpstat(printers: &str, default: &str) -> Vec<PrinterInfo> {
+    let default = default
+        .lines()
+        .find_map(|line| line.split_once("destination:"))
+        .map(|(_, name)| name.trim());
+    printers
+        .lines()
+        .filter_map(|line| line.strip_prefix("printer "))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .map(|name| PrinterInfo {
+            name: name.to_string(),
+            is_default: Some(name) == default,
+        })
+        .collect()
+}
+
+/// Parse `Name|True` lines from the Win32_Printer query
+fn parse_windows_printers(output: &str) -> Vec<PrinterInfo> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().rsplit_once('|'))
+        .map(|(name, default)| PrinterInfo {
+            name: name.to_string(),
+            is_default: default.eq_ignore_ascii_case("true"),
+        })
+        .collect()
+}
+
+/// Job id from `lp` output ("request id is Office-42 (1 file(s))")
+fn parse_lp_job_id(output: &str) -> Option<String> {
+    output
+        .split_once("request id is ")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_printer_listings() {
+        let printers = parse_lpstat(
+            "printer Office is idle.  enabled since Mon 01 Jan\nprinter Label_Writer disabled since Tue\n",
+            "system default destination: Office\n",
+        );
+        assert_eq!(
+            printers,
+            vec![
+                PrinterInfo {
+                    name: "Office".to_string(),
+                    is_default: true
+                },
+                PrinterInfo {
+                    name: "Label_Writer".to_string(),
+                    is_default: false
+                },
+            ]
+        );
+
+        let printers =
+            parse_windows_printers("Microsoft Print to PDF|False\r\nHP LaserJet|True\r\n");
+        assert_eq!(printers[0].name, "Microsoft Print to PDF");
+        assert!(printers[1].is_default);
+
+        assert_eq!(
+            parse_lp_job_id("request id is Office-42 (1 file(s))\n").as_deref(),
+            Some("Office-42")
+        );
+    }
+
+    #[test]
+    fn test_print_rejects_bad_copies() {
+        let options = PrintOptions {
+            printer: None,
+            copies: 0,
+            title: "Chapter 1".to_string(),
+        };
+        assert!(matches!(
+            print_pdf(b"%PDF-1.4", &options),
+            Err(PrintError::InvalidCopies(0))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_lp_command_arguments() {
+        let options = PrintOptions {
+            printer: Some("Office".to_string()),
+            copies: 3,
+            title: "Chapter 1".to_string(),
+        };
+        let commands = print_commands(Path::new("/tmp/chapter.pdf"), &options);
+        assert_eq!(commands.len(), 1);
+        let args: Vec<_> = commands[0]
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            args,
+            [
+                "-d",
+                "Office",
+                "-n",
+                "3",
+                "-t",
+                "Chapter 1",
+                "--",
+                "/tmp/chapter.pdf"
+            ]
+        );
+    }
+}
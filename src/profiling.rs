@@ -0,0 +1,456 @@
+//! Pipeline Profiling
+//!
+//! Measures the pipelines whose speed users notice on large projects:
+//! full-text search latency, semantic search latency and recall, and PDF
+//! rendering throughput. Runs against seed data from
+//! `database::fixtures`, so numbers from different machines and builds are
+//! measured on the same text, and compares them with a stored baseline to
+//! catch regressions. The criterion benchmarks in `benches/` time the same
+//! workload.
+//!
+//! Run in the app with
+//! `herding-cats --profile [spec] [--baseline <file>] [--save-baseline]`.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::fixtures::{self, FixtureReport, FixtureSpec};
+use crate::database::search_service::{SearchConfig, SearchOptions};
+use crate::database::vector_embedding::SearchOptions as SemanticSearchOptions;
+use crate::database::{
+    DatabaseError, DatabaseResult, EnhancedDatabaseService, SearchService, VectorEmbeddingService,
+};
+use crate::error::{AppError, AppResult};
+use crate::publishing::{pdf, PublishedDocument, PublishedSection};
+
+/// How much worse than the baseline a metric may get before it counts as
+/// a regression, as a fraction of the baseline
+pub const DEFAULT_TOLERANCE: f64 = 0.2;
+
+/// What to generate and how much to measure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileOptions {
+    pub spec: FixtureSpec,
+    /// Searches run per pipeline
+    pub queries: usize,
+    /// Semantic results checked for the source document
+    pub recall_k: usize,
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        Self {
+            spec: FixtureSpec {
+                projects: 2,
+                documents_per_project: 100,
+                embeddings: true,
+                ..Default::default()
+            },
+            queries: 50,
+            recall_k: 5,
+        }
+    }
+}
+
+/// A paragraph taken from a generated document, used as a query
+#[derive(Debug, Clone)]
+pub struct QuerySample {
+    pub document_id: Uuid,
+    pub project_id: Uuid,
+    pub paragraph: String,
+    /// Two words from the paragraph, for full-text search
+    pub terms: String,
+}
+
+impl QuerySample {
+    /// Full-text search within the sample's project
+    pub fn full_text_options(&self) -> SearchOptions {
+        SearchOptions {
+            limit: 20,
+            project_filter: Some(self.project_id),
+            ..Default::default()
+        }
+    }
+}
+
+/// Semantic search over every document, keeping the `limit` best chunks
+pub fn semantic_options(limit: usize) -> SemanticSearchOptions {
+    SemanticSearchOptions {
+        limit,
+        similarity_threshold: 0.0,
+        include_metadata: false,
+        model_filter: None,
+        document_filter: None,
+    }
+}
+
+/// Queries and a manuscript drawn from the seed data
+#[derive(Debug, Clone)]
+pub struct Workload {
+    pub fixtures: FixtureReport,
+    pub samples: Vec<QuerySample>,
+    /// The first project, ready to render
+    pub manuscript: PublishedDocument,
+}
+
+impl Workload {
+    /// Generate seed data for `options` and draw the workload from it
+    pub async fn generate(
+        db_service: &Arc<RwLock<EnhancedDatabaseService>>,
+        options: &ProfileOptions,
+    ) -> DatabaseResult<Self> {
+        let generated = fixtures::generate(db_service, &options.spec).await?;
+        Self::load(db_service, generated, options.queries, options.spec.seed).await
+    }
+
+    /// Draw `queries` samples from the generated documents
+    pub async fn load(
+        db_service: &Arc<RwLock<EnhancedDatabaseService>>,
+        fixtures: FixtureReport,
+        queries: usize,
+        seed: u64,
+    ) -> DatabaseResult<Self> {
+        let db = db_service.read().await;
+        let project_ids = &fixtures.project_ids;
+        let mut documents = Vec::new();
+        for project_id in project_ids {
+            let rows: Vec<(String, String, String)> = sqlx::query_as(
+                "SELECT id, title, content FROM documents
+                 WHERE project_id = ?1 AND is_active = 1 ORDER BY created_at, title",
+            )
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to load documents: {}", e)))?;
+            for (id, title, content) in rows {
+                let id = Uuid::parse_str(&id)
+                    .map_err(|e| DatabaseError::Service(format!("Invalid UUID: {}", e)))?;
+                documents.push((*project_id, id, title, content));
+            }
+        }
+
+        let mut manuscript = PublishedDocument::new("Profiling manuscript");
+        manuscript.sections = documents
+            .iter()
+            .filter(|(project_id, ..)| Some(project_id) == project_ids.first())
+            .map(|(_, _, title, content)| PublishedSection::from_text(title, content))
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut samples = Vec::with_capacity(queries);
+        for _ in 0..queries {
+            let Some((project_id, document_id, _, content)) = documents.choose(&mut rng) else {
+                break;
+            };
+            let paragraphs: Vec<&str> = content.split("\n\n").collect();
+            let paragraph = paragraphs.choose(&mut rng).copied().unwrap_or_default();
+            samples.push(QuerySample {
+                document_id: *document_id,
+                project_id: *project_id,
+                paragraph: paragraph.to_string(),
+                terms: search_terms(paragraph),
+            });
+        }
+
+        Ok(Self {
+            fixtures,
+            samples,
+            manuscript,
+        })
+    }
+}
+
+/// The first two words of four or more letters
+fn search_terms(paragraph: &str) -> String {
+    let words: Vec<&str> = paragraph
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| word.len() >= 4)
+        .collect();
+    words.get(..2).unwrap_or(&words).join(" ")
+}
+
+/// Summary of a set of timings, in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        ms.sort_by(|a, b| a.total_cmp(b));
+        let percentile = |p: f64| ms[((ms.len() - 1) as f64 * p).round() as usize];
+        Self {
+            samples: ms.len(),
+            mean_ms: ms.iter().sum::<f64>() / ms.len() as f64,
+            p50_ms: percentile(0.5),
+            p95_ms: percentile(0.95),
+            max_ms: ms[ms.len() - 1],
+        }
+    }
+}
+
+/// One profiling run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileReport {
+    pub documents: usize,
+    pub words: usize,
+    pub full_text: LatencyStats,
+    pub semantic: LatencyStats,
+    /// Share of semantic queries, each a paragraph of some document, that
+    /// found that document among the top `recall_k` results
+    pub semantic_recall: f64,
+    pub pdf_pages: usize,
+    pub pdf_pages_per_second: f64,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// A metric that got worse than the baseline allows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Regression {
+    pub metric: String,
+    pub baseline: f64,
+    pub current: f64,
+}
+
+impl Regression {
+    /// Relative change from the baseline, e.g. 0.35 for 35% higher
+    pub fn change(&self) -> f64 {
+        (self.current - self.baseline) / self.baseline
+    }
+}
+
+impl ProfileReport {
+    /// Metrics more than `tolerance` worse than in `baseline`. Latencies
+    /// regress by going up, recall and throughput by going down; metrics
+    /// the baseline didn't measure are skipped.
+    pub fn regressions(&self, baseline: &ProfileReport, tolerance: f64) -> Vec<Regression> {
+        let slower = [
+            (
+                "full_text.p50_ms",
+                baseline.full_text.p50_ms,
+                self.full_text.p50_ms,
+            ),
+            (
+                "full_text.p95_ms",
+                baseline.full_text.p95_ms,
+                self.full_text.p95_ms,
+            ),
+            (
+                "semantic.p50_ms",
+                baseline.semantic.p50_ms,
+                self.semantic.p50_ms,
+            ),
+            (
+                "semantic.p95_ms",
+                baseline.semantic.p95_ms,
+                self.semantic.p95_ms,
+            ),
+        ];
+        let lower = [
+            (
+                "semantic_recall",
+                baseline.semantic_recall,
+                self.semantic_recall,
+            ),
+            (
+                "pdf_pages_per_second",
+                baseline.pdf_pages_per_second,
+                self.pdf_pages_per_second,
+            ),
+        ];
+        let regressed =
+            slower
+                .into_iter()
+                .filter(|&(_, base, current)| base > 0.0 && current > base * (1.0 + tolerance))
+                .chain(lower.into_iter().filter(|&(_, base, current)| {
+                    base > 0.0 && current < base * (1.0 - tolerance)
+                }));
+        regressed
+            .map(|(metric, baseline, current)| Regression {
+                metric: metric.to_string(),
+                baseline,
+                current,
+            })
+            .collect()
+    }
+
+    pub fn load(path: &Path) -> AppResult<Self> {
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| AppError::Io(format!("Invalid baseline {}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> AppResult<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Io(format!("Failed to serialize report: {}", e)))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// A search service that always queries the index, never its cache
+pub fn uncached_search(db_service: &Arc<RwLock<EnhancedDatabaseService>>) -> SearchService {
+    SearchService::with_config(
+        Arc::clone(db_service),
+        SearchConfig {
+            enable_caching: false,
+            enable_analytics: false,
+            ..Default::default()
+        },
+    )
+}
+
+/// Generate seed data into `db_service` and measure every pipeline on it
+pub async fn run(
+    db_service: &Arc<RwLock<EnhancedDatabaseService>>,
+    options: &ProfileOptions,
+) -> DatabaseResult<ProfileReport> {
+    let workload = Workload::generate(db_service, options).await?;
+
+    let search = uncached_search(db_service);
+    search.initialize().await?;
+    let mut full_text = Vec::with_capacity(workload.samples.len());
+    for sample in &workload.samples {
+        let started = Instant::now();
+        search
+            .search_documents_advanced(&sample.terms, Some(sample.full_text_options()))
+            .await?;
+        full_text.push(started.elapsed());
+    }
+
+    let mut semantic = Vec::new();
+    let mut found = 0;
+    if options.spec.embeddings {
+        let embeddings = VectorEmbeddingService::new(Arc::clone(db_service));
+        for sample in &workload.samples {
+            let started = Instant::now();
+            let results = embeddings
+                .find_similar_documents(&sample.paragraph, Some(semantic_options(options.recall_k)))
+                .await
+                .map_err(|e| DatabaseError::Service(format!("Semantic search failed: {}", e)))?;
+            semantic.push(started.elapsed());
+            if results.iter().any(|r| r.document_id == sample.document_id) {
+                found += 1;
+            }
+        }
+    }
+
+    let started = Instant::now();
+    let rendered = workload.manuscript.render_pdf();
+    let elapsed = started.elapsed().as_secs_f64();
+    let pdf_pages = pdf::page_count(&rendered);
+
+    Ok(ProfileReport {
+        documents: workload.fixtures.documents,
+        words: workload.fixtures.words,
+        full_text: LatencyStats::from_samples(&full_text),
+        semantic: LatencyStats::from_samples(&semantic),
+        semantic_recall: if semantic.is_empty() {
+            0.0
+        } else {
+            found as f64 / semantic.len() as f64
+        },
+        pdf_pages,
+        pdf_pages_per_second: if elapsed > 0.0 {
+            pdf_pages as f64 / elapsed
+        } else {
+            0.0
+        },
+        recorded_at: Utc::now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    fn report(p50_ms: f64, recall: f64, pages_per_second: f64) -> ProfileReport {
+        ProfileReport {
+            documents: 10,
+            words: 1000,
+            full_text: LatencyStats {
+                p50_ms,
+                p95_ms: p50_ms * 2.0,
+                ..Default::default()
+            },
+            semantic: LatencyStats::default(),
+            semantic_recall: recall,
+            pdf_pages: 5,
+            pdf_pages_per_second: pages_per_second,
+            recorded_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_regressions_respect_direction_and_tolerance() {
+        let baseline = report(10.0, 0.9, 100.0);
+        assert!(report(11.0, 0.8, 90.0)
+            .regressions(&baseline, DEFAULT_TOLERANCE)
+            .is_empty());
+
+        let regressed = report(13.0, 0.6, 200.0).regressions(&baseline, DEFAULT_TOLERANCE);
+        let metrics: Vec<&str> = regressed.iter().map(|r| r.metric.as_str()).collect();
+        assert_eq!(
+            metrics,
+            ["full_text.p50_ms", "full_text.p95_ms", "semantic_recall"]
+        );
+        assert!((regressed[0].change() - 0.3).abs() < 1e-9);
+
+        let stats = LatencyStats::from_samples(&[
+            Duration::from_millis(4),
+            Duration::from_millis(1),
+            Duration::from_millis(2),
+        ]);
+        assert_eq!((stats.p50_ms, stats.max_ms), (2.0, 4.0));
+    }
+
+    #[tokio::test]
+    async fn test_profiles_generated_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let db = Arc::new(RwLock::new(db));
+        let options = ProfileOptions {
+            spec: FixtureSpec::parse(
+                "projects=1,documents=8,words=150-300,codex=0,embeddings=true",
+            )
+            .unwrap(),
+            queries: 6,
+            recall_k: 5,
+        };
+
+        let report = run(&db, &options).await.unwrap();
+        assert_eq!(report.documents, 8);
+        assert_eq!(report.full_text.samples, 6);
+        assert_eq!(report.semantic.samples, 6);
+        assert!(report.semantic_recall > 0.0);
+        assert!(report.pdf_pages >= 8);
+        assert!(report.pdf_pages_per_second > 0.0);
+
+        let path = dir.path().join("baseline.json");
+        report.save(&path).unwrap();
+        let baseline = ProfileReport::load(&path).unwrap();
+        assert_eq!(baseline.full_text.samples, 6);
+        assert!(report.regressions(&baseline, DEFAULT_TOLERANCE).is_empty());
+    }
+}
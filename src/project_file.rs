@@ -0,0 +1,146 @@
+//! Project Files
+//!
+//! `.hcats` files are small JSON descriptors pointing at a project inside a
+//! library database. Double-clicking one in the OS file manager launches
+//! (or forwards to) Herding Cats, which opens the project it names.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+pub const EXTENSION: &str = "hcats";
+pub const MIME_TYPE: &str = "application/x-herding-cats-project";
+const FORMAT: &str = "herding-cats-project";
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum ProjectFileError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Malformed project file: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("Not a Herding Cats project file")]
+    NotAProjectFile,
+    #[error("Project file version {0} is newer than this app supports")]
+    UnsupportedVersion(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectFile {
+    pub format: String,
+    pub version: u32,
+    pub project_id: String,
+    pub name: String,
+    /// Library database holding the project. Stored relative to the
+    /// project file when it lives beside or below it, absolute otherwise.
+    pub library: PathBuf,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ProjectFile {
+    pub fn new(project_id: &str, name: &str, library: &Path) -> Self {
+        Self {
+            format: FORMAT.to_string(),
+            version: FORMAT_VERSION,
+            project_id: project_id.to_string(),
+            name: name.to_string(),
+            library: library.to_path_buf(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Whether `path` looks like a project file, by extension
+    pub fn is_project_file(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(EXTENSION))
+    }
+
+    /// Write to `path`, adding the `.hcats` extension if missing.
+    /// Returns the path written.
+    pub fn save(&self, path: &Path) -> Result<PathBuf, ProjectFileError> {
+        let path = if Self::is_project_file(path) {
+            path.to_path_buf()
+        } else {
+            path.with_extension(EXTENSION)
+        };
+
+        let mut stored = self.clone();
+        let base = path.parent().map(absolute).unwrap_or_default();
+        if let Ok(relative) = absolute(&self.library).strip_prefix(&base) {
+            stored.library = relative.to_path_buf();
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(&stored)?)?;
+        Ok(path)
+    }
+
+    /// Read and validate a project file; `library` comes back absolute
+    pub fn load(path: &Path) -> Result<Self, ProjectFileError> {
+        let content = std::fs::read_to_string(path)?;
+        let value: serde_json::Value = serde_json::from_str(&content)?;
+        if value.get("format").and_then(|f| f.as_str()) != Some(FORMAT) {
+            return Err(ProjectFileError::NotAProjectFile);
+        }
+
+        let mut file: ProjectFile = serde_json::from_value(value)?;
+        if file.version > FORMAT_VERSION {
+            return Err(ProjectFileError::UnsupportedVersion(file.version));
+        }
+        if file.library.is_relative() {
+            let base = path.parent().unwrap_or_else(|| Path::new("."));
+            file.library = absolute(&base.join(&file.library));
+        }
+        Ok(file)
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| {
+        std::env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_with_relative_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("herding_cats.db");
+        std::fs::write(&library, b"").unwrap();
+
+        let file = ProjectFile::new("p-1", "The Long Winter", &library);
+        let written = file.save(&dir.path().join("The Long Winter")).unwrap();
+        assert_eq!(written.extension().unwrap(), EXTENSION);
+
+        let raw: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&written).unwrap()).unwrap();
+        assert_eq!(raw["library"], "herding_cats.db");
+
+        let loaded = ProjectFile::load(&written).unwrap();
+        assert_eq!(loaded.project_id, "p-1");
+        assert_eq!(loaded.library, library.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_rejects_foreign_and_future_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let foreign = dir.path().join("other.hcats");
+        std::fs::write(&foreign, r#"{"format":"something-else"}"#).unwrap();
+        assert!(matches!(
+            ProjectFile::load(&foreign),
+            Err(ProjectFileError::NotAProjectFile)
+        ));
+
+        let mut future = ProjectFile::new("p", "n", Path::new("/library.db"));
+        future.version = FORMAT_VERSION + 1;
+        let path = future.save(&dir.path().join("future.hcats")).unwrap();
+        assert!(matches!(
+            ProjectFile::load(&path),
+            Err(ProjectFileError::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,401 @@
+//! Cover Composition
+//!
+//! Builds a front cover from a background (an image asset or a flat colour)
+//! with the title, optional subtitle and author name set in chosen fonts.
+//! Covers are rendered per trim size: the ebook size stores recommend, or a
+//! print trim at 300 DPI including bleed. Unset text colours come from the
+//! background's palette so the text stays readable.
+
+use std::io::Cursor;
+
+use ab_glyph::{point, Font, FontVec, PxScale, PxScaleFont, ScaleFont};
+use image::codecs::jpeg::{JpegEncoder, PixelDensity};
+use image::imageops::FilterType;
+use image::{Rgb as Pixel, RgbImage};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use thiserror::Error;
+
+use super::palette::{palette_from_pixels, Rgb};
+use crate::asset_store::AssetStore;
+
+/// Resolution of print covers
+const PRINT_DPI: u32 = 300;
+/// Printers trim this much off the top, bottom and outer edge
+const BLEED_INCHES: f32 = 0.125;
+/// Text is kept this far (as a fraction of the width) from the sides
+const SIDE_MARGIN: f32 = 0.08;
+/// Longer text is set smaller rather than wrapped further
+const MAX_LINES: usize = 3;
+const JPEG_QUALITY: u8 = 92;
+const DEFAULT_BACKGROUND: Rgb = Rgb(34, 40, 49);
+/// Used when no font is chosen or the chosen family is not installed
+const FALLBACK_FONT: &[u8] = include_bytes!("../fonts/CrimsonText-Regular.ttf");
+
+/// System fonts, loaded on first use
+static SYSTEM_FONTS: Lazy<Mutex<fontdb::Database>> = Lazy::new(|| {
+    let mut db = fontdb::Database::new();
+    db.load_system_fonts();
+    Mutex::new(db)
+});
+
+#[derive(Debug, Error)]
+pub enum CoverError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Font error: {0}")]
+    Font(String),
+    #[error("Background image not found: {0}")]
+    NotFound(String),
+}
+
+/// Output size of a cover
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrimSize {
+    /// 1600 x 2560 pixels, the 1:1.6 ratio ebook stores recommend
+    Ebook,
+    /// 5" x 8"
+    Print5x8,
+    /// 5.5" x 8.5"
+    Print5_5x8_5,
+    /// 6" x 9", the usual trade paperback
+    Print6x9,
+}
+
+impl TrimSize {
+    pub const ALL: [TrimSize; 4] = [
+        TrimSize::Ebook,
+        TrimSize::Print5x8,
+        TrimSize::Print5_5x8_5,
+        TrimSize::Print6x9,
+    ];
+
+    /// Trim in inches, for print sizes
+    pub fn inches(&self) -> Option<(f32, f32)> {
+        match self {
+            TrimSize::Ebook => None,
+            TrimSize::Print5x8 => Some((5.0, 8.0)),
+            TrimSize::Print5_5x8_5 => Some((5.5, 8.5)),
+            TrimSize::Print6x9 => Some((6.0, 9.0)),
+        }
+    }
+
+    pub fn dpi(&self) -> u32 {
+        match self {
+            TrimSize::Ebook => 72,
+            _ => PRINT_DPI,
+        }
+    }
+
+    /// Pixel size of the rendered cover; print covers include bleed on the
+    /// top, bottom and outer edge
+    pub fn pixels(&self) -> (u32, u32) {
+        match self.inches() {
+            None => (1600, 2560),
+            Some((width, height)) => {
+                let dpi = self.dpi() as f32;
+                (
+                    ((width + BLEED_INCHES) * dpi).round() as u32,
+                    ((height + 2.0 * BLEED_INCHES) * dpi).round() as u32,
+                )
+            }
+        }
+    }
+}
+
+/// One line group of cover text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverText {
+    pub text: String,
+    /// Font family name; the bundled serif when unset or not installed
+    #[serde(default)]
+    pub font_family: Option<String>,
+    /// 400 is regular, 700 bold
+    #[serde(default = "default_weight")]
+    pub weight: u16,
+    /// Font size as a fraction of the cover height
+    pub size: f32,
+    /// Picked from the background when unset
+    #[serde(default)]
+    pub color: Option<Rgb>,
+    /// Vertical centre of the text as a fraction of the cover height
+    pub position: f32,
+}
+
+fn default_weight() -> u16 {
+    400
+}
+
+impl CoverText {
+    pub fn new(text: impl Into<String>, size: f32, position: f32) -> Self {
+        Self {
+            text: text.into(),
+            font_family: None,
+            weight: default_weight(),
+            size,
+            color: None,
+            position,
+        }
+    }
+}
+
+/// Everything needed to render a cover at any trim size
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverDesign {
+    /// Asset hash of the background image, scaled and cropped to fill
+    #[serde(default)]
+    pub background_asset: Option<String>,
+    /// Flat background when there is no image
+    #[serde(default)]
+    pub background_color: Option<Rgb>,
+    pub title: CoverText,
+    #[serde(default)]
+    pub subtitle: Option<CoverText>,
+    pub author: CoverText,
+}
+
+impl CoverDesign {
+    /// A centred layout: large bold title in the upper third, author near
+    /// the bottom
+    pub fn new(title: impl Into<String>, author: impl Into<String>) -> Self {
+        Self {
+            background_asset: None,
+            background_color: None,
+            title: CoverText {
+                weight: 700,
+                ..CoverText::new(title, 0.08, 0.28)
+            },
+            subtitle: None,
+            author: CoverText::new(author, 0.04, 0.86),
+        }
+    }
+
+    /// Render with the background image, if any, read from `store`
+    pub fn render(&self, trim: TrimSize, store: &AssetStore) -> Result<CoverImage, CoverError> {
+        let background = match &self.background_asset {
+            Some(hash) => Some(
+                store
+                    .read(hash)
+                    .map_err(|_| CoverError::NotFound(hash.clone()))?,
+            ),
+            None => None,
+        };
+        compose_cover(self, trim, background.as_deref())
+    }
+}
+
+/// A rendered cover
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverImage {
+    /// JPEG with the density set to `dpi`
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub dpi: u32,
+}
+
+impl CoverImage {
+    pub const MIME_TYPE: &'static str = "image/jpeg";
+}
+
+/// Render `design` at `trim`, using `background` (encoded image bytes) in
+/// place of the design's background asset
+pub fn compose_cover(
+    design: &CoverDesign,
+    trim: TrimSize,
+    background: Option<&[u8]>,
+) -> Result<CoverImage, CoverError> {
+    let (width, height) = trim.pixels();
+    let mut canvas = match background {
+        Some(bytes) => image::load_from_memory(bytes)?
+            .resize_to_fill(width, height, FilterType::Lanczos3)
+            .to_rgb8(),
+        None => {
+            let Rgb(r, g, b) = design.background_color.unwrap_or(DEFAULT_BACKGROUND);
+            RgbImage::from_pixel(width, height, Pixel([r, g, b]))
+        }
+    };
+
+    // Readable text colour for what is actually behind the text
+    let sampled = image::imageops::thumbnail(&canvas, 64, 96);
+    let pixels = sampled
+        .pixels()
+        .map(|p| Rgb(p.0[0], p.0[1], p.0[2]))
+        .collect();
+    let theme = palette_from_pixels(pixels, 4).theme;
+
+    let texts = std::iter::once(&design.title)
+        .chain(design.subtitle.as_ref())
+        .chain(std::iter::once(&design.author));
+    for text in texts.filter(|t| !t.text.trim().is_empty()) {
+        let font = load_font(text.font_family.as_deref(), text.weight)?;
+        draw_text(&mut canvas, &font, text, text.color.unwrap_or(theme.text));
+    }
+
+    let mut data = Vec::new();
+    let mut encoder = JpegEncoder::new_with_quality(Cursor::new(&mut data), JPEG_QUALITY);
+    encoder.set_pixel_density(PixelDensity::dpi(trim.dpi() as u16));
+    canvas.write_with_encoder(encoder)?;
+
+    Ok(CoverImage {
+        data,
+        width,
+        height,
+        dpi: trim.dpi(),
+    })
+}
+
+/// The installed face of `family` closest to `weight`, or the bundled serif
+fn load_font(family: Option<&str>, weight: u16) -> Result<FontVec, CoverError> {
+    if let Some(family) = family {
+        let db = SYSTEM_FONTS
+            .lock()
+            .map_err(|e| CoverError::Font(e.to_string()))?;
+        let query = fontdb::Query {
+            families: &[fontdb::Family::Name(family)],
+            weight: fontdb::Weight(weight),
+            ..Default::default()
+        };
+        let font = db.query(&query).and_then(|id| {
+            db.with_face_data(id, |data, index| {
+                FontVec::try_from_vec_and_index(data.to_vec(), index).ok()
+            })
+            .flatten()
+        });
+        if let Some(font) = font {
+            return Ok(font);
+        }
+        tracing::warn!("Font family {} not installed, using the default", family);
+    }
+    FontVec::try_from_vec(FALLBACK_FONT.to_vec()).map_err(|e| CoverError::Font(e.to_string()))
+}
+
+/// Centre `text` horizontally around its position, wrapping at words and
+/// shrinking the size if a single word is still too wide or the text needs
+/// more than `MAX_LINES` lines
+fn draw_text(canvas: &mut RgbImage, font: &FontVec, text: &CoverText, color: Rgb) {
+    let (width, height) = canvas.dimensions();
+    let max_width = width as f32 * (1.0 - 2.0 * SIDE_MARGIN);
+    let mut px = (text.size * height as f32).max(1.0);
+
+    let lines = loop {
+        let scaled = font.as_scaled(PxScale::from(px));
+        let lines = wrap_words(&text.text, max_width, |s| line_width(&scaled, s));
+        let fits = lines.len() <= MAX_LINES.max(text.text.lines().count())
+            && lines.iter().all(|l| line_width(&scaled, l) <= max_width);
+        if fits || px <= 8.0 {
+            break lines;
+        }
+        px *= 0.9;
+    };
+
+    let scaled = font.as_scaled(PxScale::from(px));
+    let line_height = scaled.height() + scaled.line_gap();
+    let block_height = line_height * lines.len() as f32;
+    let mut baseline = text.position * height as f32 - block_height / 2.0 + scaled.ascent();
+
+    for line in &lines {
+        let mut x = (width as f32 - line_width(&scaled, line)) / 2.0;
+        let mut previous = None;
+        for c in line.chars() {
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                x += scaled.kern(previous, id);
+            }
+            let glyph = id.with_scale_and_position(scaled.scale(), point(x, baseline));
+            x += scaled.h_advance(id);
+            previous = Some(id);
+
+            let Some(outline) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outline.px_bounds();
+            outline.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px < 0 || py < 0 || px >= width as i32 || py >= height as i32 {
+                    return;
+                }
+                let pixel = canvas.get_pixel_mut(px as u32, py as u32);
+                let target = [color.0, color.1, color.2];
+                for (channel, target) in pixel.0.iter_mut().zip(target) {
+                    let blended = *channel as f32 * (1.0 - coverage) + target as f32 * coverage;
+                    *channel = blended.round() as u8;
+                }
+            });
+        }
+        baseline += line_height;
+    }
+}
+
+fn line_width(font: &PxScaleFont<&FontVec>, line: &str) -> f32 {
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in line.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += font.kern(previous, id);
+        }
+        width += font.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Greedy word wrap; explicit newlines always break
+fn wrap_words(text: &str, max_width: f32, measure: impl Fn(&str) -> f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut current = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if current.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", current, word)
+            };
+            if !current.is_empty() && measure(&candidate) > max_width {
+                lines.push(std::mem::replace(&mut current, word.to_string()));
+            } else {
+                current = candidate;
+            }
+        }
+        if !current.is_empty() {
+            lines.push(current);
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cover_sizes_and_text_rendering() {
+        assert_eq!(TrimSize::Print6x9.pixels(), (1838, 2775));
+        assert_eq!(TrimSize::Ebook.pixels(), (1600, 2560));
+
+        let mut design = CoverDesign::new("The Long Way Round", "A. Writer");
+        design.background_color = Some(Rgb(240, 235, 220));
+        let cover = compose_cover(&design, TrimSize::Print5x8, None).unwrap();
+        assert_eq!((cover.width, cover.height), (1538, 2475));
+        assert_eq!(cover.dpi, 300);
+
+        // JFIF density: units = dots per inch, then 300 x 300
+        assert_eq!(&cover.data[13..18], &[1, 1, 44, 1, 44]);
+
+        // Dark text was drawn on the light background around the title
+        let decoded = image::load_from_memory(&cover.data).unwrap().to_rgb8();
+        let title_row = (0.28 * cover.height as f32) as u32;
+        let darkest = (0..cover.width)
+            .flat_map(|x| (title_row - 40..title_row + 40).map(move |y| (x, y)))
+            .map(|(x, y)| decoded.get_pixel(x, y).0[0])
+            .min()
+            .unwrap();
+        assert!(darkest < 80);
+    }
+}
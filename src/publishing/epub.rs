@@ -0,0 +1,540 @@
+//! Minimal ePub Writer
+//!
+//! Packages a `PublishedDocument` as an EPUB 3 file (with an EPUB 2 NCX for
+//! older readers). Each section becomes one XHTML chapter, preceded by a
+//! cover page when the document has a cover image; subsections are nested
+//! under their section in the table of contents. Attachments get a final
+//! page; images are packaged in the book and linked from it, other files are
+//! listed by name since readers cannot open them.
+
+use std::io::{Cursor, Write};
+
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::{escape_xml, PublishedAttachment, PublishedDocument, PublishedTable};
+use crate::error::{AppError, AppResult};
+
+/// Stylesheet shipped with every generated book
+const DEFAULT_CSS: &str = "body { font-family: serif; line-height: 1.5; margin: 0 5%; }\n\
+h1 { text-align: center; margin: 2em 0 1em; }\n\
+h2 { margin: 1.5em 0 0.75em; }\n\
+p { text-indent: 1.5em; margin: 0; }\n\
+p.first, p.note { text-indent: 0; }\n\
+p.watermark { font-size: 0.75em; color: #777; text-align: center; margin-top: 2em; }\n\
+span.anchor { font-size: 0.7em; color: #999; margin-right: 0.4em; }\n\
+div.cover { text-align: center; margin: 0; }\n\
+div.cover img { max-width: 100%; max-height: 100%; }\n\
+div.figure { text-align: center; margin: 1em 0; }\n\
+div.figure img { max-width: 100%; max-height: 50vh; }\n\
+table { border-collapse: collapse; margin: 1em 0; font-size: 0.85em; }\n\
+th, td { border: 1px solid #ccc; padding: 0.2em 0.4em; text-align: left; }\n";
+
+/// Attachment types reading systems must support (EPUB 3 core media types)
+const EMBEDDABLE_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/svg+xml",
+];
+
+/// Serialize a document to ePub bytes
+pub fn render_epub(document: &PublishedDocument) -> AppResult<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype entry must come first and be stored uncompressed
+    zip.start_file("mimetype", stored).map_err(zip_error)?;
+    zip.write_all(b"application/epub+zip")?;
+
+    zip.start_file("META-INF/container.xml", deflated)
+        .map_err(zip_error)?;
+    zip.write_all(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#,
+    )?;
+
+    zip.start_file("OEBPS/styles/main.css", deflated)
+        .map_err(zip_error)?;
+    zip.write_all(DEFAULT_CSS.as_bytes())?;
+    if let Some(theme) = &document.theme {
+        zip.write_all(
+            format!(
+                "h1, h2 {{ color: {}; }}\nspan.anchor {{ color: {}; }}\n",
+                theme.heading.hex(),
+                theme.accent.hex()
+            )
+            .as_bytes(),
+        )?;
+    }
+
+    if let Some(cover) = &document.cover_image {
+        zip.start_file("OEBPS/images/cover.jpg", stored)
+            .map_err(zip_error)?;
+        zip.write_all(cover)?;
+        zip.start_file("OEBPS/xhtml/cover.xhtml", deflated)
+            .map_err(zip_error)?;
+        zip.write_all(cover_xhtml(document).as_bytes())?;
+    }
+
+    for (index, section) in document.sections.iter().enumerate() {
+        zip.start_file(format!("OEBPS/xhtml/chapter_{}.xhtml", index + 1), deflated)
+            .map_err(zip_error)?;
+        zip.write_all(chapter_xhtml(document, index).as_bytes())?;
+        for (image_index, image) in section.images.iter().enumerate() {
+            if let Some(data) = embedded_data(image) {
+                zip.start_file(
+                    format!("OEBPS/{}", section_image_href(index, image_index, image)),
+                    deflated,
+                )
+                .map_err(zip_error)?;
+                zip.write_all(data)?;
+            }
+        }
+    }
+
+    if !document.attachments.is_empty() {
+        for (index, attachment) in document.attachments.iter().enumerate() {
+            if let Some(data) = embedded_data(attachment) {
+                zip.start_file(
+                    format!("OEBPS/{}", attachment_href(index, attachment)),
+                    deflated,
+                )
+                .map_err(zip_error)?;
+                zip.write_all(data)?;
+            }
+        }
+        zip.start_file("OEBPS/xhtml/attachments.xhtml", deflated)
+            .map_err(zip_error)?;
+        zip.write_all(attachments_xhtml(document).as_bytes())?;
+    }
+
+    zip.start_file("OEBPS/nav.xhtml", deflated)
+        .map_err(zip_error)?;
+    zip.write_all(nav_xhtml(document).as_bytes())?;
+
+    zip.start_file("OEBPS/toc.ncx", deflated)
+        .map_err(zip_error)?;
+    zip.write_all(toc_ncx(document).as_bytes())?;
+
+    zip.start_file("OEBPS/content.opf", deflated)
+        .map_err(zip_error)?;
+    zip.write_all(content_opf(document).as_bytes())?;
+
+    let cursor = zip.finish().map_err(zip_error)?;
+    Ok(cursor.into_inner())
+}
+
+fn chapter_xhtml(document: &PublishedDocument, index: usize) -> String {
+    let section = &document.sections[index];
+    let heading = if section.subsection { "h2" } else { "h1" };
+    let mut body = format!("<{heading}>{}</{heading}>\n", escape_xml(&section.title));
+
+    for (image_index, image) in section.images.iter().enumerate() {
+        if embedded_data(image).is_some() {
+            body.push_str(&format!(
+                "<div class=\"figure\"><img src=\"../{}\" alt=\"{}\"/></div>\n",
+                section_image_href(index, image_index, image),
+                escape_xml(&image.label())
+            ));
+        }
+    }
+
+    for (i, paragraph) in section.paragraphs.iter().enumerate() {
+        let class = if i == 0 { " class=\"first\"" } else { "" };
+        let anchor = section
+            .anchors
+            .get(i)
+            .map(|a| {
+                format!(
+                    "<span class=\"anchor\" id=\"{}\">[{}]</span>",
+                    escape_xml(&anchor_id(a)),
+                    escape_xml(a)
+                )
+            })
+            .unwrap_or_default();
+        body.push_str(&format!(
+            "<p{}>{}{}</p>\n",
+            class,
+            anchor,
+            escape_xml(paragraph)
+        ));
+    }
+
+    if let Some(table) = &section.table {
+        body.push_str(&table_xhtml(table));
+    }
+
+    let links: Vec<String> = section
+        .see_also
+        .iter()
+        .filter_map(|&target| {
+            document.sections.get(target).map(|s| {
+                format!(
+                    "<a href=\"chapter_{}.xhtml\">{}</a>",
+                    target + 1,
+                    escape_xml(&s.title)
+                )
+            })
+        })
+        .collect();
+    if !links.is_empty() {
+        body.push_str(&format!(
+            "<p class=\"note\"><em>See also:</em> {}</p>\n",
+            links.join(", ")
+        ));
+    }
+
+    if let Some(watermark) = &document.watermark {
+        body.push_str(&format!(
+            "<p class=\"watermark\">{}</p>\n",
+            escape_xml(watermark)
+        ));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}">
+<head>
+  <title>{title}</title>
+  <link rel="stylesheet" type="text/css" href="../styles/main.css"/>
+</head>
+<body>
+{body}</body>
+</html>
+"#,
+        lang = escape_xml(&document.language),
+        title = escape_xml(&section.title),
+        body = body
+    )
+}
+
+fn table_xhtml(table: &PublishedTable) -> String {
+    let header: String = table
+        .columns
+        .iter()
+        .map(|column| format!("<th scope=\"col\">{}</th>", escape_xml(column)))
+        .collect();
+    let rows: String = table
+        .rows
+        .iter()
+        .map(|row| {
+            let cells: String = row
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| match i {
+                    0 => format!("<th scope=\"row\">{}</th>", escape_xml(cell)),
+                    _ => format!("<td>{}</td>", escape_xml(cell)),
+                })
+                .collect();
+            format!("<tr>{}</tr>\n", cells)
+        })
+        .collect();
+    format!(
+        "<table>\n<thead><tr>{}</tr></thead>\n<tbody>\n{}</tbody>\n</table>\n",
+        header, rows
+    )
+}
+
+fn cover_xhtml(document: &PublishedDocument) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}">
+<head>
+  <title>Cover</title>
+  <link rel="stylesheet" type="text/css" href="../styles/main.css"/>
+</head>
+<body epub:type="cover">
+<div class="cover"><img src="../images/cover.jpg" alt="{title}"/></div>
+</body>
+</html>
+"#,
+        lang = escape_xml(&document.language),
+        title = escape_xml(&document.title)
+    )
+}
+
+fn attachments_xhtml(document: &PublishedDocument) -> String {
+    let items: String = document
+        .attachments
+        .iter()
+        .enumerate()
+        .map(|(index, attachment)| {
+            let label = escape_xml(&attachment.label());
+            match embedded_data(attachment) {
+                Some(_) => format!(
+                    "  <li><a href=\"../{}\">{}</a></li>\n",
+                    attachment_href(index, attachment),
+                    label
+                ),
+                None => format!("  <li>{}</li>\n", label),
+            }
+        })
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="{lang}">
+<head>
+  <title>Attachments</title>
+  <link rel="stylesheet" type="text/css" href="../styles/main.css"/>
+</head>
+<body>
+<h1>Attachments</h1>
+<ul>
+{items}</ul>
+</body>
+</html>
+"#,
+        lang = escape_xml(&document.language),
+        items = items
+    )
+}
+
+/// Content to package for an attachment, if it has any and readers can show it
+fn embedded_data(attachment: &PublishedAttachment) -> Option<&[u8]> {
+    attachment
+        .data
+        .as_deref()
+        .filter(|_| EMBEDDABLE_TYPES.contains(&attachment.mime_type.as_str()))
+}
+
+/// Path inside OEBPS; prefixed with the index so equal names cannot clash
+fn attachment_href(index: usize, attachment: &PublishedAttachment) -> String {
+    format!(
+        "attachments/{}_{}",
+        index + 1,
+        safe_file_name(&attachment.file_name)
+    )
+}
+
+/// Path inside OEBPS of an image shown in a section
+fn section_image_href(section: usize, index: usize, image: &PublishedAttachment) -> String {
+    format!(
+        "images/section_{}_{}_{}",
+        section + 1,
+        index + 1,
+        safe_file_name(&image.file_name)
+    )
+}
+
+fn safe_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn nav_xhtml(document: &PublishedDocument) -> String {
+    let mut items = String::new();
+    let mut nested = false;
+    for (i, section) in document.sections.iter().enumerate() {
+        let link = format!(
+            "<a href=\"xhtml/chapter_{}.xhtml\">{}</a>",
+            i + 1,
+            escape_xml(&section.title)
+        );
+        // A subsection opens a list inside the previous item, which stays
+        // open until the next top-level section
+        if section.subsection && i > 0 {
+            if !nested {
+                items.push_str("\n        <ol>\n");
+                nested = true;
+            }
+            items.push_str(&format!("          <li>{}</li>\n", link));
+            continue;
+        }
+        if i > 0 {
+            items.push_str(if nested {
+                "        </ol>\n      </li>\n"
+            } else {
+                "</li>\n"
+            });
+        }
+        nested = false;
+        items.push_str(&format!("      <li>{}", link));
+    }
+    if !document.sections.is_empty() {
+        items.push_str(if nested {
+            "        </ol>\n      </li>\n"
+        } else {
+            "</li>\n"
+        });
+    }
+    if !document.attachments.is_empty() {
+        items.push_str("      <li><a href=\"xhtml/attachments.xhtml\">Attachments</a></li>\n");
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head><title>Table of Contents</title></head>
+<body>
+  <nav epub:type="toc" id="toc">
+    <h1>Table of Contents</h1>
+    <ol>
+{}    </ol>
+  </nav>
+</body>
+</html>
+"#,
+        items
+    )
+}
+
+fn toc_ncx(document: &PublishedDocument) -> String {
+    let mut points: String = document
+        .sections
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            format!(
+                "    <navPoint id=\"nav_{n}\" playOrder=\"{n}\"><navLabel><text>{}</text></navLabel><content src=\"xhtml/chapter_{n}.xhtml\"/></navPoint>\n",
+                escape_xml(&s.title),
+                n = i + 1
+            )
+        })
+        .collect();
+    if !document.attachments.is_empty() {
+        points.push_str(&format!(
+            "    <navPoint id=\"nav_attachments\" playOrder=\"{n}\"><navLabel><text>Attachments</text></navLabel><content src=\"xhtml/attachments.xhtml\"/></navPoint>\n",
+            n = document.sections.len() + 1
+        ));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <head><meta name="dtb:uid" content="{}"/></head>
+  <docTitle><text>{}</text></docTitle>
+  <navMap>
+{}  </navMap>
+</ncx>
+"#,
+        escape_xml(&document.identifier),
+        escape_xml(&document.title),
+        points
+    )
+}
+
+fn content_opf(document: &PublishedDocument) -> String {
+    let mut manifest = String::from(
+        "    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
+         \x20   <item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\"/>\n\
+         \x20   <item id=\"css\" href=\"styles/main.css\" media-type=\"text/css\"/>\n",
+    );
+    let mut spine = String::new();
+    if document.cover_image.is_some() {
+        manifest.push_str(
+            "    <item id=\"cover-image\" href=\"images/cover.jpg\" media-type=\"image/jpeg\" properties=\"cover-image\"/>\n\
+             \x20   <item id=\"cover\" href=\"xhtml/cover.xhtml\" media-type=\"application/xhtml+xml\"/>\n",
+        );
+        spine.push_str("    <itemref idref=\"cover\"/>\n");
+    }
+    for i in 1..=document.sections.len() {
+        manifest.push_str(&format!(
+            "    <item id=\"chapter_{i}\" href=\"xhtml/chapter_{i}.xhtml\" media-type=\"application/xhtml+xml\"/>\n"
+        ));
+        spine.push_str(&format!("    <itemref idref=\"chapter_{i}\"/>\n"));
+    }
+    for (index, section) in document.sections.iter().enumerate() {
+        for (image_index, image) in section.images.iter().enumerate() {
+            if embedded_data(image).is_some() {
+                manifest.push_str(&format!(
+                    "    <item id=\"image_{}_{}\" href=\"{}\" media-type=\"{}\"/>\n",
+                    index + 1,
+                    image_index + 1,
+                    escape_xml(&section_image_href(index, image_index, image)),
+                    escape_xml(&image.mime_type)
+                ));
+            }
+        }
+    }
+    if !document.attachments.is_empty() {
+        manifest.push_str(
+            "    <item id=\"attachments\" href=\"xhtml/attachments.xhtml\" media-type=\"application/xhtml+xml\"/>\n",
+        );
+        spine.push_str("    <itemref idref=\"attachments\"/>\n");
+        for (index, attachment) in document.attachments.iter().enumerate() {
+            if embedded_data(attachment).is_some() {
+                manifest.push_str(&format!(
+                    "    <item id=\"attachment_{}\" href=\"{}\" media-type=\"{}\"/>\n",
+                    index + 1,
+                    escape_xml(&attachment_href(index, attachment)),
+                    escape_xml(&attachment.mime_type)
+                ));
+            }
+        }
+    }
+
+    let author = document
+        .author
+        .as_deref()
+        .map(|a| format!("    <dc:creator>{}</dc:creator>\n", escape_xml(a)))
+        .unwrap_or_default();
+    let rights = document
+        .watermark
+        .as_deref()
+        .map(|w| format!("    <dc:rights>{}</dc:rights>\n", escape_xml(w)))
+        .unwrap_or_default();
+    // EPUB 2 readers find the cover through this instead of the property
+    let cover = if document.cover_image.is_some() {
+        "    <meta name=\"cover\" content=\"cover-image\"/>\n"
+    } else {
+        ""
+    };
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">{id}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:language>{lang}</dc:language>
+{author}{rights}{cover}    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+{manifest}  </manifest>
+  <spine toc="ncx">
+{spine}  </spine>
+</package>
+"#,
+        id = escape_xml(&document.identifier),
+        title = escape_xml(&document.title),
+        lang = escape_xml(&document.language),
+        author = author,
+        rights = rights,
+        cover = cover,
+        modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+        manifest = manifest,
+        spine = spine
+    )
+}
+
+/// XML ids must not start with a digit or contain punctuation like '.'
+fn anchor_id(anchor: &str) -> String {
+    let cleaned: String = anchor
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("p_{}", cleaned)
+}
+
+fn zip_error(error: zip::result::ZipError) -> AppError {
+    AppError::Io(format!("Failed to write ePub archive: {}", error))
+}
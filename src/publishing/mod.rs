@@ -0,0 +1,378 @@
+//! Publishing Module
+//!
+//! Lightweight document writers used by services that need to hand a file
+//! to someone outside the app: reader packets, reports, certificates and
+//! story bibles.
+//! Content is described once as a `PublishedDocument` and rendered to PDF
+//! or ePub, or to a standalone HTML page for posting on the web or sharing
+//! with a reader.
+
+pub mod cover;
+pub mod epub;
+pub mod palette;
+pub mod pdf;
+pub mod redline;
+pub mod share;
+
+use serde::{Deserialize, Serialize};
+
+use crate::security::secrets_scanner::{
+    redact, scan_text, PolicyAction, ScanContext, ScanOutcome, SecretsBlocked, SecretsScanner,
+};
+
+pub use cover::{compose_cover, CoverDesign, CoverImage, CoverText, TrimSize};
+pub use epub::render_epub;
+pub use palette::{extract_palette, ColorPalette, ThemeColors};
+pub use pdf::{PdfBuilder, PdfColor, PdfFont, PdfTextStyle};
+pub use redline::{RedlineDocument, RedlineSection, RedlineSummary};
+pub use share::{render_share_bundle, ShareBundle, ShareError, ShareOptions};
+
+/// Tallest an image under a section title gets in PDFs, in points
+const SECTION_IMAGE_HEIGHT: f32 = 240.0;
+
+/// Output format for published documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PublishFormat {
+    Pdf,
+    Epub,
+}
+
+impl PublishFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PublishFormat::Pdf => "pdf",
+            PublishFormat::Epub => "epub",
+        }
+    }
+}
+
+/// A chapter or section of a published document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishedSection {
+    pub title: String,
+    pub paragraphs: Vec<String>,
+    /// Optional per-paragraph anchor labels, printed before each paragraph
+    pub anchors: Vec<String>,
+    /// Belongs to the previous top-level section: kept on its page in PDFs
+    /// and listed under it in the ePub table of contents
+    #[serde(default)]
+    pub subsection: bool,
+    /// Images shown below the title
+    #[serde(default)]
+    pub images: Vec<PublishedAttachment>,
+    /// Indexes of related sections, printed as "See also" links
+    #[serde(default)]
+    pub see_also: Vec<usize>,
+    /// Table printed after the paragraphs
+    #[serde(default)]
+    pub table: Option<PublishedTable>,
+}
+
+/// A grid of cells, such as a relationship matrix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishedTable {
+    /// Column headings; the first labels the row headings
+    pub columns: Vec<String>,
+    /// One cell per column, starting with the row heading
+    pub rows: Vec<Vec<String>>,
+}
+
+impl PublishedTable {
+    /// A row as one line of text for formats without tables, e.g.
+    /// "Mara - Tobin: sister; Wren: rival", leaving out empty cells
+    pub fn row_text(&self, row: &[String]) -> String {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&self.columns)
+            .skip(1)
+            .filter(|(cell, _)| !cell.trim().is_empty())
+            .map(|(cell, column)| format!("{}: {}", column, cell))
+            .collect();
+        let heading = row.first().map(String::as_str).unwrap_or_default();
+        if cells.is_empty() {
+            format!("{} - none", heading)
+        } else {
+            format!("{} - {}", heading, cells.join("; "))
+        }
+    }
+}
+
+impl PublishedSection {
+    pub fn new(title: impl Into<String>, paragraphs: Vec<String>) -> Self {
+        Self {
+            title: title.into(),
+            paragraphs,
+            anchors: Vec::new(),
+            subsection: false,
+            images: Vec::new(),
+            see_also: Vec::new(),
+            table: None,
+        }
+    }
+
+    /// A section from plain document text, one paragraph per blank-line
+    /// separated block
+    pub fn from_text(title: impl Into<String>, text: &str) -> Self {
+        let paragraphs = text
+            .split("\n\n")
+            .map(|block| block.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|paragraph| !paragraph.is_empty())
+            .collect();
+        Self::new(title, paragraphs)
+    }
+}
+
+/// A file listed at the end of a published document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishedAttachment {
+    pub file_name: String,
+    pub mime_type: String,
+    pub description: Option<String>,
+    /// Content to package with the document; listed by name only when absent
+    /// or when the format cannot carry it
+    pub data: Option<Vec<u8>>,
+}
+
+/// Format-independent description of a document to publish
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishedDocument {
+    pub identifier: String,
+    pub title: String,
+    pub author: Option<String>,
+    pub language: String,
+    /// Text stamped on every page/chapter (reader IDs, "confidential", ...)
+    pub watermark: Option<String>,
+    pub sections: Vec<PublishedSection>,
+    #[serde(default)]
+    pub attachments: Vec<PublishedAttachment>,
+    /// Heading and accent colours, usually picked from the cover
+    #[serde(default)]
+    pub theme: Option<ThemeColors>,
+    /// Front cover as a JPEG, see `CoverDesign`
+    #[serde(default)]
+    pub cover_image: Option<Vec<u8>>,
+}
+
+impl PublishedDocument {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            identifier: format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+            title: title.into(),
+            author: None,
+            language: "en".to_string(),
+            watermark: None,
+            sections: Vec::new(),
+            attachments: Vec::new(),
+            theme: None,
+            cover_image: None,
+        }
+    }
+
+    /// Render to the requested format
+    pub fn render(&self, format: PublishFormat) -> crate::error::AppResult<Vec<u8>> {
+        match format {
+            PublishFormat::Pdf => Ok(self.render_pdf()),
+            PublishFormat::Epub => render_epub(self),
+        }
+    }
+
+    /// All text that ends up in the rendered file
+    pub fn plain_text(&self) -> String {
+        let mut parts = vec![self.title.clone()];
+        parts.extend(self.author.clone());
+        for section in &self.sections {
+            parts.push(section.title.clone());
+            parts.extend(section.paragraphs.iter().cloned());
+            if let Some(table) = &section.table {
+                parts.extend(table.rows.iter().map(|row| table.row_text(row)));
+            }
+        }
+        parts.extend(self.attachments.iter().map(PublishedAttachment::label));
+        parts.join("\n\n")
+    }
+
+    /// Scan the document for secrets before export, redacting paragraphs
+    /// in place when the export policy says so
+    pub fn screen_secrets(
+        &mut self,
+        scanner: &SecretsScanner,
+    ) -> Result<ScanOutcome, SecretsBlocked> {
+        let outcome = scanner.check(&self.plain_text(), ScanContext::Export)?;
+        if outcome.action == PolicyAction::Redact && !outcome.findings.is_empty() {
+            for section in &mut self.sections {
+                let cells = section
+                    .table
+                    .iter_mut()
+                    .flat_map(|table| table.rows.iter_mut().flatten());
+                for paragraph in section.paragraphs.iter_mut().chain(cells) {
+                    let findings = scan_text(paragraph);
+                    if !findings.is_empty() {
+                        *paragraph = redact(paragraph, &findings);
+                    }
+                }
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Render as a PDF, one section per page run
+    pub fn render_pdf(&self) -> Vec<u8> {
+        let mut builder = PdfBuilder::new().title(self.title.clone());
+        if let Some(author) = &self.author {
+            builder = builder.author(author.clone());
+        }
+        if let Some(watermark) = &self.watermark {
+            builder = builder
+                .footer(watermark.clone())
+                .watermark(watermark.clone());
+        }
+        if let Some(theme) = &self.theme {
+            builder = builder.heading_color(theme.heading.into());
+        }
+
+        builder.heading(1, &self.title);
+        for section in &self.sections {
+            if section.subsection {
+                builder.heading(2, &section.title);
+            } else {
+                builder.page_break().heading(1, &section.title);
+            }
+            for image in &section.images {
+                if let Some(data) = &image.data {
+                    builder.image(data, SECTION_IMAGE_HEIGHT);
+                }
+            }
+            for (i, paragraph) in section.paragraphs.iter().enumerate() {
+                match section.anchors.get(i) {
+                    Some(anchor) => builder.paragraph(&format!("[{}] {}", anchor, paragraph)),
+                    None => builder.paragraph(paragraph),
+                };
+            }
+            if let Some(table) = &section.table {
+                for row in &table.rows {
+                    builder.paragraph(&table.row_text(row));
+                }
+            }
+            let see_also = self.see_also_titles(section);
+            if !see_also.is_empty() {
+                builder.styled_paragraph(
+                    &format!("See also: {}", see_also.join(", ")),
+                    PdfTextStyle {
+                        font: PdfFont::Italic,
+                        size: 10.0,
+                        color: PdfColor::GRAY,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+        if !self.attachments.is_empty() {
+            builder.page_break().heading(1, "Attachments");
+            for attachment in &self.attachments {
+                builder.paragraph(&attachment.label());
+            }
+        }
+        builder.build()
+    }
+
+    /// Render as a standalone HTML page, e.g. a serial chapter ready to
+    /// paste into or upload to a publishing platform. Images and
+    /// attachments are left out, as are headings of untitled sections.
+    pub fn render_html(&self) -> String {
+        let author = self
+            .author
+            .as_ref()
+            .map(|author| {
+                format!(
+                    "<meta name=\"author\" content=\"{}\">\n",
+                    escape_xml(author)
+                )
+            })
+            .unwrap_or_default();
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n{}<title>{}</title>\n</head>\n<body>\n<h1>{}</h1>\n{}</body>\n</html>\n",
+            escape_xml(&self.language),
+            author,
+            escape_xml(&self.title),
+            escape_xml(&self.title),
+            self.html_body()
+        )
+    }
+
+    /// The sections and watermark as HTML, without the page around them
+    fn html_body(&self) -> String {
+        let mut body = String::new();
+        for section in &self.sections {
+            let level = if section.subsection { 3 } else { 2 };
+            if !section.title.is_empty() {
+                body.push_str(&format!(
+                    "<h{}>{}</h{}>\n",
+                    level,
+                    escape_xml(&section.title),
+                    level
+                ));
+            }
+            for paragraph in &section.paragraphs {
+                body.push_str(&format!("<p>{}</p>\n", escape_xml(paragraph)));
+            }
+            if let Some(table) = &section.table {
+                body.push_str("<table>\n<tr>");
+                for column in &table.columns {
+                    body.push_str(&format!("<th>{}</th>", escape_xml(column)));
+                }
+                body.push_str("</tr>\n");
+                for row in &table.rows {
+                    body.push_str("<tr>");
+                    for cell in row {
+                        body.push_str(&format!("<td>{}</td>", escape_xml(cell)));
+                    }
+                    body.push_str("</tr>\n");
+                }
+                body.push_str("</table>\n");
+            }
+        }
+        if let Some(watermark) = &self.watermark {
+            body.push_str(&format!("<footer>{}</footer>\n", escape_xml(watermark)));
+        }
+        body
+    }
+
+    /// Titles of the sections a section refers to, skipping bad indexes
+    pub fn see_also_titles(&self, section: &PublishedSection) -> Vec<&str> {
+        section
+            .see_also
+            .iter()
+            .filter_map(|&index| self.sections.get(index))
+            .map(|target| target.title.as_str())
+            .collect()
+    }
+}
+
+impl PublishedAttachment {
+    /// "name - description", as printed in attachment lists
+    pub fn label(&self) -> String {
+        match &self.description {
+            Some(description) if !description.is_empty() => {
+                format!("{} - {}", self.file_name, description)
+            }
+            _ => self.file_name.clone(),
+        }
+    }
+}
+
+/// Split document text into paragraphs on blank lines
+pub fn split_paragraphs(text: &str) -> Vec<String> {
+    text.split("\n\n")
+        .map(|p| p.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// Escape text for XML/XHTML output
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
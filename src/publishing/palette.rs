@@ -0,0 +1,333 @@
+//! Cover Palettes
+//!
+//! Dominant colours of a cover image (median cut over a downscaled copy) and
+//! a theme derived from them: the cover background and its readable text
+//! colour, plus heading and accent colours dark enough to read on a white
+//! page. Publishing uses the theme so headings match the cover.
+
+use image::GenericImageView;
+use serde::{Deserialize, Serialize};
+
+/// Longest edge the image is reduced to before sampling
+const SAMPLE_EDGE: u32 = 96;
+/// Pixels more transparent than this are ignored
+const MIN_ALPHA: u8 = 128;
+/// WCAG contrast for body-size text
+const TEXT_CONTRAST: f32 = 4.5;
+/// WCAG contrast for large text and decorations
+const ACCENT_CONTRAST: f32 = 3.0;
+
+/// An sRGB colour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+
+    /// `#rrggbb`
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+
+    pub fn parse_hex(value: &str) -> Option<Self> {
+        let hex = value.strip_prefix('#')?;
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        Some(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    /// WCAG relative luminance
+    pub fn luminance(&self) -> f32 {
+        let linear = |c: u8| {
+            let c = c as f32 / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(self.0) + 0.7152 * linear(self.1) + 0.0722 * linear(self.2)
+    }
+
+    /// WCAG contrast ratio, from 1 to 21
+    pub fn contrast(&self, other: &Rgb) -> f32 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// (hue in degrees, saturation, lightness)
+    fn to_hsl(self) -> (f32, f32, f32) {
+        let [r, g, b] = [self.0, self.1, self.2].map(|c| c as f32 / 255.0);
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let lightness = (max + min) / 2.0;
+        let delta = max - min;
+        if delta == 0.0 {
+            return (0.0, 0.0, lightness);
+        }
+        let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+        let hue = if max == r {
+            60.0 * (((g - b) / delta).rem_euclid(6.0))
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        (hue, saturation, lightness)
+    }
+
+    fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        let x = chroma * (1.0 - ((hue / 60.0).rem_euclid(2.0) - 1.0).abs());
+        let m = lightness - chroma / 2.0;
+        let (r, g, b) = match (hue.rem_euclid(360.0) / 60.0) as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let channel = |c: f32| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Rgb(channel(r), channel(g), channel(b))
+    }
+
+    pub fn saturation(&self) -> f32 {
+        self.to_hsl().1
+    }
+
+    /// Same hue on the opposite side of the colour wheel
+    pub fn complement(&self) -> Self {
+        let (hue, saturation, lightness) = self.to_hsl();
+        Self::from_hsl(hue + 180.0, saturation, lightness)
+    }
+
+    /// Darken (keeping hue and saturation) until the contrast against
+    /// `background` reaches `ratio`
+    pub fn darken_to_contrast(&self, background: &Rgb, ratio: f32) -> Self {
+        let (hue, saturation, mut lightness) = self.to_hsl();
+        let mut color = *self;
+        while color.contrast(background) < ratio && lightness > 0.0 {
+            lightness = (lightness - 0.02).max(0.0);
+            color = Self::from_hsl(hue, saturation, lightness);
+        }
+        color
+    }
+}
+
+impl From<Rgb> for super::PdfColor {
+    fn from(color: Rgb) -> Self {
+        super::PdfColor(
+            color.0 as f32 / 255.0,
+            color.1 as f32 / 255.0,
+            color.2 as f32 / 255.0,
+        )
+    }
+}
+
+/// One dominant colour of an image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaletteColor {
+    pub color: Rgb,
+    pub hex: String,
+    /// Fraction of the sampled pixels closest to this colour
+    pub share: f32,
+}
+
+/// Colours for documents styled after a cover
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThemeColors {
+    /// Dominant cover colour, for cover pages
+    pub background: Rgb,
+    /// Black or white, whichever reads better on `background`
+    pub text: Rgb,
+    /// Heading colour readable on a white page
+    pub heading: Rgb,
+    /// Complement of the heading for rules, anchors and highlights
+    pub accent: Rgb,
+}
+
+/// Dominant colours of an image, most common first, and the theme derived
+/// from them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorPalette {
+    pub colors: Vec<PaletteColor>,
+    pub theme: ThemeColors,
+}
+
+/// Extract up to `max_colors` dominant colours from encoded image bytes
+pub fn extract_palette(bytes: &[u8], max_colors: usize) -> Result<ColorPalette, image::ImageError> {
+    let image = image::load_from_memory(bytes)?;
+    let sample = if image.width() > SAMPLE_EDGE || image.height() > SAMPLE_EDGE {
+        image.thumbnail(SAMPLE_EDGE, SAMPLE_EDGE)
+    } else {
+        image
+    };
+    let pixels: Vec<Rgb> = sample
+        .pixels()
+        .filter(|(_, _, p)| p.0[3] >= MIN_ALPHA)
+        .map(|(_, _, p)| Rgb(p.0[0], p.0[1], p.0[2]))
+        .collect();
+    Ok(palette_from_pixels(pixels, max_colors))
+}
+
+/// Median cut: split the box with the widest channel range at its median
+/// until there are `max_colors` boxes. The box averages are then refined by
+/// assigning every pixel to the nearest one, so colours straddling a split
+/// are not reported twice.
+pub fn palette_from_pixels(pixels: Vec<Rgb>, max_colors: usize) -> ColorPalette {
+    let total = pixels.len().max(1) as f32;
+    let mut boxes = vec![pixels.clone()];
+    while boxes.len() < max_colors.max(1) {
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, pixels)| pixels.len() > 1)
+            .map(|(i, pixels)| {
+                let (channel, range) = widest_channel(pixels);
+                (i, channel, range)
+            })
+            .filter(|(_, _, range)| *range > 0)
+            .max_by_key(|(_, _, range)| *range)
+            .map(|(i, channel, _)| (i, channel))
+        else {
+            break;
+        };
+        let mut pixels = boxes.swap_remove(index);
+        pixels.sort_unstable_by_key(|p| channel_value(p, channel));
+        let upper = pixels.split_off(pixels.len() / 2);
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+    let centers: Vec<Rgb> = boxes
+        .iter()
+        .filter(|pixels| !pixels.is_empty())
+        .map(|pixels| average(pixels))
+        .collect();
+
+    let mut clusters: Vec<Vec<Rgb>> = vec![Vec::new(); centers.len()];
+    for pixel in pixels {
+        if let Some(nearest) = (0..centers.len()).min_by_key(|&i| distance(&centers[i], &pixel)) {
+            clusters[nearest].push(pixel);
+        }
+    }
+    let mut colors: Vec<PaletteColor> = clusters
+        .into_iter()
+        .filter(|pixels| !pixels.is_empty())
+        .map(|pixels| {
+            let color = average(&pixels);
+            PaletteColor {
+                color,
+                hex: color.hex(),
+                share: pixels.len() as f32 / total,
+            }
+        })
+        .collect();
+    colors.sort_by(|a, b| b.share.total_cmp(&a.share));
+
+    let theme = theme_from(&colors);
+    ColorPalette { colors, theme }
+}
+
+/// Pick theme colours from a palette, most common colour first
+pub fn theme_from(colors: &[PaletteColor]) -> ThemeColors {
+    let background = colors.first().map(|c| c.color).unwrap_or(Rgb::WHITE);
+    let text = if background.contrast(&Rgb::WHITE) >= background.contrast(&Rgb::BLACK) {
+        Rgb::WHITE
+    } else {
+        Rgb::BLACK
+    };
+
+    // The most vivid colour that covers a noticeable part of the cover
+    let base = colors
+        .iter()
+        .filter(|c| c.share >= 0.05)
+        .max_by(|a, b| a.color.saturation().total_cmp(&b.color.saturation()))
+        .map(|c| c.color)
+        .unwrap_or(background);
+    let heading = base.darken_to_contrast(&Rgb::WHITE, TEXT_CONTRAST);
+    let accent = if base.saturation() < 0.1 {
+        // Greys have no meaningful complement
+        heading
+    } else {
+        base.complement()
+            .darken_to_contrast(&Rgb::WHITE, ACCENT_CONTRAST)
+    };
+
+    ThemeColors {
+        background,
+        text,
+        heading,
+        accent,
+    }
+}
+
+fn widest_channel(pixels: &[Rgb]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let values = pixels.iter().map(|p| channel_value(p, channel));
+            let (min, max) =
+                values.fold((u8::MAX, u8::MIN), |(min, max), v| (min.min(v), max.max(v)));
+            (channel, max.saturating_sub(min))
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+fn average(pixels: &[Rgb]) -> Rgb {
+    let count = pixels.len().max(1) as u64;
+    let sum = pixels.iter().fold([0u64; 3], |mut sum, p| {
+        sum[0] += p.0 as u64;
+        sum[1] += p.1 as u64;
+        sum[2] += p.2 as u64;
+        sum
+    });
+    Rgb(
+        (sum[0] / count) as u8,
+        (sum[1] / count) as u8,
+        (sum[2] / count) as u8,
+    )
+}
+
+/// Squared distance in RGB space
+fn distance(a: &Rgb, b: &Rgb) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn channel_value(pixel: &Rgb, channel: usize) -> u8 {
+    match channel {
+        0 => pixel.0,
+        1 => pixel.1,
+        _ => pixel.2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_orders_by_share_and_theme_is_readable() {
+        let navy = Rgb(20, 30, 90);
+        let gold = Rgb(230, 180, 40);
+        let mut pixels = vec![navy; 700];
+        pixels.extend(vec![gold; 300]);
+
+        let palette = palette_from_pixels(pixels, 4);
+        assert_eq!(palette.colors.len(), 2);
+        assert_eq!(palette.colors[0].color, navy);
+        assert_eq!(palette.colors[0].hex, "#141e5a");
+        assert!((palette.colors[1].share - 0.3).abs() < 0.01);
+
+        let theme = palette.theme;
+        assert_eq!(theme.background, navy);
+        assert_eq!(theme.text, Rgb::WHITE);
+        assert!(theme.heading.contrast(&Rgb::WHITE) >= TEXT_CONTRAST);
+        assert!(theme.accent.contrast(&Rgb::WHITE) >= ACCENT_CONTRAST);
+        assert_eq!(Rgb::parse_hex("#141e5a"), Some(navy));
+    }
+}
@@ -0,0 +1,741 @@
+//! Minimal PDF Writer
+//!
+//! Produces simple text PDFs (headings, paragraphs, colored lines, footers,
+//! diagonal watermarks and JPEG images) using the standard Helvetica fonts,
+//! so reports and reader packets can be rendered without a native PDF library.
+
+use std::fmt::Write as _;
+
+use image::codecs::jpeg::JpegEncoder;
+
+const PAGE_WIDTH: f32 = 612.0;
+const PAGE_HEIGHT: f32 = 792.0;
+const MARGIN: f32 = 72.0;
+const FOOTER_Y: f32 = 36.0;
+/// Larger images are scaled down before embedding
+const MAX_IMAGE_PIXELS: u32 = 1600;
+
+/// Font used for a block of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdfFont {
+    Regular,
+    Bold,
+    Italic,
+    Mono,
+}
+
+impl PdfFont {
+    fn resource(&self) -> &'static str {
+        match self {
+            PdfFont::Regular => "F1",
+            PdfFont::Bold => "F2",
+            PdfFont::Italic => "F3",
+            PdfFont::Mono => "F4",
+        }
+    }
+
+    /// Average glyph width as a fraction of the font size
+    fn width_factor(&self) -> f32 {
+        match self {
+            PdfFont::Mono => 0.6,
+            PdfFont::Bold => 0.56,
+            _ => 0.5,
+        }
+    }
+}
+
+/// RGB color with components between 0.0 and 1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PdfColor(pub f32, pub f32, pub f32);
+
+impl PdfColor {
+    pub const BLACK: PdfColor = PdfColor(0.0, 0.0, 0.0);
+    pub const GRAY: PdfColor = PdfColor(0.45, 0.45, 0.45);
+    pub const RED: PdfColor = PdfColor(0.75, 0.1, 0.1);
+    pub const GREEN: PdfColor = PdfColor(0.1, 0.5, 0.15);
+}
+
+/// Styling for a block of text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PdfTextStyle {
+    pub font: PdfFont,
+    pub size: f32,
+    pub color: PdfColor,
+    pub strikethrough: bool,
+    pub underline: bool,
+}
+
+impl Default for PdfTextStyle {
+    fn default() -> Self {
+        Self {
+            font: PdfFont::Regular,
+            size: 11.0,
+            color: PdfColor::BLACK,
+            strikethrough: false,
+            underline: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Block {
+    Text {
+        text: String,
+        style: PdfTextStyle,
+    },
+    Runs(Vec<(String, PdfTextStyle)>),
+    Spacer(f32),
+    PageBreak,
+    /// An image from `PdfBuilder::images`, with its size on the page
+    Image {
+        index: usize,
+        width: f32,
+        height: f32,
+    },
+}
+
+/// A JPEG to embed, with its size in pixels
+#[derive(Debug, Clone)]
+struct PdfImage {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Default)]
+struct Page {
+    lines: Vec<Line>,
+    images: Vec<PlacedImage>,
+}
+
+impl Page {
+    fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.images.is_empty()
+    }
+}
+
+struct Line {
+    runs: Vec<Run>,
+    y: f32,
+}
+
+/// An image on a page, `y` being its bottom edge
+struct PlacedImage {
+    index: usize,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+/// Text in one style on a line, `x` points from the left margin
+struct Run {
+    text: String,
+    style: PdfTextStyle,
+    x: f32,
+}
+
+/// Builder for a simple flowing-text PDF document
+#[derive(Debug, Clone, Default)]
+pub struct PdfBuilder {
+    title: Option<String>,
+    author: Option<String>,
+    footer: Option<String>,
+    watermark: Option<String>,
+    heading_color: Option<PdfColor>,
+    blocks: Vec<Block>,
+    images: Vec<PdfImage>,
+}
+
+impl PdfBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the document title stored in the PDF info dictionary
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set the document author stored in the PDF info dictionary
+    pub fn author(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+
+    /// Footer printed on every page next to the page number
+    pub fn footer(mut self, footer: impl Into<String>) -> Self {
+        self.footer = Some(footer.into());
+        self
+    }
+
+    /// Light diagonal watermark printed behind every page
+    pub fn watermark(mut self, watermark: impl Into<String>) -> Self {
+        self.watermark = Some(watermark.into());
+        self
+    }
+
+    /// Colour for headings instead of black
+    pub fn heading_color(mut self, color: PdfColor) -> Self {
+        self.heading_color = Some(color);
+        self
+    }
+
+    /// Add a heading (level 1 is largest)
+    pub fn heading(&mut self, level: u8, text: &str) -> &mut Self {
+        let size = match level {
+            1 => 18.0,
+            2 => 14.0,
+            _ => 12.0,
+        };
+        self.blocks.push(Block::Spacer(size * 0.4));
+        self.blocks.push(Block::Text {
+            text: text.to_string(),
+            style: PdfTextStyle {
+                font: PdfFont::Bold,
+                size,
+                color: self.heading_color.unwrap_or(PdfColor::BLACK),
+                ..Default::default()
+            },
+        });
+        self.blocks.push(Block::Spacer(size * 0.3));
+        self
+    }
+
+    /// Add a paragraph in the default style
+    pub fn paragraph(&mut self, text: &str) -> &mut Self {
+        self.styled_paragraph(text, PdfTextStyle::default())
+    }
+
+    /// Add a paragraph with explicit styling
+    pub fn styled_paragraph(&mut self, text: &str, style: PdfTextStyle) -> &mut Self {
+        self.blocks.push(Block::Text {
+            text: text.to_string(),
+            style,
+        });
+        self.blocks.push(Block::Spacer(style.size * 0.5));
+        self
+    }
+
+    /// Add a paragraph mixing styles, e.g. struck-through and underlined
+    /// words in a redline; runs are joined with spaces
+    pub fn rich_paragraph(&mut self, runs: &[(String, PdfTextStyle)]) -> &mut Self {
+        let size = runs.iter().map(|(_, style)| style.size).fold(0.0, f32::max);
+        self.blocks.push(Block::Runs(runs.to_vec()));
+        self.blocks.push(Block::Spacer(size * 0.5));
+        self
+    }
+
+    /// Add vertical space in points
+    pub fn spacer(&mut self, points: f32) -> &mut Self {
+        self.blocks.push(Block::Spacer(points));
+        self
+    }
+
+    /// Start a new page
+    pub fn page_break(&mut self) -> &mut Self {
+        self.blocks.push(Block::PageBreak);
+        self
+    }
+
+    /// Add an image, scaled to fit the text width and `max_height` points.
+    /// Images are re-encoded as RGB JPEGs; data that can't be decoded is
+    /// skipped with a warning.
+    pub fn image(&mut self, data: &[u8], max_height: f32) -> &mut Self {
+        let decoded = match image::load_from_memory(data) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                log::warn!("Skipping image that could not be decoded: {}", e);
+                return self;
+            }
+        };
+        let decoded = if decoded.width().max(decoded.height()) > MAX_IMAGE_PIXELS {
+            decoded.thumbnail(MAX_IMAGE_PIXELS, MAX_IMAGE_PIXELS)
+        } else {
+            decoded
+        };
+        let rgb = decoded.to_rgb8();
+        let mut jpeg = Vec::new();
+        if let Err(e) = JpegEncoder::new_with_quality(&mut jpeg, 85).encode_image(&rgb) {
+            log::warn!("Skipping image that could not be encoded: {}", e);
+            return self;
+        }
+
+        let (width, height) = rgb.dimensions();
+        let scale = ((PAGE_WIDTH - MARGIN * 2.0) / width as f32)
+            .min(max_height / height as f32)
+            .min(1.0);
+        self.blocks.push(Block::Image {
+            index: self.images.len(),
+            width: width as f32 * scale,
+            height: height as f32 * scale,
+        });
+        self.blocks.push(Block::Spacer(6.0));
+        self.images.push(PdfImage {
+            data: jpeg,
+            width,
+            height,
+        });
+        self
+    }
+
+    /// Lay out the document and serialize it to PDF bytes
+    pub fn build(&self) -> Vec<u8> {
+        let pages = self.layout();
+        let page_count = pages.len();
+
+        // Object numbering: 1 catalog, 2 pages, 3-6 fonts, 7 info, then
+        // page/content pairs, then images
+        let first_page_obj = 8;
+        let first_image_obj = first_page_obj + page_count * 2;
+        let mut objects: Vec<String> = Vec::new();
+
+        let kids: Vec<String> = (0..page_count)
+            .map(|i| format!("{} 0 R", first_page_obj + i * 2))
+            .collect();
+        objects.push("<< /Type /Catalog /Pages 2 0 R >>".to_string());
+        objects.push(format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            page_count
+        ));
+        for base in [
+            "Helvetica",
+            "Helvetica-Bold",
+            "Helvetica-Oblique",
+            "Courier",
+        ] {
+            objects.push(format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                base
+            ));
+        }
+        objects.push(format!(
+            "<< /Title ({}) /Author ({}) /Producer (Herding Cats) >>",
+            escape_text(self.title.as_deref().unwrap_or("")),
+            escape_text(self.author.as_deref().unwrap_or(""))
+        ));
+
+        for (index, page) in pages.iter().enumerate() {
+            let content = self.render_page(page, index + 1, page_count);
+            let mut images: Vec<usize> = page.images.iter().map(|image| image.index).collect();
+            images.dedup();
+            let xobjects = if images.is_empty() {
+                String::new()
+            } else {
+                let refs: Vec<String> = images
+                    .iter()
+                    .map(|i| format!("/Im{} {} 0 R", i + 1, first_image_obj + i))
+                    .collect();
+                format!(" /XObject << {} >>", refs.join(" "))
+            };
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R /F4 6 0 R >>{} >> \
+                 /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                xobjects,
+                first_page_obj + index * 2 + 1
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                encode_win_ansi(&content).len(),
+                content
+            ));
+        }
+
+        let mut objects: Vec<Vec<u8>> = objects.iter().map(|o| encode_win_ansi(o)).collect();
+        for image in &self.images {
+            let mut object = format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} \
+                 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+                image.width,
+                image.height,
+                image.data.len()
+            )
+            .into_bytes();
+            object.extend_from_slice(&image.data);
+            object.extend_from_slice(b"\nendstream");
+            objects.push(object);
+        }
+
+        let mut out: Vec<u8> = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            out.extend_from_slice(object);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref_offset = out.len();
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(xref, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            xref,
+            "trailer\n<< /Size {} /Root 1 0 R /Info 7 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_offset
+        );
+        out.extend_from_slice(xref.as_bytes());
+        out
+    }
+
+    fn layout(&self) -> Vec<Page> {
+        let mut pages: Vec<Page> = vec![Page::default()];
+        let top = PAGE_HEIGHT - MARGIN;
+        let mut y = top;
+
+        for block in &self.blocks {
+            match block {
+                Block::PageBreak => {
+                    if pages.last().is_some_and(|p| !p.is_empty()) {
+                        pages.push(Page::default());
+                    }
+                    y = top;
+                }
+                Block::Spacer(points) => {
+                    if y < top {
+                        y -= points;
+                    }
+                }
+                Block::Text { text, style } => {
+                    let lines = wrap_text(text, *style).into_iter().map(|text| {
+                        vec![Run {
+                            text,
+                            style: *style,
+                            x: 0.0,
+                        }]
+                    });
+                    for runs in lines {
+                        place_line(&mut pages, &mut y, runs);
+                    }
+                }
+                Block::Runs(runs) => {
+                    for runs in wrap_runs(runs) {
+                        place_line(&mut pages, &mut y, runs);
+                    }
+                }
+                Block::Image {
+                    index,
+                    width,
+                    height,
+                } => {
+                    if y - height < MARGIN && pages.last().is_some_and(|p| !p.is_empty()) {
+                        pages.push(Page::default());
+                        y = top;
+                    }
+                    y -= height;
+                    if let Some(page) = pages.last_mut() {
+                        page.images.push(PlacedImage {
+                            index: *index,
+                            x: MARGIN,
+                            y,
+                            width: *width,
+                            height: *height,
+                        });
+                    }
+                }
+            }
+        }
+
+        pages
+    }
+
+    fn render_page(&self, page: &Page, page_number: usize, page_count: usize) -> String {
+        let mut content = String::new();
+
+        if let Some(watermark) = &self.watermark {
+            let _ = writeln!(
+                content,
+                "q 0.88 g BT /F2 40 Tf 0.7071 0.7071 -0.7071 0.7071 140 220 Tm ({}) Tj ET Q",
+                escape_text(watermark)
+            );
+        }
+
+        for image in &page.images {
+            let _ = writeln!(
+                content,
+                "q {} 0 0 {} {} {} cm /Im{} Do Q",
+                image.width,
+                image.height,
+                image.x,
+                image.y,
+                image.index + 1
+            );
+        }
+
+        for (line, run) in page
+            .lines
+            .iter()
+            .flat_map(|l| l.runs.iter().map(move |r| (l, r)))
+        {
+            let PdfColor(r, g, b) = run.style.color;
+            let x = MARGIN + run.x;
+            let _ = writeln!(
+                content,
+                "BT {} {} {} rg /{} {} Tf {} {} Td ({}) Tj ET",
+                r,
+                g,
+                b,
+                run.style.font.resource(),
+                run.style.size,
+                x,
+                line.y,
+                escape_text(&run.text)
+            );
+            let width = text_width(&run.text, run.style);
+            if run.style.strikethrough {
+                let mid = line.y + run.style.size * 0.3;
+                let _ = writeln!(
+                    content,
+                    "{} {} {} RG 0.8 w {} {} m {} {} l S",
+                    r,
+                    g,
+                    b,
+                    x,
+                    mid,
+                    x + width,
+                    mid
+                );
+            }
+            if run.style.underline {
+                let under = line.y - 1.5;
+                let _ = writeln!(
+                    content,
+                    "{} {} {} RG 0.6 w {} {} m {} {} l S",
+                    r,
+                    g,
+                    b,
+                    x,
+                    under,
+                    x + width,
+                    under
+                );
+            }
+        }
+
+        let footer = match &self.footer {
+            Some(text) => format!("{}    Page {} of {}", text, page_number, page_count),
+            None => format!("Page {} of {}", page_number, page_count),
+        };
+        let _ = write!(
+            content,
+            "BT 0.45 0.45 0.45 rg /F1 8 Tf {} {} Td ({}) Tj ET",
+            MARGIN,
+            FOOTER_Y,
+            escape_text(&footer)
+        );
+
+        content
+    }
+}
+
+/// Put a line below the previous one, starting a new page when it is full
+fn place_line(pages: &mut Vec<Page>, y: &mut f32, runs: Vec<Run>) {
+    let size = runs.iter().map(|r| r.style.size).fold(0.0, f32::max);
+    let line_height = size * 1.35;
+    if *y - line_height < MARGIN {
+        pages.push(Page::default());
+        *y = PAGE_HEIGHT - MARGIN;
+    }
+    *y -= line_height;
+    if let Some(page) = pages.last_mut() {
+        page.lines.push(Line { runs, y: *y });
+    }
+}
+
+/// Word-wrap styled runs to the printable width. Words of the same style
+/// share a run; the space before a word in a new style is left undecorated.
+fn wrap_runs(runs: &[(String, PdfTextStyle)]) -> Vec<Vec<Run>> {
+    let max_width = PAGE_WIDTH - MARGIN * 2.0;
+    let mut lines = Vec::new();
+    let mut line: Vec<Run> = Vec::new();
+    let mut x = 0.0;
+
+    for (text, style) in runs {
+        for word in text.split_whitespace() {
+            let width = text_width(word, *style);
+            let space = text_width(" ", *style);
+            if !line.is_empty() && x + space + width > max_width {
+                lines.push(std::mem::take(&mut line));
+                x = 0.0;
+            }
+            match line.last_mut() {
+                Some(run) if run.style == *style => {
+                    run.text.push(' ');
+                    run.text.push_str(word);
+                    x += space + width;
+                }
+                Some(_) => {
+                    line.push(Run {
+                        text: word.to_string(),
+                        style: *style,
+                        x: x + space,
+                    });
+                    x += space + width;
+                }
+                None => {
+                    line.push(Run {
+                        text: word.to_string(),
+                        style: *style,
+                        x: 0.0,
+                    });
+                    x = width;
+                }
+            }
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Word-wrap text to the printable width for a style
+fn wrap_text(text: &str, style: PdfTextStyle) -> Vec<String> {
+    let max_width = PAGE_WIDTH - MARGIN * 2.0;
+    let mut lines = Vec::new();
+
+    for raw_line in text.split('\n') {
+        let mut current = String::new();
+        for word in raw_line.split_whitespace() {
+            let candidate = if current.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", current, word)
+            };
+            if text_width(&candidate, style) > max_width && !current.is_empty() {
+                lines.push(std::mem::replace(&mut current, word.to_string()));
+            } else {
+                current = candidate;
+            }
+        }
+        lines.push(current);
+    }
+
+    lines
+}
+
+fn text_width(text: &str, style: PdfTextStyle) -> f32 {
+    text.chars().count() as f32 * style.size * style.font.width_factor()
+}
+
+/// Escape a string for use inside a PDF literal string
+fn escape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' => out.push_str("\\("),
+            ')' => out.push_str("\\)"),
+            '\\' => out.push_str("\\\\"),
+            '\r' | '\n' | '\t' => out.push(' '),
+            '\u{2018}' | '\u{2019}' => out.push('\''),
+            '\u{201C}' | '\u{201D}' => out.push('"'),
+            '\u{2013}' | '\u{2014}' => out.push('-'),
+            '\u{2026}' => out.push_str("..."),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Number of pages in a PDF written by `PdfBuilder`, read from its page
+/// tree
+pub fn page_count(pdf: &[u8]) -> usize {
+    let find = |haystack: &[u8], needle: &[u8]| {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    };
+    let Some(pages) = find(pdf, b"/Type /Pages") else {
+        return 0;
+    };
+    let Some(count) = find(&pdf[pages..], b"/Count ") else {
+        return 0;
+    };
+    pdf[pages + count + b"/Count ".len()..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .fold(0, |n, b| n * 10 + (b - b'0') as usize)
+}
+
+/// Encode to single-byte WinAnsi (Latin-1 subset), replacing anything else
+fn encode_win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_produces_valid_structure() {
+        let mut builder = PdfBuilder::new().title("Report").footer("Confidential");
+        builder.heading(1, "Summary").paragraph("Hello (world)");
+        let bytes = builder.build();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("/Count 1"));
+        assert!(text.contains("Hello \\(world\\)"));
+        assert!(text.contains("Confidential    Page 1 of 1"));
+        assert!(text.trim_end().ends_with("%%EOF"));
+    }
+
+    #[test]
+    fn test_rich_paragraph_decorates_each_run() {
+        let deleted = PdfTextStyle {
+            strikethrough: true,
+            ..Default::default()
+        };
+        let mut builder = PdfBuilder::new();
+        builder.rich_paragraph(&[
+            ("Kept".to_string(), PdfTextStyle::default()),
+            ("gone".to_string(), deleted),
+        ]);
+        let text = String::from_utf8_lossy(&builder.build()).to_string();
+        assert!(text.contains("Tf 72 705.15 Td (Kept) Tj"));
+        // "gone" starts after "Kept " and only it is struck through
+        assert!(text.contains("Tf 99.5 705.15 Td (gone) Tj"));
+        assert_eq!(text.matches(" 0.8 w ").count(), 1);
+        assert!(text.contains("0.8 w 99.5 708.45 m 121.5 708.45 l S"));
+    }
+
+    #[test]
+    fn test_long_content_paginates() {
+        let mut builder = PdfBuilder::new();
+        for _ in 0..200 {
+            builder.paragraph("A line of text that fills the page.");
+        }
+        let text = String::from_utf8_lossy(&builder.build()).to_string();
+        assert!(!text.contains("/Count 1 "));
+        assert!(text.contains("Page 2 of"));
+        assert!(page_count(text.as_bytes()) > 1);
+    }
+
+    #[test]
+    fn test_images_are_embedded_and_scaled() {
+        let mut png = Vec::new();
+        image::RgbImage::from_pixel(936, 100, image::Rgb([200, 40, 40]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let mut builder = PdfBuilder::new();
+        builder
+            .paragraph("Portrait")
+            .image(&png, 300.0)
+            .image(b"not an image", 300.0);
+        let text = String::from_utf8_lossy(&builder.build()).to_string();
+        assert!(text.contains("/XObject << /Im1 10 0 R >>"));
+        assert!(text.contains("/Width 936 /Height 100"));
+        assert!(text.contains("/Filter /DCTDecode"));
+        // Scaled to the 468pt text width, placed below the paragraph
+        assert!(text.contains("q 468 0 0 50 72 "));
+        assert_eq!(text.matches(" Do Q").count(), 1);
+    }
+}
@@ -0,0 +1,276 @@
+//! Redline Comparisons
+//!
+//! Marked-up comparison of two versions of a manuscript, as editors expect
+//! it: inserted words underlined, deleted words struck through. Paragraphs
+//! are matched first and changed paragraphs are compared word by word.
+
+use serde::{Deserialize, Serialize};
+
+use super::{split_paragraphs, PdfBuilder, PdfColor, PdfTextStyle};
+use crate::database::prosemirror;
+use crate::database::text_diff::{diff_lines, diff_words, DiffOp, DiffSpan};
+
+/// A chapter or document in both versions; empty text for one that was
+/// added or cut
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedlineSection {
+    pub title: String,
+    pub old: String,
+    pub new: String,
+}
+
+impl RedlineSection {
+    pub fn new(title: impl Into<String>, old: impl Into<String>, new: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            old: old.into(),
+            new: new.into(),
+        }
+    }
+
+    pub fn is_changed(&self) -> bool {
+        split_paragraphs(&self.old) != split_paragraphs(&self.new)
+    }
+}
+
+/// Word counts of a comparison
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedlineSummary {
+    pub words_added: usize,
+    pub words_removed: usize,
+    pub sections_changed: usize,
+}
+
+/// A comparison between two versions, ready to render
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedlineDocument {
+    pub title: String,
+    /// Names of the compared versions, e.g. "Draft 2" and "Draft 3"
+    pub from_label: String,
+    pub to_label: String,
+    pub author: Option<String>,
+    pub sections: Vec<RedlineSection>,
+    /// Leave out sections without changes
+    pub changed_only: bool,
+}
+
+impl RedlineDocument {
+    pub fn new(
+        title: impl Into<String>,
+        from_label: impl Into<String>,
+        to_label: impl Into<String>,
+    ) -> Self {
+        Self {
+            title: title.into(),
+            from_label: from_label.into(),
+            to_label: to_label.into(),
+            author: None,
+            sections: Vec::new(),
+            changed_only: false,
+        }
+    }
+
+    pub fn summary(&self) -> RedlineSummary {
+        let mut summary = RedlineSummary::default();
+        for section in self.sections.iter().filter(|s| s.is_changed()) {
+            summary.sections_changed += 1;
+            for span in redline_paragraphs(&section.old, &section.new)
+                .iter()
+                .flatten()
+            {
+                let words = span.text.split_whitespace().count();
+                match span.op {
+                    DiffOp::Insert => summary.words_added += words,
+                    DiffOp::Delete => summary.words_removed += words,
+                    DiffOp::Equal => {}
+                }
+            }
+        }
+        summary
+    }
+
+    /// Render as a PDF, one section per page run
+    pub fn render_pdf(&self) -> Vec<u8> {
+        let mut builder = PdfBuilder::new()
+            .title(self.title.clone())
+            .footer(format!("Redline: {} to {}", self.from_label, self.to_label));
+        if let Some(author) = &self.author {
+            builder = builder.author(author.clone());
+        }
+
+        let summary = self.summary();
+        builder.heading(1, &self.title).paragraph(&format!(
+            "Changes from {} to {}: {} words added and {} removed in {} of {} sections.",
+            self.from_label,
+            self.to_label,
+            summary.words_added,
+            summary.words_removed,
+            summary.sections_changed,
+            self.sections.len()
+        ));
+        builder.rich_paragraph(&[
+            ("Inserted text".to_string(), style_for(DiffOp::Insert)),
+            ("is underlined;".to_string(), style_for(DiffOp::Equal)),
+            ("deleted text".to_string(), style_for(DiffOp::Delete)),
+            ("is struck through.".to_string(), style_for(DiffOp::Equal)),
+        ]);
+
+        for section in &self.sections {
+            if self.changed_only && !section.is_changed() {
+                continue;
+            }
+            builder.page_break().heading(1, &section.title);
+            for paragraph in redline_paragraphs(&section.old, &section.new) {
+                let runs: Vec<(String, PdfTextStyle)> = paragraph
+                    .into_iter()
+                    .map(|span| (span.text, style_for(span.op)))
+                    .collect();
+                builder.rich_paragraph(&runs);
+            }
+        }
+        builder.build()
+    }
+}
+
+/// Text of a stored document as redline paragraphs: each ProseMirror block
+/// is a paragraph; anything else is already text
+pub fn document_paragraphs(document_type: &str, content: &str) -> String {
+    match prosemirror::parse(document_type, content) {
+        Some(doc) => prosemirror::blocks(&doc)
+            .iter()
+            .map(prosemirror::plain_text)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        None => content.to_string(),
+    }
+}
+
+/// Paragraphs of the new text with the changes from the old one marked.
+/// A rewritten paragraph is compared word by word; added and cut paragraphs
+/// come out as a single insert or delete span.
+pub fn redline_paragraphs(old: &str, new: &str) -> Vec<Vec<DiffSpan>> {
+    let old_paragraphs = split_paragraphs(old).join("\n");
+    let new_paragraphs = split_paragraphs(new).join("\n");
+
+    let mut paragraphs = Vec::new();
+    let mut deleted: Vec<&str> = Vec::new();
+    let mut inserted: Vec<&str> = Vec::new();
+    let diff = diff_lines(&old_paragraphs, &new_paragraphs);
+    for line in &diff {
+        match line.op {
+            DiffOp::Delete => deleted.push(&line.text),
+            DiffOp::Insert => inserted.push(&line.text),
+            DiffOp::Equal => {
+                flush_changes(&mut paragraphs, &mut deleted, &mut inserted);
+                paragraphs.push(vec![span(DiffOp::Equal, &line.text)]);
+            }
+        }
+    }
+    flush_changes(&mut paragraphs, &mut deleted, &mut inserted);
+    paragraphs
+}
+
+/// Pair a run of cut paragraphs with the ones that replaced them
+fn flush_changes(
+    paragraphs: &mut Vec<Vec<DiffSpan>>,
+    deleted: &mut Vec<&str>,
+    inserted: &mut Vec<&str>,
+) {
+    for k in 0..deleted.len().max(inserted.len()) {
+        paragraphs.push(match (deleted.get(k), inserted.get(k)) {
+            (Some(old), Some(new)) => diff_words(old, new),
+            (Some(old), None) => vec![span(DiffOp::Delete, old)],
+            (None, Some(new)) => vec![span(DiffOp::Insert, new)],
+            (None, None) => Vec::new(),
+        });
+    }
+    deleted.clear();
+    inserted.clear();
+}
+
+fn span(op: DiffOp, text: &str) -> DiffSpan {
+    DiffSpan {
+        op,
+        text: text.to_string(),
+    }
+}
+
+fn style_for(op: DiffOp) -> PdfTextStyle {
+    match op {
+        DiffOp::Equal => PdfTextStyle::default(),
+        DiffOp::Insert => PdfTextStyle {
+            color: PdfColor::GREEN,
+            underline: true,
+            ..Default::default()
+        },
+        DiffOp::Delete => PdfTextStyle {
+            color: PdfColor::RED,
+            strikethrough: true,
+            ..Default::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redline_marks_paragraph_and_word_changes() {
+        let old = "The storm came at night.\n\nShe waited.\n\nA cut scene.";
+        let new = "The storm came at dawn.\n\nShe waited.\n\nAn added ending.\n\nCoda.";
+        let paragraphs = redline_paragraphs(old, new);
+        let ops: Vec<Vec<(DiffOp, &str)>> = paragraphs
+            .iter()
+            .map(|p| p.iter().map(|s| (s.op, s.text.as_str())).collect())
+            .collect();
+        assert_eq!(
+            ops,
+            vec![
+                vec![
+                    (DiffOp::Equal, "The storm came at"),
+                    (DiffOp::Delete, "night."),
+                    (DiffOp::Insert, "dawn."),
+                ],
+                vec![(DiffOp::Equal, "She waited.")],
+                vec![
+                    (DiffOp::Delete, "A cut scene."),
+                    (DiffOp::Insert, "An added ending."),
+                ],
+                vec![(DiffOp::Insert, "Coda.")],
+            ]
+        );
+
+        let mut document = RedlineDocument::new("Redline", "Draft 1", "Draft 2");
+        document.sections.push(RedlineSection::new("One", old, new));
+        document
+            .sections
+            .push(RedlineSection::new("Two", "Same.", "Same."));
+        document.changed_only = true;
+        assert_eq!(
+            document.summary(),
+            RedlineSummary {
+                words_added: 5,
+                words_removed: 4,
+                sections_changed: 1,
+            }
+        );
+        let pdf = String::from_utf8_lossy(&document.render_pdf()).to_string();
+        assert!(pdf.contains("(night.) Tj"));
+        assert!(pdf.contains("/Count 2"));
+    }
+
+    #[test]
+    fn test_editor_documents_redline_by_block() {
+        let doc = serde_json::json!({
+            "type": "doc",
+            "content": prosemirror::paragraphs("The storm came at night.\nShe waited."),
+        })
+        .to_string();
+        assert_eq!(
+            document_paragraphs("json", &doc),
+            "The storm came at night.\n\nShe waited."
+        );
+        assert_eq!(document_paragraphs("markdown", "# One"), "# One");
+    }
+}
@@ -0,0 +1,334 @@
+//! Share Bundles
+//!
+//! A read-only copy of a chapter as one self-contained HTML file, for
+//! sending to a beta reader without a server. With a password the text is
+//! encrypted (AES-256-GCM under a PBKDF2-SHA-256 key) and the page decrypts
+//! it in the reader's browser with Web Crypto. An expiry date is stamped
+//! into the page, which hides the text once it has passed; in encrypted
+//! bundles the date is bound to the ciphertext, so editing it breaks
+//! decryption. The page enforces the expiry itself, which keeps honest
+//! readers honest rather than stopping a determined one.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+
+use super::{escape_xml, PublishedDocument};
+
+/// PBKDF2 rounds for new bundles, per current OWASP guidance for SHA-256
+pub const DEFAULT_KDF_ITERATIONS: u32 = 600_000;
+
+fn default_kdf_iterations() -> u32 {
+    DEFAULT_KDF_ITERATIONS
+}
+
+#[derive(Debug, Error)]
+pub enum ShareError {
+    #[error("The expiry date {0} has already passed")]
+    AlreadyExpired(DateTime<Utc>),
+    #[error("The password cannot be empty")]
+    EmptyPassword,
+    #[error("Encryption failed: {0}")]
+    Encryption(String),
+}
+
+/// How a shared copy is protected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareOptions {
+    /// Password the reader types to open the copy; None for an open copy
+    #[serde(default)]
+    pub password: Option<String>,
+    /// When the page stops showing the text
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Shown under the title, e.g. "Draft 3, for Dana"
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default = "default_kdf_iterations")]
+    pub kdf_iterations: u32,
+}
+
+impl Default for ShareOptions {
+    fn default() -> Self {
+        Self {
+            password: None,
+            expires_at: None,
+            note: None,
+            kdf_iterations: DEFAULT_KDF_ITERATIONS,
+        }
+    }
+}
+
+/// A rendered share page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareBundle {
+    pub html: String,
+    pub encrypted: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// What the page script needs: the expiry and, when encrypted, how to
+/// decrypt the text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ShareData {
+    expires_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encryption: Option<SealedText>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SealedText {
+    salt: String,
+    iv: String,
+    iterations: u32,
+    ciphertext: String,
+}
+
+/// Render a document as a share page
+pub fn render_share_bundle(
+    document: &PublishedDocument,
+    options: &ShareOptions,
+) -> Result<ShareBundle, ShareError> {
+    if let Some(expires_at) = options.expires_at.filter(|at| *at <= Utc::now()) {
+        return Err(ShareError::AlreadyExpired(expires_at));
+    }
+    let expires = options
+        .expires_at
+        .map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true));
+
+    let body = document.html_body();
+    let (content, encryption) = match options.password.as_deref() {
+        Some("") => return Err(ShareError::EmptyPassword),
+        Some(password) => {
+            let sealed = seal(
+                &body,
+                password,
+                expires.as_deref().unwrap_or_default(),
+                options.kdf_iterations.max(1),
+            )?;
+            (String::new(), Some(sealed))
+        }
+        None => (body, None),
+    };
+    let encrypted = encryption.is_some();
+    let data = serde_json::to_string(&ShareData {
+        expires_at: expires.clone(),
+        encryption,
+    })
+    .map_err(|e| ShareError::Encryption(e.to_string()))?
+    // Keep the JSON from closing its script element
+    .replace("</", "<\\/");
+
+    let mut head = String::new();
+    if let Some(author) = &document.author {
+        head.push_str(&format!(
+            "<meta name=\"author\" content=\"{}\">\n",
+            escape_xml(author)
+        ));
+    }
+    if let Some(expires) = &expires {
+        head.push_str(&format!(
+            "<meta name=\"share-expires\" content=\"{}\">\n",
+            expires
+        ));
+    }
+    let mut intro = String::new();
+    if let Some(note) = options.note.as_deref().filter(|n| !n.trim().is_empty()) {
+        intro.push_str(&format!("<p class=\"note\">{}</p>\n", escape_xml(note)));
+    }
+    if let Some(expires_at) = options.expires_at {
+        intro.push_str(&format!(
+            "<p class=\"note\">Available until {}</p>\n",
+            expires_at.format("%B %-d, %Y %H:%M UTC")
+        ));
+    }
+
+    let html = format!(
+        "<!DOCTYPE html>\n<html lang=\"{lang}\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta name=\"robots\" content=\"noindex\">\n{head}<title>{title}</title>\n\
+         <style>{style}</style>\n</head>\n<body>\n<h1>{title}</h1>\n{intro}\
+         <form id=\"locked\" hidden>\n<label for=\"password\">This copy is protected. Password:</label>\n\
+         <input id=\"password\" type=\"password\" autocomplete=\"off\" autofocus>\n\
+         <button type=\"submit\">Open</button>\n<p id=\"wrong\" hidden>That password didn't open it.</p>\n</form>\n\
+         <p id=\"expired\" hidden>This shared copy has expired. Ask the author for a new one.</p>\n\
+         <article id=\"content\">\n{content}</article>\n\
+         <script type=\"application/json\" id=\"share-data\">{data}</script>\n\
+         <script>{script}</script>\n</body>\n</html>\n",
+        lang = escape_xml(&document.language),
+        head = head,
+        title = escape_xml(&document.title),
+        style = STYLE,
+        intro = intro,
+        content = content,
+        data = data,
+        script = SCRIPT,
+    );
+    Ok(ShareBundle {
+        html,
+        encrypted,
+        expires_at: options.expires_at,
+    })
+}
+
+/// Encrypt `text` the way the page script decrypts it, with the expiry as
+/// associated data
+fn seal(
+    text: &str,
+    password: &str,
+    expires: &str,
+    iterations: u32,
+) -> Result<SealedText, ShareError> {
+    let mut rng = rand::thread_rng();
+    let salt: [u8; 16] = rng.gen();
+    let iv: [u8; 12] = rng.gen();
+    let key = pbkdf2_sha256(password.as_bytes(), &salt, iterations);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&iv),
+            Payload {
+                msg: text.as_bytes(),
+                aad: expires.as_bytes(),
+            },
+        )
+        .map_err(|e| ShareError::Encryption(e.to_string()))?;
+    Ok(SealedText {
+        salt: STANDARD.encode(salt),
+        iv: STANDARD.encode(iv),
+        iterations,
+        ciphertext: STANDARD.encode(ciphertext),
+    })
+}
+
+/// PBKDF2-HMAC-SHA-256 with a 32-byte output, matching Web Crypto's
+/// `deriveKey` for an AES-256 key
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(password, salt, iterations)
+}
+
+const STYLE: &str =
+    "body{max-width:40em;margin:2em auto;padding:0 1em;font:1.1em/1.6 Georgia,serif;color:#222}\
+.note{color:#666;font-style:italic}\
+footer{margin-top:3em;color:#999;font-size:.8em}\
+form{margin:2em 0}";
+
+const SCRIPT: &str = r#"
+(function () {
+  var data = JSON.parse(document.getElementById("share-data").textContent);
+  var content = document.getElementById("content");
+  var show = function (id) { document.getElementById(id).hidden = false; };
+  if (data.expires_at && Date.now() > Date.parse(data.expires_at)) {
+    content.remove();
+    show("expired");
+    return;
+  }
+  var sealed = data.encryption;
+  if (!sealed) return;
+  var bytes = function (b64) {
+    return Uint8Array.from(atob(b64), function (c) { return c.charCodeAt(0); });
+  };
+  var encoder = new TextEncoder();
+  show("locked");
+  document.getElementById("locked").addEventListener("submit", function (event) {
+    event.preventDefault();
+    var password = document.getElementById("password").value;
+    crypto.subtle.importKey("raw", encoder.encode(password), "PBKDF2", false, ["deriveKey"])
+      .then(function (base) {
+        return crypto.subtle.deriveKey(
+          { name: "PBKDF2", salt: bytes(sealed.salt), iterations: sealed.iterations, hash: "SHA-256" },
+          base, { name: "AES-GCM", length: 256 }, false, ["decrypt"]);
+      })
+      .then(function (key) {
+        return crypto.subtle.decrypt(
+          { name: "AES-GCM", iv: bytes(sealed.iv), additionalData: encoder.encode(data.expires_at || "") },
+          key, bytes(sealed.ciphertext));
+      })
+      .then(function (plain) {
+        content.innerHTML = new TextDecoder().decode(plain);
+        document.getElementById("locked").hidden = true;
+      })
+      .catch(function () { show("wrong"); });
+  });
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::publishing::PublishedSection;
+
+    #[test]
+    fn test_pbkdf2_matches_reference() {
+        // RFC 7914 section 11
+        let key = pbkdf2_sha256(b"passwd", b"salt", 1);
+        assert_eq!(
+            key[..16],
+            [
+                0x55, 0xac, 0x04, 0x6e, 0x56, 0xe3, 0x08, 0x9f, 0xec, 0x16, 0x91, 0xc2, 0x25, 0x44,
+                0xb6, 0x05
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encrypted_bundle_opens_with_password() {
+        let mut document = PublishedDocument::new("Chapter 3");
+        document
+            .sections
+            .push(PublishedSection::from_text("", "The lantern went out."));
+        let options = ShareOptions {
+            password: Some("ember".to_string()),
+            expires_at: Some(Utc::now() + chrono::Duration::days(7)),
+            kdf_iterations: 10,
+            ..Default::default()
+        };
+        let bundle = render_share_bundle(&document, &options).unwrap();
+        assert!(bundle.encrypted);
+        assert!(!bundle.html.contains("lantern"));
+        assert!(bundle.html.contains("name=\"share-expires\""));
+
+        let json = bundle
+            .html
+            .split("id=\"share-data\">")
+            .nth(1)
+            .and_then(|rest| rest.split("</script>").next())
+            .unwrap();
+        let data: ShareData = serde_json::from_str(json).unwrap();
+        let sealed = data.encryption.unwrap();
+        let salt = STANDARD.decode(&sealed.salt).unwrap();
+        let key = pbkdf2_sha256(b"ember", &salt, sealed.iterations);
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+        let open = |aad: &[u8]| {
+            cipher.decrypt(
+                Nonce::from_slice(&STANDARD.decode(&sealed.iv).unwrap()),
+                Payload {
+                    msg: &STANDARD.decode(&sealed.ciphertext).unwrap(),
+                    aad,
+                },
+            )
+        };
+        let plain = open(data.expires_at.unwrap().as_bytes()).unwrap();
+        assert!(String::from_utf8(plain)
+            .unwrap()
+            .contains("The lantern went out."));
+        // Pushing the expiry back breaks decryption
+        assert!(open(b"2099-01-01T00:00:00Z").is_err());
+
+        let expired = ShareOptions {
+            expires_at: Some(Utc::now() - chrono::Duration::hours(1)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            render_share_bundle(&document, &expired),
+            Err(ShareError::AlreadyExpired(_))
+        ));
+        let open_copy = render_share_bundle(&document, &ShareOptions::default()).unwrap();
+        assert!(open_copy.html.contains("The lantern went out."));
+    }
+}
@@ -0,0 +1,197 @@
+//! Recent Items
+//!
+//! Most recently opened projects and documents, persisted to
+//! `recent_items.json` and mirrored to the OS shell (Windows jump list,
+//! macOS dock menu) through the change hook installed by the app.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::app_paths::AppPaths;
+use crate::deep_link::DeepLink;
+
+/// How many items are kept
+pub const MAX_RECENT_ITEMS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecentItemKind {
+    Project,
+    Document,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentItem {
+    pub kind: RecentItemKind,
+    pub id: String,
+    pub title: String,
+    pub opened_at: DateTime<Utc>,
+}
+
+impl RecentItem {
+    /// Deep link that reopens this item
+    pub fn link(&self) -> DeepLink {
+        match self.kind {
+            RecentItemKind::Project => DeepLink::OpenProject {
+                project_id: self.id.clone(),
+            },
+            RecentItemKind::Document => DeepLink::OpenDocument {
+                document_id: self.id.clone(),
+            },
+        }
+    }
+}
+
+type ChangeHook = Arc<dyn Fn(&[RecentItem]) + Send + Sync>;
+
+/// Persistent most-recently-used list
+pub struct RecentItems {
+    path: PathBuf,
+    items: Mutex<Vec<RecentItem>>,
+    on_change: Option<ChangeHook>,
+}
+
+impl std::fmt::Debug for RecentItems {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecentItems")
+            .field("path", &self.path)
+            .field("items", &self.items)
+            .finish()
+    }
+}
+
+impl RecentItems {
+    /// Load the list stored at `path`; a missing or unreadable file starts empty
+    pub fn load(path: &Path) -> Self {
+        let items = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            path: path.to_path_buf(),
+            items: Mutex::new(items),
+            on_change: None,
+        }
+    }
+
+    /// `recent_items.json` in the data directory, next to settings.json
+    pub fn default_path() -> PathBuf {
+        AppPaths::global().recent_items_path()
+    }
+
+    /// Called with the new list whenever it changes
+    pub fn with_on_change<F>(self, hook: F) -> Self
+    where
+        F: Fn(&[RecentItem]) + Send + Sync + 'static,
+    {
+        let hook: ChangeHook = Arc::new(hook);
+        hook(&self.items());
+        Self {
+            on_change: Some(hook),
+            ..self
+        }
+    }
+
+    pub fn items(&self) -> Vec<RecentItem> {
+        self.items
+            .lock()
+            .map(|items| items.clone())
+            .unwrap_or_default()
+    }
+
+    /// Move an item to the top of the list, adding it if needed
+    pub fn record(&self, kind: RecentItemKind, id: &str, title: &str) -> Result<(), String> {
+        self.update(|items| {
+            items.retain(|item| !(item.kind == kind && item.id == id));
+            items.insert(
+                0,
+                RecentItem {
+                    kind,
+                    id: id.to_string(),
+                    title: title.to_string(),
+                    opened_at: Utc::now(),
+                },
+            );
+            items.truncate(MAX_RECENT_ITEMS);
+        })
+    }
+
+    /// Drop an item, e.g. after its project was deleted
+    pub fn remove(&self, kind: RecentItemKind, id: &str) -> Result<(), String> {
+        self.update(|items| items.retain(|item| !(item.kind == kind && item.id == id)))
+    }
+
+    pub fn clear(&self) -> Result<(), String> {
+        self.update(Vec::clear)
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Vec<RecentItem>)) -> Result<(), String> {
+        let snapshot = {
+            let mut items = self
+                .items
+                .lock()
+                .map_err(|_| "Recent items lock poisoned".to_string())?;
+            change(&mut items);
+            items.clone()
+        };
+
+        let json = serde_json::to_string_pretty(&snapshot)
+            .map_err(|e| format!("Failed to serialize recent items: {}", e))?;
+        std::fs::write(&self.path, json)
+            .map_err(|e| format!("Failed to write recent items file: {}", e))?;
+
+        if let Some(hook) = &self.on_change {
+            hook(&snapshot);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_moves_to_front_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recent_items.json");
+        let changes = Arc::new(Mutex::new(0));
+        let counter = changes.clone();
+        let recent = RecentItems::load(&path).with_on_change(move |_| {
+            *counter.lock().unwrap() += 1;
+        });
+
+        recent
+            .record(RecentItemKind::Project, "p1", "Novel")
+            .unwrap();
+        recent
+            .record(RecentItemKind::Document, "d1", "Chapter 1")
+            .unwrap();
+        recent
+            .record(RecentItemKind::Project, "p1", "Novel")
+            .unwrap();
+        for i in 0..MAX_RECENT_ITEMS {
+            recent
+                .record(RecentItemKind::Document, &format!("x{}", i), "Filler")
+                .unwrap();
+        }
+        recent
+            .record(RecentItemKind::Project, "p1", "Novel")
+            .unwrap();
+
+        let items = RecentItems::load(&path).items();
+        assert_eq!(items.len(), MAX_RECENT_ITEMS);
+        assert_eq!(items[0].id, "p1");
+        assert_eq!(
+            items[0].link(),
+            DeepLink::OpenProject {
+                project_id: "p1".to_string()
+            }
+        );
+        assert!(!items.iter().any(|item| item.id == "d1"));
+        // Initial call plus one per change
+        assert_eq!(*changes.lock().unwrap(), MAX_RECENT_ITEMS + 5);
+    }
+}
@@ -0,0 +1,346 @@
+//! Scratch Space
+//!
+//! Temporary working directories for exports and backups. Each job gets a
+//! directory under the app's scratch root that is removed when its
+//! `ScratchDir` is dropped, whether the job finished or failed, and
+//! anything a crash left behind is cleared at the next start. Jobs write
+//! through their directory so they can be held to a disk quota, and jobs
+//! that may write a lot check the disk has room before they start.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use sysinfo::{DiskExt, System, SystemExt};
+use thiserror::Error;
+
+use crate::app_paths::AppPaths;
+use crate::error::{AppError, DatabaseError, ErrorCode, UserFacingError};
+
+/// Most bytes one job may write to its scratch directory by default
+pub const DEFAULT_JOB_QUOTA: u64 = 2 * 1024 * 1024 * 1024;
+/// Free space a preflight leaves on the disk for everything else
+pub const MIN_FREE_BYTES: u64 = 512 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum ScratchError {
+    #[error("Not enough disk space in {}: {required} bytes needed, {available} available", path.display())]
+    InsufficientSpace {
+        path: PathBuf,
+        required: u64,
+        available: u64,
+    },
+    #[error("Job {job} went over its disk quota of {quota} bytes")]
+    QuotaExceeded { job: String, quota: u64 },
+    #[error("Scratch paths must stay inside the job's directory: {0}")]
+    InvalidPath(PathBuf),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl UserFacingError for ScratchError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ScratchError::InsufficientSpace { .. } | ScratchError::QuotaExceeded { .. } => {
+                ErrorCode::DiskFull
+            }
+            ScratchError::InvalidPath(_) => ErrorCode::InvalidInput,
+            ScratchError::Io(_) => ErrorCode::Storage,
+        }
+    }
+}
+
+impl From<ScratchError> for AppError {
+    fn from(error: ScratchError) -> Self {
+        match error {
+            ScratchError::InsufficientSpace { .. } | ScratchError::QuotaExceeded { .. } => {
+                AppError::InsufficientSpace(error.to_string())
+            }
+            ScratchError::InvalidPath(_) | ScratchError::Io(_) => AppError::Io(error.to_string()),
+        }
+    }
+}
+
+impl From<ScratchError> for DatabaseError {
+    fn from(error: ScratchError) -> Self {
+        match error {
+            ScratchError::InsufficientSpace { .. } | ScratchError::QuotaExceeded { .. } => {
+                DatabaseError::InsufficientSpace(error.to_string())
+            }
+            ScratchError::InvalidPath(_) | ScratchError::Io(_) => {
+                DatabaseError::BackupRestoreFailed {
+                    operation: "scratch".to_string(),
+                    error: error.to_string(),
+                }
+            }
+        }
+    }
+}
+
+/// Root directory that job scratch directories are made in
+#[derive(Debug, Clone)]
+pub struct ScratchSpace {
+    root: PathBuf,
+    quota: u64,
+}
+
+impl ScratchSpace {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            quota: DEFAULT_JOB_QUOTA,
+        }
+    }
+
+    /// Scratch space in the app's cache directory
+    pub fn open_default() -> Self {
+        Self::new(AppPaths::global().scratch_dir())
+    }
+
+    /// Hold each job to `quota` bytes instead of the default
+    pub fn with_quota(mut self, quota: u64) -> Self {
+        self.quota = quota;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Remove every scratch directory. Call at startup, before any job
+    /// runs; returns how many were removed.
+    pub fn clear(&self) -> std::io::Result<usize> {
+        if !self.root.exists() {
+            return Ok(0);
+        }
+        let mut removed = 0;
+        for entry in std::fs::read_dir(&self.root)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                std::fs::remove_dir_all(&path)?;
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    /// A scratch directory for `job_id`, held to the default quota
+    pub fn create(&self, job_id: &str) -> Result<ScratchDir, ScratchError> {
+        self.create_with_quota(job_id, self.quota)
+    }
+
+    /// A scratch directory for `job_id` that may hold `quota` bytes. Fails
+    /// up front when the disk is already down to its reserve; jobs that
+    /// know roughly how much they'll write should `preflight` for it too.
+    pub fn create_with_quota(&self, job_id: &str, quota: u64) -> Result<ScratchDir, ScratchError> {
+        let name = Path::new(job_id);
+        if !is_relative_inside(name) || name.components().count() != 1 {
+            return Err(ScratchError::InvalidPath(name.to_path_buf()));
+        }
+        std::fs::create_dir_all(&self.root)?;
+        preflight(&self.root, 0)?;
+
+        let path = self.root.join(name);
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        std::fs::create_dir(&path)?;
+        Ok(ScratchDir {
+            path,
+            job: job_id.to_string(),
+            quota,
+            used: AtomicU64::new(0),
+        })
+    }
+}
+
+/// A job's scratch directory; removed with everything in it on drop
+#[derive(Debug)]
+pub struct ScratchDir {
+    path: PathBuf,
+    job: String,
+    quota: u64,
+    used: AtomicU64,
+}
+
+impl ScratchDir {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Bytes written so far
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Write a file at `relative` inside the directory, creating its
+    /// parents, and count it against the quota
+    pub fn write(
+        &self,
+        relative: impl AsRef<Path>,
+        contents: impl AsRef<[u8]>,
+    ) -> Result<PathBuf, ScratchError> {
+        let relative = relative.as_ref();
+        let contents = contents.as_ref();
+        if !is_relative_inside(relative) {
+            return Err(ScratchError::InvalidPath(relative.to_path_buf()));
+        }
+        self.reserve(contents.len() as u64)?;
+
+        let path = self.path.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Count `bytes` written some other way, such as by a library handed
+    /// a path inside the directory
+    pub fn reserve(&self, bytes: u64) -> Result<(), ScratchError> {
+        let used = self.used.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        if used > self.quota {
+            self.used.fetch_sub(bytes, Ordering::Relaxed);
+            return Err(ScratchError::QuotaExceeded {
+                job: self.job.clone(),
+                quota: self.quota,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!(
+                    "Failed to remove scratch directory {}: {}",
+                    self.path.display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Remove the directories in `dir` whose names start with `prefix`, such as
+/// the `temp_*` directories older exports left next to their output
+pub fn remove_prefixed_dirs(dir: &Path, prefix: &str) -> std::io::Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() && entry.file_name().to_string_lossy().starts_with(prefix) {
+            std::fs::remove_dir_all(entry.path())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Bytes free on the disk holding `path`, which need not exist yet;
+/// `None` when the disk can't be found
+pub fn available_space(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+    let path = existing.canonicalize().ok()?;
+
+    let mut system = System::new();
+    system.refresh_disks_list();
+    system
+        .disks()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+/// Fail unless the disk holding `path` can take `required` more bytes and
+/// still keep `MIN_FREE_BYTES` free. Passes when the free space can't be
+/// told, rather than blocking the job.
+pub fn preflight(path: &Path, required: u64) -> Result<(), ScratchError> {
+    check_space(path, required, available_space(path))
+}
+
+fn check_space(path: &Path, required: u64, available: Option<u64>) -> Result<(), ScratchError> {
+    match available {
+        Some(available) if available < required.saturating_add(MIN_FREE_BYTES) => {
+            Err(ScratchError::InsufficientSpace {
+                path: path.to_path_buf(),
+                required,
+                available,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A relative path with no `..`, so it can't leave the directory it's
+/// joined to
+fn is_relative_inside(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratch_dir_is_removed_and_holds_quota() {
+        let root = tempfile::tempdir().unwrap();
+        let space = ScratchSpace::new(root.path().join("scratch")).with_quota(16);
+
+        let scratch = space.create("job-1").unwrap();
+        let path = scratch.path().to_path_buf();
+        scratch
+            .write("OEBPS/chapter_1.xhtml", b"0123456789")
+            .unwrap();
+        assert!(path.join("OEBPS/chapter_1.xhtml").exists());
+        assert!(matches!(
+            scratch.write("OEBPS/chapter_2.xhtml", b"0123456789"),
+            Err(ScratchError::QuotaExceeded { quota: 16, .. })
+        ));
+        assert_eq!(scratch.used(), 10);
+        assert!(matches!(
+            scratch.write("../escape.txt", b"x"),
+            Err(ScratchError::InvalidPath(_))
+        ));
+        assert!(space.create("../job").is_err());
+
+        // A failed job's directory goes when it is dropped
+        drop(scratch);
+        assert!(!path.exists());
+
+        // One left behind by a crash goes at the next start
+        std::mem::forget(space.create("job-2").unwrap());
+        assert_eq!(space.clear().unwrap(), 1);
+        assert_eq!(std::fs::read_dir(space.root()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_preflight_keeps_a_reserve() {
+        let path = Path::new("/data");
+        assert!(check_space(path, 100, None).is_ok());
+        assert!(check_space(path, 100, Some(MIN_FREE_BYTES + 100)).is_ok());
+        let error = check_space(path, 100, Some(MIN_FREE_BYTES + 99)).unwrap_err();
+        assert!(matches!(
+            error,
+            ScratchError::InsufficientSpace { required: 100, .. }
+        ));
+        assert_eq!(error.code(), ErrorCode::DiskFull);
+
+        let dir = tempfile::tempdir().unwrap();
+        let exports = dir.path().join("exports");
+        std::fs::create_dir_all(exports.join("temp_1/OEBPS")).unwrap();
+        std::fs::create_dir_all(exports.join("kept")).unwrap();
+        assert_eq!(remove_prefixed_dirs(&exports, "temp_").unwrap(), 1);
+        assert!(exports.join("kept").exists());
+        assert!(available_space(&exports.join("not-yet")).is_some());
+    }
+}
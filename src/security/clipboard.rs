@@ -0,0 +1,268 @@
+//! Secure Clipboard
+//!
+//! Copies text to the system clipboard and clears it again after a timer,
+//! unless something else has been copied in the meantime. Copies of
+//! Restricted data are written to the compliance audit log and can be
+//! refused outright, which is the app-level counterpart of
+//! `DocumentPermissions::allow_copying` in exported files.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::compliance::{ComplianceService, DataClassification};
+
+/// Access to a clipboard; abstracted so the timer logic can be tested
+pub trait ClipboardBackend: Send + Sync {
+    fn set_text(&self, text: &str) -> Result<()>;
+    fn get_text(&self) -> Result<Option<String>>;
+    fn clear(&self) -> Result<()>;
+}
+
+/// The operating system clipboard
+#[derive(Debug, Default)]
+pub struct SystemClipboard;
+
+impl ClipboardBackend for SystemClipboard {
+    fn set_text(&self, text: &str) -> Result<()> {
+        arboard::Clipboard::new()?.set_text(text.to_string())?;
+        Ok(())
+    }
+
+    fn get_text(&self) -> Result<Option<String>> {
+        match arboard::Clipboard::new()?.get_text() {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn clear(&self) -> Result<()> {
+        arboard::Clipboard::new()?.clear()?;
+        Ok(())
+    }
+}
+
+/// How long copied data stays on the clipboard, by sensitivity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardPolicy {
+    pub clear_after_secs: u64,
+    pub restricted_clear_after_secs: u64,
+    /// Whether Restricted data may be copied at all
+    pub allow_restricted_copy: bool,
+}
+
+impl Default for ClipboardPolicy {
+    fn default() -> Self {
+        Self {
+            clear_after_secs: 90,
+            restricted_clear_after_secs: 20,
+            allow_restricted_copy: true,
+        }
+    }
+}
+
+impl ClipboardPolicy {
+    pub fn clear_after(&self, classification: DataClassification) -> Duration {
+        match classification {
+            DataClassification::Restricted => Duration::from_secs(self.restricted_clear_after_secs),
+            _ => Duration::from_secs(self.clear_after_secs),
+        }
+    }
+}
+
+/// What the caller gets back from a secure copy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecureCopyReceipt {
+    pub characters: usize,
+    pub clears_in_secs: u64,
+    pub logged: bool,
+}
+
+/// Clipboard writer with auto-clear and sensitive copy tracking
+pub struct SecureClipboard {
+    backend: Arc<dyn ClipboardBackend>,
+    compliance: Arc<Mutex<ComplianceService>>,
+    policy: Mutex<ClipboardPolicy>,
+    /// Bumped on every copy so stale timers leave newer copies alone
+    generation: Arc<AtomicU64>,
+}
+
+impl SecureClipboard {
+    pub fn new(compliance: Arc<Mutex<ComplianceService>>) -> Self {
+        Self::with_backend(Arc::new(SystemClipboard), compliance)
+    }
+
+    pub fn with_backend(
+        backend: Arc<dyn ClipboardBackend>,
+        compliance: Arc<Mutex<ComplianceService>>,
+    ) -> Self {
+        Self {
+            backend,
+            compliance,
+            policy: Mutex::new(ClipboardPolicy::default()),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn policy(&self) -> ClipboardPolicy {
+        self.policy.lock().map(|p| p.clone()).unwrap_or_default()
+    }
+
+    pub fn set_policy(&self, policy: ClipboardPolicy) {
+        if let Ok(mut current) = self.policy.lock() {
+            *current = policy;
+        }
+    }
+
+    /// Put text on the clipboard and schedule it to be cleared
+    pub fn copy(
+        &self,
+        text: &str,
+        classification: DataClassification,
+        source: Option<&str>,
+    ) -> Result<SecureCopyReceipt> {
+        let policy = self.policy();
+        let resource = source.unwrap_or("clipboard");
+        let restricted = classification == DataClassification::Restricted;
+
+        if restricted && !policy.allow_restricted_copy {
+            self.audit(resource, "blocked: restricted data");
+            return Err(anyhow!("Copying Restricted content is disabled"));
+        }
+
+        self.backend.set_text(text)?;
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let clear_after = policy.clear_after(classification);
+        self.schedule_clear(text.to_string(), generation, clear_after);
+
+        if restricted {
+            self.audit(
+                resource,
+                &format!(
+                    "copied {} characters; clears in {}s",
+                    text.chars().count(),
+                    clear_after.as_secs()
+                ),
+            );
+        }
+
+        Ok(SecureCopyReceipt {
+            characters: text.chars().count(),
+            clears_in_secs: clear_after.as_secs(),
+            logged: restricted,
+        })
+    }
+
+    /// Clear the clipboard immediately and cancel pending timers
+    pub fn clear_now(&self) -> Result<()> {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.backend.clear()
+    }
+
+    fn schedule_clear(&self, text: String, generation: u64, after: Duration) {
+        let backend = self.backend.clone();
+        let current = self.generation.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(after);
+            if current.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            // Leave the clipboard alone if the user has copied something else
+            if let Ok(Some(contents)) = backend.get_text() {
+                if contents == text {
+                    if let Err(e) = backend.clear() {
+                        log::warn!("Failed to clear clipboard: {}", e);
+                    }
+                }
+            }
+        });
+    }
+
+    fn audit(&self, resource: &str, result: &str) {
+        if let Ok(mut compliance) = self.compliance.lock() {
+            compliance.log_audit("clipboard_copy", resource, result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct MemoryClipboard(Mutex<Option<String>>);
+
+    impl ClipboardBackend for MemoryClipboard {
+        fn set_text(&self, text: &str) -> Result<()> {
+            *self.0.lock().unwrap() = Some(text.to_string());
+            Ok(())
+        }
+
+        fn get_text(&self) -> Result<Option<String>> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+
+        fn clear(&self) -> Result<()> {
+            *self.0.lock().unwrap() = None;
+            Ok(())
+        }
+    }
+
+    fn clipboard() -> (Arc<MemoryClipboard>, SecureClipboard) {
+        let backend = Arc::new(MemoryClipboard::default());
+        let secure = SecureClipboard::with_backend(
+            backend.clone(),
+            Arc::new(Mutex::new(ComplianceService::new())),
+        );
+        secure.set_policy(ClipboardPolicy {
+            clear_after_secs: 0,
+            restricted_clear_after_secs: 0,
+            allow_restricted_copy: true,
+        });
+        (backend, secure)
+    }
+
+    #[test]
+    fn test_copy_is_cleared_after_timer() {
+        let (backend, secure) = clipboard();
+        let receipt = secure
+            .copy("secret", DataClassification::Restricted, Some("doc-1"))
+            .unwrap();
+        assert!(receipt.logged);
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(backend.get_text().unwrap(), None);
+        let trail = secure.compliance.lock().unwrap().export_audit_log();
+        assert_eq!(trail.len(), 1);
+        assert_eq!(trail[0].resource, "doc-1");
+    }
+
+    #[test]
+    fn test_timer_leaves_newer_clipboard_contents() {
+        let (backend, secure) = clipboard();
+        secure
+            .copy("first", DataClassification::Internal, None)
+            .unwrap();
+        backend.set_text("user copied this").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(
+            backend.get_text().unwrap().as_deref(),
+            Some("user copied this")
+        );
+    }
+
+    #[test]
+    fn test_restricted_copy_can_be_blocked() {
+        let (backend, secure) = clipboard();
+        secure.set_policy(ClipboardPolicy {
+            allow_restricted_copy: false,
+            ..ClipboardPolicy::default()
+        });
+        assert!(secure
+            .copy("secret", DataClassification::Restricted, None)
+            .is_err());
+        assert_eq!(backend.get_text().unwrap(), None);
+    }
+}
@@ -0,0 +1,546 @@
+//! Destructive Operation Confirmation
+//!
+//! Optional second factor for irreversible actions. Once a passphrase or
+//! TOTP authenticator is configured, services refuse to purge the trash,
+//! delete a project, turn off backup encryption or restore over existing
+//! data unless they are handed a fresh, single-use grant issued by
+//! `ConfirmationGuard::confirm` for that exact operation.
+
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+use crate::compliance::ComplianceService;
+use crate::error::{ErrorCode, UserFacingError};
+use crate::security::passphrase::{constant_time_eq, hash_passphrase};
+use crate::security::secure_storage::SecureStorageService;
+use crate::services::SecurityService;
+
+/// Keyring entry holding the confirmation settings
+const STORAGE_KEY: &str = "destructive_confirmation";
+
+/// How long a grant stays valid after confirmation
+const GRANT_LIFETIME_SECS: i64 = 120;
+
+/// Failed attempts allowed before confirmation is locked out
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+const LOCKOUT_SECS: i64 = 300;
+
+/// TOTP parameters (RFC 6238 defaults, as used by authenticator apps)
+const TOTP_STEP_SECS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+
+/// Irreversible operations that need confirmation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DestructiveOperation {
+    PurgeTrash,
+    DeleteProject,
+    DisableEncryption,
+    RestoreOverExisting,
+}
+
+impl DestructiveOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DestructiveOperation::PurgeTrash => "purge_trash",
+            DestructiveOperation::DeleteProject => "delete_project",
+            DestructiveOperation::DisableEncryption => "disable_encryption",
+            DestructiveOperation::RestoreOverExisting => "restore_over_existing",
+        }
+    }
+}
+
+/// Second factor the user has configured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmationMethod {
+    Passphrase,
+    Totp,
+}
+
+/// What the user supplies to confirm
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", content = "value", rename_all = "snake_case")]
+pub enum ConfirmationProof {
+    Passphrase(String),
+    Totp(String),
+}
+
+/// Single-use permission to perform one destructive operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmationGrant {
+    pub token: String,
+    pub operation: DestructiveOperation,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Returned when TOTP is enabled, for the user's authenticator app
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ConfirmationError {
+    #[error("Confirmation is required before {0}")]
+    Required(&'static str),
+    #[error("Confirmation failed")]
+    Invalid,
+    #[error("Confirmation grant has expired or was already used")]
+    Expired,
+    #[error("Confirmation grant was issued for a different operation")]
+    WrongOperation,
+    #[error("Passphrase must be at least 8 characters")]
+    WeakPassphrase,
+    #[error("Too many failed confirmation attempts; try again later")]
+    LockedOut,
+    #[error("Failed to store confirmation settings: {0}")]
+    Storage(String),
+    #[error("Confirmation settings are unavailable")]
+    Unavailable,
+}
+
+impl UserFacingError for ConfirmationError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ConfirmationError::WeakPassphrase => ErrorCode::InvalidInput,
+            ConfirmationError::LockedOut => ErrorCode::RateLimited,
+            ConfirmationError::Storage(_) => ErrorCode::Storage,
+            _ => ErrorCode::PermissionDenied,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct StoredSettings {
+    passphrase: Option<(String, String)>,
+    totp_secret: Option<String>,
+}
+
+#[derive(Default)]
+struct GuardState {
+    settings: StoredSettings,
+    grants: HashMap<String, ConfirmationGrant>,
+    failed_attempts: u32,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+/// Issues and checks confirmation grants for destructive operations
+pub struct ConfirmationGuard {
+    state: Mutex<GuardState>,
+    storage: Option<Arc<SecureStorageService>>,
+    audit_log: Option<Arc<Mutex<ComplianceService>>>,
+}
+
+impl std::fmt::Debug for ConfirmationGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfirmationGuard")
+            .field("method", &self.method())
+            .finish()
+    }
+}
+
+impl Default for ConfirmationGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfirmationGuard {
+    /// A guard with no second factor configured (every operation allowed)
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(GuardState::default()),
+            storage: None,
+            audit_log: None,
+        }
+    }
+
+    /// Load settings from, and save changes to, the OS keyring
+    pub fn with_storage(self, storage: Arc<SecureStorageService>) -> Self {
+        let settings = storage
+            .find_api_key(STORAGE_KEY)
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        if let Ok(mut state) = self.state.lock() {
+            state.settings = settings;
+        }
+        Self {
+            storage: Some(storage),
+            ..self
+        }
+    }
+
+    /// Record confirmations and refusals in the compliance audit log
+    pub fn with_audit_log(mut self, audit_log: Arc<Mutex<ComplianceService>>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// The configured second factor, if any. TOTP wins when both are set.
+    /// Settings that can't be read are an error, so callers deny rather
+    /// than take them for "nothing configured".
+    pub fn method(&self) -> Result<Option<ConfirmationMethod>, ConfirmationError> {
+        let state = self
+            .state
+            .lock()
+            .map_err(|_| ConfirmationError::Unavailable)?;
+        Ok(if state.settings.totp_secret.is_some() {
+            Some(ConfirmationMethod::Totp)
+        } else if state.settings.passphrase.is_some() {
+            Some(ConfirmationMethod::Passphrase)
+        } else {
+            None
+        })
+    }
+
+    /// Set or change the confirmation passphrase. Changing an existing
+    /// configuration requires proof of the current one.
+    pub fn set_passphrase(
+        &self,
+        current: Option<&ConfirmationProof>,
+        passphrase: &str,
+    ) -> Result<(), ConfirmationError> {
+        if passphrase.chars().count() < 8 {
+            return Err(ConfirmationError::WeakPassphrase);
+        }
+        self.require_current(current)?;
+        let salt = SecurityService::new().secure_random_string(16);
+        let hash = hash_passphrase(&salt, passphrase);
+        self.update_settings(|settings| settings.passphrase = Some((salt, hash)))
+    }
+
+    /// Enable TOTP and return the shared secret for the authenticator app
+    pub fn enable_totp(
+        &self,
+        current: Option<&ConfirmationProof>,
+        account: &str,
+    ) -> Result<TotpEnrollment, ConfirmationError> {
+        self.require_current(current)?;
+        let mut secret_bytes = [0u8; 20];
+        rand::thread_rng().fill_bytes(&mut secret_bytes);
+        let secret = base32_encode(&secret_bytes);
+        self.update_settings(|settings| settings.totp_secret = Some(secret.clone()))?;
+
+        Ok(TotpEnrollment {
+            otpauth_uri: format!(
+                "otpauth://totp/Herding%20Cats:{}?secret={}&issuer=Herding%20Cats&digits={}&period={}",
+                account.replace(' ', "%20"),
+                secret,
+                TOTP_DIGITS,
+                TOTP_STEP_SECS
+            ),
+            secret,
+        })
+    }
+
+    /// Remove every configured second factor
+    pub fn disable(&self, current: &ConfirmationProof) -> Result<(), ConfirmationError> {
+        self.require_current(Some(current))?;
+        self.update_settings(|settings| *settings = StoredSettings::default())
+    }
+
+    /// Check the proof and issue a single-use grant for `operation`
+    pub fn confirm(
+        &self,
+        operation: DestructiveOperation,
+        proof: &ConfirmationProof,
+    ) -> Result<ConfirmationGrant, ConfirmationError> {
+        let result = self.verify(proof);
+        self.audit(
+            operation,
+            if result.is_ok() {
+                "confirmed"
+            } else {
+                "confirmation failed"
+            },
+        );
+        result?;
+
+        let grant = ConfirmationGrant {
+            token: uuid::Uuid::new_v4().to_string(),
+            operation,
+            expires_at: Utc::now() + Duration::seconds(GRANT_LIFETIME_SECS),
+        };
+        if let Ok(mut state) = self.state.lock() {
+            state.grants.retain(|_, g| g.expires_at > Utc::now());
+            state.grants.insert(grant.token.clone(), grant.clone());
+        }
+        Ok(grant)
+    }
+
+    /// Called by services before an irreversible operation. Passes when no
+    /// second factor is configured; otherwise consumes a matching grant.
+    pub fn authorize(
+        &self,
+        operation: DestructiveOperation,
+        token: Option<&str>,
+    ) -> Result<(), ConfirmationError> {
+        match self.method() {
+            Ok(None) => return Ok(()),
+            Ok(Some(_)) => {}
+            Err(e) => {
+                self.audit(operation, &format!("refused: {}", e));
+                return Err(e);
+            }
+        }
+        let Some(token) = token else {
+            self.audit(operation, "refused: no confirmation");
+            return Err(ConfirmationError::Required(operation.as_str()));
+        };
+
+        let mut state = self.state.lock().map_err(|_| ConfirmationError::Expired)?;
+        let result = match state.grants.get(token) {
+            None => Err(ConfirmationError::Expired),
+            Some(grant) if grant.expires_at <= Utc::now() => Err(ConfirmationError::Expired),
+            Some(grant) if grant.operation != operation => Err(ConfirmationError::WrongOperation),
+            Some(_) => Ok(()),
+        };
+        if result.is_ok() {
+            state.grants.remove(token);
+        }
+        drop(state);
+
+        if let Err(e) = &result {
+            self.audit(operation, &format!("refused: {}", e));
+        }
+        result
+    }
+
+    fn require_current(
+        &self,
+        current: Option<&ConfirmationProof>,
+    ) -> Result<(), ConfirmationError> {
+        match (self.method()?, current) {
+            (None, _) => Ok(()),
+            (Some(_), Some(proof)) => self.verify(proof),
+            (Some(_), None) => Err(ConfirmationError::Invalid),
+        }
+    }
+
+    fn verify(&self, proof: &ConfirmationProof) -> Result<(), ConfirmationError> {
+        let mut state = self.state.lock().map_err(|_| ConfirmationError::Invalid)?;
+        let now = Utc::now();
+        if state.locked_until.is_some_and(|until| until > now) {
+            return Err(ConfirmationError::LockedOut);
+        }
+
+        let valid = match proof {
+            ConfirmationProof::Passphrase(passphrase) => state
+                .settings
+                .passphrase
+                .as_ref()
+                .is_some_and(|(salt, hash)| {
+                    constant_time_eq(
+                        hash_passphrase(salt, passphrase).as_bytes(),
+                        hash.as_bytes(),
+                    )
+                }),
+            ConfirmationProof::Totp(code) => state
+                .settings
+                .totp_secret
+                .as_deref()
+                .and_then(base32_decode)
+                .is_some_and(|secret| totp_matches(&secret, code.trim(), now.timestamp())),
+        };
+
+        if valid {
+            state.failed_attempts = 0;
+            state.locked_until = None;
+            Ok(())
+        } else {
+            state.failed_attempts += 1;
+            if state.failed_attempts >= MAX_FAILED_ATTEMPTS {
+                state.failed_attempts = 0;
+                state.locked_until = Some(now + Duration::seconds(LOCKOUT_SECS));
+            }
+            Err(ConfirmationError::Invalid)
+        }
+    }
+
+    fn update_settings(
+        &self,
+        change: impl FnOnce(&mut StoredSettings),
+    ) -> Result<(), ConfirmationError> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|e| ConfirmationError::Storage(e.to_string()))?;
+        let mut settings = state.settings.clone();
+        change(&mut settings);
+
+        if let Some(storage) = &self.storage {
+            let json = serde_json::to_string(&settings)
+                .map_err(|e| ConfirmationError::Storage(e.to_string()))?;
+            storage
+                .set_api_key(STORAGE_KEY, &json)
+                .map_err(|e| ConfirmationError::Storage(e.to_string()))?;
+        }
+        state.settings = settings;
+        state.grants.clear();
+        Ok(())
+    }
+
+    fn audit(&self, operation: DestructiveOperation, result: &str) {
+        if let Some(audit_log) = &self.audit_log {
+            if let Ok(mut log) = audit_log.lock() {
+                log.log_audit("destructive_confirmation", operation.as_str(), result);
+            }
+        }
+    }
+}
+
+/// RFC 6238 code for the given time step
+fn totp_code(secret: &[u8], step: i64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    )
+}
+
+/// Accept the current code and one step either side for clock drift
+fn totp_matches(secret: &[u8], code: &str, unix_time: i64) -> bool {
+    let step = unix_time / TOTP_STEP_SECS;
+    (step - 1..=step + 1)
+        .any(|s| constant_time_eq(totp_code(secret, s).as_bytes(), code.as_bytes()))
+}
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    for chunk in bytes.chunks(5) {
+        let mut buffer = [0u8; 5];
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        let value = buffer.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+        let chars = (chunk.len() * 8).div_ceil(5);
+        for i in 0..chars {
+            let index = ((value >> (35 - i * 5)) & 0x1f) as usize;
+            output.push(BASE32_ALPHABET[index] as char);
+        }
+    }
+    output
+}
+
+fn base32_decode(text: &str) -> Option<Vec<u8>> {
+    let mut bits = 0u64;
+    let mut bit_count = 0;
+    let mut output = Vec::new();
+    for c in text.chars().filter(|c| *c != '=' && !c.is_whitespace()) {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a as char == c.to_ascii_uppercase())?;
+        bits = (bits << 5) | value as u64;
+        bit_count += 5;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            output.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totp_matches_rfc_vector() {
+        // RFC 6238 appendix B, SHA-1, T = 59s
+        let secret = b"12345678901234567890";
+        assert_eq!(totp_code(secret, 59 / TOTP_STEP_SECS), "287082");
+        assert!(totp_matches(secret, "287082", 59));
+        assert!(!totp_matches(secret, "287082", 59 + 5 * TOTP_STEP_SECS));
+        assert_eq!(base32_decode(&base32_encode(secret)).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_unconfigured_guard_allows_operations() {
+        let guard = ConfirmationGuard::new();
+        assert!(guard
+            .authorize(DestructiveOperation::DeleteProject, None)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_grant_is_single_use_and_operation_bound() {
+        let guard = ConfirmationGuard::new();
+        guard.set_passphrase(None, "correct horse").unwrap();
+        let op = DestructiveOperation::DeleteProject;
+
+        assert_eq!(
+            guard.authorize(op, None),
+            Err(ConfirmationError::Required("delete_project"))
+        );
+        assert!(guard
+            .confirm(op, &ConfirmationProof::Passphrase("wrong".into()))
+            .is_err());
+
+        let grant = guard
+            .confirm(op, &ConfirmationProof::Passphrase("correct horse".into()))
+            .unwrap();
+        assert_eq!(
+            guard.authorize(DestructiveOperation::PurgeTrash, Some(&grant.token)),
+            Err(ConfirmationError::WrongOperation)
+        );
+        assert!(guard.authorize(op, Some(&grant.token)).is_ok());
+        assert_eq!(
+            guard.authorize(op, Some(&grant.token)),
+            Err(ConfirmationError::Expired)
+        );
+    }
+
+    #[test]
+    fn test_poisoned_state_denies() {
+        let guard = ConfirmationGuard::new();
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _state = guard.state.lock().unwrap();
+            panic!("poison the lock");
+        }));
+        assert_eq!(guard.method(), Err(ConfirmationError::Unavailable));
+        assert_eq!(
+            guard.authorize(DestructiveOperation::DisableEncryption, None),
+            Err(ConfirmationError::Unavailable)
+        );
+    }
+
+    #[test]
+    fn test_repeated_failures_lock_out() {
+        let guard = ConfirmationGuard::new();
+        guard.set_passphrase(None, "correct horse").unwrap();
+        let wrong = ConfirmationProof::Passphrase("nope".into());
+        for _ in 0..MAX_FAILED_ATTEMPTS {
+            let _ = guard.confirm(DestructiveOperation::PurgeTrash, &wrong);
+        }
+        assert_eq!(
+            guard
+                .confirm(
+                    DestructiveOperation::PurgeTrash,
+                    &ConfirmationProof::Passphrase("correct horse".into())
+                )
+                .unwrap_err(),
+            ConfirmationError::LockedOut
+        );
+    }
+}
@@ -0,0 +1,447 @@
+//! Provider Credential Management
+//!
+//! Managed surface over `SecureStorageService` for third-party credentials:
+//! keys are validated against the provider before being stored, only ever
+//! returned masked, carry optional expiry dates, and every change is written
+//! to the compliance audit log.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::compliance::ComplianceService;
+use crate::security::network::{NetworkClient, NetworkError};
+use crate::security::secure_storage::SecureStorageService;
+
+/// Days before expiry at which a credential starts warning
+pub const EXPIRY_WARNING_DAYS: i64 = 14;
+
+/// What a provider credential is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderCategory {
+    Ai,
+    CloudSync,
+    WebSearch,
+}
+
+/// Third-party providers the app can hold credentials for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialProvider {
+    OpenAi,
+    Anthropic,
+    Dropbox,
+    GoogleDrive,
+    BraveSearch,
+    SerpApi,
+}
+
+impl CredentialProvider {
+    pub const ALL: [CredentialProvider; 6] = [
+        CredentialProvider::OpenAi,
+        CredentialProvider::Anthropic,
+        CredentialProvider::Dropbox,
+        CredentialProvider::GoogleDrive,
+        CredentialProvider::BraveSearch,
+        CredentialProvider::SerpApi,
+    ];
+
+    /// Keyring account name for the secret
+    pub fn id(&self) -> &'static str {
+        match self {
+            CredentialProvider::OpenAi => "openai",
+            CredentialProvider::Anthropic => "anthropic",
+            CredentialProvider::Dropbox => "dropbox",
+            CredentialProvider::GoogleDrive => "google_drive",
+            CredentialProvider::BraveSearch => "brave_search",
+            CredentialProvider::SerpApi => "serpapi",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            CredentialProvider::OpenAi => "OpenAI",
+            CredentialProvider::Anthropic => "Anthropic",
+            CredentialProvider::Dropbox => "Dropbox",
+            CredentialProvider::GoogleDrive => "Google Drive",
+            CredentialProvider::BraveSearch => "Brave Search",
+            CredentialProvider::SerpApi => "SerpApi",
+        }
+    }
+
+    pub fn category(&self) -> ProviderCategory {
+        match self {
+            CredentialProvider::OpenAi | CredentialProvider::Anthropic => ProviderCategory::Ai,
+            CredentialProvider::Dropbox | CredentialProvider::GoogleDrive => {
+                ProviderCategory::CloudSync
+            }
+            CredentialProvider::BraveSearch | CredentialProvider::SerpApi => {
+                ProviderCategory::WebSearch
+            }
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.id() == id)
+    }
+
+    fn metadata_account(&self) -> String {
+        format!("{}.meta", self.id())
+    }
+
+    /// Cheapest authenticated request that proves the key works
+    fn validation_request(
+        &self,
+        network: &NetworkClient,
+        key: &str,
+    ) -> Result<reqwest::RequestBuilder, NetworkError> {
+        Ok(match self {
+            CredentialProvider::OpenAi => network
+                .get("https://api.openai.com/v1/models")?
+                .bearer_auth(key),
+            CredentialProvider::Anthropic => network
+                .get("https://api.anthropic.com/v1/models")?
+                .header("x-api-key", key)
+                .header("anthropic-version", "2023-06-01"),
+            CredentialProvider::Dropbox => network
+                .post("https://api.dropboxapi.com/2/users/get_current_account")?
+                .bearer_auth(key),
+            CredentialProvider::GoogleDrive => network
+                .get("https://www.googleapis.com/drive/v3/about?fields=user")?
+                .bearer_auth(key),
+            CredentialProvider::BraveSearch => network
+                .get("https://api.search.brave.com/res/v1/web/search?q=test&count=1")?
+                .header("X-Subscription-Token", key),
+            CredentialProvider::SerpApi => network
+                .get("https://serpapi.com/account")?
+                .query(&[("api_key", key)]),
+        })
+    }
+}
+
+/// Result of a live validation call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ValidationOutcome {
+    Valid,
+    /// The provider rejected the key
+    Invalid {
+        http_status: u16,
+    },
+    /// The provider could not be reached or answered unexpectedly
+    Unreachable {
+        message: String,
+    },
+}
+
+impl ValidationOutcome {
+    fn as_audit_result(&self) -> &'static str {
+        match self {
+            ValidationOutcome::Valid => "valid",
+            ValidationOutcome::Invalid { .. } => "invalid",
+            ValidationOutcome::Unreachable { .. } => "unreachable",
+        }
+    }
+}
+
+/// Expiry state of a stored credential
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ExpiryStatus {
+    NoExpiry,
+    Ok { days_left: i64 },
+    ExpiringSoon { days_left: i64 },
+    Expired,
+}
+
+impl ExpiryStatus {
+    pub fn evaluate(expires_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Self {
+        match expires_at {
+            None => ExpiryStatus::NoExpiry,
+            Some(at) if at <= now => ExpiryStatus::Expired,
+            Some(at) => {
+                let days_left = (at - now).num_days();
+                if days_left < EXPIRY_WARNING_DAYS {
+                    ExpiryStatus::ExpiringSoon { days_left }
+                } else {
+                    ExpiryStatus::Ok { days_left }
+                }
+            }
+        }
+    }
+
+    pub fn needs_attention(&self) -> bool {
+        matches!(
+            self,
+            ExpiryStatus::ExpiringSoon { .. } | ExpiryStatus::Expired
+        )
+    }
+}
+
+/// Non-secret facts about a stored credential
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CredentialMetadata {
+    masked: String,
+    added_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+    last_validated_at: Option<DateTime<Utc>>,
+    last_validation: Option<ValidationOutcome>,
+}
+
+/// What the frontend sees for a stored credential
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialSummary {
+    pub provider: CredentialProvider,
+    pub display_name: String,
+    pub category: ProviderCategory,
+    pub masked_key: String,
+    pub added_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub expiry: ExpiryStatus,
+    pub last_validated_at: Option<DateTime<Utc>>,
+    pub last_validation: Option<ValidationOutcome>,
+}
+
+impl CredentialSummary {
+    fn from_metadata(provider: CredentialProvider, meta: CredentialMetadata) -> Self {
+        Self {
+            provider,
+            display_name: provider.display_name().to_string(),
+            category: provider.category(),
+            masked_key: meta.masked,
+            added_at: meta.added_at,
+            expires_at: meta.expires_at,
+            expiry: ExpiryStatus::evaluate(meta.expires_at, Utc::now()),
+            last_validated_at: meta.last_validated_at,
+            last_validation: meta.last_validation,
+        }
+    }
+
+    /// Human-readable warning if the credential needs renewing
+    pub fn expiry_warning(&self) -> Option<String> {
+        match self.expiry {
+            ExpiryStatus::Expired => Some(format!("{} credential has expired", self.display_name)),
+            ExpiryStatus::ExpiringSoon { days_left } => Some(format!(
+                "{} credential expires in {} day(s)",
+                self.display_name, days_left
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Show only enough of a secret to recognise it
+pub fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() < 12 {
+        return "••••••••".to_string();
+    }
+    let prefix: String = chars[..3].iter().collect();
+    let suffix: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}••••{}", prefix, suffix)
+}
+
+/// Add, test, list and remove provider credentials
+pub struct CredentialManager {
+    storage: Arc<SecureStorageService>,
+    compliance: Arc<Mutex<ComplianceService>>,
+    network: Arc<NetworkClient>,
+}
+
+impl CredentialManager {
+    pub fn new(
+        storage: Arc<SecureStorageService>,
+        compliance: Arc<Mutex<ComplianceService>>,
+        network: Arc<NetworkClient>,
+    ) -> Self {
+        Self {
+            storage,
+            compliance,
+            network,
+        }
+    }
+
+    /// Validate a key against the provider and store it if accepted.
+    /// Keys the provider rejects are never stored; keys that cannot be
+    /// checked (offline) are stored with the outcome recorded.
+    pub async fn add_credential(
+        &self,
+        provider: CredentialProvider,
+        key: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<CredentialSummary> {
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(anyhow!("Credential cannot be empty"));
+        }
+
+        let outcome = self.validate(provider, key).await;
+        if let ValidationOutcome::Invalid { http_status } = outcome {
+            self.audit("credential_add", provider, "rejected");
+            return Err(anyhow!(
+                "{} rejected the credential (HTTP {})",
+                provider.display_name(),
+                http_status
+            ));
+        }
+
+        let now = Utc::now();
+        let meta = CredentialMetadata {
+            masked: mask_secret(key),
+            added_at: now,
+            expires_at,
+            last_validated_at: Some(now),
+            last_validation: Some(outcome),
+        };
+        self.storage.set_api_key(provider.id(), key)?;
+        self.save_metadata(provider, &meta)?;
+        self.audit("credential_add", provider, "success");
+
+        Ok(CredentialSummary::from_metadata(provider, meta))
+    }
+
+    /// Re-run the live validation call for a stored credential
+    pub async fn test_credential(&self, provider: CredentialProvider) -> Result<CredentialSummary> {
+        let key = self
+            .storage
+            .find_api_key(provider.id())?
+            .ok_or_else(|| anyhow!("No {} credential stored", provider.display_name()))?;
+
+        let outcome = self.validate(provider, &key).await;
+        self.audit("credential_test", provider, outcome.as_audit_result());
+
+        let mut meta = self
+            .load_metadata(provider)?
+            .unwrap_or_else(|| CredentialMetadata {
+                masked: mask_secret(&key),
+                added_at: Utc::now(),
+                expires_at: None,
+                last_validated_at: None,
+                last_validation: None,
+            });
+        meta.last_validated_at = Some(Utc::now());
+        meta.last_validation = Some(outcome);
+        self.save_metadata(provider, &meta)?;
+
+        Ok(CredentialSummary::from_metadata(provider, meta))
+    }
+
+    /// Remove a stored credential and its metadata
+    pub fn remove_credential(&self, provider: CredentialProvider) -> Result<bool> {
+        if self.storage.find_api_key(provider.id())?.is_none() {
+            return Ok(false);
+        }
+        self.storage.delete_api_key(provider.id())?;
+        if self
+            .storage
+            .find_api_key(&provider.metadata_account())?
+            .is_some()
+        {
+            self.storage.delete_api_key(&provider.metadata_account())?;
+        }
+        self.audit("credential_remove", provider, "success");
+        Ok(true)
+    }
+
+    /// Masked view of one stored credential
+    pub fn get_credential(
+        &self,
+        provider: CredentialProvider,
+    ) -> Result<Option<CredentialSummary>> {
+        Ok(self
+            .load_metadata(provider)?
+            .map(|meta| CredentialSummary::from_metadata(provider, meta)))
+    }
+
+    /// Masked view of every stored credential
+    pub fn list_credentials(&self) -> Result<Vec<CredentialSummary>> {
+        let mut summaries = Vec::new();
+        for provider in CredentialProvider::ALL {
+            if let Some(summary) = self.get_credential(provider)? {
+                summaries.push(summary);
+            }
+        }
+        Ok(summaries)
+    }
+
+    async fn validate(&self, provider: CredentialProvider, key: &str) -> ValidationOutcome {
+        let request = match provider.validation_request(&self.network, key) {
+            Ok(request) => request.timeout(std::time::Duration::from_secs(15)),
+            Err(e) => {
+                return ValidationOutcome::Unreachable {
+                    message: e.to_string(),
+                }
+            }
+        };
+        match request.send().await {
+            Ok(response) => {
+                let status = response.status();
+                if status.is_success() {
+                    ValidationOutcome::Valid
+                } else if status.as_u16() == 401 || status.as_u16() == 403 {
+                    ValidationOutcome::Invalid {
+                        http_status: status.as_u16(),
+                    }
+                } else {
+                    ValidationOutcome::Unreachable {
+                        message: format!("Unexpected HTTP {}", status.as_u16()),
+                    }
+                }
+            }
+            Err(e) => ValidationOutcome::Unreachable {
+                message: e.to_string(),
+            },
+        }
+    }
+
+    fn load_metadata(&self, provider: CredentialProvider) -> Result<Option<CredentialMetadata>> {
+        match self.storage.find_api_key(&provider.metadata_account())? {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn save_metadata(&self, provider: CredentialProvider, meta: &CredentialMetadata) -> Result<()> {
+        self.storage
+            .set_api_key(&provider.metadata_account(), &serde_json::to_string(meta)?)
+    }
+
+    fn audit(&self, action: &str, provider: CredentialProvider, result: &str) {
+        if let Ok(mut compliance) = self.compliance.lock() {
+            compliance.log_audit(action, &format!("credential:{}", provider.id()), result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_mask_secret_hides_middle() {
+        assert_eq!(mask_secret("sk-abcdefghijklmnop"), "sk-••••mnop");
+        assert_eq!(mask_secret("short"), "••••••••");
+    }
+
+    #[test]
+    fn test_expiry_status_thresholds() {
+        let now = Utc::now();
+        assert_eq!(ExpiryStatus::evaluate(None, now), ExpiryStatus::NoExpiry);
+        assert_eq!(
+            ExpiryStatus::evaluate(Some(now - Duration::days(1)), now),
+            ExpiryStatus::Expired
+        );
+        assert!(ExpiryStatus::evaluate(Some(now + Duration::days(3)), now).needs_attention());
+        assert!(!ExpiryStatus::evaluate(Some(now + Duration::days(90)), now).needs_attention());
+    }
+
+    #[test]
+    fn test_provider_ids_round_trip() {
+        for provider in CredentialProvider::ALL {
+            assert_eq!(CredentialProvider::from_id(provider.id()), Some(provider));
+        }
+    }
+}
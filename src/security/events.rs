@@ -0,0 +1,123 @@
+//! Security Events
+//!
+//! In-memory log of security-relevant events (secrets found in outgoing
+//! content, blocked operations, ...) for the UI to surface. Events are also
+//! mirrored into the compliance audit log when one is attached.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::compliance::ComplianceService;
+
+/// Events kept in memory before the oldest are dropped
+const MAX_EVENTS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SecurityEventSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// Category of security event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventKind {
+    SecretDetected,
+    OperationBlocked,
+    /// Location or camera details removed from an imported file
+    MetadataStripped,
+}
+
+impl SecurityEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecurityEventKind::SecretDetected => "secret_detected",
+            SecurityEventKind::OperationBlocked => "operation_blocked",
+            SecurityEventKind::MetadataStripped => "metadata_stripped",
+        }
+    }
+}
+
+/// A single security event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityEvent {
+    pub id: String,
+    pub kind: SecurityEventKind,
+    pub severity: SecurityEventSeverity,
+    /// Where the event happened, e.g. "ai_submission" or "export"
+    pub source: String,
+    pub description: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl SecurityEvent {
+    pub fn new(
+        kind: SecurityEventKind,
+        severity: SecurityEventSeverity,
+        source: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            severity,
+            source: source.into(),
+            description: description.into(),
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// Bounded store of recent security events
+#[derive(Default)]
+pub struct SecurityEventLog {
+    events: Mutex<VecDeque<SecurityEvent>>,
+    audit_log: Option<Arc<Mutex<ComplianceService>>>,
+}
+
+impl SecurityEventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mirror every recorded event into the compliance audit log
+    pub fn with_audit_log(mut self, audit_log: Arc<Mutex<ComplianceService>>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    pub fn record(&self, event: SecurityEvent) {
+        if let Some(audit_log) = &self.audit_log {
+            if let Ok(mut log) = audit_log.lock() {
+                log.log_audit(
+                    &format!("security_{}", event.kind.as_str()),
+                    &event.source,
+                    &event.description,
+                );
+            }
+        }
+        if let Ok(mut events) = self.events.lock() {
+            if events.len() >= MAX_EVENTS {
+                events.pop_front();
+            }
+            events.push_back(event);
+        }
+    }
+
+    /// Recorded events, oldest first
+    pub fn events(&self) -> Vec<SecurityEvent> {
+        self.events
+            .lock()
+            .map(|events| events.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut events) = self.events.lock() {
+            events.clear();
+        }
+    }
+}
@@ -1 +1,9 @@
-pub mod secure_storage;
\ No newline at end of file
+pub mod clipboard;
+pub mod confirmation;
+pub mod credentials;
+pub mod events;
+pub mod network;
+pub mod passphrase;
+pub mod secrets_scanner;
+pub mod secure_storage;
+pub mod threat_detector;
@@ -0,0 +1,351 @@
+//! Network Egress Control
+//!
+//! All outbound HTTP goes through `NetworkClient`, which only lets requests
+//! reach hosts on the allow-list from `SecurityConfig`, writes every
+//! destination to the compliance audit log, and refuses everything while the
+//! global offline switch is on. Redirects are re-checked against the list.
+//! Connections made outside HTTP, such as SMTP, are checked and logged
+//! with `authorize_connection` before they are opened.
+
+use once_cell::sync::OnceCell;
+use reqwest::{redirect, Method, Url};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::compliance::ComplianceService;
+use crate::config_security::SecurityConfig;
+use crate::error::AppError;
+
+/// Global offline / kill switch shared by every `NetworkClient`
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Process-wide client for services that are not handed one explicitly
+static GLOBAL_CLIENT: OnceCell<Arc<NetworkClient>> = OnceCell::new();
+
+/// Why an outbound request was refused
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum NetworkError {
+    #[error("Network access is disabled (offline mode)")]
+    Offline,
+    #[error("Host '{0}' is not on the network allow-list")]
+    HostNotAllowed(String),
+    #[error("Invalid URL '{0}'")]
+    InvalidUrl(String),
+    #[error("Insecure scheme '{0}' is not allowed")]
+    InsecureScheme(String),
+}
+
+impl From<NetworkError> for AppError {
+    fn from(err: NetworkError) -> Self {
+        AppError::Network(err.to_string())
+    }
+}
+
+/// Turn the global offline switch on or off
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::SeqCst);
+}
+
+/// Whether all outbound traffic is currently blocked
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::SeqCst)
+}
+
+/// Does `host` match an allow-list entry? `*.example.com` matches
+/// subdomains of example.com but not example.com itself.
+pub fn host_allowed(allow_list: &[String], host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    allow_list.iter().any(|entry| {
+        let entry = entry.to_lowercase();
+        match entry.strip_prefix("*.") {
+            Some(domain) => host.ends_with(&format!(".{}", domain)),
+            None => host == entry,
+        }
+    })
+}
+
+fn check_url(allow_list: &[String], url: &Url) -> Result<(), NetworkError> {
+    if is_offline() {
+        return Err(NetworkError::Offline);
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| NetworkError::InvalidUrl(url.to_string()))?;
+    let loopback = host == "localhost" || host == "127.0.0.1" || host == "[::1]";
+    match url.scheme() {
+        "https" => {}
+        "http" if loopback => {}
+        scheme => return Err(NetworkError::InsecureScheme(scheme.to_string())),
+    }
+    if loopback || host_allowed(allow_list, host) {
+        Ok(())
+    } else {
+        Err(NetworkError::HostNotAllowed(host.to_string()))
+    }
+}
+
+/// Allow-listed, audited HTTP client
+pub struct NetworkClient {
+    allow_list: Arc<RwLock<Vec<String>>>,
+    audit_log: Option<Arc<Mutex<ComplianceService>>>,
+    client: reqwest::Client,
+    /// Built on first use: the blocking client must not be created or
+    /// dropped on an async runtime thread
+    blocking_client: OnceCell<reqwest::blocking::Client>,
+}
+
+impl NetworkClient {
+    /// Create a client from the security configuration
+    pub fn new(config: &SecurityConfig) -> Self {
+        if config.offline_mode {
+            set_offline(true);
+        }
+        let allow_list = Arc::new(RwLock::new(
+            config
+                .network_allow_list
+                .iter()
+                .map(|h| h.to_lowercase())
+                .collect(),
+        ));
+
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(60))
+                .redirect(Self::redirect_policy(allow_list.clone()))
+                .build()
+                .unwrap_or_default(),
+            blocking_client: OnceCell::new(),
+            allow_list,
+            audit_log: None,
+        }
+    }
+
+    /// Record every request destination in the compliance audit log
+    pub fn with_audit_log(mut self, audit_log: Arc<Mutex<ComplianceService>>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Make this the client returned by `NetworkClient::global()`.
+    /// Has no effect if a global client is already in use.
+    pub fn install_global(client: Arc<NetworkClient>) -> bool {
+        GLOBAL_CLIENT.set(client).is_ok()
+    }
+
+    /// The process-wide client, created from the default config if none was installed
+    pub fn global() -> Arc<NetworkClient> {
+        GLOBAL_CLIENT
+            .get_or_init(|| Arc::new(NetworkClient::new(&SecurityConfig::default())))
+            .clone()
+    }
+
+    /// Add a host to the allow-list at runtime
+    pub fn allow_host(&self, host: &str) {
+        if let Ok(mut list) = self.allow_list.write() {
+            let host = host.to_lowercase();
+            if !list.contains(&host) {
+                list.push(host);
+            }
+        }
+    }
+
+    /// Remove a host from the allow-list at runtime
+    pub fn disallow_host(&self, host: &str) {
+        if let Ok(mut list) = self.allow_list.write() {
+            list.retain(|h| !h.eq_ignore_ascii_case(host));
+        }
+    }
+
+    /// Current allow-list
+    pub fn allow_list(&self) -> Vec<String> {
+        self.allow_list
+            .read()
+            .map(|list| list.clone())
+            .unwrap_or_default()
+    }
+
+    /// Check a URL against the offline switch and allow-list without sending
+    pub fn check(&self, url: &str) -> Result<Url, NetworkError> {
+        let parsed = Url::parse(url).map_err(|_| NetworkError::InvalidUrl(url.to_string()))?;
+        check_url(&self.allow_list(), &parsed)?;
+        Ok(parsed)
+    }
+
+    /// Start an async request to an allow-listed URL
+    pub fn request(
+        &self,
+        method: Method,
+        url: &str,
+    ) -> Result<reqwest::RequestBuilder, NetworkError> {
+        let parsed = self.authorize(&method, url)?;
+        Ok(self.client.request(method, parsed))
+    }
+
+    pub fn get(&self, url: &str) -> Result<reqwest::RequestBuilder, NetworkError> {
+        self.request(Method::GET, url)
+    }
+
+    pub fn post(&self, url: &str) -> Result<reqwest::RequestBuilder, NetworkError> {
+        self.request(Method::POST, url)
+    }
+
+    /// Start a blocking request to an allow-listed URL
+    pub fn blocking_request(
+        &self,
+        method: Method,
+        url: &str,
+    ) -> Result<reqwest::blocking::RequestBuilder, NetworkError> {
+        let parsed = self.authorize(&method, url)?;
+        let client = self.blocking_client.get_or_init(|| {
+            reqwest::blocking::Client::builder()
+                .timeout(Duration::from_secs(60))
+                .redirect(Self::redirect_policy(self.allow_list.clone()))
+                .build()
+                .unwrap_or_default()
+        });
+        Ok(client.request(method, parsed))
+    }
+
+    pub fn blocking_get(
+        &self,
+        url: &str,
+    ) -> Result<reqwest::blocking::RequestBuilder, NetworkError> {
+        self.blocking_request(Method::GET, url)
+    }
+
+    /// Check a direct connection to `host:port` against the offline switch
+    /// and allow-list, and record it in the audit log. Call before opening
+    /// any socket that doesn't go through this client's HTTP requests.
+    pub fn authorize_connection(
+        &self,
+        protocol: &str,
+        host: &str,
+        port: u16,
+    ) -> Result<(), NetworkError> {
+        let loopback = host == "localhost" || host == "127.0.0.1" || host == "::1";
+        let result = if is_offline() {
+            Err(NetworkError::Offline)
+        } else if loopback || host_allowed(&self.allow_list(), host) {
+            Ok(())
+        } else {
+            Err(NetworkError::HostNotAllowed(host.to_string()))
+        };
+        self.audit(
+            &format!("network_{}", protocol.to_lowercase()),
+            &format!("{}://{}:{}", protocol.to_lowercase(), host, port),
+            &result,
+        );
+        result
+    }
+
+    fn authorize(&self, method: &Method, url: &str) -> Result<Url, NetworkError> {
+        let result = self.check(url);
+        // Log scheme, host and path only: query strings can carry credentials
+        let destination = Url::parse(url)
+            .map(|u| {
+                format!(
+                    "{}://{}{}",
+                    u.scheme(),
+                    u.host_str().unwrap_or(""),
+                    u.path()
+                )
+            })
+            .unwrap_or_else(|_| "<invalid url>".to_string());
+        self.audit(
+            &format!("network_{}", method.as_str().to_lowercase()),
+            &destination,
+            &result,
+        );
+        result
+    }
+
+    fn audit<T>(&self, action: &str, destination: &str, result: &Result<T, NetworkError>) {
+        let outcome = match result {
+            Ok(_) => "allowed".to_string(),
+            Err(e) => format!("blocked: {}", e),
+        };
+        if let Some(audit_log) = &self.audit_log {
+            if let Ok(mut log) = audit_log.lock() {
+                log.log_audit(action, destination, &outcome);
+            }
+        }
+        log::debug!("{} {} -> {}", action, destination, outcome);
+    }
+
+    fn redirect_policy(allow_list: Arc<RwLock<Vec<String>>>) -> redirect::Policy {
+        redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= 10 {
+                return attempt.error("too many redirects");
+            }
+            let list = allow_list.read().map(|l| l.clone()).unwrap_or_default();
+            match check_url(&list, attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(e) => attempt.error(e),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_allowed_matching() {
+        let list = vec![
+            "api.openai.com".to_string(),
+            "*.githubusercontent.com".to_string(),
+        ];
+        assert!(host_allowed(&list, "API.OpenAI.com"));
+        assert!(host_allowed(&list, "raw.githubusercontent.com"));
+        assert!(!host_allowed(&list, "githubusercontent.com"));
+        assert!(!host_allowed(&list, "evil-openai.com"));
+        assert!(!host_allowed(&list, "api.openai.com.evil.net"));
+    }
+
+    #[test]
+    fn test_check_rejects_unlisted_and_insecure() {
+        let client = NetworkClient::new(&SecurityConfig::default());
+        assert!(client.check("https://api.openai.com/v1/models").is_ok());
+        assert_eq!(
+            client.check("https://example.com/"),
+            Err(NetworkError::HostNotAllowed("example.com".to_string()))
+        );
+        assert_eq!(
+            client.check("http://api.openai.com/"),
+            Err(NetworkError::InsecureScheme("http".to_string()))
+        );
+        client.allow_host("example.com");
+        assert!(client.check("https://example.com/").is_ok());
+    }
+
+    #[test]
+    fn test_connections_are_checked_and_audited() {
+        let audit_log = Arc::new(Mutex::new(ComplianceService::new()));
+        let client =
+            NetworkClient::new(&SecurityConfig::default()).with_audit_log(audit_log.clone());
+        assert_eq!(
+            client.authorize_connection("smtp", "smtp.example.com", 465),
+            Err(NetworkError::HostNotAllowed("smtp.example.com".to_string()))
+        );
+        client.allow_host("smtp.example.com");
+        assert!(client
+            .authorize_connection("smtp", "smtp.example.com", 465)
+            .is_ok());
+        let trail = audit_log
+            .lock()
+            .unwrap()
+            .get_audit_trail("smtp://smtp.example.com:465")
+            .into_iter()
+            .map(|entry| (entry.action.clone(), entry.result.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(trail.len(), 2);
+        assert!(trail[0].1.starts_with("blocked"));
+        assert_eq!(
+            trail[1],
+            ("network_smtp".to_string(), "allowed".to_string())
+        );
+    }
+}
@@ -0,0 +1,45 @@
+//! Passphrase Hashing
+//!
+//! Salted hashes for the passphrases the app checks locally, such as the
+//! app lock and the destructive-operation confirmation, and a comparison
+//! that takes the same time wherever the inputs differ.
+
+use sha2::Sha256;
+
+/// PBKDF2 iterations for a passphrase, as OWASP recommends for
+/// HMAC-SHA256
+#[cfg(not(test))]
+const PASSPHRASE_KDF_ITERATIONS: u32 = 600_000;
+/// Unoptimized test builds would spend minutes on the full count
+#[cfg(test)]
+const PASSPHRASE_KDF_ITERATIONS: u32 = 1_000;
+
+/// PBKDF2-HMAC-SHA256 of a passphrase with its salt, hex encoded
+pub fn hash_passphrase(salt: &str, passphrase: &str) -> String {
+    let key = pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(
+        passphrase.as_bytes(),
+        salt.as_bytes(),
+        PASSPHRASE_KDF_ITERATIONS,
+    );
+    key.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Whether two byte strings are equal, without stopping at the first
+/// difference
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passphrase_hash_is_salted() {
+        let a = hash_passphrase("salt-a", "open sesame");
+        assert_eq!(a, hash_passphrase("salt-a", "open sesame"));
+        assert_ne!(a, hash_passphrase("salt-b", "open sesame"));
+        assert!(constant_time_eq(a.as_bytes(), a.as_bytes()));
+        assert!(!constant_time_eq(a.as_bytes(), b"short"));
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use keyring::Entry;
 
+#[derive(Debug)]
 pub struct SecureStorageService {
     service_name: String,
 }
@@ -29,4 +30,14 @@ impl SecureStorageService {
         entry.delete_password()?;
         Ok(())
     }
+
+    /// Like `get_api_key`, but a missing entry is `None` rather than an error
+    pub fn find_api_key(&self, provider: &str) -> Result<Option<String>> {
+        let entry = Entry::new(&self.service_name, provider)?;
+        match entry.get_password() {
+            Ok(password) => Ok(Some(password)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
 }
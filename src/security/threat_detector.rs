@@ -0,0 +1,437 @@
+//! Threat Detection
+//!
+//! Lightweight anomaly detection over data access: spikes in document
+//! reads, mass deletions and bulk exports during quiet hours. Detected
+//! anomalies become `ThreatAlert`s in the compliance audit log, and bulk
+//! operations that would trip a threshold can be held until the user
+//! confirms them.
+
+use chrono::{DateTime, Duration, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::compliance::ComplianceService;
+
+/// Kind of data access being tracked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AccessKind {
+    DocumentRead,
+    Deletion,
+    Export,
+}
+
+/// Kind of anomaly detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ThreatKind {
+    ReadRateSpike,
+    MassDeletion,
+    OffHoursBulkExport,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ThreatSeverity {
+    Low,
+    Medium,
+    High,
+}
+
+/// Configurable detection thresholds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyThresholds {
+    pub read_window_secs: i64,
+    pub max_reads_per_window: usize,
+    pub delete_window_secs: i64,
+    pub max_deletes_per_window: usize,
+    /// Exports of more items than this during quiet hours are flagged
+    pub max_off_hours_export_items: usize,
+    /// Local hour quiet hours start (inclusive)
+    pub quiet_hours_start: u32,
+    /// Local hour quiet hours end (exclusive); may wrap past midnight
+    pub quiet_hours_end: u32,
+    /// Hold flagged bulk operations until confirmed
+    pub require_confirmation: bool,
+}
+
+impl Default for AnomalyThresholds {
+    fn default() -> Self {
+        Self {
+            read_window_secs: 60,
+            max_reads_per_window: 200,
+            delete_window_secs: 300,
+            max_deletes_per_window: 25,
+            max_off_hours_export_items: 50,
+            quiet_hours_start: 0,
+            quiet_hours_end: 6,
+            require_confirmation: true,
+        }
+    }
+}
+
+impl AnomalyThresholds {
+    fn is_quiet_hour(&self, hour: u32) -> bool {
+        if self.quiet_hours_start <= self.quiet_hours_end {
+            hour >= self.quiet_hours_start && hour < self.quiet_hours_end
+        } else {
+            hour >= self.quiet_hours_start || hour < self.quiet_hours_end
+        }
+    }
+}
+
+/// A detected anomaly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreatAlert {
+    pub id: String,
+    pub kind: ThreatKind,
+    pub severity: ThreatSeverity,
+    pub description: String,
+    pub resource: String,
+    pub count: usize,
+    pub detected_at: DateTime<Local>,
+}
+
+/// Whether a bulk operation may proceed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum AccessDecision {
+    Allow,
+    /// Call `ThreatDetector::confirm` with the token to proceed
+    RequireConfirmation {
+        alert: ThreatAlert,
+        token: String,
+    },
+}
+
+struct PendingOperation {
+    kind: AccessKind,
+    count: usize,
+    resource: String,
+}
+
+#[derive(Default)]
+struct DetectorState {
+    events: HashMap<AccessKind, VecDeque<(DateTime<Local>, usize)>>,
+    last_alert: HashMap<ThreatKind, DateTime<Local>>,
+    alerts: Vec<ThreatAlert>,
+    pending: HashMap<String, PendingOperation>,
+}
+
+/// Access pattern anomaly detector
+pub struct ThreatDetector {
+    thresholds: Mutex<AnomalyThresholds>,
+    compliance: Arc<Mutex<ComplianceService>>,
+    state: Mutex<DetectorState>,
+}
+
+impl ThreatDetector {
+    pub fn new(thresholds: AnomalyThresholds, compliance: Arc<Mutex<ComplianceService>>) -> Self {
+        Self {
+            thresholds: Mutex::new(thresholds),
+            compliance,
+            state: Mutex::new(DetectorState::default()),
+        }
+    }
+
+    pub fn thresholds(&self) -> AnomalyThresholds {
+        self.thresholds
+            .lock()
+            .map(|t| t.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_thresholds(&self, thresholds: AnomalyThresholds) {
+        if let Ok(mut current) = self.thresholds.lock() {
+            *current = thresholds;
+        }
+    }
+
+    /// Record access that has already happened; returns any new alerts
+    pub fn record(&self, kind: AccessKind, count: usize, resource: &str) -> Vec<ThreatAlert> {
+        self.record_at(kind, count, resource, Local::now())
+    }
+
+    /// Check a bulk operation before running it. Operations that would raise
+    /// an alert are held for confirmation when the thresholds require it;
+    /// allowed operations are recorded immediately.
+    pub fn assess_bulk(&self, kind: AccessKind, count: usize, resource: &str) -> AccessDecision {
+        self.assess_bulk_at(kind, count, resource, Local::now())
+    }
+
+    /// Release a held operation; returns false for unknown or used tokens
+    pub fn confirm(&self, token: &str) -> bool {
+        let pending = match self.state.lock() {
+            Ok(mut state) => state.pending.remove(token),
+            Err(_) => None,
+        };
+        match pending {
+            Some(op) => {
+                self.audit(
+                    "threat_confirmed",
+                    &op.resource,
+                    &format!("{:?} of {} item(s) confirmed by user", op.kind, op.count),
+                );
+                self.record(op.kind, op.count, &op.resource);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Alerts raised so far, oldest first
+    pub fn alerts(&self) -> Vec<ThreatAlert> {
+        self.state
+            .lock()
+            .map(|s| s.alerts.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn clear_alerts(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.alerts.clear();
+        }
+    }
+
+    fn assess_bulk_at(
+        &self,
+        kind: AccessKind,
+        count: usize,
+        resource: &str,
+        now: DateTime<Local>,
+    ) -> AccessDecision {
+        let thresholds = self.thresholds();
+        let alert = match self.state.lock() {
+            Ok(state) => detect(&thresholds, &state, kind, count, resource, now),
+            Err(_) => None,
+        };
+
+        match alert {
+            Some(alert) if thresholds.require_confirmation => {
+                let token = uuid::Uuid::new_v4().to_string();
+                self.audit(
+                    "threat_held",
+                    resource,
+                    &format!("{:?}: {}", alert.kind, alert.description),
+                );
+                if let Ok(mut state) = self.state.lock() {
+                    state.pending.insert(
+                        token.clone(),
+                        PendingOperation {
+                            kind,
+                            count,
+                            resource: resource.to_string(),
+                        },
+                    );
+                }
+                AccessDecision::RequireConfirmation { alert, token }
+            }
+            _ => {
+                self.record_at(kind, count, resource, now);
+                AccessDecision::Allow
+            }
+        }
+    }
+
+    fn record_at(
+        &self,
+        kind: AccessKind,
+        count: usize,
+        resource: &str,
+        now: DateTime<Local>,
+    ) -> Vec<ThreatAlert> {
+        let thresholds = self.thresholds();
+        let Ok(mut state) = self.state.lock() else {
+            return Vec::new();
+        };
+
+        let alert = detect(&thresholds, &state, kind, count, resource, now);
+        let window = window_for(&thresholds, kind);
+        let events = state.events.entry(kind).or_default();
+        events.push_back((now, count));
+        while events.front().is_some_and(|(at, _)| now - *at > window) {
+            events.pop_front();
+        }
+
+        let Some(alert) = alert else {
+            return Vec::new();
+        };
+        // One alert per kind per window, so a sustained spike is not noisy
+        if state
+            .last_alert
+            .get(&alert.kind)
+            .is_some_and(|at| now - *at < window)
+        {
+            return Vec::new();
+        }
+        state.last_alert.insert(alert.kind, now);
+        state.alerts.push(alert.clone());
+        drop(state);
+
+        self.audit(
+            "threat_detected",
+            resource,
+            &format!("{:?}: {}", alert.kind, alert.description),
+        );
+        vec![alert]
+    }
+
+    fn audit(&self, action: &str, resource: &str, result: &str) {
+        if let Ok(mut compliance) = self.compliance.lock() {
+            compliance.log_audit(action, resource, result);
+        }
+    }
+}
+
+fn window_for(thresholds: &AnomalyThresholds, kind: AccessKind) -> Duration {
+    match kind {
+        AccessKind::DocumentRead => Duration::seconds(thresholds.read_window_secs),
+        AccessKind::Deletion => Duration::seconds(thresholds.delete_window_secs),
+        AccessKind::Export => Duration::hours(1),
+    }
+}
+
+/// Would adding `count` more accesses of `kind` at `now` be anomalous?
+fn detect(
+    thresholds: &AnomalyThresholds,
+    state: &DetectorState,
+    kind: AccessKind,
+    count: usize,
+    resource: &str,
+    now: DateTime<Local>,
+) -> Option<ThreatAlert> {
+    let window = window_for(thresholds, kind);
+    let recent: usize = state
+        .events
+        .get(&kind)
+        .map(|events| {
+            events
+                .iter()
+                .filter(|(at, _)| now - *at <= window)
+                .map(|(_, n)| n)
+                .sum()
+        })
+        .unwrap_or(0);
+    let total = recent + count;
+
+    let (threat, severity, description) = match kind {
+        AccessKind::DocumentRead if total > thresholds.max_reads_per_window => (
+            ThreatKind::ReadRateSpike,
+            ThreatSeverity::Medium,
+            format!(
+                "{} document reads in {}s (limit {})",
+                total, thresholds.read_window_secs, thresholds.max_reads_per_window
+            ),
+        ),
+        AccessKind::Deletion if total > thresholds.max_deletes_per_window => (
+            ThreatKind::MassDeletion,
+            ThreatSeverity::High,
+            format!(
+                "{} deletions in {}s (limit {})",
+                total, thresholds.delete_window_secs, thresholds.max_deletes_per_window
+            ),
+        ),
+        AccessKind::Export
+            if thresholds.is_quiet_hour(now.hour())
+                && count > thresholds.max_off_hours_export_items =>
+        {
+            (
+                ThreatKind::OffHoursBulkExport,
+                ThreatSeverity::Medium,
+                format!(
+                    "Export of {} items at {:02}:{:02}, during quiet hours",
+                    count,
+                    now.hour(),
+                    now.minute()
+                ),
+            )
+        }
+        _ => return None,
+    };
+
+    Some(ThreatAlert {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind: threat,
+        severity,
+        description,
+        resource: resource.to_string(),
+        count: total,
+        detected_at: now,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn detector() -> ThreatDetector {
+        ThreatDetector::new(
+            AnomalyThresholds {
+                max_reads_per_window: 10,
+                max_deletes_per_window: 5,
+                max_off_hours_export_items: 3,
+                ..AnomalyThresholds::default()
+            },
+            Arc::new(Mutex::new(ComplianceService::new())),
+        )
+    }
+
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2024, 3, 5, hour, minute, second)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_read_spike_alerts_once_per_window() {
+        let detector = detector();
+        for i in 0..10 {
+            assert!(detector
+                .record_at(AccessKind::DocumentRead, 1, "doc", at(12, 0, i))
+                .is_empty());
+        }
+        let alerts = detector.record_at(AccessKind::DocumentRead, 1, "doc", at(12, 0, 10));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, ThreatKind::ReadRateSpike);
+        assert!(detector
+            .record_at(AccessKind::DocumentRead, 1, "doc", at(12, 0, 11))
+            .is_empty());
+        // Old reads fall out of the window
+        assert!(detector
+            .record_at(AccessKind::DocumentRead, 1, "doc", at(12, 5, 0))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_mass_deletion_requires_confirmation() {
+        let detector = detector();
+        let decision = detector.assess_bulk_at(AccessKind::Deletion, 8, "project", at(12, 0, 0));
+        let AccessDecision::RequireConfirmation { alert, token } = decision else {
+            panic!("expected confirmation to be required");
+        };
+        assert_eq!(alert.kind, ThreatKind::MassDeletion);
+        assert!(detector.confirm(&token));
+        assert!(!detector.confirm(&token));
+    }
+
+    #[test]
+    fn test_exports_flagged_only_in_quiet_hours() {
+        let detector = detector();
+        assert!(matches!(
+            detector.assess_bulk_at(AccessKind::Export, 20, "project", at(14, 0, 0)),
+            AccessDecision::Allow
+        ));
+        assert!(matches!(
+            detector.assess_bulk_at(AccessKind::Export, 20, "project", at(3, 0, 0)),
+            AccessDecision::RequireConfirmation { .. }
+        ));
+
+        let wrapping = AnomalyThresholds {
+            quiet_hours_start: 22,
+            quiet_hours_end: 5,
+            ..AnomalyThresholds::default()
+        };
+        assert!(wrapping.is_quiet_hour(23));
+        assert!(wrapping.is_quiet_hour(2));
+        assert!(!wrapping.is_quiet_hour(12));
+    }
+}
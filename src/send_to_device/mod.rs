@@ -0,0 +1,476 @@
+//! Send to Device
+//!
+//! Puts an ePub of the manuscript on an e-reader: by email (Send to Kindle
+//! and similar services) through the user's own SMTP server, or by copying
+//! it onto a connected device. Each send runs as a background job whose
+//! status the frontend polls. SMTP settings live in secure storage, and the
+//! configured server is added to the network allow-list.
+
+pub mod smtp;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::error::{ErrorCode, UserFacingError};
+use crate::security::network::{NetworkClient, NetworkError};
+use crate::security::secure_storage::SecureStorageService;
+
+pub use smtp::{SmtpSecurity, SmtpSettings};
+
+/// Secure storage account for the SMTP settings
+const SETTINGS_ACCOUNT: &str = "send_to_device_smtp";
+/// Secure storage account for the SMTP password
+const PASSWORD_ACCOUNT: &str = "send_to_device_smtp_password";
+/// Largest attachment Send to Kindle accepts
+pub const MAX_EMAIL_BYTES: usize = 50 * 1024 * 1024;
+/// Finished jobs kept for the status list
+const MAX_JOBS: usize = 50;
+pub const EPUB_MIME_TYPE: &str = "application/epub+zip";
+
+#[derive(Debug, Error)]
+pub enum SendError {
+    #[error("Email sending is not set up")]
+    NotConfigured,
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("The book is {0} bytes, more than email delivery allows")]
+    TooLarge(usize),
+    #[error("Not a device folder: {0}")]
+    InvalidFolder(String),
+    #[error("Export failed: {0}")]
+    Export(String),
+    #[error("Mail server error: {0}")]
+    Smtp(String),
+    #[error("Secure storage error: {0}")]
+    Storage(String),
+    #[error(transparent)]
+    Network(#[from] NetworkError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl UserFacingError for SendError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            SendError::InvalidAddress(_) | SendError::TooLarge(_) | SendError::InvalidFolder(_) => {
+                ErrorCode::InvalidInput
+            }
+            SendError::NotConfigured => ErrorCode::Configuration,
+            SendError::Smtp(_) | SendError::Network(_) => ErrorCode::Unavailable,
+            SendError::Storage(_) | SendError::Io(_) => ErrorCode::Storage,
+            SendError::Export(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+/// Where a book is sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeviceTarget {
+    /// A device's email address, such as `name@kindle.com`
+    Email { address: String },
+    /// A folder on a connected device, see `detect_devices`
+    Folder { path: PathBuf },
+}
+
+/// Kind of a connected e-reader
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceKind {
+    Kindle,
+    Kobo,
+}
+
+/// A connected e-reader and the folder books go in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceFolder {
+    pub name: String,
+    pub kind: DeviceKind,
+    pub path: PathBuf,
+}
+
+/// Progress of a send job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SendStatus {
+    Exporting,
+    Sending,
+    Completed,
+    Failed,
+}
+
+/// One book on its way to a device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendJob {
+    pub id: Uuid,
+    pub title: String,
+    pub target: DeviceTarget,
+    pub status: SendStatus,
+    pub file_name: String,
+    pub size: Option<usize>,
+    /// Where the file was written, for folder targets
+    pub output_path: Option<PathBuf>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Runs send jobs and keeps their status
+pub struct DeviceSender {
+    storage: Arc<SecureStorageService>,
+    network: Arc<NetworkClient>,
+    jobs: Arc<Mutex<Vec<SendJob>>>,
+}
+
+impl DeviceSender {
+    /// Mail goes out through `network`; a saved server is allowed on it
+    /// straight away
+    pub fn new(storage: Arc<SecureStorageService>, network: Arc<NetworkClient>) -> Self {
+        let sender = Self {
+            storage,
+            network,
+            jobs: Arc::new(Mutex::new(Vec::new())),
+        };
+        match sender.smtp_settings() {
+            Ok(Some(settings)) => sender.network.allow_host(&settings.host),
+            Ok(None) => {}
+            Err(e) => log::warn!("Failed to load SMTP settings: {}", e),
+        }
+        sender
+    }
+
+    /// Saved SMTP settings, without the password
+    pub fn smtp_settings(&self) -> Result<Option<SmtpSettings>, SendError> {
+        let json = self
+            .storage
+            .find_api_key(SETTINGS_ACCOUNT)
+            .map_err(|e| SendError::Storage(e.to_string()))?;
+        json.map(|json| serde_json::from_str(&json).map_err(|e| SendError::Storage(e.to_string())))
+            .transpose()
+    }
+
+    /// Save SMTP settings. An empty password keeps the stored one.
+    pub fn save_smtp_settings(&self, settings: &SmtpSettings) -> Result<(), SendError> {
+        smtp::validate_address(&settings.from)?;
+        if settings.host.trim().is_empty() || settings.port == 0 {
+            return Err(SendError::Smtp(
+                "A server and port are required".to_string(),
+            ));
+        }
+        let previous = self.smtp_settings()?;
+        let json =
+            serde_json::to_string(settings).map_err(|e| SendError::Storage(e.to_string()))?;
+        self.storage
+            .set_api_key(SETTINGS_ACCOUNT, &json)
+            .map_err(|e| SendError::Storage(e.to_string()))?;
+        if let Some(previous) = previous {
+            self.network.disallow_host(&previous.host);
+        }
+        self.network.allow_host(&settings.host);
+        if !settings.password.is_empty() {
+            self.storage
+                .set_api_key(PASSWORD_ACCOUNT, &settings.password)
+                .map_err(|e| SendError::Storage(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Forget the SMTP settings and password
+    pub fn clear_smtp_settings(&self) -> Result<(), SendError> {
+        if let Some(previous) = self.smtp_settings()? {
+            self.network.disallow_host(&previous.host);
+        }
+        for account in [SETTINGS_ACCOUNT, PASSWORD_ACCOUNT] {
+            if self
+                .storage
+                .find_api_key(account)
+                .map_err(|e| SendError::Storage(e.to_string()))?
+                .is_some()
+            {
+                self.storage
+                    .delete_api_key(account)
+                    .map_err(|e| SendError::Storage(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Start a job: `export` produces the ePub, which then goes to `target`.
+    /// Returns right away; poll `job` for progress.
+    pub fn start<F>(
+        &self,
+        title: &str,
+        target: DeviceTarget,
+        export: F,
+    ) -> Result<SendJob, SendError>
+    where
+        F: FnOnce() -> Result<Vec<u8>, String> + Send + 'static,
+    {
+        // Fail fast on settings problems instead of after the export
+        let settings = match &target {
+            DeviceTarget::Email { address } => {
+                smtp::validate_address(address)?;
+                Some(self.smtp_settings_with_password()?)
+            }
+            DeviceTarget::Folder { path } => {
+                if !path.is_dir() {
+                    return Err(SendError::InvalidFolder(path.display().to_string()));
+                }
+                None
+            }
+        };
+
+        let job = SendJob {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            target,
+            status: SendStatus::Exporting,
+            file_name: file_name_for(title),
+            size: None,
+            output_path: None,
+            error: None,
+            created_at: Utc::now(),
+            finished_at: None,
+        };
+        self.record(job.clone());
+
+        let jobs = self.jobs.clone();
+        let network = self.network.clone();
+        let mut running = job.clone();
+        tokio::task::spawn_blocking(move || {
+            let update = |job: &SendJob| {
+                if let Ok(mut jobs) = jobs.lock() {
+                    if let Some(slot) = jobs.iter_mut().find(|j| j.id == job.id) {
+                        *slot = job.clone();
+                    }
+                }
+            };
+
+            let result = export().map_err(SendError::Export).and_then(|epub| {
+                running.size = Some(epub.len());
+                running.status = SendStatus::Sending;
+                update(&running);
+                deliver(&network, &running, settings.as_ref(), &epub)
+            });
+            match result {
+                Ok(output_path) => {
+                    running.status = SendStatus::Completed;
+                    running.output_path = output_path;
+                    log::info!("Sent '{}' to device", running.title);
+                }
+                Err(e) => {
+                    running.status = SendStatus::Failed;
+                    running.error = Some(e.to_string());
+                    log::warn!("Sending '{}' to device failed: {}", running.title, e);
+                }
+            }
+            running.finished_at = Some(Utc::now());
+            update(&running);
+        });
+
+        Ok(job)
+    }
+
+    /// A job by id
+    pub fn job(&self, id: Uuid) -> Option<SendJob> {
+        self.jobs
+            .lock()
+            .ok()
+            .and_then(|jobs| jobs.iter().find(|j| j.id == id).cloned())
+    }
+
+    /// Recent jobs, newest first
+    pub fn jobs(&self) -> Vec<SendJob> {
+        self.jobs
+            .lock()
+            .map(|jobs| jobs.iter().rev().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn smtp_settings_with_password(&self) -> Result<SmtpSettings, SendError> {
+        let mut settings = self.smtp_settings()?.ok_or(SendError::NotConfigured)?;
+        settings.password = self
+            .storage
+            .find_api_key(PASSWORD_ACCOUNT)
+            .map_err(|e| SendError::Storage(e.to_string()))?
+            .ok_or(SendError::NotConfigured)?;
+        Ok(settings)
+    }
+
+    fn record(&self, job: SendJob) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.push(job);
+            // Drop the oldest finished jobs; running ones are always kept
+            while jobs.len() > MAX_JOBS {
+                match jobs.iter().position(|j| j.finished_at.is_some()) {
+                    Some(index) => {
+                        jobs.remove(index);
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+}
+
+/// Hand the ePub to its target; returns the written path for folders
+fn deliver(
+    network: &NetworkClient,
+    job: &SendJob,
+    settings: Option<&SmtpSettings>,
+    epub: &[u8],
+) -> Result<Option<PathBuf>, SendError> {
+    match &job.target {
+        DeviceTarget::Email { address } => {
+            if epub.len() > MAX_EMAIL_BYTES {
+                return Err(SendError::TooLarge(epub.len()));
+            }
+            let settings = settings.ok_or(SendError::NotConfigured)?;
+            smtp::send(
+                network,
+                settings,
+                &smtp::MailMessage {
+                    to: address,
+                    subject: &job.title,
+                    body: &format!("{} - sent from Herding Cats.", job.title),
+                    attachment: Some(smtp::MailAttachment {
+                        file_name: &job.file_name,
+                        mime_type: EPUB_MIME_TYPE,
+                        data: epub,
+                    }),
+                },
+            )?;
+            Ok(None)
+        }
+        DeviceTarget::Folder { path } => copy_to_folder(path, &job.file_name, epub).map(Some),
+    }
+}
+
+/// Write the book into `folder` without replacing an existing file. The
+/// file only appears under its real name once fully written, so a device
+/// unplugged halfway never shows a broken book.
+pub fn copy_to_folder(folder: &Path, file_name: &str, data: &[u8]) -> Result<PathBuf, SendError> {
+    if !folder.is_dir() {
+        return Err(SendError::InvalidFolder(folder.display().to_string()));
+    }
+    let stem = file_name.trim_end_matches(".epub");
+    let mut target = folder.join(file_name);
+    let mut copy = 2;
+    while target.exists() {
+        target = folder.join(format!("{} ({}).epub", stem, copy));
+        copy += 1;
+    }
+
+    let partial = target.with_extension("epub.part");
+    std::fs::write(&partial, data)?;
+    if let Err(e) = std::fs::rename(&partial, &target) {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e.into());
+    }
+    Ok(target)
+}
+
+/// Connected e-readers, found by the folders they keep at their root
+pub fn detect_devices() -> Vec<DeviceFolder> {
+    mount_points()
+        .into_iter()
+        .filter_map(|root| device_at(&root))
+        .collect()
+}
+
+fn device_at(root: &Path) -> Option<DeviceFolder> {
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| root.display().to_string());
+    if root.join("documents").is_dir() && root.join("system").is_dir() {
+        Some(DeviceFolder {
+            name,
+            kind: DeviceKind::Kindle,
+            path: root.join("documents"),
+        })
+    } else if root.join(".kobo").is_dir() {
+        Some(DeviceFolder {
+            name,
+            kind: DeviceKind::Kobo,
+            path: root.to_path_buf(),
+        })
+    } else {
+        None
+    }
+}
+
+/// Where removable drives show up on this platform
+fn mount_points() -> Vec<PathBuf> {
+    if cfg!(windows) {
+        return (b'D'..=b'Z')
+            .map(|letter| PathBuf::from(format!("{}:\\", letter as char)))
+            .filter(|p| p.is_dir())
+            .collect();
+    }
+
+    let mut parents = vec![PathBuf::from("/Volumes"), PathBuf::from("/media")];
+    if let Ok(user) = std::env::var("USER") {
+        parents.push(Path::new("/media").join(&user));
+        parents.push(Path::new("/run/media").join(&user));
+    }
+    parents
+        .iter()
+        .filter_map(|parent| std::fs::read_dir(parent).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|p| p.is_dir())
+        .collect()
+}
+
+/// A file name for the book: the title without characters devices or
+/// file systems object to
+pub fn file_name_for(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| {
+            if c.is_control() || "/\\:*?\"<>|".contains(c) {
+                ' '
+            } else {
+                c
+            }
+        })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let cleaned = cleaned.trim_matches('.');
+    if cleaned.is_empty() {
+        "Manuscript.epub".to_string()
+    } else {
+        format!("{}.epub", cleaned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_devices_and_folder_copies() {
+        let dir = tempfile::tempdir().unwrap();
+        let kindle = dir.path().join("Kindle");
+        std::fs::create_dir_all(kindle.join("documents")).unwrap();
+        std::fs::create_dir_all(kindle.join("system")).unwrap();
+        let device = device_at(&kindle).unwrap();
+        assert_eq!(device.kind, DeviceKind::Kindle);
+        assert_eq!(device.path, kindle.join("documents"));
+        assert!(device_at(dir.path()).is_none());
+
+        let name = file_name_for("Book: One / Draft?");
+        assert_eq!(name, "Book One Draft.epub");
+        let first = copy_to_folder(&device.path, &name, b"one").unwrap();
+        let second = copy_to_folder(&device.path, &name, b"two").unwrap();
+        assert_eq!(second.file_name().unwrap(), "Book One Draft (2).epub");
+        assert_eq!(std::fs::read(first).unwrap(), b"one");
+        assert_eq!(std::fs::read_dir(&device.path).unwrap().count(), 2);
+    }
+}
@@ -0,0 +1,459 @@
+//! Minimal SMTP Client
+//!
+//! Just enough SMTP to hand one message with an attachment to the user's
+//! mail provider: implicit TLS (usually port 465) or STARTTLS (587), AUTH
+//! PLAIN or LOGIN, and a multipart MIME body. Plaintext connections are
+//! never used for credentials.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::SendError;
+use crate::security::network::NetworkClient;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const IO_TIMEOUT: Duration = Duration::from_secs(120);
+/// Longest reply line accepted from the server
+const MAX_LINE: usize = 4096;
+/// Base64 line length required by RFC 2045
+const BASE64_LINE: usize = 76;
+
+/// How the connection is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS
+    StartTls,
+    /// TLS from the first byte
+    Tls,
+}
+
+/// Outgoing mail server. The password is kept apart from the rest in
+/// secure storage and is never serialized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: String,
+    #[serde(default, skip_serializing)]
+    pub password: String,
+    /// Sender address; Kindles only accept mail from approved senders
+    pub from: String,
+}
+
+/// A file attached to a message
+#[derive(Debug, Clone, Copy)]
+pub struct MailAttachment<'a> {
+    pub file_name: &'a str,
+    pub mime_type: &'a str,
+    pub data: &'a [u8],
+}
+
+/// A message to send
+#[derive(Debug, Clone, Copy)]
+pub struct MailMessage<'a> {
+    pub to: &'a str,
+    pub subject: &'a str,
+    pub body: &'a str,
+    pub attachment: Option<MailAttachment<'a>>,
+}
+
+/// Send a message. Blocks until the server has accepted it, so call it
+/// from a blocking task. The server must be on `network`'s allow-list.
+pub fn send(
+    network: &NetworkClient,
+    settings: &SmtpSettings,
+    message: &MailMessage,
+) -> Result<(), SendError> {
+    validate_address(&settings.from)?;
+    validate_address(message.to)?;
+    if message.subject.contains(['\r', '\n']) {
+        return Err(SendError::InvalidAddress(
+            "Subject must be a single line".to_string(),
+        ));
+    }
+    network.authorize_connection("smtp", &settings.host, settings.port)?;
+
+    let tcp = connect(&settings.host, settings.port)?;
+    let data = build_message(&settings.from, message, Utc::now(), &uuid::Uuid::new_v4());
+    match settings.security {
+        SmtpSecurity::Tls => {
+            let mut session = Session::new(tls_stream(&settings.host, tcp)?);
+            session.expect(&[220])?;
+            let extensions = session.hello()?;
+            session.deliver(settings, &extensions, message.to, &data)
+        }
+        SmtpSecurity::StartTls => {
+            let mut plain = Session::new(tcp);
+            plain.expect(&[220])?;
+            let extensions = plain.hello()?;
+            if !extensions
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case("STARTTLS"))
+            {
+                return Err(SendError::Smtp(
+                    "The server does not offer STARTTLS".to_string(),
+                ));
+            }
+            plain.command("STARTTLS", &[220])?;
+            let mut session = Session::new(tls_stream(&settings.host, plain.into_inner()?)?);
+            let extensions = session.hello()?;
+            session.deliver(settings, &extensions, message.to, &data)
+        }
+    }
+}
+
+fn connect(host: &str, port: u16) -> Result<TcpStream, SendError> {
+    let mut last_error = None;
+    for address in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(IO_TIMEOUT))?;
+                stream.set_write_timeout(Some(IO_TIMEOUT))?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error
+        .map(SendError::Io)
+        .unwrap_or_else(|| SendError::Smtp(format!("Could not resolve {}", host))))
+}
+
+fn tls_stream(
+    host: &str,
+    tcp: TcpStream,
+) -> Result<rustls::StreamOwned<rustls::ClientConnection, TcpStream>, SendError> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+            anchor.subject,
+            anchor.spki,
+            anchor.name_constraints,
+        )
+    }));
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let server_name = rustls::ServerName::try_from(host)
+        .map_err(|_| SendError::Smtp(format!("Invalid server name: {}", host)))?;
+    let connection = rustls::ClientConnection::new(Arc::new(config), server_name)
+        .map_err(|e| SendError::Smtp(format!("TLS setup failed: {}", e)))?;
+    Ok(rustls::StreamOwned::new(connection, tcp))
+}
+
+/// One SMTP conversation over a stream
+struct Session<S: Read + Write> {
+    stream: S,
+    buffer: Vec<u8>,
+}
+
+impl<S: Read + Write> Session<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// The stream, for the STARTTLS upgrade. Anything the server sent ahead
+    /// of the handshake would be read as if it came over TLS, so it is refused.
+    fn into_inner(self) -> Result<S, SendError> {
+        if !self.buffer.is_empty() {
+            return Err(SendError::Smtp(
+                "Unexpected data before the TLS handshake".to_string(),
+            ));
+        }
+        Ok(self.stream)
+    }
+
+    /// EHLO; returns the extensions the server announced
+    fn hello(&mut self) -> Result<Vec<String>, SendError> {
+        let lines = self.command("EHLO [127.0.0.1]", &[250])?;
+        Ok(lines.into_iter().skip(1).collect())
+    }
+
+    fn deliver(
+        &mut self,
+        settings: &SmtpSettings,
+        extensions: &[String],
+        to: &str,
+        data: &str,
+    ) -> Result<(), SendError> {
+        let mechanisms: Vec<String> = extensions
+            .iter()
+            .filter_map(|e| {
+                e.to_ascii_uppercase()
+                    .strip_prefix("AUTH ")
+                    .map(str::to_string)
+            })
+            .flat_map(|m| m.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+            .collect();
+        if mechanisms.iter().any(|m| m == "PLAIN") {
+            let credentials = format!("\0{}\0{}", settings.username, settings.password);
+            self.command(
+                &format!("AUTH PLAIN {}", STANDARD.encode(credentials)),
+                &[235],
+            )?;
+        } else if mechanisms.iter().any(|m| m == "LOGIN") {
+            self.command("AUTH LOGIN", &[334])?;
+            self.command(&STANDARD.encode(&settings.username), &[334])?;
+            self.command(&STANDARD.encode(&settings.password), &[235])?;
+        } else {
+            return Err(SendError::Smtp(
+                "The server offers no supported login method".to_string(),
+            ));
+        }
+
+        self.command(&format!("MAIL FROM:<{}>", settings.from.trim()), &[250])?;
+        self.command(&format!("RCPT TO:<{}>", to.trim()), &[250, 251])?;
+        self.command("DATA", &[354])?;
+        self.stream.write_all(data.as_bytes())?;
+        self.command(".", &[250])?;
+        // The message is accepted; a server hanging up early is not a failure
+        let _ = self.command("QUIT", &[221]);
+        Ok(())
+    }
+
+    fn command(&mut self, line: &str, expected: &[u16]) -> Result<Vec<String>, SendError> {
+        self.stream.write_all(line.as_bytes())?;
+        self.stream.write_all(b"\r\n")?;
+        self.stream.flush()?;
+        self.expect(expected)
+    }
+
+    fn expect(&mut self, expected: &[u16]) -> Result<Vec<String>, SendError> {
+        let (code, lines) = self.reply()?;
+        if expected.contains(&code) {
+            Ok(lines)
+        } else {
+            Err(SendError::Smtp(format!("{} {}", code, lines.join(" "))))
+        }
+    }
+
+    /// A possibly multi-line reply: its code and the text of each line
+    fn reply(&mut self) -> Result<(u16, Vec<String>), SendError> {
+        let mut lines = Vec::new();
+        loop {
+            let line = self.read_line()?;
+            let code = line
+                .get(..3)
+                .and_then(|c| c.parse().ok())
+                .ok_or_else(|| SendError::Smtp(format!("Unexpected reply: {}", line)))?;
+            let more = line.as_bytes().get(3) == Some(&b'-');
+            lines.push(line.get(4..).unwrap_or("").to_string());
+            if !more {
+                return Ok((code, lines));
+            }
+        }
+    }
+
+    fn read_line(&mut self) -> Result<String, SendError> {
+        loop {
+            if let Some(end) = self.buffer.windows(2).position(|w| w == b"\r\n") {
+                let line: Vec<u8> = self.buffer.drain(..end + 2).collect();
+                return Ok(String::from_utf8_lossy(&line[..end]).into_owned());
+            }
+            if self.buffer.len() > MAX_LINE {
+                return Err(SendError::Smtp("Reply line too long".to_string()));
+            }
+            let mut chunk = [0u8; 1024];
+            let read = self.stream.read(&mut chunk)?;
+            if read == 0 {
+                return Err(SendError::Smtp(
+                    "Connection closed by the server".to_string(),
+                ));
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
+
+/// The message as sent after DATA, with CRLF line endings and without the
+/// terminating dot
+fn build_message(
+    from: &str,
+    message: &MailMessage,
+    date: DateTime<Utc>,
+    id: &uuid::Uuid,
+) -> String {
+    let boundary = format!("herding-cats-{}", id.simple());
+    let mut lines = vec![
+        format!("From: <{}>", from.trim()),
+        format!("To: <{}>", message.to.trim()),
+        format!("Subject: {}", encode_header(message.subject)),
+        format!("Date: {}", date.to_rfc2822()),
+        format!("Message-ID: <{}@herdingcats>", id),
+        "MIME-Version: 1.0".to_string(),
+        format!("Content-Type: multipart/mixed; boundary=\"{}\"", boundary),
+        String::new(),
+        format!("--{}", boundary),
+        "Content-Type: text/plain; charset=utf-8".to_string(),
+        "Content-Transfer-Encoding: base64".to_string(),
+        String::new(),
+    ];
+    lines.extend(base64_lines(message.body.as_bytes()));
+    if let Some(attachment) = &message.attachment {
+        let file_name = ascii_file_name(attachment.file_name);
+        lines.extend([
+            format!("--{}", boundary),
+            format!(
+                "Content-Type: {}; name=\"{}\"",
+                attachment.mime_type, file_name
+            ),
+            format!(
+                "Content-Disposition: attachment; filename=\"{}\"",
+                file_name
+            ),
+            "Content-Transfer-Encoding: base64".to_string(),
+            String::new(),
+        ]);
+        lines.extend(base64_lines(attachment.data));
+    }
+    lines.push(format!("--{}--", boundary));
+
+    let mut out = String::new();
+    for line in lines {
+        // Dot-stuffing, so no line can end the DATA section early
+        if line.starts_with('.') {
+            out.push('.');
+        }
+        out.push_str(&line);
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn base64_lines(data: &[u8]) -> Vec<String> {
+    let encoded = STANDARD.encode(data);
+    encoded
+        .as_bytes()
+        .chunks(BASE64_LINE)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect()
+}
+
+/// RFC 2047 encoding for header text that is not plain ASCII
+fn encode_header(text: &str) -> String {
+    if text.is_ascii() {
+        text.to_string()
+    } else {
+        format!("=?utf-8?B?{}?=", STANDARD.encode(text))
+    }
+}
+
+/// A file name safe for a quoted MIME parameter
+fn ascii_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || " .-_()".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    match cleaned.trim() {
+        "" => "book.epub".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// A bare `local@domain` address, nothing that could smuggle in a header
+pub fn validate_address(address: &str) -> Result<(), SendError> {
+    let address = address.trim();
+    let valid = address
+        .split_once('@')
+        .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'))
+        && !address
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "<>,;\"".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(SendError::InvalidAddress(address.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Replays canned server replies and records what the client wrote
+    struct Script {
+        replies: Cursor<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl Read for Script {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.replies.read(buf)
+        }
+    }
+
+    impl Write for Script {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_conversation_and_message() {
+        let settings = SmtpSettings {
+            host: "smtp.example.com".to_string(),
+            port: 465,
+            security: SmtpSecurity::Tls,
+            username: "me".to_string(),
+            password: "secret".to_string(),
+            from: "me@example.com".to_string(),
+        };
+        let message = MailMessage {
+            to: "reader@kindle.com",
+            subject: "Novel – draft 3",
+            body: "Your book.",
+            attachment: Some(MailAttachment {
+                file_name: "Novel: Draft.epub",
+                mime_type: "application/epub+zip",
+                data: b"PK\x03\x04",
+            }),
+        };
+        let data = build_message(&settings.from, &message, Utc::now(), &uuid::Uuid::nil());
+        assert!(data.contains("Subject: =?utf-8?B?"));
+        assert!(data.contains("filename=\"Novel_ Draft.epub\""));
+        assert!(data.ends_with("--herding-cats-00000000000000000000000000000000--\r\n"));
+
+        let replies =
+            "220 ready\r\n250-smtp.example.com\r\n250-AUTH LOGIN PLAIN\r\n250 SIZE 1000\r\n\
+                       235 ok\r\n250 ok\r\n250 ok\r\n354 go\r\n250 queued\r\n221 bye\r\n";
+        let mut session = Session::new(Script {
+            replies: Cursor::new(replies.as_bytes().to_vec()),
+            written: Vec::new(),
+        });
+        session.expect(&[220]).unwrap();
+        let extensions = session.hello().unwrap();
+        session
+            .deliver(&settings, &extensions, message.to, &data)
+            .unwrap();
+        let written = String::from_utf8(session.stream.written).unwrap();
+        assert!(written.starts_with("EHLO [127.0.0.1]\r\nAUTH PLAIN AG1lAHNlY3JldA==\r\n"));
+        assert!(written.contains("RCPT TO:<reader@kindle.com>\r\nDATA\r\n"));
+        assert!(written.ends_with("\r\n.\r\nQUIT\r\n"));
+
+        assert!(validate_address("reader@kindle.com").is_ok());
+        assert!(validate_address("a@b.com>\r\nBcc: x@y.com").is_err());
+    }
+}
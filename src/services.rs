@@ -3,6 +3,8 @@ use rand::Rng;
 use std::sync::{Arc, Mutex};
 
 pub mod ai_service;
+pub mod ask_service;
+pub mod writing_stats;
 
 /// Core service trait for dependency injection
 pub trait Service: Send + Sync {}
@@ -59,9 +61,7 @@ impl Default for SettingsService {
 impl SettingsService {
     pub fn new() -> Self {
         // Use a default settings path for standalone operation
-        let settings_path = std::env::current_dir()
-            .unwrap_or_else(|_| std::path::PathBuf::from("."))
-            .join("settings.json");
+        let settings_path = crate::app_paths::AppPaths::global().settings_path();
         SettingsService { settings_path }
     }
 
@@ -201,6 +201,36 @@ impl SecurityService {
         String::from_utf8(decrypted).map_err(|e| format!("Invalid UTF-8 in decrypted data: {}", e))
     }
 
+    /// Encrypt bytes using AES-GCM under a fresh random nonce, which is
+    /// prepended to the ciphertext
+    pub fn encrypt_bytes(&self, data: &[u8], key: &[u8; 32]) -> Result<Vec<u8>, String> {
+        use aes_gcm::aead::{Aead, KeyInit};
+
+        let nonce: [u8; 12] = rand::thread_rng().gen();
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), data)
+            .map_err(|e| format!("Encryption failed: {}", e))?;
+
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend(ciphertext);
+        Ok(encrypted)
+    }
+
+    /// Decrypt what `encrypt_bytes` produced
+    pub fn decrypt_bytes(&self, encrypted: &[u8], key: &[u8; 32]) -> Result<Vec<u8>, String> {
+        use aes_gcm::aead::{Aead, KeyInit};
+
+        if encrypted.len() < 12 {
+            return Err("Decryption failed: data is too short".to_string());
+        }
+        let (nonce, ciphertext) = encrypted.split_at(12);
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+        cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|e| format!("Decryption failed: {}", e))
+    }
+
     /// Validate API key format (basic security check)
     pub fn validate_api_key_format(&self, api_key: &str) -> bool {
         if api_key.is_empty() || api_key.trim().is_empty() {
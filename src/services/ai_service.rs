@@ -1,29 +1,152 @@
-use std::sync::{Arc, Mutex};
-use crate::database::DatabaseService;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::database::models::ai_log::AiInteraction;
+use crate::database::{AiLogService, DatabaseService, ProfileService};
+use crate::security::network;
+use crate::security::secrets_scanner::{ScanContext, SecretsScanner};
 use crate::security::secure_storage::SecureStorageService;
+use crate::settings;
 use anyhow::Result;
 
 pub struct AiService {
     _secure_storage: Arc<SecureStorageService>,
-    _db_service: Arc<Mutex<DatabaseService>>,
+    _db_service: DatabaseService,
+    secrets_scanner: Option<Arc<SecretsScanner>>,
+    interaction_log: Option<Arc<AiLogService>>,
+    profiles: Option<Arc<ProfileService>>,
+    model: Option<String>,
 }
 
+/// Prefixes of model names served on this machine, which keep working in
+/// offline mode
+const LOCAL_MODEL_PREFIXES: &[&str] = &["local:", "ollama:", "llamacpp:"];
+
+/// Characters sent to a remote model that are charged one cent against a
+/// profile's AI budget, roughly a thousand tokens
+const REMOTE_CHARS_PER_CENT: usize = 4_000;
+
 impl AiService {
-    pub fn new(secure_storage: Arc<SecureStorageService>, db_service: Arc<Mutex<DatabaseService>>) -> Self {
+    pub fn new(secure_storage: Arc<SecureStorageService>, db_service: DatabaseService) -> Self {
         Self {
             _secure_storage: secure_storage,
             _db_service: db_service,
+            secrets_scanner: None,
+            interaction_log: None,
+            profiles: None,
+            model: None,
+        }
+    }
+
+    /// Use the model chosen in settings, e.g. "gpt-4o" or "ollama:llama3"
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    /// Whether the configured model runs on this machine
+    pub fn is_local(&self) -> bool {
+        self.model
+            .as_deref()
+            .is_some_and(|model| LOCAL_MODEL_PREFIXES.iter().any(|p| model.starts_with(p)))
+    }
+
+    /// Whether a request can be answered now: remote models need the
+    /// network, which offline mode turns off
+    pub fn is_available(&self) -> bool {
+        self.is_local() || !network::is_offline()
+    }
+
+    /// Scan prompts and context for secrets before they are sent
+    pub fn with_secrets_scanner(mut self, scanner: Arc<SecretsScanner>) -> Self {
+        self.secrets_scanner = Some(scanner);
+        self
+    }
+
+    /// Log requests locally when the privacy controls turn the log on
+    pub fn with_interaction_log(mut self, log: Arc<AiLogService>) -> Self {
+        self.interaction_log = Some(log);
+        self
+    }
+
+    /// Charge requests to the active profile's monthly AI budget
+    pub fn with_profiles(mut self, profiles: Arc<ProfileService>) -> Self {
+        self.profiles = Some(profiles);
+        self
+    }
+
+    /// Estimated cost of sending `chars` characters; local models are free
+    fn estimated_cost_cents(&self, chars: usize) -> i64 {
+        if self.is_local() {
+            0
+        } else {
+            (chars / REMOTE_CHARS_PER_CENT + 1) as i64
         }
     }
 
     pub async fn generate_response(&self, prompt: &str, context: Option<&str>) -> Result<String> {
+        self.generate_response_in(None, "ai_request", prompt, context).await
+    }
+
+    /// Answer a request made for `feature`, in a project if there is one
+    #[tracing::instrument(
+        name = "ai.generate_response",
+        skip_all,
+        fields(correlation_id = crate::correlation::current(), feature = feature, prompt_chars = prompt.len())
+    )]
+    pub async fn generate_response_in(
+        &self,
+        project_id: Option<Uuid>,
+        feature: &str,
+        prompt: &str,
+        context: Option<&str>,
+    ) -> Result<String> {
+        let (prompt, context) = match &self.secrets_scanner {
+            Some(scanner) => {
+                let prompt = scanner.check(prompt, ScanContext::AiSubmission)?.content;
+                let context = match context {
+                    Some(ctx) => Some(scanner.check(ctx, ScanContext::AiSubmission)?.content),
+                    None => None,
+                };
+                (prompt, context)
+            }
+            None => (prompt.to_string(), context.map(str::to_string)),
+        };
+
+        // Refused before anything is sent when the profile is over budget
+        if let Some(profiles) = &self.profiles {
+            if let Some(profile) = profiles.active_profile().await {
+                let sent = prompt.len() + context.as_ref().map_or(0, String::len);
+                profiles
+                    .record_ai_usage(profile.id, self.estimated_cost_cents(sent))
+                    .await?;
+            }
+        }
+
         // TODO: Implement actual AI call (OpenAI/Anthropic)
         // For now, return a simulated response
         println!("Generating AI response for prompt: {}", prompt);
-        if let Some(ctx) = context {
+        if let Some(ctx) = &context {
             println!("Context: {}", ctx);
         }
-        
-        Ok(format!("AI Response to: {}", prompt))
+        let response = format!("AI Response to: {}", prompt);
+
+        if let Some(log) = &self.interaction_log {
+            let sent = match &context {
+                Some(ctx) => format!("{}\n\n{}", prompt, ctx),
+                None => prompt.clone(),
+            };
+            let mut interaction = AiInteraction::new(project_id, feature, sent, response.as_str());
+            interaction.model = self.model.clone();
+            let controls = settings::load_settings().privacy.unwrap_or_default();
+            if let Err(e) = log.record(&controls, interaction).await {
+                tracing::warn!("Failed to log AI request: {}", e);
+            }
+        }
+
+        Ok(response)
     }
 }
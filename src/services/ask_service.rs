@@ -0,0 +1,479 @@
+//! Ask Project
+//!
+//! Answers questions about a manuscript from its own text. Passages are
+//! retrieved by combining keyword (BM25) and embedding rankings, handed to
+//! the configured AI model as numbered sources, and the answer comes back
+//! with its `[n]` citation markers resolved to links into the documents.
+//! When the model cannot be reached, such as a remote model in offline
+//! mode, the best passages are returned instead of an answer.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::database::hybrid_search_service::reciprocal_rank_fusion;
+use crate::database::models::hybrid_search::default_rrf_k;
+use crate::database::vector_embedding::document_paragraphs;
+use crate::database::{EnhancedDatabaseService, VectorEmbeddingService};
+use crate::deep_link::DeepLink;
+use crate::services::ai_service::AiService;
+
+/// Passages are built from whole paragraphs up to about this length
+const PASSAGE_CHARS: usize = 800;
+
+/// Passages less similar than this need a keyword match to be used
+const MIN_SEMANTIC_SCORE: f32 = 0.05;
+
+/// Top similarity below which the sources are called weak support
+const WEAK_SUPPORT_SCORE: f32 = 0.2;
+
+const EXCERPT_CHARS: usize = 240;
+
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "are", "was", "were", "what", "when", "where", "which", "who", "whom",
+    "why", "how", "does", "did", "has", "have", "had", "that", "this", "with", "from", "into",
+    "about", "her", "his", "their", "they", "she", "him", "its", "there", "any", "ever", "our",
+];
+
+fn default_max_sources() -> usize {
+    5
+}
+
+/// A question about a project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AskRequest {
+    pub project_id: Uuid,
+    pub question: String,
+    /// Passages given to the model
+    #[serde(default = "default_max_sources")]
+    pub max_sources: usize,
+}
+
+/// A passage given to the model as source `[marker]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourcePassage {
+    pub marker: usize,
+    pub document_id: Uuid,
+    pub title: String,
+    /// Place of the document in the binder, from 1
+    pub position: usize,
+    /// Character offsets in the document's text
+    pub start_char: usize,
+    pub end_char: usize,
+    pub excerpt: String,
+    /// `herdingcats://` link to the document
+    pub link: String,
+    pub keyword_score: f32,
+    pub semantic_score: f32,
+    /// Fused rank score the passages are ordered by
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    High,
+    Medium,
+    Low,
+}
+
+/// An answer and where it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AskAnswer {
+    pub question: String,
+    pub answer: String,
+    /// Passages given to the model, best first
+    pub sources: Vec<SourcePassage>,
+    /// Markers of the sources the answer cites
+    pub cited: Vec<usize>,
+    pub confidence: Confidence,
+    /// Reasons to double-check the answer
+    pub caveats: Vec<String>,
+    /// Model that wrote the answer; unset when passages were returned instead
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Passage {
+    document_id: Uuid,
+    title: String,
+    position: usize,
+    start_char: usize,
+    end_char: usize,
+    text: String,
+}
+
+/// Service answering questions about a project's documents
+pub struct AskService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    embeddings: Arc<VectorEmbeddingService>,
+    ai_service: Arc<AiService>,
+}
+
+impl AskService {
+    pub fn new(
+        db_service: Arc<RwLock<EnhancedDatabaseService>>,
+        embeddings: Arc<VectorEmbeddingService>,
+        ai_service: Arc<AiService>,
+    ) -> Self {
+        Self {
+            db_service,
+            embeddings,
+            ai_service,
+        }
+    }
+
+    #[tracing::instrument(
+        name = "ai.ask_project",
+        skip_all,
+        fields(correlation_id = crate::correlation::current(), question_chars = request.question.len())
+    )]
+    pub async fn ask_project(&self, request: &AskRequest) -> Result<AskAnswer> {
+        let question = request.question.trim();
+        if question.is_empty() {
+            bail!("Ask a question");
+        }
+        let sources = self
+            .retrieve(request.project_id, question, request.max_sources.max(1))
+            .await?;
+
+        let mut caveats = Vec::new();
+        if sources.is_empty() {
+            caveats.push("No passage in the project matches the question.".to_string());
+            return Ok(AskAnswer {
+                question: question.to_string(),
+                answer: "The manuscript doesn't seem to cover this.".to_string(),
+                sources,
+                cited: Vec::new(),
+                confidence: Confidence::Low,
+                caveats,
+                model: None,
+            });
+        }
+
+        let top_semantic = sources
+            .iter()
+            .map(|s| s.semantic_score)
+            .fold(0.0f32, f32::max);
+        let keyword_support = sources.iter().any(|s| s.keyword_score > 0.0);
+        let mut confidence = if top_semantic >= 2.0 * WEAK_SUPPORT_SCORE && keyword_support {
+            Confidence::High
+        } else if top_semantic >= WEAK_SUPPORT_SCORE || keyword_support {
+            Confidence::Medium
+        } else {
+            caveats.push("The closest passages are only loosely related.".to_string());
+            Confidence::Low
+        };
+
+        if !self.ai_service.is_available() {
+            caveats.push(format!(
+                "{} needs the network, which offline mode has turned off; \
+                 these are the most relevant passages instead of an answer.",
+                self.ai_service.model().unwrap_or("The AI model")
+            ));
+            let answer = sources
+                .iter()
+                .map(|s| format!("[{}] {}", s.marker, s.excerpt))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let cited = sources.iter().map(|s| s.marker).collect();
+            return Ok(AskAnswer {
+                question: question.to_string(),
+                answer,
+                sources,
+                cited,
+                confidence: Confidence::Low,
+                caveats,
+                model: None,
+            });
+        }
+
+        let (prompt, context) = build_prompt(question, &sources);
+        let answer = self
+            .ai_service
+            .generate_response_in(
+                Some(request.project_id),
+                "ask_project",
+                &prompt,
+                Some(&context),
+            )
+            .await?;
+
+        let markers = citation_markers(&answer);
+        let unknown: Vec<String> = markers
+            .iter()
+            .filter(|m| **m == 0 || **m > sources.len())
+            .map(|m| format!("[{}]", m))
+            .collect();
+        let cited: Vec<usize> = markers
+            .into_iter()
+            .filter(|m| *m > 0 && *m <= sources.len())
+            .collect();
+        if !unknown.is_empty() {
+            caveats.push(format!(
+                "The answer cites {}, which is not one of the sources.",
+                unknown.join(", ")
+            ));
+            confidence = Confidence::Low;
+        }
+        if cited.is_empty() {
+            caveats.push(
+                "The answer doesn't cite the manuscript; check it against the sources.".to_string(),
+            );
+            confidence = Confidence::Low;
+        }
+
+        Ok(AskAnswer {
+            question: question.to_string(),
+            answer,
+            sources,
+            cited,
+            confidence,
+            caveats,
+            model: self.ai_service.model().map(str::to_string),
+        })
+    }
+
+    /// The passages best matching the question, by keyword and meaning
+    async fn retrieve(
+        &self,
+        project_id: Uuid,
+        question: &str,
+        limit: usize,
+    ) -> Result<Vec<SourcePassage>> {
+        let passages = self.load_passages(project_id).await?;
+        if passages.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keyword = bm25_scores(question, &passages);
+        let (model, query) = self.embeddings.embed_text(question, None).await?;
+        let mut semantic = Vec::with_capacity(passages.len());
+        for passage in &passages {
+            let (_, vector) = self
+                .embeddings
+                .embed_text(&passage.text, Some(&model))
+                .await?;
+            semantic.push(self.embeddings.calculate_cosine_similarity(&query, &vector));
+        }
+
+        let fused = reciprocal_rank_fusion(&[&keyword, &semantic], default_rrf_k());
+
+        let mut order: Vec<usize> = (0..passages.len())
+            .filter(|i| keyword[*i] > 0.0 || semantic[*i] >= MIN_SEMANTIC_SCORE)
+            .collect();
+        order.sort_by(|a, b| fused[*b].total_cmp(&fused[*a]));
+        order.truncate(limit);
+
+        Ok(order
+            .into_iter()
+            .enumerate()
+            .map(|(i, index)| {
+                let passage = &passages[index];
+                SourcePassage {
+                    marker: i + 1,
+                    document_id: passage.document_id,
+                    title: passage.title.clone(),
+                    position: passage.position,
+                    start_char: passage.start_char,
+                    end_char: passage.end_char,
+                    excerpt: excerpt(&passage.text),
+                    link: DeepLink::OpenDocument {
+                        document_id: passage.document_id.to_string(),
+                    }
+                    .to_url(),
+                    keyword_score: keyword[index],
+                    semantic_score: semantic[index],
+                    score: fused[index],
+                }
+            })
+            .collect())
+    }
+
+    /// The project's active documents as passages, in binder order;
+    /// journal pages kept out of AI context are skipped
+    async fn load_passages(&self, project_id: Uuid) -> Result<Vec<Passage>> {
+        let db = self.db_service.read().await;
+        let has_binder: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'binder_order'",
+        )
+        .fetch_one(&db.pool)
+        .await?;
+        let sql = if has_binder > 0 {
+            "SELECT d.id, d.title, d.content, d.document_type
+             FROM documents d LEFT JOIN binder_order b ON b.document_id = d.id
+             WHERE d.project_id = ?1 AND d.is_active = 1
+               AND json_extract(CASE WHEN json_valid(d.metadata) THEN d.metadata END, '$.exclude_from_ai_context') IS NOT 1
+             ORDER BY b.position IS NULL, b.position, d.created_at, d.title"
+        } else {
+            "SELECT d.id, d.title, d.content, d.document_type FROM documents d
+             WHERE d.project_id = ?1 AND d.is_active = 1
+               AND json_extract(CASE WHEN json_valid(d.metadata) THEN d.metadata END, '$.exclude_from_ai_context') IS NOT 1
+             ORDER BY d.created_at, d.title"
+        };
+        let rows: Vec<(String, String, Option<String>, String)> = sqlx::query_as(sql)
+            .bind(project_id.to_string())
+            .fetch_all(&db.pool)
+            .await?;
+
+        let mut passages = Vec::new();
+        for (position, (id, title, content, document_type)) in rows.into_iter().enumerate() {
+            let document_id = Uuid::parse_str(&id)?;
+            let content = content.unwrap_or_default();
+            let mut current: Option<Passage> = None;
+            for (start_char, end_char, text) in document_paragraphs(&document_type, &content) {
+                match current.as_mut() {
+                    Some(passage) if passage.text.chars().count() < PASSAGE_CHARS => {
+                        passage.text.push('\n');
+                        passage.text.push_str(&text);
+                        passage.end_char = end_char;
+                    }
+                    _ => {
+                        passages.extend(current.take());
+                        current = Some(Passage {
+                            document_id,
+                            title: title.clone(),
+                            position: position + 1,
+                            start_char,
+                            end_char,
+                            text,
+                        });
+                    }
+                }
+            }
+            passages.extend(current);
+        }
+        Ok(passages)
+    }
+}
+
+/// Prompt and numbered sources for the model
+fn build_prompt(question: &str, sources: &[SourcePassage]) -> (String, String) {
+    let prompt = format!(
+        "Answer the question using only the numbered sources from the manuscript. \
+         Cite every claim with the source's marker, like [1] or [2][3]. \
+         If the sources don't answer the question, say so.\n\nQuestion: {}",
+        question
+    );
+    let context = sources
+        .iter()
+        .map(|s| {
+            format!(
+                "[{}] {} (document {})\n{}",
+                s.marker, s.title, s.position, s.excerpt
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    (prompt, context)
+}
+
+/// Numbers in `[n]` and `[n, m]` markers, in order of first use
+fn citation_markers(text: &str) -> Vec<usize> {
+    let pattern = regex::Regex::new(r"\[(\d+(?:\s*,\s*\d+)*)\]").expect("static regex");
+    let mut seen = BTreeSet::new();
+    let mut markers = Vec::new();
+    for captures in pattern.captures_iter(text) {
+        for number in captures[1].split(',') {
+            if let Ok(marker) = number.trim().parse::<usize>() {
+                if seen.insert(marker) {
+                    markers.push(marker);
+                }
+            }
+        }
+    }
+    markers
+}
+
+fn terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .map(|w| w.trim_matches('\'').to_lowercase())
+        .filter(|w| w.chars().count() > 2 && !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+/// BM25 score of each passage for the question's terms
+fn bm25_scores(question: &str, passages: &[Passage]) -> Vec<f32> {
+    const K1: f32 = 1.2;
+    const B: f32 = 0.75;
+
+    let query: BTreeSet<String> = terms(question).into_iter().collect();
+    let documents: Vec<Vec<String>> = passages.iter().map(|p| terms(&p.text)).collect();
+    let count = documents.len() as f32;
+    let average = documents.iter().map(Vec::len).sum::<usize>() as f32 / count.max(1.0);
+
+    let mut containing: HashMap<&str, usize> = HashMap::new();
+    for words in &documents {
+        let unique: BTreeSet<&str> = words.iter().map(String::as_str).collect();
+        for term in &query {
+            if unique.contains(term.as_str()) {
+                *containing.entry(term.as_str()).or_default() += 1;
+            }
+        }
+    }
+
+    documents
+        .iter()
+        .map(|words| {
+            let length = words.len() as f32;
+            query
+                .iter()
+                .map(|term| {
+                    let frequency = words.iter().filter(|w| *w == term).count() as f32;
+                    if frequency == 0.0 {
+                        return 0.0;
+                    }
+                    let n = containing.get(term.as_str()).copied().unwrap_or(0) as f32;
+                    let idf = (1.0 + (count - n + 0.5) / (n + 0.5)).ln();
+                    idf * frequency * (K1 + 1.0)
+                        / (frequency + K1 * (1.0 - B + B * length / average.max(1.0)))
+                })
+                .sum()
+        })
+        .collect()
+}
+
+fn excerpt(text: &str) -> String {
+    if text.chars().count() <= EXCERPT_CHARS {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(EXCERPT_CHARS).collect();
+    let cut = cut.rsplit_once(' ').map(|(head, _)| head).unwrap_or(&cut);
+    format!("{}...", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passage(text: &str) -> Passage {
+        Passage {
+            document_id: Uuid::new_v4(),
+            title: "One".to_string(),
+            position: 1,
+            start_char: 0,
+            end_char: text.len(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_citation_markers_and_keyword_ranking() {
+        assert_eq!(
+            citation_markers("Mara sailed at dawn [2]. She returned [1, 3][2]. See [x]."),
+            vec![2, 1, 3]
+        );
+
+        let passages = vec![
+            passage("The market square filled with traders before noon."),
+            passage("Mara's lighthouse stood on the northern cliff, its lamp dark."),
+            passage("Nobody spoke of the lighthouse after the storm."),
+        ];
+        let scores = bm25_scores("Where is Mara's lighthouse?", &passages);
+        assert_eq!(scores[0], 0.0);
+        assert!(scores[1] > scores[2] && scores[2] > 0.0);
+    }
+}
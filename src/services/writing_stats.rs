@@ -0,0 +1,769 @@
+//! Writing Statistics
+//!
+//! Tracks how much is written, not just how long the manuscript is. Every
+//! save records the change in the document's word count, so the dashboard
+//! can show words added and removed per day and per document, the daily
+//! and weekly streaks, the writing sessions found in that activity and a
+//! histogram of how fast they went. A project can have a daily and a
+//! weekly word goal; reaching one queues a `WritingGoalMet` event for
+//! automation workflows, once per day or week.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::automation::{EventSystem, EventType, SystemEvent};
+use crate::database::parse::{parse_time, parse_uuid};
+use crate::database::{DatabaseError, DatabaseResult, EnhancedDatabaseService};
+
+/// Activity further apart than this starts a new session
+const SESSION_GAP: Duration = Duration::minutes(15);
+
+/// Sessions shorter than this have no meaningful speed
+const MIN_SPEED_SECONDS: i64 = 60;
+
+/// Width of a writing speed histogram bucket, in words per minute
+const SPEED_BUCKET_WPM: u32 = 10;
+
+/// Database schema for writing statistics
+pub const CREATE_WRITING_STATS_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS writing_stats_deltas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id TEXT NOT NULL,
+    document_id TEXT NOT NULL,
+    day TEXT NOT NULL,
+    recorded_at TEXT NOT NULL,
+    words_before INTEGER NOT NULL,
+    words_after INTEGER NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS writing_goals (
+    project_id TEXT PRIMARY KEY,
+    daily_words INTEGER NOT NULL DEFAULT 0,
+    weekly_words INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS writing_goal_events (
+    project_id TEXT NOT NULL,
+    period TEXT NOT NULL,
+    met_at TEXT NOT NULL,
+    PRIMARY KEY (project_id, period),
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_writing_stats_deltas_project ON writing_stats_deltas(project_id, day);
+"#;
+
+/// Word targets for a project; 0 means no target, and any writing keeps
+/// the streak going
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WritingGoal {
+    pub daily_words: i64,
+    pub weekly_words: i64,
+}
+
+impl WritingGoal {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.daily_words < 0 || self.weekly_words < 0 {
+            return Err("Word goals can't be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalPeriod {
+    Daily,
+    Weekly,
+}
+
+impl GoalPeriod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GoalPeriod::Daily => "daily",
+            GoalPeriod::Weekly => "weekly",
+        }
+    }
+}
+
+/// A goal reached by a save
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoalMet {
+    pub project_id: Uuid,
+    pub period: GoalPeriod,
+    /// The day, or the Monday of the week
+    pub starts: NaiveDate,
+    pub target: i64,
+    pub words: i64,
+}
+
+/// A document's word count before a save
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentWordCount {
+    pub project_id: Uuid,
+    pub document_id: String,
+    pub words: i64,
+}
+
+/// Words written on a day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyWords {
+    pub date: NaiveDate,
+    pub added: i64,
+    pub removed: i64,
+    pub net: i64,
+    pub goal_met: bool,
+}
+
+/// Words written in a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentWords {
+    pub document_id: String,
+    pub title: String,
+    pub added: i64,
+    pub removed: i64,
+    pub net: i64,
+}
+
+/// A stretch of saves without a long pause
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivitySession {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub duration_seconds: i64,
+    pub net_words: i64,
+    pub documents: usize,
+    /// None for sessions under a minute
+    pub words_per_minute: Option<f64>,
+}
+
+/// Sessions that wrote at `from_wpm` up to `to_wpm`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeedBucket {
+    pub from_wpm: u32,
+    pub to_wpm: u32,
+    pub sessions: usize,
+    pub minutes: f64,
+}
+
+/// Goal streaks; the current ones count today, or this week, only once
+/// the goal is met, and aren't broken before it's over
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WritingStreaks {
+    pub daily_current: u32,
+    pub daily_longest: u32,
+    pub weekly_current: u32,
+    pub weekly_longest: u32,
+}
+
+/// Progress towards the goals so far today and this week
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoalProgress {
+    pub today: i64,
+    pub this_week: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WritingStatsDashboard {
+    pub project_id: Uuid,
+    pub goal: WritingGoal,
+    pub progress: GoalProgress,
+    pub streaks: WritingStreaks,
+    pub days: Vec<DailyWords>,
+    pub documents: Vec<DocumentWords>,
+    pub sessions: Vec<ActivitySession>,
+    pub speed_histogram: Vec<SpeedBucket>,
+}
+
+/// Service for word-count activity, streaks and goals
+#[derive(Debug)]
+pub struct WritingStatsService {
+    db_service: Arc<RwLock<EnhancedDatabaseService>>,
+    /// Where goal-met events go, when automation is running
+    events: Option<EventSystem>,
+}
+
+impl WritingStatsService {
+    /// Create a new writing statistics service
+    pub fn new(db_service: Arc<RwLock<EnhancedDatabaseService>>) -> Self {
+        Self {
+            db_service,
+            events: None,
+        }
+    }
+
+    /// Queue goal-met events on `events` for workflows to trigger on
+    pub fn with_automation_events(mut self, events: EventSystem) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Initialize the writing statistics tables
+    pub async fn initialize(&self) -> DatabaseResult<()> {
+        let db = self.db_service.read().await;
+        sqlx::query(CREATE_WRITING_STATS_TABLES_SQL)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::Migration(format!("Failed to create writing stats tables: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// The document's word count, to pass to `record_words` after it's
+    /// saved; None for documents left out of word counts
+    pub async fn word_count(&self, document_id: &str) -> DatabaseResult<Option<DocumentWordCount>> {
+        let db = self.db_service.read().await;
+        let row: Option<(String, i64)> = sqlx::query_as(
+            "SELECT project_id, word_count FROM documents WHERE id = ?1 AND is_active = 1
+               AND json_extract(CASE WHEN json_valid(metadata) THEN metadata END, '$.exclude_from_word_count') IS NOT 1",
+        )
+        .bind(document_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to read word count: {}", e)))?;
+        row.map(|(project_id, words)| {
+            Ok(DocumentWordCount {
+                project_id: parse_uuid(&project_id)?,
+                document_id: document_id.to_string(),
+                words,
+            })
+        })
+        .transpose()
+    }
+
+    /// Record a save that took the document from `before` to `words`, and
+    /// return the goals it reached
+    pub async fn record_words(
+        &self,
+        before: &DocumentWordCount,
+        words: i64,
+    ) -> DatabaseResult<Vec<GoalMet>> {
+        if words == before.words {
+            return Ok(Vec::new());
+        }
+        let today = Local::now().date_naive();
+        let project = before.project_id.to_string();
+        {
+            let db = self.db_service.read().await;
+            sqlx::query(
+                "INSERT INTO writing_stats_deltas (project_id, document_id, day, recorded_at, words_before, words_after)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .bind(&project)
+            .bind(&before.document_id)
+            .bind(today.to_string())
+            .bind(Utc::now().to_rfc3339())
+            .bind(before.words)
+            .bind(words)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to record words: {}", e)))?;
+        }
+        if words < before.words {
+            return Ok(Vec::new());
+        }
+
+        let goal = self.goal(before.project_id).await?;
+        let progress = self.progress(before.project_id, today).await?;
+        let mut met = Vec::new();
+        for (period, starts, target, written) in [
+            (GoalPeriod::Daily, today, goal.daily_words, progress.today),
+            (
+                GoalPeriod::Weekly,
+                week_start(today),
+                goal.weekly_words,
+                progress.this_week,
+            ),
+        ] {
+            if target == 0 || written < target {
+                continue;
+            }
+            let db = self.db_service.read().await;
+            let inserted = sqlx::query(
+                "INSERT OR IGNORE INTO writing_goal_events (project_id, period, met_at) VALUES (?1, ?2, ?3)",
+            )
+            .bind(&project)
+            .bind(format!("{}:{}", period.as_str(), starts))
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .map_err(|e| DatabaseError::Service(format!("Failed to record goal: {}", e)))?
+            .rows_affected();
+            if inserted > 0 {
+                met.push(GoalMet {
+                    project_id: before.project_id,
+                    period,
+                    starts,
+                    target,
+                    words: written,
+                });
+            }
+        }
+
+        if let Some(events) = &self.events {
+            for goal in &met {
+                events
+                    .push(SystemEvent {
+                        event_type: EventType::WritingGoalMet,
+                        timestamp: Utc::now(),
+                        source: "writing_stats".to_string(),
+                        data: HashMap::from([
+                            ("project_id".to_string(), goal.project_id.to_string().into()),
+                            ("period".to_string(), goal.period.as_str().into()),
+                            ("starts".to_string(), goal.starts.to_string().into()),
+                            ("target".to_string(), goal.target.into()),
+                            ("words".to_string(), goal.words.into()),
+                        ]),
+                    })
+                    .await;
+            }
+        }
+        Ok(met)
+    }
+
+    /// Set a project's word goals
+    pub async fn set_goal(&self, project_id: Uuid, goal: WritingGoal) -> DatabaseResult<()> {
+        goal.validate().map_err(DatabaseError::ValidationError)?;
+        let db = self.db_service.read().await;
+        sqlx::query(
+            "INSERT INTO writing_goals (project_id, daily_words, weekly_words, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(project_id) DO UPDATE SET daily_words = excluded.daily_words,
+               weekly_words = excluded.weekly_words, updated_at = excluded.updated_at",
+        )
+        .bind(project_id.to_string())
+        .bind(goal.daily_words)
+        .bind(goal.weekly_words)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to save writing goal: {}", e)))?;
+        Ok(())
+    }
+
+    /// A project's word goals; none set means no targets
+    pub async fn goal(&self, project_id: Uuid) -> DatabaseResult<WritingGoal> {
+        let db = self.db_service.read().await;
+        let row: Option<(i64, i64)> = sqlx::query_as(
+            "SELECT daily_words, weekly_words FROM writing_goals WHERE project_id = ?1",
+        )
+        .bind(project_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load writing goal: {}", e)))?;
+        Ok(row
+            .map(|(daily_words, weekly_words)| WritingGoal {
+                daily_words,
+                weekly_words,
+            })
+            .unwrap_or_default())
+    }
+
+    /// Everything the statistics dashboard shows; days, documents and
+    /// sessions are limited to `from` and `to`, streaks never are
+    pub async fn dashboard(
+        &self,
+        project_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> DatabaseResult<WritingStatsDashboard> {
+        let today = Local::now().date_naive();
+        let goal = self.goal(project_id).await?;
+        let progress = self.progress(project_id, today).await?;
+        let from_day = from.map(|d| d.to_string());
+        let to_day = to.map(|d| d.to_string());
+
+        let db = self.db_service.read().await;
+        let day_rows: Vec<(String, i64, i64)> = sqlx::query_as(
+            "SELECT day,
+                    SUM(MAX(words_after - words_before, 0)),
+                    SUM(MAX(words_before - words_after, 0))
+             FROM writing_stats_deltas WHERE project_id = ?1
+             GROUP BY day ORDER BY day",
+        )
+        .bind(project_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load daily words: {}", e)))?;
+        let all_days: Vec<DailyWords> = day_rows
+            .into_iter()
+            .filter_map(|(day, added, removed)| {
+                let net = added - removed;
+                Some(DailyWords {
+                    date: day.parse().ok()?,
+                    added,
+                    removed,
+                    net,
+                    goal_met: net >= goal.daily_words.max(1),
+                })
+            })
+            .collect();
+
+        let document_rows: Vec<(String, Option<String>, i64, i64)> = sqlx::query_as(
+            "SELECT s.document_id, d.title,
+                    SUM(MAX(s.words_after - s.words_before, 0)),
+                    SUM(MAX(s.words_before - s.words_after, 0))
+             FROM writing_stats_deltas s LEFT JOIN documents d ON d.id = s.document_id
+             WHERE s.project_id = ?1 AND (?2 IS NULL OR s.day >= ?2) AND (?3 IS NULL OR s.day <= ?3)
+             GROUP BY s.document_id",
+        )
+        .bind(project_id.to_string())
+        .bind(&from_day)
+        .bind(&to_day)
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load document words: {}", e)))?;
+        let mut documents: Vec<DocumentWords> = document_rows
+            .into_iter()
+            .map(|(document_id, title, added, removed)| DocumentWords {
+                document_id,
+                title: title.unwrap_or_default(),
+                added,
+                removed,
+                net: added - removed,
+            })
+            .collect();
+        documents.sort_by(|a, b| b.net.cmp(&a.net).then_with(|| a.title.cmp(&b.title)));
+
+        let activity: Vec<(String, String, i64)> = sqlx::query_as(
+            "SELECT recorded_at, document_id, words_after - words_before FROM writing_stats_deltas
+             WHERE project_id = ?1 AND (?2 IS NULL OR day >= ?2) AND (?3 IS NULL OR day <= ?3)
+             ORDER BY recorded_at, id",
+        )
+        .bind(project_id.to_string())
+        .bind(&from_day)
+        .bind(&to_day)
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load writing activity: {}", e)))?;
+        let activity: Vec<(DateTime<Utc>, String, i64)> = activity
+            .into_iter()
+            .map(|(at, document_id, delta)| Ok((parse_time(&at)?, document_id, delta)))
+            .collect::<DatabaseResult<_>>()?;
+        let sessions = sessions(&activity);
+
+        Ok(WritingStatsDashboard {
+            project_id,
+            goal,
+            progress,
+            streaks: streaks(&all_days, &goal, today),
+            days: all_days
+                .into_iter()
+                .filter(|d| {
+                    from.is_none_or(|from| d.date >= from) && to.is_none_or(|to| d.date <= to)
+                })
+                .collect(),
+            documents,
+            speed_histogram: speed_histogram(&sessions),
+            sessions,
+        })
+    }
+
+    /// Net words written on `today` and in its week
+    async fn progress(&self, project_id: Uuid, today: NaiveDate) -> DatabaseResult<GoalProgress> {
+        let db = self.db_service.read().await;
+        let (today_words, week_words): (i64, i64) = sqlx::query_as(
+            "SELECT COALESCE(SUM(CASE WHEN day = ?2 THEN words_after - words_before END), 0),
+                    COALESCE(SUM(words_after - words_before), 0)
+             FROM writing_stats_deltas WHERE project_id = ?1 AND day >= ?3 AND day <= ?2",
+        )
+        .bind(project_id.to_string())
+        .bind(today.to_string())
+        .bind(week_start(today).to_string())
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| DatabaseError::Service(format!("Failed to load goal progress: {}", e)))?;
+        Ok(GoalProgress {
+            today: today_words,
+            this_week: week_words,
+        })
+    }
+}
+
+/// Monday of the date's week
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Current and longest runs of days, and of weeks, meeting the goal
+pub fn streaks(days: &[DailyWords], goal: &WritingGoal, today: NaiveDate) -> WritingStreaks {
+    let met_days: HashSet<NaiveDate> = days.iter().filter(|d| d.goal_met).map(|d| d.date).collect();
+    let mut weeks: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    for day in days {
+        *weeks.entry(week_start(day.date)).or_default() += day.net;
+    }
+    let met_weeks: HashSet<NaiveDate> = weeks
+        .into_iter()
+        .filter(|(_, net)| *net >= goal.weekly_words.max(1))
+        .map(|(week, _)| week)
+        .collect();
+
+    let (daily_current, daily_longest) = runs(&met_days, today, Duration::days(1));
+    let (weekly_current, weekly_longest) = runs(&met_weeks, week_start(today), Duration::weeks(1));
+    WritingStreaks {
+        daily_current,
+        daily_longest,
+        weekly_current,
+        weekly_longest,
+    }
+}
+
+/// The run ending at `current`, or the step before it if `current` isn't
+/// met yet, and the longest run
+fn runs(met: &HashSet<NaiveDate>, current: NaiveDate, step: Duration) -> (u32, u32) {
+    let run_back = |mut date: NaiveDate| {
+        let mut length = 0;
+        while met.contains(&date) {
+            length += 1;
+            date -= step;
+        }
+        length
+    };
+    let current_run = if met.contains(&current) {
+        run_back(current)
+    } else {
+        run_back(current - step)
+    };
+    let longest = met
+        .iter()
+        .filter(|date| !met.contains(&(**date - step)))
+        .map(|date| {
+            let mut length = 0;
+            let mut date = *date;
+            while met.contains(&date) {
+                length += 1;
+                date += step;
+            }
+            length
+        })
+        .max()
+        .unwrap_or(0);
+    (current_run, longest)
+}
+
+/// Split saves, oldest first, into sessions at pauses longer than
+/// `SESSION_GAP`
+pub fn sessions(activity: &[(DateTime<Utc>, String, i64)]) -> Vec<ActivitySession> {
+    let mut sessions: Vec<ActivitySession> = Vec::new();
+    let mut documents: HashSet<&str> = HashSet::new();
+    for (at, document_id, delta) in activity {
+        match sessions.last_mut() {
+            Some(session) if *at - session.ended_at <= SESSION_GAP => {
+                session.ended_at = *at;
+                session.net_words += delta;
+            }
+            _ => {
+                documents.clear();
+                sessions.push(ActivitySession {
+                    started_at: *at,
+                    ended_at: *at,
+                    duration_seconds: 0,
+                    net_words: *delta,
+                    documents: 0,
+                    words_per_minute: None,
+                });
+            }
+        }
+        documents.insert(document_id);
+        let session = sessions.last_mut().expect("session just added");
+        session.documents = documents.len();
+        session.duration_seconds = (session.ended_at - session.started_at).num_seconds();
+        session.words_per_minute = (session.duration_seconds >= MIN_SPEED_SECONDS)
+            .then(|| session.net_words as f64 * 60.0 / session.duration_seconds as f64);
+    }
+    sessions
+}
+
+/// Sessions by writing speed, in buckets from 0 up to the fastest; sessions
+/// with no speed or that removed more than they added are left out
+pub fn speed_histogram(sessions: &[ActivitySession]) -> Vec<SpeedBucket> {
+    let timed: Vec<(f64, f64)> = sessions
+        .iter()
+        .filter_map(|s| Some((s.words_per_minute?, s.duration_seconds as f64 / 60.0)))
+        .filter(|(wpm, _)| *wpm > 0.0)
+        .collect();
+    let Some(fastest) = timed.iter().map(|(wpm, _)| *wpm).reduce(f64::max) else {
+        return Vec::new();
+    };
+    let mut buckets: Vec<SpeedBucket> = (0..=(fastest as u32 / SPEED_BUCKET_WPM))
+        .map(|i| SpeedBucket {
+            from_wpm: i * SPEED_BUCKET_WPM,
+            to_wpm: (i + 1) * SPEED_BUCKET_WPM,
+            sessions: 0,
+            minutes: 0.0,
+        })
+        .collect();
+    for (wpm, minutes) in timed {
+        let bucket = &mut buckets[wpm as usize / SPEED_BUCKET_WPM as usize];
+        bucket.sessions += 1;
+        bucket.minutes += minutes;
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+
+    #[test]
+    fn test_streaks_and_sessions() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap(); // Thursday
+        let day = |offset: i64, net: i64| DailyWords {
+            date: today - Duration::days(offset),
+            added: net,
+            removed: 0,
+            net,
+            goal_met: net >= 500,
+        };
+        // Today isn't done yet, so the streak runs through yesterday
+        let days = [
+            day(9, 600),
+            day(8, 700),
+            day(7, 800),
+            day(2, 500),
+            day(1, 900),
+        ];
+        let goal = WritingGoal {
+            daily_words: 500,
+            weekly_words: 1000,
+        };
+        assert_eq!(
+            streaks(&days, &goal, today),
+            WritingStreaks {
+                daily_current: 2,
+                daily_longest: 3,
+                weekly_current: 2,
+                weekly_longest: 2,
+            }
+        );
+
+        let start = DateTime::parse_from_rfc3339("2026-10-15T09:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let at = |minutes: i64| start + Duration::minutes(minutes);
+        let activity = [
+            (at(0), "a".to_string(), 100),
+            (at(10), "b".to_string(), 150),
+            (at(20), "a".to_string(), -50),
+            (at(60), "a".to_string(), 30),
+        ];
+        let sessions = sessions(&activity);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(
+            (
+                sessions[0].duration_seconds,
+                sessions[0].net_words,
+                sessions[0].documents
+            ),
+            (1200, 200, 2)
+        );
+        assert_eq!(sessions[0].words_per_minute, Some(10.0));
+        assert_eq!(sessions[1].words_per_minute, None);
+        let histogram = speed_histogram(&sessions);
+        assert_eq!(histogram.len(), 2);
+        assert_eq!((histogram[1].from_wpm, histogram[1].sessions), (10, 1));
+    }
+
+    #[tokio::test]
+    async fn test_goal_met_once_and_queued_for_automation() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            EnhancedDatabaseService::new(&dir.path().join("test.db"), DatabaseConfig::default())
+                .await
+                .unwrap();
+        let project = Uuid::new_v4();
+        sqlx::query("INSERT INTO projects (id, name, created_at, updated_at) VALUES (?1, 'Ashfall', ?2, ?2)")
+            .bind(project.to_string())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        db.create_document(
+            "one".to_string(),
+            project.to_string(),
+            "The Harbor".to_string(),
+            "Dark water".to_string(),
+        )
+        .await
+        .unwrap();
+        let events = EventSystem {
+            event_queue: Arc::default(),
+        };
+        let service = WritingStatsService::new(Arc::new(RwLock::new(db)))
+            .with_automation_events(events.clone());
+        service.initialize().await.unwrap();
+        service
+            .set_goal(
+                project,
+                WritingGoal {
+                    daily_words: 100,
+                    weekly_words: 0,
+                },
+            )
+            .await
+            .unwrap();
+
+        let before = service.word_count("one").await.unwrap().unwrap();
+        assert_eq!(before.words, 2);
+        assert!(service.record_words(&before, 60).await.unwrap().is_empty());
+        let met = service
+            .record_words(
+                &DocumentWordCount {
+                    words: 60,
+                    ..before.clone()
+                },
+                120,
+            )
+            .await
+            .unwrap();
+        assert_eq!(met.len(), 1);
+        assert_eq!((met[0].period, met[0].words), (GoalPeriod::Daily, 118));
+        // Dropping under the goal and back doesn't fire again
+        service
+            .record_words(
+                &DocumentWordCount {
+                    words: 120,
+                    ..before.clone()
+                },
+                90,
+            )
+            .await
+            .unwrap();
+        assert!(service
+            .record_words(
+                &DocumentWordCount {
+                    words: 90,
+                    ..before.clone()
+                },
+                150
+            )
+            .await
+            .unwrap()
+            .is_empty());
+
+        let queued = events.event_queue.lock().await;
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].event_type, EventType::WritingGoalMet);
+        assert_eq!(queued[0].data["words"], serde_json::json!(118));
+        drop(queued);
+
+        let dashboard = service.dashboard(project, None, None).await.unwrap();
+        assert_eq!(dashboard.progress.today, 148);
+        assert_eq!(dashboard.days.len(), 1);
+        assert_eq!(
+            (dashboard.days[0].added, dashboard.days[0].removed),
+            (178, 30)
+        );
+        assert_eq!(dashboard.documents[0].title, "The Harbor");
+        assert_eq!(dashboard.streaks.daily_current, 1);
+        assert_eq!(dashboard.sessions.len(), 1);
+    }
+}
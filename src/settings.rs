@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-#[derive(Serialize, Deserialize, Clone)]
+use crate::app_paths::AppPaths;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Settings {
     pub paper_size: String,
     pub margins: f32, // in inches
@@ -15,6 +17,45 @@ pub struct Settings {
     pub enable_ai_analysis: Option<bool>,
     // Theme-specific settings
     pub theme_settings: Option<ThemeSettings>,
+    pub privacy: Option<PrivacyControls>,
+    /// Record window focus and tool switches locally (opt-in)
+    pub focus_analytics: Option<bool>,
+    /// Folder of a sentence-embedding model (config.json, tokenizer.json,
+    /// model.safetensors) to index documents with on this machine
+    pub local_embedding_model: Option<PathBuf>,
+    /// Days deleted documents and projects stay in the trash
+    pub trash_retention_days: Option<u32>,
+}
+
+/// What is removed from imported files before they are stored, and what
+/// is kept of AI requests
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrivacyControls {
+    /// GPS coordinates and other location fields in photos
+    pub strip_image_location: bool,
+    /// Camera make, model, serial numbers and maker notes
+    pub strip_camera_metadata: bool,
+    /// Keep a local log of AI prompts and responses (opt-in)
+    #[serde(default)]
+    pub log_ai_interactions: bool,
+    /// Days AI log entries are kept; 0 keeps them until deleted
+    #[serde(default = "default_ai_log_retention_days")]
+    pub ai_log_retention_days: u32,
+}
+
+fn default_ai_log_retention_days() -> u32 {
+    30
+}
+
+impl Default for PrivacyControls {
+    fn default() -> Self {
+        Self {
+            strip_image_location: true,
+            strip_camera_metadata: true,
+            log_ai_interactions: false,
+            ai_log_retention_days: default_ai_log_retention_days(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -83,22 +124,22 @@ impl Default for Settings {
             enable_ai_suggestions: Some(false),
             enable_ai_analysis: Some(true),
             theme_settings: Some(ThemeSettings::default()),
+            privacy: Some(PrivacyControls::default()),
+            focus_analytics: Some(false),
+            local_embedding_model: None,
+            trash_retention_days: Some(30),
         }
     }
 }
 
 /// Get the settings file path
 fn get_settings_path() -> PathBuf {
-    std::env::current_dir()
-        .unwrap_or_else(|_| std::path::PathBuf::from("."))
-        .join("settings.json")
+    AppPaths::global().settings_path()
 }
 
 /// Get the theme settings file path
 fn get_theme_settings_path() -> PathBuf {
-    std::env::current_dir()
-        .unwrap_or_else(|_| std::path::PathBuf::from("."))
-        .join("theme_settings.json")
+    AppPaths::global().theme_settings_path()
 }
 
 /// Load theme settings from file
@@ -0,0 +1,181 @@
+//! Shell Integration
+//!
+//! Mirrors the recent items list into the OS shell: a "Recent" category in
+//! the Windows taskbar jump list and entries in the macOS dock menu.
+//! Jump list entries relaunch the executable with a deep link, which
+//! `single_instance` forwards to the running app; dock menu entries call
+//! the deep link handler directly. Other platforms have no equivalent and
+//! ignore updates.
+
+use crate::deep_link::DeepLinkHandler;
+use crate::recent_items::RecentItem;
+
+/// Set up the shell integration. On macOS this must run on the main
+/// thread after the event loop has been created.
+pub fn install(handler: DeepLinkHandler) {
+    #[cfg(target_os = "macos")]
+    dock_menu::install(handler);
+    #[cfg(not(target_os = "macos"))]
+    let _ = handler;
+}
+
+/// Replace the recent items shown by the shell
+pub fn update_recent_items(items: &[RecentItem]) {
+    #[cfg(windows)]
+    if let Err(e) = jump_list::update(items) {
+        log::warn!("Failed to update jump list: {}", e);
+    }
+    #[cfg(target_os = "macos")]
+    dock_menu::update(items);
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let _ = items;
+}
+
+#[cfg(windows)]
+mod jump_list {
+    use windows::core::{Interface, HSTRING, PROPVARIANT};
+    use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+    };
+    use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+    use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+    use windows::Win32::UI::Shell::{
+        DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW, ShellLink,
+    };
+
+    use crate::recent_items::RecentItem;
+
+    pub fn update(items: &[RecentItem]) -> windows::core::Result<()> {
+        let exe = std::env::current_exe().map_err(|e| {
+            windows::core::Error::new(windows::Win32::Foundation::E_FAIL, e.to_string())
+        })?;
+
+        unsafe {
+            // Fails harmlessly if COM is already initialized on this thread
+            let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+
+            let list: ICustomDestinationList =
+                CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+            let mut max_slots = 0u32;
+            let _removed: IObjectArray = list.BeginList(&mut max_slots)?;
+
+            let collection: IObjectCollection =
+                CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+            for item in items.iter().take(max_slots as usize) {
+                let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+                link.SetPath(&HSTRING::from(exe.as_os_str()))?;
+                link.SetArguments(&HSTRING::from(item.link().to_url()))?;
+                link.SetDescription(&HSTRING::from(item.title.as_str()))?;
+
+                // Jump list entries show the title property, not the description
+                let properties: IPropertyStore = link.cast()?;
+                properties.SetValue(&PKEY_Title, &PROPVARIANT::from(item.title.as_str()))?;
+                properties.Commit()?;
+
+                collection.AddObject(&link)?;
+            }
+
+            let array: IObjectArray = collection.cast()?;
+            list.AppendCategory(&HSTRING::from("Recent"), &array)?;
+            list.CommitList()
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod dock_menu {
+    use cocoa::appkit::{NSApp, NSMenu, NSMenuItem};
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::{NSAutoreleasePool, NSString};
+    use objc::declare::ClassDecl;
+    use objc::runtime::{class_addMethod, object_getClass, Class, Imp, Object, Sel};
+    use objc::{class, msg_send, sel, sel_impl};
+    use once_cell::sync::OnceCell;
+    use std::sync::Mutex;
+
+    use crate::deep_link::{DeepLink, DeepLinkHandler};
+    use crate::recent_items::RecentItem;
+
+    /// (title, link) pairs shown in the dock menu, newest first
+    static ITEMS: Mutex<Vec<(String, DeepLink)>> = Mutex::new(Vec::new());
+    static HANDLER: OnceCell<DeepLinkHandler> = OnceCell::new();
+    /// Target object receiving menu item clicks, stored as a pointer
+    static TARGET: OnceCell<usize> = OnceCell::new();
+
+    pub fn install(handler: DeepLinkHandler) {
+        if HANDLER.set(handler).is_err() {
+            return;
+        }
+        unsafe {
+            let mut decl = ClassDecl::new("HerdingCatsDockMenuTarget", class!(NSObject))
+                .expect("dock menu target class registered twice");
+            decl.add_method(
+                sel!(openRecentItem:),
+                open_recent_item as extern "C" fn(&Object, Sel, id),
+            );
+            let target_class = decl.register();
+            let target: id = msg_send![target_class, new];
+            let _ = TARGET.set(target as usize);
+
+            // Add applicationDockMenu: to the app delegate tao installed
+            let delegate: id = msg_send![NSApp(), delegate];
+            if delegate == nil {
+                log::warn!("No application delegate; dock menu not installed");
+                return;
+            }
+            let delegate_class = object_getClass(delegate as *const Object) as *mut Class;
+            let imp: Imp = std::mem::transmute(dock_menu as extern "C" fn(&Object, Sel, id) -> id);
+            class_addMethod(
+                delegate_class,
+                sel!(applicationDockMenu:),
+                imp,
+                c"@@:@".as_ptr(),
+            );
+        }
+    }
+
+    pub fn update(items: &[RecentItem]) {
+        if let Ok(mut entries) = ITEMS.lock() {
+            *entries = items
+                .iter()
+                .map(|item| (item.title.clone(), item.link()))
+                .collect();
+        }
+    }
+
+    /// Built fresh each time the dock asks, so it always shows the current list
+    extern "C" fn dock_menu(_this: &Object, _sel: Sel, _sender: id) -> id {
+        let Some(target) = TARGET.get().copied() else {
+            return nil;
+        };
+        let entries = ITEMS.lock().map(|items| items.clone()).unwrap_or_default();
+        unsafe {
+            let menu = NSMenu::new(nil).autorelease();
+            for (index, (title, _)) in entries.iter().enumerate() {
+                let item = NSMenuItem::alloc(nil)
+                    .initWithTitle_action_keyEquivalent_(
+                        NSString::alloc(nil).init_str(title).autorelease(),
+                        sel!(openRecentItem:),
+                        NSString::alloc(nil).init_str("").autorelease(),
+                    )
+                    .autorelease();
+                let _: () = msg_send![item, setTag: index as i64];
+                let _: () = msg_send![item, setTarget: target as id];
+                menu.addItem_(item);
+            }
+            menu
+        }
+    }
+
+    extern "C" fn open_recent_item(_this: &Object, _sel: Sel, sender: id) {
+        let index: i64 = unsafe { msg_send![sender, tag] };
+        let link = ITEMS
+            .lock()
+            .ok()
+            .and_then(|items| items.get(index as usize).map(|(_, link)| link.clone()));
+        if let (Some(link), Some(handler)) = (link, HANDLER.get()) {
+            handler(link);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Single Instance
+//!
+//! Deep links given on the command line (jump list entries, and anything
+//! else the OS shell launches us with) must reach the instance that is
+//! already running rather than start a second one. The running instance
+//! listens on a loopback port recorded, with a random token, in a lock
+//! file; a new process with a deep link forwards it there and exits.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::app_paths::AppPaths;
+use crate::deep_link::{DeepLink, DeepLinkHandler};
+use crate::project_file::ProjectFile;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+/// Longest line accepted from a client
+const MAX_LINE_BYTES: u64 = 8 * 1024;
+
+/// Lock file in the local state directory
+pub fn lock_path() -> PathBuf {
+    AppPaths::global().local_dir.join("instance.lock")
+}
+
+/// The first deep link among the process arguments. A `.hcats` path, as
+/// passed by the OS when a project file is double-clicked, counts as one.
+pub fn launch_link() -> Option<DeepLink> {
+    std::env::args().skip(1).find_map(|arg| {
+        let path = Path::new(&arg);
+        if ProjectFile::is_project_file(path) {
+            let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+            return Some(DeepLink::OpenProjectFile { path });
+        }
+        DeepLink::parse(&arg).ok()
+    })
+}
+
+/// Hand `link` to a running instance. Returns false when none answered,
+/// in which case the caller should start up normally.
+pub fn forward(lock_path: &Path, link: &DeepLink) -> bool {
+    let Some((port, token)) = read_lock(lock_path) else {
+        return false;
+    };
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let Ok(mut stream) = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(CONNECT_TIMEOUT));
+    if writeln!(stream, "{} {}", token, link.to_url()).is_err() {
+        return false;
+    }
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).is_ok() && reply.trim() == "ok"
+}
+
+/// Listener that receives deep links forwarded by later launches
+#[derive(Debug)]
+pub struct InstanceServer {
+    lock_path: PathBuf,
+    port: u16,
+}
+
+impl InstanceServer {
+    /// Bind a loopback port, record it in the lock file and route every
+    /// forwarded link to `handler` from a background thread
+    pub fn start(lock_path: &Path, handler: DeepLinkHandler) -> std::io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let port = listener.local_addr()?.port();
+        let token = uuid::Uuid::new_v4().simple().to_string();
+
+        if let Some(parent) = lock_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(lock_path, format!("{} {}", port, token))?;
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Some(link) = receive(stream, &token) {
+                    handler(link);
+                }
+            }
+        });
+
+        Ok(Self {
+            lock_path: lock_path.to_path_buf(),
+            port,
+        })
+    }
+}
+
+impl Drop for InstanceServer {
+    fn drop(&mut self) {
+        // Leave the file alone if a newer instance has taken it over
+        if read_lock(&self.lock_path).is_some_and(|(port, _)| port == self.port) {
+            let _ = std::fs::remove_file(&self.lock_path);
+        }
+    }
+}
+
+fn receive(stream: TcpStream, token: &str) -> Option<DeepLink> {
+    let _ = stream.set_read_timeout(Some(CONNECT_TIMEOUT));
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_LINE_BYTES))
+        .read_line(&mut line)
+        .ok()?;
+
+    let (received_token, url) = line.trim().split_once(' ')?;
+    if received_token != token {
+        log::warn!("Rejected deep link forwarded with a bad token");
+        return None;
+    }
+    match DeepLink::parse(url) {
+        Ok(link) => {
+            let _ = (&stream).write_all(b"ok\n");
+            Some(link)
+        }
+        Err(e) => {
+            log::warn!("Rejected forwarded deep link: {}", e);
+            None
+        }
+    }
+}
+
+fn read_lock(lock_path: &Path) -> Option<(u16, String)> {
+    let content = std::fs::read_to_string(lock_path).ok()?;
+    let (port, token) = content.trim().split_once(' ')?;
+    Some((port.parse().ok()?, token.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{mpsc, Arc, Mutex};
+
+    #[test]
+    fn test_links_are_forwarded_to_running_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = dir.path().join("instance.lock");
+        let link = DeepLink::OpenDocument {
+            document_id: "abc".to_string(),
+        };
+        assert!(!forward(&lock, &link));
+
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let server = InstanceServer::start(
+            &lock,
+            Arc::new(move |link| {
+                let _ = sender.lock().unwrap().send(link);
+            }),
+        )
+        .unwrap();
+
+        assert!(forward(&lock, &link));
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(2)).unwrap(), link);
+
+        // A client without the token is turned away
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, server.port)).unwrap();
+        writeln!(stream, "guess {}", link.to_url()).unwrap();
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+
+        drop(server);
+        assert!(!lock.exists());
+    }
+}
@@ -0,0 +1,256 @@
+//! Image Thumbnails
+//!
+//! Downscaled copies of image assets for galleries and codex portraits,
+//! served as `app://localhost/thumbnails/<size>/<hash>` so the webview never
+//! loads full-resolution files. Thumbnails are generated on first request and
+//! cached under the cache directory; since assets never change, a cached
+//! thumbnail stays valid until its asset is removed.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::app_paths::AppPaths;
+use crate::asset_store::AssetStore;
+
+/// URL prefix for thumbnails on the app:// protocol
+pub const THUMBNAIL_PREFIX: &str = "/thumbnails/";
+const JPEG_QUALITY: u8 = 82;
+
+#[derive(Debug, Error)]
+pub enum ThumbnailError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Asset not found: {0}")]
+    NotFound(String),
+}
+
+/// Longest edge of a generated thumbnail
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThumbnailSize {
+    /// Lists and pickers
+    Small,
+    /// Gallery grids
+    Medium,
+    /// Codex portraits and previews
+    Large,
+}
+
+impl ThumbnailSize {
+    pub const ALL: [ThumbnailSize; 3] = [
+        ThumbnailSize::Small,
+        ThumbnailSize::Medium,
+        ThumbnailSize::Large,
+    ];
+
+    pub fn pixels(&self) -> u32 {
+        match self {
+            ThumbnailSize::Small => 128,
+            ThumbnailSize::Medium => 320,
+            ThumbnailSize::Large => 800,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThumbnailSize::Small => "small",
+            ThumbnailSize::Medium => "medium",
+            ThumbnailSize::Large => "large",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "small" => Some(ThumbnailSize::Small),
+            "medium" => Some(ThumbnailSize::Medium),
+            "large" => Some(ThumbnailSize::Large),
+            _ => None,
+        }
+    }
+}
+
+/// A cached thumbnail file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub path: PathBuf,
+    pub mime_type: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub struct Thumbnailer {
+    store: AssetStore,
+    cache_dir: PathBuf,
+}
+
+impl Thumbnailer {
+    pub fn new(store: AssetStore, cache_dir: &Path) -> Self {
+        Self {
+            store,
+            cache_dir: cache_dir.to_path_buf(),
+        }
+    }
+
+    /// Thumbnails of the default asset store, cached in `thumbnails` in the
+    /// cache directory
+    pub fn open_default() -> Self {
+        Self::new(
+            AssetStore::open_default(),
+            &AppPaths::global().cache_dir.join("thumbnails"),
+        )
+    }
+
+    /// URL the webview loads for a thumbnail of an asset
+    pub fn url(hash: &str, size: ThumbnailSize) -> String {
+        format!(
+            "app://localhost{}{}/{}",
+            THUMBNAIL_PREFIX,
+            size.as_str(),
+            hash
+        )
+    }
+
+    /// Cached thumbnail of an asset, generated if missing
+    pub fn get(&self, hash: &str, size: ThumbnailSize) -> Result<Thumbnail, ThumbnailError> {
+        if let Some(cached) = self.cached(hash, size) {
+            return Ok(cached);
+        }
+        let image = self.decode(hash)?;
+        self.write(hash, size, &image)
+    }
+
+    /// Generate every size for a newly imported image, decoding it once
+    pub fn generate_all(&self, hash: &str) -> Result<(), ThumbnailError> {
+        let image = self.decode(hash)?;
+        for size in ThumbnailSize::ALL {
+            if self.cached(hash, size).is_none() {
+                self.write(hash, size, &image)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete thumbnails of assets no longer in the store. Returns how many
+    /// files were removed.
+    pub fn prune(&self) -> std::io::Result<usize> {
+        let mut removed = 0;
+        for size in ThumbnailSize::ALL {
+            let Ok(entries) = std::fs::read_dir(self.cache_dir.join(size.as_str())) else {
+                continue;
+            };
+            // Skips temporary files of thumbnails being written
+            for entry in entries.flatten().filter(|entry| {
+                let extension = entry.path().extension().map(|e| e.to_os_string());
+                extension.is_some_and(|e| e == "png" || e == "jpg")
+            }) {
+                let path = entry.path();
+                let hash = path.file_stem().unwrap_or_default().to_string_lossy();
+                if !self.store.contains(&hash) {
+                    std::fs::remove_file(&path)?;
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    fn decode(&self, hash: &str) -> Result<DynamicImage, ThumbnailError> {
+        let source = self
+            .store
+            .path(hash)
+            .ok_or_else(|| ThumbnailError::NotFound(hash.to_string()))?;
+        Ok(image::ImageReader::open(&source)?
+            .with_guessed_format()?
+            .decode()?)
+    }
+
+    fn cached(&self, hash: &str, size: ThumbnailSize) -> Option<Thumbnail> {
+        // The store validates the hash, so it is safe to use in a file name
+        self.store.path(hash)?;
+        [("png", "image/png"), ("jpg", "image/jpeg")]
+            .into_iter()
+            .map(|(extension, mime_type)| Thumbnail {
+                path: self.thumbnail_path(hash, size, extension),
+                mime_type,
+            })
+            .find(|thumbnail| thumbnail.path.is_file())
+    }
+
+    /// Downscale (never upscale) and encode: PNG when the image has
+    /// transparency, JPEG otherwise
+    fn write(
+        &self,
+        hash: &str,
+        size: ThumbnailSize,
+        image: &DynamicImage,
+    ) -> Result<Thumbnail, ThumbnailError> {
+        let pixels = size.pixels();
+        let scaled = if image.width() > pixels || image.height() > pixels {
+            image.thumbnail(pixels, pixels)
+        } else {
+            image.clone()
+        };
+
+        let dir = self.cache_dir.join(size.as_str());
+        std::fs::create_dir_all(&dir)?;
+        let mut temp = tempfile::NamedTempFile::new_in(&dir)?;
+        let (extension, mime_type) = if scaled.color().has_alpha() {
+            scaled.write_to(temp.as_file_mut(), ImageFormat::Png)?;
+            ("png", "image/png")
+        } else {
+            let encoder = JpegEncoder::new_with_quality(temp.as_file_mut(), JPEG_QUALITY);
+            scaled.to_rgb8().write_with_encoder(encoder)?;
+            ("jpg", "image/jpeg")
+        };
+
+        let path = self.thumbnail_path(hash, size, extension);
+        temp.persist(&path).map_err(|e| e.error)?;
+        Ok(Thumbnail { path, mime_type })
+    }
+
+    fn thumbnail_path(&self, hash: &str, size: ThumbnailSize, extension: &str) -> PathBuf {
+        self.cache_dir
+            .join(size.as_str())
+            .join(format!("{}.{}", hash, extension))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_thumbnails_are_downscaled_and_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AssetStore::new(&dir.path().join("assets"));
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(1000, 500, Rgb([200, 40, 40])))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let asset = store.put_bytes(&png).unwrap();
+        let thumbnailer = Thumbnailer::new(store.clone(), &dir.path().join("thumbs"));
+
+        let small = thumbnailer.get(&asset.hash, ThumbnailSize::Small).unwrap();
+        assert_eq!(small.mime_type, "image/jpeg");
+        let decoded = image::open(&small.path).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (128, 64));
+        assert_eq!(
+            thumbnailer.get(&asset.hash, ThumbnailSize::Small).unwrap(),
+            small
+        );
+
+        assert!(matches!(
+            thumbnailer.get("../../secret", ThumbnailSize::Small),
+            Err(ThumbnailError::NotFound(_))
+        ));
+
+        store.retain(|_| false).unwrap();
+        assert_eq!(thumbnailer.prune().unwrap(), 1);
+    }
+}
@@ -2,10 +2,11 @@
 /// Provides comprehensive voice recognition, speech synthesis, and voice command capabilities
 
 use std::collections::{HashMap, BTreeMap};
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use crate::error::WritingToolError;
@@ -967,16 +968,14 @@ impl VoiceIntegrationManager {
         self.initialize_audio_capture().await?;
         
         // Start session
-        self.session_manager.write().unwrap().start_session(session_id)?;
+        self.session_manager.write().await.start_session(session_id)?;
         
         Ok(session_id)
     }
 
     /// Process voice input
     pub async fn process_voice_input(&self, session_id: Uuid, audio_data: &[u8]) -> Result<VoiceRecognitionResult, WritingToolError> {
-        let settings = self.settings.read().unwrap();
-        
-        if !settings.recognition_enabled {
+        if !self.settings.read().await.recognition_enabled {
             return Err(WritingToolError::VoiceRecognitionDisabled);
         }
 
@@ -994,8 +993,11 @@ impl VoiceIntegrationManager {
             language_model: None,
         };
 
-        let recognition_engine = self.recognition_engine.read().unwrap();
-        let result = recognition_engine.recognize_speech(audio_data, &config)?;
+        let result = self
+            .recognition_engine
+            .read()
+            .await
+            .recognize_speech(audio_data, &config)?;
 
         // Process recognized text as commands
         if result.confidence > config.confidence_threshold {
@@ -1004,7 +1006,7 @@ impl VoiceIntegrationManager {
 
         // Update analytics
         {
-            let mut analytics = self.analytics.write().unwrap();
+            let mut analytics = self.analytics.write().await;
             analytics.total_utterances += 1;
             analytics.average_confidence = (analytics.average_confidence + result.confidence) / 2.0;
         }
@@ -1014,9 +1016,7 @@ impl VoiceIntegrationManager {
 
     /// Synthesize speech from text
     pub async fn synthesize_speech(&self, text: &str) -> Result<SpeechSynthesisResult, WritingToolError> {
-        let settings = self.settings.read().unwrap();
-        
-        if !settings.synthesis_enabled {
+        if !self.settings.read().await.synthesis_enabled {
             return Err(WritingToolError::SpeechSynthesisDisabled);
         }
 
@@ -1033,21 +1033,22 @@ impl VoiceIntegrationManager {
             quality: AudioQuality::High,
         };
 
-        let synthesis_engine = self.synthesis_engine.read().unwrap();
-        synthesis_engine.synthesize_speech(text, &config)
+        self.synthesis_engine.read().await.synthesize_speech(text, &config)
     }
 
     /// Process voice commands
     async fn process_voice_commands(&self, text: &str, session_id: Uuid) -> Result<(), WritingToolError> {
-        let command_processor = self.command_processor.read().unwrap();
-        let session = self.session_manager.read().unwrap().get_session(session_id);
-        
-        if let Some(session) = session {
-            for command in command_processor.commands.values() {
-                if command.enabled && self.matches_command(command, text, &session.context_state)? {
-                    self.execute_command(command, text).await?;
-                    break; // Execute first matching command
-                }
+        // Copy the context out so the session lock is free while commands run
+        let context = match self.session_manager.read().await.get_session(session_id) {
+            Some(session) => session.context_state.clone(),
+            None => return Ok(()),
+        };
+
+        let command_processor = self.command_processor.read().await;
+        for command in command_processor.commands.values() {
+            if command.enabled && Self::matches_command(&command_processor, command, text, &context)? {
+                self.execute_command(&command_processor, command, text).await?;
+                break; // Execute first matching command
             }
         }
 
@@ -1055,7 +1056,7 @@ impl VoiceIntegrationManager {
     }
 
     /// Check if text matches a voice command
-    fn matches_command(&self, command: &VoiceCommand, text: &str, context: &ContextState) -> Result<bool, WritingToolError> {
+    fn matches_command(command_processor: &VoiceCommandProcessor, command: &VoiceCommand, text: &str, context: &ContextState) -> Result<bool, WritingToolError> {
         let text_lower = text.to_lowercase();
         
         // Check if any phrase matches
@@ -1070,7 +1071,7 @@ impl VoiceIntegrationManager {
 
         // Check context requirements
         for requirement in &command.context_requirements {
-            let context_matcher = self.command_processor.read().unwrap()
+            let context_matcher = command_processor
                 .context_matchers
                 .get(&format!("{:?}", requirement.context_type))
                 .ok_or_else(|| WritingToolError::ContextMatcherNotFound(format!("{:?}", requirement.context_type)))?;
@@ -1084,9 +1085,7 @@ impl VoiceIntegrationManager {
     }
 
     /// Execute voice command
-    async fn execute_command(&self, command: &VoiceCommand, text: &str) -> Result<CommandResult, WritingToolError> {
-        let command_processor = self.command_processor.read().unwrap();
-        
+    async fn execute_command(&self, command_processor: &VoiceCommandProcessor, command: &VoiceCommand, text: &str) -> Result<CommandResult, WritingToolError> {
         // Extract parameters from text (simplified implementation)
         let parameters = self.extract_command_parameters(command, text)?;
         
@@ -1099,7 +1098,7 @@ impl VoiceIntegrationManager {
 
         // Log command execution
         {
-            let mut session_manager = self.session_manager.write().unwrap();
+            let mut session_manager = self.session_manager.write().await;
             if let Some(session) = &mut session_manager.active_session {
                 session.command_count += 1;
             }
@@ -1137,40 +1136,36 @@ impl VoiceIntegrationManager {
     }
 
     /// Get voice settings
-    pub fn get_settings(&self) -> VoiceSettings {
-        self.settings.read().unwrap().clone()
+    pub async fn get_settings(&self) -> VoiceSettings {
+        self.settings.read().await.clone()
     }
 
     /// Update voice settings
-    pub fn update_settings(&self, settings: VoiceSettings) {
-        *self.settings.write().unwrap() = settings;
+    pub async fn update_settings(&self, settings: VoiceSettings) {
+        *self.settings.write().await = settings;
     }
 
     /// Get voice analytics
-    pub fn get_analytics(&self) -> VoiceAnalytics {
-        self.analytics.read().unwrap().clone()
+    pub async fn get_analytics(&self) -> VoiceAnalytics {
+        self.analytics.read().await.clone()
     }
 
     /// Create voice profile
-    pub fn create_profile(&self, profile: VoiceProfile) -> Result<String, WritingToolError> {
+    pub async fn create_profile(&self, profile: VoiceProfile) -> Result<String, WritingToolError> {
         let profile_id = profile.profile_id.to_string();
-        {
-            let mut manager = self.profile_manager.write().unwrap();
-            manager.profiles.insert(profile_id.clone(), profile);
-        }
+        self.profile_manager.write().await.profiles.insert(profile_id.clone(), profile);
         Ok(profile_id)
     }
 
     /// Get available voices for text-to-speech
-    pub fn get_available_voices(&self, language: &str) -> Vec<VoiceInfo> {
-        let synthesis_engine = self.synthesis_engine.read().unwrap();
-        synthesis_engine.get_available_voices(language)
+    pub async fn get_available_voices(&self, language: &str) -> Vec<VoiceInfo> {
+        self.synthesis_engine.read().await.get_available_voices(language)
     }
 
     /// Enable voice feedback
     pub async fn enable_voice_feedback(&self, enabled: bool) -> Result<(), WritingToolError> {
         {
-            let mut settings = self.settings.write().unwrap();
+            let mut settings = self.settings.write().await;
             settings.voice_feedback_enabled = enabled;
         }
 
@@ -1183,17 +1178,15 @@ impl VoiceIntegrationManager {
     }
 
     /// Set wake word
-    pub fn set_wake_word(&self, wake_word: String) {
-        {
-            let mut settings = self.settings.write().unwrap();
-            settings.wake_word = wake_word.clone();
-            settings.wake_word_enabled = true;
-        }
+    pub async fn set_wake_word(&self, wake_word: String) {
+        let mut settings = self.settings.write().await;
+        settings.wake_word = wake_word;
+        settings.wake_word_enabled = true;
     }
 
     /// Stop current recognition session
     pub async fn stop_recognition(&self, session_id: Uuid) -> Result<(), WritingToolError> {
-        self.session_manager.write().unwrap().end_session(session_id)?;
+        self.session_manager.write().await.end_session(session_id)?;
         Ok(())
     }
 }
@@ -1712,16 +1705,16 @@ mod tests {
         assert_eq!(result.audio_format, AudioFormat::WAV);
     }
 
-    #[test]
-    fn test_voice_integration_manager() {
+    #[tokio::test]
+    async fn test_voice_integration_manager() {
         let manager = VoiceIntegrationManager::new();
         
         // Test default initialization
-        assert!(manager.get_settings().recognition_enabled);
-        assert!(manager.get_settings().synthesis_enabled);
+        assert!(manager.get_settings().await.recognition_enabled);
+        assert!(manager.get_settings().await.synthesis_enabled);
         
         // Test voice availability
-        let voices = manager.get_available_voices("en-US");
+        let voices = manager.get_available_voices("en-US").await;
         assert!(!voices.is_empty());
     }
 